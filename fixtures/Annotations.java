@@ -0,0 +1,95 @@
+import java.lang.annotation.Inherited;
+import java.lang.annotation.Retention;
+import java.lang.annotation.RetentionPolicy;
+import java.lang.reflect.Field;
+import java.lang.reflect.Method;
+
+public class Annotations {
+
+    public enum Level { LOW, HIGH }
+
+    @Retention(RetentionPolicy.RUNTIME)
+    public @interface Tag {
+        String value();
+        int priority() default 5;
+        long id() default 7L;
+        char initial() default 'x';
+        boolean enabled() default true;
+        byte bits() default 3;
+        Level level() default Level.LOW;
+        Class<?> type() default Object.class;
+        String[] aliases() default {};
+        int[] counts() default {1, 2};
+    }
+
+    @Retention(RetentionPolicy.RUNTIME)
+    @Inherited
+    public @interface Owner {
+        String value();
+        Tag tag() default @Tag("owned");
+    }
+
+    @Retention(RetentionPolicy.CLASS)
+    public @interface Invisible {
+    }
+
+    @Retention(RetentionPolicy.RUNTIME)
+    public @interface Named {
+        String value();
+    }
+
+    @Retention(RetentionPolicy.RUNTIME)
+    public @interface Typed {
+        Class<?>[] value();
+    }
+
+    @Owner(value = "core", tag = @Tag(value = "nested", priority = 1))
+    @Tag(value = "base", level = Level.HIGH, aliases = {"a", "b"}, type = String.class)
+    @Invisible
+    public static class Base {
+        @Tag("field") public int count;
+        public String plain;
+
+        @Tag(value = "method", counts = {}, id = 9)
+        public void run() {
+        }
+
+        public void walk() {
+        }
+    }
+
+    @Named("derived\t'1'")
+    @Typed({Level.class, int[].class})
+    public static class Derived extends Base {
+    }
+
+    public static void main(String[] args) throws Exception {
+        Tag tag = Base.class.getAnnotation(Tag.class);
+        System.out.println(tag.value() + " " + tag.priority() + " " + tag.id() + " " + tag.initial() + " " + tag.enabled() + " " + tag.bits());
+        System.out.println(tag.level() + " " + tag.type().getName() + " " + tag.aliases().length + " " + tag.aliases()[1] + " " + tag.counts()[1]);
+        System.out.println(tag.annotationType().getName());
+        tag.aliases()[0] = "changed";
+        System.out.println(tag.aliases()[0]);
+
+        Owner owner = Base.class.getAnnotation(Owner.class);
+        System.out.println(owner.value() + " " + owner.tag().value() + " " + owner.tag().priority());
+        System.out.println(Base.class.getAnnotations().length + " " + Base.class.isAnnotationPresent(Invisible.class));
+        System.out.println(Derived.class.getAnnotations().length + " " + Derived.class.getDeclaredAnnotations().length
+            + " " + Derived.class.getAnnotation(Owner.class).value() + " " + Derived.class.isAnnotationPresent(Tag.class));
+
+        Method run = Base.class.getMethod("run");
+        Tag methodTag = run.getAnnotation(Tag.class);
+        System.out.println(methodTag.value() + " " + methodTag.id() + " " + methodTag.counts().length + " " + run.isAnnotationPresent(Owner.class));
+        System.out.println(Base.class.getMethod("walk").getAnnotations().length);
+
+        Field count = Base.class.getField("count");
+        System.out.println(count.getAnnotation(Tag.class).value() + " " + Base.class.getField("plain").getAnnotation(Tag.class));
+        System.out.println(Tag.class.getMethod("priority").getDefaultValue() + " " + Tag.class.getMethod("value").getDefaultValue());
+        System.out.println(Owner.class.isAnnotation() + " " + Base.class.isAnnotation() + " " + Level.HIGH.ordinal());
+
+        System.out.println(Derived.class.getAnnotation(Named.class) + " " + Derived.class.getAnnotation(Typed.class));
+        Named named = Derived.class.getAnnotation(Named.class);
+        System.out.println(named.equals(Derived.class.getAnnotation(Named.class)) + " " + named.hashCode() + " " + tag.equals(methodTag)
+            + " " + tag.equals(Base.class.getAnnotation(Tag.class)) + " " + (tag.hashCode() == Base.class.getAnnotation(Tag.class).hashCode()));
+    }
+}
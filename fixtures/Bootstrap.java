@@ -0,0 +1,277 @@
+// Exercises the bootstrap class library. Compile with `javac --release 8` so that string
+// concatenation uses StringBuilder rather than invokedynamic.
+public class Bootstrap {
+    static final String GREETING = "static final";
+    static int counter = initCounter();
+
+    static int initCounter() {
+        return 41;
+    }
+
+    public static void main(String[] args) {
+        System.out.println(GREETING);
+        counter++;
+        System.out.println("counter = " + counter);
+
+        int a = 7;
+        long b = 10000000000L;
+        double c = 2.5;
+        float d = 0.1f;
+        System.out.println(a + " " + b + " " + c + " " + d + " " + 'x' + " " + true);
+        System.out.println(a / 2 + " " + a % 3 + " " + (-a >> 1) + " " + (-a >>> 28) + " " + (b * 3));
+        System.out.println(1.0 / 0 + " " + Math.sqrt(2.0) + " " + 1e10 + " " + (long) 1e19);
+        System.out.println(Integer.MAX_VALUE + 1);
+
+        int[] squares = new int[5];
+        for (int i = 0; i < squares.length; i++) {
+            squares[i] = i * i;
+        }
+        int sum = 0;
+        for (int square : squares) {
+            sum += square;
+        }
+        System.out.println("sum of squares = " + sum);
+
+        char[] chars = { 'd', 'l', 'r', 'o', 'w' };
+        System.out.println(new StringBuilder(new String(chars)).reverse().toString());
+
+        String s = "Hello, World";
+        System.out.println(s.length() + " " + s.charAt(4) + " " + s.indexOf("World") + " " + s.substring(7));
+        System.out.println(s.toUpperCase() + " " + s.toLowerCase() + " " + s.contains("lo, W"));
+        System.out.println("  padded ".trim() + "|" + "a".compareTo("b") + "|" + "abc".hashCode());
+        String computed = new StringBuilder("Hel").append("lo, World").toString();
+        System.out.println((computed == s) + " " + computed.equals(s) + " " + (computed.intern() == s));
+
+        Integer boxed = 127;
+        Integer other = 127;
+        Integer big = 1000;
+        System.out.println((boxed == other) + " " + big.equals(1000) + " " + Integer.parseInt("-123") + " " + Integer.toHexString(255));
+
+        Shape[] shapes = { new Circle(1.0), new Square(2.0) };
+        for (Shape shape : shapes) {
+            System.out.println(shape.describe() + " " + shape.greet());
+        }
+        Object o = shapes[1];
+        System.out.println((o instanceof Square) + " " + (o instanceof Circle) + " " + (o instanceof Named) + " " + (shapes instanceof Object[]));
+
+        try {
+            System.out.println(a / (a - 7));
+        } catch (ArithmeticException e) {
+            System.out.println("caught " + e);
+        }
+
+        try {
+            System.out.println(squares[5]);
+        } catch (RuntimeException e) {
+            System.out.println("caught " + e.getMessage());
+        }
+
+        try {
+            Circle circle = (Circle) o;
+            System.out.println(circle);
+        } catch (ClassCastException e) {
+            System.out.println("caught " + e.getClass().getName());
+        }
+
+        try {
+            String nothing = null;
+            nothing.length();
+        } catch (NullPointerException e) {
+            System.out.println("caught NullPointerException");
+        }
+
+        try {
+            Integer.parseInt("12a");
+        } catch (NumberFormatException e) {
+            System.out.println("caught " + e);
+        }
+
+        try {
+            fail(3);
+        } catch (IllegalArgumentException e) {
+            System.out.println("caught " + e.getMessage() + " caused by " + e.getCause());
+        } finally {
+            System.out.println("finally");
+        }
+
+        int[][] grid = new int[3][4];
+        grid[2][3] = 9;
+        System.out.println(grid.length + " " + grid[2].length + " " + grid[2][3]);
+
+        int[] copy = new int[5];
+        System.arraycopy(squares, 1, copy, 0, 4);
+        System.out.println(copy[0] + " " + copy[3] + " " + copy[4]);
+
+        StringBuilder switched = new StringBuilder();
+        for (int i = -1; i < 5; i++) {
+            switch (i) {
+                case 0: switched.append("zero "); break;
+                case 1: switched.append("one "); break;
+                case 2:
+                case 3: switched.append("few "); break;
+                default: switched.append("many ");
+            }
+            switch (i * 1000) {
+                case -1000: switched.append("below"); break;
+                case 2000: switched.append("thousands"); break;
+                case 100000: switched.append("never"); break;
+                default: switched.append(i);
+            }
+            switched.append(i < 4 ? ", " : "\n");
+        }
+        for (String word : new String[] { "red", "Aa", "BB", "blue" }) {
+            switch (word) {
+                case "red": switched.append("RED "); break;
+                case "Aa": switched.append("Aa "); break;
+                case "BB": switched.append("BB "); break;
+                default: switched.append(word.length());
+            }
+        }
+        int wide = 0;
+        wide += 1000;
+        switched.append(" ").append(wide).append(" ");
+        for (Color color : Color.values()) {
+            switch (color) {
+                case RED: switched.append("r"); break;
+                case GREEN: switched.append("g"); break;
+                default: switched.append(color.name());
+            }
+        }
+        System.out.println(switched.toString());
+
+        Derived derived = new Derived();
+        System.out.println(((Base) derived).x + " " + derived.x + " " + derived.sum());
+
+        // Evolved.class is compiled from a version of Evolved without the removed members.
+        try {
+            System.out.println(new Evolved().removed);
+        } catch (NoSuchFieldError e) {
+            System.out.println("caught " + e);
+        }
+        try {
+            new Evolved().removedMethod();
+        } catch (NoSuchMethodError e) {
+            System.out.println("caught " + e);
+        }
+        try {
+            Evolved.removedStatic();
+        } catch (NoSuchMethodError e) {
+            System.out.println("caught " + e);
+        }
+        // Missing.class is not loaded at all.
+        try {
+            new Missing();
+        } catch (NoClassDefFoundError e) {
+            System.out.println("caught " + e);
+        }
+        // Quiet.class is compiled from a version of Quiet without greet.
+        try {
+            Greeter greeter = new Quiet();
+            greeter.greet();
+        } catch (AbstractMethodError e) {
+            System.out.println("caught " + e);
+        }
+        try {
+            unlinked();
+        } catch (UnsatisfiedLinkError e) {
+            System.out.println("caught " + e);
+        }
+
+        new IllegalStateException("printed to stderr").printStackTrace();
+    }
+
+    static native void unlinked();
+
+    static void fail(int depth) {
+        if (depth == 0) {
+            throw new IllegalArgumentException("bottom", new RuntimeException("cause"));
+        }
+        fail(depth - 1);
+    }
+}
+
+interface Named {
+    String name();
+
+    default String greet() {
+        return "I am a " + name();
+    }
+}
+
+abstract class Shape implements Named {
+    abstract double area();
+
+    String describe() {
+        return name() + " with area " + area();
+    }
+}
+
+class Circle extends Shape {
+    private final double radius;
+
+    Circle(double radius) {
+        this.radius = radius;
+    }
+
+    public String name() {
+        return "circle";
+    }
+
+    double area() {
+        return 3.0 * radius * radius;
+    }
+}
+
+class Square extends Shape {
+    private final double side;
+
+    Square(double side) {
+        this.side = side;
+    }
+
+    public String name() {
+        return "square";
+    }
+
+    double area() {
+        return side * side;
+    }
+}
+
+class Base {
+    int x = 1;
+}
+
+class Derived extends Base {
+    int x = 2;
+
+    int sum() {
+        return super.x + x;
+    }
+}
+
+class Evolved {
+    int removed;
+
+    void removedMethod() {
+    }
+
+    static void removedStatic() {
+    }
+}
+
+class Missing {
+}
+
+interface Greeter {
+    void greet();
+}
+
+class Quiet implements Greeter {
+    public void greet() {
+    }
+}
+
+enum Color {
+    RED, GREEN, BLUE
+}
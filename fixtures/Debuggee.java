@@ -0,0 +1,31 @@
+// The debugger steps through this class. Compile it with -g, so that it has the names of its
+// local variables.
+public class Debuggee {
+
+    int count;
+    String label;
+    Debuggee next;
+    static int created;
+
+    Debuggee(String label) {
+        this.label = label;
+        created++;
+    }
+
+    int add(int amount) {
+        int before = count;
+        count = before + amount;
+        return count;
+    }
+
+    public static void main(String[] args) {
+        Debuggee first = new Debuggee("first");
+        first.next = new Debuggee("second");
+        int total = 0;
+        for (int i = 1; i <= 3; i++) {
+            total += first.add(i);
+        }
+        System.out.println(total);
+    }
+
+}
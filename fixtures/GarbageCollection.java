@@ -0,0 +1,52 @@
+// Cyclic garbage is collected, and retaining more than the heap limit allows throws
+// OutOfMemoryError. Run with a small heap, e.g. -Xmx8m.
+public class GarbageCollection {
+
+    static class Node {
+
+        int key;
+        Node left, right;
+
+        Node(int key) {
+            this.key = key;
+        }
+
+    }
+
+    static Object[] retained;
+
+    public static void main(String[] args) {
+        long sum = 0;
+        for (int i = 0; i < 20000; i++) {
+            Node parent = new Node(i);
+            parent.left = new Node(i + 1);
+            parent.left.right = parent;
+            parent.right = parent;
+            Object[] ring = new Object[] { parent, null };
+            ring[1] = ring;
+            sum += parent.left.key;
+        }
+        System.out.println("sum " + sum);
+
+        int count = 0;
+        try {
+            while (true) {
+                Object[] holder = new Object[2];
+                holder[0] = retained;
+                holder[1] = new long[16384];
+                retained = holder;
+                count++;
+            }
+        } catch (OutOfMemoryError e) {
+            retained = null;
+            System.out.println("out of memory: " + e.getMessage());
+        }
+
+        System.gc();
+        Node node = new Node(count);
+        node.left = node;
+        long[] large = new long[16384];
+        System.out.println("recovered " + (node.left.key == count) + " " + large.length);
+    }
+
+}
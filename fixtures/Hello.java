@@ -0,0 +1,7 @@
+public class Hello {
+    public static void main(String[] args) {
+        int n = 42;
+        System.out.println("Hello, world!");
+        System.out.println("Hello " + n);
+    }
+}
@@ -0,0 +1,80 @@
+// Methods the optimizing tier of the JIT compiles with objects and arrays: virtual calls that it
+// inlines behind a check of the receiver class or an assumption about the class hierarchy, and
+// loops over arrays. Point3 and Triangle are only loaded once the calls have been inlined, so that
+// the optimized code has to deoptimize.
+public class Inlining {
+
+    static class Point {
+        int x;
+        int y;
+
+        int sum() {
+            return x + y;
+        }
+    }
+
+    static class Point3 extends Point {
+        int z;
+
+        int sum() {
+            return x + y + z;
+        }
+    }
+
+    static abstract class Shape {
+        int sides() {
+            return 4;
+        }
+    }
+
+    static class Square extends Shape {
+    }
+
+    static class Rectangle extends Shape {
+    }
+
+    static class Triangle extends Shape {
+        int sides() {
+            return 3;
+        }
+    }
+
+    static class Node {
+        int value;
+        Node next;
+    }
+
+    static int sum(Point p) {
+        return p.sum();
+    }
+
+    static int sides(Shape s) {
+        return s.sides() * 10;
+    }
+
+    static int sum(int[] a) {
+        int sum = 0;
+        for (int i = 0; i < a.length; i++) {
+            sum += a[i];
+        }
+        return sum;
+    }
+
+    static int sumFirst(int[] a, int n) {
+        int sum = 0;
+        for (int i = 0; i < n; i++) {
+            sum += a[i];
+        }
+        return sum;
+    }
+
+    static int total(Node node) {
+        int total = 0;
+        while (node != null) {
+            total += node.value;
+            node = node.next;
+        }
+        return total;
+    }
+
+}
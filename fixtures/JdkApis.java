@@ -0,0 +1,18 @@
+import java.util.*;
+import java.util.stream.*;
+
+public class JdkApis {
+    public static void main(String[] args) {
+        System.out.println(Arrays.toString("a,b,,c".split(",")));
+        System.out.println(String.join("-", List.of("x", "y", "z")));
+        System.out.println("ab".repeat(3));
+        System.out.println(String.format("%d %s %5.2f", 42, "str", 3.14159));
+        System.out.println("hello".chars().filter(c -> c == 'l').count());
+        System.out.println(Character.isDigit('7') + " " + Character.toUpperCase('q') + " " + Character.isLetter('!'));
+        System.out.println(Objects.hash(1, "a", null));
+        System.out.println(Math.floorMod(-7, 3));
+        System.out.println(IntStream.rangeClosed(1, 5).map(x -> x * x).sum());
+        System.out.println(Stream.of("pear", "apple", "fig").sorted().collect(Collectors.joining(", ")));
+        System.out.println(new TreeMap<>(Map.of("b", 2, "a", 1)));
+    }
+}
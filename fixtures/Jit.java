@@ -0,0 +1,115 @@
+// Static int methods that both tiers of the JIT compile, and one that they do not. The JIT and
+// the interpreter must agree on all of them.
+public class Jit {
+
+    static int fib(int n) {
+        return n < 2 ? n : fib(n - 1) + fib(n - 2);
+    }
+
+    static int gcd(int a, int b) {
+        while (b != 0) {
+            int t = a % b;
+            a = b;
+            b = t;
+        }
+        return a;
+    }
+
+    static int sumTo(int n) {
+        int sum = 0;
+        for (int i = 1; i <= n; i++) {
+            sum += i;
+        }
+        return sum;
+    }
+
+    static int collatz(int n) {
+        int steps = 0;
+        while (n != 1) {
+            n = (n & 1) == 0 ? n >> 1 : 3 * n + 1;
+            steps++;
+        }
+        return steps;
+    }
+
+    static int divide(int a, int b) {
+        return a / b + a % b;
+    }
+
+    static int bits(int x, int shift) {
+        return (x << shift) ^ (x >> shift) ^ (x >>> shift) | ~x & 0x5555;
+    }
+
+    static int narrow(int x) {
+        return (byte) x + (char) x + (short) x;
+    }
+
+    static int constants(int x) {
+        return x * 100000 - 123456789 + (-x) - 1;
+    }
+
+    static int compare(int a, int b) {
+        int result = a < b ? 1 : 0;
+        result = result * 2 + (a <= b ? 1 : 0);
+        result = result * 2 + (a > b ? 1 : 0);
+        result = result * 2 + (a >= b ? 1 : 0);
+        result = result * 2 + (a == b ? 1 : 0);
+        result = result * 2 + (a != b ? 1 : 0);
+        result = result * 2 + (a < 0 ? 1 : 0);
+        result = result * 2 + (a >= 0 ? 1 : 0);
+        result = result * 2 + (a > 0 ? 1 : 0);
+        return result * 2 + (a <= 0 ? 1 : 0);
+    }
+
+    static int depth(int n) {
+        return n == 0 ? 0 : depth(n - 1) + 1;
+    }
+
+    static void nothing(int n) {
+    }
+
+    static int callsVoid(int n) {
+        nothing(n);
+        return n + 1;
+    }
+
+    static int square(int x) {
+        return x * x;
+    }
+
+    static int sumOfSquares(int n) {
+        int sum = 0;
+        for (int i = 0; i < n; i++) {
+            sum += square(i) + square(i + 1) / (i + 1);
+        }
+        return sum;
+    }
+
+    static int folded(int x) {
+        int limit = 6 * 7;
+        if (limit > 40) {
+            return x * limit + limit / 6;
+        }
+        return x / 0;
+    }
+
+    static int quotient(int a, int b) {
+        return a / b;
+    }
+
+    static int inlinedDivision(int a, int b) {
+        return quotient(a, b) + 1;
+    }
+
+    static int manyValues(int a, int b) {
+        int c = a + b, d = a - b, e = a * b, f = a ^ b, g = a | b, h = a & b, i = a << 3, j = b >> 2;
+        int k = c * d + e;
+        return c + d * 3 + e * 5 + f * 7 + g * 11 + h * 13 + i * 17 + j * 19 + k * 23;
+    }
+
+    static int usesLongs(int n) {
+        long x = n;
+        return (int) (x * x);
+    }
+
+}
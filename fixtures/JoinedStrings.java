@@ -0,0 +1,13 @@
+public class JoinedStrings {
+    static int triangle(int n) {
+        int sum = 0;
+        for (int i = 1; i <= n; i++) {
+            sum += i;
+        }
+        return sum;
+    }
+
+    public static void main(String[] args) {
+        System.out.println(String.join("-", "x", "y") + " " + triangle(43) + " " + "x-y".contains("-"));
+    }
+}
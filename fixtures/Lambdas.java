@@ -0,0 +1,92 @@
+// Lambdas, method references and string concatenation, which javac compiles to invokedynamic.
+interface IntOperation {
+    int apply(int a, int b);
+}
+
+interface Transformer<T, R> {
+    R transform(T value);
+}
+
+interface Factory<T> {
+    T create(String name);
+}
+
+interface Action {
+    void run();
+}
+
+public class Lambdas {
+
+    private final String prefix;
+
+    Lambdas(String prefix) {
+        this.prefix = prefix;
+    }
+
+    String greet(String name) {
+        return prefix + ", " + name + "!";
+    }
+
+    void show() {
+        Action action = () -> System.out.println(prefix + " from " + this);
+        action.run();
+    }
+
+    static int twice(int x) {
+        return 2 * x;
+    }
+
+    public String toString() {
+        return "Lambdas(" + prefix + ")";
+    }
+
+    public static void main(String[] args) {
+        IntOperation add = (a, b) -> a + b;
+        IntOperation multiply = (a, b) -> a * b;
+        System.out.println("add " + add.apply(3, 4) + " multiply " + multiply.apply(3, 4));
+
+        int offset = 10;
+        IntOperation shifted = (a, b) -> a + b + offset;
+        System.out.println(shifted.apply(1, 2));
+
+        Lambdas hello = new Lambdas("Hello");
+        Transformer<String, String> greeter = hello::greet;
+        System.out.println(greeter.transform("world"));
+
+        Transformer<Integer, Integer> doubler = Lambdas::twice;
+        System.out.println(doubler.transform(21));
+
+        Transformer<String, Integer> length = String::length;
+        System.out.println(length.transform("abcde"));
+
+        Factory<Lambdas> factory = Lambdas::new;
+        System.out.println(factory.create("Hi"));
+
+        Action action = () -> System.out.println("action " + hello.prefix);
+        action.run();
+        hello.show();
+
+        IntOperation[] operations = new IntOperation[2];
+        Action[] actions = new Action[2];
+        for (int i = 0; i < 2; i++) {
+            operations[i] = (a, b) -> a - b;
+            int captured = i;
+            actions[i] = () -> System.out.println("captured " + captured);
+        }
+        System.out.println(operations[0] == operations[1]);
+        System.out.println(actions[0] == actions[1]);
+        actions[1].run();
+
+        char c = 'x';
+        long l = 1L << 40;
+        double d = 1.5;
+        float f = 0.25f;
+        boolean z = true;
+        byte b = -3;
+        short s = 7;
+        Object nothing = null;
+        System.out.println(c + "|" + l + "|" + d + "|" + f + "|" + z + "|" + b + "|" + s + "|" + nothing + "|" + hello);
+        System.out.println("tags \u0001 and \u0002 " + s + " stay");
+    }
+
+}
@@ -0,0 +1,35 @@
+import java.util.ArrayList;
+import java.util.Arrays;
+import java.util.List;
+import java.util.Objects;
+import java.util.function.BinaryOperator;
+
+public class Lists {
+    public static void main(String[] args) {
+        List<Integer> squares = new ArrayList<>();
+        for (int i = 1; i <= 5; i++) {
+            squares.add(i * i);
+        }
+        List<String> words = Arrays.asList("b", "c", "a");
+        BinaryOperator<Integer> add = Integer::sum;
+        int sum = 0;
+        for (int square : squares) {
+            sum = add.apply(sum, square);
+        }
+        System.out.println(squares + " " + words + " " + sum);
+
+        System.out.println(squares.get(2) + " " + squares.indexOf(16) + " " + words.contains("a"));
+        System.out.println(Objects.equals(words.get(0), "b") + " " + Objects.equals(null, "b") + " "
+            + Objects.hashCode(null) + " " + Objects.hashCode(7) + " " + Objects.toString(null));
+        try {
+            squares.get(5);
+        } catch (IndexOutOfBoundsException e) {
+            System.out.println(e.getMessage());
+        }
+        try {
+            Objects.requireNonNull(null, "words");
+        } catch (NullPointerException e) {
+            System.out.println(e.getMessage());
+        }
+    }
+}
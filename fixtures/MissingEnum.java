@@ -0,0 +1,24 @@
+import java.lang.annotation.ElementType;
+import java.lang.annotation.Retention;
+import java.lang.annotation.RetentionPolicy;
+
+// An annotation whose element has an enum type that cannot be loaded.
+public class MissingEnum {
+
+    @Retention(RetentionPolicy.RUNTIME)
+    @interface Kind {
+        ElementType value();
+    }
+
+    @Kind(ElementType.TYPE)
+    static class Marked {
+    }
+
+    public static void main(String[] args) {
+        try {
+            System.out.println(Marked.class.getAnnotations().length);
+        } catch (NoClassDefFoundError e) {
+            System.out.println("NoClassDefFoundError: " + e.getMessage());
+        }
+    }
+}
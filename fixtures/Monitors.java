@@ -0,0 +1,83 @@
+// Synchronized blocks and methods. Object.notify throws IllegalMonitorStateException unless the
+// monitor is held, which tells whether it was released.
+public class Monitors {
+
+    private int count;
+
+    synchronized void increment() {
+        count++;
+    }
+
+    synchronized void incrementTwice() {
+        increment();
+        increment();
+    }
+
+    synchronized void fail() {
+        throw new IllegalStateException("failed");
+    }
+
+    static synchronized boolean ownsClass() {
+        return owns(Monitors.class);
+    }
+
+    static boolean owns(Object lock) {
+        try {
+            lock.notify();
+            return true;
+        } catch (IllegalMonitorStateException e) {
+            return false;
+        }
+    }
+
+    static Object nothing() {
+        return null;
+    }
+
+    public static void main(String[] args) throws InterruptedException {
+        Object lock = new Object();
+        System.out.println(owns(lock));
+        synchronized (lock) {
+            System.out.println(owns(lock));
+            synchronized (lock) {
+                lock.notifyAll();
+            }
+            System.out.println(owns(lock));
+            lock.wait(1);
+            try {
+                lock.wait(-1);
+            } catch (IllegalArgumentException e) {
+                System.out.println(e.getMessage());
+            }
+        }
+        System.out.println(owns(lock));
+
+        Monitors monitors = new Monitors();
+        monitors.incrementTwice();
+        System.out.println(monitors.count + " " + owns(monitors) + " " + ownsClass() + " " + owns(Monitors.class));
+        try {
+            monitors.fail();
+        } catch (IllegalStateException e) {
+            System.out.println(e.getMessage() + " " + owns(monitors));
+        }
+
+        try {
+            lock.wait();
+        } catch (IllegalMonitorStateException e) {
+            System.out.println("wait: " + e.getMessage());
+        }
+
+        int[] array = new int[1];
+        synchronized (array) {
+            System.out.println(owns(array));
+        }
+        try {
+            synchronized (nothing()) {
+                System.out.println("unreachable");
+            }
+        } catch (NullPointerException e) {
+            System.out.println("null lock");
+        }
+    }
+
+}
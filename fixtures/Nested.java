@@ -0,0 +1,44 @@
+import java.lang.reflect.Modifier;
+import java.util.Arrays;
+import java.util.function.Supplier;
+
+// Nested classes as reflection sees them through the InnerClasses and EnclosingMethod attributes.
+public class Nested {
+
+    public static class Member {
+    }
+
+    private class Inner {
+    }
+
+    static Class<?> local() {
+        class Local {
+        }
+        return Local.class;
+    }
+
+    static void describe(Class<?> type) {
+        Class<?> enclosing = type.getEnclosingClass();
+        System.out.println(type.getName() + " [" + type.getSimpleName() + "] member=" + type.isMemberClass()
+            + " local=" + type.isLocalClass() + " anonymous=" + type.isAnonymousClass()
+            + " declaring=" + type.getDeclaringClass() + " enclosing=" + (enclosing == null ? null : enclosing.getName())
+            + " modifiers=[" + Modifier.toString(type.getModifiers()) + "]");
+    }
+
+    public static void main(String[] args) {
+        Supplier<String> anonymous = new Supplier<String>() {
+            public String get() {
+                return "anonymous";
+            }
+        };
+        describe(Nested.class);
+        describe(Member.class);
+        describe(Inner.class);
+        describe(local());
+        describe(anonymous.getClass());
+        System.out.println(local().getEnclosingMethod().getName() + " " + anonymous.getClass().getEnclosingMethod().getName());
+        String[] declared = Arrays.stream(Nested.class.getDeclaredClasses()).map(Class::getSimpleName).sorted().toArray(String[]::new);
+        System.out.println(Arrays.toString(declared));
+        System.out.println(Member[].class.getSimpleName() + " " + int.class.getSimpleName() + " " + Member.class.getCanonicalName());
+    }
+}
@@ -0,0 +1,27 @@
+import java.util.HashSet;
+import java.util.List;
+import java.util.Set;
+
+public class Records {
+    record Point(int x, int y) {}
+
+    record Item(String name, double weight, long id, Object tag) {}
+
+    record Empty() {}
+
+    public static void main(String[] args) {
+        Point p = new Point(1, 2);
+        System.out.println(p);
+        System.out.println(p.equals(new Point(1, 2)) + " " + p.equals(new Point(2, 1)) + " " + p.equals(null));
+        System.out.println(p.hashCode() == new Point(1, 2).hashCode());
+
+        Item item = new Item("box", 1.5, 7L, List.of(p));
+        System.out.println(item);
+        System.out.println(item.equals(new Item("box", 1.5, 7L, List.of(new Point(1, 2)))));
+        System.out.println(item.equals(new Item("box", 1.5, 8L, null)));
+
+        Set<Point> points = new HashSet<>(List.of(p, new Point(3, 4)));
+        System.out.println(points.contains(new Point(3, 4)) + " " + points.size());
+        System.out.println(new Empty() + " " + new Empty().equals(new Empty()) + " " + new Empty().hashCode());
+    }
+}
@@ -0,0 +1,149 @@
+import java.lang.reflect.Constructor;
+import java.lang.reflect.Field;
+import java.lang.reflect.InvocationTargetException;
+import java.lang.reflect.Method;
+import java.lang.reflect.Modifier;
+
+public class Reflection {
+
+    public interface Shape {
+        int sum();
+    }
+
+    public static class Point implements Shape {
+        private int x;
+        public int y;
+        static int created;
+        String label = "point";
+        public final long id = 7;
+
+        public Point(int x, int y) {
+            this.x = x;
+            this.y = y;
+            created++;
+        }
+
+        Point() {
+            this(0, 0);
+        }
+
+        public int sum() {
+            return x + y;
+        }
+
+        private long scaled(long factor) {
+            return (x + y) * factor;
+        }
+
+        public static int twice(int value) {
+            return value * 2;
+        }
+
+        public void fail(String message) {
+            throw new IllegalStateException(message);
+        }
+
+        public String toString() {
+            return label + "(" + x + ", " + y + ")";
+        }
+    }
+
+    public static class Point3 extends Point {
+        public int z;
+
+        public Point3(int x, int y, int z) {
+            super(x, y);
+            this.z = z;
+        }
+
+        public int sum() {
+            return super.sum() + z;
+        }
+    }
+
+    public static void main(String[] args) throws Exception {
+        Class<?> point = Class.forName("Reflection$Point");
+        Class<?> point3 = Point3.class;
+        System.out.println(point.getName() + " " + point.getSimpleName() + " " + Modifier.toString(Reflection.class.getModifiers())
+            + " [" + Modifier.toString(point.getModifiers()) + "] [" + Modifier.toString(Shape.class.getModifiers()) + "]");
+        System.out.println(point3.getSuperclass().getName() + " " + point.getInterfaces()[0].getSimpleName());
+        System.out.println(point.isAssignableFrom(point3) + " " + point3.isAssignableFrom(point) + " " + Shape.class.isInterface());
+        System.out.println(int[].class.isArray() + " " + int[].class.getComponentType() + " " + Integer.TYPE.isPrimitive());
+        System.out.println(point.isInstance(new Point3(1, 2, 3)) + " " + Object.class.getSuperclass());
+
+        for (Field field : point.getDeclaredFields()) {
+            System.out.println(field + " " + field.getType().getName() + " " + field.getModifiers());
+        }
+        System.out.println(point3.getFields().length + " public fields, " + point.getDeclaredMethods().length + " methods, "
+            + point.getDeclaredConstructors().length + " constructors, " + point.getConstructors().length + " public");
+
+        Constructor<?> constructor = point.getConstructor(int.class, int.class);
+        System.out.println(constructor + " " + constructor.getParameterCount());
+        Object p = constructor.newInstance(3, 4);
+        System.out.println(p);
+
+        Method sum = point.getMethod("sum");
+        System.out.println(sum + " returns " + sum.getReturnType());
+        System.out.println(sum.invoke(p) + " " + sum.invoke(new Point3(1, 2, 3)));
+
+        Method twice = point.getDeclaredMethod("twice", int.class);
+        System.out.println(twice + " " + Modifier.isStatic(twice.getModifiers()) + " " + twice.invoke(null, 21));
+
+        Method scaled = point.getDeclaredMethod("scaled", long.class);
+        System.out.println(scaled + " " + scaled.invoke(p, 10));
+
+        Method toString = point.getMethod("toString");
+        System.out.println(toString.getDeclaringClass().getSimpleName() + " " + toString.invoke(p));
+
+        Field x = point.getDeclaredField("x");
+        x.setInt(p, 10);
+        System.out.println(x.get(p) + " " + x.getInt(p) + " " + x.getLong(p) + " " + p);
+        Field label = point.getDeclaredField("label");
+        label.set(p, "moved");
+        Field created = point.getDeclaredField("created");
+        System.out.println(p + " " + created.get(null));
+
+        Field id = point.getField("id");
+        System.out.println(id + " " + id.get(p));
+        try {
+            id.set(p, 8L);
+        } catch (IllegalAccessException e) {
+            System.out.println(e.getMessage());
+        }
+        id.setAccessible(true);
+        id.set(p, 8L);
+        System.out.println(id.get(p));
+
+        try {
+            point.getMethod("scaled", long.class);
+        } catch (NoSuchMethodException e) {
+            System.out.println("NoSuchMethodException: " + e.getMessage());
+        }
+        try {
+            point.getDeclaredField("missing");
+        } catch (NoSuchFieldException e) {
+            System.out.println("NoSuchFieldException: " + e.getMessage());
+        }
+        try {
+            sum.invoke("not a point");
+        } catch (IllegalArgumentException e) {
+            System.out.println("IllegalArgumentException: " + e.getMessage());
+        }
+        try {
+            twice.invoke(null, "21");
+        } catch (IllegalArgumentException e) {
+            System.out.println("IllegalArgumentException: " + e.getMessage());
+        }
+        try {
+            point.getMethod("fail", String.class).invoke(p, "failed on purpose");
+        } catch (InvocationTargetException e) {
+            System.out.println("InvocationTargetException: " + e.getCause());
+        }
+        try {
+            Class.forName("Missing");
+        } catch (ClassNotFoundException e) {
+            System.out.println("ClassNotFoundException: " + e.getMessage());
+        }
+        System.out.println(Modifier.toString(Modifier.PUBLIC | Modifier.STATIC | Modifier.FINAL) + " " + Modifier.isPrivate(x.getModifiers()));
+    }
+}
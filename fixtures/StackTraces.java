@@ -0,0 +1,56 @@
+// Stack traces name the source file and line of every frame, leaving out the constructors of
+// the exception.
+public class StackTraces {
+
+    static class Counter {
+
+        int[] values = new int[2];
+
+        void set(int index, int value) {
+            values[index] = value;
+        }
+
+    }
+
+    static class Failure extends RuntimeException {
+
+        Failure(String message, Throwable cause) {
+            super(message, cause);
+        }
+
+    }
+
+    // Has no stack trace, which makes it cheap to create.
+    static class Quiet extends RuntimeException {
+
+        @Override
+        public Throwable fillInStackTrace() {
+            return this;
+        }
+
+    }
+
+    static void update(Counter counter, int index) {
+        try {
+            counter.set(index, 1);
+        } catch (ArrayIndexOutOfBoundsException e) {
+            throw new Failure("update failed", e);
+        }
+    }
+
+    public static void main(String[] args) {
+        try {
+            update(new Counter(), 2);
+        } catch (Failure e) {
+            for (StackTraceElement element : e.getStackTrace()) {
+                System.out.println(element);
+            }
+            StackTraceElement top = e.getCause().getStackTrace()[0];
+            System.out.println(top.getClassName() + " " + top.getMethodName() + " " + top.getFileName() + " " + top.getLineNumber());
+            e.printStackTrace();
+        }
+        System.out.println(new Quiet().getStackTrace().length);
+        update(new Counter(), -1);
+    }
+
+}
@@ -0,0 +1,26 @@
+public class SystemProperties {
+    public static void main(String[] args) {
+        System.out.println(System.getProperty("greeting"));
+        System.out.println(System.getProperty("missing", "fallback"));
+        System.out.println(System.getProperty("missing"));
+        System.out.println(System.setProperty("greeting", "changed"));
+        System.out.println(System.getProperty("greeting"));
+
+        System.out.println(args.length);
+        for (int i = 0; i < args.length; i++) {
+            System.out.println(args[i]);
+        }
+
+        try {
+            System.getProperty("");
+        } catch (IllegalArgumentException e) {
+            System.out.println(e.getMessage());
+        }
+
+        try {
+            System.exit(3);
+        } finally {
+            System.out.println("not reached");
+        }
+    }
+}
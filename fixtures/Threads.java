@@ -0,0 +1,164 @@
+// Threads communicating through monitors, a volatile field and class initialization. Everything
+// printed is independent of how the threads are scheduled.
+public class Threads {
+
+    // A bounded buffer as in the classic producer/consumer problem.
+    static class Buffer {
+
+        private final int[] items = new int[4];
+        private int head;
+        private int size;
+
+        synchronized void put(int item) throws InterruptedException {
+            while (size == items.length) {
+                wait();
+            }
+            items[(head + size) % items.length] = item;
+            size++;
+            notifyAll();
+        }
+
+        synchronized int take() throws InterruptedException {
+            while (size == 0) {
+                wait();
+            }
+            int item = items[head];
+            head = (head + 1) % items.length;
+            size--;
+            notifyAll();
+            return item;
+        }
+
+    }
+
+    // The first thread to use Slow initializes it while the others wait.
+    static class Slow {
+
+        static final int VALUE;
+
+        static {
+            try {
+                Thread.sleep(50);
+            } catch (InterruptedException e) {
+                throw new RuntimeException(e);
+            }
+            VALUE = 42;
+        }
+
+    }
+
+    static class Counter extends Thread {
+
+        private static int total;
+
+        @Override
+        public void run() {
+            for (int i = 0; i < 1000; i++) {
+                synchronized (Counter.class) {
+                    total++;
+                }
+            }
+        }
+
+    }
+
+    static volatile boolean stop;
+    static int consumed;
+
+    public static void main(String[] args) throws InterruptedException {
+        Thread current = Thread.currentThread();
+        System.out.println(current.getName() + " " + current.isAlive() + " " + current.isDaemon());
+
+        Buffer buffer = new Buffer();
+        Thread producer = new Thread(() -> {
+            try {
+                for (int i = 1; i <= 100; i++) {
+                    buffer.put(i);
+                }
+                buffer.put(-1);
+            } catch (InterruptedException e) {
+                throw new RuntimeException(e);
+            }
+        });
+        Thread consumer = new Thread(() -> {
+            try {
+                for (int item = buffer.take(); item != -1; item = buffer.take()) {
+                    consumed += item;
+                }
+            } catch (InterruptedException e) {
+                throw new RuntimeException(e);
+            }
+        }, "consumer");
+        System.out.println(producer.getName() + " " + consumer.getName() + " " + producer.isAlive());
+        consumer.start();
+        producer.start();
+        producer.join();
+        consumer.join();
+        System.out.println("consumed " + consumed + " " + consumer.isAlive());
+
+        Counter[] counters = new Counter[4];
+        for (int i = 0; i < counters.length; i++) {
+            counters[i] = new Counter();
+            counters[i].start();
+        }
+        for (Counter counter : counters) {
+            counter.join();
+        }
+        System.out.println("counted " + Counter.total);
+
+        Thread spinner = new Thread(() -> {
+            long spins = 0;
+            while (!stop) {
+                spins++;
+            }
+            System.out.println("stopped");
+        });
+        spinner.start();
+        Thread.sleep(10);
+        stop = true;
+        spinner.join();
+
+        int[] values = new int[3];
+        Thread[] readers = new Thread[values.length];
+        for (int i = 0; i < readers.length; i++) {
+            int index = i;
+            readers[i] = new Thread(() -> values[index] = Slow.VALUE);
+            readers[i].start();
+        }
+        for (Thread reader : readers) {
+            reader.join();
+        }
+        System.out.println(values[0] + " " + values[1] + " " + values[2]);
+
+        Thread named = new Thread(() -> System.out.println("in " + Thread.currentThread().getName()), "worker");
+        named.start();
+        named.join();
+        try {
+            named.start();
+        } catch (IllegalThreadStateException e) {
+            System.out.println("started twice");
+        }
+
+        Thread daemon = new Thread(() -> {});
+        daemon.setDaemon(true);
+        System.out.println("daemon " + daemon.isDaemon());
+
+        Thread failing = new Thread(() -> {
+            throw new IllegalStateException("failed");
+        }, "failing");
+        failing.start();
+        failing.join();
+
+        // main returns first, but the process waits for the last thread.
+        new Thread(() -> {
+            try {
+                Thread.sleep(20);
+            } catch (InterruptedException e) {
+                throw new RuntimeException(e);
+            }
+            System.out.println("last");
+        }).start();
+        System.out.println("main done");
+    }
+
+}
@@ -0,0 +1,13 @@
+package greetings;
+
+public class Greeter implements Greeting {
+    private final String salutation;
+
+    public Greeter(String salutation) {
+        this.salutation = salutation;
+    }
+
+    public String greet(String name) {
+        return new StringBuilder(salutation).append(", ").append(name).append('!').toString();
+    }
+}
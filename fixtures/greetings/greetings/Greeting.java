@@ -0,0 +1,5 @@
+package greetings;
+
+public interface Greeting {
+    String greet(String name);
+}
@@ -0,0 +1,8 @@
+package greetings;
+
+public class Main {
+    public static void main(String[] args) {
+        Greeting greeting = new Greeter("Hello");
+        System.out.println(greeting.greet("module"));
+    }
+}
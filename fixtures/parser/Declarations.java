@@ -0,0 +1,144 @@
+package corpus;
+
+import java.lang.annotation.*;
+import java.util.List;
+
+@Retention(RetentionPolicy.RUNTIME)
+@Target({ElementType.TYPE, ElementType.METHOD})
+@interface Marker {
+    String value() default "";
+    int[] numbers() default {1, 2};
+    Class<?> type() default Object.class;
+    ElementType element() default ElementType.TYPE;
+    Deprecated nested() default @Deprecated;
+}
+
+record Point(int x, int y) implements Comparable<Point> {
+    static final Point ORIGIN = new Point(0, 0);
+
+    Point {
+        if (x < 0) throw new IllegalArgumentException();
+    }
+
+    Point(int both) {
+        this(both, both);
+    }
+
+    public int compareTo(Point other) {
+        return Integer.compare(x, other.x);
+    }
+}
+
+record Pair<A, B>(A first, @Deprecated B second, int... rest) {}
+
+sealed interface Shape permits Circle, Square, Polygon {}
+final class Circle implements Shape {}
+non-sealed class Square implements Shape {}
+sealed abstract class Polygon implements Shape permits Triangle {}
+final class Triangle extends Polygon {}
+
+enum Planet implements Comparable<Planet> {
+    MERCURY(3.303e+23, 2.4397e6),
+    EARTH(5.976e+24, 6.37814e6) {
+        @Override
+        public String toString() {
+            return "home";
+        }
+    },
+    ;
+
+    private final double mass, radius;
+
+    Planet(double mass, double radius) {
+        this.mass = mass;
+        this.radius = radius;
+    }
+
+    double gravity() { return mass / (radius * radius); }
+}
+
+enum Empty {}
+enum Trailing { A, B, }
+
+@Marker("class")
+public abstract class Declarations<T> extends Object implements Cloneable, Runnable {
+    private static final long serialVersionUID = 1L;
+    protected transient volatile int[] counts[], total;
+    public static int counter;
+
+    static {
+        counter = 1;
+    }
+
+    {
+        total = null;
+    }
+
+    public Declarations() throws Exception {
+        super();
+    }
+
+    <U> Declarations(U value, T... rest) throws Exception {
+        this();
+    }
+
+    public abstract void run();
+
+    native int nativeMethod();
+
+    synchronized strictfp double compute(final int a, @Deprecated long b) {
+        return a + b;
+    }
+
+    int oldStyleArray()[] {
+        return null;
+    }
+
+    void receiver(Declarations<T> this, int x) {}
+
+    class Inner {
+        Inner() {}
+
+        Declarations<T> outer() {
+            return Declarations.this;
+        }
+    }
+
+    static class Nested extends Declarations<String>.Inner {
+        Nested(Declarations<String> outer) {
+            outer.super();
+        }
+    }
+
+    interface Api {
+        int CONSTANT = 1;
+        void method();
+        default void defaulted() {}
+        static void utility() {}
+        private void helper() {}
+    }
+
+    void locals() {
+        class Local {}
+        record LocalRecord(int a) {}
+        enum LocalEnum { A }
+        interface LocalInterface {}
+        final var x = new Local();
+        Runnable anonymous = new Runnable() {
+            int field;
+
+            public void run() {
+                field++;
+            }
+        };
+        Inner inner = this.new Inner();
+        Object object = "text";
+        if (object instanceof String string && !string.isEmpty()) {
+            System.out.println(string.length());
+        }
+        if (object instanceof final String s) {}
+        boolean plain = object instanceof List<?>;
+        var list = List.of(1, 2, 3);
+        @SuppressWarnings("unused") int annotated = 0;
+    }
+}
@@ -0,0 +1,50 @@
+package corpus;
+
+import java.io.Serializable;
+import java.util.*;
+import java.util.function.Function;
+import static java.util.Collections.emptyList;
+import static java.util.Map.*;
+
+class Generics<K extends Comparable<? super K>, V> implements Serializable {
+    private final Map<K, List<Map<String, V>>> nested = new HashMap<>();
+    private Map<String, Map<String, Map<String, Integer>>> deep;
+    List<? extends Number>[] arrays;
+    Map.Entry<K, V> entry;
+
+    <T extends Number & Comparable<T>> T max(List<? extends T> values) {
+        T best = null;
+        for (T value : values) {
+            if (best == null || value.compareTo(best) > 0) {
+                best = value;
+            }
+        }
+        return best;
+    }
+
+    static <A, B> Function<A, B> constant(B value) {
+        return a -> value;
+    }
+
+    void calls() {
+        List<String> strings = Collections.<String>emptyList();
+        List<String> more = emptyList();
+        Map<String, List<Integer>> map = new TreeMap<String, List<Integer>>();
+        boolean less = strings.size() < more.size();
+        boolean shifted = 1 >> 2 > 0 && 16 >>> 2 >= 4;
+        int x = 1, y = 2;
+        boolean comparison = x < y && y > x;
+        Object o = (Comparable<String> & Serializable) "s";
+        Generics<String, Integer> g = new Generics<>();
+        Integer i = g.<Integer>max(Arrays.asList(1, 2, 3));
+        List<List<String>> lists = new ArrayList<List<String>>();
+        Class<?> c = String[].class;
+        Class<?> p = int.class;
+        Class<?> v = void.class;
+        Class<?> q = java.util.Map.Entry.class;
+    }
+
+    interface Visitor<R, E extends Exception> {
+        R visit(Object node) throws E;
+    }
+}
@@ -0,0 +1,46 @@
+package corpus;
+
+import java.util.*;
+import java.util.function.*;
+
+class Lambdas {
+    Runnable empty = () -> {};
+    Supplier<String> supplier = () -> "value";
+    Function<Integer, Integer> square = x -> x * x;
+    BiFunction<Integer, Integer, Integer> add = (a, b) -> a + b;
+    BinaryOperator<Integer> typed = (Integer a, Integer b) -> { return a * b; };
+    BiConsumer<String, String> inferred = (var a, final var b) -> System.out.println(a + b);
+    Function<Integer, Function<Integer, Integer>> curried = a -> b -> a - b;
+    Comparator<String> comparator = Comparator.comparing(String::length).thenComparing(Comparator.naturalOrder());
+    Function<String, Integer> parse = Integer::parseInt;
+    Supplier<List<String>> constructor = ArrayList::new;
+    Function<Integer, int[]> arrays = int[]::new;
+    Function<Integer, String[]> stringArrays = String[]::new;
+    Function<List<String>, Integer> size = List<String>::size;
+    Object cast = (Runnable) () -> System.out.println("cast");
+    Runnable ternary = true ? () -> {} : () -> System.out.println();
+
+    String name() {
+        Supplier<String> s = super::toString;
+        Supplier<String> t = this::toString;
+        Supplier<String> q = Lambdas.super::toString;
+        return s.get() + t.get() + q.get();
+    }
+
+    void streams(List<String> words) {
+        words.stream()
+            .filter(word -> !word.isEmpty())
+            .map(word -> word.toUpperCase())
+            .forEach(System.out::println);
+        words.forEach((String word) -> {
+            if (word.length() > 3) {
+                return;
+            }
+        });
+        Callable<Runnable> nested = () -> () -> {};
+    }
+
+    interface Callable<T> {
+        T call() throws Exception;
+    }
+}
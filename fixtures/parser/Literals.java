@@ -0,0 +1,42 @@
+package corpus;
+
+class Literals {
+    int decimal = 1_000_000;
+    int hex = 0xCAFE_BABE;
+    int octal = 0777;
+    int binary = 0b1010_1010;
+    int min = -2147483648;
+    long big = 0x7FFF_FFFF_FFFF_FFFFL;
+    long minLong = -9223372036854775808L;
+    float f = 3.14f;
+    float hexFloat = 0x1.8p1f;
+    double d = 1e-10;
+    double noFraction = 1.;
+    double leadingDot = .5;
+    double exponent = 6.022_140e+23d;
+    double hexDouble = 0x.8P-2;
+    char c = 'x';
+    char escaped = '\n';
+    char quote = '\'';
+    char octalChar = '\101';
+    char unicode = 'é';
+    String s = "tab\there \"quoted\" \\ \0 \s";
+    String empty = "";
+    String emoji = "😀";
+    boolean t = true, f2 = false;
+    Object nothing = null;
+
+    String block = """
+        Hello,
+          "World"!
+        line \
+        continued\s
+        """;
+
+    String closed = """
+        no trailing newline""";
+
+    // A comment with \\u000A-looking text and /* nested-looking */ markers.
+    /** Javadoc with @tags. */
+    int ab = 'a' + 'b';
+}
@@ -0,0 +1,88 @@
+package corpus;
+
+import java.io.*;
+
+class Statements {
+    int[][] grid = {{1, 2}, {3, 4}, {}};
+
+    void loops(int[] values) throws IOException {
+        int sum = 0;
+        for (int i = 0, j = values.length - 1; i < j; i++, j--) {
+            sum += values[i] * values[j];
+        }
+        for (;;) {
+            break;
+        }
+        for (int value : values) sum -= value;
+        int k = 0;
+        while (k < 10) k++;
+        do {
+            k--;
+        } while (k > 0);
+
+        outer:
+        for (int[] row : grid) {
+            for (int cell : row) {
+                if (cell == 3) continue outer;
+                if (cell == 4) break outer;
+            }
+        }
+
+        int[] created = new int[10];
+        int[][] partial = new int[3][];
+        String[][] strings = new String[][] {{"a"}, {"b", "c"}};
+        int length = new int[] {1, 2, 3}.length;
+        grid[0][1] = created[sum & 7] = 5;
+    }
+
+    void exceptions(File file) throws Exception {
+        try (InputStream in = new FileInputStream(file); var out = new ByteArrayOutputStream()) {
+            in.transferTo(out);
+        } catch (FileNotFoundException | SecurityException e) {
+            throw e;
+        } catch (final IOException e) {
+            throw new UncheckedIOException(e);
+        } finally {
+            System.out.println("done");
+        }
+
+        InputStream stream = new ByteArrayInputStream(new byte[0]);
+        try (stream) {
+            stream.read();
+        }
+
+        try {
+            throw new Exception("message");
+        } finally {
+        }
+    }
+
+    synchronized void operators(int a, int b, boolean c) {
+        int x = a + b * 2 - (a - b) / 3 % 4;
+        x += 1; x -= 1; x *= 2; x /= 2; x %= 3;
+        x &= 0xFF; x |= 1; x ^= 2; x <<= 1; x >>= 1; x >>>= 1;
+        boolean y = !c || a > b && a != b | a == b ^ c & true;
+        int z = ~x << 2 >> 1 >>> 3;
+        int w = c ? a : b > 0 ? b : -b;
+        x = +x; x = -x; ++x; --x; x++; x--;
+        long l = (long) x + (int) 'a';
+        double d = (double) -x;
+        Object o = (Object) (Integer) x;
+        String s = (String) o + "";
+        char ch = (char) (x + '0');
+        int paren = (x) + (b);
+        synchronized (this) {
+            assert x > 0;
+            assert x > 0 : "positive";
+        }
+        ;
+    }
+
+    class Node {
+        Node next;
+    }
+
+    int chains(Node node) {
+        return node.next.next.hashCode() + this.grid[0].length + Statements.this.grid.length + super.hashCode();
+    }
+}
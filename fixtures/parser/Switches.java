@@ -0,0 +1,57 @@
+package corpus;
+
+class Switches {
+    enum Day { MONDAY, TUESDAY, WEDNESDAY, THURSDAY, FRIDAY, SATURDAY, SUNDAY }
+
+    int classic(int n) {
+        int result = 0;
+        switch (n) {
+            case 1:
+            case 2:
+                result = 1;
+                break;
+            case 3: {
+                result = 3;
+            }
+            default:
+                result++;
+        }
+        return result;
+    }
+
+    String arrows(Day day) {
+        switch (day) {
+            case MONDAY, FRIDAY -> System.out.println("work");
+            case SATURDAY -> {
+                System.out.println("weekend");
+            }
+            default -> throw new IllegalStateException();
+        }
+
+        int length = switch (day) {
+            case MONDAY, FRIDAY, SUNDAY -> 6;
+            case TUESDAY -> 7;
+            case THURSDAY, SATURDAY -> 8;
+            default -> {
+                int k = day.toString().length();
+                yield k;
+            }
+        };
+
+        String kind = switch (length) {
+            case 6:
+                yield "six";
+            case 7:
+            default:
+                yield "other";
+        };
+
+        return kind + switch ("a") { case "a" -> 'a'; default -> 'b'; };
+    }
+
+    int yieldAsName() {
+        int yield = 1;
+        yield = yield + 1;
+        return yield;
+    }
+}
@@ -0,0 +1,303 @@
+// Relocatable ELF64 object files for x86-64, with just what compiled methods need: a .text
+// section, symbols for the methods in it and for what they refer to outside it, and R_X86_64_64
+// relocations for the absolute addresses in the code.
+
+const ET_REL: u16 = 1;
+const EM_X86_64: u16 = 62;
+
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_RELA: u32 = 4;
+
+const SHF_ALLOC: u64 = 2;
+const SHF_EXECINSTR: u64 = 4;
+const SHF_INFO_LINK: u64 = 0x40;
+
+const STB_GLOBAL: u8 = 1;
+const STT_NOTYPE: u8 = 0;
+const STT_FUNC: u8 = 2;
+
+pub const R_X86_64_64: u32 = 1;
+
+const HEADER_SIZE: usize = 64;
+const SECTION_HEADER_SIZE: usize = 64;
+const SYMBOL_SIZE: usize = 24;
+const RELOCATION_SIZE: usize = 24;
+
+// The section header indices of the objects written here.
+const TEXT: u16 = 1;
+const SYMTAB: u32 = 3;
+const STRTAB: u32 = 4;
+const SHSTRTAB: u16 = 5;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    // The offset in .text of a defined symbol.
+    pub value: u64,
+    pub size: u64,
+    pub defined: bool
+}
+
+// Patches the 8 bytes at offset in .text with the address of a symbol plus addend.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Relocation {
+    pub offset: u64,
+    pub symbol: usize,
+    pub addend: i64
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Object {
+    pub text: Vec<u8>,
+    // Indices into symbols start at 1, since symbol 0 of an ELF file is always the null symbol.
+    pub symbols: Vec<Symbol>,
+    pub relocations: Vec<Relocation>
+}
+
+impl Object {
+
+    pub fn symbol(&self, index: usize) -> Option<&Symbol> {
+        index.checked_sub(1).and_then(|index| self.symbols.get(index))
+    }
+
+    pub fn write(&self) -> Vec<u8> {
+        let mut strtab = vec![0];
+        let mut symtab = vec![0; SYMBOL_SIZE];
+        for symbol in self.symbols.iter() {
+            let name = strtab.len() as u32;
+            strtab.extend_from_slice(symbol.name.as_bytes());
+            strtab.push(0);
+
+            let (kind, section) = if symbol.defined { (STT_FUNC, TEXT) } else { (STT_NOTYPE, 0) };
+            symtab.extend_from_slice(&name.to_le_bytes());
+            symtab.push(STB_GLOBAL << 4 | kind);
+            symtab.push(0);
+            symtab.extend_from_slice(&section.to_le_bytes());
+            symtab.extend_from_slice(&symbol.value.to_le_bytes());
+            symtab.extend_from_slice(&symbol.size.to_le_bytes());
+        }
+
+        let mut rela = Vec::new();
+        for relocation in self.relocations.iter() {
+            rela.extend_from_slice(&relocation.offset.to_le_bytes());
+            rela.extend_from_slice(&((relocation.symbol as u64) << 32 | R_X86_64_64 as u64).to_le_bytes());
+            rela.extend_from_slice(&relocation.addend.to_le_bytes());
+        }
+
+        let mut shstrtab = vec![0];
+        let mut section_name = |name: &str| {
+            let index = shstrtab.len() as u32;
+            shstrtab.extend_from_slice(name.as_bytes());
+            shstrtab.push(0);
+            index
+        };
+        let names = [section_name(".text"), section_name(".rela.text"), section_name(".symtab"), section_name(".strtab"), section_name(".shstrtab")];
+
+        // The header, then the contents of the sections, then the section headers.
+        let mut file = vec![0; HEADER_SIZE];
+        let place = |file: &mut Vec<u8>, contents: &[u8], alignment: usize| {
+            while !file.len().is_multiple_of(alignment) {
+                file.push(0);
+            }
+            let offset = file.len() as u64;
+            file.extend_from_slice(contents);
+            offset
+        };
+        let text_offset = place(&mut file, &self.text, 16);
+        let rela_offset = place(&mut file, &rela, 8);
+        let symtab_offset = place(&mut file, &symtab, 8);
+        let strtab_offset = place(&mut file, &strtab, 1);
+        let shstrtab_offset = place(&mut file, &shstrtab, 1);
+        let section_headers = place(&mut file, &[], 8);
+
+        let sections = [
+            SectionHeader::default(),
+            SectionHeader { name: names[0], kind: SHT_PROGBITS, flags: SHF_ALLOC | SHF_EXECINSTR, offset: text_offset, size: self.text.len() as u64, alignment: 16, ..SectionHeader::default() },
+            SectionHeader { name: names[1], kind: SHT_RELA, flags: SHF_INFO_LINK, offset: rela_offset, size: rela.len() as u64, link: SYMTAB, info: TEXT as u32, alignment: 8, entry_size: RELOCATION_SIZE as u64 },
+            // info is the index of the first global symbol, since all of them are.
+            SectionHeader { name: names[2], kind: SHT_SYMTAB, offset: symtab_offset, size: symtab.len() as u64, link: STRTAB, info: 1, alignment: 8, entry_size: SYMBOL_SIZE as u64, ..SectionHeader::default() },
+            SectionHeader { name: names[3], kind: SHT_STRTAB, offset: strtab_offset, size: strtab.len() as u64, alignment: 1, ..SectionHeader::default() },
+            SectionHeader { name: names[4], kind: SHT_STRTAB, offset: shstrtab_offset, size: shstrtab.len() as u64, alignment: 1, ..SectionHeader::default() }
+        ];
+        for section in sections.iter() {
+            section.write(&mut file);
+        }
+
+        let mut header = Vec::with_capacity(HEADER_SIZE);
+        header.extend_from_slice(b"\x7fELF");
+        // 64 bit, little endian, version 1, System V ABI
+        header.extend_from_slice(&[2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        header.extend_from_slice(&ET_REL.to_le_bytes());
+        header.extend_from_slice(&EM_X86_64.to_le_bytes());
+        header.extend_from_slice(&1u32.to_le_bytes());
+        // No entry point and no program headers.
+        header.extend_from_slice(&0u64.to_le_bytes());
+        header.extend_from_slice(&0u64.to_le_bytes());
+        header.extend_from_slice(&section_headers.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&(HEADER_SIZE as u16).to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(&(SECTION_HEADER_SIZE as u16).to_le_bytes());
+        header.extend_from_slice(&(sections.len() as u16).to_le_bytes());
+        header.extend_from_slice(&SHSTRTAB.to_le_bytes());
+        file[..HEADER_SIZE].copy_from_slice(&header);
+
+        file
+    }
+
+    // Reads an object file with a .text section and its relocations, such as write produces.
+    pub fn read(file: &[u8]) -> Result<Object, String> {
+        if file.len() < HEADER_SIZE || &file[..4] != b"\x7fELF" || file[4] != 2 || file[5] != 1 {
+            return Err(String::from("not a little endian ELF64 file"));
+        }
+        if u16_at(file, 16)? != ET_REL || u16_at(file, 18)? != EM_X86_64 {
+            return Err(String::from("not a relocatable x86-64 object"));
+        }
+
+        let section_headers = u64_at(file, 40)? as usize;
+        let sections = (0..u16_at(file, 60)? as usize)
+            .map(|index| SectionHeader::read(file, section_headers + index * SECTION_HEADER_SIZE))
+            .collect::<Result<Vec<SectionHeader>, String>>()?;
+        let contents = |section: &SectionHeader| {
+            file.get(section.offset as usize..(section.offset + section.size) as usize)
+                .ok_or_else(|| String::from("section out of bounds"))
+        };
+        let shstrtab = contents(sections.get(u16_at(file, 62)? as usize).ok_or("no section names")?)?;
+
+        let text_index = sections.iter()
+            .position(|section| section.kind == SHT_PROGBITS && string_at(shstrtab, section.name as usize) == Ok(".text"))
+            .ok_or("no .text section")?;
+        let mut object = Object { text: contents(&sections[text_index])?.to_vec(), ..Object::default() };
+
+        if let Some(symtab) = sections.iter().find(|section| section.kind == SHT_SYMTAB) {
+            let strtab = contents(sections.get(symtab.link as usize).ok_or("no symbol names")?)?;
+            let symbols = contents(symtab)?;
+            for entry in symbols.chunks(SYMBOL_SIZE).skip(1) {
+                let section = u16_at(entry, 6)?;
+                object.symbols.push(Symbol {
+                    name: string_at(strtab, u32_at(entry, 0)? as usize)?.to_owned(),
+                    value: u64_at(entry, 8)?,
+                    size: u64_at(entry, 16)?,
+                    defined: section != 0
+                });
+                if section != 0 && section as usize != text_index {
+                    return Err(format!("symbol {} is not in .text", object.symbols.last().unwrap().name));
+                }
+            }
+        }
+
+        for section in sections.iter().filter(|section| section.kind == SHT_RELA && section.info as usize == text_index) {
+            for entry in contents(section)?.chunks(RELOCATION_SIZE) {
+                let info = u64_at(entry, 8)?;
+                if info as u32 != R_X86_64_64 {
+                    return Err(format!("unsupported relocation type {}", info as u32));
+                }
+                object.relocations.push(Relocation { offset: u64_at(entry, 0)?, symbol: (info >> 32) as usize, addend: u64_at(entry, 16)? as i64 });
+            }
+        }
+
+        Ok(object)
+    }
+
+}
+
+#[derive(Default)]
+struct SectionHeader {
+    name: u32,
+    kind: u32,
+    flags: u64,
+    offset: u64,
+    size: u64,
+    link: u32,
+    info: u32,
+    alignment: u64,
+    entry_size: u64
+}
+
+impl SectionHeader {
+
+    fn write(&self, file: &mut Vec<u8>) {
+        file.extend_from_slice(&self.name.to_le_bytes());
+        file.extend_from_slice(&self.kind.to_le_bytes());
+        file.extend_from_slice(&self.flags.to_le_bytes());
+        // Nothing is loaded at an address yet.
+        file.extend_from_slice(&0u64.to_le_bytes());
+        file.extend_from_slice(&self.offset.to_le_bytes());
+        file.extend_from_slice(&self.size.to_le_bytes());
+        file.extend_from_slice(&self.link.to_le_bytes());
+        file.extend_from_slice(&self.info.to_le_bytes());
+        file.extend_from_slice(&self.alignment.to_le_bytes());
+        file.extend_from_slice(&self.entry_size.to_le_bytes());
+    }
+
+    fn read(file: &[u8], offset: usize) -> Result<SectionHeader, String> {
+        Ok(SectionHeader {
+            name: u32_at(file, offset)?,
+            kind: u32_at(file, offset + 4)?,
+            flags: u64_at(file, offset + 8)?,
+            offset: u64_at(file, offset + 24)?,
+            size: u64_at(file, offset + 32)?,
+            link: u32_at(file, offset + 40)?,
+            info: u32_at(file, offset + 44)?,
+            alignment: u64_at(file, offset + 48)?,
+            entry_size: u64_at(file, offset + 56)?
+        })
+    }
+
+}
+
+fn bytes_at(file: &[u8], offset: usize, length: usize) -> Result<&[u8], String> {
+    file.get(offset..offset + length).ok_or_else(|| String::from("truncated ELF file"))
+}
+
+fn u16_at(file: &[u8], offset: usize) -> Result<u16, String> {
+    let mut bytes = [0; 2];
+    bytes.copy_from_slice(bytes_at(file, offset, 2)?);
+    Ok(u16::from_le_bytes(bytes))
+}
+
+fn u32_at(file: &[u8], offset: usize) -> Result<u32, String> {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(bytes_at(file, offset, 4)?);
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn u64_at(file: &[u8], offset: usize) -> Result<u64, String> {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(bytes_at(file, offset, 8)?);
+    Ok(u64::from_le_bytes(bytes))
+}
+
+// A NUL terminated string in a string table.
+fn string_at(table: &[u8], offset: usize) -> Result<&str, String> {
+    let bytes = table.get(offset..).ok_or("string out of bounds")?;
+    let end = bytes.iter().position(|&byte| byte == 0).ok_or("unterminated string")?;
+    ::std::str::from_utf8(&bytes[..end]).map_err(|_| String::from("string is not UTF-8"))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn objects_survive_a_round_trip() {
+        let object = Object {
+            text: vec![0x55, 0x48, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0xc3],
+            symbols: vec![
+                Symbol { name: String::from("Main.run(I)I"), value: 0, size: 12, defined: true },
+                Symbol { name: String::from("ironjdk_safepoint"), value: 0, size: 0, defined: false }
+            ],
+            relocations: vec![Relocation { offset: 3, symbol: 2, addend: 0 }]
+        };
+
+        let file = object.write();
+        assert_eq!(&file[..4], b"\x7fELF");
+        assert_eq!(Object::read(&file), Ok(object));
+        assert!(Object::read(&file[..40]).is_err());
+    }
+
+}
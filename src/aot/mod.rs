@@ -0,0 +1,580 @@
+// Ahead-of-time compilation. Assuming that the classes a program is built with are all it will
+// ever load, finds every method its main method can reach, compiles those that the JIT can into
+// a relocatable ELF object, and bundles the object with the class files into an image. The aot
+// tool appends images to a copy of the java launcher, which runs the program in the image when
+// it finds one instead of reading its command line.
+
+pub mod elf;
+
+use class::path::{ArchiveError, ClassPath, ClassSource};
+use code::instruction::Instruction;
+use runtime::bootstrap::io::Console;
+use runtime::class::{RuntimeClass, ClassTable};
+use runtime::class::method::RuntimeMethod;
+use runtime::jit::{self, RelocationTarget};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+
+// What compiled code calls the safepoint flag of the heap.
+pub const SAFEPOINT_SYMBOL: &str = "ironjdk_safepoint";
+
+// An executable with an image ends with the length of the image and this.
+const IMAGE_MAGIC: &[u8; 8] = b"IRONJDK\x01";
+
+// A method, by the class that declares it, its name and its descriptor.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MethodName {
+    pub class_name: String,
+    pub name: String,
+    pub descriptor: String
+}
+
+impl MethodName {
+
+    pub fn new(class_name: &str, name: &str, descriptor: &str) -> MethodName {
+        MethodName { class_name: String::from(class_name), name: String::from(name), descriptor: String::from(descriptor) }
+    }
+
+    // The symbol of the compiled method, e.g. "Main.fib(I)I". Class names in internal form have
+    // no dots, so the symbol can be split again.
+    pub fn symbol(&self) -> String {
+        format!("{}.{}{}", self.class_name, self.name, self.descriptor)
+    }
+
+    pub fn from_symbol(symbol: &str) -> Option<MethodName> {
+        let dot = symbol.find('.')?;
+        let parenthesis = symbol.find('(')?;
+        if parenthesis < dot {
+            return None;
+        }
+        Some(MethodName::new(&symbol[..dot], &symbol[dot + 1..parenthesis], &symbol[parenthesis..]))
+    }
+
+}
+
+#[derive(Debug, Default)]
+pub struct Reachability {
+    // In the order they were found.
+    pub methods: Vec<MethodName>,
+    pub classes: BTreeSet<String>,
+    // Classes that are referred to but could not be found. The program fails if it gets to one.
+    pub missing: BTreeSet<String>
+}
+
+// Finds the methods and classes reachable from the roots. Calls are followed through the
+// constant pool references of invoke instructions; a virtual or interface call reaches the
+// method in every reachable class that overrides it. Invoking a class reaches its static
+// initializer, and invokedynamic reaches the lambda bodies of its class. Unlike the methods
+// they reach, the roots must be found.
+pub fn reachable(class_table: &ClassTable, roots: &[MethodName]) -> Result<Reachability, String> {
+    let mut analysis = Analysis {
+        class_table,
+        reachability: Reachability::default(),
+        found: HashSet::new(),
+        pending: roots.to_vec(),
+        virtual_calls: Vec::new()
+    };
+
+    for root in roots.iter() {
+        let class = analysis.class(&root.class_name)?
+            .ok_or_else(|| format!("class not found: {}", root.class_name))?;
+        if RuntimeClass::resolve_method(&class, &root.name, &root.descriptor).is_none() {
+            return Err(format!("method not found: {}", root.symbol()));
+        }
+    }
+
+    while let Some(reference) = analysis.pending.pop() {
+        let class = match analysis.class(&reference.class_name)? {
+            Some(class) => class,
+            None => continue
+        };
+        let declaring_class = match RuntimeClass::resolve_method(&class, &reference.name, &reference.descriptor) {
+            Some(declaring_class) => declaring_class,
+            None => continue
+        };
+        let method_name = MethodName::new(&declaring_class.class_name, &reference.name, &reference.descriptor);
+        if !analysis.found.insert(method_name.clone()) {
+            continue;
+        }
+        analysis.reachability.methods.push(method_name);
+
+        let method = declaring_class.get_declared_method(&reference.name, &reference.descriptor).unwrap();
+        analysis.method(&declaring_class, method)?;
+    }
+
+    Ok(analysis.reachability)
+}
+
+struct Analysis<'a> {
+    class_table: &'a ClassTable,
+    reachability: Reachability,
+    found: HashSet<MethodName>,
+    pending: Vec<MethodName>,
+    // The methods invoked virtually so far, which classes found later may override.
+    virtual_calls: Vec<MethodName>
+}
+
+impl<'a> Analysis<'a> {
+
+    // Loads a class and makes it reachable, along with its superclasses and superinterfaces.
+    // Returns None if it cannot be found.
+    fn class(&mut self, class_name: &str) -> Result<Option<Arc<RuntimeClass>>, String> {
+        // Arrays of classes need their element class, and arrays of primitives no class at all.
+        let class_name = if class_name.starts_with('[') {
+            let element = class_name.trim_start_matches('[');
+            match element.strip_prefix('L').and_then(|element| element.strip_suffix(';')) {
+                Some(element) => element,
+                None => return Ok(None)
+            }
+        } else {
+            class_name
+        };
+
+        let class = match self.class_table.find_class(class_name)? {
+            Some(class) => class,
+            None => {
+                self.reachability.missing.insert(String::from(class_name));
+                return Ok(None);
+            }
+        };
+        if !self.reachability.classes.insert(String::from(class_name)) {
+            return Ok(Some(class));
+        }
+
+        if let Some(ref super_class) = class.super_class {
+            self.class(&super_class.class_name)?;
+        }
+        for interface in class.interfaces.iter() {
+            self.class(&interface.class_name)?;
+        }
+
+        if class.get_declared_method("<clinit>", "()V").is_some() {
+            self.pending.push(MethodName::new(class_name, "<clinit>", "()V"));
+        }
+        for call in self.virtual_calls.iter() {
+            if class.is_subclass_of(&call.class_name) && class.get_declared_method(&call.name, &call.descriptor).is_some() {
+                self.pending.push(MethodName::new(class_name, &call.name, &call.descriptor));
+            }
+        }
+
+        Ok(Some(class))
+    }
+
+    fn method(&mut self, class: &Arc<RuntimeClass>, method: &RuntimeMethod) -> Result<(), String> {
+        let code = match method.code {
+            Some(ref code) => code,
+            None => return Ok(())
+        };
+        let constant_pool = &class.constant_pool;
+
+        for tagged_instruction in code.instructions.iter() {
+            match tagged_instruction.instruction {
+                Instruction::Invokestatic { index } | Instruction::Invokespecial { index } => {
+                    let method_ref = constant_pool.get_method_ref(index)?;
+                    self.pending.push(MethodName::new(&method_ref.class_name, &method_ref.name_and_type.name, &method_ref.name_and_type.descriptor));
+                },
+                Instruction::Invokevirtual { index } | Instruction::Invokeinterface { index, .. } => {
+                    let method_ref = constant_pool.get_method_ref(index)?;
+                    let call = MethodName::new(&method_ref.class_name, &method_ref.name_and_type.name, &method_ref.name_and_type.descriptor);
+                    self.virtual_calls.push(call.clone());
+                    for class_name in self.reachability.classes.iter() {
+                        let class = match self.class_table.get_class(class_name) {
+                            Some(class) => class,
+                            None => continue
+                        };
+                        if class.is_subclass_of(&call.class_name) && class.get_declared_method(&call.name, &call.descriptor).is_some() {
+                            self.pending.push(MethodName::new(class_name, &call.name, &call.descriptor));
+                        }
+                    }
+                    self.pending.push(call);
+                },
+                Instruction::Invokedynamic { .. } => {
+                    for lambda in class.methods.iter().filter(|method| method.name.starts_with("lambda$")) {
+                        self.pending.push(MethodName::new(&class.class_name, &lambda.name, &lambda.descriptor));
+                    }
+                },
+                Instruction::New { index } | Instruction::Anewarray { index } | Instruction::Checkcast { index } | Instruction::Instanceof { index } => {
+                    let class_name = constant_pool.get_class_name(index)?;
+                    self.class(&class_name)?;
+                },
+                Instruction::Getstatic { index } | Instruction::Putstatic { index } | Instruction::Getfield { index } | Instruction::Putfield { index } => {
+                    let field_ref = constant_pool.get_field_ref(index)?;
+                    self.class(&field_ref.class_name)?;
+                },
+                _ => {}
+            }
+        }
+
+        for entry in code.exception_table.iter().filter(|entry| entry.catch_type != 0) {
+            let class_name = constant_pool.get_class_name(entry.catch_type)?;
+            self.class(&class_name)?;
+        }
+
+        Ok(())
+    }
+
+}
+
+// Compiles the reachable methods that the JIT can compile into an object file. Methods calling
+// code that cannot be relocated are left out.
+pub fn compile(class_table: &ClassTable, reachability: &Reachability) -> elf::Object {
+    let mut compiled = Vec::new();
+    for method_name in reachability.methods.iter() {
+        let class = match class_table.get_class(&method_name.class_name) {
+            Some(class) => class,
+            None => continue
+        };
+        if let Some(method) = class.get_declared_method(&method_name.name, &method_name.descriptor) {
+            if let Some(compiled_method) = jit::compile(&class, method, class_table) {
+                compiled.push((method_name.clone(), compiled_method.entry(), compiled_method.code().to_vec(), compiled_method.absolute_addresses().to_vec()));
+            }
+        }
+    }
+
+    // Absolute addresses must be the safepoint flag or another method in the object.
+    let safepoint = class_table.heap.safepoint_flag() as *const _ as u64;
+    let address_at = |code: &[u8], position: usize| {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&code[position..position + 8]);
+        u64::from_le_bytes(bytes)
+    };
+    loop {
+        let entries: HashSet<u64> = compiled.iter().map(|&(_, entry, _, _)| entry as u64).collect();
+        let before = compiled.len();
+        compiled.retain(|(_, _, code, absolute)| {
+            absolute.iter().all(|&position| {
+                let address = address_at(code, position);
+                address == safepoint || entries.contains(&address)
+            })
+        });
+        if compiled.len() == before {
+            break;
+        }
+    }
+
+    // The safepoint flag is symbol 1, and the methods follow.
+    let mut object = elf::Object::default();
+    object.symbols.push(elf::Symbol { name: String::from(SAFEPOINT_SYMBOL), value: 0, size: 0, defined: false });
+    let mut symbols = HashMap::new();
+    for &(ref method_name, entry, ref code, _) in compiled.iter() {
+        while object.text.len() % 16 != 0 {
+            object.text.push(0xcc);
+        }
+        symbols.insert(entry as u64, object.symbols.len() + 1);
+        object.symbols.push(elf::Symbol { name: method_name.symbol(), value: object.text.len() as u64, size: code.len() as u64, defined: true });
+        object.text.extend_from_slice(code);
+    }
+    for (index, (_, _, code, absolute)) in compiled.iter().enumerate() {
+        let start = object.symbols[index + 1].value;
+        for &position in absolute.iter() {
+            let address = address_at(code, position);
+            let symbol = if address == safepoint { 1 } else { symbols[&address] };
+            object.relocations.push(elf::Relocation { offset: start + position as u64, symbol, addend: 0 });
+        }
+    }
+    object
+}
+
+// A program ready to run: the name of its main class, its class files and the object file of
+// its compiled methods.
+#[derive(Debug, PartialEq)]
+pub struct Image {
+    pub main_class: String,
+    // The JDK the program was compiled against, whose java.base module it runs with in place of
+    // the bootstrap class library.
+    pub java_home: Option<String>,
+    pub classes: Vec<(String, Vec<u8>)>,
+    pub object: Vec<u8>
+}
+
+impl Image {
+
+    // Bundles the reachable classes that the class path has. The bootstrap classes, or those of
+    // the JDK, are part of the runtime and are left out.
+    pub fn new(main_class: &str,
+               java_home: Option<&str>,
+               reachability: &Reachability,
+               class_path: &mut ClassPath,
+               object: &elf::Object) -> Result<Image, ArchiveError> {
+        let mut classes = Vec::new();
+        for class_name in reachability.classes.iter() {
+            if let Some(class_bytes) = class_path.read_class(class_name)? {
+                classes.push((class_name.clone(), class_bytes.bytes));
+            }
+        }
+        Ok(Image { main_class: String::from(main_class), java_home: java_home.map(String::from), classes, object: object.write() })
+    }
+
+    // The image followed by its length and IMAGE_MAGIC, to be appended to an executable.
+    pub fn write(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let write = |bytes: &mut Vec<u8>, data: &[u8]| {
+            bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(data);
+        };
+        write(&mut bytes, self.main_class.as_bytes());
+        write(&mut bytes, self.java_home.as_ref().map_or(&[][..], |java_home| java_home.as_bytes()));
+        write(&mut bytes, &(self.classes.len() as u32).to_le_bytes());
+        for (name, class_file) in self.classes.iter() {
+            write(&mut bytes, name.as_bytes());
+            write(&mut bytes, class_file);
+        }
+        write(&mut bytes, &self.object);
+
+        let length = bytes.len() as u64;
+        bytes.extend_from_slice(&length.to_le_bytes());
+        bytes.extend_from_slice(IMAGE_MAGIC);
+        bytes
+    }
+
+    pub fn read(bytes: &[u8]) -> Result<Image, String> {
+        let mut remaining = bytes;
+        let mut read = || -> Result<&[u8], String> {
+            let length = match remaining.get(..4) {
+                Some(length) => u32::from_le_bytes([length[0], length[1], length[2], length[3]]) as usize,
+                None => return Err(String::from("truncated image"))
+            };
+            let data = remaining.get(4..4 + length).ok_or("truncated image")?;
+            remaining = &remaining[4 + length..];
+            Ok(data)
+        };
+        let string = |data: &[u8]| String::from_utf8(data.to_vec()).map_err(|_| String::from("invalid name in image"));
+
+        let main_class = string(read()?)?;
+        let java_home = Some(string(read()?)?).filter(|java_home| !java_home.is_empty());
+        let count = read_count(read()?)?;
+        let mut classes = Vec::new();
+        for _ in 0..count {
+            let name = string(read()?)?;
+            classes.push((name, read()?.to_vec()));
+        }
+        let object = read()?.to_vec();
+        Ok(Image { main_class, java_home, classes, object })
+    }
+
+    // How many bytes at the end of an executable its image takes up, with the length and
+    // IMAGE_MAGIC, if it has one.
+    pub fn length_in(executable: &[u8]) -> Option<usize> {
+        let trailer = executable.len().checked_sub(16).map(|start| &executable[start..])?;
+        if &trailer[8..] != IMAGE_MAGIC {
+            return None;
+        }
+        let mut length = [0; 8];
+        length.copy_from_slice(&trailer[..8]);
+        (u64::from_le_bytes(length) as usize).checked_add(16).filter(|&length| length <= executable.len())
+    }
+
+    // Reads the image at the end of an executable, if it has one.
+    pub fn from_executable(path: &Path) -> io::Result<Option<Image>> {
+        let mut file = File::open(path)?;
+        let size = file.seek(SeekFrom::End(0))?;
+        if size < 16 {
+            return Ok(None);
+        }
+        let mut trailer = [0; 16];
+        file.seek(SeekFrom::End(-16))?;
+        file.read_exact(&mut trailer)?;
+        if &trailer[8..] != IMAGE_MAGIC {
+            return Ok(None);
+        }
+
+        let mut length = [0; 8];
+        length.copy_from_slice(&trailer[..8]);
+        let length = u64::from_le_bytes(length);
+        if length > size - 16 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "truncated image"));
+        }
+        let mut bytes = vec![0; length as usize];
+        file.seek(SeekFrom::End(-16 - length as i64))?;
+        file.read_exact(&mut bytes)?;
+        Image::read(&bytes).map(Some).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    // The class files of the image, to put on the class path.
+    pub fn bundle(&self) -> Bundle {
+        Bundle { classes: self.classes.iter().cloned().collect() }
+    }
+
+    // Creates the class table the program runs in, with the classes of the image after those of
+    // its JDK, and installs the compiled methods. A JDK still has to be booted.
+    pub fn runtime(&self, console: Console) -> Result<Arc<ClassTable>, String> {
+        let mut class_path = ClassPath::new();
+        let class_table = match self.java_home {
+            Some(ref java_home) => {
+                class_path.add_java_home(Path::new(java_home))
+                    .map_err(|e| format!("could not open the JDK in {}: {:?}", java_home, e))?;
+                let class_table = ClassTable::without_library(console);
+                class_table.set_property("java.home", java_home);
+                class_table
+            },
+            None => ClassTable::with_console(console)
+        };
+        class_path.add("image", self.bundle());
+        class_table.set_class_path(class_path);
+        self.install(&class_table).map_err(|e| format!("could not install the compiled methods: {}", e))?;
+        Ok(class_table)
+    }
+
+    // Loads the classes with compiled methods and installs the code of those methods. Returns
+    // how many were installed.
+    pub fn install(&self, class_table: &ClassTable) -> Result<usize, String> {
+        let object = elf::Object::read(&self.object)?;
+        let safepoint = class_table.heap.safepoint_flag() as *const _ as u64;
+
+        let mut relocations = Vec::new();
+        for relocation in object.relocations.iter() {
+            let symbol = object.symbol(relocation.symbol).ok_or("relocation without a symbol")?;
+            let target = if symbol.defined {
+                RelocationTarget::Text((symbol.value as i64 + relocation.addend) as usize)
+            } else if symbol.name == SAFEPOINT_SYMBOL {
+                RelocationTarget::Address((safepoint as i64 + relocation.addend) as u64)
+            } else {
+                return Err(format!("undefined symbol {}", symbol.name));
+            };
+            relocations.push((relocation.offset as usize, target));
+        }
+
+        let mut functions = Vec::new();
+        for symbol in object.symbols.iter().filter(|symbol| symbol.defined) {
+            let method_name = MethodName::from_symbol(&symbol.name).ok_or_else(|| format!("invalid symbol {}", symbol.name))?;
+            let class = class_table.find_class(&method_name.class_name)?
+                .ok_or_else(|| format!("{} is not in the image", method_name.class_name))?;
+            functions.push((class, method_name, symbol.value as usize, symbol.size as usize));
+        }
+        let methods = functions.iter()
+            .map(|&(ref class, ref method_name, offset, size)| {
+                class.get_declared_method(&method_name.name, &method_name.descriptor)
+                    .map(|method| (method, offset, size))
+                    .ok_or_else(|| format!("{} is not in the image", method_name.symbol()))
+            })
+            .collect::<Result<Vec<(&RuntimeMethod, usize, usize)>, String>>()?;
+
+        if !jit::install(&object.text, &relocations, &methods) {
+            return Err(String::from("could not map the compiled code"));
+        }
+        Ok(methods.len())
+    }
+
+}
+
+fn read_count(data: &[u8]) -> Result<usize, String> {
+    match data {
+        [a, b, c, d] => Ok(u32::from_le_bytes([*a, *b, *c, *d]) as usize),
+        _ => Err(String::from("invalid class count in image"))
+    }
+}
+
+// The class files of an image, as a class path entry.
+pub struct Bundle {
+    classes: HashMap<String, Vec<u8>>
+}
+
+impl ClassSource for Bundle {
+
+    fn read_class(&mut self, class_name: &str) -> Result<Option<Vec<u8>>, ArchiveError> {
+        Ok(self.classes.get(class_name).cloned())
+    }
+
+    fn has_package(&mut self, package: &str) -> bool {
+        self.classes.keys().any(|name| name.rfind('/').map(|slash| &name[..slash]) == Some(package))
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use runtime::bootstrap::ClassBuilder;
+
+    fn fixtures() -> (Arc<ClassTable>, ClassPath) {
+        let class_table = ClassTable::new();
+        let mut class_path = ClassPath::new();
+        class_path.add_entries("fixtures").unwrap();
+        class_table.set_class_path(class_path);
+
+        let mut class_path = ClassPath::new();
+        class_path.add_entries("fixtures").unwrap();
+        (class_table, class_path)
+    }
+
+    fn symbols(reachability: &Reachability) -> BTreeSet<String> {
+        reachability.methods.iter().map(MethodName::symbol).collect()
+    }
+
+    #[test]
+    fn finds_reachable_methods() {
+        let (class_table, _) = fixtures();
+        let reachability = reachable(&class_table, &[MethodName::new("Jit", "sumOfSquares", "(I)I")]).unwrap();
+        let expected: BTreeSet<String> = ["Jit.sumOfSquares(I)I", "Jit.square(I)I"].iter().map(|&symbol| String::from(symbol)).collect();
+        assert_eq!(symbols(&reachability), expected);
+        assert!(reachability.classes.contains("Jit"));
+        assert!(reachability.classes.contains("java/lang/Object"));
+
+        // Shape.describe calls area virtually, which reaches the overrides in every reachable
+        // subclass of Shape.
+        let roots = [MethodName::new("Circle", "<init>", "(D)V"), MethodName::new("Shape", "describe", "()Ljava/lang/String;")];
+        let reachability = reachable(&class_table, &roots).unwrap();
+        let symbols = symbols(&reachability);
+        assert!(symbols.contains("Circle.area()D"));
+        assert!(symbols.contains("Circle.name()Ljava/lang/String;"));
+        assert!(!symbols.contains("Square.area()D"));
+    }
+
+    // A class with a one letter name is not a primitive type, and the roots must be found.
+    #[test]
+    fn resolves_roots() {
+        let (class_table, _) = fixtures();
+        ClassBuilder::new("A").define(&class_table);
+        let reachability = reachable(&class_table, &[MethodName::new("A", "hashCode", "()I")]).unwrap();
+        assert!(reachability.classes.contains("A"));
+        assert!(reachability.missing.is_empty());
+
+        let main = |class_name| MethodName::new(class_name, "main", "([Ljava/lang/String;)V");
+        assert_eq!(reachable(&class_table, &[main("B")]).unwrap_err(), "class not found: B");
+        assert_eq!(reachable(&class_table, &[main("Jit")]).unwrap_err(), "method not found: Jit.main([Ljava/lang/String;)V");
+    }
+
+    #[test]
+    fn symbols_name_methods() {
+        let method_name = MethodName::new("com/example/Main", "fib", "(I)I");
+        assert_eq!(method_name.symbol(), "com/example/Main.fib(I)I");
+        assert_eq!(MethodName::from_symbol(&method_name.symbol()), Some(method_name));
+        assert_eq!(MethodName::from_symbol("ironjdk_safepoint"), None);
+    }
+
+    // An image compiled ahead of time runs its compiled code from the first invocation.
+    #[test]
+    #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+    fn images_install_compiled_methods() {
+        let (class_table, mut class_path) = fixtures();
+        let reachability = reachable(&class_table, &[MethodName::new("Jit", "sumOfSquares", "(I)I"), MethodName::new("Jit", "fib", "(I)I")]).unwrap();
+        let object = compile(&class_table, &reachability);
+        assert_eq!(object.symbols.iter().filter(|symbol| symbol.defined).count(), 3);
+
+        let image = Image::new("Jit", None, &reachability, &mut class_path, &object).unwrap();
+        let bytes = image.write();
+        assert_eq!(&bytes[bytes.len() - 8..], IMAGE_MAGIC);
+        assert_eq!(Image::length_in(&bytes), Some(bytes.len()));
+        assert_eq!(Image::length_in(&bytes[1..]), None);
+        let image = Image::read(&bytes[..bytes.len() - 16]).unwrap();
+        assert_eq!(image.classes.iter().map(|(name, _)| name.as_str()).collect::<Vec<&str>>(), vec!["Jit"]);
+
+        let runtime = image.runtime(Console::stdio()).unwrap();
+
+        // The methods share the memory of the image, so each must run from its own entry.
+        let class = runtime.get_class("Jit").unwrap();
+        for &(name, argument, expected) in [("fib", 20, 6765), ("square", 7, 49), ("sumOfSquares", 10, 340)].iter() {
+            let method = class.get_declared_method(name, "(I)I").unwrap();
+            assert!(matches!(method.compiled.get(), Some(Some(_))));
+            match jit::invoke(&class, method, &[::runtime::Value::Integer(argument)], &runtime) {
+                jit::Execution::Completed(result) => assert_eq!(format!("{:?}", result), format!("Value(Integer({}))", expected), "{}", name),
+                _ => panic!("{} did not run compiled", name)
+            }
+        }
+    }
+
+}
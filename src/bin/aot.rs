@@ -0,0 +1,204 @@
+extern crate ironjdk;
+
+use ironjdk::aot::{self, Image, MethodName};
+use ironjdk::class::path::ClassPath;
+use ironjdk::runtime::bootstrap::io::Console;
+use ironjdk::runtime::class::ClassTable;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+const USAGE: &str = "Usage: aot [options] <mainclass>
+
+Compiles the methods reachable from the main method of the class ahead of time,
+and writes a standalone executable that runs it. Only the classes found now are
+ever loaded by the executable.
+
+where options include:
+    -cp <path>, -classpath <path>, --class-path <path>
+                  A list of directories and jar files to search for class files,
+                  separated like PATH.
+    -o <file>     Where to write the executable. Defaults to the simple name of
+                  the main class.
+    --java-home <directory>
+                  Compile against the java.base module of an installed JDK in
+                  place of the bootstrap class library. The executable runs on
+                  the same JDK, which it loads from this directory.
+    --launcher <file>
+                  The java launcher to build the executable from. Defaults to
+                  the java binary next to this one.
+    -verbose      List the reachable methods and whether they were compiled.
+    -help, --help
+                  Print this help message";
+
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    class_path: Option<String>,
+    output: Option<String>,
+    java_home: Option<String>,
+    launcher: Option<String>,
+    verbose: bool,
+    main_class: Option<String>
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Compile(Options),
+    Help
+}
+
+fn parse_arguments(arguments: &[String]) -> Result<Command, String> {
+    let mut options = Options::default();
+    let mut remaining = arguments.iter();
+
+    while let Some(argument) = remaining.next() {
+        let mut value = || remaining.next().cloned().ok_or_else(|| format!("{} requires an argument", argument));
+
+        match argument.as_str() {
+            "-cp" | "-classpath" | "--class-path" => options.class_path = Some(value()?),
+            "-o" => options.output = Some(value()?),
+            "--java-home" => options.java_home = Some(value()?),
+            "--launcher" => options.launcher = Some(value()?),
+            "-verbose" => options.verbose = true,
+            "-help" | "--help" | "-?" => return Ok(Command::Help),
+            _ if argument.starts_with('-') => return Err(format!("invalid flag: {}", argument)),
+            _ if options.main_class.is_some() => return Err(format!("more than one main class: {}", argument)),
+            _ => options.main_class = Some(argument.clone())
+        }
+    }
+
+    Ok(Command::Compile(options))
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    process::exit(2);
+}
+
+fn main() {
+    let arguments: Vec<String> = env::args().skip(1).collect();
+    let options = match parse_arguments(&arguments) {
+        Ok(Command::Compile(Options { main_class: None, .. })) => {
+            println!("{}", USAGE);
+            process::exit(2);
+        },
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            process::exit(0);
+        },
+        Ok(Command::Compile(options)) => options,
+        Err(message) => fail(&message)
+    };
+    let main_class = options.main_class.as_ref().unwrap().replace('.', "/");
+
+    let user_class_path = options.class_path.clone()
+        .or_else(|| env::var("CLASSPATH").ok())
+        .unwrap_or_else(|| String::from("."));
+    // The image bundles the classes of the user class path only.
+    let image_class_path = || {
+        let mut class_path = ClassPath::new();
+        class_path.add_entries(&user_class_path).unwrap_or_else(|e| {
+            fail(&format!("could not open the class path {}: {:?}", user_class_path, e))
+        });
+        class_path
+    };
+
+    // With a JDK, its java.base module takes the place of the bootstrap class library. The
+    // executable finds it again by its absolute path.
+    let java_home = options.java_home.as_ref().map(|java_home| {
+        let java_home = fs::canonicalize(java_home).unwrap_or_else(|e| fail(&format!("could not open the JDK in {}: {}", java_home, e)));
+        java_home.to_string_lossy().into_owned()
+    });
+    let class_table = match java_home {
+        Some(_) => ClassTable::without_library(Console::stdio()),
+        None => ClassTable::new()
+    };
+    let mut class_path = ClassPath::new();
+    if let Some(ref java_home) = java_home {
+        class_path.add_java_home(Path::new(java_home)).unwrap_or_else(|e| {
+            fail(&format!("could not open the JDK in {}: {:?}", java_home, e))
+        });
+        class_table.set_property("java.home", java_home);
+    }
+    class_path.add_entries(&user_class_path).unwrap_or_else(|e| {
+        fail(&format!("could not open the class path {}: {:?}", user_class_path, e))
+    });
+    class_table.set_class_path(class_path);
+    match class_table.find_class(&main_class) {
+        Ok(Some(ref class)) if class.get_declared_method("main", "([Ljava/lang/String;)V").is_some() => {},
+        Ok(Some(_)) => fail(&format!("no main method in class {}", main_class)),
+        Ok(None) => fail(&format!("class not found: {}", main_class)),
+        Err(e) => fail(&format!("could not load class {}: {}", main_class, e))
+    }
+
+    let roots = [MethodName::new(&main_class, "main", "([Ljava/lang/String;)V")];
+    let reachability = aot::reachable(&class_table, &roots).unwrap_or_else(|e| fail(&e));
+    for class_name in reachability.missing.iter() {
+        eprintln!("warning: class not found: {}", class_name);
+    }
+    let object = aot::compile(&class_table, &reachability);
+    if options.verbose {
+        for method_name in reachability.methods.iter() {
+            let symbol = method_name.symbol();
+            let compiled = object.symbols.iter().any(|compiled| compiled.defined && compiled.name == symbol);
+            println!("{} {}", if compiled { "compiled   " } else { "interpreted" }, symbol);
+        }
+    }
+
+    let image = Image::new(&main_class, java_home.as_deref(), &reachability, &mut image_class_path(), &object)
+        .unwrap_or_else(|e| fail(&format!("could not read the reachable classes: {:?}", e)));
+
+    let launcher = options.launcher.clone().map(PathBuf::from).unwrap_or_else(|| {
+        let aot = env::current_exe().unwrap_or_else(|e| fail(&format!("could not find the java launcher: {}", e)));
+        aot.with_file_name(format!("java{}", env::consts::EXE_SUFFIX))
+    });
+    let mut executable = fs::read(&launcher).unwrap_or_else(|e| fail(&format!("could not read {}: {}", launcher.display(), e)));
+    // Relinking an executable built by aot replaces its image.
+    if let Some(length) = Image::length_in(&executable) {
+        executable.truncate(executable.len() - length);
+    }
+    executable.extend_from_slice(&image.write());
+
+    let output = options.output.clone().unwrap_or_else(|| String::from(main_class.rsplit('/').next().unwrap()));
+    fs::write(&output, &executable).unwrap_or_else(|e| fail(&format!("could not write {}: {}", output, e)));
+    make_executable(Path::new(&output));
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .unwrap_or_else(|e| fail(&format!("could not make {} executable: {}", path.display(), e)));
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) {}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn parse(arguments: &[&str]) -> Result<Command, String> {
+        let arguments: Vec<String> = arguments.iter().map(|&argument| String::from(argument)).collect();
+        parse_arguments(&arguments)
+    }
+
+    #[test]
+    fn options_and_main_class() {
+        let expected = Options {
+            class_path: Some(String::from("classes")),
+            output: Some(String::from("hello")),
+            verbose: true,
+            main_class: Some(String::from("com.example.Hello")),
+            ..Options::default()
+        };
+
+        assert_eq!(parse(&["-cp", "classes", "com.example.Hello", "-o", "hello", "-verbose"]), Ok(Command::Compile(expected)));
+        assert_eq!(parse(&["-o"]), Err(String::from("-o requires an argument")));
+        assert_eq!(parse(&["A", "B"]), Err(String::from("more than one main class: B")));
+        assert_eq!(parse(&["-g", "A"]), Err(String::from("invalid flag: -g")));
+    }
+
+}
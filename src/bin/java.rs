@@ -1,42 +1,778 @@
 extern crate ironjdk;
 
-use ironjdk::class::{reader, ClassFile};
+use ironjdk::aot::Image;
+use ironjdk::class::method;
+use ironjdk::class::path::ClassPath;
+use ironjdk::class::path::jar::Jar;
 use ironjdk::runtime;
+use ironjdk::runtime::{Value, ArrayElements};
+use ironjdk::runtime::bootstrap::{self, io::Console};
+use ironjdk::runtime::class::ClassTable;
+use ironjdk::runtime::debugger::Location;
+use ironjdk::runtime::interpreter::{InvokeResult, InterpreterError};
+use ironjdk::runtime::jdwp::{self, Agent};
+use ironjdk::runtime::sampler::{self, Sampler};
+use ironjdk::runtime::trace::{self, Filter, JsonTracer, TextTracer, Tracer};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
 use std::io::prelude::*;
-use std::fs::File;
-use std::io::Error;
-use ironjdk::class::method;
-use ironjdk::class::reader::ClassReaderError;
-use ironjdk::runtime::class::{RuntimeClass, ClassTable};
+use std::net::TcpListener;
+use std::path::Path;
+use std::process;
+use std::time::Duration;
+
+const USAGE: &str = "Usage: java [options] <mainclass> [args...]
+           (to execute a class)
+   or  java [options] -jar <jarfile> [args...]
+           (to execute a jar file)
+
+where options include:
+    -cp <class search path of directories and jar files>
+    -classpath <class search path of directories and jar files>
+    --class-path <class search path of directories and jar files>
+                  A list of directories and jar files to search for class files,
+                  separated like PATH.
+    -D<name>=<value>
+                  set a system property
+    -verbose:class
+                  enable verbose output about loaded classes
+    -Xmx<size>    set the maximum heap size, e.g. -Xmx64m or -Xmx1g
+    -Xmn<size>    set the size of the young generation
+    -Xlog:gc      log every garbage collection
+    -Xint         interpreted mode execution only
+    -XX:CompileThreshold=<invocations>
+                  compile methods once they have been invoked this often
+    -Xstats[:text|:json][=<file>]
+                  count method invocations, bytecodes executed, branches
+                  taken and objects allocated by the interpreter, and write
+                  them to the file, or to standard error, on exit. Methods
+                  the JIT compiles are counted when they are invoked, but
+                  not what they run, and their bytecodes are marked as
+                  incomplete.
+    -Xtrace[:<option>,...]
+                  trace what the interpreter does to standard error; options
+                  are format=text|json, file=<file> to write to instead,
+                  class=<glob> and method=<glob> to trace only matching
+                  classes and methods, both repeatable, and events=<event>+...
+                  to choose among calls, instructions, allocations, exceptions
+                  and classes, all but instructions by default. Implies -Xint.
+    -Xprof[=<file>]
+                  sample the Java stacks of all threads and write them on exit
+                  in the collapsed format of flame graph tools, to the file or
+                  to standard error
+    -XX:ProfileInterval=<milliseconds>
+                  how often -Xprof samples, 10 milliseconds by default
+    --debug       stop at the main method and read debugger commands from
+                  standard input, such as break, step, print and backtrace;
+                  type help for the list. Implies -Xint.
+    -agentlib:jdwp=transport=dt_socket,server=y,address=<port>[,suspend=y|n]
+                  let a debugger such as an IDE attach over the Java Debug Wire
+                  Protocol; with suspend=y, the default, wait for it before
+                  starting. Implies -Xint.
+    --java-home <directory>
+                  run with the java.base module of an installed JDK in place
+                  of the built-in class library
+    -version      print product version and exit
+    -help, -h, --help
+                  print this help message and exit";
+
+#[derive(Debug, PartialEq)]
+enum Main {
+    Class(String),
+    Jar(String)
+}
+
+#[derive(Debug, PartialEq)]
+enum Format {
+    Text,
+    Json
+}
+
+#[derive(Debug, PartialEq)]
+struct Stats {
+    format: Format,
+    file: Option<String>
+}
+
+#[derive(Debug, PartialEq)]
+struct Tracing {
+    format: Format,
+    file: Option<String>,
+    filter: Filter
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    class_path: Option<String>,
+    properties: Vec<(String, String)>,
+    verbose_class: bool,
+    max_heap_size: Option<usize>,
+    young_size: Option<usize>,
+    log_gc: bool,
+    compile_threshold: Option<u32>,
+    stats: Option<Stats>,
+    trace: Option<Tracing>,
+    prof: bool,
+    prof_file: Option<String>,
+    prof_interval: Option<u64>,
+    debug: bool,
+    jdwp: Option<jdwp::Options>,
+    java_home: Option<String>,
+    main: Option<Main>,
+    arguments: Vec<String>
+}
 
-fn load_class_from_file(path: &str) -> Result<ClassFile, ClassReaderError> {
-    let mut file = File::open("Counter.class").unwrap();
-    let mut buffer = Vec::new();
+// Parsed once, so the size of Options does not matter.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, PartialEq)]
+enum Command {
+    Run(Options),
+    Help,
+    Version
+}
+
+// Options come first. The main class or jar file ends them, and everything after it is passed
+// to the program.
+fn parse_arguments(arguments: &[String]) -> Result<Command, String> {
+    let mut options = Options::default();
+    let mut remaining = arguments.iter();
+
+    while let Some(argument) = remaining.next() {
+        let mut value = |what: &str| remaining.next()
+            .cloned()
+            .ok_or_else(|| format!("{} requires {} specification", argument, what));
+
+        match argument.as_str() {
+            "-cp" | "-classpath" | "--class-path" => options.class_path = Some(value("class path")?),
+            "--java-home" => options.java_home = Some(value("directory")?),
+            "-jar" => {
+                let jar = remaining.next().ok_or_else(|| String::from("-jar requires jar file specification"))?;
+                options.main = Some(Main::Jar(jar.clone()));
+                break;
+            },
+            "-verbose:class" => options.verbose_class = true,
+            "--debug" => options.debug = true,
+            _ if argument.starts_with("-agentlib:jdwp=") => {
+                options.jdwp = Some(jdwp::Options::parse(&argument["-agentlib:jdwp=".len()..])?);
+            },
+            _ if argument.starts_with("-Xmx") => {
+                let size = parse_size(&argument[4..])
+                    .ok_or_else(|| format!("Invalid maximum heap size: {}", argument))?;
+                options.max_heap_size = Some(size);
+            },
+            _ if argument.starts_with("-Xmn") => {
+                let size = parse_size(&argument[4..])
+                    .ok_or_else(|| format!("Invalid young generation size: {}", argument))?;
+                options.young_size = Some(size);
+            },
+            "-Xlog:gc" => options.log_gc = true,
+            "-Xint" => options.compile_threshold = Some(0),
+            _ if argument.starts_with("-XX:CompileThreshold=") => {
+                let threshold = argument["-XX:CompileThreshold=".len()..].parse::<u32>()
+                    .ok()
+                    .filter(|&threshold| threshold > 0)
+                    .ok_or_else(|| format!("Invalid compile threshold: {}", argument))?;
+                options.compile_threshold = Some(threshold);
+            },
+            _ if argument.starts_with("-Xstats") => {
+                options.stats = Some(parse_stats(&argument["-Xstats".len()..])
+                    .ok_or_else(|| format!("Invalid statistics option: {}", argument))?);
+            },
+            _ if argument == "-Xtrace" || argument.starts_with("-Xtrace:") => {
+                options.trace = Some(parse_trace(&argument["-Xtrace".len()..])
+                    .ok_or_else(|| format!("Invalid trace option: {}", argument))?);
+            },
+            "-Xprof" => options.prof = true,
+            _ if argument.starts_with("-Xprof=") && argument.len() > "-Xprof=".len() => {
+                options.prof = true;
+                options.prof_file = Some(String::from(&argument["-Xprof=".len()..]));
+            },
+            _ if argument.starts_with("-XX:ProfileInterval=") => {
+                let interval = argument["-XX:ProfileInterval=".len()..].parse::<u64>()
+                    .ok()
+                    .filter(|&interval| interval > 0)
+                    .ok_or_else(|| format!("Invalid profile interval: {}", argument))?;
+                options.prof_interval = Some(interval);
+            },
+            "-version" | "--version" => return Ok(Command::Version),
+            "-help" | "-h" | "--help" | "-?" => return Ok(Command::Help),
+            _ if argument.starts_with("-D") => {
+                let property = &argument[2..];
+                let (name, value) = match property.find('=') {
+                    Some(index) => (&property[..index], &property[index + 1..]),
+                    None => (property, "")
+                };
+                options.properties.push((String::from(name), String::from(value)));
+            },
+            _ if argument.starts_with('-') => return Err(format!("Unrecognized option: {}", argument)),
+            _ => {
+                options.main = Some(Main::Class(argument.clone()));
+                break;
+            }
+        }
+    }
+
+    options.arguments = remaining.cloned().collect();
+    Ok(Command::Run(options))
+}
 
-    file.read_to_end(&mut buffer).unwrap();
+// What follows -Xstats: an optional format, then an optional file.
+fn parse_stats(option: &str) -> Option<Stats> {
+    let (format, file) = match option.find('=') {
+        Some(index) if index + 1 < option.len() => (&option[..index], Some(String::from(&option[index + 1..]))),
+        Some(_) => return None,
+        None => (option, None)
+    };
+    let format = match format {
+        "" | ":text" => Format::Text,
+        ":json" => Format::Json,
+        _ => return None
+    };
+    Some(Stats { format, file })
+}
 
-    reader::read_class_file(&mut buffer)
+// What follows -Xtrace: nothing, or a colon and options separated by commas.
+fn parse_trace(option: &str) -> Option<Tracing> {
+    let mut tracing = Tracing {
+        format: Format::Text,
+        file: None,
+        filter: Filter { events: trace::ALL_EVENTS & !trace::INSTRUCTIONS, classes: Vec::new(), methods: Vec::new() }
+    };
+    if option.is_empty() {
+        return Some(tracing);
+    }
+    for option in option[1..].split(',') {
+        let index = option.find('=')?;
+        let (name, value) = (&option[..index], &option[index + 1..]);
+        if value.is_empty() {
+            return None;
+        }
+        match name {
+            "format" => tracing.format = match value {
+                "text" => Format::Text,
+                "json" => Format::Json,
+                _ => return None
+            },
+            "file" => tracing.file = Some(String::from(value)),
+            "class" => tracing.filter.classes.push(String::from(value)),
+            "method" => tracing.filter.methods.push(String::from(value)),
+            "events" => tracing.filter.events = trace::parse_events(value)?,
+            _ => return None
+        }
+    }
+    Some(tracing)
+}
+
+// A memory size as given to -Xmx or -Xmn: a number of bytes, optionally followed by k, m or g.
+fn parse_size(size: &str) -> Option<usize> {
+    let (digits, unit) = match size.char_indices().last() {
+        Some((index, 'k')) | Some((index, 'K')) => (&size[..index], 1 << 10),
+        Some((index, 'm')) | Some((index, 'M')) => (&size[..index], 1 << 20),
+        Some((index, 'g')) | Some((index, 'G')) => (&size[..index], 1 << 30),
+        _ => (size, 1)
+    };
+
+    digits.parse::<usize>().ok()?.checked_mul(unit).filter(|&size| size > 0)
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
 }
 
 fn main() {
-    println!("IronJDK 1.0.0");
+    let arguments: Vec<String> = env::args().skip(1).collect();
+    if let Some(image) = image() {
+        run_image(&image, &arguments);
+    }
+
+    let options = match parse_arguments(&arguments) {
+        Ok(Command::Run(Options { main: None, .. })) | Ok(Command::Help) => {
+            println!("{}", USAGE);
+            process::exit(if arguments.is_empty() { 1 } else { 0 });
+        },
+        Ok(Command::Version) => {
+            println!("IronJDK version \"17\"");
+            process::exit(0);
+        },
+        Ok(Command::Run(options)) => options,
+        Err(message) => {
+            eprintln!("Error: {}", message);
+            fail("Error: Could not create the Java Virtual Machine.");
+        }
+    };
+
+    // With a JDK, its java.base module takes the place of the bootstrap class library.
+    let class_table = match options.java_home {
+        Some(_) => ClassTable::without_library(Console::stdio()),
+        None => ClassTable::new()
+    };
+    let mut class_path = ClassPath::new();
+
+    if let Some(ref java_home) = options.java_home {
+        class_path.add_java_home(Path::new(java_home)).unwrap_or_else(|e| {
+            fail(&format!("Error: could not open the JDK in {}: {:?}", java_home, e))
+        });
+        class_table.set_property("java.home", java_home);
+    }
+
+    // With -jar, the jar file is the whole user class path and names the main class.
+    let (main_class, user_class_path) = match options.main {
+        Some(Main::Jar(ref path)) => {
+            let mut jar = File::open(path)
+                .map_err(|e| format!("{:?}", e))
+                .and_then(|file| Jar::open(file).map_err(|e| format!("{:?}", e)))
+                .unwrap_or_else(|e| fail(&format!("Error: Unable to access jarfile {}: {}", path, e)));
+            let main_class = match jar.main_class() {
+                Ok(Some(main_class)) => main_class,
+                _ => fail(&format!("no main manifest attribute, in {}", path))
+            };
+            class_path.add(path, jar);
+            (main_class, path.clone())
+        },
+        Some(Main::Class(ref main_class)) => {
+            let user_class_path = options.class_path.clone()
+                .or_else(|| env::var("CLASSPATH").ok())
+                .unwrap_or_else(|| String::from("."));
+            class_path.add_entries(&user_class_path).unwrap_or_else(|e| {
+                fail(&format!("Error: could not open the class path {}: {:?}", user_class_path, e))
+            });
+            (main_class.clone(), user_class_path)
+        },
+        None => unreachable!()
+    };
+
+    class_table.set_class_path(class_path);
+    class_table.set_property("java.class.path", &user_class_path);
+    for (name, value) in options.properties.iter() {
+        class_table.set_property(name, value);
+    }
+    class_table.set_verbose_class(options.verbose_class);
+    if let Some(max_heap_size) = options.max_heap_size {
+        class_table.heap.set_max_size(max_heap_size);
+    }
+    if let Some(young_size) = options.young_size {
+        class_table.heap.set_young_size(young_size);
+    }
+    if let Some(compile_threshold) = options.compile_threshold {
+        class_table.set_compile_threshold(compile_threshold);
+    }
+    if options.log_gc {
+        class_table.heap.set_log(Box::new(io::stdout()));
+    }
+    if options.stats.is_some() {
+        class_table.profile.enable();
+    }
+    if options.debug {
+        // Compiled code does not stop at breakpoints.
+        class_table.set_compile_threshold(0);
+        class_table.debugger.enable(Box::new(io::BufReader::new(io::stdin())), Box::new(io::stdout()));
+        class_table.debugger.add_breakpoint(Location::Pc(main_class.replace('.', "/"), String::from("main"), 0));
+    }
+    if let Some(tracing) = options.trace {
+        // Compiled code is not traced.
+        class_table.set_compile_threshold(0);
+        let output: Box<dyn Write + Send> = match tracing.file {
+            Some(ref file) => Box::new(BufWriter::new(File::create(file).unwrap_or_else(|e| {
+                fail(&format!("Error: could not create the trace file {}: {}", file, e))
+            }))),
+            None => Box::new(io::stderr())
+        };
+        let tracer: Box<dyn Tracer> = match tracing.format {
+            Format::Text => Box::new(TextTracer::new(output)),
+            Format::Json => Box::new(JsonTracer::new(output))
+        };
+        class_table.trace.add(tracing.filter, tracer);
+    }
+    if let Some(ref jdwp) = options.jdwp {
+        class_table.set_compile_threshold(0);
+        let listener = TcpListener::bind(&jdwp.address).unwrap_or_else(|e| {
+            fail(&format!("Error: could not listen for a debugger at {}: {}", jdwp.address, e))
+        });
+        let port = listener.local_addr().map(|address| address.port()).unwrap_or_default();
+        println!("Listening for transport dt_socket at address: {}", port);
+        if let Err(e) = Agent::start(&class_table, listener, jdwp.suspend) {
+            fail(&format!("Error: could not start the debug agent: {}", e));
+        }
+    }
+    let sampler = if options.prof {
+        let interval = options.prof_interval.map_or(sampler::DEFAULT_INTERVAL, Duration::from_millis);
+        Some(Sampler::start(class_table.clone(), interval))
+    } else {
+        None
+    };
+
+    if options.java_home.is_some() {
+        if let Err(e) = bootstrap::jdk::boot(&class_table) {
+            fail(&format!("Error occurred during initialization of VM\n{}", e));
+        }
+    }
+
+    let exit_code = run(&class_table, &main_class, &options.arguments);
+    class_table.console.flush();
+    class_table.trace.flush();
+    class_table.jdwp.vm_death();
+    if let Some(ref stats) = options.stats {
+        write_stats(&class_table, stats);
+    }
+    if let Some(sampler) = sampler {
+        write_output(options.prof_file.as_ref(), &sampler.stop().collapsed(), "the profile");
+    }
+    process::exit(exit_code);
+}
+
+fn write_stats(class_table: &ClassTable, stats: &Stats) {
+    let contents = match stats.format {
+        Format::Text => class_table.profile.report(),
+        Format::Json => class_table.profile.to_json() + "\n"
+    };
+    write_output(stats.file.as_ref(), &contents, "statistics");
+}
+
+// Writes a report to a file, or to standard error without one.
+fn write_output(file: Option<&String>, contents: &str, what: &str) {
+    match file {
+        Some(file) => {
+            if let Err(e) = fs::write(file, contents) {
+                eprintln!("Error: could not write {} to {}: {}", what, file, e);
+            }
+        },
+        None => eprint!("{}", contents)
+    }
+}
 
-    let mut class_table = ClassTable::new();
+// The image the aot tool appended to this executable, if it has one.
+fn image() -> Option<Image> {
+    let executable = env::current_exe().ok()?;
+    Image::from_executable(&executable).unwrap_or_else(|e| {
+        fail(&format!("Error: could not read the image in {}: {}", executable.display(), e))
+    })
+}
 
-    let class_file = load_class_from_file("Counter.class").unwrap();
-    let runtime_class = RuntimeClass::from_class_file(&class_file).unwrap();
+// An executable built by aot runs the main class of its image with its compiled methods, and
+// passes its whole command line to the program.
+fn run_image(image: &Image, arguments: &[String]) -> ! {
+    let class_table = image.runtime(Console::stdio()).unwrap_or_else(|e| fail(&format!("Error: {}", e)));
+    if image.java_home.is_some() {
+        if let Err(e) = bootstrap::jdk::boot(&class_table) {
+            fail(&format!("Error occurred during initialization of VM\n{}", e));
+        }
+    }
 
-    class_table.load_class(&runtime_class);
-    println!("{:?}", class_table);
+    let exit_code = run(&class_table, &image.main_class, arguments);
+    class_table.console.flush();
+    process::exit(exit_code);
+}
 
-    println!("Running class file {}", runtime_class.class_name);
+// Runs the main method of the class and returns the exit code of the process. Errors are
+// written to the standard error of the class table.
+fn run(class_table: &ClassTable, main_class: &str, arguments: &[String]) -> i32 {
+    let class_name = main_class.replace('.', "/");
+    let runtime_class = match class_table.find_class(&class_name) {
+        Ok(Some(runtime_class)) => runtime_class,
+        Ok(None) => {
+            let mut err = class_table.console.err.lock().unwrap();
+            let _ = writeln!(err, "Error: Could not find or load main class {}", main_class);
+            let _ = writeln!(err, "Caused by: java.lang.ClassNotFoundException: {}", main_class);
+            return 1;
+        },
+        Err(e) => {
+            let mut err = class_table.console.err.lock().unwrap();
+            let _ = writeln!(err, "Error: Could not find or load main class {}", main_class);
+            let _ = writeln!(err, "Caused by: java.lang.LinkageError: {}", e);
+            return 1;
+        }
+    };
 
-    let main_method = runtime_class.get_method("main").unwrap();
     let expected_access_flags = method::ACC_PUBLIC | method::ACC_STATIC;
-    if main_method.access_flags & expected_access_flags == expected_access_flags {
-        let arguments = Vec::new(); // String[]
-        runtime::interpreter::invoke_static_method(arguments, &main_method, &runtime_class, &class_table);
+    let main_method = match runtime_class.get_declared_method("main", "([Ljava/lang/String;)V") {
+        Some(main_method) if main_method.access_flags & expected_access_flags == expected_access_flags => main_method,
+        _ => {
+            let mut err = class_table.console.err.lock().unwrap();
+            let _ = writeln!(err, "Error: Main method not found in class {}, please define the main method as:", main_class);
+            let _ = writeln!(err, "   public static void main(String[] args)");
+            return 1;
+        }
+    };
+
+    // The arguments are held in a scope until main has them.
+    let _scope = class_table.stacks.scope();
+    let strings = arguments.iter()
+        .map(|argument| Value::ObjectRef(class_table.new_string(argument.encode_utf16().collect())))
+        .collect();
+    let arguments = class_table.new_array_from("Ljava/lang/String;", ArrayElements::Reference(strings));
+
+    let attachment = class_table.heap.attach();
+    let result = runtime::interpreter::initialize_class(&runtime_class, class_table)
+        .and_then(|exception| match exception {
+            Some(exception) => Ok(InvokeResult::Exception(exception)),
+            None => runtime::interpreter::invoke_static_method(vec![Value::ArrayRef(arguments)], main_method, &runtime_class, class_table)
+        });
+
+    let exit_code = match result {
+        Ok(InvokeResult::Exception(exception)) => {
+            class_table.console.flush();
+            let _ = write!(class_table.console.err.lock().unwrap(), "Exception in thread \"main\" ");
+            match runtime::interpreter::invoke_virtual(class_table, exception, "printStackTrace", "()V", Vec::new()) {
+                Err(InterpreterError::Exit(status)) => return status,
+                _ => 1
+            }
+        },
+        Ok(_) => 0,
+        Err(InterpreterError::Exit(status)) => return status,
+        Err(e) => {
+            class_table.console.flush();
+            let mut err = class_table.console.err.lock().unwrap();
+            let _ = writeln!(err, "Internal error: {:?}", e);
+            for element in class_table.stacks.take_failure() {
+                let _ = writeln!(err, "\tat {}", element);
+            }
+            1
+        }
+    };
+
+    // Like the JVM, the process lives on until all threads that are not daemon threads have
+    // finished, unless System.exit was called. The main thread no longer runs Java code, so
+    // garbage collections need not wait for it.
+    drop(attachment);
+    class_table.join_threads();
+    exit_code
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use ironjdk::aot;
+    use ironjdk::runtime::bootstrap::io::{Console, SharedBuffer};
+    use std::sync::Arc;
+
+    fn parse(arguments: &[&str]) -> Result<Command, String> {
+        let arguments: Vec<String> = arguments.iter().map(|&argument| String::from(argument)).collect();
+        parse_arguments(&arguments)
+    }
+
+    #[test]
+    fn options_end_at_the_main_class() {
+        let expected = Options {
+            class_path: Some(String::from("lib:classes")),
+            properties: vec![(String::from("greeting"), String::from("a=b")), (String::from("flag"), String::new())],
+            verbose_class: true,
+            debug: true,
+            main: Some(Main::Class(String::from("com.example.Main"))),
+            arguments: vec![String::from("-cp"), String::from("x")],
+            ..Options::default()
+        };
+
+        let command = parse(&["-cp", "lib:classes", "-Dgreeting=a=b", "-Dflag", "-verbose:class", "--debug", "com.example.Main", "-cp", "x"]);
+        assert_eq!(command, Ok(Command::Run(expected)));
+    }
+
+    #[test]
+    fn jar_files() {
+        let expected = Options {
+            main: Some(Main::Jar(String::from("app.jar"))),
+            arguments: vec![String::from("argument")],
+            ..Options::default()
+        };
+
+        assert_eq!(parse(&["-jar", "app.jar", "argument"]), Ok(Command::Run(expected)));
+        assert!(parse(&["-jar"]).is_err());
+    }
+
+    #[test]
+    fn invalid_options() {
+        assert_eq!(parse(&["-cp"]), Err(String::from("-cp requires class path specification")));
+        assert_eq!(parse(&["-Xfoo", "Main"]), Err(String::from("Unrecognized option: -Xfoo")));
+        assert_eq!(parse(&["-version", "Main"]), Ok(Command::Version));
+        assert_eq!(parse(&["-Xmx", "Main"]), Err(String::from("Invalid maximum heap size: -Xmx")));
+        assert_eq!(parse(&["-Xmx0", "Main"]), Err(String::from("Invalid maximum heap size: -Xmx0")));
+    }
+
+    #[test]
+    fn heap_sizes() {
+        let expected = Options {
+            max_heap_size: Some(64 * 1024 * 1024),
+            main: Some(Main::Class(String::from("Main"))),
+            ..Options::default()
+        };
+
+        assert_eq!(parse(&["-Xmx64m", "Main"]), Ok(Command::Run(expected)));
+
+        let expected = Options {
+            young_size: Some(4 * 1024 * 1024),
+            log_gc: true,
+            main: Some(Main::Class(String::from("Main"))),
+            ..Options::default()
+        };
+        assert_eq!(parse(&["-Xmn4m", "-Xlog:gc", "Main"]), Ok(Command::Run(expected)));
+        assert_eq!(parse(&["-Xmnx", "Main"]), Err(String::from("Invalid young generation size: -Xmnx")));
+    }
+
+    #[test]
+    fn compile_thresholds() {
+        let expected = |compile_threshold| Ok(Command::Run(Options {
+            compile_threshold: Some(compile_threshold),
+            main: Some(Main::Class(String::from("Main"))),
+            ..Options::default()
+        }));
+
+        assert_eq!(parse(&["-Xint", "Main"]), expected(0));
+        assert_eq!(parse(&["-XX:CompileThreshold=50", "Main"]), expected(50));
+        assert_eq!(parse(&["-XX:CompileThreshold=0", "Main"]), Err(String::from("Invalid compile threshold: -XX:CompileThreshold=0")));
+        assert_eq!(parse_size("4096"), Some(4096));
+        assert_eq!(parse_size("512k"), Some(512 * 1024));
+        assert_eq!(parse_size("2G"), Some(2 * 1024 * 1024 * 1024));
+        assert_eq!(parse_size("m"), None);
+        assert_eq!(parse_size("-1m"), None);
+    }
+
+    #[test]
+    fn statistics() {
+        let expected = |format, file: Option<&str>| Ok(Command::Run(Options {
+            stats: Some(Stats { format, file: file.map(String::from) }),
+            main: Some(Main::Class(String::from("Main"))),
+            ..Options::default()
+        }));
+
+        assert_eq!(parse(&["-Xstats", "Main"]), expected(Format::Text, None));
+        assert_eq!(parse(&["-Xstats:json", "Main"]), expected(Format::Json, None));
+        assert_eq!(parse(&["-Xstats:text=stats.txt", "Main"]), expected(Format::Text, Some("stats.txt")));
+        assert_eq!(parse(&["-Xstats=stats.txt", "Main"]), expected(Format::Text, Some("stats.txt")));
+        assert_eq!(parse(&["-Xstats:xml", "Main"]), Err(String::from("Invalid statistics option: -Xstats:xml")));
+        assert_eq!(parse(&["-Xstats:json=", "Main"]), Err(String::from("Invalid statistics option: -Xstats:json=")));
+    }
+
+    #[test]
+    fn tracing() {
+        let expected = |format, file: Option<&str>, events, classes: &[&str], methods: &[&str]| Ok(Command::Run(Options {
+            trace: Some(Tracing {
+                format,
+                file: file.map(String::from),
+                filter: Filter {
+                    events,
+                    classes: classes.iter().map(|&class| String::from(class)).collect(),
+                    methods: methods.iter().map(|&method| String::from(method)).collect()
+                }
+            }),
+            main: Some(Main::Class(String::from("Main"))),
+            ..Options::default()
+        }));
+        let default_events = trace::CALLS | trace::ALLOCATIONS | trace::EXCEPTIONS | trace::CLASSES;
+
+        assert_eq!(parse(&["-Xtrace", "Main"]), expected(Format::Text, None, default_events, &[], &[]));
+        assert_eq!(parse(&["-Xtrace:format=json,file=trace.json,events=calls+instructions", "Main"]),
+                   expected(Format::Json, Some("trace.json"), trace::CALLS | trace::INSTRUCTIONS, &[], &[]));
+        assert_eq!(parse(&["-Xtrace:class=com.example.*,class=Main,method=*.run", "Main"]),
+                   expected(Format::Text, None, default_events, &["com.example.*", "Main"], &["*.run"]));
+        assert_eq!(parse(&["-Xtrace:", "Main"]), Err(String::from("Invalid trace option: -Xtrace:")));
+        assert_eq!(parse(&["-Xtrace:format=xml", "Main"]), Err(String::from("Invalid trace option: -Xtrace:format=xml")));
+        assert_eq!(parse(&["-Xtrace:events=calls+returns", "Main"]), Err(String::from("Invalid trace option: -Xtrace:events=calls+returns")));
+        assert_eq!(parse(&["-Xtracing", "Main"]), Err(String::from("Unrecognized option: -Xtracing")));
+    }
+
+    #[test]
+    fn sampling_profiler() {
+        let expected = |file: Option<&str>, interval| Ok(Command::Run(Options {
+            prof: true,
+            prof_file: file.map(String::from),
+            prof_interval: interval,
+            main: Some(Main::Class(String::from("Main"))),
+            ..Options::default()
+        }));
+
+        assert_eq!(parse(&["-Xprof", "Main"]), expected(None, None));
+        assert_eq!(parse(&["-Xprof=out.folded", "-XX:ProfileInterval=5", "Main"]), expected(Some("out.folded"), Some(5)));
+        assert_eq!(parse(&["-Xprof=", "Main"]), Err(String::from("Unrecognized option: -Xprof=")));
+        assert_eq!(parse(&["-XX:ProfileInterval=0", "Main"]), Err(String::from("Invalid profile interval: -XX:ProfileInterval=0")));
+    }
+
+    #[test]
+    fn debug_agent() {
+        let expected = |address: &str, suspend| Ok(Command::Run(Options {
+            jdwp: Some(jdwp::Options { address: String::from(address), suspend }),
+            main: Some(Main::Class(String::from("Main"))),
+            ..Options::default()
+        }));
+
+        assert_eq!(parse(&["-agentlib:jdwp=transport=dt_socket,server=y,address=5005", "Main"]), expected("127.0.0.1:5005", true));
+        assert_eq!(parse(&["-agentlib:jdwp=transport=dt_socket,server=y,suspend=n,address=*:8000", "Main"]), expected("0.0.0.0:8000", false));
+        assert_eq!(parse(&["-agentlib:jdwp=transport=dt_shmem,address=5005", "Main"]), Err(String::from("Unsupported JDWP transport: dt_shmem")));
+        assert_eq!(parse(&["-agentlib:jdwp=transport=dt_socket,server=y", "Main"]), Err(String::from("JDWP needs an address")));
+    }
+
+    // A class table running the classes in fixtures, with its output captured.
+    fn fixtures() -> (Arc<ClassTable>, SharedBuffer, SharedBuffer) {
+        let (out, err) = (SharedBuffer::new(), SharedBuffer::new());
+        let class_table = ClassTable::with_console(Console::new(Box::new(out.clone()), Box::new(err.clone())));
+        let mut class_path = ClassPath::new();
+        class_path.add_entries(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures")).unwrap();
+        class_table.set_class_path(class_path);
+        (class_table, out, err)
+    }
+
+    #[test]
+    fn uncaught_exceptions_exit_with_1() {
+        let (class_table, out, err) = fixtures();
+
+        assert_eq!(run(&class_table, "StackTraces", &[]), 1);
+        class_table.console.flush();
+        assert!(out.contents().ends_with("0\n"));
+        assert!(err.contents().contains("Exception in thread \"main\" StackTraces$Failure: update failed\n"), "{}", err.contents());
+    }
+
+    #[test]
+    fn system_exit_sets_the_exit_code() {
+        let (class_table, out, _) = fixtures();
+
+        assert_eq!(run(&class_table, "SystemProperties", &[String::from("a")]), 3);
+        class_table.console.flush();
+        assert!(out.contents().ends_with("1\na\nkey can't be empty\n"), "{}", out.contents());
+    }
+
+    #[test]
+    fn missing_main_classes() {
+        let (class_table, out, err) = fixtures();
+
+        assert_eq!(run(&class_table, "com.example.Missing", &[]), 1);
+        assert_eq!(out.contents(), "");
+        assert_eq!(err.contents(), "Error: Could not find or load main class com.example.Missing\n\
+            Caused by: java.lang.ClassNotFoundException: com.example.Missing\n");
+    }
+
+    // The image is built like the aot tool builds it against the JDK, and runs like run_image
+    // runs it, on the java.base module of the same JDK.
+    #[test]
+    #[ignore = "needs JAVA_HOME"]
+    fn runs_images_on_the_jdk() {
+        let java_home = env::var("JAVA_HOME").expect("JAVA_HOME must point to an installed JDK");
+        let class_table = ClassTable::without_library(Console::stdio());
+        let mut class_path = ClassPath::new();
+        class_path.add_java_home(Path::new(&java_home)).unwrap();
+        class_path.add_entries(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures")).unwrap();
+        class_table.set_class_path(class_path);
+
+        let roots = [aot::MethodName::new("JoinedStrings", "main", "([Ljava/lang/String;)V")];
+        let reachability = aot::reachable(&class_table, &roots).unwrap();
+        let object = aot::compile(&class_table, &reachability);
+        let mut image_class_path = ClassPath::new();
+        image_class_path.add_entries(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures")).unwrap();
+        let image = Image::new("JoinedStrings", Some(&java_home), &reachability, &mut image_class_path, &object).unwrap();
+        let bytes = image.write();
+        let image = Image::read(&bytes[..bytes.len() - 16]).unwrap();
+        assert_eq!(image.classes.iter().map(|(name, _)| name.as_str()).collect::<Vec<&str>>(), vec!["JoinedStrings"]);
+
+        let (out, err) = (SharedBuffer::new(), SharedBuffer::new());
+        let class_table = image.runtime(Console::new(Box::new(out.clone()), Box::new(err.clone()))).unwrap();
+        bootstrap::jdk::boot(&class_table).unwrap();
+        let class = class_table.get_class("JoinedStrings").unwrap();
+        assert!(matches!(class.get_declared_method("triangle", "(I)I").unwrap().compiled.get(), Some(Some(_))));
+
+        assert_eq!(run(&class_table, "JoinedStrings", &[]), 0);
+        class_table.console.flush();
+        assert_eq!(out.contents(), include_str!("../../fixtures/JoinedStrings.out"));
+        assert_eq!(err.contents(), "");
     }
 
-    ()
 }
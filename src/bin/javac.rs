@@ -1,3 +1,294 @@
+extern crate ironjdk;
+
+use ironjdk::class::path::ClassPath;
+use ironjdk::class::reader;
+use ironjdk::compiler::{attr, gen, incremental, parser};
+use ironjdk::compiler::gen::GeneratedClass;
+use ironjdk::compiler::incremental::Cache;
+use ironjdk::compiler::symbols::Symbols;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+const USAGE: &str = "Usage: javac <options> <source files>
+
+Compiles the source files to class files. Language features the code generator
+does not support yet are reported as errors.
+
+where possible options include:
+    -d <directory>
+                  Specify where to place generated class files, in directories
+                  for their packages. Defaults to the directory of each source
+                  file.
+    -cp <path>, -classpath <path>, --class-path <path>
+                  Specify where to find user class files, separated like PATH
+    --incremental
+                  Only recompile the source files that changed or that use
+                  members of changed classes, as recorded in a cache in the
+                  -d directory, which is required.
+    --java-home <directory>
+                  Read the platform classes from the java.base module of an
+                  installed JDK. Defaults to the JAVA_HOME environment variable.
+    -help, --help
+                  Print this help message";
+
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    class_path: Option<String>,
+    directory: Option<String>,
+    incremental: bool,
+    java_home: Option<String>,
+    sources: Vec<String>
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Compile(Options),
+    Help
+}
+
+fn parse_arguments(arguments: &[String]) -> Result<Command, String> {
+    let mut options = Options::default();
+    let mut remaining = arguments.iter();
+
+    while let Some(argument) = remaining.next() {
+        let mut value = || remaining.next().cloned().ok_or_else(|| format!("{} requires an argument", argument));
+
+        match argument.as_str() {
+            "-cp" | "-classpath" | "--class-path" => options.class_path = Some(value()?),
+            "-d" => options.directory = Some(value()?),
+            "--incremental" => options.incremental = true,
+            "--java-home" => options.java_home = Some(value()?),
+            "-help" | "--help" | "-?" => return Ok(Command::Help),
+            _ if argument.starts_with('-') => return Err(format!("invalid flag: {}", argument)),
+            _ => options.sources.push(argument.clone())
+        }
+    }
+
+    Ok(Command::Compile(options))
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    process::exit(2);
+}
+
+// Where class files are looked up: the platform classes, the output directory when compiling
+// incrementally, and the user class path.
+fn class_path(java_home: &str, output: Option<&str>, user_class_path: &str) -> ClassPath {
+    let mut class_path = ClassPath::new();
+    class_path.add_java_home(Path::new(java_home)).unwrap_or_else(|e| {
+        fail(&format!("could not open the JDK in {}: {:?}", java_home, e))
+    });
+    for entries in output.iter().chain(Some(&user_class_path)) {
+        class_path.add_entries(entries).unwrap_or_else(|e| {
+            fail(&format!("could not open the class path {}: {:?}", entries, e))
+        });
+    }
+    class_path
+}
+
+// Compiles source files, by path and content, and gives the classes generated from each, or the
+// number of errors, which are reported.
+fn compile(sources: &[(String, String)], class_path: ClassPath) -> Result<Vec<(String, Vec<GeneratedClass>)>, usize> {
+    let mut units = Vec::new();
+    let mut errors = 0;
+    for (path, source) in sources.iter() {
+        match parser::parse(path, source) {
+            Ok(unit) => units.push((path.clone(), unit)),
+            Err(diagnostic) => {
+                eprintln!("{}", diagnostic.render(source));
+                errors += 1;
+            }
+        }
+    }
+    // Like javac, source files are only attributed once they all parse.
+    if errors > 0 {
+        return Err(errors);
+    }
+
+    let result = attr::attribute(&units, Symbols::new(class_path)).and_then(|attribution| {
+        let mut generated = Vec::new();
+        let mut diagnostics = Vec::new();
+        for (path, unit) in units.iter() {
+            match gen::generate(path, unit, &attribution) {
+                Ok(classes) => generated.push((path.clone(), classes)),
+                Err(errors) => diagnostics.extend(errors)
+            }
+        }
+        if diagnostics.is_empty() { Ok(generated) } else { Err(diagnostics) }
+    });
+
+    result.map_err(|diagnostics| {
+        for diagnostic in diagnostics.iter() {
+            let source = sources.iter().find(|&(path, _)| *path == diagnostic.file).map_or("", |(_, source)| source.as_str());
+            eprintln!("{}", diagnostic.render(source));
+        }
+        if diagnostics.iter().any(|diagnostic| diagnostic.simplified) {
+            eprintln!("Note: Some messages have been simplified; recompile with -Xdiags:verbose to get full output");
+        }
+        diagnostics.len()
+    })
+}
+
+// The path of a class file in the output directory, in the directories of its package, or
+// without -d next to its source file.
+fn class_file_path(options: &Options, source: &str, name: &str) -> PathBuf {
+    match options.directory {
+        Some(ref directory) => Path::new(directory).join(format!("{}.class", name)),
+        None => {
+            let simple_name = name.rsplit('/').next().unwrap_or(name);
+            let directory = Path::new(source).parent().map_or_else(PathBuf::new, Path::to_path_buf);
+            directory.join(format!("{}.class", simple_name))
+        }
+    }
+}
+
+fn write_class(options: &Options, source: &str, name: &str, bytes: &[u8]) {
+    let path = class_file_path(options, source, name);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).unwrap_or_else(|e| fail(&format!("could not create directory {}: {}", parent.display(), e)));
+    }
+    fs::write(&path, bytes).unwrap_or_else(|e| fail(&format!("could not write {}: {}", path.display(), e)));
+}
+
+// Recompiles the source files that changed since the last compilation, and then, until none are
+// left, those that use members whose API changed.
+fn compile_incrementally(options: &Options, sources: &[(String, String)], java_home: &str, user_class_path: &str) -> Result<(), usize> {
+    let directory = options.directory.as_deref().unwrap_or_else(|| fail("--incremental requires -d"));
+    let cache_path = Path::new(directory).join(incremental::CACHE_FILE);
+    let mut cache = Cache::load(&cache_path)
+        .filter(|cache| cache.class_path == user_class_path)
+        .unwrap_or_else(|| Cache::new(user_class_path));
+    let contents: BTreeMap<&str, &str> = sources.iter().map(|(path, source)| (path.as_str(), source.as_str())).collect();
+
+    // The classes of source files that are no longer compiled are deleted.
+    let removed: Vec<String> = cache.sources.keys().filter(|path| !contents.contains_key(path.as_str())).cloned().collect();
+    for path in removed.iter() {
+        delete_classes(options, &cache, path);
+        cache.sources.remove(path);
+    }
+
+    let mut stale: Vec<String> = sources.iter().filter(|&(path, source)| match cache.sources.get(path) {
+        Some(compiled) => compiled.hash != incremental::hash_bytes(source.as_bytes())
+            || compiled.classes.iter().any(|class| !class_file_path(options, path, &class.name).exists()),
+        None => true
+    }).map(|(path, _)| path.clone()).collect();
+
+    while !stale.is_empty() {
+        for path in stale.iter() {
+            delete_classes(options, &cache, path);
+        }
+        let batch: Vec<(String, String)> = stale.iter().map(|path| (path.clone(), String::from(contents[path.as_str()]))).collect();
+        let generated = compile(&batch, class_path(java_home, Some(directory), user_class_path))?;
+
+        for (path, classes) in generated.into_iter() {
+            let mut compiled = Vec::new();
+            for class in classes.iter() {
+                write_class(options, &path, &class.name, &class.bytes);
+                let class_file = reader::read_class_file(&class.bytes).unwrap_or_else(|e| fail(&format!("could not read class {}: {:?}", class.name, e)));
+                let api = incremental::api(&class_file).unwrap_or_else(|e| fail(&e));
+                let dependencies = incremental::dependencies(&class_file, &class.constants).unwrap_or_else(|e| fail(&e));
+                compiled.push((class.name.clone(), api, dependencies));
+            }
+            cache.record(&path, incremental::hash_bytes(contents[path.as_str()].as_bytes()), compiled);
+        }
+        cache.fingerprint_uses(&stale);
+
+        stale = cache.sources.keys().filter(|path| cache.is_stale(path)).cloned().collect();
+    }
+
+    cache.save(&cache_path).unwrap_or_else(|e| fail(&format!("could not write {}: {}", cache_path.display(), e)));
+    Ok(())
+}
+
+fn delete_classes(options: &Options, cache: &Cache, source: &str) {
+    if let Some(compiled) = cache.sources.get(source) {
+        for class in compiled.classes.iter() {
+            let _ = fs::remove_file(class_file_path(options, source, &class.name));
+        }
+    }
+}
+
 fn main() {
-    println!("javac");
-}
\ No newline at end of file
+    let arguments: Vec<String> = env::args().skip(1).collect();
+    let options = match parse_arguments(&arguments) {
+        Ok(Command::Compile(ref options)) if options.sources.is_empty() => {
+            println!("{}", USAGE);
+            process::exit(2);
+        },
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            process::exit(0);
+        },
+        Ok(Command::Compile(options)) => options,
+        Err(message) => fail(&message)
+    };
+    if options.incremental && options.directory.is_none() {
+        fail("--incremental requires -d");
+    }
+
+    let java_home = options.java_home.clone().or_else(|| env::var("JAVA_HOME").ok())
+        .unwrap_or_else(|| fail("no platform classes; set JAVA_HOME or use --java-home"));
+    let user_class_path = options.class_path.clone()
+        .or_else(|| env::var("CLASSPATH").ok())
+        .unwrap_or_else(|| String::from("."));
+
+    let sources: Vec<(String, String)> = options.sources.iter().map(|path| {
+        let source = fs::read_to_string(path).unwrap_or_else(|_| fail(&format!("file not found: {}", path)));
+        (path.clone(), source)
+    }).collect();
+
+    let result = if options.incremental {
+        compile_incrementally(&options, &sources, &java_home, &user_class_path)
+    } else {
+        compile(&sources, class_path(&java_home, None, &user_class_path)).map(|generated| {
+            for (path, classes) in generated.iter() {
+                for class in classes.iter() {
+                    write_class(&options, path, &class.name, &class.bytes);
+                }
+            }
+        })
+    };
+
+    if let Err(errors) = result {
+        eprintln!("{} error{}", errors, if errors == 1 { "" } else { "s" });
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn parse(arguments: &[&str]) -> Result<Command, String> {
+        let arguments: Vec<String> = arguments.iter().map(|&argument| String::from(argument)).collect();
+        parse_arguments(&arguments)
+    }
+
+    #[test]
+    fn options_and_source_files() {
+        let expected = Options {
+            class_path: Some(String::from("lib")),
+            directory: Some(String::from("classes")),
+            incremental: true,
+            java_home: Some(String::from("/opt/jdk")),
+            sources: vec![String::from("A.java"), String::from("B.java")]
+        };
+
+        let command = parse(&["A.java", "-classpath", "lib", "-d", "classes", "--incremental", "--java-home", "/opt/jdk", "B.java"]);
+        assert_eq!(command, Ok(Command::Compile(expected)));
+    }
+
+    #[test]
+    fn bad_options() {
+        assert_eq!(parse(&["-cp"]), Err(String::from("-cp requires an argument")));
+        assert_eq!(parse(&["A.java", "-d"]), Err(String::from("-d requires an argument")));
+        assert_eq!(parse(&["-g", "A.java"]), Err(String::from("invalid flag: -g")));
+        assert_eq!(parse(&["--help", "A.java"]), Ok(Command::Help));
+    }
+
+}
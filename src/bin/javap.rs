@@ -13,7 +13,7 @@ fn main() {
 
     file.read_to_end(&mut buffer).unwrap();
 
-    let result = reader::read_class_file(&mut buffer);
+    let result = reader::read_class_file(&buffer);
     match result {
         Ok(class_file) => {
             class_file.print_constant_pool();
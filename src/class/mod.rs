@@ -1,9 +1,14 @@
 
 // Low-level representations of a ClassFile`
 
+pub mod mutf8;
+pub mod path;
+pub mod pool;
 pub mod reader;
+pub mod writer;
 
 use code::disassembler;
+use class::mutf8::JavaString;
 
 pub mod method {
     pub const ACC_PUBLIC: u16 = 0x0001;
@@ -15,9 +20,49 @@ pub mod method {
     pub const ACC_BRIDGE: u16 = 0x0040;
     pub const ACC_VARARGS: u16 = 0x0080;
     pub const ACC_NATIVE: u16 = 0x0100;
-    pub const ACC_ABSTRACT: u16 = 0x0200;
-    pub const ACC_STRICT: u16 = 0x0400;
-    pub const ACC_SYNTHETIC: u16 = 0x0800;
+    pub const ACC_ABSTRACT: u16 = 0x0400;
+    pub const ACC_STRICT: u16 = 0x0800;
+    pub const ACC_SYNTHETIC: u16 = 0x1000;
+}
+
+pub mod field {
+    pub const ACC_PUBLIC: u16 = 0x0001;
+    pub const ACC_PRIVATE: u16 = 0x0002;
+    pub const ACC_PROTECTED: u16 = 0x0004;
+    pub const ACC_STATIC: u16 = 0x0008;
+    pub const ACC_FINAL: u16 = 0x0010;
+    pub const ACC_VOLATILE: u16 = 0x0040;
+    pub const ACC_TRANSIENT: u16 = 0x0080;
+    pub const ACC_SYNTHETIC: u16 = 0x1000;
+    pub const ACC_ENUM: u16 = 0x4000;
+}
+
+pub mod class_flags {
+    pub const ACC_PUBLIC: u16 = 0x0001;
+    // Private, protected and static only appear in the InnerClasses attribute.
+    pub const ACC_PRIVATE: u16 = 0x0002;
+    pub const ACC_PROTECTED: u16 = 0x0004;
+    pub const ACC_STATIC: u16 = 0x0008;
+    pub const ACC_FINAL: u16 = 0x0010;
+    pub const ACC_SUPER: u16 = 0x0020;
+    pub const ACC_INTERFACE: u16 = 0x0200;
+    pub const ACC_ABSTRACT: u16 = 0x0400;
+    pub const ACC_SYNTHETIC: u16 = 0x1000;
+    pub const ACC_ANNOTATION: u16 = 0x2000;
+    pub const ACC_ENUM: u16 = 0x4000;
+}
+
+// The kinds of method handle constants, see JVMS $5.4.3.5.
+pub mod reference_kind {
+    pub const REF_GET_FIELD: u8 = 1;
+    pub const REF_GET_STATIC: u8 = 2;
+    pub const REF_PUT_FIELD: u8 = 3;
+    pub const REF_PUT_STATIC: u8 = 4;
+    pub const REF_INVOKE_VIRTUAL: u8 = 5;
+    pub const REF_INVOKE_STATIC: u8 = 6;
+    pub const REF_INVOKE_SPECIAL: u8 = 7;
+    pub const REF_NEW_INVOKE_SPECIAL: u8 = 8;
+    pub const REF_INVOKE_INTERFACE: u8 = 9;
 }
 
 pub struct ClassFile {
@@ -119,7 +164,10 @@ pub enum ConstantPoolTag {
     Utf8,
     MethodHandle,
     MethodType,
-    InvokeDynamic
+    Dynamic,
+    InvokeDynamic,
+    Module,
+    Package
 }
 
 #[derive(Clone, Debug)]
@@ -157,11 +205,70 @@ impl ConstantPool {
         let entry = self.get_entry(index)?;
 
         match entry {
-            ConstantPoolEntry::Utf8(ref string) => Ok(string.clone()),
+            ConstantPoolEntry::Utf8(ref string) => string.to_rust_string(),
+            _ => Err(String::from("Expected Utf8 attribute"))
+        }
+    }
+
+    // Unlike get_utf8, this also supports constants containing unpaired surrogates.
+    pub fn get_utf16(&self, index: u16) -> Result<Vec<u16>, String> {
+        let entry = self.get_entry(index)?;
+
+        match entry {
+            ConstantPoolEntry::Utf8(ref string) => Ok(string.as_utf16().to_vec()),
             _ => Err(String::from("Expected Utf8 attribute"))
         }
     }
 
+    pub fn get_string(&self, index: u16) -> Result<Vec<u16>, String> {
+        let entry = self.get_entry(index)?;
+
+        match entry {
+            ConstantPoolEntry::String { string_index } => self.get_utf16(*string_index),
+            _ => Err(String::from("Expected String attribute"))
+        }
+    }
+
+    pub fn get_integer(&self, index: u16) -> Result<i32, String> {
+        let entry = self.get_entry(index)?;
+
+        match entry {
+            ConstantPoolEntry::Integer { bytes } => Ok(*bytes as i32),
+            _ => Err(String::from("Expected Integer attribute"))
+        }
+    }
+
+    pub fn get_float(&self, index: u16) -> Result<f32, String> {
+        let entry = self.get_entry(index)?;
+
+        match entry {
+            ConstantPoolEntry::Float { bytes } => Ok(f32::from_bits(*bytes)),
+            _ => Err(String::from("Expected Float attribute"))
+        }
+    }
+
+    pub fn get_long(&self, index: u16) -> Result<i64, String> {
+        let entry = self.get_entry(index)?;
+
+        match entry {
+            ConstantPoolEntry::Long { high_bytes, low_bytes } => {
+                Ok((((*high_bytes as u64) << 32) + (*low_bytes as u64)) as i64)
+            },
+            _ => Err(String::from("Expected Long attribute"))
+        }
+    }
+
+    pub fn get_double(&self, index: u16) -> Result<f64, String> {
+        let entry = self.get_entry(index)?;
+
+        match entry {
+            ConstantPoolEntry::Double { high_bytes, low_bytes } => {
+                Ok(f64::from_bits(((*high_bytes as u64) << 32) + (*low_bytes as u64)))
+            },
+            _ => Err(String::from("Expected Double attribute"))
+        }
+    }
+
     pub fn get_class_name(&self, index: u16) -> Result<String, String> {
         let entry = self.get_entry(index)?;
 
@@ -194,7 +301,8 @@ impl ConstantPool {
         let entry = self.get_entry(index)?;
 
         match entry {
-            ConstantPoolEntry::Methodref { class_index, name_and_type_index} => {
+            ConstantPoolEntry::Methodref { class_index, name_and_type_index} |
+            ConstantPoolEntry::InterfaceMethodref { class_index, name_and_type_index } => {
                 let class_name = self.get_class_name(*class_index)?;
                 let name_and_type = self.get_name_and_type(*name_and_type_index)?;
                 let methodref = Methodref {
@@ -207,6 +315,52 @@ impl ConstantPool {
         }
     }
 
+    // A method handle to a field refers to a Fieldref, which it keeps as a Methodref of the same
+    // class, name and descriptor.
+    pub fn get_method_handle(&self, index: u16) -> Result<MethodHandle, String> {
+        let entry = self.get_entry(index)?;
+
+        match entry {
+            ConstantPoolEntry::MethodHandle { reference_kind, reference_index } => {
+                let method_ref = match self.get_field_ref(*reference_index) {
+                    Ok(Fieldref { class_name, name_and_type }) => Methodref { class_name, name_and_type },
+                    Err(_) => self.get_method_ref(*reference_index)?
+                };
+                let method_handle = MethodHandle {
+                    reference_kind: *reference_kind,
+                    method_ref
+                };
+                Ok(method_handle)
+            },
+            _ => Err(String::from("Expected MethodHandle attribute"))
+        }
+    }
+
+    pub fn get_method_type(&self, index: u16) -> Result<String, String> {
+        let entry = self.get_entry(index)?;
+
+        match entry {
+            ConstantPoolEntry::MethodType { descriptor_index } => self.get_utf8(*descriptor_index),
+            _ => Err(String::from("Expected MethodType attribute"))
+        }
+    }
+
+    pub fn get_invoke_dynamic(&self, index: u16) -> Result<InvokeDynamic, String> {
+        let entry = self.get_entry(index)?;
+
+        match entry {
+            ConstantPoolEntry::InvokeDynamic { bootstrap_method_attr_index, name_and_type_index } => {
+                let name_and_type = self.get_name_and_type(*name_and_type_index)?;
+                let invoke_dynamic = InvokeDynamic {
+                    bootstrap_method_attr_index: *bootstrap_method_attr_index,
+                    name_and_type
+                };
+                Ok(invoke_dynamic)
+            },
+            _ => Err(String::from("Expected InvokeDynamic attribute"))
+        }
+    }
+
     pub fn get_field_ref(&self, index: u16) -> Result<Fieldref, String> {
         let entry = self.get_entry(index)?;
 
@@ -238,13 +392,25 @@ pub struct Methodref {
     pub name_and_type: NameAndType
 }
 
+#[derive(Debug)]
+pub struct MethodHandle {
+    pub reference_kind: u8,
+    pub method_ref: Methodref
+}
+
+#[derive(Debug)]
+pub struct InvokeDynamic {
+    pub bootstrap_method_attr_index: u16,
+    pub name_and_type: NameAndType
+}
+
 #[derive(Debug)]
 pub struct NameAndType {
     pub name: String,
     pub descriptor: String
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum ConstantPoolEntry {
     Class { name_index: u16 },
     Fieldref { class_index: u16, name_and_type_index: u16 },
@@ -256,10 +422,13 @@ pub enum ConstantPoolEntry {
     Long { high_bytes: u32, low_bytes: u32 },
     Double { high_bytes: u32, low_bytes: u32 },
     NameAndType { name_index: u16, descriptor_index: u16 },
-    Utf8(String),
+    Utf8(JavaString),
     MethodHandle { reference_kind: u8, reference_index: u16 },
     MethodType { descriptor_index: u16 },
+    Dynamic { bootstrap_method_attr_index: u16, name_and_type_index: u16 },
     InvokeDynamic { bootstrap_method_attr_index: u16, name_and_type_index: u16 },
+    Module { name_index: u16 },
+    Package { name_index: u16 },
 
     Placeholder
 }
@@ -305,7 +474,7 @@ pub enum Attribute {
     SourceFile { index: u16 },
     SourceDebugExtension {},
     LineNumberTable(Vec<LineNumberTableEntry>),
-    LocalVariableTable {},
+    LocalVariableTable(Vec<LocalVariableTableEntry>),
     LocalVariableTypeTable {},
     Deprecated,
     RuntimeVisibleAnnotations { annotations: Vec<Annotation> },
@@ -313,8 +482,16 @@ pub enum Attribute {
     RuntimeInvisibleAnnotations {},
     RuntimeVisibleParameterAnnotations {},
     RuntimeInvisibleParameterAnnotations {},
-    AnnotationDefault {},
-    BootstrapMethods {}
+    AnnotationDefault { default_value: AnnotationElementValue },
+    BootstrapMethods { methods: Vec<BootstrapMethod> },
+    Unrecognized(AttributeInfo)
+}
+
+// A bootstrap method handle with its static arguments, which are loadable constants.
+#[derive(Clone, Debug)]
+pub struct BootstrapMethod {
+    pub bootstrap_method_ref: u16,
+    pub bootstrap_arguments: Vec<u16>
 }
 
 #[derive(Clone, Debug)]
@@ -331,19 +508,23 @@ pub struct AnnotationElementPair {
 
 #[derive(Clone, Debug)]
 pub enum AnnotationElementValue {
-    Const(u16),
+    // The tag tells the type of the constant, which is one of B, C, D, F, I, J, S, Z or s for
+    // String.
+    Const { tag: char, const_value_index: u16 },
     EnumConst { type_name_index: u16, const_name_index: u16 },
     ClassInfo(u16),
     Annotation(Annotation),
     Array(Vec<AnnotationElementValue>)
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum StackMapFrame {
-    SameFrame,
-    SameLocals1StackItemFrame { info: VerificationTypeInfo },
-    SameLocals1StackItemFrameExtended { info: VerificationTypeInfo },
-    ChopFrame { offset_delta: u16 },
+    // The offset delta of the short forms is encoded in the frame type.
+    SameFrame { offset_delta: u8 },
+    SameLocals1StackItemFrame { offset_delta: u8, info: VerificationTypeInfo },
+    SameLocals1StackItemFrameExtended { offset_delta: u16, info: VerificationTypeInfo },
+    // Removes the last one to three locals of the previous frame.
+    ChopFrame { offset_delta: u16, chopped: u8 },
     SameFrameExtended { offset_delta: u16 },
     AppendFrame { offset_delta: u16, locals: Vec<VerificationTypeInfo> },
     FullFrame {
@@ -353,7 +534,7 @@ pub enum StackMapFrame {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum VerificationTypeInfo {
     Top,
     Integer,
@@ -382,8 +563,18 @@ pub struct InnerClassTableEntry {
     pub inner_class_access_flags: u16
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct LineNumberTableEntry {
     pub start_pc: u16,
     pub line_number: u16
 }
+
+// A local variable is live from start_pc for length bytes of code, see JVMS $4.7.13.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LocalVariableTableEntry {
+    pub start_pc: u16,
+    pub length: u16,
+    pub name_index: u16,
+    pub descriptor_index: u16,
+    pub index: u16
+}
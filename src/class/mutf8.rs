@@ -0,0 +1,191 @@
+// The "modified UTF-8" encoding used by CONSTANT_Utf8_info structures, see JVMS $4.4.7.
+//
+// It differs from standard UTF-8 in two ways: the null character is encoded with two bytes
+// (0xC0 0x80) so that encoded strings never contain a zero byte, and supplementary characters are
+// encoded as their UTF-16 surrogate pair, with each surrogate written as its own three byte
+// sequence. Java strings may also contain unpaired surrogates, so decoded constants are stored as
+// UTF-16 code units rather than as Rust strings.
+
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum Mutf8Error {
+    // A byte that can never appear in modified UTF-8 (0x00 or 0xF0 through 0xFF).
+    InvalidByte { position: usize, byte: u8 },
+    // A multi-byte sequence was cut short or had a malformed continuation byte.
+    InvalidSequence { position: usize }
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Vec<u16>, Mutf8Error> {
+    let mut chars: Vec<u16> = Vec::with_capacity(bytes.len());
+    let mut position = 0;
+
+    while position < bytes.len() {
+        let x = bytes[position];
+
+        match x {
+            0x01..=0x7F => {
+                chars.push(x as u16);
+                position += 1;
+            },
+            0xC0..=0xDF => {
+                let y = continuation(bytes, position, 1)?;
+                chars.push((((x & 0x1F) as u16) << 6) + y);
+                position += 2;
+            },
+            0xE0..=0xEF => {
+                let y = continuation(bytes, position, 1)?;
+                let z = continuation(bytes, position, 2)?;
+                chars.push((((x & 0x0F) as u16) << 12) + (y << 6) + z);
+                position += 3;
+            },
+            0x80..=0xBF => return Err(Mutf8Error::InvalidSequence { position }),
+            byte => return Err(Mutf8Error::InvalidByte { position, byte })
+        }
+    }
+
+    Ok(chars)
+}
+
+fn continuation(bytes: &[u8], start: usize, offset: usize) -> Result<u16, Mutf8Error> {
+    match bytes.get(start + offset) {
+        Some(&byte) if byte & 0xC0 == 0x80 => Ok((byte & 0x3F) as u16),
+        _ => Err(Mutf8Error::InvalidSequence { position: start })
+    }
+}
+
+pub fn encode(chars: &[u16]) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::with_capacity(chars.len());
+
+    for &c in chars.iter() {
+        match c {
+            0x0001..=0x007F => bytes.push(c as u8),
+            0x0000 | 0x0080..=0x07FF => {
+                bytes.push(0xC0 | (c >> 6) as u8);
+                bytes.push(0x80 | (c & 0x3F) as u8);
+            },
+            _ => {
+                bytes.push(0xE0 | (c >> 12) as u8);
+                bytes.push(0x80 | ((c >> 6) & 0x3F) as u8);
+                bytes.push(0x80 | (c & 0x3F) as u8);
+            }
+        }
+    }
+
+    bytes
+}
+
+// The contents of a CONSTANT_Utf8_info entry.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct JavaString {
+    chars: Vec<u16>
+}
+
+impl JavaString {
+
+    pub fn from_utf16(chars: Vec<u16>) -> JavaString {
+        JavaString { chars }
+    }
+
+    pub fn as_utf16(&self) -> &[u16] {
+        &self.chars
+    }
+
+    // Fails if the string contains an unpaired surrogate.
+    pub fn to_rust_string(&self) -> Result<String, String> {
+        String::from_utf16(&self.chars)
+            .map_err(|_| String::from("Utf8 constant contains an unpaired surrogate"))
+    }
+
+    pub fn to_string_lossy(&self) -> String {
+        String::from_utf16_lossy(&self.chars)
+    }
+
+    pub fn to_modified_utf8(&self) -> Vec<u8> {
+        encode(&self.chars)
+    }
+
+}
+
+impl<'a> From<&'a str> for JavaString {
+    fn from(string: &'a str) -> JavaString {
+        JavaString::from_utf16(string.encode_utf16().collect())
+    }
+}
+
+impl fmt::Debug for JavaString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.to_string_lossy())
+    }
+}
+
+impl fmt::Display for JavaString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_string_lossy())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn ascii() {
+        let bytes = b"Counter";
+        let chars = decode(bytes).unwrap();
+
+        assert_eq!(String::from_utf16(&chars).unwrap(), "Counter");
+        assert_eq!(encode(&chars), bytes.to_vec());
+    }
+
+    #[test]
+    fn null_character_uses_two_bytes() {
+        let bytes = vec![0x61, 0xC0, 0x80, 0x62];
+        let chars = decode(&bytes).unwrap();
+
+        assert_eq!(chars, vec![0x61, 0x00, 0x62]);
+        assert_eq!(encode(&chars), bytes);
+    }
+
+    #[test]
+    fn two_and_three_byte_characters() {
+        // U+00E9 and U+20AC are encoded exactly as in standard UTF-8.
+        let bytes = "é€".as_bytes();
+        let chars = decode(bytes).unwrap();
+
+        assert_eq!(chars, vec![0x00E9, 0x20AC]);
+        assert_eq!(encode(&chars), bytes.to_vec());
+    }
+
+    #[test]
+    fn supplementary_characters_use_surrogate_pairs() {
+        // U+1F600 is the surrogate pair D83D DE00, each written as a three byte sequence.
+        let bytes = vec![0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80];
+        let chars = decode(&bytes).unwrap();
+
+        assert_eq!(chars, vec![0xD83D, 0xDE00]);
+        assert_eq!(String::from_utf16(&chars).unwrap(), "\u{1F600}");
+        assert_eq!(encode(&"\u{1F600}".encode_utf16().collect::<Vec<u16>>()), bytes);
+    }
+
+    #[test]
+    fn unpaired_surrogates_round_trip() {
+        let chars = vec![0x0041, 0xDC00, 0x0042];
+        let string = JavaString::from_utf16(chars.clone());
+
+        assert_eq!(decode(&string.to_modified_utf8()).unwrap(), chars);
+        assert!(string.to_rust_string().is_err());
+        assert_eq!(string.to_string_lossy(), "A\u{FFFD}B");
+    }
+
+    #[test]
+    fn invalid_bytes() {
+        assert_eq!(decode(&[0x61, 0x00]), Err(Mutf8Error::InvalidByte { position: 1, byte: 0x00 }));
+        assert_eq!(decode(&[0xF0, 0x9F, 0x98, 0x80]), Err(Mutf8Error::InvalidByte { position: 0, byte: 0xF0 }));
+        assert_eq!(decode(&[0x80]), Err(Mutf8Error::InvalidSequence { position: 0 }));
+        assert_eq!(decode(&[0x61, 0xE2, 0x82]), Err(Mutf8Error::InvalidSequence { position: 1 }));
+        assert_eq!(decode(&[0xC3, 0x41]), Err(Mutf8Error::InvalidSequence { position: 0 }));
+    }
+
+}
@@ -0,0 +1,300 @@
+// A decoder for the DEFLATE format (RFC 1951), used by ZIP entries and by jimage resources
+// compressed with the "zip" plugin.
+
+use class::path::ArchiveError;
+
+const MAX_BITS: usize = 15;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31,
+    35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2,
+    3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193,
+    257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6,
+    7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13
+];
+
+// The order in which the code lengths of the code length alphabet are stored, see RFC 1951 $3.2.7.
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+// Decompresses a raw DEFLATE stream.
+pub fn inflate(input: &[u8]) -> Result<Vec<u8>, ArchiveError> {
+    let mut reader = BitReader { input, position: 0 };
+    let mut output = Vec::new();
+
+    loop {
+        let is_final = reader.bits(1)? == 1;
+
+        match reader.bits(2)? {
+            0 => inflate_stored(&mut reader, &mut output)?,
+            1 => {
+                let (literals, distances) = fixed_codes();
+                inflate_codes(&mut reader, &mut output, &literals, &distances)?;
+            },
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut reader)?;
+                inflate_codes(&mut reader, &mut output, &literals, &distances)?;
+            },
+            _ => return Err(ArchiveError::InvalidCompressedData)
+        }
+
+        if is_final {
+            return Ok(output);
+        }
+    }
+}
+
+// Decompresses a zlib stream (RFC 1950), which wraps a DEFLATE stream in a two byte header and
+// an Adler-32 checksum.
+pub fn inflate_zlib(input: &[u8]) -> Result<Vec<u8>, ArchiveError> {
+    if input.len() < 2 {
+        return Err(ArchiveError::Truncated);
+    }
+
+    let method = input[0] & 0x0F;
+    let preset_dictionary = input[1] & 0x20 != 0;
+    if method != 8 || preset_dictionary || !((input[0] as u16) << 8 | input[1] as u16).is_multiple_of(31) {
+        return Err(ArchiveError::InvalidCompressedData);
+    }
+
+    inflate(&input[2..])
+}
+
+struct BitReader<'a> {
+    input: &'a [u8],
+    // The position in bits. Bits are consumed starting at the least significant bit of each byte.
+    position: usize
+}
+
+impl<'a> BitReader<'a> {
+
+    fn bits(&mut self, count: usize) -> Result<u32, ArchiveError> {
+        let mut value = 0;
+
+        for i in 0..count {
+            let byte = *self.input.get(self.position / 8).ok_or(ArchiveError::Truncated)?;
+            let bit = (byte >> (self.position % 8)) & 1;
+            value |= (bit as u32) << i;
+            self.position += 1;
+        }
+
+        Ok(value)
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], ArchiveError> {
+        let start = self.position.div_ceil(8);
+        let bytes = self.input.get(start..start + length).ok_or(ArchiveError::Truncated)?;
+        self.position = (start + length) * 8;
+        Ok(bytes)
+    }
+
+}
+
+// A canonical Huffman code, represented by the number of codes of each length and the symbols
+// ordered by code.
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>
+}
+
+impl Huffman {
+
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &length in lengths.iter() {
+            counts[length as usize] += 1;
+        }
+
+        let mut offsets = [0u16; MAX_BITS + 2];
+        for length in 1..=MAX_BITS {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+
+        let mut symbols = vec![0; offsets[MAX_BITS + 1] as usize];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+
+        Huffman { counts, symbols }
+    }
+
+    // Huffman codes are stored starting with the most significant bit, so the code is read one
+    // bit at a time and compared against the first code of each length.
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, ArchiveError> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+
+        for length in 1..=MAX_BITS {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[length] as i32;
+
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(ArchiveError::InvalidCompressedData)
+    }
+
+}
+
+fn inflate_stored(reader: &mut BitReader, output: &mut Vec<u8>) -> Result<(), ArchiveError> {
+    let header = reader.bytes(4)?;
+    let length = header[0] as u16 | (header[1] as u16) << 8;
+    let complement = header[2] as u16 | (header[3] as u16) << 8;
+
+    if length != !complement {
+        return Err(ArchiveError::InvalidCompressedData);
+    }
+
+    output.extend_from_slice(reader.bytes(length as usize)?);
+    Ok(())
+}
+
+// The codes of block type 1, see RFC 1951 $3.2.6.
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    for (symbol, length) in lengths.iter_mut().enumerate() {
+        *length = match symbol {
+            0..=143 => 8,
+            144..=255 => 9,
+            256..=279 => 7,
+            _ => 8
+        };
+    }
+
+    (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+// The codes of block type 2 are themselves Huffman coded, see RFC 1951 $3.2.7.
+fn dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), ArchiveError> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+
+    let mut code_length_lengths = [0u8; 19];
+    for &symbol in CODE_LENGTH_ORDER.iter().take(code_length_count) {
+        code_length_lengths[symbol] = reader.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_length_lengths);
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let (length, repeat) = match code_lengths.decode(reader)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths.last().ok_or(ArchiveError::InvalidCompressedData)?;
+                (previous, 3 + reader.bits(2)? as usize)
+            },
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize)
+        };
+
+        if lengths.len() + repeat > literal_count + distance_count {
+            return Err(ArchiveError::InvalidCompressedData);
+        }
+        lengths.extend(std::iter::repeat_n(length, repeat));
+    }
+
+    let literals = Huffman::new(&lengths[..literal_count]);
+    let distances = Huffman::new(&lengths[literal_count..]);
+    Ok((literals, distances))
+}
+
+fn inflate_codes(reader: &mut BitReader,
+                 output: &mut Vec<u8>,
+                 literals: &Huffman,
+                 distances: &Huffman) -> Result<(), ArchiveError> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+
+        if symbol < 256 {
+            output.push(symbol as u8);
+            continue;
+        }
+        if symbol == 256 {
+            return Ok(());
+        }
+
+        let index = symbol - 257;
+        if index >= LENGTH_BASE.len() {
+            return Err(ArchiveError::InvalidCompressedData);
+        }
+        let length = LENGTH_BASE[index] as usize + reader.bits(LENGTH_EXTRA[index] as usize)? as usize;
+
+        let index = distances.decode(reader)? as usize;
+        if index >= DISTANCE_BASE.len() {
+            return Err(ArchiveError::InvalidCompressedData);
+        }
+        let distance = DISTANCE_BASE[index] as usize + reader.bits(DISTANCE_EXTRA[index] as usize)? as usize;
+
+        if distance > output.len() {
+            return Err(ArchiveError::InvalidCompressedData);
+        }
+
+        // The copy may overlap the bytes it produces, so it has to be done one byte at a time.
+        let start = output.len() - distance;
+        for i in 0..length {
+            let byte = output[start + i];
+            output.push(byte);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn stored_block() {
+        let input = [0x01, 0x05, 0x00, 0xFA, 0xFF, b'h', b'e', b'l', b'l', b'o'];
+
+        assert_eq!(inflate(&input).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn fixed_codes_with_back_references() {
+        let input = [
+            0x78, 0x9C, 0xCB, 0x48, 0xCD, 0xC9, 0xC9, 0x57, 0xC8, 0x40, 0x90, 0x00,
+            0x3A, 0x2E, 0x06, 0x7D
+        ];
+
+        assert_eq!(inflate_zlib(&input).unwrap(), b"hello hello hello");
+    }
+
+    #[test]
+    fn dynamic_codes() {
+        let text: Vec<u8> = (0..2000).map(|i| b"abcdefghij"[(i * i + i / 7) % 10]).collect();
+        let input = include_bytes!("../../../fixtures/dynamic.deflate");
+
+        assert_eq!(inflate(input).unwrap(), text);
+    }
+
+    #[test]
+    fn truncated_input() {
+        let input = [0x78, 0x9C, 0xCB, 0x48, 0xCD];
+
+        match inflate_zlib(&input) {
+            Err(ArchiveError::Truncated) => {},
+            x => panic!("Expected truncated input, got {:?}", x)
+        }
+    }
+
+}
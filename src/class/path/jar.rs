@@ -0,0 +1,100 @@
+// A reader for JAR files: ZIP archives with class files stored by package, and a manifest in
+// META-INF/MANIFEST.MF.
+
+use std::io::{Read, Seek};
+
+use class::path::{ArchiveError, ClassSource};
+use class::path::zip::ZipArchive;
+
+const MANIFEST_NAME: &str = "META-INF/MANIFEST.MF";
+
+pub struct Jar<R> {
+    archive: ZipArchive<R>
+}
+
+impl<R: Read + Seek> Jar<R> {
+
+    pub fn open(reader: R) -> Result<Jar<R>, ArchiveError> {
+        let archive = ZipArchive::open(reader)?;
+        Ok(Jar { archive })
+    }
+
+    // The binary name of the class named by the Main-Class attribute of the manifest, e.g.
+    // "com.example.Main".
+    pub fn main_class(&mut self) -> Result<Option<String>, ArchiveError> {
+        let manifest = match self.archive.read(MANIFEST_NAME)? {
+            Some(manifest) => manifest,
+            None => return Ok(None)
+        };
+
+        Ok(main_attribute(&String::from_utf8_lossy(&manifest), "Main-Class"))
+    }
+
+}
+
+impl<R: Read + Seek> ClassSource for Jar<R> {
+    fn read_class(&mut self, class_name: &str) -> Result<Option<Vec<u8>>, ArchiveError> {
+        self.archive.read(&format!("{}.class", class_name))
+    }
+
+    fn has_package(&mut self, package: &str) -> bool {
+        let prefix = format!("{}/", package);
+        self.archive.names().any(|name| name.starts_with(&prefix) && !name[prefix.len()..].contains('/'))
+    }
+}
+
+// Finds an attribute in the main section of a manifest. Lines are limited to 72 bytes, so long
+// values continue on lines starting with a space.
+fn main_attribute(manifest: &str, name: &str) -> Option<String> {
+    let mut value: Option<String> = None;
+
+    for line in manifest.lines() {
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some(continuation) = line.strip_prefix(' ') {
+            if let Some(ref mut value) = value {
+                value.push_str(continuation);
+            }
+            continue;
+        }
+
+        if value.is_some() {
+            break;
+        }
+
+        if let Some(index) = line.find(':') {
+            if line[..index].eq_ignore_ascii_case(name) {
+                value = Some(String::from(line[index + 1..].trim_start()));
+            }
+        }
+    }
+
+    value
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn reads_the_main_class() {
+        let mut jar = Jar::open(Cursor::new(&include_bytes!("../../../fixtures/greetings.jar")[..])).unwrap();
+
+        assert_eq!(jar.main_class().unwrap(), Some(String::from("greetings.Main")));
+        assert!(jar.read_class("greetings/Greeter").unwrap().is_some());
+        assert!(jar.read_class("Greeter").unwrap().is_none());
+    }
+
+    #[test]
+    fn manifest_attributes() {
+        let manifest = "Manifest-Version: 1.0\r\nmain-class: com.example.app.Applicati\r\n on\r\nCreated-By: 17\r\n\r\nName: Main-Class\r\nMain-Class: Other\r\n";
+
+        assert_eq!(main_attribute(manifest, "Main-Class"), Some(String::from("com.example.app.Application")));
+        assert_eq!(main_attribute(manifest, "Class-Path"), None);
+    }
+
+}
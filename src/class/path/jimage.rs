@@ -0,0 +1,580 @@
+// A reader for jimage files, the format of a JDK's lib/modules. There is no specification; this
+// follows jdk.internal.jimage.BasicImageReader.
+//
+// The file starts with an index: a header, a perfect hash table mapping resource names to their
+// locations, the locations themselves and a table of strings. Resource contents follow the index.
+// Resources are named /module/parent/base.extension, e.g. /java.base/java/lang/Object.class.
+
+use std::io::{Read, Seek};
+
+use class::mutf8;
+use class::path::{inflate, read_at, ArchiveError, ClassSource};
+
+const IMAGE_MAGIC: u32 = 0xCAFEDADA;
+const MAJOR_VERSION: u32 = 1;
+const HEADER_SIZE: usize = 28;
+
+const HASH_MULTIPLIER: i32 = 0x01000193;
+
+const COMPRESSED_MAGIC: u32 = 0xCAFEFAFA;
+const COMPRESSED_HEADER_SIZE: usize = 29;
+
+const ATTRIBUTE_END: usize = 0;
+const ATTRIBUTE_MODULE: usize = 1;
+const ATTRIBUTE_PARENT: usize = 2;
+const ATTRIBUTE_BASE: usize = 3;
+const ATTRIBUTE_EXTENSION: usize = 4;
+const ATTRIBUTE_OFFSET: usize = 5;
+const ATTRIBUTE_COMPRESSED: usize = 6;
+const ATTRIBUTE_UNCOMPRESSED: usize = 7;
+const ATTRIBUTE_COUNT: usize = 8;
+
+// Images are written in the byte order of the platform that created them.
+#[derive(Clone, Copy, Debug)]
+enum ByteOrder {
+    Little,
+    Big
+}
+
+impl ByteOrder {
+
+    fn u32(self, bytes: &[u8], offset: usize) -> Result<u32, ArchiveError> {
+        let b = bytes.get(offset..offset + 4).ok_or(ArchiveError::Truncated)?;
+        let b = [b[0], b[1], b[2], b[3]];
+
+        Ok(match self {
+            ByteOrder::Little => u32::from_le_bytes(b),
+            ByteOrder::Big => u32::from_be_bytes(b)
+        })
+    }
+
+    fn u64(self, bytes: &[u8], offset: usize) -> Result<u64, ArchiveError> {
+        let first = self.u32(bytes, offset)? as u64;
+        let second = self.u32(bytes, offset + 4)? as u64;
+
+        Ok(match self {
+            ByteOrder::Little => second << 32 | first,
+            ByteOrder::Big => first << 32 | second
+        })
+    }
+
+}
+
+pub struct ImageReader<R> {
+    reader: R,
+    byte_order: ByteOrder,
+    redirect: Vec<i32>,
+    offsets: Vec<u32>,
+    locations: Vec<u8>,
+    strings: Vec<u8>,
+    // Resource offsets are relative to the end of the index.
+    index_size: u64
+}
+
+impl<R: Read + Seek> ImageReader<R> {
+
+    pub fn open(mut reader: R) -> Result<ImageReader<R>, ArchiveError> {
+        let header = read_at(&mut reader, 0, HEADER_SIZE)?;
+
+        let byte_order = if ByteOrder::Little.u32(&header, 0)? == IMAGE_MAGIC {
+            ByteOrder::Little
+        } else if ByteOrder::Big.u32(&header, 0)? == IMAGE_MAGIC {
+            ByteOrder::Big
+        } else {
+            return Err(ArchiveError::InvalidMagic(ByteOrder::Big.u32(&header, 0)?));
+        };
+
+        let version = byte_order.u32(&header, 4)?;
+        if version >> 16 != MAJOR_VERSION {
+            return Err(ArchiveError::UnsupportedVersion(version));
+        }
+
+        let table_length = byte_order.u32(&header, 16)? as usize;
+        let locations_size = byte_order.u32(&header, 20)? as usize;
+        let strings_size = byte_order.u32(&header, 24)? as usize;
+
+        let index_size = HEADER_SIZE + 8 * table_length + locations_size + strings_size;
+        let index = read_at(&mut reader, HEADER_SIZE as u64, index_size - HEADER_SIZE)?;
+
+        let mut redirect = Vec::with_capacity(table_length);
+        let mut offsets = Vec::with_capacity(table_length);
+        for i in 0..table_length {
+            redirect.push(byte_order.u32(&index, 4 * i)? as i32);
+            offsets.push(byte_order.u32(&index, 4 * (table_length + i))?);
+        }
+
+        let locations_start = 8 * table_length;
+        let strings_start = locations_start + locations_size;
+
+        Ok(ImageReader {
+            reader,
+            byte_order,
+            redirect,
+            offsets,
+            locations: index[locations_start..strings_start].to_vec(),
+            strings: index[strings_start..].to_vec(),
+            index_size: index_size as u64
+        })
+    }
+
+    pub fn contains(&self, name: &str) -> Result<bool, ArchiveError> {
+        Ok(self.find_location(name)?.is_some())
+    }
+
+    // Returns the uncompressed contents of the named resource.
+    pub fn read_resource(&mut self, name: &str) -> Result<Option<Vec<u8>>, ArchiveError> {
+        let location = match self.find_location(name)? {
+            Some(location) => location,
+            None => return Ok(None)
+        };
+
+        let offset = self.index_size + location[ATTRIBUTE_OFFSET];
+        let compressed_size = location[ATTRIBUTE_COMPRESSED];
+        let uncompressed_size = location[ATTRIBUTE_UNCOMPRESSED];
+
+        if compressed_size == 0 {
+            return Ok(Some(read_at(&mut self.reader, offset, uncompressed_size as usize)?));
+        }
+
+        let compressed = read_at(&mut self.reader, offset, compressed_size as usize)?;
+        let resource = self.decompress(compressed)?;
+        if resource.len() as u64 != uncompressed_size {
+            return Err(ArchiveError::Malformed("decompressed resource has the wrong size"));
+        }
+
+        Ok(Some(resource))
+    }
+
+    // Finds the module containing a package, given in internal form, e.g. "java/lang". The image
+    // has a /packages/java.lang resource listing the modules that declare the package.
+    pub fn package_module(&mut self, package: &str) -> Result<Option<String>, ArchiveError> {
+        let name = format!("/packages/{}", package.replace('/', "."));
+        let modules = match self.read_resource(&name)? {
+            Some(modules) => modules,
+            None => return Ok(None)
+        };
+
+        // The resource is a list of (is_empty, module name) pairs. A module may only declare a
+        // package as empty if it is split across modules, so the non-empty declaration wins.
+        for entry in modules.chunks(8) {
+            let is_empty = self.byte_order.u32(entry, 0)?;
+            let module_name = self.byte_order.u32(entry, 4)?;
+
+            if is_empty == 0 {
+                return Ok(Some(self.string(module_name as u64)?));
+            }
+        }
+
+        Ok(None)
+    }
+
+    // Looks up the name in the perfect hash table. The redirect table either gives the slot
+    // directly, encoded as a negative number, or a seed with which to rehash the name.
+    fn find_location(&self, name: &str) -> Result<Option<[u64; ATTRIBUTE_COUNT]>, ArchiveError> {
+        let length = self.redirect.len() as i32;
+        if length == 0 {
+            return Ok(None);
+        }
+
+        let index = match self.redirect[(hash(name, HASH_MULTIPLIER) % length) as usize] {
+            0 => return Ok(None),
+            redirect if redirect < 0 => -1 - redirect,
+            seed => hash(name, seed) % length
+        };
+
+        let offset = *self.offsets.get(index as usize).ok_or(ArchiveError::Malformed("invalid redirect"))?;
+        let location = self.decode_location(offset as usize)?;
+
+        // Names that are not in the image still hash to some slot, so the name has to be checked.
+        if self.location_name(&location)? == name {
+            Ok(Some(location))
+        } else {
+            Ok(None)
+        }
+    }
+
+    // Locations are a sequence of attributes, each a byte holding the kind and the length of the
+    // value, followed by the value in big-endian order.
+    fn decode_location(&self, mut offset: usize) -> Result<[u64; ATTRIBUTE_COUNT], ArchiveError> {
+        let mut attributes = [0; ATTRIBUTE_COUNT];
+
+        loop {
+            let byte = *self.locations.get(offset).ok_or(ArchiveError::Truncated)?;
+            let kind = (byte >> 3) as usize;
+            if kind == ATTRIBUTE_END {
+                return Ok(attributes);
+            }
+            if kind >= ATTRIBUTE_COUNT {
+                return Err(ArchiveError::Malformed("invalid location attribute"));
+            }
+
+            let length = (byte & 0x7) as usize + 1;
+            let value = self.locations
+                .get(offset + 1..offset + 1 + length)
+                .ok_or(ArchiveError::Truncated)?;
+            attributes[kind] = value.iter().fold(0, |value, &b| value << 8 | b as u64);
+
+            offset += 1 + length;
+        }
+    }
+
+    fn location_name(&self, location: &[u64; ATTRIBUTE_COUNT]) -> Result<String, ArchiveError> {
+        let mut name = String::new();
+
+        let module = self.string(location[ATTRIBUTE_MODULE])?;
+        if !module.is_empty() {
+            name.push('/');
+            name.push_str(&module);
+            name.push('/');
+        }
+
+        let parent = self.string(location[ATTRIBUTE_PARENT])?;
+        if !parent.is_empty() {
+            name.push_str(&parent);
+            name.push('/');
+        }
+
+        name.push_str(&self.string(location[ATTRIBUTE_BASE])?);
+
+        let extension = self.string(location[ATTRIBUTE_EXTENSION])?;
+        if !extension.is_empty() {
+            name.push('.');
+            name.push_str(&extension);
+        }
+
+        Ok(name)
+    }
+
+    // Strings are NUL terminated and encoded in modified UTF-8.
+    fn string(&self, offset: u64) -> Result<String, ArchiveError> {
+        let bytes = self.strings.get(offset as usize..).ok_or(ArchiveError::Truncated)?;
+        let length = bytes.iter().position(|&b| b == 0).ok_or(ArchiveError::Truncated)?;
+        let chars = mutf8::decode(&bytes[..length]).map_err(|_| ArchiveError::Malformed("invalid string"))?;
+
+        Ok(String::from_utf16_lossy(&chars))
+    }
+
+    // Compressed resources start with a header naming the plugin that compressed them. Plugins
+    // can be stacked, so this repeats until the content no longer starts with a header.
+    fn decompress(&self, mut resource: Vec<u8>) -> Result<Vec<u8>, ArchiveError> {
+        while resource.len() >= COMPRESSED_HEADER_SIZE && self.byte_order.u32(&resource, 0)? == COMPRESSED_MAGIC {
+            let compressed_size = self.byte_order.u64(&resource, 4)? as usize;
+            let uncompressed_size = self.byte_order.u64(&resource, 12)? as usize;
+            let decompressor = self.string(self.byte_order.u32(&resource, 20)? as u64)?;
+
+            let content = resource
+                .get(COMPRESSED_HEADER_SIZE..COMPRESSED_HEADER_SIZE + compressed_size)
+                .ok_or(ArchiveError::Truncated)?;
+
+            resource = match decompressor.as_ref() {
+                "zip" => inflate::inflate_zlib(content)?,
+                _ => return Err(ArchiveError::Unsupported(format!("{} compression", decompressor)))
+            };
+
+            if resource.len() != uncompressed_size {
+                return Err(ArchiveError::Malformed("decompressed resource has the wrong size"));
+            }
+        }
+
+        Ok(resource)
+    }
+
+}
+
+impl<R: Read + Seek> ClassSource for ImageReader<R> {
+    fn read_class(&mut self, class_name: &str) -> Result<Option<Vec<u8>>, ArchiveError> {
+        // Named modules cannot contain classes in the unnamed package.
+        let package = match class_name.rfind('/') {
+            Some(index) => &class_name[..index],
+            None => return Ok(None)
+        };
+
+        match self.package_module(package)? {
+            Some(module) => self.read_resource(&format!("/{}/{}.class", module, class_name)),
+            None => Ok(None)
+        }
+    }
+
+    fn has_package(&mut self, package: &str) -> bool {
+        self.package_module(package).ok().is_some_and(|module| module.is_some())
+    }
+}
+
+// The FNV-1a style hash of jdk.internal.jimage.ImageStringsReader, over the UTF-8 bytes.
+fn hash(name: &str, seed: i32) -> i32 {
+    let hash = name.bytes().fold(seed, |hash, b| hash.wrapping_mul(HASH_MULTIPLIER) ^ b as i32);
+    hash & 0x7FFFFFFF
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use class::reader;
+    use code::instruction::Instruction;
+    use runtime::class::RuntimeClass;
+    use std::collections::HashMap;
+    use std::env;
+    use std::fs::File;
+    use std::io::Cursor;
+    use std::path::Path;
+
+    // Writes an image the way jlink would, including the /packages resources. Resources are
+    // stored uncompressed unless their contents are given zlib compressed.
+    struct ImageWriter {
+        strings: Vec<u8>,
+        string_offsets: HashMap<String, u32>,
+        names: Vec<String>,
+        locations: Vec<u8>,
+        location_offsets: Vec<u32>,
+        content: Vec<u8>
+    }
+
+    impl ImageWriter {
+
+        fn new() -> ImageWriter {
+            let mut writer = ImageWriter {
+                strings: Vec::new(),
+                string_offsets: HashMap::new(),
+                names: Vec::new(),
+                locations: Vec::new(),
+                location_offsets: Vec::new(),
+                content: Vec::new()
+            };
+            writer.string("");
+            writer
+        }
+
+        fn string(&mut self, string: &str) -> u32 {
+            if let Some(&offset) = self.string_offsets.get(string) {
+                return offset;
+            }
+
+            let offset = self.strings.len() as u32;
+            self.strings.extend_from_slice(string.as_bytes());
+            self.strings.push(0);
+            self.string_offsets.insert(String::from(string), offset);
+            offset
+        }
+
+        fn add(&mut self, name: &str, content: &[u8], uncompressed_size: Option<usize>) {
+            let (module, rest) = name[1..].split_at(name[1..].find('/').unwrap());
+            let rest = &rest[1..];
+            let (parent, file) = match rest.rfind('/') {
+                Some(index) => (&rest[..index], &rest[index + 1..]),
+                None => ("", rest)
+            };
+            let (base, extension) = match file.rfind('.') {
+                Some(index) => (&file[..index], &file[index + 1..]),
+                None => (file, "")
+            };
+
+            let (compressed, uncompressed) = match uncompressed_size {
+                Some(size) => (content.len() as u64, size as u64),
+                None => (0, content.len() as u64)
+            };
+            let attributes = [
+                (ATTRIBUTE_MODULE, self.string(module) as u64),
+                (ATTRIBUTE_PARENT, self.string(parent) as u64),
+                (ATTRIBUTE_BASE, self.string(base) as u64),
+                (ATTRIBUTE_EXTENSION, self.string(extension) as u64),
+                (ATTRIBUTE_OFFSET, self.content.len() as u64),
+                (ATTRIBUTE_COMPRESSED, compressed),
+                (ATTRIBUTE_UNCOMPRESSED, uncompressed)
+            ];
+
+            self.location_offsets.push(self.locations.len() as u32);
+            for &(kind, value) in attributes.iter().filter(|&&(_, value)| value != 0) {
+                let bytes: Vec<u8> = value.to_be_bytes().iter().cloned().skip_while(|&b| b == 0).collect();
+                self.locations.push((kind << 3) as u8 | (bytes.len() - 1) as u8);
+                self.locations.extend(bytes);
+            }
+            self.locations.push(ATTRIBUTE_END as u8);
+
+            self.names.push(String::from(name));
+            self.content.extend_from_slice(content);
+        }
+
+        // Adds the class files and the /packages resource of each of their packages.
+        fn add_classes(&mut self, module: &str, classes: &[(&str, &[u8])]) {
+            let mut packages: Vec<&str> = classes.iter().map(|&(name, _)| &name[..name.rfind('/').unwrap()]).collect();
+            packages.dedup();
+
+            for &(name, bytes) in classes.iter() {
+                self.add(&format!("/{}/{}.class", module, name), bytes, None);
+            }
+
+            for package in packages {
+                let mut content = 0u32.to_le_bytes().to_vec();
+                content.extend(self.string(module).to_le_bytes().iter());
+                self.add(&format!("/packages/{}", package.replace('/', ".")), &content, None);
+            }
+        }
+
+        // Builds the perfect hash table like jdk.tools.jlink.internal.PerfectHashBuilder. Buckets
+        // with collisions are placed first, by searching for a seed that spreads their names
+        // over free slots.
+        fn write(self, table_length: usize) -> Vec<u8> {
+            let mut buckets: Vec<Vec<usize>> = vec![Vec::new(); table_length];
+            for (i, name) in self.names.iter().enumerate() {
+                buckets[(hash(name, HASH_MULTIPLIER) % table_length as i32) as usize].push(i);
+            }
+
+            let mut order: Vec<usize> = (0..table_length).collect();
+            order.sort_by_key(|&bucket| usize::MAX - buckets[bucket].len());
+
+            let mut redirect = vec![0i32; table_length];
+            let mut slots: Vec<Option<usize>> = vec![None; table_length];
+
+            for &bucket in order.iter().filter(|&&bucket| buckets[bucket].len() > 1) {
+                let mut seed = 1;
+                loop {
+                    let candidates: Vec<usize> = buckets[bucket].iter()
+                        .map(|&i| (hash(&self.names[i], seed) % table_length as i32) as usize)
+                        .collect();
+                    let mut unique = candidates.clone();
+                    unique.sort();
+                    unique.dedup();
+
+                    if unique.len() == candidates.len() && candidates.iter().all(|&slot| slots[slot].is_none()) {
+                        for (&i, &slot) in buckets[bucket].iter().zip(candidates.iter()) {
+                            slots[slot] = Some(i);
+                        }
+                        redirect[bucket] = seed;
+                        break;
+                    }
+                    seed += 1;
+                }
+            }
+
+            for &bucket in order.iter().filter(|&&bucket| buckets[bucket].len() == 1) {
+                let slot = slots.iter().position(|slot| slot.is_none()).unwrap();
+                slots[slot] = Some(buckets[bucket][0]);
+                redirect[bucket] = -1 - slot as i32;
+            }
+
+            let mut image = Vec::new();
+            for value in [IMAGE_MAGIC, MAJOR_VERSION << 16, 0, self.names.len() as u32, table_length as u32,
+                          self.locations.len() as u32, self.strings.len() as u32].iter() {
+                image.extend(value.to_le_bytes().iter());
+            }
+            for value in redirect.iter() {
+                image.extend(value.to_le_bytes().iter());
+            }
+            for slot in slots.iter() {
+                let offset = slot.map_or(0, |i| self.location_offsets[i]);
+                image.extend(offset.to_le_bytes().iter());
+            }
+            image.extend(self.locations.iter());
+            image.extend(self.strings.iter());
+            image.extend(self.content.iter());
+            image
+        }
+
+    }
+
+    fn greetings_image(table_length: usize) -> ImageReader<Cursor<Vec<u8>>> {
+        let mut writer = ImageWriter::new();
+        writer.add_classes("greetings", &[
+            ("greetings/Greeter", include_bytes!("../../../fixtures/greetings/greetings/Greeter.class")),
+            ("greetings/Greeting", include_bytes!("../../../fixtures/greetings/greetings/Greeting.class")),
+            ("greetings/Main", include_bytes!("../../../fixtures/greetings/greetings/Main.class"))
+        ]);
+        writer.add("/greetings/META-INF/README", b"hello hello hello", None);
+
+        ImageReader::open(Cursor::new(writer.write(table_length))).unwrap()
+    }
+
+    #[test]
+    fn hashes_like_the_jdk() {
+        assert_eq!(hash("", HASH_MULTIPLIER), 0x01000193);
+        assert_eq!(hash("/java.base/java/lang/Object.class", HASH_MULTIPLIER), 0x7B31F51F);
+    }
+
+    #[test]
+    fn reads_classes_by_package() {
+        let mut image = greetings_image(8);
+
+        let bytes = image.read_class("greetings/Main").unwrap().unwrap();
+        let class_file = reader::read_class_file(&bytes).unwrap();
+        assert_eq!(class_file.constant_pool.get_class_name(class_file.this_class).unwrap(), "greetings/Main");
+
+        assert_eq!(image.package_module("greetings").unwrap(), Some(String::from("greetings")));
+        assert!(image.read_class("greetings/Missing").unwrap().is_none());
+        assert!(image.read_class("java/lang/Object").unwrap().is_none());
+        assert!(image.read_class("Main").unwrap().is_none());
+    }
+
+    // With fewer slots than resources every lookup has to go through a seeded rehash.
+    #[test]
+    fn resolves_hash_collisions() {
+        let mut image = greetings_image(5);
+
+        for name in ["greetings/Greeter", "greetings/Greeting", "greetings/Main"].iter() {
+            assert!(image.read_class(name).unwrap().is_some(), "{} not found", name);
+        }
+        assert_eq!(image.read_resource("/greetings/META-INF/README").unwrap().unwrap(), b"hello hello hello");
+    }
+
+    #[test]
+    fn decompresses_zip_resources() {
+        let content = b"hello hello hello";
+        let zlib = [
+            0x78, 0x9C, 0xCB, 0x48, 0xCD, 0xC9, 0xC9, 0x57, 0xC8, 0x40, 0x90, 0x00,
+            0x3A, 0x2E, 0x06, 0x7D
+        ];
+
+        let mut writer = ImageWriter::new();
+        let mut compressed = COMPRESSED_MAGIC.to_le_bytes().to_vec();
+        compressed.extend((zlib.len() as u64).to_le_bytes().iter());
+        compressed.extend((content.len() as u64).to_le_bytes().iter());
+        compressed.extend(writer.string("zip").to_le_bytes().iter());
+        compressed.extend(0u32.to_le_bytes().iter());
+        compressed.push(1);
+        compressed.extend(zlib.iter());
+        writer.add("/greetings/META-INF/README", &compressed, Some(content.len()));
+
+        let mut image = ImageReader::open(Cursor::new(writer.write(3))).unwrap();
+        assert_eq!(image.read_resource("/greetings/META-INF/README").unwrap().unwrap(), content);
+    }
+
+    // The lib/modules image of the JDK in JAVA_HOME.
+    fn installed_image() -> ImageReader<File> {
+        let java_home = env::var("JAVA_HOME").expect("JAVA_HOME must point to an installed JDK");
+        let path = Path::new(&java_home).join("lib").join("modules");
+        ImageReader::open(File::open(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e))).unwrap()
+    }
+
+    // Reads java.base from the JDK in JAVA_HOME.
+    #[test]
+    #[ignore = "needs JAVA_HOME"]
+    fn reads_installed_jdk() {
+        let mut image = installed_image();
+        assert_eq!(image.package_module("java/lang").unwrap(), Some(String::from("java.base")));
+
+        for name in ["java/lang/Object", "java/lang/String", "java/util/HashMap"].iter() {
+            let bytes = image.read_class(name).unwrap().unwrap();
+            let class_file = reader::read_class_file(&bytes).unwrap();
+            assert_eq!(&class_file.constant_pool.get_class_name(class_file.this_class).unwrap(), name);
+        }
+    }
+
+    // A class loads only if all of its methods can be disassembled. Switches are common in
+    // java.base, e.g. Map$Entry deserializes its comparators, serializable lambdas, in a switch.
+    #[test]
+    #[ignore = "needs JAVA_HOME"]
+    fn loads_classes_with_switches_from_installed_jdk() {
+        let mut image = installed_image();
+
+        for name in ["java/util/Map$Entry", "java/lang/String", "java/lang/Long", "java/util/Formatter"].iter() {
+            let bytes = image.read_class(name).unwrap().unwrap();
+            let class = RuntimeClass::from_class_file(&reader::read_class_file(&bytes).unwrap(), None, Vec::new()).unwrap();
+            let has_switch = class.methods.iter()
+                .filter_map(|method| method.code.as_ref())
+                .flat_map(|code| code.instructions.iter())
+                .any(|tagged| matches!(tagged.instruction, Instruction::Tableswitch { .. } | Instruction::Lookupswitch { .. }));
+            assert!(has_switch, "{} has no switch", name);
+        }
+    }
+
+}
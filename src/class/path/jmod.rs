@@ -0,0 +1,93 @@
+// A reader for the .jmod files in a JDK's jmods directory. A jmod file is a ZIP archive preceded
+// by a four byte header, with class files stored under classes/.
+
+use std::io::{Read, Seek};
+
+use class::path::{read_at, ArchiveError, ClassSource};
+use class::path::zip::ZipArchive;
+
+const JMOD_MAGIC: u32 = 0x4A4D0100; // "JM" followed by the major and minor version, 1.0
+
+const CLASSES_PREFIX: &str = "classes/";
+const CLASS_SUFFIX: &str = ".class";
+
+pub struct Jmod<R> {
+    archive: ZipArchive<R>
+}
+
+impl<R: Read + Seek> Jmod<R> {
+
+    pub fn open(mut reader: R) -> Result<Jmod<R>, ArchiveError> {
+        let header = read_at(&mut reader, 0, 4)?;
+        let magic = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        if magic != JMOD_MAGIC {
+            return Err(ArchiveError::InvalidMagic(magic));
+        }
+
+        let archive = ZipArchive::open(reader)?;
+        Ok(Jmod { archive })
+    }
+
+    // The names of the classes in the module, including module-info.
+    pub fn class_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.archive.names()
+            .filter(|name| name.starts_with(CLASSES_PREFIX) && name.ends_with(CLASS_SUFFIX))
+            .map(|name| String::from(&name[CLASSES_PREFIX.len()..name.len() - CLASS_SUFFIX.len()]))
+            .collect();
+        names.sort();
+        names
+    }
+
+}
+
+impl<R: Read + Seek> ClassSource for Jmod<R> {
+    fn read_class(&mut self, class_name: &str) -> Result<Option<Vec<u8>>, ArchiveError> {
+        self.archive.read(&format!("{}{}{}", CLASSES_PREFIX, class_name, CLASS_SUFFIX))
+    }
+
+    fn has_package(&mut self, package: &str) -> bool {
+        let prefix = format!("{}{}/", CLASSES_PREFIX, package);
+        self.archive.names().any(|name| name.starts_with(&prefix) && !name[prefix.len()..].contains('/'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use class::reader;
+    use std::io::Cursor;
+
+    fn greetings() -> Jmod<Cursor<&'static [u8]>> {
+        Jmod::open(Cursor::new(&include_bytes!("../../../fixtures/greetings.jmod")[..])).unwrap()
+    }
+
+    #[test]
+    fn lists_classes() {
+        let names = greetings().class_names();
+
+        assert_eq!(names, vec!["greetings/Greeter", "greetings/Greeting", "greetings/Main", "module-info"]);
+    }
+
+    #[test]
+    fn reads_deflated_class_files() {
+        let mut jmod = greetings();
+        let bytes = jmod.read_class("greetings/Greeter").unwrap().unwrap();
+        let class_file = reader::read_class_file(&bytes).unwrap();
+
+        assert_eq!(class_file.constant_pool.get_class_name(class_file.this_class).unwrap(), "greetings/Greeter");
+        assert!(jmod.read_class("greetings/Missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_plain_zip_files() {
+        let bytes = &include_bytes!("../../../fixtures/greetings.jmod")[4..];
+
+        match Jmod::open(Cursor::new(bytes)) {
+            Err(ArchiveError::InvalidMagic(_)) => {},
+            Err(e) => panic!("Expected an invalid magic number, got {:?}", e),
+            Ok(_) => panic!("Expected an invalid magic number")
+        }
+    }
+
+}
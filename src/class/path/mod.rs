@@ -0,0 +1,171 @@
+// Locating class files by name, in directories and in the archives a JDK ships its class library
+// in: .jmod files and the jimage lib/modules file.
+
+pub mod inflate;
+pub mod jar;
+pub mod jimage;
+pub mod jmod;
+pub mod zip;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use class::path::jar::Jar;
+use class::path::jimage::ImageReader;
+use class::path::jmod::Jmod;
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    Io(io::Error),
+    InvalidMagic(u32),
+    UnsupportedVersion(u32),
+    Truncated,
+    Malformed(&'static str),
+    InvalidCompressedData,
+    Unsupported(String)
+}
+
+impl From<io::Error> for ArchiveError {
+    fn from(error: io::Error) -> ArchiveError {
+        ArchiveError::Io(error)
+    }
+}
+
+// Something class files can be read from. Class names are binary names in internal form, e.g.
+// "java/lang/Object".
+pub trait ClassSource {
+    fn read_class(&mut self, class_name: &str) -> Result<Option<Vec<u8>>, ArchiveError>;
+
+    // Whether the source has classes in a package, given in internal form, e.g. "java/util".
+    fn has_package(&mut self, package: &str) -> bool;
+}
+
+// The bytes of a class file and where they were found.
+pub struct ClassBytes {
+    pub bytes: Vec<u8>,
+    pub location: String
+}
+
+// An ordered list of class sources. The first source containing a class wins. The class path is
+// shared by all threads of the virtual machine, so its sources must be Send.
+#[derive(Default)]
+pub struct ClassPath {
+    sources: Vec<(String, Box<dyn ClassSource + Send>)>
+}
+
+impl ClassPath {
+
+    pub fn new() -> ClassPath {
+        ClassPath::default()
+    }
+
+    // Adds a source, described by its location for diagnostics such as -verbose:class.
+    pub fn add<S: ClassSource + Send + 'static>(&mut self, location: &str, source: S) {
+        self.sources.push((String::from(location), Box::new(source)));
+    }
+
+    // Adds the entries of a class path string, separated like the PATH environment variable.
+    // Entries are directories or JAR files. Like the java launcher, entries that do not exist
+    // are ignored.
+    pub fn add_entries(&mut self, class_path: &str) -> Result<(), ArchiveError> {
+        for entry in env::split_paths(class_path) {
+            let entry = if entry.as_os_str().is_empty() { PathBuf::from(".") } else { entry };
+            let location = entry.display().to_string();
+
+            if entry.is_dir() {
+                self.add(&location, ClassDirectory::new(entry));
+            } else if entry.is_file() {
+                self.add(&location, Jar::open(File::open(&entry)?)?);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Adds the java.base module of an installed JDK, read from its lib/modules image or, if the
+    // JDK has no image, from jmods/java.base.jmod.
+    pub fn add_java_home(&mut self, java_home: &Path) -> Result<(), ArchiveError> {
+        let image = java_home.join("lib").join("modules");
+        if image.is_file() {
+            let location = image.display().to_string();
+            self.add(&location, ImageReader::open(File::open(image)?)?);
+            return Ok(());
+        }
+
+        let jmod = java_home.join("jmods").join("java.base.jmod");
+        if jmod.is_file() {
+            let location = jmod.display().to_string();
+            self.add(&location, Jmod::open(File::open(jmod)?)?);
+            return Ok(());
+        }
+
+        let message = format!("{} contains neither lib/modules nor jmods/java.base.jmod", java_home.display());
+        Err(ArchiveError::Io(io::Error::new(io::ErrorKind::NotFound, message)))
+    }
+
+    pub fn read_class(&mut self, class_name: &str) -> Result<Option<ClassBytes>, ArchiveError> {
+        for &mut (ref location, ref mut source) in self.sources.iter_mut() {
+            if let Some(bytes) = source.read_class(class_name)? {
+                return Ok(Some(ClassBytes { bytes, location: location.clone() }));
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub fn has_package(&mut self, package: &str) -> bool {
+        self.sources.iter_mut().any(|&mut (_, ref mut source)| source.has_package(package))
+    }
+
+}
+
+// A directory of class files laid out by package, e.g. java/lang/Object.class.
+pub struct ClassDirectory {
+    root: PathBuf
+}
+
+impl ClassDirectory {
+
+    pub fn new<P: Into<PathBuf>>(root: P) -> ClassDirectory {
+        ClassDirectory { root: root.into() }
+    }
+
+}
+
+impl ClassSource for ClassDirectory {
+    fn read_class(&mut self, class_name: &str) -> Result<Option<Vec<u8>>, ArchiveError> {
+        match ::std::fs::read(self.root.join(format!("{}.class", class_name))) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(ArchiveError::Io(e))
+        }
+    }
+
+    fn has_package(&mut self, package: &str) -> bool {
+        self.root.join(package).is_dir()
+    }
+}
+
+fn read_u16_le(bytes: &[u8], offset: usize) -> Result<u16, ArchiveError> {
+    bytes.get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or(ArchiveError::Truncated)
+}
+
+fn read_u32_le(bytes: &[u8], offset: usize) -> Result<u32, ArchiveError> {
+    bytes.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or(ArchiveError::Truncated)
+}
+
+fn read_at<R: Read + Seek>(reader: &mut R, offset: u64, length: usize) -> Result<Vec<u8>, ArchiveError> {
+    let mut bytes = vec![0; length];
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.read_exact(&mut bytes).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => ArchiveError::Truncated,
+        _ => ArchiveError::Io(e)
+    })?;
+    Ok(bytes)
+}
@@ -0,0 +1,134 @@
+// A reader for ZIP archives (PKWARE APPNOTE.TXT). Only stored and deflated entries are supported,
+// and archives must not need the ZIP64 extensions.
+
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom};
+
+use class::path::{inflate, read_at, read_u16_le, read_u32_le, ArchiveError};
+
+const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x04034b50;
+const CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+
+const LOCAL_FILE_HEADER_SIZE: usize = 30;
+const CENTRAL_DIRECTORY_HEADER_SIZE: usize = 46;
+const END_OF_CENTRAL_DIRECTORY_SIZE: usize = 22;
+const MAX_COMMENT_SIZE: usize = 0xFFFF;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+
+#[derive(Debug)]
+struct ZipEntry {
+    method: u16,
+    compressed_size: u32,
+    uncompressed_size: u32,
+    local_header_offset: u32
+}
+
+pub struct ZipArchive<R> {
+    reader: R,
+    // Where the archive starts in the underlying file. This is non-zero when data has been
+    // prepended to the archive, like the header of a jmod file.
+    base: u64,
+    entries: HashMap<String, ZipEntry>
+}
+
+impl<R: Read + Seek> ZipArchive<R> {
+
+    // Reads the central directory, which is found through the end of central directory record
+    // at the end of the archive.
+    pub fn open(mut reader: R) -> Result<ZipArchive<R>, ArchiveError> {
+        let length = reader.seek(SeekFrom::End(0))?;
+        let tail_length = length.min((END_OF_CENTRAL_DIRECTORY_SIZE + MAX_COMMENT_SIZE) as u64);
+        let tail = read_at(&mut reader, length - tail_length, tail_length as usize)?;
+
+        let end = (0..tail.len().saturating_sub(END_OF_CENTRAL_DIRECTORY_SIZE - 1))
+            .rev()
+            .find(|&i| read_u32_le(&tail, i).ok() == Some(END_OF_CENTRAL_DIRECTORY_SIGNATURE))
+            .ok_or(ArchiveError::Malformed("missing end of central directory record"))?;
+
+        let entry_count = read_u16_le(&tail, end + 10)?;
+        let directory_size = read_u32_le(&tail, end + 12)?;
+        let directory_offset = read_u32_le(&tail, end + 16)?;
+        if entry_count == 0xFFFF || directory_offset == 0xFFFF_FFFF {
+            return Err(ArchiveError::Unsupported(String::from("ZIP64 archives")));
+        }
+
+        let end_position = length - tail_length + end as u64;
+        let base = end_position
+            .checked_sub(directory_size as u64 + directory_offset as u64)
+            .ok_or(ArchiveError::Malformed("central directory is out of bounds"))?;
+
+        let directory = read_at(&mut reader, base + directory_offset as u64, directory_size as usize)?;
+        let mut entries = HashMap::new();
+        let mut position = 0;
+
+        for _ in 0..entry_count {
+            if read_u32_le(&directory, position)? != CENTRAL_DIRECTORY_SIGNATURE {
+                return Err(ArchiveError::Malformed("invalid central directory header"));
+            }
+
+            let name_length = read_u16_le(&directory, position + 28)? as usize;
+            let extra_length = read_u16_le(&directory, position + 30)? as usize;
+            let comment_length = read_u16_le(&directory, position + 32)? as usize;
+
+            let name_start = position + CENTRAL_DIRECTORY_HEADER_SIZE;
+            let name = directory.get(name_start..name_start + name_length).ok_or(ArchiveError::Truncated)?;
+
+            let entry = ZipEntry {
+                method: read_u16_le(&directory, position + 10)?,
+                compressed_size: read_u32_le(&directory, position + 20)?,
+                uncompressed_size: read_u32_le(&directory, position + 24)?,
+                local_header_offset: read_u32_le(&directory, position + 42)?
+            };
+            entries.insert(String::from_utf8_lossy(name).into_owned(), entry);
+
+            position = name_start + name_length + extra_length + comment_length;
+        }
+
+        Ok(ZipArchive { reader, base, entries })
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.entries.keys()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    // Returns the uncompressed contents of the named entry.
+    pub fn read(&mut self, name: &str) -> Result<Option<Vec<u8>>, ArchiveError> {
+        let entry = match self.entries.get(name) {
+            Some(entry) => entry,
+            None => return Ok(None)
+        };
+
+        // The local header repeats the name, but its extra field may differ from the one in the
+        // central directory, so its lengths have to be read again.
+        let header_offset = self.base + entry.local_header_offset as u64;
+        let header = read_at(&mut self.reader, header_offset, LOCAL_FILE_HEADER_SIZE)?;
+        if read_u32_le(&header, 0)? != LOCAL_FILE_HEADER_SIGNATURE {
+            return Err(ArchiveError::Malformed("invalid local file header"));
+        }
+        let name_length = read_u16_le(&header, 26)? as u64;
+        let extra_length = read_u16_le(&header, 28)? as u64;
+
+        let data_offset = header_offset + LOCAL_FILE_HEADER_SIZE as u64 + name_length + extra_length;
+        let data = read_at(&mut self.reader, data_offset, entry.compressed_size as usize)?;
+
+        let contents = match entry.method {
+            METHOD_STORED => data,
+            METHOD_DEFLATED => inflate::inflate(&data)?,
+            method => return Err(ArchiveError::Unsupported(format!("compression method {}", method)))
+        };
+
+        if contents.len() != entry.uncompressed_size as usize {
+            return Err(ArchiveError::Malformed("entry size does not match the central directory"));
+        }
+
+        Ok(Some(contents))
+    }
+
+}
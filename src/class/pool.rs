@@ -0,0 +1,125 @@
+// Builds the constant pool of a class file being generated. Each entry is added once, and adding
+// an equal entry again gives back the index of the first one.
+
+use class::{ConstantPool, ConstantPoolEntry};
+use class::mutf8::JavaString;
+use std::collections::HashMap;
+
+pub struct ConstantPoolBuilder {
+    entries: Vec<ConstantPoolEntry>,
+    indices: HashMap<ConstantPoolEntry, u16>
+}
+
+impl Default for ConstantPoolBuilder {
+    fn default() -> ConstantPoolBuilder {
+        ConstantPoolBuilder::new()
+    }
+}
+
+impl ConstantPoolBuilder {
+
+    pub fn new() -> ConstantPoolBuilder {
+        ConstantPoolBuilder { entries: Vec::new(), indices: HashMap::new() }
+    }
+
+    // The logical index of the entry, which is added if the pool doesn't have it yet.
+    pub fn add(&mut self, entry: ConstantPoolEntry) -> u16 {
+        if let Some(&index) = self.indices.get(&entry) {
+            return index;
+        }
+
+        let index = self.entries.len() as u16 + 1;
+        self.entries.push(entry.clone());
+        // Longs and doubles take up two entries (JVMS §4.4.5).
+        if let ConstantPoolEntry::Long { .. } | ConstantPoolEntry::Double { .. } = entry {
+            self.entries.push(ConstantPoolEntry::Placeholder);
+        }
+        self.indices.insert(entry, index);
+        index
+    }
+
+    pub fn utf8(&mut self, string: &str) -> u16 {
+        self.add(ConstantPoolEntry::Utf8(JavaString::from(string)))
+    }
+
+    // A class in internal form, e.g. java/lang/Object, or the descriptor of an array class.
+    pub fn class(&mut self, name: &str) -> u16 {
+        let name_index = self.utf8(name);
+        self.add(ConstantPoolEntry::Class { name_index })
+    }
+
+    pub fn string(&mut self, chars: &[u16]) -> u16 {
+        let string_index = self.add(ConstantPoolEntry::Utf8(JavaString::from_utf16(chars.to_vec())));
+        self.add(ConstantPoolEntry::String { string_index })
+    }
+
+    pub fn integer(&mut self, value: i32) -> u16 {
+        self.add(ConstantPoolEntry::Integer { bytes: value as u32 })
+    }
+
+    pub fn float(&mut self, value: f32) -> u16 {
+        self.add(ConstantPoolEntry::Float { bytes: value.to_bits() })
+    }
+
+    pub fn long(&mut self, value: i64) -> u16 {
+        let bits = value as u64;
+        self.add(ConstantPoolEntry::Long { high_bytes: (bits >> 32) as u32, low_bytes: bits as u32 })
+    }
+
+    pub fn double(&mut self, value: f64) -> u16 {
+        let bits = value.to_bits();
+        self.add(ConstantPoolEntry::Double { high_bytes: (bits >> 32) as u32, low_bytes: bits as u32 })
+    }
+
+    pub fn name_and_type(&mut self, name: &str, descriptor: &str) -> u16 {
+        let name_index = self.utf8(name);
+        let descriptor_index = self.utf8(descriptor);
+        self.add(ConstantPoolEntry::NameAndType { name_index, descriptor_index })
+    }
+
+    pub fn field_ref(&mut self, class: &str, name: &str, descriptor: &str) -> u16 {
+        let class_index = self.class(class);
+        let name_and_type_index = self.name_and_type(name, descriptor);
+        self.add(ConstantPoolEntry::Fieldref { class_index, name_and_type_index })
+    }
+
+    pub fn method_ref(&mut self, class: &str, name: &str, descriptor: &str) -> u16 {
+        let class_index = self.class(class);
+        let name_and_type_index = self.name_and_type(name, descriptor);
+        self.add(ConstantPoolEntry::Methodref { class_index, name_and_type_index })
+    }
+
+    pub fn interface_method_ref(&mut self, class: &str, name: &str, descriptor: &str) -> u16 {
+        let class_index = self.class(class);
+        let name_and_type_index = self.name_and_type(name, descriptor);
+        self.add(ConstantPoolEntry::InterfaceMethodref { class_index, name_and_type_index })
+    }
+
+    pub fn build(self) -> ConstantPool {
+        ConstantPool { entries: self.entries }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn entries_are_shared() {
+        let mut pool = ConstantPoolBuilder::new();
+        let method = pool.method_ref("Counter", "set", "(II)I");
+        assert_eq!(pool.class("Counter"), 2);
+        assert_eq!(pool.method_ref("Counter", "set", "(II)I"), method);
+
+        let long = pool.long(-2);
+        assert_eq!(pool.utf8("next"), long + 2);
+
+        let constant_pool = pool.build();
+        assert_eq!(constant_pool.get_method_ref(method).unwrap().name_and_type.descriptor, "(II)I");
+        assert_eq!(constant_pool.get_long(long).unwrap(), -2);
+        assert_eq!(constant_pool.get(long + 1), Some(&ConstantPoolEntry::Placeholder));
+    }
+
+}
@@ -2,9 +2,11 @@ use class::ConstantPool;
 use class::ConstantPoolEntry;
 use class::Field;
 use class::Attribute;
+use class::AttributeInfo;
 use class::Method;
 use class::ExceptionTableEntry;
 use class::LineNumberTableEntry;
+use class::LocalVariableTableEntry;
 use class::ClassFile;
 use class::StackMapFrame;
 use class::VerificationTypeInfo;
@@ -12,36 +14,48 @@ use class::InnerClassTableEntry;
 use class::Annotation;
 use class::AnnotationElementPair;
 use class::AnnotationElementValue;
-
-const MAGIC_NUMBER: u32 = 0xCAFEBABE;
-
-const CONSTANT_METHODREF: u8 = 10;
-const CONSTANT_FIELDREF: u8 = 9;
-const CONSTANT_CLASS: u8 = 7;
-const CONSTANT_UTF8: u8 = 1;
-const CONSTANT_NAME_AND_TYPE: u8 = 12;
-const CONSTANT_STRING: u8 = 8;
-const CONSTANT_INTEGER: u8 = 3;
-const CONSTANT_INTERFACE_METHODREF: u8 = 11;
-const CONSTANT_LONG: u8 = 5;
-const CONSTANT_FLOAT: u8 = 4;
-
-const ATTRIBUTE_CODE: &str = "Code";
-const ATTRIBUTE_SOURCE_FILE: &str = "SourceFile";
-const ATTRIBUTE_LINE_NUMBER_TABLE: &str = "LineNumberTable";
-const ATTRIBUTE_SIGNATURE: &str = "Signature";
-const ATTRIBUTE_STACK_MAP_TABLE: &str = "StackMapTable";
-const ATTRIBUTE_EXCEPTIONS: &str = "Exceptions";
-const ATTRIBUTE_CONSTANT_VALUE: &str = "ConstantValue";
-const ATTRIBUTE_INNER_CLASSES: &str = "InnerClasses";
-const ATTRIBUTE_DEPRECATED: &str = "Deprecated";
-const ATTRIBUTE_RUNTIME_VISIBLE_ANNOTATIONS: &str = "RuntimeVisibleAnnotations";
+use class::BootstrapMethod;
+use class::mutf8::{self, JavaString};
+
+pub const MAGIC_NUMBER: u32 = 0xCAFEBABE;
+
+pub const CONSTANT_METHODREF: u8 = 10;
+pub const CONSTANT_FIELDREF: u8 = 9;
+pub const CONSTANT_CLASS: u8 = 7;
+pub const CONSTANT_UTF8: u8 = 1;
+pub const CONSTANT_NAME_AND_TYPE: u8 = 12;
+pub const CONSTANT_STRING: u8 = 8;
+pub const CONSTANT_INTEGER: u8 = 3;
+pub const CONSTANT_INTERFACE_METHODREF: u8 = 11;
+pub const CONSTANT_LONG: u8 = 5;
+pub const CONSTANT_FLOAT: u8 = 4;
+pub const CONSTANT_DOUBLE: u8 = 6;
+pub const CONSTANT_METHOD_HANDLE: u8 = 15;
+pub const CONSTANT_METHOD_TYPE: u8 = 16;
+pub const CONSTANT_DYNAMIC: u8 = 17;
+pub const CONSTANT_INVOKE_DYNAMIC: u8 = 18;
+pub const CONSTANT_MODULE: u8 = 19;
+pub const CONSTANT_PACKAGE: u8 = 20;
+
+pub const ATTRIBUTE_CODE: &str = "Code";
+pub const ATTRIBUTE_SOURCE_FILE: &str = "SourceFile";
+pub const ATTRIBUTE_LINE_NUMBER_TABLE: &str = "LineNumberTable";
+pub const ATTRIBUTE_LOCAL_VARIABLE_TABLE: &str = "LocalVariableTable";
+pub const ATTRIBUTE_SIGNATURE: &str = "Signature";
+pub const ATTRIBUTE_STACK_MAP_TABLE: &str = "StackMapTable";
+pub const ATTRIBUTE_EXCEPTIONS: &str = "Exceptions";
+pub const ATTRIBUTE_CONSTANT_VALUE: &str = "ConstantValue";
+pub const ATTRIBUTE_INNER_CLASSES: &str = "InnerClasses";
+pub const ATTRIBUTE_DEPRECATED: &str = "Deprecated";
+pub const ATTRIBUTE_RUNTIME_VISIBLE_ANNOTATIONS: &str = "RuntimeVisibleAnnotations";
+pub const ATTRIBUTE_ANNOTATION_DEFAULT: &str = "AnnotationDefault";
+pub const ATTRIBUTE_BOOTSTRAP_METHODS: &str = "BootstrapMethods";
 
 
 trait Decoder : Sized {
-    fn decode(buffer: &mut Vec<u8>, cp: &ConstantPool) -> Result<Self, ClassReaderError>;
+    fn decode(buffer: &mut &[u8], cp: &ConstantPool) -> Result<Self, ClassReaderError>;
 
-    fn decode_many(buffer: &mut Vec<u8>, length: usize, cp: &ConstantPool) -> Result<Vec<Self>, ClassReaderError> {
+    fn decode_many(buffer: &mut &[u8], length: usize, cp: &ConstantPool) -> Result<Vec<Self>, ClassReaderError> {
         let mut entries: Vec<Self> = Vec::new();
 
         for _index in 0..length {
@@ -53,7 +67,7 @@ trait Decoder : Sized {
     }
 }
 
-//fn dec<T: Decoder, R: Decoder>(buffer: &mut Vec<u8>, cp: &ConstantPool) -> Result<T, String> {
+//fn dec<T: Decoder, R: Decoder>(buffer: &mut &[u8], cp: &ConstantPool) -> Result<T, String> {
 //    let a = Decoder::decode(buffer, cp);
 //    let b: Result<R, String> = Decoder::decode(buffer, cp);
 //    a
@@ -73,7 +87,8 @@ pub enum ClassReaderError {
     InvalidAnnotationElementValue(char)
 }
 
-pub fn read_class_file(buffer: &mut Vec<u8>) -> Result<ClassFile, ClassReaderError> {
+pub fn read_class_file(bytes: &[u8]) -> Result<ClassFile, ClassReaderError> {
+    let buffer = &mut &bytes[..];
     let magic = read_magic(buffer)?;
     let minor_version = read_u16(buffer)?;
     let major_version = read_u16(buffer)?;
@@ -106,14 +121,14 @@ pub fn read_class_file(buffer: &mut Vec<u8>) -> Result<ClassFile, ClassReaderErr
         attributes
     };
 
-    if buffer.len() == 0 {
+    if buffer.is_empty() {
         Ok(class_file)
     } else {
         Err(ClassReaderError::RemainingBytes)
     }
 }
 
-fn read_magic(buffer: &mut Vec<u8>) -> Result<u32, ClassReaderError> {
+fn read_magic(buffer: &mut &[u8]) -> Result<u32, ClassReaderError> {
     let magic = read_u32(buffer)?;
 
     if magic == MAGIC_NUMBER {
@@ -123,7 +138,7 @@ fn read_magic(buffer: &mut Vec<u8>) -> Result<u32, ClassReaderError> {
     }
 }
 
-fn read_constant_pool_entries(buffer: &mut Vec<u8>, length: u16) -> Result<Vec<ConstantPoolEntry>, ClassReaderError> {
+fn read_constant_pool_entries(buffer: &mut &[u8], length: u16) -> Result<Vec<ConstantPoolEntry>, ClassReaderError> {
     let mut entries: Vec<ConstantPoolEntry> = Vec::new();
 
     let mut index = 0;
@@ -134,7 +149,7 @@ fn read_constant_pool_entries(buffer: &mut Vec<u8>, length: u16) -> Result<Vec<C
         // All 8-byte constants (longs and doubles) consume two entries in the constant pool table.
         // Therefore we must increment the counter twice once we see a long or double.
         match entry {
-            ConstantPoolEntry::Long { .. } => {
+            ConstantPoolEntry::Long { .. } | ConstantPoolEntry::Double { .. } => {
                 entries.push(ConstantPoolEntry::Placeholder);
                 index += 2;
             },
@@ -151,7 +166,7 @@ fn read_constant_pool_entries(buffer: &mut Vec<u8>, length: u16) -> Result<Vec<C
     Ok(entries)
 }
 
-fn read_constant_pool_entry(buffer: &mut Vec<u8>) -> Result<ConstantPoolEntry, ClassReaderError> {
+fn read_constant_pool_entry(buffer: &mut &[u8]) -> Result<ConstantPoolEntry, ClassReaderError> {
     let tag = read_u8(buffer)?;
 
     match tag {
@@ -211,12 +226,51 @@ fn read_constant_pool_entry(buffer: &mut Vec<u8>) -> Result<ConstantPoolEntry, C
 
             Ok(ConstantPoolEntry::Long { high_bytes, low_bytes })
         },
+        CONSTANT_DOUBLE => {
+            let high_bytes = read_u32(buffer)?;
+            let low_bytes = read_u32(buffer)?;
+
+            Ok(ConstantPoolEntry::Double { high_bytes, low_bytes })
+        },
+        CONSTANT_METHOD_HANDLE => {
+            let reference_kind = read_u8(buffer)?;
+            let reference_index = read_u16(buffer)?;
+
+            Ok(ConstantPoolEntry::MethodHandle { reference_kind, reference_index })
+        },
+        CONSTANT_METHOD_TYPE => {
+            let descriptor_index = read_u16(buffer)?;
+
+            Ok(ConstantPoolEntry::MethodType { descriptor_index })
+        },
+        CONSTANT_DYNAMIC => {
+            let bootstrap_method_attr_index = read_u16(buffer)?;
+            let name_and_type_index = read_u16(buffer)?;
+
+            Ok(ConstantPoolEntry::Dynamic { bootstrap_method_attr_index, name_and_type_index })
+        },
+        CONSTANT_INVOKE_DYNAMIC => {
+            let bootstrap_method_attr_index = read_u16(buffer)?;
+            let name_and_type_index = read_u16(buffer)?;
+
+            Ok(ConstantPoolEntry::InvokeDynamic { bootstrap_method_attr_index, name_and_type_index })
+        },
+        CONSTANT_MODULE => {
+            let name_index = read_u16(buffer)?;
+
+            Ok(ConstantPoolEntry::Module { name_index })
+        },
+        CONSTANT_PACKAGE => {
+            let name_index = read_u16(buffer)?;
+
+            Ok(ConstantPoolEntry::Package { name_index })
+        },
         x => Err(ClassReaderError::InvalidConstantTag(x))
     }
 }
 
 impl Decoder for Field {
-    fn decode(buffer: &mut Vec<u8>, cp: &ConstantPool) -> Result<Self, ClassReaderError> {
+    fn decode(buffer: &mut &[u8], cp: &ConstantPool) -> Result<Self, ClassReaderError> {
         let access_flags = read_u16(buffer)?;
         let name_index = read_u16(buffer)?;
         let descriptor_index = read_u16(buffer)?;
@@ -230,7 +284,7 @@ impl Decoder for Field {
 }
 
 impl Decoder for Method {
-    fn decode(buffer: &mut Vec<u8>, cp: &ConstantPool) -> Result<Self, ClassReaderError> {
+    fn decode(buffer: &mut &[u8], cp: &ConstantPool) -> Result<Self, ClassReaderError> {
         let access_flags = read_u16(buffer)?;
         let name_index = read_u16(buffer)?;
         let descriptor_index = read_u16(buffer)?;
@@ -244,10 +298,10 @@ impl Decoder for Method {
 }
 
 impl Decoder for Attribute {
-    fn decode(buffer: &mut Vec<u8>, cp: &ConstantPool) -> Result<Self, ClassReaderError> {
+    fn decode(buffer: &mut &[u8], cp: &ConstantPool) -> Result<Self, ClassReaderError> {
         let attribute_name_index = read_u16(buffer)?;
         let attribute_length = read_u32(buffer)?;
-        let ref mut attribute_buffer = read_bytes(buffer, attribute_length as usize)?;
+        let attribute_buffer = &mut read_slice(buffer, attribute_length as usize)?;
 
         let attribute_name = cp.get_utf8(attribute_name_index)
             .map_err(|x| ClassReaderError::ExpectedAttributeName)?;
@@ -277,6 +331,12 @@ impl Decoder for Attribute {
 
                 Some(Attribute::LineNumberTable(line_number_table_entries))
             },
+            ATTRIBUTE_LOCAL_VARIABLE_TABLE => {
+                let local_variable_table_length = read_u16(attribute_buffer)?;
+                let local_variable_table_entries = LocalVariableTableEntry::decode_many(attribute_buffer, local_variable_table_length as usize, cp)?;
+
+                Some(Attribute::LocalVariableTable(local_variable_table_entries))
+            },
             ATTRIBUTE_SOURCE_FILE => {
                 let index = read_u16(attribute_buffer)?;
 
@@ -311,7 +371,24 @@ impl Decoder for Attribute {
 
                 Some(Attribute::RuntimeVisibleAnnotations { annotations })
             },
-            _ => None
+            ATTRIBUTE_ANNOTATION_DEFAULT => {
+                let default_value = AnnotationElementValue::decode(attribute_buffer, cp)?;
+
+                Some(Attribute::AnnotationDefault { default_value })
+            },
+            ATTRIBUTE_BOOTSTRAP_METHODS => {
+                let num_bootstrap_methods = read_u16(attribute_buffer)?;
+                let methods = BootstrapMethod::decode_many(attribute_buffer, num_bootstrap_methods as usize, cp)?;
+
+                Some(Attribute::BootstrapMethods { methods })
+            },
+            // Attributes we do not recognize are kept as raw bytes, since JVMS $4.7.1 requires
+            // them to be ignored rather than rejected.
+            _ => {
+                let bytes = read_bytes(attribute_buffer, attribute_length as usize)?;
+
+                Some(Attribute::Unrecognized(AttributeInfo { attribute_name_index, bytes }))
+            }
         };
 
         match attribute_option {
@@ -329,7 +406,7 @@ impl Decoder for Attribute {
 }
 
 impl Decoder for Annotation {
-    fn decode(buffer: &mut Vec<u8>, cp: &ConstantPool) -> Result<Self, ClassReaderError> {
+    fn decode(buffer: &mut &[u8], cp: &ConstantPool) -> Result<Self, ClassReaderError> {
         let type_index = read_u16(buffer)?;
         let length = read_u16(buffer)?;
         let elements = AnnotationElementPair::decode_many(buffer, length as usize, cp)?;
@@ -339,13 +416,13 @@ impl Decoder for Annotation {
 }
 
 impl Decoder for AnnotationElementValue {
-    fn decode(buffer: &mut Vec<u8>, cp: &ConstantPool) -> Result<Self, ClassReaderError> {
+    fn decode(buffer: &mut &[u8], cp: &ConstantPool) -> Result<Self, ClassReaderError> {
         let tag = read_u8(buffer)? as char;
 
         match tag {
             'B' | 'C' | 'D' | 'F' | 'I' | 'J' | 'S' | 'Z' | 's' => {
-                let value = read_u16(buffer)?;
-                Ok(AnnotationElementValue::Const(value))
+                let const_value_index = read_u16(buffer)?;
+                Ok(AnnotationElementValue::Const { tag, const_value_index })
             },
             'e' => {
                 let type_name_index = read_u16(buffer)?;
@@ -371,7 +448,7 @@ impl Decoder for AnnotationElementValue {
 }
 
 impl Decoder for AnnotationElementPair {
-    fn decode(buffer: &mut Vec<u8>, cp: &ConstantPool) -> Result<Self, ClassReaderError> {
+    fn decode(buffer: &mut &[u8], cp: &ConstantPool) -> Result<Self, ClassReaderError> {
         let element_name_index = read_u16(buffer)?;
         let element_value = AnnotationElementValue::decode(buffer, cp)?;
 
@@ -379,8 +456,18 @@ impl Decoder for AnnotationElementPair {
     }
 }
 
+impl Decoder for BootstrapMethod {
+    fn decode(buffer: &mut &[u8], _cp: &ConstantPool) -> Result<Self, ClassReaderError> {
+        let bootstrap_method_ref = read_u16(buffer)?;
+        let num_bootstrap_arguments = read_u16(buffer)?;
+        let bootstrap_arguments = read_u16_array(buffer, num_bootstrap_arguments)?;
+
+        Ok(BootstrapMethod { bootstrap_method_ref, bootstrap_arguments })
+    }
+}
+
 impl Decoder for InnerClassTableEntry {
-    fn decode(buffer: &mut Vec<u8>, cp: &ConstantPool) -> Result<Self, ClassReaderError> {
+    fn decode(buffer: &mut &[u8], _cp: &ConstantPool) -> Result<Self, ClassReaderError> {
         let inner_class_info_index = read_u16(buffer)?;
         let outer_class_info_index = read_u16(buffer)?;
         let inner_name_index = read_u16(buffer)?;
@@ -396,7 +483,7 @@ impl Decoder for InnerClassTableEntry {
 }
 
 impl Decoder for ExceptionTableEntry {
-    fn decode(buffer: &mut Vec<u8>, cp: &ConstantPool) -> Result<Self, ClassReaderError> {
+    fn decode(buffer: &mut &[u8], _cp: &ConstantPool) -> Result<Self, ClassReaderError> {
         let start_pc = read_u16(buffer)?;
         let end_pc = read_u16(buffer)?;
         let handler_pc = read_u16(buffer)?;
@@ -407,22 +494,23 @@ impl Decoder for ExceptionTableEntry {
 }
 
 impl Decoder for StackMapFrame {
-    fn decode(buffer: &mut Vec<u8>, cp: &ConstantPool) -> Result<Self, ClassReaderError> {
+    fn decode(buffer: &mut &[u8], cp: &ConstantPool) -> Result<Self, ClassReaderError> {
         let frame_type = read_u8(buffer)?;
 
         match frame_type {
-            0..=63 => Ok(StackMapFrame::SameFrame),
+            0..=63 => Ok(StackMapFrame::SameFrame { offset_delta: frame_type }),
             64..=127 => {
                 let info = VerificationTypeInfo::decode(buffer, cp)?;
-                Ok(StackMapFrame::SameLocals1StackItemFrame { info })
+                Ok(StackMapFrame::SameLocals1StackItemFrame { offset_delta: frame_type - 64, info })
             },
             247 => {
+                let offset_delta = read_u16(buffer)?;
                 let info = VerificationTypeInfo::decode(buffer, cp)?;
-                Ok(StackMapFrame::SameLocals1StackItemFrameExtended { info })
+                Ok(StackMapFrame::SameLocals1StackItemFrameExtended { offset_delta, info })
             },
             248..=250 => {
                 let offset_delta = read_u16(buffer)?;
-                Ok(StackMapFrame::ChopFrame { offset_delta })
+                Ok(StackMapFrame::ChopFrame { offset_delta, chopped: 251 - frame_type })
             },
             251 => {
                 let offset_delta = read_u16(buffer)?;
@@ -447,7 +535,7 @@ impl Decoder for StackMapFrame {
 }
 
 impl Decoder for VerificationTypeInfo {
-    fn decode(buffer: &mut Vec<u8>, cp: &ConstantPool) -> Result<Self, ClassReaderError> {
+    fn decode(buffer: &mut &[u8], _cp: &ConstantPool) -> Result<Self, ClassReaderError> {
         let tag = read_u8(buffer)?;
 
         match tag {
@@ -472,7 +560,7 @@ impl Decoder for VerificationTypeInfo {
 }
 
 impl Decoder for LineNumberTableEntry {
-    fn decode(buffer: &mut Vec<u8>, cp: &ConstantPool) -> Result<Self, ClassReaderError> {
+    fn decode(buffer: &mut &[u8], _cp: &ConstantPool) -> Result<Self, ClassReaderError> {
         let start_pc = read_u16(buffer)?;
         let line_number = read_u16(buffer)?;
 
@@ -480,24 +568,36 @@ impl Decoder for LineNumberTableEntry {
     }
 }
 
-fn read_u8(buffer: &mut Vec<u8>) -> Result<u8, ClassReaderError> {
-    match buffer.get(0) {
-        Some(&byte) => {
-            buffer.remove(0);
+impl Decoder for LocalVariableTableEntry {
+    fn decode(buffer: &mut &[u8], _cp: &ConstantPool) -> Result<Self, ClassReaderError> {
+        let start_pc = read_u16(buffer)?;
+        let length = read_u16(buffer)?;
+        let name_index = read_u16(buffer)?;
+        let descriptor_index = read_u16(buffer)?;
+        let index = read_u16(buffer)?;
+
+        Ok(LocalVariableTableEntry { start_pc, length, name_index, descriptor_index, index })
+    }
+}
+
+fn read_u8(buffer: &mut &[u8]) -> Result<u8, ClassReaderError> {
+    match buffer.split_first() {
+        Some((&byte, rest)) => {
+            *buffer = rest;
             Ok(byte)
         },
         None => Err(ClassReaderError::EndOfStream)
     }
 }
 
-fn read_u16(buffer: &mut Vec<u8>) -> Result<u16, ClassReaderError> {
+fn read_u16(buffer: &mut &[u8]) -> Result<u16, ClassReaderError> {
     let b1 = read_u8(buffer)? as u16;
     let b2 = read_u8(buffer)? as u16;
 
     Ok((b1 << 8) + b2)
 }
 
-fn read_u16_array(buffer: &mut Vec<u8>, length: u16) -> Result<Vec<u16>, ClassReaderError> {
+fn read_u16_array(buffer: &mut &[u8], length: u16) -> Result<Vec<u16>, ClassReaderError> {
     let mut entries: Vec<u16> = Vec::new();
 
     for _index in 0..length {
@@ -508,7 +608,7 @@ fn read_u16_array(buffer: &mut Vec<u8>, length: u16) -> Result<Vec<u16>, ClassRe
     Ok(entries)
 }
 
-fn read_u32(buffer: &mut Vec<u8>) -> Result<u32, ClassReaderError> {
+fn read_u32(buffer: &mut &[u8]) -> Result<u32, ClassReaderError> {
     let b1 = read_u8(buffer)? as u32;
     let b2 = read_u8(buffer)? as u32;
     let b3 = read_u8(buffer)? as u32;
@@ -517,26 +617,62 @@ fn read_u32(buffer: &mut Vec<u8>) -> Result<u32, ClassReaderError> {
     Ok((b1 << 24) + (b2 << 16) + (b3 << 8) + b4)
 }
 
-fn read_utf8(buffer: &mut Vec<u8>, length: usize) -> Result<String, ClassReaderError> {
+fn read_utf8(buffer: &mut &[u8], length: usize) -> Result<JavaString, ClassReaderError> {
     let bytes = read_bytes(buffer, length)?;
-    let string = String::from_utf8(bytes)
-        .map_err(|x| ClassReaderError::InvalidUtf8)?;
+    let chars = mutf8::decode(&bytes)
+        .map_err(|_| ClassReaderError::InvalidUtf8)?;
+
+    Ok(JavaString::from_utf16(chars))
+}
 
-    Ok(string)
+fn read_bytes(buffer: &mut &[u8], length: usize) -> Result<Vec<u8>, ClassReaderError> {
+    read_slice(buffer, length).map(|bytes| bytes.to_vec())
 }
 
-fn read_bytes(buffer: &mut Vec<u8>, length: usize) -> Result<Vec<u8>, ClassReaderError> {
+fn read_slice<'a>(buffer: &mut &'a [u8], length: usize) -> Result<&'a [u8], ClassReaderError> {
     if buffer.len() < length {
-        panic!("Buffer too small.");
         Err(ClassReaderError::EndOfStream)
     } else {
-        let mut bytes: Vec<u8> = Vec::new();
+        let (bytes, rest) = buffer.split_at(length);
+        *buffer = rest;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
 
-        for index in 0..length {
-            let b = read_u8(buffer)?;
-            bytes.push(b);
+    use super::*;
+
+    #[test]
+    fn utf8_constant_with_encoded_null() {
+        let buffer = [CONSTANT_UTF8, 0x00, 0x04, 0x61, 0xC0, 0x80, 0x62];
+
+        match read_constant_pool_entry(&mut &buffer[..]).unwrap() {
+            ConstantPoolEntry::Utf8(string) => assert_eq!(string.as_utf16(), &[0x61, 0x00, 0x62]),
+            x => panic!("expected Utf8, found {:?}", x)
         }
+    }
 
-        Ok(bytes)
+    #[test]
+    fn utf8_constant_with_lone_surrogate() {
+        let buffer = [CONSTANT_UTF8, 0x00, 0x03, 0xED, 0xA0, 0x80];
+
+        match read_constant_pool_entry(&mut &buffer[..]).unwrap() {
+            ConstantPoolEntry::Utf8(string) => assert_eq!(string.as_utf16(), &[0xD800]),
+            x => panic!("expected Utf8, found {:?}", x)
+        }
     }
+
+    #[test]
+    fn utf8_constant_in_standard_utf8_is_rejected() {
+        // A four byte UTF-8 sequence is never valid modified UTF-8.
+        let buffer = [CONSTANT_UTF8, 0x00, 0x04, 0xF0, 0x9F, 0x98, 0x80];
+
+        match read_constant_pool_entry(&mut &buffer[..]) {
+            Err(ClassReaderError::InvalidUtf8) => {},
+            x => panic!("expected InvalidUtf8, found {:?}", x)
+        }
+    }
+
 }
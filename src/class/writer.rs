@@ -0,0 +1,438 @@
+// Encodes a ClassFile into the bytes of a class file, the inverse of the reader. Attributes are
+// named by Utf8 entries, which must already be in the constant pool.
+
+use class::{Annotation, AnnotationElementValue, Attribute, ClassFile, ConstantPool, ConstantPoolEntry, Field, Method, StackMapFrame,
+            VerificationTypeInfo};
+use class::mutf8::JavaString;
+use class::reader::*;
+
+#[derive(Debug, PartialEq)]
+pub enum ClassWriterError {
+    // The constant pool has no Utf8 entry with the name of an attribute.
+    MissingAttributeName(String),
+    // The attribute is only recognized by the reader, which doesn't keep its contents.
+    UnsupportedAttribute(String)
+}
+
+pub fn write_class_file(class_file: &ClassFile) -> Result<Vec<u8>, ClassWriterError> {
+    let mut buffer = Vec::new();
+    write_u32(&mut buffer, class_file.magic);
+    write_u16(&mut buffer, class_file.minor_version);
+    write_u16(&mut buffer, class_file.major_version);
+    write_constant_pool(&mut buffer, &class_file.constant_pool);
+    write_u16(&mut buffer, class_file.access_flags);
+    write_u16(&mut buffer, class_file.this_class);
+    write_u16(&mut buffer, class_file.super_class);
+    write_u16(&mut buffer, class_file.interfaces.len() as u16);
+    for &interface in class_file.interfaces.iter() {
+        write_u16(&mut buffer, interface);
+    }
+
+    let cp = &class_file.constant_pool;
+    write_u16(&mut buffer, class_file.fields.len() as u16);
+    for field in class_file.fields.iter() {
+        field.encode(&mut buffer, cp)?;
+    }
+    write_u16(&mut buffer, class_file.methods.len() as u16);
+    for method in class_file.methods.iter() {
+        method.encode(&mut buffer, cp)?;
+    }
+    write_attributes(&mut buffer, &class_file.attributes, cp)?;
+
+    Ok(buffer)
+}
+
+trait Encoder {
+    fn encode(&self, buffer: &mut Vec<u8>, cp: &ConstantPool) -> Result<(), ClassWriterError>;
+}
+
+fn write_constant_pool(buffer: &mut Vec<u8>, cp: &ConstantPool) {
+    write_u16(buffer, cp.size() as u16 + 1);
+
+    for entry in cp.entries.iter() {
+        match *entry {
+            ConstantPoolEntry::Class { name_index } => {
+                buffer.push(CONSTANT_CLASS);
+                write_u16(buffer, name_index);
+            },
+            ConstantPoolEntry::Fieldref { class_index, name_and_type_index } => {
+                buffer.push(CONSTANT_FIELDREF);
+                write_u16(buffer, class_index);
+                write_u16(buffer, name_and_type_index);
+            },
+            ConstantPoolEntry::Methodref { class_index, name_and_type_index } => {
+                buffer.push(CONSTANT_METHODREF);
+                write_u16(buffer, class_index);
+                write_u16(buffer, name_and_type_index);
+            },
+            ConstantPoolEntry::InterfaceMethodref { class_index, name_and_type_index } => {
+                buffer.push(CONSTANT_INTERFACE_METHODREF);
+                write_u16(buffer, class_index);
+                write_u16(buffer, name_and_type_index);
+            },
+            ConstantPoolEntry::String { string_index } => {
+                buffer.push(CONSTANT_STRING);
+                write_u16(buffer, string_index);
+            },
+            ConstantPoolEntry::Integer { bytes } => {
+                buffer.push(CONSTANT_INTEGER);
+                write_u32(buffer, bytes);
+            },
+            ConstantPoolEntry::Float { bytes } => {
+                buffer.push(CONSTANT_FLOAT);
+                write_u32(buffer, bytes);
+            },
+            ConstantPoolEntry::Long { high_bytes, low_bytes } => {
+                buffer.push(CONSTANT_LONG);
+                write_u32(buffer, high_bytes);
+                write_u32(buffer, low_bytes);
+            },
+            ConstantPoolEntry::Double { high_bytes, low_bytes } => {
+                buffer.push(CONSTANT_DOUBLE);
+                write_u32(buffer, high_bytes);
+                write_u32(buffer, low_bytes);
+            },
+            ConstantPoolEntry::NameAndType { name_index, descriptor_index } => {
+                buffer.push(CONSTANT_NAME_AND_TYPE);
+                write_u16(buffer, name_index);
+                write_u16(buffer, descriptor_index);
+            },
+            ConstantPoolEntry::Utf8(ref string) => {
+                let bytes = string.to_modified_utf8();
+                buffer.push(CONSTANT_UTF8);
+                write_u16(buffer, bytes.len() as u16);
+                buffer.extend(bytes);
+            },
+            ConstantPoolEntry::MethodHandle { reference_kind, reference_index } => {
+                buffer.push(CONSTANT_METHOD_HANDLE);
+                buffer.push(reference_kind);
+                write_u16(buffer, reference_index);
+            },
+            ConstantPoolEntry::MethodType { descriptor_index } => {
+                buffer.push(CONSTANT_METHOD_TYPE);
+                write_u16(buffer, descriptor_index);
+            },
+            ConstantPoolEntry::Dynamic { bootstrap_method_attr_index, name_and_type_index } => {
+                buffer.push(CONSTANT_DYNAMIC);
+                write_u16(buffer, bootstrap_method_attr_index);
+                write_u16(buffer, name_and_type_index);
+            },
+            ConstantPoolEntry::InvokeDynamic { bootstrap_method_attr_index, name_and_type_index } => {
+                buffer.push(CONSTANT_INVOKE_DYNAMIC);
+                write_u16(buffer, bootstrap_method_attr_index);
+                write_u16(buffer, name_and_type_index);
+            },
+            ConstantPoolEntry::Module { name_index } => {
+                buffer.push(CONSTANT_MODULE);
+                write_u16(buffer, name_index);
+            },
+            ConstantPoolEntry::Package { name_index } => {
+                buffer.push(CONSTANT_PACKAGE);
+                write_u16(buffer, name_index);
+            },
+            // The second entry taken by a long or double isn't written.
+            ConstantPoolEntry::Placeholder => {}
+        }
+    }
+}
+
+impl Encoder for Field {
+    fn encode(&self, buffer: &mut Vec<u8>, cp: &ConstantPool) -> Result<(), ClassWriterError> {
+        write_u16(buffer, self.access_flags);
+        write_u16(buffer, self.name_index);
+        write_u16(buffer, self.descriptor_index);
+        write_attributes(buffer, &self.attributes, cp)
+    }
+}
+
+impl Encoder for Method {
+    fn encode(&self, buffer: &mut Vec<u8>, cp: &ConstantPool) -> Result<(), ClassWriterError> {
+        write_u16(buffer, self.access_flags);
+        write_u16(buffer, self.name_index);
+        write_u16(buffer, self.descriptor_index);
+        write_attributes(buffer, &self.attributes, cp)
+    }
+}
+
+// The contents of an attribute, without the name and length that come before them in a class file.
+pub fn write_attribute_contents(attribute: &Attribute, cp: &ConstantPool) -> Result<Vec<u8>, ClassWriterError> {
+    let mut buffer = Vec::new();
+    attribute.encode(&mut buffer, cp)?;
+    Ok(buffer.split_off(6))
+}
+
+fn write_attributes(buffer: &mut Vec<u8>, attributes: &[Attribute], cp: &ConstantPool) -> Result<(), ClassWriterError> {
+    write_u16(buffer, attributes.len() as u16);
+    for attribute in attributes.iter() {
+        attribute.encode(buffer, cp)?;
+    }
+    Ok(())
+}
+
+impl Encoder for Attribute {
+    fn encode(&self, buffer: &mut Vec<u8>, cp: &ConstantPool) -> Result<(), ClassWriterError> {
+        let mut contents = Vec::new();
+
+        let name = match *self {
+            Attribute::ConstantValue { index } => {
+                write_u16(&mut contents, index);
+                ATTRIBUTE_CONSTANT_VALUE
+            },
+            Attribute::Code { max_stack, max_locals, ref code, ref exceptions, ref attributes } => {
+                write_u16(&mut contents, max_stack);
+                write_u16(&mut contents, max_locals);
+                write_u32(&mut contents, code.len() as u32);
+                contents.extend(code.iter());
+                write_u16(&mut contents, exceptions.len() as u16);
+                for entry in exceptions.iter() {
+                    write_u16(&mut contents, entry.start_pc);
+                    write_u16(&mut contents, entry.end_pc);
+                    write_u16(&mut contents, entry.handler_pc);
+                    write_u16(&mut contents, entry.catch_type);
+                }
+                write_attributes(&mut contents, attributes, cp)?;
+                ATTRIBUTE_CODE
+            },
+            Attribute::StackMapTable { ref entries } => {
+                write_u16(&mut contents, entries.len() as u16);
+                for frame in entries.iter() {
+                    frame.encode(&mut contents, cp)?;
+                }
+                ATTRIBUTE_STACK_MAP_TABLE
+            },
+            Attribute::Exceptions { ref exception_index } => {
+                write_u16(&mut contents, exception_index.len() as u16);
+                for &index in exception_index.iter() {
+                    write_u16(&mut contents, index);
+                }
+                ATTRIBUTE_EXCEPTIONS
+            },
+            Attribute::InnerClasses { ref classes } => {
+                write_u16(&mut contents, classes.len() as u16);
+                for entry in classes.iter() {
+                    write_u16(&mut contents, entry.inner_class_info_index);
+                    write_u16(&mut contents, entry.outer_class_info_index);
+                    write_u16(&mut contents, entry.inner_name_index);
+                    write_u16(&mut contents, entry.inner_class_access_flags);
+                }
+                ATTRIBUTE_INNER_CLASSES
+            },
+            Attribute::Signature { index } => {
+                write_u16(&mut contents, index);
+                ATTRIBUTE_SIGNATURE
+            },
+            Attribute::SourceFile { index } => {
+                write_u16(&mut contents, index);
+                ATTRIBUTE_SOURCE_FILE
+            },
+            Attribute::LineNumberTable(ref entries) => {
+                write_u16(&mut contents, entries.len() as u16);
+                for entry in entries.iter() {
+                    write_u16(&mut contents, entry.start_pc);
+                    write_u16(&mut contents, entry.line_number);
+                }
+                ATTRIBUTE_LINE_NUMBER_TABLE
+            },
+            Attribute::LocalVariableTable(ref entries) => {
+                write_u16(&mut contents, entries.len() as u16);
+                for entry in entries.iter() {
+                    write_u16(&mut contents, entry.start_pc);
+                    write_u16(&mut contents, entry.length);
+                    write_u16(&mut contents, entry.name_index);
+                    write_u16(&mut contents, entry.descriptor_index);
+                    write_u16(&mut contents, entry.index);
+                }
+                ATTRIBUTE_LOCAL_VARIABLE_TABLE
+            },
+            Attribute::Deprecated => ATTRIBUTE_DEPRECATED,
+            Attribute::RuntimeVisibleAnnotations { ref annotations } => {
+                write_u16(&mut contents, annotations.len() as u16);
+                for annotation in annotations.iter() {
+                    annotation.encode(&mut contents, cp)?;
+                }
+                ATTRIBUTE_RUNTIME_VISIBLE_ANNOTATIONS
+            },
+            Attribute::AnnotationDefault { ref default_value } => {
+                default_value.encode(&mut contents, cp)?;
+                ATTRIBUTE_ANNOTATION_DEFAULT
+            },
+            Attribute::BootstrapMethods { ref methods } => {
+                write_u16(&mut contents, methods.len() as u16);
+                for method in methods.iter() {
+                    write_u16(&mut contents, method.bootstrap_method_ref);
+                    write_u16(&mut contents, method.bootstrap_arguments.len() as u16);
+                    for &index in method.bootstrap_arguments.iter() {
+                        write_u16(&mut contents, index);
+                    }
+                }
+                ATTRIBUTE_BOOTSTRAP_METHODS
+            },
+            Attribute::Unrecognized(ref info) => {
+                write_u16(buffer, info.attribute_name_index);
+                write_u32(buffer, info.bytes.len() as u32);
+                buffer.extend(info.bytes.iter());
+                return Ok(());
+            },
+            ref attribute => return Err(ClassWriterError::UnsupportedAttribute(format!("{:?}", attribute)))
+        };
+
+        write_u16(buffer, utf8_index(cp, name)?);
+        write_u32(buffer, contents.len() as u32);
+        buffer.extend(contents);
+        Ok(())
+    }
+}
+
+impl Encoder for Annotation {
+    fn encode(&self, buffer: &mut Vec<u8>, cp: &ConstantPool) -> Result<(), ClassWriterError> {
+        write_u16(buffer, self.type_index);
+        write_u16(buffer, self.elements.len() as u16);
+        for pair in self.elements.iter() {
+            write_u16(buffer, pair.element_name_index);
+            pair.element_value.encode(buffer, cp)?;
+        }
+        Ok(())
+    }
+}
+
+impl Encoder for AnnotationElementValue {
+    fn encode(&self, buffer: &mut Vec<u8>, cp: &ConstantPool) -> Result<(), ClassWriterError> {
+        match *self {
+            AnnotationElementValue::Const { tag, const_value_index } => {
+                buffer.push(tag as u8);
+                write_u16(buffer, const_value_index);
+            },
+            AnnotationElementValue::EnumConst { type_name_index, const_name_index } => {
+                buffer.push(b'e');
+                write_u16(buffer, type_name_index);
+                write_u16(buffer, const_name_index);
+            },
+            AnnotationElementValue::ClassInfo(index) => {
+                buffer.push(b'c');
+                write_u16(buffer, index);
+            },
+            AnnotationElementValue::Annotation(ref annotation) => {
+                buffer.push(b'@');
+                annotation.encode(buffer, cp)?;
+            },
+            AnnotationElementValue::Array(ref values) => {
+                buffer.push(b'[');
+                write_u16(buffer, values.len() as u16);
+                for value in values.iter() {
+                    value.encode(buffer, cp)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Encoder for StackMapFrame {
+    fn encode(&self, buffer: &mut Vec<u8>, cp: &ConstantPool) -> Result<(), ClassWriterError> {
+        match *self {
+            StackMapFrame::SameFrame { offset_delta } => buffer.push(offset_delta),
+            StackMapFrame::SameLocals1StackItemFrame { offset_delta, ref info } => {
+                buffer.push(64 + offset_delta);
+                info.encode(buffer, cp)?;
+            },
+            StackMapFrame::SameLocals1StackItemFrameExtended { offset_delta, ref info } => {
+                buffer.push(247);
+                write_u16(buffer, offset_delta);
+                info.encode(buffer, cp)?;
+            },
+            StackMapFrame::ChopFrame { offset_delta, chopped } => {
+                buffer.push(251 - chopped);
+                write_u16(buffer, offset_delta);
+            },
+            StackMapFrame::SameFrameExtended { offset_delta } => {
+                buffer.push(251);
+                write_u16(buffer, offset_delta);
+            },
+            StackMapFrame::AppendFrame { offset_delta, ref locals } => {
+                buffer.push(251 + locals.len() as u8);
+                write_u16(buffer, offset_delta);
+                for local in locals.iter() {
+                    local.encode(buffer, cp)?;
+                }
+            },
+            StackMapFrame::FullFrame { offset_delta, ref locals, ref stack } => {
+                buffer.push(255);
+                write_u16(buffer, offset_delta);
+                write_u16(buffer, locals.len() as u16);
+                for local in locals.iter() {
+                    local.encode(buffer, cp)?;
+                }
+                write_u16(buffer, stack.len() as u16);
+                for item in stack.iter() {
+                    item.encode(buffer, cp)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Encoder for VerificationTypeInfo {
+    fn encode(&self, buffer: &mut Vec<u8>, _cp: &ConstantPool) -> Result<(), ClassWriterError> {
+        match *self {
+            VerificationTypeInfo::Top => buffer.push(0),
+            VerificationTypeInfo::Integer => buffer.push(1),
+            VerificationTypeInfo::Float => buffer.push(2),
+            VerificationTypeInfo::Double => buffer.push(3),
+            VerificationTypeInfo::Long => buffer.push(4),
+            VerificationTypeInfo::Null => buffer.push(5),
+            VerificationTypeInfo::UninitializedThis => buffer.push(6),
+            VerificationTypeInfo::Object(index) => {
+                buffer.push(7);
+                write_u16(buffer, index);
+            },
+            VerificationTypeInfo::Uninitialized(offset) => {
+                buffer.push(8);
+                write_u16(buffer, offset);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn utf8_index(cp: &ConstantPool, name: &str) -> Result<u16, ClassWriterError> {
+    let utf8 = ConstantPoolEntry::Utf8(JavaString::from(name));
+    cp.entries.iter()
+        .position(|entry| *entry == utf8)
+        .map(|position| position as u16 + 1)
+        .ok_or_else(|| ClassWriterError::MissingAttributeName(String::from(name)))
+}
+
+fn write_u16(buffer: &mut Vec<u8>, value: u16) {
+    buffer.extend(value.to_be_bytes().iter());
+}
+
+fn write_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend(value.to_be_bytes().iter());
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use class::reader;
+
+    // Reading a class file and writing it back gives the same bytes.
+    #[test]
+    fn round_trips_class_files() {
+        for bytes in [&include_bytes!("../../Counter.class")[..], &include_bytes!("../../fixtures/Bootstrap.class")[..],
+                      &include_bytes!("../../fixtures/Lambdas.class")[..], &include_bytes!("../../fixtures/Annotations$Tag.class")[..],
+                      &include_bytes!("../../fixtures/Annotations$Base.class")[..]].iter() {
+            let class_file = reader::read_class_file(bytes).unwrap();
+            assert_eq!(write_class_file(&class_file).unwrap(), bytes.to_vec());
+        }
+    }
+
+    #[test]
+    fn attribute_names_must_be_in_the_constant_pool() {
+        let mut class_file = reader::read_class_file(include_bytes!("../../Counter.class")).unwrap();
+        class_file.attributes.push(Attribute::Deprecated);
+        assert_eq!(write_class_file(&class_file).err(), Some(ClassWriterError::MissingAttributeName(String::from("Deprecated"))));
+    }
+
+}
@@ -0,0 +1,334 @@
+// Encodes instructions into the bytes of a Code attribute, the inverse of the disassembler.
+
+use code::disassembler::*;
+use code::instruction::Instruction;
+
+#[derive(Debug, PartialEq)]
+pub enum AssemblerError {
+    // A wide instruction modifying an opcode that takes no local variable index.
+    Unencodable(Instruction)
+}
+
+pub fn assemble_code(instructions: &[Instruction]) -> Result<Vec<u8>, AssemblerError> {
+    let mut bytes = Vec::new();
+    for instruction in instructions.iter() {
+        let address = bytes.len();
+        assemble_instruction(&mut bytes, address, instruction)?;
+    }
+    Ok(bytes)
+}
+
+// The number of bytes an instruction at the given address takes, including its opcode. Only the
+// length of the switch instructions depends on the address.
+pub fn instruction_length(instruction: &Instruction, address: usize) -> Result<usize, AssemblerError> {
+    let mut bytes = Vec::new();
+    assemble_instruction(&mut bytes, address, instruction)?;
+    Ok(bytes.len())
+}
+
+fn assemble_instruction(bytes: &mut Vec<u8>, address: usize, instruction: &Instruction) -> Result<(), AssemblerError> {
+    match *instruction {
+        Instruction::Aaload => bytes.push(AALOAD),
+        Instruction::Aastore => bytes.push(AASTORE),
+        Instruction::AconstNull => bytes.push(ACONST_NULL),
+        Instruction::Aload0 => bytes.push(ALOAD_0),
+        Instruction::Aload1 => bytes.push(ALOAD_1),
+        Instruction::Aload2 => bytes.push(ALOAD_2),
+        Instruction::Aload3 => bytes.push(ALOAD_3),
+        Instruction::Areturn => bytes.push(ARETURN),
+        Instruction::Arraylength => bytes.push(ARRAYLENGTH),
+        Instruction::Astore0 => bytes.push(ASTORE_0),
+        Instruction::Astore1 => bytes.push(ASTORE_1),
+        Instruction::Astore2 => bytes.push(ASTORE_2),
+        Instruction::Astore3 => bytes.push(ASTORE_3),
+        Instruction::Athrow => bytes.push(ATHROW),
+        Instruction::Baload => bytes.push(BALOAD),
+        Instruction::Bastore => bytes.push(BASTORE),
+        Instruction::Caload => bytes.push(CALOAD),
+        Instruction::Castore => bytes.push(CASTORE),
+        Instruction::D2f => bytes.push(D2F),
+        Instruction::D2i => bytes.push(D2I),
+        Instruction::D2l => bytes.push(D2L),
+        Instruction::Dadd => bytes.push(DADD),
+        Instruction::Daload => bytes.push(DALOAD),
+        Instruction::Dastore => bytes.push(DASTORE),
+        Instruction::Dcmpg => bytes.push(DCMPG),
+        Instruction::Dcmpl => bytes.push(DCMPL),
+        Instruction::Dconst0 => bytes.push(DCONST_0),
+        Instruction::Dconst1 => bytes.push(DCONST_1),
+        Instruction::Ddiv => bytes.push(DDIV),
+        Instruction::Dload0 => bytes.push(DLOAD_0),
+        Instruction::Dload1 => bytes.push(DLOAD_1),
+        Instruction::Dload2 => bytes.push(DLOAD_2),
+        Instruction::Dload3 => bytes.push(DLOAD_3),
+        Instruction::Dmul => bytes.push(DMUL),
+        Instruction::Dneg => bytes.push(DNEG),
+        Instruction::Drem => bytes.push(DREM),
+        Instruction::Dreturn => bytes.push(DRETURN),
+        Instruction::Dstore0 => bytes.push(DSTORE_0),
+        Instruction::Dstore1 => bytes.push(DSTORE_1),
+        Instruction::Dstore2 => bytes.push(DSTORE_2),
+        Instruction::Dstore3 => bytes.push(DSTORE_3),
+        Instruction::Dsub => bytes.push(DSUB),
+        Instruction::Dup => bytes.push(DUP),
+        Instruction::DupX1 => bytes.push(DUP_X1),
+        Instruction::DupX2 => bytes.push(DUP_X2),
+        Instruction::Dup2 => bytes.push(DUP2),
+        Instruction::Dup2X1 => bytes.push(DUP2_X1),
+        Instruction::Dup2X2 => bytes.push(DUP2_X2),
+        Instruction::F2d => bytes.push(F2D),
+        Instruction::F2i => bytes.push(F2I),
+        Instruction::F2l => bytes.push(F2L),
+        Instruction::Fadd => bytes.push(FADD),
+        Instruction::Faload => bytes.push(FALOAD),
+        Instruction::Fastore => bytes.push(FASTORE),
+        Instruction::Fcmpg => bytes.push(FCMPG),
+        Instruction::Fcmpl => bytes.push(FCMPL),
+        Instruction::Fconst0 => bytes.push(FCONST_0),
+        Instruction::Fconst1 => bytes.push(FCONST_1),
+        Instruction::Fconst2 => bytes.push(FCONST_2),
+        Instruction::Fdiv => bytes.push(FDIV),
+        Instruction::Fload0 => bytes.push(FLOAD_0),
+        Instruction::Fload1 => bytes.push(FLOAD_1),
+        Instruction::Fload2 => bytes.push(FLOAD_2),
+        Instruction::Fload3 => bytes.push(FLOAD_3),
+        Instruction::Fmul => bytes.push(FMUL),
+        Instruction::Fneg => bytes.push(FNEG),
+        Instruction::Frem => bytes.push(FREM),
+        Instruction::Freturn => bytes.push(FRETURN),
+        Instruction::Fstore0 => bytes.push(FSTORE_0),
+        Instruction::Fstore1 => bytes.push(FSTORE_1),
+        Instruction::Fstore2 => bytes.push(FSTORE_2),
+        Instruction::Fstore3 => bytes.push(FSTORE_3),
+        Instruction::Fsub => bytes.push(FSUB),
+        Instruction::I2b => bytes.push(I2B),
+        Instruction::I2c => bytes.push(I2C),
+        Instruction::I2d => bytes.push(I2D),
+        Instruction::I2f => bytes.push(I2F),
+        Instruction::I2s => bytes.push(I2S),
+        Instruction::I2l => bytes.push(I2L),
+        Instruction::Iadd => bytes.push(IADD),
+        Instruction::Iaload => bytes.push(IALOAD),
+        Instruction::Iand => bytes.push(IAND),
+        Instruction::Iastore => bytes.push(IASTORE),
+        Instruction::IconstM1 => bytes.push(ICONST_M1),
+        Instruction::Iconst0 => bytes.push(ICONST_0),
+        Instruction::Iconst1 => bytes.push(ICONST_1),
+        Instruction::Iconst2 => bytes.push(ICONST_2),
+        Instruction::Iconst3 => bytes.push(ICONST_3),
+        Instruction::Iconst4 => bytes.push(ICONST_4),
+        Instruction::Iconst5 => bytes.push(ICONST_5),
+        Instruction::Idiv => bytes.push(IDIV),
+        Instruction::Iload0 => bytes.push(ILOAD_0),
+        Instruction::Iload1 => bytes.push(ILOAD_1),
+        Instruction::Iload2 => bytes.push(ILOAD_2),
+        Instruction::Iload3 => bytes.push(ILOAD_3),
+        Instruction::Imul => bytes.push(IMUL),
+        Instruction::Ineg => bytes.push(INEG),
+        Instruction::Ior => bytes.push(IOR),
+        Instruction::Irem => bytes.push(IREM),
+        Instruction::Ireturn => bytes.push(IRETURN),
+        Instruction::Ishl => bytes.push(ISHL),
+        Instruction::Ishr => bytes.push(ISHR),
+        Instruction::Istore0 => bytes.push(ISTORE_0),
+        Instruction::Istore1 => bytes.push(ISTORE_1),
+        Instruction::Istore2 => bytes.push(ISTORE_2),
+        Instruction::Istore3 => bytes.push(ISTORE_3),
+        Instruction::Isub => bytes.push(ISUB),
+        Instruction::Iushr => bytes.push(IUSHR),
+        Instruction::Ixor => bytes.push(IXOR),
+        Instruction::L2d => bytes.push(L2D),
+        Instruction::L2f => bytes.push(L2F),
+        Instruction::L2i => bytes.push(L2I),
+        Instruction::Ladd => bytes.push(LADD),
+        Instruction::Laload => bytes.push(LALOAD),
+        Instruction::Land => bytes.push(LAND),
+        Instruction::Lastore => bytes.push(LASTORE),
+        Instruction::Lcmp => bytes.push(LCMP),
+        Instruction::Lconst0 => bytes.push(LCONST_0),
+        Instruction::Lconst1 => bytes.push(LCONST_1),
+        Instruction::Ldiv => bytes.push(LDIV),
+        Instruction::Lload0 => bytes.push(LLOAD_0),
+        Instruction::Lload1 => bytes.push(LLOAD_1),
+        Instruction::Lload2 => bytes.push(LLOAD_2),
+        Instruction::Lload3 => bytes.push(LLOAD_3),
+        Instruction::Lmul => bytes.push(LMUL),
+        Instruction::Lneg => bytes.push(LNEG),
+        Instruction::Lor => bytes.push(LOR),
+        Instruction::Lrem => bytes.push(LREM),
+        Instruction::Lreturn => bytes.push(LRETURN),
+        Instruction::Lshl => bytes.push(LSHL),
+        Instruction::Lshr => bytes.push(LSHR),
+        Instruction::Lstore0 => bytes.push(LSTORE_0),
+        Instruction::Lstore1 => bytes.push(LSTORE_1),
+        Instruction::Lstore2 => bytes.push(LSTORE_2),
+        Instruction::Lstore3 => bytes.push(LSTORE_3),
+        Instruction::Lsub => bytes.push(LSUB),
+        Instruction::Lushr => bytes.push(LUSHR),
+        Instruction::Lxor => bytes.push(LXOR),
+        Instruction::Monitorenter => bytes.push(MONITORENTER),
+        Instruction::Monitorexit => bytes.push(MONITOREXIT),
+        Instruction::Nop => bytes.push(NOP),
+        Instruction::Pop => bytes.push(POP),
+        Instruction::Pop2 => bytes.push(POP2),
+        Instruction::Return => bytes.push(RETURN),
+        Instruction::Saload => bytes.push(SALOAD),
+        Instruction::Sastore => bytes.push(SASTORE),
+        Instruction::Swap => bytes.push(SWAP),
+        Instruction::Aload { index } => bytes.extend([ALOAD, index].iter()),
+        Instruction::Astore { index } => bytes.extend([ASTORE, index].iter()),
+        Instruction::Dload { index } => bytes.extend([DLOAD, index].iter()),
+        Instruction::Dstore { index } => bytes.extend([DSTORE, index].iter()),
+        Instruction::Fload { index } => bytes.extend([FLOAD, index].iter()),
+        Instruction::Fstore { index } => bytes.extend([FSTORE, index].iter()),
+        Instruction::Iload { index } => bytes.extend([ILOAD, index].iter()),
+        Instruction::Istore(index) => bytes.extend([ISTORE, index].iter()),
+        Instruction::Lload { index } => bytes.extend([LLOAD, index].iter()),
+        Instruction::Lstore { index } => bytes.extend([LSTORE, index].iter()),
+        Instruction::Ret { index } => bytes.extend([RET, index].iter()),
+        Instruction::Ldc { index } => bytes.extend([LDC, index].iter()),
+        Instruction::Newarray { atype } => bytes.extend([NEWARRAY, atype].iter()),
+        Instruction::Bipush { byte } => bytes.extend([BIPUSH, byte as u8].iter()),
+        Instruction::Sipush(value) => with_u16(bytes, SIPUSH, value as u16),
+        Instruction::Iinc { index, constant } => bytes.extend([IINC, index, constant as u8].iter()),
+        Instruction::Anewarray { index } => with_u16(bytes, ANEWARRAY, index),
+        Instruction::Checkcast { index } => with_u16(bytes, CHECKCAST, index),
+        Instruction::Getfield { index } => with_u16(bytes, GETFIELD, index),
+        Instruction::Getstatic { index } => with_u16(bytes, GETSTATIC, index),
+        Instruction::Putfield { index } => with_u16(bytes, PUTFIELD, index),
+        Instruction::Putstatic { index } => with_u16(bytes, PUTSTATIC, index),
+        Instruction::Instanceof { index } => with_u16(bytes, INSTANCEOF, index),
+        Instruction::Invokespecial { index } => with_u16(bytes, INVOKESPECIAL, index),
+        Instruction::Invokestatic { index } => with_u16(bytes, INVOKESTATIC, index),
+        Instruction::Invokevirtual { index } => with_u16(bytes, INVOKEVIRTUAL, index),
+        Instruction::Invokedynamic { index } => {
+            with_u16(bytes, INVOKEDYNAMIC, index);
+            bytes.extend([0, 0].iter());
+        },
+        Instruction::Invokeinterface { index, count } => {
+            with_u16(bytes, INVOKEINTERFACE, index);
+            bytes.extend([count, 0].iter());
+        },
+        Instruction::LdcW { index } => with_u16(bytes, LDC_W, index),
+        Instruction::Ldc2W { index } => with_u16(bytes, LDC2_W, index),
+        Instruction::New { index } => with_u16(bytes, NEW, index),
+        Instruction::Multianewarray { index, dimensions } => {
+            with_u16(bytes, MULTIANEWARRAY, index);
+            bytes.push(dimensions);
+        },
+        Instruction::Goto { branch_offset } => with_u16(bytes, GOTO, branch_offset as u16),
+        Instruction::IfAcmpeq { branch_offset } => with_u16(bytes, IF_ACMPEQ, branch_offset as u16),
+        Instruction::IfAcmpne { branch_offset } => with_u16(bytes, IF_ACMPNE, branch_offset as u16),
+        Instruction::IfIcmpeq { branch_offset } => with_u16(bytes, IF_ICMPEQ, branch_offset as u16),
+        Instruction::IfIcmpne { branch_offset } => with_u16(bytes, IF_ICMPNE, branch_offset as u16),
+        Instruction::IfIcmplt { branch_offset } => with_u16(bytes, IF_ICMPLT, branch_offset as u16),
+        Instruction::IfIcmpge { branch_offset } => with_u16(bytes, IF_ICMPGE, branch_offset as u16),
+        Instruction::IfIcmpgt { branch_offset } => with_u16(bytes, IF_ICMPGT, branch_offset as u16),
+        Instruction::IfIcmple { branch_offset } => with_u16(bytes, IF_ICMPLE, branch_offset as u16),
+        Instruction::Ifeq { branch_offset } => with_u16(bytes, IFEQ, branch_offset as u16),
+        Instruction::Ifne { branch_offset } => with_u16(bytes, IFNE, branch_offset as u16),
+        Instruction::Iflt { branch_offset } => with_u16(bytes, IFLT, branch_offset as u16),
+        Instruction::Ifge { branch_offset } => with_u16(bytes, IFGE, branch_offset as u16),
+        Instruction::Ifgt { branch_offset } => with_u16(bytes, IFGT, branch_offset as u16),
+        Instruction::Ifle { branch_offset } => with_u16(bytes, IFLE, branch_offset as u16),
+        Instruction::Ifnonnull { branch_offset } => with_u16(bytes, IFNONNULL, branch_offset as u16),
+        Instruction::Ifnull { branch_offset } => with_u16(bytes, IFNULL, branch_offset as u16),
+        Instruction::Jsr { branchbyte1, branchbyte2 } => bytes.extend([JSR, branchbyte1, branchbyte2].iter()),
+        Instruction::GotoW(b1, b2, b3, b4) => bytes.extend([GOTO_W, b1, b2, b3, b4].iter()),
+        Instruction::JsrW { branchbyte1, branchbyte2, branchbyte3, branchbyte4 } => {
+            bytes.extend([JSR_W, branchbyte1, branchbyte2, branchbyte3, branchbyte4].iter());
+        },
+        Instruction::Lookupswitch { default, ref pairs } => {
+            bytes.push(LOOKUPSWITCH);
+            bytes.extend(vec![0; switch_padding(address)]);
+            bytes.extend(default.to_be_bytes().iter());
+            bytes.extend((pairs.len() as i32).to_be_bytes().iter());
+            for &(key, offset) in pairs.iter() {
+                bytes.extend(key.to_be_bytes().iter());
+                bytes.extend(offset.to_be_bytes().iter());
+            }
+        },
+        Instruction::Tableswitch { default, low, ref offsets } => {
+            bytes.push(TABLESWITCH);
+            bytes.extend(vec![0; switch_padding(address)]);
+            bytes.extend(default.to_be_bytes().iter());
+            bytes.extend(low.to_be_bytes().iter());
+            bytes.extend((low + offsets.len() as i32 - 1).to_be_bytes().iter());
+            for offset in offsets.iter() {
+                bytes.extend(offset.to_be_bytes().iter());
+            }
+        },
+        Instruction::Wide { opcode, index } => match opcode {
+            ILOAD | FLOAD | ALOAD | LLOAD | DLOAD | ISTORE | FSTORE | ASTORE | LSTORE | DSTORE | RET => {
+                bytes.extend([WIDE, opcode].iter());
+                bytes.extend(index.to_be_bytes().iter());
+            },
+            _ => return Err(AssemblerError::Unencodable(instruction.clone()))
+        },
+        Instruction::WideIinc { index, constant } => {
+            bytes.extend([WIDE, IINC].iter());
+            bytes.extend(index.to_be_bytes().iter());
+            bytes.extend(constant.to_be_bytes().iter());
+        }
+    }
+    Ok(())
+}
+
+fn with_u16(bytes: &mut Vec<u8>, opcode: u8, operand: u16) {
+    bytes.push(opcode);
+    bytes.extend(operand.to_be_bytes().iter());
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use class::{reader, Attribute};
+
+    // Disassembling the code of a class file and assembling it again gives the same bytes.
+    #[test]
+    fn round_trips_code() {
+        let class_file = reader::read_class_file(include_bytes!("../../fixtures/Bootstrap.class")).unwrap();
+        for method in class_file.methods.iter() {
+            for attribute in method.attributes.iter() {
+                if let Attribute::Code { ref code, .. } = *attribute {
+                    let instructions: Vec<Instruction> = disassemble_code(code).unwrap().iter().map(|tagged| tagged.instruction.clone()).collect();
+                    assert_eq!(&assemble_code(&instructions).unwrap(), code);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lengths() {
+        assert_eq!(instruction_length(&Instruction::Iload0, 0), Ok(1));
+        assert_eq!(instruction_length(&Instruction::Sipush(-300), 0), Ok(3));
+        assert_eq!(instruction_length(&Instruction::Invokeinterface { index: 1, count: 2 }, 0), Ok(5));
+        let switch = Instruction::Tableswitch { default: 20, low: 0, offsets: vec![16, 18] };
+        assert_eq!(instruction_length(&switch, 3), Ok(21));
+        assert_eq!(instruction_length(&switch, 4), Ok(24));
+        assert!(instruction_length(&Instruction::Wide { opcode: IADD, index: 1 }, 0).is_err());
+    }
+
+    // The operands of a switch start at a multiple of four bytes from the start of the code.
+    #[test]
+    fn round_trips_switches_and_wide_instructions() {
+        let instructions = vec![
+            Instruction::Iload0,
+            Instruction::Tableswitch { default: 61, low: -1, offsets: vec![23, 51] },
+            Instruction::Lookupswitch { default: 38, pairs: vec![(-5, 28), (100000, 34)] },
+            Instruction::WideIinc { index: 300, constant: -2 },
+            Instruction::Wide { opcode: ALOAD, index: 256 },
+            Instruction::Areturn
+        ];
+        let code = assemble_code(&instructions).unwrap();
+        assert_eq!(&code[1..8], &[TABLESWITCH, 0, 0, 0, 0, 0, 61][..]);
+        assert_eq!(&code[24..32], &[LOOKUPSWITCH, 0, 0, 0, 0, 0, 0, 38][..]);
+        assert_eq!(code.len(), 63);
+
+        let disassembled = disassemble_code(&code).unwrap();
+        assert_eq!(disassembled.iter().map(|tagged| tagged.index).collect::<Vec<u16>>(), vec![0, 1, 24, 52, 58, 62]);
+        assert_eq!(disassembled.into_iter().map(|tagged| tagged.instruction).collect::<Vec<Instruction>>(), instructions);
+    }
+
+}
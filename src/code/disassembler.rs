@@ -1,207 +1,207 @@
 use code::instruction::{Instruction, TaggedInstruction};
 
-const AALOAD: u8 = 0x32;
-const AASTORE: u8 = 0x53;
-const ACONST_NULL: u8 = 0x01;
-const ALOAD: u8 = 0x19;
-const ALOAD_0: u8 = 0x2a;
-const ALOAD_1: u8 = 0x2b;
-const ALOAD_2: u8 = 0x2c;
-const ALOAD_3: u8 = 0x2d;
-const ANEWARRAY: u8 = 0xbd;
-const ARETURN: u8 = 0xb0;
-const ARRAYLENGTH: u8 = 0xbe;
-const ASTORE: u8 = 0x3a;
-const ASTORE_0: u8 = 0x4b;
-const ASTORE_1: u8 = 0x4c;
-const ASTORE_2: u8 = 0x4d;
-const ASTORE_3: u8 = 0x4e;
-const ATHROW: u8 = 0xbf;
-const BALOAD: u8 = 0x33;
-const BASTORE: u8 = 0x54;
-const BIPUSH: u8 = 0x10;
-const CALOAD: u8 = 0x34;
-const CASTORE: u8 = 0x55;
-const CHECKCAST: u8 = 0xc0;
-const D2F: u8 = 0x90;
-const D2I: u8 = 0x8e;
-const D2L: u8 = 0x8f;
-const DADD: u8 = 0x63;
-const DALOAD: u8 = 0x31;
-const DASTORE: u8 = 0x52;
-const DCMPG: u8 = 0x98;
-const DCMPL: u8 = 0x97;
-const DCONST_0: u8 = 0x0e;
-const DCONST_1: u8 = 0x0f;
-const DDIV: u8 = 0x6f;
-const DLOAD: u8 = 0x18;
-const DLOAD_0: u8 = 0x26;
-const DLOAD_1: u8 = 0x27;
-const DLOAD_2: u8 = 0x28;
-const DLOAD_3: u8 = 0x29;
-const DMUL: u8 = 0x6b;
-const DNEG: u8 = 0x77;
-const DREM: u8 = 0x73;
-const DRETURN: u8 = 0xaf;
-const DSTORE: u8 = 0x39;
-const DSTORE_0: u8 = 0x47;
-const DSTORE_1: u8 = 0x48;
-const DSTORE_2: u8 = 0x49;
-const DSTORE_3: u8 = 0x4a;
-const DSUB: u8 = 0x67;
-const DUP: u8 = 0x59;
-const DUP_X1: u8 = 0x5a;
-const DUP_X2: u8 = 0x5b;
-const DUP2: u8 = 0x5c;
-const DUP2_X1: u8 = 0x5d;
-const DUP2_X2: u8 = 0x5e;
-const F2D: u8 = 0x8d;
-const F2I: u8 = 0x8b;
-const F2L: u8 = 0x8c;
-const FADD: u8 = 0x62;
-const FALOAD: u8 = 0x30;
-const FASTORE: u8 = 0x51;
-const FCMPG: u8 = 0x96;
-const FCMPL: u8 = 0x95;
-const FCONST_0: u8 = 0x0b;
-const FCONST_1: u8 = 0x0c;
-const FCONST_2: u8 = 0x0d;
-const FDIV: u8 = 0x6e;
-const FLOAD: u8 = 0x17;
-const FLOAD_0: u8 = 0x22;
-const FLOAD_1: u8 = 0x23;
-const FLOAD_2: u8 = 0x24;
-const FLOAD_3: u8 = 0x25;
-const FMUL: u8 = 0x6a;
-const FNEG: u8 = 0x76;
-const FREM: u8 = 0x72;
-const FRETURN: u8 = 0xae;
-const FSTORE: u8 = 0x38;
-const FSTORE_0: u8 = 0x43;
-const FSTORE_1: u8 = 0x44;
-const FSTORE_2: u8 = 0x45;
-const FSTORE_3: u8 = 0x46;
-const FSUB: u8 = 0x66;
-const GETFIELD: u8 = 0xb4;
-const GETSTATIC: u8 = 0xb2;
-const GOTO: u8 = 0xa7;
-const GOTO_W: u8 = 0xc8;
-const I2B: u8 = 0x91;
-const I2C: u8 = 0x92;
-const I2D: u8 = 0x87;
-const I2F: u8 = 0x86;
-const I2L: u8 = 0x85;
-const I2S: u8 = 0x93;
-const IADD: u8 = 0x60;
-const IALOAD: u8 = 0x2e;
-const IAND: u8 = 0x7e;
-const IASTORE: u8 = 0x4f;
-const ICONST_M1: u8 = 0x02;
-const ICONST_0: u8 = 0x03;
-const ICONST_1: u8 = 0x04;
-const ICONST_2: u8 = 0x05;
-const ICONST_3: u8 = 0x06;
-const ICONST_4: u8 = 0x07;
-const ICONST_5: u8 = 0x08;
-const IDIV: u8 = 0x6c;
-const IF_ACMPEQ: u8 = 0xa5;
-const IF_ACMPNE: u8 = 0xa6;
-const IF_ICMPEQ: u8 = 0x9f;
-const IF_ICMPNE: u8 = 0xa0;
-const IF_ICMPLT: u8 = 0xa1;
-const IF_ICMPGE: u8 = 0xa2;
-const IF_ICMPGT: u8 = 0xa3;
-const IF_ICMPLE: u8 = 0xa4;
-const IFEQ: u8 = 0x99;
-const IFNE: u8 = 0x9a;
-const IFLT: u8 = 0x9b;
-const IFGE: u8 = 0x9c;
-const IFGT: u8 = 0x9d;
-const IFLE: u8 = 0x9e;
-const IFNONNULL: u8 = 0xc7;
-const IFNULL: u8 = 0xc6;
-const IINC: u8 = 0x84;
-const ILOAD: u8 = 0x15;
-const ILOAD_0: u8 = 0x1a;
-const ILOAD_1: u8 = 0x1b;
-const ILOAD_2: u8 = 0x1c;
-const ILOAD_3: u8 = 0x1d;
-const IMUL: u8 = 0x68;
-const INEG: u8 = 0x74;
-const INSTANCEOF: u8 = 0xc1;
-const INVOKEDYNAMIC: u8 = 0xba;
-const INVOKEINTERFACE: u8 = 0xb9;
-const INVOKESPECIAL: u8 = 0xb7;
-const INVOKESTATIC: u8 = 0xb8;
-const INVOKEVIRTUAL: u8 = 0xb6;
-const IOR: u8 = 0x80;
-const IREM: u8 = 0x70;
-const IRETURN: u8 = 0xac;
-const ISHL: u8 = 0x78;
-const ISHR: u8 = 0x7a;
-const ISTORE: u8 = 0x36;
-const ISTORE_0: u8 = 0x3b;
-const ISTORE_1: u8 = 0x3c;
-const ISTORE_2: u8 = 0x3d;
-const ISTORE_3: u8 = 0x3e;
-const ISUB: u8 = 0x64;
-const IUSHR: u8 = 0x7c;
-const IXOR: u8 = 0x82;
-const JSR: u8 = 0xa8;
-const JSR_W: u8 = 0xc9;
-const L2D: u8 = 0x8a;
-const L2F: u8 = 0x89;
-const L2I: u8 = 0x88;
-const LADD: u8 = 0x61;
-const LALOAD: u8 = 0x2f;
-const LAND: u8 = 0x7f;
-const LASTORE: u8 = 0x50;
-const LCMP: u8 = 0x94;
-const LCONST_0: u8 = 0x09;
-const LCONST_1: u8 = 0x0a;
-const LDC: u8 = 0x12;
-const LDC_W: u8 = 0x13;
-const LDC2_W: u8 = 0x14;
-const LDIV: u8 = 0x6d;
-const LLOAD: u8 = 0x16;
-const LLOAD_0: u8 = 0x1e;
-const LLOAD_1: u8 = 0x1f;
-const LLOAD_2: u8 = 0x20;
-const LLOAD_3: u8 = 0x21;
-const LMUL: u8 = 0x69;
-const LNEG: u8 = 0x75;
-const LOOKUPSWITCH: u8 = 0xab;
-const LOR: u8 = 0x81;
-const LREM: u8 = 0x71;
-const LRETURN: u8 = 0xad;
-const LSHL: u8 = 0x79;
-const LSHR: u8 = 0x7b;
-const LSTORE: u8 = 0x37;
-const LSTORE_0: u8 = 0x3f;
-const LSTORE_1: u8 = 0x40;
-const LSTORE_2: u8 = 0x41;
-const LSTORE_3: u8 = 0x42;
-const LSUB: u8 = 0x65;
-const LUSHR: u8 = 0x7d;
-const LXOR: u8 = 0x83;
-const MONITORENTER: u8 = 0xc2;
-const MONITOREXIT: u8 = 0xc3;
-const MULTIANEWARRAY: u8 = 0xc5;
-const NEW: u8 = 0xbb;
-const NEWARRAY: u8 = 0xbc;
-const NOP: u8 = 0x00;
-const POP: u8 = 0x57;
-const POP2: u8 = 0x58;
-const PUTFIELD: u8 = 0xb5;
-const PUTSTATIC: u8 = 0xb3;
-const RET: u8 = 0xa9;
-const RETURN: u8 = 0xb1;
-const SALOAD: u8 = 0x35;
-const SASTORE: u8 = 0x56;
-const SIPUSH: u8 = 0x11;
-const SWAP: u8 = 0x5f;
-const TABLESWITCH: u8 = 0xaa;
-const WIDE: u8 = 0xc4;
+pub const AALOAD: u8 = 0x32;
+pub const AASTORE: u8 = 0x53;
+pub const ACONST_NULL: u8 = 0x01;
+pub const ALOAD: u8 = 0x19;
+pub const ALOAD_0: u8 = 0x2a;
+pub const ALOAD_1: u8 = 0x2b;
+pub const ALOAD_2: u8 = 0x2c;
+pub const ALOAD_3: u8 = 0x2d;
+pub const ANEWARRAY: u8 = 0xbd;
+pub const ARETURN: u8 = 0xb0;
+pub const ARRAYLENGTH: u8 = 0xbe;
+pub const ASTORE: u8 = 0x3a;
+pub const ASTORE_0: u8 = 0x4b;
+pub const ASTORE_1: u8 = 0x4c;
+pub const ASTORE_2: u8 = 0x4d;
+pub const ASTORE_3: u8 = 0x4e;
+pub const ATHROW: u8 = 0xbf;
+pub const BALOAD: u8 = 0x33;
+pub const BASTORE: u8 = 0x54;
+pub const BIPUSH: u8 = 0x10;
+pub const CALOAD: u8 = 0x34;
+pub const CASTORE: u8 = 0x55;
+pub const CHECKCAST: u8 = 0xc0;
+pub const D2F: u8 = 0x90;
+pub const D2I: u8 = 0x8e;
+pub const D2L: u8 = 0x8f;
+pub const DADD: u8 = 0x63;
+pub const DALOAD: u8 = 0x31;
+pub const DASTORE: u8 = 0x52;
+pub const DCMPG: u8 = 0x98;
+pub const DCMPL: u8 = 0x97;
+pub const DCONST_0: u8 = 0x0e;
+pub const DCONST_1: u8 = 0x0f;
+pub const DDIV: u8 = 0x6f;
+pub const DLOAD: u8 = 0x18;
+pub const DLOAD_0: u8 = 0x26;
+pub const DLOAD_1: u8 = 0x27;
+pub const DLOAD_2: u8 = 0x28;
+pub const DLOAD_3: u8 = 0x29;
+pub const DMUL: u8 = 0x6b;
+pub const DNEG: u8 = 0x77;
+pub const DREM: u8 = 0x73;
+pub const DRETURN: u8 = 0xaf;
+pub const DSTORE: u8 = 0x39;
+pub const DSTORE_0: u8 = 0x47;
+pub const DSTORE_1: u8 = 0x48;
+pub const DSTORE_2: u8 = 0x49;
+pub const DSTORE_3: u8 = 0x4a;
+pub const DSUB: u8 = 0x67;
+pub const DUP: u8 = 0x59;
+pub const DUP_X1: u8 = 0x5a;
+pub const DUP_X2: u8 = 0x5b;
+pub const DUP2: u8 = 0x5c;
+pub const DUP2_X1: u8 = 0x5d;
+pub const DUP2_X2: u8 = 0x5e;
+pub const F2D: u8 = 0x8d;
+pub const F2I: u8 = 0x8b;
+pub const F2L: u8 = 0x8c;
+pub const FADD: u8 = 0x62;
+pub const FALOAD: u8 = 0x30;
+pub const FASTORE: u8 = 0x51;
+pub const FCMPG: u8 = 0x96;
+pub const FCMPL: u8 = 0x95;
+pub const FCONST_0: u8 = 0x0b;
+pub const FCONST_1: u8 = 0x0c;
+pub const FCONST_2: u8 = 0x0d;
+pub const FDIV: u8 = 0x6e;
+pub const FLOAD: u8 = 0x17;
+pub const FLOAD_0: u8 = 0x22;
+pub const FLOAD_1: u8 = 0x23;
+pub const FLOAD_2: u8 = 0x24;
+pub const FLOAD_3: u8 = 0x25;
+pub const FMUL: u8 = 0x6a;
+pub const FNEG: u8 = 0x76;
+pub const FREM: u8 = 0x72;
+pub const FRETURN: u8 = 0xae;
+pub const FSTORE: u8 = 0x38;
+pub const FSTORE_0: u8 = 0x43;
+pub const FSTORE_1: u8 = 0x44;
+pub const FSTORE_2: u8 = 0x45;
+pub const FSTORE_3: u8 = 0x46;
+pub const FSUB: u8 = 0x66;
+pub const GETFIELD: u8 = 0xb4;
+pub const GETSTATIC: u8 = 0xb2;
+pub const GOTO: u8 = 0xa7;
+pub const GOTO_W: u8 = 0xc8;
+pub const I2B: u8 = 0x91;
+pub const I2C: u8 = 0x92;
+pub const I2D: u8 = 0x87;
+pub const I2F: u8 = 0x86;
+pub const I2L: u8 = 0x85;
+pub const I2S: u8 = 0x93;
+pub const IADD: u8 = 0x60;
+pub const IALOAD: u8 = 0x2e;
+pub const IAND: u8 = 0x7e;
+pub const IASTORE: u8 = 0x4f;
+pub const ICONST_M1: u8 = 0x02;
+pub const ICONST_0: u8 = 0x03;
+pub const ICONST_1: u8 = 0x04;
+pub const ICONST_2: u8 = 0x05;
+pub const ICONST_3: u8 = 0x06;
+pub const ICONST_4: u8 = 0x07;
+pub const ICONST_5: u8 = 0x08;
+pub const IDIV: u8 = 0x6c;
+pub const IF_ACMPEQ: u8 = 0xa5;
+pub const IF_ACMPNE: u8 = 0xa6;
+pub const IF_ICMPEQ: u8 = 0x9f;
+pub const IF_ICMPNE: u8 = 0xa0;
+pub const IF_ICMPLT: u8 = 0xa1;
+pub const IF_ICMPGE: u8 = 0xa2;
+pub const IF_ICMPGT: u8 = 0xa3;
+pub const IF_ICMPLE: u8 = 0xa4;
+pub const IFEQ: u8 = 0x99;
+pub const IFNE: u8 = 0x9a;
+pub const IFLT: u8 = 0x9b;
+pub const IFGE: u8 = 0x9c;
+pub const IFGT: u8 = 0x9d;
+pub const IFLE: u8 = 0x9e;
+pub const IFNONNULL: u8 = 0xc7;
+pub const IFNULL: u8 = 0xc6;
+pub const IINC: u8 = 0x84;
+pub const ILOAD: u8 = 0x15;
+pub const ILOAD_0: u8 = 0x1a;
+pub const ILOAD_1: u8 = 0x1b;
+pub const ILOAD_2: u8 = 0x1c;
+pub const ILOAD_3: u8 = 0x1d;
+pub const IMUL: u8 = 0x68;
+pub const INEG: u8 = 0x74;
+pub const INSTANCEOF: u8 = 0xc1;
+pub const INVOKEDYNAMIC: u8 = 0xba;
+pub const INVOKEINTERFACE: u8 = 0xb9;
+pub const INVOKESPECIAL: u8 = 0xb7;
+pub const INVOKESTATIC: u8 = 0xb8;
+pub const INVOKEVIRTUAL: u8 = 0xb6;
+pub const IOR: u8 = 0x80;
+pub const IREM: u8 = 0x70;
+pub const IRETURN: u8 = 0xac;
+pub const ISHL: u8 = 0x78;
+pub const ISHR: u8 = 0x7a;
+pub const ISTORE: u8 = 0x36;
+pub const ISTORE_0: u8 = 0x3b;
+pub const ISTORE_1: u8 = 0x3c;
+pub const ISTORE_2: u8 = 0x3d;
+pub const ISTORE_3: u8 = 0x3e;
+pub const ISUB: u8 = 0x64;
+pub const IUSHR: u8 = 0x7c;
+pub const IXOR: u8 = 0x82;
+pub const JSR: u8 = 0xa8;
+pub const JSR_W: u8 = 0xc9;
+pub const L2D: u8 = 0x8a;
+pub const L2F: u8 = 0x89;
+pub const L2I: u8 = 0x88;
+pub const LADD: u8 = 0x61;
+pub const LALOAD: u8 = 0x2f;
+pub const LAND: u8 = 0x7f;
+pub const LASTORE: u8 = 0x50;
+pub const LCMP: u8 = 0x94;
+pub const LCONST_0: u8 = 0x09;
+pub const LCONST_1: u8 = 0x0a;
+pub const LDC: u8 = 0x12;
+pub const LDC_W: u8 = 0x13;
+pub const LDC2_W: u8 = 0x14;
+pub const LDIV: u8 = 0x6d;
+pub const LLOAD: u8 = 0x16;
+pub const LLOAD_0: u8 = 0x1e;
+pub const LLOAD_1: u8 = 0x1f;
+pub const LLOAD_2: u8 = 0x20;
+pub const LLOAD_3: u8 = 0x21;
+pub const LMUL: u8 = 0x69;
+pub const LNEG: u8 = 0x75;
+pub const LOOKUPSWITCH: u8 = 0xab;
+pub const LOR: u8 = 0x81;
+pub const LREM: u8 = 0x71;
+pub const LRETURN: u8 = 0xad;
+pub const LSHL: u8 = 0x79;
+pub const LSHR: u8 = 0x7b;
+pub const LSTORE: u8 = 0x37;
+pub const LSTORE_0: u8 = 0x3f;
+pub const LSTORE_1: u8 = 0x40;
+pub const LSTORE_2: u8 = 0x41;
+pub const LSTORE_3: u8 = 0x42;
+pub const LSUB: u8 = 0x65;
+pub const LUSHR: u8 = 0x7d;
+pub const LXOR: u8 = 0x83;
+pub const MONITORENTER: u8 = 0xc2;
+pub const MONITOREXIT: u8 = 0xc3;
+pub const MULTIANEWARRAY: u8 = 0xc5;
+pub const NEW: u8 = 0xbb;
+pub const NEWARRAY: u8 = 0xbc;
+pub const NOP: u8 = 0x00;
+pub const POP: u8 = 0x57;
+pub const POP2: u8 = 0x58;
+pub const PUTFIELD: u8 = 0xb5;
+pub const PUTSTATIC: u8 = 0xb3;
+pub const RET: u8 = 0xa9;
+pub const RETURN: u8 = 0xb1;
+pub const SALOAD: u8 = 0x35;
+pub const SASTORE: u8 = 0x56;
+pub const SIPUSH: u8 = 0x11;
+pub const SWAP: u8 = 0x5f;
+pub const TABLESWITCH: u8 = 0xaa;
+pub const WIDE: u8 = 0xc4;
 
 #[derive(Debug)]
 pub enum DisassemblerError {
@@ -217,7 +217,7 @@ pub fn disassemble_code(buffer: &Vec<u8>) -> Result<Vec<TaggedInstruction>, Disa
     while bytes.len() > 0 {
         let start_size = bytes.len() as u16;
 
-        let instruction = parse_instruction(&mut bytes)?;
+        let instruction = parse_instruction(&mut bytes, index)?;
 
         let end_size = bytes.len() as u16;
         let offset = start_size - end_size;
@@ -235,7 +235,9 @@ pub fn disassemble_code(buffer: &Vec<u8>) -> Result<Vec<TaggedInstruction>, Disa
     Ok(instructions)
 }
 
-fn parse_instruction(bytes: &mut Vec<u8>) -> Result<Instruction, DisassemblerError> {
+// The address of the instruction is needed to skip the padding that aligns the operands of the
+// switch instructions to a multiple of four bytes from the start of the code.
+fn parse_instruction(bytes: &mut Vec<u8>, address: u16) -> Result<Instruction, DisassemblerError> {
     let opcode = read_u8(bytes)?;
 
     match opcode {
@@ -608,7 +610,7 @@ fn parse_instruction(bytes: &mut Vec<u8>) -> Result<Instruction, DisassemblerErr
         x if x == LLOAD => {
             let index = read_u8(bytes)?;
 
-            Ok(Instruction::Lload)
+            Ok(Instruction::Lload { index })
         },
         x if x == LLOAD_0 => Ok(Instruction::Lload0),
         x if x == LLOAD_1 => Ok(Instruction::Lload1),
@@ -616,7 +618,19 @@ fn parse_instruction(bytes: &mut Vec<u8>) -> Result<Instruction, DisassemblerErr
         x if x == LLOAD_3 => Ok(Instruction::Lload3),
         x if x == LMUL => Ok(Instruction::Lmul),
         x if x == LNEG => Ok(Instruction::Lneg),
-        x if x == LOOKUPSWITCH => Ok(Instruction::Lookupswitch {}),
+        x if x == LOOKUPSWITCH => {
+            skip_padding(bytes, address)?;
+            let default = read_i32(bytes)?;
+            let npairs = read_i32(bytes)?;
+            let mut pairs = Vec::new();
+            for _ in 0..npairs {
+                let key = read_i32(bytes)?;
+                let offset = read_i32(bytes)?;
+                pairs.push((key, offset));
+            }
+
+            Ok(Instruction::Lookupswitch { default, pairs })
+        },
         x if x == LOR => Ok(Instruction::Lor),
         x if x == LREM => Ok(Instruction::Lrem),
         x if x == LRETURN => Ok(Instruction::Lreturn),
@@ -682,8 +696,34 @@ fn parse_instruction(bytes: &mut Vec<u8>) -> Result<Instruction, DisassemblerErr
             Ok(Instruction::Sipush(value as i32))
         },
         x if x == SWAP => Ok(Instruction::Swap),
-        x if x == TABLESWITCH => Ok(Instruction::Tableswitch {}),
-        x if x == WIDE => Ok(Instruction::Wide {}),
+        x if x == TABLESWITCH => {
+            skip_padding(bytes, address)?;
+            let default = read_i32(bytes)?;
+            let low = read_i32(bytes)?;
+            let high = read_i32(bytes)?;
+            let mut offsets = Vec::new();
+            for _ in low as i64..=high as i64 {
+                offsets.push(read_i32(bytes)?);
+            }
+
+            Ok(Instruction::Tableswitch { default, low, offsets })
+        },
+        x if x == WIDE => {
+            let opcode = read_u8(bytes)?;
+            let index = read_u16(bytes)?;
+
+            match opcode {
+                IINC => {
+                    let constant = read_u16(bytes)? as i16;
+
+                    Ok(Instruction::WideIinc { index, constant })
+                },
+                ILOAD | FLOAD | ALOAD | LLOAD | DLOAD | ISTORE | FSTORE | ASTORE | LSTORE | DSTORE | RET => {
+                    Ok(Instruction::Wide { opcode, index })
+                },
+                x => Err(DisassemblerError::InvalidOpcode(x))
+            }
+        },
         x => Err(DisassemblerError::InvalidOpcode(x))
     }
 }
@@ -704,3 +744,24 @@ fn read_u16(buffer: &mut Vec<u8>) -> Result<u16, DisassemblerError> {
 
     Ok((b1 << 8) + b2)
 }
+
+fn read_i32(buffer: &mut Vec<u8>) -> Result<i32, DisassemblerError> {
+    let high = read_u16(buffer)? as u32;
+    let low = read_u16(buffer)? as u32;
+
+    Ok(((high << 16) + low) as i32)
+}
+
+// Skips the zero to three bytes after the opcode of a switch at the given address.
+fn skip_padding(buffer: &mut Vec<u8>, address: u16) -> Result<(), DisassemblerError> {
+    for _ in 0..switch_padding(address as usize) {
+        read_u8(buffer)?;
+    }
+    Ok(())
+}
+
+// The number of padding bytes after the opcode of a switch at the given address, which align
+// its operands to a multiple of four bytes from the start of the code (JVMS $6.5).
+pub fn switch_padding(address: usize) -> usize {
+    (4 - (address + 1) % 4) % 4
+}
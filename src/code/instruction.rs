@@ -1,11 +1,11 @@
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub struct TaggedInstruction {
     pub instruction: Instruction,
     pub index: u16
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Instruction {
     Aaload,
     Aastore,
@@ -170,14 +170,15 @@ pub enum Instruction {
     LdcW { index: u16 },
     Ldc2W { index: u16 },
     Ldiv,
-    Lload,
+    Lload { index: u8 },
     Lload0,
     Lload1,
     Lload2,
     Lload3,
     Lmul,
     Lneg,
-    Lookupswitch {},
+    // The offsets of lookupswitch and tableswitch are relative to the address of the switch.
+    Lookupswitch { default: i32, pairs: Vec<(i32, i32)> },
     Lor,
     Lrem,
     Lreturn,
@@ -207,8 +208,11 @@ pub enum Instruction {
     Sastore,
     Sipush(i32),
     Swap,
-    Tableswitch {},
-    Wide {}
+    // The offsets of the keys low, low + 1, and so on.
+    Tableswitch { default: i32, low: i32, offsets: Vec<i32> },
+    // A load, store or ret of a local variable with a two-byte index, by the opcode it modifies.
+    Wide { opcode: u8, index: u16 },
+    WideIinc { index: u16, constant: i16 }
 }
 
 impl Instruction {
@@ -1,2 +1,3 @@
+pub mod assembler;
 pub mod disassembler;
 pub mod instruction;
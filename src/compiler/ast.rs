@@ -0,0 +1,466 @@
+// The syntax tree of a Java compilation unit, as produced by the parser. Names are not resolved
+// yet: a.b.c is a chain of field accesses on the simple name a, whatever a turns out to be.
+
+use compiler::Position;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Identifier {
+    pub name: String,
+    pub position: Position
+}
+
+// A dotted name, e.g. in package and import declarations.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QualifiedName {
+    pub parts: Vec<Identifier>
+}
+
+impl QualifiedName {
+
+    pub fn position(&self) -> Position {
+        self.parts[0].position
+    }
+
+    pub fn to_dotted(&self) -> String {
+        self.parts.iter().map(|part| part.name.as_str()).collect::<Vec<&str>>().join(".")
+    }
+
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompilationUnit {
+    pub package: Option<PackageDeclaration>,
+    pub imports: Vec<Import>,
+    pub types: Vec<TypeDeclaration>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackageDeclaration {
+    pub annotations: Vec<Annotation>,
+    pub name: QualifiedName
+}
+
+// import a.b.C; import a.b.*; import static a.b.C.m; import static a.b.C.*;
+#[derive(Clone, Debug, PartialEq)]
+pub struct Import {
+    pub name: QualifiedName,
+    pub is_static: bool,
+    pub on_demand: bool,
+    pub position: Position
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Modifier {
+    Public, Protected, Private, Static, Abstract, Final, Native, Synchronized, Transient, Volatile,
+    Strictfp, Default, Sealed, NonSealed
+}
+
+impl Modifier {
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Modifier::Public => "public",
+            Modifier::Protected => "protected",
+            Modifier::Private => "private",
+            Modifier::Static => "static",
+            Modifier::Abstract => "abstract",
+            Modifier::Final => "final",
+            Modifier::Native => "native",
+            Modifier::Synchronized => "synchronized",
+            Modifier::Transient => "transient",
+            Modifier::Volatile => "volatile",
+            Modifier::Strictfp => "strictfp",
+            Modifier::Default => "default",
+            Modifier::Sealed => "sealed",
+            Modifier::NonSealed => "non-sealed"
+        }
+    }
+
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Modifiers {
+    pub modifiers: Vec<(Modifier, Position)>,
+    pub annotations: Vec<Annotation>
+}
+
+impl Modifiers {
+
+    pub fn contains(&self, modifier: Modifier) -> bool {
+        self.modifiers.iter().any(|&(m, _)| m == modifier)
+    }
+
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Annotation {
+    pub name: QualifiedName,
+    // A single unnamed element value is named "value".
+    pub arguments: Vec<(Identifier, ElementValue)>,
+    pub position: Position
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ElementValue {
+    Expression(Expression),
+    Annotation(Annotation),
+    Array(Vec<ElementValue>)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrimitiveType {
+    Boolean, Byte, Short, Char, Int, Long, Float, Double
+}
+
+impl PrimitiveType {
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PrimitiveType::Boolean => "boolean",
+            PrimitiveType::Byte => "byte",
+            PrimitiveType::Short => "short",
+            PrimitiveType::Char => "char",
+            PrimitiveType::Int => "int",
+            PrimitiveType::Long => "long",
+            PrimitiveType::Float => "float",
+            PrimitiveType::Double => "double"
+        }
+    }
+
+}
+
+// Types as written in the source. Type annotations are parsed but not kept.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Type {
+    Primitive(PrimitiveType, Position),
+    Class(ClassType),
+    Array(Box<Type>),
+    Void(Position)
+}
+
+impl Type {
+
+    pub fn position(&self) -> Position {
+        match *self {
+            Type::Primitive(_, position) | Type::Void(position) => position,
+            Type::Class(ref class_type) => class_type.position(),
+            Type::Array(ref component) => component.position()
+        }
+    }
+
+}
+
+// A possibly qualified class or interface type, e.g. java.util.Map.Entry<K, V>. Each part has
+// its own type arguments.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClassType {
+    pub parts: Vec<ClassTypePart>
+}
+
+impl ClassType {
+
+    pub fn position(&self) -> Position {
+        self.parts[0].name.position
+    }
+
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClassTypePart {
+    pub name: Identifier,
+    // Empty for a raw type, and None for a diamond in a class instance creation.
+    pub arguments: Option<Vec<TypeArgument>>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TypeArgument {
+    Type(Type),
+    Wildcard(Option<WildcardBound>, Position)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum WildcardBound {
+    Extends(Type),
+    Super(Type)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypeParameter {
+    pub annotations: Vec<Annotation>,
+    pub name: Identifier,
+    pub bounds: Vec<Type>
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TypeKind {
+    Class, Interface, Enum, Record, Annotation
+}
+
+// A class, interface, enum, record or annotation interface declaration. Fields that don't apply to
+// a kind are left empty.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypeDeclaration {
+    pub kind: TypeKind,
+    pub modifiers: Modifiers,
+    pub name: Identifier,
+    pub type_parameters: Vec<TypeParameter>,
+    // The superclass of a class, or the superinterfaces of an interface.
+    pub extends: Vec<Type>,
+    pub implements: Vec<Type>,
+    pub permits: Vec<Type>,
+    pub record_components: Vec<Parameter>,
+    pub enum_constants: Vec<EnumConstant>,
+    pub members: Vec<Member>,
+    pub position: Position
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnumConstant {
+    pub annotations: Vec<Annotation>,
+    pub name: Identifier,
+    pub arguments: Vec<Expression>,
+    pub body: Option<Vec<Member>>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Member {
+    Field(FieldDeclaration),
+    Method(MethodDeclaration),
+    Constructor(ConstructorDeclaration),
+    Initializer { is_static: bool, body: Block },
+    Type(TypeDeclaration)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldDeclaration {
+    pub modifiers: Modifiers,
+    pub field_type: Type,
+    pub declarators: Vec<VariableDeclarator>
+}
+
+// int a, b[] = {}; declares a with type int and b with type int[].
+#[derive(Clone, Debug, PartialEq)]
+pub struct VariableDeclarator {
+    pub name: Identifier,
+    pub dimensions: usize,
+    pub initializer: Option<Expression>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Parameter {
+    pub modifiers: Modifiers,
+    pub parameter_type: Type,
+    pub variable_arity: bool,
+    pub name: Identifier
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MethodDeclaration {
+    pub modifiers: Modifiers,
+    pub type_parameters: Vec<TypeParameter>,
+    pub return_type: Type,
+    pub name: Identifier,
+    pub parameters: Vec<Parameter>,
+    pub throws: Vec<Type>,
+    // None for abstract and native methods.
+    pub body: Option<Block>,
+    // The default value of an annotation interface element.
+    pub default_value: Option<ElementValue>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConstructorDeclaration {
+    pub modifiers: Modifiers,
+    pub type_parameters: Vec<TypeParameter>,
+    pub name: Identifier,
+    // None for the compact canonical constructor of a record.
+    pub parameters: Option<Vec<Parameter>>,
+    pub throws: Vec<Type>,
+    pub body: Block
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block {
+    pub statements: Vec<Statement>,
+    pub position: Position,
+    // The position of the closing brace.
+    pub end: Position
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub position: Position
+}
+
+// A local variable declaration. The type is None for var.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LocalVariable {
+    pub modifiers: Modifiers,
+    pub variable_type: Option<Type>,
+    pub declarators: Vec<VariableDeclarator>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StatementKind {
+    Block(Block),
+    LocalVariable(LocalVariable),
+    LocalType(TypeDeclaration),
+    Expression(Expression),
+    Empty,
+    If { condition: Expression, then: Box<Statement>, otherwise: Option<Box<Statement>> },
+    While { condition: Expression, body: Box<Statement> },
+    DoWhile { body: Box<Statement>, condition: Expression },
+    For { init: Vec<Statement>, condition: Option<Expression>, update: Vec<Expression>, body: Box<Statement> },
+    ForEach { variable: LocalVariable, iterable: Expression, body: Box<Statement> },
+    Labeled { label: Identifier, body: Box<Statement> },
+    Break(Option<Identifier>),
+    Continue(Option<Identifier>),
+    Return(Option<Expression>),
+    Throw(Expression),
+    Yield(Expression),
+    Switch(Switch),
+    Synchronized { lock: Expression, body: Block },
+    Try { resources: Vec<Resource>, body: Block, catches: Vec<Catch>, finally: Option<Block> },
+    Assert { condition: Expression, message: Option<Expression> },
+    // this(...) or super(...) at the start of a constructor body. A qualified superclass
+    // constructor invocation has the outer instance as qualifier.
+    ConstructorInvocation { is_super: bool, qualifier: Option<Expression>, type_arguments: Vec<TypeArgument>, arguments: Vec<Expression> }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Resource {
+    Declaration(LocalVariable),
+    // An effectively final variable or a field access.
+    Expression(Expression)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Catch {
+    pub modifiers: Modifiers,
+    // More than one type for a multi-catch clause.
+    pub types: Vec<Type>,
+    pub name: Identifier,
+    pub body: Block,
+    pub position: Position
+}
+
+// A switch statement or expression.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Switch {
+    pub selector: Box<Expression>,
+    pub cases: Vec<SwitchCase>,
+    // The position of the closing brace.
+    pub end: Position
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SwitchCase {
+    // Empty for default.
+    pub labels: Vec<Expression>,
+    pub body: SwitchBody,
+    pub position: Position
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SwitchBody {
+    // case a: statements, which may fall through to the next case.
+    Statements(Vec<Statement>),
+    // case a -> expression;
+    Expression(Expression),
+    // case a -> { ... } or case a -> throw ...;
+    Statement(Box<Statement>)
+}
+
+// An expression is positioned where javac reports errors in it: at the operator of a unary,
+// binary, assignment, conditional or instanceof expression, at the '.' of a field access or
+// qualified method call, at the '[' of an array access, and otherwise at its first token.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub position: Position
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Literal {
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Char(u16),
+    String(Vec<u16>),
+    Boolean(bool),
+    Null
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnaryOperator {
+    Plus, Minus, BitwiseNot, Not, PreIncrement, PreDecrement, PostIncrement, PostDecrement
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Or, And, BitwiseOr, BitwiseXor, BitwiseAnd, Equal, NotEqual, Less, Greater, LessOrEqual,
+    GreaterOrEqual, ShiftLeft, ShiftRight, UnsignedShiftRight, Add, Subtract, Multiply, Divide,
+    Remainder
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LambdaParameter {
+    pub modifiers: Modifiers,
+    // None when the type is inferred, either implicitly or with var.
+    pub parameter_type: Option<Type>,
+    pub variable_arity: bool,
+    pub name: Identifier
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LambdaBody {
+    Expression(Box<Expression>),
+    Block(Block)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MethodReferenceTarget {
+    Expression(Box<Expression>),
+    Type(Type),
+    Super(Option<QualifiedName>)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExpressionKind {
+    Literal(Literal),
+    // A simple name, which may turn out to be a variable, a type or a package.
+    Name(Identifier),
+    FieldAccess { target: Box<Expression>, name: Identifier },
+    // super.name or Outer.super.name.
+    SuperFieldAccess { qualifier: Option<QualifiedName>, name: Identifier },
+    ArrayAccess { array: Box<Expression>, index: Box<Expression> },
+    // The position of the '(' is where javac reports the exceptions the call may throw.
+    MethodCall { target: Option<Box<Expression>>, type_arguments: Vec<TypeArgument>, name: Identifier, arguments: Vec<Expression>, arguments_position: Position },
+    // super.m() or Outer.super.m().
+    SuperMethodCall { qualifier: Option<QualifiedName>, type_arguments: Vec<TypeArgument>, name: Identifier, arguments: Vec<Expression>, arguments_position: Position },
+    // new C(...), outer.new Inner(...), or an anonymous class when there is a body.
+    New { outer: Option<Box<Expression>>, type_arguments: Vec<TypeArgument>, class_type: ClassType, arguments: Vec<Expression>, body: Option<Vec<Member>> },
+    // new int[n][], or new int[][] { ... } with an initializer and no dimension expressions.
+    NewArray { element_type: Type, dimensions: Vec<Expression>, extra_dimensions: usize, initializer: Option<Vec<Expression>> },
+    // { a, b } in a variable initializer.
+    ArrayInitializer(Vec<Expression>),
+    Unary { operator: UnaryOperator, operand: Box<Expression> },
+    Binary { operator: BinaryOperator, left: Box<Expression>, right: Box<Expression> },
+    // a = b, or a op= b with the operator.
+    Assign { operator: Option<BinaryOperator>, target: Box<Expression>, value: Box<Expression> },
+    Conditional { condition: Box<Expression>, then: Box<Expression>, otherwise: Box<Expression> },
+    // a instanceof T, or a instanceof T t with a pattern variable.
+    InstanceOf { expression: Box<Expression>, instance_type: Type, binding: Option<Identifier> },
+    // (T & I1 & I2) e has the additional bounds I1 and I2.
+    Cast { cast_type: Type, bounds: Vec<Type>, expression: Box<Expression> },
+    Lambda { parameters: Vec<LambdaParameter>, body: LambdaBody },
+    // Foo::bar, or Foo::new with the name new.
+    MethodReference { target: MethodReferenceTarget, type_arguments: Vec<TypeArgument>, name: Identifier },
+    // this or Outer.this.
+    This(Option<QualifiedName>),
+    ClassLiteral(Type),
+    Switch(Switch),
+    Parenthesized(Box<Expression>)
+}
@@ -0,0 +1,4801 @@
+// Attribution (JLS §6, §15): names in the syntax tree are resolved to packages, classes,
+// variables and methods, every expression is given a type, and constant expressions are folded.
+// Mistakes are reported with the messages javac reports them with. Like javac, each top-level
+// class is attributed and then, as long as nothing has been reported, checked by flow analysis.
+//
+// Classes are completed in phases, so that declarations may refer to each other in any order:
+// the classes of all source files are entered first, then their headers (type parameters and
+// supertypes) are resolved, then their members, and finally the bodies of their methods.
+
+use class::{class_flags, field, method};
+use compiler::{Diagnostic, Position};
+use compiler::ast::{self, BinaryOperator, Block, CompilationUnit, Expression, ExpressionKind, Identifier, LambdaBody, Literal, Member,
+                    MethodReferenceTarget, Modifier, Modifiers, PrimitiveType, QualifiedName, Statement, StatementKind, Switch, SwitchBody,
+                    TypeArgument, TypeDeclaration, TypeKind, UnaryOperator};
+use compiler::constant::{self, Constant};
+use compiler::flow;
+use compiler::symbols::{self, ClassSymbol, FieldSymbol, MethodMember, MethodSymbol, Symbols};
+use compiler::types::{self, ClassType, Type, TypeVariable, Wildcard, OBJECT};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::rc::Rc;
+
+// What a name, or a field access that may turn out to be a qualified name, refers to.
+#[derive(Clone, Debug)]
+pub enum Reference {
+    // A local variable or parameter, by index into the locals.
+    Local(usize),
+    // A field as declared. An instance field named by its simple name also has the class whose
+    // instance it belongs to, which is the class the name is used in or an enclosing class.
+    Field(FieldSymbol, Option<String>),
+    Class(String),
+    Package(String)
+}
+
+#[derive(Clone, Debug)]
+pub struct Local {
+    pub name: String,
+    pub local_type: Type,
+    pub is_final: bool,
+    // The value of a final local variable initialized with a constant expression.
+    pub constant: Option<Constant>
+}
+
+// A resolved method or constructor invocation.
+#[derive(Clone, Debug)]
+pub struct Invocation {
+    // The method as declared, which gives the descriptor to invoke it with.
+    pub method: MethodSymbol,
+    // The parameter types with type arguments substituted and inferred.
+    pub parameters: Vec<Type>,
+    pub result: Type,
+    // The exceptions the method declares it throws, with type arguments substituted and
+    // inferred.
+    pub throws: Vec<Type>,
+    // Whether the trailing arguments are passed in an array to a variable arity method.
+    pub varargs: bool,
+    // For an instance method called by its simple name, the class whose instance receives the
+    // call: the class the call is in or an enclosing class.
+    pub implicit_this: Option<String>
+}
+
+// The functional interface a lambda expression or method reference implements.
+#[derive(Clone, Debug)]
+pub struct Function {
+    pub interface: Type,
+    // The abstract method of the interface, as declared and as a member of the interface type.
+    pub method: MethodSymbol,
+    pub function: MethodSymbol,
+    // The method or constructor a method reference refers to.
+    pub target: Option<Invocation>
+}
+
+// The result of attribution. Syntax tree nodes are looked up by address, so the compilation
+// units must not move while it is in use.
+pub struct Attribution {
+    pub symbols: Symbols,
+    // The binary names of the classes declared in the source files, including local and
+    // anonymous classes.
+    pub classes: Vec<String>,
+    types: HashMap<usize, Type>,
+    constants: HashMap<usize, Constant>,
+    references: HashMap<usize, Reference>,
+    invocations: HashMap<usize, Invocation>,
+    functions: HashMap<usize, Function>,
+    locals: Vec<Local>,
+    declarations: HashMap<usize, usize>,
+    class_names: HashMap<usize, String>,
+    caught: HashMap<usize, Vec<Type>>,
+    methods: HashMap<usize, MethodSymbol>,
+    resolved_types: HashMap<usize, Type>
+}
+
+fn address<T>(node: &T) -> usize {
+    node as *const T as usize
+}
+
+impl Attribution {
+
+    pub fn type_of(&self, expression: &Expression) -> Option<&Type> {
+        self.types.get(&address(expression))
+    }
+
+    pub fn constant(&self, expression: &Expression) -> Option<&Constant> {
+        self.constants.get(&address(expression))
+    }
+
+    pub fn reference(&self, expression: &Expression) -> Option<&Reference> {
+        self.references.get(&address(expression))
+    }
+
+    // The invocation of a method call, a class instance creation, an explicit constructor
+    // invocation statement, or the implicit superclass constructor invocation of a constructor
+    // declaration or of the default constructor of a class declaration.
+    pub fn invocation<T>(&self, node: &T) -> Option<&Invocation> {
+        self.invocations.get(&address(node))
+    }
+
+    pub fn function(&self, expression: &Expression) -> Option<&Function> {
+        self.functions.get(&address(expression))
+    }
+
+    pub fn local(&self, index: usize) -> &Local {
+        &self.locals[index]
+    }
+
+    pub fn local_count(&self) -> usize {
+        self.locals.len()
+    }
+
+    // The local variable declared by a name in a variable declarator, parameter, catch clause
+    // or pattern.
+    pub fn declared_local(&self, name: &Identifier) -> Option<usize> {
+        self.declarations.get(&address(name)).cloned()
+    }
+
+    // The binary name of the class declared by a type declaration, by an enum constant with a
+    // body or by a class instance creation with a body.
+    pub fn class_name<T>(&self, node: &T) -> Option<&str> {
+        self.class_names.get(&address(node)).map(String::as_str)
+    }
+
+    // The exception classes a catch clause catches, more than one for a multi-catch clause.
+    pub fn caught(&self, catch: &ast::Catch) -> &[Type] {
+        self.caught.get(&address(catch)).map_or(&[], Vec::as_slice)
+    }
+
+    // The method a method or constructor declaration declares.
+    pub fn method<T>(&self, declaration: &T) -> Option<&MethodSymbol> {
+        self.methods.get(&address(declaration))
+    }
+
+    // The type a type in the source names, e.g. the type of an instanceof expression.
+    pub fn resolved_type(&self, written: &ast::Type) -> Option<&Type> {
+        self.resolved_types.get(&address(written))
+    }
+
+}
+
+// Attributes the compilation units, given with the names of their files, against the classes
+// of the class path the symbols read from.
+pub fn attribute(units: &[(String, CompilationUnit)], symbols: Symbols) -> Result<Attribution, Vec<Diagnostic>> {
+    let mut attr = Attr::new(units, symbols);
+
+    for (index, (file, unit)) in units.iter().enumerate() {
+        attr.unit = index;
+        let package = unit.package.as_ref().map_or_else(String::new, |package| package.name.to_dotted().replace('.', "/"));
+        for declaration in unit.types.iter() {
+            let name = if package.is_empty() { declaration.name.name.clone() } else { format!("{}/{}", package, declaration.name.name) };
+            if declaration.modifiers.contains(Modifier::Public) && Path::new(file).file_stem().is_some_and(|stem| *stem != *declaration.name.name) {
+                let message = format!("{} {} is public, should be declared in a file named {}.java", kind_name(declaration.kind), declaration.name.name, declaration.name.name);
+                attr.error(declaration.name.position, &message);
+            }
+            attr.enter(name, declaration, None);
+        }
+    }
+
+    let entered = attr.entered.clone();
+    for &(ref name, unit, declaration) in entered.iter() {
+        attr.unit = unit;
+        attr.check_imports(unit);
+        attr.complete_header(name, declaration);
+    }
+    for &(ref name, unit, declaration) in entered.iter() {
+        attr.unit = unit;
+        attr.check_cycles(name, declaration);
+    }
+    for &(ref name, unit, declaration) in entered.iter() {
+        attr.unit = unit;
+        attr.complete_members(name, Body::of(declaration));
+    }
+
+    for (index, (_, unit)) in units.iter().enumerate() {
+        attr.unit = index;
+        for declaration in unit.types.iter() {
+            let name = match attr.result.class_names.get(&address(declaration)) {
+                Some(name) => name.clone(),
+                None => continue
+            };
+            attr.attribute_class(&name, Body::of(declaration));
+            // Like javac, flow analysis is skipped once there are errors.
+            if attr.diagnostics.is_empty() {
+                let file = &units[index].0;
+                attr.diagnostics.extend(flow::analyze(file, declaration, &attr.result));
+            }
+        }
+    }
+
+    attr.define_constants();
+    if attr.diagnostics.is_empty() {
+        Ok(attr.result)
+    } else {
+        Err(attr.diagnostics)
+    }
+}
+
+fn kind_name(kind: TypeKind) -> &'static str {
+    match kind {
+        TypeKind::Class => "class",
+        TypeKind::Interface => "interface",
+        TypeKind::Enum => "enum",
+        TypeKind::Record => "record",
+        TypeKind::Annotation => "@interface"
+    }
+}
+
+// The body of a class: a type declaration, or the members of an anonymous class.
+#[derive(Clone, Copy)]
+struct Body<'a> {
+    kind: TypeKind,
+    declaration: Option<&'a TypeDeclaration>,
+    members: &'a [Member]
+}
+
+impl<'a> Body<'a> {
+
+    fn of(declaration: &'a TypeDeclaration) -> Body<'a> {
+        Body { kind: declaration.kind, declaration: Some(declaration), members: &declaration.members }
+    }
+
+    fn anonymous(members: &'a [Member]) -> Body<'a> {
+        Body { kind: TypeKind::Class, declaration: None, members }
+    }
+
+}
+
+// A lexical scope in a method body: local variables and local classes, by simple name.
+#[derive(Default)]
+struct Scope {
+    locals: Vec<usize>,
+    classes: Vec<(String, String)>
+}
+
+// What a return statement returns from.
+enum Returns {
+    // A method or constructor, with its return type, which is void for constructors.
+    Method(Type),
+    Initializer,
+    // A lambda body with the return type of its function type, or None while that type is being
+    // inferred, in which case the types of the returned expressions are collected.
+    Lambda(Option<Type>, Vec<Type>)
+}
+
+// What a break, continue or yield statement may jump out of.
+enum Jump {
+    Loop,
+    Switch,
+    Label(String, bool),
+    // A switch expression, with the type its results are expected to have, and the types of
+    // the results found so far.
+    SwitchExpression(Option<Type>, Vec<Type>),
+    Lambda
+}
+
+// The context of the code being attributed in one class. A local or anonymous class gets a
+// frame on top of the frame of the code it is declared in.
+struct Frame {
+    class: String,
+    // In static members, and in the arguments of an explicit constructor invocation, there is
+    // no this.
+    is_static: bool,
+    type_variables: Vec<TypeVariable>,
+    scopes: Vec<Scope>,
+    // How javac names the method the code is in, e.g. method main(String[]).
+    location: String,
+    returns: Vec<Returns>,
+    jumps: Vec<Jump>,
+    // Whether the code is the body of a constructor, where this() and super() may appear.
+    in_constructor: bool
+}
+
+impl Frame {
+
+    fn new(class: &str) -> Frame {
+        Frame {
+            class: String::from(class),
+            is_static: false,
+            type_variables: Vec::new(),
+            scopes: Vec::new(),
+            location: String::new(),
+            returns: Vec::new(),
+            jumps: Vec::new(),
+            in_constructor: false
+        }
+    }
+
+}
+
+// A final field of a source class whose initializer may be a constant expression. It is
+// evaluated the first time the value is needed, which may be before the class is attributed.
+#[derive(Clone)]
+enum PendingConstant<'a> {
+    Unevaluated(usize, &'a Expression, Type),
+    Evaluating,
+    Evaluated(Option<Constant>)
+}
+
+// What an expression before a '.' turns out to be.
+enum Qualifier {
+    Value(Type),
+    Class(Type),
+    Package(String)
+}
+
+// The three phases of overload resolution (JLS §15.12.2.2-4).
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Strict,
+    Loose,
+    Varargs
+}
+
+struct Attr<'a> {
+    units: &'a [(String, CompilationUnit)],
+    unit: usize,
+    result: Attribution,
+    diagnostics: Vec<Diagnostic>,
+    // Errors are not reported while the initializer of a constant is evaluated ahead of time.
+    silent: usize,
+    entered: Vec<(String, usize, &'a TypeDeclaration)>,
+    frames: Vec<Frame>,
+    constants: HashMap<(String, String), PendingConstant<'a>>,
+    imports_checked: Vec<usize>,
+    // Final locals declared without an initializer, and final parameters, which are told apart
+    // in errors about assigning to them.
+    blank_finals: HashSet<usize>,
+    final_parameters: HashSet<usize>,
+    // Final fields of source classes declared without an initializer, by class and name.
+    blank_final_fields: HashSet<(String, String)>,
+    // How deeply nested in method arguments the expression being attributed is. A generic
+    // method called in an argument has type arguments left uninferred if nothing constrains
+    // them, rather than their bounds.
+    in_argument: usize
+}
+
+impl<'a> Attr<'a> {
+
+    fn new(units: &'a [(String, CompilationUnit)], symbols: Symbols) -> Attr<'a> {
+        Attr {
+            units,
+            unit: 0,
+            result: Attribution {
+                symbols,
+                classes: Vec::new(),
+                types: HashMap::new(),
+                constants: HashMap::new(),
+                references: HashMap::new(),
+                invocations: HashMap::new(),
+                functions: HashMap::new(),
+                locals: Vec::new(),
+                declarations: HashMap::new(),
+                class_names: HashMap::new(),
+                caught: HashMap::new(),
+                methods: HashMap::new(),
+                resolved_types: HashMap::new()
+            },
+            diagnostics: Vec::new(),
+            silent: 0,
+            entered: Vec::new(),
+            frames: Vec::new(),
+            constants: HashMap::new(),
+            imports_checked: Vec::new(),
+            blank_finals: HashSet::new(),
+            final_parameters: HashSet::new(),
+            blank_final_fields: HashSet::new(),
+            in_argument: 0
+        }
+    }
+
+    fn symbols(&self) -> &Symbols {
+        &self.result.symbols
+    }
+
+    fn class(&self, name: &str) -> Rc<ClassSymbol> {
+        self.symbols().class(name).unwrap_or_else(|| Rc::new(ClassSymbol::new(name, 0)))
+    }
+
+    // Like javac, only the first error at a position is reported.
+    fn error(&mut self, position: Position, message: &str) {
+        let diagnostic = Diagnostic::new(&self.units[self.unit].0, position, message);
+        self.report(diagnostic);
+    }
+
+    fn report(&mut self, diagnostic: Diagnostic) {
+        if self.silent > 0 {
+            return;
+        }
+        if !self.diagnostics.iter().any(|other| other.file == diagnostic.file && other.position == diagnostic.position) {
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn current_class(&self) -> Rc<ClassSymbol> {
+        self.class(&self.frames.last().unwrap().class)
+    }
+
+    // How javac describes a class in a location, e.g. class Box<T> or interface Runnable.
+    fn describe_class(&self, name: &str) -> String {
+        let symbol = self.class(name);
+        format!("{} {}", symbol.kind_name(), symbol.this_type())
+    }
+
+    fn outermost_class(&self, name: &str) -> String {
+        let mut name = String::from(name);
+        while let Some(outer) = self.symbols().class(&name).and_then(|symbol| symbol.outer_class.clone()) {
+            name = outer;
+        }
+        name
+    }
+
+    // Enter: each class gets its binary name and a symbol with its flags and member classes.
+
+    fn enter(&mut self, name: String, declaration: &'a TypeDeclaration, outer: Option<&ClassSymbol>) {
+        if self.result.classes.contains(&name) {
+            let message = match outer {
+                Some(outer) => format!("{} {} is already defined in {} {}", kind_name(declaration.kind), declaration.name.name, outer.kind_name(), types::simple_name(&outer.name)),
+                None => format!("duplicate class: {}", name.replace('/', "."))
+            };
+            self.error(declaration.name.position, &message);
+            return;
+        }
+
+        let mut symbol = ClassSymbol::new(&name, class_access_flags(declaration, outer));
+        symbol.outer_class = outer.map(|outer| outer.name.clone());
+        symbol.member_classes = declaration.members.iter().filter_map(|member| match *member {
+            Member::Type(ref member) => Some(format!("{}${}", name, member.name.name)),
+            _ => None
+        }).collect();
+        let symbol = self.symbols().define(symbol);
+
+        self.result.classes.push(name.clone());
+        self.result.class_names.insert(address(declaration), name.clone());
+        self.entered.push((name.clone(), self.unit, declaration));
+
+        for member in declaration.members.iter() {
+            if let Member::Type(ref member) = *member {
+                self.enter(format!("{}${}", name, member.name.name), member, Some(&symbol));
+            }
+        }
+    }
+
+    // Imports that name no class are reported once per compilation unit.
+    fn check_imports(&mut self, unit: usize) {
+        if self.imports_checked.contains(&unit) {
+            return;
+        }
+        self.imports_checked.push(unit);
+
+        for import in self.units[unit].1.imports.iter() {
+            let parts: Vec<&str> = import.name.parts.iter().map(|part| part.name.as_str()).collect();
+            // A static import names a member of a class.
+            let class_parts = if import.is_static && !import.on_demand { &parts[..parts.len() - 1] } else { &parts[..] };
+            if class_parts.is_empty() {
+                continue;
+            }
+
+            let found = self.fully_qualified_class(class_parts).is_some()
+                || (import.on_demand && !import.is_static && self.symbols().package_exists(&class_parts.join("/")));
+            if found {
+                continue;
+            }
+
+            let last = class_parts.len() - 1;
+            let package = class_parts[..last].join("/");
+            let position = import.name.parts[last].position;
+            let dot = Position::new(position.line, position.column.saturating_sub(1).max(1));
+            if class_parts.len() == 1 {
+                self.error(import.name.parts[0].position, &format!("package {} does not exist", class_parts[0]));
+            } else if self.symbols().package_exists(&package) {
+                let message = format!("cannot find symbol\n  symbol:   class {}\n  location: package {}", class_parts[last], package.replace('/', "."));
+                self.error(dot, &message);
+            } else if import.on_demand && !import.is_static {
+                self.error(dot, &format!("package {} does not exist", class_parts.join(".")));
+            } else {
+                self.error(dot, &format!("package {} does not exist", package.replace('/', ".")));
+            }
+        }
+    }
+
+    // Resolves a fully qualified name such as java.util.Map.Entry: a package, a top-level class
+    // in it, and member classes.
+    fn fully_qualified_class(&self, names: &[&str]) -> Option<String> {
+        for split in 1..names.len() {
+            let top_level = format!("{}/{}", names[..split].join("/"), names[split]);
+            if self.symbols().exists(&top_level) {
+                let mut class = top_level;
+                for member in names[split + 1..].iter() {
+                    class = self.symbols().find_member_class(&class, member)?;
+                }
+                return Some(class);
+            }
+        }
+        None
+    }
+
+    // Pushes the frames of a class and of the classes it is nested in, up to the class of the
+    // current frame. Returns how many frames were pushed.
+    fn push_class_frames(&mut self, name: &str) -> usize {
+        let mut chain = vec![String::from(name)];
+        loop {
+            let outer = self.symbols().class(chain.last().unwrap()).and_then(|symbol| symbol.outer_class.clone());
+            match outer {
+                Some(ref outer) if self.frames.last().is_none_or(|frame| frame.class != *outer) => chain.push(outer.clone()),
+                _ => break
+            }
+        }
+
+        let count = chain.len();
+        for class in chain.into_iter().rev() {
+            self.frames.push(Frame::new(&class));
+        }
+        count
+    }
+
+    fn pop_frames(&mut self, count: usize) {
+        let length = self.frames.len() - count;
+        self.frames.truncate(length);
+    }
+
+    // Headers: type parameters and supertypes.
+
+    fn complete_header(&mut self, name: &str, declaration: &'a TypeDeclaration) {
+        let pushed = self.push_class_frames(name);
+        let mut symbol = (*self.class(name)).clone();
+
+        symbol.type_parameters = declaration.type_parameters.iter()
+            .map(|parameter| TypeVariable::new(&parameter.name.name, name))
+            .collect();
+        self.symbols().define(symbol.clone());
+        self.resolve_type_parameter_bounds(&declaration.type_parameters, &symbol.type_parameters);
+
+        let extends: Vec<(Type, Position)> = declaration.extends.iter().map(|written| (self.resolve_type(written), written.position())).collect();
+        let implements: Vec<(Type, Position)> = declaration.implements.iter().map(|written| (self.resolve_type(written), written.position())).collect();
+
+        match declaration.kind {
+            TypeKind::Class => {
+                symbol.super_class = match extends.first() {
+                    Some(&(ref super_class, position)) => {
+                        self.check_superclass(super_class, position);
+                        Some(if super_class.class_name().is_some() { super_class.clone() } else { Type::object() })
+                    },
+                    None if name == OBJECT => None,
+                    None => Some(Type::object())
+                };
+                symbol.interfaces = self.check_interfaces(&implements);
+            },
+            TypeKind::Interface => {
+                symbol.super_class = Some(Type::object());
+                symbol.interfaces = self.check_interfaces(&extends);
+            },
+            TypeKind::Enum => {
+                symbol.super_class = Some(Type::generic("java/lang/Enum", vec![Type::class(name)]));
+                symbol.interfaces = self.check_interfaces(&implements);
+            },
+            TypeKind::Record => {
+                symbol.super_class = Some(Type::class("java/lang/Record"));
+                symbol.interfaces = self.check_interfaces(&implements);
+            },
+            TypeKind::Annotation => {
+                symbol.super_class = Some(Type::object());
+                symbol.interfaces = vec![Type::class("java/lang/annotation/Annotation")];
+            }
+        }
+
+        self.symbols().define(symbol);
+        self.pop_frames(pushed);
+    }
+
+    fn resolve_type_parameter_bounds(&mut self, parameters: &'a [ast::TypeParameter], variables: &[TypeVariable]) {
+        for (parameter, variable) in parameters.iter().zip(variables.iter()) {
+            let bounds: Vec<Type> = parameter.bounds.iter().map(|bound| self.resolve_type(bound)).filter(|bound| !bound.is_error()).collect();
+            *variable.bounds.borrow_mut() = bounds;
+        }
+    }
+
+    fn check_superclass(&mut self, super_class: &Type, position: Position) {
+        if let Some(name) = super_class.class_name() {
+            let symbol = self.class(name);
+            if symbol.is_interface() {
+                self.error(position, "no interface expected here");
+            } else if symbol.is_final() {
+                self.error(position, &format!("cannot inherit from final {}", types::simple_name(name)));
+            } else if symbol.is_enum() || name == "java/lang/Enum" {
+                self.error(position, "classes cannot directly extend java.lang.Enum");
+            }
+        }
+    }
+
+    fn check_interfaces(&mut self, written: &[(Type, Position)]) -> Vec<Type> {
+        let mut interfaces = Vec::new();
+        for &(ref interface, position) in written.iter() {
+            if let Some(name) = interface.class_name() {
+                if !self.class(name).is_interface() {
+                    self.error(position, "interface expected here");
+                } else {
+                    interfaces.push(interface.clone());
+                }
+            }
+        }
+        interfaces
+    }
+
+    fn check_cycles(&mut self, name: &str, declaration: &'a TypeDeclaration) {
+        if self.inherits_from(name, name, &mut Vec::new()) {
+            self.error(declaration.name.position, &format!("cyclic inheritance involving {}", types::simple_name(name)));
+            let mut symbol = (*self.class(name)).clone();
+            symbol.super_class = if symbol.super_class.is_some() { Some(Type::object()) } else { None };
+            symbol.interfaces.clear();
+            self.symbols().define(symbol);
+        }
+    }
+
+    fn inherits_from(&self, name: &str, ancestor: &str, visited: &mut Vec<String>) -> bool {
+        let symbol = match self.symbols().class(name) {
+            Some(symbol) => symbol,
+            None => return false
+        };
+        for supertype in symbol.super_class.iter().chain(symbol.interfaces.iter()).filter_map(Type::class_name) {
+            if supertype == ancestor {
+                return true;
+            }
+            if !visited.iter().any(|class| class == supertype) {
+                visited.push(String::from(supertype));
+                if self.inherits_from(supertype, ancestor, visited) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    // Members: fields, methods and constructors, including those the language declares
+    // implicitly.
+
+    fn complete_members(&mut self, name: &str, body: Body<'a>) {
+        let pushed = self.push_class_frames(name);
+        let mut symbol = (*self.class(name)).clone();
+        let is_interface = symbol.is_interface();
+        symbol.fields.clear();
+        symbol.methods.clear();
+
+        if let Some(declaration) = body.declaration {
+            for constant in declaration.enum_constants.iter() {
+                let flags = field::ACC_PUBLIC | field::ACC_STATIC | field::ACC_FINAL | field::ACC_ENUM;
+                self.add_field(&mut symbol, &constant.name, Type::class(name), flags);
+            }
+            for component in declaration.record_components.iter() {
+                let component_type = self.parameter_type(component);
+                self.add_field(&mut symbol, &component.name, component_type, field::ACC_PRIVATE | field::ACC_FINAL);
+                self.blank_final_fields.insert((String::from(name), component.name.name.clone()));
+            }
+        }
+
+        for member in body.members.iter() {
+            match *member {
+                Member::Field(ref declaration) => {
+                    let mut flags = modifier_flags(&declaration.modifiers);
+                    if is_interface {
+                        flags |= field::ACC_PUBLIC | field::ACC_STATIC | field::ACC_FINAL;
+                    }
+                    let field_type = self.resolve_type(&declaration.field_type);
+                    for declarator in declaration.declarators.iter() {
+                        let declared_type = array_type(field_type.clone(), declarator.dimensions);
+                        self.add_field(&mut symbol, &declarator.name, declared_type.clone(), flags);
+                        if flags & field::ACC_FINAL != 0 && declarator.initializer.is_none() {
+                            self.blank_final_fields.insert((String::from(name), declarator.name.name.clone()));
+                        }
+                        if flags & field::ACC_FINAL != 0 && (declared_type.is_primitive() || declared_type.is_string()) {
+                            if let Some(ref initializer) = declarator.initializer {
+                                let key = (String::from(name), declarator.name.name.clone());
+                                self.constants.insert(key, PendingConstant::Unevaluated(self.unit, initializer, declared_type));
+                            }
+                        }
+                    }
+                },
+                Member::Method(ref declaration) => {
+                    let method = self.method_symbol(name, declaration, is_interface);
+                    self.add_method(&mut symbol, method.clone(), declaration.name.position);
+                    self.result.methods.insert(address(declaration), method);
+                },
+                Member::Constructor(ref declaration) => {
+                    let method = self.constructor_symbol(name, declaration, body);
+                    self.add_method(&mut symbol, method.clone(), declaration.name.position);
+                    self.result.methods.insert(address(declaration), method);
+                },
+                _ => {}
+            }
+        }
+
+        self.add_implicit_members(&mut symbol, body);
+        self.symbols().define(symbol);
+        self.pop_frames(pushed);
+    }
+
+    fn add_field(&mut self, symbol: &mut ClassSymbol, name: &Identifier, field_type: Type, access_flags: u16) {
+        if symbol.fields.iter().any(|field| field.name == name.name) {
+            let message = format!("variable {} is already defined in {} {}", name.name, symbol.kind_name(), types::simple_name(&symbol.name));
+            self.error(name.position, &message);
+            return;
+        }
+        symbol.fields.push(FieldSymbol { owner: symbol.name.clone(), name: name.name.clone(), field_type, access_flags, constant: None });
+    }
+
+    fn add_method(&mut self, symbol: &mut ClassSymbol, method: MethodSymbol, position: Position) {
+        let duplicate = symbol.methods.iter().any(|other| other.name == method.name && other.same_erased_parameters(&method));
+        if duplicate {
+            let kind = if method.is_constructor() { "constructor" } else { "method" };
+            let message = format!("{} {} is already defined in {} {}", kind, method.signature(), symbol.kind_name(), types::simple_name(&symbol.name));
+            self.error(position, &message);
+            return;
+        }
+        symbol.methods.push(method);
+    }
+
+    fn parameter_type(&mut self, parameter: &'a ast::Parameter) -> Type {
+        let parameter_type = self.resolve_type(&parameter.parameter_type);
+        if parameter.variable_arity { Type::array_of(parameter_type) } else { parameter_type }
+    }
+
+    // The type parameters of a method are in scope in its whole declaration.
+    fn method_type_parameters(&mut self, owner: String, parameters: &'a [ast::TypeParameter]) -> Vec<TypeVariable> {
+        let variables: Vec<TypeVariable> = parameters.iter().map(|parameter| TypeVariable::new(&parameter.name.name, &owner)).collect();
+        self.frame().type_variables = variables.clone();
+        self.resolve_type_parameter_bounds(parameters, &variables);
+        variables
+    }
+
+    fn method_symbol(&mut self, class: &str, declaration: &'a ast::MethodDeclaration, is_interface: bool) -> MethodSymbol {
+        let owner = format!("{}.{}@{}:{}", class, declaration.name.name, declaration.name.position.line, declaration.name.position.column);
+        let type_parameters = self.method_type_parameters(owner, &declaration.type_parameters);
+        let parameters = declaration.parameters.iter().map(|parameter| self.parameter_type(parameter)).collect();
+        let return_type = self.resolve_type(&declaration.return_type);
+        let throws = declaration.throws.iter().map(|thrown| self.resolve_type(thrown)).collect();
+        self.frame().type_variables.clear();
+
+        let modifiers = &declaration.modifiers;
+        let mut access_flags = modifier_flags(modifiers);
+        if is_interface {
+            if !modifiers.contains(Modifier::Private) {
+                access_flags |= method::ACC_PUBLIC;
+            }
+            if declaration.body.is_none() && !modifiers.contains(Modifier::Static) {
+                access_flags |= method::ACC_ABSTRACT;
+            }
+        }
+        if declaration.parameters.last().is_some_and(|parameter| parameter.variable_arity) {
+            access_flags |= method::ACC_VARARGS;
+        }
+
+        MethodSymbol { owner: String::from(class), name: declaration.name.name.clone(), type_parameters, parameters, return_type, throws, access_flags }
+    }
+
+    fn constructor_symbol(&mut self, class: &str, declaration: &'a ast::ConstructorDeclaration, body: Body<'a>) -> MethodSymbol {
+        if declaration.name.name != types::simple_name(class) {
+            self.error(declaration.name.position, "invalid method declaration; return type required");
+        }
+
+        let owner = format!("{}.<init>@{}:{}", class, declaration.name.position.line, declaration.name.position.column);
+        let type_parameters = self.method_type_parameters(owner, &declaration.type_parameters);
+        let parameters = match declaration.parameters {
+            Some(ref parameters) => parameters.iter().map(|parameter| self.parameter_type(parameter)).collect(),
+            None => body.declaration.map_or_else(Vec::new, |record| {
+                record.record_components.iter().map(|component| self.parameter_type(component)).collect()
+            })
+        };
+        let throws = declaration.throws.iter().map(|thrown| self.resolve_type(thrown)).collect();
+        self.frame().type_variables.clear();
+
+        let mut access_flags = modifier_flags(&declaration.modifiers);
+        if body.kind == TypeKind::Enum {
+            access_flags = (access_flags & !(method::ACC_PUBLIC | method::ACC_PROTECTED)) | method::ACC_PRIVATE;
+        }
+        let variable_arity = match declaration.parameters {
+            Some(ref parameters) => parameters.last().is_some_and(|parameter| parameter.variable_arity),
+            None => body.declaration.is_some_and(|record| record.record_components.last().is_some_and(|component| component.variable_arity))
+        };
+        if variable_arity {
+            access_flags |= method::ACC_VARARGS;
+        }
+
+        MethodSymbol { owner: String::from(class), name: String::from("<init>"), type_parameters, parameters, return_type: Type::Void, throws, access_flags }
+    }
+
+    // The default constructor, the methods of enums, and the canonical constructor, accessors
+    // and Object methods of records (JLS §8.8.9, §8.9.3, §8.10).
+    fn add_implicit_members(&mut self, symbol: &mut ClassSymbol, body: Body<'a>) {
+        let this_type = symbol.this_type();
+        let method = |name: &str, parameters: Vec<Type>, return_type: Type, access_flags: u16| MethodSymbol {
+            owner: symbol.name.clone(),
+            name: String::from(name),
+            type_parameters: Vec::new(),
+            parameters,
+            return_type,
+            throws: Vec::new(),
+            access_flags
+        };
+        let mut implicit = Vec::new();
+
+        let has_constructor = body.members.iter().any(|member| matches!(*member, Member::Constructor(_)));
+        if body.kind == TypeKind::Enum {
+            implicit.push(method("values", Vec::new(), Type::array_of(this_type.clone()), method::ACC_PUBLIC | method::ACC_STATIC));
+            implicit.push(method("valueOf", vec![Type::string()], this_type.clone(), method::ACC_PUBLIC | method::ACC_STATIC));
+        }
+        if body.kind == TypeKind::Record {
+            let components = body.declaration.map_or(&[][..], |declaration| &declaration.record_components[..]);
+            let component_types: Vec<Type> = symbol.fields.iter().take(components.len()).map(|field| field.field_type.clone()).collect();
+            let has_canonical = symbol.methods.iter().any(|declared| declared.is_constructor() && declared.parameters.len() == component_types.len()
+                && declared.parameters.iter().zip(component_types.iter()).all(|(a, b)| a.erasure() == b.erasure()));
+            if !has_canonical {
+                let mut flags = symbol.access_flags & (method::ACC_PUBLIC | method::ACC_PROTECTED | method::ACC_PRIVATE);
+                if components.last().is_some_and(|component| component.variable_arity) {
+                    flags |= method::ACC_VARARGS;
+                }
+                implicit.push(method("<init>", component_types.clone(), Type::Void, flags));
+            }
+            for (component, component_type) in components.iter().zip(component_types.iter()) {
+                let declared = symbol.methods.iter().any(|declared| declared.name == component.name.name && declared.parameters.is_empty());
+                if !declared {
+                    implicit.push(method(&component.name.name, Vec::new(), component_type.clone(), method::ACC_PUBLIC));
+                }
+            }
+            for &(object_method, ref parameters, ref return_type) in [
+                ("equals", vec![Type::object()], Type::boolean()),
+                ("hashCode", Vec::new(), Type::int()),
+                ("toString", Vec::new(), Type::string())
+            ].iter() {
+                let declared = symbol.methods.iter().any(|declared| declared.name == object_method && declared.parameters.len() == parameters.len());
+                if !declared {
+                    implicit.push(method(object_method, parameters.clone(), return_type.clone(), method::ACC_PUBLIC | method::ACC_FINAL));
+                }
+            }
+        } else if !has_constructor && !symbol.is_interface() {
+            let flags = match body.kind {
+                TypeKind::Enum => method::ACC_PRIVATE,
+                _ if body.declaration.is_none() => 0,
+                _ => symbol.access_flags & (method::ACC_PUBLIC | method::ACC_PROTECTED | method::ACC_PRIVATE)
+            };
+            implicit.push(method("<init>", Vec::new(), Type::Void, flags));
+        }
+
+        symbol.methods.extend(implicit);
+    }
+
+}
+
+fn class_access_flags(declaration: &TypeDeclaration, outer: Option<&ClassSymbol>) -> u16 {
+    let modifiers = &declaration.modifiers;
+    let mut flags = modifier_flags(modifiers) & (class_flags::ACC_PUBLIC | class_flags::ACC_PRIVATE | class_flags::ACC_PROTECTED
+        | class_flags::ACC_STATIC | class_flags::ACC_FINAL | class_flags::ACC_ABSTRACT);
+    let nested = outer.is_some();
+
+    match declaration.kind {
+        TypeKind::Class => {},
+        TypeKind::Interface => flags |= class_flags::ACC_INTERFACE | class_flags::ACC_ABSTRACT,
+        TypeKind::Annotation => flags |= class_flags::ACC_INTERFACE | class_flags::ACC_ABSTRACT | class_flags::ACC_ANNOTATION,
+        TypeKind::Enum => {
+            flags |= class_flags::ACC_ENUM;
+            // An enum is final unless a constant has a class body.
+            if declaration.enum_constants.iter().all(|constant| constant.body.is_none()) {
+                flags |= class_flags::ACC_FINAL;
+            }
+        },
+        TypeKind::Record => flags |= class_flags::ACC_FINAL
+    }
+    if nested && declaration.kind != TypeKind::Class {
+        flags |= class_flags::ACC_STATIC;
+    }
+    if outer.is_some_and(|outer| outer.is_interface()) {
+        flags |= class_flags::ACC_PUBLIC | class_flags::ACC_STATIC;
+    }
+    flags
+}
+
+// Access flags from modifiers. The flags of fields and methods have the same values.
+fn modifier_flags(modifiers: &Modifiers) -> u16 {
+    let mut flags = 0;
+    for &(modifier, _) in modifiers.modifiers.iter() {
+        flags |= match modifier {
+            Modifier::Public => method::ACC_PUBLIC,
+            Modifier::Protected => method::ACC_PROTECTED,
+            Modifier::Private => method::ACC_PRIVATE,
+            Modifier::Static => method::ACC_STATIC,
+            Modifier::Final => method::ACC_FINAL,
+            Modifier::Abstract => method::ACC_ABSTRACT,
+            Modifier::Native => method::ACC_NATIVE,
+            Modifier::Synchronized => method::ACC_SYNCHRONIZED,
+            Modifier::Strictfp => method::ACC_STRICT,
+            Modifier::Volatile => field::ACC_VOLATILE,
+            Modifier::Transient => field::ACC_TRANSIENT,
+            Modifier::Default | Modifier::Sealed | Modifier::NonSealed => 0
+        };
+    }
+    flags
+}
+
+fn array_type(mut element: Type, dimensions: usize) -> Type {
+    if element.is_error() {
+        return element;
+    }
+    for _ in 0..dimensions {
+        element = Type::array_of(element);
+    }
+    element
+}
+
+// Types and names.
+impl<'a> Attr<'a> {
+
+    // Resolves a type written in the source. Errors are reported, and give Error.
+    fn resolve_type(&mut self, written: &'a ast::Type) -> Type {
+        let resolved = match *written {
+            ast::Type::Primitive(primitive, _) => Type::Primitive(primitive),
+            ast::Type::Void(_) => Type::Void,
+            ast::Type::Array(ref component) => array_type(self.resolve_type(component), 1),
+            ast::Type::Class(ref class_type) => self.resolve_class_type(class_type)
+        };
+        self.result.resolved_types.insert(address(written), resolved.clone());
+        resolved
+    }
+
+    // A class type. Its first name is looked up in scope, or else taken to be the start of a
+    // package name; the rest are member classes.
+    fn resolve_class_type(&mut self, class_type: &'a ast::ClassType) -> Type {
+        let parts = &class_type.parts;
+        let (mut name, consumed) = match self.lookup_type(&parts[0].name.name) {
+            Some(Type::Variable(variable)) => {
+                if parts.len() > 1 {
+                    self.error(parts[1].name.position, "cannot select from a type variable");
+                    return Type::Error;
+                }
+                return Type::Variable(variable);
+            },
+            Some(found) => (String::from(found.class_name().unwrap()), 1),
+            None => {
+                let names: Vec<&Identifier> = parts.iter().map(|part| &part.name).collect();
+                match self.package_class(&names) {
+                    Some(found) => found,
+                    None => {
+                        self.report_missing_type(&names);
+                        return Type::Error;
+                    }
+                }
+            }
+        };
+
+        for part in parts[consumed..].iter() {
+            match self.symbols().find_member_class(&name, &part.name.name) {
+                Some(member) => name = member,
+                None => {
+                    let message = format!("cannot find symbol\n  symbol:   class {}\n  location: {}", part.name.name, self.describe_class(&name));
+                    self.error(part.name.position, &message);
+                    return Type::Error;
+                }
+            }
+        }
+
+        let last = parts.last().unwrap();
+        let arguments = match last.arguments {
+            Some(ref arguments) if !arguments.is_empty() => arguments,
+            _ => return Type::class(&name)
+        };
+        let resolved: Vec<Type> = arguments.iter().map(|argument| self.resolve_type_argument(argument)).collect();
+        let expected = self.class(&name).type_parameters.len();
+        if expected != resolved.len() {
+            let message = if expected == 0 {
+                format!("type {} does not take parameters", types::simple_name(&name))
+            } else {
+                format!("wrong number of type arguments; required {}", expected)
+            };
+            self.error(last.name.position, &message);
+            return Type::Error;
+        }
+        Type::generic(&name, resolved)
+    }
+
+    // A qualified type name that starts with a package, e.g. java.util.Map.Entry, and the
+    // number of names that make up the package and top-level class.
+    fn package_class(&self, names: &[&Identifier]) -> Option<(String, usize)> {
+        let names: Vec<&str> = names.iter().map(|name| name.name.as_str()).collect();
+        (1..names.len()).map(|split| (format!("{}/{}", names[..split].join("/"), names[split]), split + 1))
+            .find(|(class, _)| self.symbols().exists(class))
+    }
+
+    fn report_missing_type(&mut self, parts: &[&Identifier]) {
+        if parts.len() == 1 {
+            let location = self.location();
+            let message = format!("cannot find symbol\n  symbol:   class {}\n  location: {}", parts[0].name, location);
+            self.error(parts[0].position, &message);
+            return;
+        }
+
+        // javac reports the longest prefix that is not a package, at the '.' after it.
+        let names: Vec<&str> = parts.iter().map(|part| part.name.as_str()).collect();
+        let mut length = 1;
+        while length < names.len() - 1 && self.symbols().package_exists(&names[..length + 1].join("/")) {
+            length += 1;
+        }
+        let position = parts[length].position;
+        let dot = Position::new(position.line, position.column.saturating_sub(1).max(1));
+        if self.symbols().package_exists(&names[..length].join("/")) {
+            let message = format!("cannot find symbol\n  symbol:   class {}\n  location: package {}", names[length], names[..length].join("."));
+            self.error(dot, &message);
+        } else {
+            self.error(dot, &format!("package {} does not exist", names[..length].join(".")));
+        }
+    }
+
+    fn resolve_type_argument(&mut self, argument: &'a TypeArgument) -> Type {
+        match *argument {
+            TypeArgument::Type(ref written) => self.reference_type(written),
+            TypeArgument::Wildcard(None, _) => Type::Wildcard(Wildcard::Unbounded),
+            TypeArgument::Wildcard(Some(ast::WildcardBound::Extends(ref bound)), _) => {
+                Type::Wildcard(Wildcard::Extends(Box::new(self.reference_type(bound))))
+            },
+            TypeArgument::Wildcard(Some(ast::WildcardBound::Super(ref bound)), _) => {
+                Type::Wildcard(Wildcard::Super(Box::new(self.reference_type(bound))))
+            }
+        }
+    }
+
+    fn reference_type(&mut self, written: &'a ast::Type) -> Type {
+        let resolved = self.resolve_type(written);
+        if resolved.is_primitive() {
+            let message = format!("unexpected type\n  required: reference\n  found:    {}", resolved);
+            self.error(written.position(), &message);
+            return Type::Error;
+        }
+        resolved
+    }
+
+    // How javac describes where a name was looked up: the class the code is in.
+    fn location(&self) -> String {
+        match self.frames.last() {
+            Some(frame) => self.describe_class(&frame.class),
+            None => String::new()
+        }
+    }
+
+    // Finds a type by simple name (JLS §6.5.5.1): a type variable, local class or member class
+    // in scope, then a class imported by a single-type import, a class of the same package, a
+    // class imported on demand, and finally a class of java.lang.
+    fn lookup_type(&self, name: &str) -> Option<Type> {
+        for frame in self.frames.iter().rev() {
+            if let Some(variable) = frame.type_variables.iter().rev().find(|variable| variable.name == name) {
+                return Some(Type::Variable(variable.clone()));
+            }
+            for scope in frame.scopes.iter().rev() {
+                if let Some((_, class)) = scope.classes.iter().rev().find(|&(simple, _)| simple == name) {
+                    return Some(Type::class(class));
+                }
+            }
+
+            let symbol = self.class(&frame.class);
+            if let Some(variable) = symbol.type_parameters.iter().find(|variable| variable.name == name) {
+                return Some(Type::Variable(variable.clone()));
+            }
+            if let Some(member) = self.symbols().find_member_class(&frame.class, name) {
+                return Some(Type::class(&member));
+            }
+        }
+        self.lookup_top_level_type(name).map(|class| Type::class(&class))
+    }
+
+    fn lookup_top_level_type(&self, name: &str) -> Option<String> {
+        let unit = &self.units[self.unit].1;
+
+        for import in unit.imports.iter().filter(|import| !import.on_demand) {
+            let parts: Vec<&str> = import.name.parts.iter().map(|part| part.name.as_str()).collect();
+            if *parts.last().unwrap() == name {
+                let found = if import.is_static {
+                    self.fully_qualified_class(&parts[..parts.len() - 1]).and_then(|class| self.symbols().find_member_class(&class, name))
+                } else {
+                    self.fully_qualified_class(&parts)
+                };
+                if found.is_some() {
+                    return found;
+                }
+            }
+        }
+
+        let package = unit.package.as_ref().map_or_else(String::new, |package| package.name.to_dotted().replace('.', "/"));
+        let same_package = if package.is_empty() { String::from(name) } else { format!("{}/{}", package, name) };
+        if self.symbols().exists(&same_package) {
+            return Some(same_package);
+        }
+
+        for import in unit.imports.iter().filter(|import| import.on_demand) {
+            let parts: Vec<&str> = import.name.parts.iter().map(|part| part.name.as_str()).collect();
+            if let Some(class) = self.fully_qualified_class(&parts) {
+                if let Some(member) = self.symbols().find_member_class(&class, name) {
+                    return Some(member);
+                }
+            } else if !import.is_static {
+                let class = format!("{}/{}", parts.join("/"), name);
+                if self.symbols().exists(&class) {
+                    return Some(class);
+                }
+            }
+        }
+
+        let java_lang = format!("java/lang/{}", name);
+        if self.symbols().exists(&java_lang) {
+            return Some(java_lang);
+        }
+        None
+    }
+
+    // Resolves the qualified name of a class in Outer.this and Outer.super.
+    fn resolve_class_name(&mut self, name: &'a QualifiedName) -> Option<String> {
+        let names: Vec<&Identifier> = name.parts.iter().collect();
+        let (mut class, consumed) = match self.lookup_type(&names[0].name) {
+            Some(Type::Class(found)) => (found.name, 1),
+            _ => match self.package_class(&names) {
+                Some(found) => found,
+                None => {
+                    self.report_missing_type(&names);
+                    return None;
+                }
+            }
+        };
+        for part in names[consumed..].iter() {
+            match self.symbols().find_member_class(&class, &part.name) {
+                Some(member) => class = member,
+                None => {
+                    let message = format!("cannot find symbol\n  symbol:   class {}\n  location: {}", part.name, self.describe_class(&class));
+                    self.error(part.position, &message);
+                    return None;
+                }
+            }
+        }
+        Some(class)
+    }
+
+    // Finds a local variable by simple name, in the innermost scope that declares it.
+    fn lookup_local(&self, name: &str) -> Option<usize> {
+        for frame in self.frames.iter().rev() {
+            for scope in frame.scopes.iter().rev() {
+                if let Some(&local) = scope.locals.iter().rev().find(|&&local| self.result.locals[local].name == name) {
+                    return Some(local);
+                }
+            }
+        }
+        None
+    }
+
+    // Finds a field by simple name in the classes the code is in, innermost first (JLS §6.5.6.1),
+    // then among static imports. Also gives the class of the frame it was found in, and whether
+    // an instance field would have to be accessed from a static context.
+    fn lookup_field(&mut self, name: &str) -> Option<(FieldSymbol, Option<String>, bool)> {
+        let mut static_context = false;
+        for index in (0..self.frames.len()).rev() {
+            let class = self.frames[index].class.clone();
+            static_context |= self.frames[index].is_static;
+            let site = self.class(&class).this_type();
+            if let Some(field) = self.symbols().find_field(&site, name) {
+                return Some((field, Some(class), static_context));
+            }
+            if !self.class(&class).is_inner() {
+                static_context = true;
+            }
+        }
+
+        let unit = &self.units[self.unit].1;
+        for import in unit.imports.iter().filter(|import| import.is_static) {
+            let parts: Vec<&str> = import.name.parts.iter().map(|part| part.name.as_str()).collect();
+            let (class_parts, member) = if import.on_demand { (&parts[..], name) } else { (&parts[..parts.len() - 1], *parts.last().unwrap()) };
+            if member != name {
+                continue;
+            }
+            if let Some(class) = self.fully_qualified_class(class_parts) {
+                if let Some(field) = self.symbols().find_field(&Type::class(&class), name).filter(FieldSymbol::is_static) {
+                    return Some((field, None, false));
+                }
+            }
+        }
+        None
+    }
+
+    // The field as declared, with its constant value.
+    fn declared_field(&mut self, member: &FieldSymbol) -> FieldSymbol {
+        let mut declared = self.class(&member.owner).fields.iter().find(|field| field.name == member.name).cloned().unwrap_or_else(|| member.clone());
+        if declared.constant.is_none() && declared.is_final() {
+            declared.constant = self.field_constant(&member.owner, &member.name);
+        }
+        declared
+    }
+
+    // The value of a constant field of a source class, evaluating its initializer the first
+    // time. A field whose initializer refers to itself is not a constant.
+    fn field_constant(&mut self, owner: &str, name: &str) -> Option<Constant> {
+        let key = (String::from(owner), String::from(name));
+        let (unit, initializer, field_type) = match self.constants.get(&key) {
+            Some(&PendingConstant::Unevaluated(unit, initializer, ref field_type)) => (unit, initializer, field_type.clone()),
+            Some(PendingConstant::Evaluated(constant)) => return constant.clone(),
+            _ => return None
+        };
+        self.constants.insert(key.clone(), PendingConstant::Evaluating);
+
+        let saved_unit = self.unit;
+        let saved_frames = std::mem::take(&mut self.frames);
+        self.unit = unit;
+        self.silent += 1;
+        let pushed = self.push_class_frames(owner);
+        let is_static = self.class(owner).fields.iter().any(|field| field.name == name && field.is_static());
+        self.frame().is_static = is_static;
+
+        self.expression(initializer, Some(&field_type));
+        let constant = self.constant_of(initializer).and_then(|value| self.assignment_constant(&value, &field_type));
+
+        self.pop_frames(pushed);
+        self.silent -= 1;
+        self.frames = saved_frames;
+        self.unit = saved_unit;
+        self.constants.insert(key, PendingConstant::Evaluated(constant.clone()));
+        constant
+    }
+
+    fn constant_of(&self, expression: &Expression) -> Option<Constant> {
+        self.result.constants.get(&address(expression)).cloned()
+    }
+
+    // The value a constant has once assigned to a variable of a type.
+    fn assignment_constant(&self, value: &Constant, target: &Type) -> Option<Constant> {
+        match *target {
+            Type::Primitive(primitive) => constant::convert(value, primitive),
+            _ if target.is_string() => match *value {
+                Constant::String(_) => Some(value.clone()),
+                _ => None
+            },
+            _ => None
+        }
+    }
+
+    // Stores the constants of source fields in their symbols, where code generation finds them
+    // for ConstantValue attributes.
+    fn define_constants(&mut self) {
+        let keys: Vec<(String, String)> = self.constants.keys().cloned().collect();
+        let mut by_class: HashMap<String, Vec<(String, Constant)>> = HashMap::new();
+        for (owner, name) in keys {
+            if let Some(constant) = self.field_constant(&owner, &name) {
+                by_class.entry(owner).or_default().push((name, constant));
+            }
+        }
+        for (owner, constants) in by_class {
+            let mut symbol = (*self.class(&owner)).clone();
+            for field in symbol.fields.iter_mut() {
+                if let Some((_, constant)) = constants.iter().find(|&(name, _)| *name == field.name) {
+                    field.constant = Some(constant.clone());
+                }
+            }
+            self.symbols().define(symbol);
+        }
+    }
+
+}
+
+// Class bodies.
+impl<'a> Attr<'a> {
+
+    fn attribute_class(&mut self, name: &str, body: Body<'a>) {
+        let pushed = self.push_class_frames(name);
+        let symbol = self.class(name);
+        let in_argument = std::mem::replace(&mut self.in_argument, 0);
+
+        if let Some(declaration) = body.declaration {
+            for constant in declaration.enum_constants.iter() {
+                self.enum_constant(name, constant);
+            }
+        }
+
+        for member in body.members.iter() {
+            self.enter_member_context(name, false);
+            match *member {
+                Member::Field(ref declaration) => {
+                    let is_static = symbol.is_interface() || declaration.modifiers.contains(Modifier::Static);
+                    self.frame().is_static = is_static;
+                    for declarator in declaration.declarators.iter() {
+                        if let Some(ref initializer) = declarator.initializer {
+                            let field_type = symbol.fields.iter().find(|field| field.name == declarator.name.name)
+                                .map_or(Type::Error, |field| field.field_type.clone());
+                            self.variable_initializer(initializer, &field_type);
+                        }
+                    }
+                },
+                Member::Method(ref declaration) => self.method_body(declaration, &symbol),
+                Member::Constructor(ref declaration) => self.constructor_body(declaration, body, &symbol),
+                Member::Initializer { is_static, ref body } => {
+                    self.frame().is_static = is_static;
+                    self.frame().returns.push(Returns::Initializer);
+                    self.block(body);
+                },
+                Member::Type(ref declaration) => {
+                    if let Some(member) = self.result.class_names.get(&address(declaration)).cloned() {
+                        self.attribute_class(&member, Body::of(declaration));
+                    }
+                }
+            }
+        }
+        self.enter_member_context(name, false);
+
+        if let Some(declaration) = body.declaration {
+            let has_constructor = body.members.iter().any(|member| matches!(*member, Member::Constructor(_)));
+            if !has_constructor && body.kind == TypeKind::Class {
+                self.implicit_super_call(address(declaration), declaration.position);
+            }
+            self.check_abstract_methods(name, declaration.position);
+        }
+        self.pop_frames(pushed);
+        self.in_argument = in_argument;
+    }
+
+    // Resets the frame of a class for attributing one of its members.
+    fn enter_member_context(&mut self, class: &str, is_static: bool) {
+        let location = self.describe_class(class);
+        let frame = self.frame();
+        frame.is_static = is_static;
+        frame.type_variables.clear();
+        frame.scopes = vec![Scope::default()];
+        frame.location = location;
+        frame.returns.clear();
+        frame.jumps.clear();
+        frame.in_constructor = false;
+    }
+
+    fn method_body(&mut self, declaration: &'a ast::MethodDeclaration, class: &ClassSymbol) {
+        let method = match self.result.methods.get(&address(declaration)) {
+            Some(method) => method.clone(),
+            None => return
+        };
+
+        let has_body = declaration.body.is_some();
+        let modifiers = &declaration.modifiers;
+        if class.is_interface() {
+            if has_body && !modifiers.contains(Modifier::Default) && !modifiers.contains(Modifier::Static) && !modifiers.contains(Modifier::Private) {
+                self.error(declaration.name.position, "interface abstract methods cannot have body");
+            }
+        } else if has_body && (method.is_abstract() || modifiers.contains(Modifier::Native)) {
+            let kind = if method.is_abstract() { "abstract" } else { "native" };
+            self.error(declaration.name.position, &format!("{} methods cannot have a body", kind));
+        } else if !has_body && !method.is_abstract() && !modifiers.contains(Modifier::Native) {
+            self.error(declaration.name.position, "missing method body, or declare abstract");
+        }
+
+        let body = match declaration.body {
+            Some(ref body) => body,
+            None => return
+        };
+        {
+            let frame = self.frame();
+            frame.is_static = method.is_static();
+            frame.type_variables = method.type_parameters.clone();
+            frame.location = format!("method {}", method.signature());
+            frame.returns.push(Returns::Method(method.return_type.clone()));
+        }
+        for (parameter, parameter_type) in declaration.parameters.iter().zip(method.parameters.iter()) {
+            self.declare_parameter(parameter, parameter_type.clone());
+        }
+        self.block_statements(&body.statements);
+    }
+
+    fn constructor_body(&mut self, declaration: &'a ast::ConstructorDeclaration, body: Body<'a>, class: &ClassSymbol) {
+        let method = match self.result.methods.get(&address(declaration)) {
+            Some(method) => method.clone(),
+            None => return
+        };
+        {
+            let frame = self.frame();
+            frame.type_variables = method.type_parameters.clone();
+            frame.location = format!("constructor {}", method.signature());
+            frame.returns.push(Returns::Method(Type::Void));
+            frame.in_constructor = true;
+        }
+
+        match declaration.parameters {
+            Some(ref parameters) => {
+                for (parameter, parameter_type) in parameters.iter().zip(method.parameters.iter()) {
+                    self.declare_parameter(parameter, parameter_type.clone());
+                }
+            },
+            None => {
+                if let Some(record) = body.declaration {
+                    for (component, parameter_type) in record.record_components.iter().zip(method.parameters.iter()) {
+                        self.declare_local(&component.name, parameter_type.clone(), false, None);
+                    }
+                }
+            }
+        }
+
+        let explicit = declaration.body.statements.first().is_some_and(|first| matches!(first.kind, StatementKind::ConstructorInvocation { .. }));
+        if !explicit && body.kind == TypeKind::Class && class.name != OBJECT {
+            self.implicit_super_call(address(declaration), declaration.body.position);
+        }
+        self.block_statements(&declaration.body.statements);
+    }
+
+    // The superclass constructor invocation a constructor without this() or super() starts
+    // with, and the one in a default constructor.
+    fn implicit_super_call(&mut self, key: usize, position: Position) {
+        let class = self.current_class();
+        let super_type = match class.super_class {
+            Some(ref super_type) => super_type.clone(),
+            None => return
+        };
+        let candidates = self.symbols().find_methods(&super_type, "<init>");
+        if let Some(invocation) = self.resolve(candidates, &[], &[], None, position, &MethodName::Constructor(&super_type)) {
+            self.result.invocations.insert(key, invocation);
+        }
+    }
+
+    fn enum_constant(&mut self, name: &str, constant: &'a ast::EnumConstant) {
+        self.enter_member_context(name, true);
+        let site = Type::class(name);
+        let candidates = self.symbols().find_methods(&site, "<init>");
+        let invocation = self.resolve(candidates, &constant.arguments, &[], None, constant.name.position, &MethodName::Constructor(&site));
+        if let Some(invocation) = invocation {
+            self.result.invocations.insert(address(constant), invocation);
+        }
+
+        if let Some(ref members) = constant.body {
+            let anonymous = self.anonymous_class(&site, members, address(constant));
+            self.attribute_class(&anonymous, Body::anonymous(members));
+        }
+    }
+
+    // The name javac gives a local or anonymous class: the name of the class it is declared in,
+    // $, a number that makes it unique, and the simple name of a local class.
+    fn local_class_name(&self, outer: &str, simple_name: &str) -> String {
+        (1..).map(|index| format!("{}${}{}", outer, index, simple_name))
+            .find(|name| !self.result.classes.contains(name))
+            .unwrap()
+    }
+
+    // Enters an anonymous class, which extends a class or implements an interface, and completes
+    // its members. Its body is attributed separately.
+    fn anonymous_class(&mut self, super_type: &Type, members: &'a [Member], key: usize) -> String {
+        let outer = self.frames.last().unwrap().class.clone();
+        let name = self.local_class_name(&outer, "");
+        let is_static = self.frames.last().unwrap().is_static;
+
+        let mut symbol = ClassSymbol::new(&name, if is_static { class_flags::ACC_STATIC } else { 0 });
+        symbol.outer_class = Some(outer);
+        let is_interface = super_type.class_name().is_some_and(|super_name| self.class(super_name).is_interface());
+        if is_interface {
+            symbol.super_class = Some(Type::object());
+            symbol.interfaces = vec![super_type.clone()];
+        } else {
+            symbol.super_class = Some(if super_type.is_error() { Type::object() } else { super_type.clone() });
+        }
+        symbol.member_classes = members.iter().filter_map(|member| match *member {
+            Member::Type(ref member) => Some(format!("{}${}", name, member.name.name)),
+            _ => None
+        }).collect();
+        let symbol = self.symbols().define(symbol);
+        self.result.classes.push(name.clone());
+        self.result.class_names.insert(key, name.clone());
+
+        let start = self.entered.len();
+        for member in members.iter() {
+            if let Member::Type(ref member) = *member {
+                self.enter(format!("{}${}", name, member.name.name), member, Some(&symbol));
+            }
+        }
+        self.complete_entered(start);
+        self.complete_members(&name, Body::anonymous(members));
+        name
+    }
+
+    // Completes the headers and members of classes entered while attributing a method body.
+    fn complete_entered(&mut self, start: usize) {
+        let entered: Vec<(String, usize, &'a TypeDeclaration)> = self.entered[start..].to_vec();
+        for &(ref name, _, declaration) in entered.iter() {
+            self.complete_header(name, declaration);
+        }
+        for &(ref name, _, declaration) in entered.iter() {
+            self.check_cycles(name, declaration);
+        }
+        for &(ref name, _, declaration) in entered.iter() {
+            self.complete_members(name, Body::of(declaration));
+        }
+    }
+
+    fn local_class(&mut self, declaration: &'a TypeDeclaration) {
+        let outer = self.frames.last().unwrap().class.clone();
+        let simple_name = &declaration.name.name;
+        let duplicate = self.frames.last().unwrap().scopes.iter().any(|scope| scope.classes.iter().any(|(name, _)| name == simple_name));
+        if duplicate {
+            let location = self.frames.last().unwrap().location.clone();
+            let message = format!("{} {} is already defined in {}", kind_name(declaration.kind), simple_name, location);
+            self.error(declaration.name.position, &message);
+            return;
+        }
+
+        let name = self.local_class_name(&outer, simple_name);
+        let start = self.entered.len();
+        let outer_symbol = self.class(&outer);
+        self.enter(name.clone(), declaration, Some(&outer_symbol));
+        let mut symbol = (*self.class(&name)).clone();
+        if self.frames.last().unwrap().is_static {
+            // A local class in a static context has no enclosing instance.
+            symbol.access_flags |= class_flags::ACC_STATIC;
+        }
+        self.symbols().define(symbol);
+
+        self.frame().scopes.last_mut().unwrap().classes.push((simple_name.clone(), name.clone()));
+        self.complete_entered(start);
+        self.attribute_class(&name, Body::of(declaration));
+    }
+
+    // A class that is not abstract must implement every abstract method it inherits.
+    fn check_abstract_methods(&mut self, name: &str, position: Position) {
+        let symbol = self.class(name);
+        if symbol.is_abstract() || symbol.is_interface() || (symbol.is_enum() && !symbol.is_final()) {
+            return;
+        }
+
+        let site = symbol.this_type();
+        let mut names = Vec::new();
+        self.abstract_method_names(&site, &mut names, &mut Vec::new());
+        for method_name in names.iter() {
+            let methods = self.symbols().find_methods(&site, method_name);
+            if let Some(missing) = methods.iter().find(|method| method.member.is_abstract()) {
+                let class = if types::simple_name(name).chars().all(|c| c.is_ascii_digit()) {
+                    format!("<anonymous {}>", name.replace('/', "."))
+                } else {
+                    String::from(types::simple_name(name))
+                };
+                let message = format!("{} is not abstract and does not override abstract method {} in {}",
+                    class, missing.member.signature(), types::simple_name(&missing.member.owner));
+                self.error(position, &message);
+                return;
+            }
+        }
+    }
+
+    fn abstract_method_names(&self, site: &Type, names: &mut Vec<String>, visited: &mut Vec<String>) {
+        if let Some(class) = site.class_name() {
+            if visited.iter().any(|visited| visited == class) {
+                return;
+            }
+            visited.push(String::from(class));
+            if let Some(symbol) = self.symbols().class(class) {
+                for method in symbol.methods.iter().filter(|method| method.is_abstract()) {
+                    if !names.contains(&method.name) {
+                        names.push(method.name.clone());
+                    }
+                }
+            }
+        }
+        for supertype in self.symbols().supertypes(site) {
+            self.abstract_method_names(&supertype, names, visited);
+        }
+    }
+
+    fn declare_parameter(&mut self, parameter: &'a ast::Parameter, parameter_type: Type) {
+        let is_final = parameter.modifiers.contains(Modifier::Final);
+        let local = self.declare_local(&parameter.name, parameter_type, is_final, None);
+        if is_final {
+            self.final_parameters.insert(local);
+        }
+    }
+
+    // Declares a local variable in the innermost scope.
+    fn declare_local(&mut self, name: &'a Identifier, local_type: Type, is_final: bool, constant: Option<Constant>) -> usize {
+        let index = self.new_local(name, local_type, is_final);
+        self.result.locals[index].constant = constant;
+        self.frame().scopes.last_mut().unwrap().locals.push(index);
+        index
+    }
+
+    // Creates a local variable without bringing it into scope, as for pattern variables. A local
+    // variable may not have the name of another one in scope in the same method, lambda bodies
+    // included (JLS §6.4).
+    fn new_local(&mut self, name: &'a Identifier, local_type: Type, is_final: bool) -> usize {
+        let duplicate = self.frames.last().unwrap().scopes.iter()
+            .any(|scope| scope.locals.iter().any(|&local| self.result.locals[local].name == name.name));
+        if duplicate {
+            let location = self.frames.last().unwrap().location.clone();
+            self.error(name.position, &format!("variable {} is already defined in {}", name.name, location));
+        }
+
+        let index = self.result.locals.len();
+        self.result.locals.push(Local { name: name.name.clone(), local_type, is_final, constant: None });
+        self.result.declarations.insert(address(name), index);
+        index
+    }
+
+    fn push_scope(&mut self) {
+        self.frame().scopes.push(Scope::default());
+    }
+
+    fn pop_scope(&mut self) {
+        self.frame().scopes.pop();
+    }
+
+}
+
+// Statements.
+impl<'a> Attr<'a> {
+
+    fn block(&mut self, block: &'a Block) {
+        self.push_scope();
+        self.block_statements(&block.statements);
+        self.pop_scope();
+    }
+
+    fn block_statements(&mut self, statements: &'a [Statement]) {
+        // Only the first statement of a constructor body may invoke another constructor.
+        let in_constructor = self.frames.last().unwrap().in_constructor;
+        self.frame().in_constructor = false;
+
+        for (index, statement) in statements.iter().enumerate() {
+            if let StatementKind::ConstructorInvocation { is_super, .. } = statement.kind {
+                if index > 0 || !in_constructor {
+                    let keyword = if is_super { "super" } else { "this" };
+                    self.error(statement.position, &format!("call to {} must be first statement in constructor", keyword));
+                }
+            }
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &'a Statement) {
+        match statement.kind {
+            StatementKind::Block(ref block) => self.block(block),
+            StatementKind::LocalVariable(ref variable) => self.local_variable(variable),
+            StatementKind::LocalType(ref declaration) => self.local_class(declaration),
+            StatementKind::Expression(ref expression) => {
+                self.expression(expression, None);
+            },
+            StatementKind::Empty => {},
+            StatementKind::If { ref condition, ref then, ref otherwise } => {
+                self.condition(condition);
+                let (when_true, when_false) = self.bindings(condition);
+                self.scoped_statement(then, &when_true);
+                if let Some(ref otherwise) = *otherwise {
+                    self.scoped_statement(otherwise, &when_false);
+                }
+                // if (!(o instanceof String s)) return; leaves s in scope after the if.
+                if !can_complete_normally(then) && otherwise.is_none() {
+                    self.introduce(&when_false);
+                }
+            },
+            StatementKind::While { ref condition, ref body } => {
+                self.condition(condition);
+                let (when_true, when_false) = self.bindings(condition);
+                self.frame().jumps.push(Jump::Loop);
+                self.scoped_statement(body, &when_true);
+                self.frame().jumps.pop();
+                self.introduce(&when_false);
+            },
+            StatementKind::DoWhile { ref body, ref condition } => {
+                self.frame().jumps.push(Jump::Loop);
+                self.scoped_statement(body, &[]);
+                self.frame().jumps.pop();
+                self.condition(condition);
+                let (_, when_false) = self.bindings(condition);
+                self.introduce(&when_false);
+            },
+            StatementKind::For { ref init, ref condition, ref update, ref body } => {
+                self.push_scope();
+                for statement in init.iter() {
+                    self.statement(statement);
+                }
+                let (when_true, when_false) = match *condition {
+                    Some(ref condition) => {
+                        self.condition(condition);
+                        self.bindings(condition)
+                    },
+                    None => (Vec::new(), Vec::new())
+                };
+                self.push_scope();
+                self.introduce(&when_true);
+                for expression in update.iter() {
+                    self.expression(expression, None);
+                }
+                self.frame().jumps.push(Jump::Loop);
+                self.scoped_statement(body, &[]);
+                self.frame().jumps.pop();
+                self.pop_scope();
+                self.pop_scope();
+                self.introduce(&when_false);
+            },
+            StatementKind::ForEach { ref variable, ref iterable, ref body } => self.for_each(variable, iterable, body),
+            StatementKind::Labeled { ref label, ref body } => {
+                let is_loop = matches!(body.kind, StatementKind::While { .. } | StatementKind::DoWhile { .. }
+                    | StatementKind::For { .. } | StatementKind::ForEach { .. });
+                self.frame().jumps.push(Jump::Label(label.name.clone(), is_loop));
+                self.statement(body);
+                self.frame().jumps.pop();
+            },
+            StatementKind::Break(ref label) => self.jump(statement.position, label.as_ref(), false),
+            StatementKind::Continue(ref label) => self.jump(statement.position, label.as_ref(), true),
+            StatementKind::Return(ref value) => self.return_statement(statement.position, value.as_ref()),
+            StatementKind::Throw(ref exception) => {
+                let found = self.expression(exception, None);
+                let throwable = Type::class("java/lang/Throwable");
+                if !found.is_error() && !self.symbols().is_subtype(&found, &throwable) {
+                    self.incompatible(exception.position, &found, &throwable);
+                }
+            },
+            StatementKind::Yield(ref value) => {
+                if !self.yield_value(value) {
+                    self.error(statement.position, "yield outside of switch expression");
+                }
+            },
+            StatementKind::Switch(ref switch) => {
+                self.switch(switch, None, false);
+            },
+            StatementKind::Synchronized { ref lock, ref body } => {
+                let found = self.expression(lock, None);
+                if found.is_primitive() {
+                    let message = format!("unexpected type\n  required: reference\n  found:    {}", found);
+                    self.error(lock.position, &message);
+                }
+                self.block(body);
+            },
+            StatementKind::Try { ref resources, ref body, ref catches, ref finally } => {
+                self.push_scope();
+                for resource in resources.iter() {
+                    match *resource {
+                        ast::Resource::Declaration(ref variable) => {
+                            self.local_variable(variable);
+                            for declarator in variable.declarators.iter() {
+                                if let Some(local) = self.result.declarations.get(&address(&declarator.name)).cloned() {
+                                    let local_type = self.result.locals[local].local_type.clone();
+                                    self.check_resource(declarator.name.position, &local_type);
+                                }
+                            }
+                        },
+                        ast::Resource::Expression(ref expression) => {
+                            let found = self.expression(expression, None);
+                            self.check_resource(expression.position, &found);
+                        }
+                    }
+                }
+                self.block(body);
+                self.pop_scope();
+
+                for catch in catches.iter() {
+                    self.push_scope();
+                    let alternatives: Vec<Type> = catch.types.iter().map(|written| {
+                        let caught = self.resolve_type(written);
+                        let throwable = Type::class("java/lang/Throwable");
+                        if !caught.is_error() && !self.symbols().is_subtype(&caught, &throwable) {
+                            self.incompatible(written.position(), &caught, &throwable);
+                        }
+                        caught
+                    }).collect();
+                    let caught = if alternatives.len() == 1 { alternatives[0].clone() } else { self.lub(&alternatives) };
+                    self.result.caught.insert(address(catch), alternatives.clone());
+                    let is_final = alternatives.len() > 1 || catch.modifiers.contains(Modifier::Final);
+                    self.declare_local(&catch.name, caught, is_final, None);
+                    self.block_statements(&catch.body.statements);
+                    self.pop_scope();
+                }
+                if let Some(ref finally) = *finally {
+                    self.block(finally);
+                }
+            },
+            StatementKind::Assert { ref condition, ref message } => {
+                self.condition(condition);
+                if let Some(ref message) = *message {
+                    self.value(message, None);
+                }
+            },
+            StatementKind::ConstructorInvocation { is_super, ref qualifier, ref type_arguments, ref arguments } => {
+                let class = self.current_class();
+                let site = if is_super { class.super_class.clone().unwrap_or(Type::Error) } else { class.this_type() };
+                if is_super && class.is_enum() {
+                    self.error(statement.position, "call to super not allowed in enum constructor");
+                }
+
+                // The arguments are evaluated before this exists.
+                let was_static = self.frames.last().unwrap().is_static;
+                self.frame().is_static = true;
+                if let Some(ref qualifier) = *qualifier {
+                    self.value(qualifier, None);
+                }
+                let explicit: Vec<Type> = type_arguments.iter().map(|argument| self.resolve_type_argument(argument)).collect();
+                let candidates = self.symbols().find_methods(&site, "<init>");
+                let invocation = self.resolve(candidates, arguments, &explicit, None, statement.position, &MethodName::Constructor(&site));
+                self.frame().is_static = was_static;
+                if let Some(invocation) = invocation {
+                    self.result.invocations.insert(address(statement), invocation);
+                }
+            }
+        }
+    }
+
+    // A statement in its own scope, with pattern variables introduced by a condition.
+    fn scoped_statement(&mut self, statement: &'a Statement, bindings: &[usize]) {
+        self.push_scope();
+        self.introduce(bindings);
+        self.statement(statement);
+        self.pop_scope();
+    }
+
+    fn introduce(&mut self, bindings: &[usize]) {
+        self.frame().scopes.last_mut().unwrap().locals.extend(bindings.iter().cloned());
+    }
+
+    fn condition(&mut self, condition: &'a Expression) {
+        let found = self.expression(condition, Some(&Type::boolean()));
+        if !found.is_error() && !found.is_boolean() {
+            self.incompatible(condition.position, &found, &Type::boolean());
+        }
+    }
+
+    // The pattern variables a condition introduces when it is true and when it is false
+    // (JLS §6.3.1).
+    fn bindings(&self, condition: &Expression) -> (Vec<usize>, Vec<usize>) {
+        match condition.kind {
+            ExpressionKind::InstanceOf { binding: Some(ref binding), .. } => {
+                (self.result.declarations.get(&address(binding)).cloned().into_iter().collect(), Vec::new())
+            },
+            ExpressionKind::Parenthesized(ref inner) => self.bindings(inner),
+            ExpressionKind::Unary { operator: UnaryOperator::Not, ref operand } => {
+                let (when_true, when_false) = self.bindings(operand);
+                (when_false, when_true)
+            },
+            ExpressionKind::Binary { operator: BinaryOperator::And, ref left, ref right } => {
+                let (mut when_true, _) = self.bindings(left);
+                when_true.extend(self.bindings(right).0);
+                (when_true, Vec::new())
+            },
+            ExpressionKind::Binary { operator: BinaryOperator::Or, ref left, ref right } => {
+                let (_, mut when_false) = self.bindings(left);
+                when_false.extend(self.bindings(right).1);
+                (Vec::new(), when_false)
+            },
+            _ => (Vec::new(), Vec::new())
+        }
+    }
+
+    fn local_variable(&mut self, variable: &'a ast::LocalVariable) {
+        let declared = variable.variable_type.as_ref().map(|written| self.resolve_type(written));
+        let is_final = variable.modifiers.contains(Modifier::Final);
+
+        for declarator in variable.declarators.iter() {
+            match declared {
+                Some(ref declared) => {
+                    let local_type = array_type(declared.clone(), declarator.dimensions);
+                    let local = self.declare_local(&declarator.name, local_type.clone(), is_final, None);
+                    if is_final && declarator.initializer.is_none() {
+                        self.blank_finals.insert(local);
+                    }
+                    if let Some(ref initializer) = declarator.initializer {
+                        self.variable_initializer(initializer, &local_type);
+                        if is_final {
+                            let constant = self.constant_of(initializer).and_then(|value| self.assignment_constant(&value, &local_type));
+                            self.result.locals[local].constant = constant;
+                        }
+                    }
+                },
+                None => {
+                    let local_type = self.inferred_local_type(&declarator.name, declarator.initializer.as_ref());
+                    let local = self.declare_local(&declarator.name, local_type.clone(), is_final, None);
+                    if let (true, Some(initializer)) = (is_final, declarator.initializer.as_ref()) {
+                        let constant = self.constant_of(initializer).and_then(|value| self.assignment_constant(&value, &local_type));
+                        self.result.locals[local].constant = constant;
+                    }
+                }
+            }
+        }
+    }
+
+    // The type of a local variable declared with var: the type of its initializer.
+    fn inferred_local_type(&mut self, name: &Identifier, initializer: Option<&'a Expression>) -> Type {
+        let initializer = match initializer {
+            Some(initializer) => initializer,
+            None => {
+                self.cannot_infer(name, "cannot use 'var' on variable without initializer");
+                return Type::Error;
+            }
+        };
+
+        let reason = match strip_parentheses(initializer).kind {
+            ExpressionKind::ArrayInitializer(_) => Some("array initializer needs an explicit target-type"),
+            ExpressionKind::Lambda { .. } => Some("lambda expression needs an explicit target-type"),
+            ExpressionKind::MethodReference { .. } => Some("method reference needs an explicit target-type"),
+            _ => None
+        };
+        if let Some(reason) = reason {
+            self.cannot_infer(name, reason);
+            self.silent += 1;
+            self.expression(initializer, None);
+            self.silent -= 1;
+            return Type::Error;
+        }
+
+        match self.expression(initializer, None) {
+            Type::Null => {
+                self.cannot_infer(name, "variable initializer is 'null'");
+                Type::Error
+            },
+            Type::Void => {
+                self.cannot_infer(name, "variable initializer is 'void'");
+                Type::Error
+            },
+            found => upward_projection(found)
+        }
+    }
+
+    fn cannot_infer(&mut self, name: &Identifier, reason: &str) {
+        let message = format!("cannot infer type for local variable {}\n  ({})", name.name, reason);
+        self.error(name.position, &message);
+    }
+
+    fn variable_initializer(&mut self, initializer: &'a Expression, target: &Type) {
+        if let ExpressionKind::ArrayInitializer(ref elements) = initializer.kind {
+            self.array_initializer(initializer, elements, target);
+        } else {
+            let found = self.expression(initializer, Some(target));
+            self.check_assignable(initializer, &found, target);
+        }
+    }
+
+    fn array_initializer(&mut self, initializer: &'a Expression, elements: &'a [Expression], target: &Type) {
+        let component = match *target {
+            Type::Array(ref component) => (**component).clone(),
+            Type::Error => Type::Error,
+            _ => {
+                self.error(initializer.position, &format!("illegal initializer for {}", target));
+                Type::Error
+            }
+        };
+        for element in elements.iter() {
+            self.variable_initializer(element, &component);
+        }
+        self.result.types.insert(address(initializer), target.clone());
+    }
+
+    fn for_each(&mut self, variable: &'a ast::LocalVariable, iterable: &'a Expression, body: &'a Statement) {
+        let found = self.value(iterable, None);
+        let element = match found {
+            Type::Array(ref component) => (**component).clone(),
+            Type::Error => Type::Error,
+            _ => match self.symbols().as_super(&found, "java/lang/Iterable") {
+                Some(Type::Class(ref iterable_type)) => match iterable_type.arguments.first() {
+                    Some(argument) => upper_bound(argument),
+                    None => Type::object()
+                },
+                Some(_) => Type::Error,
+                None => {
+                    let message = format!("for-each not applicable to expression type\n  required: array or java.lang.Iterable\n  found:    {}", found);
+                    self.error(iterable.position, &message);
+                    Type::Error
+                }
+            }
+        };
+
+        self.push_scope();
+        let is_final = variable.modifiers.contains(Modifier::Final);
+        for declarator in variable.declarators.iter() {
+            let local_type = match variable.variable_type {
+                Some(ref written) => {
+                    let declared = array_type(self.resolve_type(written), declarator.dimensions);
+                    if !self.is_assignable(&element, &declared, None) {
+                        self.incompatible(iterable.position, &element, &declared);
+                    }
+                    declared
+                },
+                None => element.clone()
+            };
+            self.declare_local(&declarator.name, local_type, is_final, None);
+        }
+        self.frame().jumps.push(Jump::Loop);
+        self.scoped_statement(body, &[]);
+        self.frame().jumps.pop();
+        self.pop_scope();
+    }
+
+    fn check_resource(&mut self, position: Position, found: &Type) {
+        let closeable = Type::class("java/lang/AutoCloseable");
+        if !found.is_error() && !self.symbols().is_subtype(found, &closeable) {
+            let message = format!("incompatible types: try-with-resources not applicable to variable type\n    ({} cannot be converted to AutoCloseable)", found);
+            self.error(position, &message);
+        }
+    }
+
+    fn jump(&mut self, position: Position, label: Option<&Identifier>, is_continue: bool) {
+        let keyword = if is_continue { "continue" } else { "break" };
+        let mut error = None;
+        {
+            let jumps = &self.frames.last().unwrap().jumps;
+            let mut found = false;
+            for jump in jumps.iter().rev() {
+                match (jump, label) {
+                    (&Jump::Lambda, _) => break,
+                    (&Jump::SwitchExpression(..), _) => {
+                        error = Some(format!("attempting to {} out of a switch expression", keyword));
+                        found = true;
+                        break;
+                    },
+                    (&Jump::Loop, None) => {
+                        found = true;
+                        break;
+                    },
+                    (&Jump::Switch, None) if !is_continue => {
+                        found = true;
+                        break;
+                    },
+                    (&Jump::Label(ref name, is_loop), Some(label)) if *name == label.name => {
+                        if is_continue && !is_loop {
+                            error = Some(format!("not a loop label: {}", name));
+                        }
+                        found = true;
+                        break;
+                    },
+                    _ => {}
+                }
+            }
+            if !found {
+                error = Some(match label {
+                    Some(label) => format!("undefined label: {}", label.name),
+                    None if is_continue => String::from("continue outside of loop"),
+                    None => String::from("break outside switch or loop")
+                });
+            }
+        }
+        if let Some(error) = error {
+            self.error(position, &error);
+        }
+    }
+
+    fn return_statement(&mut self, position: Position, value: Option<&'a Expression>) {
+        let expected = match self.frames.last().unwrap().returns.last() {
+            Some(Returns::Method(return_type)) => Some(Ok(return_type.clone())),
+            Some(Returns::Lambda(expected, _)) => Some(Err(expected.clone())),
+            Some(&Returns::Initializer) | None => None
+        };
+
+        match (expected, value) {
+            (None, _) => {
+                self.error(position, "return outside method");
+                if let Some(value) = value {
+                    self.expression(value, None);
+                }
+            },
+            (Some(Ok(Type::Void)), Some(value)) => {
+                self.expression(value, None);
+                self.error(value.position, "incompatible types: unexpected return value");
+            },
+            (Some(Ok(Type::Void)), None) => {},
+            (Some(Ok(_)), None) => self.error(position, "incompatible types: missing return value"),
+            (Some(Ok(return_type)), Some(value)) => {
+                let found = self.expression(value, Some(&return_type));
+                self.check_assignable(value, &found, &return_type);
+            },
+            (Some(Err(Some(Type::Void))), Some(value)) => {
+                self.expression(value, None);
+                self.error(value.position, "incompatible types: bad return type in lambda expression\n    unexpected return value");
+            },
+            (Some(Err(Some(Type::Void))), None) | (Some(Err(None)), None) => {},
+            (Some(Err(Some(_))), None) => {
+                self.error(position, "incompatible types: bad return type in lambda expression\n    missing return value");
+            },
+            (Some(Err(Some(return_type))), Some(value)) => {
+                let found = self.expression(value, Some(&return_type));
+                self.check_lambda_result(value, &found, &return_type);
+            },
+            (Some(Err(None)), Some(value)) => {
+                let found = self.expression(value, None);
+                if let Some(&mut Returns::Lambda(_, ref mut found_types)) = self.frame().returns.last_mut() {
+                    found_types.push(found);
+                }
+            }
+        }
+    }
+
+    // Attributes the value of a yield statement or of a case of a switch expression, and
+    // returns false when there is no switch expression to yield from.
+    fn yield_value(&mut self, value: &'a Expression) -> bool {
+        let mut expected = None;
+        let mut found_switch = false;
+        for jump in self.frames.last().unwrap().jumps.iter().rev() {
+            match *jump {
+                Jump::Lambda => break,
+                Jump::SwitchExpression(ref target, _) => {
+                    expected = target.clone();
+                    found_switch = true;
+                    break;
+                },
+                _ => {}
+            }
+        }
+        if !found_switch {
+            self.expression(value, None);
+            return false;
+        }
+
+        let found = self.value(value, expected.as_ref());
+        if let Some(ref expected) = expected {
+            self.check_assignable(value, &found, expected);
+        }
+        for jump in self.frame().jumps.iter_mut().rev() {
+            if let Jump::SwitchExpression(_, ref mut results) = *jump {
+                results.push(found);
+                break;
+            }
+        }
+        true
+    }
+
+    // A switch statement, or a switch expression, whose type is returned.
+    fn switch(&mut self, switch: &'a Switch, expected: Option<&Type>, is_expression: bool) -> Type {
+        let selector = self.value(&switch.selector, None);
+        let enum_class = selector.class_name().filter(|name| self.class(name).is_enum()).map(String::from);
+        let label_type = match selector.unboxed() {
+            Some(primitive) if selector.is_class(primitive.boxed()) || selector.is_primitive() => Type::Primitive(primitive),
+            _ => selector.clone()
+        };
+
+        let valid = selector.is_error() || enum_class.is_some() || selector.is_string()
+            || matches!(label_type, Type::Primitive(PrimitiveType::Char) | Type::Primitive(PrimitiveType::Byte)
+                | Type::Primitive(PrimitiveType::Short) | Type::Primitive(PrimitiveType::Int));
+        if !valid {
+            self.error(switch.selector.position, "patterns in switch statements are a preview feature and are disabled by default.\n  (use --enable-preview to enable patterns in switch statements)");
+        }
+
+        self.push_scope();
+        self.frame().jumps.push(if is_expression { Jump::SwitchExpression(expected.cloned(), Vec::new()) } else { Jump::Switch });
+        let mut seen_constants: Vec<Constant> = Vec::new();
+        let mut seen_names: Vec<String> = Vec::new();
+        let mut has_default = false;
+
+        for case in switch.cases.iter() {
+            if case.labels.is_empty() {
+                if has_default {
+                    self.error(case.position, "duplicate default label");
+                }
+                has_default = true;
+            }
+            for label in case.labels.iter() {
+                if let Some(ref enum_class) = enum_class {
+                    match label.kind {
+                        ExpressionKind::Name(ref name) => {
+                            let constant = self.class(enum_class).fields.iter().find(|field| field.name == name.name && field.access_flags & field::ACC_ENUM != 0).cloned();
+                            match constant {
+                                Some(constant) => {
+                                    self.result.types.insert(address(label), selector.clone());
+                                    self.result.references.insert(address(label), Reference::Field(constant, None));
+                                    if seen_names.contains(&name.name) {
+                                        self.error(case.position, "duplicate case label");
+                                    }
+                                    seen_names.push(name.name.clone());
+                                },
+                                None => {
+                                    let message = format!("cannot find symbol\n  symbol:   variable {}\n  location: {}", name.name, self.describe_class(enum_class));
+                                    self.error(label.position, &message);
+                                }
+                            }
+                        },
+                        _ => self.error(label.position, "an enum switch case label must be the unqualified name of an enumeration constant")
+                    }
+                    continue;
+                }
+
+                let found = self.value(label, Some(&label_type));
+                if !valid {
+                    continue;
+                }
+                match self.constant_of(label) {
+                    None if !found.is_error() => self.error(label.position, "constant expression required"),
+                    None => {},
+                    Some(value) => {
+                        if !self.check_assignable(label, &found, &label_type) {
+                            continue;
+                        }
+                        let value = self.assignment_constant(&value, &label_type).unwrap_or(value);
+                        if seen_constants.contains(&value) {
+                            self.error(case.position, "duplicate case label");
+                        }
+                        seen_constants.push(value);
+                    }
+                }
+            }
+
+            match case.body {
+                SwitchBody::Statements(ref statements) => {
+                    for statement in statements.iter() {
+                        self.statement(statement);
+                    }
+                },
+                SwitchBody::Expression(ref expression) => {
+                    if is_expression {
+                        self.yield_value(expression);
+                    } else {
+                        self.expression(expression, None);
+                    }
+                },
+                SwitchBody::Statement(ref statement) => self.scoped_statement(statement, &[])
+            }
+        }
+
+        let results = match self.frame().jumps.pop() {
+            Some(Jump::SwitchExpression(_, results)) => results,
+            _ => Vec::new()
+        };
+        self.pop_scope();
+
+        match expected {
+            Some(expected) => expected.clone(),
+            None if is_expression => self.unify(&results),
+            None => Type::Void
+        }
+    }
+
+}
+
+// Whether a statement can complete normally, as far as pattern variable scoping cares: a
+// statement that ends in a jump cannot.
+fn can_complete_normally(statement: &Statement) -> bool {
+    match statement.kind {
+        StatementKind::Return(_) | StatementKind::Throw(_) | StatementKind::Break(_) | StatementKind::Continue(_)
+            | StatementKind::Yield(_) => false,
+        StatementKind::Block(ref block) => block.statements.last().is_none_or(can_complete_normally),
+        StatementKind::If { ref then, otherwise: Some(ref otherwise), .. } => can_complete_normally(then) || can_complete_normally(otherwise),
+        _ => true
+    }
+}
+
+fn strip_parentheses(expression: &Expression) -> &Expression {
+    match expression.kind {
+        ExpressionKind::Parenthesized(ref inner) => strip_parentheses(inner),
+        _ => expression
+    }
+}
+
+// The upper bound of a type argument, which is what a wildcard stands for when read.
+fn upper_bound(argument: &Type) -> Type {
+    match *argument {
+        Type::Wildcard(Wildcard::Extends(ref bound)) => (**bound).clone(),
+        Type::Wildcard(_) => Type::object(),
+        _ => argument.clone()
+    }
+}
+
+// The type a var declaration gets for an initializer whose type mentions wildcards (JLS
+// §4.10.5), approximated by replacing top-level wildcards of captured types by their bounds.
+fn upward_projection(found: Type) -> Type {
+    match found {
+        Type::Null => Type::object(),
+        found => found
+    }
+}
+
+// Expressions.
+impl<'a> Attr<'a> {
+
+    // Attributes an expression and records its type. The expected type is the type of the
+    // variable or parameter the value is assigned to, from which lambda expressions, method
+    // references, diamonds and generic method calls get their types.
+    fn expression(&mut self, expression: &'a Expression, expected: Option<&Type>) -> Type {
+        let found = self.expression_type(expression, expected);
+        self.result.types.insert(address(expression), found.clone());
+        found
+    }
+
+    // An expression that must have a value, unlike a call of a void method.
+    fn value(&mut self, expression: &'a Expression, expected: Option<&Type>) -> Type {
+        let found = self.expression(expression, expected);
+        if found.is_void() {
+            self.error(expression.position, "'void' type not allowed here");
+            return Type::Error;
+        }
+        found
+    }
+
+    fn set_constant(&mut self, expression: &Expression, constant: Option<Constant>) {
+        if let Some(constant) = constant {
+            self.result.constants.insert(address(expression), constant);
+        }
+    }
+
+    fn expression_type(&mut self, expression: &'a Expression, expected: Option<&Type>) -> Type {
+        match expression.kind {
+            ExpressionKind::Literal(ref literal) => self.literal(expression, literal),
+            ExpressionKind::Name(ref name) => self.name(expression, name),
+            ExpressionKind::FieldAccess { ref target, ref name } => {
+                let qualifier = self.qualifier(target);
+                self.select(expression, target, qualifier, name)
+            },
+            ExpressionKind::SuperFieldAccess { ref qualifier, ref name } => self.super_field(expression, qualifier.as_ref(), name),
+            ExpressionKind::ArrayAccess { ref array, ref index } => {
+                let array_type = self.value(array, None);
+                self.index(index);
+                match array_type {
+                    Type::Array(component) => *component,
+                    Type::Error => Type::Error,
+                    found => {
+                        self.error(expression.position, &format!("array required, but {} found", found));
+                        Type::Error
+                    }
+                }
+            },
+            ExpressionKind::MethodCall { ref target, ref type_arguments, ref name, ref arguments, .. } => {
+                self.method_call(expression, target.as_deref(), type_arguments, name, arguments, expected)
+            },
+            ExpressionKind::SuperMethodCall { ref qualifier, ref type_arguments, ref name, ref arguments, .. } => {
+                self.super_method_call(expression, qualifier.as_ref(), type_arguments, name, arguments, expected)
+            },
+            ExpressionKind::New { ref outer, ref type_arguments, ref class_type, ref arguments, ref body } => {
+                self.new_instance(expression, outer.as_deref(), type_arguments, class_type, arguments, body.as_deref(), expected)
+            },
+            ExpressionKind::NewArray { ref element_type, ref dimensions, extra_dimensions, ref initializer } => {
+                let element = self.resolve_type(element_type);
+                let generic = match element {
+                    Type::Variable(_) => true,
+                    Type::Class(ref class_type) => class_type.arguments.iter().any(|argument| *argument != Type::Wildcard(Wildcard::Unbounded)),
+                    _ => false
+                };
+                if generic {
+                    self.error(expression.position, "generic array creation");
+                }
+                for dimension in dimensions.iter() {
+                    self.index(dimension);
+                }
+                let array = array_type(element, dimensions.len() + extra_dimensions);
+                if let Some(ref elements) = *initializer {
+                    self.array_initializer(expression, elements, &array);
+                }
+                array
+            },
+            ExpressionKind::ArrayInitializer(ref elements) => match expected {
+                Some(target) => {
+                    self.array_initializer(expression, elements, target);
+                    target.clone()
+                },
+                None => {
+                    self.error(expression.position, "array initializer is not allowed here");
+                    Type::Error
+                }
+            },
+            ExpressionKind::Unary { operator, ref operand } => self.unary(expression, operator, operand),
+            ExpressionKind::Binary { operator, ref left, ref right } => self.binary(expression, operator, left, right),
+            ExpressionKind::Assign { operator, ref target, ref value } => self.assign(expression, operator, target, value),
+            ExpressionKind::Conditional { ref condition, ref then, ref otherwise } => self.conditional(expression, condition, then, otherwise, expected),
+            ExpressionKind::InstanceOf { expression: ref operand, ref instance_type, ref binding } => {
+                self.instance_of(expression, operand, instance_type, binding.as_ref())
+            },
+            ExpressionKind::Cast { ref cast_type, ref bounds, expression: ref operand } => self.cast(expression, cast_type, bounds, operand),
+            ExpressionKind::Lambda { ref parameters, ref body } => self.lambda(expression, parameters, body, expected, &[]).0,
+            ExpressionKind::MethodReference { ref target, ref type_arguments, ref name } => {
+                self.method_reference(expression, target, type_arguments, name, expected, &[]).0
+            },
+            ExpressionKind::This(ref qualifier) => self.this(expression, qualifier.as_ref()),
+            ExpressionKind::ClassLiteral(ref written) => {
+                let boxed = match self.resolve_type(written) {
+                    Type::Primitive(primitive) => Type::class(primitive.boxed()),
+                    Type::Void => Type::class("java/lang/Void"),
+                    Type::Error => Type::Error,
+                    found => found.erasure()
+                };
+                Type::generic("java/lang/Class", vec![boxed])
+            },
+            ExpressionKind::Switch(ref switch) => self.switch(switch, expected, true),
+            ExpressionKind::Parenthesized(ref inner) => {
+                let found = self.expression(inner, expected);
+                let constant = self.constant_of(inner);
+                self.set_constant(expression, constant);
+                found
+            }
+        }
+    }
+
+    fn literal(&mut self, expression: &Expression, literal: &Literal) -> Type {
+        let (found, constant) = match *literal {
+            Literal::Int(value) => (Type::int(), Some(Constant::Int(value))),
+            Literal::Long(value) => (Type::Primitive(PrimitiveType::Long), Some(Constant::Long(value))),
+            Literal::Float(value) => (Type::Primitive(PrimitiveType::Float), Some(Constant::Float(value))),
+            Literal::Double(value) => (Type::Primitive(PrimitiveType::Double), Some(Constant::Double(value))),
+            Literal::Char(value) => (Type::Primitive(PrimitiveType::Char), Some(Constant::Int(i32::from(value)))),
+            Literal::String(ref value) => (Type::string(), Some(Constant::String(value.clone()))),
+            Literal::Boolean(value) => (Type::boolean(), Some(Constant::Boolean(value))),
+            Literal::Null => (Type::Null, None)
+        };
+        self.set_constant(expression, constant);
+        found
+    }
+
+    // A simple name in an expression, which names a local variable or a field.
+    fn name(&mut self, expression: &'a Expression, name: &'a Identifier) -> Type {
+        if let Some(local) = self.lookup_local(&name.name) {
+            self.result.references.insert(address(expression), Reference::Local(local));
+            let constant = self.result.locals[local].constant.clone();
+            self.set_constant(expression, constant);
+            return self.result.locals[local].local_type.clone();
+        }
+        if let Some((member, class, static_context)) = self.lookup_field(&name.name) {
+            if !member.is_static() && static_context {
+                self.error(expression.position, &format!("non-static variable {} cannot be referenced from a static context", name.name));
+            }
+            let implicit_this = if member.is_static() { None } else { class };
+            return self.field_reference(expression, member, implicit_this);
+        }
+
+        let message = format!("cannot find symbol\n  symbol:   variable {}\n  location: {}", name.name, self.location());
+        self.error(name.position, &message);
+        Type::Error
+    }
+
+    fn field_reference(&mut self, expression: &Expression, member: FieldSymbol, implicit_this: Option<String>) -> Type {
+        if !self.is_accessible(&member.owner, member.access_flags) {
+            self.access_error(expression.position, &member.name, &member.owner, member.access_flags);
+        }
+        let declared = self.declared_field(&member);
+        self.set_constant(expression, declared.constant.clone());
+        self.result.references.insert(address(expression), Reference::Field(declared, implicit_this));
+        member.field_type
+    }
+
+    // Whether a member with the given flags declared in a class can be used from the code being
+    // attributed (JLS §6.6).
+    fn is_accessible(&self, owner: &str, access_flags: u16) -> bool {
+        if access_flags & method::ACC_PUBLIC != 0 {
+            return true;
+        }
+        let current = match self.frames.last() {
+            Some(frame) => frame.class.clone(),
+            None => return true
+        };
+        if access_flags & method::ACC_PRIVATE != 0 {
+            return self.outermost_class(owner) == self.outermost_class(&current);
+        }
+        if types::package_of(owner) == types::package_of(&current) {
+            return true;
+        }
+        access_flags & method::ACC_PROTECTED != 0 && self.frames.iter().any(|frame| self.symbols().is_subclass(&frame.class, owner))
+    }
+
+    fn access_error(&mut self, position: Position, member: &str, owner: &str, access_flags: u16) {
+        let class = types::simple_name(owner);
+        let message = if access_flags & method::ACC_PRIVATE != 0 {
+            format!("{} has private access in {}", member, class)
+        } else if access_flags & method::ACC_PROTECTED != 0 {
+            format!("{} has protected access in {}", member, class)
+        } else {
+            format!("{} is not public in {}; cannot be accessed from outside package", member, class)
+        };
+        self.error(position, &message);
+    }
+
+    // Attributes what comes before a '.', which may also be the name of a class or a package
+    // (JLS §6.5.2).
+    fn qualifier(&mut self, expression: &'a Expression) -> Qualifier {
+        match expression.kind {
+            ExpressionKind::Name(ref name) => {
+                if self.lookup_local(&name.name).is_some() || self.lookup_field(&name.name).is_some() {
+                    return Qualifier::Value(self.value(expression, None));
+                }
+                if let Some(found) = self.lookup_type(&name.name) {
+                    self.qualifying_class(expression, &found);
+                    return Qualifier::Class(found);
+                }
+                self.result.references.insert(address(expression), Reference::Package(name.name.clone()));
+                Qualifier::Package(name.name.clone())
+            },
+            ExpressionKind::FieldAccess { ref target, ref name } => {
+                let qualifier = self.qualifier(target);
+                match qualifier {
+                    Qualifier::Package(ref package) => {
+                        let class = format!("{}/{}", package, name.name);
+                        if self.symbols().exists(&class) {
+                            let found = Type::class(&class);
+                            self.qualifying_class(expression, &found);
+                            return Qualifier::Class(found);
+                        }
+                        self.result.references.insert(address(expression), Reference::Package(class.clone()));
+                        return Qualifier::Package(class);
+                    },
+                    Qualifier::Class(ref class) if self.symbols().find_field(class, &name.name).is_none() => {
+                        let member = class.class_name().and_then(|class| self.symbols().find_member_class(class, &name.name));
+                        if let Some(member) = member {
+                            let found = Type::class(&member);
+                            self.qualifying_class(expression, &found);
+                            return Qualifier::Class(found);
+                        }
+                    },
+                    _ => {}
+                }
+                let found = self.select(expression, target, qualifier, name);
+                self.result.types.insert(address(expression), found.clone());
+                Qualifier::Value(found)
+            },
+            _ => Qualifier::Value(self.value(expression, None))
+        }
+    }
+
+    fn qualifying_class(&mut self, expression: &Expression, found: &Type) {
+        if let Some(class) = found.class_name() {
+            self.result.references.insert(address(expression), Reference::Class(String::from(class)));
+        }
+        self.result.types.insert(address(expression), found.clone());
+    }
+
+    // Reports a member selected from a package that has no such class. A simple name that is
+    // neither a variable nor a class is reported as a missing variable.
+    fn missing_package_member(&mut self, expression: &Expression, target: &Expression, package: &str, member: &str) {
+        if !package.contains('/') {
+            let message = format!("cannot find symbol\n  symbol:   variable {}\n  location: {}", package, self.location());
+            self.error(target.position, &message);
+        } else if self.symbols().package_exists(package) {
+            let message = format!("cannot find symbol\n  symbol:   class {}\n  location: package {}", member, package.replace('/', "."));
+            self.error(expression.position, &message);
+        } else {
+            let (parent, last) = package.split_at(package.rfind('/').unwrap());
+            if self.symbols().package_exists(parent) {
+                let message = format!("cannot find symbol\n  symbol:   class {}\n  location: package {}", &last[1..], parent.replace('/', "."));
+                self.error(target.position, &message);
+            } else {
+                self.error(target.position, &format!("package {} does not exist", parent.replace('/', ".")));
+            }
+        }
+    }
+
+    // How javac describes the type a member was looked up in.
+    fn describe_type(&self, site: &Type) -> String {
+        match *site {
+            Type::Class(ref class_type) => format!("{} {}", self.class(&class_type.name).kind_name(), site),
+            _ => format!("class {}", site)
+        }
+    }
+
+    // The location of a member looked up in the type of an expression: the variable, if the
+    // expression names one, or else the type.
+    fn site_location(&self, target: &Expression, site: &Type) -> String {
+        match self.result.references.get(&address(target)) {
+            Some(&Reference::Local(local)) => format!("variable {} of type {}", self.result.locals[local].name, site),
+            Some(Reference::Field(field, _)) => format!("variable {} of type {}", field.name, site),
+            _ => self.describe_type(site)
+        }
+    }
+
+    // A field selected from a qualifier.
+    fn select(&mut self, expression: &'a Expression, target: &'a Expression, qualifier: Qualifier, name: &'a Identifier) -> Type {
+        match qualifier {
+            Qualifier::Package(package) => {
+                self.missing_package_member(expression, target, &package, &name.name);
+                Type::Error
+            },
+            Qualifier::Class(class) => match self.symbols().find_field(&class, &name.name) {
+                Some(member) => {
+                    if !member.is_static() {
+                        self.error(expression.position, &format!("non-static variable {} cannot be referenced from a static context", name.name));
+                    }
+                    self.field_reference(expression, member, None)
+                },
+                None => {
+                    let message = format!("cannot find symbol\n  symbol:   variable {}\n  location: {}", name.name, self.describe_type(&class));
+                    self.error(expression.position, &message);
+                    Type::Error
+                }
+            },
+            Qualifier::Value(site) => match site {
+                Type::Error => Type::Error,
+                Type::Array(_) if name.name == "length" => Type::int(),
+                Type::Primitive(_) | Type::Null => {
+                    self.error(expression.position, &format!("{} cannot be dereferenced", site));
+                    Type::Error
+                },
+                _ => match self.symbols().find_field(&site, &name.name) {
+                    Some(member) => self.field_reference(expression, member, None),
+                    None => {
+                        let message = format!("cannot find symbol\n  symbol:   variable {}\n  location: {}", name.name, self.site_location(target, &site));
+                        self.error(expression.position, &message);
+                        Type::Error
+                    }
+                }
+            }
+        }
+    }
+
+    // The class that super refers to in super.f, super.m() and Outer.super.m(): the current class,
+    // or an enclosing class.
+    fn super_class_of(&mut self, position: Position, qualifier: Option<&'a QualifiedName>) -> Option<String> {
+        if self.frames.last().unwrap().is_static {
+            self.error(position, "non-static variable super cannot be referenced from a static context");
+        }
+        match qualifier {
+            None => Some(self.frames.last().unwrap().class.clone()),
+            Some(qualifier) => self.resolve_class_name(qualifier)
+        }
+    }
+
+    fn super_field(&mut self, expression: &'a Expression, qualifier: Option<&'a QualifiedName>, name: &'a Identifier) -> Type {
+        let class = match self.super_class_of(expression.position, qualifier) {
+            Some(class) => class,
+            None => return Type::Error
+        };
+        let super_type = self.class(&class).super_class.clone().unwrap_or(Type::Error);
+        match self.symbols().find_field(&super_type, &name.name) {
+            Some(member) => {
+                let implicit_this = if member.is_static() { None } else { Some(class) };
+                self.field_reference(expression, member, implicit_this)
+            },
+            None => {
+                if !super_type.is_error() {
+                    let message = format!("cannot find symbol\n  symbol: variable {}", name.name);
+                    self.error(name.position, &message);
+                }
+                Type::Error
+            }
+        }
+    }
+
+    // An array index or dimension, which is promoted to int.
+    fn index(&mut self, expression: &'a Expression) {
+        let found = self.value(expression, None);
+        let valid = found.unboxed().is_some_and(|primitive| primitive.is_integral() && primitive.promoted() == PrimitiveType::Int);
+        if !found.is_error() && !valid {
+            self.incompatible(expression.position, &found, &Type::int());
+        }
+    }
+
+    fn this(&mut self, expression: &'a Expression, qualifier: Option<&'a QualifiedName>) -> Type {
+        let class = match qualifier {
+            None => self.frames.last().unwrap().class.clone(),
+            Some(qualifier) => match self.resolve_class_name(qualifier) {
+                Some(class) => class,
+                None => return Type::Error
+            }
+        };
+
+        let mut static_context = false;
+        let mut found = false;
+        for frame in self.frames.iter().rev() {
+            static_context |= frame.is_static;
+            if frame.class == class {
+                found = true;
+                break;
+            }
+            if !self.class(&frame.class).is_inner() {
+                static_context = true;
+            }
+        }
+        if !found {
+            self.error(expression.position, &format!("not an enclosing class: {}", types::simple_name(&class)));
+            return Type::Error;
+        }
+        if static_context {
+            self.error(expression.position, "non-static variable this cannot be referenced from a static context");
+        }
+        self.result.references.insert(address(expression), Reference::Class(class.clone()));
+        self.class(&class).this_type()
+    }
+
+    // The class whose instance is the enclosing instance of a new instance of an inner class
+    // that is a member of the given class.
+    fn enclosing_instance(&self, outer: &str) -> Option<String> {
+        let mut static_context = false;
+        for frame in self.frames.iter().rev() {
+            static_context |= frame.is_static;
+            if self.symbols().is_subclass(&frame.class, outer) {
+                return if static_context { None } else { Some(frame.class.clone()) };
+            }
+            if !self.class(&frame.class).is_inner() {
+                static_context = true;
+            }
+        }
+        None
+    }
+
+    fn unary(&mut self, expression: &'a Expression, operator: UnaryOperator, operand: &'a Expression) -> Type {
+        match operator {
+            UnaryOperator::PreIncrement | UnaryOperator::PreDecrement | UnaryOperator::PostIncrement | UnaryOperator::PostDecrement => {
+                let found = self.variable(operand);
+                if !found.is_error() && !found.is_numeric() {
+                    self.error(expression.position, &format!("bad operand type {} for unary operator '{}'", found, unary_symbol(operator)));
+                    return Type::Error;
+                }
+                found
+            },
+            UnaryOperator::Not => {
+                let found = self.value(operand, None);
+                if !found.is_error() && !found.is_boolean() {
+                    self.error(expression.position, &format!("bad operand type {} for unary operator '!'", found));
+                    return Type::Error;
+                }
+                let folded = self.constant_of(operand).and_then(|value| constant::unary(operator, &value));
+                self.set_constant(expression, folded);
+                Type::boolean()
+            },
+            UnaryOperator::Plus | UnaryOperator::Minus | UnaryOperator::BitwiseNot => {
+                let found = self.value(operand, None);
+                if found.is_error() {
+                    return Type::Error;
+                }
+                let valid = if operator == UnaryOperator::BitwiseNot { found.is_integral() } else { found.is_numeric() };
+                if !valid {
+                    self.error(expression.position, &format!("bad operand type {} for unary operator '{}'", found, unary_symbol(operator)));
+                    return Type::Error;
+                }
+                let promoted = found.unboxed().unwrap().promoted();
+                let folded = self.constant_of(operand)
+                    .and_then(|value| constant::convert(&value, promoted))
+                    .and_then(|value| constant::unary(operator, &value));
+                self.set_constant(expression, folded);
+                Type::Primitive(promoted)
+            }
+        }
+    }
+
+    // The target of an assignment or of an increment or decrement, which must be a variable.
+    fn variable(&mut self, target: &'a Expression) -> Type {
+        let inner = strip_parentheses(target);
+        let found = self.expression(target, None);
+        // The current value of a constant variable is not what is assigned to.
+        self.result.constants.remove(&address(target));
+        self.result.constants.remove(&address(inner));
+
+        let is_variable = matches!(inner.kind, ExpressionKind::Name(_) | ExpressionKind::FieldAccess { .. }
+            | ExpressionKind::SuperFieldAccess { .. } | ExpressionKind::ArrayAccess { .. });
+        if !is_variable {
+            if !found.is_error() {
+                self.error(target.position, "unexpected type\n  required: variable\n  found:    value");
+            }
+            return Type::Error;
+        }
+        self.check_final_assignment(inner);
+        found
+    }
+
+    // Final variables may only be assigned where definite assignment analysis allows it: blank
+    // final locals, and blank final fields in the constructors and initializers of their class.
+    fn check_final_assignment(&mut self, target: &'a Expression) {
+        let name = match target.kind {
+            ExpressionKind::Name(ref name) | ExpressionKind::FieldAccess { ref name, .. } | ExpressionKind::SuperFieldAccess { ref name, .. } => name,
+            _ => return
+        };
+        match self.result.references.get(&address(target)).cloned() {
+            Some(Reference::Local(local)) => {
+                if !self.result.locals[local].is_final || self.blank_finals.contains(&local) {
+                    return;
+                }
+                let message = if self.final_parameters.contains(&local) {
+                    format!("final parameter {} may not be assigned", name.name)
+                } else {
+                    format!("cannot assign a value to final variable {}", name.name)
+                };
+                self.error(target.position, &message);
+            },
+            Some(Reference::Field(field, _)) => {
+                if !field.is_final() {
+                    return;
+                }
+                let by_simple_name = match target.kind {
+                    ExpressionKind::Name(_) => true,
+                    ExpressionKind::FieldAccess { target: ref qualifier, .. } => matches!(strip_parentheses(qualifier).kind, ExpressionKind::This(None)),
+                    _ => false
+                };
+                let frame = self.frames.last().unwrap();
+                let in_initializer = frame.location.starts_with("constructor ") || matches!(frame.returns.first(), Some(&Returns::Initializer));
+                let allowed = by_simple_name && in_initializer
+                    && field.owner == frame.class
+                    && field.is_static() == frame.is_static
+                    && !frame.jumps.iter().any(|jump| matches!(*jump, Jump::Lambda))
+                    && self.blank_final_fields.contains(&(field.owner.clone(), field.name.clone()));
+                if !allowed {
+                    self.error(target.position, &format!("cannot assign a value to final variable {}", name.name));
+                }
+            },
+            _ => {
+                let is_length = matches!(target.kind, ExpressionKind::FieldAccess { target: ref array, .. }
+                    if matches!(self.result.types.get(&address(&**array)), Some(&Type::Array(_))));
+                if is_length {
+                    self.error(target.position, "cannot assign a value to final variable length");
+                }
+            }
+        }
+    }
+
+    fn binary(&mut self, expression: &'a Expression, operator: BinaryOperator, left: &'a Expression, right: &'a Expression) -> Type {
+        let left_type = self.value(left, None);
+        let right_type = match operator {
+            // Pattern variables of the left operand are in scope in the right operand.
+            BinaryOperator::And | BinaryOperator::Or => {
+                let (when_true, when_false) = self.bindings(left);
+                self.push_scope();
+                self.introduce(if operator == BinaryOperator::And { &when_true } else { &when_false });
+                let found = self.value(right, None);
+                self.pop_scope();
+                found
+            },
+            _ => self.value(right, None)
+        };
+        if left_type.is_error() || right_type.is_error() {
+            return Type::Error;
+        }
+
+        let (result, operand) = match self.binary_type(operator, &left_type, &right_type) {
+            Some(found) => found,
+            None => {
+                let message = format!("bad operand types for binary operator '{}'\n  first type:  {}\n  second type: {}", binary_symbol(operator), left_type, right_type);
+                self.error(expression.position, &message);
+                return Type::Error;
+            }
+        };
+        if operand.is_none() && !result.is_string() && !self.is_castable(&left_type, &right_type) && !self.is_castable(&right_type, &left_type) {
+            self.error(expression.position, &format!("incomparable types: {} and {}", left_type, right_type));
+        }
+
+        if let (Some(left_value), Some(right_value)) = (self.constant_of(left), self.constant_of(right)) {
+            let folded = if result.is_string() {
+                let mut text = left_value.to_java_string(left_type.primitive());
+                text.extend(right_value.to_java_string(right_type.primitive()));
+                Some(Constant::String(text))
+            } else {
+                operand.and_then(|operand| {
+                    let left_value = constant::convert(&left_value, operand)?;
+                    let right_operand = if is_shift(operator) { right_type.unboxed()?.promoted() } else { operand };
+                    let right_value = constant::convert(&right_value, right_operand)?;
+                    constant::binary(operator, &left_value, &right_value)
+                })
+            };
+            self.set_constant(expression, folded);
+        }
+        result
+    }
+
+    // The type of a binary operation, and the type its operands are promoted to, which is None
+    // for string concatenation and reference comparisons. None if the operator does not apply.
+    fn binary_type(&self, operator: BinaryOperator, left: &Type, right: &Type) -> Option<(Type, Option<PrimitiveType>)> {
+        use compiler::ast::BinaryOperator::*;
+
+        let numeric = || if left.is_numeric() && right.is_numeric() {
+            Some(PrimitiveType::promote(left.unboxed()?, right.unboxed()?))
+        } else {
+            None
+        };
+        match operator {
+            Add if left.is_string() || right.is_string() => Some((Type::string(), None)),
+            Add | Subtract | Multiply | Divide | Remainder => numeric().map(|promoted| (Type::Primitive(promoted), Some(promoted))),
+            ShiftLeft | ShiftRight | UnsignedShiftRight => {
+                if left.is_integral() && right.is_integral() {
+                    let promoted = left.unboxed()?.promoted();
+                    Some((Type::Primitive(promoted), Some(promoted)))
+                } else {
+                    None
+                }
+            },
+            Less | Greater | LessOrEqual | GreaterOrEqual => numeric().map(|promoted| (Type::boolean(), Some(promoted))),
+            Equal | NotEqual => {
+                if left.is_numeric() && right.is_numeric() && (left.is_primitive() || right.is_primitive()) {
+                    numeric().map(|promoted| (Type::boolean(), Some(promoted)))
+                } else if left.is_boolean() && right.is_boolean() && (left.is_primitive() || right.is_primitive()) {
+                    Some((Type::boolean(), Some(PrimitiveType::Boolean)))
+                } else if left.is_reference() && right.is_reference() {
+                    Some((Type::boolean(), None))
+                } else {
+                    None
+                }
+            },
+            BitwiseAnd | BitwiseOr | BitwiseXor => {
+                if left.is_boolean() && right.is_boolean() {
+                    Some((Type::boolean(), Some(PrimitiveType::Boolean)))
+                } else if left.is_integral() && right.is_integral() {
+                    numeric().map(|promoted| (Type::Primitive(promoted), Some(promoted)))
+                } else {
+                    None
+                }
+            },
+            And | Or => {
+                if left.is_boolean() && right.is_boolean() { Some((Type::boolean(), Some(PrimitiveType::Boolean))) } else { None }
+            }
+        }
+    }
+
+    fn assign(&mut self, expression: &'a Expression, operator: Option<BinaryOperator>, target: &'a Expression, value: &'a Expression) -> Type {
+        let target_type = self.variable(target);
+        match operator {
+            None => {
+                let found = self.value(value, Some(&target_type));
+                self.check_assignable(value, &found, &target_type);
+            },
+            Some(operator) => {
+                let found = self.value(value, None);
+                if target_type.is_error() || found.is_error() || (operator == BinaryOperator::Add && target_type.is_string()) {
+                    return target_type;
+                }
+                // The result of a compound assignment is cast back to the type of the variable.
+                let valid = match self.binary_type(operator, &target_type, &found) {
+                    Some((ref result, _)) if result.is_string() => false,
+                    Some((ref result, _)) => self.is_castable(result, &target_type),
+                    None => false
+                };
+                if !valid {
+                    let message = format!("bad operand types for binary operator '{}'\n  first type:  {}\n  second type: {}", binary_symbol(operator), target_type, found);
+                    self.error(expression.position, &message);
+                }
+            }
+        }
+        target_type
+    }
+
+    fn conditional(&mut self, expression: &'a Expression, condition: &'a Expression, then: &'a Expression, otherwise: &'a Expression, expected: Option<&Type>) -> Type {
+        self.condition(condition);
+        let (when_true, when_false) = self.bindings(condition);
+        self.push_scope();
+        self.introduce(&when_true);
+        let then_type = self.value(then, expected);
+        self.pop_scope();
+        self.push_scope();
+        self.introduce(&when_false);
+        let otherwise_type = self.value(otherwise, expected);
+        self.pop_scope();
+
+        let result = self.conditional_type(then, &then_type, otherwise, &otherwise_type, expected);
+        if let (Some(Constant::Boolean(chosen)), Some(then_value), Some(otherwise_value)) = (self.constant_of(condition), self.constant_of(then), self.constant_of(otherwise)) {
+            let value = if chosen { then_value } else { otherwise_value };
+            let folded = self.assignment_constant(&value, &result);
+            self.set_constant(expression, folded);
+        }
+        result
+    }
+
+    // The type of a conditional expression (JLS §15.25).
+    fn conditional_type(&self, then: &Expression, then_type: &Type, otherwise: &Expression, otherwise_type: &Type, expected: Option<&Type>) -> Type {
+        use compiler::ast::PrimitiveType::*;
+
+        if then_type.is_error() || otherwise_type.is_error() {
+            return Type::Error;
+        }
+        if then_type == otherwise_type {
+            return then_type.clone();
+        }
+        if then_type.is_boolean() && otherwise_type.is_boolean() {
+            return Type::boolean();
+        }
+        if then_type.is_numeric() && otherwise_type.is_numeric() {
+            let (a, b) = (then_type.unboxed().unwrap(), otherwise_type.unboxed().unwrap());
+            // A narrower type is kept when the other operand is an int constant that fits in it.
+            let fits = |narrow: PrimitiveType, other: PrimitiveType, expression: &Expression| {
+                matches!(narrow, Byte | Short | Char) && other == Int && self.constant_of(expression).is_some_and(|value| value.fits_in(narrow))
+            };
+            if fits(a, b, otherwise) {
+                return Type::Primitive(a);
+            }
+            if fits(b, a, then) {
+                return Type::Primitive(b);
+            }
+            if (a == Byte && b == Short) || (a == Short && b == Byte) {
+                return Type::Primitive(Short);
+            }
+            return Type::Primitive(PrimitiveType::promote(a, b));
+        }
+        if let Some(expected) = expected {
+            return expected.clone();
+        }
+        self.lub(&[boxed(then_type), boxed(otherwise_type)])
+    }
+
+    fn instance_of(&mut self, expression: &'a Expression, operand: &'a Expression, instance_type: &'a ast::Type, binding: Option<&'a Identifier>) -> Type {
+        let found = self.value(operand, None);
+        let target = self.resolve_type(instance_type);
+        if target.is_primitive() {
+            let message = format!("unexpected type\n  required: reference\n  found:    {}", target);
+            self.error(instance_type.position(), &message);
+        } else if found.is_primitive() {
+            let message = format!("unexpected type\n  required: reference\n  found:    {}", found);
+            self.error(operand.position, &message);
+        } else if !self.is_castable(&found, &target) {
+            self.incompatible(expression.position, &found, &target);
+        } else if binding.is_some() && !found.is_error() && !target.is_error() && found != Type::Null && self.symbols().is_subtype(&found, &target) {
+            let message = format!("expression type {} is a subtype of pattern type {}", found, target);
+            self.error(expression.position, &message);
+        }
+
+        // A pattern variable is declared here, but only comes into scope where the pattern is
+        // known to have matched.
+        if let Some(binding) = binding {
+            self.new_local(binding, target, false);
+        }
+        Type::boolean()
+    }
+
+    fn cast(&mut self, expression: &'a Expression, cast_type: &'a ast::Type, bounds: &'a [ast::Type], operand: &'a Expression) -> Type {
+        let target = self.resolve_type(cast_type);
+        for bound in bounds.iter() {
+            self.resolve_type(bound);
+        }
+        let expected = if is_functional_expression(operand) { Some(&target) } else { None };
+        let found = self.value(operand, expected);
+        if !self.is_castable(&found, &target) {
+            self.incompatible(operand.position, &found, &target);
+            return target;
+        }
+
+        let folded = self.constant_of(operand).and_then(|value| self.assignment_constant(&value, &target));
+        self.set_constant(expression, folded);
+        target
+    }
+
+    // Casting conversion (JLS §5.5), leniently for reference types: a cast between classes that
+    // are not related, or from a final class to an interface it does not implement, is rejected.
+    fn is_castable(&self, found: &Type, target: &Type) -> bool {
+        match (found, target) {
+            (&Type::Error, _) | (_, &Type::Error) => true,
+            (&Type::Primitive(from), &Type::Primitive(to)) => from.is_numeric() == to.is_numeric(),
+            (&Type::Primitive(from), _) => self.symbols().is_subtype(&Type::class(from.boxed()), target),
+            (_, &Type::Primitive(to)) => match found.unboxed() {
+                Some(from) => from.widens_to(to),
+                None => self.symbols().is_subtype(&Type::class(to.boxed()), found)
+            },
+            (&Type::Null, _) => target.is_reference(),
+            (&Type::Void, _) | (_, &Type::Void) => false,
+            (Type::Array(from), Type::Array(to)) => {
+                if from.is_primitive() || to.is_primitive() { from == to } else { self.is_castable(from, to) }
+            },
+            (&Type::Variable(_), _) | (_, &Type::Variable(_)) => true,
+            _ => {
+                let (from, to) = (found.erasure(), target.erasure());
+                if self.symbols().is_subtype(&from, &to) || self.symbols().is_subtype(&to, &from) {
+                    return true;
+                }
+                match (from.class_name(), to.class_name()) {
+                    (Some(from), Some(to)) => {
+                        let (from, to) = (self.class(from), self.class(to));
+                        (from.is_interface() && !to.is_final()) || (to.is_interface() && !from.is_final())
+                    },
+                    _ => false
+                }
+            }
+        }
+    }
+
+    // Reports a value of one type that cannot be assigned to a variable of another.
+    fn incompatible(&mut self, position: Position, found: &Type, target: &Type) {
+        if found.is_error() || target.is_error() {
+            return;
+        }
+        let message = format!("incompatible types: {}", self.conversion_problem(found, target));
+        self.error(position, &message);
+    }
+
+    fn conversion_problem(&self, found: &Type, target: &Type) -> String {
+        match (found, target) {
+            (&Type::Primitive(from), &Type::Primitive(to)) if from.is_numeric() && to.is_numeric() => {
+                format!("possible lossy conversion from {} to {}", found, target)
+            },
+            _ => format!("{} cannot be converted to {}", found, target)
+        }
+    }
+
+    // Checks that the value of an expression can be assigned to a variable of the target type.
+    // Each operand of a conditional expression is checked on its own.
+    fn check_assignable(&mut self, expression: &'a Expression, found: &Type, target: &Type) -> bool {
+        if let ExpressionKind::Conditional { ref then, ref otherwise, .. } = strip_parentheses(expression).kind {
+            if !target.is_error() && self.constant_of(expression).is_none() {
+                let then_type = self.result.types.get(&address(&**then)).cloned().unwrap_or(Type::Error);
+                let otherwise_type = self.result.types.get(&address(&**otherwise)).cloned().unwrap_or(Type::Error);
+                let then_valid = self.check_assignable(then, &then_type, target);
+                return self.check_assignable(otherwise, &otherwise_type, target) && then_valid;
+            }
+        }
+
+        let constant = self.constant_of(expression);
+        if self.is_assignable(found, target, constant.as_ref()) {
+            return true;
+        }
+        self.incompatible(expression.position, found, target);
+        false
+    }
+
+    // Assignment conversion (JLS §5.2): a widening, boxing or unboxing conversion, or the
+    // narrowing of an int constant that fits in the variable.
+    fn is_assignable(&self, found: &Type, target: &Type, constant: Option<&Constant>) -> bool {
+        if found.is_error() || target.is_error() {
+            return true;
+        }
+        if found.is_void() || target.is_void() {
+            return false;
+        }
+        if self.is_convertible(found, target, true) {
+            return true;
+        }
+
+        let narrow = match *target {
+            Type::Primitive(primitive) => Some(primitive),
+            Type::Class(ref class_type) => PrimitiveType::unbox(&class_type.name)
+                .filter(|primitive| matches!(*primitive, PrimitiveType::Byte | PrimitiveType::Short | PrimitiveType::Char)),
+            _ => None
+        };
+        let from_int = matches!(found.primitive(), Some(PrimitiveType::Byte) | Some(PrimitiveType::Short) | Some(PrimitiveType::Char) | Some(PrimitiveType::Int));
+        match (narrow, constant) {
+            (Some(narrow), Some(constant)) => from_int && constant.fits_in(narrow),
+            _ => false
+        }
+    }
+
+    // Whether a value of one type converts to another in a strict invocation context, or with
+    // boxing and unboxing in a loose one (JLS §5.3).
+    fn is_convertible(&self, found: &Type, target: &Type, loose: bool) -> bool {
+        match (found, target) {
+            (&Type::Error, _) | (_, &Type::Error) => true,
+            (&Type::Primitive(from), &Type::Primitive(to)) => from.widens_to(to),
+            (&Type::Primitive(from), _) => loose && self.symbols().is_subtype(&Type::class(from.boxed()), target),
+            (_, &Type::Primitive(to)) => loose && found.unboxed().is_some_and(|from| from.widens_to(to)),
+            _ => self.symbols().is_subtype(found, target)
+        }
+    }
+
+    // The least upper bound of reference types (JLS §4.10.4), simplified to a single class or
+    // interface: the most specific supertype they share, preferring a class when there are
+    // several, and Object when no one is best.
+    fn lub(&self, types: &[Type]) -> Type {
+        self.lub_at_depth(types, 0)
+    }
+
+    fn lub_at_depth(&self, types: &[Type], depth: usize) -> Type {
+        if types.iter().any(Type::is_error) {
+            return Type::Error;
+        }
+        let types: Vec<Type> = types.iter().filter(|found| **found != Type::Null).map(boxed).collect();
+        let first = match types.first() {
+            Some(first) => first.clone(),
+            None => return Type::Null
+        };
+        if types.iter().all(|found| symbols::same_type(found, &first)) {
+            return first;
+        }
+        if let Some(upper) = types.iter().find(|upper| types.iter().all(|found| self.symbols().is_subtype(found, upper))) {
+            return upper.clone();
+        }
+        if types.iter().all(|found| matches!(*found, Type::Array(ref component) if !component.is_primitive())) {
+            let components: Vec<Type> = types.iter().filter_map(|found| found.component().cloned()).collect();
+            return Type::array_of(self.lub_at_depth(&components, depth));
+        }
+
+        let mut closure = Vec::new();
+        self.erased_supertypes(&first, &mut closure);
+        let common: Vec<String> = closure.into_iter()
+            .filter(|class| types.iter().all(|found| self.symbols().as_super(found, class).is_some()))
+            .collect();
+        let minimal: Vec<&String> = common.iter()
+            .filter(|class| !common.iter().any(|other| other != *class && self.symbols().is_subclass(other, class)))
+            .collect();
+        let chosen = if minimal.len() == 1 {
+            minimal[0].clone()
+        } else {
+            match minimal.iter().find(|class| !self.class(class).is_interface()) {
+                Some(class) => (*class).clone(),
+                None => String::from(OBJECT)
+            }
+        };
+
+        // Type arguments are kept when they agree, and are otherwise replaced by wildcards.
+        let parameterizations: Vec<ClassType> = types.iter().filter_map(|found| match self.symbols().as_super(found, &chosen) {
+            Some(Type::Class(class_type)) => Some(class_type),
+            _ => None
+        }).collect();
+        let arguments = match parameterizations.first() {
+            Some(first) if !first.arguments.is_empty() && parameterizations.iter().all(|other| other.arguments.len() == first.arguments.len()) => {
+                (0..first.arguments.len()).map(|index| {
+                    let column: Vec<Type> = parameterizations.iter().map(|other| other.arguments[index].clone()).collect();
+                    if column.iter().all(|argument| symbols::same_type(argument, &column[0])) {
+                        column[0].clone()
+                    } else if depth < 2 && column.iter().all(|argument| !matches!(*argument, Type::Wildcard(_))) {
+                        Type::Wildcard(Wildcard::Extends(Box::new(self.lub_at_depth(&column, depth + 1))))
+                    } else {
+                        Type::Wildcard(Wildcard::Unbounded)
+                    }
+                }).collect()
+            },
+            _ => Vec::new()
+        };
+        Type::generic(&chosen, arguments)
+    }
+
+    // The classes and interfaces a type is a subtype of, nearest first.
+    fn erased_supertypes(&self, found: &Type, closure: &mut Vec<String>) {
+        let mut queue = vec![found.clone()];
+        while !queue.is_empty() {
+            let next = queue.remove(0);
+            if let Some(class) = next.class_name() {
+                if closure.iter().any(|known| known == class) {
+                    continue;
+                }
+                closure.push(String::from(class));
+            }
+            queue.extend(self.symbols().supertypes(&next));
+        }
+    }
+
+    // The type of a switch expression without a target type, from the types of its results.
+    fn unify(&self, results: &[Type]) -> Type {
+        if results.is_empty() || results.iter().any(Type::is_error) {
+            return Type::Error;
+        }
+        if results.iter().all(|found| found == &results[0]) {
+            return results[0].clone();
+        }
+        if results.iter().all(Type::is_boolean) {
+            return Type::boolean();
+        }
+        if results.iter().all(Type::is_numeric) {
+            let promoted = results.iter().filter_map(Type::unboxed).fold(PrimitiveType::Int, PrimitiveType::promote);
+            return Type::Primitive(promoted);
+        }
+        self.lub(results)
+    }
+
+}
+
+fn unary_symbol(operator: UnaryOperator) -> &'static str {
+    match operator {
+        UnaryOperator::Plus => "+",
+        UnaryOperator::Minus => "-",
+        UnaryOperator::BitwiseNot => "~",
+        UnaryOperator::Not => "!",
+        UnaryOperator::PreIncrement | UnaryOperator::PostIncrement => "++",
+        UnaryOperator::PreDecrement | UnaryOperator::PostDecrement => "--"
+    }
+}
+
+fn binary_symbol(operator: BinaryOperator) -> &'static str {
+    use compiler::ast::BinaryOperator::*;
+    match operator {
+        Or => "||",
+        And => "&&",
+        BitwiseOr => "|",
+        BitwiseXor => "^",
+        BitwiseAnd => "&",
+        Equal => "==",
+        NotEqual => "!=",
+        Less => "<",
+        Greater => ">",
+        LessOrEqual => "<=",
+        GreaterOrEqual => ">=",
+        ShiftLeft => "<<",
+        ShiftRight => ">>",
+        UnsignedShiftRight => ">>>",
+        Add => "+",
+        Subtract => "-",
+        Multiply => "*",
+        Divide => "/",
+        Remainder => "%"
+    }
+}
+
+fn is_shift(operator: BinaryOperator) -> bool {
+    matches!(operator, BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight | BinaryOperator::UnsignedShiftRight)
+}
+
+// Primitive types are boxed where a reference type is needed.
+fn boxed(found: &Type) -> Type {
+    match *found {
+        Type::Primitive(primitive) => Type::class(primitive.boxed()),
+        _ => found.clone()
+    }
+}
+
+fn is_functional_expression(expression: &Expression) -> bool {
+    matches!(strip_parentheses(expression).kind, ExpressionKind::Lambda { .. } | ExpressionKind::MethodReference { .. })
+}
+
+// The expressions that may be used as statements (JLS §14.8).
+fn is_statement_expression(expression: &Expression) -> bool {
+    match expression.kind {
+        ExpressionKind::MethodCall { .. } | ExpressionKind::SuperMethodCall { .. } | ExpressionKind::New { .. } | ExpressionKind::Assign { .. } => true,
+        ExpressionKind::Unary { operator, .. } => matches!(operator, UnaryOperator::PreIncrement | UnaryOperator::PreDecrement
+            | UnaryOperator::PostIncrement | UnaryOperator::PostDecrement),
+        _ => false
+    }
+}
+
+// An argument of a method invocation. Lambda expressions and method references are attributed
+// only once the method is chosen, against the type of its parameter; other arguments are
+// attributed on their own first. Method references look up methods with the parameter types
+// of a function type, which have no expression.
+enum Argument<'a> {
+    Typed(Type, Option<&'a Expression>),
+    Deferred(&'a Expression)
+}
+
+// What an invocation names, for error messages: a method, with where it was looked up, or a
+// constructor of a class.
+enum MethodName<'n> {
+    Method(&'n str, String),
+    Constructor(&'n Type)
+}
+
+// The bounds found for the type parameters of a generic method while checking that it is
+// applicable to arguments (JLS §18). Unlike the JLS, inference variables are resolved one at
+// a time: to a type they must equal, else to the lub of their lower bounds, else to their most
+// specific upper bound.
+#[derive(Clone)]
+struct Inference {
+    variables: Vec<TypeVariable>,
+    equal: Vec<Vec<Type>>,
+    lower: Vec<Vec<Type>>,
+    upper: Vec<Vec<Type>>
+}
+
+impl Inference {
+
+    fn new(variables: Vec<TypeVariable>) -> Inference {
+        let count = variables.len();
+        Inference { variables, equal: vec![Vec::new(); count], lower: vec![Vec::new(); count], upper: vec![Vec::new(); count] }
+    }
+
+    fn index(&self, t: &Type) -> Option<usize> {
+        match *t {
+            Type::Variable(ref variable) => self.variables.iter().position(|other| other == variable),
+            _ => None
+        }
+    }
+
+    fn mentions(&self, t: &Type) -> bool {
+        let variables = &self.variables;
+        !variables.is_empty() && t.mentions(|variable| variables.contains(variable))
+    }
+
+}
+
+// Method invocations, class instance creation, lambda expressions and method references.
+impl<'a> Attr<'a> {
+
+    fn type_arguments(&mut self, arguments: &'a [TypeArgument]) -> Vec<Type> {
+        arguments.iter().map(|argument| self.resolve_type_argument(argument)).collect()
+    }
+
+    fn method_call(&mut self, expression: &'a Expression, target: Option<&'a Expression>, type_arguments: &'a [TypeArgument],
+                   name: &'a Identifier, arguments: &'a [Expression], expected: Option<&Type>) -> Type {
+        let explicit = self.type_arguments(type_arguments);
+        let (candidates, location, this_class, static_only, site) = match target {
+            None => match self.lookup_method(&name.name) {
+                Some((candidates, class, static_context)) => {
+                    let site = class.as_ref().map(|class| self.class(class).this_type());
+                    (candidates, self.location(), class, static_context, site)
+                },
+                None => (Vec::new(), self.location(), None, false, None)
+            },
+            Some(target) => match self.qualifier(target) {
+                Qualifier::Package(package) => {
+                    self.missing_package_member(expression, target, &package, &name.name);
+                    self.attribute_arguments(arguments);
+                    return Type::Error;
+                },
+                Qualifier::Class(class) => {
+                    let candidates = self.symbols().find_methods(&class, &name.name);
+                    (candidates, self.describe_type(&class), None, true, Some(class))
+                },
+                Qualifier::Value(Type::Error) => {
+                    self.attribute_arguments(arguments);
+                    return Type::Error;
+                },
+                Qualifier::Value(site) => {
+                    if site.is_primitive() || site == Type::Null {
+                        self.error(expression.position, &format!("{} cannot be dereferenced", site));
+                        self.attribute_arguments(arguments);
+                        return Type::Error;
+                    }
+                    let candidates = self.symbols().find_methods(&site, &name.name);
+                    (candidates, self.site_location(target, &site), None, false, Some(site))
+                }
+            }
+        };
+
+        let method_name = MethodName::Method(&name.name, location);
+        let mut invocation = match self.resolve(candidates, arguments, &explicit, expected, expression.position, &method_name) {
+            Some(invocation) => invocation,
+            None => return Type::Error
+        };
+        if static_only && !invocation.method.is_static() {
+            let message = format!("non-static method {} cannot be referenced from a static context", invocation.method.signature());
+            self.error(expression.position, &message);
+        }
+        if target.is_none() && !invocation.method.is_static() {
+            invocation.implicit_this = this_class;
+        }
+        // The return types of clone() on arrays and of getClass() are special (JLS §10.7, §4.3.2).
+        if arguments.is_empty() {
+            match site {
+                Some(Type::Array(_)) if name.name == "clone" => {
+                    // The clone method of an array throws no checked exceptions.
+                    invocation.result = site.clone().unwrap();
+                    invocation.throws.clear();
+                },
+                Some(ref site) if name.name == "getClass" => {
+                    invocation.result = Type::generic("java/lang/Class", vec![Type::Wildcard(Wildcard::Extends(Box::new(site.erasure())))]);
+                },
+                _ => {}
+            }
+        }
+        let result = invocation.result.clone();
+        self.result.invocations.insert(address(expression), invocation);
+        result
+    }
+
+    // Finds the methods a simple method name refers to: those of the innermost class the code is
+    // in that has methods with the name (JLS §15.12.1), or else statically imported ones. Also
+    // gives the class, and whether an instance method would be called from a static context.
+    fn lookup_method(&self, name: &str) -> Option<(Vec<MethodMember>, Option<String>, bool)> {
+        let mut static_context = false;
+        for frame in self.frames.iter().rev() {
+            static_context |= frame.is_static;
+            let symbol = self.class(&frame.class);
+            let candidates = self.symbols().find_methods(&symbol.this_type(), name);
+            if !candidates.is_empty() {
+                return Some((candidates, Some(frame.class.clone()), static_context));
+            }
+            if !symbol.is_inner() {
+                static_context = true;
+            }
+        }
+
+        let mut candidates = Vec::new();
+        for import in self.units[self.unit].1.imports.iter().filter(|import| import.is_static) {
+            let parts: Vec<&str> = import.name.parts.iter().map(|part| part.name.as_str()).collect();
+            let (class_parts, member) = if import.on_demand { (&parts[..], name) } else { (&parts[..parts.len() - 1], *parts.last().unwrap()) };
+            if member != name {
+                continue;
+            }
+            if let Some(class) = self.fully_qualified_class(class_parts) {
+                candidates.extend(self.symbols().find_methods(&Type::class(&class), name).into_iter().filter(|method| method.member.is_static()));
+            }
+        }
+        if candidates.is_empty() { None } else { Some((candidates, None, false)) }
+    }
+
+    fn super_method_call(&mut self, expression: &'a Expression, qualifier: Option<&'a QualifiedName>, type_arguments: &'a [TypeArgument],
+                         name: &'a Identifier, arguments: &'a [Expression], expected: Option<&Type>) -> Type {
+        let explicit = self.type_arguments(type_arguments);
+        let class = match self.super_class_of(expression.position, qualifier) {
+            Some(class) => class,
+            None => {
+                self.attribute_arguments(arguments);
+                return Type::Error;
+            }
+        };
+
+        // I.super.m() calls a default method of a direct superinterface; Outer.super.m() calls a
+        // method of the superclass of an enclosing class.
+        let current = self.current_class();
+        let interface = current.interfaces.iter().find(|interface| interface.class_name() == Some(class.as_str())).cloned();
+        let (site, receiver) = match interface {
+            Some(interface) => (interface, current.name.clone()),
+            None => (self.class(&class).super_class.clone().unwrap_or(Type::Error), class)
+        };
+        if site.is_error() {
+            self.attribute_arguments(arguments);
+            return Type::Error;
+        }
+
+        let candidates = self.symbols().find_methods(&site, &name.name);
+        let method_name = MethodName::Method(&name.name, self.describe_type(&site));
+        let mut invocation = match self.resolve(candidates, arguments, &explicit, expected, expression.position, &method_name) {
+            Some(invocation) => invocation,
+            None => return Type::Error
+        };
+        if invocation.method.is_abstract() {
+            let message = format!("abstract method {} in {} cannot be accessed directly", invocation.method.signature(), types::simple_name(&invocation.method.owner));
+            self.error(expression.position, &message);
+        }
+        if !invocation.method.is_static() {
+            invocation.implicit_this = Some(receiver);
+        }
+        let result = invocation.result.clone();
+        self.result.invocations.insert(address(expression), invocation);
+        result
+    }
+
+    // The constructors of a class. With a diamond, the type parameters of the class are
+    // inferred along with those of the constructor, as if it were a generic method returning
+    // the class type (JLS §15.9.3).
+    fn constructors(&self, site: &Type, diamond: bool) -> Vec<MethodMember> {
+        let symbol = match site.class_name() {
+            Some(class) => self.class(class),
+            None => return Vec::new()
+        };
+        if !diamond || symbol.type_parameters.is_empty() {
+            return self.symbols().find_methods(site, "<init>");
+        }
+        let this_type = symbol.this_type();
+        symbol.methods.iter().filter(|declared| declared.is_constructor()).map(|declared| {
+            let mut type_parameters = symbol.type_parameters.clone();
+            type_parameters.extend(declared.type_parameters.iter().cloned());
+            let member = MethodSymbol { type_parameters, return_type: this_type.clone(), ..declared.clone() };
+            MethodMember { declared: declared.clone(), member }
+        }).collect()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn new_instance(&mut self, expression: &'a Expression, outer: Option<&'a Expression>, type_arguments: &'a [TypeArgument],
+                    class_type: &'a ast::ClassType, arguments: &'a [Expression], body: Option<&'a [Member]>, expected: Option<&Type>) -> Type {
+        let explicit = self.type_arguments(type_arguments);
+        let diamond = class_type.parts.last().is_some_and(|part| part.arguments.is_none());
+        let written = match outer {
+            // outer.new Inner() names a member class of the type of outer.
+            Some(outer) => {
+                let outer_type = self.value(outer, None);
+                let part = &class_type.parts[0];
+                match outer_type.class_name().and_then(|class| self.symbols().find_member_class(class, &part.name.name)) {
+                    Some(member) => {
+                        let arguments: Vec<Type> = part.arguments.iter().flatten().map(|argument| self.resolve_type_argument(argument)).collect();
+                        Type::generic(&member, arguments)
+                    },
+                    None => {
+                        if !outer_type.is_error() {
+                            let message = format!("cannot find symbol\n  symbol:   class {}\n  location: {}", part.name.name, self.describe_type(&outer_type));
+                            self.error(part.name.position, &message);
+                        }
+                        Type::Error
+                    }
+                }
+            },
+            None => self.resolve_class_type(class_type)
+        };
+        let class_name = match written {
+            Type::Class(ref written) => written.name.clone(),
+            Type::Variable(ref variable) => {
+                let message = format!("unexpected type\n  required: class\n  found:    type parameter {}", variable.name);
+                self.error(class_type.position(), &message);
+                self.attribute_arguments(arguments);
+                return Type::Error;
+            },
+            _ => {
+                self.attribute_arguments(arguments);
+                return Type::Error;
+            }
+        };
+
+        let symbol = self.class(&class_name);
+        let problem = if symbol.is_enum() {
+            Some(String::from("enum classes may not be instantiated"))
+        } else if body.is_none() && symbol.is_abstract() {
+            Some(format!("{} is abstract; cannot be instantiated", types::simple_name(&class_name)))
+        } else if body.is_some() && symbol.is_final() {
+            Some(format!("cannot inherit from final {}", types::simple_name(&class_name)))
+        } else {
+            None
+        };
+        if let Some(problem) = problem {
+            self.error(expression.position, &problem);
+            self.attribute_arguments(arguments);
+            return Type::Error;
+        }
+
+        // A new instance of an inner class gets an enclosing instance from the code it is created in.
+        let mut implicit_this = None;
+        if outer.is_none() && symbol.is_inner() {
+            let enclosing = symbol.outer_class.clone().unwrap_or_default();
+            match self.enclosing_instance(&enclosing) {
+                Some(class) => implicit_this = Some(class),
+                None => self.error(expression.position, "non-static variable this cannot be referenced from a static context")
+            }
+        }
+
+        let inferred = diamond && !symbol.is_interface() && !symbol.type_parameters.is_empty();
+        let site = if symbol.is_interface() { Type::object() } else { written.clone() };
+        let candidates = self.constructors(&site, inferred);
+        let mut result = written;
+        if let Some(mut invocation) = self.resolve(candidates, arguments, &explicit, expected, expression.position, &MethodName::Constructor(&site)) {
+            if inferred {
+                result = invocation.result.clone();
+            }
+            invocation.result = result.clone();
+            invocation.implicit_this = implicit_this;
+            self.result.invocations.insert(address(expression), invocation);
+        }
+        if diamond && symbol.is_interface() {
+            if let Some(expected) = expected.filter(|expected| expected.class_name() == Some(class_name.as_str())) {
+                result = expected.clone();
+            }
+        }
+
+        match body {
+            Some(members) => {
+                let anonymous = self.anonymous_class(&result, members, address(expression));
+                self.attribute_class(&anonymous, Body::anonymous(members));
+                self.check_abstract_methods(&anonymous, expression.position);
+                Type::class(&anonymous)
+            },
+            None => result
+        }
+    }
+
+    // Attributes arguments whose method could not be found.
+    fn attribute_arguments(&mut self, arguments: &'a [Expression]) {
+        for argument in arguments.iter() {
+            if is_functional_expression(argument) {
+                self.silent += 1;
+                self.expression(argument, None);
+                self.silent -= 1;
+            } else {
+                self.value(argument, None);
+            }
+        }
+    }
+
+    // The function type of a functional interface (JLS §9.9): its single abstract method as a
+    // member of the non-wildcard parameterization of the interface, which is returned too, with
+    // the method as declared. The error is why the type is not a functional interface.
+    fn function_type(&self, target: &Type) -> Result<(Type, MethodSymbol, MethodSymbol), String> {
+        let class_type = match *target {
+            Type::Class(ref class_type) => class_type,
+            _ => return Err(String::new())
+        };
+        let symbol = self.class(&class_type.name);
+        if !symbol.is_interface() {
+            return Err(String::new());
+        }
+
+        let parameters = &symbol.type_parameters;
+        let arguments = class_type.arguments.iter().zip(parameters.iter()).map(|(argument, parameter)| match *argument {
+            Type::Wildcard(Wildcard::Extends(ref bound)) | Type::Wildcard(Wildcard::Super(ref bound)) => (**bound).clone(),
+            Type::Wildcard(Wildcard::Unbounded) => {
+                let bound = parameter.upper_bounds()[0].clone();
+                if bound.mentions(|variable| parameters.contains(variable)) { bound.erasure() } else { bound }
+            },
+            _ => argument.clone()
+        }).collect();
+        let interface = Type::generic(&class_type.name, arguments);
+
+        let mut names = Vec::new();
+        self.abstract_method_names(&interface, &mut names, &mut Vec::new());
+        let mut abstract_methods: Vec<MethodMember> = Vec::new();
+        for name in names.iter() {
+            for method in self.symbols().find_methods(&interface, name) {
+                let known = abstract_methods.iter().any(|other| other.member.name == method.member.name && other.member.same_erased_parameters(&method.member));
+                if method.member.is_abstract() && !is_object_method(&method.member) && !known {
+                    abstract_methods.push(method);
+                }
+            }
+        }
+
+        let simple_name = types::simple_name(&class_type.name);
+        match abstract_methods.len() {
+            1 => {
+                let method = abstract_methods.remove(0);
+                Ok((interface, method.declared, method.member))
+            },
+            0 => Err(format!("\n    no abstract method found in interface {}", simple_name)),
+            _ => Err(format!("\n    multiple non-overriding abstract methods found in interface {}", simple_name))
+        }
+    }
+
+    // The functional interface a lambda expression or method reference is expected to
+    // implement, or None after reporting why there is none.
+    fn expected_function(&mut self, expression: &Expression, expected: Option<&Type>, what: &str) -> Option<(Type, MethodSymbol, MethodSymbol)> {
+        match expected {
+            None => {
+                self.error(expression.position, &format!("{} not expected here", what));
+                None
+            },
+            Some(target) if target.is_error() => None,
+            Some(target) => match self.function_type(target) {
+                Ok(function) => Some(function),
+                Err(reason) => {
+                    self.error(expression.position, &format!("incompatible types: {} is not a functional interface{}", target, reason));
+                    None
+                }
+            }
+        }
+    }
+
+    // Attributes a lambda expression against its target type. While the type arguments of a
+    // generic method are inferred, the target may mention the open inference variables. The
+    // lambda then gives bounds for them: the types of its results must be compatible with the
+    // return type of its function type.
+    fn lambda(&mut self, expression: &'a Expression, parameters: &'a [ast::LambdaParameter], body: &'a LambdaBody,
+              expected: Option<&Type>, open: &[TypeVariable]) -> (Type, Vec<(Type, Type)>) {
+        let mut function = self.expected_function(expression, expected, "lambda expression");
+        if function.as_ref().is_some_and(|(_, _, function)| function.parameters.len() != parameters.len()) {
+            self.error(expression.position, "incompatible types: incompatible parameter types in lambda expression");
+            function = None;
+        }
+        let is_open = |found: &Type| found.mentions(|variable| open.contains(variable));
+        let (interface, method, function) = match function {
+            Some(ref found) if !found.2.parameters.iter().any(is_open) => found.clone(),
+            _ => {
+                // Without parameter types, the body is attributed only to find the lambdas and
+                // classes in it.
+                self.silent += 1;
+                self.lambda_body(parameters, body, &vec![Type::Error; parameters.len()], Some(Type::Error));
+                self.silent -= 1;
+                return (function.map_or(Type::Error, |found| found.0), Vec::new());
+            }
+        };
+
+        let constraints = if is_open(&function.return_type) {
+            let results = self.lambda_body(parameters, body, &function.parameters, None);
+            results.into_iter().filter(|found| !found.is_void()).map(|found| (found, function.return_type.clone())).collect()
+        } else {
+            self.lambda_body(parameters, body, &function.parameters, Some(function.return_type.clone()));
+            // A block body that returns values must not complete normally (JLS §15.27.3).
+            if let LambdaBody::Block(ref block) = *body {
+                if !function.return_type.is_void() && !function.return_type.is_error() && flow::can_complete_normally(block, &self.result) {
+                    self.error(expression.position, "incompatible types: bad return type in lambda expression\n    missing return value");
+                }
+            }
+            Vec::new()
+        };
+        self.result.functions.insert(address(expression), Function { interface: interface.clone(), method, function, target: None });
+        (interface, constraints)
+    }
+
+    // Attributes the body of a lambda expression with the parameter types and return type of
+    // its function type. Without a return type, the types of the results are returned.
+    fn lambda_body(&mut self, parameters: &'a [ast::LambdaParameter], body: &'a LambdaBody, parameter_types: &[Type], return_type: Option<Type>) -> Vec<Type> {
+        let in_argument = std::mem::replace(&mut self.in_argument, 0);
+        self.push_scope();
+        for (parameter, inferred) in parameters.iter().zip(parameter_types.iter()) {
+            let parameter_type = match parameter.parameter_type {
+                Some(ref written) => {
+                    let declared = self.resolve_type(written);
+                    if parameter.variable_arity { Type::array_of(declared) } else { declared }
+                },
+                None => inferred.clone()
+            };
+            let is_final = parameter.modifiers.contains(Modifier::Final);
+            let local = self.declare_local(&parameter.name, parameter_type, is_final, None);
+            if is_final {
+                self.final_parameters.insert(local);
+            }
+        }
+        self.frame().jumps.push(Jump::Lambda);
+
+        let mut results = Vec::new();
+        match *body {
+            LambdaBody::Expression(ref value) => match return_type {
+                Some(Type::Void) => {
+                    self.expression(value, None);
+                    if !is_statement_expression(value) {
+                        self.error(value.position, "incompatible types: lambda body is not compatible with a void functional interface\n    (consider using a block lambda body, or use a statement expression instead)");
+                    }
+                },
+                Some(return_type) => {
+                    let found = self.expression(value, Some(&return_type));
+                    self.check_lambda_result(value, &found, &return_type);
+                },
+                None => results.push(self.expression(value, None))
+            },
+            LambdaBody::Block(ref block) => {
+                self.frame().returns.push(Returns::Lambda(return_type, Vec::new()));
+                self.block_statements(&block.statements);
+                if let Some(Returns::Lambda(_, found)) = self.frame().returns.pop() {
+                    results = found;
+                }
+            }
+        }
+
+        self.frame().jumps.pop();
+        self.pop_scope();
+        self.in_argument = in_argument;
+        results
+    }
+
+    fn check_lambda_result(&mut self, value: &'a Expression, found: &Type, return_type: &Type) {
+        let constant = self.constant_of(value);
+        if found.is_error() || return_type.is_error() || self.is_assignable(found, return_type, constant.as_ref()) {
+            return;
+        }
+        let message = format!("incompatible types: bad return type in lambda expression\n    {}", self.conversion_problem(found, return_type));
+        self.error(value.position, &message);
+    }
+
+    // Attributes a method reference against its target type (JLS §15.13). Like a lambda
+    // expression, it gives bounds for the open inference variables of its target.
+    #[allow(clippy::too_many_arguments)]
+    fn method_reference(&mut self, expression: &'a Expression, target: &'a MethodReferenceTarget, type_arguments: &'a [TypeArgument],
+                        name: &'a Identifier, expected: Option<&Type>, open: &[TypeVariable]) -> (Type, Vec<(Type, Type)>) {
+        let explicit = self.type_arguments(type_arguments);
+        let is_open = |found: &Type| found.mentions(|variable| open.contains(variable));
+        let (interface, method, function) = match self.expected_function(expression, expected, "method reference") {
+            Some(function) => function,
+            None => {
+                if let MethodReferenceTarget::Expression(ref qualifier) = *target {
+                    self.silent += 1;
+                    self.qualifier(qualifier);
+                    self.silent -= 1;
+                }
+                return (Type::Error, Vec::new());
+            }
+        };
+        if function.parameters.iter().any(is_open) {
+            return (interface, self.exact_reference_bounds(target, name, &function));
+        }
+
+        let parameters = function.parameters.clone();
+        let return_type = if function.return_type.is_void() || is_open(&function.return_type) { None } else { Some(function.return_type.clone()) };
+        let invocation = match *target {
+            MethodReferenceTarget::Expression(ref qualifier) => match self.qualifier(qualifier) {
+                Qualifier::Class(class) => self.unbound_reference(expression, &class, name, &parameters, &explicit, return_type.as_ref()),
+                Qualifier::Value(Type::Error) => None,
+                Qualifier::Value(site) => {
+                    if site.is_primitive() || site == Type::Null {
+                        self.error(expression.position, &format!("{} cannot be dereferenced", site));
+                        None
+                    } else {
+                        let candidates = self.symbols().find_methods(&site, &name.name);
+                        let method_name = MethodName::Method(&name.name, self.site_location(qualifier, &site));
+                        self.invoke_types(candidates, &parameters, &explicit, return_type.as_ref(), expression.position, &method_name)
+                    }
+                },
+                Qualifier::Package(package) => {
+                    self.missing_package_member(expression, qualifier, &package, &name.name);
+                    None
+                }
+            },
+            MethodReferenceTarget::Type(ref written) => match self.resolve_type(written) {
+                Type::Error => None,
+                class => self.unbound_reference(expression, &class, name, &parameters, &explicit, return_type.as_ref())
+            },
+            MethodReferenceTarget::Super(ref qualifier) => {
+                match self.super_class_of(expression.position, qualifier.as_ref()) {
+                    Some(class) => {
+                        let site = self.class(&class).super_class.clone().unwrap_or(Type::Error);
+                        let candidates = self.symbols().find_methods(&site, &name.name);
+                        let method_name = MethodName::Method(&name.name, self.describe_type(&site));
+                        let invocation = self.invoke_types(candidates, &parameters, &explicit, return_type.as_ref(), expression.position, &method_name);
+                        invocation.map(|invocation| Invocation { implicit_this: Some(class), ..invocation })
+                    },
+                    None => None
+                }
+            }
+        };
+
+        let mut constraints = Vec::new();
+        if let Some(ref invocation) = invocation {
+            let found = &invocation.result;
+            if is_open(&function.return_type) {
+                if !found.is_void() {
+                    constraints.push((found.clone(), function.return_type.clone()));
+                }
+            } else if !function.return_type.is_void() && !self.is_assignable(found, &function.return_type, None) {
+                let message = format!("incompatible types: bad return type in method reference\n    {}", self.conversion_problem(found, &function.return_type));
+                self.error(expression.position, &message);
+            }
+        }
+        self.result.functions.insert(address(expression), Function { interface: interface.clone(), method, function, target: invocation });
+        (interface, constraints)
+    }
+
+    // An exact method reference, Type::name naming a single method that is not generic, gives
+    // bounds for open parameter types of the function type (JLS §18.2.1): Person::getName as a
+    // Function<T, U> makes T a subtype of Person.
+    fn exact_reference_bounds(&mut self, target: &'a MethodReferenceTarget, name: &Identifier, function: &MethodSymbol) -> Vec<(Type, Type)> {
+        let class = match *target {
+            MethodReferenceTarget::Expression(ref qualifier) => {
+                self.silent += 1;
+                let qualifier = self.qualifier(qualifier);
+                self.silent -= 1;
+                match qualifier {
+                    Qualifier::Class(class) => class,
+                    _ => return Vec::new()
+                }
+            },
+            _ => return Vec::new()
+        };
+        let methods = self.symbols().find_methods(&class, &name.name);
+        if methods.len() != 1 || !methods[0].member.type_parameters.is_empty() || methods[0].member.is_varargs() {
+            return Vec::new();
+        }
+        let method = &methods[0].member;
+        let mut constraints = Vec::new();
+        let receiver = usize::from(!method.is_static());
+        if method.parameters.len() + receiver != function.parameters.len() {
+            return Vec::new();
+        }
+        if receiver == 1 {
+            constraints.push((function.parameters[0].clone(), class.clone()));
+        }
+        for (parameter, formal) in method.parameters.iter().zip(function.parameters[receiver..].iter()) {
+            constraints.push((formal.clone(), parameter.clone()));
+        }
+        if !function.return_type.is_void() && !method.return_type.is_void() {
+            constraints.push((method.return_type.clone(), function.return_type.clone()));
+        }
+        constraints
+    }
+
+    // Type::name, which names a static method that takes all the parameters of the function
+    // type, or an instance method of the first parameter that takes the others (JLS §15.13.1).
+    // Type::new names a constructor.
+    fn unbound_reference(&mut self, expression: &'a Expression, class: &Type, name: &Identifier, parameters: &[Type],
+                         explicit: &[Type], return_type: Option<&Type>) -> Option<Invocation> {
+        if name.name == "new" {
+            if let Type::Array(_) = *class {
+                // int[]::new takes the length of the array.
+                let method = MethodSymbol {
+                    owner: class.descriptor(),
+                    name: String::from("<init>"),
+                    type_parameters: Vec::new(),
+                    parameters: vec![Type::int()],
+                    return_type: class.clone(),
+                    throws: Vec::new(),
+                    access_flags: method::ACC_PUBLIC
+                };
+                return Some(Invocation { method, parameters: vec![Type::int()], result: class.clone(), throws: Vec::new(), varargs: false, implicit_this: None });
+            }
+            let symbol = self.class(class.class_name()?);
+            if symbol.is_abstract() {
+                self.error(expression.position, &format!("{} is abstract; cannot be instantiated", types::simple_name(&symbol.name)));
+                return None;
+            }
+            let raw = matches!(*class, Type::Class(ref class_type) if class_type.arguments.is_empty());
+            let candidates = self.constructors(class, raw);
+            let mut invocation = self.invoke_types(candidates, parameters, explicit, return_type, expression.position, &MethodName::Constructor(class))?;
+            if symbol.is_inner() {
+                invocation.implicit_this = symbol.outer_class.as_ref().and_then(|outer| self.enclosing_instance(outer));
+            }
+            return Some(invocation);
+        }
+
+        let candidates = self.symbols().find_methods(class, &name.name);
+        self.silent += 1;
+        let as_static = self.invoke_types(candidates, parameters, explicit, return_type, expression.position, &MethodName::Method(&name.name, String::new()))
+            .filter(|invocation| invocation.method.is_static());
+        let receiver = parameters.first().filter(|first| first.is_reference() && self.symbols().is_subtype(&first.erasure(), &class.erasure())).cloned();
+        let as_instance = receiver.and_then(|first| {
+            // The receiver gives the type arguments of a raw class.
+            let site = match *class {
+                Type::Class(ref class_type) if class_type.arguments.is_empty() => self.symbols().as_super(&first, &class_type.name).unwrap_or_else(|| class.clone()),
+                _ => class.clone()
+            };
+            let candidates = self.symbols().find_methods(&site, &name.name);
+            self.invoke_types(candidates, &parameters[1..], explicit, return_type, expression.position, &MethodName::Method(&name.name, String::new()))
+        }).filter(|invocation| !invocation.method.is_static());
+        self.silent -= 1;
+
+        match (as_static, as_instance) {
+            (Some(invocation), None) | (None, Some(invocation)) => Some(invocation),
+            (Some(invocation), Some(_)) => {
+                let message = format!("incompatible types: invalid method reference\n    reference to {} is ambiguous", name.name);
+                self.error(expression.position, &message);
+                Some(invocation)
+            },
+            (None, None) => {
+                let arguments: Vec<String> = parameters.iter().map(Type::to_string).collect();
+                let message = format!("invalid method reference\n  cannot find symbol\n    symbol:   method {}({})\n    location: {}",
+                    name.name, arguments.join(","), self.describe_type(class));
+                self.error(expression.position, &message);
+                None
+            }
+        }
+    }
+
+    // Attributes a lambda expression or method reference argument against the type of its
+    // parameter, and returns the bounds it gives for the open inference variables.
+    fn deferred(&mut self, expression: &'a Expression, target: &Type, open: &[TypeVariable]) -> Vec<(Type, Type)> {
+        let inner = strip_parentheses(expression);
+        let (found, constraints) = match inner.kind {
+            ExpressionKind::Lambda { ref parameters, ref body } => self.lambda(inner, parameters, body, Some(target), open),
+            ExpressionKind::MethodReference { target: ref reference, ref type_arguments, ref name } => {
+                self.method_reference(inner, reference, type_arguments, name, Some(target), open)
+            },
+            _ => (self.expression(inner, Some(target)), Vec::new())
+        };
+        self.result.types.insert(address(inner), found.clone());
+        self.result.types.insert(address(expression), found);
+        constraints
+    }
+
+    // Once the type arguments of a generic method are inferred, they are substituted into the
+    // types recorded for its lambda and method reference arguments.
+    fn complete_function(&mut self, expression: &Expression, solution: &[(TypeVariable, Type)]) {
+        let inner = strip_parentheses(expression);
+        if let Some(function) = self.result.functions.get_mut(&address(inner)) {
+            function.interface = function.interface.substitute(solution);
+            function.function = function.function.substitute(solution);
+        }
+        for key in [address(inner), address(expression)].iter() {
+            if let Some(found) = self.result.types.get_mut(key) {
+                *found = found.substitute(solution);
+            }
+        }
+    }
+
+}
+
+// Method resolution (JLS §15.12.2) and type inference.
+impl<'a> Attr<'a> {
+
+    // Chooses the method or constructor an invocation with the given arguments calls, and
+    // infers its type arguments. Errors are reported, and give None.
+    fn resolve(&mut self, candidates: Vec<MethodMember>, arguments: &'a [Expression], explicit: &[Type], expected: Option<&Type>,
+               position: Position, name: &MethodName) -> Option<Invocation> {
+        let prepared = arguments.iter().map(|argument| {
+            if is_functional_expression(argument) {
+                Argument::Deferred(argument)
+            } else {
+                self.in_argument += 1;
+                let found = self.value(argument, None);
+                self.in_argument -= 1;
+                Argument::Typed(found, Some(argument))
+            }
+        }).collect();
+        self.invoke(candidates, prepared, explicit, expected, position, name)
+    }
+
+    // Resolution with the parameter types of a function type as arguments, for method
+    // references.
+    fn invoke_types(&mut self, candidates: Vec<MethodMember>, types: &[Type], explicit: &[Type], expected: Option<&Type>,
+                    position: Position, name: &MethodName) -> Option<Invocation> {
+        let arguments = types.iter().map(|found| Argument::Typed(found.clone(), None)).collect();
+        self.invoke(candidates, arguments, explicit, expected, position, name)
+    }
+
+    fn invoke(&mut self, candidates: Vec<MethodMember>, arguments: Vec<Argument<'a>>, explicit: &[Type], expected: Option<&Type>,
+              position: Position, name: &MethodName) -> Option<Invocation> {
+        if candidates.is_empty() {
+            if let MethodName::Method(method, ref location) = *name {
+                let message = format!("cannot find symbol\n  symbol:   method {}({})\n  location: {}", method, self.argument_list(&arguments), location);
+                self.error(position, &message);
+            }
+            self.attribute_unused(&arguments);
+            return None;
+        }
+
+        let accessible: Vec<MethodMember> = candidates.iter()
+            .filter(|candidate| self.is_accessible(&candidate.declared.owner, candidate.declared.access_flags))
+            .cloned()
+            .collect();
+        for &phase in [Phase::Strict, Phase::Loose, Phase::Varargs].iter() {
+            let mut applicable = Vec::new();
+            for candidate in accessible.iter() {
+                if self.applicable(candidate, &arguments, explicit, phase).is_some() {
+                    applicable.push(candidate.clone());
+                }
+            }
+            if !applicable.is_empty() {
+                return match self.most_specific(applicable, &arguments, phase, position, name) {
+                    Some(selected) => Some(self.instantiate(selected, &arguments, explicit, expected, phase, name)),
+                    None => {
+                        self.attribute_unused(&arguments);
+                        None
+                    }
+                };
+            }
+        }
+
+        // A method that would apply but cannot be accessed is reported as such.
+        let mut hidden = None;
+        let inaccessible: Vec<&MethodMember> = candidates.iter()
+            .filter(|candidate| !self.is_accessible(&candidate.declared.owner, candidate.declared.access_flags))
+            .collect();
+        for candidate in inaccessible {
+            let phases = [Phase::Strict, Phase::Loose, Phase::Varargs];
+            if phases.iter().any(|&phase| self.applicable(candidate, &arguments, explicit, phase).is_some()) {
+                hidden = Some(candidate.declared.clone());
+                break;
+            }
+        }
+        match hidden {
+            Some(hidden) => self.access_error(position, &hidden.signature(), &hidden.owner, hidden.access_flags),
+            None => {
+                let reported = if accessible.is_empty() { &candidates } else { &accessible };
+                self.report_inapplicable(reported, &arguments, explicit, position, name);
+            }
+        }
+        self.attribute_unused(&arguments);
+        None
+    }
+
+    // Lambda expressions and method references of an invocation that failed are attributed
+    // without a target, to find the classes declared in them.
+    fn attribute_unused(&mut self, arguments: &[Argument<'a>]) {
+        for argument in arguments.iter() {
+            if let Argument::Deferred(expression) = *argument {
+                self.silent += 1;
+                self.expression(expression, None);
+                self.silent -= 1;
+            }
+        }
+    }
+
+    // The types of the parameters that arguments are passed to in a phase: in the variable
+    // arity phase, the trailing arguments are passed to the component type of the last one.
+    fn formal_parameters(method: &MethodSymbol, count: usize, phase: Phase) -> Option<Vec<Type>> {
+        let parameters = &method.parameters;
+        if phase == Phase::Varargs {
+            if !method.is_varargs() || count + 1 < parameters.len() {
+                return None;
+            }
+            let fixed = parameters.len() - 1;
+            let component = parameters[fixed].component().cloned().unwrap_or(Type::Error);
+            let mut formals = parameters[..fixed].to_vec();
+            formals.extend(std::iter::repeat_n(component, count - fixed));
+            Some(formals)
+        } else if count == parameters.len() {
+            Some(parameters.clone())
+        } else {
+            None
+        }
+    }
+
+    // The formal parameters of a candidate with explicit type arguments substituted, and the
+    // inference variables left to infer.
+    fn candidate_formals(candidate: &MethodMember, count: usize, explicit: &[Type], phase: Phase) -> Option<(Vec<Type>, Vec<TypeVariable>)> {
+        let method = &candidate.member;
+        let formals = Attr::formal_parameters(method, count, phase)?;
+        if explicit.is_empty() {
+            return Some((formals, method.type_parameters.clone()));
+        }
+        if explicit.len() != method.type_parameters.len() {
+            return Some((formals, Vec::new()));
+        }
+        let substitution: Vec<(TypeVariable, Type)> = method.type_parameters.iter().cloned().zip(explicit.iter().cloned()).collect();
+        Some((formals.iter().map(|formal| formal.substitute(&substitution)).collect(), Vec::new()))
+    }
+
+    // Whether a method is applicable to arguments in a phase (JLS §15.12.2.2-4), with the bounds
+    // found for its type parameters.
+    fn applicable(&mut self, candidate: &MethodMember, arguments: &[Argument<'a>], explicit: &[Type], phase: Phase) -> Option<Inference> {
+        let (formals, variables) = Attr::candidate_formals(candidate, arguments.len(), explicit, phase)?;
+        let mut inference = Inference::new(variables);
+        for (argument, formal) in arguments.iter().zip(formals.iter()) {
+            let compatible = match *argument {
+                Argument::Typed(ref found, _) => self.reduce_compatible(&mut inference, found, formal, phase),
+                Argument::Deferred(expression) => self.potentially_compatible(expression, formal, &inference)
+            };
+            if !compatible {
+                return None;
+            }
+        }
+        if self.consistent(&inference) { Some(inference) } else { None }
+    }
+
+    // Whether a lambda expression or method reference could be compatible with a parameter type
+    // (JLS §15.12.2.1).
+    fn potentially_compatible(&mut self, expression: &'a Expression, formal: &Type, inference: &Inference) -> bool {
+        if inference.index(formal).is_some() {
+            return true;
+        }
+        let function = match self.function_type(formal) {
+            Ok((_, _, function)) => function,
+            Err(_) => return false
+        };
+        match strip_parentheses(expression).kind {
+            ExpressionKind::Lambda { ref parameters, ref body } => {
+                if parameters.len() != function.parameters.len() {
+                    return false;
+                }
+                match *body {
+                    LambdaBody::Expression(ref value) => {
+                        if function.return_type.is_void() {
+                            return is_statement_expression(value);
+                        }
+                        // An explicitly typed lambda is pertinent to applicability: one whose body
+                        // calls a void method does not return a value (JLS §15.12.2.2).
+                        let explicitly_typed = parameters.iter().all(|parameter| parameter.parameter_type.is_some());
+                        !(explicitly_typed && self.is_void_call(parameters, value))
+                    },
+                    LambdaBody::Block(ref block) => {
+                        if function.return_type.is_void() {
+                            !returns_value(&block.statements)
+                        } else {
+                            returns_value(&block.statements) || block.statements.last().is_some_and(|last| !can_complete_normally(last))
+                        }
+                    }
+                }
+            },
+            _ => true
+        }
+    }
+
+    // Whether the body of an explicitly typed lambda expression is a call of a void method. The
+    // call is attributed on trial, unless it declares classes, which may be attributed only once.
+    fn is_void_call(&mut self, parameters: &'a [ast::LambdaParameter], value: &'a Expression) -> bool {
+        let declares_class = |expression: &Expression| is_functional_expression(expression) || matches!(expression.kind, ExpressionKind::New { body: Some(_), .. });
+        let simple = match value.kind {
+            ExpressionKind::MethodCall { ref target, ref arguments, .. } => {
+                !target.as_ref().is_some_and(|target| declares_class(target)) && !arguments.iter().any(declares_class)
+            },
+            _ => false
+        };
+        if !simple {
+            return false;
+        }
+
+        self.silent += 1;
+        self.push_scope();
+        for parameter in parameters.iter() {
+            let parameter_type = parameter.parameter_type.as_ref().map_or(Type::Error, |written| self.resolve_type(written));
+            self.declare_local(&parameter.name, parameter_type, false, None);
+        }
+        let found = self.expression(value, None);
+        self.pop_scope();
+        self.silent -= 1;
+        found.is_void()
+    }
+
+    // Whether the bounds of the inference variables agree with each other and with the declared
+    // bounds of the type parameters.
+    fn consistent(&self, inference: &Inference) -> bool {
+        (0..inference.variables.len()).all(|index| self.bound_conflict(inference, index).is_none())
+    }
+
+    // How the bounds of an inference variable conflict, as javac describes it. Bounds that
+    // mention inference variables are not checked.
+    fn bound_conflict(&self, inference: &Inference, index: usize) -> Option<String> {
+        let proper = |found: &&Type| !inference.mentions(found);
+        let equal: Vec<&Type> = inference.equal[index].iter().filter(proper).collect();
+        let lower: Vec<&Type> = inference.lower[index].iter().filter(proper).collect();
+        let declared = inference.variables[index].upper_bounds();
+        let upper: Vec<&Type> = declared.iter().filter(|bound| !bound.is_class(OBJECT)).chain(inference.upper[index].iter()).filter(proper).collect();
+        let list = |bounds: &[&Type]| bounds.iter().map(|bound| bound.to_string()).collect::<Vec<String>>().join(",");
+
+        if equal.iter().any(|found| !symbols::same_type(found, equal[0])) {
+            let reversed: Vec<&Type> = equal.iter().rev().cloned().collect();
+            return Some(format!("has incompatible equality constraints {}", list(&reversed)));
+        }
+        // javac 17 labels upper bounds as lower bounds in these messages.
+        if let Some(&found) = equal.first() {
+            if upper.iter().any(|bound| !self.symbols().is_subtype(found, bound)) {
+                return Some(format!("has incompatible bounds\n    equality constraints: {}\n    lower bounds: {}", list(&equal), list(&upper)));
+            }
+            if lower.iter().any(|bound| !self.symbols().is_subtype(bound, found)) {
+                return Some(format!("has incompatible bounds\n    equality constraints: {}\n    lower bounds: {}", list(&equal), list(&lower)));
+            }
+        }
+        if lower.iter().any(|bound| upper.iter().any(|other| !self.symbols().is_subtype(bound, other))) {
+            return Some(format!("has incompatible bounds\n    lower bounds: {}\n    lower bounds: {}", list(&upper), list(&lower)));
+        }
+        None
+    }
+
+    // Chooses the most specific of the applicable methods (JLS §15.12.2.5).
+    fn most_specific(&mut self, applicable: Vec<MethodMember>, arguments: &[Argument<'a>], phase: Phase, position: Position, name: &MethodName) -> Option<MethodMember> {
+        if applicable.len() == 1 {
+            return applicable.into_iter().next();
+        }
+        let maximal: Vec<&MethodMember> = applicable.iter().enumerate().filter(|&(index, method)| {
+            applicable.iter().enumerate().all(|(other_index, other)| {
+                index == other_index || !self.more_specific(other, method, arguments, phase) || self.more_specific(method, other, arguments, phase)
+            })
+        }).map(|(_, method)| method).collect();
+        if maximal.is_empty() {
+            return applicable.into_iter().next();
+        }
+        if maximal.len() == 1 {
+            return Some(maximal[0].clone());
+        }
+
+        // Methods with the same signature inherited from several supertypes: a concrete one is
+        // preferred, and else one with the most specific return type.
+        if maximal.iter().all(|method| method.member.same_erased_parameters(&maximal[0].member)) {
+            let chosen = maximal.iter().find(|method| !method.member.is_abstract())
+                .or_else(|| maximal.iter().find(|method| maximal.iter().all(|other| self.symbols().is_subtype(&method.member.return_type, &other.member.return_type))))
+                .unwrap_or(&maximal[0]);
+            return Some((*chosen).clone());
+        }
+
+        // An erroneous argument is compatible with every parameter type, and has been reported.
+        if arguments.iter().any(|argument| matches!(*argument, Argument::Typed(Type::Error, _))) {
+            return Some(maximal[0].clone());
+        }
+
+        let (kind, method_name) = match *name {
+            MethodName::Method(method, _) => ("method", String::from(method)),
+            MethodName::Constructor(site) => ("constructor", String::from(types::simple_name(site.class_name().unwrap_or(OBJECT))))
+        };
+        let describe = |method: &MethodMember| format!("{} {} in {}", kind, method.declared.signature(), types::simple_name(&method.declared.owner));
+        let message = format!("reference to {} is ambiguous\n  both {} and {} match", method_name, describe(maximal[0]), describe(maximal[1]));
+        self.error(position, &message);
+        None
+    }
+
+    // Whether one method is more specific than another for the arguments: each of its parameter
+    // types is a subtype of the other's, or for a lambda expression or method reference, a
+    // functional interface whose return type is more specific.
+    fn more_specific(&self, first: &MethodMember, second: &MethodMember, arguments: &[Argument<'a>], phase: Phase) -> bool {
+        let count = arguments.len();
+        let (first_formals, second_formals) = match (Attr::formal_parameters(&first.member, count, phase), Attr::formal_parameters(&second.member, count, phase)) {
+            (Some(first_formals), Some(second_formals)) => (first_formals, second_formals),
+            _ => return false
+        };
+        let variables = &second.member.type_parameters;
+        arguments.iter().zip(first_formals.iter().zip(second_formals.iter())).all(|(argument, (s, t))| {
+            let t = if t.mentions(|variable| variables.contains(variable)) { t.erasure() } else { t.clone() };
+            match *argument {
+                Argument::Deferred(_) if !self.symbols().is_subtype(s, &t) => self.functional_more_specific(s, &t),
+                _ => self.is_convertible(s, &t, false)
+            }
+        })
+    }
+
+    fn functional_more_specific(&self, s: &Type, t: &Type) -> bool {
+        match (self.function_type(s), self.function_type(t)) {
+            (Ok((_, _, first)), Ok((_, _, second))) => {
+                let (first_result, second_result) = (&first.return_type, &second.return_type);
+                first.parameters.len() == second.parameters.len()
+                    && (second_result.is_void()
+                        || (!first_result.is_void() && (self.symbols().is_subtype(first_result, second_result) || (first_result.is_primitive() && !second_result.is_primitive()))))
+            },
+            _ => false
+        }
+    }
+
+    // Infers the type arguments of the chosen method: from the arguments, the expected type of
+    // the invocation, and then from its lambda expressions and method references in order.
+    fn instantiate(&mut self, candidate: MethodMember, arguments: &[Argument<'a>], explicit: &[Type], expected: Option<&Type>, phase: Phase, name: &MethodName) -> Invocation {
+        let (formals, variables) = Attr::candidate_formals(&candidate, arguments.len(), explicit, phase).unwrap_or_default();
+        let mut method = candidate.member.clone();
+        if variables.is_empty() && explicit.len() == method.type_parameters.len() && !explicit.is_empty() {
+            let substitution: Vec<(TypeVariable, Type)> = method.type_parameters.iter().cloned().zip(explicit.iter().cloned()).collect();
+            method = method.substitute(&substitution);
+        }
+
+        let mut inference = Inference::new(variables);
+        for (argument, formal) in arguments.iter().zip(formals.iter()) {
+            if let Argument::Typed(ref found, _) = *argument {
+                self.reduce_compatible(&mut inference, found, formal, Phase::Loose);
+            }
+        }
+        if let Some(expected) = expected {
+            if !expected.is_error() && !expected.is_void() && inference.mentions(&method.return_type) {
+                let mut trial = inference.clone();
+                if self.reduce_subtype(&mut trial, &method.return_type, &boxed(expected)) && self.consistent(&trial) {
+                    inference = trial;
+                }
+            }
+        }
+        for (argument, formal) in arguments.iter().zip(formals.iter()) {
+            if let Argument::Deferred(expression) = *argument {
+                let partial = self.solve(&inference, false);
+                let open: Vec<TypeVariable> = inference.variables.iter().filter(|variable| !partial.iter().any(|(solved, _)| solved == *variable)).cloned().collect();
+                let constraints = self.deferred(expression, &formal.substitute(&partial), &open);
+                for (found, bound) in constraints {
+                    self.reduce_compatible(&mut inference, &found, &bound, Phase::Loose);
+                }
+            }
+        }
+
+        // A type parameter that only appears in the throws clause is inferred as
+        // RuntimeException when nothing else constrains it (JLS §18.1.3).
+        for thrown in method.throws.iter() {
+            if let Some(index) = inference.index(thrown) {
+                if inference.equal[index].is_empty() && inference.lower[index].is_empty() {
+                    inference.equal[index].push(Type::class("java/lang/RuntimeException"));
+                }
+            }
+        }
+        let solution = self.solve(&inference, true);
+        for argument in arguments.iter() {
+            if let Argument::Deferred(expression) = *argument {
+                self.complete_function(expression, &solution);
+            }
+        }
+        let parameters = method.parameters.iter().map(|parameter| parameter.substitute(&solution)).collect();
+        let mut result = method.return_type.substitute(&solution);
+        if let MethodName::Constructor(site) = *name {
+            if result.is_void() {
+                result = site.clone();
+            }
+        }
+        let throws = method.throws.iter().map(|thrown| thrown.substitute(&solution)).collect();
+        Invocation { method: candidate.declared, parameters, result, throws, varargs: phase == Phase::Varargs, implicit_this: None }
+    }
+
+    // Resolves the inference variables that have bounds, or with complete, all of them: those
+    // without bounds become their declared bound, or are left unknown in an argument, where the
+    // invocation the argument is passed to could have constrained them.
+    fn solve(&self, inference: &Inference, complete: bool) -> Vec<(TypeVariable, Type)> {
+        let proper = |found: &&Type| !inference.mentions(found);
+        let mut solution = Vec::new();
+        for (index, variable) in inference.variables.iter().enumerate() {
+            let lower: Vec<Type> = inference.lower[index].iter().filter(proper).cloned().collect();
+            let upper: Vec<&Type> = inference.upper[index].iter().filter(proper).collect();
+            let found = if let Some(equal) = inference.equal[index].iter().find(proper) {
+                Some(equal.clone())
+            } else if !lower.is_empty() {
+                Some(match self.lub(&lower) {
+                    Type::Null => Type::object(),
+                    found => found
+                })
+            } else {
+                upper.iter().find(|bound| upper.iter().all(|other| self.symbols().is_subtype(bound, other))).map(|upper| (*upper).clone())
+            };
+            if let Some(found) = found {
+                solution.push((variable.clone(), found));
+            }
+        }
+
+        // Variables bound to types mentioning other variables, such as List<U>.
+        for (index, variable) in inference.variables.iter().enumerate() {
+            if solution.iter().any(|(solved, _)| solved == variable) {
+                continue;
+            }
+            let bounds = inference.equal[index].iter().chain(inference.lower[index].iter());
+            let found = bounds.map(|bound| bound.substitute(&solution)).find(|bound| !inference.mentions(bound));
+            if let Some(found) = found {
+                solution.push((variable.clone(), found));
+            }
+        }
+
+        if complete {
+            for variable in inference.variables.iter() {
+                if solution.iter().any(|(solved, _)| solved == variable) {
+                    continue;
+                }
+                let found = if self.in_argument > 0 {
+                    Type::Error
+                } else {
+                    let bound = variable.upper_bounds()[0].clone();
+                    if inference.mentions(&bound) { bound.erasure() } else { bound }
+                };
+                solution.push((variable.clone(), found));
+            }
+        }
+        solution
+    }
+
+    // Reduces the compatibility of an argument with a parameter type to bounds on inference
+    // variables (JLS §18.2.2).
+    fn reduce_compatible(&self, inference: &mut Inference, found: &Type, formal: &Type, phase: Phase) -> bool {
+        if found.is_error() || formal.is_error() {
+            return true;
+        }
+        if found.is_void() {
+            return false;
+        }
+        if !inference.mentions(formal) {
+            return self.is_convertible(found, formal, phase != Phase::Strict);
+        }
+        match found.primitive() {
+            Some(primitive) => phase != Phase::Strict && self.reduce_subtype(inference, &Type::class(primitive.boxed()), formal),
+            None => self.reduce_subtype(inference, found, formal)
+        }
+    }
+
+    // Reduces s <: t, where either may mention inference variables (JLS §18.2.3).
+    fn reduce_subtype(&self, inference: &mut Inference, s: &Type, t: &Type) -> bool {
+        if s.is_error() || t.is_error() {
+            return true;
+        }
+        if let Some(index) = inference.index(t) {
+            if *s != Type::Null {
+                inference.lower[index].push(s.clone());
+            }
+            return true;
+        }
+        if let Some(index) = inference.index(s) {
+            inference.upper[index].push(t.clone());
+            return true;
+        }
+        if !inference.mentions(s) && !inference.mentions(t) {
+            return self.symbols().is_subtype(s, t);
+        }
+
+        match *t {
+            Type::Class(ref target) => {
+                if *s == Type::Null {
+                    return true;
+                }
+                match self.symbols().as_super(s, &target.name) {
+                    Some(Type::Class(ref found)) => {
+                        if found.arguments.is_empty() || target.arguments.is_empty() {
+                            return true;
+                        }
+                        found.arguments.len() == target.arguments.len()
+                            && found.arguments.iter().zip(target.arguments.iter()).all(|(argument, formal)| self.reduce_contains(inference, argument, formal))
+                    },
+                    Some(_) => true,
+                    None => false
+                }
+            },
+            Type::Array(ref target) => match *s {
+                Type::Array(ref found) => {
+                    if found.is_primitive() || target.is_primitive() { found == target } else { self.reduce_subtype(inference, found, target) }
+                },
+                Type::Null => true,
+                _ => false
+            },
+            _ => true
+        }
+    }
+
+    // Reduces the containment of a type argument by another (JLS §18.2.3).
+    fn reduce_contains(&self, inference: &mut Inference, found: &Type, formal: &Type) -> bool {
+        match *formal {
+            Type::Wildcard(Wildcard::Unbounded) => true,
+            Type::Wildcard(Wildcard::Extends(ref bound)) => match *found {
+                Type::Wildcard(Wildcard::Extends(ref upper)) => self.reduce_subtype(inference, upper, bound),
+                Type::Wildcard(_) => self.reduce_subtype(inference, &Type::object(), bound),
+                _ => self.reduce_subtype(inference, found, bound)
+            },
+            Type::Wildcard(Wildcard::Super(ref bound)) => match *found {
+                Type::Wildcard(Wildcard::Super(ref lower)) => self.reduce_subtype(inference, bound, lower),
+                Type::Wildcard(_) => false,
+                _ => self.reduce_subtype(inference, bound, found)
+            },
+            _ => self.reduce_equal(inference, found, formal)
+        }
+    }
+
+    // Reduces type equality (JLS §18.2.4).
+    fn reduce_equal(&self, inference: &mut Inference, s: &Type, t: &Type) -> bool {
+        if s.is_error() || t.is_error() {
+            return true;
+        }
+        if let Some(index) = inference.index(t) {
+            inference.equal[index].push(s.clone());
+            return true;
+        }
+        if let Some(index) = inference.index(s) {
+            inference.equal[index].push(t.clone());
+            return true;
+        }
+        match (s, t) {
+            (Type::Class(a), Type::Class(b)) => {
+                a.name == b.name && (a.arguments.is_empty() || b.arguments.is_empty()
+                    || (a.arguments.len() == b.arguments.len()
+                        && a.arguments.iter().zip(b.arguments.iter()).all(|(a, b)| self.reduce_equal(inference, a, b))))
+            },
+            (Type::Array(a), Type::Array(b)) => self.reduce_equal(inference, a, b),
+            (&Type::Wildcard(Wildcard::Extends(ref a)), &Type::Wildcard(Wildcard::Extends(ref b)))
+                | (&Type::Wildcard(Wildcard::Super(ref a)), &Type::Wildcard(Wildcard::Super(ref b))) => self.reduce_equal(inference, a, b),
+            _ => symbols::same_type(s, t)
+        }
+    }
+
+    // Reports that no method applies to the arguments, as javac does: a single candidate with
+    // one mismatched argument as an incompatible type at the argument, and otherwise with the
+    // reason each candidate does not apply.
+    fn report_inapplicable(&mut self, candidates: &[MethodMember], arguments: &[Argument<'a>], explicit: &[Type], position: Position, name: &MethodName) {
+        if arguments.iter().any(|argument| matches!(*argument, Argument::Typed(ref found, _) if found.is_error())) {
+            return;
+        }
+        let (kind, method_name) = match *name {
+            MethodName::Method(method, _) => ("method", String::from(method)),
+            MethodName::Constructor(site) => ("constructor", String::from(types::simple_name(site.class_name().unwrap_or(OBJECT))))
+        };
+        let found = match self.argument_list(arguments) {
+            ref list if list.is_empty() => String::from("no arguments"),
+            list => list
+        };
+
+        // When only one candidate fails on a single argument rather than on the number of
+        // arguments, javac simplifies the error to an incompatible type at that argument.
+        let mut mismatched = Vec::new();
+        for candidate in candidates.iter() {
+            if let (Some(index), reason) = self.mismatch(candidate, arguments, explicit) {
+                mismatched.push((candidate, index, reason));
+            }
+        }
+        if let [(candidate, index, ref reason)] = mismatched[..] {
+            let generic = !candidate.member.type_parameters.is_empty() && explicit.is_empty();
+            if let (Argument::Typed(_, Some(argument)), false) = (&arguments[index], generic) {
+                let diagnostic = Diagnostic { simplified: true, ..Diagnostic::new(&self.units[self.unit].0, argument.position, &format!("incompatible types: {}", reason)) };
+                self.report(diagnostic);
+                return;
+            }
+        }
+
+        if candidates.len() == 1 {
+            let candidate = &candidates[0];
+            let (index, reason) = self.mismatch(candidate, arguments, explicit);
+            let generic = !candidate.member.type_parameters.is_empty() && explicit.is_empty();
+            let signature = candidate.member.signature();
+            let required = &signature[signature.find('(').unwrap() + 1..signature.len() - 1];
+            let required = if required.is_empty() { "no arguments" } else { required };
+            let reason = if index.is_some() { format!("argument mismatch; {}", reason) } else { reason };
+            let reason = if generic && index.is_some() {
+                let variables: Vec<&str> = candidate.member.type_parameters.iter().map(|variable| variable.name.as_str()).collect();
+                format!("cannot infer type-variable(s) {}\n    ({})", variables.join(","), reason)
+            } else {
+                reason
+            };
+            let message = format!("{} {} in {} cannot be applied to given types;\n  required: {}\n  found:    {}\n  reason: {}",
+                kind, method_name, self.describe_class(&candidate.declared.owner), required, found, reason);
+            self.error(position, &message);
+            return;
+        }
+
+        let mut message = format!("no suitable {} found for {}({})", kind, method_name, self.argument_list(arguments));
+        for candidate in candidates.iter() {
+            let (index, reason) = self.mismatch(candidate, arguments, explicit);
+            let reason = if index.is_some() { format!("argument mismatch; {}", reason) } else { reason };
+            let declared = &candidate.declared;
+            let type_parameters = if declared.type_parameters.is_empty() {
+                String::new()
+            } else {
+                let names: Vec<&str> = declared.type_parameters.iter().map(|variable| variable.name.as_str()).collect();
+                format!("<{}>", names.join(","))
+            };
+            message.push_str(&format!("\n    {} {}.{}{} is not applicable\n      ({})",
+                kind, types::simple_name(&declared.owner), type_parameters, declared.signature(), reason));
+        }
+        self.error(position, &message);
+    }
+
+    // Why a method does not apply to arguments, and the index of the argument that does not
+    // match, if it is one argument.
+    fn mismatch(&mut self, candidate: &MethodMember, arguments: &[Argument<'a>], explicit: &[Type]) -> (Option<usize>, String) {
+        let phase = if candidate.member.is_varargs() { Phase::Varargs } else { Phase::Loose };
+        let (formals, variables) = match Attr::candidate_formals(candidate, arguments.len(), explicit, phase) {
+            Some(found) => found,
+            None => return (None, String::from("actual and formal argument lists differ in length"))
+        };
+        let mut inference = Inference::new(variables);
+        for (index, (argument, formal)) in arguments.iter().zip(formals.iter()).enumerate() {
+            match *argument {
+                Argument::Typed(ref found, _) => {
+                    if !self.reduce_compatible(&mut inference, found, formal, Phase::Loose) {
+                        return (Some(index), self.conversion_problem(found, formal));
+                    }
+                },
+                Argument::Deferred(expression) => {
+                    if !self.potentially_compatible(expression, formal, &inference) {
+                        let problem = match self.function_type(formal) {
+                            Err(_) => format!("{} is not a functional interface", formal),
+                            Ok((_, _, ref function)) if matches!(strip_parentheses(expression).kind,
+                                ExpressionKind::Lambda { ref parameters, .. } if parameters.len() != function.parameters.len()) => {
+                                String::from("incompatible parameter types in lambda expression")
+                            },
+                            Ok(_) => String::from("bad return type in lambda expression")
+                        };
+                        return (Some(index), problem);
+                    }
+                }
+            }
+        }
+        let conflict = (0..inference.variables.len())
+            .find_map(|index| self.bound_conflict(&inference, index).map(|conflict| (index, conflict)));
+        match conflict {
+            Some((index, conflict)) => (None, format!("inference variable {} {}", inference.variables[index].name, conflict)),
+            None => (None, String::from("actual and formal argument lists differ in length"))
+        }
+    }
+
+    // The argument types of an invocation as javac lists them.
+    fn argument_list(&self, arguments: &[Argument<'a>]) -> String {
+        let types: Vec<String> = arguments.iter().map(|argument| match *argument {
+            Argument::Typed(ref found, _) => found.to_string(),
+            Argument::Deferred(expression) => match strip_parentheses(expression).kind {
+                ExpressionKind::Lambda { ref parameters, .. } => {
+                    let names: Vec<&str> = parameters.iter().map(|parameter| parameter.name.name.as_str()).collect();
+                    format!("({})->{{ }}", names.join(","))
+                },
+                _ => String::from("method reference")
+            }
+        }).collect();
+        types.join(",")
+    }
+
+}
+
+// equals, hashCode and toString, which an interface may redeclare without them counting as
+// its abstract methods (JLS §9.8).
+fn is_object_method(method: &MethodSymbol) -> bool {
+    match (method.name.as_str(), method.parameters.len()) {
+        ("equals", 1) => method.parameters[0].is_class(OBJECT),
+        ("hashCode", 0) | ("toString", 0) => true,
+        _ => false
+    }
+}
+
+// Whether a lambda body returns a value, not counting the bodies of lambda expressions and
+// classes declared in it.
+fn returns_value(statements: &[Statement]) -> bool {
+    statements.iter().any(|statement| match statement.kind {
+        StatementKind::Return(ref value) => value.is_some(),
+        StatementKind::Block(ref block) | StatementKind::Synchronized { body: ref block, .. } => returns_value(&block.statements),
+        StatementKind::If { ref then, ref otherwise, .. } => {
+            returns_value(std::slice::from_ref(&**then)) || otherwise.as_ref().is_some_and(|otherwise| returns_value(std::slice::from_ref(&**otherwise)))
+        },
+        StatementKind::While { ref body, .. } | StatementKind::DoWhile { ref body, .. } | StatementKind::For { ref body, .. }
+            | StatementKind::ForEach { ref body, .. } | StatementKind::Labeled { ref body, .. } => returns_value(std::slice::from_ref(&**body)),
+        StatementKind::Switch(ref switch) => switch.cases.iter().any(|case| match case.body {
+            SwitchBody::Statements(ref statements) => returns_value(statements),
+            SwitchBody::Statement(ref statement) => returns_value(std::slice::from_ref(&**statement)),
+            SwitchBody::Expression(_) => false
+        }),
+        StatementKind::Try { ref body, ref catches, ref finally, .. } => {
+            returns_value(&body.statements) || catches.iter().any(|catch| returns_value(&catch.body.statements))
+                || finally.as_ref().is_some_and(|finally| returns_value(&finally.statements))
+        },
+        _ => false
+    })
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::*;
+    use class::path::ClassPath;
+    use compiler::parser;
+    use std::env;
+
+    // Just enough of java.base for the tests, compiled along with the source under test so that
+    // they need no JDK.
+    pub const LIBRARY: &[(&str, &str)] = &[
+        ("java/lang/Object.java", "package java.lang;\npublic class Object {\n  public Object() {}\n  public boolean equals(Object o) { return this == o; }\n  public native int hashCode();\n  public native String toString();\n}"),
+        ("java/lang/CharSequence.java", "package java.lang;\npublic interface CharSequence {\n  int length();\n}"),
+        ("java/lang/String.java", "package java.lang;\npublic final class String implements CharSequence {\n  public native int length();\n}"),
+        ("java/lang/Number.java", "package java.lang;\npublic abstract class Number {\n  public abstract int intValue();\n}"),
+        ("java/lang/Integer.java", "package java.lang;\npublic final class Integer extends Number {\n  public native int intValue();\n  public static native Integer valueOf(int i);\n}"),
+        ("java/lang/Throwable.java", "package java.lang;\npublic class Throwable {\n  public Throwable() {}\n  public native String getMessage();\n}"),
+        ("java/lang/Exception.java", "package java.lang;\npublic class Exception extends Throwable {\n  public Exception() {}\n}"),
+        ("java/lang/RuntimeException.java", "package java.lang;\npublic class RuntimeException extends Exception {\n  public RuntimeException() {}\n}"),
+        ("java/lang/Error.java", "package java.lang;\npublic class Error extends Throwable {\n  public Error() {}\n}"),
+        ("java/lang/Runnable.java", "package java.lang;\npublic interface Runnable {\n  void run();\n}"),
+        ("java/lang/System.java", "package java.lang;\npublic final class System {\n  public static final java.io.PrintStream out = null;\n}"),
+        ("java/io/PrintStream.java", "package java.io;\npublic class PrintStream {\n  public native void println(int i);\n  public native void println(long l);\n  public native void println(Object o);\n  public native void println(String s);\n}"),
+        ("java/util/List.java", "package java.util;\npublic interface List<E> {\n  int size();\n  E get(int index);\n}")
+    ];
+
+    // The source file A.java followed by the LIBRARY.
+    pub fn with_library(source: &str) -> Vec<(String, CompilationUnit)> {
+        let mut units = vec![(String::from("A.java"), parser::parse("A.java", source).unwrap())];
+        for &(file, library_source) in LIBRARY.iter() {
+            units.push((String::from(file), parser::parse(file, library_source).unwrap()));
+        }
+        units
+    }
+
+    pub fn format_errors(diagnostics: &[Diagnostic]) -> Vec<String> {
+        diagnostics.iter().map(|diagnostic| format!("{}:{}: {}", diagnostic.position.line, diagnostic.position.column, diagnostic.message)).collect()
+    }
+
+    // Attributes a source file along with the LIBRARY and gives the errors as
+    // line:column: message.
+    pub fn errors(source: &str) -> Vec<String> {
+        match attribute(&with_library(source), Symbols::new(ClassPath::new())) {
+            Ok(_) => Vec::new(),
+            Err(diagnostics) => format_errors(&diagnostics)
+        }
+    }
+
+    // Like errors, but against java.base from the JDK in JAVA_HOME.
+    fn jdk_errors(source: &str) -> Vec<String> {
+        let java_home = env::var("JAVA_HOME").expect("JAVA_HOME must point to an installed JDK");
+        let mut class_path = ClassPath::new();
+        class_path.add_java_home(Path::new(&java_home)).unwrap();
+
+        let unit = parser::parse("A.java", source).unwrap();
+        match attribute(&[(String::from("A.java"), unit)], Symbols::new(class_path)) {
+            Ok(_) => Vec::new(),
+            Err(diagnostics) => format_errors(&diagnostics)
+        }
+    }
+
+    const NAMES_AND_TYPES: &str = "import java.util.*;\nclass A {\n  int f(List<String> xs) {\n    String z = 1;\n    return xs.size() + undefined;\n  }\n}";
+
+    #[test]
+    fn resolves_names_and_types() {
+        let errors = errors(NAMES_AND_TYPES);
+        assert_eq!(errors, vec![
+            "4:16: incompatible types: int cannot be converted to String",
+            "5:24: cannot find symbol\n  symbol:   variable undefined\n  location: class A"
+        ]);
+    }
+
+    // The same errors with the List of the JDK, which has many more supertypes and members.
+    #[test]
+    #[ignore = "needs JAVA_HOME"]
+    fn resolves_names_and_types_against_installed_jdk() {
+        assert_eq!(jdk_errors(NAMES_AND_TYPES), errors(NAMES_AND_TYPES));
+    }
+
+    #[test]
+    fn resolves_overloads() {
+        let source = "class A {\n  void m(int a) {}\n  void m(long a) {}\n  void n() { m(1); m(1L); m(\"x\"); m(undefined); }\n}";
+        let errors = errors(source);
+        // An erroneous argument doesn't make the call ambiguous.
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("4:27: no suitable method found for m(String)"));
+        assert!(errors[1].starts_with("4:37: cannot find symbol"));
+    }
+
+    #[test]
+    fn checks_casts_and_folds_constants() {
+        let source = "class A {\n  static final int X = (int) 3.9 << 2;\n  static final String S = \"a\" + X + 'c';\n  void m(Object o) { Integer i = (Integer) o; String s = (String) 1; }\n}";
+        let errors = errors(source);
+        assert_eq!(errors, vec!["4:67: incompatible types: int cannot be converted to String"]);
+
+        let source = "class A {\n  static final int X = (int) 3.9 << 2;\n  static final String S = \"a\" + X + 'c';\n}";
+        let attribution = attribute(&with_library(source), Symbols::new(ClassPath::new())).unwrap();
+        let a = attribution.symbols.class("A").unwrap();
+        let constant = |name: &str| a.fields.iter().find(|field| field.name == name).unwrap().constant.clone();
+        assert_eq!(constant("X"), Some(Constant::Int(12)));
+        assert_eq!(constant("S"), Some(Constant::String("a12c".encode_utf16().collect())));
+    }
+
+}
@@ -0,0 +1,519 @@
+// The code of a method as it is generated: its instructions, and the types of the local variables
+// and operand stack at each point, which give the StackMapTable (JVMS §4.10.1). Like javac, the
+// generator tells the buffer what each instruction pops and pushes, and the buffer tracks the
+// frame, the maximum stack depth and the frames at jump targets.
+
+use class::{Attribute, LineNumberTableEntry, StackMapFrame, VerificationTypeInfo};
+use class::pool::ConstantPoolBuilder;
+use code::assembler;
+use code::instruction::Instruction;
+
+// The verification type of a value. Classes are named in internal form and arrays by their
+// descriptors, as in Class constants.
+#[derive(Clone, Debug, PartialEq)]
+pub enum VerificationType {
+    Top,
+    Int,
+    Float,
+    Long,
+    Double,
+    Null,
+    UninitializedThis,
+    Object(String),
+    // The result of a new instruction at an offset, before its constructor is called.
+    Uninitialized(u16)
+}
+
+impl VerificationType {
+
+    // The number of local variable slots and operand stack entries a value takes.
+    pub fn width(&self) -> usize {
+        match *self {
+            VerificationType::Long | VerificationType::Double => 2,
+            _ => 1
+        }
+    }
+
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Label(usize);
+
+// The types of the local variables, by slot, and of the operand stack. The second slot of a long
+// or double local is Top.
+#[derive(Clone, Debug, PartialEq)]
+struct State {
+    locals: Vec<VerificationType>,
+    stack: Vec<VerificationType>
+}
+
+struct LabelInfo {
+    position: Option<u16>,
+    // The merged states of the jumps to the label that come before it is placed.
+    state: Option<State>,
+    is_target: bool
+}
+
+pub struct Code {
+    instructions: Vec<Instruction>,
+    pc: u16,
+    // None after an unconditional jump or return, when the next instruction is unreachable until
+    // a label is placed.
+    state: Option<State>,
+    initial: State,
+    max_stack: usize,
+    max_locals: usize,
+    labels: Vec<LabelInfo>,
+    // The instructions to patch once their targets are placed, with their offsets.
+    jumps: Vec<(usize, u16, Label)>,
+    frames: Vec<(u16, State)>,
+    lines: Vec<LineNumberTableEntry>
+}
+
+impl Code {
+
+    // The code of a method whose parameters, including this, have the given types.
+    pub fn new(parameters: Vec<VerificationType>) -> Code {
+        let mut locals = Vec::new();
+        for parameter in parameters.into_iter() {
+            let width = parameter.width();
+            locals.push(parameter);
+            if width == 2 {
+                locals.push(VerificationType::Top);
+            }
+        }
+        let initial = State { locals, stack: Vec::new() };
+        Code {
+            instructions: Vec::new(),
+            pc: 0,
+            max_locals: initial.locals.len(),
+            state: Some(initial.clone()),
+            initial,
+            max_stack: 0,
+            labels: Vec::new(),
+            jumps: Vec::new(),
+            frames: Vec::new(),
+            lines: Vec::new()
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.state.is_some()
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    // Appends an instruction that pops a number of values, counting longs and doubles once, and
+    // pushes at most one.
+    pub fn emit(&mut self, instruction: Instruction, pops: usize, push: Option<VerificationType>) {
+        self.append(instruction);
+        for _ in 0..pops {
+            self.pop();
+        }
+        if let Some(push) = push {
+            self.push(push);
+        }
+    }
+
+    // Appends an instruction after which the next one is unreachable, e.g. a return or athrow.
+    pub fn emit_exit(&mut self, instruction: Instruction) {
+        self.append(instruction);
+        self.state = None;
+    }
+
+    // Like javac, nothing is emitted while the code is unreachable.
+    fn append(&mut self, instruction: Instruction) {
+        if self.state.is_none() {
+            return;
+        }
+        // Every instruction the generator emits can be encoded.
+        let length = assembler::instruction_length(&instruction, self.pc as usize).unwrap_or(1);
+        self.instructions.push(instruction);
+        self.pc += length as u16;
+    }
+
+    pub fn pop(&mut self) -> VerificationType {
+        self.state.as_mut().and_then(|state| state.stack.pop()).unwrap_or(VerificationType::Top)
+    }
+
+    pub fn push(&mut self, value: VerificationType) {
+        if let Some(ref mut state) = self.state {
+            state.stack.push(value);
+            let depth = state.stack.iter().map(VerificationType::width).sum();
+            self.max_stack = self.max_stack.max(depth);
+        }
+    }
+
+    pub fn peek(&self) -> VerificationType {
+        self.state.as_ref().and_then(|state| state.stack.last().cloned()).unwrap_or(VerificationType::Top)
+    }
+
+    // Replaces the type of the value on top of the stack, e.g. with the type of a conditional
+    // expression once its branches meet.
+    pub fn set_top(&mut self, value: VerificationType) {
+        if let Some(ref mut state) = self.state {
+            if let Some(top) = state.stack.last_mut() {
+                *top = value;
+            }
+        }
+    }
+
+    // Duplicates the value on top of the stack, inserting the copy below the values that take
+    // up the given number of slots under it, with one of the dup instructions.
+    pub fn dup(&mut self, under: usize) {
+        let top = self.peek();
+        let instruction = match (top.width(), under) {
+            (1, 0) => Instruction::Dup,
+            (1, 1) => Instruction::DupX1,
+            (1, _) => Instruction::DupX2,
+            (_, 0) => Instruction::Dup2,
+            (_, 1) => Instruction::Dup2X1,
+            (_, _) => Instruction::Dup2X2
+        };
+        self.append(instruction);
+
+        if let Some(ref mut state) = self.state {
+            let mut slots = 0;
+            let mut index = state.stack.len().saturating_sub(1);
+            while slots < under && index > 0 {
+                index -= 1;
+                slots += state.stack[index].width();
+            }
+            state.stack.insert(index, top);
+            let depth = state.stack.iter().map(VerificationType::width).sum();
+            self.max_stack = self.max_stack.max(depth);
+        }
+    }
+
+    // Duplicates the two values on top of the stack, which take a slot each, e.g. an array and
+    // an index, with dup2.
+    pub fn dup_pair(&mut self) {
+        self.append(Instruction::Dup2);
+        if let Some(ref mut state) = self.state {
+            let length = state.stack.len();
+            if length >= 2 {
+                let pair = state.stack[length - 2..].to_vec();
+                state.stack.extend(pair);
+            }
+            let depth = state.stack.iter().map(VerificationType::width).sum();
+            self.max_stack = self.max_stack.max(depth);
+        }
+    }
+
+    // Swaps the two values on top of the stack, which take a slot each.
+    pub fn swap(&mut self) {
+        self.append(Instruction::Swap);
+        if let Some(ref mut state) = self.state {
+            let length = state.stack.len();
+            if length >= 2 {
+                state.stack.swap(length - 1, length - 2);
+            }
+        }
+    }
+
+    // Records that a local variable holds a value of a type from now on.
+    pub fn store(&mut self, slot: usize, value: VerificationType) {
+        let width = value.width();
+        self.max_locals = self.max_locals.max(slot + width);
+        if let Some(ref mut state) = self.state {
+            if state.locals.len() < slot + width {
+                state.locals.resize(slot + width, VerificationType::Top);
+            }
+            // A long or double that took up this slot as its second one is no longer valid.
+            if slot > 0 && state.locals[slot - 1].width() == 2 {
+                state.locals[slot - 1] = VerificationType::Top;
+            }
+            state.locals[slot] = value;
+            if width == 2 {
+                state.locals[slot + 1] = VerificationType::Top;
+            }
+        }
+    }
+
+    // Forgets the local variables from a slot on, which go out of scope.
+    pub fn end_scope(&mut self, slot: usize) {
+        if let Some(ref mut state) = self.state {
+            state.locals.truncate(slot);
+        }
+    }
+
+    // Replaces an uninitialized object by the initialized class once its constructor is called.
+    pub fn initialize(&mut self, uninitialized: &VerificationType, class: &str) {
+        if let Some(ref mut state) = self.state {
+            let initialized = VerificationType::Object(String::from(class));
+            for value in state.locals.iter_mut().chain(state.stack.iter_mut()) {
+                if value == uninitialized {
+                    *value = initialized.clone();
+                }
+            }
+        }
+    }
+
+    pub fn new_label(&mut self) -> Label {
+        self.labels.push(LabelInfo { position: None, state: None, is_target: false });
+        Label(self.labels.len() - 1)
+    }
+
+    // Appends a jump to a label, which pops the operands of the condition first.
+    pub fn jump(&mut self, instruction: Instruction, pops: usize, target: Label) {
+        for _ in 0..pops {
+            self.pop();
+        }
+        let state = match self.state {
+            Some(ref state) => state.clone(),
+            None => return
+        };
+
+        let info = &mut self.labels[target.0];
+        info.is_target = true;
+        if info.position.is_none() {
+            info.state = Some(match info.state.take() {
+                Some(merged) => merge(&merged, &state),
+                None => state
+            });
+        }
+
+        self.jumps.push((self.instructions.len(), self.pc, target));
+        let is_goto = matches!(instruction, Instruction::Goto { .. });
+        self.append(instruction);
+        if is_goto {
+            self.state = None;
+        }
+    }
+
+    // Places a label at the next instruction, which can be reached from the jumps to it and,
+    // unless the code before is unreachable, by falling through.
+    pub fn bind(&mut self, label: Label) {
+        let info = &mut self.labels[label.0];
+        info.position = Some(self.pc);
+        self.state = match (self.state.take(), info.state.take()) {
+            (Some(current), Some(jumped)) => Some(merge(&current, &jumped)),
+            (current, jumped) => current.or(jumped)
+        };
+
+        if let Some(ref state) = self.state {
+            // Labels placed at the same instruction share the frame.
+            if self.frames.last().is_some_and(|&(pc, _)| pc == self.pc) {
+                self.frames.pop();
+            }
+            self.frames.push((self.pc, state.clone()));
+        }
+    }
+
+    // Starts the code of a statement on a line of the source file.
+    pub fn line(&mut self, line: u32) {
+        if !self.is_alive() {
+            return;
+        }
+        if self.lines.last().is_some_and(|entry| entry.start_pc == self.pc) {
+            self.lines.pop();
+        }
+        if self.lines.last().is_none_or(|entry| u32::from(entry.line_number) != line) {
+            self.lines.push(LineNumberTableEntry { start_pc: self.pc, line_number: line as u16 });
+        }
+    }
+
+    // The Code attribute, with its LineNumberTable and StackMapTable. Fails if a jump is too
+    // far for its offset.
+    pub fn finish(mut self, pool: &mut ConstantPoolBuilder) -> Result<Attribute, String> {
+        for &(index, pc, label) in self.jumps.iter() {
+            let target = self.labels[label.0].position.ok_or_else(|| String::from("jump to a label that was never placed"))?;
+            let offset = i32::from(target) - i32::from(pc);
+            if offset < i32::from(i16::MIN) || offset > i32::from(i16::MAX) {
+                return Err(String::from("code too large"));
+            }
+            self.instructions[index] = with_offset(self.instructions[index].clone(), offset as i16);
+        }
+
+        let code = assembler::assemble_code(&self.instructions).map_err(|e| format!("{:?}", e))?;
+        if code.len() > 65535 {
+            return Err(String::from("code too large"));
+        }
+
+        // Only the frames at jump targets are needed.
+        let targets: Vec<u16> = self.labels.iter().filter(|info| info.is_target).filter_map(|info| info.position).collect();
+        let mut entries = Vec::new();
+        let mut previous = (self.initial.clone(), None);
+        for &(pc, ref state) in self.frames.iter().filter(|&&(pc, _)| targets.contains(&pc)) {
+            let offset_delta = match previous.1 {
+                Some(previous_pc) => pc - previous_pc - 1,
+                None => pc
+            };
+            entries.push(frame(offset_delta, &previous.0, state, pool));
+            previous = (state.clone(), Some(pc));
+        }
+
+        let mut attributes = Vec::new();
+        pool.utf8("LineNumberTable");
+        attributes.push(Attribute::LineNumberTable(self.lines));
+        if !entries.is_empty() {
+            pool.utf8("StackMapTable");
+            attributes.push(Attribute::StackMapTable { entries });
+        }
+
+        pool.utf8("Code");
+        Ok(Attribute::Code {
+            max_stack: self.max_stack as u16,
+            max_locals: self.max_locals as u16,
+            code,
+            exceptions: Vec::new(),
+            attributes
+        })
+    }
+
+}
+
+// The state at a point reached in two ways: the locals that differ are unusable, and the stacks,
+// which the generator keeps the same, are taken from the first.
+fn merge(a: &State, b: &State) -> State {
+    let locals = a.locals.iter().zip(b.locals.iter())
+        .map(|(a, b)| if a == b { a.clone() } else { VerificationType::Top })
+        .collect();
+    State { locals, stack: a.stack.clone() }
+}
+
+fn with_offset(instruction: Instruction, branch_offset: i16) -> Instruction {
+    match instruction {
+        Instruction::Goto { .. } => Instruction::Goto { branch_offset },
+        Instruction::IfAcmpeq { .. } => Instruction::IfAcmpeq { branch_offset },
+        Instruction::IfAcmpne { .. } => Instruction::IfAcmpne { branch_offset },
+        Instruction::IfIcmpeq { .. } => Instruction::IfIcmpeq { branch_offset },
+        Instruction::IfIcmpne { .. } => Instruction::IfIcmpne { branch_offset },
+        Instruction::IfIcmplt { .. } => Instruction::IfIcmplt { branch_offset },
+        Instruction::IfIcmpge { .. } => Instruction::IfIcmpge { branch_offset },
+        Instruction::IfIcmpgt { .. } => Instruction::IfIcmpgt { branch_offset },
+        Instruction::IfIcmple { .. } => Instruction::IfIcmple { branch_offset },
+        Instruction::Ifeq { .. } => Instruction::Ifeq { branch_offset },
+        Instruction::Ifne { .. } => Instruction::Ifne { branch_offset },
+        Instruction::Iflt { .. } => Instruction::Iflt { branch_offset },
+        Instruction::Ifge { .. } => Instruction::Ifge { branch_offset },
+        Instruction::Ifgt { .. } => Instruction::Ifgt { branch_offset },
+        Instruction::Ifle { .. } => Instruction::Ifle { branch_offset },
+        Instruction::Ifnonnull { .. } => Instruction::Ifnonnull { branch_offset },
+        Instruction::Ifnull { .. } => Instruction::Ifnull { branch_offset },
+        other => other
+    }
+}
+
+// The locals of a frame as the StackMapTable lists them: a long or double once, and without the
+// unusable locals at the end.
+fn frame_locals(state: &State, pool: &mut ConstantPoolBuilder) -> Vec<VerificationTypeInfo> {
+    let mut locals = Vec::new();
+    let mut slot = 0;
+    while slot < state.locals.len() {
+        locals.push(verification_info(&state.locals[slot], pool));
+        slot += state.locals[slot].width();
+    }
+    while locals.last() == Some(&VerificationTypeInfo::Top) {
+        locals.pop();
+    }
+    locals
+}
+
+// The most compact encoding of a frame relative to the previous one (JVMS §4.7.4).
+fn frame(offset_delta: u16, previous: &State, state: &State, pool: &mut ConstantPoolBuilder) -> StackMapFrame {
+    let previous_locals = frame_locals(previous, pool);
+    let locals = frame_locals(state, pool);
+    let stack: Vec<VerificationTypeInfo> = state.stack.iter().map(|value| verification_info(value, pool)).collect();
+
+    if locals == previous_locals && stack.is_empty() {
+        if offset_delta < 64 {
+            StackMapFrame::SameFrame { offset_delta: offset_delta as u8 }
+        } else {
+            StackMapFrame::SameFrameExtended { offset_delta }
+        }
+    } else if locals == previous_locals && stack.len() == 1 {
+        let info = stack[0].clone();
+        if offset_delta < 64 {
+            StackMapFrame::SameLocals1StackItemFrame { offset_delta: offset_delta as u8, info }
+        } else {
+            StackMapFrame::SameLocals1StackItemFrameExtended { offset_delta, info }
+        }
+    } else if stack.is_empty() && locals.len() < previous_locals.len() && previous_locals.len() - locals.len() <= 3
+            && previous_locals.starts_with(&locals) {
+        StackMapFrame::ChopFrame { offset_delta, chopped: (previous_locals.len() - locals.len()) as u8 }
+    } else if stack.is_empty() && locals.len() > previous_locals.len() && locals.len() - previous_locals.len() <= 3
+            && locals.starts_with(&previous_locals) {
+        StackMapFrame::AppendFrame { offset_delta, locals: locals[previous_locals.len()..].to_vec() }
+    } else {
+        StackMapFrame::FullFrame { offset_delta, locals, stack }
+    }
+}
+
+fn verification_info(value: &VerificationType, pool: &mut ConstantPoolBuilder) -> VerificationTypeInfo {
+    match *value {
+        VerificationType::Top => VerificationTypeInfo::Top,
+        VerificationType::Int => VerificationTypeInfo::Integer,
+        VerificationType::Float => VerificationTypeInfo::Float,
+        VerificationType::Long => VerificationTypeInfo::Long,
+        VerificationType::Double => VerificationTypeInfo::Double,
+        VerificationType::Null => VerificationTypeInfo::Null,
+        VerificationType::UninitializedThis => VerificationTypeInfo::UninitializedThis,
+        VerificationType::Object(ref class) => VerificationTypeInfo::Object(pool.class(class)),
+        VerificationType::Uninitialized(offset) => VerificationTypeInfo::Uninitialized(offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    // if (a < b) { int c = a; } return; jumps over the block, which has a local of its own.
+    #[test]
+    fn frames_at_jump_targets() {
+        let mut pool = ConstantPoolBuilder::new();
+        let mut code = Code::new(vec![VerificationType::Int, VerificationType::Int]);
+        code.line(3);
+        code.emit(Instruction::Iload0, 0, Some(VerificationType::Int));
+        code.emit(Instruction::Iload1, 0, Some(VerificationType::Int));
+        let end = code.new_label();
+        code.jump(Instruction::IfIcmpge { branch_offset: 0 }, 2, end);
+        code.line(4);
+        code.emit(Instruction::Iload0, 0, Some(VerificationType::Int));
+        code.emit(Instruction::Istore2, 1, None);
+        code.store(2, VerificationType::Int);
+        code.end_scope(2);
+        code.bind(end);
+        code.line(6);
+        code.emit_exit(Instruction::Return);
+
+        match code.finish(&mut pool).unwrap() {
+            Attribute::Code { max_stack, max_locals, code, attributes, .. } => {
+                assert_eq!((max_stack, max_locals), (2, 3));
+                assert_eq!(code, vec![0x1a, 0x1b, 0xa2, 0x00, 0x05, 0x1a, 0x3d, 0xb1]);
+                match attributes[0] {
+                    Attribute::LineNumberTable(ref lines) => assert_eq!(*lines, vec![
+                        LineNumberTableEntry { start_pc: 0, line_number: 3 },
+                        LineNumberTableEntry { start_pc: 5, line_number: 4 },
+                        LineNumberTableEntry { start_pc: 7, line_number: 6 }
+                    ]),
+                    ref attribute => panic!("Expected a LineNumberTable, got {:?}", attribute)
+                }
+                match attributes[1] {
+                    Attribute::StackMapTable { ref entries } => assert_eq!(*entries, vec![StackMapFrame::SameFrame { offset_delta: 7 }]),
+                    ref attribute => panic!("Expected a StackMapTable, got {:?}", attribute)
+                }
+            },
+            attribute => panic!("Expected a Code attribute, got {:?}", attribute)
+        }
+    }
+
+    #[test]
+    fn frames_are_compressed() {
+        let mut pool = ConstantPoolBuilder::new();
+        let empty = State { locals: vec![VerificationType::Int], stack: Vec::new() };
+        let appended = State { locals: vec![VerificationType::Int, VerificationType::Long, VerificationType::Top], stack: Vec::new() };
+        let with_stack = State { locals: vec![VerificationType::Int], stack: vec![VerificationType::Object(String::from("A"))] };
+
+        assert_eq!(frame(2, &empty, &appended, &mut pool), StackMapFrame::AppendFrame { offset_delta: 2, locals: vec![VerificationTypeInfo::Long] });
+        assert_eq!(frame(3, &appended, &empty, &mut pool), StackMapFrame::ChopFrame { offset_delta: 3, chopped: 1 });
+        assert_eq!(frame(70, &empty, &with_stack, &mut pool),
+                   StackMapFrame::SameLocals1StackItemFrameExtended { offset_delta: 70, info: VerificationTypeInfo::Object(2) });
+        assert_eq!(frame(0, &appended, &with_stack, &mut pool),
+                   StackMapFrame::FullFrame { offset_delta: 0, locals: vec![VerificationTypeInfo::Integer], stack: vec![VerificationTypeInfo::Object(2)] });
+    }
+
+}
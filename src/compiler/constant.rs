@@ -0,0 +1,289 @@
+// Compile-time constant expressions (JLS §15.29). Constants are folded with the semantics of the
+// operations at run time: integer arithmetic wraps, and division by zero is not a constant.
+
+use compiler::ast::{BinaryOperator, PrimitiveType, UnaryOperator};
+use runtime::bootstrap::{format_double, format_float};
+
+// The value of a constant expression. Values of type boolean, byte, short, char and int are
+// all Int or Boolean; the type of the expression tells them apart.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Constant {
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Boolean(bool),
+    String(Vec<u16>)
+}
+
+impl Constant {
+
+    fn as_long(&self) -> Option<i64> {
+        match *self {
+            Constant::Int(value) => Some(i64::from(value)),
+            Constant::Long(value) => Some(value),
+            Constant::Float(value) => Some(value as i64),
+            Constant::Double(value) => Some(value as i64),
+            _ => None
+        }
+    }
+
+    fn as_double(&self) -> Option<f64> {
+        match *self {
+            Constant::Int(value) => Some(f64::from(value)),
+            Constant::Long(value) => Some(value as f64),
+            Constant::Float(value) => Some(f64::from(value)),
+            Constant::Double(value) => Some(value),
+            _ => None
+        }
+    }
+
+    // The string conversion of the value (JLS §5.1.11), given its type.
+    pub fn to_java_string(&self, primitive: Option<PrimitiveType>) -> Vec<u16> {
+        let text = match *self {
+            Constant::Int(value) if primitive == Some(PrimitiveType::Char) => return vec![value as u16],
+            Constant::Int(value) => value.to_string(),
+            Constant::Long(value) => value.to_string(),
+            Constant::Float(value) => format_float(value),
+            Constant::Double(value) => format_double(value),
+            Constant::Boolean(value) => value.to_string(),
+            Constant::String(ref chars) => return chars.clone()
+        };
+        text.encode_utf16().collect()
+    }
+
+    // Whether an int constant can be assigned to a variable of a narrower type (JLS §5.2).
+    pub fn fits_in(&self, primitive: PrimitiveType) -> bool {
+        let value = match *self {
+            Constant::Int(value) => value,
+            _ => return false
+        };
+
+        match primitive {
+            PrimitiveType::Byte => i32::from(value as i8) == value,
+            PrimitiveType::Short => i32::from(value as i16) == value,
+            PrimitiveType::Char => i32::from(value as u16) == value,
+            _ => false
+        }
+    }
+
+}
+
+// Converts a constant to a primitive type, as a cast or numeric promotion would.
+pub fn convert(value: &Constant, target: PrimitiveType) -> Option<Constant> {
+    if let Constant::Boolean(_) = *value {
+        return if target == PrimitiveType::Boolean { Some(value.clone()) } else { None };
+    }
+    if let Constant::String(_) = *value {
+        return None;
+    }
+
+    let converted = match target {
+        PrimitiveType::Boolean => return None,
+        PrimitiveType::Byte => Constant::Int(i32::from(to_int(value)? as i8)),
+        PrimitiveType::Short => Constant::Int(i32::from(to_int(value)? as i16)),
+        PrimitiveType::Char => Constant::Int(i32::from(to_int(value)? as u16)),
+        PrimitiveType::Int => Constant::Int(to_int(value)?),
+        PrimitiveType::Long => Constant::Long(value.as_long()?),
+        PrimitiveType::Float => Constant::Float(match *value {
+            Constant::Long(long) => long as f32,
+            _ => value.as_double()? as f32
+        }),
+        PrimitiveType::Double => Constant::Double(value.as_double()?)
+    };
+    Some(converted)
+}
+
+// Narrowing to int goes through long for integral values and saturates for floating point
+// values, like d2i and f2i.
+fn to_int(value: &Constant) -> Option<i32> {
+    match *value {
+        Constant::Int(value) => Some(value),
+        Constant::Long(value) => Some(value as i32),
+        Constant::Float(value) => Some(value as i32),
+        Constant::Double(value) => Some(value as i32),
+        _ => None
+    }
+}
+
+// Folds a unary operator applied to a constant that has already been promoted.
+pub fn unary(operator: UnaryOperator, value: &Constant) -> Option<Constant> {
+    let folded = match (operator, value) {
+        (UnaryOperator::Plus, _) => value.clone(),
+        (UnaryOperator::Minus, &Constant::Int(value)) => Constant::Int(value.wrapping_neg()),
+        (UnaryOperator::Minus, &Constant::Long(value)) => Constant::Long(value.wrapping_neg()),
+        (UnaryOperator::Minus, &Constant::Float(value)) => Constant::Float(-value),
+        (UnaryOperator::Minus, &Constant::Double(value)) => Constant::Double(-value),
+        (UnaryOperator::BitwiseNot, &Constant::Int(value)) => Constant::Int(!value),
+        (UnaryOperator::BitwiseNot, &Constant::Long(value)) => Constant::Long(!value),
+        (UnaryOperator::Not, &Constant::Boolean(value)) => Constant::Boolean(!value),
+        _ => return None
+    };
+    Some(folded)
+}
+
+// Folds a binary operator. Operands of arithmetic and comparison operators have already been
+// promoted to the same type; the right operand of a shift is an int or a long. String
+// concatenation is folded by the caller, which knows the types of the operands.
+pub fn binary(operator: BinaryOperator, left: &Constant, right: &Constant) -> Option<Constant> {
+    use compiler::ast::BinaryOperator::*;
+
+    if let (&Constant::Boolean(left), &Constant::Boolean(right)) = (left, right) {
+        let value = match operator {
+            Or => left || right,
+            And => left && right,
+            BitwiseOr => left | right,
+            BitwiseXor => left ^ right,
+            BitwiseAnd => left & right,
+            Equal => left == right,
+            NotEqual => left != right,
+            _ => return None
+        };
+        return Some(Constant::Boolean(value));
+    }
+
+    match operator {
+        ShiftLeft | ShiftRight | UnsignedShiftRight => return shift(operator, left, right.as_long()?),
+        Equal | NotEqual | Less | Greater | LessOrEqual | GreaterOrEqual => return compare(operator, left, right),
+        _ => {}
+    }
+
+    let folded = match (left, right) {
+        (&Constant::Int(left), &Constant::Int(right)) => Constant::Int(match operator {
+            Add => left.wrapping_add(right),
+            Subtract => left.wrapping_sub(right),
+            Multiply => left.wrapping_mul(right),
+            Divide if right != 0 => left.wrapping_div(right),
+            Remainder if right != 0 => left.wrapping_rem(right),
+            BitwiseAnd => left & right,
+            BitwiseOr => left | right,
+            BitwiseXor => left ^ right,
+            _ => return None
+        }),
+        (&Constant::Long(left), &Constant::Long(right)) => Constant::Long(match operator {
+            Add => left.wrapping_add(right),
+            Subtract => left.wrapping_sub(right),
+            Multiply => left.wrapping_mul(right),
+            Divide if right != 0 => left.wrapping_div(right),
+            Remainder if right != 0 => left.wrapping_rem(right),
+            BitwiseAnd => left & right,
+            BitwiseOr => left | right,
+            BitwiseXor => left ^ right,
+            _ => return None
+        }),
+        (&Constant::Float(left), &Constant::Float(right)) => Constant::Float(match operator {
+            Add => left + right,
+            Subtract => left - right,
+            Multiply => left * right,
+            Divide => left / right,
+            Remainder => left % right,
+            _ => return None
+        }),
+        (&Constant::Double(left), &Constant::Double(right)) => Constant::Double(match operator {
+            Add => left + right,
+            Subtract => left - right,
+            Multiply => left * right,
+            Divide => left / right,
+            Remainder => left % right,
+            _ => return None
+        }),
+        _ => return None
+    };
+    Some(folded)
+}
+
+// Only the low five or six bits of the shift distance are used (JLS §15.19).
+fn shift(operator: BinaryOperator, value: &Constant, distance: i64) -> Option<Constant> {
+    let folded = match *value {
+        Constant::Int(value) => {
+            let distance = (distance & 0x1f) as u32;
+            Constant::Int(match operator {
+                BinaryOperator::ShiftLeft => value.wrapping_shl(distance),
+                BinaryOperator::ShiftRight => value.wrapping_shr(distance),
+                _ => ((value as u32) >> distance) as i32
+            })
+        },
+        Constant::Long(value) => {
+            let distance = (distance & 0x3f) as u32;
+            Constant::Long(match operator {
+                BinaryOperator::ShiftLeft => value.wrapping_shl(distance),
+                BinaryOperator::ShiftRight => value.wrapping_shr(distance),
+                _ => ((value as u64) >> distance) as i64
+            })
+        },
+        _ => return None
+    };
+    Some(folded)
+}
+
+fn compare(operator: BinaryOperator, left: &Constant, right: &Constant) -> Option<Constant> {
+    use std::cmp::Ordering;
+
+    let ordering = match (left, right) {
+        (&Constant::Int(left), &Constant::Int(right)) => Some(left.cmp(&right)),
+        (&Constant::Long(left), &Constant::Long(right)) => Some(left.cmp(&right)),
+        (&Constant::Float(left), &Constant::Float(right)) => left.partial_cmp(&right),
+        (&Constant::Double(left), &Constant::Double(right)) => left.partial_cmp(&right),
+        _ => return None
+    };
+
+    // Every comparison with NaN is false, except !=.
+    let value = match ordering {
+        None => operator == BinaryOperator::NotEqual,
+        Some(ordering) => match operator {
+            BinaryOperator::Equal => ordering == Ordering::Equal,
+            BinaryOperator::NotEqual => ordering != Ordering::Equal,
+            BinaryOperator::Less => ordering == Ordering::Less,
+            BinaryOperator::Greater => ordering == Ordering::Greater,
+            BinaryOperator::LessOrEqual => ordering != Ordering::Greater,
+            _ => ordering != Ordering::Less
+        }
+    };
+    Some(Constant::Boolean(value))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn folds_integer_arithmetic_with_wrapping() {
+        assert_eq!(binary(BinaryOperator::Add, &Constant::Int(i32::MAX), &Constant::Int(1)), Some(Constant::Int(i32::MIN)));
+        assert_eq!(binary(BinaryOperator::Divide, &Constant::Int(i32::MIN), &Constant::Int(-1)), Some(Constant::Int(i32::MIN)));
+        assert_eq!(binary(BinaryOperator::Remainder, &Constant::Int(-7), &Constant::Int(2)), Some(Constant::Int(-1)));
+        assert_eq!(binary(BinaryOperator::Divide, &Constant::Int(1), &Constant::Int(0)), None);
+        assert_eq!(binary(BinaryOperator::UnsignedShiftRight, &Constant::Int(-1), &Constant::Int(28)), Some(Constant::Int(15)));
+        assert_eq!(binary(BinaryOperator::ShiftLeft, &Constant::Long(1), &Constant::Int(65)), Some(Constant::Long(2)));
+    }
+
+    #[test]
+    fn folds_floating_point_and_comparisons() {
+        assert_eq!(binary(BinaryOperator::Divide, &Constant::Double(1.0), &Constant::Double(0.0)), Some(Constant::Double(f64::INFINITY)));
+        assert_eq!(binary(BinaryOperator::Equal, &Constant::Double(f64::NAN), &Constant::Double(f64::NAN)), Some(Constant::Boolean(false)));
+        assert_eq!(binary(BinaryOperator::NotEqual, &Constant::Float(f32::NAN), &Constant::Float(1.0)), Some(Constant::Boolean(true)));
+        assert_eq!(binary(BinaryOperator::And, &Constant::Boolean(true), &Constant::Boolean(false)), Some(Constant::Boolean(false)));
+    }
+
+    #[test]
+    fn converts_like_casts() {
+        assert_eq!(convert(&Constant::Int(300), PrimitiveType::Byte), Some(Constant::Int(44)));
+        assert_eq!(convert(&Constant::Int(-1), PrimitiveType::Char), Some(Constant::Int(0xffff)));
+        assert_eq!(convert(&Constant::Double(1e20), PrimitiveType::Int), Some(Constant::Int(i32::MAX)));
+        assert_eq!(convert(&Constant::Double(f64::NAN), PrimitiveType::Long), Some(Constant::Long(0)));
+        assert_eq!(convert(&Constant::Long(1 << 40), PrimitiveType::Int), Some(Constant::Int(0)));
+        assert!(Constant::Int(127).fits_in(PrimitiveType::Byte));
+        assert!(!Constant::Int(128).fits_in(PrimitiveType::Byte));
+        assert!(!Constant::Int(-1).fits_in(PrimitiveType::Char));
+    }
+
+    #[test]
+    fn converts_to_strings_like_java() {
+        let text = |value: Constant, primitive| String::from_utf16(&value.to_java_string(primitive)).unwrap();
+        assert_eq!(text(Constant::Int(65), Some(PrimitiveType::Char)), "A");
+        assert_eq!(text(Constant::Double(1e10), Some(PrimitiveType::Double)), "1.0E10");
+        assert_eq!(text(Constant::Float(0.5), Some(PrimitiveType::Float)), "0.5");
+        assert_eq!(text(Constant::Boolean(true), Some(PrimitiveType::Boolean)), "true");
+    }
+
+}
@@ -0,0 +1,2264 @@
+// Flow analysis (JLS §14.22, §16, §11.2), which javac runs on each top-level class once it is
+// attributed without errors. Like javac, it makes four passes over the class and the classes
+// nested in it, each with its own errors: whether statements are reachable, whether variables
+// are definitely assigned before they are read and definitely unassigned before a final one is
+// assigned, whether checked exceptions are caught or declared, and whether the local variables
+// lambda expressions and inner classes refer to are effectively final.
+
+use compiler::{Diagnostic, Position};
+use compiler::ast::{BinaryOperator, Block, EnumConstant, Expression, ExpressionKind, Identifier, LambdaBody, LambdaParameter, LocalVariable,
+                    Member, MethodReferenceTarget, Modifier, PrimitiveType, Resource, Statement, StatementKind, Switch, SwitchBody,
+                    TypeDeclaration, TypeKind, UnaryOperator};
+use compiler::ast;
+use compiler::attr::{Attribution, Reference};
+use compiler::constant::Constant;
+use compiler::types::Type;
+use class::field;
+use std::collections::{HashMap, HashSet};
+use std::mem;
+
+pub fn analyze(file: &str, declaration: &TypeDeclaration, attribution: &Attribution) -> Vec<Diagnostic> {
+    let body = match Body::of(declaration, attribution, false) {
+        Some(body) => body,
+        None => return Vec::new()
+    };
+    let mut log = Log::new(file);
+    Alive::new(attribution, &mut log).class(body);
+    let reassigned = {
+        let mut assign = Assign::new(attribution, &mut log);
+        assign.class(body);
+        assign.reassigned
+    };
+    Thrown::new(attribution, &mut log, &reassigned).class(body);
+    Capture::new(attribution, &mut log, &reassigned).class(body);
+    log.diagnostics
+}
+
+// Whether a block can complete normally (JLS §14.22), which attribution checks for the body of
+// a lambda expression that must return a value.
+pub fn can_complete_normally(block: &Block, attribution: &Attribution) -> bool {
+    let mut log = Log::new("");
+    let mut alive = Alive::new(attribution, &mut log);
+    alive.statements(&block.statements);
+    alive.alive != Liveness::Dead
+}
+
+fn completes_normally(statement: &Statement, attribution: &Attribution) -> bool {
+    let mut log = Log::new("");
+    let mut alive = Alive::new(attribution, &mut log);
+    alive.statement(statement);
+    alive.alive != Liveness::Dead
+}
+
+// The errors of the passes. Like javac, only the first error at a position is reported.
+struct Log {
+    file: String,
+    diagnostics: Vec<Diagnostic>
+}
+
+impl Log {
+
+    fn new(file: &str) -> Log {
+        Log { file: String::from(file), diagnostics: Vec::new() }
+    }
+
+    fn error(&mut self, position: Position, message: &str) {
+        if !self.diagnostics.iter().any(|diagnostic| diagnostic.position == position) {
+            self.diagnostics.push(Diagnostic::new(&self.file, position, message));
+        }
+    }
+
+    fn count(&self) -> usize {
+        self.diagnostics.len()
+    }
+
+}
+
+// A class body: a type declaration, or the body of an anonymous class or an enum constant.
+#[derive(Clone, Copy)]
+struct Body<'a> {
+    name: &'a str,
+    kind: TypeKind,
+    declaration: Option<&'a TypeDeclaration>,
+    members: &'a [Member],
+    // Whether the class is local or anonymous, or a member of such a class.
+    local: bool
+}
+
+impl<'a> Body<'a> {
+
+    fn of(declaration: &'a TypeDeclaration, attribution: &'a Attribution, local: bool) -> Option<Body<'a>> {
+        let name = attribution.class_name(declaration)?;
+        Some(Body { name, kind: declaration.kind, declaration: Some(declaration), members: &declaration.members, local })
+    }
+
+    fn anonymous<T>(node: &T, members: &'a [Member], attribution: &'a Attribution) -> Option<Body<'a>> {
+        let name = attribution.class_name(node)?;
+        Some(Body { name, kind: TypeKind::Class, declaration: None, members, local: true })
+    }
+
+    fn is_anonymous(&self) -> bool {
+        self.declaration.is_none()
+    }
+
+    fn enum_constants(&self) -> &'a [EnumConstant] {
+        self.declaration.map_or(&[], |declaration| &declaration.enum_constants[..])
+    }
+
+    // The fields, initializers and member classes, which javac visits before the methods: the
+    // static ones first and then the others.
+    fn initializers(&self, is_static: bool) -> Vec<&'a Member> {
+        self.members.iter()
+            .filter(|member| !matches!(**member, Member::Method(_) | Member::Constructor(_)))
+            .filter(|member| self.is_static(member) == is_static)
+            .collect()
+    }
+
+    fn methods(&self) -> Vec<&'a Member> {
+        self.members.iter().filter(|member| matches!(**member, Member::Method(_) | Member::Constructor(_))).collect()
+    }
+
+    fn is_static(&self, member: &Member) -> bool {
+        let in_interface = matches!(self.kind, TypeKind::Interface | TypeKind::Annotation);
+        match *member {
+            Member::Field(ref declaration) => in_interface || declaration.modifiers.contains(Modifier::Static),
+            Member::Initializer { is_static, .. } => is_static,
+            Member::Type(ref declaration) => in_interface || declaration.kind != TypeKind::Class || declaration.modifiers.contains(Modifier::Static),
+            _ => false
+        }
+    }
+
+    fn has_default_constructor(&self) -> bool {
+        matches!(self.kind, TypeKind::Class | TypeKind::Enum) && !self.members.iter().any(|member| matches!(*member, Member::Constructor(_)))
+    }
+
+    // The blank final fields, which must be definitely assigned by the initializers or by every
+    // constructor.
+    fn blank_finals(&self, is_static: bool) -> Vec<&'a Identifier> {
+        self.members.iter().filter_map(|member| match *member {
+            Member::Field(ref declaration) if declaration.modifiers.contains(Modifier::Final) && self.is_static(member) == is_static => Some(declaration),
+            _ => None
+        }).flat_map(|declaration| declaration.declarators.iter().filter(|declarator| declarator.initializer.is_none()).map(|declarator| &declarator.name))
+            .collect()
+    }
+
+}
+
+// A constructor that does not start with this(...) (JLS §16.9).
+fn is_initial_constructor(declaration: &ast::ConstructorDeclaration) -> bool {
+    !declaration.body.statements.first().is_some_and(|first| matches!(first.kind, StatementKind::ConstructorInvocation { is_super: false, .. }))
+}
+
+fn is_constant(expression: &Expression, attribution: &Attribution, value: bool) -> bool {
+    attribution.constant(expression) == Some(&Constant::Boolean(value))
+}
+
+fn strip_parentheses(expression: &Expression) -> &Expression {
+    match expression.kind {
+        ExpressionKind::Parenthesized(ref inner) => strip_parentheses(inner),
+        _ => expression
+    }
+}
+
+// A simple name, or a field access qualified by this, which is how a blank final field may be
+// assigned.
+fn is_simple_name(expression: &Expression) -> bool {
+    match expression.kind {
+        ExpressionKind::Name(_) => true,
+        ExpressionKind::FieldAccess { ref target, .. } => matches!(strip_parentheses(target).kind, ExpressionKind::This(None)),
+        _ => false
+    }
+}
+
+// Where javac reports a type: a qualified name at the dot before its last part.
+fn type_position(tree: &ast::Type) -> Position {
+    match *tree {
+        ast::Type::Class(ref class_type) if class_type.parts.len() > 1 => {
+            let last = class_type.parts[class_type.parts.len() - 1].name.position;
+            Position { line: last.line, column: last.column - 1 }
+        },
+        _ => tree.position()
+    }
+}
+
+// Whether a switch expression has a result for every value of its selector: it has a default
+// label, or names every constant of an enum.
+fn is_exhaustive(switch: &Switch, attribution: &Attribution) -> bool {
+    if switch.cases.iter().any(|case| case.labels.is_empty()) {
+        return true;
+    }
+    let class = match attribution.type_of(&switch.selector).and_then(Type::class_name).and_then(|name| attribution.symbols.class(name)) {
+        Some(class) if class.is_enum() => class,
+        _ => return false
+    };
+    let named: HashSet<&str> = switch.cases.iter().flat_map(|case| case.labels.iter()).filter_map(|label| match label.kind {
+        ExpressionKind::Name(ref name) => Some(name.name.as_str()),
+        _ => None
+    }).collect();
+    class.fields.iter().filter(|constant| constant.access_flags & field::ACC_ENUM != 0).all(|constant| named.contains(constant.name.as_str()))
+}
+
+// Walks the statements and expressions of a class, so that each pass only handles what it is
+// interested in. Every operand of an expression goes through operand.
+trait Scanner<'a> {
+
+    fn attribution(&self) -> &'a Attribution;
+
+    fn class(&mut self, body: Body<'a>);
+
+    fn statement(&mut self, statement: &'a Statement) {
+        scan_statement(self, statement);
+    }
+
+    fn expression(&mut self, expression: &'a Expression) {
+        scan_expression(self, expression);
+    }
+
+    fn operand(&mut self, expression: &'a Expression) {
+        self.expression(expression);
+    }
+
+    fn block(&mut self, block: &'a Block) {
+        for statement in block.statements.iter() {
+            self.statement(statement);
+        }
+    }
+
+    fn lambda(&mut self, _expression: &'a Expression, parameters: &'a [LambdaParameter], body: &'a LambdaBody) {
+        scan_lambda(self, parameters, body);
+    }
+
+    // A local variable or parameter comes into scope.
+    fn declare(&mut self, _name: &'a Identifier) {}
+
+}
+
+fn scan_statement<'a, S: Scanner<'a> + ?Sized>(scanner: &mut S, statement: &'a Statement) {
+    match statement.kind {
+        StatementKind::Block(ref block) => scanner.block(block),
+        StatementKind::LocalVariable(ref variable) => scan_local_variable(scanner, variable),
+        StatementKind::LocalType(ref declaration) => {
+            if let Some(body) = Body::of(declaration, scanner.attribution(), true) {
+                scanner.class(body);
+            }
+        },
+        StatementKind::Expression(ref expression) | StatementKind::Throw(ref expression) | StatementKind::Yield(ref expression)
+            | StatementKind::Return(Some(ref expression)) => scanner.operand(expression),
+        StatementKind::Empty | StatementKind::Break(_) | StatementKind::Continue(_) | StatementKind::Return(None) => {},
+        StatementKind::If { ref condition, ref then, ref otherwise } => {
+            scanner.operand(condition);
+            scanner.statement(then);
+            if let Some(ref otherwise) = *otherwise {
+                scanner.statement(otherwise);
+            }
+        },
+        StatementKind::While { ref condition, ref body } => {
+            scanner.operand(condition);
+            scanner.statement(body);
+        },
+        StatementKind::DoWhile { ref body, ref condition } => {
+            scanner.statement(body);
+            scanner.operand(condition);
+        },
+        StatementKind::For { ref init, ref condition, ref update, ref body } => {
+            for statement in init.iter() {
+                scanner.statement(statement);
+            }
+            if let Some(ref condition) = *condition {
+                scanner.operand(condition);
+            }
+            scanner.statement(body);
+            for expression in update.iter() {
+                scanner.operand(expression);
+            }
+        },
+        StatementKind::ForEach { ref variable, ref iterable, ref body } => {
+            scanner.operand(iterable);
+            scan_local_variable(scanner, variable);
+            scanner.statement(body);
+        },
+        StatementKind::Labeled { ref body, .. } => scanner.statement(body),
+        StatementKind::Switch(ref switch) => scan_switch(scanner, switch),
+        StatementKind::Synchronized { ref lock, ref body } => {
+            scanner.operand(lock);
+            scanner.block(body);
+        },
+        StatementKind::Try { ref resources, ref body, ref catches, ref finally } => {
+            for resource in resources.iter() {
+                match *resource {
+                    Resource::Declaration(ref variable) => scan_local_variable(scanner, variable),
+                    Resource::Expression(ref expression) => scanner.operand(expression)
+                }
+            }
+            scanner.block(body);
+            for catch in catches.iter() {
+                scanner.declare(&catch.name);
+                scanner.block(&catch.body);
+            }
+            if let Some(ref finally) = *finally {
+                scanner.block(finally);
+            }
+        },
+        StatementKind::Assert { ref condition, ref message } => {
+            scanner.operand(condition);
+            if let Some(ref message) = *message {
+                scanner.operand(message);
+            }
+        },
+        StatementKind::ConstructorInvocation { ref qualifier, ref arguments, .. } => {
+            if let Some(ref qualifier) = *qualifier {
+                scanner.operand(qualifier);
+            }
+            for argument in arguments.iter() {
+                scanner.operand(argument);
+            }
+        }
+    }
+}
+
+fn scan_local_variable<'a, S: Scanner<'a> + ?Sized>(scanner: &mut S, variable: &'a LocalVariable) {
+    for declarator in variable.declarators.iter() {
+        scanner.declare(&declarator.name);
+        if let Some(ref initializer) = declarator.initializer {
+            scanner.operand(initializer);
+        }
+    }
+}
+
+fn scan_switch<'a, S: Scanner<'a> + ?Sized>(scanner: &mut S, switch: &'a Switch) {
+    scanner.operand(&switch.selector);
+    for case in switch.cases.iter() {
+        match case.body {
+            SwitchBody::Statements(ref statements) => {
+                for statement in statements.iter() {
+                    scanner.statement(statement);
+                }
+            },
+            SwitchBody::Expression(ref expression) => scanner.operand(expression),
+            SwitchBody::Statement(ref statement) => scanner.statement(statement)
+        }
+    }
+}
+
+fn scan_lambda<'a, S: Scanner<'a> + ?Sized>(scanner: &mut S, parameters: &'a [LambdaParameter], body: &'a LambdaBody) {
+    for parameter in parameters.iter() {
+        scanner.declare(&parameter.name);
+    }
+    match *body {
+        LambdaBody::Expression(ref expression) => scanner.operand(expression),
+        LambdaBody::Block(ref block) => scanner.block(block)
+    }
+}
+
+fn scan_enum_constant<'a, S: Scanner<'a> + ?Sized>(scanner: &mut S, constant: &'a EnumConstant) {
+    for argument in constant.arguments.iter() {
+        scanner.operand(argument);
+    }
+    if let Some(ref members) = constant.body {
+        if let Some(body) = Body::anonymous(constant, members, scanner.attribution()) {
+            scanner.class(body);
+        }
+    }
+}
+
+// The members of a class in the order javac visits them: static fields, initializers and member
+// classes, then instance ones, and then the methods and constructors.
+fn scan_class<'a, S: Scanner<'a> + ?Sized>(scanner: &mut S, body: Body<'a>) {
+    for constant in body.enum_constants() {
+        scan_enum_constant(scanner, constant);
+    }
+    for &is_static in [true, false].iter() {
+        for member in body.initializers(is_static) {
+            scan_initializer(scanner, body, member);
+        }
+    }
+    for member in body.methods() {
+        match *member {
+            Member::Method(ref declaration) => {
+                for parameter in declaration.parameters.iter() {
+                    scanner.declare(&parameter.name);
+                }
+                if let Some(ref block) = declaration.body {
+                    scanner.block(block);
+                }
+            },
+            Member::Constructor(ref declaration) => {
+                for parameter in declaration.parameters.iter().flat_map(|parameters| parameters.iter()) {
+                    scanner.declare(&parameter.name);
+                }
+                scanner.block(&declaration.body);
+            },
+            _ => {}
+        }
+    }
+}
+
+fn scan_initializer<'a, S: Scanner<'a> + ?Sized>(scanner: &mut S, body: Body<'a>, member: &'a Member) {
+    match *member {
+        Member::Field(ref declaration) => {
+            for declarator in declaration.declarators.iter() {
+                if let Some(ref initializer) = declarator.initializer {
+                    scanner.operand(initializer);
+                }
+            }
+        },
+        Member::Initializer { body: ref block, .. } => scanner.block(block),
+        Member::Type(ref declaration) => {
+            if let Some(member) = Body::of(declaration, scanner.attribution(), body.local) {
+                scanner.class(member);
+            }
+        },
+        _ => {}
+    }
+}
+
+fn scan_expression<'a, S: Scanner<'a> + ?Sized>(scanner: &mut S, expression: &'a Expression) {
+    match expression.kind {
+        ExpressionKind::Literal(_) | ExpressionKind::Name(_) | ExpressionKind::SuperFieldAccess { .. } | ExpressionKind::This(_)
+            | ExpressionKind::ClassLiteral(_) => {},
+        ExpressionKind::FieldAccess { ref target, .. } => scanner.operand(target),
+        ExpressionKind::ArrayAccess { ref array, ref index } => {
+            scanner.operand(array);
+            scanner.operand(index);
+        },
+        ExpressionKind::MethodCall { ref target, ref arguments, .. } => {
+            if let Some(ref target) = *target {
+                scanner.operand(target);
+            }
+            for argument in arguments.iter() {
+                scanner.operand(argument);
+            }
+        },
+        ExpressionKind::SuperMethodCall { ref arguments, .. } => {
+            for argument in arguments.iter() {
+                scanner.operand(argument);
+            }
+        },
+        ExpressionKind::New { ref outer, ref arguments, ref body, .. } => {
+            if let Some(ref outer) = *outer {
+                scanner.operand(outer);
+            }
+            for argument in arguments.iter() {
+                scanner.operand(argument);
+            }
+            if let Some(ref members) = *body {
+                if let Some(body) = Body::anonymous(expression, members, scanner.attribution()) {
+                    scanner.class(body);
+                }
+            }
+        },
+        ExpressionKind::NewArray { ref dimensions, ref initializer, .. } => {
+            for dimension in dimensions.iter() {
+                scanner.operand(dimension);
+            }
+            for element in initializer.iter().flat_map(|elements| elements.iter()) {
+                scanner.operand(element);
+            }
+        },
+        ExpressionKind::ArrayInitializer(ref elements) => {
+            for element in elements.iter() {
+                scanner.operand(element);
+            }
+        },
+        ExpressionKind::Unary { ref operand, .. } | ExpressionKind::Cast { expression: ref operand, .. }
+            | ExpressionKind::Parenthesized(ref operand) => scanner.operand(operand),
+        ExpressionKind::Binary { ref left, ref right, .. } | ExpressionKind::Assign { target: ref left, value: ref right, .. } => {
+            scanner.operand(left);
+            scanner.operand(right);
+        },
+        ExpressionKind::Conditional { ref condition, ref then, ref otherwise } => {
+            scanner.operand(condition);
+            scanner.operand(then);
+            scanner.operand(otherwise);
+        },
+        ExpressionKind::InstanceOf { expression: ref operand, ref binding, .. } => {
+            scanner.operand(operand);
+            if let Some(ref binding) = *binding {
+                scanner.declare(binding);
+            }
+        },
+        ExpressionKind::Lambda { ref parameters, ref body } => scanner.lambda(expression, parameters, body),
+        ExpressionKind::MethodReference { ref target, .. } => {
+            if let MethodReferenceTarget::Expression(ref target) = *target {
+                scanner.operand(target);
+            }
+        },
+        ExpressionKind::Switch(ref switch) => scan_switch(scanner, switch)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TargetKind {
+    Loop, Switch, SwitchExpression, Label
+}
+
+// A statement that break, continue and yield statements may jump to.
+struct Target<'a> {
+    id: usize,
+    kind: TargetKind,
+    label: Option<&'a str>
+}
+
+#[derive(Default)]
+struct Targets<'a> {
+    stack: Vec<Target<'a>>,
+    count: usize
+}
+
+impl<'a> Targets<'a> {
+
+    fn push(&mut self, kind: TargetKind, label: Option<&'a str>) -> usize {
+        self.count += 1;
+        self.stack.push(Target { id: self.count, kind, label });
+        self.count
+    }
+
+    fn pop(&mut self) {
+        self.stack.pop();
+    }
+
+    // The target of a break, continue or yield statement, and whether it continues a loop.
+    fn of(&self, statement: &StatementKind) -> Option<(usize, bool)> {
+        let innermost = |predicate: &dyn Fn(&Target) -> bool| self.stack.iter().rev().find(|target| predicate(target)).map(|target| target.id);
+        match *statement {
+            StatementKind::Break(None) => innermost(&|target| matches!(target.kind, TargetKind::Loop | TargetKind::Switch)).map(|id| (id, false)),
+            StatementKind::Break(Some(ref label)) => innermost(&|target| target.label == Some(label.name.as_str())).map(|id| (id, false)),
+            StatementKind::Continue(None) => innermost(&|target| target.kind == TargetKind::Loop).map(|id| (id, true)),
+            // A labeled continue continues the loop the label is on.
+            StatementKind::Continue(Some(ref label)) => {
+                let index = self.stack.iter().rposition(|target| target.label == Some(label.name.as_str()))?;
+                self.stack[index + 1..].iter().find(|target| target.kind != TargetKind::Label).map(|target| (target.id, true))
+            },
+            StatementKind::Yield(_) => innermost(&|target| target.kind == TargetKind::SwitchExpression).map(|id| (id, false)),
+            _ => None
+        }
+    }
+
+}
+
+// Reachability (JLS §14.22). After an unreachable statement is reported, the statements after it
+// are taken to be reachable again, so that it is reported once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Liveness {
+    Alive, Dead, Recovery
+}
+
+impl Liveness {
+
+    fn from(alive: bool) -> Liveness {
+        if alive { Liveness::Alive } else { Liveness::Dead }
+    }
+
+    fn or(self, other: Liveness) -> Liveness {
+        match self {
+            Liveness::Dead => other,
+            Liveness::Alive => Liveness::Alive,
+            Liveness::Recovery => if other == Liveness::Alive { Liveness::Alive } else { Liveness::Recovery }
+        }
+    }
+
+    fn and(self, alive: bool) -> Liveness {
+        match self {
+            Liveness::Dead => Liveness::Dead,
+            Liveness::Alive => Liveness::from(alive),
+            Liveness::Recovery => if alive { Liveness::Recovery } else { Liveness::Dead }
+        }
+    }
+
+}
+
+struct Alive<'a, 'l> {
+    attribution: &'a Attribution,
+    log: &'l mut Log,
+    alive: Liveness,
+    targets: Targets<'a>,
+    // The jumps whose targets are still being analyzed, and whether they continue a loop.
+    jumps: Vec<(usize, bool)>
+}
+
+impl<'a, 'l> Alive<'a, 'l> {
+
+    fn new(attribution: &'a Attribution, log: &'l mut Log) -> Alive<'a, 'l> {
+        Alive { attribution, log, alive: Liveness::Alive, targets: Targets::default(), jumps: Vec::new() }
+    }
+
+    // Whether any of the jumps were to a target, which are resolved.
+    fn resolve(&mut self, target: usize, continues: bool) -> Liveness {
+        let count = self.jumps.len();
+        self.jumps.retain(|&jump| jump != (target, continues));
+        Liveness::from(self.jumps.len() != count)
+    }
+
+    fn statements(&mut self, statements: &'a [Statement]) {
+        for statement in statements.iter() {
+            self.statement(statement);
+        }
+    }
+
+    fn initializer(&mut self, body: Body<'a>, member: &'a Member) {
+        self.alive = Liveness::Alive;
+        scan_initializer(self, body, member);
+        if let Member::Initializer { body: ref block, .. } = *member {
+            if self.alive == Liveness::Dead {
+                self.log.error(block.position, "initializer must be able to complete normally");
+            }
+        }
+        self.jumps.clear();
+    }
+
+    fn method(&mut self, member: &'a Member) {
+        let (body, returns_value) = match *member {
+            Member::Method(ref declaration) => match declaration.body {
+                Some(ref body) => (body, !matches!(declaration.return_type, ast::Type::Void(_))),
+                None => return
+            },
+            Member::Constructor(ref declaration) => (&declaration.body, false),
+            _ => return
+        };
+        self.alive = Liveness::Alive;
+        self.statements(&body.statements);
+        if self.alive == Liveness::Alive && returns_value {
+            self.log.error(body.end, "missing return statement");
+        }
+        self.jumps.clear();
+    }
+
+    fn switch_statement(&mut self, switch: &'a Switch) {
+        self.expression(&switch.selector);
+        let target = self.targets.push(TargetKind::Switch, None);
+        for case in switch.cases.iter() {
+            self.alive = Liveness::Alive;
+            match case.body {
+                SwitchBody::Statements(ref statements) => self.statements(statements),
+                SwitchBody::Expression(ref expression) => {
+                    self.expression(expression);
+                    self.jumps.push((target, false));
+                    self.alive = Liveness::Dead;
+                },
+                SwitchBody::Statement(ref statement) => {
+                    self.statement(statement);
+                    if self.alive != Liveness::Dead {
+                        self.jumps.push((target, false));
+                        self.alive = Liveness::Dead;
+                    }
+                }
+            }
+        }
+        // Without a default label, no case may apply.
+        if !switch.cases.iter().any(|case| case.labels.is_empty()) {
+            self.alive = Liveness::Alive;
+        }
+        self.alive = self.alive.or(self.resolve(target, false));
+        self.targets.pop();
+    }
+
+    fn switch_expression(&mut self, expression: &'a Expression, switch: &'a Switch) {
+        self.expression(&switch.selector);
+        let alive = self.alive;
+        let target = self.targets.push(TargetKind::SwitchExpression, None);
+        for (index, case) in switch.cases.iter().enumerate() {
+            self.alive = Liveness::Alive;
+            match case.body {
+                SwitchBody::Statements(ref statements) => {
+                    self.statements(statements);
+                    if self.alive == Liveness::Alive && index + 1 == switch.cases.len() {
+                        self.log.error(switch.end, "switch expression completes without providing a value\n  (switch expressions must either provide a value or throw for all possible input values)");
+                    }
+                },
+                SwitchBody::Expression(ref expression) => {
+                    self.expression(expression);
+                    self.jumps.push((target, false));
+                    self.alive = Liveness::Dead;
+                },
+                SwitchBody::Statement(ref statement) => {
+                    self.statement(statement);
+                    if self.alive == Liveness::Alive {
+                        let end = match statement.kind {
+                            StatementKind::Block(ref block) => block.end,
+                            _ => statement.position
+                        };
+                        self.log.error(end, "switch rule completes without providing a value\n  (switch rules in switch expressions must either provide a value or throw)");
+                    }
+                }
+            }
+        }
+        if !is_exhaustive(switch, self.attribution) {
+            self.log.error(expression.position, "the switch expression does not cover all possible input values");
+        }
+        self.alive = alive.or(self.resolve(target, false));
+        self.targets.pop();
+    }
+
+}
+
+impl<'a, 'l> Scanner<'a> for Alive<'a, 'l> {
+
+    fn attribution(&self) -> &'a Attribution {
+        self.attribution
+    }
+
+    fn class(&mut self, body: Body<'a>) {
+        let alive = self.alive;
+        let jumps = mem::take(&mut self.jumps);
+        let targets = mem::take(&mut self.targets);
+
+        for constant in body.enum_constants() {
+            self.alive = Liveness::Alive;
+            scan_enum_constant(self, constant);
+        }
+        for &is_static in [true, false].iter() {
+            for member in body.initializers(is_static) {
+                self.initializer(body, member);
+            }
+        }
+        for member in body.methods() {
+            self.method(member);
+        }
+
+        self.alive = alive;
+        self.jumps = jumps;
+        self.targets = targets;
+    }
+
+    fn statement(&mut self, statement: &'a Statement) {
+        if self.alive == Liveness::Dead {
+            // javac reports a local variable declaration at the name of its first variable.
+            let position = match statement.kind {
+                StatementKind::LocalVariable(ref variable) => variable.declarators.first().map_or(statement.position, |declarator| declarator.name.position),
+                _ => statement.position
+            };
+            self.log.error(position, "unreachable statement");
+            if statement.kind != StatementKind::Empty {
+                self.alive = Liveness::Recovery;
+            }
+        }
+
+        match statement.kind {
+            StatementKind::Block(ref block) => self.statements(&block.statements),
+            StatementKind::If { ref condition, ref then, ref otherwise } => {
+                self.expression(condition);
+                self.statement(then);
+                if let Some(ref otherwise) = *otherwise {
+                    let after_then = self.alive;
+                    self.alive = Liveness::Alive;
+                    self.statement(otherwise);
+                    self.alive = self.alive.or(after_then);
+                } else {
+                    self.alive = Liveness::Alive;
+                }
+            },
+            StatementKind::While { ref condition, ref body } => {
+                let target = self.targets.push(TargetKind::Loop, None);
+                self.expression(condition);
+                self.alive = Liveness::from(!is_constant(condition, self.attribution, false));
+                self.statement(body);
+                self.alive = self.alive.or(self.resolve(target, true));
+                self.alive = self.resolve(target, false).or(Liveness::from(!is_constant(condition, self.attribution, true)));
+                self.targets.pop();
+            },
+            StatementKind::DoWhile { ref body, ref condition } => {
+                let target = self.targets.push(TargetKind::Loop, None);
+                self.statement(body);
+                self.alive = self.alive.or(self.resolve(target, true));
+                self.expression(condition);
+                self.alive = self.alive.and(!is_constant(condition, self.attribution, true));
+                self.alive = self.alive.or(self.resolve(target, false));
+                self.targets.pop();
+            },
+            StatementKind::For { ref init, ref condition, ref update, ref body } => {
+                self.statements(init);
+                let target = self.targets.push(TargetKind::Loop, None);
+                self.alive = match *condition {
+                    Some(ref condition) => {
+                        self.expression(condition);
+                        Liveness::from(!is_constant(condition, self.attribution, false))
+                    },
+                    None => Liveness::Alive
+                };
+                self.statement(body);
+                self.alive = self.alive.or(self.resolve(target, true));
+                for expression in update.iter() {
+                    self.expression(expression);
+                }
+                let exits = condition.as_ref().is_some_and(|condition| !is_constant(condition, self.attribution, true));
+                self.alive = self.resolve(target, false).or(Liveness::from(exits));
+                self.targets.pop();
+            },
+            StatementKind::ForEach { ref iterable, ref body, .. } => {
+                let target = self.targets.push(TargetKind::Loop, None);
+                self.expression(iterable);
+                self.statement(body);
+                self.resolve(target, true);
+                self.resolve(target, false);
+                self.alive = Liveness::Alive;
+                self.targets.pop();
+            },
+            StatementKind::Labeled { ref label, ref body } => {
+                let target = self.targets.push(TargetKind::Label, Some(&label.name));
+                self.statement(body);
+                self.alive = self.alive.or(self.resolve(target, false));
+                self.targets.pop();
+            },
+            StatementKind::Break(_) | StatementKind::Continue(_) | StatementKind::Yield(_) => {
+                if let StatementKind::Yield(ref value) = statement.kind {
+                    self.expression(value);
+                }
+                if let Some(jump) = self.targets.of(&statement.kind) {
+                    self.jumps.push(jump);
+                }
+                self.alive = Liveness::Dead;
+            },
+            StatementKind::Return(_) | StatementKind::Throw(_) => {
+                scan_statement(self, statement);
+                self.alive = Liveness::Dead;
+            },
+            StatementKind::Switch(ref switch) => self.switch_statement(switch),
+            StatementKind::Try { ref resources, ref body, ref catches, ref finally } => {
+                for resource in resources.iter() {
+                    match *resource {
+                        Resource::Declaration(ref variable) => scan_local_variable(self, variable),
+                        Resource::Expression(ref expression) => self.expression(expression)
+                    }
+                }
+                let first_jump = self.jumps.len();
+                self.statements(&body.statements);
+                let mut alive = self.alive;
+                for catch in catches.iter() {
+                    self.alive = Liveness::Alive;
+                    self.statements(&catch.body.statements);
+                    alive = alive.or(self.alive);
+                }
+                if let Some(ref finally) = *finally {
+                    let last_jump = self.jumps.len();
+                    self.alive = Liveness::Alive;
+                    self.statements(&finally.statements);
+                    // A finally block that cannot complete normally discards the jumps out of
+                    // the try block and the catch clauses.
+                    if self.alive == Liveness::Dead {
+                        self.jumps.drain(first_jump..last_jump);
+                    } else {
+                        self.alive = alive;
+                    }
+                } else {
+                    self.alive = alive;
+                }
+            },
+            _ => scan_statement(self, statement)
+        }
+    }
+
+    fn expression(&mut self, expression: &'a Expression) {
+        match expression.kind {
+            ExpressionKind::Switch(ref switch) => self.switch_expression(expression, switch),
+            _ => scan_expression(self, expression)
+        }
+    }
+
+    fn lambda(&mut self, _expression: &'a Expression, _parameters: &'a [LambdaParameter], body: &'a LambdaBody) {
+        let alive = mem::replace(&mut self.alive, Liveness::Alive);
+        let jumps = mem::take(&mut self.jumps);
+        let targets = mem::take(&mut self.targets);
+        match *body {
+            LambdaBody::Expression(ref expression) => self.expression(expression),
+            LambdaBody::Block(ref block) => self.statements(&block.statements)
+        }
+        self.alive = alive;
+        self.jumps = jumps;
+        self.targets = targets;
+    }
+
+}
+
+// A set of variables by address, as definite assignment keeps them.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Bits(Vec<u64>);
+
+impl Bits {
+
+    fn contains(&self, bit: usize) -> bool {
+        self.0.get(bit / 64).is_some_and(|word| word & (1 << (bit % 64)) != 0)
+    }
+
+    fn insert(&mut self, bit: usize) {
+        if self.0.len() <= bit / 64 {
+            self.0.resize(bit / 64 + 1, 0);
+        }
+        self.0[bit / 64] |= 1 << (bit % 64);
+    }
+
+    fn remove(&mut self, bit: usize) {
+        if let Some(word) = self.0.get_mut(bit / 64) {
+            *word &= !(1 << (bit % 64));
+        }
+    }
+
+    fn insert_range(&mut self, from: usize, to: usize) {
+        for bit in from..to {
+            self.insert(bit);
+        }
+    }
+
+    fn remove_from(&mut self, from: usize) {
+        for bit in from..self.0.len() * 64 {
+            self.remove(bit);
+        }
+    }
+
+    fn intersect(&mut self, other: &Bits) {
+        for (index, word) in self.0.iter_mut().enumerate() {
+            *word &= other.0.get(index).cloned().unwrap_or(0);
+        }
+    }
+
+    fn union(&mut self, other: &Bits) {
+        if self.0.len() < other.0.len() {
+            self.0.resize(other.0.len(), 0);
+        }
+        for (word, other) in self.0.iter_mut().zip(other.0.iter()) {
+            *word |= other;
+        }
+    }
+
+    // Whether a variable from an address on is in this set and not in the other.
+    fn has_outside(&self, other: &Bits, from: usize) -> bool {
+        (from..self.0.len() * 64).any(|bit| self.contains(bit) && !other.contains(bit))
+    }
+
+}
+
+// The variables definitely assigned and definitely unassigned after a boolean expression when
+// it is true and when it is false (JLS §16.1).
+#[derive(Clone)]
+struct Conditional {
+    inits_true: Bits,
+    uninits_true: Bits,
+    inits_false: Bits,
+    uninits_false: Bits
+}
+
+impl Conditional {
+
+    fn negate(self) -> Conditional {
+        Conditional { inits_true: self.inits_false, uninits_true: self.uninits_false, inits_false: self.inits_true, uninits_false: self.uninits_true }
+    }
+
+}
+
+// A variable whose assignment is tracked: a local variable or parameter, or a blank final field
+// of a class being analyzed.
+struct Variable<'a> {
+    name: &'a str,
+    position: Position,
+    local: Option<usize>,
+    // The class that declares a field.
+    owner: Option<&'a str>,
+    is_static: bool,
+    is_final: bool
+}
+
+// A jump or return statement, with the variables definitely (un)assigned before it.
+struct Exit {
+    target: Option<(usize, bool)>,
+    position: Position,
+    inits: Bits,
+    uninits: Bits
+}
+
+// Definite assignment (JLS §16). Like javac, variables get consecutive addresses as they are
+// declared, and the addresses of the variables of a block are reused once it ends.
+struct Assign<'a, 'l> {
+    attribution: &'a Attribution,
+    log: &'l mut Log,
+    variables: Vec<Variable<'a>>,
+    // The first variable whose assignment is checked: fields are only tracked in the
+    // initializers and initial constructors of the class that declares them.
+    first: usize,
+    // The first variable a jump out of the method or lambda body makes definitely assigned.
+    returns: usize,
+    inits: Bits,
+    uninits: Bits,
+    // The variables definitely unassigned anywhere in the try block being analyzed.
+    uninits_try: Bits,
+    // The state after a boolean expression, when it is split into true and false.
+    conditional: Option<Conditional>,
+    // Whether a loop body is analyzed the second time, which finds final variables assigned in
+    // an earlier iteration.
+    second_pass: bool,
+    initial_constructor: bool,
+    class: &'a str,
+    targets: Targets<'a>,
+    exits: Vec<Exit>,
+    // The local variables assigned when not definitely unassigned, which are not effectively
+    // final (JLS §4.12.4).
+    reassigned: HashSet<usize>
+}
+
+impl<'a, 'l> Assign<'a, 'l> {
+
+    fn new(attribution: &'a Attribution, log: &'l mut Log) -> Assign<'a, 'l> {
+        Assign {
+            attribution,
+            log,
+            variables: Vec::new(),
+            first: 0,
+            returns: 0,
+            inits: Bits::default(),
+            uninits: Bits::default(),
+            uninits_try: Bits::default(),
+            conditional: None,
+            second_pass: false,
+            initial_constructor: false,
+            class: "",
+            targets: Targets::default(),
+            exits: Vec::new(),
+            reassigned: HashSet::new()
+        }
+    }
+
+    fn new_variable(&mut self, variable: Variable<'a>) -> usize {
+        let address = self.variables.len();
+        self.variables.push(variable);
+        self.inits.remove(address);
+        self.uninits.insert(address);
+        address
+    }
+
+    fn declare_local(&mut self, name: &'a Identifier) -> Option<usize> {
+        let local = self.attribution.declared_local(name)?;
+        let is_final = self.attribution.local(local).is_final;
+        Some(self.new_variable(Variable { name: &name.name, position: name.position, local: Some(local), owner: None, is_static: false, is_final }))
+    }
+
+    // A parameter is definitely assigned when its scope starts.
+    fn declare_parameter(&mut self, name: &'a Identifier) {
+        if let Some(address) = self.declare_local(name) {
+            self.inits.insert(address);
+            self.uninits.remove(address);
+        }
+    }
+
+    fn end_scope(&mut self, next: usize) {
+        self.variables.truncate(next);
+    }
+
+    // The tracked variable an expression names.
+    fn variable(&self, expression: &Expression) -> Option<usize> {
+        match *self.attribution.reference(expression)? {
+            Reference::Local(local) => self.variables.iter().rposition(|variable| variable.local == Some(local)),
+            Reference::Field(ref field, _) if is_simple_name(expression) => self.variables.iter()
+                .rposition(|variable| variable.owner == Some(field.owner.as_str()) && variable.name == field.name),
+            _ => None
+        }
+    }
+
+    fn check_init(&mut self, position: Position, address: usize) {
+        self.check_init_with(position, address, "might not have been initialized");
+    }
+
+    fn check_init_with(&mut self, position: Position, address: usize, problem: &str) {
+        let variable = &self.variables[address];
+        if (address >= self.first || variable.local.is_some()) && !self.inits.contains(address) {
+            self.log.error(position, &format!("variable {} {}", variable.name, problem));
+            self.inits.insert(address);
+        }
+    }
+
+    fn let_init(&mut self, position: Position, address: usize) {
+        let variable = &self.variables[address];
+        if address < self.first {
+            if variable.is_final {
+                self.log.error(position, &format!("variable {} might already have been assigned", variable.name));
+            }
+            return;
+        }
+        if !variable.is_final {
+            let local = variable.local.unwrap();
+            if !self.reassigned.contains(&local) {
+                if self.uninits.contains(address) {
+                    self.uninit(address);
+                } else {
+                    self.reassigned.insert(local);
+                }
+            }
+        } else if !self.uninits.contains(address) {
+            let problem = if self.second_pass { "might be assigned in loop" } else { "might already have been assigned" };
+            self.log.error(position, &format!("variable {} {}", variable.name, problem));
+        } else {
+            self.uninit(address);
+        }
+        self.inits.insert(address);
+    }
+
+    fn uninit(&mut self, address: usize) {
+        if !self.inits.contains(address) {
+            self.uninits_try.remove(address);
+        }
+        self.uninits.remove(address);
+    }
+
+    fn let_init_target(&mut self, target: &'a Expression) {
+        let target = strip_parentheses(target);
+        if matches!(target.kind, ExpressionKind::Name(_) | ExpressionKind::FieldAccess { .. }) {
+            if let Some(address) = self.variable(target) {
+                self.let_init(target.position, address);
+            }
+        }
+    }
+
+    // After a jump every variable is both definitely assigned and definitely unassigned,
+    // except that a constructor does not assign static fields.
+    fn mark_dead(&mut self) {
+        for address in self.returns..self.variables.len() {
+            let variable = &self.variables[address];
+            if !(self.initial_constructor && variable.owner.is_some() && variable.is_static) {
+                self.inits.insert(address);
+            }
+        }
+        self.uninits.insert_range(self.returns, self.variables.len());
+    }
+
+    fn record_exit(&mut self, target: Option<(usize, bool)>, position: Position) {
+        self.exits.push(Exit { target, position, inits: self.inits.clone(), uninits: self.uninits.clone() });
+        self.mark_dead();
+    }
+
+    // Merges the state at the jumps to a target, and tells whether there were any.
+    fn resolve(&mut self, target: usize, continues: bool) -> bool {
+        let (resolved, pending): (Vec<Exit>, Vec<Exit>) = mem::take(&mut self.exits).into_iter().partition(|exit| exit.target == Some((target, continues)));
+        self.exits = pending;
+        for exit in resolved.iter() {
+            self.inits.intersect(&exit.inits);
+            self.uninits.intersect(&exit.uninits);
+        }
+        !resolved.is_empty()
+    }
+
+    fn merge(&mut self) {
+        if let Some(conditional) = self.conditional.take() {
+            self.inits = conditional.inits_true;
+            self.inits.intersect(&conditional.inits_false);
+            self.uninits = conditional.uninits_true;
+            self.uninits.intersect(&conditional.uninits_false);
+        }
+    }
+
+    fn scan_expression(&mut self, expression: &'a Expression) {
+        self.expression(expression);
+        self.merge();
+    }
+
+    // A boolean expression. A constant one is not analyzed: the branch it never takes is dead.
+    fn condition(&mut self, expression: &'a Expression) -> Conditional {
+        if let Some(&Constant::Boolean(value)) = self.attribution.constant(expression) {
+            let mut never_inits = self.inits.clone();
+            never_inits.insert_range(self.first, self.variables.len());
+            let mut never_uninits = self.uninits.clone();
+            never_uninits.insert_range(self.first, self.variables.len());
+            let (inits, uninits) = (self.inits.clone(), self.uninits.clone());
+            return if value {
+                Conditional { inits_true: inits, uninits_true: uninits, inits_false: never_inits, uninits_false: never_uninits }
+            } else {
+                Conditional { inits_true: never_inits, uninits_true: never_uninits, inits_false: inits, uninits_false: uninits }
+            };
+        }
+        self.expression(expression);
+        self.conditional.take().unwrap_or_else(|| Conditional {
+            inits_true: self.inits.clone(),
+            uninits_true: self.uninits.clone(),
+            inits_false: self.inits.clone(),
+            uninits_false: self.uninits.clone()
+        })
+    }
+
+    fn block_statements(&mut self, statements: &'a [Statement]) {
+        let next = self.variables.len();
+        for statement in statements.iter() {
+            self.statement(statement);
+        }
+        self.end_scope(next);
+    }
+
+    fn local_variable(&mut self, variable: &'a LocalVariable) {
+        for declarator in variable.declarators.iter() {
+            let address = self.declare_local(&declarator.name);
+            if let Some(ref initializer) = declarator.initializer {
+                self.scan_expression(initializer);
+                if let Some(address) = address {
+                    self.let_init(declarator.name.position, address);
+                }
+            }
+        }
+    }
+
+    fn while_loop(&mut self, condition: &'a Expression, body: &'a Statement) {
+        let target = self.targets.push(TargetKind::Loop, None);
+        let second_pass = mem::replace(&mut self.second_pass, false);
+        let errors = self.log.count();
+        let mut uninits_entry = self.uninits.clone();
+        uninits_entry.remove_from(self.variables.len());
+        let (mut inits_skip, mut uninits_skip) = (Bits::default(), Bits::default());
+        loop {
+            let conditional = self.condition(condition);
+            if !self.second_pass {
+                inits_skip = conditional.inits_false;
+                uninits_skip = conditional.uninits_false;
+            }
+            self.inits = conditional.inits_true;
+            self.uninits = conditional.uninits_true;
+            self.statement(body);
+            self.resolve(target, true);
+            if self.log.count() != errors || self.second_pass || !uninits_entry.has_outside(&self.uninits, self.first) {
+                break;
+            }
+            uninits_entry.intersect(&self.uninits);
+            self.uninits = uninits_entry.clone();
+            self.second_pass = true;
+        }
+        self.second_pass = second_pass;
+        self.inits = inits_skip;
+        self.uninits = uninits_skip;
+        self.resolve(target, false);
+        self.targets.pop();
+    }
+
+    fn do_while_loop(&mut self, body: &'a Statement, condition: &'a Expression) {
+        let target = self.targets.push(TargetKind::Loop, None);
+        let second_pass = mem::replace(&mut self.second_pass, false);
+        let errors = self.log.count();
+        let mut uninits_entry = self.uninits.clone();
+        uninits_entry.remove_from(self.variables.len());
+        let (mut inits_skip, mut uninits_skip) = (Bits::default(), Bits::default());
+        loop {
+            self.statement(body);
+            self.resolve(target, true);
+            let conditional = self.condition(condition);
+            if !self.second_pass {
+                inits_skip = conditional.inits_false.clone();
+                uninits_skip = conditional.uninits_false.clone();
+            }
+            if self.log.count() != errors || self.second_pass || !uninits_entry.has_outside(&conditional.uninits_true, self.first) {
+                break;
+            }
+            self.inits = conditional.inits_true;
+            uninits_entry.intersect(&conditional.uninits_true);
+            self.uninits = uninits_entry.clone();
+            self.second_pass = true;
+        }
+        self.second_pass = second_pass;
+        self.inits = inits_skip;
+        self.uninits = uninits_skip;
+        self.resolve(target, false);
+        self.targets.pop();
+    }
+
+    fn for_loop(&mut self, init: &'a [Statement], condition: Option<&'a Expression>, update: &'a [Expression], body: &'a Statement) {
+        let next = self.variables.len();
+        for statement in init.iter() {
+            self.statement(statement);
+        }
+        let target = self.targets.push(TargetKind::Loop, None);
+        let second_pass = mem::replace(&mut self.second_pass, false);
+        let errors = self.log.count();
+        let (mut inits_skip, mut uninits_skip) = (Bits::default(), Bits::default());
+        loop {
+            let mut uninits_entry = self.uninits.clone();
+            uninits_entry.remove_from(self.variables.len());
+            match condition {
+                Some(condition) => {
+                    let conditional = self.condition(condition);
+                    if !self.second_pass {
+                        inits_skip = conditional.inits_false;
+                        uninits_skip = conditional.uninits_false;
+                    }
+                    self.inits = conditional.inits_true;
+                    self.uninits = conditional.uninits_true;
+                },
+                // Without a condition the loop is only left by jumps.
+                None if !self.second_pass => {
+                    inits_skip = self.inits.clone();
+                    inits_skip.insert_range(self.first, self.variables.len());
+                    uninits_skip = self.uninits.clone();
+                    uninits_skip.insert_range(self.first, self.variables.len());
+                },
+                None => {}
+            }
+            self.statement(body);
+            self.resolve(target, true);
+            for expression in update.iter() {
+                self.scan_expression(expression);
+            }
+            if self.log.count() != errors || self.second_pass || !uninits_entry.has_outside(&self.uninits, self.first) {
+                break;
+            }
+            uninits_entry.intersect(&self.uninits);
+            self.uninits = uninits_entry;
+            self.second_pass = true;
+        }
+        self.second_pass = second_pass;
+        self.inits = inits_skip;
+        self.uninits = uninits_skip;
+        self.resolve(target, false);
+        self.targets.pop();
+        self.end_scope(next);
+    }
+
+    fn for_each_loop(&mut self, variable: &'a LocalVariable, iterable: &'a Expression, body: &'a Statement) {
+        let next = self.variables.len();
+        let addresses: Vec<(Position, usize)> = variable.declarators.iter()
+            .filter_map(|declarator| self.declare_local(&declarator.name).map(|address| (declarator.name.position, address)))
+            .collect();
+        self.scan_expression(iterable);
+        let inits_start = self.inits.clone();
+        let mut uninits_start = self.uninits.clone();
+        for &(position, address) in addresses.iter() {
+            self.let_init(position, address);
+        }
+
+        let target = self.targets.push(TargetKind::Loop, None);
+        let second_pass = mem::replace(&mut self.second_pass, false);
+        let errors = self.log.count();
+        loop {
+            let mut uninits_entry = self.uninits.clone();
+            uninits_entry.remove_from(self.variables.len());
+            self.statement(body);
+            self.resolve(target, true);
+            if self.log.count() != errors || self.second_pass || !uninits_entry.has_outside(&self.uninits, self.first) {
+                break;
+            }
+            uninits_entry.intersect(&self.uninits);
+            self.uninits = uninits_entry;
+            self.second_pass = true;
+        }
+        self.second_pass = second_pass;
+        self.inits = inits_start;
+        uninits_start.intersect(&self.uninits);
+        self.uninits = uninits_start;
+        self.resolve(target, false);
+        self.targets.pop();
+        self.end_scope(next);
+    }
+
+    fn switch(&mut self, switch: &'a Switch, is_expression: bool) {
+        let next = self.variables.len();
+        self.scan_expression(&switch.selector);
+        let mut inits_switch = self.inits.clone();
+        let mut uninits_switch = self.uninits.clone();
+        let target = self.targets.push(if is_expression { TargetKind::SwitchExpression } else { TargetKind::Switch }, None);
+        for case in switch.cases.iter() {
+            self.inits = inits_switch.clone();
+            self.uninits.intersect(&uninits_switch);
+            match case.body {
+                SwitchBody::Statements(ref statements) => {
+                    for statement in statements.iter() {
+                        self.statement(statement);
+                    }
+                    // The scope of a local variable declared in a case is the rest of the
+                    // switch block, but it is not assigned when another case is entered.
+                    for statement in statements.iter() {
+                        if let StatementKind::LocalVariable(ref variable) = statement.kind {
+                            for declarator in variable.declarators.iter() {
+                                let local = self.attribution.declared_local(&declarator.name);
+                                if let Some(address) = self.variables.iter().rposition(|variable| variable.local.is_some() && variable.local == local) {
+                                    inits_switch.remove(address);
+                                    uninits_switch.insert(address);
+                                }
+                            }
+                        }
+                    }
+                },
+                SwitchBody::Expression(ref expression) => {
+                    self.scan_expression(expression);
+                    self.record_exit(Some((target, false)), expression.position);
+                },
+                SwitchBody::Statement(ref statement) => {
+                    self.statement(statement);
+                    if !is_expression && completes_normally(statement, self.attribution) {
+                        self.record_exit(Some((target, false)), statement.position);
+                    }
+                }
+            }
+        }
+        if !is_exhaustive(switch, self.attribution) {
+            if is_expression {
+                self.mark_dead();
+            } else {
+                self.inits = inits_switch;
+                self.uninits.intersect(&uninits_switch);
+            }
+        }
+        self.resolve(target, false);
+        self.targets.pop();
+        self.end_scope(next);
+    }
+
+    fn try_statement(&mut self, resources: &'a [Resource], body: &'a Block, catches: &'a [ast::Catch], finally: Option<&'a Block>) {
+        let uninits_try_outer = self.uninits_try.clone();
+        let first_exit = self.exits.len();
+        let inits_try = self.inits.clone();
+        self.uninits_try = self.uninits.clone();
+        for resource in resources.iter() {
+            match *resource {
+                Resource::Declaration(ref variable) => self.local_variable(variable),
+                Resource::Expression(ref expression) => self.scan_expression(expression)
+            }
+        }
+        self.block_statements(&body.statements);
+        self.uninits_try.intersect(&self.uninits);
+        let mut inits_end = self.inits.clone();
+        let mut uninits_end = self.uninits.clone();
+        let next = self.variables.len();
+
+        // Each catch clause may be entered from anywhere in the try block.
+        for catch in catches.iter() {
+            self.inits = inits_try.clone();
+            self.uninits = self.uninits_try.clone();
+            self.declare_parameter(&catch.name);
+            self.block_statements(&catch.body.statements);
+            inits_end.intersect(&self.inits);
+            uninits_end.intersect(&self.uninits);
+            self.end_scope(next);
+        }
+
+        match finally {
+            Some(finally) => {
+                self.inits = inits_try;
+                self.uninits = self.uninits_try.clone();
+                let exits = self.exits.split_off(first_exit);
+                self.block_statements(&finally.statements);
+                if can_complete_normally(finally, self.attribution) {
+                    self.uninits.intersect(&uninits_end);
+                    for mut exit in exits {
+                        exit.inits.union(&self.inits);
+                        exit.uninits.intersect(&self.uninits);
+                        self.exits.push(exit);
+                    }
+                    self.inits.union(&inits_end);
+                }
+            },
+            None => {
+                self.inits = inits_end;
+                self.uninits = uninits_end;
+            }
+        }
+        self.uninits_try.intersect(&uninits_try_outer);
+        self.uninits_try.intersect(&self.uninits);
+    }
+
+    fn initializer(&mut self, body: Body<'a>, member: &'a Member) {
+        match *member {
+            Member::Initializer { body: ref block, .. } => self.block_statements(&block.statements),
+            _ => scan_initializer(self, body, member)
+        }
+        self.exits.clear();
+    }
+
+    // The blank final fields a default constructor leaves unassigned.
+    fn default_constructor(&mut self) {
+        let inits = self.inits.clone();
+        for address in self.first..self.variables.len() {
+            if self.variables[address].owner == Some(self.class) {
+                let position = self.variables[address].position;
+                self.check_init_with(position, address, "not initialized in the default constructor");
+            }
+        }
+        self.inits = inits;
+    }
+
+    fn method(&mut self, member: &'a Member) {
+        let (parameters, body, initial) = match *member {
+            Member::Method(ref declaration) => match declaration.body {
+                Some(ref body) => (&declaration.parameters[..], body, false),
+                None => return
+            },
+            Member::Constructor(ref declaration) => {
+                (declaration.parameters.as_ref().map_or(&[][..], |parameters| &parameters[..]), &declaration.body, is_initial_constructor(declaration))
+            },
+            _ => return
+        };
+
+        let (inits, uninits) = (self.inits.clone(), self.uninits.clone());
+        let (next, first, returns) = (self.variables.len(), self.first, self.returns);
+        let initial_constructor = mem::replace(&mut self.initial_constructor, initial);
+        // Fields are only tracked in initial constructors.
+        if !initial {
+            self.first = next;
+        }
+        self.returns = self.first;
+        for parameter in parameters.iter() {
+            self.declare_parameter(&parameter.name);
+        }
+        self.block_statements(&body.statements);
+
+        if initial {
+            for address in self.first..self.variables.len() {
+                if self.variables[address].owner == Some(self.class) {
+                    self.check_init(body.end, address);
+                }
+            }
+            for exit in mem::take(&mut self.exits) {
+                self.inits = exit.inits;
+                for address in self.first..self.variables.len() {
+                    self.check_init(exit.position, address);
+                }
+            }
+        }
+        self.exits.clear();
+
+        self.inits = inits;
+        self.uninits = uninits;
+        self.end_scope(next);
+        self.first = first;
+        self.returns = returns;
+        self.initial_constructor = initial_constructor;
+    }
+
+}
+
+impl<'a, 'l> Scanner<'a> for Assign<'a, 'l> {
+
+    fn attribution(&self) -> &'a Attribution {
+        self.attribution
+    }
+
+    fn class(&mut self, body: Body<'a>) {
+        let class = mem::replace(&mut self.class, body.name);
+        let (next, first, returns) = (self.variables.len(), self.first, self.returns);
+        let exits = mem::take(&mut self.exits);
+        let targets = mem::take(&mut self.targets);
+        let initial_constructor = mem::replace(&mut self.initial_constructor, false);
+        // The code of an anonymous class is part of the code around it.
+        if !body.is_anonymous() {
+            self.first = next;
+        }
+        self.returns = self.first;
+
+        for &is_static in [true, false].iter() {
+            for name in body.blank_finals(is_static) {
+                self.new_variable(Variable { name: &name.name, position: name.position, local: None, owner: Some(body.name), is_static, is_final: true });
+            }
+            if is_static {
+                for constant in body.enum_constants() {
+                    scan_enum_constant(self, constant);
+                }
+            }
+            for member in body.initializers(is_static) {
+                self.initializer(body, member);
+            }
+        }
+        if body.has_default_constructor() {
+            self.default_constructor();
+        }
+        for member in body.methods() {
+            self.method(member);
+        }
+
+        self.class = class;
+        self.end_scope(next);
+        self.first = first;
+        self.returns = returns;
+        self.exits = exits;
+        self.targets = targets;
+        self.initial_constructor = initial_constructor;
+    }
+
+    fn statement(&mut self, statement: &'a Statement) {
+        match statement.kind {
+            StatementKind::Block(ref block) => self.block_statements(&block.statements),
+            StatementKind::LocalVariable(ref variable) => self.local_variable(variable),
+            StatementKind::Expression(ref expression) => self.scan_expression(expression),
+            StatementKind::If { ref condition, ref then, ref otherwise } => {
+                let conditional = self.condition(condition);
+                self.inits = conditional.inits_true;
+                self.uninits = conditional.uninits_true;
+                self.statement(then);
+                match *otherwise {
+                    Some(ref otherwise) => {
+                        let inits_then = mem::replace(&mut self.inits, conditional.inits_false);
+                        let uninits_then = mem::replace(&mut self.uninits, conditional.uninits_false);
+                        self.statement(otherwise);
+                        self.inits.intersect(&inits_then);
+                        self.uninits.intersect(&uninits_then);
+                    },
+                    None => {
+                        self.inits.intersect(&conditional.inits_false);
+                        self.uninits.intersect(&conditional.uninits_false);
+                    }
+                }
+            },
+            StatementKind::While { ref condition, ref body } => self.while_loop(condition, body),
+            StatementKind::DoWhile { ref body, ref condition } => self.do_while_loop(body, condition),
+            StatementKind::For { ref init, ref condition, ref update, ref body } => self.for_loop(init, condition.as_ref(), update, body),
+            StatementKind::ForEach { ref variable, ref iterable, ref body } => self.for_each_loop(variable, iterable, body),
+            StatementKind::Labeled { ref label, ref body } => {
+                let target = self.targets.push(TargetKind::Label, Some(&label.name));
+                self.statement(body);
+                self.resolve(target, false);
+                self.targets.pop();
+            },
+            StatementKind::Break(_) | StatementKind::Continue(_) => {
+                let target = self.targets.of(&statement.kind);
+                self.record_exit(target, statement.position);
+            },
+            StatementKind::Yield(ref value) => {
+                self.scan_expression(value);
+                let target = self.targets.of(&statement.kind);
+                self.record_exit(target, statement.position);
+            },
+            StatementKind::Return(ref value) => {
+                if let Some(ref value) = *value {
+                    self.scan_expression(value);
+                }
+                self.record_exit(None, statement.position);
+            },
+            StatementKind::Throw(ref exception) => {
+                self.scan_expression(exception);
+                self.mark_dead();
+            },
+            StatementKind::Switch(ref switch) => self.switch(switch, false),
+            StatementKind::Try { ref resources, ref body, ref catches, ref finally } => self.try_statement(resources, body, catches, finally.as_ref()),
+            StatementKind::Assert { ref condition, ref message } => {
+                let inits_exit = self.inits.clone();
+                let mut uninits_exit = self.uninits.clone();
+                let conditional = self.condition(condition);
+                uninits_exit.intersect(&conditional.uninits_true);
+                if let Some(ref message) = *message {
+                    self.inits = conditional.inits_false;
+                    self.uninits = conditional.uninits_false;
+                    self.scan_expression(message);
+                }
+                self.inits = inits_exit;
+                self.uninits = uninits_exit;
+            },
+            _ => scan_statement(self, statement)
+        }
+    }
+
+    fn expression(&mut self, expression: &'a Expression) {
+        match expression.kind {
+            ExpressionKind::Name(_) => {
+                if let Some(address) = self.variable(expression) {
+                    self.check_init(expression.position, address);
+                }
+            },
+            ExpressionKind::FieldAccess { ref target, .. } => {
+                self.scan_expression(target);
+                if is_simple_name(expression) {
+                    if let Some(address) = self.variable(expression) {
+                        self.check_init(expression.position, address);
+                    }
+                }
+            },
+            ExpressionKind::Assign { operator, ref target, ref value } => {
+                if operator.is_some() || !is_simple_name(target) {
+                    self.scan_expression(target);
+                }
+                self.scan_expression(value);
+                self.let_init_target(target);
+            },
+            ExpressionKind::Unary { operator, ref operand } => match operator {
+                UnaryOperator::Not => {
+                    let conditional = self.condition(operand);
+                    self.conditional = Some(conditional.negate());
+                },
+                UnaryOperator::PreIncrement | UnaryOperator::PreDecrement | UnaryOperator::PostIncrement | UnaryOperator::PostDecrement => {
+                    self.scan_expression(operand);
+                    self.let_init_target(operand);
+                },
+                _ => self.scan_expression(operand)
+            },
+            ExpressionKind::Binary { operator: BinaryOperator::And, ref left, ref right } => {
+                let left = self.condition(left);
+                self.inits = left.inits_true;
+                self.uninits = left.uninits_true;
+                let mut right = self.condition(right);
+                right.inits_false.intersect(&left.inits_false);
+                right.uninits_false.intersect(&left.uninits_false);
+                self.conditional = Some(right);
+            },
+            ExpressionKind::Binary { operator: BinaryOperator::Or, ref left, ref right } => {
+                let left = self.condition(left);
+                self.inits = left.inits_false;
+                self.uninits = left.uninits_false;
+                let mut right = self.condition(right);
+                right.inits_true.intersect(&left.inits_true);
+                right.uninits_true.intersect(&left.uninits_true);
+                self.conditional = Some(right);
+            },
+            ExpressionKind::Conditional { ref condition, ref then, ref otherwise } => {
+                let conditional = self.condition(condition);
+                self.inits = conditional.inits_true;
+                self.uninits = conditional.uninits_true;
+                let boolean = Type::Primitive(PrimitiveType::Boolean);
+                if self.attribution.type_of(then) == Some(&boolean) && self.attribution.type_of(otherwise) == Some(&boolean) {
+                    let when_then = self.condition(then);
+                    self.inits = conditional.inits_false;
+                    self.uninits = conditional.uninits_false;
+                    let mut when_otherwise = self.condition(otherwise);
+                    when_otherwise.inits_true.intersect(&when_then.inits_true);
+                    when_otherwise.uninits_true.intersect(&when_then.uninits_true);
+                    when_otherwise.inits_false.intersect(&when_then.inits_false);
+                    when_otherwise.uninits_false.intersect(&when_then.uninits_false);
+                    self.conditional = Some(when_otherwise);
+                } else {
+                    self.scan_expression(then);
+                    let inits_then = mem::replace(&mut self.inits, conditional.inits_false);
+                    let uninits_then = mem::replace(&mut self.uninits, conditional.uninits_false);
+                    self.scan_expression(otherwise);
+                    self.inits.intersect(&inits_then);
+                    self.uninits.intersect(&uninits_then);
+                }
+            },
+            ExpressionKind::Parenthesized(ref inner) => self.expression(inner),
+            ExpressionKind::InstanceOf { expression: ref operand, ref binding, .. } => {
+                self.scan_expression(operand);
+                if let Some(ref binding) = *binding {
+                    self.declare_parameter(binding);
+                }
+            },
+            ExpressionKind::Switch(ref switch) => self.switch(switch, true),
+            _ => scan_expression(self, expression)
+        }
+    }
+
+    fn operand(&mut self, expression: &'a Expression) {
+        self.scan_expression(expression);
+    }
+
+    // A lambda body may read the variables definitely assigned before it, and its assignments
+    // do not count after it.
+    fn lambda(&mut self, _expression: &'a Expression, parameters: &'a [LambdaParameter], body: &'a LambdaBody) {
+        let (inits, uninits) = (self.inits.clone(), self.uninits.clone());
+        let (next, returns) = (self.variables.len(), self.returns);
+        let exits = mem::take(&mut self.exits);
+        let targets = mem::take(&mut self.targets);
+        self.returns = next;
+        for parameter in parameters.iter() {
+            self.declare_parameter(&parameter.name);
+        }
+        match *body {
+            LambdaBody::Expression(ref expression) => self.scan_expression(expression),
+            LambdaBody::Block(ref block) => self.block_statements(&block.statements)
+        }
+        self.returns = returns;
+        self.inits = inits;
+        self.uninits = uninits;
+        self.exits = exits;
+        self.targets = targets;
+        self.end_scope(next);
+    }
+
+}
+
+// An exception thrown where it is neither caught nor declared, which is reported once the method
+// or initializer it is thrown in has been analyzed.
+struct Uncaught {
+    position: Position,
+    message: String
+}
+
+// Exception checking (JLS §11.2).
+struct Thrown<'a, 'l> {
+    attribution: &'a Attribution,
+    log: &'l mut Log,
+    reassigned: &'l HashSet<usize>,
+    // The checked exceptions the code analyzed so far may throw, and those that are caught or
+    // declared.
+    thrown: Vec<Type>,
+    caught: Vec<Type>,
+    uncaught: Vec<Uncaught>,
+    // The exceptions a final or effectively final catch parameter may rethrow (JLS §11.2.2).
+    rethrown: HashMap<usize, Vec<Type>>
+}
+
+impl<'a, 'l> Thrown<'a, 'l> {
+
+    fn new(attribution: &'a Attribution, log: &'l mut Log, reassigned: &'l HashSet<usize>) -> Thrown<'a, 'l> {
+        Thrown { attribution, log, reassigned, thrown: Vec::new(), caught: Vec::new(), uncaught: Vec::new(), rethrown: HashMap::new() }
+    }
+
+    fn is_subtype(&self, s: &Type, t: &Type) -> bool {
+        self.attribution.symbols.is_subtype(s, t)
+    }
+
+    fn is_unchecked(&self, exception: &Type) -> bool {
+        match *exception {
+            Type::Class(_) | Type::Variable(_) => {
+                self.is_subtype(exception, &Type::class("java/lang/RuntimeException")) || self.is_subtype(exception, &Type::class("java/lang/Error"))
+            },
+            _ => true
+        }
+    }
+
+    // Whether an exception is a subclass of one of the exceptions.
+    fn is_handled(&self, exception: &Type, exceptions: &[Type]) -> bool {
+        exceptions.iter().any(|handled| self.is_subtype(exception, handled))
+    }
+
+    // Adds an exception to a set of exceptions, in which no exception is a subclass of another.
+    fn include(&self, exception: &Type, exceptions: &[Type]) -> Vec<Type> {
+        if self.is_handled(exception, exceptions) {
+            return exceptions.to_vec();
+        }
+        let mut included = self.exclude(exception, exceptions);
+        included.insert(0, exception.clone());
+        included
+    }
+
+    fn exclude(&self, exception: &Type, exceptions: &[Type]) -> Vec<Type> {
+        exceptions.iter().filter(|other| !self.is_subtype(other, exception)).cloned().collect()
+    }
+
+    fn union(&self, a: &[Type], b: &[Type]) -> Vec<Type> {
+        b.iter().fold(a.to_vec(), |union, exception| self.include(exception, &union))
+    }
+
+    fn difference(&self, a: &[Type], b: &[Type]) -> Vec<Type> {
+        b.iter().fold(a.to_vec(), |difference, exception| self.exclude(exception, &difference))
+    }
+
+    fn intersection(&self, a: &[Type], b: &[Type]) -> Vec<Type> {
+        let mut intersection = Vec::new();
+        for exception in a.iter().filter(|exception| self.is_handled(exception, b)) {
+            intersection = self.include(exception, &intersection);
+        }
+        for exception in b.iter().filter(|exception| self.is_handled(exception, a)) {
+            intersection = self.include(exception, &intersection);
+        }
+        intersection
+    }
+
+    fn mark_thrown(&mut self, position: Position, exception: &Type, resource: Option<&str>) {
+        if self.is_unchecked(exception) {
+            return;
+        }
+        if !self.is_handled(exception, &self.caught) {
+            let mut message = format!("unreported exception {}; must be caught or declared to be thrown", exception);
+            if let Some(resource) = resource {
+                message.push_str(&format!("\n  exception thrown from implicit call to close() on resource variable '{}'", resource));
+            }
+            self.uncaught.push(Uncaught { position, message });
+        }
+        self.thrown = self.include(exception, &self.thrown);
+    }
+
+    fn report_uncaught(&mut self) {
+        for uncaught in mem::take(&mut self.uncaught) {
+            self.log.error(uncaught.position, &uncaught.message);
+        }
+    }
+
+    fn invocation_throws<T>(&self, node: &T) -> Vec<Type> {
+        self.attribution.invocation(node).map_or_else(Vec::new, |invocation| invocation.throws.clone())
+    }
+
+    // The exceptions the close() method of a resource throws.
+    fn close_throws(&self, resource_type: &Type) -> Vec<Type> {
+        self.attribution.symbols.find_methods(resource_type, "close").into_iter()
+            .find(|close| close.member.parameters.is_empty())
+            .map_or_else(Vec::new, |close| close.member.throws)
+    }
+
+    fn try_statement(&mut self, resources: &'a [Resource], body: &'a Block, catches: &'a [ast::Catch], finally: Option<&'a Block>) {
+        let caught_outer = self.caught.clone();
+        let thrown_outer = mem::take(&mut self.thrown);
+        for catch in catches.iter() {
+            for exception in self.attribution.caught(catch) {
+                self.caught = self.include(exception, &self.caught);
+            }
+        }
+        let first_uncaught = self.uncaught.len();
+
+        for resource in resources.iter() {
+            match *resource {
+                Resource::Declaration(ref variable) => scan_local_variable(self, variable),
+                Resource::Expression(ref expression) => self.operand(expression)
+            }
+        }
+        for resource in resources.iter() {
+            match *resource {
+                Resource::Declaration(ref variable) => {
+                    for declarator in variable.declarators.iter() {
+                        if let Some(local) = self.attribution.declared_local(&declarator.name) {
+                            for exception in self.close_throws(&self.attribution.local(local).local_type) {
+                                self.mark_thrown(declarator.name.position, &exception, Some(&declarator.name.name));
+                            }
+                        }
+                    }
+                },
+                Resource::Expression(ref expression) => {
+                    if let Some(resource_type) = self.attribution.type_of(expression) {
+                        for exception in self.close_throws(resource_type) {
+                            self.mark_thrown(expression.position, &exception, None);
+                        }
+                    }
+                }
+            }
+        }
+        self.block(body);
+
+        let unchecked = [Type::class("java/lang/RuntimeException"), Type::class("java/lang/Error")];
+        let thrown_in_try = self.union(&self.thrown, &unchecked);
+        self.thrown = thrown_outer;
+        self.caught = caught_outer;
+        let mut caught_in_try = Vec::new();
+        for catch in catches.iter() {
+            let caught = self.attribution.caught(catch);
+            let rethrown = self.difference(&thrown_in_try, &caught_in_try);
+            for (index, exception) in caught.iter().enumerate() {
+                if exception.is_error() {
+                    continue;
+                }
+                // javac reports a multi-catch clause at the alternative.
+                let position = if caught.len() > 1 { type_position(&catch.types[index]) } else { catch.position };
+                if self.is_handled(exception, &caught_in_try) {
+                    self.log.error(position, &format!("exception {} has already been caught", exception));
+                } else if !self.is_unchecked(exception) && !exception.is_class("java/lang/Exception") && !exception.is_class("java/lang/Throwable")
+                    && !thrown_in_try.iter().any(|thrown| self.is_subtype(thrown, exception) || self.is_subtype(exception, thrown)) {
+                    self.log.error(position, &format!("exception {} is never thrown in body of corresponding try statement", exception));
+                }
+                caught_in_try = self.include(exception, &caught_in_try);
+            }
+            let local = self.attribution.declared_local(&catch.name);
+            if let Some(local) = local {
+                let precise = self.intersection(caught, &rethrown);
+                self.rethrown.insert(local, precise);
+            }
+            self.block(&catch.body);
+            if let Some(local) = local {
+                self.rethrown.remove(&local);
+            }
+        }
+
+        let uncaught = self.uncaught.split_off(first_uncaught);
+        let rethrown = self.difference(&thrown_in_try, &caught_in_try);
+        match finally {
+            Some(finally) => {
+                let thrown = mem::take(&mut self.thrown);
+                self.block(finally);
+                // A finally block that cannot complete normally discards the exceptions of the
+                // try block and the catch clauses.
+                if can_complete_normally(finally, self.attribution) {
+                    self.thrown = self.union(&self.thrown, &rethrown);
+                    self.thrown = self.union(&self.thrown, &thrown);
+                    self.uncaught.extend(uncaught);
+                } else {
+                    self.thrown = self.union(&self.thrown, &thrown);
+                }
+            },
+            None => {
+                self.thrown = self.union(&self.thrown, &rethrown);
+                self.uncaught.extend(uncaught);
+            }
+        }
+    }
+
+    fn method(&mut self, member: &'a Member) {
+        let caught = self.caught.clone();
+        let declared = self.attribution.method(member).map_or_else(Vec::new, |method| method.throws.clone());
+        match *member {
+            Member::Method(ref declaration) => {
+                self.caught = declared;
+                if let Some(ref body) = declaration.body {
+                    self.block(body);
+                }
+            },
+            Member::Constructor(ref declaration) => {
+                if is_initial_constructor(declaration) {
+                    self.caught = self.union(&self.caught, &declared);
+                } else {
+                    self.caught = declared;
+                }
+                // The implicit superclass constructor invocation is at the start of the body.
+                for exception in self.invocation_throws(declaration) {
+                    self.mark_thrown(declaration.body.position, &exception, None);
+                }
+                self.block(&declaration.body);
+            },
+            _ => {}
+        }
+        self.caught = caught;
+    }
+
+}
+
+impl<'a, 'l> Scanner<'a> for Thrown<'a, 'l> {
+
+    fn attribution(&self) -> &'a Attribution {
+        self.attribution
+    }
+
+    fn class(&mut self, body: Body<'a>) {
+        let caught = self.caught.clone();
+        let thrown = mem::take(&mut self.thrown);
+        let uncaught = mem::take(&mut self.uncaught);
+        // An anonymous class is part of the code around it: its instance initializers may throw
+        // what that code may.
+        if !body.is_anonymous() {
+            self.caught = Vec::new();
+        }
+
+        for constant in body.enum_constants() {
+            scan_enum_constant(self, constant);
+            for exception in self.invocation_throws(constant) {
+                self.mark_thrown(constant.name.position, &exception, None);
+            }
+            self.report_uncaught();
+        }
+        for member in body.initializers(true) {
+            scan_initializer(self, body, member);
+            self.report_uncaught();
+        }
+        // Instance initializers may throw what every initial constructor declares.
+        if !body.is_anonymous() {
+            let mut constructors = body.members.iter().filter_map(|member| match *member {
+                Member::Constructor(ref declaration) if is_initial_constructor(declaration) => {
+                    Some(self.attribution.method(member).map_or_else(Vec::new, |method| method.throws.clone()))
+                },
+                _ => None
+            });
+            if let Some(first) = constructors.next() {
+                let declared = constructors.fold(first, |declared, other| self.intersection(&other, &declared));
+                self.caught = declared;
+            }
+        }
+        for member in body.initializers(false) {
+            scan_initializer(self, body, member);
+            self.report_uncaught();
+        }
+        let thrown_outer = if body.is_anonymous() { self.union(&self.thrown, &thrown) } else { thrown };
+
+        if let Some(declaration) = body.declaration {
+            if body.has_default_constructor() {
+                for exception in self.invocation_throws(declaration) {
+                    if !self.is_unchecked(&exception) {
+                        let message = format!("unreported exception {} in default constructor", exception);
+                        self.uncaught.push(Uncaught { position: declaration.position, message });
+                    }
+                }
+                self.report_uncaught();
+            }
+        }
+        for member in body.methods() {
+            self.method(member);
+            self.report_uncaught();
+        }
+
+        self.thrown = thrown_outer;
+        self.caught = caught;
+        self.uncaught = uncaught;
+    }
+
+    fn statement(&mut self, statement: &'a Statement) {
+        match statement.kind {
+            StatementKind::Throw(ref exception) => {
+                self.operand(exception);
+                let precise = match (&strip_parentheses(exception).kind, self.attribution.reference(exception)) {
+                    (&ExpressionKind::Name(_), Some(&Reference::Local(local))) => {
+                        let effectively_final = self.attribution.local(local).is_final || !self.reassigned.contains(&local);
+                        if effectively_final { self.rethrown.get(&local).cloned() } else { None }
+                    },
+                    _ => None
+                };
+                match precise {
+                    Some(exceptions) => {
+                        for exception in exceptions.iter() {
+                            self.mark_thrown(statement.position, exception, None);
+                        }
+                    },
+                    None => {
+                        if let Some(exception) = self.attribution.type_of(exception) {
+                            self.mark_thrown(statement.position, exception, None);
+                        }
+                    }
+                }
+            },
+            StatementKind::Try { ref resources, ref body, ref catches, ref finally } => self.try_statement(resources, body, catches, finally.as_ref()),
+            StatementKind::ConstructorInvocation { .. } => {
+                scan_statement(self, statement);
+                for exception in self.invocation_throws(statement) {
+                    self.mark_thrown(statement.position, &exception, None);
+                }
+            },
+            _ => scan_statement(self, statement)
+        }
+    }
+
+    fn expression(&mut self, expression: &'a Expression) {
+        match expression.kind {
+            ExpressionKind::MethodCall { arguments_position, .. } | ExpressionKind::SuperMethodCall { arguments_position, .. } => {
+                scan_expression(self, expression);
+                for exception in self.invocation_throws(expression) {
+                    self.mark_thrown(arguments_position, &exception, None);
+                }
+            },
+            ExpressionKind::New { ref outer, ref arguments, ref body, .. } => {
+                if let Some(ref outer) = *outer {
+                    self.operand(outer);
+                }
+                for argument in arguments.iter() {
+                    self.operand(argument);
+                }
+                let throws = self.invocation_throws(expression);
+                for exception in throws.iter() {
+                    self.mark_thrown(expression.position, exception, None);
+                }
+                // The constructor of an anonymous class may throw what the superclass
+                // constructor does.
+                if let Some(ref members) = *body {
+                    let caught = self.caught.clone();
+                    for exception in throws.iter() {
+                        self.caught = self.include(exception, &self.caught);
+                    }
+                    if let Some(body) = Body::anonymous(expression, members, self.attribution) {
+                        self.class(body);
+                    }
+                    self.caught = caught;
+                }
+            },
+            _ => scan_expression(self, expression)
+        }
+    }
+
+    // A lambda body may throw what the function type of the lambda expression declares.
+    fn lambda(&mut self, expression: &'a Expression, parameters: &'a [LambdaParameter], body: &'a LambdaBody) {
+        let declared = self.attribution.function(expression).map_or_else(Vec::new, |function| function.function.throws.clone());
+        let caught = mem::replace(&mut self.caught, declared);
+        let thrown = mem::take(&mut self.thrown);
+        let uncaught = mem::take(&mut self.uncaught);
+        scan_lambda(self, parameters, body);
+        self.report_uncaught();
+        self.caught = caught;
+        self.thrown = thrown;
+        self.uncaught = uncaught;
+    }
+
+}
+
+// A lambda body or a class body, with the local variables declared in it.
+struct Frame {
+    lambda: bool,
+    // Whether the variables of enclosing blocks may be referred to.
+    captures: bool,
+    locals: HashSet<usize>
+}
+
+// The local variables referred to from lambda bodies and inner classes must be final or
+// effectively final (JLS §15.27.2, §8.1.3).
+struct Capture<'a, 'l> {
+    attribution: &'a Attribution,
+    log: &'l mut Log,
+    reassigned: &'l HashSet<usize>,
+    frames: Vec<Frame>
+}
+
+impl<'a, 'l> Capture<'a, 'l> {
+
+    fn new(attribution: &'a Attribution, log: &'l mut Log, reassigned: &'l HashSet<usize>) -> Capture<'a, 'l> {
+        Capture { attribution, log, reassigned, frames: Vec::new() }
+    }
+
+    // The innermost lambda body or local class, when a local variable is declared outside it.
+    fn captured(&self, expression: &Expression) -> Option<(usize, bool)> {
+        let local = match (&expression.kind, self.attribution.reference(expression)) {
+            (&ExpressionKind::Name(_), Some(&Reference::Local(local))) => local,
+            _ => return None
+        };
+        let frame = self.frames.last()?;
+        if frame.captures && !frame.locals.contains(&local) { Some((local, frame.lambda)) } else { None }
+    }
+
+    fn report(&mut self, position: Position, lambda: bool) {
+        let from = if lambda { "a lambda expression" } else { "an inner class" };
+        self.log.error(position, &format!("local variables referenced from {} must be final or effectively final", from));
+    }
+
+    fn let_init(&mut self, target: &'a Expression) {
+        let target = strip_parentheses(target);
+        if let Some((_, lambda)) = self.captured(target) {
+            self.report(target.position, lambda);
+        }
+    }
+
+}
+
+impl<'a, 'l> Scanner<'a> for Capture<'a, 'l> {
+
+    fn attribution(&self) -> &'a Attribution {
+        self.attribution
+    }
+
+    fn class(&mut self, body: Body<'a>) {
+        self.frames.push(Frame { lambda: false, captures: body.local, locals: HashSet::new() });
+        scan_class(self, body);
+        self.frames.pop();
+    }
+
+    fn expression(&mut self, expression: &'a Expression) {
+        match expression.kind {
+            ExpressionKind::Name(_) => {
+                if let Some((local, lambda)) = self.captured(expression) {
+                    if !self.attribution.local(local).is_final && self.reassigned.contains(&local) {
+                        self.report(expression.position, lambda);
+                    }
+                }
+            },
+            ExpressionKind::Assign { operator, ref target, ref value } => {
+                if operator.is_some() || !matches!(strip_parentheses(target).kind, ExpressionKind::Name(_)) {
+                    self.operand(target);
+                }
+                self.operand(value);
+                self.let_init(target);
+            },
+            ExpressionKind::Unary { operator: UnaryOperator::PreIncrement | UnaryOperator::PreDecrement
+                | UnaryOperator::PostIncrement | UnaryOperator::PostDecrement, ref operand } => {
+                self.operand(operand);
+                self.let_init(operand);
+            },
+            _ => scan_expression(self, expression)
+        }
+    }
+
+    fn lambda(&mut self, _expression: &'a Expression, parameters: &'a [LambdaParameter], body: &'a LambdaBody) {
+        self.frames.push(Frame { lambda: true, captures: true, locals: HashSet::new() });
+        scan_lambda(self, parameters, body);
+        self.frames.pop();
+    }
+
+    fn declare(&mut self, name: &'a Identifier) {
+        if let (Some(local), Some(frame)) = (self.attribution.declared_local(name), self.frames.last_mut()) {
+            frame.locals.insert(local);
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use compiler::attr::tests::errors;
+
+    #[test]
+    fn reports_unreachable_code_and_missing_returns() {
+        let source = "class A {\n  int g() { }\n  void h() { return; int dead = 0; }\n  int k() { while (true) {} }\n}";
+        let errors = errors(source);
+        assert_eq!(errors, vec!["2:13: missing return statement", "3:26: unreachable statement"]);
+    }
+
+    #[test]
+    fn checks_definite_assignment() {
+        let source = "class A {\n  final int x;\n  A() { }\n  int f(boolean b) {\n    int y;\n    if (b) y = 1;\n    return y;\n  }\n}";
+        let errors = errors(source);
+        assert_eq!(errors, vec!["3:9: variable x might not have been initialized", "7:12: variable y might not have been initialized"]);
+    }
+
+    #[test]
+    fn checks_exceptions_and_effectively_final_locals() {
+        let source = "class A {\n  void f() { throw new Exception(); }\n  void g() { int i = 0; i++; Runnable r = () -> System.out.println(i); }\n}";
+        let errors = errors(source);
+        assert_eq!(errors, vec![
+            "2:14: unreported exception Exception; must be caught or declared to be thrown",
+            "3:68: local variables referenced from a lambda expression must be final or effectively final"
+        ]);
+    }
+
+}
@@ -0,0 +1,2205 @@
+// Code generation: each attributed class is turned into a class file. Like javac, expressions
+// are compiled straight to instructions, conditions to jumps, string concatenation to
+// StringBuilder calls, and field initializers into the constructors and <clinit>.
+//
+// Only part of the language is supported yet. Anything else is reported as an error, and no
+// class file is generated for the compilation unit.
+
+use class::{class_flags, field, method, Attribute, ClassFile, Field, Method};
+use class::pool::ConstantPoolBuilder;
+use class::reader::MAGIC_NUMBER;
+use class::writer;
+use code::instruction::Instruction;
+use compiler::{Diagnostic, Position};
+use compiler::ast::{BinaryOperator, Block, CompilationUnit, Expression, ExpressionKind, Identifier, Literal, Member, Parameter,
+                    PrimitiveType, Statement, StatementKind, TypeDeclaration, TypeKind, UnaryOperator, VariableDeclarator};
+use compiler::attr::{Attribution, Invocation, Reference};
+use compiler::code::{Code, Label, VerificationType};
+use compiler::constant::{self, Constant};
+use compiler::symbols::{ClassSymbol, FieldSymbol, MethodSymbol};
+use compiler::types::{Type, TypeVariable, OBJECT};
+use std::collections::{BTreeSet, HashMap};
+use std::mem;
+use std::path::Path;
+use std::rc::Rc;
+
+// Java 17 class files.
+const MAJOR_VERSION: u16 = 61;
+
+const STRING_BUILDER: &str = "java/lang/StringBuilder";
+const ITERATOR: &str = "java/util/Iterator";
+
+// A generated class file.
+#[derive(Debug)]
+pub struct GeneratedClass {
+    pub name: String,
+    pub bytes: Vec<u8>,
+    // The constant fields of other classes, by class and name, whose values the code uses. Unlike
+    // other members they leave no trace in the class file.
+    pub constants: BTreeSet<(String, String)>
+}
+
+// Generates the class files of the top-level classes of a compilation unit.
+pub fn generate(file: &str, unit: &CompilationUnit, attribution: &Attribution) -> Result<Vec<GeneratedClass>, Vec<Diagnostic>> {
+    let mut classes = Vec::new();
+    let mut diagnostics = Vec::new();
+
+    for declaration in unit.types.iter() {
+        let symbol = match attribution.class_name(declaration).and_then(|name| attribution.symbols.class(name)) {
+            Some(symbol) => symbol,
+            None => continue
+        };
+        let mut gen = Gen::new(file, attribution, symbol, &declaration.members);
+        let class_file = gen.class(declaration);
+        if !gen.diagnostics.is_empty() {
+            diagnostics.extend(gen.diagnostics);
+            continue;
+        }
+        match writer::write_class_file(&class_file) {
+            Ok(bytes) => classes.push(GeneratedClass { name: gen.class.name.clone(), bytes, constants: gen.constants }),
+            Err(e) => diagnostics.push(Diagnostic::new(file, declaration.position, &format!("could not write class file: {:?}", e)))
+        }
+    }
+
+    if diagnostics.is_empty() {
+        Ok(classes)
+    } else {
+        Err(diagnostics)
+    }
+}
+
+// Where a break or continue statement jumps to: the end of a statement, and for loops the code
+// that starts the next iteration.
+struct Target {
+    label: Option<String>,
+    is_loop: bool,
+    exit: Label,
+    next: Option<Label>,
+    // The first local variable slot that is not in scope at the target.
+    scope: usize
+}
+
+// A variable being assigned to. Fields and array elements need their object, or their array and
+// index, on the stack first.
+enum Variable {
+    Local(usize),
+    Static(String, FieldSymbol),
+    Field(String, FieldSymbol),
+    Element
+}
+
+// The variable with the type as declared, which is what instructions use, and the type it has
+// where it is used, which may be more specific for a generic field.
+struct Access {
+    variable: Variable,
+    declared: Type,
+    used: Type
+}
+
+impl Access {
+
+    // The number of stack slots the object, or the array and index, take.
+    fn receiver_slots(&self) -> usize {
+        match self.variable {
+            Variable::Local(_) | Variable::Static(..) => 0,
+            Variable::Field(..) => 1,
+            Variable::Element => 2
+        }
+    }
+
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Invoke {
+    Virtual,
+    Special,
+    Static
+}
+
+struct Gen<'a> {
+    file: &'a str,
+    attribution: &'a Attribution,
+    class: Rc<ClassSymbol>,
+    members: &'a [Member],
+    pool: ConstantPoolBuilder,
+    diagnostics: Vec<Diagnostic>,
+    // The method being generated: its code, the slots of its local variables by index into the
+    // locals of the attribution, and the statements break and continue may jump to.
+    code: Code,
+    slots: HashMap<usize, usize>,
+    next_slot: usize,
+    targets: Vec<Target>,
+    return_type: Type,
+    // The label of a labeled loop, until the loop takes it.
+    pending_label: Option<String>,
+    constants: BTreeSet<(String, String)>
+}
+
+impl<'a> Gen<'a> {
+
+    fn new(file: &'a str, attribution: &'a Attribution, class: Rc<ClassSymbol>, members: &'a [Member]) -> Gen<'a> {
+        Gen {
+            file,
+            attribution,
+            class,
+            members,
+            pool: ConstantPoolBuilder::new(),
+            diagnostics: Vec::new(),
+            code: Code::new(Vec::new()),
+            slots: HashMap::new(),
+            next_slot: 0,
+            targets: Vec::new(),
+            return_type: Type::Void,
+            pending_label: None,
+            constants: BTreeSet::new()
+        }
+    }
+
+    fn unsupported(&mut self, position: Position, what: &str) {
+        let message = format!("{} are not supported yet", what);
+        self.diagnostics.push(Diagnostic::new(self.file, position, &message));
+    }
+
+    fn error(&mut self, position: Position, message: &str) {
+        self.diagnostics.push(Diagnostic::new(self.file, position, message));
+    }
+
+    // The constant value of an expression, noting the constant fields of other classes it uses.
+    fn constant_of(&mut self, expression: &Expression) -> Option<&'a Constant> {
+        let value = self.attribution.constant(expression)?;
+        self.note_constants(expression);
+        Some(value)
+    }
+
+    fn note_constants(&mut self, expression: &Expression) {
+        match expression.kind {
+            ExpressionKind::Name(_) | ExpressionKind::FieldAccess { .. } => {
+                if let Some(Reference::Field(symbol, _)) = self.attribution.reference(expression) {
+                    if symbol.owner != self.class.name {
+                        self.constants.insert((symbol.owner.clone(), symbol.name.clone()));
+                    }
+                }
+            },
+            ExpressionKind::Parenthesized(ref operand) | ExpressionKind::Unary { ref operand, .. } | ExpressionKind::Cast { expression: ref operand, .. } => {
+                self.note_constants(operand);
+            },
+            ExpressionKind::Binary { ref left, ref right, .. } => {
+                self.note_constants(left);
+                self.note_constants(right);
+            },
+            ExpressionKind::Conditional { ref condition, ref then, ref otherwise } => {
+                self.note_constants(condition);
+                self.note_constants(then);
+                self.note_constants(otherwise);
+            },
+            _ => {}
+        }
+    }
+
+    fn type_of(&self, expression: &Expression) -> Type {
+        self.attribution.type_of(expression).cloned().unwrap_or(Type::Error)
+    }
+
+    fn super_class(&self) -> String {
+        self.class.super_class.as_ref().and_then(|super_class| super_class.erasure().class_name().map(String::from)).unwrap_or_else(|| String::from(OBJECT))
+    }
+
+    fn is_interface(&self, class: &str) -> bool {
+        self.attribution.symbols.class(class).is_some_and(|symbol| symbol.is_interface())
+    }
+
+}
+
+// Classes, fields and methods.
+impl<'a> Gen<'a> {
+
+    fn class(&mut self, declaration: &'a TypeDeclaration) -> ClassFile {
+        match declaration.kind {
+            TypeKind::Class | TypeKind::Interface => {},
+            TypeKind::Enum => self.unsupported(declaration.position, "enum classes"),
+            TypeKind::Record => self.unsupported(declaration.position, "record classes"),
+            TypeKind::Annotation => self.unsupported(declaration.position, "annotation interfaces")
+        }
+
+        let mut fields = Vec::new();
+        let mut methods = Vec::new();
+        let has_constructor = self.members.iter().any(|member| matches!(*member, Member::Constructor(_)));
+        if declaration.kind == TypeKind::Class && !has_constructor {
+            methods.push(self.default_constructor(declaration));
+        }
+
+        for member in self.members.iter() {
+            match *member {
+                Member::Field(ref declaration) => {
+                    for declarator in declaration.declarators.iter() {
+                        fields.push(self.field(declarator));
+                    }
+                },
+                Member::Method(ref declaration) => {
+                    if let Some(symbol) = self.attribution.method(declaration).cloned() {
+                        let method = self.method(&symbol, &declaration.parameters, declaration.body.as_ref(), declaration.name.position);
+                        methods.push(method);
+                    }
+                },
+                Member::Constructor(ref declaration) => {
+                    if let Some(symbol) = self.attribution.method(declaration).cloned() {
+                        let parameters = declaration.parameters.as_ref().map_or(&[][..], Vec::as_slice);
+                        let invocation = self.attribution.invocation(declaration).cloned();
+                        let method = self.constructor(&symbol, parameters, &declaration.body, invocation, declaration.name.position);
+                        methods.push(method);
+                    }
+                },
+                Member::Initializer { .. } => {},
+                Member::Type(ref declaration) => self.unsupported(declaration.position, "member classes")
+            }
+        }
+
+        let has_static_initializer = self.members.iter().any(|member| match *member {
+            Member::Field(ref declaration) => declaration.declarators.iter().any(|declarator| self.is_static_initialized(declarator)),
+            Member::Initializer { is_static, .. } => is_static,
+            _ => false
+        });
+        if has_static_initializer {
+            methods.push(self.class_initializer());
+        }
+
+        let mut access_flags = self.class.access_flags & !(class_flags::ACC_PRIVATE | class_flags::ACC_PROTECTED | class_flags::ACC_STATIC);
+        if declaration.kind == TypeKind::Class {
+            access_flags |= class_flags::ACC_SUPER;
+        }
+        let this_class = self.pool.class(&self.class.name);
+        let super_class = self.super_class();
+        let super_class = self.pool.class(&super_class);
+        let interfaces: Vec<String> = self.class.interfaces.iter().filter_map(|interface| interface.erasure().class_name().map(String::from)).collect();
+        let interfaces = interfaces.iter().map(|interface| self.pool.class(interface)).collect();
+
+        let mut attributes = Vec::new();
+        let mut signature = self.type_parameters_signature(&self.class.type_parameters);
+        let supertypes = self.class.super_class.iter().chain(self.class.interfaces.iter());
+        let generic = !signature.is_empty() || supertypes.clone().any(|supertype| supertype.signature() != supertype.descriptor());
+        for supertype in supertypes {
+            signature.push_str(&supertype.signature());
+        }
+        if generic {
+            if declaration.kind == TypeKind::Interface && self.class.super_class.is_none() {
+                signature.insert_str(signature.find('>').map_or(0, |end| end + 1), "Ljava/lang/Object;");
+            }
+            attributes.push(self.signature(&signature));
+        }
+
+        let source_file = Path::new(self.file).file_name().map_or_else(|| String::from(self.file), |name| name.to_string_lossy().into_owned());
+        self.pool.utf8("SourceFile");
+        attributes.push(Attribute::SourceFile { index: self.pool.utf8(&source_file) });
+
+        let pool = mem::take(&mut self.pool);
+        ClassFile {
+            magic: MAGIC_NUMBER,
+            minor_version: 0,
+            major_version: MAJOR_VERSION,
+            constant_pool: pool.build(),
+            access_flags,
+            this_class,
+            super_class,
+            interfaces,
+            fields,
+            methods,
+            attributes
+        }
+    }
+
+    fn signature(&mut self, signature: &str) -> Attribute {
+        self.pool.utf8("Signature");
+        Attribute::Signature { index: self.pool.utf8(signature) }
+    }
+
+    // The type parameters of a generic class or method in a signature, e.g.
+    // <T:Ljava/lang/Object;U::Ljava/lang/Comparable<TU;>;>. A bound that is an interface follows
+    // an empty class bound.
+    fn type_parameters_signature(&self, type_parameters: &[TypeVariable]) -> String {
+        if type_parameters.is_empty() {
+            return String::new();
+        }
+        let mut signature = String::from("<");
+        for variable in type_parameters.iter() {
+            signature.push_str(&variable.name);
+            let bounds = variable.bounds.borrow();
+            if bounds.is_empty() {
+                signature.push_str(":Ljava/lang/Object;");
+            }
+            for (i, bound) in bounds.iter().enumerate() {
+                let is_interface = bound.class_name().is_some_and(|class| self.is_interface(class));
+                if i == 0 && is_interface {
+                    signature.push(':');
+                }
+                signature.push(':');
+                signature.push_str(&bound.signature());
+            }
+        }
+        signature.push('>');
+        signature
+    }
+
+    fn field_symbol(&self, name: &str) -> Option<&FieldSymbol> {
+        self.class.fields.iter().find(|field| field.name == name)
+    }
+
+    // Static final fields with constant values are initialized from their ConstantValue
+    // attributes rather than in <clinit>.
+    fn is_static_initialized(&self, declarator: &VariableDeclarator) -> bool {
+        declarator.initializer.is_some() && self.field_symbol(&declarator.name.name)
+            .is_some_and(|symbol| symbol.is_static() && !(symbol.is_final() && symbol.constant.is_some()))
+    }
+
+    fn field(&mut self, declarator: &VariableDeclarator) -> Field {
+        let symbol = match self.field_symbol(&declarator.name.name) {
+            Some(symbol) => symbol.clone(),
+            None => return Field { access_flags: 0, name_index: 0, descriptor_index: 0, attributes: Vec::new() }
+        };
+
+        let mut attributes = Vec::new();
+        if let (true, Some(value)) = (symbol.is_final(), symbol.constant.as_ref()) {
+            if let Some(ref initializer) = declarator.initializer {
+                self.note_constants(initializer);
+            }
+            let index = match *value {
+                Constant::Int(value) => self.pool.integer(value),
+                Constant::Boolean(value) => self.pool.integer(value as i32),
+                Constant::Long(value) => self.pool.long(value),
+                Constant::Float(value) => self.pool.float(value),
+                Constant::Double(value) => self.pool.double(value),
+                Constant::String(ref chars) => self.pool.string(chars)
+            };
+            self.pool.utf8("ConstantValue");
+            attributes.push(Attribute::ConstantValue { index });
+        }
+        let signature = symbol.field_type.signature();
+        if signature != symbol.field_type.descriptor() {
+            attributes.push(self.signature(&signature));
+        }
+
+        Field {
+            access_flags: symbol.access_flags & (field::ACC_PUBLIC | field::ACC_PRIVATE | field::ACC_PROTECTED | field::ACC_STATIC | field::ACC_FINAL
+                                                 | field::ACC_VOLATILE | field::ACC_TRANSIENT),
+            name_index: self.pool.utf8(&symbol.name),
+            descriptor_index: self.pool.utf8(&symbol.field_type.descriptor()),
+            attributes
+        }
+    }
+
+    // Starts the code of a method, with this and the parameters in the first local variable
+    // slots.
+    fn begin(&mut self, this: Option<VerificationType>, parameters: &[Parameter], return_type: Type) {
+        let mut types = Vec::new();
+        self.slots.clear();
+        self.targets.clear();
+        self.next_slot = 0;
+        if let Some(this) = this {
+            types.push(this);
+            self.next_slot = 1;
+        }
+        for parameter in parameters.iter() {
+            if let Some(index) = self.attribution.declared_local(&parameter.name) {
+                let parameter_type = self.attribution.local(index).local_type.clone();
+                types.push(verification_type(&parameter_type));
+                self.slots.insert(index, self.next_slot);
+                self.next_slot += parameter_type.width();
+            }
+        }
+        self.return_type = return_type;
+        self.code = Code::new(types);
+    }
+
+    fn finish(&mut self, symbol: &MethodSymbol, position: Position) -> Method {
+        let mut attributes = Vec::new();
+        if symbol.access_flags & (method::ACC_ABSTRACT | method::ACC_NATIVE) == 0 {
+            let code = mem::replace(&mut self.code, Code::new(Vec::new()));
+            match code.finish(&mut self.pool) {
+                Ok(code) => attributes.push(code),
+                Err(message) => self.error(position, &message)
+            }
+        }
+        if !symbol.throws.is_empty() {
+            let exceptions: Vec<String> = symbol.throws.iter().filter_map(|thrown| thrown.erasure().class_name().map(String::from)).collect();
+            let exception_index = exceptions.iter().map(|exception| self.pool.class(exception)).collect();
+            self.pool.utf8("Exceptions");
+            attributes.push(Attribute::Exceptions { exception_index });
+        }
+        let mut signature = self.type_parameters_signature(&symbol.type_parameters);
+        signature.push('(');
+        for parameter in symbol.parameters.iter() {
+            signature.push_str(&parameter.signature());
+        }
+        signature.push(')');
+        signature.push_str(&symbol.return_type.signature());
+        // Like javac, the thrown types are only given when one is a type variable.
+        if symbol.throws.iter().any(|thrown| matches!(*thrown, Type::Variable(_))) {
+            for thrown in symbol.throws.iter() {
+                signature.push('^');
+                signature.push_str(&thrown.signature());
+            }
+        }
+        if signature != symbol.descriptor() {
+            attributes.push(self.signature(&signature));
+        }
+
+        Method {
+            access_flags: symbol.access_flags,
+            name_index: self.pool.utf8(&symbol.name),
+            descriptor_index: self.pool.utf8(&symbol.descriptor()),
+            attributes
+        }
+    }
+
+    fn method(&mut self, symbol: &MethodSymbol, parameters: &[Parameter], body: Option<&Block>, position: Position) -> Method {
+        let this = if symbol.is_static() { None } else { Some(VerificationType::Object(self.class.name.clone())) };
+        self.begin(this, parameters, symbol.return_type.clone());
+        if let Some(body) = body {
+            self.statements(&body.statements);
+            if self.code.is_alive() {
+                self.code.line(body.end.line);
+                self.code.emit_exit(Instruction::Return);
+            }
+        }
+        self.finish(symbol, position)
+    }
+
+    fn default_constructor(&mut self, declaration: &TypeDeclaration) -> Method {
+        let symbol = self.class.methods.iter().find(|method| method.is_constructor()).cloned()
+            .unwrap_or_else(|| MethodSymbol {
+                owner: self.class.name.clone(),
+                name: String::from("<init>"),
+                type_parameters: Vec::new(),
+                parameters: Vec::new(),
+                return_type: Type::Void,
+                throws: Vec::new(),
+                access_flags: self.class.access_flags & method::ACC_PUBLIC
+            });
+        let invocation = self.attribution.invocation(declaration).cloned();
+
+        self.begin(Some(VerificationType::UninitializedThis), &[], Type::Void);
+        self.code.line(declaration.position.line);
+        self.super_constructor_call(invocation, &[], true, declaration.position);
+        self.code.emit_exit(Instruction::Return);
+        self.finish(&symbol, declaration.position)
+    }
+
+    fn constructor(&mut self, symbol: &MethodSymbol, parameters: &[Parameter], body: &Block, invocation: Option<Invocation>, position: Position) -> Method {
+        self.begin(Some(VerificationType::UninitializedThis), parameters, Type::Void);
+        self.code.line(position.line);
+        let explicit = body.statements.first().is_some_and(|first| matches!(first.kind, StatementKind::ConstructorInvocation { .. }));
+        if !explicit {
+            self.super_constructor_call(invocation, &[], true, position);
+        }
+        self.statements(&body.statements);
+        if self.code.is_alive() {
+            self.code.line(body.end.line);
+            self.code.emit_exit(Instruction::Return);
+        }
+        self.finish(symbol, position)
+    }
+
+    // this(...) or super(...), after which a superclass constructor call runs the initializers
+    // of the instance fields and the instance initializers.
+    fn super_constructor_call(&mut self, invocation: Option<Invocation>, arguments: &[Expression], is_super: bool, position: Position) {
+        let invocation = match invocation {
+            Some(invocation) => invocation,
+            None => return
+        };
+        let owner = if is_super { self.super_class() } else { self.class.name.clone() };
+
+        self.code.emit(Instruction::Aload0, 0, Some(VerificationType::UninitializedThis));
+        self.arguments(arguments, &invocation, position);
+        self.invoke(Invoke::Special, &owner, &invocation.method);
+        let name = self.class.name.clone();
+        self.code.initialize(&VerificationType::UninitializedThis, &name);
+
+        if is_super {
+            self.initializers(false);
+        }
+    }
+
+    // The initializers of the instance or static fields and the initializer blocks, in the
+    // order they are in the class.
+    fn initializers(&mut self, is_static: bool) {
+        let members = self.members;
+        for member in members.iter() {
+            match *member {
+                Member::Field(ref declaration) => {
+                    for declarator in declaration.declarators.iter() {
+                        let symbol = match self.field_symbol(&declarator.name.name) {
+                            Some(symbol) if symbol.is_static() == is_static => symbol.clone(),
+                            _ => continue
+                        };
+                        let initializer = match declarator.initializer {
+                            Some(ref initializer) if !is_static || self.is_static_initialized(declarator) => initializer,
+                            _ => continue
+                        };
+
+                        self.code.line(declarator.name.position.line);
+                        if !is_static {
+                            self.code.emit(Instruction::Aload0, 0, Some(VerificationType::Object(self.class.name.clone())));
+                        }
+                        self.initializer(initializer, &symbol.field_type);
+                        let owner = self.class.name.clone();
+                        let access = Access {
+                            variable: if is_static { Variable::Static(owner, symbol.clone()) } else { Variable::Field(owner, symbol.clone()) },
+                            declared: symbol.field_type.clone(),
+                            used: symbol.field_type.clone()
+                        };
+                        self.store(&access);
+                    }
+                },
+                Member::Initializer { is_static: initializer_is_static, ref body } if initializer_is_static == is_static => self.block(body),
+                _ => {}
+            }
+        }
+    }
+
+    fn class_initializer(&mut self) -> Method {
+        let symbol = MethodSymbol {
+            owner: self.class.name.clone(),
+            name: String::from("<clinit>"),
+            type_parameters: Vec::new(),
+            parameters: Vec::new(),
+            return_type: Type::Void,
+            throws: Vec::new(),
+            access_flags: method::ACC_STATIC
+        };
+
+        self.begin(None, &[], Type::Void);
+        self.initializers(true);
+        self.code.emit_exit(Instruction::Return);
+        let position = self.members.first().map_or(Position::new(1, 1), |_| Position::new(1, 1));
+        self.finish(&symbol, position)
+    }
+
+}
+
+// Statements.
+impl<'a> Gen<'a> {
+
+    fn statements(&mut self, statements: &[Statement]) {
+        for statement in statements.iter() {
+            self.statement(statement);
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        let scope = self.next_slot;
+        self.statements(&block.statements);
+        self.end_scope(scope);
+    }
+
+    fn end_scope(&mut self, scope: usize) {
+        self.code.end_scope(scope);
+        self.next_slot = scope;
+    }
+
+    // Gives a local variable the next free slot.
+    fn declare(&mut self, name: &Identifier) -> Option<(usize, Type)> {
+        let index = self.attribution.declared_local(name)?;
+        let local_type = self.attribution.local(index).local_type.clone();
+        let slot = self.temporary(&local_type, name.position);
+        self.slots.insert(index, slot);
+        Some((slot, local_type))
+    }
+
+    // A local variable slot for a value the code keeps, e.g. the index of an enhanced for loop.
+    fn temporary(&mut self, value_type: &Type, position: Position) -> usize {
+        let slot = self.next_slot;
+        self.next_slot += value_type.width();
+        if self.next_slot > 256 {
+            self.unsupported(position, "methods with more than 256 local variable slots");
+        }
+        slot
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        // Like javac, unreachable code, e.g. after an infinite loop, isn't generated.
+        if !self.code.is_alive() {
+            return;
+        }
+        self.code.line(statement.position.line);
+
+        let label = self.pending_label.take();
+        match statement.kind {
+            StatementKind::Block(ref block) => self.block(block),
+            StatementKind::LocalVariable(ref declaration) => {
+                for declarator in declaration.declarators.iter() {
+                    let (slot, local_type) = match self.declare(&declarator.name) {
+                        Some(local) => local,
+                        None => continue
+                    };
+                    if let Some(ref initializer) = declarator.initializer {
+                        self.initializer(initializer, &local_type);
+                        self.store_local(slot, &local_type);
+                    }
+                }
+            },
+            StatementKind::Expression(ref expression) => self.effect(expression),
+            StatementKind::Empty => {},
+            StatementKind::If { ref condition, ref then, ref otherwise } => {
+                let otherwise_label = self.code.new_label();
+                self.condition(condition, false, otherwise_label);
+                self.statement(then);
+                match *otherwise {
+                    Some(ref otherwise) => {
+                        let end = self.code.new_label();
+                        self.code.jump(Instruction::Goto { branch_offset: 0 }, 0, end);
+                        self.code.bind(otherwise_label);
+                        self.statement(otherwise);
+                        self.code.bind(end);
+                    },
+                    None => self.code.bind(otherwise_label)
+                }
+            },
+            StatementKind::While { ref condition, ref body } => self.in_loop(label, &[], Some(condition), &[], body, true),
+            StatementKind::DoWhile { ref body, ref condition } => self.in_loop(label, &[], Some(condition), &[], body, false),
+            StatementKind::For { ref init, ref condition, ref update, ref body } => self.in_loop(label, init, condition.as_ref(), update, body, true),
+            StatementKind::ForEach { ref variable, ref iterable, ref body } => {
+                if let Some(declarator) = variable.declarators.first() {
+                    self.for_each(label, &declarator.name, iterable, body);
+                }
+            },
+            StatementKind::Labeled { ref label, ref body } => {
+                let is_loop = matches!(body.kind, StatementKind::While { .. } | StatementKind::DoWhile { .. } | StatementKind::For { .. } | StatementKind::ForEach { .. });
+                if is_loop {
+                    self.pending_label = Some(label.name.clone());
+                    self.statement(body);
+                } else {
+                    let exit = self.code.new_label();
+                    self.targets.push(Target { label: Some(label.name.clone()), is_loop: false, exit, next: None, scope: self.next_slot });
+                    self.statement(body);
+                    self.targets.pop();
+                    self.code.bind(exit);
+                }
+            },
+            StatementKind::Break(ref label) => {
+                let exit = self.target(label.as_ref(), false).map(|target| target.exit);
+                if let Some(exit) = exit {
+                    self.code.jump(Instruction::Goto { branch_offset: 0 }, 0, exit);
+                }
+            },
+            StatementKind::Continue(ref label) => {
+                let next = self.target(label.as_ref(), true).and_then(|target| target.next);
+                if let Some(next) = next {
+                    self.code.jump(Instruction::Goto { branch_offset: 0 }, 0, next);
+                }
+            },
+            StatementKind::Return(ref value) => {
+                let return_type = self.return_type.clone();
+                if let Some(ref value) = *value {
+                    self.value_as(value, &return_type);
+                }
+                self.code.emit_exit(return_instruction(&return_type));
+            },
+            StatementKind::Throw(ref exception) => {
+                self.expression(exception);
+                self.code.emit_exit(Instruction::Athrow);
+            },
+            StatementKind::ConstructorInvocation { is_super, ref qualifier, ref arguments, .. } => {
+                if qualifier.is_some() {
+                    self.unsupported(statement.position, "qualified superclass constructor invocations");
+                }
+                let invocation = self.attribution.invocation(statement).cloned();
+                self.super_constructor_call(invocation, arguments, is_super, statement.position);
+            },
+            StatementKind::LocalType(_) => self.unsupported(statement.position, "local classes"),
+            StatementKind::Yield(_) | StatementKind::Switch(_) => self.unsupported(statement.position, "switch statements"),
+            StatementKind::Synchronized { .. } => self.unsupported(statement.position, "synchronized statements"),
+            StatementKind::Try { .. } => self.unsupported(statement.position, "try statements"),
+            StatementKind::Assert { .. } => self.unsupported(statement.position, "assert statements")
+        }
+    }
+
+    fn target(&self, label: Option<&Identifier>, is_continue: bool) -> Option<&Target> {
+        self.targets.iter().rev().find(|target| match label {
+            Some(label) => target.label.as_ref() == Some(&label.name),
+            None => target.is_loop
+        }).filter(|target| !is_continue || target.is_loop)
+    }
+
+    // A while, do or basic for loop. Like javac, a loop that tests first has the condition at
+    // the top and jumps back to it at the end.
+    fn in_loop(&mut self, label: Option<String>, init: &[Statement], condition: Option<&Expression>, update: &[Expression], body: &Statement, test_first: bool) {
+        let scope = self.next_slot;
+        self.statements(init);
+
+        let start = self.code.new_label();
+        let exit = self.code.new_label();
+        let next = self.code.new_label();
+        self.code.bind(start);
+        if let (true, Some(condition)) = (test_first, condition) {
+            self.condition(condition, false, exit);
+        }
+
+        self.targets.push(Target { label, is_loop: true, exit, next: Some(next), scope: self.next_slot });
+        self.statement(body);
+        let target = self.targets.pop().unwrap();
+        self.code.bind(next);
+        self.code.end_scope(target.scope);
+
+        for expression in update.iter() {
+            self.code.line(expression.position.line);
+            self.effect(expression);
+        }
+        match (test_first, condition) {
+            (false, Some(condition)) => self.condition(condition, true, start),
+            _ => self.code.jump(Instruction::Goto { branch_offset: 0 }, 0, start)
+        }
+        self.code.bind(exit);
+        self.end_scope(scope);
+    }
+
+    // for (T x : e), over an array with an index, or over an Iterable with its iterator.
+    fn for_each(&mut self, label: Option<String>, name: &Identifier, iterable: &Expression, body: &Statement) {
+        let scope = self.next_slot;
+        let iterable_type = self.type_of(iterable).erasure();
+        let start = self.code.new_label();
+        let exit = self.code.new_label();
+        let next = self.code.new_label();
+
+        self.expression(iterable);
+        let (array, index) = match iterable_type {
+            Type::Array(ref component) => {
+                let array = self.temporary(&iterable_type, iterable.position);
+                self.store_local(array, &iterable_type);
+                let length = self.temporary(&Type::int(), iterable.position);
+                self.load_local(array, &iterable_type);
+                self.code.emit(Instruction::Arraylength, 1, Some(VerificationType::Int));
+                self.store_local(length, &Type::int());
+                let index = self.temporary(&Type::int(), iterable.position);
+                self.code.emit(Instruction::Iconst0, 0, Some(VerificationType::Int));
+                self.store_local(index, &Type::int());
+
+                self.code.bind(start);
+                self.load_local(index, &Type::int());
+                self.load_local(length, &Type::int());
+                self.code.jump(Instruction::IfIcmpge { branch_offset: 0 }, 2, exit);
+                if let Some((slot, variable_type)) = self.declare(name) {
+                    self.load_local(array, &iterable_type);
+                    self.load_local(index, &Type::int());
+                    self.code.emit(array_load(component), 2, Some(verification_type(component)));
+                    self.coerce(component, &variable_type);
+                    self.store_local(slot, &variable_type);
+                }
+                (Some(array), Some(index))
+            },
+            _ => {
+                let owner = iterable_type.class_name().unwrap_or(OBJECT).to_string();
+                let iterator = Type::class(ITERATOR);
+                self.call_method(Invoke::Virtual, &owner, "iterator", "()Ljava/util/Iterator;", 0, Some(verification_type(&iterator)));
+                let slot = self.temporary(&iterator, iterable.position);
+                self.store_local(slot, &iterator);
+
+                self.code.bind(start);
+                self.load_local(slot, &iterator);
+                self.call_method(Invoke::Virtual, ITERATOR, "hasNext", "()Z", 0, Some(VerificationType::Int));
+                self.code.jump(Instruction::Ifeq { branch_offset: 0 }, 1, exit);
+                if let Some((variable, variable_type)) = self.declare(name) {
+                    self.load_local(slot, &iterator);
+                    self.call_method(Invoke::Virtual, ITERATOR, "next", "()Ljava/lang/Object;", 0, Some(VerificationType::Object(String::from(OBJECT))));
+                    self.coerce(&Type::object(), &variable_type);
+                    self.store_local(variable, &variable_type);
+                }
+                (None, None)
+            }
+        };
+
+        self.targets.push(Target { label, is_loop: true, exit, next: Some(next), scope: self.next_slot });
+        self.statement(body);
+        let target = self.targets.pop().unwrap();
+        self.code.bind(next);
+        self.code.end_scope(target.scope);
+        if let (Some(_), Some(index)) = (array, index) {
+            self.code.emit(Instruction::Iinc { index: index as u8, constant: 1 }, 0, None);
+        }
+        self.code.jump(Instruction::Goto { branch_offset: 0 }, 0, start);
+        self.code.bind(exit);
+        self.end_scope(scope);
+    }
+
+    // The initializer of a variable, which may be an array initializer.
+    fn initializer(&mut self, initializer: &Expression, variable_type: &Type) {
+        match initializer.kind {
+            ExpressionKind::ArrayInitializer(ref elements) => self.new_array_with(variable_type, elements),
+            _ => self.value_as(initializer, variable_type)
+        }
+    }
+
+    // An expression statement, whose value is discarded.
+    fn effect(&mut self, expression: &Expression) {
+        match expression.kind {
+            ExpressionKind::Parenthesized(ref inner) => self.effect(inner),
+            ExpressionKind::Assign { operator, ref target, ref value } => self.assign(target, operator, value, false),
+            ExpressionKind::Unary { operator, ref operand } if is_increment(operator) => self.increment(operand, operator, false),
+            _ => {
+                self.expression(expression);
+                match self.type_of(expression).width() {
+                    0 => {},
+                    1 => self.code.emit(Instruction::Pop, 1, None),
+                    _ => self.code.emit(Instruction::Pop2, 1, None)
+                }
+            }
+        }
+    }
+
+}
+
+// Expressions.
+impl<'a> Gen<'a> {
+
+    // Pushes the value of an expression, with the erasure of its type.
+    fn expression(&mut self, expression: &Expression) {
+        if let Some(value) = self.constant_of(expression) {
+            self.constant(value);
+            return;
+        }
+
+        let found = self.type_of(expression);
+        match expression.kind {
+            ExpressionKind::Literal(Literal::Null) => self.code.emit(Instruction::AconstNull, 0, Some(VerificationType::Null)),
+            ExpressionKind::Literal(_) => {},
+            ExpressionKind::Name(_) | ExpressionKind::FieldAccess { .. } | ExpressionKind::SuperFieldAccess { .. } | ExpressionKind::ArrayAccess { .. } => {
+                self.load_variable(expression);
+            },
+            ExpressionKind::MethodCall { .. } | ExpressionKind::SuperMethodCall { .. } => self.method_call(expression),
+            ExpressionKind::New { ref outer, ref arguments, ref body, .. } => {
+                if outer.is_some() {
+                    self.unsupported(expression.position, "qualified class instance creations");
+                } else if body.is_some() {
+                    self.unsupported(expression.position, "anonymous classes");
+                } else {
+                    self.new_object(expression, &found, arguments);
+                }
+            },
+            ExpressionKind::NewArray { ref dimensions, ref initializer, .. } => {
+                match *initializer {
+                    Some(ref elements) => self.new_array_with(&found, elements),
+                    None => {
+                        for dimension in dimensions.iter() {
+                            self.value_as(dimension, &Type::int());
+                        }
+                        self.new_array(&found, dimensions.len());
+                    }
+                }
+            },
+            ExpressionKind::ArrayInitializer(ref elements) => self.new_array_with(&found, elements),
+            ExpressionKind::Unary { operator, ref operand } => match operator {
+                UnaryOperator::Plus => self.value_as(operand, &found),
+                UnaryOperator::Minus => {
+                    self.value_as(operand, &found);
+                    let instruction = match kind(&found) {
+                        Kind::Int => Instruction::Ineg,
+                        Kind::Long => Instruction::Lneg,
+                        Kind::Float => Instruction::Fneg,
+                        Kind::Double => Instruction::Dneg
+                    };
+                    self.code.emit(instruction, 1, Some(verification_type(&found)));
+                },
+                UnaryOperator::BitwiseNot => {
+                    self.value_as(operand, &found);
+                    if kind(&found) == Kind::Long {
+                        self.constant(&Constant::Long(-1));
+                        self.code.emit(Instruction::Lxor, 2, Some(VerificationType::Long));
+                    } else {
+                        self.constant(&Constant::Int(-1));
+                        self.code.emit(Instruction::Ixor, 2, Some(VerificationType::Int));
+                    }
+                },
+                UnaryOperator::Not => self.condition_value(expression),
+                _ => self.increment(operand, operator, true)
+            },
+            ExpressionKind::Binary { operator, ref left, ref right } => match operator {
+                BinaryOperator::Or | BinaryOperator::And | BinaryOperator::Equal | BinaryOperator::NotEqual | BinaryOperator::Less
+                | BinaryOperator::Greater | BinaryOperator::LessOrEqual | BinaryOperator::GreaterOrEqual => self.condition_value(expression),
+                BinaryOperator::Add if found.is_string() => self.concatenation(expression),
+                _ => {
+                    self.value_as(left, &found);
+                    let right_type = if is_shift(operator) { Type::int() } else { found.clone() };
+                    self.value_as(right, &right_type);
+                    self.arithmetic(operator, &found);
+                }
+            },
+            ExpressionKind::Assign { operator, ref target, ref value } => self.assign(target, operator, value, true),
+            ExpressionKind::Conditional { ref condition, ref then, ref otherwise } => {
+                let otherwise_label = self.code.new_label();
+                let end = self.code.new_label();
+                // Both branches leave a value of the conditional's type, so the frame at the end
+                // has that type on top rather than a merge of the branch types.
+                self.condition(condition, false, otherwise_label);
+                self.value_as(then, &found);
+                self.code.set_top(verification_type(&found));
+                self.code.jump(Instruction::Goto { branch_offset: 0 }, 0, end);
+                self.code.bind(otherwise_label);
+                self.value_as(otherwise, &found);
+                self.code.set_top(verification_type(&found));
+                self.code.bind(end);
+            },
+            ExpressionKind::InstanceOf { expression: ref operand, ref instance_type, ref binding } => {
+                if binding.is_some() {
+                    self.unsupported(expression.position, "pattern matching for instanceof");
+                    return;
+                }
+                self.expression(operand);
+                let tested = self.attribution.resolved_type(instance_type).cloned().unwrap_or_else(Type::object);
+                let index = self.class_constant(&tested);
+                self.code.emit(Instruction::Instanceof { index }, 1, Some(VerificationType::Int));
+            },
+            ExpressionKind::Cast { expression: ref operand, .. } => {
+                let operand_type = self.type_of(operand);
+                self.expression(operand);
+                self.coerce(&operand_type, &found);
+            },
+            ExpressionKind::Lambda { .. } => self.unsupported(expression.position, "lambda expressions"),
+            ExpressionKind::MethodReference { .. } => self.unsupported(expression.position, "method references"),
+            ExpressionKind::This(_) => {
+                if found.erasure().class_name() == Some(&self.class.name) {
+                    self.code.emit(Instruction::Aload0, 0, Some(VerificationType::Object(self.class.name.clone())));
+                } else {
+                    self.unsupported(expression.position, "qualified this expressions");
+                }
+            },
+            ExpressionKind::ClassLiteral(ref written) => {
+                match self.attribution.resolved_type(written).cloned() {
+                    Some(Type::Primitive(primitive)) => self.get_static(primitive.boxed(), "TYPE", "Ljava/lang/Class;"),
+                    Some(Type::Void) => self.get_static("java/lang/Void", "TYPE", "Ljava/lang/Class;"),
+                    Some(literal_type) => {
+                        let index = self.class_constant(&literal_type);
+                        self.ldc(index, VerificationType::Object(String::from("java/lang/Class")));
+                    },
+                    None => {}
+                }
+            },
+            ExpressionKind::Switch(_) => self.unsupported(expression.position, "switch expressions"),
+            ExpressionKind::Parenthesized(ref inner) => self.expression(inner)
+        }
+    }
+
+    // Pushes the value of an expression converted to a type, as in an assignment, a method
+    // argument or an operand of a numeric operator.
+    fn value_as(&mut self, expression: &Expression, target: &Type) {
+        if let (Some(value), Some(primitive)) = (self.constant_of(expression), target.primitive()) {
+            if let Some(converted) = constant::convert(value, primitive) {
+                self.constant(&converted);
+                return;
+            }
+        }
+        let found = self.type_of(expression);
+        self.expression(expression);
+        self.coerce(&found, target);
+    }
+
+    fn constant(&mut self, value: &Constant) {
+        match *value {
+            Constant::Int(value) => self.int_constant(value),
+            Constant::Boolean(value) => self.int_constant(value as i32),
+            Constant::Long(0) => self.code.emit(Instruction::Lconst0, 0, Some(VerificationType::Long)),
+            Constant::Long(1) => self.code.emit(Instruction::Lconst1, 0, Some(VerificationType::Long)),
+            Constant::Long(value) => {
+                let index = self.pool.long(value);
+                self.code.emit(Instruction::Ldc2W { index }, 0, Some(VerificationType::Long));
+            },
+            Constant::Float(value) if value.to_bits() == 0 => self.code.emit(Instruction::Fconst0, 0, Some(VerificationType::Float)),
+            Constant::Float(1.0) => self.code.emit(Instruction::Fconst1, 0, Some(VerificationType::Float)),
+            Constant::Float(2.0) => self.code.emit(Instruction::Fconst2, 0, Some(VerificationType::Float)),
+            Constant::Float(value) => {
+                let index = self.pool.float(value);
+                self.ldc(index, VerificationType::Float);
+            },
+            Constant::Double(value) if value.to_bits() == 0 => self.code.emit(Instruction::Dconst0, 0, Some(VerificationType::Double)),
+            Constant::Double(1.0) => self.code.emit(Instruction::Dconst1, 0, Some(VerificationType::Double)),
+            Constant::Double(value) => {
+                let index = self.pool.double(value);
+                self.code.emit(Instruction::Ldc2W { index }, 0, Some(VerificationType::Double));
+            },
+            Constant::String(ref chars) => {
+                let index = self.pool.string(chars);
+                self.ldc(index, VerificationType::Object(String::from("java/lang/String")));
+            }
+        }
+    }
+
+    fn int_constant(&mut self, value: i32) {
+        let instruction = match value {
+            -1 => Instruction::IconstM1,
+            0 => Instruction::Iconst0,
+            1 => Instruction::Iconst1,
+            2 => Instruction::Iconst2,
+            3 => Instruction::Iconst3,
+            4 => Instruction::Iconst4,
+            5 => Instruction::Iconst5,
+            _ if i32::from(value as i8) == value => Instruction::Bipush { byte: value as i8 },
+            _ if i32::from(value as i16) == value => Instruction::Sipush(value),
+            _ => {
+                let index = self.pool.integer(value);
+                self.ldc(index, VerificationType::Int);
+                return;
+            }
+        };
+        self.code.emit(instruction, 0, Some(VerificationType::Int));
+    }
+
+    fn ldc(&mut self, index: u16, value: VerificationType) {
+        let instruction = if index < 256 { Instruction::Ldc { index: index as u8 } } else { Instruction::LdcW { index } };
+        self.code.emit(instruction, 0, Some(value));
+    }
+
+    // The Class constant of a class, or of an array type by its descriptor.
+    fn class_constant(&mut self, class_type: &Type) -> u16 {
+        match class_type.erasure() {
+            Type::Class(ref class_type) => self.pool.class(&class_type.name),
+            erased => self.pool.class(&erased.descriptor())
+        }
+    }
+
+    // Converts the value on top of the stack from one type to another: between primitive types,
+    // by boxing and unboxing, and with a cast to a reference type it may not have.
+    fn coerce(&mut self, from: &Type, to: &Type) {
+        if from.is_error() || to.is_error() || from.is_void() || to.is_void() {
+            return;
+        }
+
+        if let Some(target) = to.primitive() {
+            let source = match from.primitive() {
+                Some(source) => source,
+                None => {
+                    let source = match from.unboxed() {
+                        Some(source) => source,
+                        None => {
+                            // A cast such as (int) object unboxes an Integer.
+                            let boxed = Type::class(target.boxed());
+                            self.coerce(from, &boxed);
+                            target
+                        }
+                    };
+                    let descriptor = format!("(){}", source.descriptor());
+                    let name = format!("{}Value", source.name());
+                    self.call_method(Invoke::Virtual, source.boxed(), &name, &descriptor, 0, Some(verification_type(&Type::Primitive(source))));
+                    source
+                }
+            };
+            self.convert(source, target);
+        } else if let Some(source) = from.primitive() {
+            let primitive = to.unboxed().unwrap_or(source);
+            self.convert(source, primitive);
+            let descriptor = format!("({})L{};", primitive.descriptor(), primitive.boxed());
+            self.call_method(Invoke::Static, primitive.boxed(), "valueOf", &descriptor, 1, Some(VerificationType::Object(String::from(primitive.boxed()))));
+        } else if *from != Type::Null {
+            let (from, to) = (from.erasure(), to.erasure());
+            if !self.attribution.symbols.is_subtype(&from, &to) {
+                let index = self.class_constant(&to);
+                self.code.emit(Instruction::Checkcast { index }, 1, Some(verification_type(&to)));
+            }
+        }
+    }
+
+    // A widening or narrowing primitive conversion (JLS §5.1.2, §5.1.3).
+    fn convert(&mut self, from: PrimitiveType, to: PrimitiveType) {
+        if from == to {
+            return;
+        }
+        let (source, target) = (kind(&Type::Primitive(from)), kind(&Type::Primitive(to)));
+        let instruction = match (source, target) {
+            (Kind::Int, Kind::Long) => Some(Instruction::I2l),
+            (Kind::Int, Kind::Float) => Some(Instruction::I2f),
+            (Kind::Int, Kind::Double) => Some(Instruction::I2d),
+            (Kind::Long, Kind::Int) => Some(Instruction::L2i),
+            (Kind::Long, Kind::Float) => Some(Instruction::L2f),
+            (Kind::Long, Kind::Double) => Some(Instruction::L2d),
+            (Kind::Float, Kind::Int) => Some(Instruction::F2i),
+            (Kind::Float, Kind::Long) => Some(Instruction::F2l),
+            (Kind::Float, Kind::Double) => Some(Instruction::F2d),
+            (Kind::Double, Kind::Int) => Some(Instruction::D2i),
+            (Kind::Double, Kind::Long) => Some(Instruction::D2l),
+            (Kind::Double, Kind::Float) => Some(Instruction::D2f),
+            _ => None
+        };
+        if let Some(instruction) = instruction {
+            self.code.emit(instruction, 1, Some(verification_type(&Type::Primitive(to))));
+        }
+
+        if !from.widens_to(to) {
+            let narrowing = match to {
+                PrimitiveType::Byte => Some(Instruction::I2b),
+                PrimitiveType::Short => Some(Instruction::I2s),
+                PrimitiveType::Char => Some(Instruction::I2c),
+                _ => None
+            };
+            if let Some(narrowing) = narrowing {
+                self.code.emit(narrowing, 1, Some(VerificationType::Int));
+            }
+        }
+    }
+
+    fn arithmetic(&mut self, operator: BinaryOperator, operand_type: &Type) {
+        use self::Kind::*;
+        let operand_kind = kind(operand_type);
+        let instruction = match (operator, operand_kind) {
+            (BinaryOperator::Add, Int) => Instruction::Iadd,
+            (BinaryOperator::Add, Long) => Instruction::Ladd,
+            (BinaryOperator::Add, Float) => Instruction::Fadd,
+            (BinaryOperator::Add, Double) => Instruction::Dadd,
+            (BinaryOperator::Subtract, Int) => Instruction::Isub,
+            (BinaryOperator::Subtract, Long) => Instruction::Lsub,
+            (BinaryOperator::Subtract, Float) => Instruction::Fsub,
+            (BinaryOperator::Subtract, Double) => Instruction::Dsub,
+            (BinaryOperator::Multiply, Int) => Instruction::Imul,
+            (BinaryOperator::Multiply, Long) => Instruction::Lmul,
+            (BinaryOperator::Multiply, Float) => Instruction::Fmul,
+            (BinaryOperator::Multiply, Double) => Instruction::Dmul,
+            (BinaryOperator::Divide, Int) => Instruction::Idiv,
+            (BinaryOperator::Divide, Long) => Instruction::Ldiv,
+            (BinaryOperator::Divide, Float) => Instruction::Fdiv,
+            (BinaryOperator::Divide, Double) => Instruction::Ddiv,
+            (BinaryOperator::Remainder, Int) => Instruction::Irem,
+            (BinaryOperator::Remainder, Long) => Instruction::Lrem,
+            (BinaryOperator::Remainder, Float) => Instruction::Frem,
+            (BinaryOperator::Remainder, Double) => Instruction::Drem,
+            (BinaryOperator::ShiftLeft, Long) => Instruction::Lshl,
+            (BinaryOperator::ShiftLeft, _) => Instruction::Ishl,
+            (BinaryOperator::ShiftRight, Long) => Instruction::Lshr,
+            (BinaryOperator::ShiftRight, _) => Instruction::Ishr,
+            (BinaryOperator::UnsignedShiftRight, Long) => Instruction::Lushr,
+            (BinaryOperator::UnsignedShiftRight, _) => Instruction::Iushr,
+            (BinaryOperator::BitwiseAnd, Long) => Instruction::Land,
+            (BinaryOperator::BitwiseAnd, _) => Instruction::Iand,
+            (BinaryOperator::BitwiseOr, Long) => Instruction::Lor,
+            (BinaryOperator::BitwiseOr, _) => Instruction::Ior,
+            (BinaryOperator::BitwiseXor, Long) => Instruction::Lxor,
+            (BinaryOperator::BitwiseXor, _) => Instruction::Ixor,
+            _ => return
+        };
+        self.code.emit(instruction, 2, Some(verification_type(operand_type)));
+    }
+
+    // A string concatenation, with a StringBuilder that each operand is appended to.
+    fn concatenation(&mut self, expression: &Expression) {
+        let mut operands = Vec::new();
+        self.concatenation_operands(expression, &mut operands);
+
+        self.new_string_builder();
+        for operand in operands.into_iter() {
+            self.expression(operand);
+            let operand_type = self.type_of(operand);
+            self.append(&operand_type);
+        }
+        self.call_method(Invoke::Virtual, STRING_BUILDER, "toString", "()Ljava/lang/String;", 0, Some(VerificationType::Object(String::from("java/lang/String"))));
+    }
+
+    fn concatenation_operands<'e>(&self, expression: &'e Expression, operands: &mut Vec<&'e Expression>) {
+        match expression.kind {
+            ExpressionKind::Binary { operator: BinaryOperator::Add, ref left, ref right }
+                    if self.type_of(expression).is_string() && self.attribution.constant(expression).is_none() => {
+                self.concatenation_operands(left, operands);
+                self.concatenation_operands(right, operands);
+            },
+            _ => operands.push(expression)
+        }
+    }
+
+    fn new_string_builder(&mut self) {
+        let pc = self.code.pc();
+        let index = self.pool.class(STRING_BUILDER);
+        self.code.emit(Instruction::New { index }, 0, Some(VerificationType::Uninitialized(pc)));
+        self.code.dup(0);
+        self.call_method(Invoke::Special, STRING_BUILDER, "<init>", "()V", 0, None);
+        self.code.initialize(&VerificationType::Uninitialized(pc), STRING_BUILDER);
+    }
+
+    // Appends the value on top of the stack to the StringBuilder under it.
+    fn append(&mut self, operand_type: &Type) {
+        let parameter = match *operand_type {
+            Type::Primitive(PrimitiveType::Byte) | Type::Primitive(PrimitiveType::Short) => "I",
+            Type::Primitive(primitive) => primitive.descriptor(),
+            _ if operand_type.is_string() => "Ljava/lang/String;",
+            _ => "Ljava/lang/Object;"
+        };
+        let descriptor = format!("({})Ljava/lang/StringBuilder;", parameter);
+        self.call_method(Invoke::Virtual, STRING_BUILDER, "append", &descriptor, 1, Some(VerificationType::Object(String::from(STRING_BUILDER))));
+    }
+
+    // Pushes 1 if a condition holds and 0 otherwise.
+    fn condition_value(&mut self, expression: &Expression) {
+        let otherwise = self.code.new_label();
+        let end = self.code.new_label();
+        self.condition(expression, false, otherwise);
+        self.code.emit(Instruction::Iconst1, 0, Some(VerificationType::Int));
+        self.code.jump(Instruction::Goto { branch_offset: 0 }, 0, end);
+        self.code.bind(otherwise);
+        self.code.emit(Instruction::Iconst0, 0, Some(VerificationType::Int));
+        self.code.bind(end);
+    }
+
+    // Jumps to a label if a condition has a value, and falls through otherwise. && and ||
+    // short-circuit with jumps, and comparisons jump with the if instructions.
+    fn condition(&mut self, expression: &Expression, jump_if: bool, target: Label) {
+        if let Some(&Constant::Boolean(value)) = self.constant_of(expression) {
+            if value == jump_if {
+                self.code.jump(Instruction::Goto { branch_offset: 0 }, 0, target);
+            }
+            return;
+        }
+
+        match expression.kind {
+            ExpressionKind::Parenthesized(ref inner) => self.condition(inner, jump_if, target),
+            ExpressionKind::Unary { operator: UnaryOperator::Not, ref operand } => self.condition(operand, !jump_if, target),
+            ExpressionKind::Binary { operator: BinaryOperator::And, ref left, ref right } |
+            ExpressionKind::Binary { operator: BinaryOperator::Or, ref left, ref right } => {
+                // a && b is false as soon as a is, and a || b is true as soon as a is.
+                let short_circuit = matches!(expression.kind, ExpressionKind::Binary { operator: BinaryOperator::Or, .. });
+                if jump_if == short_circuit {
+                    self.condition(left, jump_if, target);
+                    self.condition(right, jump_if, target);
+                } else {
+                    let skip = self.code.new_label();
+                    self.condition(left, short_circuit, skip);
+                    self.condition(right, jump_if, target);
+                    self.code.bind(skip);
+                }
+            },
+            ExpressionKind::Binary { operator, ref left, ref right } if is_comparison(operator) => {
+                self.comparison(operator, left, right, jump_if, target);
+            },
+            _ => {
+                self.value_as(expression, &Type::boolean());
+                let instruction = if jump_if { Instruction::Ifne { branch_offset: 0 } } else { Instruction::Ifeq { branch_offset: 0 } };
+                self.code.jump(instruction, 1, target);
+            }
+        }
+    }
+
+    fn comparison(&mut self, operator: BinaryOperator, left: &Expression, right: &Expression, jump_if: bool, target: Label) {
+        let jump_on = if jump_if { operator } else { negate(operator) };
+        let (left_type, right_type) = (self.type_of(left), self.type_of(right));
+
+        if !left_type.is_primitive() && !right_type.is_primitive() {
+            let is_null = |expression: &Expression| matches!(strip(expression).kind, ExpressionKind::Literal(Literal::Null));
+            let equal = jump_on == BinaryOperator::Equal;
+            if is_null(right) || is_null(left) {
+                let operand = if is_null(right) { left } else { right };
+                self.expression(operand);
+                let instruction = if equal { Instruction::Ifnull { branch_offset: 0 } } else { Instruction::Ifnonnull { branch_offset: 0 } };
+                self.code.jump(instruction, 1, target);
+            } else {
+                self.expression(left);
+                self.expression(right);
+                let instruction = if equal { Instruction::IfAcmpeq { branch_offset: 0 } } else { Instruction::IfAcmpne { branch_offset: 0 } };
+                self.code.jump(instruction, 2, target);
+            }
+            return;
+        }
+
+        let operand_type = match (left_type.unboxed(), right_type.unboxed()) {
+            (Some(PrimitiveType::Boolean), _) => Type::boolean(),
+            (Some(left), Some(right)) => Type::Primitive(PrimitiveType::promote(left, right)),
+            _ => Type::int()
+        };
+        self.value_as(left, &operand_type);
+        match kind(&operand_type) {
+            Kind::Int => {
+                if self.constant_of(right) == Some(&Constant::Int(0)) {
+                    self.code.jump(if_zero(jump_on), 1, target);
+                } else {
+                    self.value_as(right, &operand_type);
+                    self.code.jump(if_compare(jump_on), 2, target);
+                }
+            },
+            operand_kind => {
+                self.value_as(right, &operand_type);
+                // NaN makes < and <= false with fcmpg, and > and >= false with fcmpl.
+                let greater_on_nan = matches!(operator, BinaryOperator::Less | BinaryOperator::LessOrEqual);
+                let instruction = match operand_kind {
+                    Kind::Long => Instruction::Lcmp,
+                    Kind::Float if greater_on_nan => Instruction::Fcmpg,
+                    Kind::Float => Instruction::Fcmpl,
+                    _ if greater_on_nan => Instruction::Dcmpg,
+                    _ => Instruction::Dcmpl
+                };
+                self.code.emit(instruction, 2, Some(VerificationType::Int));
+                self.code.jump(if_zero(jump_on), 1, target);
+            }
+        }
+    }
+
+    // Whether an expression names a class or package rather than having a value.
+    fn is_type_name(&self, expression: &Expression) -> bool {
+        matches!(self.attribution.reference(expression), Some(&Reference::Class(_)) | Some(&Reference::Package(_)))
+    }
+
+    // The class a member is accessed through, which is the class of the qualifier or, for a
+    // simple name, the class the code is in (JLS §13.1).
+    fn qualifying_class(&self, qualifier: Option<&Expression>) -> String {
+        let qualifier = match qualifier {
+            Some(qualifier) => qualifier,
+            None => return self.class.name.clone()
+        };
+        if let Some(Reference::Class(class)) = self.attribution.reference(qualifier) {
+            return class.clone();
+        }
+        match self.type_of(qualifier).erasure() {
+            Type::Class(class_type) => class_type.name,
+            _ => String::from(OBJECT)
+        }
+    }
+
+    // Pushes what a variable needs on the stack before it is loaded or stored.
+    fn variable(&mut self, expression: &Expression) -> Option<Access> {
+        let used = self.type_of(expression);
+        let variable = match strip(expression).kind {
+            ExpressionKind::ArrayAccess { ref array, ref index } => {
+                let array_type = self.type_of(array);
+                self.expression(array);
+                self.value_as(index, &Type::int());
+                let component = array_type.component().cloned().unwrap_or_else(Type::object);
+                return Some(Access { variable: Variable::Element, declared: component, used });
+            },
+            ExpressionKind::SuperFieldAccess { ref qualifier, .. } => {
+                if qualifier.is_some() {
+                    self.unsupported(expression.position, "qualified superclass field accesses");
+                    return None;
+                }
+                match self.attribution.reference(strip(expression)).cloned() {
+                    Some(Reference::Field(symbol, _)) => {
+                        let owner = self.super_class();
+                        if symbol.is_static() {
+                            Variable::Static(owner, symbol)
+                        } else {
+                            self.code.emit(Instruction::Aload0, 0, Some(VerificationType::Object(self.class.name.clone())));
+                            Variable::Field(owner, symbol)
+                        }
+                    },
+                    _ => return None
+                }
+            },
+            ExpressionKind::FieldAccess { ref target, .. } => {
+                let symbol = match self.attribution.reference(strip(expression)).cloned() {
+                    Some(Reference::Field(symbol, _)) => symbol,
+                    _ => return None
+                };
+                let owner = self.qualifying_class(Some(target));
+                let is_type_name = self.is_type_name(target);
+                if symbol.is_static() {
+                    // The qualifier is evaluated even though the field is static.
+                    if !is_type_name {
+                        self.effect(target);
+                    }
+                    Variable::Static(owner, symbol)
+                } else {
+                    self.expression(target);
+                    Variable::Field(owner, symbol)
+                }
+            },
+            ExpressionKind::Name(_) => match self.attribution.reference(strip(expression)).cloned() {
+                Some(Reference::Local(index)) => {
+                    let local_type = self.attribution.local(index).local_type.clone();
+                    let slot = self.slots.get(&index).cloned()?;
+                    return Some(Access { variable: Variable::Local(slot), declared: local_type.clone(), used: local_type });
+                },
+                Some(Reference::Field(symbol, implicit_this)) => {
+                    let owner = self.class.name.clone();
+                    if symbol.is_static() {
+                        Variable::Static(owner, symbol)
+                    } else if implicit_this.as_ref() == Some(&owner) {
+                        self.code.emit(Instruction::Aload0, 0, Some(VerificationType::Object(owner.clone())));
+                        Variable::Field(owner, symbol)
+                    } else {
+                        self.unsupported(expression.position, "fields of enclosing instances");
+                        return None;
+                    }
+                },
+                _ => return None
+            },
+            _ => return None
+        };
+
+        let declared = match variable {
+            Variable::Static(_, ref symbol) | Variable::Field(_, ref symbol) => symbol.field_type.clone(),
+            _ => used.clone()
+        };
+        Some(Access { variable, declared, used })
+    }
+
+    fn load_variable(&mut self, expression: &Expression) {
+        if let ExpressionKind::FieldAccess { ref target, ref name } = expression.kind {
+            if self.attribution.reference(expression).is_none() && name.name == "length" && self.type_of(target).component().is_some() {
+                self.expression(target);
+                self.code.emit(Instruction::Arraylength, 1, Some(VerificationType::Int));
+                return;
+            }
+        }
+        if let Some(access) = self.variable(expression) {
+            self.load(&access);
+        }
+    }
+
+    fn load(&mut self, access: &Access) {
+        match access.variable {
+            Variable::Local(slot) => self.load_local(slot, &access.declared),
+            Variable::Static(ref owner, ref symbol) => {
+                let index = self.pool.field_ref(owner, &symbol.name, &symbol.field_type.descriptor());
+                self.code.emit(Instruction::Getstatic { index }, 0, Some(verification_type(&symbol.field_type)));
+            },
+            Variable::Field(ref owner, ref symbol) => {
+                let index = self.pool.field_ref(owner, &symbol.name, &symbol.field_type.descriptor());
+                self.code.emit(Instruction::Getfield { index }, 1, Some(verification_type(&symbol.field_type)));
+            },
+            Variable::Element => self.code.emit(array_load(&access.declared), 2, Some(verification_type(&access.declared)))
+        }
+        // A generic field has the erasure of its type as declared.
+        if access.used.is_reference() {
+            self.coerce(&access.declared, &access.used);
+        }
+    }
+
+    fn store(&mut self, access: &Access) {
+        match access.variable {
+            Variable::Local(slot) => self.store_local(slot, &access.declared),
+            Variable::Static(ref owner, ref symbol) => {
+                let index = self.pool.field_ref(owner, &symbol.name, &symbol.field_type.descriptor());
+                self.code.emit(Instruction::Putstatic { index }, 1, None);
+            },
+            Variable::Field(ref owner, ref symbol) => {
+                let index = self.pool.field_ref(owner, &symbol.name, &symbol.field_type.descriptor());
+                self.code.emit(Instruction::Putfield { index }, 2, None);
+            },
+            Variable::Element => self.code.emit(array_store(&access.declared), 3, None)
+        }
+    }
+
+    fn load_local(&mut self, slot: usize, local_type: &Type) {
+        use self::Kind::*;
+        let instruction = match (local_kind(local_type), slot) {
+            (Some(Int), 0) => Instruction::Iload0,
+            (Some(Int), 1) => Instruction::Iload1,
+            (Some(Int), 2) => Instruction::Iload2,
+            (Some(Int), 3) => Instruction::Iload3,
+            (Some(Int), _) => Instruction::Iload { index: slot as u8 },
+            (Some(Long), 0) => Instruction::Lload0,
+            (Some(Long), 1) => Instruction::Lload1,
+            (Some(Long), 2) => Instruction::Lload2,
+            (Some(Long), 3) => Instruction::Lload3,
+            (Some(Long), _) => Instruction::Lload { index: slot as u8 },
+            (Some(Float), 0) => Instruction::Fload0,
+            (Some(Float), 1) => Instruction::Fload1,
+            (Some(Float), 2) => Instruction::Fload2,
+            (Some(Float), 3) => Instruction::Fload3,
+            (Some(Float), _) => Instruction::Fload { index: slot as u8 },
+            (Some(Double), 0) => Instruction::Dload0,
+            (Some(Double), 1) => Instruction::Dload1,
+            (Some(Double), 2) => Instruction::Dload2,
+            (Some(Double), 3) => Instruction::Dload3,
+            (Some(Double), _) => Instruction::Dload { index: slot as u8 },
+            (None, 0) => Instruction::Aload0,
+            (None, 1) => Instruction::Aload1,
+            (None, 2) => Instruction::Aload2,
+            (None, 3) => Instruction::Aload3,
+            (None, _) => Instruction::Aload { index: slot as u8 }
+        };
+        self.code.emit(instruction, 0, Some(verification_type(local_type)));
+    }
+
+    fn store_local(&mut self, slot: usize, local_type: &Type) {
+        use self::Kind::*;
+        let instruction = match (local_kind(local_type), slot) {
+            (Some(Int), 0) => Instruction::Istore0,
+            (Some(Int), 1) => Instruction::Istore1,
+            (Some(Int), 2) => Instruction::Istore2,
+            (Some(Int), 3) => Instruction::Istore3,
+            (Some(Int), _) => Instruction::Istore(slot as u8),
+            (Some(Long), 0) => Instruction::Lstore0,
+            (Some(Long), 1) => Instruction::Lstore1,
+            (Some(Long), 2) => Instruction::Lstore2,
+            (Some(Long), 3) => Instruction::Lstore3,
+            (Some(Long), _) => Instruction::Lstore { index: slot as u8 },
+            (Some(Float), 0) => Instruction::Fstore0,
+            (Some(Float), 1) => Instruction::Fstore1,
+            (Some(Float), 2) => Instruction::Fstore2,
+            (Some(Float), 3) => Instruction::Fstore3,
+            (Some(Float), _) => Instruction::Fstore { index: slot as u8 },
+            (Some(Double), 0) => Instruction::Dstore0,
+            (Some(Double), 1) => Instruction::Dstore1,
+            (Some(Double), 2) => Instruction::Dstore2,
+            (Some(Double), 3) => Instruction::Dstore3,
+            (Some(Double), _) => Instruction::Dstore { index: slot as u8 },
+            (None, 0) => Instruction::Astore0,
+            (None, 1) => Instruction::Astore1,
+            (None, 2) => Instruction::Astore2,
+            (None, 3) => Instruction::Astore3,
+            (None, _) => Instruction::Astore { index: slot as u8 }
+        };
+        self.code.emit(instruction, 1, None);
+        // Like javac, a local has its declared type in the frames, not the type of the value
+        // stored: the frame at a loop head is written before the back edge, which may store a
+        // different value, e.g. a String into a local that held null.
+        self.code.store(slot, verification_type(local_type));
+    }
+
+    // a = b, or a op= b, which converts the result back to the type of a.
+    fn assign(&mut self, target: &Expression, operator: Option<BinaryOperator>, value: &Expression, want_value: bool) {
+        let access = match self.variable(target) {
+            Some(access) => access,
+            None => return
+        };
+
+        match operator {
+            None => self.value_as(value, &access.used),
+            Some(operator) => {
+                if let (Variable::Local(slot), Some(constant)) = (&access.variable, self.increment_constant(&access, operator, value)) {
+                    self.code.emit(Instruction::Iinc { index: *slot as u8, constant }, 0, None);
+                    if want_value {
+                        self.load(&access);
+                    }
+                    return;
+                }
+
+                self.duplicate_receiver(&access);
+                self.load(&access);
+                if operator == BinaryOperator::Add && access.used.is_string() {
+                    self.new_string_builder();
+                    self.code.swap();
+                    self.append(&access.used);
+                    self.expression(value);
+                    let value_type = self.type_of(value);
+                    self.append(&value_type);
+                    self.call_method(Invoke::Virtual, STRING_BUILDER, "toString", "()Ljava/lang/String;", 0, Some(VerificationType::Object(String::from("java/lang/String"))));
+                } else {
+                    let value_type = self.type_of(value);
+                    let operand_type = match (access.used.unboxed(), value_type.unboxed()) {
+                        (Some(left), _) if is_shift(operator) => Type::Primitive(left.promoted()),
+                        (Some(PrimitiveType::Boolean), _) => Type::boolean(),
+                        (Some(left), Some(right)) => Type::Primitive(PrimitiveType::promote(left, right)),
+                        _ => Type::int()
+                    };
+                    self.coerce(&access.used, &operand_type);
+                    let value_target = if is_shift(operator) { Type::int() } else { operand_type.clone() };
+                    self.value_as(value, &value_target);
+                    self.arithmetic(operator, &operand_type);
+                    self.coerce(&operand_type, &access.used);
+                }
+            }
+        }
+
+        if want_value {
+            self.code.dup(access.receiver_slots());
+        }
+        self.store(&access);
+    }
+
+    // The constant to add to an int local with iinc for a += or -= of a small constant.
+    fn increment_constant(&mut self, access: &Access, operator: BinaryOperator, value: &Expression) -> Option<i8> {
+        if access.declared != Type::int() || !matches!(access.variable, Variable::Local(_)) {
+            return None;
+        }
+        let value = match (self.constant_of(value), self.type_of(value)) {
+            (Some(&Constant::Int(value)), ref value_type) if *value_type != Type::Primitive(PrimitiveType::Char) => value,
+            _ => return None
+        };
+        let value = match operator {
+            BinaryOperator::Add => value,
+            BinaryOperator::Subtract => value.checked_neg()?,
+            _ => return None
+        };
+        if i32::from(value as i8) == value { Some(value as i8) } else { None }
+    }
+
+    fn duplicate_receiver(&mut self, access: &Access) {
+        match access.variable {
+            Variable::Field(..) => self.code.dup(0),
+            Variable::Element => self.code.dup_pair(),
+            _ => {}
+        }
+    }
+
+    // ++a, --a, a++ or a--.
+    fn increment(&mut self, operand: &Expression, operator: UnaryOperator, want_value: bool) {
+        let access = match self.variable(operand) {
+            Some(access) => access,
+            None => return
+        };
+        let is_postfix = matches!(operator, UnaryOperator::PostIncrement | UnaryOperator::PostDecrement);
+        let is_increment = matches!(operator, UnaryOperator::PreIncrement | UnaryOperator::PostIncrement);
+
+        if let (&Variable::Local(slot), true) = (&access.variable, access.declared == Type::int()) {
+            if want_value && is_postfix {
+                self.load(&access);
+            }
+            self.code.emit(Instruction::Iinc { index: slot as u8, constant: if is_increment { 1 } else { -1 } }, 0, None);
+            if want_value && !is_postfix {
+                self.load(&access);
+            }
+            return;
+        }
+
+        self.duplicate_receiver(&access);
+        self.load(&access);
+        if want_value && is_postfix {
+            self.code.dup(access.receiver_slots());
+        }
+        let operand_type = Type::Primitive(access.used.unboxed().map_or(PrimitiveType::Int, PrimitiveType::promoted));
+        self.coerce(&access.used, &operand_type);
+        let one = constant::convert(&Constant::Int(1), operand_type.primitive().unwrap_or(PrimitiveType::Int)).unwrap_or(Constant::Int(1));
+        self.constant(&one);
+        self.arithmetic(if is_increment { BinaryOperator::Add } else { BinaryOperator::Subtract }, &operand_type);
+        self.coerce(&operand_type, &access.used);
+        if want_value && !is_postfix {
+            self.code.dup(access.receiver_slots());
+        }
+        self.store(&access);
+    }
+
+    fn method_call(&mut self, expression: &Expression) {
+        let invocation = match self.attribution.invocation(expression).cloned() {
+            Some(invocation) => invocation,
+            None => return
+        };
+        let method = &invocation.method;
+
+        let (kind, owner, arguments) = match expression.kind {
+            ExpressionKind::MethodCall { ref target, ref arguments, .. } => {
+                let target = target.as_ref().map(|target| &**target);
+                let owner = self.qualifying_class(target);
+                if method.is_static() {
+                    if let Some(target) = target {
+                        if !self.is_type_name(target) {
+                            self.effect(target);
+                        }
+                    }
+                    (Invoke::Static, owner, arguments)
+                } else {
+                    match target {
+                        Some(target) => self.expression(target),
+                        None if invocation.implicit_this.as_ref() == Some(&self.class.name) || invocation.implicit_this.is_none() => {
+                            self.code.emit(Instruction::Aload0, 0, Some(VerificationType::Object(self.class.name.clone())));
+                        },
+                        None => {
+                            self.unsupported(expression.position, "calls of methods of enclosing instances");
+                            return;
+                        }
+                    }
+                    let kind = if method.is_private() && owner == self.class.name { Invoke::Special } else { Invoke::Virtual };
+                    // The clone method of an array is called on the array class.
+                    let owner = match self.type_of_target(target) {
+                        Some(array) if array.component().is_some() && method.name == "clone" => array.descriptor(),
+                        _ => owner
+                    };
+                    (kind, owner, arguments)
+                }
+            },
+            ExpressionKind::SuperMethodCall { ref qualifier, ref arguments, .. } => {
+                if qualifier.is_some() {
+                    self.unsupported(expression.position, "qualified superclass method calls");
+                    return;
+                }
+                if method.is_static() {
+                    (Invoke::Static, self.super_class(), arguments)
+                } else {
+                    self.code.emit(Instruction::Aload0, 0, Some(VerificationType::Object(self.class.name.clone())));
+                    (Invoke::Special, self.super_class(), arguments)
+                }
+            },
+            _ => return
+        };
+
+        self.arguments(arguments, &invocation, expression.position);
+        self.invoke(kind, &owner, method);
+        // A generic method returns the erasure of its return type as declared.
+        let found = self.type_of(expression);
+        if found.is_reference() {
+            self.coerce(&method.return_type, &found);
+        }
+    }
+
+    fn type_of_target(&self, target: Option<&Expression>) -> Option<Type> {
+        target.map(|target| self.type_of(target).erasure())
+    }
+
+    // The arguments of a method or constructor, with the trailing ones in an array for a
+    // variable arity call.
+    fn arguments(&mut self, arguments: &[Expression], invocation: &Invocation, position: Position) {
+        let parameters = &invocation.parameters;
+        if invocation.varargs && !parameters.is_empty() {
+            let fixed = parameters.len() - 1;
+            for (argument, parameter) in arguments.iter().zip(parameters[..fixed].iter()) {
+                self.value_as(argument, parameter);
+            }
+            let array_type = parameters[fixed].erasure();
+            self.new_array_with(&array_type, if arguments.len() > fixed { &arguments[fixed..] } else { &[] });
+        } else {
+            if arguments.len() != parameters.len() {
+                self.error(position, "wrong number of arguments");
+                return;
+            }
+            for (argument, parameter) in arguments.iter().zip(parameters.iter()) {
+                self.value_as(argument, parameter);
+            }
+        }
+    }
+
+    fn invoke(&mut self, kind: Invoke, owner: &str, method: &MethodSymbol) {
+        let pops = method.parameters.len() + if kind == Invoke::Static { 0 } else { 1 };
+        let push = if method.return_type.is_void() { None } else { Some(verification_type(&method.return_type)) };
+        let descriptor = method.descriptor();
+        self.call_method(kind, owner, &method.name, &descriptor, pops - if kind == Invoke::Static { 0 } else { 1 }, push);
+    }
+
+    // Calls a method with a number of arguments, and for an instance method the receiver under
+    // them.
+    fn call_method(&mut self, kind: Invoke, owner: &str, name: &str, descriptor: &str, arguments: usize, push: Option<VerificationType>) {
+        let interface = self.is_interface(owner);
+        let index = if interface {
+            self.pool.interface_method_ref(owner, name, descriptor)
+        } else {
+            self.pool.method_ref(owner, name, descriptor)
+        };
+        let instruction = match kind {
+            Invoke::Static => Instruction::Invokestatic { index },
+            Invoke::Special => Instruction::Invokespecial { index },
+            Invoke::Virtual if interface => Instruction::Invokeinterface { index, count: argument_slots(descriptor) as u8 + 1 },
+            Invoke::Virtual => Instruction::Invokevirtual { index }
+        };
+        let pops = arguments + if kind == Invoke::Static { 0 } else { 1 };
+        self.code.emit(instruction, pops, push);
+    }
+
+    fn get_static(&mut self, owner: &str, name: &str, descriptor: &str) {
+        let index = self.pool.field_ref(owner, name, descriptor);
+        let value = Type::class(&descriptor[1..descriptor.len() - 1]);
+        self.code.emit(Instruction::Getstatic { index }, 0, Some(verification_type(&value)));
+    }
+
+    fn new_object(&mut self, expression: &Expression, class_type: &Type, arguments: &[Expression]) {
+        let name = match class_type.erasure() {
+            Type::Class(class_type) => class_type.name,
+            _ => return
+        };
+        if self.attribution.symbols.class(&name).is_some_and(|symbol| symbol.is_inner()) {
+            self.unsupported(expression.position, "inner class instance creations");
+            return;
+        }
+        let invocation = match self.attribution.invocation(expression).cloned() {
+            Some(invocation) => invocation,
+            None => return
+        };
+
+        let pc = self.code.pc();
+        let index = self.pool.class(&name);
+        self.code.emit(Instruction::New { index }, 0, Some(VerificationType::Uninitialized(pc)));
+        self.code.dup(0);
+        self.arguments(arguments, &invocation, expression.position);
+        self.invoke(Invoke::Special, &name, &invocation.method);
+        self.code.initialize(&VerificationType::Uninitialized(pc), &name);
+    }
+
+    // Creates an array from the lengths of its first dimensions on the stack.
+    fn new_array(&mut self, array_type: &Type, dimensions: usize) {
+        let array_type = array_type.erasure();
+        let component = array_type.component().cloned().unwrap_or_else(Type::object);
+        let instruction = if dimensions > 1 {
+            Instruction::Multianewarray { index: self.class_constant(&array_type), dimensions: dimensions as u8 }
+        } else {
+            match component {
+                Type::Primitive(primitive) => Instruction::Newarray { atype: array_type_code(primitive) },
+                _ => Instruction::Anewarray { index: self.class_constant(&component) }
+            }
+        };
+        self.code.emit(instruction, dimensions, Some(verification_type(&array_type)));
+    }
+
+    // Creates an array with the values of its elements, as in an array initializer.
+    fn new_array_with(&mut self, array_type: &Type, elements: &[Expression]) {
+        let component = array_type.component().cloned().unwrap_or_else(Type::object);
+        self.int_constant(elements.len() as i32);
+        self.new_array(array_type, 1);
+        for (index, element) in elements.iter().enumerate() {
+            self.code.dup(0);
+            self.int_constant(index as i32);
+            self.initializer(element, &component);
+            self.code.emit(array_store(&component), 3, None);
+        }
+    }
+
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Int,
+    Long,
+    Float,
+    Double
+}
+
+// How the JVM computes with values of a primitive type: booleans, bytes, shorts and chars are
+// ints.
+fn kind(primitive_type: &Type) -> Kind {
+    match primitive_type.unboxed() {
+        Some(PrimitiveType::Long) => Kind::Long,
+        Some(PrimitiveType::Float) => Kind::Float,
+        Some(PrimitiveType::Double) => Kind::Double,
+        _ => Kind::Int
+    }
+}
+
+// The kind of a primitive local variable, or None for a reference.
+fn local_kind(local_type: &Type) -> Option<Kind> {
+    if local_type.is_primitive() { Some(kind(local_type)) } else { None }
+}
+
+pub fn verification_type(value_type: &Type) -> VerificationType {
+    match value_type.erasure() {
+        Type::Primitive(PrimitiveType::Long) => VerificationType::Long,
+        Type::Primitive(PrimitiveType::Float) => VerificationType::Float,
+        Type::Primitive(PrimitiveType::Double) => VerificationType::Double,
+        Type::Primitive(_) => VerificationType::Int,
+        Type::Null => VerificationType::Null,
+        Type::Class(class_type) => VerificationType::Object(class_type.name),
+        Type::Array(component) => VerificationType::Object(Type::Array(component).descriptor()),
+        _ => VerificationType::Object(String::from(OBJECT))
+    }
+}
+
+fn return_instruction(return_type: &Type) -> Instruction {
+    match local_kind(return_type) {
+        _ if return_type.is_void() => Instruction::Return,
+        Some(Kind::Int) => Instruction::Ireturn,
+        Some(Kind::Long) => Instruction::Lreturn,
+        Some(Kind::Float) => Instruction::Freturn,
+        Some(Kind::Double) => Instruction::Dreturn,
+        None => Instruction::Areturn
+    }
+}
+
+fn array_load(component: &Type) -> Instruction {
+    match component.primitive() {
+        Some(PrimitiveType::Boolean) | Some(PrimitiveType::Byte) => Instruction::Baload,
+        Some(PrimitiveType::Char) => Instruction::Caload,
+        Some(PrimitiveType::Short) => Instruction::Saload,
+        Some(PrimitiveType::Int) => Instruction::Iaload,
+        Some(PrimitiveType::Long) => Instruction::Laload,
+        Some(PrimitiveType::Float) => Instruction::Faload,
+        Some(PrimitiveType::Double) => Instruction::Daload,
+        None => Instruction::Aaload
+    }
+}
+
+fn array_store(component: &Type) -> Instruction {
+    match component.primitive() {
+        Some(PrimitiveType::Boolean) | Some(PrimitiveType::Byte) => Instruction::Bastore,
+        Some(PrimitiveType::Char) => Instruction::Castore,
+        Some(PrimitiveType::Short) => Instruction::Sastore,
+        Some(PrimitiveType::Int) => Instruction::Iastore,
+        Some(PrimitiveType::Long) => Instruction::Lastore,
+        Some(PrimitiveType::Float) => Instruction::Fastore,
+        Some(PrimitiveType::Double) => Instruction::Dastore,
+        None => Instruction::Aastore
+    }
+}
+
+// The atype operand of newarray (JVMS §6.5.newarray).
+fn array_type_code(primitive: PrimitiveType) -> u8 {
+    match primitive {
+        PrimitiveType::Boolean => 4,
+        PrimitiveType::Char => 5,
+        PrimitiveType::Float => 6,
+        PrimitiveType::Double => 7,
+        PrimitiveType::Byte => 8,
+        PrimitiveType::Short => 9,
+        PrimitiveType::Int => 10,
+        PrimitiveType::Long => 11
+    }
+}
+
+// The number of local variable slots the parameters of a method descriptor take.
+fn argument_slots(descriptor: &str) -> usize {
+    let mut slots = 0;
+    let mut chars = descriptor[1..].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ')' => break,
+            'J' | 'D' => slots += 2,
+            'L' => {
+                slots += 1;
+                chars.by_ref().find(|&c| c == ';');
+            },
+            '[' => {
+                slots += 1;
+                let mut c = chars.next();
+                while c == Some('[') {
+                    c = chars.next();
+                }
+                if c == Some('L') {
+                    chars.by_ref().find(|&c| c == ';');
+                }
+            },
+            _ => slots += 1
+        }
+    }
+    slots
+}
+
+fn strip(expression: &Expression) -> &Expression {
+    match expression.kind {
+        ExpressionKind::Parenthesized(ref inner) => strip(inner),
+        _ => expression
+    }
+}
+
+fn is_increment(operator: UnaryOperator) -> bool {
+    matches!(operator, UnaryOperator::PreIncrement | UnaryOperator::PreDecrement | UnaryOperator::PostIncrement | UnaryOperator::PostDecrement)
+}
+
+fn is_shift(operator: BinaryOperator) -> bool {
+    matches!(operator, BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight | BinaryOperator::UnsignedShiftRight)
+}
+
+fn is_comparison(operator: BinaryOperator) -> bool {
+    matches!(operator, BinaryOperator::Equal | BinaryOperator::NotEqual | BinaryOperator::Less | BinaryOperator::Greater
+                       | BinaryOperator::LessOrEqual | BinaryOperator::GreaterOrEqual)
+}
+
+fn negate(operator: BinaryOperator) -> BinaryOperator {
+    match operator {
+        BinaryOperator::Equal => BinaryOperator::NotEqual,
+        BinaryOperator::NotEqual => BinaryOperator::Equal,
+        BinaryOperator::Less => BinaryOperator::GreaterOrEqual,
+        BinaryOperator::GreaterOrEqual => BinaryOperator::Less,
+        BinaryOperator::Greater => BinaryOperator::LessOrEqual,
+        BinaryOperator::LessOrEqual => BinaryOperator::Greater,
+        other => other
+    }
+}
+
+// The jump that compares an int with zero.
+fn if_zero(operator: BinaryOperator) -> Instruction {
+    match operator {
+        BinaryOperator::Equal => Instruction::Ifeq { branch_offset: 0 },
+        BinaryOperator::NotEqual => Instruction::Ifne { branch_offset: 0 },
+        BinaryOperator::Less => Instruction::Iflt { branch_offset: 0 },
+        BinaryOperator::GreaterOrEqual => Instruction::Ifge { branch_offset: 0 },
+        BinaryOperator::Greater => Instruction::Ifgt { branch_offset: 0 },
+        _ => Instruction::Ifle { branch_offset: 0 }
+    }
+}
+
+// The jump that compares two ints.
+fn if_compare(operator: BinaryOperator) -> Instruction {
+    match operator {
+        BinaryOperator::Equal => Instruction::IfIcmpeq { branch_offset: 0 },
+        BinaryOperator::NotEqual => Instruction::IfIcmpne { branch_offset: 0 },
+        BinaryOperator::Less => Instruction::IfIcmplt { branch_offset: 0 },
+        BinaryOperator::GreaterOrEqual => Instruction::IfIcmpge { branch_offset: 0 },
+        BinaryOperator::Greater => Instruction::IfIcmpgt { branch_offset: 0 },
+        _ => Instruction::IfIcmple { branch_offset: 0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use class::path::ClassPath;
+    use class::reader;
+    use compiler::attr;
+    use compiler::symbols::Symbols;
+    use runtime::Value;
+    use runtime::bootstrap::io::{Console, SharedBuffer};
+    use runtime::class::ClassTable;
+    use runtime::interpreter::{self, InvokeResult};
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process::{self, Command};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Compiles a source file against the library of the attribution tests, which stands in for
+    // java.base.
+    fn compile(source: &str) -> Result<Vec<GeneratedClass>, Vec<String>> {
+        let units = attr::tests::with_library(source);
+        let attribution = attr::attribute(&units, Symbols::new(ClassPath::new())).unwrap();
+        generate("A.java", &units[0].1, &attribution).map_err(|diagnostics| attr::tests::format_errors(&diagnostics))
+    }
+
+    // Runs the main method of the last class and gives what it printed.
+    fn run(classes: &[GeneratedClass]) -> String {
+        let out = SharedBuffer::new();
+        let class_table = ClassTable::with_console(Console::new(Box::new(out.clone()), Box::new(SharedBuffer::new())));
+        let mut class = None;
+        for generated in classes.iter() {
+            class = Some(class_table.define_class(&reader::read_class_file(&generated.bytes).unwrap()).unwrap());
+        }
+
+        let class = class.unwrap();
+        let main = class.get_declared_method("main", "([Ljava/lang/String;)V").unwrap();
+        assert!(interpreter::initialize_class(&class, &class_table).unwrap().is_none());
+        let arguments = vec![Value::ArrayRef(class_table.new_array("Ljava/lang/String;", 0))];
+        match interpreter::invoke_method(&class, main, arguments, &class_table).unwrap() {
+            InvokeResult::Void => out.contents(),
+            x => panic!("main did not return normally: {:?}", x)
+        }
+    }
+
+    #[test]
+    fn generates_runnable_classes() {
+        let source = "public class A {
+    static final int LIMIT = 5;
+    static int counter = 10;
+    private long total;
+    int[] values = {1, 2, 3};
+
+    A(long total) { this.total = total; }
+
+    A() { this(7); }
+
+    long add(int x) {
+        total += x;
+        return total;
+    }
+
+    static int fib(int n) {
+        return n < 2 ? n : fib(n - 1) + fib(n - 2);
+    }
+
+    static int sum(int... xs) {
+        int s = 0;
+        for (int x : xs) {
+            s += x;
+        }
+        return s;
+    }
+
+    public static void main(String[] args) {
+        A a = new A();
+        System.out.println(a.add(3));
+        System.out.println(\"fib \" + fib(10));
+        String s = \"\";
+        outer:
+        for (int i = 0; i < LIMIT; i++) {
+            for (int j = 0; j < 10; j++) {
+                if (j > i) continue outer;
+                if (i == 4) break outer;
+                s += j;
+            }
+        }
+        System.out.println(s);
+        int k = 0;
+        while (true) {
+            k++;
+            if (k >= 3 && k % 2 == 1 || k > 100) break;
+        }
+        System.out.println(\"k=\" + k + \" counter=\" + counter++ + \" \" + counter);
+        int[][] grid = new int[3][4];
+        grid[1][2] = 5;
+        grid[1][2] += 3;
+        grid[1][2]++;
+        System.out.println(grid[1][2] + grid.length * 10 + sum(1, 2, 3) + a.values.length);
+        char c = 'a';
+        c += 1;
+        byte b = (byte) 200;
+        long big = 1L << 40;
+        Integer boxed = 5;
+        int unboxed = boxed + 1;
+        System.out.println(c + \" \" + b + \" \" + big + \" \" + (big > 3 ? \"yes\" : \"no\") + \" \" + unboxed + \" \" + (a == null));
+    }
+}";
+        let classes = compile(source).unwrap();
+
+        assert_eq!(classes.iter().map(|generated| generated.name.as_str()).collect::<Vec<_>>(), vec!["A"]);
+        let expected = "10\nfib 55\n0010120123\nk=3 counter=10 11\n48\nb -56 1099511627776 yes 6 false\n";
+        assert_eq!(run(&classes), expected);
+        if let Some(output) = verify_and_run_on_jvm(&classes) {
+            assert_eq!(output, expected);
+        }
+    }
+
+    // Runs the main method of the last class on a JVM, the one in JAVA_HOME or else the one on
+    // the PATH, and gives what it printed. With -Xverify:all it checks the classes against their
+    // StackMapTables. Without a JVM there is nothing to verify with, and None is given.
+    fn verify_and_run_on_jvm(classes: &[GeneratedClass]) -> Option<String> {
+        let java = match env::var("JAVA_HOME") {
+            Ok(java_home) => Path::new(&java_home).join("bin").join("java"),
+            Err(_) => PathBuf::from("java")
+        };
+        static RUNS: AtomicUsize = AtomicUsize::new(0);
+        let directory = env::temp_dir().join(format!("ironjdk-gen-{}-{}", process::id(), RUNS.fetch_add(1, Ordering::SeqCst)));
+        fs::create_dir_all(&directory).unwrap();
+        for generated in classes.iter() {
+            fs::write(directory.join(format!("{}.class", generated.name)), &generated.bytes).unwrap();
+        }
+
+        let main_class = &classes.last().unwrap().name;
+        let output = Command::new(&java).arg("-Xverify:all").arg("-cp").arg(&directory).arg(main_class).output();
+        fs::remove_dir_all(&directory).unwrap();
+        let output = match output {
+            Ok(output) => output,
+            Err(e) => {
+                eprintln!("not verified: could not run {}: {}", java.display(), e);
+                return None;
+            }
+        };
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        Some(String::from_utf8(output.stdout).unwrap())
+    }
+
+    // The frame at a loop head is written before the back edge, so reference locals must have
+    // their declared types in it, not the types of the values stored before the loop.
+    #[test]
+    fn passes_verification() {
+        let source = "public class A {
+    static String describe(Object o) {
+        String result = null;
+        for (int i = 0; i < 3; i++) {
+            if (i == 1) {
+                result = \"x\" + o;
+            } else if (result != null) {
+                result = result + i;
+            }
+        }
+        return result;
+    }
+
+    public static void main(String[] args) {
+        String s = null;
+        for (int i = 0; i < 3; i++) {
+            if (i == 1) s = \"x\";
+        }
+        System.out.println(s);
+        Object last = null;
+        int n = 0;
+        while (n < 4) {
+            Integer boxed = n * 2;
+            last = boxed;
+            n++;
+        }
+        System.out.println(last + \" \" + describe(args.length));
+        CharSequence text = null;
+        for (String word : new String[] { \"a\", \"b\" }) {
+            text = text == null ? word : text + word;
+        }
+        System.out.println(text);
+        boolean b = args.length == 0;
+        String yes = b ? \"yes\" : null;
+        Object o = args.length == 0 ? new Object() : \"s\";
+        System.out.println(yes + \" \" + (o instanceof String));
+    }
+}";
+        let classes = compile(source).unwrap();
+
+        let expected = "x\n6 x02\nab\nyes false\n";
+        assert_eq!(run(&classes), expected);
+        if let Some(output) = verify_and_run_on_jvm(&classes) {
+            assert_eq!(output, expected);
+        }
+    }
+
+    #[test]
+    fn reports_unsupported_features() {
+        let source = "class A {\n  void f(Object o) {\n    synchronized (o) {}\n    Runnable r = () -> {};\n  }\n}";
+        let errors = compile(source).unwrap_err();
+
+        assert_eq!(errors, vec![
+            "3:5: synchronized statements are not supported yet",
+            "4:18: lambda expressions are not supported yet"
+        ]);
+    }
+
+}
@@ -0,0 +1,458 @@
+// Incremental compilation. A cache next to the class files records, for each source file, a hash
+// of its content, and for each class compiled from it its API and the members of other classes
+// it uses. A source file is recompiled when its content changes, when one of its class files is
+// missing, or when a member it uses changes.
+//
+// Like the incremental compilers of build tools, members are tracked by name: a class that calls
+// m on another class depends on all the methods named m there and in its superclasses, so adding
+// an overload is a change. Only classes compiled from the source files are tracked; a different
+// class path recompiles everything.
+
+use class::{field, method, Attribute, ClassFile, ConstantPoolEntry};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::Path;
+
+const HEADER: &str = "ironjdk javac cache 1";
+
+// The file name of the cache in the output directory.
+pub const CACHE_FILE: &str = ".javac-cache";
+
+// The 64-bit FNV-1a hash, which unlike the standard library's hasher is the same in every run.
+pub struct Fnv(u64);
+
+impl Fnv {
+
+    pub fn new() -> Fnv {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+
+}
+
+impl Default for Fnv {
+    fn default() -> Fnv {
+        Fnv::new()
+    }
+}
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes.iter() {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+pub fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = Fnv::new();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+// The API of a class: what other classes see when they are compiled against it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Api {
+    // The superclass and the superinterfaces.
+    pub supertypes: Vec<String>,
+    // A hash of the flags and supertypes.
+    pub header: u64,
+    // A hash of the fields or methods with a name that aren't private, by "field name" or
+    // "method name". A field's hash includes its constant value.
+    pub members: BTreeMap<String, u64>
+}
+
+pub fn api(class_file: &ClassFile) -> Result<Api, String> {
+    let pool = &class_file.constant_pool;
+    let mut supertypes = Vec::new();
+    if class_file.super_class != 0 {
+        supertypes.push(pool.get_class_name(class_file.super_class)?);
+    }
+    for &interface in class_file.interfaces.iter() {
+        supertypes.push(pool.get_class_name(interface)?);
+    }
+
+    let mut header = Fnv::new();
+    class_file.access_flags.hash(&mut header);
+    supertypes.hash(&mut header);
+
+    let mut members: BTreeMap<String, Fnv> = BTreeMap::new();
+    for field in class_file.fields.iter().filter(|field| field.access_flags & field::ACC_PRIVATE == 0) {
+        let hasher = members.entry(format!("field {}", pool.get_utf8(field.name_index)?)).or_default();
+        field.access_flags.hash(hasher);
+        pool.get_utf8(field.descriptor_index)?.hash(hasher);
+        for attribute in field.attributes.iter() {
+            if let Attribute::ConstantValue { index } = *attribute {
+                pool.get(index).hash(hasher);
+            }
+        }
+    }
+    for method in class_file.methods.iter().filter(|method| method.access_flags & method::ACC_PRIVATE == 0) {
+        let name = pool.get_utf8(method.name_index)?;
+        if name == "<clinit>" {
+            continue;
+        }
+        let hasher = members.entry(format!("method {}", name)).or_default();
+        method.access_flags.hash(hasher);
+        pool.get_utf8(method.descriptor_index)?.hash(hasher);
+        for attribute in method.attributes.iter() {
+            if let Attribute::Exceptions { ref exception_index } = *attribute {
+                for &exception in exception_index.iter() {
+                    pool.get_class_name(exception)?.hash(hasher);
+                }
+            }
+        }
+    }
+
+    Ok(Api {
+        supertypes,
+        header: header.finish(),
+        members: members.into_iter().map(|(key, hasher)| (key, hasher.finish())).collect()
+    })
+}
+
+// Something a class uses of another class.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Dependency {
+    // The flags and supertypes of a class, which it uses e.g. by naming it in a cast.
+    Class(String),
+    // All of the API of a superclass or superinterface, whose methods the class may override
+    // or have to implement.
+    Supertype(String),
+    // The fields or methods with a name, by class and "field name" or "method name".
+    Member(String, String)
+}
+
+// The dependencies of a class: the classes, fields and methods its constant pool refers to, the
+// classes in the descriptors, its supertypes, and the constants whose values it uses.
+pub fn dependencies(class_file: &ClassFile, constants: &BTreeSet<(String, String)>) -> Result<BTreeSet<Dependency>, String> {
+    let pool = &class_file.constant_pool;
+    let mut dependencies = BTreeSet::new();
+    let mut descriptors = Vec::new();
+
+    for (i, entry) in pool.entries.iter().enumerate() {
+        let index = i as u16 + 1;
+        match *entry {
+            ConstantPoolEntry::Class { .. } => {
+                let name = pool.get_class_name(index)?;
+                if name.starts_with('[') {
+                    descriptors.push(name);
+                } else {
+                    dependencies.insert(Dependency::Class(name));
+                }
+            },
+            ConstantPoolEntry::Fieldref { .. } => {
+                let field = pool.get_field_ref(index)?;
+                dependencies.insert(Dependency::Member(field.class_name, format!("field {}", field.name_and_type.name)));
+                descriptors.push(field.name_and_type.descriptor);
+            },
+            ConstantPoolEntry::Methodref { .. } | ConstantPoolEntry::InterfaceMethodref { .. } => {
+                let method = pool.get_method_ref(index)?;
+                dependencies.insert(Dependency::Member(method.class_name, format!("method {}", method.name_and_type.name)));
+                descriptors.push(method.name_and_type.descriptor);
+            },
+            _ => {}
+        }
+    }
+    for field in class_file.fields.iter() {
+        descriptors.push(pool.get_utf8(field.descriptor_index)?);
+    }
+    for method in class_file.methods.iter() {
+        descriptors.push(pool.get_utf8(method.descriptor_index)?);
+    }
+    for descriptor in descriptors.iter() {
+        for class in descriptor_classes(descriptor) {
+            dependencies.insert(Dependency::Class(String::from(class)));
+        }
+    }
+    for supertype in api(class_file)?.supertypes.into_iter() {
+        dependencies.insert(Dependency::Supertype(supertype));
+    }
+    for (owner, name) in constants.iter() {
+        dependencies.insert(Dependency::Member(owner.clone(), format!("field {}", name)));
+    }
+
+    let this_class = pool.get_class_name(class_file.this_class)?;
+    dependencies.retain(|dependency| match *dependency {
+        Dependency::Class(ref class) | Dependency::Supertype(ref class) | Dependency::Member(ref class, _) => *class != this_class
+    });
+    Ok(dependencies)
+}
+
+// The class names in a field or method descriptor, or in the name of an array class.
+fn descriptor_classes(descriptor: &str) -> Vec<&str> {
+    let mut classes = Vec::new();
+    let mut rest = descriptor;
+    while let Some(start) = rest.find('L') {
+        match rest[start..].find(';') {
+            Some(end) => {
+                classes.push(&rest[start + 1..start + end]);
+                rest = &rest[start + end + 1..];
+            },
+            None => break
+        }
+    }
+    classes
+}
+
+// A class compiled from a source file, with the fingerprints of its dependencies when it was
+// compiled.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompiledClass {
+    pub name: String,
+    pub api: Api,
+    pub uses: BTreeMap<Dependency, u64>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompiledSource {
+    pub hash: u64,
+    pub classes: Vec<CompiledClass>
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Cache {
+    pub class_path: String,
+    pub sources: BTreeMap<String, CompiledSource>
+}
+
+impl Cache {
+
+    pub fn new(class_path: &str) -> Cache {
+        Cache { class_path: String::from(class_path), sources: BTreeMap::new() }
+    }
+
+    // Reads a cache, or gives None if there is none or it can't be read.
+    pub fn load(path: &Path) -> Option<Cache> {
+        Cache::parse(&fs::read_to_string(path).ok()?)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    // The cache is a text file with a line for each source file, class, member and dependency.
+    pub fn parse(text: &str) -> Option<Cache> {
+        let mut lines = text.lines();
+        if lines.next()? != HEADER {
+            return None;
+        }
+        let mut cache = Cache::new(lines.next()?.strip_prefix("classpath ")?);
+        let mut source: Option<&mut CompiledSource> = None;
+
+        for line in lines {
+            let (kind, rest) = line.split_once(' ')?;
+            match kind {
+                "source" => {
+                    let (hash, path) = rest.split_once(' ')?;
+                    let compiled = CompiledSource { hash: u64::from_str_radix(hash, 16).ok()?, classes: Vec::new() };
+                    source = Some(cache.sources.entry(String::from(path)).or_insert(compiled));
+                },
+                "class" => {
+                    let (header, name) = rest.split_once(' ')?;
+                    let api = Api { supertypes: Vec::new(), header: u64::from_str_radix(header, 16).ok()?, members: BTreeMap::new() };
+                    source.as_mut()?.classes.push(CompiledClass { name: String::from(name), api, uses: BTreeMap::new() });
+                },
+                _ => {
+                    let class = source.as_mut()?.classes.last_mut()?;
+                    let mut fields = rest.split(' ');
+                    match (kind, fields.next(), fields.next(), fields.next(), fields.next()) {
+                        ("supertype", Some(name), None, None, None) => class.api.supertypes.push(String::from(name)),
+                        ("member", Some(hash), Some(kind), Some(name), None) => {
+                            class.api.members.insert(format!("{} {}", kind, name), u64::from_str_radix(hash, 16).ok()?);
+                        },
+                        ("uses", Some(hash), Some(kind), Some(owner), member) => {
+                            let dependency = match (kind, member) {
+                                ("class", None) => Dependency::Class(String::from(owner)),
+                                ("supertype", None) => Dependency::Supertype(String::from(owner)),
+                                (_, Some(name)) => Dependency::Member(String::from(owner), format!("{} {}", kind, name)),
+                                _ => return None
+                            };
+                            class.uses.insert(dependency, u64::from_str_radix(hash, 16).ok()?);
+                        },
+                        _ => return None
+                    }
+                }
+            }
+        }
+        Some(cache)
+    }
+
+    // The source file a class was compiled from.
+    fn class(&self, name: &str) -> Option<&CompiledClass> {
+        self.sources.values().flat_map(|source| source.classes.iter()).find(|class| class.name == name)
+    }
+
+    // A hash of what a dependency refers to in the classes compiled so far, including what the
+    // class inherits from the superclasses and superinterfaces compiled from source files. It
+    // is None for a class that wasn't compiled from a source file.
+    pub fn fingerprint(&self, dependency: &Dependency) -> Option<u64> {
+        let owner = match *dependency {
+            Dependency::Class(ref owner) | Dependency::Supertype(ref owner) | Dependency::Member(ref owner, _) => owner
+        };
+        self.class(owner)?;
+        let mut hasher = Fnv::new();
+        self.fingerprint_into(dependency, owner, &mut hasher, &mut HashSet::new());
+        Some(hasher.finish())
+    }
+
+    fn fingerprint_into(&self, dependency: &Dependency, owner: &str, hasher: &mut Fnv, visited: &mut HashSet<String>) {
+        // Superclass cycles are errors, but the cache may have been written by a run that
+        // reported them.
+        if !visited.insert(String::from(owner)) {
+            return;
+        }
+        let class = match self.class(owner) {
+            Some(class) => class,
+            None => return
+        };
+        owner.hash(hasher);
+        match *dependency {
+            Dependency::Class(_) => class.api.header.hash(hasher),
+            Dependency::Supertype(_) => {
+                class.api.header.hash(hasher);
+                class.api.members.hash(hasher);
+            },
+            Dependency::Member(_, ref member) => class.api.members.get(member).hash(hasher)
+        }
+        for supertype in class.api.supertypes.iter() {
+            self.fingerprint_into(dependency, supertype, hasher, visited);
+        }
+    }
+
+    // Records the classes compiled from a source file. The fingerprints of their dependencies
+    // are recorded by fingerprint_uses once all the classes compiled together are recorded.
+    pub fn record(&mut self, path: &str, hash: u64, classes: Vec<(String, Api, BTreeSet<Dependency>)>) {
+        let classes = classes.into_iter()
+            .map(|(name, api, dependencies)| CompiledClass { name, api, uses: dependencies.into_iter().map(|dependency| (dependency, 0)).collect() })
+            .collect();
+        self.sources.insert(String::from(path), CompiledSource { hash, classes });
+    }
+
+    // Fingerprints the dependencies of the classes compiled from source files, dropping those on
+    // classes that weren't compiled from source files.
+    pub fn fingerprint_uses(&mut self, paths: &[String]) {
+        for path in paths.iter() {
+            let classes = match self.sources.get(path) {
+                Some(source) => &source.classes,
+                None => continue
+            };
+            let uses: Vec<BTreeMap<Dependency, u64>> = classes.iter().map(|class| {
+                class.uses.keys().filter_map(|dependency| self.fingerprint(dependency).map(|hash| (dependency.clone(), hash))).collect()
+            }).collect();
+            let source = self.sources.get_mut(path).unwrap();
+            for (class, uses) in source.classes.iter_mut().zip(uses) {
+                class.uses = uses;
+            }
+        }
+    }
+
+    // Whether a source file must be recompiled because something one of its classes uses has
+    // changed since it was compiled.
+    pub fn is_stale(&self, path: &str) -> bool {
+        self.sources.get(path).is_some_and(|source| source.classes.iter().any(|class| {
+            class.uses.iter().any(|(dependency, &hash)| self.fingerprint(dependency) != Some(hash))
+        }))
+    }
+
+}
+
+impl fmt::Display for Cache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut text = format!("{}\nclasspath {}\n", HEADER, self.class_path);
+        for (path, source) in self.sources.iter() {
+            text.push_str(&format!("source {:016x} {}\n", source.hash, path));
+            for class in source.classes.iter() {
+                text.push_str(&format!("class {:016x} {}\n", class.api.header, class.name));
+                for supertype in class.api.supertypes.iter() {
+                    text.push_str(&format!("supertype {}\n", supertype));
+                }
+                for (member, hash) in class.api.members.iter() {
+                    text.push_str(&format!("member {:016x} {}\n", hash, member));
+                }
+                for (dependency, hash) in class.uses.iter() {
+                    let used = match *dependency {
+                        Dependency::Class(ref owner) => format!("class {}", owner),
+                        Dependency::Supertype(ref owner) => format!("supertype {}", owner),
+                        Dependency::Member(ref owner, ref member) => {
+                            let (kind, name) = member.split_once(' ').unwrap_or((member, ""));
+                            format!("{} {} {}", kind, owner, name)
+                        }
+                    };
+                    text.push_str(&format!("uses {:016x} {}\n", hash, used));
+                }
+            }
+        }
+        f.write_str(&text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn api(supertypes: &[&str], members: &[(&str, u64)]) -> Api {
+        Api {
+            supertypes: supertypes.iter().map(|&supertype| String::from(supertype)).collect(),
+            header: 1,
+            members: members.iter().map(|&(member, hash)| (String::from(member), hash)).collect()
+        }
+    }
+
+    fn member(owner: &str, member: &str) -> Dependency {
+        Dependency::Member(String::from(owner), String::from(member))
+    }
+
+    // B extends C, and A uses B.m and the constant C.X.
+    fn cache() -> Cache {
+        let mut cache = Cache::new(".");
+        let uses: BTreeSet<Dependency> = vec![member("B", "method m"), member("C", "field X"), member("java/lang/Object", "method <init>")].into_iter().collect();
+        cache.record("A.java", 1, vec![(String::from("A"), api(&["java/lang/Object"], &[]), uses)]);
+        cache.record("B.java", 2, vec![(String::from("B"), api(&["C"], &[("method m", 10)]), vec![Dependency::Supertype(String::from("C"))].into_iter().collect())]);
+        cache.record("C.java", 3, vec![(String::from("C"), api(&["java/lang/Object"], &[("field X", 20), ("method m", 30)]), BTreeSet::new())]);
+        cache.fingerprint_uses(&[String::from("A.java"), String::from("B.java"), String::from("C.java")]);
+        cache
+    }
+
+    #[test]
+    fn changed_members_make_users_stale() {
+        let mut cache = cache();
+        assert!(!cache.is_stale("A.java") && !cache.is_stale("B.java"));
+        // Only dependencies on classes compiled from source files are tracked.
+        assert_eq!(cache.sources["A.java"].classes[0].uses.len(), 2);
+
+        // A new overload of m in the superclass of B.
+        cache.record("C.java", 4, vec![(String::from("C"), api(&["java/lang/Object"], &[("field X", 20), ("method m", 31)]), BTreeSet::new())]);
+        assert!(cache.is_stale("A.java") && cache.is_stale("B.java"));
+
+        // A change in the body of a method isn't a change to the API.
+        let mut cache = self::cache();
+        cache.record("C.java", 4, vec![(String::from("C"), api(&["java/lang/Object"], &[("field X", 20), ("method m", 30), ("method n", 40)]), BTreeSet::new())]);
+        assert!(!cache.is_stale("A.java"));
+        assert!(cache.is_stale("B.java"));
+    }
+
+    #[test]
+    fn round_trips_through_text() {
+        let cache = cache();
+        let text = cache.to_string();
+
+        assert!(text.starts_with("ironjdk javac cache 1\nclasspath .\nsource 0000000000000001 A.java\nclass 0000000000000001 A\nsupertype java/lang/Object\nuses "));
+        assert_eq!(Cache::parse(&text), Some(cache));
+        assert_eq!(Cache::parse("something else"), None);
+    }
+
+    #[test]
+    fn finds_classes_in_descriptors() {
+        assert_eq!(descriptor_classes("(I[Ljava/lang/String;J)LA;"), vec!["java/lang/String", "A"]);
+        assert_eq!(descriptor_classes("[[I"), Vec::<&str>::new());
+    }
+
+}
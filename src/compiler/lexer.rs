@@ -0,0 +1,939 @@
+// The lexical structure of Java source files (JLS §3). Unicode escapes are translated first, and
+// the translated characters are split into tokens, skipping white space and comments.
+
+use std::char;
+use std::fmt;
+
+use compiler::{Diagnostic, Position};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Keyword {
+    Abstract, Assert, Boolean, Break, Byte, Case, Catch, Char, Class, Const, Continue, Default, Do,
+    Double, Else, Enum, Extends, False, Final, Finally, Float, For, Goto, If, Implements, Import,
+    Instanceof, Int, Interface, Long, Native, New, Null, Package, Private, Protected, Public,
+    Return, Short, Static, Strictfp, Super, Switch, Synchronized, This, Throw, Throws, Transient,
+    True, Try, Void, Volatile, While, Underscore
+}
+
+const KEYWORDS: &[(&str, Keyword)] = &[
+    ("abstract", Keyword::Abstract), ("assert", Keyword::Assert), ("boolean", Keyword::Boolean),
+    ("break", Keyword::Break), ("byte", Keyword::Byte), ("case", Keyword::Case),
+    ("catch", Keyword::Catch), ("char", Keyword::Char), ("class", Keyword::Class),
+    ("const", Keyword::Const), ("continue", Keyword::Continue), ("default", Keyword::Default),
+    ("do", Keyword::Do), ("double", Keyword::Double), ("else", Keyword::Else),
+    ("enum", Keyword::Enum), ("extends", Keyword::Extends), ("false", Keyword::False),
+    ("final", Keyword::Final), ("finally", Keyword::Finally), ("float", Keyword::Float),
+    ("for", Keyword::For), ("goto", Keyword::Goto), ("if", Keyword::If),
+    ("implements", Keyword::Implements), ("import", Keyword::Import),
+    ("instanceof", Keyword::Instanceof), ("int", Keyword::Int), ("interface", Keyword::Interface),
+    ("long", Keyword::Long), ("native", Keyword::Native), ("new", Keyword::New),
+    ("null", Keyword::Null), ("package", Keyword::Package), ("private", Keyword::Private),
+    ("protected", Keyword::Protected), ("public", Keyword::Public), ("return", Keyword::Return),
+    ("short", Keyword::Short), ("static", Keyword::Static), ("strictfp", Keyword::Strictfp),
+    ("super", Keyword::Super), ("switch", Keyword::Switch),
+    ("synchronized", Keyword::Synchronized), ("this", Keyword::This), ("throw", Keyword::Throw),
+    ("throws", Keyword::Throws), ("transient", Keyword::Transient), ("true", Keyword::True),
+    ("try", Keyword::Try), ("void", Keyword::Void), ("volatile", Keyword::Volatile),
+    ("while", Keyword::While), ("_", Keyword::Underscore)
+];
+
+impl Keyword {
+
+    pub fn from_name(name: &str) -> Option<Keyword> {
+        KEYWORDS.iter().find(|&&(n, _)| n == name).map(|&(_, keyword)| keyword)
+    }
+
+    pub fn name(self) -> &'static str {
+        KEYWORDS.iter().find(|&&(_, k)| k == self).map(|&(name, _)| name).unwrap()
+    }
+
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TokenKind {
+    Identifier(String),
+    Keyword(Keyword),
+    // Decimal 2147483648 and 9223372036854775808L are only valid as the operand of unary minus,
+    // which the parser checks.
+    IntLiteral { value: u32, negation_only: bool },
+    LongLiteral { value: u64, negation_only: bool },
+    FloatLiteral(f32),
+    DoubleLiteral(f64),
+    CharLiteral(u16),
+    // String literals and text blocks, as UTF-16 code units.
+    StringLiteral(Vec<u16>),
+    LParen, RParen, LBrace, RBrace, LBracket, RBracket, Semicolon, Comma, Dot, Ellipsis, At,
+    ColonColon,
+    Assign, Gt, Lt, Bang, Tilde, Question, Colon, Arrow, EqEq, Ge, Le, Ne, AndAnd, OrOr,
+    PlusPlus, MinusMinus, Plus, Minus, Star, Slash, Amp, Bar, Caret, Percent, Shl, Shr, UShr,
+    PlusAssign, MinusAssign, StarAssign, SlashAssign, AmpAssign, BarAssign, CaretAssign,
+    PercentAssign, ShlAssign, ShrAssign, UShrAssign,
+    Eof
+}
+
+const OPERATORS: &[(&str, TokenKind)] = &[
+    (">>>=", TokenKind::UShrAssign), ("<<=", TokenKind::ShlAssign), (">>=", TokenKind::ShrAssign),
+    (">>>", TokenKind::UShr), ("...", TokenKind::Ellipsis),
+    ("::", TokenKind::ColonColon), ("->", TokenKind::Arrow), ("==", TokenKind::EqEq),
+    (">=", TokenKind::Ge), ("<=", TokenKind::Le), ("!=", TokenKind::Ne), ("&&", TokenKind::AndAnd),
+    ("||", TokenKind::OrOr), ("++", TokenKind::PlusPlus), ("--", TokenKind::MinusMinus),
+    ("<<", TokenKind::Shl), (">>", TokenKind::Shr), ("+=", TokenKind::PlusAssign),
+    ("-=", TokenKind::MinusAssign), ("*=", TokenKind::StarAssign), ("/=", TokenKind::SlashAssign),
+    ("&=", TokenKind::AmpAssign), ("|=", TokenKind::BarAssign), ("^=", TokenKind::CaretAssign),
+    ("%=", TokenKind::PercentAssign),
+    ("(", TokenKind::LParen), (")", TokenKind::RParen), ("{", TokenKind::LBrace),
+    ("}", TokenKind::RBrace), ("[", TokenKind::LBracket), ("]", TokenKind::RBracket),
+    (";", TokenKind::Semicolon), (",", TokenKind::Comma), (".", TokenKind::Dot),
+    ("@", TokenKind::At), ("=", TokenKind::Assign), (">", TokenKind::Gt), ("<", TokenKind::Lt),
+    ("!", TokenKind::Bang), ("~", TokenKind::Tilde), ("?", TokenKind::Question),
+    (":", TokenKind::Colon), ("+", TokenKind::Plus), ("-", TokenKind::Minus),
+    ("*", TokenKind::Star), ("/", TokenKind::Slash), ("&", TokenKind::Amp), ("|", TokenKind::Bar),
+    ("^", TokenKind::Caret), ("%", TokenKind::Percent)
+];
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TokenKind::Identifier(ref name) => write!(f, "{}", name),
+            TokenKind::Keyword(keyword) => write!(f, "{}", keyword.name()),
+            TokenKind::IntLiteral { .. } | TokenKind::LongLiteral { .. } | TokenKind::FloatLiteral(_)
+                | TokenKind::DoubleLiteral(_) | TokenKind::CharLiteral(_) | TokenKind::StringLiteral(_) => write!(f, "<literal>"),
+            TokenKind::Eof => write!(f, "<EOF>"),
+            ref operator => {
+                let text = OPERATORS.iter().find(|entry| entry.1 == *operator).map(|entry| entry.0).unwrap();
+                write!(f, "{}", text)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub position: Position,
+    // The position just past the last character of the token.
+    pub end: Position
+}
+
+// A character after Unicode escapes have been translated. Escapes of unpaired surrogates are kept
+// as their code unit, so values are not always valid chars.
+#[derive(Clone, Copy)]
+struct SourceChar {
+    value: u32,
+    position: Position
+}
+
+pub fn tokenize(file: &str, source: &str) -> Result<Vec<Token>, Diagnostic> {
+    let characters = translate_unicode_escapes(file, source)?;
+    let end = end_position(source);
+    let mut lexer = Lexer { file, characters, index: 0, end };
+    let mut tokens = Vec::new();
+
+    loop {
+        let token = lexer.next_token()?;
+        let eof = token.kind == TokenKind::Eof;
+        tokens.push(token);
+        if eof {
+            return Ok(tokens);
+        }
+    }
+}
+
+fn end_position(source: &str) -> Position {
+    let mut position = Position::new(1, 1);
+    let mut previous = '\0';
+    for c in source.chars() {
+        position = advance(position, previous, c);
+        previous = c;
+    }
+    advance(position, previous, '\0')
+}
+
+// The position of a character, given the position and value of the one before it.
+fn advance(position: Position, previous: char, current: char) -> Position {
+    match previous {
+        '\n' => Position::new(position.line + 1, 1),
+        '\r' if current != '\n' => Position::new(position.line + 1, 1),
+        '\0' if position == Position::new(1, 1) => position,
+        _ => Position::new(position.line, position.column + 1)
+    }
+}
+
+// JLS §3.3: \uXXXX, with any number of u's, is an escape unless the backslash is itself preceded
+// by an odd number of backslashes.
+fn translate_unicode_escapes(file: &str, source: &str) -> Result<Vec<SourceChar>, Diagnostic> {
+    let raw: Vec<char> = source.chars().collect();
+    let mut positions = Vec::with_capacity(raw.len());
+    let mut position = Position::new(1, 1);
+    for (i, &c) in raw.iter().enumerate() {
+        if i > 0 {
+            position = advance(position, raw[i - 1], c);
+        }
+        positions.push(position);
+    }
+
+    let mut characters: Vec<SourceChar> = Vec::with_capacity(raw.len());
+    let mut backslashes = 0;
+    let mut i = 0;
+    while i < raw.len() {
+        let c = raw[i];
+        let position = positions[i];
+
+        if c == '\\' && backslashes % 2 == 0 && raw.get(i + 1) == Some(&'u') {
+            let mut j = i + 1;
+            while raw.get(j) == Some(&'u') {
+                j += 1;
+            }
+
+            let digits: String = raw[j..].iter().take(4).collect();
+            let unit = match u32::from_str_radix(&digits, 16) {
+                Ok(unit) if digits.len() == 4 && digits.chars().all(|c| c.is_ascii_hexdigit()) => unit,
+                _ => return Err(Diagnostic::new(file, position, "illegal unicode escape"))
+            };
+
+            let combined = match characters.last() {
+                Some(high) if (0xDC00..0xE000).contains(&unit) && (0xD800..0xDC00).contains(&high.value) => {
+                    Some(0x10000 + ((high.value - 0xD800) << 10) + (unit - 0xDC00))
+                },
+                _ => None
+            };
+            match combined {
+                Some(value) => characters.last_mut().unwrap().value = value,
+                None => characters.push(SourceChar { value: unit, position })
+            }
+
+            backslashes = 0;
+            i = j + 4;
+            continue;
+        }
+
+        backslashes = if c == '\\' { backslashes + 1 } else { 0 };
+        characters.push(SourceChar { value: c as u32, position });
+        i += 1;
+    }
+
+    Ok(characters)
+}
+
+fn is_line_terminator(c: u32) -> bool {
+    c == '\n' as u32 || c == '\r' as u32
+}
+
+// White space as defined by Character.isWhitespace, used for text block indentation.
+fn is_whitespace(c: u32) -> bool {
+    match char::from_u32(c) {
+        Some('\u{00A0}') | Some('\u{2007}') | Some('\u{202F}') => false,
+        Some(c) => c.is_whitespace() || ('\u{1C}'..='\u{1F}').contains(&c),
+        None => false
+    }
+}
+
+fn is_identifier_start(c: u32) -> bool {
+    match char::from_u32(c) {
+        Some(c) => c.is_alphabetic() || c == '_' || c == '$',
+        None => false
+    }
+}
+
+fn is_identifier_part(c: u32) -> bool {
+    match char::from_u32(c) {
+        Some(c) => c.is_alphanumeric() || c == '_' || c == '$' || (c as u32) < 0x09 || ('\u{0E}'..='\u{1B}').contains(&c) || c == '\u{7F}',
+        None => false
+    }
+}
+
+fn digit_value(c: u32, radix: u32) -> Option<u32> {
+    char::from_u32(c).and_then(|c| c.to_digit(radix))
+}
+
+fn to_utf16(value: u32, units: &mut Vec<u16>) {
+    match char::from_u32(value) {
+        Some(c) => {
+            let mut buffer = [0u16; 2];
+            units.extend_from_slice(c.encode_utf16(&mut buffer));
+        },
+        None => units.push(value as u16)
+    }
+}
+
+struct Lexer<'a> {
+    file: &'a str,
+    characters: Vec<SourceChar>,
+    index: usize,
+    end: Position
+}
+
+impl<'a> Lexer<'a> {
+
+    fn peek(&self, offset: usize) -> Option<u32> {
+        self.characters.get(self.index + offset).map(|c| c.value)
+    }
+
+    fn is(&self, offset: usize, c: char) -> bool {
+        self.peek(offset) == Some(c as u32)
+    }
+
+    fn position(&self) -> Position {
+        self.characters.get(self.index).map(|c| c.position).unwrap_or(self.end)
+    }
+
+    fn error<T>(&self, position: Position, message: &str) -> Result<T, Diagnostic> {
+        Err(Diagnostic::new(self.file, position, message))
+    }
+
+    fn skip_white_space_and_comments(&mut self) -> Result<(), Diagnostic> {
+        loop {
+            match self.peek(0) {
+                Some(c) if c == ' ' as u32 || c == '\t' as u32 || c == 0x0C || is_line_terminator(c) => self.index += 1,
+                Some(0x1A) if self.index == self.characters.len() - 1 => self.index += 1,
+                Some(_) if self.is(0, '/') && self.is(1, '/') => {
+                    while self.peek(0).is_some_and(|c| !is_line_terminator(c)) {
+                        self.index += 1;
+                    }
+                },
+                Some(_) if self.is(0, '/') && self.is(1, '*') => {
+                    let start = self.position();
+                    self.index += 2;
+                    loop {
+                        if self.peek(0).is_none() {
+                            return self.error(start, "unclosed comment");
+                        }
+                        if self.is(0, '*') && self.is(1, '/') {
+                            self.index += 2;
+                            break;
+                        }
+                        self.index += 1;
+                    }
+                },
+                _ => return Ok(())
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Token, Diagnostic> {
+        self.skip_white_space_and_comments()?;
+
+        let position = self.position();
+        let kind = match self.peek(0) {
+            None => TokenKind::Eof,
+            Some(c) if is_identifier_start(c) => self.identifier(),
+            Some(c) if digit_value(c, 10).is_some() => self.number(position)?,
+            Some(_) if self.is(0, '.') && self.peek(1).and_then(|c| digit_value(c, 10)).is_some() => self.number(position)?,
+            Some(_) if self.is(0, '"') && self.is(1, '"') && self.is(2, '"') => self.text_block(position)?,
+            Some(_) if self.is(0, '"') => self.string(position)?,
+            Some(_) if self.is(0, '\'') => self.character(position)?,
+            Some(_) => self.operator(position)?
+        };
+
+        Ok(Token { kind, position, end: self.position() })
+    }
+
+    fn identifier(&mut self) -> TokenKind {
+        let mut name = String::new();
+        while let Some(c) = self.peek(0) {
+            if !is_identifier_part(c) {
+                break;
+            }
+            if let Some(c) = char::from_u32(c) {
+                name.push(c);
+            }
+            self.index += 1;
+        }
+
+        match Keyword::from_name(&name) {
+            Some(keyword) => TokenKind::Keyword(keyword),
+            None => TokenKind::Identifier(name)
+        }
+    }
+
+    fn operator(&mut self, position: Position) -> Result<TokenKind, Diagnostic> {
+        for &(text, ref kind) in OPERATORS {
+            if text.chars().enumerate().all(|(i, c)| self.is(i, c)) {
+                self.index += text.len();
+                return Ok(kind.clone());
+            }
+        }
+
+        let c = self.peek(0).unwrap();
+        match char::from_u32(c) {
+            Some(c) if c.is_ascii_graphic() => self.error(position, &format!("illegal character: '{}'", c)),
+            _ => self.error(position, &format!("illegal character: '\\u{:04x}'", c))
+        }
+    }
+
+    // Digits of the given radix, possibly separated by underscores. Underscores may not start or
+    // end the digits.
+    fn digits(&mut self, radix: u32, digits: &mut String) -> Result<(), Diagnostic> {
+        let start = digits.len();
+        while let Some(c) = self.peek(0) {
+            if c == '_' as u32 {
+                if digits.len() == start {
+                    return self.error(self.position(), "illegal underscore");
+                }
+                let mut j = 0;
+                while self.is(j, '_') {
+                    j += 1;
+                }
+                if self.peek(j).and_then(|c| digit_value(c, radix)).is_none() {
+                    return self.error(self.characters[self.index + j - 1].position, "illegal underscore");
+                }
+                self.index += j;
+            } else if let Some(digit) = digit_value(c, radix) {
+                digits.push(char::from_digit(digit, radix).unwrap());
+                self.index += 1;
+            } else {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // JLS §3.10.1 and §3.10.2.
+    fn number(&mut self, position: Position) -> Result<TokenKind, Diagnostic> {
+        let hex = self.is(0, '0') && (self.is(1, 'x') || self.is(1, 'X'));
+        let binary = self.is(0, '0') && (self.is(1, 'b') || self.is(1, 'B'));
+
+        if hex || binary {
+            self.index += 2;
+            let radix = if hex { 16 } else { 2 };
+            let mut digits = String::new();
+            self.digits(radix, &mut digits)?;
+
+            if hex && (self.is(0, '.') || self.is(0, 'p') || self.is(0, 'P')) {
+                return self.hexadecimal_floating_point(position, digits);
+            }
+            if digits.is_empty() {
+                let message = if hex { "hexadecimal numbers must contain at least one hexadecimal digit" } else { "binary numbers must contain at least one binary digit" };
+                return self.error(position, message);
+            }
+            return self.integer(position, &digits, radix, false);
+        }
+
+        let mut digits = String::new();
+        self.digits(10, &mut digits)?;
+
+        let floating_point = self.is(0, '.') && !self.is(1, '.')
+            || self.is(0, 'e') || self.is(0, 'E')
+            || self.is(0, 'f') || self.is(0, 'F') || self.is(0, 'd') || self.is(0, 'D');
+        if floating_point {
+            return self.decimal_floating_point(position, digits);
+        }
+
+        if digits.len() > 1 && digits.starts_with('0') {
+            if let Some(c) = digits.chars().find(|c| c.to_digit(8).is_none()) {
+                return self.error(position, &format!("illegal digit in an octal literal: {}", c));
+            }
+            let octal = String::from(&digits[1..]);
+            return self.integer(position, &octal, 8, false);
+        }
+
+        self.integer(position, &digits, 10, true)
+    }
+
+    fn integer(&mut self, position: Position, digits: &str, radix: u32, decimal: bool) -> Result<TokenKind, Diagnostic> {
+        let long = self.is(0, 'l') || self.is(0, 'L');
+        if long {
+            self.index += 1;
+        }
+        if self.peek(0).is_some_and(is_identifier_part) {
+            return self.error(self.position(), "';' expected");
+        }
+
+        let value = digits.chars().try_fold(0u64, |value, c| {
+            value.checked_mul(radix as u64).and_then(|value| value.checked_add(c.to_digit(radix).unwrap() as u64))
+        });
+
+        let (limit, negation_limit) = match (long, decimal) {
+            (false, true) => (i32::MAX as u64, 1 << 31),
+            (false, false) => (u32::MAX as u64, u32::MAX as u64),
+            (true, true) => (i64::MAX as u64, 1 << 63),
+            (true, false) => (u64::MAX, u64::MAX)
+        };
+        let value = match value {
+            Some(value) if value <= negation_limit => value,
+            _ => return self.error(position, "integer number too large")
+        };
+
+        let negation_only = value > limit;
+        Ok(if long {
+            TokenKind::LongLiteral { value, negation_only }
+        } else {
+            TokenKind::IntLiteral { value: value as u32, negation_only }
+        })
+    }
+
+    // A float suffix, or a double one when there is no suffix.
+    fn floating_point_suffix(&mut self) -> bool {
+        if self.is(0, 'f') || self.is(0, 'F') {
+            self.index += 1;
+            true
+        } else {
+            if self.is(0, 'd') || self.is(0, 'D') {
+                self.index += 1;
+            }
+            false
+        }
+    }
+
+    fn decimal_floating_point(&mut self, position: Position, mut digits: String) -> Result<TokenKind, Diagnostic> {
+        if self.is(0, '.') {
+            self.index += 1;
+            digits.push('.');
+            if self.is(0, '_') {
+                return self.error(self.position(), "illegal underscore");
+            }
+            self.digits(10, &mut digits)?;
+        }
+
+        if self.is(0, 'e') || self.is(0, 'E') {
+            self.index += 1;
+            digits.push('e');
+            if self.is(0, '+') || self.is(0, '-') {
+                digits.push(if self.is(0, '-') { '-' } else { '+' });
+                self.index += 1;
+            }
+            let length = digits.len();
+            self.digits(10, &mut digits)?;
+            if digits.len() == length {
+                return self.error(position, "malformed floating-point literal");
+            }
+        }
+
+        let float = self.floating_point_suffix();
+        if self.peek(0).is_some_and(is_identifier_part) {
+            return self.error(self.position(), "';' expected");
+        }
+
+        let zero = !digits.split('e').next().unwrap().chars().any(|c| c.is_ascii_digit() && c != '0');
+        if float {
+            let value: f32 = digits.parse().map_err(|_| Diagnostic::new(self.file, position, "malformed floating-point literal"))?;
+            self.check_floating_point(position, value.is_infinite(), value == 0.0 && !zero)?;
+            Ok(TokenKind::FloatLiteral(value))
+        } else {
+            let value: f64 = digits.parse().map_err(|_| Diagnostic::new(self.file, position, "malformed floating-point literal"))?;
+            self.check_floating_point(position, value.is_infinite(), value == 0.0 && !zero)?;
+            Ok(TokenKind::DoubleLiteral(value))
+        }
+    }
+
+    fn check_floating_point(&self, position: Position, too_large: bool, too_small: bool) -> Result<(), Diagnostic> {
+        if too_large {
+            self.error(position, "floating-point number too large")
+        } else if too_small {
+            self.error(position, "floating-point number too small")
+        } else {
+            Ok(())
+        }
+    }
+
+    // 0x1.8p1 is 3.0. The significand keeps its first 60 bits, with a sticky bit standing for any
+    // non-zero digits after them, so that conversion rounds correctly.
+    fn hexadecimal_floating_point(&mut self, position: Position, integer_digits: String) -> Result<TokenKind, Diagnostic> {
+        let mut fraction_digits = String::new();
+        if self.is(0, '.') {
+            self.index += 1;
+            self.digits(16, &mut fraction_digits)?;
+        }
+        if integer_digits.is_empty() && fraction_digits.is_empty() {
+            return self.error(position, "hexadecimal numbers must contain at least one hexadecimal digit");
+        }
+        if !(self.is(0, 'p') || self.is(0, 'P')) {
+            return self.error(position, "malformed floating-point literal");
+        }
+        self.index += 1;
+
+        let negative = self.is(0, '-');
+        if self.is(0, '+') || self.is(0, '-') {
+            self.index += 1;
+        }
+        let mut exponent_digits = String::new();
+        self.digits(10, &mut exponent_digits)?;
+        if exponent_digits.is_empty() {
+            return self.error(position, "malformed floating-point literal");
+        }
+        let exponent: i64 = exponent_digits.parse::<i64>().unwrap_or(i64::MAX / 2).min(100_000);
+        let mut exponent = if negative { -exponent } else { exponent };
+
+        let float = self.floating_point_suffix();
+        if self.peek(0).is_some_and(is_identifier_part) {
+            return self.error(self.position(), "';' expected");
+        }
+
+        let all_digits = format!("{}{}", integer_digits, fraction_digits);
+        let significant = all_digits.trim_start_matches('0');
+        exponent -= 4 * fraction_digits.len() as i64;
+        let mut significand = 0u64;
+        let mut sticky = false;
+        for (i, c) in significant.chars().enumerate() {
+            let digit = c.to_digit(16).unwrap() as u64;
+            if i < 15 {
+                significand = significand << 4 | digit;
+            } else {
+                exponent += 4;
+                sticky |= digit != 0;
+            }
+        }
+        if sticky {
+            significand = significand << 1 | 1;
+            exponent -= 1;
+        }
+
+        let zero = significand == 0;
+        let scale = exponent.clamp(-2000, 2000) as i32;
+        if float {
+            let value = scale_by_power_of_two(significand as f64, scale) as f32;
+            self.check_floating_point(position, value.is_infinite(), value == 0.0 && !zero)?;
+            Ok(TokenKind::FloatLiteral(value))
+        } else {
+            let value = scale_by_power_of_two(significand as f64, scale);
+            self.check_floating_point(position, value.is_infinite(), value == 0.0 && !zero)?;
+            Ok(TokenKind::DoubleLiteral(value))
+        }
+    }
+
+    // Escape sequences (JLS §3.10.7), after the backslash.
+    fn escape(&mut self, units: &mut Vec<u16>) -> Result<(), Diagnostic> {
+        let position = self.characters[self.index - 1].position;
+        let c = match self.peek(0).and_then(char::from_u32) {
+            Some(c) => c,
+            None => return self.error(position, "illegal escape character")
+        };
+        self.index += 1;
+
+        let unit = match c {
+            'b' => 0x08,
+            't' => 0x09,
+            'n' => 0x0A,
+            'f' => 0x0C,
+            'r' => 0x0D,
+            's' => 0x20,
+            '"' => 0x22,
+            '\'' => 0x27,
+            '\\' => 0x5C,
+            '0'..='7' => {
+                let mut value = c.to_digit(8).unwrap();
+                let max_digits = if c <= '3' { 3 } else { 2 };
+                for _ in 1..max_digits {
+                    match self.peek(0).and_then(|c| digit_value(c, 8)) {
+                        Some(digit) => {
+                            value = value * 8 + digit;
+                            self.index += 1;
+                        },
+                        None => break
+                    }
+                }
+                value as u16
+            },
+            _ => return self.error(position, "illegal escape character")
+        };
+
+        units.push(unit);
+        Ok(())
+    }
+
+    fn string(&mut self, position: Position) -> Result<TokenKind, Diagnostic> {
+        self.index += 1;
+        let mut units = Vec::new();
+        loop {
+            match self.peek(0) {
+                None => return self.error(position, "unclosed string literal"),
+                Some(c) if is_line_terminator(c) => return self.error(position, "unclosed string literal"),
+                Some(c) if c == '"' as u32 => {
+                    self.index += 1;
+                    return Ok(TokenKind::StringLiteral(units));
+                },
+                Some(c) if c == '\\' as u32 => {
+                    self.index += 1;
+                    self.escape(&mut units)?;
+                },
+                Some(c) => {
+                    to_utf16(c, &mut units);
+                    self.index += 1;
+                }
+            }
+        }
+    }
+
+    fn character(&mut self, position: Position) -> Result<TokenKind, Diagnostic> {
+        self.index += 1;
+        let mut units = Vec::new();
+        match self.peek(0) {
+            Some(c) if c == '\'' as u32 => return self.error(position, "empty character literal"),
+            Some(c) if c == '\\' as u32 => {
+                self.index += 1;
+                self.escape(&mut units)?;
+            },
+            Some(c) if !is_line_terminator(c) => {
+                to_utf16(c, &mut units);
+                self.index += 1;
+            },
+            _ => return self.error(position, "illegal line end in character literal")
+        }
+
+        if units.len() != 1 || !self.is(0, '\'') {
+            return self.error(position, "unclosed character literal");
+        }
+        self.index += 1;
+        Ok(TokenKind::CharLiteral(units[0]))
+    }
+
+    // JLS §3.10.6. The opening delimiter must be followed by a line terminator. Line terminators
+    // are normalized to \n, incidental indentation and trailing white space are stripped as
+    // String.stripIndent does, and escape sequences are interpreted last.
+    fn text_block(&mut self, position: Position) -> Result<TokenKind, Diagnostic> {
+        self.index += 3;
+        while self.peek(0).is_some_and(|c| c == ' ' as u32 || c == '\t' as u32 || c == 0x0C) {
+            self.index += 1;
+        }
+        match self.peek(0) {
+            Some(c) if is_line_terminator(c) => self.skip_line_terminator(),
+            _ => return self.error(position, "illegal text block open delimiter sequence, missing line terminator")
+        }
+
+        let mut lines: Vec<Vec<SourceChar>> = vec![Vec::new()];
+        loop {
+            match self.peek(0) {
+                None => return self.error(position, "unclosed text block"),
+                Some(_) if self.is(0, '"') && self.is(1, '"') && self.is(2, '"') => {
+                    self.index += 3;
+                    break;
+                },
+                Some(c) if is_line_terminator(c) => {
+                    self.skip_line_terminator();
+                    lines.push(Vec::new());
+                },
+                Some(c) => {
+                    lines.last_mut().unwrap().push(self.characters[self.index]);
+                    self.index += 1;
+                    // An escaped character can't end the text block.
+                    if c == '\\' as u32 {
+                        if let Some(&escaped) = self.characters.get(self.index) {
+                            if !is_line_terminator(escaped.value) {
+                                lines.last_mut().unwrap().push(escaped);
+                                self.index += 1;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let blank = |line: &[SourceChar]| line.iter().all(|c| is_whitespace(c.value));
+        let indentation = |line: &[SourceChar]| line.iter().take_while(|c| is_whitespace(c.value)).count();
+        let last = lines.len() - 1;
+        let minimum = lines.iter()
+            .enumerate()
+            .filter(|&(i, line)| !blank(line) || i == last)
+            .map(|(_, line)| indentation(line))
+            .min()
+            .unwrap_or(0);
+
+        let mut content: Vec<SourceChar> = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            if !blank(line) {
+                let end = line.len() - line.iter().rev().take_while(|c| is_whitespace(c.value)).count();
+                content.extend_from_slice(&line[minimum..end]);
+            }
+            if i != last {
+                content.push(SourceChar { value: '\n' as u32, position });
+            }
+        }
+
+        // Interpret escapes in the stripped content, reusing the lexer's escape handling.
+        let mut escapes = Lexer { file: self.file, characters: content, index: 0, end: position };
+        let mut units = Vec::new();
+        while let Some(c) = escapes.peek(0) {
+            escapes.index += 1;
+            if c == '\\' as u32 {
+                if escapes.is(0, '\n') {
+                    escapes.index += 1;
+                } else {
+                    escapes.escape(&mut units)?;
+                }
+            } else {
+                to_utf16(c, &mut units);
+            }
+        }
+
+        Ok(TokenKind::StringLiteral(units))
+    }
+
+    fn skip_line_terminator(&mut self) {
+        if self.is(0, '\r') && self.is(1, '\n') {
+            self.index += 1;
+        }
+        self.index += 1;
+    }
+
+}
+
+fn scale_by_power_of_two(value: f64, exponent: i32) -> f64 {
+    let mut value = value;
+    let mut exponent = exponent;
+    // Scale in steps that stay within the range of normal doubles.
+    while exponent > 1000 {
+        value *= 2f64.powi(1000);
+        exponent -= 1000;
+    }
+    while exponent < -1000 {
+        value *= 2f64.powi(-1000);
+        exponent += 1000;
+    }
+    value * 2f64.powi(exponent)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<TokenKind> {
+        let mut kinds: Vec<TokenKind> = tokenize("Test.java", source).unwrap().into_iter().map(|token| token.kind).collect();
+        assert_eq!(kinds.pop(), Some(TokenKind::Eof));
+        kinds
+    }
+
+    fn kind(source: &str) -> TokenKind {
+        let mut kinds = kinds(source);
+        assert_eq!(kinds.len(), 1, "{:?}", kinds);
+        kinds.remove(0)
+    }
+
+    fn error(source: &str) -> String {
+        let diagnostic = tokenize("Test.java", source).unwrap_err();
+        format!("{}:{}: {}", diagnostic.position.line, diagnostic.position.column, diagnostic.message)
+    }
+
+    fn string(value: &str) -> TokenKind {
+        TokenKind::StringLiteral(value.encode_utf16().collect())
+    }
+
+    fn int(value: u32) -> TokenKind {
+        TokenKind::IntLiteral { value, negation_only: false }
+    }
+
+    fn long(value: u64) -> TokenKind {
+        TokenKind::LongLiteral { value, negation_only: false }
+    }
+
+    #[test]
+    fn identifiers_keywords_and_operators() {
+        assert_eq!(kinds("int $x_1 = a>>>=b; // comment\n/* block */ Ü::new->"), vec![
+            TokenKind::Keyword(Keyword::Int),
+            TokenKind::Identifier(String::from("$x_1")),
+            TokenKind::Assign,
+            TokenKind::Identifier(String::from("a")),
+            TokenKind::UShrAssign,
+            TokenKind::Identifier(String::from("b")),
+            TokenKind::Semicolon,
+            TokenKind::Identifier(String::from("Ü")),
+            TokenKind::ColonColon,
+            TokenKind::Keyword(Keyword::New),
+            TokenKind::Arrow
+        ]);
+        assert_eq!(kinds("var record"), vec![TokenKind::Identifier(String::from("var")), TokenKind::Identifier(String::from("record"))]);
+    }
+
+    #[test]
+    fn positions() {
+        let tokens = tokenize("Test.java", "a\r\n  bc\rd\n\te").unwrap();
+        let positions: Vec<(u32, u32)> = tokens.iter().map(|token| (token.position.line, token.position.column)).collect();
+
+        assert_eq!(positions, vec![(1, 1), (2, 3), (3, 1), (4, 2), (4, 3)]);
+        assert_eq!(tokens[1].end, Position::new(2, 5));
+    }
+
+    #[test]
+    fn unicode_escapes() {
+        assert_eq!(kinds("\\u0069nt \\uuu0061\\u003B"), vec![
+            TokenKind::Keyword(Keyword::Int),
+            TokenKind::Identifier(String::from("a")),
+            TokenKind::Semicolon
+        ]);
+        // \\u0041 is a backslash followed by u0041, not an escape.
+        assert_eq!(kind("\"\\\\u0041\""), string("\\u0041"));
+        assert_eq!(kind("\"\\u005c\\u005c\""), string("\\"));
+        assert_eq!(kind("\"\\uD83D\\uDE00\""), string("\u{1F600}"));
+        assert_eq!(kind("\"\\uD800\""), TokenKind::StringLiteral(vec![0xD800]));
+        // A translated line terminator ends a line comment.
+        assert_eq!(kind("// \\u000A x"), TokenKind::Identifier(String::from("x")));
+        assert_eq!(error("a \\u00G1"), "1:3: illegal unicode escape");
+    }
+
+    #[test]
+    fn integer_literals() {
+        assert_eq!(kind("0"), int(0));
+        assert_eq!(kind("1_000_000"), int(1000000));
+        assert_eq!(kind("0x7fff_FFFF"), int(0x7FFFFFFF));
+        assert_eq!(kind("0xFFFFFFFF"), int(0xFFFFFFFF));
+        assert_eq!(kind("0b1010"), int(10));
+        assert_eq!(kind("0777"), int(511));
+        assert_eq!(kind("0_7"), int(7));
+        assert_eq!(kind("2147483648"), TokenKind::IntLiteral { value: 1 << 31, negation_only: true });
+        assert_eq!(kind("123L"), long(123));
+        assert_eq!(kind("0xFFFFFFFFFFFFFFFFl"), long(u64::MAX));
+        assert_eq!(kind("9223372036854775808L"), TokenKind::LongLiteral { value: 1 << 63, negation_only: true });
+
+        assert_eq!(error("2147483649"), "1:1: integer number too large");
+        assert_eq!(error("0x1_0000_0000"), "1:1: integer number too large");
+        assert_eq!(error("1_"), "1:2: illegal underscore");
+        assert_eq!(error("0x_1"), "1:3: illegal underscore");
+        assert_eq!(error("09"), "1:1: illegal digit in an octal literal: 9");
+        assert_eq!(error("0x"), "1:1: hexadecimal numbers must contain at least one hexadecimal digit");
+    }
+
+    #[test]
+    fn floating_point_literals() {
+        assert_eq!(kind("1.5"), TokenKind::DoubleLiteral(1.5));
+        assert_eq!(kind("1."), TokenKind::DoubleLiteral(1.0));
+        assert_eq!(kind(".25"), TokenKind::DoubleLiteral(0.25));
+        assert_eq!(kind("1e3"), TokenKind::DoubleLiteral(1000.0));
+        assert_eq!(kind("1_0.5E-1d"), TokenKind::DoubleLiteral(1.05));
+        assert_eq!(kind("3f"), TokenKind::FloatLiteral(3.0));
+        assert_eq!(kind("0.1f"), TokenKind::FloatLiteral(0.1));
+        assert_eq!(kind("0x1.8p1"), TokenKind::DoubleLiteral(3.0));
+        assert_eq!(kind("0x.8P-1f"), TokenKind::FloatLiteral(0.25));
+        assert_eq!(kind("0x1.fffffffffffffp1023"), TokenKind::DoubleLiteral(f64::MAX));
+        assert_eq!(kind("4.9e-324"), TokenKind::DoubleLiteral(5e-324));
+        assert_eq!(kinds("1..2").len(), 3);
+
+        assert_eq!(error("1e400"), "1:1: floating-point number too large");
+        assert_eq!(error("1e-400"), "1:1: floating-point number too small");
+        assert_eq!(error("3.4e39f"), "1:1: floating-point number too large");
+        assert_eq!(error("1e"), "1:1: malformed floating-point literal");
+        assert_eq!(error("0x1.0"), "1:1: malformed floating-point literal");
+    }
+
+    #[test]
+    fn character_and_string_literals() {
+        assert_eq!(kind("'a'"), TokenKind::CharLiteral(0x61));
+        assert_eq!(kind("'\\n'"), TokenKind::CharLiteral(0x0A));
+        assert_eq!(kind("'\\''"), TokenKind::CharLiteral(0x27));
+        assert_eq!(kind("'\\377'"), TokenKind::CharLiteral(0xFF));
+        assert_eq!(kind("'\u{20AC}'"), TokenKind::CharLiteral(0x20AC));
+        assert_eq!(kind("\"a\\tb\\\"c\\\\\\0\\12\\s\""), string("a\tb\"c\\\0\n "));
+        assert_eq!(kind("\"\\400\""), string(" 0"));
+        assert_eq!(kind("\"\u{1F600}\""), string("\u{1F600}"));
+
+        assert_eq!(error("''"), "1:1: empty character literal");
+        assert_eq!(error("'ab'"), "1:1: unclosed character literal");
+        assert_eq!(error("'\u{1F600}'"), "1:1: unclosed character literal");
+        assert_eq!(error("\"abc\ndef\""), "1:1: unclosed string literal");
+        assert_eq!(error("\"\\q\""), "1:2: illegal escape character");
+        assert_eq!(error("x /* never closed"), "1:3: unclosed comment");
+        assert_eq!(error("a # b"), "1:3: illegal character: '#'");
+    }
+
+    #[test]
+    fn text_blocks() {
+        assert_eq!(kind("\"\"\"\n    Hello,\n      World!\n    \"\"\""), string("Hello,\n  World!\n"));
+        assert_eq!(kind("\"\"\"   \r\n  a  \r\n\r\n  b\"\"\""), string("a\n\nb"));
+        assert_eq!(kind("\"\"\"\n    a\n  \"\"\""), string("  a\n"));
+        assert_eq!(kind("\"\"\"\n  a\\\n  b\\s\\n\n  \"\"\""), string("ab \n\n"));
+        assert_eq!(kind("\"\"\"\n  \"quoted\" \\\"\"\"\n  \"\"\""), string("\"quoted\" \"\"\"\n"));
+
+        assert_eq!(error("\"\"\"abc\"\"\""), "1:1: illegal text block open delimiter sequence, missing line terminator");
+        assert_eq!(error("\"\"\"\nabc"), "1:1: unclosed text block");
+    }
+
+}
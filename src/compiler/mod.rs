@@ -1,3 +1,77 @@
-fn compiler() {
+// A compiler for the Java programming language. Source files are turned into tokens by the lexer
+// and into a syntax tree by the parser, which is attributed and checked, and class files are
+// generated from it.
 
-}
\ No newline at end of file
+pub mod ast;
+pub mod attr;
+pub mod code;
+pub mod constant;
+pub mod flow;
+pub mod gen;
+pub mod incremental;
+pub mod lexer;
+pub mod parser;
+pub mod symbols;
+pub mod types;
+
+use std::fmt;
+
+// A position in a source file. Lines and columns start at 1, and columns count Unicode code
+// points in the source as written, before Unicode escapes are translated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: u32,
+    pub column: u32
+}
+
+impl Position {
+
+    pub fn new(line: u32, column: u32) -> Position {
+        Position { line, column }
+    }
+
+}
+
+// An error in a source file, reported like javac does: file:line:column: error: message.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub file: String,
+    pub position: Position,
+    pub message: String,
+    // Whether the message is a simplified form of a more detailed one, which javac notes once
+    // after the errors.
+    pub simplified: bool
+}
+
+impl Diagnostic {
+
+    pub fn new(file: &str, position: Position, message: &str) -> Diagnostic {
+        Diagnostic { file: String::from(file), position, message: String::from(message), simplified: false }
+    }
+
+    // The first line of the diagnostic, the offending source line with a caret under the column,
+    // and then the rest of the message, which gives details such as the symbol not found.
+    pub fn render(&self, source: &str) -> String {
+        let line = source.lines().nth(self.position.line as usize - 1).unwrap_or("");
+        let indent: String = line.chars()
+            .take(self.position.column as usize - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        let mut message = self.message.splitn(2, '\n');
+        let summary = message.next().unwrap_or("");
+        let mut rendered = format!("{}:{}: error: {}\n{}\n{}^", self.file, self.position.line, summary, line, indent);
+        if let Some(details) = message.next() {
+            rendered.push('\n');
+            rendered.push_str(details);
+        }
+        rendered
+    }
+
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}: error: {}", self.file, self.position.line, self.position.column, self.message)
+    }
+}
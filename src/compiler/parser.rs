@@ -0,0 +1,2231 @@
+// A recursive descent parser for the Java 17 language (JLS chapters 7 to 15). Where the grammar
+// needs more lookahead, e.g. to tell a local variable declaration from an expression statement or
+// a cast from a parenthesized expression, the parser tries one alternative and backtracks.
+
+use compiler::{Diagnostic, Position};
+use compiler::ast::*;
+use compiler::lexer::{self, Keyword, Token, TokenKind};
+
+pub fn parse(file: &str, source: &str) -> Result<CompilationUnit, Diagnostic> {
+    let tokens = lexer::tokenize(file, source)?;
+    let mut parser = Parser { file, tokens, index: 0, splits: Vec::new() };
+    parser.compilation_unit()
+}
+
+type ParseResult<T> = Result<T, Diagnostic>;
+
+// A point to backtrack to: the token index and the number of split tokens.
+#[derive(Clone, Copy)]
+struct Snapshot {
+    index: usize,
+    splits: usize
+}
+
+struct Parser<'a> {
+    file: &'a str,
+    tokens: Vec<Token>,
+    index: usize,
+    // Tokens like >> that were split to close type argument lists, with their original form so
+    // that backtracking can undo the split.
+    splits: Vec<(usize, Token)>
+}
+
+fn primitive_type(kind: &TokenKind) -> Option<PrimitiveType> {
+    match *kind {
+        TokenKind::Keyword(Keyword::Boolean) => Some(PrimitiveType::Boolean),
+        TokenKind::Keyword(Keyword::Byte) => Some(PrimitiveType::Byte),
+        TokenKind::Keyword(Keyword::Short) => Some(PrimitiveType::Short),
+        TokenKind::Keyword(Keyword::Char) => Some(PrimitiveType::Char),
+        TokenKind::Keyword(Keyword::Int) => Some(PrimitiveType::Int),
+        TokenKind::Keyword(Keyword::Long) => Some(PrimitiveType::Long),
+        TokenKind::Keyword(Keyword::Float) => Some(PrimitiveType::Float),
+        TokenKind::Keyword(Keyword::Double) => Some(PrimitiveType::Double),
+        _ => None
+    }
+}
+
+fn modifier(keyword: Keyword) -> Option<Modifier> {
+    match keyword {
+        Keyword::Public => Some(Modifier::Public),
+        Keyword::Protected => Some(Modifier::Protected),
+        Keyword::Private => Some(Modifier::Private),
+        Keyword::Static => Some(Modifier::Static),
+        Keyword::Abstract => Some(Modifier::Abstract),
+        Keyword::Final => Some(Modifier::Final),
+        Keyword::Native => Some(Modifier::Native),
+        Keyword::Synchronized => Some(Modifier::Synchronized),
+        Keyword::Transient => Some(Modifier::Transient),
+        Keyword::Volatile => Some(Modifier::Volatile),
+        Keyword::Strictfp => Some(Modifier::Strictfp),
+        Keyword::Default => Some(Modifier::Default),
+        _ => None
+    }
+}
+
+fn assignment_operator(kind: &TokenKind) -> Option<Option<BinaryOperator>> {
+    match *kind {
+        TokenKind::Assign => Some(None),
+        TokenKind::PlusAssign => Some(Some(BinaryOperator::Add)),
+        TokenKind::MinusAssign => Some(Some(BinaryOperator::Subtract)),
+        TokenKind::StarAssign => Some(Some(BinaryOperator::Multiply)),
+        TokenKind::SlashAssign => Some(Some(BinaryOperator::Divide)),
+        TokenKind::PercentAssign => Some(Some(BinaryOperator::Remainder)),
+        TokenKind::AmpAssign => Some(Some(BinaryOperator::BitwiseAnd)),
+        TokenKind::BarAssign => Some(Some(BinaryOperator::BitwiseOr)),
+        TokenKind::CaretAssign => Some(Some(BinaryOperator::BitwiseXor)),
+        TokenKind::ShlAssign => Some(Some(BinaryOperator::ShiftLeft)),
+        TokenKind::ShrAssign => Some(Some(BinaryOperator::ShiftRight)),
+        TokenKind::UShrAssign => Some(Some(BinaryOperator::UnsignedShiftRight)),
+        _ => None
+    }
+}
+
+const RELATIONAL_PRECEDENCE: u8 = 7;
+
+// Binary operators and their precedence, from || (1) to multiplicative operators (10).
+fn binary_operator(kind: &TokenKind) -> Option<(BinaryOperator, u8)> {
+    match *kind {
+        TokenKind::OrOr => Some((BinaryOperator::Or, 1)),
+        TokenKind::AndAnd => Some((BinaryOperator::And, 2)),
+        TokenKind::Bar => Some((BinaryOperator::BitwiseOr, 3)),
+        TokenKind::Caret => Some((BinaryOperator::BitwiseXor, 4)),
+        TokenKind::Amp => Some((BinaryOperator::BitwiseAnd, 5)),
+        TokenKind::EqEq => Some((BinaryOperator::Equal, 6)),
+        TokenKind::Ne => Some((BinaryOperator::NotEqual, 6)),
+        TokenKind::Lt => Some((BinaryOperator::Less, RELATIONAL_PRECEDENCE)),
+        TokenKind::Gt => Some((BinaryOperator::Greater, RELATIONAL_PRECEDENCE)),
+        TokenKind::Le => Some((BinaryOperator::LessOrEqual, RELATIONAL_PRECEDENCE)),
+        TokenKind::Ge => Some((BinaryOperator::GreaterOrEqual, RELATIONAL_PRECEDENCE)),
+        TokenKind::Shl => Some((BinaryOperator::ShiftLeft, 8)),
+        TokenKind::Shr => Some((BinaryOperator::ShiftRight, 8)),
+        TokenKind::UShr => Some((BinaryOperator::UnsignedShiftRight, 8)),
+        TokenKind::Plus => Some((BinaryOperator::Add, 9)),
+        TokenKind::Minus => Some((BinaryOperator::Subtract, 9)),
+        TokenKind::Star => Some((BinaryOperator::Multiply, 10)),
+        TokenKind::Slash => Some((BinaryOperator::Divide, 10)),
+        TokenKind::Percent => Some((BinaryOperator::Remainder, 10)),
+        _ => None
+    }
+}
+
+// The names in a chain of simple names and field accesses, e.g. a.b.c.
+fn qualified_name_of(expression: &Expression) -> Option<QualifiedName> {
+    match expression.kind {
+        ExpressionKind::Name(ref name) => Some(QualifiedName { parts: vec![name.clone()] }),
+        ExpressionKind::FieldAccess { ref target, ref name } => {
+            let mut qualified_name = qualified_name_of(target)?;
+            qualified_name.parts.push(name.clone());
+            Some(qualified_name)
+        },
+        _ => None
+    }
+}
+
+fn class_type_of(name: QualifiedName) -> ClassType {
+    ClassType {
+        parts: name.parts.into_iter().map(|name| ClassTypePart { name, arguments: Some(Vec::new()) }).collect()
+    }
+}
+
+fn is_statement_expression(expression: &Expression) -> bool {
+    match expression.kind {
+        ExpressionKind::Assign { .. } | ExpressionKind::MethodCall { .. } | ExpressionKind::SuperMethodCall { .. }
+            | ExpressionKind::New { .. } => true,
+        ExpressionKind::Unary { operator, .. } => matches!(operator,
+            UnaryOperator::PreIncrement | UnaryOperator::PreDecrement | UnaryOperator::PostIncrement | UnaryOperator::PostDecrement),
+        _ => false
+    }
+}
+
+impl<'a> Parser<'a> {
+
+    // Tokens
+
+    fn peek(&self) -> &TokenKind {
+        &self.tokens[self.index].kind
+    }
+
+    fn peek_at(&self, offset: usize) -> &TokenKind {
+        let index = (self.index + offset).min(self.tokens.len() - 1);
+        &self.tokens[index].kind
+    }
+
+    fn position(&self) -> Position {
+        self.tokens[self.index].position
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.index].clone();
+        if token.kind != TokenKind::Eof {
+            self.index += 1;
+        }
+        token
+    }
+
+    fn is(&self, kind: &TokenKind) -> bool {
+        self.peek() == kind
+    }
+
+    fn is_keyword(&self, keyword: Keyword) -> bool {
+        *self.peek() == TokenKind::Keyword(keyword)
+    }
+
+    // Contextual keywords like var, yield and record are identifiers to the lexer.
+    fn is_identifier(&self, offset: usize, name: &str) -> bool {
+        match *self.peek_at(offset) {
+            TokenKind::Identifier(ref identifier) => identifier == name,
+            _ => false
+        }
+    }
+
+    fn eat(&mut self, kind: &TokenKind) -> bool {
+        if self.is(kind) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: Keyword) -> bool {
+        self.eat(&TokenKind::Keyword(keyword))
+    }
+
+    fn expect(&mut self, kind: TokenKind) -> ParseResult<Token> {
+        if self.is(&kind) {
+            Ok(self.advance())
+        } else {
+            self.expected(&format!("'{}'", kind))
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: Keyword) -> ParseResult<Token> {
+        self.expect(TokenKind::Keyword(keyword))
+    }
+
+    fn error<T>(&self, position: Position, message: &str) -> ParseResult<T> {
+        Err(Diagnostic::new(self.file, position, message))
+    }
+
+    // Like javac, a missing token is reported just after the previous one.
+    fn expected<T>(&self, what: &str) -> ParseResult<T> {
+        if *self.peek() == TokenKind::Eof {
+            return self.error(self.position(), "reached end of file while parsing");
+        }
+        let position = if self.index > 0 { self.tokens[self.index - 1].end } else { self.position() };
+        self.error(position, &format!("{} expected", what))
+    }
+
+    fn identifier(&mut self) -> ParseResult<Identifier> {
+        match *self.peek() {
+            TokenKind::Identifier(ref name) => {
+                let identifier = Identifier { name: name.clone(), position: self.position() };
+                self.index += 1;
+                Ok(identifier)
+            },
+            _ => self.expected("<identifier>")
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot { index: self.index, splits: self.splits.len() }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        while self.splits.len() > snapshot.splits {
+            let (index, original) = self.splits.pop().unwrap();
+            self.tokens.remove(index + 1);
+            self.tokens[index] = original;
+        }
+        self.index = snapshot.index;
+    }
+
+    // Runs an alternative, backtracking if it fails.
+    fn attempt<T, F>(&mut self, alternative: F) -> Option<T> where F: FnOnce(&mut Parser<'a>) -> ParseResult<T> {
+        let snapshot = self.snapshot();
+        match alternative(self) {
+            Ok(result) => Some(result),
+            Err(_) => {
+                self.restore(snapshot);
+                None
+            }
+        }
+    }
+
+    // Whether an alternative would succeed, without consuming anything.
+    fn lookahead<F>(&mut self, alternative: F) -> bool where F: FnOnce(&mut Parser<'a>) -> ParseResult<bool> {
+        let snapshot = self.snapshot();
+        let result = alternative(self).unwrap_or(false);
+        self.restore(snapshot);
+        result
+    }
+
+    // Closes a type argument list. In List<List<String>> the lexer sees >>, which is split into
+    // two > tokens; >>>, >= and >>= are split the same way.
+    fn expect_gt(&mut self) -> ParseResult<()> {
+        let rest = match *self.peek() {
+            TokenKind::Gt => {
+                self.advance();
+                return Ok(());
+            },
+            TokenKind::Shr => TokenKind::Gt,
+            TokenKind::UShr => TokenKind::Shr,
+            TokenKind::Ge => TokenKind::Assign,
+            TokenKind::ShrAssign => TokenKind::Ge,
+            TokenKind::UShrAssign => TokenKind::ShrAssign,
+            _ => return self.expected("'>'")
+        };
+
+        let original = self.tokens[self.index].clone();
+        let middle = Position::new(original.position.line, original.position.column + 1);
+        let greater = Token { kind: TokenKind::Gt, position: original.position, end: middle };
+        let remainder = Token { kind: rest, position: middle, end: original.end };
+
+        self.tokens[self.index] = greater;
+        self.tokens.insert(self.index + 1, remainder);
+        self.splits.push((self.index, original));
+        self.index += 1;
+        Ok(())
+    }
+
+    // Compilation units (JLS §7.3)
+
+    fn compilation_unit(&mut self) -> ParseResult<CompilationUnit> {
+        let mut package = None;
+        let start = self.snapshot();
+        let modifiers = self.modifiers()?;
+        if self.is_keyword(Keyword::Package) {
+            if let Some(&(modifier, position)) = modifiers.modifiers.first() {
+                return self.error(position, &format!("modifier {} not allowed here", modifier.name()));
+            }
+            self.advance();
+            let name = self.qualified_name()?;
+            self.expect(TokenKind::Semicolon)?;
+            package = Some(PackageDeclaration { annotations: modifiers.annotations, name });
+        } else {
+            self.restore(start);
+        }
+
+        let mut imports = Vec::new();
+        while self.is_keyword(Keyword::Import) || self.is(&TokenKind::Semicolon) {
+            if !self.eat(&TokenKind::Semicolon) {
+                imports.push(self.import()?);
+            }
+        }
+
+        let mut types = Vec::new();
+        while !self.is(&TokenKind::Eof) {
+            if self.eat(&TokenKind::Semicolon) {
+                continue;
+            }
+            if self.is_keyword(Keyword::Import) {
+                return self.error(self.position(), "class, interface, enum, or record expected");
+            }
+            let modifiers = self.modifiers()?;
+            types.push(self.type_declaration(modifiers)?);
+        }
+
+        Ok(CompilationUnit { package, imports, types })
+    }
+
+    fn qualified_name(&mut self) -> ParseResult<QualifiedName> {
+        let mut parts = vec![self.identifier()?];
+        while self.is(&TokenKind::Dot) && matches!(*self.peek_at(1), TokenKind::Identifier(_)) {
+            self.advance();
+            parts.push(self.identifier()?);
+        }
+        Ok(QualifiedName { parts })
+    }
+
+    fn import(&mut self) -> ParseResult<Import> {
+        let position = self.expect_keyword(Keyword::Import)?.position;
+        let is_static = self.eat_keyword(Keyword::Static);
+        let name = self.qualified_name()?;
+        let on_demand = self.eat(&TokenKind::Dot);
+        if on_demand {
+            self.expect(TokenKind::Star)?;
+        } else if name.parts.len() < 2 {
+            return self.expected("'.'");
+        }
+        self.expect(TokenKind::Semicolon)?;
+        Ok(Import { name, is_static, on_demand, position })
+    }
+
+    // Modifiers and annotations (JLS §8.1.1, §9.7)
+
+    // sealed and non-sealed are modifiers only before a declaration.
+    fn is_sealed_modifier(&self, offset: usize) -> bool {
+        match *self.peek_at(offset) {
+            TokenKind::Keyword(_) | TokenKind::At => true,
+            TokenKind::Identifier(ref name) => name == "sealed" || name == "non",
+            _ => false
+        }
+    }
+
+    fn is_non_sealed(&self) -> bool {
+        let adjacent = |i: usize| self.tokens.get(i + 1).is_some_and(|next| self.tokens[i].end == next.position);
+        self.is_identifier(0, "non") && *self.peek_at(1) == TokenKind::Minus && self.is_identifier(2, "sealed")
+            && adjacent(self.index) && adjacent(self.index + 1) && self.is_sealed_modifier(3)
+    }
+
+    fn modifiers(&mut self) -> ParseResult<Modifiers> {
+        let mut modifiers = Modifiers::default();
+        loop {
+            let position = self.position();
+            let modifier = match *self.peek() {
+                TokenKind::At if *self.peek_at(1) != TokenKind::Keyword(Keyword::Interface) => {
+                    modifiers.annotations.push(self.annotation()?);
+                    continue;
+                },
+                TokenKind::Keyword(Keyword::Default) if matches!(*self.peek_at(1), TokenKind::Colon | TokenKind::Arrow) => break,
+                TokenKind::Keyword(keyword) => match modifier(keyword) {
+                    Some(modifier) => modifier,
+                    None => break
+                },
+                TokenKind::Identifier(ref name) if name == "sealed" && self.is_sealed_modifier(1) => Modifier::Sealed,
+                TokenKind::Identifier(_) if self.is_non_sealed() => {
+                    self.index += 2;
+                    Modifier::NonSealed
+                },
+                _ => break
+            };
+            self.advance();
+
+            if modifiers.contains(modifier) {
+                return self.error(position, "repeated modifier");
+            }
+            modifiers.modifiers.push((modifier, position));
+        }
+        Ok(modifiers)
+    }
+
+    fn annotation(&mut self) -> ParseResult<Annotation> {
+        let position = self.expect(TokenKind::At)?.position;
+        let name = self.qualified_name()?;
+        let mut arguments = Vec::new();
+
+        if self.eat(&TokenKind::LParen) {
+            if matches!(*self.peek(), TokenKind::Identifier(_)) && *self.peek_at(1) == TokenKind::Assign {
+                loop {
+                    let name = self.identifier()?;
+                    self.expect(TokenKind::Assign)?;
+                    arguments.push((name, self.element_value()?));
+                    if !self.eat(&TokenKind::Comma) {
+                        break;
+                    }
+                }
+            } else if !self.is(&TokenKind::RParen) {
+                let name = Identifier { name: String::from("value"), position: self.position() };
+                arguments.push((name, self.element_value()?));
+            }
+            self.expect(TokenKind::RParen)?;
+        }
+
+        Ok(Annotation { name, arguments, position })
+    }
+
+    fn element_value(&mut self) -> ParseResult<ElementValue> {
+        match *self.peek() {
+            TokenKind::At => Ok(ElementValue::Annotation(self.annotation()?)),
+            TokenKind::LBrace => {
+                self.advance();
+                let mut values = Vec::new();
+                while !self.is(&TokenKind::RBrace) {
+                    values.push(self.element_value()?);
+                    if !self.eat(&TokenKind::Comma) {
+                        break;
+                    }
+                }
+                self.expect(TokenKind::RBrace)?;
+                Ok(ElementValue::Array(values))
+            },
+            _ => Ok(ElementValue::Expression(self.conditional()?))
+        }
+    }
+
+    // Type annotations are allowed before any use of a type, but aren't kept.
+    fn type_annotations(&mut self) -> ParseResult<()> {
+        while self.is(&TokenKind::At) && *self.peek_at(1) != TokenKind::Keyword(Keyword::Interface) {
+            self.annotation()?;
+        }
+        Ok(())
+    }
+
+    // Types (JLS §4, §8.1.2)
+
+    fn parse_type(&mut self) -> ParseResult<Type> {
+        self.type_annotations()?;
+        let position = self.position();
+        let element_type = match primitive_type(self.peek()) {
+            Some(primitive) => {
+                self.advance();
+                Type::Primitive(primitive, position)
+            },
+            None => Type::Class(self.class_type(false)?)
+        };
+        Ok(self.dimensions(element_type))
+    }
+
+    // Consumes a [] pair, which may be annotated as in String @NonNull [].
+    fn dimension(&mut self) -> bool {
+        self.attempt(|parser| {
+            parser.type_annotations()?;
+            parser.expect(TokenKind::LBracket)?;
+            parser.expect(TokenKind::RBracket)
+        }).is_some()
+    }
+
+    // Trailing [] pairs, each making an array type.
+    fn dimensions(&mut self, mut element_type: Type) -> Type {
+        while self.dimension() {
+            element_type = Type::Array(Box::new(element_type));
+        }
+        element_type
+    }
+
+    fn dimension_count(&mut self) -> usize {
+        let mut count = 0;
+        while self.dimension() {
+            count += 1;
+        }
+        count
+    }
+
+    fn class_type(&mut self, diamond: bool) -> ParseResult<ClassType> {
+        let mut parts = Vec::new();
+        loop {
+            self.type_annotations()?;
+            let name = self.identifier()?;
+            let arguments = if self.is(&TokenKind::Lt) { self.type_arguments(diamond)? } else { Some(Vec::new()) };
+            parts.push(ClassTypePart { name, arguments });
+
+            if self.is(&TokenKind::Dot) && matches!(*self.peek_at(1), TokenKind::Identifier(_) | TokenKind::At) {
+                self.advance();
+            } else {
+                return Ok(ClassType { parts });
+            }
+        }
+    }
+
+    // <A, B>, or None for the diamond <> when it is allowed.
+    fn type_arguments(&mut self, diamond: bool) -> ParseResult<Option<Vec<TypeArgument>>> {
+        self.expect(TokenKind::Lt)?;
+        if diamond && self.is(&TokenKind::Gt) {
+            self.advance();
+            return Ok(None);
+        }
+
+        let mut arguments = Vec::new();
+        loop {
+            arguments.push(self.type_argument()?);
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+        self.expect_gt()?;
+        Ok(Some(arguments))
+    }
+
+    fn type_argument(&mut self) -> ParseResult<TypeArgument> {
+        self.type_annotations()?;
+        let position = self.position();
+        if self.eat(&TokenKind::Question) {
+            let bound = if self.eat_keyword(Keyword::Extends) {
+                Some(WildcardBound::Extends(self.parse_type()?))
+            } else if self.eat_keyword(Keyword::Super) {
+                Some(WildcardBound::Super(self.parse_type()?))
+            } else {
+                None
+            };
+            return Ok(TypeArgument::Wildcard(bound, position));
+        }
+
+        let argument = self.parse_type()?;
+        if let Type::Primitive(..) = argument {
+            return self.error(position, "unexpected type");
+        }
+        Ok(TypeArgument::Type(argument))
+    }
+
+    fn type_parameters(&mut self) -> ParseResult<Vec<TypeParameter>> {
+        self.expect(TokenKind::Lt)?;
+        let mut parameters = Vec::new();
+        loop {
+            let mut annotations = Vec::new();
+            while self.is(&TokenKind::At) {
+                annotations.push(self.annotation()?);
+            }
+            let name = self.identifier()?;
+            let mut bounds = Vec::new();
+            if self.eat_keyword(Keyword::Extends) {
+                bounds.push(self.parse_type()?);
+                while self.eat(&TokenKind::Amp) {
+                    bounds.push(self.parse_type()?);
+                }
+            }
+            parameters.push(TypeParameter { annotations, name, bounds });
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+        self.expect_gt()?;
+        Ok(parameters)
+    }
+
+    fn type_list(&mut self) -> ParseResult<Vec<Type>> {
+        let mut types = vec![self.parse_type()?];
+        while self.eat(&TokenKind::Comma) {
+            types.push(self.parse_type()?);
+        }
+        Ok(types)
+    }
+
+    // Declarations (JLS §8, §9)
+
+    fn is_record_declaration(&self) -> bool {
+        self.is_identifier(0, "record") && matches!(*self.peek_at(1), TokenKind::Identifier(_))
+            && matches!(*self.peek_at(2), TokenKind::LParen | TokenKind::Lt)
+    }
+
+    fn is_type_declaration(&self) -> bool {
+        match *self.peek() {
+            TokenKind::Keyword(Keyword::Class) | TokenKind::Keyword(Keyword::Interface) | TokenKind::Keyword(Keyword::Enum) => true,
+            TokenKind::At => *self.peek_at(1) == TokenKind::Keyword(Keyword::Interface),
+            _ => self.is_record_declaration()
+        }
+    }
+
+    fn type_declaration(&mut self, modifiers: Modifiers) -> ParseResult<TypeDeclaration> {
+        let position = self.position();
+        let kind = match *self.peek() {
+            TokenKind::Keyword(Keyword::Class) => TypeKind::Class,
+            TokenKind::Keyword(Keyword::Interface) => TypeKind::Interface,
+            TokenKind::Keyword(Keyword::Enum) => TypeKind::Enum,
+            TokenKind::At if *self.peek_at(1) == TokenKind::Keyword(Keyword::Interface) => {
+                self.advance();
+                TypeKind::Annotation
+            },
+            _ if self.is_record_declaration() => TypeKind::Record,
+            _ => return self.error(position, "class, interface, enum, or record expected")
+        };
+        self.advance();
+
+        let name = self.identifier()?;
+        let mut declaration = TypeDeclaration {
+            kind,
+            modifiers,
+            name,
+            type_parameters: Vec::new(),
+            extends: Vec::new(),
+            implements: Vec::new(),
+            permits: Vec::new(),
+            record_components: Vec::new(),
+            enum_constants: Vec::new(),
+            members: Vec::new(),
+            position
+        };
+
+        if kind != TypeKind::Enum && kind != TypeKind::Annotation && self.is(&TokenKind::Lt) {
+            declaration.type_parameters = self.type_parameters()?;
+        }
+
+        if kind == TypeKind::Record {
+            self.expect(TokenKind::LParen)?;
+            if !self.is(&TokenKind::RParen) {
+                loop {
+                    declaration.record_components.push(self.formal_parameter()?);
+                    if !self.eat(&TokenKind::Comma) {
+                        break;
+                    }
+                }
+            }
+            self.expect(TokenKind::RParen)?;
+        }
+
+        if self.eat_keyword(Keyword::Extends) {
+            match kind {
+                TypeKind::Class => declaration.extends.push(self.parse_type()?),
+                TypeKind::Interface => declaration.extends = self.type_list()?,
+                _ => return self.error(self.tokens[self.index - 1].position, "'{' expected")
+            }
+        }
+        if kind != TypeKind::Interface && kind != TypeKind::Annotation && self.eat_keyword(Keyword::Implements) {
+            declaration.implements = self.type_list()?;
+        }
+        if (kind == TypeKind::Class || kind == TypeKind::Interface) && self.is_identifier(0, "permits") {
+            self.advance();
+            declaration.permits = self.type_list()?;
+        }
+
+        let class_name = declaration.name.name.clone();
+        if kind == TypeKind::Enum {
+            let (constants, members) = self.enum_body(&class_name)?;
+            declaration.enum_constants = constants;
+            declaration.members = members;
+        } else {
+            declaration.members = self.class_body(Some(&class_name))?;
+        }
+
+        Ok(declaration)
+    }
+
+    fn enum_body(&mut self, class_name: &str) -> ParseResult<(Vec<EnumConstant>, Vec<Member>)> {
+        self.expect(TokenKind::LBrace)?;
+        let mut constants = Vec::new();
+        while !self.is(&TokenKind::Semicolon) && !self.is(&TokenKind::RBrace) {
+            let annotations = self.modifiers()?.annotations;
+            let name = self.identifier()?;
+            let arguments = if self.is(&TokenKind::LParen) { self.arguments()? } else { Vec::new() };
+            let body = if self.is(&TokenKind::LBrace) { Some(self.class_body(None)?) } else { None };
+            constants.push(EnumConstant { annotations, name, arguments, body });
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+
+        let mut members = Vec::new();
+        if self.eat(&TokenKind::Semicolon) {
+            while !self.is(&TokenKind::RBrace) {
+                if let Some(member) = self.member(Some(class_name))? {
+                    members.push(member);
+                }
+            }
+        }
+        self.expect(TokenKind::RBrace)?;
+        Ok((constants, members))
+    }
+
+    // The body of a class, interface, record or annotation interface. Without a class name, as
+    // for anonymous classes, there are no constructors.
+    fn class_body(&mut self, class_name: Option<&str>) -> ParseResult<Vec<Member>> {
+        self.expect(TokenKind::LBrace)?;
+        let mut members = Vec::new();
+        while !self.eat(&TokenKind::RBrace) {
+            if let Some(member) = self.member(class_name)? {
+                members.push(member);
+            }
+        }
+        Ok(members)
+    }
+
+    fn member(&mut self, class_name: Option<&str>) -> ParseResult<Option<Member>> {
+        if self.eat(&TokenKind::Semicolon) {
+            return Ok(None);
+        }
+        if self.is(&TokenKind::Eof) {
+            return self.expected("'}'");
+        }
+        if self.is(&TokenKind::LBrace) {
+            return Ok(Some(Member::Initializer { is_static: false, body: self.block()? }));
+        }
+        if self.is_keyword(Keyword::Static) && *self.peek_at(1) == TokenKind::LBrace {
+            self.advance();
+            return Ok(Some(Member::Initializer { is_static: true, body: self.block()? }));
+        }
+
+        let modifiers = self.modifiers()?;
+        if self.is_type_declaration() {
+            return Ok(Some(Member::Type(self.type_declaration(modifiers)?)));
+        }
+
+        let type_parameters = if self.is(&TokenKind::Lt) { self.type_parameters()? } else { Vec::new() };
+
+        let is_constructor = match (self.peek(), class_name) {
+            (TokenKind::Identifier(name), Some(class_name)) => name == class_name,
+            _ => false
+        };
+        if is_constructor && *self.peek_at(1) == TokenKind::LParen {
+            let name = self.identifier()?;
+            let parameters = self.formal_parameters()?;
+            let throws = self.throws()?;
+            let body = self.block()?;
+            return Ok(Some(Member::Constructor(ConstructorDeclaration { modifiers, type_parameters, name, parameters: Some(parameters), throws, body })));
+        }
+        if is_constructor && *self.peek_at(1) == TokenKind::LBrace {
+            let name = self.identifier()?;
+            let body = self.block()?;
+            return Ok(Some(Member::Constructor(ConstructorDeclaration { modifiers, type_parameters, name, parameters: None, throws: Vec::new(), body })));
+        }
+
+        let position = self.position();
+        let member_type = if self.eat_keyword(Keyword::Void) { Type::Void(position) } else { self.parse_type()? };
+        let name = self.identifier()?;
+
+        if self.is(&TokenKind::LParen) {
+            let parameters = self.formal_parameters()?;
+            let mut return_type = member_type;
+            for _ in 0..self.dimension_count() {
+                return_type = Type::Array(Box::new(return_type));
+            }
+            let throws = self.throws()?;
+            let default_value = if self.eat_keyword(Keyword::Default) { Some(self.element_value()?) } else { None };
+            let body = if self.eat(&TokenKind::Semicolon) { None } else { Some(self.block()?) };
+            return Ok(Some(Member::Method(MethodDeclaration { modifiers, type_parameters, return_type, name, parameters, throws, body, default_value })));
+        }
+
+        if !type_parameters.is_empty() || member_type == Type::Void(position) {
+            return self.expected("'('");
+        }
+        let declarators = self.variable_declarators(Some(name))?;
+        self.expect(TokenKind::Semicolon)?;
+        Ok(Some(Member::Field(FieldDeclaration { modifiers, field_type: member_type, declarators })))
+    }
+
+    fn throws(&mut self) -> ParseResult<Vec<Type>> {
+        if self.eat_keyword(Keyword::Throws) {
+            self.type_list()
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    fn formal_parameters(&mut self) -> ParseResult<Vec<Parameter>> {
+        self.expect(TokenKind::LParen)?;
+        let mut parameters = Vec::new();
+        if !self.is(&TokenKind::RParen) {
+            loop {
+                if let Some(parameter) = self.receiver_or_formal_parameter()? {
+                    parameters.push(parameter);
+                }
+                if !self.eat(&TokenKind::Comma) {
+                    break;
+                }
+            }
+        }
+        self.expect(TokenKind::RParen)?;
+        Ok(parameters)
+    }
+
+    // A receiver parameter, as in m(Foo this), only exists for annotations and is skipped.
+    fn receiver_or_formal_parameter(&mut self) -> ParseResult<Option<Parameter>> {
+        let receiver = self.attempt(|parser| {
+            parser.modifiers()?;
+            parser.parse_type()?;
+            while matches!(*parser.peek(), TokenKind::Identifier(_)) && *parser.peek_at(1) == TokenKind::Dot {
+                parser.index += 2;
+            }
+            parser.expect_keyword(Keyword::This)
+        });
+        match receiver {
+            Some(_) => Ok(None),
+            None => Ok(Some(self.formal_parameter()?))
+        }
+    }
+
+    fn formal_parameter(&mut self) -> ParseResult<Parameter> {
+        let modifiers = self.modifiers()?;
+        let mut parameter_type = self.parse_type()?;
+        self.type_annotations()?;
+        let variable_arity = self.eat(&TokenKind::Ellipsis);
+        let name = self.identifier()?;
+        for _ in 0..self.dimension_count() {
+            parameter_type = Type::Array(Box::new(parameter_type));
+        }
+        Ok(Parameter { modifiers, parameter_type, variable_arity, name })
+    }
+
+    // a, b[] = {}, c = 1. The first name may already have been parsed.
+    fn variable_declarators(&mut self, first: Option<Identifier>) -> ParseResult<Vec<VariableDeclarator>> {
+        let mut declarators = Vec::new();
+        let mut name = first;
+        loop {
+            let name = match name.take() {
+                Some(name) => name,
+                None => self.identifier()?
+            };
+            let dimensions = self.dimension_count();
+            let initializer = if self.eat(&TokenKind::Assign) { Some(self.variable_initializer()?) } else { None };
+            declarators.push(VariableDeclarator { name, dimensions, initializer });
+            if !self.eat(&TokenKind::Comma) {
+                return Ok(declarators);
+            }
+        }
+    }
+
+    fn variable_initializer(&mut self) -> ParseResult<Expression> {
+        if self.is(&TokenKind::LBrace) {
+            let position = self.position();
+            let elements = self.array_initializer()?;
+            Ok(Expression { kind: ExpressionKind::ArrayInitializer(elements), position })
+        } else {
+            self.expression()
+        }
+    }
+
+    fn array_initializer(&mut self) -> ParseResult<Vec<Expression>> {
+        self.expect(TokenKind::LBrace)?;
+        let mut elements = Vec::new();
+        while !self.is(&TokenKind::RBrace) {
+            elements.push(self.variable_initializer()?);
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+        self.expect(TokenKind::RBrace)?;
+        Ok(elements)
+    }
+
+    // Blocks and statements (JLS §14)
+
+    fn block(&mut self) -> ParseResult<Block> {
+        let position = self.expect(TokenKind::LBrace)?.position;
+        let mut statements = Vec::new();
+        while !self.is(&TokenKind::RBrace) {
+            if self.is(&TokenKind::Eof) {
+                return self.expected("'}'");
+            }
+            statements.push(self.block_statement()?);
+        }
+        let end = self.advance().position;
+        Ok(Block { statements, position, end })
+    }
+
+    // Whether the statement starts with a type followed by a name, which makes it a local
+    // variable declaration rather than an expression.
+    fn is_local_variable_declaration(&mut self) -> bool {
+        match *self.peek() {
+            TokenKind::Keyword(Keyword::Final) | TokenKind::At => true,
+            TokenKind::Identifier(_) | TokenKind::Keyword(_) => self.lookahead(|parser| {
+                parser.parse_type()?;
+                Ok(matches!(*parser.peek(), TokenKind::Identifier(_)))
+            }),
+            _ => false
+        }
+    }
+
+    fn is_yield_statement(&self) -> bool {
+        self.is_identifier(0, "yield") && !matches!(*self.peek_at(1),
+            TokenKind::Assign | TokenKind::PlusAssign | TokenKind::MinusAssign | TokenKind::StarAssign
+                | TokenKind::SlashAssign | TokenKind::PercentAssign | TokenKind::AmpAssign | TokenKind::BarAssign
+                | TokenKind::CaretAssign | TokenKind::ShlAssign | TokenKind::ShrAssign | TokenKind::UShrAssign
+                | TokenKind::Dot | TokenKind::LBracket | TokenKind::ColonColon | TokenKind::Colon
+                | TokenKind::Semicolon | TokenKind::Arrow | TokenKind::PlusPlus | TokenKind::MinusMinus)
+    }
+
+    fn block_statement(&mut self) -> ParseResult<Statement> {
+        let position = self.position();
+        let has_modifiers = matches!(*self.peek(),
+            TokenKind::Keyword(Keyword::Class) | TokenKind::Keyword(Keyword::Interface) | TokenKind::Keyword(Keyword::Enum)
+                | TokenKind::Keyword(Keyword::Abstract) | TokenKind::Keyword(Keyword::Static) | TokenKind::Keyword(Keyword::Strictfp)
+                | TokenKind::Keyword(Keyword::Final) | TokenKind::At);
+
+        if has_modifiers || self.is_record_declaration() {
+            let modifiers = self.modifiers()?;
+            if self.is_type_declaration() {
+                let declaration = self.type_declaration(modifiers)?;
+                return Ok(Statement { kind: StatementKind::LocalType(declaration), position });
+            }
+            let variable = self.local_variable(modifiers)?;
+            self.expect(TokenKind::Semicolon)?;
+            return Ok(Statement { kind: StatementKind::LocalVariable(variable), position });
+        }
+
+        if !self.is_yield_statement() && self.is_local_variable_declaration() {
+            let variable = self.local_variable(Modifiers::default())?;
+            self.expect(TokenKind::Semicolon)?;
+            return Ok(Statement { kind: StatementKind::LocalVariable(variable), position });
+        }
+
+        self.statement()
+    }
+
+    // The type of a local variable, or None for var.
+    fn local_variable_type(&mut self) -> ParseResult<Option<Type>> {
+        if self.is_identifier(0, "var") && matches!(*self.peek_at(1), TokenKind::Identifier(_)) {
+            self.advance();
+            Ok(None)
+        } else {
+            Ok(Some(self.parse_type()?))
+        }
+    }
+
+    fn local_variable(&mut self, modifiers: Modifiers) -> ParseResult<LocalVariable> {
+        let variable_type = self.local_variable_type()?;
+        let declarators = self.variable_declarators(None)?;
+        Ok(LocalVariable { modifiers, variable_type, declarators })
+    }
+
+    fn parenthesized(&mut self) -> ParseResult<Expression> {
+        self.expect(TokenKind::LParen)?;
+        let expression = self.expression()?;
+        self.expect(TokenKind::RParen)?;
+        Ok(expression)
+    }
+
+    fn statement(&mut self) -> ParseResult<Statement> {
+        let position = self.position();
+        let kind = match *self.peek() {
+            TokenKind::LBrace => StatementKind::Block(self.block()?),
+            TokenKind::Semicolon => {
+                self.advance();
+                StatementKind::Empty
+            },
+            TokenKind::Keyword(Keyword::If) => {
+                self.advance();
+                let condition = self.parenthesized()?;
+                let then = Box::new(self.statement()?);
+                let otherwise = if self.eat_keyword(Keyword::Else) { Some(Box::new(self.statement()?)) } else { None };
+                StatementKind::If { condition, then, otherwise }
+            },
+            TokenKind::Keyword(Keyword::While) => {
+                self.advance();
+                let condition = self.parenthesized()?;
+                let body = Box::new(self.statement()?);
+                StatementKind::While { condition, body }
+            },
+            TokenKind::Keyword(Keyword::Do) => {
+                self.advance();
+                let body = Box::new(self.statement()?);
+                self.expect_keyword(Keyword::While)?;
+                let condition = self.parenthesized()?;
+                self.expect(TokenKind::Semicolon)?;
+                StatementKind::DoWhile { body, condition }
+            },
+            TokenKind::Keyword(Keyword::For) => self.for_statement()?,
+            TokenKind::Keyword(Keyword::Try) => self.try_statement()?,
+            TokenKind::Keyword(Keyword::Switch) => StatementKind::Switch(self.switch()?),
+            TokenKind::Keyword(Keyword::Return) => {
+                self.advance();
+                let value = if self.is(&TokenKind::Semicolon) { None } else { Some(self.expression()?) };
+                self.expect(TokenKind::Semicolon)?;
+                StatementKind::Return(value)
+            },
+            TokenKind::Keyword(Keyword::Break) | TokenKind::Keyword(Keyword::Continue) => {
+                let is_break = self.advance().kind == TokenKind::Keyword(Keyword::Break);
+                let label = if self.is(&TokenKind::Semicolon) { None } else { Some(self.identifier()?) };
+                self.expect(TokenKind::Semicolon)?;
+                if is_break { StatementKind::Break(label) } else { StatementKind::Continue(label) }
+            },
+            TokenKind::Keyword(Keyword::Throw) => {
+                self.advance();
+                let exception = self.expression()?;
+                self.expect(TokenKind::Semicolon)?;
+                StatementKind::Throw(exception)
+            },
+            TokenKind::Keyword(Keyword::Synchronized) => {
+                self.advance();
+                let lock = self.parenthesized()?;
+                let body = self.block()?;
+                StatementKind::Synchronized { lock, body }
+            },
+            TokenKind::Keyword(Keyword::Assert) => {
+                self.advance();
+                let condition = self.expression()?;
+                let message = if self.eat(&TokenKind::Colon) { Some(self.expression()?) } else { None };
+                self.expect(TokenKind::Semicolon)?;
+                StatementKind::Assert { condition, message }
+            },
+            TokenKind::Keyword(Keyword::This) | TokenKind::Keyword(Keyword::Super) if *self.peek_at(1) == TokenKind::LParen => {
+                let is_super = self.advance().kind == TokenKind::Keyword(Keyword::Super);
+                let arguments = self.arguments()?;
+                self.expect(TokenKind::Semicolon)?;
+                StatementKind::ConstructorInvocation { is_super, qualifier: None, type_arguments: Vec::new(), arguments }
+            },
+            TokenKind::Lt => {
+                let type_arguments = self.type_arguments(false)?.unwrap_or_default();
+                let is_super = match *self.peek() {
+                    TokenKind::Keyword(Keyword::This) => false,
+                    TokenKind::Keyword(Keyword::Super) => true,
+                    _ => return self.expected("'this' or 'super'")
+                };
+                self.advance();
+                let arguments = self.arguments()?;
+                self.expect(TokenKind::Semicolon)?;
+                StatementKind::ConstructorInvocation { is_super, qualifier: None, type_arguments, arguments }
+            },
+            TokenKind::Identifier(_) if self.is_yield_statement() => {
+                self.advance();
+                let value = self.expression()?;
+                self.expect(TokenKind::Semicolon)?;
+                StatementKind::Yield(value)
+            },
+            TokenKind::Identifier(_) if *self.peek_at(1) == TokenKind::Colon => {
+                let label = self.identifier()?;
+                self.advance();
+                let body = Box::new(self.statement()?);
+                StatementKind::Labeled { label, body }
+            },
+            TokenKind::Keyword(Keyword::Else) => return self.error(position, "'else' without 'if'"),
+            TokenKind::Keyword(Keyword::Catch) => return self.error(position, "'catch' without 'try'"),
+            TokenKind::Keyword(Keyword::Finally) => return self.error(position, "'finally' without 'try'"),
+            TokenKind::Keyword(Keyword::Case) => return self.error(position, "orphaned case"),
+            TokenKind::Keyword(Keyword::Default) => return self.error(position, "orphaned default"),
+            _ => return self.expression_statement()
+        };
+        Ok(Statement { kind, position })
+    }
+
+    fn expression_statement(&mut self) -> ParseResult<Statement> {
+        let position = self.position();
+        let expression = self.expression()?;
+
+        // outer.super(...) invokes the superclass constructor with an outer instance.
+        if self.is(&TokenKind::Dot) && *self.peek_at(1) == TokenKind::Keyword(Keyword::Super) && *self.peek_at(2) == TokenKind::LParen {
+            self.index += 2;
+            let arguments = self.arguments()?;
+            self.expect(TokenKind::Semicolon)?;
+            let kind = StatementKind::ConstructorInvocation { is_super: true, qualifier: Some(expression), type_arguments: Vec::new(), arguments };
+            return Ok(Statement { kind, position });
+        }
+
+        if !is_statement_expression(&expression) {
+            return self.error(expression.position, "not a statement");
+        }
+        self.expect(TokenKind::Semicolon)?;
+        Ok(Statement { kind: StatementKind::Expression(expression), position })
+    }
+
+    fn for_statement(&mut self) -> ParseResult<StatementKind> {
+        self.expect_keyword(Keyword::For)?;
+        self.expect(TokenKind::LParen)?;
+
+        if self.is_local_variable_declaration() {
+            let enhanced = self.attempt(|parser| {
+                let modifiers = parser.modifiers()?;
+                let variable_type = parser.local_variable_type()?;
+                let name = parser.identifier()?;
+                parser.expect(TokenKind::Colon)?;
+                let declarators = vec![VariableDeclarator { name, dimensions: 0, initializer: None }];
+                Ok(LocalVariable { modifiers, variable_type, declarators })
+            });
+            if let Some(variable) = enhanced {
+                let iterable = self.expression()?;
+                self.expect(TokenKind::RParen)?;
+                let body = Box::new(self.statement()?);
+                return Ok(StatementKind::ForEach { variable, iterable, body });
+            }
+        }
+
+        let mut init = Vec::new();
+        let position = self.position();
+        if self.is_local_variable_declaration() {
+            let modifiers = self.modifiers()?;
+            let variable = self.local_variable(modifiers)?;
+            init.push(Statement { kind: StatementKind::LocalVariable(variable), position });
+        } else if !self.is(&TokenKind::Semicolon) {
+            init = self.statement_expressions()?
+                .into_iter()
+                .map(|expression| Statement { position: expression.position, kind: StatementKind::Expression(expression) })
+                .collect();
+        }
+        self.expect(TokenKind::Semicolon)?;
+
+        let condition = if self.is(&TokenKind::Semicolon) { None } else { Some(self.expression()?) };
+        self.expect(TokenKind::Semicolon)?;
+
+        let update = if self.is(&TokenKind::RParen) { Vec::new() } else { self.statement_expressions()? };
+        self.expect(TokenKind::RParen)?;
+
+        let body = Box::new(self.statement()?);
+        Ok(StatementKind::For { init, condition, update, body })
+    }
+
+    fn statement_expressions(&mut self) -> ParseResult<Vec<Expression>> {
+        let mut expressions = Vec::new();
+        loop {
+            let expression = self.expression()?;
+            if !is_statement_expression(&expression) {
+                return self.error(expression.position, "not a statement");
+            }
+            expressions.push(expression);
+            if !self.eat(&TokenKind::Comma) {
+                return Ok(expressions);
+            }
+        }
+    }
+
+    fn try_statement(&mut self) -> ParseResult<StatementKind> {
+        let position = self.expect_keyword(Keyword::Try)?.position;
+
+        let mut resources = Vec::new();
+        if self.eat(&TokenKind::LParen) {
+            while !self.is(&TokenKind::RParen) {
+                resources.push(self.resource()?);
+                if !self.eat(&TokenKind::Semicolon) {
+                    break;
+                }
+            }
+            self.expect(TokenKind::RParen)?;
+        }
+
+        let body = self.block()?;
+
+        let mut catches = Vec::new();
+        while self.is_keyword(Keyword::Catch) {
+            let position = self.advance().position;
+            self.expect(TokenKind::LParen)?;
+            let modifiers = self.modifiers()?;
+            let mut types = vec![self.parse_type()?];
+            while self.eat(&TokenKind::Bar) {
+                types.push(self.parse_type()?);
+            }
+            let name = self.identifier()?;
+            self.expect(TokenKind::RParen)?;
+            let body = self.block()?;
+            catches.push(Catch { modifiers, types, name, body, position });
+        }
+
+        let finally = if self.eat_keyword(Keyword::Finally) { Some(self.block()?) } else { None };
+
+        if resources.is_empty() && catches.is_empty() && finally.is_none() {
+            return self.error(position, "'try' without 'catch', 'finally' or resource declarations");
+        }
+        Ok(StatementKind::Try { resources, body, catches, finally })
+    }
+
+    fn resource(&mut self) -> ParseResult<Resource> {
+        if self.is_local_variable_declaration() {
+            let modifiers = self.modifiers()?;
+            let variable_type = self.local_variable_type()?;
+            let name = self.identifier()?;
+            self.expect(TokenKind::Assign)?;
+            let initializer = Some(self.expression()?);
+            let declarators = vec![VariableDeclarator { name, dimensions: 0, initializer }];
+            Ok(Resource::Declaration(LocalVariable { modifiers, variable_type, declarators }))
+        } else {
+            Ok(Resource::Expression(self.expression()?))
+        }
+    }
+
+    // Switch statements and expressions (JLS §14.11, §15.28) share their syntax.
+    fn switch(&mut self) -> ParseResult<Switch> {
+        self.expect_keyword(Keyword::Switch)?;
+        let selector = Box::new(self.parenthesized()?);
+        self.expect(TokenKind::LBrace)?;
+
+        let mut cases = Vec::new();
+        let mut end = self.position();
+        while !self.eat(&TokenKind::RBrace) {
+            let position = self.position();
+            let mut labels = Vec::new();
+            if self.eat_keyword(Keyword::Case) {
+                loop {
+                    // A lambda is not allowed here, so case a -> ... is a case label.
+                    labels.push(self.conditional()?);
+                    if !self.eat(&TokenKind::Comma) {
+                        break;
+                    }
+                }
+            } else if !self.eat_keyword(Keyword::Default) {
+                return self.expected("'case', 'default', or '}'");
+            }
+
+            let body = if self.eat(&TokenKind::Arrow) {
+                if self.is(&TokenKind::LBrace) || self.is_keyword(Keyword::Throw) {
+                    SwitchBody::Statement(Box::new(self.statement()?))
+                } else {
+                    let expression = self.expression()?;
+                    self.expect(TokenKind::Semicolon)?;
+                    SwitchBody::Expression(expression)
+                }
+            } else {
+                if self.is(&TokenKind::Arrow) || !self.is(&TokenKind::Colon) {
+                    return self.expected("':' or '->'");
+                }
+                self.advance();
+                let mut statements = Vec::new();
+                while !matches!(*self.peek(), TokenKind::Keyword(Keyword::Case) | TokenKind::Keyword(Keyword::Default) | TokenKind::RBrace | TokenKind::Eof) {
+                    statements.push(self.block_statement()?);
+                }
+                SwitchBody::Statements(statements)
+            };
+
+            cases.push(SwitchCase { labels, body, position });
+            end = self.position();
+        }
+
+        Ok(Switch { selector, cases, end })
+    }
+
+    // Expressions (JLS §15)
+
+    fn expression(&mut self) -> ParseResult<Expression> {
+        if self.is_lambda() {
+            return self.lambda();
+        }
+
+        let target = self.conditional()?;
+        if let Some(operator) = assignment_operator(self.peek()) {
+            let position = self.advance().position;
+            let value = self.expression()?;
+            let kind = ExpressionKind::Assign { operator, target: Box::new(target), value: Box::new(value) };
+            return Ok(Expression { kind, position });
+        }
+        Ok(target)
+    }
+
+    // x -> ..., (x, y) -> ... and (int x) -> ... all start a lambda.
+    fn is_lambda(&self) -> bool {
+        match *self.peek() {
+            TokenKind::Identifier(_) => *self.peek_at(1) == TokenKind::Arrow,
+            TokenKind::LParen => {
+                let mut depth = 0;
+                for (offset, token) in self.tokens[self.index..].iter().enumerate() {
+                    match token.kind {
+                        TokenKind::LParen => depth += 1,
+                        TokenKind::RParen => {
+                            depth -= 1;
+                            if depth == 0 {
+                                return *self.peek_at(offset + 1) == TokenKind::Arrow;
+                            }
+                        },
+                        TokenKind::Eof => return false,
+                        _ => {}
+                    }
+                }
+                false
+            },
+            _ => false
+        }
+    }
+
+    fn lambda(&mut self) -> ParseResult<Expression> {
+        let position = self.position();
+        let mut parameters = Vec::new();
+
+        if let TokenKind::Identifier(_) = *self.peek() {
+            let name = self.identifier()?;
+            parameters.push(LambdaParameter { modifiers: Modifiers::default(), parameter_type: None, variable_arity: false, name });
+        } else {
+            self.expect(TokenKind::LParen)?;
+            let inferred = matches!(*self.peek(), TokenKind::Identifier(_)) && matches!(*self.peek_at(1), TokenKind::Comma | TokenKind::RParen);
+            while !self.is(&TokenKind::RParen) {
+                let parameter = if inferred {
+                    let name = self.identifier()?;
+                    LambdaParameter { modifiers: Modifiers::default(), parameter_type: None, variable_arity: false, name }
+                } else {
+                    let modifiers = self.modifiers()?;
+                    let parameter_type = self.local_variable_type()?;
+                    let variable_arity = self.eat(&TokenKind::Ellipsis);
+                    let name = self.identifier()?;
+                    let parameter_type = parameter_type.map(|mut parameter_type| {
+                        for _ in 0..self.dimension_count() {
+                            parameter_type = Type::Array(Box::new(parameter_type));
+                        }
+                        parameter_type
+                    });
+                    LambdaParameter { modifiers, parameter_type, variable_arity, name }
+                };
+                parameters.push(parameter);
+                if !self.eat(&TokenKind::Comma) {
+                    break;
+                }
+            }
+            self.expect(TokenKind::RParen)?;
+        }
+
+        self.expect(TokenKind::Arrow)?;
+        let body = if self.is(&TokenKind::LBrace) {
+            LambdaBody::Block(self.block()?)
+        } else {
+            LambdaBody::Expression(Box::new(self.expression()?))
+        };
+        Ok(Expression { kind: ExpressionKind::Lambda { parameters, body }, position })
+    }
+
+    fn conditional(&mut self) -> ParseResult<Expression> {
+        let condition = self.binary(1)?;
+        if !self.is(&TokenKind::Question) {
+            return Ok(condition);
+        }
+
+        let position = self.advance().position;
+        let then = self.expression()?;
+        self.expect(TokenKind::Colon)?;
+        let otherwise = if self.is_lambda() { self.lambda()? } else { self.conditional()? };
+        let kind = ExpressionKind::Conditional { condition: Box::new(condition), then: Box::new(then), otherwise: Box::new(otherwise) };
+        Ok(Expression { kind, position })
+    }
+
+    // Binary operators by precedence climbing. Operands of operators with at least the given
+    // precedence are combined, left to right.
+    fn binary(&mut self, minimum_precedence: u8) -> ParseResult<Expression> {
+        let mut left = self.unary()?;
+        loop {
+            let position = self.position();
+            if self.is_keyword(Keyword::Instanceof) && RELATIONAL_PRECEDENCE >= minimum_precedence {
+                self.advance();
+                self.modifiers()?;
+                let instance_type = self.parse_type()?;
+                let binding = if matches!(*self.peek(), TokenKind::Identifier(_)) { Some(self.identifier()?) } else { None };
+                let kind = ExpressionKind::InstanceOf { expression: Box::new(left), instance_type, binding };
+                left = Expression { kind, position };
+                continue;
+            }
+
+            let (operator, precedence) = match binary_operator(self.peek()) {
+                Some((operator, precedence)) if precedence >= minimum_precedence => (operator, precedence),
+                _ => return Ok(left)
+            };
+            self.advance();
+            let right = self.binary(precedence + 1)?;
+            let kind = ExpressionKind::Binary { operator, left: Box::new(left), right: Box::new(right) };
+            left = Expression { kind, position };
+        }
+    }
+
+    fn unary(&mut self) -> ParseResult<Expression> {
+        let position = self.position();
+        let operator = match *self.peek() {
+            TokenKind::PlusPlus => UnaryOperator::PreIncrement,
+            TokenKind::MinusMinus => UnaryOperator::PreDecrement,
+            TokenKind::Plus => UnaryOperator::Plus,
+            TokenKind::Minus => UnaryOperator::Minus,
+            TokenKind::Tilde => UnaryOperator::BitwiseNot,
+            TokenKind::Bang => UnaryOperator::Not,
+            TokenKind::LParen => {
+                if let Some(cast) = self.cast()? {
+                    return Ok(cast);
+                }
+                let primary = self.primary()?;
+                return self.postfix(primary);
+            },
+            _ => {
+                let primary = self.primary()?;
+                return self.postfix(primary);
+            }
+        };
+        self.advance();
+
+        // -2147483648 and -9223372036854775808L are the only places these literals can appear.
+        if operator == UnaryOperator::Minus {
+            let literal = match *self.peek() {
+                TokenKind::IntLiteral { value, .. } => Some(Literal::Int((value as i32).wrapping_neg())),
+                TokenKind::LongLiteral { value, .. } => Some(Literal::Long((value as i64).wrapping_neg())),
+                _ => None
+            };
+            if let Some(literal) = literal {
+                self.advance();
+                return Ok(Expression { kind: ExpressionKind::Literal(literal), position });
+            }
+        }
+
+        let operand = self.unary()?;
+        Ok(Expression { kind: ExpressionKind::Unary { operator, operand: Box::new(operand) }, position })
+    }
+
+    // Whether the token after the parentheses can start the operand of a cast to a reference
+    // type. (a) + b is an addition, but (T) +b is not a cast to T: only primitive casts may be
+    // followed by + or -.
+    fn can_start_cast_operand(&self) -> bool {
+        match *self.peek() {
+            TokenKind::Identifier(_) | TokenKind::IntLiteral { .. } | TokenKind::LongLiteral { .. }
+                | TokenKind::FloatLiteral(_) | TokenKind::DoubleLiteral(_) | TokenKind::CharLiteral(_)
+                | TokenKind::StringLiteral(_) | TokenKind::LParen | TokenKind::Bang | TokenKind::Tilde => true,
+            TokenKind::Keyword(keyword) => match keyword {
+                Keyword::This | Keyword::Super | Keyword::New | Keyword::True | Keyword::False | Keyword::Null
+                    | Keyword::Switch | Keyword::Void => true,
+                _ => primitive_type(self.peek()).is_some()
+            },
+            _ => false
+        }
+    }
+
+    fn cast(&mut self) -> ParseResult<Option<Expression>> {
+        let position = self.position();
+        let snapshot = self.snapshot();
+        self.expect(TokenKind::LParen)?;
+
+        if primitive_type(self.peek()).is_some() {
+            let cast_type = self.parse_type()?;
+            if !self.eat(&TokenKind::RParen) {
+                self.restore(snapshot);
+                return Ok(None);
+            }
+            let expression = Box::new(self.unary()?);
+            return Ok(Some(Expression { kind: ExpressionKind::Cast { cast_type, bounds: Vec::new(), expression }, position }));
+        }
+
+        let reference = self.attempt(|parser| {
+            let cast_type = parser.parse_type()?;
+            let mut bounds = Vec::new();
+            while parser.eat(&TokenKind::Amp) {
+                bounds.push(parser.parse_type()?);
+            }
+            parser.expect(TokenKind::RParen)?;
+            Ok((cast_type, bounds))
+        });
+
+        match reference {
+            Some((cast_type, bounds)) if self.can_start_cast_operand() => {
+                let expression = if self.is_lambda() { self.lambda()? } else { self.unary()? };
+                let kind = ExpressionKind::Cast { cast_type, bounds, expression: Box::new(expression) };
+                Ok(Some(Expression { kind, position }))
+            },
+            _ => {
+                self.restore(snapshot);
+                Ok(None)
+            }
+        }
+    }
+
+    fn arguments(&mut self) -> ParseResult<Vec<Expression>> {
+        self.expect(TokenKind::LParen)?;
+        let mut arguments = Vec::new();
+        if !self.is(&TokenKind::RParen) {
+            loop {
+                arguments.push(self.expression()?);
+                if !self.eat(&TokenKind::Comma) {
+                    break;
+                }
+            }
+        }
+        self.expect(TokenKind::RParen)?;
+        Ok(arguments)
+    }
+
+    fn literal(&mut self) -> ParseResult<Option<Literal>> {
+        let literal = match *self.peek() {
+            TokenKind::IntLiteral { negation_only: true, .. } | TokenKind::LongLiteral { negation_only: true, .. } => {
+                return self.error(self.position(), "integer number too large");
+            },
+            TokenKind::IntLiteral { value, .. } => Literal::Int(value as i32),
+            TokenKind::LongLiteral { value, .. } => Literal::Long(value as i64),
+            TokenKind::FloatLiteral(value) => Literal::Float(value),
+            TokenKind::DoubleLiteral(value) => Literal::Double(value),
+            TokenKind::CharLiteral(value) => Literal::Char(value),
+            TokenKind::StringLiteral(ref value) => Literal::String(value.clone()),
+            TokenKind::Keyword(Keyword::True) => Literal::Boolean(true),
+            TokenKind::Keyword(Keyword::False) => Literal::Boolean(false),
+            TokenKind::Keyword(Keyword::Null) => Literal::Null,
+            _ => return Ok(None)
+        };
+        self.advance();
+        Ok(Some(literal))
+    }
+
+    // Method references after ::, e.g. String::valueOf, List::<String>of or int[]::new.
+    fn method_reference(&mut self, target: MethodReferenceTarget, position: Position) -> ParseResult<Expression> {
+        self.expect(TokenKind::ColonColon)?;
+        let type_arguments = if self.is(&TokenKind::Lt) { self.type_arguments(false)?.unwrap_or_default() } else { Vec::new() };
+        let name = if self.is_keyword(Keyword::New) {
+            Identifier { name: String::from("new"), position: self.advance().position }
+        } else {
+            self.identifier()?
+        };
+        Ok(Expression { kind: ExpressionKind::MethodReference { target, type_arguments, name }, position })
+    }
+
+    // int.class, String[].class, or the same types before :: in a method reference.
+    fn type_suffix(&mut self, target_type: Type, position: Position) -> ParseResult<Expression> {
+        if self.is(&TokenKind::ColonColon) {
+            return self.method_reference(MethodReferenceTarget::Type(target_type), position);
+        }
+        self.expect(TokenKind::Dot)?;
+        self.expect_keyword(Keyword::Class)?;
+        Ok(Expression { kind: ExpressionKind::ClassLiteral(target_type), position })
+    }
+
+    // super.f, super.m(), super::m and their qualified forms, after super.
+    fn super_suffix(&mut self, qualifier: Option<QualifiedName>, position: Position) -> ParseResult<Expression> {
+        if self.is(&TokenKind::ColonColon) {
+            return self.method_reference(MethodReferenceTarget::Super(qualifier), position);
+        }
+        self.expect(TokenKind::Dot)?;
+        let type_arguments = if self.is(&TokenKind::Lt) { self.type_arguments(false)?.unwrap_or_default() } else { Vec::new() };
+        let name = self.identifier()?;
+        let kind = if self.is(&TokenKind::LParen) || !type_arguments.is_empty() {
+            let arguments_position = self.position();
+            let arguments = self.arguments()?;
+            ExpressionKind::SuperMethodCall { qualifier, type_arguments, name, arguments, arguments_position }
+        } else {
+            ExpressionKind::SuperFieldAccess { qualifier, name }
+        };
+        Ok(Expression { kind, position })
+    }
+
+    fn primary(&mut self) -> ParseResult<Expression> {
+        let position = self.position();
+
+        if let Some(literal) = self.literal()? {
+            return Ok(Expression { kind: ExpressionKind::Literal(literal), position });
+        }
+
+        if let Some(primitive) = primitive_type(self.peek()) {
+            self.advance();
+            let primitive = self.dimensions(Type::Primitive(primitive, position));
+            return self.type_suffix(primitive, position);
+        }
+
+        let kind = match *self.peek() {
+            TokenKind::Keyword(Keyword::Void) => {
+                self.advance();
+                return self.type_suffix(Type::Void(position), position);
+            },
+            TokenKind::Keyword(Keyword::This) => {
+                self.advance();
+                ExpressionKind::This(None)
+            },
+            TokenKind::Keyword(Keyword::Super) => {
+                self.advance();
+                return self.super_suffix(None, position);
+            },
+            TokenKind::Keyword(Keyword::New) => return self.creator(None),
+            TokenKind::Keyword(Keyword::Switch) => ExpressionKind::Switch(self.switch()?),
+            TokenKind::LParen => {
+                self.advance();
+                let expression = self.expression()?;
+                self.expect(TokenKind::RParen)?;
+                ExpressionKind::Parenthesized(Box::new(expression))
+            },
+            TokenKind::Identifier(_) => {
+                // A generic type in a method reference, e.g. List<String>::size.
+                if *self.peek_at(1) == TokenKind::Lt {
+                    let generic_type = self.attempt(|parser| {
+                        let generic_type = parser.parse_type()?;
+                        if parser.is(&TokenKind::ColonColon) { Ok(generic_type) } else { parser.expected("'::'") }
+                    });
+                    if let Some(generic_type) = generic_type {
+                        return self.method_reference(MethodReferenceTarget::Type(generic_type), position);
+                    }
+                }
+
+                let name = self.identifier()?;
+                if self.is(&TokenKind::LParen) {
+                    let arguments_position = self.position();
+                    let arguments = self.arguments()?;
+                    ExpressionKind::MethodCall { target: None, type_arguments: Vec::new(), name, arguments, arguments_position }
+                } else {
+                    ExpressionKind::Name(name)
+                }
+            },
+            _ => return self.error(position, "illegal start of expression")
+        };
+
+        Ok(Expression { kind, position })
+    }
+
+    // Field accesses, method calls, array accesses, method references and postfix operators
+    // after a primary expression.
+    fn postfix(&mut self, mut expression: Expression) -> ParseResult<Expression> {
+        loop {
+            // The expressions built here are positioned at the '.', '[' or postfix operator.
+            let start = expression.position;
+            let position = self.position();
+            let kind = match *self.peek() {
+                TokenKind::Dot => {
+                    self.advance();
+                    match *self.peek() {
+                        TokenKind::Identifier(_) => {
+                            let name = self.identifier()?;
+                            if self.is(&TokenKind::LParen) {
+                                let arguments_position = self.position();
+                                let arguments = self.arguments()?;
+                                ExpressionKind::MethodCall { target: Some(Box::new(expression)), type_arguments: Vec::new(), name, arguments, arguments_position }
+                            } else {
+                                ExpressionKind::FieldAccess { target: Box::new(expression), name }
+                            }
+                        },
+                        TokenKind::Lt => {
+                            let type_arguments = self.type_arguments(false)?.unwrap_or_default();
+                            let name = self.identifier()?;
+                            let arguments_position = self.position();
+                            let arguments = self.arguments()?;
+                            ExpressionKind::MethodCall { target: Some(Box::new(expression)), type_arguments, name, arguments, arguments_position }
+                        },
+                        TokenKind::Keyword(Keyword::New) => {
+                            expression = self.creator(Some(expression))?;
+                            continue;
+                        },
+                        TokenKind::Keyword(Keyword::This) => {
+                            let qualifier = self.qualifier(&expression)?;
+                            self.advance();
+                            ExpressionKind::This(Some(qualifier))
+                        },
+                        TokenKind::Keyword(Keyword::Class) => {
+                            let qualifier = self.qualifier(&expression)?;
+                            self.advance();
+                            ExpressionKind::ClassLiteral(Type::Class(class_type_of(qualifier)))
+                        },
+                        // outer.super(...) is handled as a statement.
+                        TokenKind::Keyword(Keyword::Super) if *self.peek_at(1) == TokenKind::LParen => {
+                            self.index -= 1;
+                            return Ok(expression);
+                        },
+                        TokenKind::Keyword(Keyword::Super) => {
+                            let qualifier = self.qualifier(&expression)?;
+                            self.advance();
+                            expression = self.super_suffix(Some(qualifier), start)?;
+                            continue;
+                        },
+                        _ => return self.expected("<identifier>")
+                    }
+                },
+                TokenKind::LBracket if *self.peek_at(1) == TokenKind::RBracket => {
+                    let qualifier = self.qualifier(&expression)?;
+                    let array_type = self.dimensions(Type::Class(class_type_of(qualifier)));
+                    expression = self.type_suffix(array_type, start)?;
+                    continue;
+                },
+                TokenKind::LBracket => {
+                    self.advance();
+                    let index = self.expression()?;
+                    self.expect(TokenKind::RBracket)?;
+                    ExpressionKind::ArrayAccess { array: Box::new(expression), index: Box::new(index) }
+                },
+                TokenKind::ColonColon => {
+                    expression = self.method_reference(MethodReferenceTarget::Expression(Box::new(expression)), start)?;
+                    continue;
+                },
+                TokenKind::PlusPlus | TokenKind::MinusMinus => {
+                    let operator = if self.advance().kind == TokenKind::PlusPlus { UnaryOperator::PostIncrement } else { UnaryOperator::PostDecrement };
+                    ExpressionKind::Unary { operator, operand: Box::new(expression) }
+                },
+                _ => return Ok(expression)
+            };
+            expression = Expression { kind, position };
+        }
+    }
+
+    // The qualifying name of Outer.this, Outer.super or a.b.C.class.
+    fn qualifier(&self, expression: &Expression) -> ParseResult<QualifiedName> {
+        match qualified_name_of(expression) {
+            Some(name) => Ok(name),
+            None => self.error(self.position(), "<identifier> expected")
+        }
+    }
+
+    // Class instance and array creation (JLS §15.9, §15.10.1), after an optional outer instance.
+    fn creator(&mut self, outer: Option<Expression>) -> ParseResult<Expression> {
+        let new = self.expect_keyword(Keyword::New)?;
+        let position = outer.as_ref().map_or(new.position, |outer| outer.position);
+
+        let type_arguments = if self.is(&TokenKind::Lt) { self.type_arguments(false)?.unwrap_or_default() } else { Vec::new() };
+        self.type_annotations()?;
+
+        let type_position = self.position();
+        if let Some(primitive) = primitive_type(self.peek()) {
+            self.advance();
+            return self.array_creator(Type::Primitive(primitive, type_position), position);
+        }
+
+        let class_type = self.class_type(true)?;
+        if self.is(&TokenKind::LBracket) && outer.is_none() {
+            return self.array_creator(Type::Class(class_type), position);
+        }
+
+        let arguments = self.arguments()?;
+        let body = if self.is(&TokenKind::LBrace) { Some(self.class_body(None)?) } else { None };
+        let kind = ExpressionKind::New { outer: outer.map(Box::new), type_arguments, class_type, arguments, body };
+        Ok(Expression { kind, position })
+    }
+
+    fn array_creator(&mut self, element_type: Type, position: Position) -> ParseResult<Expression> {
+        let mut dimensions = Vec::new();
+        let mut extra_dimensions = 0;
+        while self.is(&TokenKind::LBracket) {
+            if *self.peek_at(1) == TokenKind::RBracket {
+                self.index += 2;
+                extra_dimensions += 1;
+            } else if extra_dimensions == 0 {
+                self.advance();
+                dimensions.push(self.expression()?);
+                self.expect(TokenKind::RBracket)?;
+            } else {
+                break;
+            }
+        }
+
+        let initializer = if self.is(&TokenKind::LBrace) {
+            if !dimensions.is_empty() {
+                return self.error(self.position(), "array creation with both dimension expression and initialization is illegal");
+            }
+            Some(self.array_initializer()?)
+        } else {
+            if dimensions.is_empty() {
+                return self.error(position, if extra_dimensions == 0 { "'(' or '[' expected" } else { "array dimension missing" });
+            }
+            None
+        };
+
+        let kind = ExpressionKind::NewArray { element_type, dimensions, extra_dimensions, initializer };
+        Ok(Expression { kind, position })
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn parse_source(source: &str) -> CompilationUnit {
+        parse("Test.java", source).unwrap_or_else(|diagnostic| panic!("{}", diagnostic.render(source)))
+    }
+
+    fn error(source: &str) -> String {
+        let diagnostic = parse("Test.java", source).unwrap_err();
+        format!("{}:{}: {}", diagnostic.position.line, diagnostic.position.column, diagnostic.message)
+    }
+
+    fn members(source: &str) -> Vec<Member> {
+        let mut unit = parse_source(&format!("class Test {{ {} }}", source));
+        unit.types.remove(0).members
+    }
+
+    fn statements(source: &str) -> Vec<Statement> {
+        match members(&format!("void test() {{ {} }}", source)).remove(0) {
+            Member::Method(method) => method.body.unwrap().statements,
+            member => panic!("Expected a method, got {:?}", member)
+        }
+    }
+
+    fn expression(source: &str) -> String {
+        match members(&format!("Object test = {};", source)).remove(0) {
+            Member::Field(mut field) => show(&field.declarators.remove(0).initializer.unwrap()),
+            member => panic!("Expected a field, got {:?}", member)
+        }
+    }
+
+    fn show_type(shown: &Type) -> String {
+        match *shown {
+            Type::Primitive(primitive, _) => String::from(primitive.name()),
+            Type::Void(_) => String::from("void"),
+            Type::Array(ref component) => format!("{}[]", show_type(component)),
+            Type::Class(ref class_type) => show_class_type(class_type)
+        }
+    }
+
+    fn show_class_type(class_type: &ClassType) -> String {
+        let parts: Vec<String> = class_type.parts.iter().map(|part| match part.arguments {
+            None => format!("{}<>", part.name.name),
+            Some(ref arguments) if arguments.is_empty() => part.name.name.clone(),
+            Some(ref arguments) => format!("{}{}", part.name.name, show_type_arguments(arguments))
+        }).collect();
+        parts.join(".")
+    }
+
+    fn show_type_arguments(arguments: &[TypeArgument]) -> String {
+        if arguments.is_empty() {
+            return String::new();
+        }
+        let arguments: Vec<String> = arguments.iter().map(|argument| match *argument {
+            TypeArgument::Type(ref argument) => show_type(argument),
+            TypeArgument::Wildcard(None, _) => String::from("?"),
+            TypeArgument::Wildcard(Some(WildcardBound::Extends(ref bound)), _) => format!("? extends {}", show_type(bound)),
+            TypeArgument::Wildcard(Some(WildcardBound::Super(ref bound)), _) => format!("? super {}", show_type(bound))
+        }).collect();
+        format!("<{}>", arguments.join(", "))
+    }
+
+    fn show_all(expressions: &[Expression]) -> String {
+        expressions.iter().map(show).collect::<Vec<String>>().join(", ")
+    }
+
+    fn binary_symbol(operator: BinaryOperator) -> &'static str {
+        match operator {
+            BinaryOperator::Or => "||",
+            BinaryOperator::And => "&&",
+            BinaryOperator::BitwiseOr => "|",
+            BinaryOperator::BitwiseXor => "^",
+            BinaryOperator::BitwiseAnd => "&",
+            BinaryOperator::Equal => "==",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::Less => "<",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::LessOrEqual => "<=",
+            BinaryOperator::GreaterOrEqual => ">=",
+            BinaryOperator::ShiftLeft => "<<",
+            BinaryOperator::ShiftRight => ">>",
+            BinaryOperator::UnsignedShiftRight => ">>>",
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Remainder => "%"
+        }
+    }
+
+    // Operators are shown as S-expressions, so that the shape of the tree is explicit.
+    fn show(expression: &Expression) -> String {
+        match expression.kind {
+            ExpressionKind::Literal(ref literal) => match *literal {
+                Literal::Int(value) => value.to_string(),
+                Literal::Long(value) => format!("{}L", value),
+                Literal::Float(value) => format!("{:?}f", value),
+                Literal::Double(value) => format!("{:?}", value),
+                Literal::Char(value) => format!("{:?}", String::from_utf16_lossy(&[value])),
+                Literal::String(ref value) => format!("{:?}", String::from_utf16_lossy(value)),
+                Literal::Boolean(value) => value.to_string(),
+                Literal::Null => String::from("null")
+            },
+            ExpressionKind::Name(ref name) => name.name.clone(),
+            ExpressionKind::FieldAccess { ref target, ref name } => format!("{}.{}", show(target), name.name),
+            ExpressionKind::SuperFieldAccess { ref qualifier, ref name } => match *qualifier {
+                Some(ref qualifier) => format!("{}.super.{}", qualifier.to_dotted(), name.name),
+                None => format!("super.{}", name.name)
+            },
+            ExpressionKind::ArrayAccess { ref array, ref index } => format!("{}[{}]", show(array), show(index)),
+            ExpressionKind::MethodCall { ref target, ref type_arguments, ref name, ref arguments, .. } => {
+                let target = target.as_ref().map_or(String::new(), |target| format!("{}.", show(target)));
+                format!("{}{}{}({})", target, show_type_arguments(type_arguments), name.name, show_all(arguments))
+            },
+            ExpressionKind::SuperMethodCall { ref qualifier, ref name, ref arguments, .. } => {
+                let qualifier = qualifier.as_ref().map_or(String::new(), |qualifier| format!("{}.", qualifier.to_dotted()));
+                format!("{}super.{}({})", qualifier, name.name, show_all(arguments))
+            },
+            ExpressionKind::New { ref outer, ref class_type, ref arguments, ref body, .. } => {
+                let outer = outer.as_ref().map_or(String::new(), |outer| format!("{}.", show(outer)));
+                let body = body.as_ref().map_or(String::new(), |body| format!(" {{{}}}", body.len()));
+                format!("{}new {}({}){}", outer, show_class_type(class_type), show_all(arguments), body)
+            },
+            ExpressionKind::NewArray { ref element_type, ref dimensions, extra_dimensions, ref initializer } => {
+                let dimensions: String = dimensions.iter().map(|dimension| format!("[{}]", show(dimension))).collect();
+                let initializer = initializer.as_ref().map_or(String::new(), |elements| format!(" {{{}}}", show_all(elements)));
+                format!("new {}{}{}{}", show_type(element_type), dimensions, "[]".repeat(extra_dimensions), initializer)
+            },
+            ExpressionKind::ArrayInitializer(ref elements) => format!("{{{}}}", show_all(elements)),
+            ExpressionKind::Unary { operator, ref operand } => {
+                let symbol = match operator {
+                    UnaryOperator::Plus => "+",
+                    UnaryOperator::Minus => "-",
+                    UnaryOperator::BitwiseNot => "~",
+                    UnaryOperator::Not => "!",
+                    UnaryOperator::PreIncrement => "pre++",
+                    UnaryOperator::PreDecrement => "pre--",
+                    UnaryOperator::PostIncrement => "post++",
+                    UnaryOperator::PostDecrement => "post--"
+                };
+                format!("({} {})", symbol, show(operand))
+            },
+            ExpressionKind::Binary { operator, ref left, ref right } => format!("({} {} {})", binary_symbol(operator), show(left), show(right)),
+            ExpressionKind::Assign { operator, ref target, ref value } => {
+                let symbol = operator.map_or("", binary_symbol);
+                format!("({}= {} {})", symbol, show(target), show(value))
+            },
+            ExpressionKind::Conditional { ref condition, ref then, ref otherwise } => format!("(? {} {} {})", show(condition), show(then), show(otherwise)),
+            ExpressionKind::InstanceOf { ref expression, ref instance_type, ref binding } => {
+                let binding = binding.as_ref().map_or(String::new(), |binding| format!(" {}", binding.name));
+                format!("(instanceof {} {}{})", show(expression), show_type(instance_type), binding)
+            },
+            ExpressionKind::Cast { ref cast_type, ref bounds, ref expression } => {
+                let bounds: String = bounds.iter().map(|bound| format!(" & {}", show_type(bound))).collect();
+                format!("(cast {}{} {})", show_type(cast_type), bounds, show(expression))
+            },
+            ExpressionKind::Lambda { ref parameters, ref body } => {
+                let parameters: Vec<String> = parameters.iter().map(|parameter| match parameter.parameter_type {
+                    Some(ref parameter_type) => format!("{}{} {}", show_type(parameter_type), if parameter.variable_arity { "..." } else { "" }, parameter.name.name),
+                    None => parameter.name.name.clone()
+                }).collect();
+                let body = match *body {
+                    LambdaBody::Expression(ref expression) => show(expression),
+                    LambdaBody::Block(ref block) => format!("{{{}}}", block.statements.len())
+                };
+                format!("(lambda ({}) {})", parameters.join(", "), body)
+            },
+            ExpressionKind::MethodReference { ref target, ref type_arguments, ref name } => {
+                let target = match *target {
+                    MethodReferenceTarget::Expression(ref expression) => show(expression),
+                    MethodReferenceTarget::Type(ref target_type) => show_type(target_type),
+                    MethodReferenceTarget::Super(None) => String::from("super"),
+                    MethodReferenceTarget::Super(Some(ref qualifier)) => format!("{}.super", qualifier.to_dotted())
+                };
+                format!("{}::{}{}", target, show_type_arguments(type_arguments), name.name)
+            },
+            ExpressionKind::This(None) => String::from("this"),
+            ExpressionKind::This(Some(ref qualifier)) => format!("{}.this", qualifier.to_dotted()),
+            ExpressionKind::ClassLiteral(ref class_type) => format!("{}.class", show_type(class_type)),
+            ExpressionKind::Switch(ref switch) => format!("(switch {} {})", show(&switch.selector), switch.cases.len()),
+            ExpressionKind::Parenthesized(ref expression) => format!("(paren {})", show(expression))
+        }
+    }
+
+    #[test]
+    fn counter() {
+        let unit = parse("Counter.java", include_str!("../../Counter.java")).unwrap();
+        let counter = &unit.types[0];
+
+        assert_eq!(counter.name.name, "Counter");
+        assert_eq!(counter.kind, TypeKind::Class);
+        assert!(counter.modifiers.contains(Modifier::Public));
+
+        let shapes: Vec<String> = counter.members.iter().map(|member| match *member {
+            Member::Field(ref field) => {
+                let names: Vec<&str> = field.declarators.iter().map(|declarator| declarator.name.name.as_str()).collect();
+                format!("field {} {}", show_type(&field.field_type), names.join(", "))
+            },
+            Member::Constructor(ref constructor) => format!("constructor {}", constructor.parameters.as_ref().unwrap().len()),
+            Member::Method(ref method) => format!("method {} {}", show_type(&method.return_type), method.name.name),
+            _ => String::from("other")
+        }).collect();
+        assert_eq!(shapes, vec![
+            "field int key",
+            "field Counter left, right",
+            "constructor 1",
+            "method void it",
+            "method int set",
+            "method void main"
+        ]);
+
+        let main = match counter.members[5] {
+            Member::Method(ref method) => method,
+            _ => unreachable!()
+        };
+        assert_eq!(show_type(&main.parameters[0].parameter_type), "String[]");
+        let last = main.body.as_ref().unwrap().statements.last().unwrap();
+        assert_eq!(last.position, Position::new(25, 9));
+        match last.kind {
+            StatementKind::LocalVariable(ref variable) => {
+                assert_eq!(show(variable.declarators[0].initializer.as_ref().unwrap()), "(== c null)");
+            },
+            ref kind => panic!("Expected a local variable, got {:?}", kind)
+        }
+    }
+
+    #[test]
+    fn corpus() {
+        let corpus = [
+            ("Declarations.java", include_str!("../../fixtures/parser/Declarations.java")),
+            ("Generics.java", include_str!("../../fixtures/parser/Generics.java")),
+            ("Lambdas.java", include_str!("../../fixtures/parser/Lambdas.java")),
+            ("Literals.java", include_str!("../../fixtures/parser/Literals.java")),
+            ("Statements.java", include_str!("../../fixtures/parser/Statements.java")),
+            ("Switches.java", include_str!("../../fixtures/parser/Switches.java"))
+        ];
+
+        for &(file, source) in corpus.iter() {
+            let unit = parse(file, source).unwrap_or_else(|diagnostic| panic!("{}", diagnostic.render(source)));
+            assert_eq!(unit.package.unwrap().name.to_dotted(), "corpus");
+            assert_eq!(unit.types.iter().filter(|declaration| format!("{}.java", declaration.name.name) == file).count(), 1);
+        }
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(expression("a + b * c - d"), "(- (+ a (* b c)) d)");
+        assert_eq!(expression("a || b && c | d ^ e & f == g < h << i + j * k"),
+            "(|| a (&& b (| c (^ d (& e (== f (< g (<< h (+ i (* j k))))))))))");
+        assert_eq!(expression("a = b += c = 1"), "(= a (+= b (= c 1)))");
+        assert_eq!(expression("c ? x : y ? z : w"), "(? c x (? y z w))");
+        assert_eq!(expression("-x++ + ~!y"), "(+ (- (post++ x)) (~ (! y)))");
+        assert_eq!(expression("- -1"), "(- -1)");
+        assert_eq!(expression("-2147483648"), "-2147483648");
+        assert_eq!(expression("-9223372036854775808L"), "-9223372036854775808L");
+        assert_eq!(expression("x instanceof String s && s.isEmpty()"), "(&& (instanceof x String s) s.isEmpty())");
+        assert_eq!(expression("a.b.c[i][j].d(e).f"), "a.b.c[i][j].d(e).f");
+    }
+
+    #[test]
+    fn generics() {
+        assert_eq!(expression("new HashMap<String, List<Map<String, Integer>>>()"), "new HashMap<String, List<Map<String, Integer>>>()");
+        assert_eq!(expression("new ArrayList<>()"), "new ArrayList<>()");
+        assert_eq!(expression("Collections.<String>emptyList()"), "Collections.<String>emptyList()");
+        assert_eq!(expression("i < n >> 1"), "(< i (>> n 1))");
+        assert_eq!(expression("a < b == c > d"), "(== (< a b) (> c d))");
+        assert_eq!(expression("f(a < b, c > d)"), "f((< a b), (> c d))");
+        assert_eq!(expression("java.util.Map.Entry.class"), "java.util.Map.Entry.class");
+
+        match members("Map<K, List<? extends Number>>[] a; Map<String, List<? super T>> b;").as_slice() {
+            [Member::Field(a), Member::Field(b)] => {
+                assert_eq!(show_type(&a.field_type), "Map<K, List<? extends Number>>[]");
+                assert_eq!(show_type(&b.field_type), "Map<String, List<? super T>>");
+            },
+            members => panic!("Expected two fields, got {:?}", members)
+        }
+
+        match members("<T extends Number & Comparable<T>> T max(T... values) { return null; }").remove(0) {
+            Member::Method(method) => {
+                assert_eq!(method.type_parameters[0].bounds.iter().map(show_type).collect::<Vec<String>>(), vec!["Number", "Comparable<T>"]);
+                assert!(method.parameters[0].variable_arity);
+            },
+            member => panic!("Expected a method, got {:?}", member)
+        }
+    }
+
+    #[test]
+    fn casts_and_parentheses() {
+        assert_eq!(expression("(int) x + y"), "(+ (cast int x) y)");
+        assert_eq!(expression("(int) -x"), "(cast int (- x))");
+        assert_eq!(expression("(a) + b"), "(+ (paren a) b)");
+        assert_eq!(expression("(Integer) - 1"), "(- (paren Integer) 1)");
+        assert_eq!(expression("(String) o.toString()"), "(cast String o.toString())");
+        assert_eq!(expression("(List<String>) o"), "(cast List<String> o)");
+        assert_eq!(expression("(int[][]) o"), "(cast int[][] o)");
+        assert_eq!(expression("(Comparable<T> & Serializable) o"), "(cast Comparable<T> & Serializable o)");
+        assert_eq!(expression("(Object) (String) s"), "(cast Object (cast String s))");
+        assert_eq!(expression("(a < b)"), "(paren (< a b))");
+        assert_eq!(expression("(Runnable) () -> {}"), "(cast Runnable (lambda () {0}))");
+    }
+
+    #[test]
+    fn lambdas_and_method_references() {
+        assert_eq!(expression("x -> x + 1"), "(lambda (x) (+ x 1))");
+        assert_eq!(expression("(a, b) -> { return a; }"), "(lambda (a, b) {1})");
+        assert_eq!(expression("(int a, String... b) -> a"), "(lambda (int a, String... b) a)");
+        assert_eq!(expression("(var a, final var b) -> a"), "(lambda (a, b) a)");
+        assert_eq!(expression("a -> b -> a"), "(lambda (a) (lambda (b) a))");
+        assert_eq!(expression("f(x -> x, (y) -> y)"), "f((lambda (x) x), (lambda (y) y))");
+        assert_eq!(expression("c ? () -> 1 : () -> 2"), "(? c (lambda () 1) (lambda () 2))");
+
+        assert_eq!(expression("String::valueOf"), "String::valueOf");
+        assert_eq!(expression("System.out::println"), "System.out::println");
+        assert_eq!(expression("ArrayList::new"), "ArrayList::new");
+        assert_eq!(expression("int[]::new"), "int[]::new");
+        assert_eq!(expression("String[][]::new"), "String[][]::new");
+        assert_eq!(expression("List<String>::size"), "List<String>::size");
+        assert_eq!(expression("Arrays::<String>asList"), "Arrays::<String>asList");
+        assert_eq!(expression("super::toString"), "super::toString");
+        assert_eq!(expression("Outer.super::toString"), "Outer.super::toString");
+        assert_eq!(expression("this::run"), "this::run");
+    }
+
+    #[test]
+    fn creation_and_primaries() {
+        assert_eq!(expression("new int[n][]"), "new int[n][]");
+        assert_eq!(expression("new String[][] {{\"a\"}, {}}"), "new String[][] {{\"a\"}, {}}");
+        assert_eq!(expression("new int[] {1, 2,}.length"), "new int[] {1, 2}.length");
+        assert_eq!(expression("outer.new Inner(1)"), "outer.new Inner(1)");
+        assert_eq!(expression("new Runnable() { public void run() {} }"), "new Runnable() {1}");
+        assert_eq!(expression("Outer.this.x"), "Outer.this.x");
+        assert_eq!(expression("super.x + Outer.super.m(1)"), "(+ super.x Outer.super.m(1))");
+        assert_eq!(expression("int.class"), "int.class");
+        assert_eq!(expression("String[].class"), "String[].class");
+        assert_eq!(expression("void.class"), "void.class");
+        assert_eq!(expression("{1, {2}}"), "{1, {2}}");
+        assert_eq!(expression("\"\"\"\n    text\n    \"\"\".length()"), "\"text\\n\".length()");
+        assert_eq!(expression("'\\u0041'"), "\"A\"");
+    }
+
+    #[test]
+    fn statements_and_declarations() {
+        let kinds: Vec<String> = statements("
+            int a = 1, b[] = {};
+            List<String> list;
+            var v = a;
+            final int f = 2;
+            a = b[0];
+            foo(a);
+            yield(a);
+            int yield = 1;
+            label: for (;;) break label;
+            for (int x : xs) {}
+            for (var i = 0; i < 10; i++, j++) {}
+            class Local {}
+            record Pair(int a, int b) {}
+            this.x++;
+        ").iter().map(|statement| match statement.kind {
+            StatementKind::LocalVariable(ref variable) => {
+                let variable_type = variable.variable_type.as_ref().map_or(String::from("var"), show_type);
+                let names: Vec<String> = variable.declarators.iter().map(|declarator| format!("{}{}", declarator.name.name, "[]".repeat(declarator.dimensions))).collect();
+                format!("local {} {}", variable_type, names.join(", "))
+            },
+            StatementKind::Expression(ref expression) => format!("expression {}", show(expression)),
+            StatementKind::Yield(ref value) => format!("yield {}", show(value)),
+            StatementKind::Labeled { ref label, .. } => format!("label {}", label.name),
+            StatementKind::ForEach { ref variable, ref iterable, .. } => format!("for each {} in {}", variable.declarators[0].name.name, show(iterable)),
+            StatementKind::For { ref init, ref update, .. } => format!("for {} {}", init.len(), update.len()),
+            StatementKind::LocalType(ref declaration) => format!("local {:?} {}", declaration.kind, declaration.name.name),
+            ref kind => format!("{:?}", kind)
+        }).collect();
+
+        assert_eq!(kinds, vec![
+            "local int a, b[]",
+            "local List<String> list",
+            "local var v",
+            "local int f",
+            "expression (= a b[0])",
+            "expression foo(a)",
+            "yield (paren a)",
+            "local int yield",
+            "label label",
+            "for each x in xs",
+            "for 1 2",
+            "local Class Local",
+            "local Record Pair",
+            "expression (post++ this.x)"
+        ]);
+    }
+
+    #[test]
+    fn switches() {
+        let switch = match statements("switch (day) { case A, B -> f(); case C -> { g(); } default -> throw e; }").remove(0).kind {
+            StatementKind::Switch(switch) => switch,
+            kind => panic!("Expected a switch, got {:?}", kind)
+        };
+        assert_eq!(switch.cases.len(), 3);
+        assert_eq!(show_all(&switch.cases[0].labels), "A, B");
+        assert!(matches!(switch.cases[0].body, SwitchBody::Expression(_)));
+        assert!(matches!(switch.cases[1].body, SwitchBody::Statement(_)));
+        assert!(switch.cases[2].labels.is_empty());
+
+        let switch = match statements("int x = switch (n) { case 1: case 2: yield 3; default: { yield 4; } };").remove(0).kind {
+            StatementKind::LocalVariable(mut variable) => match variable.declarators.remove(0).initializer.unwrap().kind {
+                ExpressionKind::Switch(switch) => switch,
+                kind => panic!("Expected a switch expression, got {:?}", kind)
+            },
+            kind => panic!("Expected a local variable, got {:?}", kind)
+        };
+        let sizes: Vec<usize> = switch.cases.iter().map(|case| match case.body {
+            SwitchBody::Statements(ref statements) => statements.len(),
+            _ => panic!("Expected statements")
+        }).collect();
+        assert_eq!(sizes, vec![0, 1, 1]);
+    }
+
+    #[test]
+    fn type_declarations() {
+        let unit = parse_source("
+            package a.b;
+            import java.util.*;
+            import static java.lang.Math.max;
+            @Deprecated public sealed interface Shape<T> extends A, B permits Circle {}
+            non-sealed class Circle implements Shape<String> {}
+            record Point(int x, int y) { Point { } }
+            enum Color { RED, GREEN { }, BLUE(1); Color() {} Color(int x) {} }
+            @interface Marker { int value() default 1; }
+        ");
+
+        assert_eq!(unit.package.as_ref().unwrap().name.to_dotted(), "a.b");
+        assert_eq!(unit.imports.iter().map(|import| (import.name.to_dotted(), import.is_static, import.on_demand)).collect::<Vec<_>>(), vec![
+            (String::from("java.util"), false, true),
+            (String::from("java.lang.Math.max"), true, false)
+        ]);
+
+        let shape = &unit.types[0];
+        assert_eq!(shape.kind, TypeKind::Interface);
+        assert!(shape.modifiers.contains(Modifier::Sealed));
+        assert_eq!(shape.modifiers.annotations[0].name.to_dotted(), "Deprecated");
+        assert_eq!(shape.extends.len(), 2);
+        assert_eq!(shape.permits.iter().map(show_type).collect::<Vec<String>>(), vec!["Circle"]);
+
+        assert!(unit.types[1].modifiers.contains(Modifier::NonSealed));
+
+        let point = &unit.types[2];
+        assert_eq!(point.kind, TypeKind::Record);
+        assert_eq!(point.record_components.len(), 2);
+        assert!(matches!(point.members[0], Member::Constructor(ConstructorDeclaration { parameters: None, .. })));
+
+        let color = &unit.types[3];
+        assert_eq!(color.enum_constants.iter().map(|constant| constant.name.name.as_str()).collect::<Vec<&str>>(), vec!["RED", "GREEN", "BLUE"]);
+        assert!(color.enum_constants[1].body.is_some());
+        assert_eq!(color.enum_constants[2].arguments.len(), 1);
+        assert_eq!(color.members.len(), 2);
+
+        match unit.types[4].members[0] {
+            Member::Method(ref method) => assert!(method.default_value.is_some()),
+            ref member => panic!("Expected a method, got {:?}", member)
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error("class A { int x }"), "1:16: ';' expected");
+        assert_eq!(error("class A {"), "1:10: reached end of file while parsing");
+        assert_eq!(error("class A { void m() { 1 + 2; } }"), "1:24: not a statement");
+        assert_eq!(error("class A { void m() { x = ; } }"), "1:26: illegal start of expression");
+        assert_eq!(error("class A {\n  void m() {\n    foo(\n  }\n}"), "4:3: illegal start of expression");
+        assert_eq!(error("int x;"), "1:1: class, interface, enum, or record expected");
+        assert_eq!(error("class A { int x = 2147483648; }"), "1:19: integer number too large");
+        assert_eq!(error("class A { public public int x; }"), "1:18: repeated modifier");
+        assert_eq!(error("class A { void m() { try {} } }"), "1:22: 'try' without 'catch', 'finally' or resource declarations");
+        assert_eq!(error("class A { void m() { else {} } }"), "1:22: 'else' without 'if'");
+        assert_eq!(error("class A { char c = ''; }"), "1:20: empty character literal");
+        assert_eq!(error("class A { Object o = new int[]; }"), "1:22: array dimension missing");
+        assert_eq!(error("class A { List<int> x; }"), "1:16: unexpected type");
+
+        let source = "class A {\n\tint x\n}";
+        let diagnostic = parse("A.java", source).unwrap_err();
+        assert_eq!(diagnostic.render(source), "A.java:2: error: ';' expected\n\tint x\n\t     ^");
+    }
+
+}
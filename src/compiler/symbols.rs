@@ -0,0 +1,880 @@
+// The classes, fields and methods the compiler knows about: those declared in the source files
+// being compiled, and those read from class files on the class path when first needed.
+
+use class::{Attribute, ClassFile};
+use class::{class_flags, field, method};
+use class::path::ClassPath;
+use class::reader;
+use compiler::ast::PrimitiveType;
+use compiler::constant::Constant;
+use compiler::types::{self, ClassType, Type, TypeVariable, Wildcard, OBJECT};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+#[derive(Clone, Debug)]
+pub struct FieldSymbol {
+    // The class that declares the field.
+    pub owner: String,
+    pub name: String,
+    pub field_type: Type,
+    pub access_flags: u16,
+    // The value of a constant variable (JLS §4.12.4).
+    pub constant: Option<Constant>
+}
+
+impl FieldSymbol {
+
+    pub fn is_static(&self) -> bool {
+        self.access_flags & field::ACC_STATIC != 0
+    }
+
+    pub fn is_final(&self) -> bool {
+        self.access_flags & field::ACC_FINAL != 0
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.access_flags & field::ACC_PRIVATE != 0
+    }
+
+}
+
+// A method or constructor. Constructors are named <init> and return void.
+#[derive(Clone, Debug)]
+pub struct MethodSymbol {
+    // The class that declares the method.
+    pub owner: String,
+    pub name: String,
+    pub type_parameters: Vec<TypeVariable>,
+    pub parameters: Vec<Type>,
+    pub return_type: Type,
+    pub throws: Vec<Type>,
+    pub access_flags: u16
+}
+
+impl MethodSymbol {
+
+    pub fn is_static(&self) -> bool {
+        self.access_flags & method::ACC_STATIC != 0
+    }
+
+    pub fn is_abstract(&self) -> bool {
+        self.access_flags & method::ACC_ABSTRACT != 0
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.access_flags & method::ACC_PRIVATE != 0
+    }
+
+    pub fn is_varargs(&self) -> bool {
+        self.access_flags & method::ACC_VARARGS != 0
+    }
+
+    pub fn is_constructor(&self) -> bool {
+        self.name == "<init>"
+    }
+
+    // The method descriptor of the erased signature, e.g. (ILjava/lang/String;)V.
+    pub fn descriptor(&self) -> String {
+        let parameters: String = self.parameters.iter().map(Type::descriptor).collect();
+        format!("({}){}", parameters, self.return_type.descriptor())
+    }
+
+    // The name and parameter types as javac shows them, e.g. append(String...) or, for a
+    // constructor, Point(int,int).
+    pub fn signature(&self) -> String {
+        let name = if self.is_constructor() { types::simple_name(&self.owner) } else { &self.name };
+        let parameters: Vec<String> = self.parameters.iter().enumerate().map(|(index, parameter)| {
+            match *parameter {
+                Type::Array(ref component) if self.is_varargs() && index + 1 == self.parameters.len() => format!("{}...", component),
+                _ => parameter.to_string()
+            }
+        }).collect();
+        format!("{}({})", name, parameters.join(","))
+    }
+
+    pub fn substitute(&self, substitution: &[(TypeVariable, Type)]) -> MethodSymbol {
+        MethodSymbol {
+            parameters: self.parameters.iter().map(|parameter| parameter.substitute(substitution)).collect(),
+            return_type: self.return_type.substitute(substitution),
+            throws: self.throws.iter().map(|thrown| thrown.substitute(substitution)).collect(),
+            ..self.clone()
+        }
+    }
+
+    // The method as a member of a raw type: every type in its signature is erased.
+    pub fn erase(&self) -> MethodSymbol {
+        MethodSymbol {
+            type_parameters: Vec::new(),
+            parameters: self.parameters.iter().map(Type::erasure).collect(),
+            return_type: self.return_type.erasure(),
+            throws: self.throws.iter().map(Type::erasure).collect(),
+            ..self.clone()
+        }
+    }
+
+    // Whether two methods have the same parameter types after erasure, so that one overrides
+    // or hides the other.
+    pub fn same_erased_parameters(&self, other: &MethodSymbol) -> bool {
+        self.parameters.len() == other.parameters.len()
+            && self.parameters.iter().zip(other.parameters.iter()).all(|(a, b)| a.erasure() == b.erasure())
+    }
+
+}
+
+// A method as a member of a type: as declared, which gives the descriptor it is invoked with,
+// and with the type arguments of the type substituted into its signature.
+#[derive(Clone, Debug)]
+pub struct MethodMember {
+    pub declared: MethodSymbol,
+    pub member: MethodSymbol
+}
+
+#[derive(Clone, Debug)]
+pub struct ClassSymbol {
+    pub name: String,
+    // For a member class, the flags of its declaration, which may include private, protected
+    // and static.
+    pub access_flags: u16,
+    pub type_parameters: Vec<TypeVariable>,
+    // None only for java/lang/Object.
+    pub super_class: Option<Type>,
+    pub interfaces: Vec<Type>,
+    pub fields: Vec<FieldSymbol>,
+    pub methods: Vec<MethodSymbol>,
+    // The enclosing class of a member, local or anonymous class.
+    pub outer_class: Option<String>,
+    // The binary names of the member classes.
+    pub member_classes: Vec<String>
+}
+
+impl ClassSymbol {
+
+    pub fn new(name: &str, access_flags: u16) -> ClassSymbol {
+        ClassSymbol {
+            name: String::from(name),
+            access_flags,
+            type_parameters: Vec::new(),
+            super_class: None,
+            interfaces: Vec::new(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+            outer_class: None,
+            member_classes: Vec::new()
+        }
+    }
+
+    pub fn is_interface(&self) -> bool {
+        self.access_flags & class_flags::ACC_INTERFACE != 0
+    }
+
+    pub fn is_abstract(&self) -> bool {
+        self.access_flags & class_flags::ACC_ABSTRACT != 0
+    }
+
+    pub fn is_final(&self) -> bool {
+        self.access_flags & class_flags::ACC_FINAL != 0
+    }
+
+    pub fn is_enum(&self) -> bool {
+        self.access_flags & class_flags::ACC_ENUM != 0
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.access_flags & class_flags::ACC_STATIC != 0
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.access_flags & class_flags::ACC_PRIVATE != 0
+    }
+
+    pub fn is_public(&self) -> bool {
+        self.access_flags & class_flags::ACC_PUBLIC != 0
+    }
+
+    // Whether instances have an enclosing instance of the outer class (JLS §8.1.3).
+    pub fn is_inner(&self) -> bool {
+        self.outer_class.is_some() && !self.is_static() && !self.is_interface()
+    }
+
+    // The type of this inside the class, parameterized by its own type parameters.
+    pub fn this_type(&self) -> Type {
+        let arguments = self.type_parameters.iter().cloned().map(Type::Variable).collect();
+        Type::generic(&self.name, arguments)
+    }
+
+    pub fn kind_name(&self) -> &'static str {
+        if self.access_flags & class_flags::ACC_ANNOTATION != 0 {
+            "@interface"
+        } else if self.is_interface() {
+            "interface"
+        } else if self.is_enum() {
+            "enum"
+        } else {
+            "class"
+        }
+    }
+
+}
+
+// All classes known to the compiler, by binary name. Classes not declared in a source file are
+// read from the class path the first time they are looked up.
+pub struct Symbols {
+    class_path: RefCell<ClassPath>,
+    // None caches that a class could not be found.
+    classes: RefCell<HashMap<String, Option<Rc<ClassSymbol>>>>,
+    // The packages of the classes declared in source files.
+    packages: RefCell<HashSet<String>>
+}
+
+impl Symbols {
+
+    pub fn new(class_path: ClassPath) -> Symbols {
+        Symbols { class_path: RefCell::new(class_path), classes: RefCell::new(HashMap::new()), packages: RefCell::new(HashSet::new()) }
+    }
+
+    // Adds or replaces a class declared in a source file.
+    pub fn define(&self, symbol: ClassSymbol) -> Rc<ClassSymbol> {
+        self.packages.borrow_mut().insert(String::from(types::package_of(&symbol.name)));
+        let symbol = Rc::new(symbol);
+        self.classes.borrow_mut().insert(symbol.name.clone(), Some(symbol.clone()));
+        symbol
+    }
+
+    pub fn class(&self, name: &str) -> Option<Rc<ClassSymbol>> {
+        if let Some(found) = self.classes.borrow().get(name) {
+            return found.clone();
+        }
+
+        let loaded = self.load(name).map(Rc::new);
+        self.classes.borrow_mut().insert(String::from(name), loaded.clone());
+        loaded
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        self.class(name).is_some()
+    }
+
+    pub fn package_exists(&self, package: &str) -> bool {
+        self.packages.borrow().contains(package) || self.class_path.borrow_mut().has_package(package)
+    }
+
+    // Class files that cannot be read or parsed are treated as missing, like javac treats a bad
+    // class file it does not need.
+    fn load(&self, name: &str) -> Option<ClassSymbol> {
+        let bytes = self.class_path.borrow_mut().read_class(name).ok()??;
+        let class_file = reader::read_class_file(&bytes.bytes).ok()?;
+        from_class_file(&class_file).ok()
+    }
+
+    // The substitution of the type parameters of a class by the type arguments of a
+    // parameterization of it, or None for a raw type. Wildcards are replaced by their bounds
+    // when the substitution is used for the types of members, and kept when it is used for
+    // supertypes.
+    pub fn substitution(&self, symbol: &ClassSymbol, class_type: &ClassType, keep_wildcards: bool) -> Option<Vec<(TypeVariable, Type)>> {
+        if symbol.type_parameters.is_empty() {
+            return Some(Vec::new());
+        }
+        if class_type.arguments.len() != symbol.type_parameters.len() {
+            return None;
+        }
+
+        let substitution = symbol.type_parameters.iter().zip(class_type.arguments.iter()).map(|(parameter, argument)| {
+            let argument = match *argument {
+                Type::Wildcard(_) if keep_wildcards => argument.clone(),
+                Type::Wildcard(Wildcard::Extends(ref bound)) | Type::Wildcard(Wildcard::Super(ref bound)) => (**bound).clone(),
+                Type::Wildcard(Wildcard::Unbounded) => parameter.upper_bounds()[0].erasure(),
+                _ => argument.clone()
+            };
+            (parameter.clone(), argument)
+        }).collect();
+        Some(substitution)
+    }
+
+    // The direct supertypes of a type (JLS §4.10), with type arguments substituted. Interfaces
+    // have Object as a supertype.
+    pub fn supertypes(&self, t: &Type) -> Vec<Type> {
+        match *t {
+            Type::Class(ref class_type) => {
+                let symbol = match self.class(&class_type.name) {
+                    Some(symbol) => symbol,
+                    None => return Vec::new()
+                };
+
+                let mut supertypes: Vec<Type> = symbol.super_class.iter().chain(symbol.interfaces.iter()).cloned().collect();
+                if symbol.is_interface() && symbol.interfaces.is_empty() {
+                    supertypes.push(Type::object());
+                }
+
+                match self.substitution(&symbol, class_type, true) {
+                    Some(substitution) => supertypes.iter().map(|supertype| supertype.substitute(&substitution)).collect(),
+                    None => supertypes.iter().map(Type::erasure).collect()
+                }
+            },
+            Type::Variable(ref variable) => variable.upper_bounds(),
+            Type::Array(_) => vec![Type::object(), Type::class("java/lang/Cloneable"), Type::class("java/io/Serializable")],
+            _ => Vec::new()
+        }
+    }
+
+    // The supertype of a type that is a parameterization of the given class, if there is one.
+    // A class whose supertypes cannot be loaded is assumed to have any supertype, and the result
+    // is then Error.
+    pub fn as_super(&self, t: &Type, class: &str) -> Option<Type> {
+        if let Type::Class(ref class_type) = *t {
+            if class_type.name == class {
+                return Some(t.clone());
+            }
+            if class_type.name != OBJECT && !self.exists(&class_type.name) {
+                return Some(Type::Error);
+            }
+        }
+
+        let mut found = None;
+        for supertype in self.supertypes(t) {
+            match self.as_super(&supertype, class) {
+                Some(Type::Error) => found = Some(Type::Error),
+                Some(supertype) => return Some(supertype),
+                None => {}
+            }
+        }
+        found
+    }
+
+    pub fn is_subclass(&self, name: &str, of: &str) -> bool {
+        if name == of || of == OBJECT {
+            return true;
+        }
+
+        match self.class(name) {
+            Some(symbol) => symbol.super_class.iter()
+                .chain(symbol.interfaces.iter())
+                .filter_map(Type::class_name)
+                .any(|supertype| self.is_subclass(supertype, of)),
+            None => false
+        }
+    }
+
+    // Subtyping (JLS §4.10). Raw types are treated as subtypes of every parameterization of
+    // their supertypes, which makes unchecked conversions succeed.
+    pub fn is_subtype(&self, s: &Type, t: &Type) -> bool {
+        match (s, t) {
+            (&Type::Error, _) | (_, &Type::Error) => true,
+            (&Type::Primitive(a), &Type::Primitive(b)) => a == b,
+            (&Type::Null, _) => t.is_reference(),
+            (Type::Array(a), Type::Array(b)) => {
+                if a.is_primitive() || b.is_primitive() { a == b } else { self.is_subtype(a, b) }
+            },
+            (Type::Variable(a), Type::Variable(b)) if a == b => true,
+            (Type::Variable(variable), _) => variable.upper_bounds().iter().any(|bound| self.is_subtype(bound, t)),
+            (&Type::Class(_), &Type::Class(ref target)) | (&Type::Array(_), &Type::Class(ref target)) => {
+                match self.as_super(s, &target.name) {
+                    Some(Type::Class(ref found)) => {
+                        found.arguments.is_empty() || target.arguments.is_empty()
+                            || (found.arguments.len() == target.arguments.len()
+                                && target.arguments.iter().zip(found.arguments.iter()).all(|(t, s)| self.contains(t, s)))
+                    },
+                    Some(_) => true,
+                    None => false
+                }
+            },
+            _ => false
+        }
+    }
+
+    // Type argument containment (JLS §4.5.1): whether the argument t contains s.
+    pub fn contains(&self, t: &Type, s: &Type) -> bool {
+        match *t {
+            Type::Wildcard(Wildcard::Unbounded) => true,
+            Type::Wildcard(Wildcard::Extends(ref bound)) => match *s {
+                Type::Wildcard(Wildcard::Extends(ref upper)) => self.is_subtype(upper, bound),
+                Type::Wildcard(_) => bound.is_class(OBJECT),
+                _ => self.is_subtype(s, bound)
+            },
+            Type::Wildcard(Wildcard::Super(ref bound)) => match *s {
+                Type::Wildcard(Wildcard::Super(ref lower)) => self.is_subtype(bound, lower),
+                Type::Wildcard(_) => false,
+                _ => self.is_subtype(bound, s)
+            },
+            _ => same_type(t, s)
+        }
+    }
+
+    // The type of a member declared in a class as seen from a type that has the class as a
+    // supertype: the class's type arguments are substituted, and the member of a raw type is
+    // erased.
+    fn member_substitution(&self, site: &Type, owner: &str) -> Option<Vec<(TypeVariable, Type)>> {
+        let symbol = self.class(owner)?;
+        match self.as_super(site, owner) {
+            Some(Type::Class(ref class_type)) => self.substitution(&symbol, class_type, false),
+            _ => None
+        }
+    }
+
+    // Finds a field that is a member of a type: one declared in the class, or else inherited
+    // from a superinterface or the superclass (JLS §8.3).
+    pub fn find_field(&self, site: &Type, name: &str) -> Option<FieldSymbol> {
+        let field = self.find_declared_field(site, name, &mut Vec::new())?;
+        let mut field = field;
+        match self.member_substitution(site, &field.owner) {
+            Some(substitution) => field.field_type = field.field_type.substitute(&substitution),
+            None => field.field_type = field.field_type.erasure()
+        }
+        Some(field)
+    }
+
+    fn find_declared_field(&self, site: &Type, name: &str, visited: &mut Vec<String>) -> Option<FieldSymbol> {
+        if let Type::Class(ref class_type) = *site {
+            if visited.contains(&class_type.name) {
+                return None;
+            }
+            visited.push(class_type.name.clone());
+
+            let symbol = self.class(&class_type.name)?;
+            if let Some(field) = symbol.fields.iter().find(|field| field.name == name) {
+                return Some(field.clone());
+            }
+        }
+
+        let mut supertypes = self.supertypes(site);
+        // Fields of superinterfaces are found before fields of the superclass.
+        if !supertypes.is_empty() && !matches!(*site, Type::Variable(_)) {
+            let super_class = supertypes.remove(0);
+            supertypes.push(super_class);
+        }
+        supertypes.iter().filter_map(|supertype| self.find_declared_field(supertype, name, visited)).next()
+    }
+
+    // The methods with a name that are members of a type: those the class declares, and those
+    // it inherits and does not override (JLS §8.4.8). Their signatures are seen from the type.
+    pub fn find_methods(&self, site: &Type, name: &str) -> Vec<MethodMember> {
+        let mut methods = Vec::new();
+        self.collect_methods(site, name, &mut methods, &mut Vec::new());
+        methods
+    }
+
+    fn collect_methods(&self, site: &Type, name: &str, methods: &mut Vec<MethodMember>, visited: &mut Vec<String>) {
+        if let Type::Class(ref class_type) = *site {
+            if visited.contains(&class_type.name) {
+                return;
+            }
+            visited.push(class_type.name.clone());
+
+            let symbol = match self.class(&class_type.name) {
+                Some(symbol) => symbol,
+                None => return
+            };
+            let substitution = self.substitution(&symbol, class_type, false);
+            for declared in symbol.methods.iter().filter(|declared| declared.name == name) {
+                // Static methods cannot mention the type parameters of the class, and are not
+                // erased in a raw type (JLS §4.8).
+                let member = match substitution {
+                    _ if declared.is_static() => declared.clone(),
+                    Some(ref substitution) => declared.substitute(substitution),
+                    None => declared.erase()
+                };
+                // A method found first overrides those with the same signature found later.
+                let overridden = methods.iter().any(|found: &MethodMember| found.member.same_erased_parameters(&member));
+                if !overridden {
+                    methods.push(MethodMember { declared: declared.clone(), member });
+                }
+            }
+
+            // Constructors are not inherited.
+            if name == "<init>" {
+                return;
+            }
+        }
+
+        for supertype in self.supertypes(site) {
+            self.collect_methods(&supertype, name, methods, visited);
+        }
+    }
+
+    // Finds a member class by simple name, declared in the class or inherited (JLS §8.5).
+    pub fn find_member_class(&self, class: &str, simple_name: &str) -> Option<String> {
+        self.find_member_class_in(class, simple_name, &mut Vec::new())
+    }
+
+    fn find_member_class_in(&self, class: &str, simple_name: &str, visited: &mut Vec<String>) -> Option<String> {
+        if visited.iter().any(|name| name == class) {
+            return None;
+        }
+        visited.push(String::from(class));
+
+        let symbol = self.class(class)?;
+        let declared = symbol.member_classes.iter().find(|member| member_name(member) == simple_name);
+        if let Some(member) = declared {
+            return Some(member.clone());
+        }
+
+        symbol.super_class.iter()
+            .chain(symbol.interfaces.iter())
+            .filter_map(Type::class_name)
+            .filter_map(|supertype| self.find_member_class_in(supertype, simple_name, visited))
+            .next()
+    }
+
+}
+
+// The simple name of a member class: what follows the last $ of its binary name.
+fn member_name(binary_name: &str) -> &str {
+    binary_name.rsplit('$').next().unwrap_or(binary_name)
+}
+
+// Type equality, where Error is the same as any type and a raw type the same as any
+// parameterization of its class.
+pub fn same_type(a: &Type, b: &Type) -> bool {
+    match (a, b) {
+        (&Type::Error, _) | (_, &Type::Error) => true,
+        (Type::Class(a), Type::Class(b)) => {
+            a.name == b.name && (a.arguments.is_empty() || b.arguments.is_empty()
+                || (a.arguments.len() == b.arguments.len() && a.arguments.iter().zip(b.arguments.iter()).all(|(a, b)| same_type(a, b))))
+        },
+        (Type::Array(a), Type::Array(b)) => same_type(a, b),
+        (&Type::Wildcard(Wildcard::Extends(ref a)), &Type::Wildcard(Wildcard::Extends(ref b)))
+            | (&Type::Wildcard(Wildcard::Super(ref a)), &Type::Wildcard(Wildcard::Super(ref b))) => same_type(a, b),
+        _ => a == b
+    }
+}
+
+// Builds the symbol of a class from its class file. Generic signatures are read from Signature
+// attributes, and synthetic members are left out, as they cannot be used from source code.
+pub fn from_class_file(class_file: &ClassFile) -> Result<ClassSymbol, String> {
+    let pool = &class_file.constant_pool;
+    let name = pool.get_class_name(class_file.this_class)?;
+    let mut symbol = ClassSymbol::new(&name, class_file.access_flags);
+
+    for attribute in class_file.attributes.iter() {
+        match *attribute {
+            Attribute::InnerClasses { ref classes } => {
+                for entry in classes.iter().filter(|entry| entry.inner_class_info_index != 0) {
+                    let inner = pool.get_class_name(entry.inner_class_info_index)?;
+                    let outer = if entry.outer_class_info_index == 0 { None } else { Some(pool.get_class_name(entry.outer_class_info_index)?) };
+                    if inner == name {
+                        symbol.access_flags = entry.inner_class_access_flags;
+                        symbol.outer_class = outer;
+                    } else if outer.as_deref() == Some(name.as_str()) && entry.inner_name_index != 0 {
+                        symbol.member_classes.push(inner);
+                    }
+                }
+            },
+            Attribute::Signature { index } => {
+                let mut parser = SignatureParser::new(&pool.get_utf8(index)?, Vec::new());
+                symbol.type_parameters = parser.type_parameters(&name)?;
+                symbol.super_class = Some(parser.reference_type()?);
+                while parser.peek().is_some() {
+                    symbol.interfaces.push(parser.reference_type()?);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    let has_signature = class_file.attributes.iter().any(|attribute| matches!(*attribute, Attribute::Signature { .. }));
+    if !has_signature {
+        if class_file.super_class != 0 {
+            symbol.super_class = Some(Type::class(&pool.get_class_name(class_file.super_class)?));
+        }
+        for &interface in class_file.interfaces.iter() {
+            symbol.interfaces.push(Type::class(&pool.get_class_name(interface)?));
+        }
+    }
+
+    for field in class_file.fields.iter().filter(|field| field.access_flags & field::ACC_SYNTHETIC == 0) {
+        let field_name = pool.get_utf8(field.name_index)?;
+        let descriptor = pool.get_utf8(field.descriptor_index)?;
+        let mut field_type = SignatureParser::new(&descriptor, Vec::new()).java_type()?;
+        let mut constant = None;
+
+        for attribute in field.attributes.iter() {
+            match *attribute {
+                Attribute::Signature { index } => {
+                    field_type = SignatureParser::new(&pool.get_utf8(index)?, symbol.type_parameters.clone()).java_type()?;
+                },
+                Attribute::ConstantValue { index } if field.access_flags & field::ACC_FINAL != 0 => {
+                    constant = Some(match descriptor.as_str() {
+                        "Z" => Constant::Boolean(pool.get_integer(index)? != 0),
+                        "B" | "S" | "C" | "I" => Constant::Int(pool.get_integer(index)?),
+                        "J" => Constant::Long(pool.get_long(index)?),
+                        "F" => Constant::Float(pool.get_float(index)?),
+                        "D" => Constant::Double(pool.get_double(index)?),
+                        _ => Constant::String(pool.get_string(index)?)
+                    });
+                },
+                _ => {}
+            }
+        }
+
+        symbol.fields.push(FieldSymbol { owner: name.clone(), name: field_name, field_type, access_flags: field.access_flags, constant });
+    }
+
+    for method in class_file.methods.iter() {
+        if method.access_flags & (method::ACC_SYNTHETIC | method::ACC_BRIDGE) != 0 {
+            continue;
+        }
+        let method_name = pool.get_utf8(method.name_index)?;
+        if method_name == "<clinit>" {
+            continue;
+        }
+
+        let descriptor = pool.get_utf8(method.descriptor_index)?;
+        let owner = format!("{}.{}{}", name, method_name, descriptor);
+        let mut signature = None;
+        let mut throws = Vec::new();
+        for attribute in method.attributes.iter() {
+            match *attribute {
+                Attribute::Signature { index } => signature = Some(pool.get_utf8(index)?),
+                Attribute::Exceptions { ref exception_index } => {
+                    for &index in exception_index.iter() {
+                        throws.push(Type::class(&pool.get_class_name(index)?));
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        let mut parser = SignatureParser::new(signature.as_ref().unwrap_or(&descriptor), symbol.type_parameters.clone());
+        let type_parameters = parser.type_parameters(&owner)?;
+        let (parameters, return_type, signature_throws) = parser.method_type()?;
+        if !signature_throws.is_empty() {
+            throws = signature_throws;
+        }
+
+        symbol.methods.push(MethodSymbol {
+            owner: name.clone(),
+            name: method_name,
+            type_parameters,
+            parameters,
+            return_type,
+            throws,
+            access_flags: method.access_flags
+        });
+    }
+
+    Ok(symbol)
+}
+
+// A parser for descriptors and generic signatures (JVMS §4.7.9.1). Type variables are looked
+// up among those in scope; a variable of an enclosing class is erased to Object.
+struct SignatureParser {
+    chars: Vec<char>,
+    index: usize,
+    variables: Vec<TypeVariable>
+}
+
+impl SignatureParser {
+
+    fn new(signature: &str, variables: Vec<TypeVariable>) -> SignatureParser {
+        SignatureParser { chars: signature.chars().collect(), index: 0, variables }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).cloned()
+    }
+
+    fn next(&mut self) -> Result<char, String> {
+        let c = self.peek().ok_or_else(|| String::from("Truncated signature"))?;
+        self.index += 1;
+        Ok(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        let c = self.next()?;
+        if c == expected { Ok(()) } else { Err(format!("Expected '{}' in signature, found '{}'", expected, c)) }
+    }
+
+    fn identifier(&mut self, terminators: &[char]) -> String {
+        let start = self.index;
+        while self.peek().is_some_and(|c| !terminators.contains(&c)) {
+            self.index += 1;
+        }
+        self.chars[start..self.index].iter().collect()
+    }
+
+    // The type parameters are created before their bounds are parsed, as a bound may refer to
+    // any of them.
+    fn type_parameters(&mut self, owner: &str) -> Result<Vec<TypeVariable>, String> {
+        if self.peek() != Some('<') {
+            return Ok(Vec::new());
+        }
+
+        let start = self.index;
+        self.index += 1;
+        let mut parameters = Vec::new();
+        while self.peek() != Some('>') {
+            parameters.push(TypeVariable::new(&self.identifier(&[':']), owner));
+            while self.peek() == Some(':') {
+                self.index += 1;
+                if self.peek() != Some(':') {
+                    self.reference_type()?;
+                }
+            }
+        }
+
+        self.index = start + 1;
+        self.variables.extend(parameters.iter().cloned());
+        for parameter in parameters.iter() {
+            self.identifier(&[':']);
+            let mut bounds = Vec::new();
+            while self.peek() == Some(':') {
+                self.index += 1;
+                if self.peek() != Some(':') {
+                    bounds.push(self.reference_type()?);
+                }
+            }
+            *parameter.bounds.borrow_mut() = bounds;
+        }
+        self.expect('>')?;
+
+        Ok(parameters)
+    }
+
+    fn method_type(&mut self) -> Result<(Vec<Type>, Type, Vec<Type>), String> {
+        self.expect('(')?;
+        let mut parameters = Vec::new();
+        while self.peek() != Some(')') {
+            parameters.push(self.java_type()?);
+        }
+        self.expect(')')?;
+
+        let return_type = if self.peek() == Some('V') {
+            self.index += 1;
+            Type::Void
+        } else {
+            self.java_type()?
+        };
+
+        let mut throws = Vec::new();
+        while self.peek() == Some('^') {
+            self.index += 1;
+            throws.push(self.reference_type()?);
+        }
+
+        Ok((parameters, return_type, throws))
+    }
+
+    fn java_type(&mut self) -> Result<Type, String> {
+        match self.peek().and_then(PrimitiveType::from_descriptor) {
+            Some(primitive) => {
+                self.index += 1;
+                Ok(Type::Primitive(primitive))
+            },
+            None => self.reference_type()
+        }
+    }
+
+    fn reference_type(&mut self) -> Result<Type, String> {
+        match self.next()? {
+            'L' => {
+                // Only the type arguments of the innermost class are kept.
+                let mut name = String::new();
+                let arguments = loop {
+                    name.push_str(&self.identifier(&['<', '.', ';']));
+                    let arguments = if self.peek() == Some('<') { self.type_arguments()? } else { Vec::new() };
+                    match self.next()? {
+                        '.' => name.push('$'),
+                        ';' => break arguments,
+                        c => return Err(format!("Unexpected '{}' in signature", c))
+                    }
+                };
+                Ok(Type::generic(&name, arguments))
+            },
+            'T' => {
+                let name = self.identifier(&[';']);
+                self.expect(';')?;
+                let variable = self.variables.iter().rev().find(|variable| variable.name == name);
+                Ok(variable.map_or_else(Type::object, |variable| Type::Variable(variable.clone())))
+            },
+            '[' => Ok(Type::array_of(self.java_type()?)),
+            c => Err(format!("Unexpected '{}' in signature", c))
+        }
+    }
+
+    fn type_arguments(&mut self) -> Result<Vec<Type>, String> {
+        self.expect('<')?;
+        let mut arguments = Vec::new();
+        while self.peek() != Some('>') {
+            let argument = match self.peek() {
+                Some('*') => {
+                    self.index += 1;
+                    Type::Wildcard(Wildcard::Unbounded)
+                },
+                Some('+') => {
+                    self.index += 1;
+                    Type::Wildcard(Wildcard::Extends(Box::new(self.reference_type()?)))
+                },
+                Some('-') => {
+                    self.index += 1;
+                    Type::Wildcard(Wildcard::Super(Box::new(self.reference_type()?)))
+                },
+                _ => self.reference_type()?
+            };
+            arguments.push(argument);
+        }
+        self.expect('>')?;
+        Ok(arguments)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use class::path::ClassDirectory;
+
+    fn fixtures() -> Symbols {
+        let mut class_path = ClassPath::new();
+        class_path.add("fixtures", ClassDirectory::new(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures")));
+        Symbols::new(class_path)
+    }
+
+    #[test]
+    fn parses_generic_signatures() {
+        let mut parser = SignatureParser::new("<K:Ljava/lang/Object;V::Ljava/lang/Comparable<-TV;>;>Ljava/util/AbstractMap<TK;TV;>;", Vec::new());
+        let parameters = parser.type_parameters("p/Map").unwrap();
+        assert_eq!(parameters.len(), 2);
+        assert_eq!(parameters[1].bounds.borrow()[0].to_string(), "Comparable<? super V>");
+        assert_eq!(parser.reference_type().unwrap().to_string(), "AbstractMap<K,V>");
+
+        let mut parser = SignatureParser::new("<T:Ljava/lang/Object;>(Ljava/util/Map<TT;[I>.Entry<TT;>;[TT;)TT;^Ljava/io/IOException;", Vec::new());
+        let parameters = parser.type_parameters("m").unwrap();
+        let (arguments, return_type, throws) = parser.method_type().unwrap();
+        assert_eq!(arguments, vec![
+            Type::generic("java/util/Map$Entry", vec![Type::Variable(parameters[0].clone())]),
+            Type::array_of(Type::Variable(parameters[0].clone()))
+        ]);
+        assert_eq!(return_type, Type::Variable(parameters[0].clone()));
+        assert_eq!(throws, vec![Type::class("java/io/IOException")]);
+    }
+
+    #[test]
+    fn loads_classes_from_the_class_path() {
+        let symbols = fixtures();
+        let circle = symbols.class("Circle").unwrap();
+        assert_eq!(circle.super_class, Some(Type::class("Shape")));
+        assert!(circle.fields.iter().any(|field| field.name == "radius" && field.is_private()));
+        assert!(symbols.class("Missing").is_none());
+
+        let shape = symbols.class("Shape").unwrap();
+        assert!(shape.is_abstract());
+        let describe = symbols.find_methods(&Type::class("Circle"), "describe");
+        assert_eq!(describe.len(), 1);
+        assert_eq!(describe[0].member.owner, "Shape");
+        assert_eq!(describe[0].declared.descriptor(), "()Ljava/lang/String;");
+    }
+
+    #[test]
+    fn relates_types_through_supertypes() {
+        let symbols = fixtures();
+        assert!(symbols.is_subtype(&Type::class("Circle"), &Type::class("Named")));
+        assert!(symbols.is_subclass("Square", "Shape"));
+        assert!(!symbols.is_subtype(&Type::class("Circle"), &Type::class("Square")));
+        assert!(symbols.is_subtype(&Type::array_of(Type::class("Circle")), &Type::array_of(Type::class("Shape"))));
+        assert!(symbols.is_subtype(&Type::array_of(Type::int()), &Type::object()));
+        assert!(!symbols.is_subtype(&Type::array_of(Type::int()), &Type::array_of(Type::class("java/lang/Integer"))));
+        assert!(symbols.is_subtype(&Type::Null, &Type::class("Circle")));
+    }
+
+}
@@ -0,0 +1,445 @@
+// Types as the compiler sees them once names are resolved. Class names are binary names in
+// internal form, e.g. java/util/Map$Entry, like in class files.
+
+use compiler::ast::PrimitiveType;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+pub const OBJECT: &str = "java/lang/Object";
+pub const STRING: &str = "java/lang/String";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Type {
+    Primitive(PrimitiveType),
+    Void,
+    // The type of the null literal.
+    Null,
+    Class(ClassType),
+    Array(Box<Type>),
+    Variable(TypeVariable),
+    // Only appears as a type argument.
+    Wildcard(Wildcard),
+    // The type of an erroneous expression or an unknown inferred type. It is compatible with
+    // everything, so that a mistake is reported once rather than wherever its result is used.
+    Error
+}
+
+// A class or interface type. The arguments are empty for a raw or non-generic type.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClassType {
+    pub name: String,
+    pub arguments: Vec<Type>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Wildcard {
+    Unbounded,
+    Extends(Box<Type>),
+    Super(Box<Type>)
+}
+
+// A type parameter of a class or method. The owner tells apart type variables with the same
+// name. The bounds are shared and filled in after the variable is created, because they may
+// refer to the variable itself, as in T extends Comparable<T>.
+#[derive(Clone)]
+pub struct TypeVariable {
+    pub name: String,
+    pub owner: String,
+    pub bounds: Rc<RefCell<Vec<Type>>>
+}
+
+impl TypeVariable {
+
+    pub fn new(name: &str, owner: &str) -> TypeVariable {
+        TypeVariable { name: String::from(name), owner: String::from(owner), bounds: Rc::new(RefCell::new(Vec::new())) }
+    }
+
+    // The bounds, or Object for an unbounded variable.
+    pub fn upper_bounds(&self) -> Vec<Type> {
+        let bounds = self.bounds.borrow();
+        if bounds.is_empty() { vec![Type::object()] } else { bounds.clone() }
+    }
+
+}
+
+impl PartialEq for TypeVariable {
+    fn eq(&self, other: &TypeVariable) -> bool {
+        self.name == other.name && self.owner == other.owner
+    }
+}
+
+impl fmt::Debug for TypeVariable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl Type {
+
+    pub fn class(name: &str) -> Type {
+        Type::Class(ClassType { name: String::from(name), arguments: Vec::new() })
+    }
+
+    pub fn generic(name: &str, arguments: Vec<Type>) -> Type {
+        Type::Class(ClassType { name: String::from(name), arguments })
+    }
+
+    pub fn object() -> Type {
+        Type::class(OBJECT)
+    }
+
+    pub fn string() -> Type {
+        Type::class(STRING)
+    }
+
+    pub fn array_of(component: Type) -> Type {
+        Type::Array(Box::new(component))
+    }
+
+    pub fn int() -> Type {
+        Type::Primitive(PrimitiveType::Int)
+    }
+
+    pub fn boolean() -> Type {
+        Type::Primitive(PrimitiveType::Boolean)
+    }
+
+    pub fn is_primitive(&self) -> bool {
+        matches!(*self, Type::Primitive(_))
+    }
+
+    pub fn is_reference(&self) -> bool {
+        matches!(*self, Type::Null | Type::Class(_) | Type::Array(_) | Type::Variable(_))
+    }
+
+    pub fn is_error(&self) -> bool {
+        *self == Type::Error
+    }
+
+    pub fn is_void(&self) -> bool {
+        *self == Type::Void
+    }
+
+    pub fn is_class(&self, name: &str) -> bool {
+        match *self {
+            Type::Class(ref class_type) => class_type.name == name,
+            _ => false
+        }
+    }
+
+    pub fn is_string(&self) -> bool {
+        self.is_class(STRING)
+    }
+
+    pub fn primitive(&self) -> Option<PrimitiveType> {
+        match *self {
+            Type::Primitive(primitive) => Some(primitive),
+            _ => None
+        }
+    }
+
+    pub fn class_name(&self) -> Option<&str> {
+        match *self {
+            Type::Class(ref class_type) => Some(&class_type.name),
+            _ => None
+        }
+    }
+
+    pub fn component(&self) -> Option<&Type> {
+        match *self {
+            Type::Array(ref component) => Some(component),
+            _ => None
+        }
+    }
+
+    // The primitive type of a primitive or boxed type.
+    pub fn unboxed(&self) -> Option<PrimitiveType> {
+        match *self {
+            Type::Primitive(primitive) => Some(primitive),
+            Type::Class(ref class_type) => PrimitiveType::unbox(&class_type.name),
+            _ => None
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        self.unboxed().is_some_and(PrimitiveType::is_numeric)
+    }
+
+    pub fn is_integral(&self) -> bool {
+        self.unboxed().is_some_and(PrimitiveType::is_integral)
+    }
+
+    pub fn is_boolean(&self) -> bool {
+        self.unboxed() == Some(PrimitiveType::Boolean)
+    }
+
+    // Whether the type mentions a type variable for which the predicate holds.
+    pub fn mentions<F: Fn(&TypeVariable) -> bool + Copy>(&self, predicate: F) -> bool {
+        match *self {
+            Type::Variable(ref variable) => predicate(variable),
+            Type::Class(ref class_type) => class_type.arguments.iter().any(|argument| argument.mentions(predicate)),
+            Type::Array(ref component) => component.mentions(predicate),
+            Type::Wildcard(Wildcard::Extends(ref bound)) | Type::Wildcard(Wildcard::Super(ref bound)) => bound.mentions(predicate),
+            _ => false
+        }
+    }
+
+    // Replaces type variables by the types they map to.
+    pub fn substitute(&self, substitution: &[(TypeVariable, Type)]) -> Type {
+        if substitution.is_empty() {
+            return self.clone();
+        }
+
+        match *self {
+            Type::Variable(ref variable) => substitution.iter()
+                .find(|&(from, _)| from == variable)
+                .map_or_else(|| self.clone(), |(_, to)| to.clone()),
+            Type::Class(ref class_type) => Type::Class(ClassType {
+                name: class_type.name.clone(),
+                arguments: class_type.arguments.iter().map(|argument| argument.substitute(substitution)).collect()
+            }),
+            Type::Array(ref component) => Type::array_of(component.substitute(substitution)),
+            Type::Wildcard(Wildcard::Extends(ref bound)) => Type::Wildcard(Wildcard::Extends(Box::new(bound.substitute(substitution)))),
+            Type::Wildcard(Wildcard::Super(ref bound)) => Type::Wildcard(Wildcard::Super(Box::new(bound.substitute(substitution)))),
+            _ => self.clone()
+        }
+    }
+
+    // The type without type arguments, as it exists at run time.
+    pub fn erasure(&self) -> Type {
+        match *self {
+            Type::Class(ref class_type) => Type::class(&class_type.name),
+            Type::Array(ref component) => Type::array_of(component.erasure()),
+            Type::Variable(ref variable) => variable.upper_bounds()[0].erasure(),
+            Type::Wildcard(Wildcard::Extends(ref bound)) => bound.erasure(),
+            Type::Wildcard(_) | Type::Null | Type::Error => Type::object(),
+            _ => self.clone()
+        }
+    }
+
+    // The field descriptor of the erasure, e.g. I, [J or Ljava/lang/String;.
+    pub fn descriptor(&self) -> String {
+        match self.erasure() {
+            Type::Primitive(primitive) => String::from(primitive.descriptor()),
+            Type::Void => String::from("V"),
+            Type::Class(class_type) => format!("L{};", class_type.name),
+            Type::Array(component) => format!("[{}", component.descriptor()),
+            _ => format!("L{};", OBJECT)
+        }
+    }
+
+    // The generic signature of the type (JVMS §4.7.9.1), e.g. Ljava/util/List<TT;>;. It is the
+    // descriptor for a type without type arguments or type variables.
+    pub fn signature(&self) -> String {
+        match *self {
+            Type::Class(ref class_type) if !class_type.arguments.is_empty() => {
+                let arguments: String = class_type.arguments.iter().map(Type::signature).collect();
+                format!("L{}<{}>;", class_type.name, arguments)
+            },
+            Type::Array(ref component) => format!("[{}", component.signature()),
+            Type::Variable(ref variable) => format!("T{};", variable.name),
+            Type::Wildcard(Wildcard::Unbounded) => String::from("*"),
+            Type::Wildcard(Wildcard::Extends(ref bound)) => format!("+{}", bound.signature()),
+            Type::Wildcard(Wildcard::Super(ref bound)) => format!("-{}", bound.signature()),
+            _ => self.descriptor()
+        }
+    }
+
+    // The number of local variable slots and operand stack entries a value takes.
+    pub fn width(&self) -> usize {
+        match *self {
+            Type::Primitive(PrimitiveType::Long) | Type::Primitive(PrimitiveType::Double) => 2,
+            Type::Void => 0,
+            _ => 1
+        }
+    }
+
+}
+
+impl fmt::Display for Type {
+    // Types are shown like javac shows them in diagnostics: classes by their simple names and
+    // type arguments without spaces, e.g. Map<String,List<Integer>>.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Type::Primitive(primitive) => write!(f, "{}", primitive.name()),
+            Type::Void => write!(f, "void"),
+            Type::Null => write!(f, "<null>"),
+            Type::Class(ref class_type) => {
+                write!(f, "{}", simple_name(&class_type.name))?;
+                if !class_type.arguments.is_empty() {
+                    let arguments: Vec<String> = class_type.arguments.iter().map(|argument| argument.to_string()).collect();
+                    write!(f, "<{}>", arguments.join(","))?;
+                }
+                Ok(())
+            },
+            Type::Array(ref component) => write!(f, "{}[]", component),
+            Type::Variable(ref variable) => write!(f, "{}", variable.name),
+            Type::Wildcard(Wildcard::Unbounded) => write!(f, "?"),
+            Type::Wildcard(Wildcard::Extends(ref bound)) => write!(f, "? extends {}", bound),
+            Type::Wildcard(Wildcard::Super(ref bound)) => write!(f, "? super {}", bound),
+            Type::Error => write!(f, "<any>")
+        }
+    }
+}
+
+// The name of a class without its package and enclosing classes, e.g. Entry for
+// java/util/Map$Entry. The number javac puts before the name of a local class is dropped.
+pub fn simple_name(binary_name: &str) -> &str {
+    let name = binary_name.rsplit('/').next().unwrap_or(binary_name);
+    let name = name.rsplit('$').next().unwrap_or(name);
+    let trimmed = name.trim_start_matches(|c: char| c.is_ascii_digit());
+    if trimmed.is_empty() { name } else { trimmed }
+}
+
+// The package of a class in internal form, empty for the unnamed package.
+pub fn package_of(binary_name: &str) -> &str {
+    binary_name.rfind('/').map_or("", |index| &binary_name[..index])
+}
+
+impl PrimitiveType {
+
+    pub fn descriptor(self) -> &'static str {
+        match self {
+            PrimitiveType::Boolean => "Z",
+            PrimitiveType::Byte => "B",
+            PrimitiveType::Short => "S",
+            PrimitiveType::Char => "C",
+            PrimitiveType::Int => "I",
+            PrimitiveType::Long => "J",
+            PrimitiveType::Float => "F",
+            PrimitiveType::Double => "D"
+        }
+    }
+
+    pub fn from_descriptor(descriptor: char) -> Option<PrimitiveType> {
+        match descriptor {
+            'Z' => Some(PrimitiveType::Boolean),
+            'B' => Some(PrimitiveType::Byte),
+            'S' => Some(PrimitiveType::Short),
+            'C' => Some(PrimitiveType::Char),
+            'I' => Some(PrimitiveType::Int),
+            'J' => Some(PrimitiveType::Long),
+            'F' => Some(PrimitiveType::Float),
+            'D' => Some(PrimitiveType::Double),
+            _ => None
+        }
+    }
+
+    // The wrapper class values of the type are boxed into.
+    pub fn boxed(self) -> &'static str {
+        match self {
+            PrimitiveType::Boolean => "java/lang/Boolean",
+            PrimitiveType::Byte => "java/lang/Byte",
+            PrimitiveType::Short => "java/lang/Short",
+            PrimitiveType::Char => "java/lang/Character",
+            PrimitiveType::Int => "java/lang/Integer",
+            PrimitiveType::Long => "java/lang/Long",
+            PrimitiveType::Float => "java/lang/Float",
+            PrimitiveType::Double => "java/lang/Double"
+        }
+    }
+
+    pub fn unbox(class_name: &str) -> Option<PrimitiveType> {
+        PRIMITIVES.iter().cloned().find(|primitive| primitive.boxed() == class_name)
+    }
+
+    pub fn is_numeric(self) -> bool {
+        self != PrimitiveType::Boolean
+    }
+
+    pub fn is_integral(self) -> bool {
+        matches!(self, PrimitiveType::Byte | PrimitiveType::Short | PrimitiveType::Char | PrimitiveType::Int | PrimitiveType::Long)
+    }
+
+    // Widening primitive conversion (JLS §5.1.2), including the identity conversion.
+    pub fn widens_to(self, target: PrimitiveType) -> bool {
+        use compiler::ast::PrimitiveType::*;
+        self == target || match self {
+            Byte => matches!(target, Short | Int | Long | Float | Double),
+            Short | Char => matches!(target, Int | Long | Float | Double),
+            Int => matches!(target, Long | Float | Double),
+            Long => matches!(target, Float | Double),
+            Float => target == Double,
+            _ => false
+        }
+    }
+
+    // Unary numeric promotion (JLS §5.6).
+    pub fn promoted(self) -> PrimitiveType {
+        match self {
+            PrimitiveType::Byte | PrimitiveType::Short | PrimitiveType::Char => PrimitiveType::Int,
+            _ => self
+        }
+    }
+
+    // Binary numeric promotion (JLS §5.6).
+    pub fn promote(left: PrimitiveType, right: PrimitiveType) -> PrimitiveType {
+        use compiler::ast::PrimitiveType::*;
+        if left == Double || right == Double {
+            Double
+        } else if left == Float || right == Float {
+            Float
+        } else if left == Long || right == Long {
+            Long
+        } else {
+            Int
+        }
+    }
+
+}
+
+pub const PRIMITIVES: [PrimitiveType; 8] = [
+    PrimitiveType::Boolean, PrimitiveType::Byte, PrimitiveType::Short, PrimitiveType::Char,
+    PrimitiveType::Int, PrimitiveType::Long, PrimitiveType::Float, PrimitiveType::Double
+];
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn displays_like_javac() {
+        let entry = Type::generic("java/util/Map$Entry", vec![Type::string(), Type::array_of(Type::int())]);
+        assert_eq!(entry.to_string(), "Entry<String,int[]>");
+
+        let wildcard = Type::generic("java/util/List", vec![Type::Wildcard(Wildcard::Extends(Box::new(Type::class("java/lang/Number"))))]);
+        assert_eq!(wildcard.to_string(), "List<? extends Number>");
+        assert_eq!(Type::Null.to_string(), "<null>");
+        assert_eq!(simple_name("p/Outer$1Local"), "Local");
+    }
+
+    #[test]
+    fn erases_type_variables_to_their_first_bound() {
+        let variable = TypeVariable::new("T", "p/Sorter");
+        let comparable = Type::generic("java/lang/Comparable", vec![Type::Variable(variable.clone())]);
+        variable.bounds.borrow_mut().push(comparable);
+
+        let list = Type::generic("java/util/List", vec![Type::Variable(variable.clone())]);
+        assert_eq!(Type::Variable(variable.clone()).erasure(), Type::class("java/lang/Comparable"));
+        assert_eq!(Type::array_of(Type::Variable(variable)).descriptor(), "[Ljava/lang/Comparable;");
+        assert_eq!(list.descriptor(), "Ljava/util/List;");
+        assert_eq!(list.signature(), "Ljava/util/List<TT;>;");
+        assert_eq!(Type::generic("java/util/Map", vec![Type::Wildcard(Wildcard::Unbounded), Type::Wildcard(Wildcard::Super(Box::new(Type::string())))]).signature(),
+                   "Ljava/util/Map<*-Ljava/lang/String;>;");
+    }
+
+    #[test]
+    fn substitutes_type_arguments() {
+        let variable = TypeVariable::new("E", "java/util/List");
+        let list = Type::generic("java/util/List", vec![Type::Variable(variable.clone())]);
+        let substituted = list.substitute(&[(variable, Type::string())]);
+        assert_eq!(substituted, Type::generic("java/util/List", vec![Type::string()]));
+    }
+
+    #[test]
+    fn widens_and_promotes_primitives() {
+        assert!(PrimitiveType::Char.widens_to(PrimitiveType::Int));
+        assert!(!PrimitiveType::Char.widens_to(PrimitiveType::Short));
+        assert!(PrimitiveType::Long.widens_to(PrimitiveType::Float));
+        assert_eq!(PrimitiveType::promote(PrimitiveType::Byte, PrimitiveType::Char), PrimitiveType::Int);
+        assert_eq!(Type::class("java/lang/Integer").unboxed(), Some(PrimitiveType::Int));
+    }
+
+}
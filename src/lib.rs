@@ -1,5 +1,7 @@
 extern crate core;
 
+pub mod aot;
 pub mod class;
 pub mod code;
+pub mod compiler;
 pub mod runtime;
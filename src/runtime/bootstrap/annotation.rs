@@ -0,0 +1,370 @@
+use runtime::{Value, HeapCell, Object};
+use runtime::class::{ClassTable, RuntimeClass};
+use runtime::class::annotation::{AnnotationValue, RuntimeAnnotation};
+use runtime::class::method::RuntimeMethod;
+use runtime::interpreter::{self, InvokeResult, InterpreterError};
+use runtime::bootstrap::{ClassBuilder, call, object_arg, return_string, format_float, format_double, reflect};
+use runtime::string;
+use class::field;
+use class::method::ACC_PUBLIC;
+use std::sync::Arc;
+
+// Annotations are seen through proxies: instances of a class implementing the annotation
+// interface, with a field holding the value of each element, whose methods return them. Like
+// lambda classes, the proxy class of an annotation interface is defined the first time it is
+// needed.
+
+pub const ANNOTATION_CLASS_NAME: &str = "java/lang/annotation/Annotation";
+pub const INHERITED_CLASS_NAME: &str = "java/lang/annotation/Inherited";
+
+pub fn load(class_table: &ClassTable) {
+    ClassBuilder::interface(ANNOTATION_CLASS_NAME).define(class_table);
+}
+
+// The elements of an annotation interface, which are its abstract methods without parameters.
+fn elements(interface: &RuntimeClass) -> Vec<&RuntimeMethod> {
+    interface.methods
+        .iter()
+        .filter(|method| method.is_abstract() && method.descriptor.starts_with("()"))
+        .collect()
+}
+
+fn return_type(method: &RuntimeMethod) -> &str {
+    &method.descriptor[2..]
+}
+
+fn proxy_class(class_table: &ClassTable, interface: &Arc<RuntimeClass>) -> Arc<RuntimeClass> {
+    let name = format!("{}$$Proxy", interface.class_name);
+    if let Some(class) = class_table.get_class(&name) {
+        return class;
+    }
+
+    let mut builder = ClassBuilder::new(&name).implements(&interface.class_name);
+    for method in elements(interface) {
+        builder = builder
+            .field(&method.name, return_type(method), field::ACC_PRIVATE | field::ACC_FINAL)
+            .native(&method.name, &method.descriptor, ACC_PUBLIC, annotation_element);
+    }
+    builder
+        .native("annotationType", "()Ljava/lang/Class;", ACC_PUBLIC, annotation_annotation_type)
+        .native("equals", "(Ljava/lang/Object;)Z", ACC_PUBLIC, annotation_equals)
+        .native("hashCode", "()I", ACC_PUBLIC, annotation_hash_code)
+        .native("toString", "()Ljava/lang/String;", ACC_PUBLIC, annotation_to_string)
+        .define(class_table)
+}
+
+// Whether reflection sees an annotation. Like in the JDK, annotations whose interface cannot be
+// loaded are left out.
+pub fn is_visible(class_table: &ClassTable, annotation: &RuntimeAnnotation) -> bool {
+    matches!(class_table.find_class(&annotation.type_name), Ok(Some(_)))
+}
+
+// Creates the proxy of an annotation. The inner error is an exception thrown while loading or
+// initializing the classes of its enum constants.
+pub fn new_annotation(class_table: &ClassTable, annotation: &RuntimeAnnotation) -> Result<Result<Value, Value>, InterpreterError> {
+    let interface = interpreter::resolve_class(&annotation.type_name, class_table)?;
+    let class = proxy_class(class_table, &interface);
+    let proxy = match interpreter::allocate_object(&class, class_table)? {
+        Ok(proxy) => proxy,
+        Err(exception) => return Ok(Err(exception))
+    };
+
+    for method in elements(&interface) {
+        // The JDK throws IncompleteAnnotationException when an element without a value is read,
+        // which only happens if the interface changed after the annotation was compiled.
+        let value = match annotation.get(&method.name).or(method.annotation_default.as_ref()) {
+            Some(value) => match element_value(class_table, value, return_type(method))? {
+                Ok(value) => value,
+                Err(exception) => return Ok(Err(exception))
+            },
+            None => Value::default_for(return_type(method))
+        };
+        proxy.borrow_mut().put_field(method.name.clone(), value);
+    }
+
+    Ok(Ok(Value::ObjectRef(proxy)))
+}
+
+pub fn new_annotations(class_table: &ClassTable, annotations: &[RuntimeAnnotation]) -> Result<Result<Vec<Value>, Value>, InterpreterError> {
+    let mut proxies = Vec::new();
+    for annotation in annotations.iter().filter(|annotation| is_visible(class_table, annotation)) {
+        match new_annotation(class_table, annotation)? {
+            Ok(proxy) => proxies.push(proxy),
+            Err(exception) => return Ok(Err(exception))
+        }
+    }
+    Ok(Ok(proxies))
+}
+
+// The value of an element as its method returns it, given the field descriptor of its return
+// type. The inner error is the NoClassDefFoundError thrown if an enum class cannot be loaded, or
+// an exception thrown while initializing it.
+pub fn element_value(class_table: &ClassTable, value: &AnnotationValue, descriptor: &str) -> Result<Result<Value, Value>, InterpreterError> {
+    let value = match *value {
+        AnnotationValue::Boolean(b) => Value::Integer(b as i32),
+        AnnotationValue::Byte(b) => Value::Integer(b as i32),
+        AnnotationValue::Character(c) => Value::Integer(c as i32),
+        AnnotationValue::Short(s) => Value::Integer(s as i32),
+        AnnotationValue::Integer(i) => Value::Integer(i),
+        AnnotationValue::Long(l) => Value::Long(l),
+        AnnotationValue::Float(f) => Value::Float(f),
+        AnnotationValue::Double(d) => Value::Double(d),
+        AnnotationValue::String(ref chars) => Value::ObjectRef(class_table.intern_string(chars.clone())),
+        AnnotationValue::Enum { ref type_name, ref const_name } => {
+            let class = match interpreter::link_class(type_name, class_table)? {
+                Ok(class) => class,
+                Err(exception) => return Ok(Err(exception))
+            };
+            if let Some(exception) = interpreter::initialize_class(&class, class_table)? {
+                return Ok(Err(exception));
+            }
+            class.get_static(const_name).unwrap_or(Value::Null)
+        },
+        AnnotationValue::Class(ref class_descriptor) => reflect::type_mirror(class_table, class_descriptor),
+        AnnotationValue::Annotation(ref annotation) => return new_annotation(class_table, annotation),
+        AnnotationValue::Array(ref values) => {
+            let component_type = descriptor.get(1..).unwrap_or("Ljava/lang/Object;");
+            let array = class_table.new_array(component_type, values.len());
+            for (index, value) in values.iter().enumerate() {
+                match element_value(class_table, value, component_type)? {
+                    Ok(value) => array.borrow_mut().set(index, value)?,
+                    Err(exception) => return Ok(Err(exception))
+                }
+            }
+            Value::ArrayRef(array)
+        }
+    };
+
+    Ok(Ok(value))
+}
+
+// The name of an element, the field descriptor of its type and its value.
+type Element = (String, String, Value);
+
+// The annotation interface a proxy implements, and the elements of the proxy.
+fn proxy_elements(proxy: &Arc<HeapCell<Object>>) -> Result<(Arc<RuntimeClass>, Vec<Element>), InterpreterError> {
+    let interface = proxy.borrow().class().interfaces.first().cloned().ok_or(InterpreterError::UnexpectedOperand)?;
+    let values = elements(&interface)
+        .iter()
+        .map(|method| {
+            let value = proxy.borrow().get_field(method.name.clone());
+            (method.name.clone(), String::from(return_type(method)), value)
+        })
+        .collect();
+    Ok((interface, values))
+}
+
+// Every element method of a proxy class shares this native, and tells which element it returns
+// by the method of its frame. Arrays are copied, so that callers cannot change the annotation.
+fn annotation_element(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let frame = class_table.stacks.current_frames().pop().ok_or(InterpreterError::UnexpectedOperand)?;
+    let value = match this.borrow().get_field(frame.method().name.clone()) {
+        Value::ArrayRef(array) => Value::ArrayRef(class_table.clone_array(&array)),
+        value => value
+    };
+    Ok(InvokeResult::Value(value))
+}
+
+fn annotation_annotation_type(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let (interface, _) = proxy_elements(&this)?;
+    Ok(InvokeResult::Value(Value::ObjectRef(class_table.get_mirror(&interface.class_name))))
+}
+
+// Annotations are equal if they are of the same interface and their elements are equal, see
+// Annotation.equals.
+fn annotation_equals(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let other = match arguments[1] {
+        Value::ObjectRef(ref other) if Arc::ptr_eq(other.borrow().class(), this.borrow().class()) => other.clone(),
+        _ => return Ok(InvokeResult::Value(Value::Integer(0)))
+    };
+
+    let (_, values) = proxy_elements(&this)?;
+    let (_, other_values) = proxy_elements(&other)?;
+    for ((_, descriptor, value), (_, _, other_value)) in values.iter().zip(other_values.iter()) {
+        match values_equal(class_table, value, other_value, descriptor)? {
+            Ok(true) => {},
+            Ok(false) => return Ok(InvokeResult::Value(Value::Integer(0))),
+            Err(exception) => return Ok(InvokeResult::Exception(exception))
+        }
+    }
+    Ok(InvokeResult::Value(Value::Integer(1)))
+}
+
+// Primitives are compared like their wrappers' equals does, arrays like Arrays.equals, and other
+// objects by equals.
+fn values_equal(class_table: &ClassTable, value: &Value, other: &Value, descriptor: &str) -> Result<Result<bool, Value>, InterpreterError> {
+    let equal = match (value, other) {
+        (&Value::Float(a), &Value::Float(b)) => a.to_bits() == b.to_bits(),
+        (&Value::Double(a), &Value::Double(b)) => a.to_bits() == b.to_bits(),
+        (&Value::Integer(a), &Value::Integer(b)) => a == b,
+        (&Value::Long(a), &Value::Long(b)) => a == b,
+        (Value::ArrayRef(a), Value::ArrayRef(b)) => {
+            let component_type = &descriptor[1..];
+            let (a, b) = (a.borrow(), b.borrow());
+            if a.len() != b.len() {
+                return Ok(Ok(false));
+            }
+            for index in 0..a.len() {
+                match values_equal(class_table, &a.get(index), &b.get(index), component_type)? {
+                    Ok(true) => {},
+                    result => return Ok(result)
+                }
+            }
+            true
+        },
+        (&Value::ObjectRef(_), _) => {
+            return match call(class_table, value.clone(), "equals", "(Ljava/lang/Object;)Z", vec![other.clone()])? {
+                Ok(Some(Value::Integer(equal))) => Ok(Ok(equal != 0)),
+                Ok(_) => Err(InterpreterError::UnexpectedOperand),
+                Err(exception) => Ok(Err(exception))
+            };
+        },
+        _ => value.same_reference(other)
+    };
+    Ok(Ok(equal))
+}
+
+// The sum of (127 * name.hashCode()) ^ value.hashCode() over the elements, see
+// Annotation.hashCode.
+fn annotation_hash_code(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let (_, values) = proxy_elements(&this)?;
+    let mut hash_code = 0i32;
+    for (name, descriptor, value) in values.iter() {
+        let name_hash_code = name.encode_utf16().fold(0i32, |hash, c| hash.wrapping_mul(31).wrapping_add(c as i32));
+        let value_hash_code = match value_hash_code(class_table, value, descriptor)? {
+            Ok(value_hash_code) => value_hash_code,
+            Err(exception) => return Ok(InvokeResult::Exception(exception))
+        };
+        hash_code = hash_code.wrapping_add(name_hash_code.wrapping_mul(127) ^ value_hash_code);
+    }
+    Ok(InvokeResult::Value(Value::Integer(hash_code)))
+}
+
+// The hash code of the wrapper of a primitive, Arrays.hashCode of an array, or hashCode of an
+// object.
+fn value_hash_code(class_table: &ClassTable, value: &Value, descriptor: &str) -> Result<Result<i32, Value>, InterpreterError> {
+    let hash_code = match (value, descriptor) {
+        (&Value::Integer(b), "Z") => if b != 0 { 1231 } else { 1237 },
+        (&Value::Integer(i), _) => i,
+        (&Value::Long(l), _) => (l ^ ((l as u64) >> 32) as i64) as i32,
+        (&Value::Float(f), _) => f.to_bits() as i32,
+        (&Value::Double(d), _) => {
+            let bits = d.to_bits();
+            (bits ^ (bits >> 32)) as i32
+        },
+        (Value::ArrayRef(array), _) => {
+            let component_type = &descriptor[1..];
+            let array = array.borrow();
+            let mut hash_code = 1i32;
+            for index in 0..array.len() {
+                match value_hash_code(class_table, &array.get(index), component_type)? {
+                    Ok(element_hash_code) => hash_code = hash_code.wrapping_mul(31).wrapping_add(element_hash_code),
+                    Err(exception) => return Ok(Err(exception))
+                }
+            }
+            hash_code
+        },
+        (&Value::ObjectRef(_), _) => {
+            return match call(class_table, value.clone(), "hashCode", "()I", Vec::new())? {
+                Ok(Some(Value::Integer(hash_code))) => Ok(Ok(hash_code)),
+                Ok(_) => Err(InterpreterError::UnexpectedOperand),
+                Err(exception) => Ok(Err(exception))
+            };
+        },
+        _ => 0
+    };
+    Ok(Ok(hash_code))
+}
+
+// How the annotation would be written in source, e.g. @Named("x") or @Tag(value="x", count=3).
+// An element named value is written without its name if it is the only one.
+fn annotation_to_string(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let (interface, values) = proxy_elements(&this)?;
+    let lone_value = values.len() == 1 && values[0].0 == "value";
+
+    let mut elements = Vec::new();
+    for (name, descriptor, value) in values.iter() {
+        let source = match source_string(class_table, value, descriptor)? {
+            Ok(source) => source,
+            Err(exception) => return Ok(InvokeResult::Exception(exception))
+        };
+        if lone_value {
+            elements.push(source);
+        } else {
+            elements.push(format!("{}={}", name, source));
+        }
+    }
+
+    let string = format!("@{}({})", interface.class_name.replace('/', "."), elements.join(", "));
+    return_string(class_table, &string)
+}
+
+// An element value as a source literal, written like the JDK does.
+fn source_string(class_table: &ClassTable, value: &Value, descriptor: &str) -> Result<Result<String, Value>, InterpreterError> {
+    let source = match (value, descriptor) {
+        (&Value::Integer(b), "Z") => String::from(if b != 0 { "true" } else { "false" }),
+        (&Value::Integer(b), "B") => format!("(byte)0x{:02x}", b as u8),
+        (&Value::Integer(c), "C") => format!("'{}'", quote(c as u16)),
+        (&Value::Integer(i), _) => i.to_string(),
+        (&Value::Long(l), _) => format!("{}L", l),
+        (&Value::Float(f), _) if f.is_nan() => String::from("0.0f/0.0f"),
+        (&Value::Float(f), _) if f.is_infinite() => String::from(if f > 0.0 { "1.0f/0.0f" } else { "-1.0f/0.0f" }),
+        (&Value::Float(f), _) => format!("{}f", format_float(f)),
+        (&Value::Double(d), _) if d.is_nan() => String::from("0.0/0.0"),
+        (&Value::Double(d), _) if d.is_infinite() => String::from(if d > 0.0 { "1.0/0.0" } else { "-1.0/0.0" }),
+        (&Value::Double(d), _) => format_double(d),
+        (Value::ArrayRef(array), _) => {
+            let component_type = &descriptor[1..];
+            let array = array.borrow();
+            let mut sources = Vec::new();
+            for index in 0..array.len() {
+                match source_string(class_table, &array.get(index), component_type)? {
+                    Ok(source) => sources.push(source),
+                    Err(exception) => return Ok(Err(exception))
+                }
+            }
+            format!("{{{}}}", sources.join(", "))
+        },
+        (Value::ObjectRef(object), "Ljava/lang/String;") => {
+            let quoted: String = string::get_chars(&object.borrow()).into_iter().map(quote).collect();
+            format!("\"{}\"", quoted)
+        },
+        (Value::ObjectRef(object), "Ljava/lang/Class;") => format!("{}.class", reflect::mirror_type_name(object)),
+        (Value::ObjectRef(object), _) if object.borrow().class().is_subclass_of("java/lang/Enum") => {
+            match object.borrow().get_field(String::from("name")) {
+                Value::ObjectRef(name) => string::to_rust_string(&name.borrow()),
+                _ => String::from("null")
+            }
+        },
+        (&Value::ObjectRef(_), _) => {
+            match call(class_table, value.clone(), "toString", "()Ljava/lang/String;", Vec::new())? {
+                Ok(Some(Value::ObjectRef(string))) => string::to_rust_string(&string.borrow()),
+                Ok(_) => String::from("null"),
+                Err(exception) => return Ok(Err(exception))
+            }
+        },
+        _ => String::from("null")
+    };
+    Ok(Ok(source))
+}
+
+// Escapes a character of a char or String literal.
+fn quote(c: u16) -> String {
+    match c {
+        0x08 => String::from("\\b"),
+        0x0C => String::from("\\f"),
+        0x0A => String::from("\\n"),
+        0x0D => String::from("\\r"),
+        0x09 => String::from("\\t"),
+        0x27 => String::from("\\'"),
+        0x22 => String::from("\\\""),
+        0x5C => String::from("\\\\"),
+        0x20..=0x7E => String::from((c as u8) as char),
+        _ => format!("\\u{:04x}", c)
+    }
+}
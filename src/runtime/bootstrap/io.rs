@@ -0,0 +1,261 @@
+use runtime::Value;
+use runtime::class::ClassTable;
+use runtime::interpreter::{InvokeResult, InterpreterError};
+use runtime::bootstrap::{ClassBuilder, object_arg, int_arg, long_arg, float_arg, double_arg, string_arg,
+                         to_java_chars, format_float, format_double};
+use class::field;
+use class::method::ACC_PUBLIC;
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+pub const PRINT_STREAM_CLASS_NAME: &str = "java/io/PrintStream";
+
+const STDOUT: i32 = 1;
+const STDERR: i32 = 2;
+
+// The destinations of System.out and System.err, shared by all threads.
+pub struct Console {
+    pub out: Mutex<Box<dyn Write + Send>>,
+    pub err: Mutex<Box<dyn Write + Send>>
+}
+
+impl Console {
+
+    pub fn new(out: Box<dyn Write + Send>, err: Box<dyn Write + Send>) -> Console {
+        Console {
+            out: Mutex::new(out),
+            err: Mutex::new(err)
+        }
+    }
+
+    pub fn stdio() -> Console {
+        Console::new(Box::new(io::stdout()), Box::new(io::stderr()))
+    }
+
+    pub fn flush(&self) {
+        let _ = self.out.lock().unwrap().flush();
+        let _ = self.err.lock().unwrap().flush();
+    }
+
+}
+
+impl fmt::Debug for Console {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Console")
+    }
+}
+
+// An in-memory writer whose contents can be read back after it has been handed to a Console.
+#[derive(Clone, Default)]
+pub struct SharedBuffer {
+    buffer: Arc<Mutex<Vec<u8>>>
+}
+
+impl SharedBuffer {
+
+    pub fn new() -> SharedBuffer {
+        SharedBuffer::default()
+    }
+
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.buffer.lock().unwrap()).into_owned()
+    }
+
+}
+
+impl Write for SharedBuffer {
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+}
+
+pub fn load(class_table: &ClassTable) {
+    ClassBuilder::new(PRINT_STREAM_CLASS_NAME)
+        .field("fd", "I", field::ACC_PRIVATE | field::ACC_FINAL)
+        .native("print", "(Ljava/lang/String;)V", ACC_PUBLIC, print_string)
+        .native("print", "(Ljava/lang/Object;)V", ACC_PUBLIC, print_object)
+        .native("print", "(I)V", ACC_PUBLIC, print_int)
+        .native("print", "(J)V", ACC_PUBLIC, print_long)
+        .native("print", "(F)V", ACC_PUBLIC, print_float)
+        .native("print", "(D)V", ACC_PUBLIC, print_double)
+        .native("print", "(C)V", ACC_PUBLIC, print_char)
+        .native("print", "(Z)V", ACC_PUBLIC, print_boolean)
+        .native("print", "([C)V", ACC_PUBLIC, print_chars)
+        .native("println", "()V", ACC_PUBLIC, println)
+        .native("println", "(Ljava/lang/String;)V", ACC_PUBLIC, println_string)
+        .native("println", "(Ljava/lang/Object;)V", ACC_PUBLIC, println_object)
+        .native("println", "(I)V", ACC_PUBLIC, println_int)
+        .native("println", "(J)V", ACC_PUBLIC, println_long)
+        .native("println", "(F)V", ACC_PUBLIC, println_float)
+        .native("println", "(D)V", ACC_PUBLIC, println_double)
+        .native("println", "(C)V", ACC_PUBLIC, println_char)
+        .native("println", "(Z)V", ACC_PUBLIC, println_boolean)
+        .native("println", "([C)V", ACC_PUBLIC, println_chars)
+        .native("flush", "()V", ACC_PUBLIC, flush)
+        .define(class_table);
+}
+
+// Creates the PrintStream stored in System.out or System.err.
+pub fn new_print_stream(class_table: &ClassTable, fd: i32) -> Value {
+    let class = class_table.get_class(PRINT_STREAM_CLASS_NAME).unwrap();
+    let stream = class_table.new_object(&class);
+    stream.borrow_mut().put_field(String::from("fd"), Value::Integer(fd));
+    Value::ObjectRef(stream)
+}
+
+pub fn stdout(class_table: &ClassTable) -> Value {
+    new_print_stream(class_table, STDOUT)
+}
+
+pub fn stderr(class_table: &ClassTable) -> Value {
+    new_print_stream(class_table, STDERR)
+}
+
+fn write(class_table: &ClassTable, arguments: &[Value], text: &str) -> Result<InvokeResult, InterpreterError> {
+    let stream = object_arg(arguments, 0)?;
+    let fd = stream.borrow().get_field(String::from("fd"));
+
+    let mut writer = match fd {
+        Value::Integer(STDERR) => class_table.console.err.lock().unwrap(),
+        _ => class_table.console.out.lock().unwrap()
+    };
+    // Like java.io.PrintStream, write errors are not reported to the caller.
+    let _ = writer.write_all(text.as_bytes());
+
+    Ok(InvokeResult::Void)
+}
+
+fn chars_text(arguments: &[Value]) -> Result<String, InterpreterError> {
+    match arguments.get(1) {
+        Some(Value::ArrayRef(array)) => {
+            let array = array.borrow();
+            let chars = array.as_chars().ok_or(InterpreterError::UnexpectedOperand)?;
+            Ok(String::from_utf16_lossy(chars))
+        },
+        _ => Err(InterpreterError::UnexpectedOperand)
+    }
+}
+
+fn string_text(arguments: &[Value]) -> Result<String, InterpreterError> {
+    match string_arg(arguments, 1)? {
+        Some(chars) => Ok(String::from_utf16_lossy(&chars)),
+        None => Ok(String::from("null"))
+    }
+}
+
+fn char_text(arguments: &[Value]) -> Result<String, InterpreterError> {
+    let c = int_arg(arguments, 1)? as u16;
+    Ok(String::from_utf16_lossy(&[c]))
+}
+
+fn boolean_text(arguments: &[Value]) -> Result<String, InterpreterError> {
+    let text = if int_arg(arguments, 1)? != 0 { "true" } else { "false" };
+    Ok(String::from(text))
+}
+
+fn print_string(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let text = string_text(&arguments)?;
+    write(class_table, &arguments, &text)
+}
+
+fn print_object(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let chars = java_try!(to_java_chars(class_table, &arguments[1]));
+    write(class_table, &arguments, &String::from_utf16_lossy(&chars))
+}
+
+fn print_int(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let text = int_arg(&arguments, 1)?.to_string();
+    write(class_table, &arguments, &text)
+}
+
+fn print_long(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let text = long_arg(&arguments, 1)?.to_string();
+    write(class_table, &arguments, &text)
+}
+
+fn print_float(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let text = format_float(float_arg(&arguments, 1)?);
+    write(class_table, &arguments, &text)
+}
+
+fn print_double(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let text = format_double(double_arg(&arguments, 1)?);
+    write(class_table, &arguments, &text)
+}
+
+fn print_char(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let text = char_text(&arguments)?;
+    write(class_table, &arguments, &text)
+}
+
+fn print_boolean(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let text = boolean_text(&arguments)?;
+    write(class_table, &arguments, &text)
+}
+
+fn print_chars(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let text = chars_text(&arguments)?;
+    write(class_table, &arguments, &text)
+}
+
+fn println(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    write(class_table, &arguments, "\n")
+}
+
+fn println_string(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let text = string_text(&arguments)? + "\n";
+    write(class_table, &arguments, &text)
+}
+
+fn println_object(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let chars = java_try!(to_java_chars(class_table, &arguments[1]));
+    write(class_table, &arguments, &(String::from_utf16_lossy(&chars) + "\n"))
+}
+
+fn println_int(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let text = int_arg(&arguments, 1)?.to_string() + "\n";
+    write(class_table, &arguments, &text)
+}
+
+fn println_long(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let text = long_arg(&arguments, 1)?.to_string() + "\n";
+    write(class_table, &arguments, &text)
+}
+
+fn println_float(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let text = format_float(float_arg(&arguments, 1)?) + "\n";
+    write(class_table, &arguments, &text)
+}
+
+fn println_double(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let text = format_double(double_arg(&arguments, 1)?) + "\n";
+    write(class_table, &arguments, &text)
+}
+
+fn println_char(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let text = char_text(&arguments)? + "\n";
+    write(class_table, &arguments, &text)
+}
+
+fn println_boolean(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let text = boolean_text(&arguments)? + "\n";
+    write(class_table, &arguments, &text)
+}
+
+fn println_chars(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let text = chars_text(&arguments)? + "\n";
+    write(class_table, &arguments, &text)
+}
+
+fn flush(class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    class_table.console.flush();
+    Ok(InvokeResult::Void)
+}
@@ -0,0 +1,397 @@
+use class::{field, method};
+use class::reference_kind::*;
+use runtime::{Value, HeapCell, Object};
+use runtime::class::{ClassTable, RuntimeClass};
+use runtime::class::field::RuntimeField;
+use runtime::interpreter::{self, InvokeResult, InterpreterError};
+use runtime::invokedynamic;
+use runtime::bootstrap::{reflect, object_arg, int_arg, throw_new, encode};
+use runtime::bootstrap::jdk::{Natives, no_op, get_field, put_field, null_result};
+use runtime::bootstrap::jdk::misc::STATIC_FIELD_OFFSET;
+use runtime::bootstrap::jdk::reflect::{METHOD_MODIFIERS, FIELD_MODIFIERS};
+use runtime::string;
+use std::sync::Arc;
+
+const METHOD_HANDLE_NATIVES: &str = "java/lang/invoke/MethodHandleNatives";
+const METHOD_HANDLE: &str = "java/lang/invoke/MethodHandle";
+const RESOLVED_METHOD_NAME: &str = "java/lang/invoke/ResolvedMethodName";
+
+// The flags of a MemberName, as java.lang.invoke.MemberName declares them. The reference kind
+// (JVMS $5.4.3.5) is kept in the bits from REFERENCE_KIND_SHIFT.
+const IS_METHOD: i32 = 0x10000;
+const IS_CONSTRUCTOR: i32 = 0x20000;
+const IS_FIELD: i32 = 0x40000;
+const ALL_KINDS: i32 = 0xf0000;
+const CALLER_SENSITIVE: i32 = 0x100000;
+const TRUSTED_FINAL: i32 = 0x200000;
+const REFERENCE_KIND_SHIFT: i32 = 24;
+const REFERENCE_KIND_MASK: i32 = 0xf;
+
+pub static NATIVES: Natives = &[
+    (METHOD_HANDLE_NATIVES, "init", "(Ljava/lang/invoke/MemberName;Ljava/lang/Object;)V", method_handle_natives_init),
+    (METHOD_HANDLE_NATIVES, "expand", "(Ljava/lang/invoke/MemberName;)V", method_handle_natives_expand),
+    (METHOD_HANDLE_NATIVES, "resolve", "(Ljava/lang/invoke/MemberName;Ljava/lang/Class;IZ)Ljava/lang/invoke/MemberName;",
+     method_handle_natives_resolve),
+    (METHOD_HANDLE_NATIVES, "objectFieldOffset", "(Ljava/lang/invoke/MemberName;)J", method_handle_natives_object_field_offset),
+    (METHOD_HANDLE_NATIVES, "staticFieldOffset", "(Ljava/lang/invoke/MemberName;)J", method_handle_natives_static_field_offset),
+    (METHOD_HANDLE_NATIVES, "staticFieldBase", "(Ljava/lang/invoke/MemberName;)Ljava/lang/Object;", method_handle_natives_static_field_base),
+    (METHOD_HANDLE_NATIVES, "setCallSiteTargetNormal", "(Ljava/lang/invoke/CallSite;Ljava/lang/invoke/MethodHandle;)V",
+     method_handle_natives_set_call_site_target),
+    (METHOD_HANDLE_NATIVES, "setCallSiteTargetVolatile", "(Ljava/lang/invoke/CallSite;Ljava/lang/invoke/MethodHandle;)V",
+     method_handle_natives_set_call_site_target),
+    (METHOD_HANDLE_NATIVES, "clearCallSiteContext", "(Ljava/lang/invoke/MethodHandleNatives$CallSiteContext;)V", no_op),
+    (METHOD_HANDLE_NATIVES, "getNamedCon", "(I[Ljava/lang/Object;)I", method_handle_natives_get_named_con),
+
+    (METHOD_HANDLE, "invokeBasic", "([Ljava/lang/Object;)Ljava/lang/Object;", method_handle_invoke_basic),
+    (METHOD_HANDLE, "linkToStatic", "([Ljava/lang/Object;)Ljava/lang/Object;", method_handle_link_to_static),
+    (METHOD_HANDLE, "linkToSpecial", "([Ljava/lang/Object;)Ljava/lang/Object;", method_handle_link_to_static),
+    (METHOD_HANDLE, "linkToVirtual", "([Ljava/lang/Object;)Ljava/lang/Object;", method_handle_link_to_virtual),
+    (METHOD_HANDLE, "linkToInterface", "([Ljava/lang/Object;)Ljava/lang/Object;", method_handle_link_to_virtual)
+];
+
+// HotSpot keeps the method a MemberName is resolved to in fields it injects into its
+// ResolvedMethodName: the mirror of the class declaring the method and the position of the
+// method in that class.
+pub fn injected_fields(class_name: &str) -> Vec<RuntimeField> {
+    if class_name != RESOLVED_METHOD_NAME {
+        return Vec::new();
+    }
+    vec![
+        RuntimeField::new("vmholder", "Ljava/lang/Class;", field::ACC_PRIVATE | field::ACC_FINAL),
+        RuntimeField::new("vmtarget", "J", field::ACC_PRIVATE | field::ACC_FINAL)
+    ]
+}
+
+// java.lang.invoke.MethodHandleNatives
+//
+// A MemberName names a method, constructor or field by its class, name and type, which is a
+// MethodType, a descriptor or a Class. Resolving it fills in the class declaring the member, its
+// modifiers and reference kind in the flags and, for methods and constructors, the
+// ResolvedMethodName the intrinsics below invoke.
+
+// Makes a resolved MemberName of a Method, Constructor or Field, whose slot is the position of
+// the member in its class as for reflection.
+fn method_handle_natives_init(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let member = object_arg(&arguments, 0)?;
+    let reflected = object_arg(&arguments, 1)?;
+    let class = match get_field(&reflected, "clazz") {
+        Value::ObjectRef(mirror) => reflect::mirror_class(class_table, &mirror)?.ok_or(InterpreterError::UnexpectedOperand)?,
+        _ => return Err(InterpreterError::UnexpectedOperand)
+    };
+    let slot = match get_field(&reflected, "slot") {
+        Value::Integer(slot) => slot as usize,
+        _ => return Err(InterpreterError::UnexpectedOperand)
+    };
+
+    if reflected.borrow().class().class_name == "java/lang/reflect/Field" {
+        let field = class.fields.get(slot).ok_or(InterpreterError::UnexpectedOperand)?;
+        let reference_kind = if field.is_static() { REF_GET_STATIC } else { REF_GET_FIELD };
+        put_field(&member, "clazz", Value::ObjectRef(class_table.get_mirror(&class.class_name)));
+        put_field(&member, "flags", Value::Integer(field_flags(&class, field, reference_kind)));
+    } else {
+        let method = class.methods.get(slot).ok_or(InterpreterError::UnexpectedOperand)?;
+        let reference_kind = if method.name == "<init>" { REF_INVOKE_SPECIAL } else { REF_INVOKE_VIRTUAL };
+        set_method(class_table, &member, &class, slot, reference_kind)?;
+    }
+    Ok(InvokeResult::Void)
+}
+
+// Fills in the name and type of a MemberName made by init.
+fn method_handle_natives_expand(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let member = object_arg(&arguments, 0)?;
+    if let Some((class, slot)) = resolved_method(class_table, &member)? {
+        let method = &class.methods[slot];
+        put_field(&member, "name", Value::ObjectRef(class_table.intern_string(encode(&method.name))));
+        put_field(&member, "type", Value::ObjectRef(class_table.intern_string(encode(&method.descriptor))));
+    }
+    Ok(InvokeResult::Void)
+}
+
+// Resolves a MemberName in place, ignoring access. A member that cannot be found throws the
+// LinkageError that resolving a symbolic reference to it would, unless the resolution is
+// speculative, in which case null is returned.
+fn method_handle_natives_resolve(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let member = object_arg(&arguments, 0)?;
+    let speculative = int_arg(&arguments, 3)? != 0;
+    let class = match get_field(&member, "clazz") {
+        Value::ObjectRef(mirror) => reflect::mirror_class(class_table, &mirror)?,
+        _ => None
+    };
+    let name = match get_field(&member, "name") {
+        Value::ObjectRef(name) => Some(string::to_rust_string(&name.borrow())),
+        _ => None
+    };
+    let (class, name) = match (class, name) {
+        (Some(class), Some(name)) => (class, name),
+        _ => return throw_new(class_table, "java/lang/IllegalArgumentException", Some("nothing to resolve"))
+    };
+    let flags = match get_field(&member, "flags") {
+        Value::Integer(flags) => flags,
+        _ => return Err(InterpreterError::UnexpectedOperand)
+    };
+    let reference_kind = ((flags >> REFERENCE_KIND_SHIFT) & REFERENCE_KIND_MASK) as u8;
+    let descriptor = member_type_descriptor(&get_field(&member, "type"), flags & IS_FIELD != 0)?;
+
+    let (error, message) = match flags & ALL_KINDS {
+        IS_METHOD | IS_CONSTRUCTOR => {
+            if let Some((declaring_class, slot)) = find_method(class_table, &class, &name, &descriptor) {
+                set_method(class_table, &member, &declaring_class, slot, reference_kind)?;
+                return Ok(InvokeResult::Value(Value::ObjectRef(member)));
+            }
+            ("java/lang/NoSuchMethodError", format!("{}.{}{}", class.class_name.replace('/', "."), name, descriptor))
+        },
+        IS_FIELD => {
+            if let Some((declaring_class, slot)) = find_field(&class, &name, &descriptor) {
+                let field = &declaring_class.fields[slot];
+                let setter = reference_kind == REF_PUT_FIELD || reference_kind == REF_PUT_STATIC;
+                let reference_kind = match (field.is_static(), setter) {
+                    (true, true) => REF_PUT_STATIC,
+                    (true, false) => REF_GET_STATIC,
+                    (false, true) => REF_PUT_FIELD,
+                    (false, false) => REF_GET_FIELD
+                };
+                put_field(&member, "clazz", Value::ObjectRef(class_table.get_mirror(&declaring_class.class_name)));
+                put_field(&member, "flags", Value::Integer(field_flags(&declaring_class, field, reference_kind)));
+                return Ok(InvokeResult::Value(Value::ObjectRef(member)));
+            }
+            ("java/lang/NoSuchFieldError", name)
+        },
+        _ => ("java/lang/LinkageError", format!("cannot resolve {}", name))
+    };
+    if speculative {
+        return null_result();
+    }
+    throw_new(class_table, error, Some(&message))
+}
+
+fn method_handle_natives_object_field_offset(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let (class, slot) = member_field(class_table, &object_arg(&arguments, 0)?)?;
+    let position = class.instance_field_position(&class.fields[slot].name).ok_or(InterpreterError::UnexpectedOperand)?;
+    Ok(InvokeResult::Value(Value::Long(position as i64)))
+}
+
+fn method_handle_natives_static_field_offset(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let (_, slot) = member_field(class_table, &object_arg(&arguments, 0)?)?;
+    Ok(InvokeResult::Value(Value::Long(STATIC_FIELD_OFFSET + slot as i64)))
+}
+
+fn method_handle_natives_static_field_base(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let member = object_arg(&arguments, 0)?;
+    Ok(InvokeResult::Value(get_field(&member, "clazz")))
+}
+
+// Call sites are linked to the method that invokes their target, so a new target is picked up
+// without relinking.
+fn method_handle_natives_set_call_site_target(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let call_site = object_arg(&arguments, 0)?;
+    put_field(&call_site, "target", arguments[1].clone());
+    Ok(InvokeResult::Void)
+}
+
+// The constants verifyConstants checks against the VM when assertions are enabled. There are
+// none to check.
+fn method_handle_natives_get_named_con(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Integer(0)))
+}
+
+// Resolves the method or constructor of a MemberName: constructors are declared by the class
+// itself, and the signature polymorphic methods of MethodHandle and VarHandle take any type.
+// Returns the declaring class and the position of the method in it.
+fn find_method(class_table: &ClassTable, class: &Arc<RuntimeClass>, name: &str, descriptor: &str) -> Option<(Arc<RuntimeClass>, usize)> {
+    let declaring_class = if name == "<init>" {
+        class.clone()
+    } else if invokedynamic::is_signature_polymorphic(class_table, &class.class_name, name) {
+        let slot = class.methods.iter().position(|method| method.name == name)?;
+        return Some((class.clone(), slot));
+    } else {
+        RuntimeClass::resolve_method(class, name, descriptor)?
+    };
+    let slot = declaring_class.methods.iter().position(|method| method.name == name && method.descriptor == descriptor)?;
+    Some((declaring_class, slot))
+}
+
+// Resolves a field by name and descriptor as JVMS $5.4.3.2 does: in the class, its
+// superinterfaces and then its superclasses. Returns the declaring class and the position of the
+// field in it.
+fn find_field(class: &Arc<RuntimeClass>, name: &str, descriptor: &str) -> Option<(Arc<RuntimeClass>, usize)> {
+    if let Some(slot) = class.fields.iter().position(|field| field.name == name && field.descriptor_string == descriptor) {
+        return Some((class.clone(), slot));
+    }
+    class.interfaces.iter()
+        .chain(class.super_class.iter())
+        .find_map(|super_class| find_field(super_class, name, descriptor))
+}
+
+// Resolves the MemberName to the method at the slot of the class. The reference kind asked for
+// becomes the one the method is invoked with: static methods are invoked statically, methods of
+// interfaces through the interface, and private methods without dispatch.
+fn set_method(class_table: &ClassTable,
+              member: &Arc<HeapCell<Object>>,
+              class: &Arc<RuntimeClass>,
+              slot: usize,
+              reference_kind: u8) -> Result<(), InterpreterError> {
+    let method = &class.methods[slot];
+    let (kind, reference_kind) = if method.name == "<init>" {
+        (IS_CONSTRUCTOR, REF_INVOKE_SPECIAL)
+    } else if method.is_static() {
+        (IS_METHOD, REF_INVOKE_STATIC)
+    } else if reference_kind == REF_INVOKE_SPECIAL || method.access_flags & method::ACC_PRIVATE != 0 {
+        (IS_METHOD, REF_INVOKE_SPECIAL)
+    } else if class.is_interface() {
+        (IS_METHOD, REF_INVOKE_INTERFACE)
+    } else {
+        (IS_METHOD, REF_INVOKE_VIRTUAL)
+    };
+    let caller_sensitive = method.annotations.iter()
+        .any(|annotation| annotation.type_name == "jdk/internal/reflect/CallerSensitive");
+    let flags = (method.access_flags & METHOD_MODIFIERS) as i32 | kind | (reference_kind as i32) << REFERENCE_KIND_SHIFT |
+        if caller_sensitive { CALLER_SENSITIVE } else { 0 };
+
+    let resolved_method_name = interpreter::resolve_class(RESOLVED_METHOD_NAME, class_table)?;
+    let resolved = class_table.new_object(&resolved_method_name);
+    put_field(&resolved, "vmholder", Value::ObjectRef(class_table.get_mirror(&class.class_name)));
+    put_field(&resolved, "vmtarget", Value::Long(slot as i64));
+    put_field(member, "clazz", Value::ObjectRef(class_table.get_mirror(&class.class_name)));
+    put_field(member, "flags", Value::Integer(flags));
+    put_field(member, "method", Value::ObjectRef(resolved));
+    Ok(())
+}
+
+// The flags of a resolved field. The final fields of records and hidden classes cannot be set
+// through reflection, like static final fields.
+fn field_flags(class: &RuntimeClass, field: &RuntimeField, reference_kind: u8) -> i32 {
+    let is_record = class.super_class.as_ref().is_some_and(|super_class| super_class.class_name == "java/lang/Record");
+    let trusted_final = field.access_flags & field::ACC_FINAL != 0 && (field.is_static() || is_record || class.is_hidden());
+    (field.access_flags & FIELD_MODIFIERS) as i32 | IS_FIELD | (reference_kind as i32) << REFERENCE_KIND_SHIFT |
+        if trusted_final { TRUSTED_FINAL } else { 0 }
+}
+
+// The descriptor of the type of a MemberName. Methods have a MethodType, a descriptor or the
+// array of their return type and parameter types; fields have a Class or a descriptor.
+fn member_type_descriptor(member_type: &Value, is_field: bool) -> Result<String, InterpreterError> {
+    let mirror_descriptor = |value: Value| match value {
+        Value::ObjectRef(mirror) => Ok(reflect::mirror_descriptor(&mirror)),
+        _ => Err(InterpreterError::UnexpectedOperand)
+    };
+    let method_descriptor = |return_type: Value, parameter_types: Value| -> Result<String, InterpreterError> {
+        let parameter_types = match parameter_types {
+            Value::ArrayRef(array) => array,
+            _ => return Err(InterpreterError::UnexpectedOperand)
+        };
+        let parameter_types = parameter_types.borrow();
+        let mut descriptor = String::from("(");
+        for index in 0..parameter_types.len() {
+            descriptor.push_str(&mirror_descriptor(parameter_types.get(index))?);
+        }
+        descriptor.push(')');
+        descriptor.push_str(&mirror_descriptor(return_type)?);
+        Ok(descriptor)
+    };
+
+    match *member_type {
+        Value::ObjectRef(ref object) => {
+            let class_name = object.borrow().class().class_name.clone();
+            match class_name.as_str() {
+                "java/lang/String" => Ok(string::to_rust_string(&object.borrow())),
+                "java/lang/Class" if is_field => Ok(reflect::mirror_descriptor(object)),
+                "java/lang/invoke/MethodType" => method_descriptor(get_field(object, "rtype"), get_field(object, "ptypes")),
+                _ => Err(InterpreterError::UnexpectedOperand)
+            }
+        },
+        Value::ArrayRef(ref array) => {
+            let (return_type, parameter_types) = {
+                let array = array.borrow();
+                (array.get(0), array.get(1))
+            };
+            method_descriptor(return_type, parameter_types)
+        },
+        _ => Err(InterpreterError::UnexpectedOperand)
+    }
+}
+
+// The method a MemberName was resolved to, as the declaring class and the position of the method
+// in it, or None if it is not resolved to a method.
+pub fn resolved_method(class_table: &ClassTable, member: &Arc<HeapCell<Object>>) -> Result<Option<(Arc<RuntimeClass>, usize)>, InterpreterError> {
+    let resolved = match get_field(member, "method") {
+        Value::ObjectRef(resolved) => resolved,
+        _ => return Ok(None)
+    };
+    match (get_field(&resolved, "vmholder"), get_field(&resolved, "vmtarget")) {
+        (Value::ObjectRef(mirror), Value::Long(slot)) => {
+            let class = reflect::mirror_class(class_table, &mirror)?.ok_or(InterpreterError::UnexpectedOperand)?;
+            Ok(Some((class, slot as usize)))
+        },
+        _ => Ok(None)
+    }
+}
+
+// The field a resolved MemberName names, as the declaring class and the position of the field in
+// it.
+fn member_field(class_table: &ClassTable, member: &Arc<HeapCell<Object>>) -> Result<(Arc<RuntimeClass>, usize), InterpreterError> {
+    let class = match get_field(member, "clazz") {
+        Value::ObjectRef(mirror) => reflect::mirror_class(class_table, &mirror)?.ok_or(InterpreterError::UnexpectedOperand)?,
+        _ => return Err(InterpreterError::UnexpectedOperand)
+    };
+    let name = match get_field(member, "name") {
+        Value::ObjectRef(name) => string::to_rust_string(&name.borrow()),
+        _ => return Err(InterpreterError::UnexpectedOperand)
+    };
+    let slot = class.fields.iter().position(|field| field.name == name).ok_or(InterpreterError::UnexpectedOperand)?;
+    Ok((class, slot))
+}
+
+// java.lang.invoke.MethodHandle
+//
+// The intrinsics LambdaForms are made of, which the interpreter invokes with the arguments of the
+// call site. invokeBasic invokes the method the LambdaForm of a method handle, its first argument,
+// was compiled to. The linkTo methods invoke the method their trailing MemberName was resolved
+// to, selecting it by the class of the receiver for linkToVirtual and linkToInterface.
+
+fn method_handle_invoke_basic(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let method_handle = object_arg(&arguments, 0)?;
+    let form = match get_field(&method_handle, "form") {
+        Value::ObjectRef(form) => form,
+        _ => return Err(InterpreterError::UnexpectedOperand)
+    };
+    let entry = match get_field(&form, "vmentry") {
+        Value::ObjectRef(entry) => entry,
+        _ => return Err(InterpreterError::UnexpectedOperand)
+    };
+    invoke_resolved(class_table, &entry, arguments)
+}
+
+fn method_handle_link_to_static(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let (member, arguments) = trailing_member(arguments)?;
+    invoke_resolved(class_table, &member, arguments)
+}
+
+fn method_handle_link_to_virtual(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let (member, mut arguments) = trailing_member(arguments)?;
+    let (class, slot) = resolved_method(class_table, &member)?.ok_or(InterpreterError::UnexpectedOperand)?;
+    let method = &class.methods[slot];
+    if arguments.is_empty() {
+        return Err(InterpreterError::UnexpectedOperand);
+    }
+    let receiver = arguments.remove(0);
+    interpreter::invoke_virtual(class_table, receiver, &method.name, &method.descriptor, arguments)
+}
+
+fn trailing_member(mut arguments: Vec<Value>) -> Result<(Arc<HeapCell<Object>>, Vec<Value>), InterpreterError> {
+    match arguments.pop() {
+        Some(Value::ObjectRef(member)) => Ok((member, arguments)),
+        _ => Err(InterpreterError::UnexpectedOperand)
+    }
+}
+
+// Invokes the method a MemberName was resolved to, initializing its class first for static
+// methods.
+fn invoke_resolved(class_table: &ClassTable, member: &Arc<HeapCell<Object>>, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let (class, slot) = resolved_method(class_table, member)?.ok_or(InterpreterError::UnexpectedOperand)?;
+    let method = &class.methods[slot];
+    if method.is_static() {
+        if let Some(exception) = interpreter::initialize_class(&class, class_table)? {
+            return Ok(InvokeResult::Exception(exception));
+        }
+    }
+    interpreter::invoke_method(&class, method, arguments, class_table)
+}
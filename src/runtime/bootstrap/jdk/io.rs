@@ -0,0 +1,168 @@
+use runtime::{Value, HeapCell, Object};
+use runtime::class::ClassTable;
+use runtime::interpreter::{InvokeResult, InterpreterError};
+use runtime::bootstrap::{object_arg, int_arg, string_arg, throw_new, return_string};
+use runtime::bootstrap::jdk::{Natives, get_field, boolean_result};
+use runtime::string;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+const STDIN: i32 = 0;
+const STDERR: i32 = 2;
+
+// The bits of java.io.FileSystem that getBooleanAttributes0 returns.
+const BA_EXISTS: i32 = 0x01;
+const BA_REGULAR: i32 = 0x02;
+const BA_DIRECTORY: i32 = 0x04;
+
+pub static NATIVES: Natives = &[
+    ("java/io/FileDescriptor", "getHandle", "(I)J", file_descriptor_get_handle),
+    ("java/io/FileDescriptor", "getAppend", "(I)Z", file_descriptor_get_append),
+
+    ("java/io/FileOutputStream", "writeBytes", "([BIIZ)V", file_output_stream_write_bytes),
+    ("java/io/FileOutputStream", "write", "(IZ)V", file_output_stream_write),
+
+    ("java/io/FileInputStream", "readBytes", "([BII)I", file_input_stream_read_bytes),
+    ("java/io/FileInputStream", "available0", "()I", file_input_stream_available0),
+
+    ("java/io/UnixFileSystem", "canonicalize0", "(Ljava/lang/String;)Ljava/lang/String;", unix_file_system_canonicalize0),
+    ("java/io/UnixFileSystem", "getBooleanAttributes0", "(Ljava/io/File;)I", unix_file_system_get_boolean_attributes0)
+];
+
+// Handles are only used on Windows.
+fn file_descriptor_get_handle(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Long(-1)))
+}
+
+fn file_descriptor_get_append(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    boolean_result(false)
+}
+
+// The number of the file descriptor of a FileInputStream or FileOutputStream. Only the standard
+// streams are open, as opening files has no natives yet.
+fn stream_fd(stream: &Arc<HeapCell<Object>>) -> Option<i32> {
+    match get_field(stream, "fd") {
+        Value::ObjectRef(fd) => match get_field(&fd, "fd") {
+            Value::Integer(fd) if fd >= 0 => Some(fd),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+// Writes to System.out and System.err go to the console of the class table, like those of the
+// PrintStream of the bootstrap class library.
+fn write_fd(class_table: &ClassTable, stream: &Arc<HeapCell<Object>>, bytes: &[u8]) -> Result<InvokeResult, InterpreterError> {
+    let result = match stream_fd(stream) {
+        Some(STDIN) | None => return throw_new(class_table, "java/io/IOException", Some("Stream Closed")),
+        Some(STDERR) => class_table.console.err.lock().unwrap().write_all(bytes),
+        Some(_) => class_table.console.out.lock().unwrap().write_all(bytes)
+    };
+    match result {
+        Ok(()) => Ok(InvokeResult::Void),
+        Err(e) => throw_new(class_table, "java/io/IOException", Some(&e.to_string()))
+    }
+}
+
+fn file_output_stream_write_bytes(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let offset = int_arg(&arguments, 2)?;
+    let length = int_arg(&arguments, 3)?;
+    let bytes = match arguments[1] {
+        Value::ArrayRef(ref array) => {
+            let array = array.borrow();
+            let bytes = array.as_bytes().ok_or(InterpreterError::UnexpectedOperand)?;
+            if offset < 0 || length < 0 || offset as usize + length as usize > bytes.len() {
+                return throw_new(class_table, "java/lang/IndexOutOfBoundsException", None);
+            }
+            bytes[offset as usize..(offset + length) as usize].to_vec()
+        },
+        _ => return throw_new(class_table, "java/lang/NullPointerException", None)
+    };
+    write_fd(class_table, &this, &bytes)
+}
+
+fn file_output_stream_write(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let byte = int_arg(&arguments, 1)? as u8;
+    write_fd(class_table, &this, &[byte])
+}
+
+// Reads from standard input, blocking outside of the heap so that the collector can run.
+fn file_input_stream_read_bytes(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let offset = int_arg(&arguments, 2)?;
+    let length = int_arg(&arguments, 3)?;
+    let array = match arguments[1] {
+        Value::ArrayRef(ref array) => array.clone(),
+        _ => return throw_new(class_table, "java/lang/NullPointerException", None)
+    };
+    if offset < 0 || length < 0 || offset as usize + length as usize > array.borrow().len() {
+        return throw_new(class_table, "java/lang/IndexOutOfBoundsException", None);
+    }
+    if stream_fd(&this) != Some(STDIN) {
+        return throw_new(class_table, "java/io/IOException", Some("Stream Closed"));
+    }
+
+    let mut buffer = vec![0; length as usize];
+    let read = class_table.heap.blocking(|| io::stdin().lock().read(&mut buffer));
+    match read {
+        Ok(0) if length > 0 => Ok(InvokeResult::Value(Value::Integer(-1))),
+        Ok(read) => {
+            let mut array = array.borrow_mut();
+            let bytes = array.as_bytes_mut().ok_or(InterpreterError::UnexpectedOperand)?;
+            bytes[offset as usize..offset as usize + read].copy_from_slice(&buffer[..read]);
+            Ok(InvokeResult::Value(Value::Integer(read as i32)))
+        },
+        Err(e) => throw_new(class_table, "java/io/IOException", Some(&e.to_string()))
+    }
+}
+
+// What standard input has buffered is not known, which 0 stands for.
+fn file_input_stream_available0(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Integer(0)))
+}
+
+// Resolves . and .. in an absolute path, and symbolic links of the part that exists, like the
+// canonicalize of the JDK.
+fn canonicalize(path: &Path) -> PathBuf {
+    if let Ok(canonical) = fs::canonicalize(path) {
+        return canonical;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => canonicalize(parent).join(name),
+        _ => path.components().fold(PathBuf::new(), |mut canonical, component| {
+            match component {
+                Component::ParentDir => { canonical.pop(); },
+                Component::CurDir => {},
+                component => canonical.push(component)
+            }
+            canonical
+        })
+    }
+}
+
+fn unix_file_system_canonicalize0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let path = match string_arg(&arguments, 1)? {
+        Some(path) => String::from_utf16_lossy(&path),
+        None => return throw_new(class_table, "java/lang/NullPointerException", None)
+    };
+    return_string(class_table, &canonicalize(Path::new(&path)).display().to_string())
+}
+
+fn unix_file_system_get_boolean_attributes0(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let file = object_arg(&arguments, 1)?;
+    let path = match get_field(&file, "path") {
+        Value::ObjectRef(path) => string::to_rust_string(&path.borrow()),
+        _ => return Err(InterpreterError::UnexpectedOperand)
+    };
+    let attributes = match fs::metadata(path) {
+        Ok(metadata) if metadata.is_dir() => BA_EXISTS | BA_DIRECTORY,
+        Ok(metadata) if metadata.is_file() => BA_EXISTS | BA_REGULAR,
+        Ok(_) => BA_EXISTS,
+        Err(_) => 0
+    };
+    Ok(InvokeResult::Value(Value::Integer(attributes)))
+}
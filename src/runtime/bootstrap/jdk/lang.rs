@@ -0,0 +1,497 @@
+use runtime::{Value, ArrayElements, Elements};
+use runtime::class::ClassTable;
+use runtime::interpreter::{self, InvokeResult, InterpreterError};
+use runtime::bootstrap::{lang, reflect, throwable, object_arg, int_arg, long_arg, float_arg, double_arg, string_arg, throw_new,
+                         return_string};
+use runtime::bootstrap::jdk::{Natives, no_op, get_field, put_field, boolean_result, null_result};
+use std::thread;
+
+pub static NATIVES: Natives = &[
+    ("java/lang/Object", "hashCode", "()I", lang::object_hash_code),
+    ("java/lang/Object", "getClass", "()Ljava/lang/Class;", lang::object_get_class),
+    ("java/lang/Object", "clone", "()Ljava/lang/Object;", lang::object_clone),
+    ("java/lang/Object", "wait", "(J)V", lang::object_wait),
+    ("java/lang/Object", "notify", "()V", lang::object_notify),
+    ("java/lang/Object", "notifyAll", "()V", lang::object_notify_all),
+
+    ("java/lang/Class", "getPrimitiveClass", "(Ljava/lang/String;)Ljava/lang/Class;", class_get_primitive_class),
+    ("java/lang/Class", "desiredAssertionStatus0", "(Ljava/lang/Class;)Z", class_desired_assertion_status0),
+    ("java/lang/Class", "forName0", "(Ljava/lang/String;ZLjava/lang/ClassLoader;Ljava/lang/Class;)Ljava/lang/Class;", class_for_name0),
+    ("java/lang/Class", "initClassName", "()Ljava/lang/String;", class_init_class_name),
+    ("java/lang/Class", "isArray", "()Z", reflect::class_is_array),
+    ("java/lang/Class", "isPrimitive", "()Z", reflect::class_is_primitive),
+    ("java/lang/Class", "isInterface", "()Z", reflect::class_is_interface),
+    ("java/lang/Class", "isInstance", "(Ljava/lang/Object;)Z", reflect::class_is_instance),
+    ("java/lang/Class", "isAssignableFrom", "(Ljava/lang/Class;)Z", reflect::class_is_assignable_from),
+    ("java/lang/Class", "isHidden", "()Z", class_is_hidden),
+    ("java/lang/Class", "getSuperclass", "()Ljava/lang/Class;", reflect::class_get_superclass),
+    ("java/lang/Class", "getInterfaces0", "()[Ljava/lang/Class;", reflect::class_get_interfaces),
+    ("java/lang/Class", "getModifiers", "()I", reflect::class_get_modifiers),
+
+    ("java/lang/System", "setIn0", "(Ljava/io/InputStream;)V", system_set_in0),
+    ("java/lang/System", "setOut0", "(Ljava/io/PrintStream;)V", system_set_out0),
+    ("java/lang/System", "setErr0", "(Ljava/io/PrintStream;)V", system_set_err0),
+    ("java/lang/System", "currentTimeMillis", "()J", lang::system_current_time_millis),
+    ("java/lang/System", "nanoTime", "()J", lang::system_nano_time),
+    ("java/lang/System", "arraycopy", "(Ljava/lang/Object;ILjava/lang/Object;II)V", lang::system_arraycopy),
+    ("java/lang/System", "identityHashCode", "(Ljava/lang/Object;)I", lang::system_identity_hash_code),
+    ("java/lang/System", "mapLibraryName", "(Ljava/lang/String;)Ljava/lang/String;", system_map_library_name),
+
+    ("java/lang/Runtime", "availableProcessors", "()I", runtime_available_processors),
+    ("java/lang/Runtime", "freeMemory", "()J", runtime_free_memory),
+    ("java/lang/Runtime", "totalMemory", "()J", runtime_max_memory),
+    ("java/lang/Runtime", "maxMemory", "()J", runtime_max_memory),
+    ("java/lang/Runtime", "gc", "()V", lang::system_gc),
+
+    ("java/lang/Shutdown", "beforeHalt", "()V", shutdown_before_halt),
+    ("java/lang/Shutdown", "halt0", "(I)V", shutdown_halt0),
+
+    ("java/lang/String", "intern", "()Ljava/lang/String;", string_intern),
+    ("java/lang/StringUTF16", "isBigEndian", "()Z", string_utf16_is_big_endian),
+
+    ("java/lang/Float", "floatToRawIntBits", "(F)I", float_float_to_raw_int_bits),
+    ("java/lang/Float", "intBitsToFloat", "(I)F", float_int_bits_to_float),
+    ("java/lang/Double", "doubleToRawLongBits", "(D)J", double_double_to_raw_long_bits),
+    ("java/lang/Double", "longBitsToDouble", "(J)D", double_long_bits_to_double),
+
+    ("java/lang/StrictMath", "sin", "(D)D", strict_math_sin),
+    ("java/lang/StrictMath", "cos", "(D)D", strict_math_cos),
+    ("java/lang/StrictMath", "tan", "(D)D", strict_math_tan),
+    ("java/lang/StrictMath", "asin", "(D)D", strict_math_asin),
+    ("java/lang/StrictMath", "acos", "(D)D", strict_math_acos),
+    ("java/lang/StrictMath", "atan", "(D)D", strict_math_atan),
+    ("java/lang/StrictMath", "log", "(D)D", strict_math_log),
+    ("java/lang/StrictMath", "log10", "(D)D", strict_math_log10),
+    ("java/lang/StrictMath", "sqrt", "(D)D", strict_math_sqrt),
+    ("java/lang/StrictMath", "sinh", "(D)D", strict_math_sinh),
+    ("java/lang/StrictMath", "cosh", "(D)D", strict_math_cosh),
+    ("java/lang/StrictMath", "tanh", "(D)D", strict_math_tanh),
+    ("java/lang/StrictMath", "expm1", "(D)D", strict_math_expm1),
+    ("java/lang/StrictMath", "log1p", "(D)D", strict_math_log1p),
+    ("java/lang/StrictMath", "IEEEremainder", "(DD)D", strict_math_ieee_remainder),
+    ("java/lang/StrictMath", "atan2", "(DD)D", strict_math_atan2),
+
+    ("java/lang/Throwable", "fillInStackTrace", "(I)Ljava/lang/Throwable;", throwable_fill_in_stack_trace),
+    ("java/lang/StackTraceElement", "initStackTraceElements", "([Ljava/lang/StackTraceElement;Ljava/lang/Throwable;)V",
+     stack_trace_element_init_stack_trace_elements),
+    ("java/lang/NullPointerException", "getExtendedNPEMessage", "()Ljava/lang/String;", null_pointer_exception_get_extended_npe_message),
+
+    ("java/lang/ref/Reference", "getAndClearReferencePendingList", "()Ljava/lang/ref/Reference;", reference_get_and_clear_reference_pending_list),
+    ("java/lang/ref/Reference", "hasReferencePendingList", "()Z", reference_has_reference_pending_list),
+    ("java/lang/ref/Reference", "waitForReferencePendingList", "()V", reference_wait_for_reference_pending_list),
+    ("java/lang/ref/Reference", "refersTo0", "(Ljava/lang/Object;)Z", reference_refers_to0),
+    ("java/lang/ref/Reference", "clear0", "()V", reference_clear0),
+    ("java/lang/ref/PhantomReference", "refersTo0", "(Ljava/lang/Object;)Z", reference_refers_to0),
+
+    ("java/lang/ClassLoader", "findBootstrapClass", "(Ljava/lang/String;)Ljava/lang/Class;", class_loader_find_bootstrap_class),
+    ("java/lang/ClassLoader", "findLoadedClass0", "(Ljava/lang/String;)Ljava/lang/Class;", class_loader_find_loaded_class0),
+    ("java/lang/ClassLoader", "defineClass1",
+     "(Ljava/lang/ClassLoader;Ljava/lang/String;[BIILjava/security/ProtectionDomain;Ljava/lang/String;)Ljava/lang/Class;",
+     class_loader_define_class1),
+    ("java/lang/ClassLoader", "defineClass0",
+     "(Ljava/lang/ClassLoader;Ljava/lang/Class;Ljava/lang/String;[BIILjava/security/ProtectionDomain;ZILjava/lang/Object;)Ljava/lang/Class;",
+     class_loader_define_class0),
+
+    // The virtual machine keeps no module graph: readability and exports are only checked by the
+    // Module objects themselves.
+    ("java/lang/Module", "defineModule0", "(Ljava/lang/Module;ZLjava/lang/String;Ljava/lang/String;[Ljava/lang/Object;)V", no_op),
+    ("java/lang/Module", "addReads0", "(Ljava/lang/Module;Ljava/lang/Module;)V", no_op),
+    ("java/lang/Module", "addExports0", "(Ljava/lang/Module;Ljava/lang/String;Ljava/lang/Module;)V", no_op),
+    ("java/lang/Module", "addExportsToAll0", "(Ljava/lang/Module;Ljava/lang/String;)V", no_op),
+    ("java/lang/Module", "addExportsToAllUnnamed0", "(Ljava/lang/Module;Ljava/lang/String;)V", no_op)
+];
+
+// java.lang.Class
+
+fn class_get_primitive_class(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let name = string_arg(&arguments, 0)?.ok_or(InterpreterError::UnexpectedOperand)?;
+    let mirror = class_table.get_mirror(&String::from_utf16_lossy(&name));
+    Ok(InvokeResult::Value(Value::ObjectRef(mirror)))
+}
+
+// Assertions are disabled, as they are without -ea.
+fn class_desired_assertion_status0(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    boolean_result(false)
+}
+
+// Finds a class by its binary name, e.g. java.lang.String or [Ljava.lang.String;. All classes
+// come from the class path, so the class loader makes no difference.
+fn class_for_name0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let name = match string_arg(&arguments, 0)? {
+        Some(chars) => String::from_utf16_lossy(&chars),
+        None => return throw_new(class_table, "java/lang/NullPointerException", None)
+    };
+    let initialize = int_arg(&arguments, 1)? != 0;
+
+    let internal_name = name.replace('.', "/");
+    let element_name = internal_name.trim_start_matches('[');
+    let element_name = match element_name.strip_prefix('L').and_then(|name| name.strip_suffix(';')) {
+        Some(class_name) if internal_name.starts_with('[') => class_name,
+        _ if internal_name.starts_with('[') && element_name.len() == 1 && "ZBCSIJFD".contains(element_name) => {
+            return Ok(InvokeResult::Value(Value::ObjectRef(class_table.get_mirror(&internal_name))));
+        },
+        _ if internal_name.starts_with('[') => return throw_new(class_table, "java/lang/ClassNotFoundException", Some(&name)),
+        _ => element_name
+    };
+    let class = match class_table.find_class(element_name) {
+        Ok(Some(class)) if !name.contains('/') => class,
+        _ => return throw_new(class_table, "java/lang/ClassNotFoundException", Some(&name))
+    };
+    if initialize && !internal_name.starts_with('[') {
+        if let Some(exception) = interpreter::initialize_class(&class, class_table)? {
+            return Ok(InvokeResult::Exception(exception));
+        }
+    }
+    Ok(InvokeResult::Value(Value::ObjectRef(class_table.get_mirror(&internal_name))))
+}
+
+// Mirrors are created with their name, so there is nothing left to initialize.
+fn class_init_class_name(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    Ok(InvokeResult::Value(get_field(&this, "name")))
+}
+
+fn class_is_hidden(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let class = reflect::mirror_class(class_table, &this)?;
+    boolean_result(class.is_some_and(|class| class.is_hidden()))
+}
+
+// java.lang.System
+
+// The streams are final fields, which only the VM may change.
+fn set_stream(class_table: &ClassTable, name: &str, stream: Value) -> Result<InvokeResult, InterpreterError> {
+    let system = interpreter::resolve_class("java/lang/System", class_table)?;
+    system.put_static(name, stream);
+    Ok(InvokeResult::Void)
+}
+
+fn system_set_in0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    set_stream(class_table, "in", arguments[0].clone())
+}
+
+fn system_set_out0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    set_stream(class_table, "out", arguments[0].clone())
+}
+
+fn system_set_err0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    set_stream(class_table, "err", arguments[0].clone())
+}
+
+fn system_map_library_name(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    match string_arg(&arguments, 0)? {
+        Some(name) => return_string(class_table, &format!("lib{}.so", String::from_utf16_lossy(&name))),
+        None => throw_new(class_table, "java/lang/NullPointerException", None)
+    }
+}
+
+// java.lang.Runtime
+
+fn runtime_available_processors(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let processors = thread::available_parallelism().map(|processors| processors.get()).unwrap_or(1);
+    Ok(InvokeResult::Value(Value::Integer(processors as i32)))
+}
+
+// The heap is reserved up front, so its total size is its maximum size.
+fn runtime_max_memory(class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Long(class_table.heap.usage().max as i64)))
+}
+
+fn runtime_free_memory(class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let usage = class_table.heap.usage();
+    Ok(InvokeResult::Value(Value::Long(usage.max.saturating_sub(usage.used) as i64)))
+}
+
+// java.lang.Shutdown
+
+fn shutdown_before_halt(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Void)
+}
+
+// Shutdown hooks have run by now. Like System.exit of the bootstrap class library, this unwinds
+// to the launcher, which exits the process.
+fn shutdown_halt0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let status = int_arg(&arguments, 0)?;
+    class_table.console.flush();
+    Err(InterpreterError::Exit(status))
+}
+
+// java.lang.String
+
+fn string_intern(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    Ok(InvokeResult::Value(Value::ObjectRef(class_table.intern(&this))))
+}
+
+// See string::new_string.
+fn string_utf16_is_big_endian(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    boolean_result(false)
+}
+
+// java.lang.Float and java.lang.Double
+
+fn float_float_to_raw_int_bits(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Integer(float_arg(&arguments, 0)?.to_bits() as i32)))
+}
+
+fn float_int_bits_to_float(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Float(f32::from_bits(int_arg(&arguments, 0)? as u32))))
+}
+
+fn double_double_to_raw_long_bits(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Long(double_arg(&arguments, 0)?.to_bits() as i64)))
+}
+
+fn double_long_bits_to_double(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Double(f64::from_bits(long_arg(&arguments, 0)? as u64))))
+}
+
+// java.lang.StrictMath
+
+fn unary(arguments: &[Value], f: fn(f64) -> f64) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Double(f(double_arg(arguments, 0)?))))
+}
+
+fn strict_math_sin(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unary(&arguments, f64::sin)
+}
+
+fn strict_math_cos(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unary(&arguments, f64::cos)
+}
+
+fn strict_math_tan(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unary(&arguments, f64::tan)
+}
+
+fn strict_math_asin(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unary(&arguments, f64::asin)
+}
+
+fn strict_math_acos(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unary(&arguments, f64::acos)
+}
+
+fn strict_math_atan(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unary(&arguments, f64::atan)
+}
+
+fn strict_math_log(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unary(&arguments, f64::ln)
+}
+
+fn strict_math_log10(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unary(&arguments, f64::log10)
+}
+
+fn strict_math_sqrt(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unary(&arguments, f64::sqrt)
+}
+
+fn strict_math_sinh(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unary(&arguments, f64::sinh)
+}
+
+fn strict_math_cosh(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unary(&arguments, f64::cosh)
+}
+
+fn strict_math_tanh(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unary(&arguments, f64::tanh)
+}
+
+fn strict_math_expm1(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unary(&arguments, f64::exp_m1)
+}
+
+fn strict_math_log1p(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unary(&arguments, f64::ln_1p)
+}
+
+// The remainder of x / y with the quotient rounded to the nearest integer, ties to even, as IEEE
+// 754 defines it.
+fn strict_math_ieee_remainder(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let (x, y) = (double_arg(&arguments, 0)?, double_arg(&arguments, 1)?);
+    let remainder = if x.is_infinite() || y == 0.0 || x.is_nan() || y.is_nan() {
+        f64::NAN
+    } else if y.is_infinite() {
+        x
+    } else {
+        let quotient = x / y;
+        let mut n = quotient.round();
+        if (quotient - quotient.trunc()).abs() == 0.5 && n % 2.0 != 0.0 {
+            n -= quotient.signum();
+        }
+        let remainder = x - n * y;
+        if remainder == 0.0 { 0.0f64.copysign(x) } else { remainder }
+    };
+    Ok(InvokeResult::Value(Value::Double(remainder)))
+}
+
+fn strict_math_atan2(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let (y, x) = (double_arg(&arguments, 0)?, double_arg(&arguments, 1)?);
+    Ok(InvokeResult::Value(Value::Double(y.atan2(x))))
+}
+
+// java.lang.Throwable and java.lang.StackTraceElement
+
+// The backtrace HotSpot records is opaque to Java code. Here it is the StackTraceElement array
+// itself, which initStackTraceElements copies from once the stack trace is asked for.
+fn throwable_fill_in_stack_trace(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let elements = throwable::current_stack_trace(class_table, &this).iter()
+        .map(|element| throwable::new_stack_trace_element(class_table, element))
+        .collect::<Result<Vec<Value>, InterpreterError>>()?;
+    let depth = elements.len() as i32;
+    let backtrace = class_table.new_array_from("Ljava/lang/StackTraceElement;", ArrayElements::Reference(elements));
+
+    put_field(&this, "backtrace", Value::ArrayRef(backtrace));
+    put_field(&this, "depth", Value::Integer(depth));
+    Ok(InvokeResult::Value(Value::ObjectRef(this)))
+}
+
+const STACK_TRACE_ELEMENT_FIELDS: &[&str] = &["declaringClassObject", "declaringClass", "methodName", "fileName", "lineNumber"];
+
+fn stack_trace_element_init_stack_trace_elements(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let elements = match arguments[0] {
+        Value::ArrayRef(ref elements) => elements.clone(),
+        _ => return Err(InterpreterError::UnexpectedOperand)
+    };
+    let throwable = object_arg(&arguments, 1)?;
+    let backtrace = match get_field(&throwable, "backtrace") {
+        Value::ArrayRef(backtrace) => backtrace,
+        _ => return Ok(InvokeResult::Void)
+    };
+
+    let elements = elements.borrow();
+    let backtrace = backtrace.borrow();
+    if let (Elements::Reference(elements), Elements::Reference(backtrace)) = (elements.elements(), backtrace.elements()) {
+        for (element, recorded) in elements.iter().zip(backtrace.iter()) {
+            if let (Value::ObjectRef(element), Value::ObjectRef(recorded)) = (element, recorded) {
+                for &name in STACK_TRACE_ELEMENT_FIELDS.iter() {
+                    put_field(element, name, get_field(recorded, name));
+                }
+            }
+        }
+    }
+    Ok(InvokeResult::Void)
+}
+
+// Without the bytecode analysis of JEP 358, NullPointerExceptions have the plain message.
+fn null_pointer_exception_get_extended_npe_message(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    null_result()
+}
+
+// java.lang.ref.Reference
+
+// The collector treats references as strong, so none are ever pending.
+fn reference_get_and_clear_reference_pending_list(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    null_result()
+}
+
+fn reference_has_reference_pending_list(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    boolean_result(false)
+}
+
+// Blocks the Reference Handler thread for good, without holding up collections.
+fn reference_wait_for_reference_pending_list(class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    class_table.heap.blocking(|| loop {
+        thread::park();
+    })
+}
+
+fn reference_refers_to0(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    boolean_result(get_field(&this, "referent").same_reference(&arguments[1]))
+}
+
+fn reference_clear0(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    put_field(&this, "referent", Value::Null);
+    Ok(InvokeResult::Void)
+}
+
+// java.lang.ClassLoader
+
+// Looks a class up by its binary name on the class path, which all classes are loaded from.
+fn class_loader_find_bootstrap_class(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let name = string_arg(&arguments, 0)?.ok_or(InterpreterError::UnexpectedOperand)?;
+    match class_table.find_class(&String::from_utf16_lossy(&name).replace('.', "/")) {
+        Ok(Some(class)) => Ok(InvokeResult::Value(Value::ObjectRef(class_table.get_mirror(&class.class_name)))),
+        _ => null_result()
+    }
+}
+
+fn class_loader_find_loaded_class0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let name = match string_arg(&arguments, 1)? {
+        Some(name) => String::from_utf16_lossy(&name).replace('.', "/"),
+        None => return null_result()
+    };
+    match class_table.get_class(&name) {
+        Some(class) => Ok(InvokeResult::Value(Value::ObjectRef(class_table.get_mirror(&class.class_name)))),
+        None => null_result()
+    }
+}
+
+// Defines a class from the bytes of its class file, as Proxy does for the classes it generates.
+// Like all others, the class is loaded by the boot loader whichever loader defines it.
+fn class_loader_define_class1(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let name = string_arg(&arguments, 1)?.map(|name| String::from_utf16_lossy(&name).replace('.', "/"));
+    let bytes = match class_bytes(class_table, &arguments[2], int_arg(&arguments, 3)?, int_arg(&arguments, 4)?)? {
+        Ok(bytes) => bytes,
+        Err(exception) => return Ok(exception)
+    };
+    let source = string_arg(&arguments, 6)?.map_or_else(|| String::from("__JVM_DefineClass__"), |source| String::from_utf16_lossy(&source));
+    match class_table.define_class_bytes(name.as_deref(), &bytes, &source) {
+        Ok(class) => Ok(InvokeResult::Value(Value::ObjectRef(class_table.get_mirror(&class.class_name)))),
+        Err(message) => throw_new(class_table, "java/lang/ClassFormatError", Some(&message))
+    }
+}
+
+// Defines a class for Lookup.defineClass and Lookup.defineHiddenClass, which java.lang.invoke
+// uses for the classes it spins. The class data is kept in the mirror of the class, where
+// MethodHandles.classData finds it.
+fn class_loader_define_class0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    const HIDDEN_CLASS: i32 = 0x2;
+
+    let name = string_arg(&arguments, 2)?.map(|name| String::from_utf16_lossy(&name).replace('.', "/"));
+    let bytes = match class_bytes(class_table, &arguments[3], int_arg(&arguments, 4)?, int_arg(&arguments, 5)?)? {
+        Ok(bytes) => bytes,
+        Err(exception) => return Ok(exception)
+    };
+    let initialize = int_arg(&arguments, 7)? != 0;
+    let flags = int_arg(&arguments, 8)?;
+    let defined = if flags & HIDDEN_CLASS != 0 {
+        class_table.define_hidden_class(&bytes, "__JVM_LookupDefineClass__")
+    } else {
+        class_table.define_class_bytes(name.as_deref(), &bytes, "__JVM_LookupDefineClass__")
+    };
+    let class = match defined {
+        Ok(class) => class,
+        Err(message) => return throw_new(class_table, "java/lang/ClassFormatError", Some(&message))
+    };
+    let mirror = class_table.get_mirror(&class.class_name);
+    put_field(&mirror, "classData", arguments[9].clone());
+    if initialize {
+        if let Some(exception) = interpreter::initialize_class(&class, class_table)? {
+            return Ok(InvokeResult::Exception(exception));
+        }
+    }
+    Ok(InvokeResult::Value(Value::ObjectRef(mirror)))
+}
+
+// The bytes of a class file, from length bytes of an array starting at offset. The inner error
+// is the exception thrown if they are out of its bounds.
+fn class_bytes(class_table: &ClassTable, array: &Value, offset: i32, length: i32) -> Result<Result<Vec<u8>, InvokeResult>, InterpreterError> {
+    match *array {
+        Value::ArrayRef(ref array) => {
+            let array = array.borrow();
+            let bytes = array.as_bytes().ok_or(InterpreterError::UnexpectedOperand)?;
+            if offset < 0 || length < 0 || offset as usize + length as usize > bytes.len() {
+                return throw_new(class_table, "java/lang/ArrayIndexOutOfBoundsException", None).map(Err);
+            }
+            Ok(Ok(bytes[offset as usize..(offset + length) as usize].to_vec()))
+        },
+        _ => throw_new(class_table, "java/lang/NullPointerException", None).map(Err)
+    }
+}
@@ -0,0 +1,877 @@
+use runtime::{Value, ArrayElements, Elements, HeapCell, Object, Array, primitive_size};
+use runtime::class::{ClassTable, RuntimeClass, ClassState};
+use runtime::interpreter::{self, InvokeResult, InterpreterError};
+use runtime::bootstrap::{reflect, object_arg, int_arg, long_arg, string_arg, throw_new, new_string, encode};
+use runtime::bootstrap::jdk::{Natives, no_op, get_field, boolean_result, null_result};
+use runtime::string;
+use std::alloc::{self, Layout};
+use std::env;
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::ptr;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const UNSAFE: &str = "jdk/internal/misc/Unsafe";
+
+pub static NATIVES: Natives = &[
+    (UNSAFE, "arrayBaseOffset0", "(Ljava/lang/Class;)I", unsafe_array_base_offset0),
+    (UNSAFE, "arrayIndexScale0", "(Ljava/lang/Class;)I", unsafe_array_index_scale0),
+    (UNSAFE, "objectFieldOffset0", "(Ljava/lang/reflect/Field;)J", unsafe_object_field_offset0),
+    (UNSAFE, "objectFieldOffset1", "(Ljava/lang/Class;Ljava/lang/String;)J", unsafe_object_field_offset1),
+    (UNSAFE, "staticFieldOffset0", "(Ljava/lang/reflect/Field;)J", unsafe_static_field_offset0),
+    (UNSAFE, "staticFieldBase0", "(Ljava/lang/reflect/Field;)Ljava/lang/Object;", unsafe_static_field_base0),
+
+    (UNSAFE, "getBoolean", "(Ljava/lang/Object;J)Z", unsafe_get_boolean),
+    (UNSAFE, "getByte", "(Ljava/lang/Object;J)B", unsafe_get_byte),
+    (UNSAFE, "getShort", "(Ljava/lang/Object;J)S", unsafe_get_short),
+    (UNSAFE, "getChar", "(Ljava/lang/Object;J)C", unsafe_get_char),
+    (UNSAFE, "getInt", "(Ljava/lang/Object;J)I", unsafe_get_int),
+    (UNSAFE, "getLong", "(Ljava/lang/Object;J)J", unsafe_get_long),
+    (UNSAFE, "getFloat", "(Ljava/lang/Object;J)F", unsafe_get_float),
+    (UNSAFE, "getDouble", "(Ljava/lang/Object;J)D", unsafe_get_double),
+    (UNSAFE, "getReference", "(Ljava/lang/Object;J)Ljava/lang/Object;", unsafe_get_reference),
+    (UNSAFE, "getBooleanVolatile", "(Ljava/lang/Object;J)Z", unsafe_get_boolean),
+    (UNSAFE, "getByteVolatile", "(Ljava/lang/Object;J)B", unsafe_get_byte),
+    (UNSAFE, "getShortVolatile", "(Ljava/lang/Object;J)S", unsafe_get_short),
+    (UNSAFE, "getCharVolatile", "(Ljava/lang/Object;J)C", unsafe_get_char),
+    (UNSAFE, "getIntVolatile", "(Ljava/lang/Object;J)I", unsafe_get_int),
+    (UNSAFE, "getLongVolatile", "(Ljava/lang/Object;J)J", unsafe_get_long),
+    (UNSAFE, "getFloatVolatile", "(Ljava/lang/Object;J)F", unsafe_get_float),
+    (UNSAFE, "getDoubleVolatile", "(Ljava/lang/Object;J)D", unsafe_get_double),
+    (UNSAFE, "getReferenceVolatile", "(Ljava/lang/Object;J)Ljava/lang/Object;", unsafe_get_reference),
+
+    (UNSAFE, "putBoolean", "(Ljava/lang/Object;JZ)V", unsafe_put_boolean),
+    (UNSAFE, "putByte", "(Ljava/lang/Object;JB)V", unsafe_put_byte),
+    (UNSAFE, "putShort", "(Ljava/lang/Object;JS)V", unsafe_put_short),
+    (UNSAFE, "putChar", "(Ljava/lang/Object;JC)V", unsafe_put_char),
+    (UNSAFE, "putInt", "(Ljava/lang/Object;JI)V", unsafe_put_int),
+    (UNSAFE, "putLong", "(Ljava/lang/Object;JJ)V", unsafe_put_long),
+    (UNSAFE, "putFloat", "(Ljava/lang/Object;JF)V", unsafe_put_float),
+    (UNSAFE, "putDouble", "(Ljava/lang/Object;JD)V", unsafe_put_double),
+    (UNSAFE, "putReference", "(Ljava/lang/Object;JLjava/lang/Object;)V", unsafe_put_reference),
+    (UNSAFE, "putBooleanVolatile", "(Ljava/lang/Object;JZ)V", unsafe_put_boolean),
+    (UNSAFE, "putByteVolatile", "(Ljava/lang/Object;JB)V", unsafe_put_byte),
+    (UNSAFE, "putShortVolatile", "(Ljava/lang/Object;JS)V", unsafe_put_short),
+    (UNSAFE, "putCharVolatile", "(Ljava/lang/Object;JC)V", unsafe_put_char),
+    (UNSAFE, "putIntVolatile", "(Ljava/lang/Object;JI)V", unsafe_put_int),
+    (UNSAFE, "putLongVolatile", "(Ljava/lang/Object;JJ)V", unsafe_put_long),
+    (UNSAFE, "putFloatVolatile", "(Ljava/lang/Object;JF)V", unsafe_put_float),
+    (UNSAFE, "putDoubleVolatile", "(Ljava/lang/Object;JD)V", unsafe_put_double),
+    (UNSAFE, "putReferenceVolatile", "(Ljava/lang/Object;JLjava/lang/Object;)V", unsafe_put_reference),
+
+    (UNSAFE, "compareAndSetInt", "(Ljava/lang/Object;JII)Z", unsafe_compare_and_set_int),
+    (UNSAFE, "compareAndSetLong", "(Ljava/lang/Object;JJJ)Z", unsafe_compare_and_set_long),
+    (UNSAFE, "compareAndSetReference", "(Ljava/lang/Object;JLjava/lang/Object;Ljava/lang/Object;)Z", unsafe_compare_and_set_reference),
+    (UNSAFE, "compareAndExchangeInt", "(Ljava/lang/Object;JII)I", unsafe_compare_and_exchange_int),
+    (UNSAFE, "compareAndExchangeLong", "(Ljava/lang/Object;JJJ)J", unsafe_compare_and_exchange_long),
+    (UNSAFE, "compareAndExchangeReference", "(Ljava/lang/Object;JLjava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object;",
+     unsafe_compare_and_exchange_reference),
+
+    (UNSAFE, "allocateMemory0", "(J)J", unsafe_allocate_memory0),
+    (UNSAFE, "reallocateMemory0", "(JJ)J", unsafe_reallocate_memory0),
+    (UNSAFE, "freeMemory0", "(J)V", unsafe_free_memory0),
+    (UNSAFE, "setMemory0", "(Ljava/lang/Object;JJB)V", unsafe_set_memory0),
+    (UNSAFE, "copyMemory0", "(Ljava/lang/Object;JLjava/lang/Object;JJ)V", unsafe_copy_memory0),
+
+    (UNSAFE, "allocateInstance", "(Ljava/lang/Class;)Ljava/lang/Object;", unsafe_allocate_instance),
+    (UNSAFE, "ensureClassInitialized0", "(Ljava/lang/Class;)V", unsafe_ensure_class_initialized0),
+    (UNSAFE, "shouldBeInitialized0", "(Ljava/lang/Class;)Z", unsafe_should_be_initialized0),
+    (UNSAFE, "throwException", "(Ljava/lang/Throwable;)V", unsafe_throw_exception),
+    (UNSAFE, "fullFence", "()V", no_op),
+    (UNSAFE, "loadFence", "()V", no_op),
+    (UNSAFE, "storeFence", "()V", no_op),
+    (UNSAFE, "getLoadAverage0", "([DI)I", unsafe_get_load_average0),
+
+    ("jdk/internal/misc/CDS", "isDumpingClassList0", "()Z", returns_false),
+    ("jdk/internal/misc/CDS", "isDumpingArchive0", "()Z", returns_false),
+    ("jdk/internal/misc/CDS", "isSharingEnabled0", "()Z", returns_false),
+    ("jdk/internal/misc/CDS", "getRandomSeedForDumping", "()J", cds_get_random_seed_for_dumping),
+    ("jdk/internal/misc/CDS", "initializeFromArchive", "(Ljava/lang/Class;)V", no_op),
+    ("jdk/internal/misc/CDS", "defineArchivedModules", "(Ljava/lang/ClassLoader;Ljava/lang/ClassLoader;)V", no_op),
+    ("jdk/internal/misc/CDS", "logLambdaFormInvoker", "(Ljava/lang/String;)V", no_op),
+
+    ("jdk/internal/loader/BootLoader", "setBootLoaderUnnamedModule0", "(Ljava/lang/Module;)V", boot_loader_set_boot_loader_unnamed_module0),
+
+    ("jdk/internal/misc/VM", "initialize", "()V", no_op),
+    ("jdk/internal/misc/VM", "latestUserDefinedLoader0", "()Ljava/lang/ClassLoader;", returns_null),
+    ("jdk/internal/misc/VM", "getuid", "()J", vm_getuid),
+    ("jdk/internal/misc/VM", "geteuid", "()J", vm_getuid),
+    ("jdk/internal/misc/VM", "getgid", "()J", vm_getgid),
+    ("jdk/internal/misc/VM", "getegid", "()J", vm_getgid),
+    ("jdk/internal/misc/VM", "getNanoTimeAdjustment", "(J)J", vm_get_nano_time_adjustment),
+    ("jdk/internal/misc/VM", "getRuntimeArguments", "()[Ljava/lang/String;", vm_get_runtime_arguments),
+
+    ("jdk/internal/misc/Signal", "findSignal0", "(Ljava/lang/String;)I", signal_find_signal0),
+    ("jdk/internal/misc/Signal", "handle0", "(IJ)J", signal_handle0),
+
+    ("jdk/internal/util/SystemProps$Raw", "vmProperties", "()[Ljava/lang/String;", system_props_raw_vm_properties),
+    ("jdk/internal/util/SystemProps$Raw", "platformProperties", "()[Ljava/lang/String;", system_props_raw_platform_properties),
+
+    ("java/lang/ProcessEnvironment", "environ", "()[[B", process_environment_environ),
+
+    ("java/util/concurrent/atomic/AtomicLong", "VMSupportsCS8", "()Z", returns_true),
+
+    ("java/security/AccessController", "getStackAccessControlContext", "()Ljava/security/AccessControlContext;", returns_null),
+    ("java/security/AccessController", "getInheritedAccessControlContext", "()Ljava/security/AccessControlContext;", returns_null),
+    ("java/security/AccessController", "ensureMaterializedForStackWalk", "(Ljava/lang/Object;)V", no_op),
+    ("java/security/AccessController", "getProtectionDomain", "(Ljava/lang/Class;)Ljava/security/ProtectionDomain;", returns_null)
+];
+
+fn returns_false(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    boolean_result(false)
+}
+
+fn returns_true(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    boolean_result(true)
+}
+
+fn returns_null(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    null_result()
+}
+
+// jdk.internal.misc.Unsafe
+//
+// Offsets name fields and array components rather than addresses. The offset of an instance
+// field is its position in the object, and that of a static field is its index among the fields
+// of the class plus STATIC_FIELD_OFFSET, with the mirror of the class as the base. Arrays are laid
+// out with the base offset and index scales HotSpot has with compressed references. A null base
+// means the offset is an address of memory Unsafe allocated.
+
+pub const STATIC_FIELD_OFFSET: i64 = 1 << 32;
+const ARRAY_BASE_OFFSET: i64 = 16;
+const REFERENCE_INDEX_SCALE: i64 = 4;
+
+// The type Unsafe accesses memory as, given by the name of the method.
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Boolean,
+    Byte,
+    Short,
+    Char,
+    Int,
+    Long,
+    Float,
+    Double,
+    Reference
+}
+
+impl Kind {
+
+    fn descriptor(self) -> &'static str {
+        match self {
+            Kind::Boolean => "Z",
+            Kind::Byte => "B",
+            Kind::Short => "S",
+            Kind::Char => "C",
+            Kind::Int => "I",
+            Kind::Long => "J",
+            Kind::Float => "F",
+            Kind::Double => "D",
+            Kind::Reference => "Ljava/lang/Object;"
+        }
+    }
+
+}
+
+// The bits of a primitive value, sign extended.
+fn bits(value: &Value) -> i64 {
+    match *value {
+        Value::Integer(i) => i as i64,
+        Value::Long(l) => l,
+        Value::Float(f) => f.to_bits() as i32 as i64,
+        Value::Double(d) => d.to_bits() as i64,
+        Value::Short(s) => s as i64,
+        Value::Byte(b) => b as i64,
+        Value::Character(c) => c as i64,
+        _ => 0
+    }
+}
+
+// The value of a field, component or return value with the given descriptor that has the bits,
+// truncated to its size.
+fn from_bits(bits: i64, descriptor: &str) -> Value {
+    match descriptor {
+        "Z" => Value::Integer((bits as u8 != 0) as i32),
+        "B" => Value::Integer(bits as i8 as i32),
+        "S" => Value::Integer(bits as i16 as i32),
+        "C" => Value::Integer(bits as u16 as i32),
+        "I" => Value::Integer(bits as i32),
+        "J" => Value::Long(bits),
+        "F" => Value::Float(f32::from_bits(bits as u32)),
+        "D" => Value::Double(f64::from_bits(bits as u64)),
+        _ => Value::Null
+    }
+}
+
+// Converts a value read or to be written to the given type, reinterpreting the bits of
+// primitives as Unsafe does when the type of the method differs from that of the field.
+fn convert(value: Value, descriptor: &str) -> Value {
+    if descriptor.len() == 1 {
+        from_bits(bits(&value), descriptor)
+    } else {
+        value
+    }
+}
+
+fn same(a: &Value, b: &Value, kind: Kind) -> bool {
+    match kind {
+        Kind::Reference => a.same_reference(b),
+        _ => bits(&convert(a.clone(), kind.descriptor())) == bits(&convert(b.clone(), kind.descriptor()))
+    }
+}
+
+enum Location {
+    Field(Arc<HeapCell<Object>>, usize),
+    Static(Arc<RuntimeClass>, String),
+    // An array and the offset of the access from its first component.
+    Component(Arc<HeapCell<Array>>, usize),
+    Address(*mut u8)
+}
+
+// Finds what the base and offset arguments of an access, which start at the given index, refer
+// to. Offsets outside of the object are an error of the caller, which HotSpot does not check.
+fn locate(class_table: &ClassTable, arguments: &[Value], index: usize) -> Result<Location, InterpreterError> {
+    let offset = long_arg(arguments, index + 1)?;
+    match arguments[index] {
+        Value::Null => Ok(Location::Address(offset as usize as *mut u8)),
+        Value::ArrayRef(ref array) if offset >= ARRAY_BASE_OFFSET => Ok(Location::Component(array.clone(), (offset - ARRAY_BASE_OFFSET) as usize)),
+        Value::ObjectRef(ref object) if offset >= STATIC_FIELD_OFFSET => {
+            let class = reflect::mirror_class(class_table, object)?.ok_or(InterpreterError::UnexpectedOperand)?;
+            let name = class.fields.get((offset - STATIC_FIELD_OFFSET) as usize)
+                .map(|field| field.name.clone())
+                .ok_or(InterpreterError::UnexpectedOperand)?;
+            Ok(Location::Static(class, name))
+        },
+        Value::ObjectRef(ref object) if offset >= 0 && (offset as usize) < object.borrow().fields().len() => {
+            Ok(Location::Field(object.clone(), offset as usize))
+        },
+        _ => Err(InterpreterError::UnexpectedOperand)
+    }
+}
+
+fn field_descriptor(object: &Object, position: usize) -> String {
+    object.class().instance_fields[position].descriptor_string.clone()
+}
+
+fn static_descriptor(class: &RuntimeClass, name: &str) -> String {
+    class.fields.iter()
+        .find(|field| field.name == name && field.is_static())
+        .map(|field| field.descriptor_string.clone())
+        .unwrap_or_default()
+}
+
+// Reads a primitive of the given size from the bytes of an array or from memory Unsafe
+// allocated.
+fn read_bits(bytes: &[u8], size: usize) -> i64 {
+    match size {
+        1 => bytes[0] as i8 as i64,
+        2 => i16::from_ne_bytes([bytes[0], bytes[1]]) as i64,
+        4 => i32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as i64,
+        _ => i64::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]])
+    }
+}
+
+fn write_bits(bytes: &mut [u8], size: usize, bits: i64) {
+    match size {
+        1 => bytes[0] = bits as u8,
+        2 => bytes[..2].copy_from_slice(&(bits as i16).to_ne_bytes()),
+        4 => bytes[..4].copy_from_slice(&(bits as i32).to_ne_bytes()),
+        _ => bytes[..8].copy_from_slice(&bits.to_ne_bytes())
+    }
+}
+
+fn kind_size(kind: Kind) -> usize {
+    primitive_size(kind.descriptor()).unwrap_or(REFERENCE_INDEX_SCALE as usize)
+}
+
+// The bytes of the array the access covers, or None if it is out of bounds or the array holds
+// references.
+fn component_bytes(array: &Array, offset: usize, size: usize) -> Option<&[u8]> {
+    array.as_bytes()?.get(offset..offset + size)
+}
+
+fn reference_index(array: &Array, offset: usize) -> Option<usize> {
+    let index = offset / REFERENCE_INDEX_SCALE as usize;
+    if array.as_bytes().is_none() && index < array.len() { Some(index) } else { None }
+}
+
+fn get_component(array: &Array, offset: usize, kind: Kind) -> Result<Value, InterpreterError> {
+    if kind == Kind::Reference {
+        let index = reference_index(array, offset).ok_or(InterpreterError::UnexpectedOperand)?;
+        return Ok(array.get(index));
+    }
+    let size = kind_size(kind);
+    let bytes = component_bytes(array, offset, size).ok_or(InterpreterError::UnexpectedOperand)?;
+    Ok(from_bits(read_bits(bytes, size), kind.descriptor()))
+}
+
+fn put_component(class_table: &ClassTable, array: &mut Array, offset: usize, kind: Kind, value: Value) -> Result<(), InterpreterError> {
+    if kind == Kind::Reference {
+        let index = reference_index(array, offset).ok_or(InterpreterError::UnexpectedOperand)?;
+        array.set(index, value)?;
+        if let Elements::Reference(elements) = array.elements() {
+            class_table.heap.write_barrier(&elements[index]);
+        }
+        return Ok(());
+    }
+    let size = kind_size(kind);
+    let bytes = array.as_bytes_mut()
+        .and_then(|bytes| bytes.get_mut(offset..offset + size))
+        .ok_or(InterpreterError::UnexpectedOperand)?;
+    write_bits(bytes, size, bits(&value));
+    Ok(())
+}
+
+fn get_address(address: *mut u8, kind: Kind) -> Result<Value, InterpreterError> {
+    if kind == Kind::Reference {
+        return Err(InterpreterError::UnexpectedOperand);
+    }
+    let size = kind_size(kind);
+    let bytes = unsafe { std::slice::from_raw_parts(address, size) };
+    Ok(from_bits(read_bits(bytes, size), kind.descriptor()))
+}
+
+fn put_address(address: *mut u8, kind: Kind, value: Value) -> Result<(), InterpreterError> {
+    if kind == Kind::Reference {
+        return Err(InterpreterError::UnexpectedOperand);
+    }
+    let size = kind_size(kind);
+    let bytes = unsafe { std::slice::from_raw_parts_mut(address, size) };
+    write_bits(bytes, size, bits(&value));
+    Ok(())
+}
+
+fn get(location: &Location, kind: Kind) -> Result<Value, InterpreterError> {
+    match *location {
+        Location::Field(ref object, position) => Ok(convert(object.borrow().fields()[position].clone(), kind.descriptor())),
+        Location::Static(ref class, ref name) => {
+            let value = class.get_static(name).ok_or(InterpreterError::UnexpectedOperand)?;
+            Ok(convert(value, kind.descriptor()))
+        },
+        Location::Component(ref array, offset) => get_component(&array.borrow(), offset, kind),
+        Location::Address(address) => get_address(address, kind)
+    }
+}
+
+fn put(class_table: &ClassTable, location: &Location, kind: Kind, value: Value) -> Result<(), InterpreterError> {
+    match *location {
+        Location::Field(ref object, position) => {
+            let mut object = object.borrow_mut();
+            let value = convert(value, &field_descriptor(&object, position));
+            object.fields_mut()[position] = value;
+            class_table.heap.write_barrier(&object.fields()[position]);
+            Ok(())
+        },
+        Location::Static(ref class, ref name) => {
+            let value = convert(value, &static_descriptor(class, name));
+            class.put_static(name, value);
+            Ok(())
+        },
+        Location::Component(ref array, offset) => put_component(class_table, &mut array.borrow_mut(), offset, kind, value),
+        Location::Address(address) => put_address(address, kind, value)
+    }
+}
+
+// Stores the value if the current one is the expected one, in one step under the lock of the
+// object, array or statics, and returns the value found. Memory Unsafe allocated has no lock, as
+// the classes of java.base only use it for buffers.
+fn compare_and_exchange(class_table: &ClassTable, location: &Location, kind: Kind, expected: Value, value: Value) -> Result<Value, InterpreterError> {
+    match *location {
+        Location::Field(ref object, position) => {
+            let mut object = object.borrow_mut();
+            let found = object.fields()[position].clone();
+            if same(&found, &expected, kind) {
+                let value = convert(value, &field_descriptor(&object, position));
+                object.fields_mut()[position] = value;
+                class_table.heap.write_barrier(&object.fields()[position]);
+            }
+            Ok(convert(found, kind.descriptor()))
+        },
+        Location::Static(ref class, ref name) => {
+            let value = convert(value, &static_descriptor(class, name));
+            let found = class.compare_and_exchange_static(name, |found| same(found, &expected, kind), value)
+                .ok_or(InterpreterError::UnexpectedOperand)?;
+            Ok(convert(found, kind.descriptor()))
+        },
+        Location::Component(ref array, offset) => {
+            let mut array = array.borrow_mut();
+            let found = get_component(&array, offset, kind)?;
+            if same(&found, &expected, kind) {
+                put_component(class_table, &mut array, offset, kind, value)?;
+            }
+            Ok(found)
+        },
+        Location::Address(address) => {
+            let found = get_address(address, kind)?;
+            if same(&found, &expected, kind) {
+                put_address(address, kind, value)?;
+            }
+            Ok(found)
+        }
+    }
+}
+
+fn unsafe_get(class_table: &ClassTable, arguments: &[Value], kind: Kind) -> Result<InvokeResult, InterpreterError> {
+    let location = locate(class_table, arguments, 1)?;
+    Ok(InvokeResult::Value(get(&location, kind)?))
+}
+
+fn unsafe_put(class_table: &ClassTable, arguments: &[Value], kind: Kind) -> Result<InvokeResult, InterpreterError> {
+    let location = locate(class_table, arguments, 1)?;
+    put(class_table, &location, kind, arguments[3].clone())?;
+    Ok(InvokeResult::Void)
+}
+
+fn unsafe_compare_and_set(class_table: &ClassTable, arguments: &[Value], kind: Kind) -> Result<InvokeResult, InterpreterError> {
+    let location = locate(class_table, arguments, 1)?;
+    let found = compare_and_exchange(class_table, &location, kind, arguments[3].clone(), arguments[4].clone())?;
+    boolean_result(same(&found, &arguments[3], kind))
+}
+
+fn unsafe_compare_and_exchange(class_table: &ClassTable, arguments: &[Value], kind: Kind) -> Result<InvokeResult, InterpreterError> {
+    let location = locate(class_table, arguments, 1)?;
+    let found = compare_and_exchange(class_table, &location, kind, arguments[3].clone(), arguments[4].clone())?;
+    Ok(InvokeResult::Value(found))
+}
+
+fn unsafe_array_base_offset0(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Integer(ARRAY_BASE_OFFSET as i32)))
+}
+
+fn unsafe_array_index_scale0(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let mirror = object_arg(&arguments, 1)?;
+    let descriptor = reflect::mirror_descriptor(&mirror);
+    let scale = descriptor.strip_prefix('[')
+        .and_then(primitive_size)
+        .unwrap_or(REFERENCE_INDEX_SCALE as usize);
+    Ok(InvokeResult::Value(Value::Integer(scale as i32)))
+}
+
+fn instance_field_offset(class_table: &ClassTable, mirror: &Arc<HeapCell<Object>>, name: &Value) -> Result<InvokeResult, InterpreterError> {
+    let class = reflect::mirror_class(class_table, mirror)?.ok_or(InterpreterError::UnexpectedOperand)?;
+    let name = match *name {
+        Value::ObjectRef(ref name) => string::to_rust_string(&name.borrow()),
+        _ => return throw_new(class_table, "java/lang/NullPointerException", None)
+    };
+    match class.instance_field_position(&name) {
+        Some(position) => Ok(InvokeResult::Value(Value::Long(position as i64))),
+        None => throw_new(class_table, "java/lang/InternalError", Some(&name))
+    }
+}
+
+fn unsafe_object_field_offset0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let field = object_arg(&arguments, 1)?;
+    let mirror = match get_field(&field, "clazz") {
+        Value::ObjectRef(mirror) => mirror,
+        _ => return Err(InterpreterError::UnexpectedOperand)
+    };
+    instance_field_offset(class_table, &mirror, &get_field(&field, "name"))
+}
+
+fn unsafe_object_field_offset1(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let mirror = object_arg(&arguments, 1)?;
+    instance_field_offset(class_table, &mirror, &arguments[2])
+}
+
+fn unsafe_static_field_offset0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let field = object_arg(&arguments, 1)?;
+    let (mirror, name) = match (get_field(&field, "clazz"), get_field(&field, "name")) {
+        (Value::ObjectRef(mirror), Value::ObjectRef(name)) => (mirror, string::to_rust_string(&name.borrow())),
+        _ => return Err(InterpreterError::UnexpectedOperand)
+    };
+    let class = reflect::mirror_class(class_table, &mirror)?.ok_or(InterpreterError::UnexpectedOperand)?;
+    match class.fields.iter().position(|field| field.name == name && field.is_static()) {
+        Some(index) => Ok(InvokeResult::Value(Value::Long(STATIC_FIELD_OFFSET + index as i64))),
+        None => throw_new(class_table, "java/lang/InternalError", Some(&name))
+    }
+}
+
+fn unsafe_static_field_base0(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let field = object_arg(&arguments, 1)?;
+    Ok(InvokeResult::Value(get_field(&field, "clazz")))
+}
+
+fn unsafe_get_boolean(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unsafe_get(class_table, &arguments, Kind::Boolean)
+}
+
+fn unsafe_get_byte(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unsafe_get(class_table, &arguments, Kind::Byte)
+}
+
+fn unsafe_get_short(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unsafe_get(class_table, &arguments, Kind::Short)
+}
+
+fn unsafe_get_char(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unsafe_get(class_table, &arguments, Kind::Char)
+}
+
+fn unsafe_get_int(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unsafe_get(class_table, &arguments, Kind::Int)
+}
+
+fn unsafe_get_long(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unsafe_get(class_table, &arguments, Kind::Long)
+}
+
+fn unsafe_get_float(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unsafe_get(class_table, &arguments, Kind::Float)
+}
+
+fn unsafe_get_double(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unsafe_get(class_table, &arguments, Kind::Double)
+}
+
+fn unsafe_get_reference(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unsafe_get(class_table, &arguments, Kind::Reference)
+}
+
+fn unsafe_put_boolean(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unsafe_put(class_table, &arguments, Kind::Boolean)
+}
+
+fn unsafe_put_byte(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unsafe_put(class_table, &arguments, Kind::Byte)
+}
+
+fn unsafe_put_short(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unsafe_put(class_table, &arguments, Kind::Short)
+}
+
+fn unsafe_put_char(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unsafe_put(class_table, &arguments, Kind::Char)
+}
+
+fn unsafe_put_int(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unsafe_put(class_table, &arguments, Kind::Int)
+}
+
+fn unsafe_put_long(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unsafe_put(class_table, &arguments, Kind::Long)
+}
+
+fn unsafe_put_float(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unsafe_put(class_table, &arguments, Kind::Float)
+}
+
+fn unsafe_put_double(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unsafe_put(class_table, &arguments, Kind::Double)
+}
+
+fn unsafe_put_reference(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unsafe_put(class_table, &arguments, Kind::Reference)
+}
+
+fn unsafe_compare_and_set_int(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unsafe_compare_and_set(class_table, &arguments, Kind::Int)
+}
+
+fn unsafe_compare_and_set_long(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unsafe_compare_and_set(class_table, &arguments, Kind::Long)
+}
+
+fn unsafe_compare_and_set_reference(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unsafe_compare_and_set(class_table, &arguments, Kind::Reference)
+}
+
+fn unsafe_compare_and_exchange_int(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unsafe_compare_and_exchange(class_table, &arguments, Kind::Int)
+}
+
+fn unsafe_compare_and_exchange_long(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unsafe_compare_and_exchange(class_table, &arguments, Kind::Long)
+}
+
+fn unsafe_compare_and_exchange_reference(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    unsafe_compare_and_exchange(class_table, &arguments, Kind::Reference)
+}
+
+// Memory Unsafe allocates starts with its size, which freeing and reallocating it needs.
+const MEMORY_HEADER: usize = 8;
+
+fn memory_layout(size: usize) -> Layout {
+    Layout::from_size_align(size + MEMORY_HEADER, MEMORY_HEADER).unwrap()
+}
+
+fn unsafe_allocate_memory0(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let size = long_arg(&arguments, 1)? as usize;
+    let address = unsafe {
+        let block = alloc::alloc(memory_layout(size));
+        if block.is_null() {
+            0
+        } else {
+            ptr::write(block as *mut usize, size);
+            block.add(MEMORY_HEADER) as usize
+        }
+    };
+    Ok(InvokeResult::Value(Value::Long(address as i64)))
+}
+
+fn unsafe_reallocate_memory0(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let address = long_arg(&arguments, 1)? as usize as *mut u8;
+    let size = long_arg(&arguments, 2)? as usize;
+    let address = unsafe {
+        let block = address.sub(MEMORY_HEADER);
+        let old_size = ptr::read(block as *const usize);
+        let block = alloc::realloc(block, memory_layout(old_size), size + MEMORY_HEADER);
+        if block.is_null() {
+            0
+        } else {
+            ptr::write(block as *mut usize, size);
+            block.add(MEMORY_HEADER) as usize
+        }
+    };
+    Ok(InvokeResult::Value(Value::Long(address as i64)))
+}
+
+fn unsafe_free_memory0(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let address = long_arg(&arguments, 1)? as usize as *mut u8;
+    unsafe {
+        let block = address.sub(MEMORY_HEADER);
+        let size = ptr::read(block as *const usize);
+        alloc::dealloc(block, memory_layout(size));
+    }
+    Ok(InvokeResult::Void)
+}
+
+// The bytes a base and offset refer to, for bulk operations. References cannot be accessed as
+// bytes.
+fn with_bytes<R>(location: &Location, length: usize, f: impl FnOnce(&mut [u8]) -> R) -> Result<R, InterpreterError> {
+    match *location {
+        Location::Component(ref array, offset) => {
+            let mut array = array.borrow_mut();
+            let bytes = array.as_bytes_mut()
+                .and_then(|bytes| bytes.get_mut(offset..offset + length))
+                .ok_or(InterpreterError::UnexpectedOperand)?;
+            Ok(f(bytes))
+        },
+        Location::Address(address) => Ok(f(unsafe { std::slice::from_raw_parts_mut(address, length) })),
+        _ => Err(InterpreterError::UnexpectedOperand)
+    }
+}
+
+fn unsafe_set_memory0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let location = locate(class_table, &arguments, 1)?;
+    let length = long_arg(&arguments, 3)? as usize;
+    let value = int_arg(&arguments, 4)? as u8;
+    with_bytes(&location, length, |bytes| bytes.fill(value))?;
+    Ok(InvokeResult::Void)
+}
+
+// The bytes are copied through a buffer, as the source and destination may be the same array.
+fn unsafe_copy_memory0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let source = locate(class_table, &arguments, 1)?;
+    let destination = locate(class_table, &arguments, 3)?;
+    let length = long_arg(&arguments, 5)? as usize;
+    let bytes = with_bytes(&source, length, |bytes| bytes.to_vec())?;
+    with_bytes(&destination, length, |destination| destination.copy_from_slice(&bytes))?;
+    Ok(InvokeResult::Void)
+}
+
+fn unsafe_allocate_instance(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let mirror = object_arg(&arguments, 1)?;
+    let class = match reflect::mirror_class(class_table, &mirror)? {
+        Some(ref class) if !class.is_abstract() && !reflect::mirror_descriptor(&mirror).starts_with('[') => class.clone(),
+        _ => return throw_new(class_table, "java/lang/InstantiationException", Some(&reflect::mirror_type_name(&mirror)))
+    };
+    if let Some(exception) = interpreter::initialize_class(&class, class_table)? {
+        return Ok(InvokeResult::Exception(exception));
+    }
+    match interpreter::allocate_object(&class, class_table)? {
+        Ok(object) => Ok(InvokeResult::Value(Value::ObjectRef(object))),
+        Err(exception) => Ok(InvokeResult::Exception(exception))
+    }
+}
+
+fn unsafe_ensure_class_initialized0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let mirror = object_arg(&arguments, 1)?;
+    if let Some(class) = reflect::mirror_class(class_table, &mirror)? {
+        if let Some(exception) = interpreter::initialize_class(&class, class_table)? {
+            return Ok(InvokeResult::Exception(exception));
+        }
+    }
+    Ok(InvokeResult::Void)
+}
+
+fn unsafe_should_be_initialized0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let mirror = object_arg(&arguments, 1)?;
+    let class = reflect::mirror_class(class_table, &mirror)?;
+    boolean_result(class.is_some_and(|class| class.state() != ClassState::Initialized))
+}
+
+fn unsafe_throw_exception(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Exception(arguments[1].clone()))
+}
+
+// The load average is not available.
+fn unsafe_get_load_average0(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Integer(-1)))
+}
+
+// jdk.internal.misc.CDS
+
+// There is no archive to dump, so the seed only has to be the same every time.
+fn cds_get_random_seed_for_dumping(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Long(0)))
+}
+
+// jdk.internal.loader.BootLoader
+
+fn boot_loader_set_boot_loader_unnamed_module0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    class_table.set_unnamed_module(&object_arg(&arguments, 0)?);
+    Ok(InvokeResult::Void)
+}
+
+// jdk.internal.misc.VM
+
+// The owner of /proc/self is the user and group the process runs as.
+fn vm_getuid(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let uid = fs::metadata("/proc/self").map(|metadata| metadata.uid() as i64).unwrap_or(-1);
+    Ok(InvokeResult::Value(Value::Long(uid)))
+}
+
+fn vm_getgid(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let gid = fs::metadata("/proc/self").map(|metadata| metadata.gid() as i64).unwrap_or(-1);
+    Ok(InvokeResult::Value(Value::Long(gid)))
+}
+
+// The nanoseconds from the given number of seconds since the epoch to now, or -1 if that does not
+// fit in a long, for Instant.now.
+fn vm_get_nano_time_adjustment(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let offset = long_arg(&arguments, 0)?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let adjustment = (now.as_secs() as i64).checked_sub(offset)
+        .and_then(|seconds| seconds.checked_mul(1_000_000_000))
+        .and_then(|nanos| nanos.checked_add(now.subsec_nanos() as i64))
+        .unwrap_or(-1);
+    Ok(InvokeResult::Value(Value::Long(adjustment)))
+}
+
+fn vm_get_runtime_arguments(class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::ArrayRef(class_table.new_array("Ljava/lang/String;", 0))))
+}
+
+// jdk.internal.misc.Signal
+
+const SIGNALS: &[(&str, i32)] = &[("HUP", 1), ("INT", 2), ("QUIT", 3), ("ABRT", 6), ("KILL", 9), ("USR1", 10), ("USR2", 12),
+                                  ("PIPE", 13), ("ALRM", 14), ("TERM", 15), ("CHLD", 17), ("CONT", 18), ("TSTP", 20)];
+
+fn signal_find_signal0(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let name = string_arg(&arguments, 0)?.map(|name| String::from_utf16_lossy(&name)).unwrap_or_default();
+    let number = SIGNALS.iter().find(|&&(signal, _)| signal == name).map_or(-1, |&(_, number)| number);
+    Ok(InvokeResult::Value(Value::Integer(number)))
+}
+
+// Signals keep their default handling, which the returned 0 stands for.
+fn signal_handle0(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Long(0)))
+}
+
+// jdk.internal.util.SystemProps$Raw
+
+const INFLATION_THRESHOLD: &str = "sun.reflect.inflationThreshold";
+
+fn string_array(class_table: &ClassTable, strings: &[Option<String>]) -> Value {
+    let _scope = class_table.stacks.scope();
+    let strings = strings.iter()
+        .map(|string| match string {
+            Some(string) => new_string(class_table, encode(string)),
+            None => Value::Null
+        })
+        .collect::<Vec<Value>>();
+    let array = Value::ArrayRef(class_table.new_array_from("Ljava/lang/String;", ArrayElements::Reference(strings)));
+    class_table.stacks.keep(&array);
+    array
+}
+
+// The properties of the class table, which include those given with -D and java.home, as names
+// followed by values. Reflection keeps calling methods through the natives of its accessors,
+// as the bytecode accessors it would generate after a while need class definition.
+fn system_props_raw_vm_properties(class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let mut properties = class_table.properties();
+    if !properties.iter().any(|(name, _)| name == INFLATION_THRESHOLD) {
+        properties.push((String::from(INFLATION_THRESHOLD), i32::MAX.to_string()));
+    }
+    let properties = properties.into_iter()
+        .flat_map(|(name, value)| vec![Some(name), Some(value)])
+        .collect::<Vec<Option<String>>>();
+    Ok(InvokeResult::Value(string_array(class_table, &properties)))
+}
+
+// The defaults of the platform, at the indexes the _<name>_NDX constants of SystemProps$Raw give.
+fn system_props_raw_platform_properties(class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let raw = interpreter::resolve_class("jdk/internal/util/SystemProps$Raw", class_table)?;
+    let length = match raw.get_static("FIXED_LENGTH") {
+        Some(Value::Integer(length)) => length as usize,
+        _ => return Err(InterpreterError::UnexpectedOperand)
+    };
+
+    let mut properties = vec![None; length];
+    for (name, value) in platform_properties() {
+        if let Some(Value::Integer(index)) = raw.get_static(&format!("_{}_NDX", name.replace('.', "_"))) {
+            if let Some(property) = properties.get_mut(index as usize) {
+                *property = Some(value);
+            }
+        }
+    }
+    Ok(InvokeResult::Value(string_array(class_table, &properties)))
+}
+
+fn platform_properties() -> Vec<(&'static str, String)> {
+    let mut properties = vec![
+        ("display.language", String::from("en")),
+        ("display.country", String::from("US")),
+        ("format.language", String::from("en")),
+        ("format.country", String::from("US")),
+        ("file.encoding", String::from("UTF-8")),
+        ("sun.jnu.encoding", String::from("UTF-8")),
+        ("file.separator", String::from("/")),
+        ("path.separator", String::from(":")),
+        ("line.separator", String::from("\n")),
+        ("java.io.tmpdir", String::from("/tmp")),
+        ("os.name", String::from("Linux")),
+        ("os.arch", String::from(if env::consts::ARCH == "x86_64" { "amd64" } else { env::consts::ARCH })),
+        ("sun.arch.data.model", (8 * std::mem::size_of::<usize>()).to_string()),
+        ("sun.cpu.endian", String::from(if cfg!(target_endian = "big") { "big" } else { "little" })),
+        ("sun.io.unicode.encoding", String::from(if cfg!(target_endian = "big") { "UnicodeBig" } else { "UnicodeLittle" }))
+    ];
+    if let Ok(version) = fs::read_to_string("/proc/sys/kernel/osrelease") {
+        properties.push(("os.version", String::from(version.trim())));
+    }
+    if let Ok(dir) = env::current_dir() {
+        properties.push(("user.dir", dir.display().to_string()));
+    }
+    properties.push(("user.home", env::var("HOME").unwrap_or_else(|_| String::from("?"))));
+    properties.push(("user.name", env::var("USER").ok().or_else(user_name).unwrap_or_else(|| String::from("?"))));
+    properties
+}
+
+// The name /etc/passwd gives the user the process runs as.
+fn user_name() -> Option<String> {
+    let uid = fs::metadata("/proc/self").ok()?.uid().to_string();
+    fs::read_to_string("/etc/passwd").ok()?
+        .lines()
+        .map(|line| line.split(':').collect::<Vec<&str>>())
+        .find(|fields| fields.get(2) == Some(&uid.as_str()))
+        .map(|fields| String::from(fields[0]))
+}
+
+// java.lang.ProcessEnvironment
+
+// The environment as alternating names and values.
+fn process_environment_environ(class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let _scope = class_table.stacks.scope();
+    let entries = env::vars_os()
+        .flat_map(|(name, value)| vec![name, value])
+        .map(|bytes| {
+            let bytes = bytes.as_bytes().iter().map(|&b| b as i8).collect();
+            Value::ArrayRef(class_table.new_array_from("B", ArrayElements::Byte(bytes)))
+        })
+        .collect::<Vec<Value>>();
+    let environment = Value::ArrayRef(class_table.new_array_from("[B", ArrayElements::Reference(entries)));
+    class_table.stacks.keep(&environment);
+    Ok(InvokeResult::Value(environment))
+}
@@ -0,0 +1,218 @@
+// The natives of the java.base module of a JDK. When the class path has a JDK, its classes are
+// loaded in place of the bootstrap class library and their native methods are bound to these by
+// class, name and descriptor. boot then brings up java.base the way HotSpot does before it runs
+// the main method.
+
+use runtime::{Value, HeapCell, Object};
+use runtime::class::{ClassTable, RuntimeClass};
+use runtime::class::field::RuntimeField;
+use runtime::class::method::NativeMethod;
+use runtime::interpreter::{self, InvokeResult, InterpreterError};
+use runtime::bootstrap::{self, new_string, encode};
+use runtime::bootstrap::thread::THREAD_CLASS_NAME;
+use std::sync::Arc;
+
+mod invoke;
+mod io;
+mod lang;
+mod misc;
+mod reflect;
+mod thread;
+
+pub use self::invoke::resolved_method;
+
+// The natives of each module as (class, name, descriptor, native) tuples.
+type Natives = &'static [(&'static str, &'static str, &'static str, NativeMethod)];
+
+const NATIVES: &[Natives] = &[lang::NATIVES, thread::NATIVES, misc::NATIVES, reflect::NATIVES, invoke::NATIVES, io::NATIVES];
+
+const THREAD_GROUP_CLASS_NAME: &str = "java/lang/ThreadGroup";
+
+// Values of java.lang.Thread.threadStatus, as HotSpot encodes them.
+const RUNNABLE: i32 = 5;
+const TERMINATED: i32 = 2;
+
+const NORM_PRIORITY: i32 = 5;
+
+// The level of jdk.internal.misc.VM.initLevel once System.initPhase2 has booted the module system.
+const MODULE_SYSTEM_INITED: i32 = 2;
+
+// The native a JDK class binds the method to. Classes register their natives with a
+// registerNatives or initIDs method, which there is nothing to do for.
+pub fn find_native(class_name: &str, name: &str, descriptor: &str) -> Option<NativeMethod> {
+    if (name == "registerNatives" || name == "initIDs") && descriptor == "()V" {
+        return Some(no_op);
+    }
+
+    NATIVES.iter()
+        .flat_map(|natives| natives.iter())
+        .find(|&&(native_class_name, native_name, native_descriptor, _)| {
+            native_class_name == class_name && native_name == name && native_descriptor == descriptor
+        })
+        .map(|&(_, _, _, native)| native)
+}
+
+// The fields HotSpot injects into JDK classes to keep data of the virtual machine in.
+pub fn injected_fields(class_name: &str) -> Vec<RuntimeField> {
+    invoke::injected_fields(class_name)
+}
+
+fn no_op(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Void)
+}
+
+// Initializes java.base on the current thread, which becomes the main thread, following
+// Threads::create_vm of HotSpot: the core classes are initialized, the main thread and its
+// thread group are created and System.initPhase1 sets up the system properties and streams.
+// The module system and the system class loader, which the later phases set up, are left out, as
+// all classes are loaded from the class path: every class is put in the unnamed module of the boot
+// loader instead. Returns the exception that failed the boot.
+pub fn boot(class_table: &ClassTable) -> Result<(), String> {
+    let _attachment = class_table.heap.attach();
+    let _scope = class_table.stacks.scope();
+
+    let exception = match start(class_table) {
+        Ok(InvokeResult::Exception(exception)) => exception,
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(format!("{:?}", e))
+    };
+    match bootstrap::to_java_chars(class_table, &exception) {
+        Ok(Ok(chars)) => Err(String::from_utf16_lossy(&chars)),
+        _ => Err(format!("{:?}", exception))
+    }
+}
+
+fn start(class_table: &ClassTable) -> Result<InvokeResult, InterpreterError> {
+    // Unsafe reads the constants as it is initialized, so HotSpot injects them first.
+    let unsafe_constants = java_try!(initialize(class_table, "jdk/internal/misc/UnsafeConstants"));
+    unsafe_constants.put_static("ADDRESS_SIZE0", Value::Integer(8));
+    unsafe_constants.put_static("PAGE_SIZE", Value::Integer(4096));
+    unsafe_constants.put_static("BIG_ENDIAN", Value::Integer(cfg!(target_endian = "big") as i32));
+    unsafe_constants.put_static("UNALIGNED_ACCESS", Value::Integer(1));
+
+    for class_name in ["java/lang/String", "java/lang/System", "java/lang/Class"].iter() {
+        java_try!(initialize(class_table, class_name));
+    }
+
+    let thread_group_class = java_try!(initialize(class_table, THREAD_GROUP_CLASS_NAME));
+    let system_group = java_try!(new_instance(class_table, &thread_group_class, "()V", Vec::new()));
+    let main = new_string(class_table, encode("main"));
+    let main_group = java_try!(new_instance(class_table, &thread_group_class, "(Ljava/lang/ThreadGroup;Ljava/lang/String;)V",
+                                            vec![system_group, main.clone()]));
+
+    // The constructor of Thread copies the priority of the current thread, which is the thread
+    // being constructed.
+    let thread_class = java_try!(initialize(class_table, THREAD_CLASS_NAME));
+    let thread = class_table.new_object(&thread_class);
+    put_field(&thread, "priority", Value::Integer(NORM_PRIORITY));
+    put_field(&thread, "eetop", Value::Long(1));
+    class_table.set_thread_object(Some(thread.clone()));
+    let constructor = thread_class.get_declared_method("<init>", "(Ljava/lang/ThreadGroup;Ljava/lang/String;)V")
+        .ok_or_else(|| InterpreterError::MethodNotFound(String::from("java/lang/Thread.<init>(Ljava/lang/ThreadGroup;Ljava/lang/String;)V")))?;
+    let arguments = vec![Value::ObjectRef(thread.clone()), main_group, main];
+    if let InvokeResult::Exception(exception) = interpreter::invoke_method(&thread_class, constructor, arguments, class_table)? {
+        return Ok(InvokeResult::Exception(exception));
+    }
+    put_field(&thread, "threadStatus", Value::Integer(RUNNABLE));
+
+    // Initializing Method initializes AccessibleObject, which hands the reflection factory its
+    // access to java.lang.reflect.
+    java_try!(initialize(class_table, "java/lang/reflect/Method"));
+
+    let system_class = java_try!(initialize(class_table, "java/lang/System"));
+    let init_phase1 = system_class.get_declared_method("initPhase1", "()V")
+        .ok_or_else(|| InterpreterError::MethodNotFound(String::from("java/lang/System.initPhase1()V")))?;
+    if let InvokeResult::Exception(exception) = interpreter::invoke_method(&system_class, init_phase1, Vec::new(), class_table)? {
+        return Ok(InvokeResult::Exception(exception));
+    }
+
+    // BootLoader creates its unnamed module as it is initialized, which then becomes the module of
+    // all classes. The boot layer has no modules. That done, the module system counts as
+    // initialized, which Proxy waits for.
+    java_try!(initialize(class_table, "jdk/internal/loader/BootLoader"));
+    let module_layer_class = java_try!(initialize(class_table, "java/lang/ModuleLayer"));
+    let empty_layer = module_layer_class.get_static("EMPTY_LAYER").unwrap_or(Value::Null);
+    system_class.put_static("bootLayer", empty_layer);
+    let vm_class = java_try!(initialize(class_table, "jdk/internal/misc/VM"));
+    let init_level = vm_class.get_declared_method("initLevel", "(I)V")
+        .ok_or_else(|| InterpreterError::MethodNotFound(String::from("jdk/internal/misc/VM.initLevel(I)V")))?;
+    interpreter::invoke_method(&vm_class, init_level, vec![Value::Integer(MODULE_SYSTEM_INITED)], class_table)
+}
+
+// Loads and initializes a class. The inner error is the exception thrown by its initializer.
+fn initialize(class_table: &ClassTable, class_name: &str) -> Result<Result<Arc<RuntimeClass>, Value>, InterpreterError> {
+    let class = interpreter::resolve_class(class_name, class_table)?;
+    match interpreter::initialize_class(&class, class_table)? {
+        Some(exception) => Ok(Err(exception)),
+        None => Ok(Ok(class))
+    }
+}
+
+// Creates an object with the constructor of the given descriptor, keeping it in the scope of the
+// caller. The inner error is the exception thrown by the constructor.
+fn new_instance(class_table: &ClassTable,
+                class: &Arc<RuntimeClass>,
+                descriptor: &str,
+                arguments: Vec<Value>) -> Result<Result<Value, Value>, InterpreterError> {
+    let constructor = class.get_declared_method("<init>", descriptor)
+        .ok_or_else(|| InterpreterError::MethodNotFound(format!("{}.<init>{}", class.class_name, descriptor)))?;
+    let object = Value::ObjectRef(class_table.new_object(class));
+    let mut arguments = arguments;
+    arguments.insert(0, object.clone());
+    match interpreter::invoke_method(class, constructor, arguments, class_table)? {
+        InvokeResult::Exception(exception) => Ok(Err(exception)),
+        _ => Ok(Ok(object))
+    }
+}
+
+fn get_field(object: &Arc<HeapCell<Object>>, name: &str) -> Value {
+    object.borrow().get_field(String::from(name))
+}
+
+fn put_field(object: &Arc<HeapCell<Object>>, name: &str, value: Value) {
+    object.borrow_mut().put_field(String::from(name), value);
+}
+
+fn boolean_result(value: bool) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Integer(value as i32)))
+}
+
+fn null_result() -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Null))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use runtime::bootstrap::io::{Console, SharedBuffer};
+    use class::reader;
+    use class::path::ClassPath;
+    use std::env;
+    use std::path::Path;
+
+    // Boots the JDK in JAVA_HOME and runs hello world on its java.base.
+    #[test]
+    #[ignore = "needs JAVA_HOME"]
+    fn runs_on_installed_jdk() {
+        let java_home = env::var("JAVA_HOME").expect("JAVA_HOME must point to an installed JDK");
+        let out = SharedBuffer::new();
+        let class_table = ClassTable::without_library(Console::new(Box::new(out.clone()), Box::new(SharedBuffer::new())));
+        let mut class_path = ClassPath::new();
+        class_path.add_java_home(Path::new(&java_home)).unwrap();
+        class_table.set_class_path(class_path);
+        class_table.set_property("java.home", &java_home);
+        boot(&class_table).unwrap();
+
+        let class_file = reader::read_class_file(include_bytes!("../../../../fixtures/Hello.class")).unwrap();
+        let class = class_table.define_class(&class_file).unwrap();
+        assert!(interpreter::initialize_class(&class, &class_table).unwrap().is_none());
+        let main = class.get_declared_method("main", "([Ljava/lang/String;)V").unwrap();
+        let arguments = vec![Value::ArrayRef(class_table.new_array("Ljava/lang/String;", 0))];
+        interpreter::invoke_method(&class, main, arguments, &class_table).unwrap();
+        class_table.console.flush();
+
+        assert_eq!(out.contents(), "Hello, world!\nHello 42\n");
+    }
+
+}
@@ -0,0 +1,365 @@
+use runtime::{Value, ArrayElements, HeapCell, Object};
+use runtime::class::{ClassTable, RuntimeClass, mirror_name};
+use runtime::class::method::MethodDescriptor;
+use runtime::interpreter::{self, InvokeResult, InterpreterError};
+use runtime::bootstrap::{reflect, object_arg, int_arg, throw_new, encode};
+use runtime::bootstrap::jdk::{Natives, boolean_result, null_result, put_field};
+use class::{field, method, ConstantPool};
+use std::sync::Arc;
+
+// The access flags the JDK reports as modifiers of methods and fields, as HotSpot's
+// JVM_RECOGNIZED_METHOD_MODIFIERS and JVM_RECOGNIZED_FIELD_MODIFIERS.
+pub const METHOD_MODIFIERS: u16 = 0x1dff;
+pub const FIELD_MODIFIERS: u16 = 0x50df;
+
+pub static NATIVES: Natives = &[
+    ("java/lang/Class", "getDeclaredFields0", "(Z)[Ljava/lang/reflect/Field;", class_get_declared_fields0),
+    ("java/lang/Class", "getDeclaredMethods0", "(Z)[Ljava/lang/reflect/Method;", class_get_declared_methods0),
+    ("java/lang/Class", "getDeclaredConstructors0", "(Z)[Ljava/lang/reflect/Constructor;", class_get_declared_constructors0),
+    ("java/lang/Class", "getDeclaredClasses0", "()[Ljava/lang/Class;", class_get_declared_classes0),
+    ("java/lang/Class", "getDeclaringClass0", "()Ljava/lang/Class;", class_get_declaring_class0),
+    ("java/lang/Class", "getEnclosingMethod0", "()[Ljava/lang/Object;", class_get_enclosing_method0),
+    ("java/lang/Class", "getSimpleBinaryName0", "()Ljava/lang/String;", class_get_simple_binary_name0),
+    ("java/lang/Class", "getGenericSignature0", "()Ljava/lang/String;", returns_null),
+    ("java/lang/Class", "getRawAnnotations", "()[B", class_get_raw_annotations),
+    ("java/lang/Class", "getRawTypeAnnotations", "()[B", class_get_raw_type_annotations),
+    ("java/lang/Class", "getConstantPool", "()Ljdk/internal/reflect/ConstantPool;", class_get_constant_pool),
+    ("java/lang/Class", "getPermittedSubclasses0", "()[Ljava/lang/Class;", returns_null),
+    ("java/lang/Class", "getProtectionDomain0", "()Ljava/security/ProtectionDomain;", returns_null),
+    ("java/lang/Class", "getNestHost0", "()Ljava/lang/Class;", class_get_nest_host0),
+    ("java/lang/Class", "isRecord0", "()Z", class_is_record0),
+
+    ("jdk/internal/reflect/NativeMethodAccessorImpl", "invoke0", "(Ljava/lang/reflect/Method;Ljava/lang/Object;[Ljava/lang/Object;)Ljava/lang/Object;",
+     native_method_accessor_impl_invoke0),
+    ("jdk/internal/reflect/NativeConstructorAccessorImpl", "newInstance0", "(Ljava/lang/reflect/Constructor;[Ljava/lang/Object;)Ljava/lang/Object;",
+     native_constructor_accessor_impl_new_instance0),
+
+    ("jdk/internal/reflect/ConstantPool", "getSize0", "(Ljava/lang/Object;)I", constant_pool_get_size0),
+    ("jdk/internal/reflect/ConstantPool", "getIntAt0", "(Ljava/lang/Object;I)I", constant_pool_get_int_at0),
+    ("jdk/internal/reflect/ConstantPool", "getLongAt0", "(Ljava/lang/Object;I)J", constant_pool_get_long_at0),
+    ("jdk/internal/reflect/ConstantPool", "getFloatAt0", "(Ljava/lang/Object;I)F", constant_pool_get_float_at0),
+    ("jdk/internal/reflect/ConstantPool", "getDoubleAt0", "(Ljava/lang/Object;I)D", constant_pool_get_double_at0),
+    ("jdk/internal/reflect/ConstantPool", "getStringAt0", "(Ljava/lang/Object;I)Ljava/lang/String;", constant_pool_get_string_at0),
+    ("jdk/internal/reflect/ConstantPool", "getUTF8At0", "(Ljava/lang/Object;I)Ljava/lang/String;", constant_pool_get_utf8_at0),
+
+    ("jdk/internal/reflect/Reflection", "getCallerClass", "()Ljava/lang/Class;", reflection_get_caller_class),
+    ("jdk/internal/reflect/Reflection", "getClassAccessFlags", "(Ljava/lang/Class;)I", reflection_get_class_access_flags),
+    ("jdk/internal/reflect/Reflection", "areNestMates", "(Ljava/lang/Class;Ljava/lang/Class;)Z", reflection_are_nest_mates),
+
+    ("java/lang/reflect/Array", "newArray", "(Ljava/lang/Class;I)Ljava/lang/Object;", array_new_array),
+    ("java/lang/reflect/Array", "getLength", "(Ljava/lang/Object;)I", array_get_length)
+];
+
+// java.lang.Class
+//
+// Field, Method and Constructor objects are made the way HotSpot makes them, with their fields
+// set directly. Their slot is the position of the member in the RuntimeClass, as for the members
+// of the bootstrap class library. Annotations are handed over as the bytes of their attributes,
+// which AnnotationParser reads with the ConstantPool of the class. Nested classes are described
+// by the InnerClasses and EnclosingMethod attributes. The attributes for generic signatures,
+// permitted subclasses and nests are not kept by the class table, so classes have none: the
+// natives reading them return null, which java.lang.Class takes for a missing attribute.
+
+fn returns_null(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    null_result()
+}
+
+// The member classes of a class, which are those the InnerClasses attribute has it as the outer
+// class of.
+fn class_get_declared_classes0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let class = mirror_class_file(class_table, &object_arg(&arguments, 0)?)?;
+    let member_classes: Vec<String> = class.iter()
+        .flat_map(|class| class.inner_classes.iter().filter(move |inner_class| inner_class.outer_class_name.as_ref() == Some(&class.class_name)))
+        .map(|inner_class| format!("L{};", inner_class.class_name))
+        .collect();
+    Ok(InvokeResult::Value(class_mirrors(class_table, &member_classes)))
+}
+
+// The class a member class is declared in, or null for other classes.
+fn class_get_declaring_class0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let class = mirror_class_file(class_table, &object_arg(&arguments, 0)?)?;
+    match class.as_ref().and_then(|class| class.inner_class()).and_then(|inner_class| inner_class.outer_class_name.as_ref()) {
+        Some(outer_class_name) => Ok(InvokeResult::Value(Value::ObjectRef(class_table.get_mirror(outer_class_name)))),
+        None => null_result()
+    }
+}
+
+// The name of a nested class in the source, or null for top level and anonymous classes.
+fn class_get_simple_binary_name0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let class = mirror_class_file(class_table, &object_arg(&arguments, 0)?)?;
+    match class.as_ref().and_then(|class| class.inner_class()).and_then(|inner_class| inner_class.simple_name.as_ref()) {
+        Some(simple_name) => Ok(InvokeResult::Value(Value::ObjectRef(class_table.intern_string(encode(simple_name))))),
+        None => null_result()
+    }
+}
+
+// The class a local or anonymous class is declared in, with the name and descriptor of the
+// method it is declared in or nulls, or null for other classes.
+fn class_get_enclosing_method0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let class = mirror_class_file(class_table, &object_arg(&arguments, 0)?)?;
+    let (enclosing_class_name, method) = match class.as_ref().and_then(|class| class.enclosing_method.as_ref()) {
+        Some(enclosing_method) => enclosing_method.clone(),
+        None => return null_result()
+    };
+    let string = |value: &str| Value::ObjectRef(class_table.intern_string(encode(value)));
+    let mut info = vec![Value::ObjectRef(class_table.get_mirror(&enclosing_class_name)), Value::Null, Value::Null];
+    if let Some((name, descriptor)) = method {
+        info[1] = string(&name);
+        info[2] = string(&descriptor);
+    }
+    Ok(InvokeResult::Value(Value::ArrayRef(class_table.new_array_from("Ljava/lang/Object;", ArrayElements::Reference(info)))))
+}
+
+// Without a NestHost attribute, a class is the host of its own nest.
+fn class_get_nest_host0(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(arguments[0].clone()))
+}
+
+fn class_is_record0(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    boolean_result(false)
+}
+
+// The class a mirror stands for, or None for primitive types and arrays, which have no class file.
+fn mirror_class_file(class_table: &ClassTable, mirror: &Arc<HeapCell<Object>>) -> Result<Option<Arc<RuntimeClass>>, InterpreterError> {
+    if reflect::mirror_descriptor(mirror).starts_with('[') {
+        return Ok(None);
+    }
+    reflect::mirror_class(class_table, mirror)
+}
+
+fn class_get_raw_annotations(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let class = mirror_class_file(class_table, &object_arg(&arguments, 0)?)?;
+    Ok(InvokeResult::Value(byte_array(class_table, class.and_then(|class| class.raw_annotations.annotations.clone()))))
+}
+
+fn class_get_raw_type_annotations(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let class = mirror_class_file(class_table, &object_arg(&arguments, 0)?)?;
+    Ok(InvokeResult::Value(byte_array(class_table, class.and_then(|class| class.raw_annotations.type_annotations.clone()))))
+}
+
+// HotSpot hands the constant pool to the ConstantPool natives in the constantPoolOop field, which
+// here holds the mirror of the class.
+fn class_get_constant_pool(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let constant_pool_class = interpreter::resolve_class("jdk/internal/reflect/ConstantPool", class_table)?;
+    let constant_pool = class_table.new_object(&constant_pool_class);
+    put_field(&constant_pool, "constantPoolOop", arguments[0].clone());
+    Ok(InvokeResult::Value(Value::ObjectRef(constant_pool)))
+}
+
+// The contents of an attribute as a byte[], or null if there is no attribute.
+fn byte_array(class_table: &ClassTable, bytes: Option<Vec<u8>>) -> Value {
+    match bytes {
+        Some(bytes) => {
+            let bytes = bytes.into_iter().map(|b| b as i8).collect();
+            Value::ArrayRef(class_table.new_array_from("B", ArrayElements::Byte(bytes)))
+        },
+        None => Value::Null
+    }
+}
+
+fn class_mirrors(class_table: &ClassTable, descriptors: &[String]) -> Value {
+    let mirrors = descriptors.iter().map(|descriptor| reflect::type_mirror(class_table, descriptor)).collect();
+    Value::ArrayRef(class_table.new_array_from("Ljava/lang/Class;", ArrayElements::Reference(mirrors)))
+}
+
+fn new_member(class_table: &ClassTable, member_class_name: &str, class: &RuntimeClass, slot: usize, modifiers: u16) -> Result<Arc<HeapCell<Object>>, InterpreterError> {
+    let member_class = interpreter::resolve_class(member_class_name, class_table)?;
+    let member = class_table.new_object(&member_class);
+    put_field(&member, "clazz", Value::ObjectRef(class_table.get_mirror(&class.class_name)));
+    put_field(&member, "slot", Value::Integer(slot as i32));
+    put_field(&member, "modifiers", Value::Integer(modifiers as i32));
+    Ok(member)
+}
+
+fn members(class_table: &ClassTable, member_class_name: &str, members: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let component_type = format!("L{};", member_class_name);
+    Ok(InvokeResult::Value(Value::ArrayRef(class_table.new_array_from(&component_type, ArrayElements::Reference(members)))))
+}
+
+// The class of the mirror a Class native is called on, and whether only public members are asked
+// for. Primitive types and arrays declare no members.
+fn declaring_class(class_table: &ClassTable, arguments: &[Value]) -> Result<(Option<Arc<RuntimeClass>>, bool), InterpreterError> {
+    let mirror = object_arg(arguments, 0)?;
+    let public_only = int_arg(arguments, 1)? != 0;
+    if reflect::mirror_descriptor(&mirror).starts_with('[') {
+        return Ok((None, public_only));
+    }
+    Ok((reflect::mirror_class(class_table, &mirror)?, public_only))
+}
+
+fn class_get_declared_fields0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    const FIELD_CLASS_NAME: &str = "java/lang/reflect/Field";
+    let (class, public_only) = declaring_class(class_table, &arguments)?;
+    let mut fields = Vec::new();
+    for class in class.iter() {
+        for (slot, runtime_field) in class.fields.iter().enumerate() {
+            if public_only && runtime_field.access_flags & field::ACC_PUBLIC == 0 {
+                continue;
+            }
+            let field = new_member(class_table, FIELD_CLASS_NAME, class, slot, runtime_field.access_flags & FIELD_MODIFIERS)?;
+            let trusted_final = runtime_field.access_flags & field::ACC_FINAL != 0 && runtime_field.is_static();
+            put_field(&field, "name", Value::ObjectRef(class_table.intern_string(encode(&runtime_field.name))));
+            put_field(&field, "type", reflect::type_mirror(class_table, &runtime_field.descriptor_string));
+            put_field(&field, "trustedFinal", Value::Integer(trusted_final as i32));
+            put_field(&field, "annotations", byte_array(class_table, runtime_field.raw_annotations.annotations.clone()));
+            fields.push(Value::ObjectRef(field));
+        }
+    }
+    members(class_table, FIELD_CLASS_NAME, fields)
+}
+
+// The methods, or the constructors, a class declares.
+fn declared_methods(class_table: &ClassTable, arguments: &[Value], constructors: bool) -> Result<Vec<Value>, InterpreterError> {
+    let member_class_name = if constructors { "java/lang/reflect/Constructor" } else { "java/lang/reflect/Method" };
+    let (class, public_only) = declaring_class(class_table, arguments)?;
+    let mut methods = Vec::new();
+    for class in class.iter() {
+        for (slot, runtime_method) in class.methods.iter().enumerate() {
+            if (runtime_method.name == "<init>") != constructors || runtime_method.name == "<clinit>" ||
+                (public_only && runtime_method.access_flags & method::ACC_PUBLIC == 0) {
+                continue;
+            }
+            let descriptor = MethodDescriptor::parse(&runtime_method.descriptor).ok_or(InterpreterError::UnexpectedOperand)?;
+            let method = new_member(class_table, member_class_name, class, slot, runtime_method.access_flags & METHOD_MODIFIERS)?;
+            put_field(&method, "parameterTypes", class_mirrors(class_table, &descriptor.parameter_types()));
+            put_field(&method, "exceptionTypes", class_mirrors(class_table, &[]));
+            put_field(&method, "annotations", byte_array(class_table, runtime_method.raw_annotations.annotations.clone()));
+            if !constructors {
+                put_field(&method, "name", Value::ObjectRef(class_table.intern_string(encode(&runtime_method.name))));
+                put_field(&method, "returnType", reflect::type_mirror(class_table, &descriptor.return_type()));
+                put_field(&method, "annotationDefault", byte_array(class_table, runtime_method.raw_annotations.annotation_default.clone()));
+            }
+            methods.push(Value::ObjectRef(method));
+        }
+    }
+    Ok(methods)
+}
+
+fn class_get_declared_methods0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let methods = declared_methods(class_table, &arguments, false)?;
+    members(class_table, "java/lang/reflect/Method", methods)
+}
+
+fn class_get_declared_constructors0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let constructors = declared_methods(class_table, &arguments, true)?;
+    members(class_table, "java/lang/reflect/Constructor", constructors)
+}
+
+// jdk.internal.reflect.NativeMethodAccessorImpl and NativeConstructorAccessorImpl, which
+// Method.invoke and Constructor.newInstance call once they have checked access.
+
+fn native_method_accessor_impl_invoke0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let method = object_arg(&arguments, 0)?;
+    reflect::invoke(class_table, &method, arguments[1].clone(), &arguments[2])
+}
+
+fn native_constructor_accessor_impl_new_instance0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let constructor = object_arg(&arguments, 0)?;
+    reflect::construct(class_table, &constructor, &arguments[1])
+}
+
+// jdk.internal.reflect.ConstantPool, whose natives take the mirror in its constantPoolOop field
+// and an index. Indices of constants of another kind are illegal arguments, as in HotSpot.
+
+fn constant_pool_get_size0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let size = mirror_class_file(class_table, &object_arg(&arguments, 1)?)?.map_or(0, |class| class.constant_pool.size() + 1);
+    Ok(InvokeResult::Value(Value::Integer(size as i32)))
+}
+
+fn constant_pool_get<T, F, V>(class_table: &ClassTable, arguments: &[Value], get: F, value: V) -> Result<InvokeResult, InterpreterError>
+    where F: Fn(&ConstantPool, u16) -> Result<T, String>, V: Fn(T) -> Value {
+    let class = mirror_class_file(class_table, &object_arg(arguments, 1)?)?;
+    let index = int_arg(arguments, 2)?;
+    match class {
+        Some(ref class) if index > 0 && index as usize <= class.constant_pool.size() => match get(&class.constant_pool, index as u16) {
+            Ok(constant) => Ok(InvokeResult::Value(value(constant))),
+            Err(_) => throw_new(class_table, "java/lang/IllegalArgumentException", Some("Wrong type at constant pool index"))
+        },
+        _ => throw_new(class_table, "java/lang/IllegalArgumentException", Some("Constant pool index out of bounds"))
+    }
+}
+
+fn constant_pool_get_int_at0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    constant_pool_get(class_table, &arguments, ConstantPool::get_integer, Value::Integer)
+}
+
+fn constant_pool_get_long_at0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    constant_pool_get(class_table, &arguments, ConstantPool::get_long, Value::Long)
+}
+
+fn constant_pool_get_float_at0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    constant_pool_get(class_table, &arguments, ConstantPool::get_float, Value::Float)
+}
+
+fn constant_pool_get_double_at0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    constant_pool_get(class_table, &arguments, ConstantPool::get_double, Value::Double)
+}
+
+fn constant_pool_get_string_at0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    constant_pool_get(class_table, &arguments, ConstantPool::get_string, |chars| Value::ObjectRef(class_table.intern_string(chars)))
+}
+
+fn constant_pool_get_utf8_at0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    constant_pool_get(class_table, &arguments, ConstantPool::get_utf16, |chars| Value::ObjectRef(class_table.intern_string(chars)))
+}
+
+// jdk.internal.reflect.Reflection
+
+// Frames of the reflection machinery, which callers are looked for past.
+fn is_reflection(class_name: &str) -> bool {
+    class_name.starts_with("jdk/internal/reflect/") || class_name == "java/lang/reflect/Method"
+}
+
+// The class of the method that called the @CallerSensitive method calling this native: the frames
+// on top are this native and the caller sensitive method.
+fn reflection_get_caller_class(class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let frames = class_table.stacks.current_frames();
+    let caller = frames.iter().rev()
+        .skip(2)
+        .find(|frame| !is_reflection(&frame.class.class_name));
+    match caller {
+        Some(frame) => Ok(InvokeResult::Value(Value::ObjectRef(class_table.get_mirror(&frame.class.class_name)))),
+        None => Ok(InvokeResult::Value(Value::Null))
+    }
+}
+
+fn reflection_get_class_access_flags(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let mirror = object_arg(&arguments, 0)?;
+    let access_flags = reflect::mirror_class(class_table, &mirror)?.map_or(0, |class| class.access_flags);
+    Ok(InvokeResult::Value(Value::Integer(access_flags as i32)))
+}
+
+// Classes are nest mates if they are nested in the same top level class. The NestHost and
+// NestMembers attributes say the same for classes compiled from one source file.
+fn reflection_are_nest_mates(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let top_level = |index| -> Result<String, InterpreterError> {
+        let descriptor = reflect::mirror_descriptor(&object_arg(&arguments, index)?);
+        Ok(mirror_name(&descriptor).split('$').next().unwrap_or_default().to_string())
+    };
+    boolean_result(top_level(0)? == top_level(1)?)
+}
+
+// java.lang.reflect.Array
+
+fn array_new_array(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let component = match arguments[0] {
+        Value::ObjectRef(ref mirror) => reflect::mirror_descriptor(mirror),
+        _ => return throw_new(class_table, "java/lang/NullPointerException", None)
+    };
+    let length = int_arg(&arguments, 1)?;
+    if component == "V" {
+        return throw_new(class_table, "java/lang/IllegalArgumentException", None);
+    }
+    if length < 0 {
+        return throw_new(class_table, "java/lang/NegativeArraySizeException", Some(&length.to_string()));
+    }
+    Ok(InvokeResult::Value(Value::ArrayRef(class_table.new_array(&component, length as usize))))
+}
+
+fn array_get_length(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    match arguments[0] {
+        Value::ArrayRef(ref array) => Ok(InvokeResult::Value(Value::Integer(array.borrow().len() as i32))),
+        Value::Null => throw_new(class_table, "java/lang/NullPointerException", None),
+        _ => throw_new(class_table, "java/lang/IllegalArgumentException", Some("Argument is not an array"))
+    }
+}
@@ -0,0 +1,250 @@
+use runtime::{Value, HeapCell, Object};
+use runtime::class::ClassTable;
+use runtime::interpreter::{self, InvokeResult, InterpreterError};
+use runtime::bootstrap::{object_arg, int_arg, long_arg, throw_new};
+use runtime::bootstrap::thread::{THREAD_CLASS_NAME, STACK_SIZE, exit};
+use runtime::bootstrap::jdk::{Natives, RUNNABLE, TERMINATED, no_op, get_field, put_field, boolean_result};
+use runtime::monitor;
+use runtime::string;
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub static NATIVES: Natives = &[
+    ("java/lang/Thread", "currentThread", "()Ljava/lang/Thread;", thread_current_thread),
+    ("java/lang/Thread", "yield", "()V", thread_yield),
+    ("java/lang/Thread", "sleep", "(J)V", thread_sleep),
+    ("java/lang/Thread", "start0", "()V", thread_start0),
+    ("java/lang/Thread", "holdsLock", "(Ljava/lang/Object;)Z", thread_holds_lock),
+    ("java/lang/Thread", "setPriority0", "(I)V", no_op),
+    ("java/lang/Thread", "setNativeName", "(Ljava/lang/String;)V", no_op),
+    ("java/lang/Thread", "interrupt0", "()V", thread_interrupt0),
+    ("java/lang/Thread", "clearInterruptEvent", "()V", no_op),
+
+    ("jdk/internal/misc/Unsafe", "park", "(ZJ)V", unsafe_park),
+    ("jdk/internal/misc/Unsafe", "unpark", "(Ljava/lang/Object;)V", unsafe_unpark)
+];
+
+// What LockSupport parks and unparks threads with: a permit, which unpark makes available and
+// park waits for and consumes. Sleeping threads wait on it too, to wake up when interrupted.
+#[derive(Default)]
+struct Parker {
+    permit: Mutex<bool>,
+    changed: Condvar
+}
+
+static PARKERS: OnceLock<Mutex<HashMap<ThreadId, Arc<Parker>>>> = OnceLock::new();
+
+fn parker(thread: ThreadId) -> Arc<Parker> {
+    PARKERS.get_or_init(Default::default).lock().unwrap()
+        .entry(thread)
+        .or_default()
+        .clone()
+}
+
+fn is_interrupted(thread: &Arc<HeapCell<Object>>) -> bool {
+    matches!(get_field(thread, "interrupted"), Value::Integer(1))
+}
+
+// Wakes the thread of the java.lang.Thread instance, if it is parked or sleeping.
+fn wake(class_table: &ClassTable, thread_object: &Arc<HeapCell<Object>>, permit: bool) {
+    if let Some(thread) = class_table.find_thread(thread_object) {
+        let parker = parker(thread);
+        let mut available = parker.permit.lock().unwrap();
+        *available |= permit;
+        parker.changed.notify_all();
+    }
+}
+
+fn thread_current_thread(class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    match class_table.get_thread_object() {
+        Some(thread) => Ok(InvokeResult::Value(Value::ObjectRef(thread))),
+        None => Ok(InvokeResult::Value(Value::Null))
+    }
+}
+
+fn thread_yield(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    thread::yield_now();
+    Ok(InvokeResult::Void)
+}
+
+// Sleeps until the time is up or the thread is interrupted, which clears the interrupt status.
+fn thread_sleep(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let millis = long_arg(&arguments, 0)?;
+    if millis < 0 {
+        return throw_new(class_table, "java/lang/IllegalArgumentException", Some("timeout value is negative"));
+    }
+    let this = class_table.get_thread_object();
+    let interrupted = || this.as_ref().is_some_and(is_interrupted);
+
+    let deadline = Instant::now() + Duration::from_millis(millis as u64);
+    let parker = parker(thread::current().id());
+    let woken = class_table.heap.blocking(|| {
+        let mut permit = parker.permit.lock().unwrap();
+        loop {
+            if interrupted() {
+                return true;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            permit = parker.changed.wait_timeout(permit, deadline - now).unwrap().0;
+        }
+    });
+
+    if woken {
+        if let Some(ref this) = this {
+            put_field(this, "interrupted", Value::Integer(0));
+        }
+        return throw_new(class_table, "java/lang/InterruptedException", Some("sleep interrupted"));
+    }
+    Ok(InvokeResult::Void)
+}
+
+// Starts a new thread running the run method of the Thread, like the start of the bootstrap
+// class library. The fields HotSpot keeps for the thread, eetop and threadStatus, tell whether it
+// is alive and whether it has been started.
+fn thread_start0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    put_field(&this, "eetop", Value::Long(1));
+    put_field(&this, "threadStatus", Value::Integer(RUNNABLE));
+
+    let name = match get_field(&this, "name") {
+        Value::ObjectRef(name) => string::to_rust_string(&name.borrow()),
+        _ => String::new()
+    };
+    let daemon = matches!(get_field(&this, "daemon"), Value::Integer(1));
+
+    // The Thread is only a root of the heap here until the new thread has registered it, so
+    // this waits for that.
+    let shared_class_table = class_table.shared();
+    let thread_object = this.clone();
+    let (registered, on_registered) = mpsc::channel();
+    let spawned = thread::Builder::new()
+        .name(name)
+        .stack_size(STACK_SIZE)
+        .spawn(move || run_thread(&shared_class_table, thread_object, registered));
+    let handle = match spawned {
+        Ok(handle) => handle,
+        Err(_) => {
+            put_field(&this, "eetop", Value::Long(0));
+            return throw_new(class_table, "java/lang/OutOfMemoryError", Some("unable to create native thread"));
+        }
+    };
+    let _ = class_table.heap.blocking(|| on_registered.recv());
+
+    if !daemon {
+        class_table.add_thread(handle);
+    }
+    Ok(InvokeResult::Void)
+}
+
+// An exception the thread does not catch goes to its uncaught exception handler, and the
+// thread then cleans up after itself with Thread.exit, as in HotSpot's JavaThread::exit.
+fn run_thread(class_table: &ClassTable, thread: Arc<HeapCell<Object>>, registered: Sender<()>) {
+    let _attachment = class_table.heap.attach();
+    class_table.set_thread_object(Some(thread.clone()));
+    let _ = registered.send(());
+
+    let this = Value::ObjectRef(thread.clone());
+    let result = interpreter::invoke_virtual(class_table, this.clone(), "run", "()V", Vec::new())
+        .and_then(|result| match result {
+            InvokeResult::Exception(exception) => {
+                interpreter::invoke_virtual(class_table, this.clone(), "dispatchUncaughtException", "(Ljava/lang/Throwable;)V", vec![exception])
+            },
+            result => Ok(result)
+        })
+        .and_then(|_| {
+            let class = interpreter::resolve_class(THREAD_CLASS_NAME, class_table)?;
+            let thread_exit = class.get_declared_method("exit", "()V")
+                .ok_or_else(|| InterpreterError::MethodNotFound(String::from("java/lang/Thread.exit()V")))?;
+            interpreter::invoke_method(&class, thread_exit, vec![this], class_table)
+        });
+    match result {
+        Ok(_) => {},
+        Err(InterpreterError::Exit(status)) => exit(class_table, status),
+        Err(e) => {
+            class_table.console.flush();
+            eprintln!("Internal error: {:?}", e);
+            for element in class_table.stacks.take_failure() {
+                eprintln!("\tat {}", element);
+            }
+        }
+    }
+
+    class_table.stacks.remove_current();
+
+    // Threads blocked in join wait on the monitor of the Thread.
+    class_table.heap.enter_monitor(thread.monitor());
+    put_field(&thread, "eetop", Value::Long(0));
+    put_field(&thread, "threadStatus", Value::Integer(TERMINATED));
+    thread.monitor().notify(true);
+    thread.monitor().exit();
+
+    PARKERS.get_or_init(Default::default).lock().unwrap().remove(&thread::current().id());
+    class_table.set_thread_object(None);
+}
+
+fn thread_holds_lock(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    match monitor::with_monitor(&arguments[0], |monitor| monitor.is_held()) {
+        Some(held) => boolean_result(held),
+        None => throw_new(class_table, "java/lang/NullPointerException", None)
+    }
+}
+
+// Thread.interrupt has set the interrupt status already.
+fn thread_interrupt0(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    wake(class_table, &this, false);
+    Ok(InvokeResult::Void)
+}
+
+// jdk.internal.misc.Unsafe
+
+// Waits for the permit, or until the thread is interrupted or the time is up: an absolute time
+// is in milliseconds since the epoch, a relative one in nanoseconds with 0 meaning no timeout.
+// Like the park of HotSpot, it may also return for no reason, so callers check their condition.
+fn unsafe_park(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let absolute = int_arg(&arguments, 1)? != 0;
+    let time = long_arg(&arguments, 2)?;
+    let deadline = match (absolute, time) {
+        (true, millis) => {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as i64;
+            Some(Instant::now() + Duration::from_millis(millis.saturating_sub(now).max(0) as u64))
+        },
+        (false, 0) => None,
+        (false, nanos) if nanos < 0 => return Ok(InvokeResult::Void),
+        (false, nanos) => Some(Instant::now() + Duration::from_nanos(nanos as u64))
+    };
+    let this = class_table.get_thread_object();
+    let interrupted = || this.as_ref().is_some_and(is_interrupted);
+
+    let parker = parker(thread::current().id());
+    class_table.heap.blocking(|| {
+        let mut permit = parker.permit.lock().unwrap();
+        while !*permit && !interrupted() {
+            match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break;
+                    }
+                    permit = parker.changed.wait_timeout(permit, deadline - now).unwrap().0;
+                },
+                None => permit = parker.changed.wait(permit).unwrap()
+            }
+        }
+        *permit = false;
+    });
+    Ok(InvokeResult::Void)
+}
+
+fn unsafe_unpark(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    if let Value::ObjectRef(ref thread) = arguments[1] {
+        wake(class_table, thread, true);
+    }
+    Ok(InvokeResult::Void)
+}
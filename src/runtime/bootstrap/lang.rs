@@ -0,0 +1,1030 @@
+use runtime::{Value, Elements};
+use runtime::class::{ClassTable, OBJECT_CLASS_NAME, CLASS_CLASS_NAME};
+use runtime::interpreter::{self, InvokeResult, InterpreterError};
+use runtime::bootstrap::{self, ClassBuilder, io, reflect, object_arg, int_arg, long_arg, float_arg, double_arg, string_arg,
+                         throw_new, return_string, identity_hash_code};
+use runtime::gc;
+use runtime::monitor;
+use runtime::string;
+use class::field;
+use class::method::{ACC_PUBLIC, ACC_STATIC, ACC_PROTECTED, ACC_FINAL, ACC_VARARGS};
+use std::cell::Cell;
+use std::cmp;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH, Instant};
+
+const PUBLIC_STATIC: u16 = ACC_PUBLIC | ACC_STATIC;
+const CONSTANT: u16 = field::ACC_PUBLIC | field::ACC_STATIC | field::ACC_FINAL;
+
+// Boxed values between these bounds are cached by valueOf, see JLS $5.1.7.
+const CACHE_LOW: i32 = -128;
+const CACHE_HIGH: i32 = 127;
+
+pub fn load(class_table: &ClassTable) {
+    ClassBuilder::new(OBJECT_CLASS_NAME)
+        .native("<init>", "()V", ACC_PUBLIC, object_init)
+        .native("hashCode", "()I", ACC_PUBLIC, object_hash_code)
+        .native("equals", "(Ljava/lang/Object;)Z", ACC_PUBLIC, object_equals)
+        .native("toString", "()Ljava/lang/String;", ACC_PUBLIC, object_to_string)
+        .native("getClass", "()Ljava/lang/Class;", ACC_PUBLIC, object_get_class)
+        .native("clone", "()Ljava/lang/Object;", ACC_PROTECTED, object_clone)
+        .native("wait", "()V", ACC_PUBLIC | ACC_FINAL, object_wait)
+        .native("wait", "(J)V", ACC_PUBLIC | ACC_FINAL, object_wait)
+        .native("wait", "(JI)V", ACC_PUBLIC | ACC_FINAL, object_wait)
+        .native("notify", "()V", ACC_PUBLIC | ACC_FINAL, object_notify)
+        .native("notifyAll", "()V", ACC_PUBLIC | ACC_FINAL, object_notify_all)
+        .define(class_table);
+
+    ClassBuilder::interface("java/io/Serializable").define(class_table);
+    ClassBuilder::interface("java/lang/Cloneable").define(class_table);
+    ClassBuilder::interface("java/lang/CharSequence").define(class_table);
+    ClassBuilder::interface("java/lang/Comparable").define(class_table);
+
+    ClassBuilder::new(CLASS_CLASS_NAME)
+        .implements("java/io/Serializable")
+        .field("name", "Ljava/lang/String;", field::ACC_PRIVATE)
+        .native("getName", "()Ljava/lang/String;", ACC_PUBLIC, class_get_name)
+        .native("toString", "()Ljava/lang/String;", ACC_PUBLIC, class_to_string)
+        .native("desiredAssertionStatus", "()Z", ACC_PUBLIC, class_desired_assertion_status)
+        .native("forName", "(Ljava/lang/String;)Ljava/lang/Class;", PUBLIC_STATIC, reflect::class_for_name)
+        .native("getSimpleName", "()Ljava/lang/String;", ACC_PUBLIC, reflect::class_get_simple_name)
+        .native("getModifiers", "()I", ACC_PUBLIC, reflect::class_get_modifiers)
+        .native("getSuperclass", "()Ljava/lang/Class;", ACC_PUBLIC, reflect::class_get_superclass)
+        .native("getInterfaces", "()[Ljava/lang/Class;", ACC_PUBLIC, reflect::class_get_interfaces)
+        .native("getComponentType", "()Ljava/lang/Class;", ACC_PUBLIC, reflect::class_get_component_type)
+        .native("isInterface", "()Z", ACC_PUBLIC, reflect::class_is_interface)
+        .native("isAnnotation", "()Z", ACC_PUBLIC, reflect::class_is_annotation)
+        .native("isArray", "()Z", ACC_PUBLIC, reflect::class_is_array)
+        .native("isPrimitive", "()Z", ACC_PUBLIC, reflect::class_is_primitive)
+        .native("isInstance", "(Ljava/lang/Object;)Z", ACC_PUBLIC, reflect::class_is_instance)
+        .native("isAssignableFrom", "(Ljava/lang/Class;)Z", ACC_PUBLIC, reflect::class_is_assignable_from)
+        .native("getFields", "()[Ljava/lang/reflect/Field;", ACC_PUBLIC, reflect::class_get_fields)
+        .native("getDeclaredFields", "()[Ljava/lang/reflect/Field;", ACC_PUBLIC, reflect::class_get_declared_fields)
+        .native("getField", "(Ljava/lang/String;)Ljava/lang/reflect/Field;", ACC_PUBLIC, reflect::class_get_field)
+        .native("getDeclaredField", "(Ljava/lang/String;)Ljava/lang/reflect/Field;", ACC_PUBLIC, reflect::class_get_declared_field)
+        .native("getMethods", "()[Ljava/lang/reflect/Method;", ACC_PUBLIC, reflect::class_get_methods)
+        .native("getDeclaredMethods", "()[Ljava/lang/reflect/Method;", ACC_PUBLIC, reflect::class_get_declared_methods)
+        .native("getMethod", "(Ljava/lang/String;[Ljava/lang/Class;)Ljava/lang/reflect/Method;", ACC_PUBLIC | ACC_VARARGS,
+                reflect::class_get_method)
+        .native("getDeclaredMethod", "(Ljava/lang/String;[Ljava/lang/Class;)Ljava/lang/reflect/Method;", ACC_PUBLIC | ACC_VARARGS,
+                reflect::class_get_declared_method)
+        .native("getConstructors", "()[Ljava/lang/reflect/Constructor;", ACC_PUBLIC, reflect::class_get_constructors)
+        .native("getDeclaredConstructors", "()[Ljava/lang/reflect/Constructor;", ACC_PUBLIC, reflect::class_get_declared_constructors)
+        .native("getConstructor", "([Ljava/lang/Class;)Ljava/lang/reflect/Constructor;", ACC_PUBLIC | ACC_VARARGS,
+                reflect::class_get_constructor)
+        .native("getDeclaredConstructor", "([Ljava/lang/Class;)Ljava/lang/reflect/Constructor;", ACC_PUBLIC | ACC_VARARGS,
+                reflect::class_get_declared_constructor)
+        .native("getAnnotation", "(Ljava/lang/Class;)Ljava/lang/annotation/Annotation;", ACC_PUBLIC, reflect::class_get_annotation)
+        .native("isAnnotationPresent", "(Ljava/lang/Class;)Z", ACC_PUBLIC, reflect::class_is_annotation_present)
+        .native("getAnnotations", "()[Ljava/lang/annotation/Annotation;", ACC_PUBLIC, reflect::class_get_annotations)
+        .native("getDeclaredAnnotations", "()[Ljava/lang/annotation/Annotation;", ACC_PUBLIC, reflect::class_get_declared_annotations)
+        .define(class_table);
+
+    ClassBuilder::new("java/lang/System")
+        .field("out", "Ljava/io/PrintStream;", CONSTANT)
+        .field("err", "Ljava/io/PrintStream;", CONSTANT)
+        .native("currentTimeMillis", "()J", PUBLIC_STATIC, system_current_time_millis)
+        .native("nanoTime", "()J", PUBLIC_STATIC, system_nano_time)
+        .native("arraycopy", "(Ljava/lang/Object;ILjava/lang/Object;II)V", PUBLIC_STATIC, system_arraycopy)
+        .native("exit", "(I)V", PUBLIC_STATIC, system_exit)
+        .native("gc", "()V", PUBLIC_STATIC, system_gc)
+        .native("identityHashCode", "(Ljava/lang/Object;)I", PUBLIC_STATIC, system_identity_hash_code)
+        .native("lineSeparator", "()Ljava/lang/String;", PUBLIC_STATIC, system_line_separator)
+        .native("getProperty", "(Ljava/lang/String;)Ljava/lang/String;", PUBLIC_STATIC, system_get_property)
+        .native("getProperty", "(Ljava/lang/String;Ljava/lang/String;)Ljava/lang/String;", PUBLIC_STATIC, system_get_property_default)
+        .native("setProperty", "(Ljava/lang/String;Ljava/lang/String;)Ljava/lang/String;", PUBLIC_STATIC, system_set_property)
+        .define(class_table);
+
+    // javac checks the receiver of a bound method reference such as `hello::greet` with
+    // Objects.requireNonNull. The other methods are the null-safe helpers programs call most.
+    // With --java-home the stub is not loaded, and the class from java.base takes its place.
+    ClassBuilder::new("java/util/Objects")
+        .native("requireNonNull", "(Ljava/lang/Object;)Ljava/lang/Object;", PUBLIC_STATIC, objects_require_non_null)
+        .native("requireNonNull", "(Ljava/lang/Object;Ljava/lang/String;)Ljava/lang/Object;", PUBLIC_STATIC, objects_require_non_null)
+        .native("checkIndex", "(II)I", PUBLIC_STATIC, objects_check_index)
+        .native("equals", "(Ljava/lang/Object;Ljava/lang/Object;)Z", PUBLIC_STATIC, objects_equals)
+        .native("hashCode", "(Ljava/lang/Object;)I", PUBLIC_STATIC, objects_hash_code)
+        .native("toString", "(Ljava/lang/Object;)Ljava/lang/String;", PUBLIC_STATIC, objects_to_string)
+        .define(class_table);
+
+    ClassBuilder::new("java/lang/Math")
+        .field("PI", "D", CONSTANT)
+        .field("E", "D", CONSTANT)
+        .native("abs", "(I)I", PUBLIC_STATIC, math_abs_int)
+        .native("abs", "(J)J", PUBLIC_STATIC, math_abs_long)
+        .native("abs", "(F)F", PUBLIC_STATIC, math_abs_float)
+        .native("abs", "(D)D", PUBLIC_STATIC, math_abs_double)
+        .native("max", "(II)I", PUBLIC_STATIC, math_max_int)
+        .native("max", "(JJ)J", PUBLIC_STATIC, math_max_long)
+        .native("max", "(FF)F", PUBLIC_STATIC, math_max_float)
+        .native("max", "(DD)D", PUBLIC_STATIC, math_max_double)
+        .native("min", "(II)I", PUBLIC_STATIC, math_min_int)
+        .native("min", "(JJ)J", PUBLIC_STATIC, math_min_long)
+        .native("min", "(FF)F", PUBLIC_STATIC, math_min_float)
+        .native("min", "(DD)D", PUBLIC_STATIC, math_min_double)
+        .native("sqrt", "(D)D", PUBLIC_STATIC, math_sqrt)
+        .native("cbrt", "(D)D", PUBLIC_STATIC, math_cbrt)
+        .native("pow", "(DD)D", PUBLIC_STATIC, math_pow)
+        .native("floor", "(D)D", PUBLIC_STATIC, math_floor)
+        .native("ceil", "(D)D", PUBLIC_STATIC, math_ceil)
+        .native("round", "(F)I", PUBLIC_STATIC, math_round_float)
+        .native("round", "(D)J", PUBLIC_STATIC, math_round_double)
+        .native("random", "()D", PUBLIC_STATIC, math_random)
+        .native("sin", "(D)D", PUBLIC_STATIC, math_sin)
+        .native("cos", "(D)D", PUBLIC_STATIC, math_cos)
+        .native("tan", "(D)D", PUBLIC_STATIC, math_tan)
+        .native("atan2", "(DD)D", PUBLIC_STATIC, math_atan2)
+        .native("log", "(D)D", PUBLIC_STATIC, math_log)
+        .native("log10", "(D)D", PUBLIC_STATIC, math_log10)
+        .native("exp", "(D)D", PUBLIC_STATIC, math_exp)
+        .define(class_table);
+    let math = class_table.get_class("java/lang/Math").unwrap();
+    math.put_static("PI", Value::Double(::std::f64::consts::PI));
+    math.put_static("E", Value::Double(::std::f64::consts::E));
+
+    ClassBuilder::new("java/lang/Enum")
+        .implements("java/lang/Comparable")
+        .implements("java/io/Serializable")
+        .field("name", "Ljava/lang/String;", field::ACC_PRIVATE | field::ACC_FINAL)
+        .field("ordinal", "I", field::ACC_PRIVATE | field::ACC_FINAL)
+        .native("<init>", "(Ljava/lang/String;I)V", ACC_PROTECTED, enum_init)
+        .native("name", "()Ljava/lang/String;", ACC_PUBLIC | ACC_FINAL, enum_name)
+        .native("ordinal", "()I", ACC_PUBLIC | ACC_FINAL, enum_ordinal)
+        .native("toString", "()Ljava/lang/String;", ACC_PUBLIC, enum_name)
+        .define(class_table);
+
+    ClassBuilder::new("java/lang/Number")
+        .implements("java/io/Serializable")
+        .native("<init>", "()V", ACC_PUBLIC, object_init)
+        .define(class_table);
+
+    let integer = ClassBuilder::new("java/lang/Integer")
+        .super_class("java/lang/Number")
+        .implements("java/lang/Comparable")
+        .field("value", "I", field::ACC_PRIVATE | field::ACC_FINAL)
+        .field("MIN_VALUE", "I", CONSTANT)
+        .field("TYPE", "Ljava/lang/Class;", CONSTANT)
+        .field("MAX_VALUE", "I", CONSTANT)
+        .field("cache", "[Ljava/lang/Integer;", field::ACC_PRIVATE | field::ACC_STATIC | field::ACC_FINAL)
+        .native("<init>", "(I)V", ACC_PUBLIC, integer_init)
+        .native("valueOf", "(I)Ljava/lang/Integer;", PUBLIC_STATIC, integer_value_of)
+        .native("valueOf", "(Ljava/lang/String;)Ljava/lang/Integer;", PUBLIC_STATIC, integer_value_of_string)
+        .native("parseInt", "(Ljava/lang/String;)I", PUBLIC_STATIC, integer_parse_int)
+        .native("parseInt", "(Ljava/lang/String;I)I", PUBLIC_STATIC, integer_parse_int_radix)
+        .native("toString", "(I)Ljava/lang/String;", PUBLIC_STATIC, integer_to_string_static)
+        .native("toHexString", "(I)Ljava/lang/String;", PUBLIC_STATIC, integer_to_hex_string)
+        .native("toBinaryString", "(I)Ljava/lang/String;", PUBLIC_STATIC, integer_to_binary_string)
+        .native("compare", "(II)I", PUBLIC_STATIC, integer_compare)
+        .native("sum", "(II)I", PUBLIC_STATIC, integer_sum)
+        .native("max", "(II)I", PUBLIC_STATIC, integer_max)
+        .native("min", "(II)I", PUBLIC_STATIC, integer_min)
+        .native("intValue", "()I", ACC_PUBLIC, integer_int_value)
+        .native("longValue", "()J", ACC_PUBLIC, integer_long_value)
+        .native("doubleValue", "()D", ACC_PUBLIC, integer_double_value)
+        .native("toString", "()Ljava/lang/String;", ACC_PUBLIC, integer_to_string)
+        .native("hashCode", "()I", ACC_PUBLIC, integer_int_value)
+        .native("equals", "(Ljava/lang/Object;)Z", ACC_PUBLIC, integer_equals)
+        .native("compareTo", "(Ljava/lang/Integer;)I", ACC_PUBLIC, integer_compare_to)
+        .native("compareTo", "(Ljava/lang/Object;)I", ACC_PUBLIC, integer_compare_to)
+        .define(class_table);
+    integer.put_static("MIN_VALUE", Value::Integer(i32::MIN));
+    integer.put_static("MAX_VALUE", Value::Integer(i32::MAX));
+    let cache = class_table.new_array("Ljava/lang/Integer;", (CACHE_HIGH - CACHE_LOW + 1) as usize);
+    for value in CACHE_LOW..=CACHE_HIGH {
+        let boxed = new_boxed(class_table, "java/lang/Integer", Value::Integer(value));
+        cache.borrow_mut().set((value - CACHE_LOW) as usize, boxed).unwrap();
+    }
+    integer.put_static("cache", Value::ArrayRef(cache));
+
+    let long = ClassBuilder::new("java/lang/Long")
+        .super_class("java/lang/Number")
+        .implements("java/lang/Comparable")
+        .field("value", "J", field::ACC_PRIVATE | field::ACC_FINAL)
+        .field("MIN_VALUE", "J", CONSTANT)
+        .field("TYPE", "Ljava/lang/Class;", CONSTANT)
+        .field("MAX_VALUE", "J", CONSTANT)
+        .native("<init>", "(J)V", ACC_PUBLIC, long_init)
+        .native("valueOf", "(J)Ljava/lang/Long;", PUBLIC_STATIC, long_value_of)
+        .native("parseLong", "(Ljava/lang/String;)J", PUBLIC_STATIC, long_parse_long)
+        .native("toString", "(J)Ljava/lang/String;", PUBLIC_STATIC, long_to_string_static)
+        .native("toHexString", "(J)Ljava/lang/String;", PUBLIC_STATIC, long_to_hex_string)
+        .native("toBinaryString", "(J)Ljava/lang/String;", PUBLIC_STATIC, long_to_binary_string)
+        .native("compare", "(JJ)I", PUBLIC_STATIC, long_compare)
+        .native("sum", "(JJ)J", PUBLIC_STATIC, long_sum)
+        .native("max", "(JJ)J", PUBLIC_STATIC, long_max)
+        .native("min", "(JJ)J", PUBLIC_STATIC, long_min)
+        .native("intValue", "()I", ACC_PUBLIC, long_int_value)
+        .native("longValue", "()J", ACC_PUBLIC, long_long_value)
+        .native("doubleValue", "()D", ACC_PUBLIC, long_double_value)
+        .native("toString", "()Ljava/lang/String;", ACC_PUBLIC, long_to_string)
+        .native("hashCode", "()I", ACC_PUBLIC, long_hash_code)
+        .native("equals", "(Ljava/lang/Object;)Z", ACC_PUBLIC, long_equals)
+        .native("compareTo", "(Ljava/lang/Long;)I", ACC_PUBLIC, long_compare_to)
+        .native("compareTo", "(Ljava/lang/Object;)I", ACC_PUBLIC, long_compare_to)
+        .define(class_table);
+    long.put_static("MIN_VALUE", Value::Long(i64::MIN));
+    long.put_static("MAX_VALUE", Value::Long(i64::MAX));
+}
+
+// System.out and System.err can only be created once java.io.PrintStream has been loaded.
+pub fn load_system_streams(class_table: &ClassTable) {
+    let system = class_table.get_class("java/lang/System").unwrap();
+    system.put_static("out", io::stdout(class_table));
+    system.put_static("err", io::stderr(class_table));
+}
+
+fn new_boxed(class_table: &ClassTable, class_name: &str, value: Value) -> Value {
+    let class = class_table.get_class(class_name).unwrap();
+    let boxed = class_table.new_object(&class);
+    boxed.borrow_mut().put_field(String::from("value"), value);
+    Value::ObjectRef(boxed)
+}
+
+fn boxed_value(arguments: &[Value], index: usize) -> Result<Value, InterpreterError> {
+    let boxed = object_arg(arguments, index)?;
+    let value = boxed.borrow().get_field(String::from("value"));
+    Ok(value)
+}
+
+// java.lang.Object
+
+fn object_init(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Void)
+}
+
+pub fn object_hash_code(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Integer(identity_hash_code(&arguments[0]))))
+}
+
+fn object_equals(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let equal = arguments[0].same_reference(&arguments[1]);
+    Ok(InvokeResult::Value(Value::Integer(equal as i32)))
+}
+
+// The class name followed by the unsigned hexadecimal hash code, e.g. "Counter@1b6d3586".
+fn object_to_string(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = arguments[0].clone();
+    let hash_code = match java_try!(bootstrap::call(class_table, this.clone(), "hashCode", "()I", Vec::new())) {
+        Some(Value::Integer(hash_code)) => hash_code,
+        _ => return Err(InterpreterError::UnexpectedOperand)
+    };
+
+    let string = format!("{}@{:x}", class_name_of(&this).replace('/', "."), hash_code as u32);
+    return_string(class_table, &string)
+}
+
+pub fn object_get_class(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let mirror = class_table.get_mirror(&class_name_of(&arguments[0]));
+    Ok(InvokeResult::Value(Value::ObjectRef(mirror)))
+}
+
+pub fn object_clone(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    match arguments[0] {
+        Value::ArrayRef(ref array) => {
+            let size = {
+                let array = array.borrow();
+                gc::array_size(array.component_type(), array.len())
+            };
+            if !class_table.reserve(size) {
+                return throw_new(class_table, "java/lang/OutOfMemoryError", Some(gc::OUT_OF_MEMORY));
+            }
+
+            let copy = class_table.clone_array(array);
+            Ok(InvokeResult::Value(Value::ArrayRef(copy)))
+        },
+        Value::ObjectRef(ref object) => {
+            let class = object.borrow().class().clone();
+            if !class.is_subclass_of("java/lang/Cloneable") {
+                let class_name = class.class_name.replace('/', ".");
+                return throw_new(class_table, "java/lang/CloneNotSupportedException", Some(&class_name));
+            }
+            if !class_table.reserve(gc::object_size(class.instance_fields.len())) {
+                return throw_new(class_table, "java/lang/OutOfMemoryError", Some(gc::OUT_OF_MEMORY));
+            }
+
+            let copy = class_table.clone_object(object);
+            Ok(InvokeResult::Value(Value::ObjectRef(copy)))
+        },
+        _ => Err(InterpreterError::UnexpectedOperand)
+    }
+}
+
+// wait, notify and notifyAll may only be called by the thread holding the monitor.
+fn holds_monitor(value: &Value) -> bool {
+    monitor::with_monitor(value, |monitor| monitor.is_held()).unwrap_or(false)
+}
+
+// A timeout of zero means waiting until notified.
+pub fn object_wait(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    if !holds_monitor(&arguments[0]) {
+        return throw_new(class_table, "java/lang/IllegalMonitorStateException", Some("current thread is not owner"));
+    }
+
+    let millis = if arguments.len() > 1 { long_arg(&arguments, 1)? } else { 0 };
+    let nanos = if arguments.len() > 2 { int_arg(&arguments, 2)? } else { 0 };
+    if millis < 0 {
+        return throw_new(class_table, "java/lang/IllegalArgumentException", Some("timeout value is negative"));
+    }
+    if !(0..=999_999).contains(&nanos) {
+        return throw_new(class_table, "java/lang/IllegalArgumentException", Some("nanosecond timeout value out of range"));
+    }
+
+    let timeout = Duration::from_millis(millis as u64) + Duration::from_nanos(nanos as u64);
+    let timeout = if timeout.is_zero() { None } else { Some(timeout) };
+    class_table.heap.blocking(|| monitor::with_monitor(&arguments[0], |monitor| monitor.wait(timeout)));
+    Ok(InvokeResult::Void)
+}
+
+pub fn object_notify(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    notify(class_table, &arguments[0], false)
+}
+
+pub fn object_notify_all(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    notify(class_table, &arguments[0], true)
+}
+
+fn notify(class_table: &ClassTable, object: &Value, all: bool) -> Result<InvokeResult, InterpreterError> {
+    match monitor::with_monitor(object, |monitor| monitor.notify(all)) {
+        Some(true) => Ok(InvokeResult::Void),
+        _ => throw_new(class_table, "java/lang/IllegalMonitorStateException", Some("current thread is not owner"))
+    }
+}
+
+// The internal name of the class of a non-null reference. Array classes are named by their
+// descriptor.
+fn class_name_of(value: &Value) -> String {
+    match value {
+        Value::ObjectRef(object) => object.borrow().class().class_name.clone(),
+        Value::ArrayRef(array) => array.borrow().descriptor(),
+        _ => String::from(OBJECT_CLASS_NAME)
+    }
+}
+
+// java.lang.Class
+
+fn class_get_name(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let mirror = object_arg(&arguments, 0)?;
+    let name = mirror.borrow().get_field(String::from("name"));
+    Ok(InvokeResult::Value(name))
+}
+
+// Assertions are disabled, as they are in the JDK without -ea. Classes with assert statements
+// read this in their static initializer.
+fn class_desired_assertion_status(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Integer(0)))
+}
+
+fn class_to_string(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let mirror = object_arg(&arguments, 0)?;
+    let name = match mirror.borrow().get_field(String::from("name")) {
+        Value::ObjectRef(name) => string::to_rust_string(&name.borrow()),
+        _ => return Err(InterpreterError::UnexpectedOperand)
+    };
+
+    // Primitive types are named without a kind, e.g. int.
+    if !name.contains('.') && class_table.get_class(&name).is_none() && !name.starts_with('[') {
+        return return_string(class_table, &name);
+    }
+
+    let is_interface = class_table.get_class(&name.replace('.', "/"))
+        .map(|class| class.is_interface())
+        .unwrap_or(false);
+    let kind = if is_interface { "interface" } else { "class" };
+
+    return_string(class_table, &format!("{} {}", kind, name))
+}
+
+// java.lang.Enum
+
+fn enum_init(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let mut this = this.borrow_mut();
+    this.put_field(String::from("name"), arguments[1].clone());
+    this.put_field(String::from("ordinal"), Value::Integer(int_arg(&arguments, 2)?));
+    Ok(InvokeResult::Void)
+}
+
+fn enum_name(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let name = this.borrow().get_field(String::from("name"));
+    Ok(InvokeResult::Value(name))
+}
+
+fn enum_ordinal(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let ordinal = this.borrow().get_field(String::from("ordinal"));
+    Ok(InvokeResult::Value(ordinal))
+}
+
+// java.util.Objects
+
+fn objects_require_non_null(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    match arguments[0] {
+        Value::Null => {
+            let message = match string_arg(&arguments, 1) {
+                Ok(Some(chars)) => Some(String::from_utf16_lossy(&chars)),
+                _ => None
+            };
+            throw_new(class_table, "java/lang/NullPointerException", message.as_deref())
+        },
+        ref object => Ok(InvokeResult::Value(object.clone()))
+    }
+}
+
+fn objects_check_index(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let index = int_arg(&arguments, 0)?;
+    let length = int_arg(&arguments, 1)?;
+    if index < 0 || index >= length {
+        let message = format!("Index {} out of bounds for length {}", index, length);
+        return throw_new(class_table, "java/lang/IndexOutOfBoundsException", Some(&message));
+    }
+    int_result(index)
+}
+
+fn objects_equals(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    if arguments[0].same_reference(&arguments[1]) {
+        return int_result(1);
+    }
+    if let Value::Null = arguments[0] {
+        return int_result(0);
+    }
+    let equals = java_try!(bootstrap::call(class_table, arguments[0].clone(), "equals", "(Ljava/lang/Object;)Z", vec![arguments[1].clone()]));
+    Ok(InvokeResult::Value(equals.ok_or(InterpreterError::UnexpectedOperand)?))
+}
+
+fn objects_hash_code(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    if let Value::Null = arguments[0] {
+        return int_result(0);
+    }
+    let hash_code = java_try!(bootstrap::call(class_table, arguments[0].clone(), "hashCode", "()I", Vec::new()));
+    Ok(InvokeResult::Value(hash_code.ok_or(InterpreterError::UnexpectedOperand)?))
+}
+
+fn objects_to_string(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let chars = java_try!(bootstrap::to_java_chars(class_table, &arguments[0]));
+    Ok(InvokeResult::Value(bootstrap::new_string(class_table, chars)))
+}
+
+// java.lang.System
+
+pub fn system_current_time_millis(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as i64)
+        .unwrap_or(0);
+    Ok(InvokeResult::Value(Value::Long(millis)))
+}
+
+// The origin of System.nanoTime, which is the same for all threads.
+static EPOCH: OnceLock<Instant> = OnceLock::new();
+
+thread_local! {
+    static RANDOM_STATE: Cell<u64> = const { Cell::new(0) };
+}
+
+pub fn system_nano_time(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let nanos = EPOCH.get_or_init(Instant::now).elapsed().as_nanos() as i64;
+    Ok(InvokeResult::Value(Value::Long(nanos)))
+}
+
+pub fn system_arraycopy(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let (source, destination) = match (&arguments[0], &arguments[2]) {
+        (Value::ArrayRef(source), Value::ArrayRef(destination)) => (source.clone(), destination.clone()),
+        (Value::Null, _) | (_, Value::Null) => return throw_new(class_table, "java/lang/NullPointerException", None),
+        _ => return throw_new(class_table, "java/lang/ArrayStoreException", Some("arraycopy: argument type mismatch"))
+    };
+    let source_position = int_arg(&arguments, 1)?;
+    let destination_position = int_arg(&arguments, 3)?;
+    let length = int_arg(&arguments, 4)?;
+
+    let source_length = source.borrow().len() as i64;
+    let destination_length = destination.borrow().len() as i64;
+    if source_position < 0 || destination_position < 0 || length < 0 ||
+        source_position as i64 + length as i64 > source_length ||
+        destination_position as i64 + length as i64 > destination_length {
+        return throw_new(class_table, "java/lang/ArrayIndexOutOfBoundsException", Some("arraycopy: last source index out of bounds"));
+    }
+
+    // Copying through a temporary buffer handles overlapping ranges within the same array.
+    let values: Vec<Value> = {
+        let source = source.borrow();
+        (0..length).map(|i| source.get((source_position + i) as usize)).collect()
+    };
+
+    // The exception is thrown once the destination is no longer borrowed, as allocating it may
+    // collect garbage.
+    let stored = {
+        let mut destination = destination.borrow_mut();
+        let start = destination_position as usize;
+        let stored = values.into_iter()
+            .enumerate()
+            .all(|(i, value)| destination.set(start + i, value).is_ok());
+        if let Elements::Reference(elements) = destination.elements() {
+            class_table.heap.write_barrier_range(&elements[start..start + length as usize]);
+        }
+        stored
+    };
+    if !stored {
+        return throw_new(class_table, "java/lang/ArrayStoreException", Some("arraycopy: type mismatch"));
+    }
+
+    Ok(InvokeResult::Void)
+}
+
+pub fn system_gc(class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    class_table.collect_garbage();
+    Ok(InvokeResult::Void)
+}
+
+fn system_exit(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let status = int_arg(&arguments, 0)?;
+    class_table.console.flush();
+    Err(InterpreterError::Exit(status))
+}
+
+pub fn system_identity_hash_code(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Integer(identity_hash_code(&arguments[0]))))
+}
+
+fn system_line_separator(class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    return_string(class_table, "\n")
+}
+
+// Returns the name of a property as a Rust string, or the NullPointerException to throw.
+fn property_name(class_table: &ClassTable, arguments: &[Value]) -> Result<Result<String, Value>, InterpreterError> {
+    match string_arg(arguments, 0)? {
+        Some(name) if name.is_empty() => {
+            let exception = interpreter::new_throwable(class_table, "java/lang/IllegalArgumentException", Some("key can't be empty"))?;
+            Ok(Err(exception))
+        },
+        Some(name) => Ok(Ok(String::from_utf16_lossy(&name))),
+        None => {
+            let exception = interpreter::new_throwable(class_table, "java/lang/NullPointerException", Some("key can't be null"))?;
+            Ok(Err(exception))
+        }
+    }
+}
+
+fn optional_string(class_table: &ClassTable, value: Option<String>) -> Result<InvokeResult, InterpreterError> {
+    match value {
+        Some(value) => return_string(class_table, &value),
+        None => Ok(InvokeResult::Value(Value::Null))
+    }
+}
+
+fn system_get_property(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let name = java_try!(property_name(class_table, &arguments));
+    optional_string(class_table, class_table.get_property(&name))
+}
+
+fn system_get_property_default(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let name = java_try!(property_name(class_table, &arguments));
+    match class_table.get_property(&name) {
+        Some(value) => return_string(class_table, &value),
+        None => Ok(InvokeResult::Value(arguments[1].clone()))
+    }
+}
+
+fn system_set_property(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let name = java_try!(property_name(class_table, &arguments));
+    let value = match string_arg(&arguments, 1)? {
+        Some(value) => String::from_utf16_lossy(&value),
+        None => return throw_new(class_table, "java/lang/NullPointerException", Some("value can't be null"))
+    };
+
+    optional_string(class_table, class_table.set_property(&name, &value))
+}
+
+// java.lang.Math
+
+fn int_result(value: i32) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Integer(value)))
+}
+
+fn long_result(value: i64) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Long(value)))
+}
+
+fn float_result(value: f32) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Float(value)))
+}
+
+fn double_result(value: f64) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Double(value)))
+}
+
+fn math_abs_int(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    int_result(int_arg(&arguments, 0)?.wrapping_abs())
+}
+
+fn math_abs_long(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    long_result(long_arg(&arguments, 0)?.wrapping_abs())
+}
+
+fn math_abs_float(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    float_result(float_arg(&arguments, 0)?.abs())
+}
+
+fn math_abs_double(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    double_result(double_arg(&arguments, 0)?.abs())
+}
+
+fn math_max_int(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    int_result(int_arg(&arguments, 0)?.max(int_arg(&arguments, 1)?))
+}
+
+fn math_max_long(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    long_result(long_arg(&arguments, 0)?.max(long_arg(&arguments, 1)?))
+}
+
+// Unlike f32::max, Math.max returns NaN if either argument is NaN.
+fn math_max_float(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let (a, b) = (float_arg(&arguments, 0)?, float_arg(&arguments, 1)?);
+    float_result(if a.is_nan() || b.is_nan() { f32::NAN } else { a.max(b) })
+}
+
+fn math_max_double(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let (a, b) = (double_arg(&arguments, 0)?, double_arg(&arguments, 1)?);
+    double_result(if a.is_nan() || b.is_nan() { f64::NAN } else { a.max(b) })
+}
+
+fn math_min_int(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    int_result(int_arg(&arguments, 0)?.min(int_arg(&arguments, 1)?))
+}
+
+fn math_min_long(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    long_result(long_arg(&arguments, 0)?.min(long_arg(&arguments, 1)?))
+}
+
+fn math_min_float(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let (a, b) = (float_arg(&arguments, 0)?, float_arg(&arguments, 1)?);
+    float_result(if a.is_nan() || b.is_nan() { f32::NAN } else { a.min(b) })
+}
+
+fn math_min_double(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let (a, b) = (double_arg(&arguments, 0)?, double_arg(&arguments, 1)?);
+    double_result(if a.is_nan() || b.is_nan() { f64::NAN } else { a.min(b) })
+}
+
+fn math_sqrt(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    double_result(double_arg(&arguments, 0)?.sqrt())
+}
+
+fn math_cbrt(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    double_result(double_arg(&arguments, 0)?.cbrt())
+}
+
+fn math_pow(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    double_result(double_arg(&arguments, 0)?.powf(double_arg(&arguments, 1)?))
+}
+
+fn math_floor(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    double_result(double_arg(&arguments, 0)?.floor())
+}
+
+fn math_ceil(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    double_result(double_arg(&arguments, 0)?.ceil())
+}
+
+// Math.round rounds half up, so Math.round(-2.5) is -2 rather than -3.
+fn math_round_float(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    int_result((float_arg(&arguments, 0)? + 0.5).floor() as i32)
+}
+
+fn math_round_double(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    long_result((double_arg(&arguments, 0)? + 0.5).floor() as i64)
+}
+
+// A xorshift generator seeded from the clock. Math.random makes no guarantees about its
+// algorithm, only that the result is in [0, 1).
+fn math_random(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let next = RANDOM_STATE.with(|state| {
+        let mut x = state.get();
+        if x == 0 {
+            x = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_nanos() as u64)
+                .unwrap_or(0x2545_f491_4f6c_dd1d) | 1;
+        }
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        state.set(x);
+        x
+    });
+
+    double_result((next >> 11) as f64 / (1u64 << 53) as f64)
+}
+
+fn math_sin(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    double_result(double_arg(&arguments, 0)?.sin())
+}
+
+fn math_cos(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    double_result(double_arg(&arguments, 0)?.cos())
+}
+
+fn math_tan(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    double_result(double_arg(&arguments, 0)?.tan())
+}
+
+fn math_atan2(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    double_result(double_arg(&arguments, 0)?.atan2(double_arg(&arguments, 1)?))
+}
+
+fn math_log(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    double_result(double_arg(&arguments, 0)?.ln())
+}
+
+fn math_log10(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    double_result(double_arg(&arguments, 0)?.log10())
+}
+
+fn math_exp(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    double_result(double_arg(&arguments, 0)?.exp())
+}
+
+// java.lang.Integer
+
+fn integer_init(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    this.borrow_mut().put_field(String::from("value"), Value::Integer(int_arg(&arguments, 1)?));
+    Ok(InvokeResult::Void)
+}
+
+pub fn box_integer(class_table: &ClassTable, value: i32) -> Value {
+    if (CACHE_LOW..=CACHE_HIGH).contains(&value) {
+        let integer = class_table.get_class("java/lang/Integer").unwrap();
+        if let Some(Value::ArrayRef(cache)) = integer.get_static("cache") {
+            return cache.borrow().get((value - CACHE_LOW) as usize);
+        }
+    }
+
+    new_boxed(class_table, "java/lang/Integer", Value::Integer(value))
+}
+
+fn integer_value_of(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let value = int_arg(&arguments, 0)?;
+    Ok(InvokeResult::Value(box_integer(class_table, value)))
+}
+
+// Parses a string like Integer.parseInt, returning None if it is not a valid number.
+fn parse_integer(chars: &[u16], radix: u32, min: i64, max: i64) -> Option<i64> {
+    let string = String::from_utf16(chars).ok()?;
+    let (negative, digits) = match string.chars().next() {
+        Some('-') => (true, &string[1..]),
+        Some('+') => (false, &string[1..]),
+        _ => (false, &string[..])
+    };
+    if digits.is_empty() {
+        return None;
+    }
+
+    let mut result: i128 = 0;
+    for c in digits.chars() {
+        result = result * radix as i128 + c.to_digit(radix)? as i128;
+        if result > max as i128 + 1 {
+            return None;
+        }
+    }
+    let result = if negative { -result } else { result };
+
+    if result < min as i128 || result > max as i128 {
+        None
+    } else {
+        Some(result as i64)
+    }
+}
+
+fn number_format_exception(class_table: &ClassTable, chars: Option<&Vec<u16>>) -> Result<InvokeResult, InterpreterError> {
+    let message = match chars {
+        Some(chars) => format!("For input string: \"{}\"", String::from_utf16_lossy(chars)),
+        None => String::from("Cannot parse null string: null")
+    };
+    throw_new(class_table, "java/lang/NumberFormatException", Some(&message))
+}
+
+fn parse_int_arguments(class_table: &ClassTable, arguments: &[Value], radix: u32) -> Result<Result<i32, InvokeResult>, InterpreterError> {
+    let chars = string_arg(arguments, 0)?;
+    let parsed = chars.as_ref()
+        .and_then(|chars| parse_integer(chars, radix, i32::MIN as i64, i32::MAX as i64));
+
+    match parsed {
+        Some(value) => Ok(Ok(value as i32)),
+        None => Ok(Err(number_format_exception(class_table, chars.as_ref())?))
+    }
+}
+
+fn integer_parse_int(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    match parse_int_arguments(class_table, &arguments, 10)? {
+        Ok(value) => int_result(value),
+        Err(exception) => Ok(exception)
+    }
+}
+
+fn integer_parse_int_radix(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let radix = int_arg(&arguments, 1)?;
+    if !(2..=36).contains(&radix) {
+        let message = format!("radix {} out of range", radix);
+        return throw_new(class_table, "java/lang/NumberFormatException", Some(&message));
+    }
+
+    match parse_int_arguments(class_table, &arguments, radix as u32)? {
+        Ok(value) => int_result(value),
+        Err(exception) => Ok(exception)
+    }
+}
+
+fn integer_value_of_string(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    match parse_int_arguments(class_table, &arguments, 10)? {
+        Ok(value) => Ok(InvokeResult::Value(box_integer(class_table, value))),
+        Err(exception) => Ok(exception)
+    }
+}
+
+fn integer_to_string_static(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    return_string(class_table, &int_arg(&arguments, 0)?.to_string())
+}
+
+fn integer_to_hex_string(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    return_string(class_table, &format!("{:x}", int_arg(&arguments, 0)? as u32))
+}
+
+fn integer_to_binary_string(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    return_string(class_table, &format!("{:b}", int_arg(&arguments, 0)? as u32))
+}
+
+fn integer_compare(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let ordering = int_arg(&arguments, 0)?.cmp(&int_arg(&arguments, 1)?);
+    int_result(ordering as i32)
+}
+
+fn integer_sum(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    int_result(int_arg(&arguments, 0)?.wrapping_add(int_arg(&arguments, 1)?))
+}
+
+fn integer_max(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    int_result(cmp::max(int_arg(&arguments, 0)?, int_arg(&arguments, 1)?))
+}
+
+fn integer_min(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    int_result(cmp::min(int_arg(&arguments, 0)?, int_arg(&arguments, 1)?))
+}
+
+fn integer_int_value(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(boxed_value(&arguments, 0)?))
+}
+
+fn integer_long_value(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    match boxed_value(&arguments, 0)? {
+        Value::Integer(value) => long_result(value as i64),
+        _ => Err(InterpreterError::UnexpectedOperand)
+    }
+}
+
+fn integer_double_value(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    match boxed_value(&arguments, 0)? {
+        Value::Integer(value) => double_result(value as f64),
+        _ => Err(InterpreterError::UnexpectedOperand)
+    }
+}
+
+fn integer_to_string(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    match boxed_value(&arguments, 0)? {
+        Value::Integer(value) => return_string(class_table, &value.to_string()),
+        _ => Err(InterpreterError::UnexpectedOperand)
+    }
+}
+
+// Boxed values are equal if the other object is of the same class and holds the same value.
+fn boxed_equals(class_name: &str, arguments: &[Value]) -> Result<InvokeResult, InterpreterError> {
+    let equal = match arguments[1] {
+        Value::ObjectRef(ref other) if other.borrow().class().class_name == class_name => {
+            let value = boxed_value(arguments, 0)?;
+            let other_value = other.borrow().get_field(String::from("value"));
+            match (value, other_value) {
+                (Value::Integer(a), Value::Integer(b)) => a == b,
+                (Value::Long(a), Value::Long(b)) => a == b,
+                _ => false
+            }
+        },
+        _ => false
+    };
+
+    int_result(equal as i32)
+}
+
+fn integer_equals(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    boxed_equals("java/lang/Integer", &arguments)
+}
+
+fn integer_compare_to(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    if let Value::Null = arguments[1] {
+        return throw_new(class_table, "java/lang/NullPointerException", None);
+    }
+
+    match (boxed_value(&arguments, 0)?, boxed_value(&arguments, 1)?) {
+        (Value::Integer(a), Value::Integer(b)) => int_result(a.cmp(&b) as i32),
+        _ => Err(InterpreterError::UnexpectedOperand)
+    }
+}
+
+// java.lang.Long
+
+fn long_init(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    this.borrow_mut().put_field(String::from("value"), Value::Long(long_arg(&arguments, 1)?));
+    Ok(InvokeResult::Void)
+}
+
+fn long_value_of(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let value = long_arg(&arguments, 0)?;
+    Ok(InvokeResult::Value(new_boxed(class_table, "java/lang/Long", Value::Long(value))))
+}
+
+fn long_parse_long(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let chars = string_arg(&arguments, 0)?;
+    let parsed = chars.as_ref()
+        .and_then(|chars| parse_integer(chars, 10, i64::MIN, i64::MAX));
+
+    match parsed {
+        Some(value) => long_result(value),
+        None => number_format_exception(class_table, chars.as_ref())
+    }
+}
+
+fn long_to_string_static(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    return_string(class_table, &long_arg(&arguments, 0)?.to_string())
+}
+
+fn long_to_hex_string(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    return_string(class_table, &format!("{:x}", long_arg(&arguments, 0)? as u64))
+}
+
+fn long_to_binary_string(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    return_string(class_table, &format!("{:b}", long_arg(&arguments, 0)? as u64))
+}
+
+fn long_compare(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let ordering = long_arg(&arguments, 0)?.cmp(&long_arg(&arguments, 1)?);
+    int_result(ordering as i32)
+}
+
+fn long_sum(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    long_result(long_arg(&arguments, 0)?.wrapping_add(long_arg(&arguments, 1)?))
+}
+
+fn long_max(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    long_result(cmp::max(long_arg(&arguments, 0)?, long_arg(&arguments, 1)?))
+}
+
+fn long_min(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    long_result(cmp::min(long_arg(&arguments, 0)?, long_arg(&arguments, 1)?))
+}
+
+fn long_int_value(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    match boxed_value(&arguments, 0)? {
+        Value::Long(value) => int_result(value as i32),
+        _ => Err(InterpreterError::UnexpectedOperand)
+    }
+}
+
+fn long_long_value(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(boxed_value(&arguments, 0)?))
+}
+
+fn long_double_value(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    match boxed_value(&arguments, 0)? {
+        Value::Long(value) => double_result(value as f64),
+        _ => Err(InterpreterError::UnexpectedOperand)
+    }
+}
+
+fn long_to_string(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    match boxed_value(&arguments, 0)? {
+        Value::Long(value) => return_string(class_table, &value.to_string()),
+        _ => Err(InterpreterError::UnexpectedOperand)
+    }
+}
+
+fn long_hash_code(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    match boxed_value(&arguments, 0)? {
+        Value::Long(value) => int_result((value ^ ((value as u64) >> 32) as i64) as i32),
+        _ => Err(InterpreterError::UnexpectedOperand)
+    }
+}
+
+fn long_equals(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    boxed_equals("java/lang/Long", &arguments)
+}
+
+fn long_compare_to(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    if let Value::Null = arguments[1] {
+        return throw_new(class_table, "java/lang/NullPointerException", None);
+    }
+
+    match (boxed_value(&arguments, 0)?, boxed_value(&arguments, 1)?) {
+        (Value::Long(a), Value::Long(b)) => int_result(a.cmp(&b) as i32),
+        _ => Err(InterpreterError::UnexpectedOperand)
+    }
+}
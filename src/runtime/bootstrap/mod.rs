@@ -0,0 +1,634 @@
+use class::ConstantPool;
+use class::class_flags::{ACC_PUBLIC, ACC_INTERFACE, ACC_ABSTRACT};
+use runtime::{Value, HeapCell, Object};
+use runtime::class::{RuntimeClass, ClassTable, OBJECT_CLASS_NAME};
+use runtime::class::field::RuntimeField;
+use runtime::class::method::{RuntimeMethod, NativeMethod};
+use runtime::interpreter::{self, InvokeResult, InterpreterError};
+use runtime::string;
+use std::sync::Arc;
+
+// Unwraps the result of calling back into Java code from a native method. If the callee threw an
+// exception, it is rethrown to the caller of the native method.
+macro_rules! java_try {
+    ($e:expr) => {
+        match $e? {
+            Ok(value) => value,
+            Err(exception) => return Ok(InvokeResult::Exception(exception))
+        }
+    };
+}
+
+mod annotation;
+pub mod io;
+pub mod jdk;
+mod lang;
+mod reflect;
+mod strings;
+mod thread;
+mod throwable;
+
+pub use self::throwable::fill_in_stack_trace;
+
+// The bootstrap class library is a small subset of java.base implemented in Rust. It is loaded
+// into every class table so that simple programs can run without a JDK installation.
+pub fn load(class_table: &ClassTable) {
+    lang::load(class_table);
+    strings::load(class_table);
+    throwable::load(class_table);
+    reflect::load(class_table);
+    annotation::load(class_table);
+    thread::load(class_table);
+    io::load(class_table);
+    lang::load_system_streams(class_table);
+}
+
+// The native a method of a class loaded from the class path is bound to, if it has one.
+pub fn find_native(class_name: &str, name: &str, descriptor: &str) -> Option<NativeMethod> {
+    jdk::find_native(class_name, name, descriptor)
+}
+
+// The instance fields the virtual machine adds to a class loaded from the class path.
+pub fn injected_fields(class_name: &str) -> Vec<RuntimeField> {
+    jdk::injected_fields(class_name)
+}
+
+pub struct ClassBuilder {
+    name: String,
+    access_flags: u16,
+    super_class: Option<String>,
+    interfaces: Vec<String>,
+    fields: Vec<RuntimeField>,
+    methods: Vec<RuntimeMethod>
+}
+
+impl ClassBuilder {
+
+    pub fn new(name: &str) -> ClassBuilder {
+        let super_class = if name == OBJECT_CLASS_NAME {
+            None
+        } else {
+            Some(String::from(OBJECT_CLASS_NAME))
+        };
+
+        ClassBuilder {
+            name: String::from(name),
+            access_flags: ACC_PUBLIC,
+            super_class,
+            interfaces: Vec::new(),
+            fields: Vec::new(),
+            methods: Vec::new()
+        }
+    }
+
+    pub fn interface(name: &str) -> ClassBuilder {
+        let mut builder = ClassBuilder::new(name);
+        builder.access_flags |= ACC_INTERFACE | ACC_ABSTRACT;
+        builder
+    }
+
+    pub fn super_class(mut self, name: &str) -> ClassBuilder {
+        self.super_class = Some(String::from(name));
+        self
+    }
+
+    pub fn implements(mut self, name: &str) -> ClassBuilder {
+        self.interfaces.push(String::from(name));
+        self
+    }
+
+    pub fn field(mut self, name: &str, descriptor: &str, access_flags: u16) -> ClassBuilder {
+        self.fields.push(RuntimeField::new(name, descriptor, access_flags));
+        self
+    }
+
+    pub fn native(mut self, name: &str, descriptor: &str, access_flags: u16, native: NativeMethod) -> ClassBuilder {
+        self.methods.push(RuntimeMethod::native(name, descriptor, access_flags, native));
+        self
+    }
+
+    // Creates the class and loads it. The superclass and interfaces must already be loaded.
+    pub fn define(self, class_table: &ClassTable) -> Arc<RuntimeClass> {
+        let super_class = self.super_class
+            .map(|name| class_table.get_class(&name).unwrap());
+        let interfaces = self.interfaces
+            .iter()
+            .map(|name| class_table.get_class(name).unwrap())
+            .collect();
+
+        let runtime_class = RuntimeClass::new(
+            &self.name,
+            self.access_flags,
+            super_class,
+            interfaces,
+            ConstantPool::new(),
+            self.fields,
+            self.methods
+        );
+        class_table.load_class(&runtime_class);
+
+        runtime_class
+    }
+
+}
+
+// Calls an instance method from a native method. The inner error is an exception thrown by the
+// callee.
+fn call(class_table: &ClassTable,
+        this: Value,
+        name: &str,
+        descriptor: &str,
+        arguments: Vec<Value>) -> Result<Result<Option<Value>, Value>, InterpreterError> {
+    match interpreter::invoke_virtual(class_table, this, name, descriptor, arguments)? {
+        InvokeResult::Value(value) => Ok(Ok(Some(value))),
+        InvokeResult::Void => Ok(Ok(None)),
+        InvokeResult::Exception(exception) => Ok(Err(exception))
+    }
+}
+
+// The characters of String.valueOf(value), calling toString on objects.
+pub fn to_java_chars(class_table: &ClassTable, value: &Value) -> Result<Result<Vec<u16>, Value>, InterpreterError> {
+    let string = match value {
+        Value::Null => return Ok(Ok(encode("null"))),
+        Value::ObjectRef(_) | Value::ArrayRef(_) => {
+            match call(class_table, value.clone(), "toString", "()Ljava/lang/String;", Vec::new())? {
+                Ok(Some(string)) => string,
+                Ok(None) => return Err(InterpreterError::UnexpectedOperand),
+                Err(exception) => return Ok(Err(exception))
+            }
+        },
+        _ => return Err(InterpreterError::UnexpectedOperand)
+    };
+
+    match string {
+        Value::ObjectRef(string) => Ok(Ok(string::get_chars(&string.borrow()))),
+        _ => Ok(Ok(encode("null")))
+    }
+}
+
+fn throw_new(class_table: &ClassTable, class_name: &str, message: Option<&str>) -> Result<InvokeResult, InterpreterError> {
+    let exception = interpreter::new_throwable(class_table, class_name, message)?;
+    Ok(InvokeResult::Exception(exception))
+}
+
+fn encode(string: &str) -> Vec<u16> {
+    string.encode_utf16().collect()
+}
+
+fn new_string(class_table: &ClassTable, chars: Vec<u16>) -> Value {
+    Value::ObjectRef(class_table.new_string(chars))
+}
+
+fn return_string(class_table: &ClassTable, string: &str) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(new_string(class_table, encode(string))))
+}
+
+fn object_arg(arguments: &[Value], index: usize) -> Result<Arc<HeapCell<Object>>, InterpreterError> {
+    match arguments.get(index) {
+        Some(Value::ObjectRef(object)) => Ok(object.clone()),
+        _ => Err(InterpreterError::UnexpectedOperand)
+    }
+}
+
+fn int_arg(arguments: &[Value], index: usize) -> Result<i32, InterpreterError> {
+    match arguments.get(index) {
+        Some(Value::Integer(i)) => Ok(*i),
+        _ => Err(InterpreterError::UnexpectedOperand)
+    }
+}
+
+fn long_arg(arguments: &[Value], index: usize) -> Result<i64, InterpreterError> {
+    match arguments.get(index) {
+        Some(Value::Long(l)) => Ok(*l),
+        _ => Err(InterpreterError::UnexpectedOperand)
+    }
+}
+
+fn float_arg(arguments: &[Value], index: usize) -> Result<f32, InterpreterError> {
+    match arguments.get(index) {
+        Some(Value::Float(f)) => Ok(*f),
+        _ => Err(InterpreterError::UnexpectedOperand)
+    }
+}
+
+fn double_arg(arguments: &[Value], index: usize) -> Result<f64, InterpreterError> {
+    match arguments.get(index) {
+        Some(Value::Double(d)) => Ok(*d),
+        _ => Err(InterpreterError::UnexpectedOperand)
+    }
+}
+
+// The characters of a String argument, or None if it was null.
+fn string_arg(arguments: &[Value], index: usize) -> Result<Option<Vec<u16>>, InterpreterError> {
+    match arguments.get(index) {
+        Some(Value::ObjectRef(string)) => Ok(Some(string::get_chars(&string.borrow()))),
+        Some(Value::Null) => Ok(None),
+        _ => Err(InterpreterError::UnexpectedOperand)
+    }
+}
+
+// The identity hash code is derived from the address of the object.
+fn identity_hash_code(value: &Value) -> i32 {
+    let address = match value {
+        Value::ObjectRef(object) => Arc::as_ptr(object) as usize,
+        Value::ArrayRef(array) => Arc::as_ptr(array) as usize,
+        _ => 0
+    };
+
+    (address >> 3) as i32
+}
+
+// The output of Float.toString and Double.toString: the shortest decimal that uniquely
+// distinguishes the value, using computerized scientific notation outside of [10^-3, 10^7).
+pub fn format_double(value: f64) -> String {
+    if value.is_nan() {
+        return String::from("NaN");
+    }
+    if value.is_infinite() {
+        return String::from(if value > 0.0 { "Infinity" } else { "-Infinity" });
+    }
+
+    let magnitude = value.abs();
+    if magnitude == 0.0 || (1e-3..1e7).contains(&magnitude) {
+        let decimal = value.to_string();
+        if decimal.contains('.') { decimal } else { decimal + ".0" }
+    } else {
+        scientific(format!("{:e}", value))
+    }
+}
+
+pub fn format_float(value: f32) -> String {
+    if value.is_nan() {
+        return String::from("NaN");
+    }
+    if value.is_infinite() {
+        return String::from(if value > 0.0 { "Infinity" } else { "-Infinity" });
+    }
+
+    let magnitude = value.abs();
+    if magnitude == 0.0 || (1e-3..1e7).contains(&magnitude) {
+        let decimal = value.to_string();
+        if decimal.contains('.') { decimal } else { decimal + ".0" }
+    } else {
+        scientific(format!("{:e}", value))
+    }
+}
+
+// Converts Rust's "1.5e-5" exponent notation into Java's "1.5E-5".
+fn scientific(formatted: String) -> String {
+    let mut parts = formatted.splitn(2, 'e');
+    let mantissa = parts.next().unwrap_or("0");
+    let exponent = parts.next().unwrap_or("0");
+
+    if mantissa.contains('.') {
+        format!("{}E{}", mantissa, exponent)
+    } else {
+        format!("{}.0E{}", mantissa, exponent)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::*;
+    use super::io::{Console, SharedBuffer};
+    use class::reader;
+    use class::method::{ACC_PUBLIC, ACC_STATIC};
+    use class::path::ClassPath;
+    use class::path::jmod::Jmod;
+    use std::env;
+    use std::io::Cursor;
+    use std::path::Path;
+
+    #[test]
+    fn formats_doubles_like_java() {
+        assert_eq!(format_double(1.0), "1.0");
+        assert_eq!(format_double(-0.0), "-0.0");
+        assert_eq!(format_double(0.1), "0.1");
+        assert_eq!(format_double(123456.5), "123456.5");
+        assert_eq!(format_double(1e7), "1.0E7");
+        assert_eq!(format_double(1.5e-5), "1.5E-5");
+        assert_eq!(format_double(f64::NAN), "NaN");
+        assert_eq!(format_double(f64::NEG_INFINITY), "-Infinity");
+        assert_eq!(format_float(0.1), "0.1");
+        assert_eq!(format_float(3.0e10), "3.0E10");
+    }
+
+    #[test]
+    fn object_layout_includes_superclass_fields() {
+        let class_table = ClassTable::new();
+        let exception_class = class_table.get_class("java/lang/ArithmeticException").unwrap();
+
+        assert!(exception_class.is_subclass_of("java/lang/RuntimeException"));
+        assert!(exception_class.is_subclass_of("java/lang/Throwable"));
+        assert!(exception_class.instance_fields.iter().any(|field| field.name == "detailMessage"));
+    }
+
+    pub fn class_table() -> (Arc<ClassTable>, SharedBuffer, SharedBuffer) {
+        let out = SharedBuffer::new();
+        let err = SharedBuffer::new();
+        let class_table = ClassTable::with_console(Console::new(Box::new(out.clone()), Box::new(err.clone())));
+
+        (class_table, out, err)
+    }
+
+    // Defines the classes in order and runs the main method of the last one, returning what was
+    // written to System.out and System.err.
+    pub fn run_main(classes: &[&[u8]]) -> (String, String) {
+        let (class_table, out, err) = class_table();
+
+        let class = define(&class_table, classes);
+        run(&class_table, &class);
+
+        (out.contents(), err.contents())
+    }
+
+    // Defines the classes in order, returning the last one. Classes they need that are not
+    // loaded yet come from the class path.
+    pub fn define(class_table: &ClassTable, classes: &[&[u8]]) -> Arc<RuntimeClass> {
+        let mut class = None;
+        for bytes in classes.iter() {
+            class = Some(class_table.define_class_bytes(None, bytes, "fixtures").unwrap());
+        }
+        class.unwrap()
+    }
+
+    pub fn run(class_table: &ClassTable, class: &Arc<RuntimeClass>) {
+        let main = class.get_declared_method("main", "([Ljava/lang/String;)V").unwrap();
+        assert_eq!(main.access_flags & (ACC_PUBLIC | ACC_STATIC), ACC_PUBLIC | ACC_STATIC);
+
+        let exception = interpreter::initialize_class(class, class_table).unwrap();
+        assert!(exception.is_none());
+
+        let arguments = vec![Value::ArrayRef(class_table.new_array("Ljava/lang/String;", 0))];
+        let attachment = class_table.heap.attach();
+        match interpreter::invoke_method(class, main, arguments, class_table).unwrap() {
+            InvokeResult::Void => {},
+            x => panic!("main did not return normally: {:?}", x)
+        }
+        drop(attachment);
+        class_table.join_threads();
+    }
+
+    #[test]
+    fn hello_world() {
+        let (out, _) = run_main(&[include_bytes!("../../../fixtures/Hello.class")]);
+
+        assert_eq!(out, "Hello, world!\nHello 42\n");
+    }
+
+    #[test]
+    fn bootstrap_library() {
+        let (out, err) = run_main(&[
+            include_bytes!("../../../fixtures/Named.class"),
+            include_bytes!("../../../fixtures/Shape.class"),
+            include_bytes!("../../../fixtures/Circle.class"),
+            include_bytes!("../../../fixtures/Square.class"),
+            include_bytes!("../../../fixtures/Base.class"),
+            include_bytes!("../../../fixtures/Derived.class"),
+            include_bytes!("../../../fixtures/Evolved.class"),
+            include_bytes!("../../../fixtures/Greeter.class"),
+            include_bytes!("../../../fixtures/Quiet.class"),
+            include_bytes!("../../../fixtures/Color.class"),
+            include_bytes!("../../../fixtures/Bootstrap$1.class"),
+            include_bytes!("../../../fixtures/Bootstrap.class")
+        ]);
+
+        assert_eq!(out, include_str!("../../../fixtures/Bootstrap.out"));
+        assert_eq!(err, "java.lang.IllegalStateException: printed to stderr\n\tat Bootstrap.main(Bootstrap.java:180)\n");
+    }
+
+    // Each lambda gets a class implementing its functional interface, and string concatenation
+    // follows the recipe javac put in the constant pool.
+    #[test]
+    fn invokedynamic_call_sites() {
+        let (out, err) = run_main(&[
+            include_bytes!("../../../fixtures/IntOperation.class"),
+            include_bytes!("../../../fixtures/Transformer.class"),
+            include_bytes!("../../../fixtures/Factory.class"),
+            include_bytes!("../../../fixtures/Action.class"),
+            include_bytes!("../../../fixtures/Lambdas.class")
+        ]);
+
+        assert_eq!(out, include_str!("../../../fixtures/Lambdas.out"));
+        assert_eq!(err, "");
+    }
+
+    const REFLECTION: &[&[u8]] = &[
+        include_bytes!("../../../fixtures/Reflection$Shape.class"),
+        include_bytes!("../../../fixtures/Reflection$Point.class"),
+        include_bytes!("../../../fixtures/Reflection$Point3.class"),
+        include_bytes!("../../../fixtures/Reflection.class")
+    ];
+
+    #[test]
+    fn reflection() {
+        let (out, err) = run_main(REFLECTION);
+
+        assert_eq!(out, include_str!("../../../fixtures/Reflection.out"));
+        assert_eq!(err, "");
+    }
+
+    const ANNOTATIONS: &[&[u8]] = &[
+        include_bytes!("../../../fixtures/Annotations$Level.class"),
+        include_bytes!("../../../fixtures/Annotations$Tag.class"),
+        include_bytes!("../../../fixtures/Annotations$Owner.class"),
+        include_bytes!("../../../fixtures/Annotations$Invisible.class"),
+        include_bytes!("../../../fixtures/Annotations$Named.class"),
+        include_bytes!("../../../fixtures/Annotations$Typed.class"),
+        include_bytes!("../../../fixtures/Annotations$Base.class"),
+        include_bytes!("../../../fixtures/Annotations$Derived.class"),
+        include_bytes!("../../../fixtures/Annotations.class")
+    ];
+
+    #[test]
+    fn annotations() {
+        let (out, err) = run_main(ANNOTATIONS);
+
+        assert_eq!(out, include_str!("../../../fixtures/Annotations.out"));
+        assert_eq!(err, "");
+    }
+
+    // The built-in library has no ElementType, so reading an annotation with an element of that
+    // type fails like resolving the class in code would.
+    #[test]
+    fn annotation_with_missing_enum_class() {
+        let (out, err) = run_main(&[
+            include_bytes!("../../../fixtures/MissingEnum$Kind.class"),
+            include_bytes!("../../../fixtures/MissingEnum$Marked.class"),
+            include_bytes!("../../../fixtures/MissingEnum.class")
+        ]);
+
+        assert_eq!(out, include_str!("../../../fixtures/MissingEnum.out"));
+        assert_eq!(err, "");
+    }
+
+    // Greeter and Greeting are only referenced by Main, so they are loaded on demand.
+    #[test]
+    fn loads_classes_from_jmod() {
+        let (class_table, out, _) = class_table();
+        let jmod = Jmod::open(Cursor::new(&include_bytes!("../../../fixtures/greetings.jmod")[..])).unwrap();
+        let mut class_path = ClassPath::new();
+        class_path.add("greetings.jmod", jmod);
+        class_table.set_class_path(class_path);
+
+        let main = class_table.find_class("greetings/Main").unwrap().unwrap();
+        assert!(class_table.get_class("greetings/Greeter").is_none());
+        run(&class_table, &main);
+
+        assert_eq!(out.contents(), "Hello, module!\n");
+        assert!(class_table.get_class("greetings/Greeter").unwrap().is_subclass_of("greetings/Greeting"));
+        assert!(class_table.find_class("greetings/Missing").unwrap().is_none());
+    }
+
+    // A class table that runs with the java.base module of the JDK in JAVA_HOME, booted like the
+    // java launcher does with --java-home.
+    fn jdk_class_table() -> (Arc<ClassTable>, SharedBuffer, SharedBuffer) {
+        let java_home = env::var("JAVA_HOME").expect("JAVA_HOME must point to an installed JDK");
+        let out = SharedBuffer::new();
+        let err = SharedBuffer::new();
+        let class_table = ClassTable::without_library(Console::new(Box::new(out.clone()), Box::new(err.clone())));
+        let mut class_path = ClassPath::new();
+        class_path.add_java_home(Path::new(&java_home)).unwrap();
+        class_table.set_class_path(class_path);
+        class_table.set_property("java.home", &java_home);
+        jdk::boot(&class_table).unwrap();
+
+        (class_table, out, err)
+    }
+
+    // ArrayList, Arrays.asList, Objects and the Integer::sum method reference all come from the
+    // installed JDK.
+    #[test]
+    #[ignore = "needs JAVA_HOME"]
+    fn runs_collections_from_installed_jdk() {
+        let (class_table, out, err) = jdk_class_table();
+
+        let class_file = reader::read_class_file(include_bytes!("../../../fixtures/Lists.class")).unwrap();
+        let class = class_table.define_class(&class_file).unwrap();
+        run(&class_table, &class);
+
+        assert_eq!(out.contents(), include_str!("../../../fixtures/Lists.out"));
+        assert_eq!(err.contents(), "");
+    }
+
+    // String.split, format, join, repeat and chars, Character, Objects.hash, Math.floorMod and
+    // streams run the code of java.base, with only its natives built in.
+    #[test]
+    #[ignore = "needs JAVA_HOME"]
+    fn runs_string_character_and_stream_apis_from_installed_jdk() {
+        let (class_table, out, err) = jdk_class_table();
+
+        let class_file = reader::read_class_file(include_bytes!("../../../fixtures/JdkApis.class")).unwrap();
+        let class = class_table.define_class(&class_file).unwrap();
+        run(&class_table, &class);
+
+        assert_eq!(out.contents(), include_str!("../../../fixtures/JdkApis.out"));
+        assert_eq!(err.contents(), "");
+    }
+
+    // The simple names of the nested classes come from their InnerClasses attributes.
+    #[test]
+    #[ignore = "needs JAVA_HOME"]
+    fn runs_reflection_from_installed_jdk() {
+        let (class_table, out, err) = jdk_class_table();
+
+        let class = define(&class_table, REFLECTION);
+        run(&class_table, &class);
+
+        assert_eq!(out.contents(), include_str!("../../../fixtures/Reflection.out"));
+        assert_eq!(err.contents(), "");
+    }
+
+    // Member, local and anonymous classes are told apart by the InnerClasses and EnclosingMethod
+    // attributes.
+    #[test]
+    #[ignore = "needs JAVA_HOME"]
+    fn describes_nested_classes_from_installed_jdk() {
+        let (class_table, out, err) = jdk_class_table();
+
+        let class = define(&class_table, &[
+            include_bytes!("../../../fixtures/Nested$Member.class"),
+            include_bytes!("../../../fixtures/Nested$Inner.class"),
+            include_bytes!("../../../fixtures/Nested$1Local.class"),
+            include_bytes!("../../../fixtures/Nested$1.class"),
+            include_bytes!("../../../fixtures/Nested.class")
+        ]);
+        run(&class_table, &class);
+
+        assert_eq!(out.contents(), include_str!("../../../fixtures/Nested.out"));
+        assert_eq!(err.contents(), "");
+    }
+
+    // The toString, equals and hashCode methods of records are linked by ObjectMethods.bootstrap,
+    // which java.lang.invoke runs, spinning the classes of its lambda forms as hidden classes.
+    // Linking starts the cleaner thread, which looks at the thread group of the current thread,
+    // so the JDK is booted on the thread that runs main.
+    #[test]
+    #[ignore = "needs JAVA_HOME"]
+    fn runs_records_from_installed_jdk() {
+        let (out, err) = ::std::thread::Builder::new()
+            .stack_size(thread::STACK_SIZE)
+            .spawn(|| {
+                let (class_table, out, err) = jdk_class_table();
+                let class = define(&class_table, &[
+                    include_bytes!("../../../fixtures/Records$Point.class"),
+                    include_bytes!("../../../fixtures/Records$Item.class"),
+                    include_bytes!("../../../fixtures/Records$Empty.class"),
+                    include_bytes!("../../../fixtures/Records.class")
+                ]);
+                run(&class_table, &class);
+                (out, err)
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+
+        assert_eq!(out.contents(), include_str!("../../../fixtures/Records.out"));
+        assert_eq!(err.contents(), "");
+    }
+
+    // AnnotationParser of the JDK reads the annotation attributes through the ConstantPool of the
+    // class, and the annotations are proxies that Proxy generates and defines. Generating them
+    // calls deeper than the stack of a test thread allows, so main runs on a stack of a Java thread.
+    #[test]
+    #[ignore = "needs JAVA_HOME"]
+    fn runs_annotations_from_installed_jdk() {
+        let (class_table, out, err) = jdk_class_table();
+
+        let class = define(&class_table, ANNOTATIONS);
+        ::std::thread::Builder::new()
+            .stack_size(thread::STACK_SIZE)
+            .spawn(move || run(&class_table, &class))
+            .unwrap()
+            .join()
+            .unwrap();
+
+        assert_eq!(out.contents(), include_str!("../../../fixtures/Annotations.out"));
+        assert_eq!(err.contents(), "");
+    }
+
+    // System.exit unwinds the interpreter with the status, without running finally blocks.
+    #[test]
+    fn system_properties_and_exit() {
+        let (class_table, out, _) = class_table();
+        class_table.set_property("greeting", "hello");
+        let class_file = reader::read_class_file(include_bytes!("../../../fixtures/SystemProperties.class")).unwrap();
+        let class = class_table.define_class(&class_file).unwrap();
+        let main = class.get_declared_method("main", "([Ljava/lang/String;)V").unwrap();
+
+        let arguments = vec![encode("a"), encode("b c")]
+            .into_iter()
+            .map(|chars| new_string(&class_table, chars))
+            .collect();
+        let arguments = class_table.new_array_from("Ljava/lang/String;", ::runtime::ArrayElements::Reference(arguments));
+
+        match interpreter::invoke_method(&class, main, vec![Value::ArrayRef(arguments)], &class_table) {
+            Err(InterpreterError::Exit(3)) => {},
+            x => panic!("Expected System.exit(3), got {:?}", x)
+        }
+        assert_eq!(out.contents(), "hello\nfallback\nnull\nhello\nchanged\n2\na\nb c\nkey can't be empty\n");
+        assert_eq!(class_table.get_property("greeting"), Some(String::from("changed")));
+    }
+
+}
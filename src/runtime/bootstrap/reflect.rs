@@ -0,0 +1,1369 @@
+use runtime::{Value, ArrayElements, HeapCell, Object};
+use runtime::class::{ClassTable, RuntimeClass, OBJECT_CLASS_NAME, mirror_name};
+use runtime::class::annotation::RuntimeAnnotation;
+use runtime::class::field::RuntimeField;
+use runtime::class::method::{RuntimeMethod, MethodDescriptor};
+use runtime::interpreter::{self, InvokeResult, InterpreterError};
+use runtime::invokedynamic;
+use runtime::bootstrap::{annotation, ClassBuilder, object_arg, int_arg, string_arg, throw_new, return_string, encode};
+use runtime::string;
+use class::{field, method};
+use class::class_flags;
+use class::method::{ACC_PUBLIC, ACC_STATIC};
+use std::sync::Arc;
+
+// Reflection over the classes of the class table. Method, Field and Constructor objects name
+// their member by the mirror of the declaring class and its position among the methods or fields
+// of the RuntimeClass, and the interpreter does the rest.
+
+const MODIFIER_CLASS_NAME: &str = "java/lang/reflect/Modifier";
+const ACCESSIBLE_OBJECT_CLASS_NAME: &str = "java/lang/reflect/AccessibleObject";
+const EXECUTABLE_CLASS_NAME: &str = "java/lang/reflect/Executable";
+const METHOD_CLASS_NAME: &str = "java/lang/reflect/Method";
+const CONSTRUCTOR_CLASS_NAME: &str = "java/lang/reflect/Constructor";
+const FIELD_CLASS_NAME: &str = "java/lang/reflect/Field";
+const INVOCATION_TARGET_EXCEPTION_CLASS_NAME: &str = "java/lang/reflect/InvocationTargetException";
+
+const PUBLIC_STATIC: u16 = ACC_PUBLIC | ACC_STATIC;
+const CONSTANT: u16 = field::ACC_PUBLIC | field::ACC_STATIC | field::ACC_FINAL;
+
+// The bits of java.lang.reflect.Modifier, which are the access flags of class files.
+const MODIFIERS: &[(&str, u16)] = &[
+    ("PUBLIC", method::ACC_PUBLIC),
+    ("PRIVATE", method::ACC_PRIVATE),
+    ("PROTECTED", method::ACC_PROTECTED),
+    ("STATIC", method::ACC_STATIC),
+    ("FINAL", method::ACC_FINAL),
+    ("SYNCHRONIZED", method::ACC_SYNCHRONIZED),
+    ("VOLATILE", field::ACC_VOLATILE),
+    ("TRANSIENT", field::ACC_TRANSIENT),
+    ("NATIVE", method::ACC_NATIVE),
+    ("INTERFACE", class_flags::ACC_INTERFACE),
+    ("ABSTRACT", method::ACC_ABSTRACT),
+    ("STRICT", method::ACC_STRICT)
+];
+
+// The modifiers in the order Modifier.toString writes them.
+const MODIFIER_NAMES: &[(&str, u16)] = &[
+    ("public", method::ACC_PUBLIC),
+    ("protected", method::ACC_PROTECTED),
+    ("private", method::ACC_PRIVATE),
+    ("abstract", method::ACC_ABSTRACT),
+    ("static", method::ACC_STATIC),
+    ("final", method::ACC_FINAL),
+    ("transient", field::ACC_TRANSIENT),
+    ("volatile", field::ACC_VOLATILE),
+    ("synchronized", method::ACC_SYNCHRONIZED),
+    ("native", method::ACC_NATIVE),
+    ("strictfp", method::ACC_STRICT),
+    ("interface", class_flags::ACC_INTERFACE)
+];
+
+// The modifiers that Class.getModifiers reports. ACC_SUPER shares its bit with synchronized.
+const CLASS_MODIFIERS: u16 = class_flags::ACC_PUBLIC | class_flags::ACC_PRIVATE | class_flags::ACC_PROTECTED |
+    class_flags::ACC_STATIC | class_flags::ACC_FINAL | class_flags::ACC_INTERFACE | class_flags::ACC_ABSTRACT |
+    class_flags::ACC_SYNTHETIC | class_flags::ACC_ANNOTATION | class_flags::ACC_ENUM;
+
+// The modifiers written by Method.toString, leaving out those sharing bits with bridge and
+// varargs.
+const METHOD_MODIFIERS: u16 = method::ACC_PUBLIC | method::ACC_PROTECTED | method::ACC_PRIVATE | method::ACC_ABSTRACT |
+    method::ACC_STATIC | method::ACC_FINAL | method::ACC_SYNCHRONIZED | method::ACC_NATIVE | method::ACC_STRICT;
+
+const FIELD_MODIFIERS: u16 = field::ACC_PUBLIC | field::ACC_PROTECTED | field::ACC_PRIVATE | field::ACC_STATIC |
+    field::ACC_FINAL | field::ACC_TRANSIENT | field::ACC_VOLATILE;
+
+pub fn load(class_table: &ClassTable) {
+    let modifier = ClassBuilder::new(MODIFIER_CLASS_NAME);
+    let modifier = MODIFIERS.iter().fold(modifier, |builder, &(name, _)| builder.field(name, "I", CONSTANT))
+        .native("isPublic", "(I)Z", PUBLIC_STATIC, modifier_is_public)
+        .native("isPrivate", "(I)Z", PUBLIC_STATIC, modifier_is_private)
+        .native("isProtected", "(I)Z", PUBLIC_STATIC, modifier_is_protected)
+        .native("isStatic", "(I)Z", PUBLIC_STATIC, modifier_is_static)
+        .native("isFinal", "(I)Z", PUBLIC_STATIC, modifier_is_final)
+        .native("isSynchronized", "(I)Z", PUBLIC_STATIC, modifier_is_synchronized)
+        .native("isVolatile", "(I)Z", PUBLIC_STATIC, modifier_is_volatile)
+        .native("isTransient", "(I)Z", PUBLIC_STATIC, modifier_is_transient)
+        .native("isNative", "(I)Z", PUBLIC_STATIC, modifier_is_native)
+        .native("isInterface", "(I)Z", PUBLIC_STATIC, modifier_is_interface)
+        .native("isAbstract", "(I)Z", PUBLIC_STATIC, modifier_is_abstract)
+        .native("isStrict", "(I)Z", PUBLIC_STATIC, modifier_is_strict)
+        .native("toString", "(I)Ljava/lang/String;", PUBLIC_STATIC, modifier_to_string)
+        .define(class_table);
+    for &(name, flag) in MODIFIERS.iter() {
+        modifier.put_static(name, Value::Integer(flag as i32));
+    }
+
+    ClassBuilder::interface("java/lang/reflect/Member").define(class_table);
+
+    ClassBuilder::new(ACCESSIBLE_OBJECT_CLASS_NAME)
+        .field("override", "Z", field::ACC_PRIVATE)
+        .native("setAccessible", "(Z)V", ACC_PUBLIC, accessible_object_set_accessible)
+        .native("trySetAccessible", "()Z", ACC_PUBLIC, accessible_object_try_set_accessible)
+        .native("isAccessible", "()Z", ACC_PUBLIC, accessible_object_is_accessible)
+        .define(class_table);
+
+    // Methods and constructors have the same fields, and tell themselves apart by name.
+    ClassBuilder::new(EXECUTABLE_CLASS_NAME)
+        .super_class(ACCESSIBLE_OBJECT_CLASS_NAME)
+        .implements("java/lang/reflect/Member")
+        .field("clazz", "Ljava/lang/Class;", field::ACC_PRIVATE | field::ACC_FINAL)
+        .field("slot", "I", field::ACC_PRIVATE | field::ACC_FINAL)
+        .native("getDeclaringClass", "()Ljava/lang/Class;", ACC_PUBLIC, member_get_declaring_class)
+        .native("getModifiers", "()I", ACC_PUBLIC, executable_get_modifiers)
+        .native("getParameterTypes", "()[Ljava/lang/Class;", ACC_PUBLIC, executable_get_parameter_types)
+        .native("getParameterCount", "()I", ACC_PUBLIC, executable_get_parameter_count)
+        .native("toString", "()Ljava/lang/String;", ACC_PUBLIC, executable_to_string)
+        .native("getAnnotation", "(Ljava/lang/Class;)Ljava/lang/annotation/Annotation;", ACC_PUBLIC, executable_get_annotation)
+        .native("isAnnotationPresent", "(Ljava/lang/Class;)Z", ACC_PUBLIC, executable_is_annotation_present)
+        .native("getAnnotations", "()[Ljava/lang/annotation/Annotation;", ACC_PUBLIC, executable_get_annotations)
+        .native("getDeclaredAnnotations", "()[Ljava/lang/annotation/Annotation;", ACC_PUBLIC, executable_get_annotations)
+        .define(class_table);
+
+    ClassBuilder::new(METHOD_CLASS_NAME)
+        .super_class(EXECUTABLE_CLASS_NAME)
+        .native("getName", "()Ljava/lang/String;", ACC_PUBLIC, method_get_name)
+        .native("getReturnType", "()Ljava/lang/Class;", ACC_PUBLIC, method_get_return_type)
+        .native("invoke", "(Ljava/lang/Object;[Ljava/lang/Object;)Ljava/lang/Object;", ACC_PUBLIC, method_invoke)
+        .native("getDefaultValue", "()Ljava/lang/Object;", ACC_PUBLIC, method_get_default_value)
+        .define(class_table);
+
+    ClassBuilder::new(CONSTRUCTOR_CLASS_NAME)
+        .super_class(EXECUTABLE_CLASS_NAME)
+        .native("getName", "()Ljava/lang/String;", ACC_PUBLIC, constructor_get_name)
+        .native("newInstance", "([Ljava/lang/Object;)Ljava/lang/Object;", ACC_PUBLIC, constructor_new_instance)
+        .define(class_table);
+
+    ClassBuilder::new(FIELD_CLASS_NAME)
+        .super_class(ACCESSIBLE_OBJECT_CLASS_NAME)
+        .implements("java/lang/reflect/Member")
+        .field("clazz", "Ljava/lang/Class;", field::ACC_PRIVATE | field::ACC_FINAL)
+        .field("slot", "I", field::ACC_PRIVATE | field::ACC_FINAL)
+        .native("getDeclaringClass", "()Ljava/lang/Class;", ACC_PUBLIC, member_get_declaring_class)
+        .native("getName", "()Ljava/lang/String;", ACC_PUBLIC, field_get_name)
+        .native("getModifiers", "()I", ACC_PUBLIC, field_get_modifiers)
+        .native("getType", "()Ljava/lang/Class;", ACC_PUBLIC, field_get_type)
+        .native("toString", "()Ljava/lang/String;", ACC_PUBLIC, field_to_string)
+        .native("getAnnotation", "(Ljava/lang/Class;)Ljava/lang/annotation/Annotation;", ACC_PUBLIC, field_get_annotation)
+        .native("isAnnotationPresent", "(Ljava/lang/Class;)Z", ACC_PUBLIC, field_is_annotation_present)
+        .native("getAnnotations", "()[Ljava/lang/annotation/Annotation;", ACC_PUBLIC, field_get_annotations)
+        .native("getDeclaredAnnotations", "()[Ljava/lang/annotation/Annotation;", ACC_PUBLIC, field_get_annotations)
+        .native("get", "(Ljava/lang/Object;)Ljava/lang/Object;", ACC_PUBLIC, field_get)
+        .native("getBoolean", "(Ljava/lang/Object;)Z", ACC_PUBLIC, field_get_boolean)
+        .native("getInt", "(Ljava/lang/Object;)I", ACC_PUBLIC, field_get_int)
+        .native("getLong", "(Ljava/lang/Object;)J", ACC_PUBLIC, field_get_long)
+        .native("getDouble", "(Ljava/lang/Object;)D", ACC_PUBLIC, field_get_double)
+        .native("set", "(Ljava/lang/Object;Ljava/lang/Object;)V", ACC_PUBLIC, field_set)
+        .native("setBoolean", "(Ljava/lang/Object;Z)V", ACC_PUBLIC, field_set_boolean)
+        .native("setInt", "(Ljava/lang/Object;I)V", ACC_PUBLIC, field_set_int)
+        .native("setLong", "(Ljava/lang/Object;J)V", ACC_PUBLIC, field_set_long)
+        .native("setDouble", "(Ljava/lang/Object;D)V", ACC_PUBLIC, field_set_double)
+        .define(class_table);
+
+    // Naming the mirrors of primitive types takes strings, so the TYPE constants of the wrapper
+    // classes are set here rather than with the rest of their statics.
+    for &(class_name, primitive) in [("java/lang/Integer", "int"), ("java/lang/Long", "long")].iter() {
+        let class = class_table.get_class(class_name).unwrap();
+        class.put_static("TYPE", Value::ObjectRef(class_table.get_mirror(primitive)));
+    }
+
+    ClassBuilder::new(INVOCATION_TARGET_EXCEPTION_CLASS_NAME)
+        .super_class("java/lang/ReflectiveOperationException")
+        .native("getTargetException", "()Ljava/lang/Throwable;", ACC_PUBLIC, invocation_target_exception_get_target_exception)
+        .define(class_table);
+}
+
+fn boolean_result(value: bool) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Integer(value as i32)))
+}
+
+fn int_result(value: i32) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Integer(value)))
+}
+
+fn get_field(object: &Arc<HeapCell<Object>>, name: &str) -> Value {
+    object.borrow().get_field(String::from(name))
+}
+
+fn illegal_argument(class_table: &ClassTable, message: &str) -> Result<InvokeResult, InterpreterError> {
+    throw_new(class_table, "java/lang/IllegalArgumentException", Some(message))
+}
+
+// Types
+
+// The class named by a reference descriptor, e.g. java/lang/String, or the descriptor itself for
+// arrays, which is how array classes are named.
+fn class_name(descriptor: &str) -> &str {
+    if descriptor.starts_with('L') && descriptor.ends_with(';') {
+        &descriptor[1..descriptor.len() - 1]
+    } else {
+        descriptor
+    }
+}
+
+// The field descriptor of the type a mirror stands for.
+pub fn mirror_descriptor(mirror: &Arc<HeapCell<Object>>) -> String {
+    let name = mirror_internal_name(mirror);
+    match name.as_str() {
+        "boolean" => String::from("Z"),
+        "byte" => String::from("B"),
+        "char" => String::from("C"),
+        "short" => String::from("S"),
+        "int" => String::from("I"),
+        "long" => String::from("J"),
+        "float" => String::from("F"),
+        "double" => String::from("D"),
+        "void" => String::from("V"),
+        _ if name.starts_with('[') => name,
+        _ => format!("L{};", name)
+    }
+}
+
+fn mirror_internal_name(mirror: &Arc<HeapCell<Object>>) -> String {
+    match get_field(mirror, "name") {
+        Value::ObjectRef(name) => string::to_rust_string(&name.borrow()).replace('.', "/"),
+        _ => String::new()
+    }
+}
+
+fn is_primitive(descriptor: &str) -> bool {
+    descriptor.len() == 1
+}
+
+pub fn type_mirror(class_table: &ClassTable, descriptor: &str) -> Value {
+    Value::ObjectRef(class_table.get_mirror(&mirror_name(descriptor)))
+}
+
+// The name of a type in the source, as members print it, e.g. int[] or java.lang.String.
+fn type_name(descriptor: &str) -> String {
+    match descriptor.strip_prefix('[') {
+        Some(component) => type_name(component) + "[]",
+        None => mirror_name(descriptor).replace('/', ".")
+    }
+}
+
+// The source name of the type a mirror stands for, e.g. int[].
+pub fn mirror_type_name(mirror: &Arc<HeapCell<Object>>) -> String {
+    type_name(&mirror_descriptor(mirror))
+}
+
+fn class_mirrors(class_table: &ClassTable, descriptors: &[String]) -> Value {
+    let mirrors = descriptors.iter().map(|descriptor| type_mirror(class_table, descriptor)).collect();
+    Value::ArrayRef(class_table.new_array_from("Ljava/lang/Class;", ArrayElements::Reference(mirrors)))
+}
+
+// The class a mirror stands for, loading it if needed, or None for primitive types.
+pub fn mirror_class(class_table: &ClassTable, mirror: &Arc<HeapCell<Object>>) -> Result<Option<Arc<RuntimeClass>>, InterpreterError> {
+    let descriptor = mirror_descriptor(mirror);
+    if is_primitive(&descriptor) {
+        return Ok(None);
+    }
+    if descriptor.starts_with('[') {
+        return interpreter::resolve_class(OBJECT_CLASS_NAME, class_table).map(Some);
+    }
+    interpreter::resolve_class(class_name(&descriptor), class_table).map(Some)
+}
+
+fn parameter_types(method: &RuntimeMethod) -> Result<Vec<String>, InterpreterError> {
+    let descriptor = MethodDescriptor::parse(&method.descriptor).ok_or(InterpreterError::UnexpectedOperand)?;
+    Ok(descriptor.parameter_types())
+}
+
+// Java's widening primitive conversions, JLS $5.1.2, along with the identity conversion.
+fn widens(from: &str, to: &str) -> bool {
+    from == to || match from {
+        "B" => matches!(to, "S" | "I" | "J" | "F" | "D"),
+        "S" | "C" => matches!(to, "I" | "J" | "F" | "D"),
+        "I" => matches!(to, "J" | "F" | "D"),
+        "J" => matches!(to, "F" | "D"),
+        "F" => to == "D",
+        _ => false
+    }
+}
+
+fn primitive_of_wrapper(class_name: &str) -> Option<&'static str> {
+    match class_name {
+        "java/lang/Boolean" => Some("Z"),
+        "java/lang/Byte" => Some("B"),
+        "java/lang/Character" => Some("C"),
+        "java/lang/Short" => Some("S"),
+        "java/lang/Integer" => Some("I"),
+        "java/lang/Long" => Some("J"),
+        "java/lang/Float" => Some("F"),
+        "java/lang/Double" => Some("D"),
+        _ => None
+    }
+}
+
+// Converts a reflective argument to a value of the field descriptor, unboxing and widening
+// primitives as Method.invoke does. Returns None if it has the wrong type.
+fn unreflect(class_table: &ClassTable, value: &Value, descriptor: &str) -> Option<Value> {
+    if !is_primitive(descriptor) {
+        let assignable = matches!(*value, Value::Null) || interpreter::is_instance_of(value, class_name(descriptor), class_table);
+        return if assignable { Some(value.clone()) } else { None };
+    }
+    match *value {
+        Value::ObjectRef(ref boxed) => {
+            let from = primitive_of_wrapper(&boxed.borrow().class().class_name)?;
+            if !widens(from, descriptor) {
+                return None;
+            }
+            Some(invokedynamic::widen(get_field(boxed, "value"), descriptor))
+        },
+        _ => None
+    }
+}
+
+// Boxes a primitive value of the field descriptor to return it from a reflective call. The inner
+// error is an exception thrown while boxing.
+fn reflect(class_table: &ClassTable, value: Value, descriptor: &str) -> Result<Result<Value, Value>, InterpreterError> {
+    invokedynamic::convert(class_table, value, descriptor, "Ljava/lang/Object;")
+}
+
+// Access checks
+
+// Whether setAccessible(true) was called on the member.
+fn is_overridden(member: &Arc<HeapCell<Object>>) -> bool {
+    matches!(get_field(member, "override"), Value::Integer(1))
+}
+
+// Whether code in the caller may use a member of the declaring class with the given access
+// flags, following JLS $6.6. Nestmates, the classes of one source file, may use each other's
+// private members; nests are told by the name of the top level class.
+fn is_accessible(caller: &RuntimeClass, declaring: &RuntimeClass, access_flags: u16) -> bool {
+    let package = |name: &str| name.rfind('/').map_or(String::new(), |index| String::from(&name[..index]));
+    let nest = |name: &str| String::from(name.split('$').next().unwrap_or(name));
+    let same_package = package(&caller.class_name) == package(&declaring.class_name);
+
+    if declaring.access_flags & class_flags::ACC_PUBLIC == 0 && !same_package {
+        return false;
+    }
+    if access_flags & method::ACC_PUBLIC != 0 {
+        true
+    } else if access_flags & method::ACC_PRIVATE != 0 {
+        nest(&caller.class_name) == nest(&declaring.class_name)
+    } else if access_flags & method::ACC_PROTECTED != 0 {
+        same_package || caller.is_subclass_of(&declaring.class_name)
+    } else {
+        same_package
+    }
+}
+
+// Checks that the Java method calling the native reflective method may use the member, unless
+// setAccessible(true) was called on it. Returns the IllegalAccessException if not.
+fn check_access(class_table: &ClassTable,
+                member: &Arc<HeapCell<Object>>,
+                declaring: &RuntimeClass,
+                access_flags: u16) -> Result<Option<Value>, InterpreterError> {
+    if is_overridden(member) {
+        return Ok(None);
+    }
+    // The innermost frame is the native method itself.
+    let frames = class_table.stacks.current_frames();
+    let caller = match frames.len().checked_sub(2).and_then(|index| frames.get(index)) {
+        Some(frame) => frame.class.clone(),
+        None => return Ok(None)
+    };
+    if is_accessible(&caller, declaring, access_flags) {
+        return Ok(None);
+    }
+
+    let message = format!("class {} cannot access a member of class {} with modifiers \"{}\"",
+                          caller.class_name.replace('/', "."), declaring.class_name.replace('/', "."),
+                          modifiers_string(access_flags & (METHOD_MODIFIERS | FIELD_MODIFIERS)));
+    interpreter::new_throwable(class_table, "java/lang/IllegalAccessException", Some(&message)).map(Some)
+}
+
+// java.lang.reflect.Modifier
+
+fn modifiers_string(modifiers: u16) -> String {
+    MODIFIER_NAMES.iter()
+        .filter(|&&(_, flag)| modifiers & flag != 0)
+        .map(|&(name, _)| name)
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+fn has_modifier(arguments: &[Value], flag: u16) -> Result<InvokeResult, InterpreterError> {
+    boolean_result(int_arg(arguments, 0)? & flag as i32 != 0)
+}
+
+fn modifier_is_public(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    has_modifier(&arguments, method::ACC_PUBLIC)
+}
+
+fn modifier_is_private(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    has_modifier(&arguments, method::ACC_PRIVATE)
+}
+
+fn modifier_is_protected(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    has_modifier(&arguments, method::ACC_PROTECTED)
+}
+
+fn modifier_is_static(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    has_modifier(&arguments, method::ACC_STATIC)
+}
+
+fn modifier_is_final(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    has_modifier(&arguments, method::ACC_FINAL)
+}
+
+fn modifier_is_synchronized(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    has_modifier(&arguments, method::ACC_SYNCHRONIZED)
+}
+
+fn modifier_is_volatile(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    has_modifier(&arguments, field::ACC_VOLATILE)
+}
+
+fn modifier_is_transient(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    has_modifier(&arguments, field::ACC_TRANSIENT)
+}
+
+fn modifier_is_native(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    has_modifier(&arguments, method::ACC_NATIVE)
+}
+
+fn modifier_is_interface(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    has_modifier(&arguments, class_flags::ACC_INTERFACE)
+}
+
+fn modifier_is_abstract(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    has_modifier(&arguments, method::ACC_ABSTRACT)
+}
+
+fn modifier_is_strict(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    has_modifier(&arguments, method::ACC_STRICT)
+}
+
+fn modifier_to_string(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    return_string(class_table, &modifiers_string(int_arg(&arguments, 0)? as u16))
+}
+
+// java.lang.reflect.AccessibleObject
+
+fn accessible_object_set_accessible(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    this.borrow_mut().put_field(String::from("override"), Value::Integer(int_arg(&arguments, 1)?));
+    Ok(InvokeResult::Void)
+}
+
+fn accessible_object_try_set_accessible(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    this.borrow_mut().put_field(String::from("override"), Value::Integer(1));
+    boolean_result(true)
+}
+
+fn accessible_object_is_accessible(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let value = get_field(&this, "override");
+    Ok(InvokeResult::Value(value))
+}
+
+// Members
+
+fn new_member(class_table: &ClassTable, member_class_name: &str, class: &RuntimeClass, slot: usize) -> Result<Value, InterpreterError> {
+    let member_class = interpreter::resolve_class(member_class_name, class_table)?;
+    let member = class_table.new_object(&member_class);
+    let mirror = class_table.get_mirror(&class.class_name);
+    {
+        let mut member = member.borrow_mut();
+        member.put_field(String::from("clazz"), Value::ObjectRef(mirror));
+        member.put_field(String::from("slot"), Value::Integer(slot as i32));
+    }
+    Ok(Value::ObjectRef(member))
+}
+
+fn members(class_table: &ClassTable, member_class_name: &str, members: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let component_type = format!("L{};", member_class_name);
+    Ok(InvokeResult::Value(Value::ArrayRef(class_table.new_array_from(&component_type, ArrayElements::Reference(members)))))
+}
+
+// The declaring class of a Method, Constructor or Field object, and its position in the class.
+fn member_slot(class_table: &ClassTable, member: &Arc<HeapCell<Object>>) -> Result<(Arc<RuntimeClass>, usize), InterpreterError> {
+    let class = match get_field(member, "clazz") {
+        Value::ObjectRef(mirror) => mirror_class(class_table, &mirror)?.ok_or(InterpreterError::UnexpectedOperand)?,
+        _ => return Err(InterpreterError::UnexpectedOperand)
+    };
+    match get_field(member, "slot") {
+        Value::Integer(slot) => Ok((class, slot as usize)),
+        _ => Err(InterpreterError::UnexpectedOperand)
+    }
+}
+
+// Runs a function on the method or constructor of the first argument.
+fn with_method<T, F>(class_table: &ClassTable, arguments: &[Value], f: F) -> Result<T, InterpreterError>
+    where F: FnOnce(&Arc<RuntimeClass>, &RuntimeMethod) -> Result<T, InterpreterError> {
+    let this = object_arg(arguments, 0)?;
+    let (class, slot) = member_slot(class_table, &this)?;
+    let method = class.methods.get(slot).ok_or(InterpreterError::UnexpectedOperand)?;
+    f(&class, method)
+}
+
+fn with_field<T, F>(class_table: &ClassTable, arguments: &[Value], f: F) -> Result<T, InterpreterError>
+    where F: FnOnce(&Arc<RuntimeClass>, &RuntimeField) -> Result<T, InterpreterError> {
+    let this = object_arg(arguments, 0)?;
+    let (class, slot) = member_slot(class_table, &this)?;
+    let field = class.fields.get(slot).ok_or(InterpreterError::UnexpectedOperand)?;
+    f(&class, field)
+}
+
+fn member_get_declaring_class(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    Ok(InvokeResult::Value(get_field(&this, "clazz")))
+}
+
+// java.lang.reflect.Executable, Method and Constructor
+
+fn is_constructor(method: &RuntimeMethod) -> bool {
+    method.name == "<init>"
+}
+
+fn executable_get_modifiers(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    with_method(class_table, &arguments, |_, method| int_result(method.access_flags as i32))
+}
+
+fn executable_get_parameter_types(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let parameter_types = with_method(class_table, &arguments, |_, method| parameter_types(method))?;
+    Ok(InvokeResult::Value(class_mirrors(class_table, &parameter_types)))
+}
+
+fn executable_get_parameter_count(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let parameter_types = with_method(class_table, &arguments, |_, method| parameter_types(method))?;
+    int_result(parameter_types.len() as i32)
+}
+
+// e.g. public static int Calculator.add(int,int), or public Point(int,int) for a constructor.
+fn executable_to_string(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let string = with_method(class_table, &arguments, |class, method| {
+        let modifiers = modifiers_string(method.access_flags & METHOD_MODIFIERS);
+        let parameters = parameter_types(method)?.iter().map(|descriptor| type_name(descriptor)).collect::<Vec<String>>().join(",");
+        let class_name = class.class_name.replace('/', ".");
+        let mut string = if modifiers.is_empty() { String::new() } else { modifiers + " " };
+        if is_constructor(method) {
+            string.push_str(&format!("{}({})", class_name, parameters));
+        } else {
+            let descriptor = MethodDescriptor::parse(&method.descriptor).ok_or(InterpreterError::UnexpectedOperand)?;
+            string.push_str(&format!("{} {}.{}({})", type_name(&descriptor.return_type()), class_name, method.name, parameters));
+        }
+        Ok(string)
+    })?;
+    return_string(class_table, &string)
+}
+
+fn method_get_name(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let name = with_method(class_table, &arguments, |_, method| Ok(method.name.clone()))?;
+    Ok(InvokeResult::Value(Value::ObjectRef(class_table.intern_string(encode(&name)))))
+}
+
+fn method_get_return_type(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let return_type = with_method(class_table, &arguments, |_, method| {
+        MethodDescriptor::parse(&method.descriptor).map(|descriptor| descriptor.return_type()).ok_or(InterpreterError::UnexpectedOperand)
+    })?;
+    Ok(InvokeResult::Value(type_mirror(class_table, &return_type)))
+}
+
+// The default of an element of an annotation interface, boxed, or null if it has none.
+fn method_get_default_value(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let default = with_method(class_table, &arguments, |_, method| {
+        Ok(method.annotation_default.clone().map(|value| (value, String::from(&method.descriptor[2..]))))
+    })?;
+    let (value, return_type) = match default {
+        Some(default) => default,
+        None => return Ok(InvokeResult::Value(Value::Null))
+    };
+    let value = java_try!(annotation::element_value(class_table, &value, &return_type));
+    match reflect(class_table, value, &return_type)? {
+        Ok(value) => Ok(InvokeResult::Value(value)),
+        Err(exception) => Ok(InvokeResult::Exception(exception))
+    }
+}
+
+fn executable_get_annotation(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let annotations = with_method(class_table, &arguments, |_, method| Ok(method.annotations.clone()))?;
+    get_annotation(class_table, &annotations, &arguments)
+}
+
+fn executable_is_annotation_present(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let annotations = with_method(class_table, &arguments, |_, method| Ok(method.annotations.clone()))?;
+    is_annotation_present(class_table, &annotations, &arguments)
+}
+
+fn executable_get_annotations(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let annotations = with_method(class_table, &arguments, |_, method| Ok(method.annotations.clone()))?;
+    get_annotations(class_table, &annotations)
+}
+
+fn constructor_get_name(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let name = with_method(class_table, &arguments, |class, _| Ok(class.class_name.replace('/', ".")))?;
+    Ok(InvokeResult::Value(Value::ObjectRef(class_table.intern_string(encode(&name)))))
+}
+
+// Converts the arguments of a reflective call to the parameter types of the method. The inner
+// error is the IllegalArgumentException thrown if they do not match.
+fn unreflect_arguments(class_table: &ClassTable, method: &RuntimeMethod, arguments: &Value) -> Result<Result<Vec<Value>, Value>, InterpreterError> {
+    let arguments = match *arguments {
+        Value::ArrayRef(ref array) => {
+            let array = array.borrow();
+            (0..array.len()).map(|index| array.get(index)).collect()
+        },
+        _ => Vec::new()
+    };
+    let parameter_types = parameter_types(method)?;
+    if arguments.len() != parameter_types.len() {
+        let message = format!("wrong number of arguments: {} expected: {}", arguments.len(), parameter_types.len());
+        return interpreter::new_throwable(class_table, "java/lang/IllegalArgumentException", Some(&message)).map(Err);
+    }
+
+    let mut values = Vec::new();
+    for (argument, parameter_type) in arguments.iter().zip(parameter_types.iter()) {
+        match unreflect(class_table, argument, parameter_type) {
+            Some(value) => values.push(value),
+            None => return interpreter::new_throwable(class_table, "java/lang/IllegalArgumentException", Some("argument type mismatch")).map(Err)
+        }
+    }
+    Ok(Ok(values))
+}
+
+// Wraps an exception thrown by a method invoked through reflection. The exception of the JDK
+// keeps it in a field of its own.
+fn invocation_target_exception(class_table: &ClassTable, target: Value) -> Result<InvokeResult, InterpreterError> {
+    let exception = interpreter::new_throwable(class_table, INVOCATION_TARGET_EXCEPTION_CLASS_NAME, None)?;
+    if let Value::ObjectRef(ref object) = exception {
+        let mut object = object.borrow_mut();
+        let field = if object.class().instance_field_position("target").is_some() { "target" } else { "cause" };
+        object.put_field(String::from(field), target);
+    }
+    Ok(InvokeResult::Exception(exception))
+}
+
+fn method_invoke(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let (class, slot) = member_slot(class_table, &this)?;
+    let method = class.methods.get(slot).ok_or(InterpreterError::UnexpectedOperand)?;
+    if let Some(exception) = check_access(class_table, &this, &class, method.access_flags)? {
+        return Ok(InvokeResult::Exception(exception));
+    }
+    invoke(class_table, &this, arguments[1].clone(), &arguments[2])
+}
+
+// Invokes the method of a Method object with the receiver and array of arguments, once access to
+// it has been checked. Static and private methods are invoked as declared. Others are selected by
+// the class of the receiver, like invokevirtual and invokeinterface do.
+pub fn invoke(class_table: &ClassTable,
+              member: &Arc<HeapCell<Object>>,
+              receiver: Value,
+              arguments: &Value) -> Result<InvokeResult, InterpreterError> {
+    let (class, slot) = member_slot(class_table, member)?;
+    let method = class.methods.get(slot).ok_or(InterpreterError::UnexpectedOperand)?;
+    if !method.is_static() {
+        match receiver {
+            Value::Null => return throw_new(class_table, "java/lang/NullPointerException", None),
+            ref receiver if !interpreter::is_instance_of(receiver, &class.class_name, class_table) => {
+                return illegal_argument(class_table, "object is not an instance of declaring class");
+            },
+            _ => {}
+        }
+    }
+    let mut values = match unreflect_arguments(class_table, method, arguments)? {
+        Ok(values) => values,
+        Err(exception) => return Ok(InvokeResult::Exception(exception))
+    };
+
+    let result = if method.is_static() {
+        if let Some(exception) = interpreter::initialize_class(&class, class_table)? {
+            return Ok(InvokeResult::Exception(exception));
+        }
+        interpreter::invoke_method(&class, method, values, class_table)?
+    } else if method.access_flags & method::ACC_PRIVATE != 0 {
+        values.insert(0, receiver);
+        interpreter::invoke_method(&class, method, values, class_table)?
+    } else {
+        interpreter::invoke_virtual(class_table, receiver, &method.name, &method.descriptor, values)?
+    };
+
+    let return_type = MethodDescriptor::parse(&method.descriptor).ok_or(InterpreterError::UnexpectedOperand)?.return_type();
+    match result {
+        InvokeResult::Value(value) => match reflect(class_table, value, &return_type)? {
+            Ok(value) => Ok(InvokeResult::Value(value)),
+            Err(exception) => Ok(InvokeResult::Exception(exception))
+        },
+        InvokeResult::Void => Ok(InvokeResult::Value(Value::Null)),
+        InvokeResult::Exception(exception) => invocation_target_exception(class_table, exception)
+    }
+}
+
+fn constructor_new_instance(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let (class, slot) = member_slot(class_table, &this)?;
+    let method = class.methods.get(slot).ok_or(InterpreterError::UnexpectedOperand)?;
+    if let Some(exception) = check_access(class_table, &this, &class, method.access_flags)? {
+        return Ok(InvokeResult::Exception(exception));
+    }
+    construct(class_table, &this, &arguments[1])
+}
+
+// Creates an object with the constructor of a Constructor object and the array of arguments, once
+// access to it has been checked.
+pub fn construct(class_table: &ClassTable, member: &Arc<HeapCell<Object>>, arguments: &Value) -> Result<InvokeResult, InterpreterError> {
+    let (class, slot) = member_slot(class_table, member)?;
+    let method = class.methods.get(slot).ok_or(InterpreterError::UnexpectedOperand)?;
+    if class.access_flags & (class_flags::ACC_ABSTRACT | class_flags::ACC_INTERFACE) != 0 {
+        return throw_new(class_table, "java/lang/InstantiationException", None);
+    }
+    let mut values = match unreflect_arguments(class_table, method, arguments)? {
+        Ok(values) => values,
+        Err(exception) => return Ok(InvokeResult::Exception(exception))
+    };
+
+    if let Some(exception) = interpreter::initialize_class(&class, class_table)? {
+        return Ok(InvokeResult::Exception(exception));
+    }
+    let object = match interpreter::allocate_object(&class, class_table)? {
+        Ok(object) => object,
+        Err(exception) => return Ok(InvokeResult::Exception(exception))
+    };
+    values.insert(0, Value::ObjectRef(object.clone()));
+    match interpreter::invoke_method(&class, method, values, class_table)? {
+        InvokeResult::Exception(exception) => invocation_target_exception(class_table, exception),
+        _ => Ok(InvokeResult::Value(Value::ObjectRef(object)))
+    }
+}
+
+fn invocation_target_exception_get_target_exception(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    Ok(InvokeResult::Value(get_field(&this, "cause")))
+}
+
+// java.lang.reflect.Field
+
+fn field_get_name(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let name = with_field(class_table, &arguments, |_, field| Ok(field.name.clone()))?;
+    Ok(InvokeResult::Value(Value::ObjectRef(class_table.intern_string(encode(&name)))))
+}
+
+fn field_get_modifiers(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    with_field(class_table, &arguments, |_, field| int_result(field.access_flags as i32))
+}
+
+fn field_get_type(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let descriptor = with_field(class_table, &arguments, |_, field| Ok(field.descriptor_string.clone()))?;
+    Ok(InvokeResult::Value(type_mirror(class_table, &descriptor)))
+}
+
+// e.g. private int Point.x
+fn field_to_string(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let string = with_field(class_table, &arguments, |class, field| {
+        let modifiers = modifiers_string(field.access_flags & FIELD_MODIFIERS);
+        let modifiers = if modifiers.is_empty() { modifiers } else { modifiers + " " };
+        Ok(format!("{}{} {}.{}", modifiers, type_name(&field.descriptor_string), class.class_name.replace('/', "."), field.name))
+    })?;
+    return_string(class_table, &string)
+}
+
+fn field_get_annotation(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let annotations = with_field(class_table, &arguments, |_, field| Ok(field.annotations.clone()))?;
+    get_annotation(class_table, &annotations, &arguments)
+}
+
+fn field_is_annotation_present(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let annotations = with_field(class_table, &arguments, |_, field| Ok(field.annotations.clone()))?;
+    is_annotation_present(class_table, &annotations, &arguments)
+}
+
+fn field_get_annotations(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let annotations = with_field(class_table, &arguments, |_, field| Ok(field.annotations.clone()))?;
+    get_annotations(class_table, &annotations)
+}
+
+// Where a field accessed through reflection is: in the statics of its class, or at a position
+// in an object.
+enum Location {
+    Static(Arc<RuntimeClass>, String),
+    Instance(Arc<HeapCell<Object>>, usize)
+}
+
+struct FieldAccess {
+    location: Location,
+    descriptor: String,
+    // e.g. "final int field Point.x", for the messages of exceptions.
+    description: String,
+    is_final: bool
+}
+
+// Finds the field of the Field object in the first argument, in the object of the second for
+// instance fields. The inner error is the exception thrown if it may not be accessed or the
+// object is of the wrong class.
+fn field_access(class_table: &ClassTable, arguments: &[Value]) -> Result<Result<FieldAccess, Value>, InterpreterError> {
+    let this = object_arg(arguments, 0)?;
+    let (class, slot) = member_slot(class_table, &this)?;
+    let field = class.fields.get(slot).ok_or(InterpreterError::UnexpectedOperand)?;
+    if let Some(exception) = check_access(class_table, &this, &class, field.access_flags)? {
+        return Ok(Err(exception));
+    }
+
+    let location = if field.is_static() {
+        if let Some(exception) = interpreter::initialize_class(&class, class_table)? {
+            return Ok(Err(exception));
+        }
+        Location::Static(class.clone(), field.name.clone())
+    } else {
+        match arguments[1] {
+            Value::ObjectRef(ref object) if object.borrow().class().is_subclass_of(&class.class_name) => {
+                // The fields of the declaring class come first in the layout of its subclasses,
+                // so a field hiding this one further down is not found.
+                let position = class.instance_fields.iter()
+                    .rposition(|instance_field| instance_field.name == field.name)
+                    .ok_or(InterpreterError::UnexpectedOperand)?;
+                Location::Instance(object.clone(), position)
+            },
+            Value::Null => return interpreter::new_throwable(class_table, "java/lang/NullPointerException", None).map(Err),
+            ref object => {
+                let message = format!("Can not get {} field {}.{} on {}", type_name(&field.descriptor_string),
+                                      class.class_name.replace('/', "."), field.name, value_class_name(object));
+                return interpreter::new_throwable(class_table, "java/lang/IllegalArgumentException", Some(&message)).map(Err);
+            }
+        }
+    };
+
+    let is_final = field.access_flags & field::ACC_FINAL != 0;
+    let modifiers = match (field.is_static(), is_final) {
+        (true, true) => "static final ",
+        (true, false) => "static ",
+        (false, true) => "final ",
+        (false, false) => ""
+    };
+    let description = format!("{}{} field {}.{}", modifiers, type_name(&field.descriptor_string),
+                              class.class_name.replace('/', "."), field.name);
+    // Final instance fields may be set once setAccessible(true) was called, static ones never.
+    let is_final = is_final && (field.is_static() || !is_overridden(&this));
+    Ok(Ok(FieldAccess { location, descriptor: field.descriptor_string.clone(), description, is_final }))
+}
+
+fn value_class_name(value: &Value) -> String {
+    match *value {
+        Value::ObjectRef(ref object) => object.borrow().class().class_name.replace('/', "."),
+        Value::ArrayRef(ref array) => array.borrow().descriptor().replace('/', "."),
+        _ => String::from("null value")
+    }
+}
+
+impl FieldAccess {
+
+    fn get(&self) -> Value {
+        match self.location {
+            Location::Static(ref class, ref name) => class.get_static(name).unwrap_or(Value::Null),
+            Location::Instance(ref object, position) => object.borrow().fields()[position].clone()
+        }
+    }
+
+    fn set(&self, value: Value) {
+        match self.location {
+            Location::Static(ref class, ref name) => class.put_static(name, value),
+            Location::Instance(ref object, position) => object.borrow_mut().fields_mut()[position] = value
+        }
+    }
+
+}
+
+fn field_get(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let access = match field_access(class_table, &arguments)? {
+        Ok(access) => access,
+        Err(exception) => return Ok(InvokeResult::Exception(exception))
+    };
+    match reflect(class_table, access.get(), &access.descriptor)? {
+        Ok(value) => Ok(InvokeResult::Value(value)),
+        Err(exception) => Ok(InvokeResult::Exception(exception))
+    }
+}
+
+// Reads a primitive field as the given type, which it must widen to.
+fn field_get_primitive(class_table: &ClassTable, arguments: &[Value], descriptor: &str) -> Result<InvokeResult, InterpreterError> {
+    let access = match field_access(class_table, arguments)? {
+        Ok(access) => access,
+        Err(exception) => return Ok(InvokeResult::Exception(exception))
+    };
+    if !is_primitive(&access.descriptor) || !widens(&access.descriptor, descriptor) {
+        let message = format!("Attempt to get {} as {}", access.description, type_name(descriptor));
+        return illegal_argument(class_table, &message);
+    }
+    Ok(InvokeResult::Value(invokedynamic::widen(access.get(), descriptor)))
+}
+
+fn field_get_boolean(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    field_get_primitive(class_table, &arguments, "Z")
+}
+
+fn field_get_int(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    field_get_primitive(class_table, &arguments, "I")
+}
+
+fn field_get_long(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    field_get_primitive(class_table, &arguments, "J")
+}
+
+fn field_get_double(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    field_get_primitive(class_table, &arguments, "D")
+}
+
+// Sets the field to a value, converted by the given function to the type of the field. The
+// function returns None if the value has the wrong type.
+fn set_field_value<F>(class_table: &ClassTable, arguments: &[Value], value_type: &str, convert: F) -> Result<InvokeResult, InterpreterError>
+    where F: FnOnce(&str) -> Option<Value> {
+    let access = match field_access(class_table, arguments)? {
+        Ok(access) => access,
+        Err(exception) => return Ok(InvokeResult::Exception(exception))
+    };
+    if access.is_final {
+        let message = format!("Can not set {} to {}", access.description, value_type);
+        return throw_new(class_table, "java/lang/IllegalAccessException", Some(&message));
+    }
+    match convert(&access.descriptor) {
+        Some(value) => {
+            access.set(value);
+            Ok(InvokeResult::Void)
+        },
+        None => illegal_argument(class_table, &format!("Can not set {} to {}", access.description, value_type))
+    }
+}
+
+fn field_set(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let value = arguments[2].clone();
+    set_field_value(class_table, &arguments, &value_class_name(&value), |descriptor| {
+        if is_primitive(descriptor) && matches!(value, Value::Null) {
+            None
+        } else {
+            unreflect(class_table, &value, descriptor)
+        }
+    })
+}
+
+// Sets the field to a primitive of the given type, which must widen to the type of the field.
+fn field_set_primitive(class_table: &ClassTable, arguments: &[Value], descriptor: &str) -> Result<InvokeResult, InterpreterError> {
+    let value = arguments[2].clone();
+    set_field_value(class_table, arguments, &type_name(descriptor), |field_descriptor| {
+        if is_primitive(field_descriptor) && widens(descriptor, field_descriptor) {
+            Some(invokedynamic::widen(value, field_descriptor))
+        } else {
+            None
+        }
+    })
+}
+
+fn field_set_boolean(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    field_set_primitive(class_table, &arguments, "Z")
+}
+
+fn field_set_int(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    field_set_primitive(class_table, &arguments, "I")
+}
+
+fn field_set_long(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    field_set_primitive(class_table, &arguments, "J")
+}
+
+fn field_set_double(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    field_set_primitive(class_table, &arguments, "D")
+}
+
+// Annotations
+
+// The annotation of the type given by the mirror in the second argument, or null.
+fn get_annotation(class_table: &ClassTable, annotations: &[RuntimeAnnotation], arguments: &[Value]) -> Result<InvokeResult, InterpreterError> {
+    let type_name = match arguments.get(1) {
+        Some(Value::ObjectRef(mirror)) => mirror_internal_name(mirror),
+        _ => return throw_new(class_table, "java/lang/NullPointerException", None)
+    };
+    let found = annotations.iter()
+        .find(|annotation| annotation.type_name == type_name && annotation::is_visible(class_table, annotation));
+    match found {
+        Some(annotation) => Ok(InvokeResult::Value(java_try!(annotation::new_annotation(class_table, annotation)))),
+        None => Ok(InvokeResult::Value(Value::Null))
+    }
+}
+
+fn is_annotation_present(class_table: &ClassTable, annotations: &[RuntimeAnnotation], arguments: &[Value]) -> Result<InvokeResult, InterpreterError> {
+    let type_name = match arguments.get(1) {
+        Some(Value::ObjectRef(mirror)) => mirror_internal_name(mirror),
+        _ => return throw_new(class_table, "java/lang/NullPointerException", None)
+    };
+    let present = annotations.iter()
+        .any(|annotation| annotation.type_name == type_name && annotation::is_visible(class_table, annotation));
+    boolean_result(present)
+}
+
+fn get_annotations(class_table: &ClassTable, annotations: &[RuntimeAnnotation]) -> Result<InvokeResult, InterpreterError> {
+    let proxies = java_try!(annotation::new_annotations(class_table, annotations));
+    let component_type = format!("L{};", annotation::ANNOTATION_CLASS_NAME);
+    Ok(InvokeResult::Value(Value::ArrayRef(class_table.new_array_from(&component_type, ArrayElements::Reference(proxies)))))
+}
+
+// The annotations of a class followed by those it inherits: the annotations of its superclasses
+// whose interface is annotated with @Inherited, unless one of the same interface came before.
+fn class_annotations(class_table: &ClassTable, class: &Arc<RuntimeClass>, inherited: bool) -> Vec<RuntimeAnnotation> {
+    let mut annotations = class.annotations.clone();
+    let mut super_class = if inherited { class.super_class.clone() } else { None };
+    while let Some(current) = super_class {
+        for annotation in current.annotations.iter() {
+            let is_inherited = match class_table.find_class(&annotation.type_name) {
+                Ok(Some(interface)) => interface.annotations.iter().any(|meta| meta.type_name == annotation::INHERITED_CLASS_NAME),
+                _ => false
+            };
+            if is_inherited && !annotations.iter().any(|other| other.type_name == annotation.type_name) {
+                annotations.push(annotation.clone());
+            }
+        }
+        super_class = current.super_class.clone();
+    }
+    annotations
+}
+
+fn this_annotations(class_table: &ClassTable, arguments: &[Value], inherited: bool) -> Result<Vec<RuntimeAnnotation>, InterpreterError> {
+    match this_class(class_table, arguments)? {
+        Some(ref class) if !this_descriptor(arguments)?.starts_with('[') => Ok(class_annotations(class_table, class, inherited)),
+        _ => Ok(Vec::new())
+    }
+}
+
+pub fn class_get_annotation(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let annotations = this_annotations(class_table, &arguments, true)?;
+    get_annotation(class_table, &annotations, &arguments)
+}
+
+pub fn class_is_annotation_present(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let annotations = this_annotations(class_table, &arguments, true)?;
+    is_annotation_present(class_table, &annotations, &arguments)
+}
+
+pub fn class_get_annotations(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let annotations = this_annotations(class_table, &arguments, true)?;
+    get_annotations(class_table, &annotations)
+}
+
+pub fn class_get_declared_annotations(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let annotations = this_annotations(class_table, &arguments, false)?;
+    get_annotations(class_table, &annotations)
+}
+
+pub fn class_is_annotation(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let descriptor = this_descriptor(&arguments)?;
+    let is_annotation = !descriptor.starts_with('[') &&
+        this_class(class_table, &arguments)?.is_some_and(|class| class.access_flags & class_flags::ACC_ANNOTATION != 0);
+    boolean_result(is_annotation)
+}
+
+// java.lang.Class
+
+pub fn class_for_name(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let name = match string_arg(&arguments, 0)? {
+        Some(chars) => String::from_utf16_lossy(&chars),
+        None => return throw_new(class_table, "java/lang/NullPointerException", None)
+    };
+    let class = match class_table.find_class(&name.replace('.', "/")) {
+        Ok(Some(class)) if !name.contains('/') => class,
+        _ => return throw_new(class_table, "java/lang/ClassNotFoundException", Some(&name))
+    };
+    if let Some(exception) = interpreter::initialize_class(&class, class_table)? {
+        return Ok(InvokeResult::Exception(exception));
+    }
+    Ok(InvokeResult::Value(Value::ObjectRef(class_table.get_mirror(&class.class_name))))
+}
+
+// The class of a mirror that is not for a primitive type. Arrays have the members of Object.
+fn this_class(class_table: &ClassTable, arguments: &[Value]) -> Result<Option<Arc<RuntimeClass>>, InterpreterError> {
+    let mirror = object_arg(arguments, 0)?;
+    mirror_class(class_table, &mirror)
+}
+
+fn this_descriptor(arguments: &[Value]) -> Result<String, InterpreterError> {
+    let mirror = object_arg(arguments, 0)?;
+    Ok(mirror_descriptor(&mirror))
+}
+
+// The name in the source, without the package or enclosing classes. Arrays are named after their
+// component, and anonymous classes have no name.
+pub fn class_get_simple_name(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let descriptor = this_descriptor(&arguments)?;
+    let name = type_name(&descriptor);
+    let (name, dimensions) = match name.find("[]") {
+        Some(index) => (&name[..index], &name[index..]),
+        None => (&name[..], "")
+    };
+    let name = name.rsplit('.').next().unwrap_or(name);
+    let name = name.rsplit('$').next().unwrap_or(name);
+    let name = if name.starts_with(|c: char| c.is_ascii_digit()) { "" } else { name };
+    return_string(class_table, &format!("{}{}", name, dimensions))
+}
+
+// Nested classes have the modifiers they were declared with, which only their InnerClasses
+// attribute records.
+pub fn class_get_modifiers(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let descriptor = this_descriptor(&arguments)?;
+    let modifiers = match this_class(class_table, &arguments)? {
+        Some(ref class) if !descriptor.starts_with('[') => {
+            class.inner_class().map_or(class.access_flags, |inner_class| inner_class.access_flags) & CLASS_MODIFIERS
+        },
+        _ => class_flags::ACC_PUBLIC | class_flags::ACC_FINAL | class_flags::ACC_ABSTRACT
+    };
+    int_result(modifiers as i32)
+}
+
+pub fn class_get_superclass(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let descriptor = this_descriptor(&arguments)?;
+    if descriptor.starts_with('[') {
+        return Ok(InvokeResult::Value(Value::ObjectRef(class_table.get_mirror(OBJECT_CLASS_NAME))));
+    }
+    let super_class = match this_class(class_table, &arguments)? {
+        Some(ref class) if !class.is_interface() => class.super_class.clone(),
+        _ => None
+    };
+    match super_class {
+        Some(super_class) => Ok(InvokeResult::Value(Value::ObjectRef(class_table.get_mirror(&super_class.class_name)))),
+        None => Ok(InvokeResult::Value(Value::Null))
+    }
+}
+
+pub fn class_get_interfaces(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let descriptor = this_descriptor(&arguments)?;
+    let interfaces = match this_class(class_table, &arguments)? {
+        Some(_) if descriptor.starts_with('[') => vec![String::from("Ljava/lang/Cloneable;"), String::from("Ljava/io/Serializable;")],
+        Some(class) => class.interfaces.iter().map(|interface| format!("L{};", interface.class_name)).collect(),
+        None => Vec::new()
+    };
+    Ok(InvokeResult::Value(class_mirrors(class_table, &interfaces)))
+}
+
+pub fn class_is_interface(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let descriptor = this_descriptor(&arguments)?;
+    let is_interface = !descriptor.starts_with('[') && this_class(class_table, &arguments)?.is_some_and(|class| class.is_interface());
+    boolean_result(is_interface)
+}
+
+pub fn class_is_array(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    boolean_result(this_descriptor(&arguments)?.starts_with('['))
+}
+
+pub fn class_is_primitive(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    boolean_result(is_primitive(&this_descriptor(&arguments)?))
+}
+
+pub fn class_get_component_type(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    match this_descriptor(&arguments)?.strip_prefix('[') {
+        Some(component) => Ok(InvokeResult::Value(type_mirror(class_table, component))),
+        None => Ok(InvokeResult::Value(Value::Null))
+    }
+}
+
+pub fn class_is_instance(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let descriptor = this_descriptor(&arguments)?;
+    let is_instance = !is_primitive(&descriptor) && interpreter::is_instance_of(&arguments[1], class_name(&descriptor), class_table);
+    boolean_result(is_instance)
+}
+
+pub fn class_is_assignable_from(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let descriptor = this_descriptor(&arguments)?;
+    let other = match arguments.get(1) {
+        Some(Value::ObjectRef(other)) => other.clone(),
+        _ => return throw_new(class_table, "java/lang/NullPointerException", None)
+    };
+    let other_descriptor = mirror_descriptor(&other);
+    let assignable = if descriptor == other_descriptor {
+        true
+    } else if is_primitive(&descriptor) || is_primitive(&other_descriptor) {
+        false
+    } else if other_descriptor.starts_with('[') {
+        interpreter::is_array_assignable(&other_descriptor, class_name(&descriptor), class_table)
+    } else {
+        mirror_class(class_table, &other)?.is_some_and(|class| class.is_subclass_of(class_name(&descriptor)))
+    };
+    boolean_result(assignable)
+}
+
+// The members of a class, declared by it, or public ones including those it inherits, which are
+// found in the class, then its superclasses, then its superinterfaces.
+fn public_classes(class: &Arc<RuntimeClass>) -> Vec<Arc<RuntimeClass>> {
+    let mut classes: Vec<Arc<RuntimeClass>> = Vec::new();
+    let mut current = Some(class.clone());
+    while let Some(c) = current {
+        classes.push(c.clone());
+        current = c.super_class.clone();
+    }
+    let mut pending: Vec<Arc<RuntimeClass>> = classes.iter().flat_map(|c| c.interfaces.iter().cloned()).collect();
+    while let Some(interface) = pending.pop() {
+        if !classes.iter().any(|c| c.class_name == interface.class_name) {
+            pending.extend(interface.interfaces.iter().cloned());
+            classes.push(interface);
+        }
+    }
+    classes
+}
+
+// Each method the class declares, or each public one including those it inherits, other than
+// constructors and initializers, as (class, slot). Constructors are selected instead if asked
+// for. Inherited methods overridden by one found earlier are left out.
+fn find_methods(class: &Arc<RuntimeClass>, public: bool, constructors: bool) -> Vec<(Arc<RuntimeClass>, usize)> {
+    let classes = if public && !constructors { public_classes(class) } else { vec![class.clone()] };
+    let mut found: Vec<(Arc<RuntimeClass>, usize)> = Vec::new();
+    for c in classes.iter() {
+        for (slot, method) in c.methods.iter().enumerate() {
+            let selected = is_constructor(method) == constructors &&
+                method.name != "<clinit>" &&
+                (!public || method.access_flags & method::ACC_PUBLIC != 0) &&
+                !found.iter().any(|&(ref other, other_slot)| {
+                    let other = &other.methods[other_slot];
+                    other.name == method.name && other.descriptor == method.descriptor
+                });
+            if selected {
+                found.push((c.clone(), slot));
+            }
+        }
+    }
+    found
+}
+
+fn find_fields(class: &Arc<RuntimeClass>, public: bool) -> Vec<(Arc<RuntimeClass>, usize)> {
+    let classes = if public { public_classes(class) } else { vec![class.clone()] };
+    classes.iter()
+        .flat_map(|c| c.fields.iter().enumerate()
+            .filter(|&(_, field)| !public || field.access_flags & field::ACC_PUBLIC != 0)
+            .map(move |(slot, _)| (c.clone(), slot)))
+        .collect()
+}
+
+fn get_methods(class_table: &ClassTable, arguments: &[Value], public: bool, constructors: bool) -> Result<InvokeResult, InterpreterError> {
+    let member_class_name = if constructors { CONSTRUCTOR_CLASS_NAME } else { METHOD_CLASS_NAME };
+    let found = match this_class(class_table, arguments)? {
+        Some(ref class) if !this_descriptor(arguments)?.starts_with('[') => find_methods(class, public, constructors),
+        _ => Vec::new()
+    };
+    let values = found.iter()
+        .map(|&(ref class, slot)| new_member(class_table, member_class_name, class, slot))
+        .collect::<Result<Vec<Value>, InterpreterError>>()?;
+    members(class_table, member_class_name, values)
+}
+
+fn get_fields(class_table: &ClassTable, arguments: &[Value], public: bool) -> Result<InvokeResult, InterpreterError> {
+    let found = match this_class(class_table, arguments)? {
+        Some(ref class) if !this_descriptor(arguments)?.starts_with('[') => find_fields(class, public),
+        _ => Vec::new()
+    };
+    let values = found.iter()
+        .map(|&(ref class, slot)| new_member(class_table, FIELD_CLASS_NAME, class, slot))
+        .collect::<Result<Vec<Value>, InterpreterError>>()?;
+    members(class_table, FIELD_CLASS_NAME, values)
+}
+
+// Selects a method or constructor by its name and parameter types, given as an array of mirrors,
+// or throws NoSuchMethodException.
+fn get_method(class_table: &ClassTable, arguments: &[Value], public: bool, name: Option<&str>) -> Result<InvokeResult, InterpreterError> {
+    let parameter_mirrors: Vec<Arc<HeapCell<Object>>> = match arguments.last() {
+        Some(Value::ArrayRef(array)) => {
+            let array = array.borrow();
+            (0..array.len()).filter_map(|index| match array.get(index) {
+                Value::ObjectRef(mirror) => Some(mirror),
+                _ => None
+            }).collect()
+        },
+        _ => Vec::new()
+    };
+    let parameters: String = parameter_mirrors.iter().map(mirror_descriptor).collect();
+    let descriptor_start = format!("({})", parameters);
+
+    let constructors = name.is_none();
+    let found = match this_class(class_table, arguments)? {
+        Some(ref class) if !this_descriptor(arguments)?.starts_with('[') => find_methods(class, public, constructors),
+        _ => Vec::new()
+    };
+    let method_name = name.unwrap_or("<init>");
+    let selected = found.iter().find(|&&(ref class, slot)| {
+        let method = &class.methods[slot];
+        method.name == method_name && method.descriptor.starts_with(&descriptor_start)
+    });
+    match selected {
+        Some(&(ref class, slot)) => {
+            let member_class_name = if constructors { CONSTRUCTOR_CLASS_NAME } else { METHOD_CLASS_NAME };
+            Ok(InvokeResult::Value(new_member(class_table, member_class_name, class, slot)?))
+        },
+        None => {
+            let class_name = type_name(&this_descriptor(arguments)?);
+            let parameter_names = parameter_mirrors.iter()
+                .map(|mirror| mirror_internal_name(mirror).replace('/', "."))
+                .collect::<Vec<String>>()
+                .join(", ");
+            let message = format!("{}.{}({})", class_name, method_name, parameter_names);
+            throw_new(class_table, "java/lang/NoSuchMethodException", Some(&message))
+        }
+    }
+}
+
+fn get_field_named(class_table: &ClassTable, arguments: &[Value], public: bool) -> Result<InvokeResult, InterpreterError> {
+    let name = match name_arg(arguments)? {
+        Some(name) => name,
+        None => return throw_new(class_table, "java/lang/NullPointerException", None)
+    };
+    let found = match this_class(class_table, arguments)? {
+        Some(ref class) if !this_descriptor(arguments)?.starts_with('[') => find_fields(class, public),
+        _ => Vec::new()
+    };
+    match found.iter().find(|&&(ref class, slot)| class.fields[slot].name == name) {
+        Some(&(ref class, slot)) => Ok(InvokeResult::Value(new_member(class_table, FIELD_CLASS_NAME, class, slot)?)),
+        None => throw_new(class_table, "java/lang/NoSuchFieldException", Some(&name))
+    }
+}
+
+fn name_arg(arguments: &[Value]) -> Result<Option<String>, InterpreterError> {
+    Ok(string_arg(arguments, 1)?.map(|chars| String::from_utf16_lossy(&chars)))
+}
+
+pub fn class_get_declared_methods(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    get_methods(class_table, &arguments, false, false)
+}
+
+pub fn class_get_methods(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    get_methods(class_table, &arguments, true, false)
+}
+
+pub fn class_get_declared_constructors(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    get_methods(class_table, &arguments, false, true)
+}
+
+pub fn class_get_constructors(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    get_methods(class_table, &arguments, true, true)
+}
+
+pub fn class_get_declared_fields(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    get_fields(class_table, &arguments, false)
+}
+
+pub fn class_get_fields(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    get_fields(class_table, &arguments, true)
+}
+
+pub fn class_get_declared_method(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    match name_arg(&arguments)? {
+        Some(name) => get_method(class_table, &arguments, false, Some(&name)),
+        None => throw_new(class_table, "java/lang/NullPointerException", None)
+    }
+}
+
+pub fn class_get_method(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    match name_arg(&arguments)? {
+        Some(name) => get_method(class_table, &arguments, true, Some(&name)),
+        None => throw_new(class_table, "java/lang/NullPointerException", None)
+    }
+}
+
+pub fn class_get_declared_constructor(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    get_method(class_table, &arguments, false, None)
+}
+
+pub fn class_get_constructor(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    get_method(class_table, &arguments, true, None)
+}
+
+pub fn class_get_declared_field(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    get_field_named(class_table, &arguments, false)
+}
+
+pub fn class_get_field(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    get_field_named(class_table, &arguments, true)
+}
@@ -0,0 +1,612 @@
+use runtime::{Value, Object, ArrayElements, HeapCell};
+use runtime::class::ClassTable;
+use runtime::interpreter::{InvokeResult, InterpreterError};
+use runtime::bootstrap::{ClassBuilder, object_arg, int_arg, long_arg, float_arg, double_arg, string_arg,
+                         to_java_chars, throw_new, encode, new_string, format_float, format_double};
+use runtime::string::{self, STRING_CLASS_NAME, VALUE_FIELD};
+use class::field;
+use class::method::{ACC_PUBLIC, ACC_STATIC};
+use std::sync::Arc;
+
+const PUBLIC_STATIC: u16 = ACC_PUBLIC | ACC_STATIC;
+const STRING_BUILDER_CLASS_NAME: &str = "java/lang/StringBuilder";
+
+pub fn load(class_table: &ClassTable) {
+    ClassBuilder::new(STRING_CLASS_NAME)
+        .implements("java/io/Serializable")
+        .implements("java/lang/Comparable")
+        .implements("java/lang/CharSequence")
+        .field(VALUE_FIELD, "[C", field::ACC_PRIVATE | field::ACC_FINAL)
+        .field("hash", "I", field::ACC_PRIVATE)
+        .native("<init>", "()V", ACC_PUBLIC, string_init)
+        .native("<init>", "([C)V", ACC_PUBLIC, string_init_chars)
+        .native("<init>", "([CII)V", ACC_PUBLIC, string_init_chars_range)
+        .native("<init>", "(Ljava/lang/String;)V", ACC_PUBLIC, string_init_string)
+        .native("<init>", "(Ljava/lang/StringBuilder;)V", ACC_PUBLIC, string_init_string)
+        .native("length", "()I", ACC_PUBLIC, string_length)
+        .native("isEmpty", "()Z", ACC_PUBLIC, string_is_empty)
+        .native("charAt", "(I)C", ACC_PUBLIC, string_char_at)
+        .native("equals", "(Ljava/lang/Object;)Z", ACC_PUBLIC, string_equals)
+        .native("equalsIgnoreCase", "(Ljava/lang/String;)Z", ACC_PUBLIC, string_equals_ignore_case)
+        .native("hashCode", "()I", ACC_PUBLIC, string_hash_code)
+        .native("toString", "()Ljava/lang/String;", ACC_PUBLIC, string_to_string)
+        .native("compareTo", "(Ljava/lang/String;)I", ACC_PUBLIC, string_compare_to)
+        .native("compareTo", "(Ljava/lang/Object;)I", ACC_PUBLIC, string_compare_to)
+        .native("concat", "(Ljava/lang/String;)Ljava/lang/String;", ACC_PUBLIC, string_concat)
+        .native("substring", "(I)Ljava/lang/String;", ACC_PUBLIC, string_substring)
+        .native("substring", "(II)Ljava/lang/String;", ACC_PUBLIC, string_substring_range)
+        .native("indexOf", "(I)I", ACC_PUBLIC, string_index_of_char)
+        .native("indexOf", "(Ljava/lang/String;)I", ACC_PUBLIC, string_index_of_string)
+        .native("lastIndexOf", "(I)I", ACC_PUBLIC, string_last_index_of_char)
+        .native("contains", "(Ljava/lang/CharSequence;)Z", ACC_PUBLIC, string_contains)
+        .native("startsWith", "(Ljava/lang/String;)Z", ACC_PUBLIC, string_starts_with)
+        .native("endsWith", "(Ljava/lang/String;)Z", ACC_PUBLIC, string_ends_with)
+        .native("toUpperCase", "()Ljava/lang/String;", ACC_PUBLIC, string_to_upper_case)
+        .native("toLowerCase", "()Ljava/lang/String;", ACC_PUBLIC, string_to_lower_case)
+        .native("trim", "()Ljava/lang/String;", ACC_PUBLIC, string_trim)
+        .native("intern", "()Ljava/lang/String;", ACC_PUBLIC, string_intern)
+        .native("toCharArray", "()[C", ACC_PUBLIC, string_to_char_array)
+        .native("replace", "(CC)Ljava/lang/String;", ACC_PUBLIC, string_replace)
+        .native("valueOf", "(Ljava/lang/Object;)Ljava/lang/String;", PUBLIC_STATIC, string_value_of_object)
+        .native("valueOf", "([C)Ljava/lang/String;", PUBLIC_STATIC, string_value_of_chars)
+        .native("valueOf", "(I)Ljava/lang/String;", PUBLIC_STATIC, string_value_of_int)
+        .native("valueOf", "(J)Ljava/lang/String;", PUBLIC_STATIC, string_value_of_long)
+        .native("valueOf", "(F)Ljava/lang/String;", PUBLIC_STATIC, string_value_of_float)
+        .native("valueOf", "(D)Ljava/lang/String;", PUBLIC_STATIC, string_value_of_double)
+        .native("valueOf", "(C)Ljava/lang/String;", PUBLIC_STATIC, string_value_of_char)
+        .native("valueOf", "(Z)Ljava/lang/String;", PUBLIC_STATIC, string_value_of_boolean)
+        .define(class_table);
+
+    ClassBuilder::new(STRING_BUILDER_CLASS_NAME)
+        .implements("java/io/Serializable")
+        .implements("java/lang/CharSequence")
+        .field(VALUE_FIELD, "[C", field::ACC_PRIVATE)
+        .native("<init>", "()V", ACC_PUBLIC, string_builder_init)
+        .native("<init>", "(I)V", ACC_PUBLIC, string_builder_init)
+        .native("<init>", "(Ljava/lang/String;)V", ACC_PUBLIC, string_builder_init_string)
+        .native("<init>", "(Ljava/lang/CharSequence;)V", ACC_PUBLIC, string_builder_init_string)
+        .native("append", "(Ljava/lang/String;)Ljava/lang/StringBuilder;", ACC_PUBLIC, append_object)
+        .native("append", "(Ljava/lang/Object;)Ljava/lang/StringBuilder;", ACC_PUBLIC, append_object)
+        .native("append", "(Ljava/lang/CharSequence;)Ljava/lang/StringBuilder;", ACC_PUBLIC, append_object)
+        .native("append", "([C)Ljava/lang/StringBuilder;", ACC_PUBLIC, append_chars)
+        .native("append", "(I)Ljava/lang/StringBuilder;", ACC_PUBLIC, append_int)
+        .native("append", "(J)Ljava/lang/StringBuilder;", ACC_PUBLIC, append_long)
+        .native("append", "(F)Ljava/lang/StringBuilder;", ACC_PUBLIC, append_float)
+        .native("append", "(D)Ljava/lang/StringBuilder;", ACC_PUBLIC, append_double)
+        .native("append", "(C)Ljava/lang/StringBuilder;", ACC_PUBLIC, append_char)
+        .native("append", "(Z)Ljava/lang/StringBuilder;", ACC_PUBLIC, append_boolean)
+        .native("insert", "(ILjava/lang/String;)Ljava/lang/StringBuilder;", ACC_PUBLIC, string_builder_insert)
+        .native("insert", "(IC)Ljava/lang/StringBuilder;", ACC_PUBLIC, string_builder_insert_char)
+        .native("toString", "()Ljava/lang/String;", ACC_PUBLIC, string_builder_to_string)
+        .native("length", "()I", ACC_PUBLIC, string_length)
+        .native("charAt", "(I)C", ACC_PUBLIC, string_char_at)
+        .native("setCharAt", "(IC)V", ACC_PUBLIC, string_builder_set_char_at)
+        .native("deleteCharAt", "(I)Ljava/lang/StringBuilder;", ACC_PUBLIC, string_builder_delete_char_at)
+        .native("reverse", "()Ljava/lang/StringBuilder;", ACC_PUBLIC, string_builder_reverse)
+        .native("setLength", "(I)V", ACC_PUBLIC, string_builder_set_length)
+        .define(class_table);
+}
+
+// The characters of `this`, which is either a String or a StringBuilder.
+fn this_chars(arguments: &[Value]) -> Result<Vec<u16>, InterpreterError> {
+    let this = object_arg(arguments, 0)?;
+    let chars = string::get_chars(&this.borrow());
+    Ok(chars)
+}
+
+fn set_chars(class_table: &ClassTable, object: &Arc<HeapCell<Object>>, chars: Vec<u16>) {
+    let value = Value::ArrayRef(class_table.new_array_from("C", ArrayElements::Character(chars)));
+    object.borrow_mut().put_field(String::from(VALUE_FIELD), value);
+}
+
+fn chars_array_arg(arguments: &[Value], index: usize) -> Result<Option<Vec<u16>>, InterpreterError> {
+    match arguments.get(index) {
+        Some(Value::ArrayRef(array)) => {
+            let array = array.borrow();
+            let chars = array.as_chars().ok_or(InterpreterError::UnexpectedOperand)?;
+            Ok(Some(chars.to_vec()))
+        },
+        Some(Value::Null) => Ok(None),
+        _ => Err(InterpreterError::UnexpectedOperand)
+    }
+}
+
+fn string_result(class_table: &ClassTable, chars: Vec<u16>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(new_string(class_table, chars)))
+}
+
+fn int_result(value: i32) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Integer(value)))
+}
+
+fn boolean_result(value: bool) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::Integer(value as i32)))
+}
+
+fn null_pointer(class_table: &ClassTable) -> Result<InvokeResult, InterpreterError> {
+    throw_new(class_table, "java/lang/NullPointerException", None)
+}
+
+fn index_out_of_bounds(class_table: &ClassTable, index: i32) -> Result<InvokeResult, InterpreterError> {
+    let message = format!("String index out of range: {}", index);
+    throw_new(class_table, "java/lang/StringIndexOutOfBoundsException", Some(&message))
+}
+
+fn index_of(haystack: &[u16], needle: &[u16]) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+// Case mapping of a single UTF-16 code unit. Characters whose mapping is not a single BMP
+// character, such as 'ß', are left unchanged.
+fn map_case<F, I>(chars: &[u16], mapping: F) -> Vec<u16>
+    where F: Fn(char) -> I, I: Iterator<Item = char> {
+    chars.iter()
+        .map(|&unit| {
+            let c = match ::std::char::from_u32(unit as u32) {
+                Some(c) => c,
+                None => return unit
+            };
+            let mut mapped = mapping(c);
+            match (mapped.next(), mapped.next()) {
+                (Some(m), None) if (m as u32) < 0x10000 => m as u32 as u16,
+                _ => unit
+            }
+        })
+        .collect()
+}
+
+// java.lang.String
+
+fn string_init(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    set_chars(class_table, &object_arg(&arguments, 0)?, Vec::new());
+    Ok(InvokeResult::Void)
+}
+
+fn string_init_chars(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    match chars_array_arg(&arguments, 1)? {
+        Some(chars) => {
+            set_chars(class_table, &object_arg(&arguments, 0)?, chars);
+            Ok(InvokeResult::Void)
+        },
+        None => null_pointer(class_table)
+    }
+}
+
+fn string_init_chars_range(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let chars = match chars_array_arg(&arguments, 1)? {
+        Some(chars) => chars,
+        None => return null_pointer(class_table)
+    };
+    let offset = int_arg(&arguments, 2)?;
+    let count = int_arg(&arguments, 3)?;
+
+    if offset < 0 || count < 0 || offset as usize + count as usize > chars.len() {
+        return index_out_of_bounds(class_table, offset + count);
+    }
+
+    set_chars(class_table, &object_arg(&arguments, 0)?, chars[(offset as usize)..((offset + count) as usize)].to_vec());
+    Ok(InvokeResult::Void)
+}
+
+// Copies the characters of a String or StringBuilder.
+fn string_init_string(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let other = match arguments[1] {
+        Value::ObjectRef(ref other) => other.clone(),
+        _ => return null_pointer(class_table)
+    };
+    let chars = string::get_chars(&other.borrow());
+
+    set_chars(class_table, &object_arg(&arguments, 0)?, chars);
+    Ok(InvokeResult::Void)
+}
+
+fn string_length(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    int_result(this_chars(&arguments)?.len() as i32)
+}
+
+fn string_is_empty(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    boolean_result(this_chars(&arguments)?.is_empty())
+}
+
+fn string_char_at(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let chars = this_chars(&arguments)?;
+    let index = int_arg(&arguments, 1)?;
+
+    match chars.get(index as usize) {
+        Some(&c) if index >= 0 => int_result(c as i32),
+        _ => index_out_of_bounds(class_table, index)
+    }
+}
+
+fn string_equals(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let equal = match arguments[1] {
+        Value::ObjectRef(ref other) if other.borrow().class().class_name == STRING_CLASS_NAME => {
+            this_chars(&arguments)? == string::get_chars(&other.borrow())
+        },
+        _ => false
+    };
+
+    boolean_result(equal)
+}
+
+fn string_equals_ignore_case(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let equal = match string_arg(&arguments, 1)? {
+        Some(other) => {
+            let chars = this_chars(&arguments)?;
+            map_case(&chars, char::to_lowercase) == map_case(&other, char::to_lowercase)
+        },
+        None => false
+    };
+
+    boolean_result(equal)
+}
+
+// s[0]*31^(n-1) + s[1]*31^(n-2) + ... + s[n-1], cached in the hash field like the JDK does.
+fn string_hash_code(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    if let Value::Integer(hash) = this.borrow().get_field(String::from("hash")) {
+        if hash != 0 {
+            return int_result(hash);
+        }
+    }
+
+    let hash = string::get_chars(&this.borrow())
+        .iter()
+        .fold(0i32, |hash, &c| hash.wrapping_mul(31).wrapping_add(c as i32));
+    this.borrow_mut().put_field(String::from("hash"), Value::Integer(hash));
+
+    int_result(hash)
+}
+
+fn string_to_string(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(arguments[0].clone()))
+}
+
+fn string_compare_to(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let chars = this_chars(&arguments)?;
+    let other = match string_arg(&arguments, 1)? {
+        Some(other) => other,
+        None => return null_pointer(class_table)
+    };
+
+    for (a, b) in chars.iter().zip(other.iter()) {
+        if a != b {
+            return int_result(*a as i32 - *b as i32);
+        }
+    }
+
+    int_result(chars.len() as i32 - other.len() as i32)
+}
+
+fn string_concat(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let mut chars = this_chars(&arguments)?;
+    match string_arg(&arguments, 1)? {
+        Some(other) => chars.extend(other),
+        None => return null_pointer(class_table)
+    }
+
+    string_result(class_table, chars)
+}
+
+fn substring(class_table: &ClassTable, chars: Vec<u16>, begin: i32, end: i32) -> Result<InvokeResult, InterpreterError> {
+    if begin < 0 {
+        return index_out_of_bounds(class_table, begin);
+    }
+    if end as usize > chars.len() {
+        return index_out_of_bounds(class_table, end);
+    }
+    if begin > end {
+        return index_out_of_bounds(class_table, end - begin);
+    }
+
+    string_result(class_table, chars[(begin as usize)..(end as usize)].to_vec())
+}
+
+fn string_substring(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let chars = this_chars(&arguments)?;
+    let begin = int_arg(&arguments, 1)?;
+    let end = chars.len() as i32;
+    substring(class_table, chars, begin, end)
+}
+
+fn string_substring_range(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let chars = this_chars(&arguments)?;
+    let begin = int_arg(&arguments, 1)?;
+    let end = int_arg(&arguments, 2)?;
+    substring(class_table, chars, begin, end)
+}
+
+fn string_index_of_char(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let chars = this_chars(&arguments)?;
+    let c = int_arg(&arguments, 1)?;
+    let index = chars.iter().position(|&unit| unit as i32 == c);
+    int_result(index.map(|index| index as i32).unwrap_or(-1))
+}
+
+fn string_index_of_string(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let chars = this_chars(&arguments)?;
+    let needle = match string_arg(&arguments, 1)? {
+        Some(needle) => needle,
+        None => return null_pointer(class_table)
+    };
+
+    int_result(index_of(&chars, &needle).map(|index| index as i32).unwrap_or(-1))
+}
+
+fn string_last_index_of_char(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let chars = this_chars(&arguments)?;
+    let c = int_arg(&arguments, 1)?;
+    let index = chars.iter().rposition(|&unit| unit as i32 == c);
+    int_result(index.map(|index| index as i32).unwrap_or(-1))
+}
+
+fn string_contains(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    if let Value::Null = arguments[1] {
+        return null_pointer(class_table);
+    }
+
+    let chars = this_chars(&arguments)?;
+    let needle = java_try!(to_java_chars(class_table, &arguments[1]));
+    boolean_result(index_of(&chars, &needle).is_some())
+}
+
+fn string_starts_with(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let chars = this_chars(&arguments)?;
+    match string_arg(&arguments, 1)? {
+        Some(prefix) => boolean_result(chars.starts_with(&prefix)),
+        None => null_pointer(class_table)
+    }
+}
+
+fn string_ends_with(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let chars = this_chars(&arguments)?;
+    match string_arg(&arguments, 1)? {
+        Some(suffix) => boolean_result(chars.ends_with(&suffix)),
+        None => null_pointer(class_table)
+    }
+}
+
+fn string_to_upper_case(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let chars = this_chars(&arguments)?;
+    string_result(class_table, map_case(&chars, char::to_uppercase))
+}
+
+fn string_to_lower_case(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let chars = this_chars(&arguments)?;
+    string_result(class_table, map_case(&chars, char::to_lowercase))
+}
+
+// Removes leading and trailing characters up to and including U+0020, as String.trim does.
+fn string_trim(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let chars = this_chars(&arguments)?;
+    let start = chars.iter().position(|&c| c > 0x20).unwrap_or(chars.len());
+    let end = chars.iter().rposition(|&c| c > 0x20).map(|end| end + 1).unwrap_or(start);
+
+    if start == 0 && end == chars.len() {
+        return Ok(InvokeResult::Value(arguments[0].clone()));
+    }
+    string_result(class_table, chars[start..end].to_vec())
+}
+
+fn string_intern(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    Ok(InvokeResult::Value(Value::ObjectRef(class_table.intern(&this))))
+}
+
+fn string_to_char_array(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let chars = this_chars(&arguments)?;
+    Ok(InvokeResult::Value(Value::ArrayRef(class_table.new_array_from("C", ArrayElements::Character(chars)))))
+}
+
+fn string_replace(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let old_char = int_arg(&arguments, 1)? as u16;
+    let new_char = int_arg(&arguments, 2)? as u16;
+    let chars = this_chars(&arguments)?
+        .into_iter()
+        .map(|c| if c == old_char { new_char } else { c })
+        .collect();
+
+    string_result(class_table, chars)
+}
+
+fn string_value_of_object(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let chars = java_try!(to_java_chars(class_table, &arguments[0]));
+    string_result(class_table, chars)
+}
+
+fn string_value_of_chars(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    match chars_array_arg(&arguments, 0)? {
+        Some(chars) => string_result(class_table, chars),
+        None => null_pointer(class_table)
+    }
+}
+
+fn string_value_of_int(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    string_result(class_table, encode(&int_arg(&arguments, 0)?.to_string()))
+}
+
+fn string_value_of_long(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    string_result(class_table, encode(&long_arg(&arguments, 0)?.to_string()))
+}
+
+fn string_value_of_float(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    string_result(class_table, encode(&format_float(float_arg(&arguments, 0)?)))
+}
+
+fn string_value_of_double(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    string_result(class_table, encode(&format_double(double_arg(&arguments, 0)?)))
+}
+
+fn string_value_of_char(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    string_result(class_table, vec![int_arg(&arguments, 0)? as u16])
+}
+
+fn string_value_of_boolean(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let value = if int_arg(&arguments, 0)? != 0 { "true" } else { "false" };
+    string_result(class_table, encode(value))
+}
+
+// java.lang.StringBuilder
+
+fn string_builder_init(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    set_chars(class_table, &object_arg(&arguments, 0)?, Vec::new());
+    Ok(InvokeResult::Void)
+}
+
+fn string_builder_init_string(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    if let Value::Null = arguments[1] {
+        return null_pointer(class_table);
+    }
+
+    let chars = java_try!(to_java_chars(class_table, &arguments[1]));
+    set_chars(class_table, &object_arg(&arguments, 0)?, chars);
+    Ok(InvokeResult::Void)
+}
+
+// Appends to the builder and returns it, so that calls can be chained.
+fn append(class_table: &ClassTable, arguments: &[Value], suffix: &[u16]) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(arguments, 0)?;
+    let mut chars = string::get_chars(&this.borrow());
+    chars.extend_from_slice(suffix);
+    set_chars(class_table, &this, chars);
+
+    Ok(InvokeResult::Value(arguments[0].clone()))
+}
+
+fn append_object(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let chars = java_try!(to_java_chars(class_table, &arguments[1]));
+    append(class_table, &arguments, &chars)
+}
+
+fn append_chars(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    match chars_array_arg(&arguments, 1)? {
+        Some(chars) => append(class_table, &arguments, &chars),
+        None => null_pointer(class_table)
+    }
+}
+
+fn append_int(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    append(class_table, &arguments, &encode(&int_arg(&arguments, 1)?.to_string()))
+}
+
+fn append_long(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    append(class_table, &arguments, &encode(&long_arg(&arguments, 1)?.to_string()))
+}
+
+fn append_float(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    append(class_table, &arguments, &encode(&format_float(float_arg(&arguments, 1)?)))
+}
+
+fn append_double(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    append(class_table, &arguments, &encode(&format_double(double_arg(&arguments, 1)?)))
+}
+
+fn append_char(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    append(class_table, &arguments, &[int_arg(&arguments, 1)? as u16])
+}
+
+fn append_boolean(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let value = if int_arg(&arguments, 1)? != 0 { "true" } else { "false" };
+    append(class_table, &arguments, &encode(value))
+}
+
+fn insert(class_table: &ClassTable, arguments: &[Value], inserted: &[u16]) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(arguments, 0)?;
+    let offset = int_arg(arguments, 1)?;
+    let mut chars = string::get_chars(&this.borrow());
+
+    if offset < 0 || offset as usize > chars.len() {
+        return index_out_of_bounds(class_table, offset);
+    }
+
+    let tail = chars.split_off(offset as usize);
+    chars.extend_from_slice(inserted);
+    chars.extend(tail);
+    set_chars(class_table, &this, chars);
+
+    Ok(InvokeResult::Value(arguments[0].clone()))
+}
+
+fn string_builder_insert(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let inserted = string_arg(&arguments, 2)?.unwrap_or_else(|| encode("null"));
+    insert(class_table, &arguments, &inserted)
+}
+
+fn string_builder_insert_char(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let c = int_arg(&arguments, 2)? as u16;
+    insert(class_table, &arguments, &[c])
+}
+
+fn string_builder_to_string(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    string_result(class_table, this_chars(&arguments)?)
+}
+
+fn string_builder_set_char_at(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let index = int_arg(&arguments, 1)?;
+    let c = int_arg(&arguments, 2)? as u16;
+    let mut chars = string::get_chars(&this.borrow());
+
+    if index < 0 || index as usize >= chars.len() {
+        return index_out_of_bounds(class_table, index);
+    }
+
+    chars[index as usize] = c;
+    set_chars(class_table, &this, chars);
+    Ok(InvokeResult::Void)
+}
+
+fn string_builder_delete_char_at(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let index = int_arg(&arguments, 1)?;
+    let mut chars = string::get_chars(&this.borrow());
+
+    if index < 0 || index as usize >= chars.len() {
+        return index_out_of_bounds(class_table, index);
+    }
+
+    chars.remove(index as usize);
+    set_chars(class_table, &this, chars);
+    Ok(InvokeResult::Value(arguments[0].clone()))
+}
+
+// Surrogate pairs are kept in order, so reversing never produces lone surrogates that were not
+// already in the builder.
+fn string_builder_reverse(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let chars = string::get_chars(&this.borrow());
+
+    let mut reversed = Vec::with_capacity(chars.len());
+    let mut i = chars.len();
+    while i > 0 {
+        let low = chars[i - 1];
+        if i > 1 && (0xDC00..0xE000).contains(&low) && (0xD800..0xDC00).contains(&chars[i - 2]) {
+            reversed.push(chars[i - 2]);
+            reversed.push(low);
+            i -= 2;
+        } else {
+            reversed.push(low);
+            i -= 1;
+        }
+    }
+
+    set_chars(class_table, &this, reversed);
+    Ok(InvokeResult::Value(arguments[0].clone()))
+}
+
+fn string_builder_set_length(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let length = int_arg(&arguments, 1)?;
+    if length < 0 {
+        return index_out_of_bounds(class_table, length);
+    }
+
+    let mut chars = string::get_chars(&this.borrow());
+    chars.resize(length as usize, 0);
+    set_chars(class_table, &this, chars);
+    Ok(InvokeResult::Void)
+}
@@ -0,0 +1,381 @@
+use runtime::{Value, HeapCell, Object};
+use runtime::class::ClassTable;
+use runtime::interpreter::{self, InvokeResult, InterpreterError};
+use runtime::bootstrap::{self, ClassBuilder, object_arg, int_arg, long_arg, string_arg, throw_new, encode, new_string,
+                         return_string};
+use runtime::string;
+use class::field;
+use class::method::{ACC_PUBLIC, ACC_STATIC};
+use std::io::Write;
+use std::process;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const THREAD_CLASS_NAME: &str = "java/lang/Thread";
+
+const PUBLIC_STATIC: u16 = ACC_PUBLIC | ACC_STATIC;
+
+// Java threads run on threads of their own, with the stack size of the main thread on Linux.
+// The interpreter recurses for every Java call, so the Rust default of 2 MiB is too small.
+pub const STACK_SIZE: usize = 8 * 1024 * 1024;
+
+pub fn load(class_table: &ClassTable) {
+    ClassBuilder::interface("java/lang/Runnable").define(class_table);
+
+    ClassBuilder::new(THREAD_CLASS_NAME)
+        .implements("java/lang/Runnable")
+        .field("name", "Ljava/lang/String;", field::ACC_PRIVATE)
+        .field("target", "Ljava/lang/Runnable;", field::ACC_PRIVATE)
+        .field("daemon", "Z", field::ACC_PRIVATE)
+        .field("started", "Z", field::ACC_PRIVATE)
+        .field("alive", "Z", field::ACC_PRIVATE)
+        .field("threadInitNumber", "I", field::ACC_PRIVATE | field::ACC_STATIC)
+        .native("<init>", "()V", ACC_PUBLIC, thread_init)
+        .native("<init>", "(Ljava/lang/Runnable;)V", ACC_PUBLIC, thread_init_target)
+        .native("<init>", "(Ljava/lang/Runnable;Ljava/lang/String;)V", ACC_PUBLIC, thread_init_target_name)
+        .native("<init>", "(Ljava/lang/String;)V", ACC_PUBLIC, thread_init_name)
+        .native("start", "()V", ACC_PUBLIC, thread_start)
+        .native("run", "()V", ACC_PUBLIC, thread_run)
+        .native("join", "()V", ACC_PUBLIC, thread_join)
+        .native("join", "(J)V", ACC_PUBLIC, thread_join)
+        .native("isAlive", "()Z", ACC_PUBLIC, thread_is_alive)
+        .native("getName", "()Ljava/lang/String;", ACC_PUBLIC, thread_get_name)
+        .native("setName", "(Ljava/lang/String;)V", ACC_PUBLIC, thread_set_name)
+        .native("isDaemon", "()Z", ACC_PUBLIC, thread_is_daemon)
+        .native("setDaemon", "(Z)V", ACC_PUBLIC, thread_set_daemon)
+        .native("toString", "()Ljava/lang/String;", ACC_PUBLIC, thread_to_string)
+        .native("currentThread", "()Ljava/lang/Thread;", PUBLIC_STATIC, thread_current_thread)
+        .native("sleep", "(J)V", PUBLIC_STATIC, thread_sleep)
+        .native("yield", "()V", PUBLIC_STATIC, thread_yield)
+        .define(class_table);
+}
+
+fn get_field(thread: &Arc<HeapCell<Object>>, name: &str) -> Value {
+    thread.borrow().get_field(String::from(name))
+}
+
+fn put_field(thread: &Arc<HeapCell<Object>>, name: &str, value: Value) {
+    thread.borrow_mut().put_field(String::from(name), value);
+}
+
+fn is_set(thread: &Arc<HeapCell<Object>>, name: &str) -> bool {
+    matches!(get_field(thread, name), Value::Integer(1))
+}
+
+// The java.lang.Thread instance of the current thread. The thread the launcher runs main on, or
+// any other thread not started by Thread.start, gets one named "main" when it first asks.
+fn current_thread(class_table: &ClassTable) -> Result<Arc<HeapCell<Object>>, InterpreterError> {
+    if let Some(thread) = class_table.get_thread_object() {
+        return Ok(thread);
+    }
+
+    let class = interpreter::resolve_class(THREAD_CLASS_NAME, class_table)?;
+    let thread = class_table.new_object(&class);
+    put_field(&thread, "name", new_string(class_table, encode("main")));
+    put_field(&thread, "started", Value::Integer(1));
+    put_field(&thread, "alive", Value::Integer(1));
+    class_table.set_thread_object(Some(thread.clone()));
+    Ok(thread)
+}
+
+// Threads without a name are numbered in the order they are created, like in the JDK.
+fn next_thread_name(class_table: &ClassTable) -> Result<Value, InterpreterError> {
+    let class = interpreter::resolve_class(THREAD_CLASS_NAME, class_table)?;
+    let mirror = class_table.get_mirror(THREAD_CLASS_NAME);
+
+    class_table.heap.enter_monitor(mirror.monitor());
+    let number = match class.get_static("threadInitNumber") {
+        Some(Value::Integer(number)) => number,
+        _ => 0
+    };
+    class.put_static("threadInitNumber", Value::Integer(number + 1));
+    mirror.monitor().exit();
+
+    Ok(new_string(class_table, encode(&format!("Thread-{}", number))))
+}
+
+// A new thread is a daemon thread if the thread creating it is one.
+fn init(class_table: &ClassTable, arguments: &[Value], target: Value, name: Value) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(arguments, 0)?;
+    let daemon = get_field(&current_thread(class_table)?, "daemon");
+
+    put_field(&this, "target", target);
+    put_field(&this, "name", name);
+    put_field(&this, "daemon", daemon);
+    Ok(InvokeResult::Void)
+}
+
+fn thread_init(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let name = next_thread_name(class_table)?;
+    init(class_table, &arguments, Value::Null, name)
+}
+
+fn thread_init_target(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let name = next_thread_name(class_table)?;
+    init(class_table, &arguments, arguments[1].clone(), name)
+}
+
+fn thread_init_target_name(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    if let Value::Null = arguments[2] {
+        return throw_new(class_table, "java/lang/NullPointerException", Some("'name' is null"));
+    }
+    init(class_table, &arguments, arguments[1].clone(), arguments[2].clone())
+}
+
+fn thread_init_name(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    if let Value::Null = arguments[1] {
+        return throw_new(class_table, "java/lang/NullPointerException", Some("'name' is null"));
+    }
+    init(class_table, &arguments, Value::Null, arguments[1].clone())
+}
+
+// Starts a new thread running the run method of the Thread. The launcher waits for all threads
+// that are not daemon threads before the process exits.
+fn thread_start(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    {
+        let mut thread = this.borrow_mut();
+        if let Value::Integer(1) = thread.get_field(String::from("started")) {
+            drop(thread);
+            return throw_new(class_table, "java/lang/IllegalThreadStateException", None);
+        }
+        thread.put_field(String::from("started"), Value::Integer(1));
+        thread.put_field(String::from("alive"), Value::Integer(1));
+    }
+
+    let name = match get_field(&this, "name") {
+        Value::ObjectRef(name) => string::to_rust_string(&name.borrow()),
+        _ => String::new()
+    };
+    let daemon = is_set(&this, "daemon");
+
+    // The Thread is only a root of the heap here until the new thread has registered it, so
+    // this waits for that.
+    let shared_class_table = class_table.shared();
+    let thread_object = this.clone();
+    let (registered, on_registered) = mpsc::channel();
+    let spawned = thread::Builder::new()
+        .name(name)
+        .stack_size(STACK_SIZE)
+        .spawn(move || run_thread(&shared_class_table, thread_object, registered));
+    let handle = match spawned {
+        Ok(handle) => handle,
+        Err(_) => return throw_new(class_table, "java/lang/OutOfMemoryError", Some("unable to create native thread"))
+    };
+    let _ = class_table.heap.blocking(|| on_registered.recv());
+
+    if !daemon {
+        class_table.add_thread(handle);
+    }
+    Ok(InvokeResult::Void)
+}
+
+fn run_thread(class_table: &ClassTable, thread: Arc<HeapCell<Object>>, registered: Sender<()>) {
+    let _attachment = class_table.heap.attach();
+    class_table.set_thread_object(Some(thread.clone()));
+    let _ = registered.send(());
+
+    match interpreter::invoke_virtual(class_table, Value::ObjectRef(thread.clone()), "run", "()V", Vec::new()) {
+        Ok(InvokeResult::Exception(exception)) => {
+            let name = match get_field(&thread, "name") {
+                Value::ObjectRef(name) => string::to_rust_string(&name.borrow()),
+                _ => String::new()
+            };
+            class_table.console.flush();
+            let _ = write!(class_table.console.err.lock().unwrap(), "Exception in thread \"{}\" ", name);
+            if let Err(InterpreterError::Exit(status)) =
+                interpreter::invoke_virtual(class_table, exception, "printStackTrace", "()V", Vec::new()) {
+                exit(class_table, status);
+            }
+        },
+        Ok(_) => {},
+        Err(InterpreterError::Exit(status)) => exit(class_table, status),
+        Err(e) => {
+            class_table.console.flush();
+            eprintln!("Internal error: {:?}", e);
+            for element in class_table.stacks.take_failure() {
+                eprintln!("\tat {}", element);
+            }
+        }
+    }
+
+    class_table.stacks.remove_current();
+
+    // Threads blocked in join wait on the monitor of the Thread.
+    class_table.heap.enter_monitor(thread.monitor());
+    put_field(&thread, "alive", Value::Integer(0));
+    thread.monitor().notify(true);
+    thread.monitor().exit();
+
+    class_table.set_thread_object(None);
+}
+
+// System.exit called on a thread other than main ends the process without waiting for anything.
+pub fn exit(class_table: &ClassTable, status: i32) -> ! {
+    class_table.console.flush();
+    process::exit(status);
+}
+
+fn thread_run(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    match get_field(&this, "target") {
+        Value::Null => Ok(InvokeResult::Void),
+        target => {
+            java_try!(bootstrap::call(class_table, target, "run", "()V", Vec::new()));
+            Ok(InvokeResult::Void)
+        }
+    }
+}
+
+// Waits for the thread to die, or at most the given number of milliseconds. A timeout of zero
+// means waiting forever.
+fn thread_join(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let millis = if arguments.len() > 1 { long_arg(&arguments, 1)? } else { 0 };
+    if millis < 0 {
+        return throw_new(class_table, "java/lang/IllegalArgumentException", Some("timeout value is negative"));
+    }
+    let deadline = if millis > 0 { Some(Instant::now() + Duration::from_millis(millis as u64)) } else { None };
+
+    // Reading whether the thread is alive does not change the heap, so it may run during a
+    // garbage collection.
+    let monitor = this.monitor();
+    class_table.heap.blocking(|| {
+        monitor.enter();
+        while is_set(&this, "alive") {
+            match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break;
+                    }
+                    monitor.wait(Some(deadline - now));
+                },
+                None => { monitor.wait(None); }
+            }
+        }
+        monitor.exit();
+    });
+
+    Ok(InvokeResult::Void)
+}
+
+fn thread_is_alive(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    Ok(InvokeResult::Value(get_field(&this, "alive")))
+}
+
+fn thread_get_name(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    Ok(InvokeResult::Value(get_field(&this, "name")))
+}
+
+fn thread_set_name(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    if string_arg(&arguments, 1)?.is_none() {
+        return throw_new(class_table, "java/lang/NullPointerException", Some("name cannot be null"));
+    }
+
+    let this = object_arg(&arguments, 0)?;
+    put_field(&this, "name", arguments[1].clone());
+    Ok(InvokeResult::Void)
+}
+
+fn thread_is_daemon(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    Ok(InvokeResult::Value(get_field(&this, "daemon")))
+}
+
+// Whether a thread is a daemon thread can only be changed before it is started.
+fn thread_set_daemon(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    if is_set(&this, "alive") {
+        return throw_new(class_table, "java/lang/IllegalThreadStateException", None);
+    }
+
+    put_field(&this, "daemon", Value::Integer(int_arg(&arguments, 1)?));
+    Ok(InvokeResult::Void)
+}
+
+// There are no thread groups or priorities, so every thread is shown at normal priority in the
+// main group.
+fn thread_to_string(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let name = match get_field(&this, "name") {
+        Value::ObjectRef(name) => string::to_rust_string(&name.borrow()),
+        _ => String::new()
+    };
+    return_string(class_table, &format!("Thread[{},5,main]", name))
+}
+
+fn thread_current_thread(class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    Ok(InvokeResult::Value(Value::ObjectRef(current_thread(class_table)?)))
+}
+
+fn thread_sleep(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let millis = long_arg(&arguments, 0)?;
+    if millis < 0 {
+        return throw_new(class_table, "java/lang/IllegalArgumentException", Some("timeout value is negative"));
+    }
+
+    class_table.heap.blocking(|| thread::sleep(Duration::from_millis(millis as u64)));
+    Ok(InvokeResult::Void)
+}
+
+fn thread_yield(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    thread::yield_now();
+    Ok(InvokeResult::Void)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use runtime::bootstrap::tests::run_main;
+
+    fn to_rust_string(value: Value) -> String {
+        match value {
+            Value::ObjectRef(string) => string::to_rust_string(&string.borrow()),
+            x => panic!("not a string: {:?}", x)
+        }
+    }
+
+    // A thread not started by Thread.start gets a live Thread named "main" the first time it
+    // asks, and keeps it.
+    #[test]
+    fn current_thread_is_main() {
+        let class_table = ClassTable::new();
+        assert!(class_table.get_thread_object().is_none());
+
+        let thread = current_thread(&class_table).unwrap();
+        assert!(Arc::ptr_eq(&thread, &current_thread(&class_table).unwrap()));
+        assert_eq!(to_rust_string(get_field(&thread, "name")), "main");
+        assert!(is_set(&thread, "started"));
+        assert!(is_set(&thread, "alive"));
+    }
+
+    #[test]
+    fn numbers_unnamed_threads() {
+        let class_table = ClassTable::new();
+        let names: Vec<_> = (0..2).map(|_| to_rust_string(next_thread_name(&class_table).unwrap())).collect();
+
+        assert_eq!(names, vec!["Thread-0", "Thread-1"]);
+    }
+
+    // An exception thrown by a thread other than main is printed, and only ends that thread.
+    #[test]
+    fn threads() {
+        let (out, err) = run_main(&[
+            include_bytes!("../../../fixtures/Threads$Buffer.class"),
+            include_bytes!("../../../fixtures/Threads$Slow.class"),
+            include_bytes!("../../../fixtures/Threads$Counter.class"),
+            include_bytes!("../../../fixtures/Threads.class")
+        ]);
+
+        assert_eq!(out, include_str!("../../../fixtures/Threads.out"));
+        assert!(err.starts_with("Exception in thread \"failing\" java.lang.IllegalStateException: failed\n"));
+    }
+
+
+}
@@ -0,0 +1,367 @@
+use runtime::{Value, ArrayElements, HeapCell, Object};
+use runtime::class::ClassTable;
+use runtime::frames::{self, StackTraceElement, NATIVE_METHOD};
+use runtime::interpreter::{self, InvokeResult, InterpreterError};
+use runtime::bootstrap::{self, ClassBuilder, object_arg, int_arg, to_java_chars, encode, new_string, return_string, throw_new};
+use runtime::string;
+use class::field;
+use class::method::ACC_PUBLIC;
+use std::io::Write;
+use std::sync::Arc;
+
+pub const THROWABLE_CLASS_NAME: &str = "java/lang/Throwable";
+const STACK_TRACE_ELEMENT_CLASS_NAME: &str = "java/lang/StackTraceElement";
+const STACK_TRACE_DESCRIPTOR: &str = "[Ljava/lang/StackTraceElement;";
+
+// Subclasses of Throwable as (class, superclass) pairs. They declare no fields or methods of their
+// own, so the constructors and methods of Throwable are found by method resolution.
+const EXCEPTION_CLASSES: &[(&str, &str)] = &[
+    ("java/lang/Exception", THROWABLE_CLASS_NAME),
+    ("java/lang/Error", THROWABLE_CLASS_NAME),
+    ("java/lang/RuntimeException", "java/lang/Exception"),
+    ("java/lang/InterruptedException", "java/lang/Exception"),
+    ("java/lang/CloneNotSupportedException", "java/lang/Exception"),
+    ("java/lang/ReflectiveOperationException", "java/lang/Exception"),
+    ("java/lang/ClassNotFoundException", "java/lang/ReflectiveOperationException"),
+    ("java/lang/IllegalAccessException", "java/lang/ReflectiveOperationException"),
+    ("java/lang/InstantiationException", "java/lang/ReflectiveOperationException"),
+    ("java/lang/NoSuchFieldException", "java/lang/ReflectiveOperationException"),
+    ("java/lang/NoSuchMethodException", "java/lang/ReflectiveOperationException"),
+    ("java/lang/ArithmeticException", "java/lang/RuntimeException"),
+    ("java/lang/ArrayStoreException", "java/lang/RuntimeException"),
+    ("java/lang/ClassCastException", "java/lang/RuntimeException"),
+    ("java/lang/IllegalArgumentException", "java/lang/RuntimeException"),
+    ("java/lang/NumberFormatException", "java/lang/IllegalArgumentException"),
+    ("java/lang/IllegalThreadStateException", "java/lang/IllegalArgumentException"),
+    ("java/lang/IllegalMonitorStateException", "java/lang/RuntimeException"),
+    ("java/lang/IllegalStateException", "java/lang/RuntimeException"),
+    ("java/lang/IndexOutOfBoundsException", "java/lang/RuntimeException"),
+    ("java/lang/ArrayIndexOutOfBoundsException", "java/lang/IndexOutOfBoundsException"),
+    ("java/lang/StringIndexOutOfBoundsException", "java/lang/IndexOutOfBoundsException"),
+    ("java/lang/NegativeArraySizeException", "java/lang/RuntimeException"),
+    ("java/lang/NullPointerException", "java/lang/RuntimeException"),
+    ("java/lang/UnsupportedOperationException", "java/lang/RuntimeException"),
+    ("java/lang/LinkageError", "java/lang/Error"),
+    ("java/lang/BootstrapMethodError", "java/lang/LinkageError"),
+    ("java/lang/ExceptionInInitializerError", "java/lang/LinkageError"),
+    ("java/lang/NoClassDefFoundError", "java/lang/LinkageError"),
+    ("java/lang/UnsatisfiedLinkError", "java/lang/LinkageError"),
+    ("java/lang/IncompatibleClassChangeError", "java/lang/LinkageError"),
+    ("java/lang/AbstractMethodError", "java/lang/IncompatibleClassChangeError"),
+    ("java/lang/InstantiationError", "java/lang/IncompatibleClassChangeError"),
+    ("java/lang/NoSuchFieldError", "java/lang/IncompatibleClassChangeError"),
+    ("java/lang/NoSuchMethodError", "java/lang/IncompatibleClassChangeError"),
+    ("java/lang/VirtualMachineError", "java/lang/Error"),
+    ("java/lang/OutOfMemoryError", "java/lang/VirtualMachineError"),
+    ("java/lang/StackOverflowError", "java/lang/VirtualMachineError"),
+    ("java/lang/InternalError", "java/lang/VirtualMachineError")
+];
+
+pub fn load(class_table: &ClassTable) {
+    ClassBuilder::new(STACK_TRACE_ELEMENT_CLASS_NAME)
+        .implements("java/io/Serializable")
+        .field("declaringClass", "Ljava/lang/String;", field::ACC_PRIVATE)
+        .field("methodName", "Ljava/lang/String;", field::ACC_PRIVATE)
+        .field("fileName", "Ljava/lang/String;", field::ACC_PRIVATE)
+        .field("lineNumber", "I", field::ACC_PRIVATE)
+        .native("<init>", "(Ljava/lang/String;Ljava/lang/String;Ljava/lang/String;I)V", ACC_PUBLIC, stack_trace_element_init)
+        .native("getClassName", "()Ljava/lang/String;", ACC_PUBLIC, stack_trace_element_get_class_name)
+        .native("getMethodName", "()Ljava/lang/String;", ACC_PUBLIC, stack_trace_element_get_method_name)
+        .native("getFileName", "()Ljava/lang/String;", ACC_PUBLIC, stack_trace_element_get_file_name)
+        .native("getLineNumber", "()I", ACC_PUBLIC, stack_trace_element_get_line_number)
+        .native("isNativeMethod", "()Z", ACC_PUBLIC, stack_trace_element_is_native_method)
+        .native("toString", "()Ljava/lang/String;", ACC_PUBLIC, stack_trace_element_to_string)
+        .define(class_table);
+
+    ClassBuilder::new(THROWABLE_CLASS_NAME)
+        .implements("java/io/Serializable")
+        .field("detailMessage", "Ljava/lang/String;", field::ACC_PRIVATE)
+        .field("cause", "Ljava/lang/Throwable;", field::ACC_PRIVATE)
+        .field("stackTrace", STACK_TRACE_DESCRIPTOR, field::ACC_PRIVATE)
+        .native("<init>", "()V", ACC_PUBLIC, throwable_init)
+        .native("<init>", "(Ljava/lang/String;)V", ACC_PUBLIC, throwable_init_message)
+        .native("<init>", "(Ljava/lang/String;Ljava/lang/Throwable;)V", ACC_PUBLIC, throwable_init_message_cause)
+        .native("<init>", "(Ljava/lang/Throwable;)V", ACC_PUBLIC, throwable_init_cause)
+        .native("getMessage", "()Ljava/lang/String;", ACC_PUBLIC, throwable_get_message)
+        .native("getLocalizedMessage", "()Ljava/lang/String;", ACC_PUBLIC, throwable_get_localized_message)
+        .native("getCause", "()Ljava/lang/Throwable;", ACC_PUBLIC, throwable_get_cause)
+        .native("initCause", "(Ljava/lang/Throwable;)Ljava/lang/Throwable;", ACC_PUBLIC, throwable_init_cause_method)
+        .native("toString", "()Ljava/lang/String;", ACC_PUBLIC, throwable_to_string)
+        .native("printStackTrace", "()V", ACC_PUBLIC, throwable_print_stack_trace)
+        .native("fillInStackTrace", "()Ljava/lang/Throwable;", ACC_PUBLIC, throwable_fill_in_stack_trace)
+        .native("getStackTrace", "()[Ljava/lang/StackTraceElement;", ACC_PUBLIC, throwable_get_stack_trace)
+        .native("setStackTrace", "([Ljava/lang/StackTraceElement;)V", ACC_PUBLIC, throwable_set_stack_trace)
+        .define(class_table);
+
+    for &(class_name, super_class_name) in EXCEPTION_CLASSES.iter() {
+        ClassBuilder::new(class_name)
+            .super_class(super_class_name)
+            .define(class_table);
+    }
+}
+
+fn set_field(arguments: &[Value], name: &str, value: Value) -> Result<(), InterpreterError> {
+    let this = object_arg(arguments, 0)?;
+    this.borrow_mut().put_field(String::from(name), value);
+    Ok(())
+}
+
+// Like the constructors of Throwable, calls fillInStackTrace, which subclasses may override.
+fn call_fill_in_stack_trace(class_table: &ClassTable, arguments: &[Value]) -> Result<InvokeResult, InterpreterError> {
+    java_try!(bootstrap::call(class_table, arguments[0].clone(), "fillInStackTrace", "()Ljava/lang/Throwable;", Vec::new()));
+    Ok(InvokeResult::Void)
+}
+
+fn throwable_init(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    call_fill_in_stack_trace(class_table, &arguments)
+}
+
+fn throwable_init_message(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    set_field(&arguments, "detailMessage", arguments[1].clone())?;
+    call_fill_in_stack_trace(class_table, &arguments)
+}
+
+fn throwable_init_message_cause(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    set_field(&arguments, "detailMessage", arguments[1].clone())?;
+    set_field(&arguments, "cause", arguments[2].clone())?;
+    call_fill_in_stack_trace(class_table, &arguments)
+}
+
+// The message defaults to the string representation of the cause.
+fn throwable_init_cause(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let message = match arguments[1] {
+        Value::Null => Value::Null,
+        ref cause => new_string(class_table, java_try!(to_java_chars(class_table, cause)))
+    };
+
+    set_field(&arguments, "detailMessage", message)?;
+    set_field(&arguments, "cause", arguments[1].clone())?;
+    call_fill_in_stack_trace(class_table, &arguments)
+}
+
+fn throwable_get_message(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let message = this.borrow().get_field(String::from("detailMessage"));
+    Ok(InvokeResult::Value(message))
+}
+
+fn throwable_get_localized_message(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let message = java_try!(bootstrap::call(class_table, arguments[0].clone(), "getMessage", "()Ljava/lang/String;", Vec::new()));
+    Ok(InvokeResult::Value(message.unwrap_or(Value::Null)))
+}
+
+fn throwable_get_cause(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let cause = this.borrow().get_field(String::from("cause"));
+    Ok(InvokeResult::Value(cause))
+}
+
+fn throwable_init_cause_method(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    set_field(&arguments, "cause", arguments[1].clone())?;
+    Ok(InvokeResult::Value(arguments[0].clone()))
+}
+
+// The class name, followed by ": " and the localized message if there is one.
+fn throwable_to_string(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let class_name = this.borrow().class().class_name.replace('/', ".");
+    let mut chars = encode(&class_name);
+
+    let message = java_try!(bootstrap::call(class_table, arguments[0].clone(), "getLocalizedMessage", "()Ljava/lang/String;", Vec::new()));
+    if let Some(Value::ObjectRef(message)) = message {
+        chars.extend(encode(": "));
+        chars.extend(::runtime::string::get_chars(&message.borrow()));
+    }
+
+    Ok(InvokeResult::Value(new_string(class_table, chars)))
+}
+
+// Prints the throwable and its stack trace, then each of its causes with the frames they do not
+// have in common with the throwable they caused, to System.err.
+fn throwable_print_stack_trace(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let mut current = arguments[0].clone();
+    let mut prefix = "";
+    let mut enclosing_trace = Vec::new();
+
+    while let Value::ObjectRef(throwable) = current.clone() {
+        let chars = java_try!(to_java_chars(class_table, &current));
+        let trace = stack_trace_of(&throwable);
+        let common = trace.iter().rev()
+            .zip(enclosing_trace.iter().rev())
+            .take_while(|&(element, enclosing)| element == enclosing)
+            .count();
+
+        let mut lines = format!("{}{}\n", prefix, String::from_utf16_lossy(&chars));
+        for element in trace[..trace.len() - common].iter() {
+            lines.push_str(&format!("\tat {}\n", element));
+        }
+        if common > 0 {
+            lines.push_str(&format!("\t... {} more\n", common));
+        }
+        let _ = class_table.console.err.lock().unwrap().write_all(lines.as_bytes());
+
+        let cause = throwable.borrow().get_field(String::from("cause"));
+        if cause.same_reference(&current) {
+            break;
+        }
+        current = cause;
+        prefix = "Caused by: ";
+        enclosing_trace = trace;
+    }
+
+    Ok(InvokeResult::Void)
+}
+
+// Records the frames of the current thread in the throwable.
+pub fn fill_in_stack_trace(class_table: &ClassTable, throwable: &Arc<HeapCell<Object>>) -> Result<(), InterpreterError> {
+    let elements = current_stack_trace(class_table, throwable).iter()
+        .map(|element| new_stack_trace_element(class_table, element))
+        .collect::<Result<Vec<Value>, InterpreterError>>()?;
+    let stack_trace = class_table.new_array_from("Ljava/lang/StackTraceElement;", ArrayElements::Reference(elements));
+    throwable.borrow_mut().put_field(String::from("stackTrace"), Value::ArrayRef(stack_trace));
+    Ok(())
+}
+
+// The frames of the current thread, leaving out those creating the throwable: the constructors
+// of its class and superclasses, and fillInStackTrace.
+pub fn current_stack_trace(class_table: &ClassTable, throwable: &Arc<HeapCell<Object>>) -> Vec<StackTraceElement> {
+    let frames = class_table.stacks.current_frames();
+    let class = throwable.borrow().class().clone();
+    let creating = frames.iter().rev()
+        .take_while(|frame| match frame.method().name.as_str() {
+            "fillInStackTrace" => true,
+            "<init>" => class.is_subclass_of(&frame.class.class_name),
+            _ => false
+        })
+        .count();
+
+    frames::stack_trace(&frames[..frames.len() - creating])
+}
+
+// Creates a StackTraceElement object. The one of the JDK also refers to the class of the frame,
+// which it formats the element by.
+pub fn new_stack_trace_element(class_table: &ClassTable, element: &StackTraceElement) -> Result<Value, InterpreterError> {
+    let class = interpreter::resolve_class(STACK_TRACE_ELEMENT_CLASS_NAME, class_table)?;
+    let object = class_table.new_object(&class);
+    let declaring_class = new_string(class_table, encode(&element.class_name));
+    let method_name = new_string(class_table, encode(&element.method_name));
+    let file_name = match element.file_name {
+        Some(ref file_name) => new_string(class_table, encode(file_name)),
+        None => Value::Null
+    };
+    {
+        let mut object = object.borrow_mut();
+        object.put_field(String::from("declaringClass"), declaring_class);
+        object.put_field(String::from("methodName"), method_name);
+        object.put_field(String::from("fileName"), file_name);
+        object.put_field(String::from("lineNumber"), Value::Integer(element.line_number));
+    }
+    if class.instance_field_position("declaringClassObject").is_some() {
+        let mirror = class_table.get_mirror(&element.class_name.replace('.', "/"));
+        object.borrow_mut().put_field(String::from("declaringClassObject"), Value::ObjectRef(mirror));
+    }
+    Ok(Value::ObjectRef(object))
+}
+
+// Reads a StackTraceElement object.
+fn stack_trace_element(object: &Object) -> StackTraceElement {
+    let string_field = |name: &str| match object.get_field(String::from(name)) {
+        Value::ObjectRef(string) => Some(string::to_rust_string(&string.borrow())),
+        _ => None
+    };
+    StackTraceElement {
+        class_name: string_field("declaringClass").unwrap_or_default(),
+        method_name: string_field("methodName").unwrap_or_default(),
+        file_name: string_field("fileName"),
+        line_number: match object.get_field(String::from("lineNumber")) {
+            Value::Integer(line_number) => line_number,
+            _ => -1
+        }
+    }
+}
+
+// The stack trace recorded in a throwable, skipping any null elements set by setStackTrace.
+fn stack_trace_of(throwable: &Arc<HeapCell<Object>>) -> Vec<StackTraceElement> {
+    let stack_trace = throwable.borrow().get_field(String::from("stackTrace"));
+    match stack_trace {
+        Value::ArrayRef(array) => {
+            let array = array.borrow();
+            (0..array.len())
+                .filter_map(|index| match array.get(index) {
+                    Value::ObjectRef(element) => Some(stack_trace_element(&element.borrow())),
+                    _ => None
+                })
+                .collect()
+        },
+        _ => Vec::new()
+    }
+}
+
+fn throwable_fill_in_stack_trace(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    fill_in_stack_trace(class_table, &this)?;
+    Ok(InvokeResult::Value(arguments[0].clone()))
+}
+
+// A copy of the stack trace, so that callers cannot change it.
+fn throwable_get_stack_trace(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let stack_trace = this.borrow().get_field(String::from("stackTrace"));
+    let copy = match stack_trace {
+        Value::ArrayRef(array) => class_table.clone_array(&array),
+        _ => class_table.new_array("Ljava/lang/StackTraceElement;", 0)
+    };
+    Ok(InvokeResult::Value(Value::ArrayRef(copy)))
+}
+
+fn throwable_set_stack_trace(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let copy = match arguments.get(1) {
+        Some(Value::ArrayRef(array)) => class_table.clone_array(array),
+        _ => return throw_new(class_table, "java/lang/NullPointerException", None)
+    };
+    set_field(&arguments, "stackTrace", Value::ArrayRef(copy))?;
+    Ok(InvokeResult::Void)
+}
+
+fn stack_trace_element_init(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    set_field(&arguments, "declaringClass", arguments[1].clone())?;
+    set_field(&arguments, "methodName", arguments[2].clone())?;
+    set_field(&arguments, "fileName", arguments[3].clone())?;
+    set_field(&arguments, "lineNumber", Value::Integer(int_arg(&arguments, 4)?))?;
+    Ok(InvokeResult::Void)
+}
+
+fn get_field(arguments: &[Value], name: &str) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(arguments, 0)?;
+    let value = this.borrow().get_field(String::from(name));
+    Ok(InvokeResult::Value(value))
+}
+
+fn stack_trace_element_get_class_name(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    get_field(&arguments, "declaringClass")
+}
+
+fn stack_trace_element_get_method_name(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    get_field(&arguments, "methodName")
+}
+
+fn stack_trace_element_get_file_name(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    get_field(&arguments, "fileName")
+}
+
+fn stack_trace_element_get_line_number(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    get_field(&arguments, "lineNumber")
+}
+
+fn stack_trace_element_is_native_method(_class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let line_number = this.borrow().get_field(String::from("lineNumber"));
+    Ok(InvokeResult::Value(Value::Integer(matches!(line_number, Value::Integer(NATIVE_METHOD)) as i32)))
+}
+
+// e.g. Counter.set(Counter.java:17)
+fn stack_trace_element_to_string(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let this = object_arg(&arguments, 0)?;
+    let element = stack_trace_element(&this.borrow());
+    return_string(class_table, &element.to_string())
+}
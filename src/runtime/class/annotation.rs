@@ -0,0 +1,222 @@
+use class::{Annotation, AnnotationElementValue, Attribute, ConstantPool};
+use class::writer;
+
+// An annotation of a class, method or field, with the constant pool indices of its elements
+// resolved to the values they stand for.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuntimeAnnotation {
+    // The internal name of the annotation interface, e.g. java/lang/Deprecated.
+    pub type_name: String,
+    // The elements given where the annotation is used, in the order they were written. Elements
+    // left out take the default of their method in the annotation interface.
+    pub elements: Vec<(String, AnnotationValue)>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AnnotationValue {
+    Boolean(bool),
+    Byte(i8),
+    Character(u16),
+    Short(i16),
+    Integer(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    String(Vec<u16>),
+    // A constant of the enum class with the given internal name.
+    Enum { type_name: String, const_name: String },
+    // A class literal, by the descriptor of its type, e.g. Ljava/lang/String;, I or V.
+    Class(String),
+    Annotation(RuntimeAnnotation),
+    Array(Vec<AnnotationValue>)
+}
+
+impl RuntimeAnnotation {
+
+    pub fn from_class_annotation(annotation: &Annotation, cp: &ConstantPool) -> Result<RuntimeAnnotation, String> {
+        let type_name = class_name(&cp.get_utf8(annotation.type_index)?);
+        let elements = annotation.elements
+            .iter()
+            .map(|pair| Ok((cp.get_utf8(pair.element_name_index)?, AnnotationValue::from_element_value(&pair.element_value, cp)?)))
+            .collect::<Result<Vec<(String, AnnotationValue)>, String>>()?;
+
+        Ok(RuntimeAnnotation { type_name, elements })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AnnotationValue> {
+        self.elements
+            .iter()
+            .find(|(element_name, _)| element_name == name)
+            .map(|(_, value)| value)
+    }
+
+}
+
+impl AnnotationValue {
+
+    pub fn from_element_value(value: &AnnotationElementValue, cp: &ConstantPool) -> Result<AnnotationValue, String> {
+        let value = match *value {
+            AnnotationElementValue::Const { tag, const_value_index } => match tag {
+                'Z' => AnnotationValue::Boolean(cp.get_integer(const_value_index)? != 0),
+                'B' => AnnotationValue::Byte(cp.get_integer(const_value_index)? as i8),
+                'C' => AnnotationValue::Character(cp.get_integer(const_value_index)? as u16),
+                'S' => AnnotationValue::Short(cp.get_integer(const_value_index)? as i16),
+                'I' => AnnotationValue::Integer(cp.get_integer(const_value_index)?),
+                'J' => AnnotationValue::Long(cp.get_long(const_value_index)?),
+                'F' => AnnotationValue::Float(cp.get_float(const_value_index)?),
+                'D' => AnnotationValue::Double(cp.get_double(const_value_index)?),
+                's' => AnnotationValue::String(cp.get_utf16(const_value_index)?),
+                _ => return Err(format!("Invalid annotation element tag {}", tag))
+            },
+            AnnotationElementValue::EnumConst { type_name_index, const_name_index } => AnnotationValue::Enum {
+                type_name: class_name(&cp.get_utf8(type_name_index)?),
+                const_name: cp.get_utf8(const_name_index)?
+            },
+            AnnotationElementValue::ClassInfo(index) => AnnotationValue::Class(cp.get_utf8(index)?),
+            AnnotationElementValue::Annotation(ref annotation) => {
+                AnnotationValue::Annotation(RuntimeAnnotation::from_class_annotation(annotation, cp)?)
+            },
+            AnnotationElementValue::Array(ref values) => {
+                let values = values
+                    .iter()
+                    .map(|value| AnnotationValue::from_element_value(value, cp))
+                    .collect::<Result<Vec<AnnotationValue>, String>>()?;
+                AnnotationValue::Array(values)
+            }
+        };
+
+        Ok(value)
+    }
+
+}
+
+// The annotations in the RuntimeVisibleAnnotations attributes of a class, method or field.
+// Annotations that are only kept in class files are not visible to reflection.
+pub fn visible_annotations(attributes: &[Attribute], cp: &ConstantPool) -> Result<Vec<RuntimeAnnotation>, String> {
+    let mut resolved = Vec::new();
+    for attribute in attributes.iter() {
+        if let Attribute::RuntimeVisibleAnnotations { ref annotations } = *attribute {
+            for annotation in annotations.iter() {
+                resolved.push(RuntimeAnnotation::from_class_annotation(annotation, cp)?);
+            }
+        }
+    }
+    Ok(resolved)
+}
+
+// The default value of an element of an annotation interface, from the AnnotationDefault
+// attribute of its method.
+pub fn annotation_default(attributes: &[Attribute], cp: &ConstantPool) -> Result<Option<AnnotationValue>, String> {
+    for attribute in attributes.iter() {
+        if let Attribute::AnnotationDefault { ref default_value } = *attribute {
+            return AnnotationValue::from_element_value(default_value, cp).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+// The contents of the annotation attributes of a class, method or field as they are in the class
+// file. The JDK parses them itself, against the constant pool of the class.
+#[derive(Clone, Debug, Default)]
+pub struct RawAnnotations {
+    pub annotations: Option<Vec<u8>>,
+    pub type_annotations: Option<Vec<u8>>,
+    pub annotation_default: Option<Vec<u8>>
+}
+
+impl RawAnnotations {
+
+    pub fn from_attributes(attributes: &[Attribute], cp: &ConstantPool) -> Result<RawAnnotations, String> {
+        let mut raw = RawAnnotations::default();
+        for attribute in attributes.iter() {
+            match *attribute {
+                Attribute::RuntimeVisibleAnnotations { .. } => raw.annotations = Some(contents(attribute, cp)?),
+                Attribute::AnnotationDefault { .. } => raw.annotation_default = Some(contents(attribute, cp)?),
+                // The reader does not parse type annotations, so they are kept as they were read.
+                Attribute::Unrecognized(ref info) if cp.get_utf8(info.attribute_name_index)? == "RuntimeVisibleTypeAnnotations" => {
+                    raw.type_annotations = Some(info.bytes.clone())
+                },
+                _ => {}
+            }
+        }
+        Ok(raw)
+    }
+
+}
+
+fn contents(attribute: &Attribute, cp: &ConstantPool) -> Result<Vec<u8>, String> {
+    writer::write_attribute_contents(attribute, cp).map_err(|e| format!("{:?}", e))
+}
+
+// Annotation types and enum classes are named by field descriptors, e.g. Ljava/lang/Deprecated;.
+fn class_name(descriptor: &str) -> String {
+    if descriptor.starts_with('L') && descriptor.ends_with(';') {
+        String::from(&descriptor[1..descriptor.len() - 1])
+    } else {
+        String::from(descriptor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use class::reader;
+    use runtime::class::RuntimeClass;
+
+    fn load(bytes: &[u8]) -> ::std::sync::Arc<RuntimeClass> {
+        RuntimeClass::from_class_file(&reader::read_class_file(bytes).unwrap(), None, Vec::new()).unwrap()
+    }
+
+    fn string(value: &str) -> AnnotationValue {
+        AnnotationValue::String(value.encode_utf16().collect())
+    }
+
+    #[test]
+    fn resolves_annotations_of_classes_methods_and_fields() {
+        let base = load(include_bytes!("../../../fixtures/Annotations$Base.class"));
+
+        let owner = RuntimeAnnotation {
+            type_name: String::from("Annotations$Owner"),
+            elements: vec![
+                (String::from("value"), string("core")),
+                (String::from("tag"), AnnotationValue::Annotation(RuntimeAnnotation {
+                    type_name: String::from("Annotations$Tag"),
+                    elements: vec![(String::from("value"), string("nested")), (String::from("priority"), AnnotationValue::Integer(1))]
+                }))
+            ]
+        };
+        assert_eq!(base.annotations[0], owner);
+
+        // The annotation kept only in the class file is left out.
+        assert_eq!(base.annotations.len(), 2);
+        let tag = &base.annotations[1];
+        assert_eq!(tag.get("level"), Some(&AnnotationValue::Enum {
+            type_name: String::from("Annotations$Level"),
+            const_name: String::from("HIGH")
+        }));
+        assert_eq!(tag.get("aliases"), Some(&AnnotationValue::Array(vec![string("a"), string("b")])));
+        assert_eq!(tag.get("type"), Some(&AnnotationValue::Class(String::from("Ljava/lang/String;"))));
+        assert_eq!(tag.get("priority"), None);
+
+        let run = base.methods.iter().find(|method| method.name == "run").unwrap();
+        assert_eq!(run.annotations[0].get("id"), Some(&AnnotationValue::Long(9)));
+        assert_eq!(run.annotations[0].get("counts"), Some(&AnnotationValue::Array(Vec::new())));
+        assert_eq!(base.fields[0].annotations[0].get("value"), Some(&string("field")));
+        assert!(base.fields[1].annotations.is_empty());
+    }
+
+    #[test]
+    fn resolves_defaults_of_annotation_interfaces() {
+        let tag = load(include_bytes!("../../../fixtures/Annotations$Tag.class"));
+        let default = |name: &str| tag.methods.iter().find(|method| method.name == name).unwrap().annotation_default.clone();
+
+        assert_eq!(default("value"), None);
+        assert_eq!(default("priority"), Some(AnnotationValue::Integer(5)));
+        assert_eq!(default("initial"), Some(AnnotationValue::Character('x' as u16)));
+        assert_eq!(default("enabled"), Some(AnnotationValue::Boolean(true)));
+        assert_eq!(default("bits"), Some(AnnotationValue::Byte(3)));
+        assert_eq!(default("type"), Some(AnnotationValue::Class(String::from("Ljava/lang/Object;"))));
+        assert_eq!(default("counts"), Some(AnnotationValue::Array(vec![AnnotationValue::Integer(1), AnnotationValue::Integer(2)])));
+    }
+
+}
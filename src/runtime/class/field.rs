@@ -1,14 +1,33 @@
-use class::{Field, ConstantPool};
+use class::{Field, Attribute, ConstantPool};
+use runtime::class::annotation::{self, RawAnnotations, RuntimeAnnotation};
+use class::field::ACC_STATIC;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct RuntimeField {
     pub access_flags: u16,
     pub name: String,
-    pub descriptor: FieldDescriptor
+    pub descriptor: FieldDescriptor,
+    pub descriptor_string: String,
+    // Index of the ConstantValue attribute's constant, used to initialize static final fields.
+    pub constant_value_index: Option<u16>,
+    pub annotations: Vec<RuntimeAnnotation>,
+    pub raw_annotations: RawAnnotations
 }
 
 impl RuntimeField {
 
+    pub fn new(name: &str, descriptor: &str, access_flags: u16) -> RuntimeField {
+        RuntimeField {
+            access_flags,
+            name: String::from(name),
+            descriptor: FieldDescriptor::from_str(descriptor).unwrap(),
+            descriptor_string: String::from(descriptor),
+            constant_value_index: None,
+            annotations: Vec::new(),
+            raw_annotations: RawAnnotations::default()
+        }
+    }
+
     pub fn from_class_field(field: &Field, cp: &ConstantPool) -> Option<RuntimeField> {
         let name = cp.get_utf8(field.name_index).unwrap();
         let descriptor_tag = cp.get_utf8(field.descriptor_index).unwrap();
@@ -16,19 +35,35 @@ impl RuntimeField {
         // TODO: We can do this decoding in the class representation
         let descriptor = FieldDescriptor::from_str(descriptor_tag.as_str()).unwrap();
 
+        let constant_value_index = field.attributes
+            .iter()
+            .filter_map(|attribute| match attribute {
+                Attribute::ConstantValue { index } => Some(*index),
+                _ => None
+            })
+            .next();
+
         let runtime_field = RuntimeField {
             access_flags: field.access_flags,
             name,
-            descriptor
+            descriptor,
+            descriptor_string: descriptor_tag,
+            constant_value_index,
+            annotations: annotation::visible_annotations(&field.attributes, cp).ok()?,
+            raw_annotations: RawAnnotations::from_attributes(&field.attributes, cp).ok()?
         };
 
         Some(runtime_field)
     }
 
+    pub fn is_static(&self) -> bool {
+        self.access_flags & ACC_STATIC != 0
+    }
+
 }
 
 // TODO: See $4.3.2 of JVMS for a more precise encoding.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum FieldDescriptor {
     Byte,
     Character,
@@ -39,7 +74,7 @@ pub enum FieldDescriptor {
     ClassReference { class_name: String },
     Short,
     Boolean,
-    ArrayReference()
+    ArrayReference(Box<FieldDescriptor>)
 }
 
 impl FieldDescriptor {
@@ -58,7 +93,10 @@ impl FieldDescriptor {
             },
             "S" => Some(FieldDescriptor::Short),
             "Z" => Some(FieldDescriptor::Boolean),
-            // TODO: Parse array types
+            x if x.starts_with("[") => {
+                let component = FieldDescriptor::from_str(&x[1..])?;
+                Some(FieldDescriptor::ArrayReference(Box::new(component)))
+            },
             _ => None
         }
     }
@@ -1,44 +1,141 @@
-use class::{Method, Attribute, ConstantPool, Field};
+use class::{Method, Attribute, ConstantPool, ExceptionTableEntry, LineNumberTableEntry};
+use class::method::{ACC_STATIC, ACC_SYNCHRONIZED, ACC_NATIVE, ACC_ABSTRACT};
 use code::disassembler;
 use code::instruction::TaggedInstruction;
+use runtime::Value;
+use runtime::class::annotation::{self, AnnotationValue, RawAnnotations, RuntimeAnnotation};
+use runtime::class::ClassTable;
+use runtime::interpreter::{InvokeResult, InterpreterError};
+use runtime::jit::CompiledMethod;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU32, AtomicU64};
+
+// A method implemented in Rust. Arguments are passed in the same order as they would be in local
+// variables, so for instance methods the first argument is `this`.
+pub type NativeMethod = fn(&ClassTable, Vec<Value>) -> Result<InvokeResult, InterpreterError>;
 
 #[derive(Debug)]
 pub struct RuntimeMethod {
     pub name: String,
+    pub descriptor: String,
     pub access_flags: u16,
-    pub code: Code
+    // Native and abstract methods have no code.
+    pub code: Option<Code>,
+    pub native: Option<NativeMethod>,
+    pub annotations: Vec<RuntimeAnnotation>,
+    // The value an element of an annotation interface takes when an annotation leaves it out.
+    pub annotation_default: Option<AnnotationValue>,
+    pub raw_annotations: RawAnnotations,
+    // How often the interpreter has run the method, which decides when it is compiled.
+    pub invocations: AtomicU32,
+    // How hot the method has run in the interpreter while profiling, see Profile::heat.
+    pub heat: AtomicU64,
+    // The machine code of the method once the JIT has tried to compile it, or None if it could
+    // not be compiled.
+    pub compiled: OnceLock<Option<CompiledMethod>>,
+    // The same for the optimizing tier, which compiles methods that stay hot.
+    pub optimized: OnceLock<Option<CompiledMethod>>
 }
 
 impl RuntimeMethod {
 
     pub fn from_class_method(method: &Method, cp: &ConstantPool) -> Option<RuntimeMethod> {
-        let name = cp.get_utf8(method.name_index).unwrap();
-        let code = RuntimeMethod::get_code(method)?;
+        let name = cp.get_utf8(method.name_index).ok()?;
+        let descriptor = cp.get_utf8(method.descriptor_index).ok()?;
+        let code = RuntimeMethod::get_code(method, cp);
+
+        if code.is_none() && method.access_flags & (ACC_NATIVE | ACC_ABSTRACT) == 0 {
+            return None;
+        }
 
         let runtime_method = RuntimeMethod {
             name,
+            descriptor,
             access_flags: method.access_flags,
-            code
+            code,
+            native: None,
+            annotations: annotation::visible_annotations(&method.attributes, cp).ok()?,
+            annotation_default: annotation::annotation_default(&method.attributes, cp).ok()?,
+            raw_annotations: RawAnnotations::from_attributes(&method.attributes, cp).ok()?,
+            invocations: AtomicU32::new(0),
+            heat: AtomicU64::new(0),
+            compiled: OnceLock::new(),
+            optimized: OnceLock::new()
         };
 
         Some(runtime_method)
     }
 
-    fn get_code(method: &Method) -> Option<Code> {
+    pub fn native(name: &str, descriptor: &str, access_flags: u16, native: NativeMethod) -> RuntimeMethod {
+        RuntimeMethod {
+            name: String::from(name),
+            descriptor: String::from(descriptor),
+            access_flags: access_flags | ACC_NATIVE,
+            code: None,
+            native: Some(native),
+            annotations: Vec::new(),
+            annotation_default: None,
+            raw_annotations: RawAnnotations::default(),
+            invocations: AtomicU32::new(0),
+            heat: AtomicU64::new(0),
+            compiled: OnceLock::new(),
+            optimized: OnceLock::new()
+        }
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.access_flags & ACC_STATIC != 0
+    }
+
+    pub fn is_abstract(&self) -> bool {
+        self.access_flags & ACC_ABSTRACT != 0
+    }
+
+    pub fn is_native(&self) -> bool {
+        self.access_flags & ACC_NATIVE != 0
+    }
+
+    pub fn is_synchronized(&self) -> bool {
+        self.access_flags & ACC_SYNCHRONIZED != 0
+    }
+
+    fn get_code(method: &Method, cp: &ConstantPool) -> Option<Code> {
         for a in method.attributes.iter() {
-            match a {
-                &Attribute::Code { max_stack, max_locals, ref code, .. } => {
-                    let instructions = disassembler::disassemble_code(&code).ok()?;
-
-                    let code = Code {
-                        max_stack,
-                        max_locals,
-                        instructions
-                    };
-
-                    return Some(code);
-                },
-                _ => {}
+            if let Attribute::Code { max_stack, max_locals, ref code, ref exceptions, ref attributes } = *a {
+                let instructions = disassembler::disassemble_code(code).ok()?;
+                let mut line_numbers: Vec<LineNumberTableEntry> = attributes.iter()
+                    .filter_map(|attribute| match *attribute {
+                        Attribute::LineNumberTable(ref entries) => Some(entries.iter().cloned()),
+                        _ => None
+                    })
+                    .flatten()
+                    .collect();
+                line_numbers.sort_by_key(|entry| entry.start_pc);
+                let local_variables = attributes.iter()
+                    .filter_map(|attribute| match *attribute {
+                        Attribute::LocalVariableTable(ref entries) => Some(entries.iter()),
+                        _ => None
+                    })
+                    .flatten()
+                    .filter_map(|entry| Some(LocalVariable {
+                        start_pc: entry.start_pc,
+                        length: entry.length,
+                        name: cp.get_utf8(entry.name_index).ok()?,
+                        descriptor: cp.get_utf8(entry.descriptor_index).ok()?,
+                        index: entry.index
+                    }))
+                    .collect();
+
+                let code = Code {
+                    max_stack,
+                    max_locals,
+                    instructions,
+                    exception_table: exceptions.clone(),
+                    line_numbers,
+                    local_variables
+                };
+
+                return Some(code);
             }
         }
 
@@ -51,7 +148,47 @@ impl RuntimeMethod {
 pub struct Code {
     pub max_stack: u16,
     pub max_locals: u16,
-    pub instructions: Vec<TaggedInstruction>
+    pub instructions: Vec<TaggedInstruction>,
+    pub exception_table: Vec<ExceptionTableEntry>,
+    // From the LineNumberTable attributes, sorted by start_pc.
+    pub line_numbers: Vec<LineNumberTableEntry>,
+    // From the LocalVariableTable attributes, which javac only writes with -g.
+    pub local_variables: Vec<LocalVariable>
+}
+
+#[derive(Debug)]
+pub struct LocalVariable {
+    pub start_pc: u16,
+    pub length: u16,
+    pub name: String,
+    pub descriptor: String,
+    pub index: u16
+}
+
+impl Code {
+
+    // Maps a code index (the pc) to the position of the instruction in the instruction list.
+    pub fn instruction_position(&self, pc: u16) -> Option<usize> {
+        self.instructions
+            .binary_search_by_key(&pc, |instruction| instruction.index)
+            .ok()
+    }
+
+    // The source line of the instruction at pc, if the class file has line numbers.
+    pub fn line_number(&self, pc: u16) -> Option<u16> {
+        self.line_numbers.iter()
+            .take_while(|entry| entry.start_pc <= pc)
+            .last()
+            .map(|entry| entry.line_number)
+    }
+
+    // The local variable in the slot at pc, if the class file has local variable names.
+    pub fn local_variable(&self, index: u16, pc: u16) -> Option<&LocalVariable> {
+        self.local_variables.iter().find(|variable| {
+            variable.index == index && pc >= variable.start_pc && (pc as u32) < variable.start_pc as u32 + variable.length as u32
+        })
+    }
+
 }
 
 // Method descriptors are described in JVMS $4.3.3
@@ -67,6 +204,27 @@ impl MethodDescriptor {
         self.parameter_descriptors.len()
     }
 
+    pub fn returns_void(&self) -> bool {
+        matches!(self.return_descriptor, ReturnDescriptor::Void)
+    }
+
+    // The field descriptor of each parameter, e.g. ["I", "Ljava/lang/String;"] for
+    // "(ILjava/lang/String;)V".
+    pub fn parameter_types(&self) -> Vec<String> {
+        self.parameter_descriptors
+            .iter()
+            .map(|field_type| field_type.descriptor())
+            .collect()
+    }
+
+    // The field descriptor of the return type, or "V" for void.
+    pub fn return_type(&self) -> String {
+        match self.return_descriptor {
+            ReturnDescriptor::Void => String::from("V"),
+            ReturnDescriptor::Field(ref field_type) => field_type.descriptor()
+        }
+    }
+
     pub fn parse(input: &str) -> Option<MethodDescriptor> {
         let mut lexemes = MethodDescriptor::lex(input);
         MethodDescriptor::parse_method_descriptor(&mut lexemes).ok()
@@ -87,6 +245,7 @@ impl MethodDescriptor {
                 "I" => Lexeme::Integer,
                 "J" => Lexeme::Long,
                 "S" => Lexeme::Short,
+                "Z" => Lexeme::Boolean,
                 "[" => Lexeme::LeftSquareBracket,
                 "V" => Lexeme::Void,
                 _ => {
@@ -128,7 +287,7 @@ impl MethodDescriptor {
         while !done {
             match MethodDescriptor::parse_field_type(lexemes) {
                 Ok(field_type) => parameter_descriptors.push(field_type),
-                Err(e) => done = true
+                Err(_) => done = true
             }
         }
 
@@ -138,7 +297,7 @@ impl MethodDescriptor {
     fn parse_return_descriptor(lexemes: &mut Vec<Lexeme>) -> Result<ReturnDescriptor, String> {
         match MethodDescriptor::parse_field_type(lexemes) {
             Ok(field_type) => Ok(ReturnDescriptor::Field(field_type)),
-            Err(e1) => match MethodDescriptor::parse_void(lexemes) {
+            Err(_) => match MethodDescriptor::parse_void(lexemes) {
                 Ok(_) => Ok(ReturnDescriptor::Void),
                 Err(e2) => Err(e2)
             }
@@ -156,17 +315,18 @@ impl MethodDescriptor {
             Some(Lexeme::Class(name)) => Ok(FieldType::Class(name.clone())),
             Some(Lexeme::Short) => Ok(FieldType::Short),
             Some(Lexeme::Boolean) => Ok(FieldType::Boolean),
-            // TODO: Array descriptor
-            _ => Err(String::from("Did not find field type"))
-        };
-
-        match token {
-            Ok(_) => {
+            Some(Lexeme::LeftSquareBracket) => {
                 lexemes.remove(0);
+                let component = MethodDescriptor::parse_field_type(lexemes)?;
+                return Ok(FieldType::Array(Box::new(component)));
             },
-            Err(_) => {}
+            _ => Err(String::from("Did not find field type"))
         };
 
+        if token.is_ok() {
+            lexemes.remove(0);
+        }
+
         token
     }
 
@@ -219,7 +379,24 @@ enum FieldType {
     Class(String),
     Short,
     Boolean,
-    Array()
+    Array(Box<FieldType>)
+}
+
+impl FieldType {
+    fn descriptor(&self) -> String {
+        match self {
+            FieldType::Byte => String::from("B"),
+            FieldType::Character => String::from("C"),
+            FieldType::Double => String::from("D"),
+            FieldType::Float => String::from("F"),
+            FieldType::Integer => String::from("I"),
+            FieldType::Long => String::from("J"),
+            FieldType::Class(name) => format!("L{};", name),
+            FieldType::Short => String::from("S"),
+            FieldType::Boolean => String::from("Z"),
+            FieldType::Array(component) => format!("[{}", component.descriptor())
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -1,88 +1,930 @@
-use class::{ClassFile, ConstantPool};
+use class::{reader, Attribute, BootstrapMethod, ClassFile, ConstantPool, ConstantPoolEntry, InnerClassTableEntry};
+use class::mutf8::JavaString;
+use class::class_flags::{ACC_ABSTRACT, ACC_INTERFACE};
+use class::path::ClassPath;
 use std::collections::HashMap;
-use std::rc::Rc;
-use runtime::Value;
-use runtime::class::field::{RuntimeField, FieldDescriptor};
+use std::env;
+use std::io::Write;
+use std::path;
+use std::sync::{Arc, Condvar, Mutex, RwLock, Weak};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::thread::{self, JoinHandle, ThreadId};
+use runtime::{Value, HeapCell, Object, Array, ArrayElements};
+use runtime::bootstrap::{self, io::Console};
+use runtime::debugger::Debugger;
+use runtime::frames::ThreadStacks;
+use runtime::gc::{Heap, Roots};
+use runtime::class::annotation::{RawAnnotations, RuntimeAnnotation};
+use runtime::class::field::RuntimeField;
 use runtime::class::method::RuntimeMethod;
+use runtime::invokedynamic::{CallSite, Lambda};
+use runtime::jdwp::Agent;
+use runtime::jit;
+use runtime::profile::Profile;
+use runtime::string::{self, StringTable};
+use runtime::trace::Trace;
 
+pub mod annotation;
 pub mod field;
 pub mod method;
 
-#[derive(Debug)]
+pub const OBJECT_CLASS_NAME: &str = "java/lang/Object";
+pub const CLASS_CLASS_NAME: &str = "java/lang/Class";
+
+// The class table is shared by all threads of the virtual machine.
 pub struct ClassTable {
-    classes: HashMap<String, Rc<RuntimeClass>>
+    this: Weak<ClassTable>,
+    classes: RwLock<HashMap<String, Arc<RuntimeClass>>>,
+    strings: Mutex<StringTable>,
+    mirrors: Mutex<HashMap<String, Arc<HeapCell<Object>>>>,
+    // The module of every class once a JDK has defined the unnamed module of the boot loader. There
+    // is no module system, so all classes are loaded by the boot loader into its unnamed module.
+    unnamed_module: Mutex<Option<Arc<HeapCell<Object>>>>,
+    class_path: Mutex<ClassPath>,
+    verbose_class: AtomicBool,
+    // The number of invocations after which a method is compiled, or 0 if the JIT is disabled.
+    compile_threshold: AtomicU32,
+    properties: RwLock<HashMap<String, String>>,
+    // The implementations of the functional interface classes spun by LambdaMetafactory.
+    lambdas: Mutex<HashMap<String, Arc<Lambda>>>,
+    // The number of hidden classes defined so far, which makes their names unique.
+    hidden_classes: AtomicU32,
+    // The non-daemon threads started by Thread.start, which the launcher waits for.
+    threads: Mutex<Vec<JoinHandle<()>>>,
+    // The java.lang.Thread instance of each running thread that has one.
+    thread_objects: Mutex<HashMap<ThreadId, Arc<HeapCell<Object>>>>,
+    pub heap: Heap,
+    pub console: Console,
+    pub profile: Profile,
+    // The assumptions optimized code makes about the classes loaded so far.
+    pub dependencies: jit::Dependencies,
+    // The Java frames of every thread.
+    pub stacks: ThreadStacks,
+    pub debugger: Debugger,
+    pub jdwp: Agent,
+    pub trace: Trace
 }
 
 impl ClassTable {
 
-    pub fn load_class(&mut self, class: &Rc<RuntimeClass>) {
-        self.classes.insert(class.class_name.clone(), class.clone());
+    pub fn load_class(&self, class: &Arc<RuntimeClass>) {
+        self.classes.write().unwrap().insert(class.class_name.clone(), class.clone());
+        self.dependencies.class_loaded(class);
+    }
+
+    pub fn get_class(&self, name: &str) -> Option<Arc<RuntimeClass>> {
+        self.classes.read().unwrap().get(name).cloned()
+    }
+
+    pub fn loaded_classes(&self) -> Vec<Arc<RuntimeClass>> {
+        self.classes.read().unwrap().values().cloned().collect()
+    }
+
+    // Creates a RuntimeClass from a class file and loads it. The superclass and superinterfaces
+    // must already be loaded.
+    pub fn define_class(&self, class_file: &ClassFile) -> Result<Arc<RuntimeClass>, String> {
+        let super_class = if class_file.is_java_lang_object() {
+            None
+        } else {
+            let super_class_name = class_file.constant_pool.get_class_name(class_file.super_class)?;
+            let super_class = self.get_class(&super_class_name)
+                .ok_or_else(|| format!("Superclass {} is not loaded", super_class_name))?;
+            Some(super_class)
+        };
+
+        let mut interfaces = Vec::new();
+        for index in class_file.interfaces.iter() {
+            let interface_name = class_file.constant_pool.get_class_name(*index)?;
+            let interface = self.get_class(&interface_name)
+                .ok_or_else(|| format!("Interface {} is not loaded", interface_name))?;
+            interfaces.push(interface);
+        }
+
+        // Two threads may race to load the same class, in which case both get the first one defined.
+        let runtime_class = RuntimeClass::from_class_file(class_file, super_class, interfaces)?;
+        let runtime_class = self.classes.write().unwrap()
+            .entry(runtime_class.class_name.clone())
+            .or_insert(runtime_class)
+            .clone();
+        self.dependencies.class_loaded(&runtime_class);
+
+        Ok(runtime_class)
+    }
+
+    // Sets where classes that are not loaded yet are searched for. The classes of the bootstrap
+    // class library are always loaded, so they take precedence.
+    pub fn set_class_path(&self, class_path: ClassPath) {
+        *self.class_path.lock().unwrap() = class_path;
+    }
+
+    // Returns the named class, loading it from the class path if necessary. Its superclass and
+    // superinterfaces are loaded first. Returns None if the class path does not contain it.
+    pub fn find_class(&self, name: &str) -> Result<Option<Arc<RuntimeClass>>, String> {
+        if let Some(class) = self.get_class(name) {
+            return Ok(Some(class));
+        }
+
+        let class_bytes = match self.class_path.lock().unwrap().read_class(name) {
+            Ok(Some(class_bytes)) => class_bytes,
+            Ok(None) => return Ok(None),
+            Err(e) => return Err(format!("Could not read {}: {:?}", name, e))
+        };
+        self.define_class_bytes(Some(name), &class_bytes.bytes, &class_bytes.location).map(Some)
+    }
+
+    // Defines a class from the bytes of its class file, which must be for the named class if a
+    // name is given. Its superclass and superinterfaces are loaded from the class path first.
+    pub fn define_class_bytes(&self, name: Option<&str>, bytes: &[u8], location: &str) -> Result<Arc<RuntimeClass>, String> {
+        let class_file = reader::read_class_file(bytes)
+            .map_err(|e| format!("Could not parse {}: {:?}", name.unwrap_or(location), e))?;
+        self.define_class_file(name, &class_file, location)
+    }
+
+    fn define_class_file(&self, name: Option<&str>, class_file: &ClassFile, location: &str) -> Result<Arc<RuntimeClass>, String> {
+        let class_name = class_file.constant_pool.get_class_name(class_file.this_class)?;
+        let name = name.unwrap_or(&class_name);
+        if class_name != name {
+            return Err(format!("{} (wrong name: {})", name, class_name));
+        }
+
+        let mut dependencies = class_file.interfaces.clone();
+        if !class_file.is_java_lang_object() {
+            dependencies.insert(0, class_file.super_class);
+        }
+        for index in dependencies {
+            let dependency = class_file.constant_pool.get_class_name(index)?;
+            self.find_class(&dependency)?
+                .ok_or_else(|| format!("{} needs {}, which was not found", name, dependency))?;
+        }
+
+        let class = self.define_class(class_file)?;
+        if self.verbose_class.load(Ordering::SeqCst) {
+            self.log_class_load(name, location);
+        }
+        if self.trace.is_enabled() {
+            self.trace.class_loaded(&class, location);
+        }
+        if self.jdwp.is_enabled() {
+            self.jdwp.class_prepared(self, &class);
+        }
+
+        Ok(class)
+    }
+
+    // Defines a hidden class (JVMS $5.3.5) from the bytes of its class file. Hidden classes are
+    // never looked up by name, so several may be defined from the same bytes: like HotSpot, the
+    // name in the class file gets a unique suffix, with a + that no other class name has.
+    pub fn define_hidden_class(&self, bytes: &[u8], location: &str) -> Result<Arc<RuntimeClass>, String> {
+        let mut class_file = reader::read_class_file(bytes)
+            .map_err(|e| format!("Could not parse {}: {:?}", location, e))?;
+        let name_index = match class_file.constant_pool.get(class_file.this_class) {
+            Some(ConstantPoolEntry::Class { name_index }) => *name_index,
+            _ => return Err(format!("Invalid class in {}", location))
+        };
+        let class_name = class_file.constant_pool.get_utf8(name_index)?;
+        let hidden_name = format!("{}+0x{:08x}", class_name, self.hidden_classes.fetch_add(1, Ordering::SeqCst) + 1);
+        class_file.constant_pool.entries[name_index as usize - 1] = ConstantPoolEntry::Utf8(JavaString::from(hidden_name.as_str()));
+
+        self.define_class_file(Some(&hidden_name), &class_file, location)
+    }
+
+    // Enables -verbose:class logging. The classes loaded so far, which are the bootstrap classes,
+    // are logged straight away.
+    pub fn set_verbose_class(&self, verbose: bool) {
+        if verbose && !self.verbose_class.load(Ordering::SeqCst) {
+            let mut names: Vec<String> = self.classes.read().unwrap().keys().cloned().collect();
+            names.sort();
+            for name in names.iter() {
+                self.log_class_load(name, "bootstrap");
+            }
+        }
+
+        self.verbose_class.store(verbose, Ordering::SeqCst);
+    }
+
+    pub fn compile_threshold(&self) -> u32 {
+        self.compile_threshold.load(Ordering::SeqCst)
+    }
+
+    // Sets how often a method runs in the interpreter before it is compiled, as given by
+    // -XX:CompileThreshold. 0 disables the JIT, as -Xint does.
+    pub fn set_compile_threshold(&self, threshold: u32) {
+        self.compile_threshold.store(threshold, Ordering::SeqCst);
+    }
+
+    fn log_class_load(&self, name: &str, location: &str) {
+        let line = format!("[class,load] {} source: {}\n", name.replace('/', "."), location);
+        let _ = self.console.out.lock().unwrap().write_all(line.as_bytes());
+    }
+
+    pub fn get_property(&self, name: &str) -> Option<String> {
+        self.properties.read().unwrap().get(name).cloned()
+    }
+
+    // Sets a system property, returning the previous value.
+    pub fn set_property(&self, name: &str, value: &str) -> Option<String> {
+        self.properties.write().unwrap().insert(String::from(name), String::from(value))
+    }
+
+    // All system properties, sorted by name.
+    pub fn properties(&self) -> Vec<(String, String)> {
+        let mut properties: Vec<(String, String)> = self.properties.read().unwrap()
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        properties.sort();
+        properties
+    }
+
+    // Returns the interned java.lang.String instance for the given UTF-16 code units. The string
+    // is allocated without holding the lock of the table, as allocating may wait for a collection
+    // that other threads must reach a safepoint for.
+    pub fn intern_string(&self, chars: Vec<u16>) -> Arc<HeapCell<Object>> {
+        if let Some(string) = self.strings.lock().unwrap().get(&chars) {
+            return string;
+        }
+
+        let string = self.new_string(chars);
+        self.intern(&string)
+    }
+
+    pub fn intern(&self, string: &Arc<HeapCell<Object>>) -> Arc<HeapCell<Object>> {
+        self.strings.lock().unwrap().intern(string)
+    }
+
+    // Creates a new java.lang.String instance that is not interned.
+    pub fn new_string(&self, chars: Vec<u16>) -> Arc<HeapCell<Object>> {
+        string::new_string(self, chars)
+    }
+
+    // Returns the java.lang.Class instance representing the named class. There is exactly one
+    // mirror per class name: if two threads race to create one, both get the first one recorded.
+    // The name is interned first, so that the table of strings holds it while the mirror is
+    // allocated.
+    pub fn get_mirror(&self, class_name: &str) -> Arc<HeapCell<Object>> {
+        if let Some(mirror) = self.mirrors.lock().unwrap().get(class_name) {
+            return mirror.clone();
+        }
+
+        let class_class = self.get_class(CLASS_CLASS_NAME).unwrap();
+        let scope = self.stacks.scope();
+        let name = self.intern_string(class_name.replace('/', ".").encode_utf16().collect());
+        let mirror = self.new_object(&class_class);
+        scope.hold(&Value::ObjectRef(mirror.clone()));
+        mirror.borrow_mut().put_field(String::from("name"), Value::ObjectRef(name));
+        if let Some(ref module) = *self.unnamed_module.lock().unwrap() {
+            mirror.borrow_mut().put_field(String::from("module"), Value::ObjectRef(module.clone()));
+        }
+        // The java.lang.Class of the JDK keeps the component type of array classes in a field.
+        if let (Some(component), Some(_)) = (class_name.strip_prefix('['), class_class.instance_field_position("componentType")) {
+            let component_type = self.get_mirror(&mirror_name(component));
+            mirror.borrow_mut().put_field(String::from("componentType"), Value::ObjectRef(component_type));
+        }
+
+        self.mirrors.lock().unwrap().entry(String::from(class_name)).or_insert(mirror).clone()
+    }
+
+    // Puts every class into the unnamed module of the boot loader, as BootLoader asks the JDK to
+    // once it has created the module.
+    pub fn set_unnamed_module(&self, module: &Arc<HeapCell<Object>>) {
+        *self.unnamed_module.lock().unwrap() = Some(module.clone());
+        for mirror in self.mirrors.lock().unwrap().values() {
+            mirror.borrow_mut().put_field(String::from("module"), Value::ObjectRef(module.clone()));
+        }
+    }
+
+    // Allocates an instance of the class on the heap, with the frames, statics and tables of
+    // this class table as the roots of any collection it causes. The instance is kept in the
+    // innermost handle scope of the Rust code allocating it, see ThreadStacks::keep.
+    pub fn new_object(&self, class: &Arc<RuntimeClass>) -> Arc<HeapCell<Object>> {
+        self.kept_object(self.heap.new_object(self, class))
+    }
+
+    pub fn new_array(&self, component_type: &str, length: usize) -> Arc<HeapCell<Array>> {
+        self.kept_array(self.heap.new_array(self, component_type, length))
+    }
+
+    pub fn new_array_from(&self, component_type: &str, elements: ArrayElements) -> Arc<HeapCell<Array>> {
+        self.kept_array(self.heap.new_array_from(self, component_type, elements))
+    }
+
+    pub fn clone_object(&self, object: &Arc<HeapCell<Object>>) -> Arc<HeapCell<Object>> {
+        self.kept_object(self.heap.clone_object(self, object))
+    }
+
+    pub fn clone_array(&self, array: &Arc<HeapCell<Array>>) -> Arc<HeapCell<Array>> {
+        self.kept_array(self.heap.clone_array(self, array))
+    }
+
+    fn kept_object(&self, object: Arc<HeapCell<Object>>) -> Arc<HeapCell<Object>> {
+        self.stacks.keep(&Value::ObjectRef(object.clone()));
+        object
+    }
+
+    fn kept_array(&self, array: Arc<HeapCell<Array>>) -> Arc<HeapCell<Array>> {
+        self.stacks.keep(&Value::ArrayRef(array.clone()));
+        array
+    }
+
+    // Makes room for an allocation of the given size, see Heap::reserve.
+    pub fn reserve(&self, size: usize) -> bool {
+        self.heap.reserve(self, size)
+    }
+
+    // Collects the garbage on the whole heap, as System.gc does.
+    pub fn collect_garbage(&self) -> bool {
+        self.heap.collect(self)
+    }
+
+    pub fn get_lambda(&self, class_name: &str) -> Option<Arc<Lambda>> {
+        self.lambdas.lock().unwrap().get(class_name).cloned()
+    }
+
+    // Records the implementation of a lambda class, returning the name to define the class
+    // under. Like HotSpot, the name is derived from the class that created the lambda.
+    pub fn add_lambda(&self, host_class_name: &str, lambda: Lambda) -> String {
+        let mut lambdas = self.lambdas.lock().unwrap();
+        let name = format!("{}$$Lambda${}", host_class_name, lambdas.len() + 1);
+        lambdas.insert(name.clone(), Arc::new(lambda));
+        name
+    }
+
+    // A new reference to this class table, for handing to another thread.
+    pub fn shared(&self) -> Arc<ClassTable> {
+        self.this.upgrade().unwrap()
+    }
+
+    pub fn add_thread(&self, thread: JoinHandle<()>) {
+        self.threads.lock().unwrap().push(thread);
+    }
+
+    // Waits for all non-daemon threads to finish, including the ones they start in turn.
+    pub fn join_threads(&self) {
+        loop {
+            let thread = self.threads.lock().unwrap().pop();
+            match thread {
+                Some(thread) => { let _ = thread.join(); },
+                None => break
+            }
+        }
+    }
+
+    pub fn get_thread_object(&self) -> Option<Arc<HeapCell<Object>>> {
+        self.thread_objects.lock().unwrap().get(&thread::current().id()).cloned()
+    }
+
+    // Associates the current thread with a java.lang.Thread instance, or dissociates it when the
+    // thread finishes.
+    pub fn set_thread_object(&self, thread_object: Option<Arc<HeapCell<Object>>>) {
+        let mut thread_objects = self.thread_objects.lock().unwrap();
+        match thread_object {
+            Some(thread_object) => thread_objects.insert(thread::current().id(), thread_object),
+            None => thread_objects.remove(&thread::current().id())
+        };
+    }
+
+    // The thread a java.lang.Thread instance runs on, if it is running.
+    pub fn find_thread(&self, thread_object: &Arc<HeapCell<Object>>) -> Option<ThreadId> {
+        self.thread_objects.lock().unwrap()
+            .iter()
+            .find(|(_, object)| Arc::ptr_eq(object, thread_object))
+            .map(|(&thread, _)| thread)
+    }
+
+    pub fn new() -> Arc<ClassTable> {
+        ClassTable::with_console(Console::stdio())
+    }
+
+    // Creates a class table with the bootstrap class library loaded, writing System.out and
+    // System.err to the given console.
+    pub fn with_console(console: Console) -> Arc<ClassTable> {
+        let class_table = ClassTable::without_library(console);
+        bootstrap::load(&class_table);
+        class_table
+    }
+
+    // Creates a class table without the bootstrap class library, for the java.base module of a
+    // JDK on the class path to take its place, see bootstrap::jdk::boot.
+    pub fn without_library(console: Console) -> Arc<ClassTable> {
+        Arc::new_cyclic(|this| ClassTable {
+            this: this.clone(),
+            classes: RwLock::new(HashMap::new()),
+            strings: Mutex::new(StringTable::new()),
+            mirrors: Mutex::new(HashMap::new()),
+            unnamed_module: Mutex::new(None),
+            class_path: Mutex::new(ClassPath::new()),
+            verbose_class: AtomicBool::new(false),
+            compile_threshold: AtomicU32::new(jit::COMPILE_THRESHOLD),
+            properties: RwLock::new(default_properties()),
+            lambdas: Mutex::new(HashMap::new()),
+            hidden_classes: AtomicU32::new(0),
+            threads: Mutex::new(Vec::new()),
+            thread_objects: Mutex::new(HashMap::new()),
+            heap: Heap::new(),
+            console,
+            profile: Profile::new(),
+            dependencies: jit::Dependencies::new(),
+            stacks: ThreadStacks::new(),
+            debugger: Debugger::new(),
+            jdwp: Agent::new(),
+            trace: Trace::new()
+        })
     }
 
-    pub fn get_class(&self, name: &str) -> Option<&Rc<RuntimeClass>> {
-        self.classes.get(name)
+}
+
+// The roots of the heap are the frames and handle scopes of every thread, the statics of every
+// class, the tables of the class table and the objects a debugger has ids for. No thread
+// allocates while holding the lock of a table, and every other thread is stopped outside them,
+// so they can all be locked.
+impl Roots for ClassTable {
+    fn classes(&self) -> Vec<Arc<RuntimeClass>> {
+        self.loaded_classes()
     }
 
-    pub fn new() -> ClassTable {
-        ClassTable {
-            classes: HashMap::new()
+    fn visit(&self, visit: &mut dyn FnMut(&Value)) {
+        for (_, frames) in self.stacks.snapshot() {
+            for frame in frames.iter() {
+                let stack_frame = frame.stack_frame();
+                if stack_frame.is_null() {
+                    continue;
+                }
+                // Every thread but the collecting one is stopped at a safepoint, and the
+                // collecting one is allocating, so none of them is changing its frames.
+                let stack_frame = unsafe { &*stack_frame };
+                stack_frame.locals.iter().for_each(&mut *visit);
+                stack_frame.stack.iter().for_each(&mut *visit);
+            }
         }
+        self.stacks.visit_handles(visit);
+
+        self.strings.lock().unwrap().visit(visit);
+        self.mirrors.lock().unwrap().values().for_each(|mirror| visit(&Value::ObjectRef(mirror.clone())));
+        self.unnamed_module.lock().unwrap().iter().for_each(|module| visit(&Value::ObjectRef(module.clone())));
+        self.thread_objects.lock().unwrap().values().for_each(|thread_object| visit(&Value::ObjectRef(thread_object.clone())));
+        self.jdwp.visit_objects(visit);
     }
+}
 
+// The system properties every Java implementation provides, see System.getProperties.
+fn default_properties() -> HashMap<String, String> {
+    let mut properties = HashMap::new();
+    let mut set = |name: &str, value: &str| properties.insert(String::from(name), String::from(value));
+
+    set("java.version", "17");
+    set("java.specification.version", "17");
+    set("java.vendor", "IronJDK");
+    set("java.vm.name", "IronJDK");
+    set("java.class.version", "61.0");
+    set("java.class.path", "");
+    set("os.name", env::consts::OS);
+    set("os.arch", env::consts::ARCH);
+    set("file.separator", path::MAIN_SEPARATOR_STR);
+    set("path.separator", if cfg!(windows) { ";" } else { ":" });
+    set("line.separator", "\n");
+    set("file.encoding", "UTF-8");
+    if let Ok(dir) = env::current_dir() {
+        set("user.dir", &dir.display().to_string());
+    }
+    if let Ok(home) = env::var("HOME") {
+        set("user.home", &home);
+    }
+
+    properties
+}
+
+// The class and method named by the class_index and method_index of an EnclosingMethod attribute.
+fn enclosing_method(bytes: &[u8], cp: &ConstantPool) -> Result<(String, Option<(String, String)>), String> {
+    if bytes.len() != 4 {
+        return Err(String::from("Invalid EnclosingMethod attribute"));
+    }
+    let class_index = u16::from_be_bytes([bytes[0], bytes[1]]);
+    let method = match u16::from_be_bytes([bytes[2], bytes[3]]) {
+        0 => None,
+        method_index => {
+            let name_and_type = cp.get_name_and_type(method_index)?;
+            Some((name_and_type.name, name_and_type.descriptor))
+        }
+    };
+    Ok((cp.get_class_name(class_index)?, method))
+}
+
+// An entry of the InnerClasses attribute, naming a nested class.
+#[derive(Clone, Debug)]
+pub struct InnerClass {
+    pub class_name: String,
+    // The class it is a member of, or None for local and anonymous classes.
+    pub outer_class_name: Option<String>,
+    // The name it has in the source, or None for anonymous classes.
+    pub simple_name: Option<String>,
+    // The access flags it was declared with, which may be private, protected or static unlike
+    // those of its class file.
+    pub access_flags: u16
+}
+
+impl InnerClass {
+
+    fn from_class_entry(entry: &InnerClassTableEntry, cp: &ConstantPool) -> Result<InnerClass, String> {
+        Ok(InnerClass {
+            class_name: cp.get_class_name(entry.inner_class_info_index)?,
+            outer_class_name: match entry.outer_class_info_index {
+                0 => None,
+                index => Some(cp.get_class_name(index)?)
+            },
+            simple_name: match entry.inner_name_index {
+                0 => None,
+                index => Some(cp.get_utf8(index)?)
+            },
+            access_flags: entry.inner_class_access_flags
+        })
+    }
+
+}
+
+// The name Class.getName gives the type of a field descriptor, e.g. int, java.lang.String or
+// [Ljava.lang.String;. Mirrors are looked up by the internal form of these names.
+pub fn mirror_name(descriptor: &str) -> String {
+    match descriptor {
+        "Z" => String::from("boolean"),
+        "B" => String::from("byte"),
+        "C" => String::from("char"),
+        "S" => String::from("short"),
+        "I" => String::from("int"),
+        "J" => String::from("long"),
+        "F" => String::from("float"),
+        "D" => String::from("double"),
+        "V" => String::from("void"),
+        _ if descriptor.starts_with('L') && descriptor.ends_with(';') => String::from(&descriptor[1..descriptor.len() - 1]),
+        _ => String::from(descriptor)
+    }
+}
+
+// The initialization state of a class, see JVMS $5.5.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClassState {
+    Linked,
+    BeingInitialized(ThreadId),
+    Initialized,
+    Erroneous
 }
 
 #[derive(Debug)]
 pub struct RuntimeClass {
     pub class_name: String,
+    pub access_flags: u16,
+    pub super_class: Option<Arc<RuntimeClass>>,
+    pub interfaces: Vec<Arc<RuntimeClass>>,
     pub constant_pool: ConstantPool,
+    // The fields and methods declared by this class.
     pub fields: Vec<RuntimeField>,
-    pub methods: Vec<RuntimeMethod>
-//    pub static_fields: Vec<RuntimeField> // TODO: Here or somewhere else?
+    pub methods: Vec<RuntimeMethod>,
+    // The layout of instances: the instance fields of all superclasses followed by our own.
+    pub instance_fields: Vec<RuntimeField>,
+    pub bootstrap_methods: Vec<BootstrapMethod>,
+    // The name of the source file the class was compiled from, without its directory.
+    pub source_file: Option<String>,
+    pub annotations: Vec<RuntimeAnnotation>,
+    pub raw_annotations: RawAnnotations,
+    // The entries of the InnerClasses attribute: the classes nested in this one, and this one if
+    // it is nested itself.
+    pub inner_classes: Vec<InnerClass>,
+    // The class a local or anonymous class is declared in, and the name and descriptor of the
+    // method if it is declared in one, from the EnclosingMethod attribute.
+    pub enclosing_method: Option<(String, Option<(String, String)>)>,
+    statics: RwLock<HashMap<String, Value>>,
+    // Set when a reference is stored into a static, like a dirty card, see put_static.
+    statics_dirty: AtomicBool,
+    state: Mutex<ClassState>,
+    // Signalled when the state changes, for threads waiting for another thread to initialize
+    // the class.
+    state_changed: Condvar,
+    // Linked invokedynamic call sites by the index of their InvokeDynamic constant.
+    call_sites: Mutex<HashMap<u16, Arc<CallSite>>>
 }
 
 impl RuntimeClass {
 
+    pub fn new(class_name: &str,
+               access_flags: u16,
+               super_class: Option<Arc<RuntimeClass>>,
+               interfaces: Vec<Arc<RuntimeClass>>,
+               constant_pool: ConstantPool,
+               fields: Vec<RuntimeField>,
+               methods: Vec<RuntimeMethod>) -> Arc<RuntimeClass> {
+        Arc::new(RuntimeClass::create(class_name, access_flags, super_class, interfaces, constant_pool, fields, methods))
+    }
+
+    fn create(class_name: &str,
+              access_flags: u16,
+              super_class: Option<Arc<RuntimeClass>>,
+              interfaces: Vec<Arc<RuntimeClass>>,
+              constant_pool: ConstantPool,
+              fields: Vec<RuntimeField>,
+              methods: Vec<RuntimeMethod>) -> RuntimeClass {
+        let mut instance_fields = match super_class {
+            Some(ref super_class) => super_class.instance_fields.clone(),
+            None => Vec::new()
+        };
+        instance_fields.extend(fields.iter().filter(|field| !field.is_static()).cloned());
+
+        let statics = fields
+            .iter()
+            .filter(|field| field.is_static())
+            .map(|field| (field.name.clone(), Value::default_for(&field.descriptor_string)))
+            .collect();
+
+        RuntimeClass {
+            class_name: String::from(class_name),
+            access_flags,
+            super_class,
+            interfaces,
+            constant_pool,
+            fields,
+            methods,
+            instance_fields,
+            bootstrap_methods: Vec::new(),
+            source_file: None,
+            annotations: Vec::new(),
+            raw_annotations: RawAnnotations::default(),
+            inner_classes: Vec::new(),
+            enclosing_method: None,
+            statics: RwLock::new(statics),
+            statics_dirty: AtomicBool::new(false),
+            state: Mutex::new(ClassState::Linked),
+            state_changed: Condvar::new(),
+            call_sites: Mutex::new(HashMap::new())
+        }
+    }
+
+    // The position of an instance field in objects of this class and its subclasses, which lay
+    // out the fields of their superclasses first. Searching from the end finds the field declared
+    // closest to this class when a subclass hides a field of its superclass with the same name.
+    pub fn instance_field_position(&self, name: &str) -> Option<usize> {
+        self.instance_fields.iter().rposition(|field| field.name == name)
+    }
+
     pub fn default_fields(&self) -> Vec<Value> {
-        self.fields
+        self.instance_fields
             .iter()
-            .map(|field|
-                // See JVMS $2.3 and $2.4 for default values.
-                match field.descriptor {
-                    FieldDescriptor::Integer => Value::Integer(0),
-                    _ => Value::Null
-                }
-            )
+            .map(|field| Value::default_for(&field.descriptor_string))
             .collect()
     }
 
+    pub fn is_interface(&self) -> bool {
+        self.access_flags & ACC_INTERFACE != 0
+    }
+
+    // The entry of the InnerClasses attribute for this class, if it is nested in another.
+    pub fn inner_class(&self) -> Option<&InnerClass> {
+        self.inner_classes.iter().find(|inner_class| inner_class.class_name == self.class_name)
+    }
+
+    pub fn is_abstract(&self) -> bool {
+        self.access_flags & ACC_ABSTRACT != 0
+    }
+
+    // Whether the class is hidden, by its name as ClassTable::define_hidden_class gives it.
+    pub fn is_hidden(&self) -> bool {
+        self.class_name.contains('+')
+    }
+
+    // Whether the class was made up for a lambda, by its name as ClassTable::add_lambda gives it.
+    pub fn is_lambda(&self) -> bool {
+        self.class_name.contains("$$Lambda$")
+    }
+
+    pub fn state(&self) -> ClassState {
+        *self.state.lock().unwrap()
+    }
+
+    pub fn set_state(&self, state: ClassState) {
+        *self.state.lock().unwrap() = state;
+        self.state_changed.notify_all();
+    }
+
+    // Steps 1 to 5 of the initialization procedure in JVMS $5.5. Waits while another thread is
+    // initializing the class, then returns the state found. If the class is only linked, the
+    // current thread becomes the one initializing it.
+    pub fn claim_initialization(&self) -> ClassState {
+        let current = thread::current().id();
+        let mut state = self.state.lock().unwrap();
+
+        loop {
+            match *state {
+                ClassState::BeingInitialized(thread) if thread != current => {
+                    state = self.state_changed.wait(state).unwrap();
+                },
+                ClassState::Linked => {
+                    *state = ClassState::BeingInitialized(current);
+                    return ClassState::Linked;
+                },
+                found => return found
+            }
+        }
+    }
+
     pub fn get_method(&self, name: &str) -> Option<&RuntimeMethod> {
         self.methods
             .iter()
             .find(|method| method.name == name)
     }
 
-    pub fn from_class_file(class_file: &ClassFile) -> Result<Rc<RuntimeClass>, String> {
+    pub fn get_declared_method(&self, name: &str, descriptor: &str) -> Option<&RuntimeMethod> {
+        self.methods
+            .iter()
+            .find(|method| method.name == name && method.descriptor == descriptor)
+    }
+
+    // Method resolution as described in JVMS $5.4.3.3: the class itself and its superclasses
+    // are searched first, followed by the superinterfaces. Returns the declaring class.
+    pub fn resolve_method(class: &Arc<RuntimeClass>, name: &str, descriptor: &str) -> Option<Arc<RuntimeClass>> {
+        let mut current = Some(class.clone());
+
+        while let Some(c) = current {
+            if c.get_declared_method(name, descriptor).is_some() {
+                return Some(c);
+            }
+            current = c.super_class.clone();
+        }
+
+        RuntimeClass::resolve_interface_method(class, name, descriptor)
+    }
+
+    // Searches the superinterfaces for a non-abstract method, which is how default methods are
+    // selected. Abstract declarations are only returned if no default method exists.
+    fn resolve_interface_method(class: &Arc<RuntimeClass>, name: &str, descriptor: &str) -> Option<Arc<RuntimeClass>> {
+        let mut abstract_declaration = None;
+        let mut pending: Vec<Arc<RuntimeClass>> = vec![class.clone()];
+
+        while let Some(c) = pending.pop() {
+            if c.is_interface() {
+                if let Some(method) = c.get_declared_method(name, descriptor) {
+                    if !method.is_abstract() {
+                        return Some(c.clone());
+                    }
+                    abstract_declaration = abstract_declaration.or_else(|| Some(c.clone()));
+                }
+            }
+
+            pending.extend(c.interfaces.iter().cloned());
+            if let Some(ref super_class) = c.super_class {
+                pending.push(super_class.clone());
+            }
+        }
+
+        abstract_declaration
+    }
+
+    // Whether this class is the named class, or a subclass or implementation of it.
+    pub fn is_subclass_of(&self, class_name: &str) -> bool {
+        if self.class_name == class_name {
+            return true;
+        }
+
+        let superclass_match = match self.super_class {
+            Some(ref super_class) => super_class.is_subclass_of(class_name),
+            None => false
+        };
+
+        superclass_match || self.interfaces.iter().any(|interface| interface.is_subclass_of(class_name))
+    }
+
+    pub fn has_static(&self, name: &str) -> bool {
+        self.statics.read().unwrap().contains_key(name)
+    }
+
+    // Finds the class declaring the named static field, searching superinterfaces and
+    // superclasses as described in JVMS $5.4.3.2.
+    pub fn resolve_static(class: &Arc<RuntimeClass>, name: &str) -> Option<Arc<RuntimeClass>> {
+        if class.has_static(name) {
+            return Some(class.clone());
+        }
+
+        for interface in class.interfaces.iter() {
+            if let Some(declaring_class) = RuntimeClass::resolve_static(interface, name) {
+                return Some(declaring_class);
+            }
+        }
+
+        match class.super_class {
+            Some(ref super_class) => RuntimeClass::resolve_static(super_class, name),
+            None => None
+        }
+    }
+
+    pub fn get_static(&self, name: &str) -> Option<Value> {
+        self.statics.read().unwrap().get(name).cloned()
+    }
+
+    // Storing a reference dirties the statics, as the write barrier for static fields: they are
+    // roots of the next minor collection.
+    pub fn put_static(&self, name: &str, value: Value) {
+        if let Value::ObjectRef(_) | Value::ArrayRef(_) = value {
+            self.dirty_statics();
+        }
+        self.statics.write().unwrap().insert(String::from(name), value);
+    }
+
+    // Stores the value into the named static if its current value is the expected one, as one
+    // atomic step, and returns the value found. This is how Unsafe compares and sets statics.
+    pub fn compare_and_exchange_static(&self, name: &str, expected: impl FnOnce(&Value) -> bool, value: Value) -> Option<Value> {
+        let mut statics = self.statics.write().unwrap();
+        let current = statics.get_mut(name)?;
+        let found = current.clone();
+        if expected(&found) {
+            if let Value::ObjectRef(_) | Value::ArrayRef(_) = value {
+                self.dirty_statics();
+            }
+            *current = value;
+        }
+        Some(found)
+    }
+
+    pub fn dirty_statics(&self) {
+        self.statics_dirty.store(true, Ordering::Relaxed);
+    }
+
+    pub fn statics_dirty(&self) -> bool {
+        self.statics_dirty.load(Ordering::Relaxed)
+    }
+
+    pub fn clean_statics(&self) {
+        self.statics_dirty.store(false, Ordering::Relaxed);
+    }
+
+    // Calls visit with the value of every static field, and the constants the call sites of the
+    // class evaluate to, for the garbage collector.
+    pub fn visit_statics(&self, visit: &mut dyn FnMut(&Value)) {
+        self.statics.read().unwrap().values().for_each(&mut *visit);
+        self.call_sites.lock().unwrap().values().for_each(|call_site| call_site.visit(visit));
+    }
+
+    pub fn get_call_site(&self, index: u16) -> Option<Arc<CallSite>> {
+        self.call_sites.lock().unwrap().get(&index).cloned()
+    }
+
+    // The call site may hold a new constant, so the statics are dirtied as by put_static.
+    pub fn put_call_site(&self, index: u16, call_site: Arc<CallSite>) {
+        self.dirty_statics();
+        self.call_sites.lock().unwrap().insert(index, call_site);
+    }
+
+    pub fn from_class_file(class_file: &ClassFile,
+                           super_class: Option<Arc<RuntimeClass>>,
+                           interfaces: Vec<Arc<RuntimeClass>>) -> Result<Arc<RuntimeClass>, String> {
         let class_name = class_file.constant_pool.get_class_name(class_file.this_class)?;
         let cp = class_file.constant_pool.clone(); // TODO: Better representation?
 
         let fields = class_file.fields
             .iter()
-            .map(|field| RuntimeField::from_class_field(field, &cp).unwrap())
-            .collect();
+            .map(|field| RuntimeField::from_class_field(field, &cp)
+                .ok_or_else(|| format!("Invalid field in {}", class_name)))
+            .collect::<Result<Vec<RuntimeField>, String>>()?;
 
-        let methods = class_file.methods
+        let mut methods = class_file.methods
             .iter()
-            .map(|method| RuntimeMethod::from_class_method(method, &cp).unwrap())
-            .collect();
+            .map(|method| RuntimeMethod::from_class_method(method, &cp)
+                .ok_or_else(|| format!("Invalid method in {}", class_name)))
+            .collect::<Result<Vec<RuntimeMethod>, String>>()?;
+        for method in methods.iter_mut().filter(|method| method.is_native()) {
+            method.native = bootstrap::find_native(&class_name, &method.name, &method.descriptor);
+        }
 
-        let runtime_class = RuntimeClass {
-            class_name,
-            constant_pool: cp,
+        let mut runtime_class = RuntimeClass::create(
+            &class_name,
+            class_file.access_flags,
+            super_class,
+            interfaces,
+            cp,
             fields,
             methods
-        };
+        );
+        // Fields only the virtual machine knows about, which reflection does not see.
+        runtime_class.instance_fields.extend(bootstrap::injected_fields(&class_name));
+
+        runtime_class.annotations = annotation::visible_annotations(&class_file.attributes, &runtime_class.constant_pool)?;
+        runtime_class.raw_annotations = RawAnnotations::from_attributes(&class_file.attributes, &runtime_class.constant_pool)?;
+        for attribute in class_file.attributes.iter() {
+            match *attribute {
+                Attribute::BootstrapMethods { ref methods } => runtime_class.bootstrap_methods = methods.clone(),
+                Attribute::SourceFile { index } => runtime_class.source_file = runtime_class.constant_pool.get_utf8(index).ok(),
+                Attribute::InnerClasses { ref classes } => {
+                    runtime_class.inner_classes = classes
+                        .iter()
+                        .filter(|entry| entry.inner_class_info_index != 0)
+                        .map(|entry| InnerClass::from_class_entry(entry, &runtime_class.constant_pool))
+                        .collect::<Result<Vec<InnerClass>, String>>()?;
+                },
+                // The reader does not parse EnclosingMethod, so it comes as the bytes of its class
+                // and method indices.
+                Attribute::Unrecognized(ref info) if runtime_class.constant_pool.get_utf8(info.attribute_name_index)? == "EnclosingMethod" => {
+                    runtime_class.enclosing_method = Some(enclosing_method(&info.bytes, &runtime_class.constant_pool)?);
+                },
+                _ => {}
+            }
+        }
 
-        Ok(Rc::new(runtime_class))
+        Ok(Arc::new(runtime_class))
     }
 
 }
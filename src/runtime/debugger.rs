@@ -0,0 +1,622 @@
+use runtime::{Value, Array, Elements, HeapCell, Object};
+use runtime::class::{RuntimeClass, ClassTable};
+use runtime::class::method::RuntimeMethod;
+use runtime::interpreter::InterpreterError;
+use runtime::stack::StackFrame;
+use runtime::string;
+use std::io::{BufRead, Write};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, ThreadId};
+
+// An interactive debugger for the interpreter, as with java --debug. Before each instruction
+// it runs, the interpreter asks the debugger whether to stop there, at a breakpoint or after a
+// step. When it stops, the debugger reads commands until told to go on.
+//
+// Only the thread that stopped waits for commands. Other threads keep running, and stop in
+// turn if they get to a breakpoint.
+
+const HELP: &str = "Commands:
+  break <class>.<method>[@<pc>]   stop at a pc of a method, its first by default
+  break <class>:<line>            stop at the first instruction of a source line
+  break <file>.java:<line>        the same, in the classes compiled from a file
+  delete <n>                      remove breakpoint n
+  breakpoints                     list the breakpoints
+  continue, c                     run until the next breakpoint
+  step, s                         run to the next source line, into calls
+  next, n                         run to the next source line, over calls
+  stepi, si                       run one instruction
+  stack                           print the operand stack
+  locals                          print the local variables
+  print <expression>, p           print a local, this, a static field, or a
+                                  field of one of them, e.g. node.left.key
+  backtrace, bt                   print the frames of this thread
+  quit, q                         exit the virtual machine";
+
+#[derive(Debug, PartialEq)]
+pub enum Location {
+    // A pc in a method, by class name and method name. Every method with the name matches.
+    Pc(String, String, u16),
+    // The first instruction of a line in any method of a class.
+    Line(String, u16),
+    // The first instruction of a line in any class compiled from a source file.
+    File(String, u16)
+}
+
+impl Location {
+
+    // Parses Class.method, Class.method@pc, Class:line or File.java:line. Class names may be
+    // written with dots or slashes.
+    pub fn parse(location: &str) -> Option<Location> {
+        if let Some(colon) = location.rfind(':') {
+            let line = location[colon + 1..].parse().ok()?;
+            let name = &location[..colon];
+            if name.ends_with(".java") {
+                return Some(Location::File(String::from(name), line));
+            }
+            return Some(Location::Line(name.replace('.', "/"), line));
+        }
+
+        let (method, pc) = match location.find('@') {
+            Some(at) => (&location[..at], location[at + 1..].parse().ok()?),
+            None => (location, 0)
+        };
+        let dot = method.rfind('.')?;
+        if dot == 0 || dot + 1 == method.len() {
+            return None;
+        }
+        Some(Location::Pc(method[..dot].replace('.', "/"), String::from(&method[dot + 1..]), pc))
+    }
+
+    fn matches(&self, class: &RuntimeClass, method: &RuntimeMethod, pc: u16) -> bool {
+        match *self {
+            Location::Pc(ref class_name, ref name, at) => {
+                at == pc && *class_name == class.class_name && *name == method.name
+            },
+            Location::Line(ref class_name, line) => *class_name == class.class_name && starts_line(method, pc, line),
+            Location::File(ref file, line) => class.source_file.as_ref() == Some(file) && starts_line(method, pc, line)
+        }
+    }
+
+    // Checks that the location is in code that can be found, so that a misspelt class or method,
+    // or a line without code, is not silently never hit. A source file is found by the classes
+    // loaded from it, or by the class named after it.
+    fn resolve(&self, class_table: &ClassTable) -> Result<(), String> {
+        match *self {
+            Location::Pc(ref class_name, ref name, pc) => {
+                let class = find_class(class_table, class_name)?;
+                let mut methods = class.methods.iter().filter(|method| method.name == *name).peekable();
+                if methods.peek().is_none() {
+                    return Err(format!("No method {}.{}", class_name.replace('/', "."), name));
+                }
+                if !methods.any(|method| method.code.as_ref().is_some_and(|code| code.instruction_position(pc).is_some())) {
+                    return Err(format!("No instruction at {}", self));
+                }
+                Ok(())
+            },
+            Location::Line(ref class_name, line) => {
+                let class = find_class(class_table, class_name)?;
+                if !has_line(&class, line) {
+                    return Err(format!("No code at {}", self));
+                }
+                Ok(())
+            },
+            Location::File(ref file, line) => {
+                let mut classes: Vec<Arc<RuntimeClass>> = class_table.loaded_classes()
+                    .into_iter()
+                    .filter(|class| class.source_file.as_ref() == Some(file))
+                    .collect();
+                if classes.is_empty() {
+                    match class_table.find_class(file.trim_end_matches(".java")) {
+                        Ok(Some(class)) if class.source_file.as_ref() == Some(file) => classes.push(class),
+                        _ => return Err(format!("No class compiled from {}", file))
+                    }
+                }
+                if !classes.iter().any(|class| has_line(class, line)) {
+                    return Err(format!("No code at {}", self));
+                }
+                Ok(())
+            }
+        }
+    }
+
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Location::Pc(ref class_name, ref name, pc) => write!(f, "{}.{}@{}", class_name.replace('/', "."), name, pc),
+            Location::Line(ref class_name, line) => write!(f, "{}:{}", class_name.replace('/', "."), line),
+            Location::File(ref file, line) => write!(f, "{}:{}", file, line)
+        }
+    }
+}
+
+enum Stepping {
+    Instruction(ThreadId),
+    // To the start of another line, or of a line in another frame. Stepping over calls only
+    // stops in the frame that stepped or the frames it returns to.
+    Line { thread: ThreadId, depth: usize, line: Option<u16>, over: bool }
+}
+
+enum Outcome {
+    Resume,
+    Quit,
+    // The commands ran out.
+    Detach
+}
+
+struct Session {
+    input: Box<dyn BufRead + Send>,
+    output: Box<dyn Write + Send>,
+    breakpoints: Vec<(usize, Location)>,
+    next_breakpoint: usize,
+    stepping: Option<Stepping>
+}
+
+// What the interpreter is doing when it stops.
+struct Stop<'a> {
+    class_table: &'a ClassTable,
+    class: &'a Arc<RuntimeClass>,
+    method: &'a RuntimeMethod,
+    pc: u16,
+    stack_frame: &'a StackFrame
+}
+
+pub struct Debugger {
+    enabled: AtomicBool,
+    session: Mutex<Option<Session>>
+}
+
+impl Debugger {
+
+    pub fn new() -> Debugger {
+        Debugger { enabled: AtomicBool::new(false), session: Mutex::new(None) }
+    }
+
+    // Starts debugging, reading commands from input and writing to output.
+    pub fn enable(&self, input: Box<dyn BufRead + Send>, output: Box<dyn Write + Send>) {
+        *self.session.lock().unwrap() = Some(Session { input, output, breakpoints: Vec::new(), next_breakpoint: 1, stepping: None });
+        self.enabled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn add_breakpoint(&self, location: Location) -> Option<usize> {
+        self.session.lock().unwrap().as_mut().map(|session| session.add_breakpoint(location))
+    }
+
+    // Called by the interpreter before it runs the instruction at pc. Returns Exit if the
+    // debugger was told to quit.
+    pub fn before_instruction(&self,
+                              class_table: &ClassTable,
+                              class: &Arc<RuntimeClass>,
+                              method: &RuntimeMethod,
+                              pc: u16,
+                              stack_frame: &StackFrame) -> Result<(), InterpreterError> {
+        let mut session = self.session.lock().unwrap();
+        let session = match *session {
+            Some(ref mut session) => session,
+            None => return Ok(())
+        };
+
+        let stop = Stop { class_table, class, method, pc, stack_frame };
+        let reason = match session.breakpoints.iter().find(|&(_, location)| location.matches(class, method, pc)) {
+            Some(&(id, ref location)) => format!("Breakpoint {} ({})", id, location),
+            None if session.stepped(&stop) => String::from("Stepped"),
+            None => return Ok(())
+        };
+        session.stepping = None;
+
+        let _ = writeln!(session.output, "{} at {}", reason, describe(class, method, pc));
+        class_table.console.flush();
+        match session.repl(&stop) {
+            Outcome::Resume => Ok(()),
+            Outcome::Quit => Err(InterpreterError::Exit(1)),
+            Outcome::Detach => {
+                // No more commands can be read, so the program runs on undisturbed.
+                self.enabled.store(false, Ordering::SeqCst);
+                Ok(())
+            }
+        }
+    }
+
+}
+
+impl Default for Debugger {
+    fn default() -> Debugger {
+        Debugger::new()
+    }
+}
+
+impl Session {
+
+    fn add_breakpoint(&mut self, location: Location) -> usize {
+        let id = self.next_breakpoint;
+        self.next_breakpoint += 1;
+        self.breakpoints.push((id, location));
+        id
+    }
+
+    fn stepped(&self, stop: &Stop) -> bool {
+        match self.stepping {
+            Some(Stepping::Instruction(thread)) => thread == thread::current().id(),
+            Some(Stepping::Line { thread, depth, line, over }) => {
+                if thread != thread::current().id() {
+                    return false;
+                }
+                let code = match stop.method.code {
+                    Some(ref code) => code,
+                    None => return false
+                };
+                if !code.line_numbers.iter().any(|entry| entry.start_pc == stop.pc) {
+                    return false;
+                }
+                let current_depth = stop.class_table.stacks.depth();
+                if over && current_depth > depth {
+                    return false;
+                }
+                current_depth != depth || code.line_number(stop.pc) != line
+            },
+            None => false
+        }
+    }
+
+    // Reads and runs commands until one goes on with the program.
+    fn repl(&mut self, stop: &Stop) -> Outcome {
+        loop {
+            let _ = write!(self.output, "> ");
+            let _ = self.output.flush();
+            let mut line = String::new();
+            match self.input.read_line(&mut line) {
+                Ok(0) | Err(_) => return Outcome::Detach,
+                Ok(_) => {}
+            }
+
+            let mut words = line.split_whitespace();
+            let command = match words.next() {
+                Some(command) => command,
+                None => continue
+            };
+            let argument = words.next();
+
+            match (command, argument) {
+                ("continue", None) | ("c", None) => return Outcome::Resume,
+                ("step", None) | ("s", None) | ("next", None) | ("n", None) => {
+                    let line = stop.method.code.as_ref().and_then(|code| code.line_number(stop.pc));
+                    let over = command.starts_with('n');
+                    self.stepping = Some(Stepping::Line { thread: thread::current().id(), depth: stop.class_table.stacks.depth(), line, over });
+                    return Outcome::Resume;
+                },
+                ("stepi", None) | ("si", None) => {
+                    self.stepping = Some(Stepping::Instruction(thread::current().id()));
+                    return Outcome::Resume;
+                },
+                ("break", Some(location)) | ("b", Some(location)) => match Location::parse(location) {
+                    Some(location) => match location.resolve(stop.class_table) {
+                        Ok(()) => {
+                            let message = format!("Breakpoint {} at {}", self.next_breakpoint, location);
+                            self.add_breakpoint(location);
+                            let _ = writeln!(self.output, "{}", message);
+                        },
+                        Err(message) => {
+                            let _ = writeln!(self.output, "{}", message);
+                        }
+                    },
+                    None => {
+                        let _ = writeln!(self.output, "Invalid location {}, expected Class.method, Class.method@pc, Class:line or File.java:line", location);
+                    }
+                },
+                ("delete", Some(id)) => {
+                    let before = self.breakpoints.len();
+                    self.breakpoints.retain(|&(existing, _)| Some(existing) != id.parse().ok());
+                    if self.breakpoints.len() == before {
+                        let _ = writeln!(self.output, "No breakpoint {}", id);
+                    }
+                },
+                ("breakpoints", None) => {
+                    for &(id, ref location) in self.breakpoints.iter() {
+                        let _ = writeln!(self.output, "{}: {}", id, location);
+                    }
+                },
+                ("stack", None) => {
+                    for (depth, value) in stop.stack_frame.stack.iter().rev().enumerate() {
+                        let _ = writeln!(self.output, "[{}] {}", depth, format_value(value));
+                    }
+                },
+                ("locals", None) => {
+                    // With local variable names, slots without a live variable are left out.
+                    let code = stop.method.code.as_ref().filter(|code| !code.local_variables.is_empty());
+                    for (index, value) in stop.stack_frame.locals.iter().enumerate() {
+                        let name = match code {
+                            Some(code) => match code.local_variable(index as u16, stop.pc) {
+                                Some(variable) => variable.name.clone(),
+                                None => continue
+                            },
+                            None => format!("local{}", index)
+                        };
+                        let _ = writeln!(self.output, "{} = {}", name, format_value(value));
+                    }
+                },
+                ("print", Some(_)) | ("p", Some(_)) => {
+                    let expression = line.trim()[command.len()..].trim();
+                    let result = evaluate(stop, expression)
+                        .map(|value| describe_value(&value))
+                        .unwrap_or_else(|message| message);
+                    let _ = writeln!(self.output, "{}", result);
+                },
+                ("backtrace", None) | ("bt", None) | ("where", None) => {
+                    let frames = stop.class_table.stacks.current_frames();
+                    for (depth, frame) in frames.iter().rev().enumerate() {
+                        let location = match frame.pc() {
+                            Some(pc) => describe(&frame.class, frame.method(), pc),
+                            None => format!("{}.{}{}", frame.class.class_name.replace('/', "."), frame.method().name, frame.method().descriptor)
+                        };
+                        let _ = writeln!(self.output, "#{} {}", depth, location);
+                    }
+                },
+                ("quit", None) | ("q", None) => return Outcome::Quit,
+                ("help", None) | ("h", None) | ("?", None) => {
+                    let _ = writeln!(self.output, "{}", HELP);
+                },
+                _ => {
+                    let _ = writeln!(self.output, "Unknown command: {}. Try help.", line.trim());
+                }
+            }
+        }
+    }
+
+}
+
+fn find_class(class_table: &ClassTable, class_name: &str) -> Result<Arc<RuntimeClass>, String> {
+    match class_table.find_class(class_name) {
+        Ok(Some(class)) => Ok(class),
+        Ok(None) => Err(format!("No class {}", class_name.replace('/', "."))),
+        Err(message) => Err(message)
+    }
+}
+
+// Whether any method of the class has code on the line.
+fn has_line(class: &RuntimeClass, line: u16) -> bool {
+    class.methods.iter().any(|method| {
+        method.code.as_ref().is_some_and(|code| code.line_numbers.iter().any(|entry| entry.line_number == line))
+    })
+}
+
+// Whether pc is the first instruction of the line in the method.
+fn starts_line(method: &RuntimeMethod, pc: u16, line: u16) -> bool {
+    method.code.as_ref().is_some_and(|code| {
+        code.line_numbers.iter().any(|entry| entry.start_pc == pc && entry.line_number == line)
+    })
+}
+
+// e.g. Debuggee.add(I)I pc 5, line 17
+fn describe(class: &RuntimeClass, method: &RuntimeMethod, pc: u16) -> String {
+    let mut description = format!("{}.{}{} pc {}", class.class_name.replace('/', "."), method.name, method.descriptor, pc);
+    if let Some(line) = method.code.as_ref().and_then(|code| code.line_number(pc)) {
+        description.push_str(&format!(", line {}", line));
+    }
+    description
+}
+
+// Evaluates a path of field reads, starting from a local variable, this or a static field.
+fn evaluate(stop: &Stop, expression: &str) -> Result<Value, String> {
+    let segments: Vec<&str> = expression.split('.').map(str::trim).collect();
+    let (mut value, rest) = root(stop, &segments)?;
+    let mut path = segments[..segments.len() - rest.len()].join(".");
+
+    for &segment in rest.iter() {
+        value = match value {
+            Value::ObjectRef(ref object) => field(object, segment).ok_or_else(|| format!("{} has no field {}", path, segment))?,
+            Value::ArrayRef(ref array) if segment == "length" => Value::Integer(array.borrow().len() as i32),
+            Value::Null => return Err(format!("{} is null", path)),
+            _ => return Err(format!("{} is not an object", path))
+        };
+        path.push('.');
+        path.push_str(segment);
+    }
+    Ok(value)
+}
+
+// The value the expression starts from, and the segments left to read as fields.
+fn root<'a, 'b>(stop: &Stop, segments: &'a [&'b str]) -> Result<(Value, &'a [&'b str]), String> {
+    let first = segments[0];
+    let code = stop.method.code.as_ref();
+    let locals = &stop.stack_frame.locals;
+
+    if first == "this" && !stop.method.is_static() {
+        return Ok((locals[0].clone(), &segments[1..]));
+    }
+    let named = (0..locals.len()).find(|&index| {
+        code.and_then(|code| code.local_variable(index as u16, stop.pc)).is_some_and(|variable| variable.name == first)
+    });
+    let numbered = first.strip_prefix("local").and_then(|index| index.parse::<usize>().ok()).filter(|&index| index < locals.len());
+    if let Some(index) = named.or(numbered) {
+        return Ok((locals[index].clone(), &segments[1..]));
+    }
+
+    // A field of this, a static field of the class being run, or one of a class named by the
+    // longest prefix.
+    if let (false, Some(Value::ObjectRef(this))) = (stop.method.is_static(), locals.first()) {
+        if let Some(value) = field(this, first) {
+            return Ok((value, &segments[1..]));
+        }
+    }
+    if let Some(value) = RuntimeClass::resolve_static(stop.class, first).and_then(|class| class.get_static(first)) {
+        return Ok((value, &segments[1..]));
+    }
+    for length in (1..segments.len()).rev() {
+        let class_name = segments[..length].join("/");
+        if let Some(class) = stop.class_table.get_class(&class_name) {
+            let name = segments[length];
+            let value = RuntimeClass::resolve_static(&class, name).and_then(|class| class.get_static(name))
+                .ok_or_else(|| format!("{} has no static field {}", class_name.replace('/', "."), name))?;
+            return Ok((value, &segments[length + 1..]));
+        }
+    }
+    Err(format!("No local variable or static field {}", first))
+}
+
+fn field(object: &Arc<HeapCell<Object>>, name: &str) -> Option<Value> {
+    let object = object.borrow();
+    let position = object.class().instance_fields.iter().rposition(|field| field.name == name)?;
+    Some(object.fields()[position].clone())
+}
+
+// A value on one line. Strings are quoted, other objects and arrays named by class and identity.
+pub fn format_value(value: &Value) -> String {
+    match *value {
+        Value::Long(value) => format!("{}L", value),
+        Value::Integer(value) => value.to_string(),
+        Value::Float(value) => format!("{}f", value),
+        Value::Double(value) => value.to_string(),
+        Value::Short(value) => value.to_string(),
+        Value::Byte(value) => value.to_string(),
+        Value::Character(value) => format!("'{}'", value),
+        Value::ObjectRef(ref object) => {
+            let object = object.borrow();
+            if object.class().class_name == string::STRING_CLASS_NAME {
+                format!("{:?}", string::to_rust_string(&object))
+            } else {
+                format!("{}@{:p}", object.class().class_name.replace('/', "."), &*object)
+            }
+        },
+        Value::ArrayRef(ref array) => format!("{:?}", *array.borrow()),
+        Value::Null => String::from("null")
+    }
+}
+
+// A value as print shows it: objects with their fields, and arrays with their elements.
+fn describe_value(value: &Value) -> String {
+    match *value {
+        Value::ObjectRef(ref object) if object.borrow().class().class_name != string::STRING_CLASS_NAME => {
+            let fields: Vec<String> = {
+                let object = object.borrow();
+                object.class().instance_fields.iter().zip(object.fields().iter())
+                    .map(|(field, value)| format!("{}: {}", field.name, format_value(value)))
+                    .collect()
+            };
+            format!("{} {{ {} }}", format_value(value), fields.join(", "))
+        },
+        Value::ArrayRef(ref array) => format!("{} {}", format_value(value), elements(&array.borrow())),
+        _ => format_value(value)
+    }
+}
+
+fn elements(array: &Array) -> String {
+    fn list<T, F: Fn(&T) -> String>(elements: &[T], format: F) -> String {
+        let formatted: Vec<String> = elements.iter().map(format).collect();
+        format!("[{}]", formatted.join(", "))
+    }
+
+    match array.elements() {
+        Elements::Byte(elements) => list(elements, |element| element.to_string()),
+        Elements::Character(elements) => list(elements, |&element| format!("{:?}", String::from_utf16_lossy(&[element]))),
+        Elements::Short(elements) => list(elements, |element| element.to_string()),
+        Elements::Integer(elements) => list(elements, |element| element.to_string()),
+        Elements::Long(elements) => list(elements, |element| element.to_string()),
+        Elements::Float(elements) => list(elements, |element| element.to_string()),
+        Elements::Double(elements) => list(elements, |element| element.to_string()),
+        Elements::Reference(elements) => list(elements, format_value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use class::reader;
+    use runtime::interpreter;
+    use runtime::bootstrap::io::{Console, SharedBuffer};
+    use std::io::Cursor;
+
+    #[test]
+    fn locations() {
+        assert_eq!(Location::parse("com.example.Main.run"), Some(Location::Pc(String::from("com/example/Main"), String::from("run"), 0)));
+        assert_eq!(Location::parse("Main.run@12"), Some(Location::Pc(String::from("Main"), String::from("run"), 12)));
+        assert_eq!(Location::parse("com.example.Main:17"), Some(Location::Line(String::from("com/example/Main"), 17)));
+        assert_eq!(Location::parse("Main.java:17"), Some(Location::File(String::from("Main.java"), 17)));
+        assert_eq!(Location::parse("Main"), None);
+        assert_eq!(Location::parse("Main.run@x"), None);
+        assert_eq!(Location::parse("Main:x"), None);
+    }
+
+    // Runs Debuggee.main under the debugger with the given commands and returns what it wrote.
+    fn debug(commands: &str) -> String {
+        let out = SharedBuffer::new();
+        let class_table = ClassTable::with_console(Console::new(Box::new(out.clone()), Box::new(SharedBuffer::new())));
+        class_table.set_compile_threshold(0);
+        let class = class_table.define_class(&reader::read_class_file(include_bytes!("../../fixtures/Debuggee.class")).unwrap()).unwrap();
+
+        let transcript = SharedBuffer::new();
+        class_table.debugger.enable(Box::new(Cursor::new(commands.as_bytes().to_vec())), Box::new(transcript.clone()));
+        class_table.debugger.add_breakpoint(Location::Pc(String::from("Debuggee"), String::from("main"), 0));
+
+        let main = class.get_declared_method("main", "([Ljava/lang/String;)V").unwrap();
+        let arguments = Value::ArrayRef(class_table.new_array("Ljava/lang/String;", 0));
+        interpreter::invoke_method(&class, main, vec![arguments], &class_table).unwrap();
+        class_table.console.flush();
+        assert_eq!(out.contents(), "10\n");
+        transcript.contents()
+    }
+
+    #[test]
+    fn breakpoints_and_inspection() {
+        let transcript = debug("break Debuggee:17\nbreakpoints\nc\nlocals\nprint this\nprint this.next\nprint created\nprint count\nbt\ndelete 2\nc\n");
+        let expected_start = "Breakpoint 1 (Debuggee.main@0) at Debuggee.main([Ljava/lang/String;)V pc 0, line 22\n\
+            > Breakpoint 2 at Debuggee:17\n\
+            > 1: Debuggee.main@0\n\
+            2: Debuggee:17\n\
+            > Breakpoint 2 (Debuggee:17) at Debuggee.add(I)I pc 5, line 17\n\
+            > this = Debuggee@";
+        assert!(transcript.starts_with(expected_start), "{}", transcript);
+        assert!(transcript.contains("\namount = 1\nbefore = 0\n"));
+        assert!(transcript.contains(" { count: 0, label: \"first\", next: Debuggee@"));
+        assert!(transcript.contains("\n> Debuggee@"));
+        assert!(transcript.contains(" { count: 0, label: \"second\", next: null }\n> 2\n> 0\n"));
+        assert!(transcript.contains("> #0 Debuggee.add(I)I pc 5, line 17\n#1 Debuggee.main([Ljava/lang/String;)V pc "));
+        assert!(transcript.ends_with("> > "));
+    }
+
+    #[test]
+    fn source_files_and_unknown_locations() {
+        let transcript = debug("break Debuggee.java:17\nbreak Debugee:17\nbreak Debugee.java:17\nbreak Debuggee.ad\n\
+            break Debuggee.add@3\nbreak Debuggee:20\nbreak Debuggee.java:4\nbreak Debuggee.add@5\nbreakpoints\ndelete 3\nc\ndelete 2\nc\n");
+        let expected = "Breakpoint 1 (Debuggee.main@0) at Debuggee.main([Ljava/lang/String;)V pc 0, line 22\n\
+            > Breakpoint 2 at Debuggee.java:17\n\
+            > No class Debugee\n\
+            > No class compiled from Debugee.java\n\
+            > No method Debuggee.ad\n\
+            > No instruction at Debuggee.add@3\n\
+            > No code at Debuggee:20\n\
+            > No code at Debuggee.java:4\n\
+            > Breakpoint 3 at Debuggee.add@5\n\
+            > 1: Debuggee.main@0\n\
+            2: Debuggee.java:17\n\
+            3: Debuggee.add@5\n\
+            > > Breakpoint 2 (Debuggee.java:17) at Debuggee.add(I)I pc 5, line 17\n\
+            > > ";
+        assert_eq!(transcript, expected);
+    }
+
+    #[test]
+    fn stepping() {
+        let transcript = debug("next\nnext\nstep\nstepi\nstack\nprint first.label\nprint nothing\nstep\nstep\nc\n");
+        let stops: Vec<&str> = transcript.lines()
+            .filter_map(|line| line.trim_start_matches("> ").strip_prefix("Stepped at "))
+            .collect();
+        assert_eq!(stops, vec![
+            "Debuggee.main([Ljava/lang/String;)V pc 10, line 23",
+            "Debuggee.main([Ljava/lang/String;)V pc 23, line 24",
+            "Debuggee.main([Ljava/lang/String;)V pc 25, line 25",
+            "Debuggee.main([Ljava/lang/String;)V pc 26, line 25",
+            "Debuggee.main([Ljava/lang/String;)V pc 32, line 26",
+            "Debuggee.add(I)I pc 0, line 16"
+        ]);
+        assert!(transcript.contains("> [0] 1\n"));
+        assert!(transcript.contains("> \"first\"\n> No local variable or static field nothing\n"));
+    }
+
+}
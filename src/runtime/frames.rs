@@ -0,0 +1,461 @@
+use runtime::class::RuntimeClass;
+use runtime::class::method::RuntimeMethod;
+use runtime::stack::StackFrame;
+use runtime::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::ptr;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicPtr, AtomicU32, AtomicUsize, Ordering};
+use std::thread::{self, ThreadId};
+
+// The Java frames each thread is running, so that they can be looked at from other threads, as
+// the sampling profiler does. The interpreter keeps its StackFrames on the native stack; this
+// records which method each frame runs and where it is, and where its StackFrame is.
+
+// A method being run, interpreted, compiled or native.
+pub struct Frame {
+    pub class: Arc<RuntimeClass>,
+    method: *const RuntimeMethod,
+    // The pc of the instruction being interpreted, or NO_PC while compiled or native code runs.
+    pc: AtomicU32,
+    // The locals and operands of an interpreted frame, or null.
+    stack_frame: AtomicPtr<StackFrame>
+}
+
+const NO_PC: u32 = u32::MAX;
+
+// The method is owned by the class, which the frame keeps alive.
+unsafe impl Send for Frame {}
+unsafe impl Sync for Frame {}
+
+impl Frame {
+
+    pub fn method(&self) -> &RuntimeMethod {
+        unsafe { &*self.method }
+    }
+
+    pub fn pc(&self) -> Option<u16> {
+        match self.pc.load(Ordering::Relaxed) {
+            NO_PC => None,
+            pc => Some(pc as u16)
+        }
+    }
+
+    pub fn set_pc(&self, pc: u16) {
+        self.pc.store(pc as u32, Ordering::Relaxed);
+    }
+
+    // The source line being run, if the class file has line numbers.
+    pub fn line_number(&self) -> Option<u16> {
+        self.method().code.as_ref()?.line_number(self.pc()?)
+    }
+
+    pub fn stack_trace_element(&self) -> StackTraceElement {
+        let method = self.method();
+        let line_number = if method.native.is_some() {
+            NATIVE_METHOD
+        } else {
+            self.line_number().map_or(-1, i32::from)
+        };
+        StackTraceElement {
+            class_name: self.class.class_name.replace('/', "."),
+            method_name: method.name.clone(),
+            file_name: self.class.source_file.clone(),
+            line_number
+        }
+    }
+
+    // Where the locals and operands of the frame are if it is interpreted, or null. The thread
+    // running the frame changes them as it goes, so they may only be looked at while that thread
+    // is stopped, as the JDWP agent stops threads.
+    pub fn stack_frame(&self) -> *const StackFrame {
+        self.stack_frame.load(Ordering::Acquire)
+    }
+
+}
+
+// The line number of native methods in stack traces, as in Java.
+pub const NATIVE_METHOD: i32 = -2;
+
+// A frame as stack traces show it, e.g. Counter.set(Counter.java:17).
+#[derive(Clone, Debug, PartialEq)]
+pub struct StackTraceElement {
+    // With dots, e.g. java.lang.String.
+    pub class_name: String,
+    pub method_name: String,
+    // From the SourceFile attribute.
+    pub file_name: Option<String>,
+    // The source line, -1 if it is unknown, or NATIVE_METHOD.
+    pub line_number: i32
+}
+
+impl fmt::Display for StackTraceElement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}(", self.class_name, self.method_name)?;
+        match (self.line_number, self.file_name.as_ref()) {
+            (NATIVE_METHOD, _) => write!(f, "Native Method)"),
+            (line_number, Some(file_name)) if line_number >= 0 => write!(f, "{}:{})", file_name, line_number),
+            (_, Some(file_name)) => write!(f, "{})", file_name),
+            (_, None) => write!(f, "Unknown Source)")
+        }
+    }
+}
+
+// The stack trace of frames, innermost first, as Throwable.getStackTrace returns it. Lambda
+// classes have no source, so like HotSpot's hidden classes their frames are left out.
+pub fn stack_trace(frames: &[Arc<Frame>]) -> Vec<StackTraceElement> {
+    frames.iter().rev()
+        .filter(|frame| !frame.class.is_lambda())
+        .map(|frame| frame.stack_trace_element())
+        .collect()
+}
+
+// The frames of one thread, outermost first, and the references Rust code on it holds.
+#[derive(Default)]
+struct ThreadStack {
+    frames: Vec<Arc<Frame>>,
+    // The references held in the open handle scopes, innermost last.
+    handles: Vec<Value>,
+    // Where the handles of each open scope begin, and how many frames there were when it opened.
+    scopes: Vec<(usize, usize)>
+}
+
+type Stack = Mutex<ThreadStack>;
+
+pub struct ThreadStacks {
+    // Distinguishes the class tables a thread has run in, for the cache of the current stack.
+    id: usize,
+    stacks: Mutex<HashMap<ThreadId, (String, Arc<Stack>)>>,
+    // The frames each thread had where an InterpreterError was raised, outermost first.
+    failures: Mutex<HashMap<ThreadId, Vec<Arc<Frame>>>>
+}
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    // The stack of the current thread, and the id of the ThreadStacks it is registered with.
+    static CURRENT: RefCell<Option<(usize, Arc<Stack>)>> = const { RefCell::new(None) };
+}
+
+impl ThreadStacks {
+
+    pub fn new() -> ThreadStacks {
+        ThreadStacks {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            stacks: Mutex::new(HashMap::new()),
+            failures: Mutex::new(HashMap::new())
+        }
+    }
+
+    fn current(&self) -> Arc<Stack> {
+        CURRENT.with(|current| {
+            let mut current = current.borrow_mut();
+            match *current {
+                Some((id, ref stack)) if id == self.id => stack.clone(),
+                _ => {
+                    let thread = thread::current();
+                    let name = String::from(thread.name().unwrap_or("main"));
+                    let stack = self.stacks.lock().unwrap()
+                        .entry(thread.id())
+                        .or_insert_with(|| (name, Arc::new(Mutex::new(ThreadStack::default()))))
+                        .1.clone();
+                    *current = Some((self.id, stack.clone()));
+                    stack
+                }
+            }
+        })
+    }
+
+    // Pushes a frame for the method on the stack of the current thread, which is popped when
+    // the guard is dropped.
+    pub fn enter(&self, class: &Arc<RuntimeClass>, method: &RuntimeMethod) -> FrameGuard {
+        let stack = self.current();
+        let frame = Arc::new(Frame {
+            class: class.clone(),
+            method,
+            pc: AtomicU32::new(NO_PC),
+            stack_frame: AtomicPtr::new(ptr::null_mut())
+        });
+        stack.lock().unwrap().frames.push(frame.clone());
+        FrameGuard { stack, frame }
+    }
+
+    // Opens a handle scope on the current thread. Rust code holding references across an
+    // allocation must keep them in a scope, as the collector only knows of the references in the
+    // frames, the statics and the tables. Allocations made while the scope is the innermost one
+    // and no frame has been entered since are kept in it too, as are the results of methods
+    // invoked then, so a native method only needs the scope the interpreter opens for it.
+    pub fn scope(&self) -> HandleScope {
+        let stack = self.current();
+        {
+            let mut stack = stack.lock().unwrap();
+            let scope = (stack.handles.len(), stack.frames.len());
+            stack.scopes.push(scope);
+        }
+        HandleScope { stack }
+    }
+
+    // Keeps the reference in the innermost handle scope of the current thread, if Rust code that
+    // opened it is the one running.
+    pub fn keep(&self, value: &Value) {
+        if !is_handle(value) {
+            return;
+        }
+        let stack = self.current();
+        let mut stack = stack.lock().unwrap();
+        if stack.scopes.last().is_some_and(|&(_, depth)| depth == stack.frames.len()) {
+            stack.handles.push(value.clone());
+        }
+    }
+
+    // Visits the references held in the handle scopes of every thread.
+    pub fn visit_handles(&self, visit: &mut dyn FnMut(&Value)) {
+        let stacks: Vec<Arc<Stack>> = self.stacks.lock().unwrap().values().map(|(_, stack)| stack.clone()).collect();
+        for stack in stacks {
+            stack.lock().unwrap().handles.iter().for_each(&mut *visit);
+        }
+    }
+
+    // Forgets the current thread, once it has finished.
+    pub fn remove_current(&self) {
+        self.stacks.lock().unwrap().remove(&thread::current().id());
+        self.failures.lock().unwrap().remove(&thread::current().id());
+        CURRENT.with(|current| *current.borrow_mut() = None);
+    }
+
+    // The frames of the current thread, outermost first.
+    pub fn current_frames(&self) -> Vec<Arc<Frame>> {
+        self.current().lock().unwrap().frames.clone()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.current().lock().unwrap().frames.len()
+    }
+
+    // Records the frames of the current thread as those where an InterpreterError was raised.
+    // The interpreter records them in every frame the error passes on its way out, so frames
+    // recorded deeper down during the same unwinding are kept: they still begin with the current
+    // ones, which cannot have moved on since.
+    pub fn record_failure(&self) {
+        let frames = self.current_frames();
+        let mut failures = self.failures.lock().unwrap();
+        let failure = failures.entry(thread::current().id()).or_default();
+        let unwinding = failure.len() > frames.len() &&
+            failure.iter().zip(frames.iter()).all(|(recorded, frame)| Arc::ptr_eq(recorded, frame));
+        if !unwinding {
+            *failure = frames;
+        }
+    }
+
+    // The stack trace where the InterpreterError the current thread failed with was raised.
+    pub fn take_failure(&self) -> Vec<StackTraceElement> {
+        let frames = self.failures.lock().unwrap().remove(&thread::current().id()).unwrap_or_default();
+        stack_trace(&frames)
+    }
+
+    // Adds the current thread before it runs any Java code, so that it is already listed.
+    pub fn register_current(&self) {
+        self.current();
+    }
+
+    // The id and name of every thread that has run Java code.
+    pub fn threads(&self) -> Vec<(ThreadId, String)> {
+        self.stacks.lock().unwrap().iter().map(|(&thread, (name, _))| (thread, name.clone())).collect()
+    }
+
+    // The frames of a thread, outermost first, or None if it has finished or never ran Java code.
+    pub fn frames(&self, thread: ThreadId) -> Option<Vec<Arc<Frame>>> {
+        let stack = self.stacks.lock().unwrap().get(&thread)?.1.clone();
+        let frames = stack.lock().unwrap().frames.clone();
+        Some(frames)
+    }
+
+    // The name and frames, outermost first, of every thread that has run Java code.
+    pub fn snapshot(&self) -> Vec<(String, Vec<Arc<Frame>>)> {
+        let stacks: Vec<(String, Arc<Stack>)> = self.stacks.lock().unwrap().values().cloned().collect();
+        stacks.into_iter()
+            .map(|(name, stack)| {
+                let frames = stack.lock().unwrap().frames.clone();
+                (name, frames)
+            })
+            .collect()
+    }
+
+}
+
+impl Default for ThreadStacks {
+    fn default() -> ThreadStacks {
+        ThreadStacks::new()
+    }
+}
+
+pub struct FrameGuard {
+    stack: Arc<Stack>,
+    frame: Arc<Frame>
+}
+
+impl FrameGuard {
+
+    pub fn set_pc(&self, pc: u16) {
+        self.frame.set_pc(pc);
+    }
+
+    // Records where the interpreter keeps the locals and operands of the frame. They must stay
+    // there until the guard is dropped.
+    pub fn set_stack_frame(&self, stack_frame: &StackFrame) {
+        self.frame.stack_frame.store(stack_frame as *const StackFrame as *mut StackFrame, Ordering::Release);
+    }
+
+}
+
+impl Drop for FrameGuard {
+    fn drop(&mut self) {
+        let mut stack = self.stack.lock().unwrap();
+        if let Some(position) = stack.frames.iter().rposition(|frame| Arc::ptr_eq(frame, &self.frame)) {
+            stack.frames.remove(position);
+        }
+    }
+}
+
+// Keeps references alive for Rust code until it is dropped, see ThreadStacks::scope.
+pub struct HandleScope {
+    stack: Arc<Stack>
+}
+
+impl HandleScope {
+
+    pub fn hold(&self, value: &Value) {
+        if is_handle(value) {
+            self.stack.lock().unwrap().handles.push(value.clone());
+        }
+    }
+
+}
+
+// Null and the primitives need no keeping alive.
+fn is_handle(value: &Value) -> bool {
+    matches!(value, Value::ObjectRef(_) | Value::ArrayRef(_))
+}
+
+impl Drop for HandleScope {
+    fn drop(&mut self) {
+        let mut stack = self.stack.lock().unwrap();
+        if let Some((handles, _)) = stack.scopes.pop() {
+            stack.handles.truncate(handles);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use runtime::bootstrap::tests::class_table;
+    use class::reader;
+    use class::method::ACC_PUBLIC;
+    use runtime::bootstrap::ClassBuilder;
+    use runtime::class::ClassTable;
+    use runtime::interpreter::{self, InterpreterError, InvokeResult};
+
+    #[test]
+    fn formats_stack_trace_elements() {
+        let element = |file_name: Option<&str>, line_number| StackTraceElement {
+            class_name: String::from("Counter"),
+            method_name: String::from("set"),
+            file_name: file_name.map(String::from),
+            line_number
+        };
+        assert_eq!(element(Some("Counter.java"), 17).to_string(), "Counter.set(Counter.java:17)");
+        assert_eq!(element(Some("Counter.java"), -1).to_string(), "Counter.set(Counter.java)");
+        assert_eq!(element(None, 17).to_string(), "Counter.set(Unknown Source)");
+        assert_eq!(element(None, NATIVE_METHOD).to_string(), "Counter.set(Native Method)");
+    }
+
+    // The frames an error was raised in are kept while it unwinds them.
+    #[test]
+    fn records_failures() {
+        let class_table = ClassTable::new();
+        let class = class_table.define_class(&reader::read_class_file(include_bytes!("../../fixtures/Jit.class")).unwrap()).unwrap();
+        let code = class.get_declared_method("sumTo", "(I)I").unwrap().code.as_ref().unwrap();
+
+        let outer = class_table.stacks.enter(&class, class.get_declared_method("sumTo", "(I)I").unwrap());
+        outer.set_pc(0);
+        let inner = class_table.stacks.enter(&class, class.get_declared_method("fib", "(I)I").unwrap());
+        class_table.stacks.record_failure();
+        drop(inner);
+        class_table.stacks.record_failure();
+
+        let line = code.line_number(0).unwrap() as i32;
+        let expected = vec![
+            StackTraceElement { class_name: String::from("Jit"), method_name: String::from("fib"), file_name: Some(String::from("Jit.java")), line_number: -1 },
+            StackTraceElement { class_name: String::from("Jit"), method_name: String::from("sumTo"), file_name: Some(String::from("Jit.java")), line_number: line }
+        ];
+        assert_eq!(class_table.stacks.take_failure(), expected);
+        assert_eq!(class_table.stacks.take_failure(), Vec::new());
+
+        // A later failure in other frames replaces an earlier one.
+        class_table.stacks.record_failure();
+        drop(outer);
+        let _other = class_table.stacks.enter(&class, class.get_declared_method("fib", "(I)I").unwrap());
+        class_table.stacks.record_failure();
+        assert_eq!(class_table.stacks.take_failure()[0].method_name, "fib");
+    }
+
+    #[test]
+    fn leaves_out_lambda_frames() {
+        fn run(_class_table: &ClassTable, _arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+            Ok(InvokeResult::Void)
+        }
+
+        let class_table = ClassTable::new();
+        let class = class_table.define_class(&reader::read_class_file(include_bytes!("../../fixtures/Jit.class")).unwrap()).unwrap();
+        let lambda = ClassBuilder::new("Jit$$Lambda$1").native("run", "()V", ACC_PUBLIC, run).define(&class_table);
+
+        let _outer = class_table.stacks.enter(&class, class.get_declared_method("sumTo", "(I)I").unwrap());
+        let _lambda = class_table.stacks.enter(&lambda, lambda.get_declared_method("run", "()V").unwrap());
+        let _inner = class_table.stacks.enter(&class, class.get_declared_method("fib", "(I)I").unwrap());
+        let methods: Vec<String> = stack_trace(&class_table.stacks.current_frames()).into_iter()
+            .map(|element| element.method_name)
+            .collect();
+        assert_eq!(methods, vec!["fib", "sumTo"]);
+    }
+
+    // Stack traces have the source file and line of every frame, and causes leave out the frames
+    // they have in common with the exception they caused.
+    #[test]
+    fn stack_traces() {
+        let (class_table, out, err) = class_table();
+        let mut class = None;
+        for bytes in [
+            &include_bytes!("../../fixtures/StackTraces$Counter.class")[..],
+            &include_bytes!("../../fixtures/StackTraces$Failure.class")[..],
+            &include_bytes!("../../fixtures/StackTraces$Quiet.class")[..],
+            &include_bytes!("../../fixtures/StackTraces.class")[..]
+        ].iter() {
+            class = Some(class_table.define_class(&reader::read_class_file(bytes).unwrap()).unwrap());
+        }
+        let class = class.unwrap();
+        let main = class.get_declared_method("main", "([Ljava/lang/String;)V").unwrap();
+        let arguments = vec![Value::ArrayRef(class_table.new_array("Ljava/lang/String;", 0))];
+
+        let exception = match interpreter::invoke_method(&class, main, arguments, &class_table).unwrap() {
+            InvokeResult::Exception(exception) => exception,
+            x => panic!("main did not throw: {:?}", x)
+        };
+        interpreter::invoke_virtual(&class_table, exception, "printStackTrace", "()V", Vec::new()).unwrap();
+
+        assert_eq!(out.contents(), include_str!("../../fixtures/StackTraces.out"));
+        let expected_end = "StackTraces$Failure: update failed\n\
+            \tat StackTraces.update(StackTraces.java:37)\n\
+            \tat StackTraces.main(StackTraces.java:53)\n\
+            Caused by: java.lang.ArrayIndexOutOfBoundsException: Index -1 out of bounds for length 2\n\
+            \tat StackTraces$Counter.set(StackTraces.java:10)\n\
+            \tat StackTraces.update(StackTraces.java:35)\n\
+            \t... 1 more\n";
+        assert!(err.contents().ends_with(expected_end), "{}", err.contents());
+        assert!(err.contents().starts_with("StackTraces$Failure: update failed\n\tat StackTraces.update(StackTraces.java:37)\n"));
+    }
+
+}
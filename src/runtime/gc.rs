@@ -1,3 +0,0 @@
-fn gc() {
-
-}
\ No newline at end of file
@@ -0,0 +1,1712 @@
+use runtime::{Value, HeapCell, Object, Array, ArrayElements, Elements, ElementsMut, primitive_size};
+use runtime::class::RuntimeClass;
+use runtime::monitor::Monitor;
+use std::alloc::{self, Layout};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::mem;
+use std::ptr::{self, NonNull};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+mod space;
+
+use self::space::{CardTable, Space, ALIGNMENT};
+
+// Every object and array is allocated by the heap, which keeps a handle to it until a collection
+// finds it unreachable. The collector traces from the roots: the locals and operand stacks of the
+// interpreted frames of every thread, the statics of the loaded classes, and the tables of the
+// virtual machine, such as interned strings and class mirrors. Everything reachable from a root
+// is live.
+//
+// Rust code holds handles too: the arguments and locals of native methods, exceptions on their
+// way out of the interpreter, and the values the launcher passes to main. Rust code that holds a
+// handle across an allocation keeps it in a handle scope of its thread, which is a root as well,
+// see frames::ThreadStacks::scope.
+//
+// The handles stay put, but the fields of objects and the components of arrays are stored in
+// spaces the heap moves them between, see space.rs. Most objects die young, so the heap is split
+// into two generations:
+//
+// - New objects are allocated in eden by bumping a pointer. A minor collection copies the live
+//   young objects to a fresh survivor space, or promotes them to the old generation once they
+//   have survived TENURING_THRESHOLD minor collections, and then empties eden and the previous
+//   survivor space all at once. Dead objects are never looked at beyond dropping their fields.
+// - The old generation is a single space, collected by a full collection once the heap has grown
+//   enough. It marks the whole heap and then compacts the old space, sliding the live objects
+//   towards its start in address order, and promotes every live young object.
+//
+// A minor collection does not trace the old generation, so stores of references into old objects
+// go through a write barrier, which dirties the card of the slot in a card table. The old objects
+// on dirty cards, and the statics of classes whose statics were stored into, are roots of the next
+// minor collection, which cleans the cards again unless they still refer to young objects. Native
+// methods store into fields without the write barrier, so the old objects that were borrowed
+// mutably since the last collection are roots of the next minor collection too.
+//
+// An object that is borrowed while the heap is collected, by the collecting thread or by a thread
+// that does not run Java code such as the JDWP agent, is pinned: its fields are not moved, and
+// the space they are in is kept until it has been moved out.
+
+// The heap limit if -Xmx is not given.
+pub const DEFAULT_MAX_SIZE: usize = 1024 * 1024 * 1024;
+
+// The message of the OutOfMemoryError thrown when the heap limit is reached, as in HotSpot.
+pub const OUT_OF_MEMORY: &str = "Java heap space";
+
+// The size of the young generation if -Xmn is not given. It is at most a third of the heap.
+pub const DEFAULT_YOUNG_SIZE: usize = 8 * 1024 * 1024;
+
+// The smallest amount of allocation between two full collections.
+const MIN_COLLECTION_INTERVAL: usize = 16 * 1024 * 1024;
+
+// The number of minor collections an object survives before it is promoted to the old generation.
+const TENURING_THRESHOLD: u32 = 2;
+
+// Where the collector finds the references into the heap from outside of it.
+pub trait Roots {
+    // The loaded classes, whose statics are roots.
+    fn classes(&self) -> Vec<Arc<RuntimeClass>>;
+
+    // Calls visit with every other root: the locals and operand stacks of interpreted frames,
+    // the handle scopes of Rust code and the references held by the tables of the virtual
+    // machine. The collector runs while every other thread that runs Java code is stopped at a
+    // safepoint, so their frames stay put.
+    fn visit(&self, visit: &mut dyn FnMut(&Value));
+}
+
+// Explicit roots, for using a heap on its own.
+impl Roots for Vec<Value> {
+    fn classes(&self) -> Vec<Arc<RuntimeClass>> {
+        Vec::new()
+    }
+
+    fn visit(&self, visit: &mut dyn FnMut(&Value)) {
+        self.iter().for_each(visit);
+    }
+}
+
+// The fields of an object or the components of an array, as the collector moves them.
+trait Payload {
+    fn memory(&self) -> *mut u8;
+    fn set_memory(&mut self, memory: *mut u8);
+    // In bytes.
+    fn payload_size(&self) -> usize;
+    fn references(&self) -> &[Value];
+    fn references_mut(&mut self) -> &mut [Value];
+    fn is_detached(&self) -> bool;
+    fn set_detached(&mut self, detached: bool);
+}
+
+impl Payload for Object {
+    fn memory(&self) -> *mut u8 {
+        self.fields as *mut u8
+    }
+
+    fn set_memory(&mut self, memory: *mut u8) {
+        self.fields = memory as *mut Value;
+    }
+
+    fn payload_size(&self) -> usize {
+        self.length * mem::size_of::<Value>()
+    }
+
+    fn references(&self) -> &[Value] {
+        self.fields()
+    }
+
+    fn references_mut(&mut self) -> &mut [Value] {
+        self.fields_mut()
+    }
+
+    fn is_detached(&self) -> bool {
+        self.detached
+    }
+
+    fn set_detached(&mut self, detached: bool) {
+        self.detached = detached;
+    }
+}
+
+impl Payload for Array {
+    fn memory(&self) -> *mut u8 {
+        self.elements
+    }
+
+    fn set_memory(&mut self, memory: *mut u8) {
+        self.elements = memory;
+    }
+
+    fn payload_size(&self) -> usize {
+        self.length * component_size(&self.component_type)
+    }
+
+    fn references(&self) -> &[Value] {
+        match self.elements() {
+            Elements::Reference(elements) => elements,
+            _ => &[]
+        }
+    }
+
+    fn references_mut(&mut self) -> &mut [Value] {
+        match self.elements_mut() {
+            ElementsMut::Reference(elements) => elements,
+            _ => &mut []
+        }
+    }
+
+    fn is_detached(&self) -> bool {
+        self.detached
+    }
+
+    fn set_detached(&mut self, detached: bool) {
+        self.detached = detached;
+    }
+}
+
+// The layout of payloads allocated outside of the spaces of the heap.
+fn detached_layout(size: usize) -> Layout {
+    Layout::from_size_align(space::aligned(size), ALIGNMENT).unwrap()
+}
+
+fn allocate_detached(size: usize) -> *mut u8 {
+    let layout = detached_layout(size);
+    let memory = unsafe { alloc::alloc(layout) };
+    if memory.is_null() {
+        alloc::handle_alloc_error(layout);
+    }
+    memory
+}
+
+// Drops the references in a payload, and frees it if it is detached. The payload is left empty.
+fn free<T: Payload>(contents: &mut T) {
+    let size = contents.payload_size();
+    unsafe { ptr::drop_in_place(contents.references_mut()) };
+    if contents.is_detached() {
+        unsafe { alloc::dealloc(contents.memory(), detached_layout(size)) };
+    }
+    contents.set_memory(NonNull::<Value>::dangling().as_ptr() as *mut u8);
+    contents.set_detached(false);
+}
+
+// Moves the payload of a cell to the memory place returns for its size, unless the cell is
+// borrowed, its payload is detached or place returns None. Returns whether it was moved.
+fn relocate<T: Payload, F: FnOnce(usize) -> Option<*mut u8>>(cell: &HeapCell<T>, place: F) -> bool {
+    let mut contents = match cell.try_borrow_mut() {
+        Some(contents) => contents,
+        None => return false
+    };
+    if contents.is_detached() {
+        return false;
+    }
+
+    let size = contents.payload_size();
+    match place(size) {
+        Some(memory) => {
+            // The old and new places may overlap when the old space is compacted.
+            unsafe { ptr::copy(contents.memory(), memory, size) };
+            contents.set_memory(memory);
+            true
+        },
+        None => false
+    }
+}
+
+// Moves a payload out of the heap, for an object that outlives it.
+fn detach<T: Payload>(contents: &mut T) {
+    if contents.is_detached() {
+        return;
+    }
+    let size = contents.payload_size();
+    let memory = allocate_detached(size);
+    unsafe { ptr::copy_nonoverlapping(contents.memory(), memory, size) };
+    contents.set_memory(memory);
+    contents.set_detached(true);
+}
+
+impl Drop for Object {
+    fn drop(&mut self) {
+        if self.detached {
+            free(self);
+        }
+    }
+}
+
+impl Drop for Array {
+    fn drop(&mut self) {
+        if self.detached {
+            free(self);
+        }
+    }
+}
+
+// Moves the values into memory, which must have room for them.
+unsafe fn move_into<T>(mut values: Vec<T>, memory: *mut u8) {
+    ptr::copy_nonoverlapping(values.as_ptr(), memory as *mut T, values.len());
+    values.set_len(0);
+}
+
+impl Object {
+
+    // An object with its fields set to their default values, stored in memory.
+    unsafe fn create(class: &Arc<RuntimeClass>, memory: *mut u8) -> Object {
+        let fields = class.default_fields();
+        let length = fields.len();
+        move_into(fields, memory);
+        Object { class: class.clone(), fields: memory as *mut Value, length, detached: false }
+    }
+
+    // A shallow copy, as made by Object.clone, stored in memory.
+    unsafe fn copy(&self, memory: *mut u8) -> Object {
+        move_into(self.fields().to_vec(), memory);
+        Object { class: self.class.clone(), fields: memory as *mut Value, length: self.length, detached: false }
+    }
+
+}
+
+impl Array {
+
+    // An array whose components have the given field descriptor, e.g. "I" for an int[] or
+    // "Ljava/lang/String;" for a String[], set to their default values and stored in memory.
+    unsafe fn create(component_type: &str, length: usize, memory: *mut u8) -> Array {
+        let array = Array { component_type: String::from(component_type), elements: memory, length, detached: false };
+        if Value::default_for(component_type).is_reference() {
+            for index in 0..length {
+                ptr::write((memory as *mut Value).add(index), Value::Null);
+            }
+        } else {
+            // Zero bits are 0 and 0.0 in every primitive type.
+            ptr::write_bytes(memory, 0, array.payload_size());
+        }
+        array
+    }
+
+    unsafe fn from_elements(component_type: &str, elements: ArrayElements, memory: *mut u8) -> Array {
+        let length = elements.len();
+        match elements {
+            ArrayElements::Byte(elements) => move_into(elements, memory),
+            ArrayElements::Character(elements) => move_into(elements, memory),
+            ArrayElements::Short(elements) => move_into(elements, memory),
+            ArrayElements::Integer(elements) => move_into(elements, memory),
+            ArrayElements::Long(elements) => move_into(elements, memory),
+            ArrayElements::Float(elements) => move_into(elements, memory),
+            ArrayElements::Double(elements) => move_into(elements, memory),
+            ArrayElements::Reference(elements) => move_into(elements, memory)
+        }
+        Array { component_type: String::from(component_type), elements: memory, length, detached: false }
+    }
+
+    unsafe fn copy(&self, memory: *mut u8) -> Array {
+        match self.elements() {
+            Elements::Reference(elements) => move_into(elements.to_vec(), memory),
+            _ => ptr::copy_nonoverlapping(self.elements, memory, self.payload_size())
+        }
+        Array { component_type: self.component_type.clone(), elements: memory, length: self.length, detached: false }
+    }
+
+}
+
+// A handle the heap keeps to every object and array it allocated.
+enum Cell {
+    Object(Arc<HeapCell<Object>>),
+    Array(Arc<HeapCell<Array>>)
+}
+
+struct Young {
+    cell: Cell,
+    // The number of minor collections the object has survived.
+    age: u32,
+    // Where the payload is. Collections run while the payload of a pinned object is borrowed, so
+    // the heap keeps track of it rather than reading it from the object.
+    memory: usize
+}
+
+struct Old {
+    cell: Cell,
+    // Where the payload is in the old space, and the bytes it takes there.
+    memory: usize,
+    size: usize
+}
+
+#[derive(Default)]
+struct Generations {
+    // Where new objects are allocated.
+    eden: Option<Space>,
+    // Where the young objects that survived the last minor collection are.
+    survivors: Option<Space>,
+    // Spaces that could not be emptied, as they hold pinned objects.
+    retained: Vec<Space>,
+    young: Vec<Young>,
+    old_space: Option<Space>,
+    // In the order of their payloads in the old space.
+    old: Vec<Old>
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Collection {
+    Minor,
+    Full
+}
+
+// Where an allocation went.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Placement {
+    Young,
+    Old,
+    // Outside of the spaces of the heap, if they are full. The object is kept in the young
+    // generation, and never moved.
+    Detached
+}
+
+impl Cell {
+
+    fn address(&self) -> usize {
+        match self {
+            Cell::Object(object) => Arc::as_ptr(object) as usize,
+            Cell::Array(array) => Arc::as_ptr(array) as usize
+        }
+    }
+
+    fn strong_count(&self) -> usize {
+        match self {
+            Cell::Object(object) => Arc::strong_count(object),
+            Cell::Array(array) => Arc::strong_count(array)
+        }
+    }
+
+    fn value(&self) -> Value {
+        match self {
+            Cell::Object(object) => Value::ObjectRef(object.clone()),
+            Cell::Array(array) => Value::ArrayRef(array.clone())
+        }
+    }
+
+    // Whether the cell was borrowed mutably since this was last asked.
+    fn take_written(&self) -> bool {
+        let written = match self {
+            Cell::Object(object) => &object.written,
+            Cell::Array(array) => &array.written
+        };
+        written.load(Ordering::Relaxed) && written.swap(false, Ordering::Relaxed)
+    }
+
+    // The bytes the object takes, as the heap accounts for them.
+    fn size(&self) -> usize {
+        match self {
+            Cell::Object(object) => object_size(object.borrow().length),
+            Cell::Array(array) => {
+                let array = array.borrow();
+                array_size(array.component_type(), array.len())
+            }
+        }
+    }
+
+    // Calls f with every reference in the fields or components of the cell, returning false
+    // without calling it if the cell is being written to. The collecting thread may be the one
+    // writing, so it cannot wait.
+    fn references<F: FnMut(&Value)>(&self, f: F) -> bool {
+        fn visit<T: Payload, F: FnMut(&Value)>(cell: &HeapCell<T>, f: F) -> bool {
+            match cell.contents.try_read() {
+                Ok(contents) => {
+                    contents.references().iter().filter(|value| value.is_reference()).for_each(f);
+                    true
+                },
+                Err(_) => false
+            }
+        }
+
+        match self {
+            Cell::Object(object) => visit(object, f),
+            Cell::Array(array) => visit(array, f)
+        }
+    }
+
+    // Dirties the cards of the slots of an old cell that refer to young objects. If the cell
+    // cannot be read, all the cards of its payload are dirtied.
+    fn remember(&self, cards: &CardTable, young: &HashSet<usize>, memory: usize, size: usize) {
+        fn remember<T: Payload>(cell: &HeapCell<T>, cards: &CardTable, young: &HashSet<usize>, memory: usize, size: usize) {
+            match cell.contents.try_read() {
+                Ok(contents) => {
+                    for slot in contents.references().iter() {
+                        if handle_address(slot).is_some_and(|address| young.contains(&address)) {
+                            cards.dirty(slot as *const Value as *const u8);
+                        }
+                    }
+                },
+                Err(_) => cards.dirty_range(memory as *const u8, size)
+            }
+        }
+
+        match self {
+            Cell::Object(object) => remember(object, cards, young, memory, size),
+            Cell::Array(array) => remember(array, cards, young, memory, size)
+        }
+    }
+
+    // Frees the payload of a dead cell.
+    fn free(&self) {
+        match self {
+            Cell::Object(object) => free(&mut *object.borrow_mut()),
+            Cell::Array(array) => free(&mut *array.borrow_mut())
+        }
+    }
+
+    fn relocate<F: FnOnce(usize) -> Option<*mut u8>>(&self, place: F) -> bool {
+        match self {
+            Cell::Object(object) => relocate(object, place),
+            Cell::Array(array) => relocate(array, place)
+        }
+    }
+
+    fn detach(&self) {
+        match self {
+            Cell::Object(object) => detach(&mut *object.borrow_mut()),
+            Cell::Array(array) => detach(&mut *array.borrow_mut())
+        }
+    }
+
+}
+
+// The address of the handle a value refers to, if it is a reference.
+fn handle_address(value: &Value) -> Option<usize> {
+    match value {
+        Value::ObjectRef(object) => Some(Arc::as_ptr(object) as usize),
+        Value::ArrayRef(array) => Some(Arc::as_ptr(array) as usize),
+        _ => None
+    }
+}
+
+// The approximate number of bytes taken by an object with the given number of fields.
+pub fn object_size(field_count: usize) -> usize {
+    mem::size_of::<HeapCell<Object>>() + field_count * mem::size_of::<Value>()
+}
+
+fn component_size(component_type: &str) -> usize {
+    primitive_size(component_type).unwrap_or(mem::size_of::<Value>())
+}
+
+// The approximate number of bytes taken by an array, saturating for absurd lengths.
+pub fn array_size(component_type: &str, length: usize) -> usize {
+    length.saturating_mul(component_size(component_type)).saturating_add(mem::size_of::<HeapCell<Array>>())
+}
+
+#[derive(Debug, Default)]
+struct SafepointState {
+    // Threads running Java code, which must stop before the heap is collected.
+    attached: HashSet<ThreadId>,
+    // Attached threads that are stopped, or blocked where they cannot touch the heap.
+    stopped: HashSet<ThreadId>,
+    collecting: bool
+}
+
+impl SafepointState {
+
+    fn others_running(&self, current: ThreadId) -> bool {
+        self.attached.iter().any(|thread| *thread != current && !self.stopped.contains(thread))
+    }
+
+}
+
+// The collected statistics of a heap.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct HeapUsage {
+    pub used: usize,
+    pub max: usize,
+    // The number of full collections.
+    pub collections: usize,
+    pub minor_collections: usize,
+    // Bytes promoted to the old generation.
+    pub promoted: usize,
+    pub pause_time: Duration
+}
+
+pub struct Heap {
+    generations: Mutex<Generations>,
+    // The cards of the old space, once it is reserved.
+    cards: OnceLock<CardTable>,
+    // Bytes used by the objects that were live after the last collection, plus the bytes
+    // allocated since.
+    used: AtomicUsize,
+    // Bytes used by the old generation, including objects that died since the last full
+    // collection.
+    old_used: AtomicUsize,
+    // Bytes allocated in the young generation since the last collection.
+    young_used: AtomicUsize,
+    max_size: AtomicUsize,
+    young_size: AtomicUsize,
+    next_collection: AtomicUsize,
+    collections: AtomicUsize,
+    minor_collections: AtomicUsize,
+    promoted: AtomicUsize,
+    pause_time: Mutex<Duration>,
+    safepoint: Mutex<SafepointState>,
+    safepoint_changed: Condvar,
+    // Set while a collection is pending, so that polling is cheap the rest of the time.
+    collecting: AtomicBool,
+    // Where collections are logged, as with -Xlog:gc.
+    log: Mutex<Option<Box<dyn Write + Send>>>,
+    started: Instant
+}
+
+impl Heap {
+
+    pub fn new() -> Heap {
+        Heap {
+            generations: Mutex::new(Generations::default()),
+            cards: OnceLock::new(),
+            used: AtomicUsize::new(0),
+            old_used: AtomicUsize::new(0),
+            young_used: AtomicUsize::new(0),
+            max_size: AtomicUsize::new(DEFAULT_MAX_SIZE),
+            young_size: AtomicUsize::new(DEFAULT_YOUNG_SIZE),
+            next_collection: AtomicUsize::new(MIN_COLLECTION_INTERVAL),
+            collections: AtomicUsize::new(0),
+            minor_collections: AtomicUsize::new(0),
+            promoted: AtomicUsize::new(0),
+            pause_time: Mutex::new(Duration::default()),
+            safepoint: Mutex::new(SafepointState::default()),
+            safepoint_changed: Condvar::new(),
+            collecting: AtomicBool::new(false),
+            log: Mutex::new(None),
+            started: Instant::now()
+        }
+    }
+
+    // Sets the heap limit, as given by -Xmx.
+    pub fn set_max_size(&self, max_size: usize) {
+        self.max_size.store(max_size, Ordering::SeqCst);
+        let next_collection = self.next_collection.load(Ordering::SeqCst).min(max_size);
+        self.next_collection.store(next_collection, Ordering::SeqCst);
+    }
+
+    // Sets the size of the young generation, as given by -Xmn.
+    pub fn set_young_size(&self, young_size: usize) {
+        self.young_size.store(young_size, Ordering::SeqCst);
+    }
+
+    // Logs every collection to the given writer, as with -Xlog:gc.
+    pub fn set_log(&self, log: Box<dyn Write + Send>) {
+        *self.log.lock().unwrap() = Some(log);
+    }
+
+    pub fn usage(&self) -> HeapUsage {
+        HeapUsage {
+            used: self.used.load(Ordering::SeqCst),
+            max: self.max_size.load(Ordering::SeqCst),
+            collections: self.collections.load(Ordering::SeqCst),
+            minor_collections: self.minor_collections.load(Ordering::SeqCst),
+            promoted: self.promoted.load(Ordering::SeqCst),
+            pause_time: *self.pause_time.lock().unwrap()
+        }
+    }
+
+    fn eden_size(&self) -> usize {
+        self.young_size.load(Ordering::SeqCst).min(self.max_size.load(Ordering::SeqCst) / 3)
+    }
+
+    // Makes room for an allocation of the given size, collecting the young generation when it is
+    // full and the whole heap when it has grown enough since the last full collection. Returns
+    // false if the allocation would exceed the heap limit even after a full collection, in which
+    // case the interpreter throws OutOfMemoryError instead of allocating.
+    pub fn reserve(&self, roots: &dyn Roots, size: usize) -> bool {
+        self.poll();
+
+        let max_size = self.max_size.load(Ordering::SeqCst);
+        let young_size = self.young_size.load(Ordering::SeqCst).min(max_size / 3);
+        let young_used = self.young_used.load(Ordering::SeqCst);
+        if young_used > 0 && young_used.saturating_add(size) > young_size {
+            self.collect_minor(roots);
+        }
+
+        let mut collected = false;
+        if self.used.load(Ordering::SeqCst).saturating_add(size) > self.next_collection.load(Ordering::SeqCst) {
+            collected = self.collect(roots);
+        }
+        // If another thread was already collecting, its collection may have been a minor one.
+        if self.used.load(Ordering::SeqCst).saturating_add(size) > max_size && !collected {
+            self.collect(roots);
+        }
+        self.used.load(Ordering::SeqCst).saturating_add(size) <= max_size
+    }
+
+    // Allocates an instance of the class with its fields set to their default values. Like every
+    // allocation, this is a safepoint, and it may collect garbage first. Objects the virtual
+    // machine allocates for itself may take the heap past its limit.
+    pub fn new_object(&self, roots: &dyn Roots, class: &Arc<RuntimeClass>) -> Arc<HeapCell<Object>> {
+        let field_count = class.instance_fields.len();
+        self.allocate(roots, object_size(field_count), field_count * mem::size_of::<Value>(),
+            |memory| unsafe { Object::create(class, memory) }, Cell::Object)
+    }
+
+    // Allocates an array whose components have the given field descriptor, with its components
+    // set to their default values.
+    pub fn new_array(&self, roots: &dyn Roots, component_type: &str, length: usize) -> Arc<HeapCell<Array>> {
+        let payload_size = length.saturating_mul(component_size(component_type));
+        self.allocate(roots, array_size(component_type, length), payload_size,
+            |memory| unsafe { Array::create(component_type, length, memory) }, Cell::Array)
+    }
+
+    // Allocates an array with the given components.
+    pub fn new_array_from(&self, roots: &dyn Roots, component_type: &str, elements: ArrayElements) -> Arc<HeapCell<Array>> {
+        let length = elements.len();
+        let payload_size = length * component_size(component_type);
+        self.allocate(roots, array_size(component_type, length), payload_size,
+            |memory| unsafe { Array::from_elements(component_type, elements, memory) }, Cell::Array)
+    }
+
+    // Allocates a shallow copy of an object, as made by Object.clone. The copy has a monitor of
+    // its own. The object is only borrowed once the collection the allocation may cause is over,
+    // as a thread that borrows an object while other threads wait to stop keeps them waiting.
+    pub fn clone_object(&self, roots: &dyn Roots, object: &Arc<HeapCell<Object>>) -> Arc<HeapCell<Object>> {
+        let field_count = object.borrow().length;
+        self.allocate(roots, object_size(field_count), field_count * mem::size_of::<Value>(),
+            |memory| unsafe { object.borrow().copy(memory) }, Cell::Object)
+    }
+
+    pub fn clone_array(&self, roots: &dyn Roots, array: &Arc<HeapCell<Array>>) -> Arc<HeapCell<Array>> {
+        let (size, payload_size) = {
+            let array = array.borrow();
+            (array_size(array.component_type(), array.len()), array.payload_size())
+        };
+        self.allocate(roots, size, payload_size, |memory| unsafe { array.borrow().copy(memory) }, Cell::Array)
+    }
+
+    // Finds room for a payload of the given size and creates the contents of the new cell there.
+    // Small payloads go to eden, and payloads that would fill a good part of it straight to the
+    // old generation. If eden is full, the young generation is collected first.
+    fn allocate<T: Payload, F: FnOnce(*mut u8) -> T>(&self,
+                                                      roots: &dyn Roots,
+                                                      size: usize,
+                                                      payload_size: usize,
+                                                      create: F,
+                                                      cell: fn(Arc<HeapCell<T>>) -> Cell) -> Arc<HeapCell<T>> {
+        self.reserve(roots, size);
+
+        let mut generations = self.generations.lock().unwrap();
+        let mut placement = self.place(&mut generations, payload_size, false);
+        if placement.is_none() {
+            drop(generations);
+            self.collect_minor(roots);
+            generations = self.generations.lock().unwrap();
+            placement = self.place(&mut generations, payload_size, true);
+        }
+        let (memory, placement) = placement.unwrap_or_else(|| (allocate_detached(payload_size), Placement::Detached));
+
+        let mut contents = create(memory);
+        if placement == Placement::Detached {
+            contents.set_detached(true);
+        }
+        if placement == Placement::Old && !contents.references().is_empty() {
+            // The old space has cards as soon as it holds anything.
+            self.cards.get().unwrap().dirty_range(memory, payload_size);
+        }
+        let handle = HeapCell::new(contents);
+
+        let memory = memory as usize;
+        match placement {
+            Placement::Old => {
+                generations.old.push(Old { cell: cell(handle.clone()), memory, size: space::aligned(payload_size) });
+                self.old_used.fetch_add(size, Ordering::SeqCst);
+            },
+            Placement::Young | Placement::Detached => {
+                generations.young.push(Young { cell: cell(handle.clone()), age: 0, memory });
+                self.young_used.fetch_add(size, Ordering::SeqCst);
+            }
+        }
+        self.used.fetch_add(size, Ordering::SeqCst);
+        handle
+    }
+
+    fn place(&self, generations: &mut Generations, size: usize, collected: bool) -> Option<(*mut u8, Placement)> {
+        let eden_size = self.eden_size();
+        let young = space::aligned(size) <= eden_size / 2;
+        if young {
+            if generations.eden.is_none() {
+                generations.eden = Space::new(eden_size);
+            }
+            if let Some(memory) = generations.eden.as_mut().and_then(|eden| eden.allocate(size)) {
+                return Some((memory, Placement::Young));
+            }
+        }
+        if !young || collected {
+            if let Some(memory) = self.allocate_old(generations, size) {
+                return Some((memory, Placement::Old));
+            }
+        }
+        None
+    }
+
+    // Allocates in the old space, reserving it first if needed. It is reserved large enough for
+    // twice the heap limit, as the virtual machine may allocate past the limit and pinned objects
+    // can keep it from being compacted completely, or smaller if that much is not available. If
+    // the old space is full, a full collection is run before the next allocation.
+    fn allocate_old(&self, generations: &mut Generations, size: usize) -> Option<*mut u8> {
+        if generations.old_space.is_none() {
+            let mut capacity = self.max_size.load(Ordering::SeqCst).saturating_mul(2);
+            while generations.old_space.is_none() && capacity >= size {
+                generations.old_space = Space::new(capacity);
+                capacity /= 2;
+            }
+            let space = generations.old_space.as_ref()?;
+            self.cards.get_or_init(|| CardTable::new(space));
+        }
+
+        let memory = generations.old_space.as_mut()?.allocate(size);
+        if memory.is_none() {
+            self.next_collection.store(0, Ordering::SeqCst);
+        }
+        memory
+    }
+
+    // The write barrier, called after a reference is stored into slot, a field of an object or a
+    // component of an array, while the object is still borrowed. The next minor collection looks
+    // for references to young objects in the old objects on dirty cards.
+    pub fn write_barrier(&self, slot: &Value) {
+        if handle_address(slot).is_some() {
+            if let Some(cards) = self.cards.get() {
+                cards.dirty(slot as *const Value as *const u8);
+            }
+        }
+    }
+
+    // The write barrier for stores into a range of components, as by System.arraycopy.
+    pub fn write_barrier_range(&self, slots: &[Value]) {
+        if let Some(cards) = self.cards.get() {
+            slots.iter()
+                .filter(|slot| handle_address(slot).is_some())
+                .for_each(|slot| cards.dirty(slot as *const Value as *const u8));
+        }
+    }
+
+
+    // Registers the current thread as one that runs Java code, until the returned guard is
+    // dropped. A collection only starts when all attached threads are stopped.
+    pub fn attach(&self) -> Attachment<'_> {
+        let current = thread::current().id();
+        let mut state = self.lock_safepoint();
+        while state.collecting {
+            state = self.safepoint_changed.wait(state).unwrap();
+        }
+        state.attached.insert(current);
+        Attachment { heap: self }
+    }
+
+    fn detach(&self) {
+        let current = thread::current().id();
+        let mut state = self.lock_safepoint();
+        state.attached.remove(&current);
+        state.stopped.remove(&current);
+        self.safepoint_changed.notify_all();
+    }
+
+    fn lock_safepoint(&self) -> MutexGuard<'_, SafepointState> {
+        self.safepoint.lock().unwrap()
+    }
+
+    // The flag that is set while a collection is pending. Compiled code polls it directly.
+    pub fn safepoint_flag(&self) -> &AtomicBool {
+        &self.collecting
+    }
+
+    // A safepoint: if another thread is waiting to collect garbage, stops until it is done.
+    // Called on allocation and on backward branches, so that loops cannot hold up a collection.
+    pub fn poll(&self) {
+        if self.collecting.load(Ordering::SeqCst) {
+            self.blocking(|| ());
+        }
+    }
+
+    // Runs f, which may block, as if the current thread were stopped at a safepoint. f must not
+    // change any references on the heap.
+    pub fn blocking<R, F: FnOnce() -> R>(&self, f: F) -> R {
+        let current = thread::current().id();
+        {
+            let mut state = self.lock_safepoint();
+            if !state.attached.contains(&current) || state.stopped.contains(&current) {
+                drop(state);
+                return f();
+            }
+            state.stopped.insert(current);
+            self.safepoint_changed.notify_all();
+        }
+
+        let result = f();
+
+        let mut state = self.lock_safepoint();
+        while state.collecting {
+            state = self.safepoint_changed.wait(state).unwrap();
+        }
+        state.stopped.remove(&current);
+        result
+    }
+
+    // monitorenter, which blocks while another thread holds the monitor.
+    pub fn enter_monitor(&self, monitor: &Monitor) {
+        if !monitor.try_enter() {
+            self.blocking(|| monitor.enter());
+        }
+    }
+
+    // Stops all other attached threads and collects the garbage on the whole heap. If another
+    // thread is already collecting, waits for it to finish instead and returns false.
+    pub fn collect(&self, roots: &dyn Roots) -> bool {
+        self.stop_the_world(roots, Collection::Full)
+    }
+
+    // Like collect, but only collects the young generation.
+    pub fn collect_minor(&self, roots: &dyn Roots) -> bool {
+        self.stop_the_world(roots, Collection::Minor)
+    }
+
+    fn stop_the_world(&self, roots: &dyn Roots, collection: Collection) -> bool {
+        let current = thread::current().id();
+        {
+            let mut state = self.lock_safepoint();
+            if state.collecting {
+                drop(state);
+                self.blocking(|| ());
+                return false;
+            }
+            state.collecting = true;
+            self.collecting.store(true, Ordering::SeqCst);
+            self.safepoint_changed.notify_all();
+
+            while state.others_running(current) {
+                state = self.safepoint_changed.wait(state).unwrap();
+            }
+        }
+
+        let started = Instant::now();
+        let used_before = self.used.load(Ordering::SeqCst);
+        let promoted = match collection {
+            Collection::Minor => self.collect_young(roots),
+            Collection::Full => self.collect_all(roots)
+        };
+        let pause = started.elapsed();
+        *self.pause_time.lock().unwrap() += pause;
+        self.log_collection(collection, used_before, promoted, pause);
+
+        let mut state = self.lock_safepoint();
+        state.collecting = false;
+        self.collecting.store(false, Ordering::SeqCst);
+        self.safepoint_changed.notify_all();
+        true
+    }
+
+    // Collects the young generation, copying the live young objects to a new survivor space or
+    // promoting them. Returns the number of bytes promoted to the old generation.
+    fn collect_young(&self, roots: &dyn Roots) -> usize {
+        let mut generations = self.generations.lock().unwrap();
+        let generations = &mut *generations;
+
+        // The old objects and the statics that references to young objects were stored into, and
+        // the old objects native methods wrote to.
+        let mut remembered = match self.cards.get() {
+            Some(cards) => overlapping(&generations.old, &cards.take_dirty()),
+            None => Vec::new()
+        };
+        remembered.extend((0..generations.old.len()).filter(|&position| generations.old[position].cell.take_written()));
+        remembered.sort_unstable();
+        remembered.dedup();
+        let classes: Vec<Arc<RuntimeClass>> = roots.classes().into_iter().filter(|class| class.statics_dirty()).collect();
+
+        let marked = {
+            let cells: Vec<&Cell> = generations.young.iter().map(|young| &young.cell).collect();
+            let remembered: Vec<&Cell> = remembered.iter().map(|&position| &generations.old[position].cell).collect();
+            mark(&cells, roots, &classes, &remembered)
+        };
+        let live = sweep(mem::take(&mut generations.young), marked.as_deref(), |young| &young.cell);
+
+        let survivor_size = self.eden_size() / 4;
+        let mut to_space = None;
+        let mut promoted = 0;
+        let mut promoted_positions = Vec::new();
+        let mut young_used = 0;
+        for Young { cell, age, memory } in live {
+            let age = age + 1;
+            let targets = if age >= TENURING_THRESHOLD {
+                [Placement::Old, Placement::Young]
+            } else {
+                [Placement::Young, Placement::Old]
+            };
+
+            let mut moved = None;
+            cell.relocate(|size| {
+                for target in targets {
+                    let memory = match target {
+                        Placement::Old => self.allocate_old(generations, size),
+                        _ => allocate_in(&mut to_space, survivor_size, size)
+                    };
+                    if let Some(memory) = memory {
+                        moved = Some((target, memory as usize, size));
+                        return Some(memory);
+                    }
+                }
+                None
+            });
+
+            match moved {
+                Some((Placement::Old, memory, size)) => {
+                    promoted += cell.size();
+                    cell.take_written();
+                    promoted_positions.push(generations.old.len());
+                    generations.old.push(Old { cell, memory, size: space::aligned(size) });
+                },
+                Some((_, memory, _)) => {
+                    young_used += cell.size();
+                    generations.young.push(Young { cell, age, memory });
+                },
+                None => {
+                    young_used += cell.size();
+                    generations.young.push(Young { cell, age, memory });
+                }
+            }
+        }
+        release_spaces(generations, to_space);
+
+        // The old objects that still refer to young objects stay on dirty cards.
+        let young: HashSet<usize> = generations.young.iter().map(|young| young.cell.address()).collect();
+        if let Some(cards) = self.cards.get() {
+            if !young.is_empty() {
+                for &position in remembered.iter().chain(promoted_positions.iter()) {
+                    let old = &generations.old[position];
+                    old.cell.remember(cards, &young, old.memory, old.size);
+                }
+            }
+        }
+        remember_statics(&classes, &young);
+
+        let old_used = self.old_used.fetch_add(promoted, Ordering::SeqCst) + promoted;
+        self.used.store(old_used + young_used, Ordering::SeqCst);
+        self.young_used.store(young_used, Ordering::SeqCst);
+        self.promoted.fetch_add(promoted, Ordering::SeqCst);
+        self.minor_collections.fetch_add(1, Ordering::SeqCst);
+        promoted
+    }
+
+    // Collects the whole heap, compacting the old generation and then promoting every live young
+    // object. Returns the number of bytes promoted to the old generation.
+    fn collect_all(&self, roots: &dyn Roots) -> usize {
+        let mut generations = self.generations.lock().unwrap();
+        let generations = &mut *generations;
+
+        let classes = roots.classes();
+        let marked = {
+            let cells: Vec<&Cell> = generations.young.iter()
+                .map(|young| &young.cell)
+                .chain(generations.old.iter().map(|old| &old.cell))
+                .collect();
+            mark(&cells, roots, &classes, &[])
+        };
+        let young_count = generations.young.len();
+        let young = sweep(mem::take(&mut generations.young), marked.as_ref().map(|marked| &marked[..young_count]), |young| &young.cell);
+        let old = sweep(mem::take(&mut generations.old), marked.as_ref().map(|marked| &marked[young_count..]), |old| &old.cell);
+
+        // The live old objects slide towards the start of the old space, in address order. A
+        // pinned object stays where it is, and the objects after it move up to it.
+        let mut old_used = 0;
+        if let Some(space) = generations.old_space.as_mut() {
+            let base = space.base() as usize;
+            let mut top = base;
+            for Old { cell, memory, size } in old {
+                let memory = if memory == top || cell.relocate(|_| Some(top as *mut u8)) { top } else { memory };
+                top = memory + size;
+                old_used += cell.size();
+                generations.old.push(Old { cell, memory, size });
+            }
+            space.truncate(top - base);
+        }
+
+        let mut promoted = 0;
+        let mut young_used = 0;
+        for Young { cell, age, memory } in young {
+            let mut moved = None;
+            cell.relocate(|size| {
+                let memory = self.allocate_old(generations, size)?;
+                moved = Some((memory as usize, size));
+                Some(memory)
+            });
+
+            match moved {
+                Some((memory, size)) => {
+                    promoted += cell.size();
+                    generations.old.push(Old { cell, memory, size: space::aligned(size) });
+                },
+                None => {
+                    young_used += cell.size();
+                    generations.young.push(Young { cell, age, memory });
+                }
+            }
+        }
+        release_spaces(generations, None);
+
+        // Only objects that could not be promoted are still young.
+        let young: HashSet<usize> = generations.young.iter().map(|young| young.cell.address()).collect();
+        generations.old.iter().for_each(|old| { old.cell.take_written(); });
+        if let Some(cards) = self.cards.get() {
+            cards.clear();
+            if !young.is_empty() {
+                for old in generations.old.iter() {
+                    old.cell.remember(cards, &young, old.memory, old.size);
+                }
+            }
+        }
+        remember_statics(&classes, &young);
+
+        let max_size = self.max_size.load(Ordering::SeqCst);
+        let old_used = old_used + promoted;
+        let used = old_used + young_used;
+        self.old_used.store(old_used, Ordering::SeqCst);
+        self.young_used.store(young_used, Ordering::SeqCst);
+        self.used.store(used, Ordering::SeqCst);
+        self.next_collection.store(used.saturating_mul(2).max(MIN_COLLECTION_INTERVAL).min(max_size), Ordering::SeqCst);
+        self.promoted.fetch_add(promoted, Ordering::SeqCst);
+        self.collections.fetch_add(1, Ordering::SeqCst);
+        promoted
+    }
+
+    // Logs a collection in the format of -Xlog:gc in HotSpot, with the promoted bytes added.
+    fn log_collection(&self, collection: Collection, used_before: usize, promoted: usize, pause: Duration) {
+        let mut log = self.log.lock().unwrap();
+        let log = match *log {
+            Some(ref mut log) => log,
+            None => return
+        };
+
+        let number = self.collections.load(Ordering::SeqCst) + self.minor_collections.load(Ordering::SeqCst) - 1;
+        let kind = match collection {
+            Collection::Minor => "Young",
+            Collection::Full => "Full"
+        };
+        let _ = writeln!(log, "[{:.3}s][info][gc] GC({}) Pause {} {}K->{}K({}K) promoted {}K {:.3}ms",
+            self.started.elapsed().as_secs_f64(), number, kind, used_before / 1024,
+            self.used.load(Ordering::SeqCst) / 1024, self.max_size.load(Ordering::SeqCst) / 1024,
+            promoted / 1024, pause.as_secs_f64() * 1000.0);
+        let _ = log.flush();
+    }
+
+}
+
+
+// Allocates in a survivor space, creating it first if needed.
+fn allocate_in(space: &mut Option<Space>, capacity: usize, size: usize) -> Option<*mut u8> {
+    if space.is_none() {
+        *space = Space::new(capacity);
+    }
+    space.as_mut()?.allocate(size)
+}
+
+// The positions of the old objects whose payloads overlap the given address ranges, which are in
+// ascending order.
+fn overlapping(old: &[Old], ranges: &[(usize, usize)]) -> Vec<usize> {
+    let mut positions = Vec::new();
+    for &(start, end) in ranges {
+        let mut position = old.partition_point(|old| old.memory + old.size <= start);
+        while position < old.len() && old[position].memory < end {
+            positions.push(position);
+            position += 1;
+        }
+    }
+    positions.dedup();
+    positions
+}
+
+// Frees the payloads of the entries whose cells are not marked and then drops their handles,
+// returning the live entries. A dead cell is only referenced by other dead cells, so dropping the
+// handles once all the payloads are freed does not recurse through long chains. If marking
+// failed, every entry is live.
+fn sweep<T, F: Fn(&T) -> &Cell>(entries: Vec<T>, marked: Option<&[bool]>, cell: F) -> Vec<T> {
+    let marked = match marked {
+        Some(marked) => marked,
+        None => return entries
+    };
+
+    let (live, dead): (Vec<_>, Vec<_>) = entries.into_iter().zip(marked).partition(|&(_, &marked)| marked);
+    dead.iter().for_each(|(entry, _)| cell(entry).free());
+    drop(dead);
+    live.into_iter().map(|(entry, _)| entry).collect()
+}
+
+// Empties eden and the previous survivor space, unless they still hold objects that could not be
+// moved out of them, in which case they are retained until those objects are moved. The to-space
+// of the collection becomes the survivor space.
+fn release_spaces(generations: &mut Generations, to_space: Option<Space>) {
+    let staying: Vec<usize> = generations.young.iter().map(|young| young.memory).collect();
+    let holds = |space: &Space| staying.iter().any(|&memory| space.contains(memory as *const u8));
+
+    if let Some(mut eden) = generations.eden.take() {
+        if holds(&eden) {
+            generations.retained.push(eden);
+        } else {
+            eden.truncate(0);
+            generations.eden = Some(eden);
+        }
+    }
+    if let Some(survivors) = generations.survivors.take() {
+        if holds(&survivors) {
+            generations.retained.push(survivors);
+        }
+    }
+    generations.retained.retain(holds);
+    generations.survivors = to_space;
+}
+
+// Cleans the statics of the classes, except those that still refer to young objects.
+fn remember_statics(classes: &[Arc<RuntimeClass>], young: &HashSet<usize>) {
+    for class in classes {
+        class.clean_statics();
+        let mut dirty = false;
+        class.visit_statics(&mut |value| dirty |= handle_address(value).is_some_and(|address| young.contains(&address)));
+        if dirty {
+            class.dirty_statics();
+        }
+    }
+}
+
+// Marks the cells that are reachable from the roots, returning whether each one is live, or None
+// if a cell is being written to, as its references cannot be traced then. The statics of the
+// given classes are roots, and so are the references in the remembered cells, which are not
+// marked themselves.
+fn mark(cells: &[&Cell], roots: &dyn Roots, classes: &[Arc<RuntimeClass>], remembered: &[&Cell]) -> Option<Vec<bool>> {
+    let positions: HashMap<usize, usize> = cells.iter()
+        .enumerate()
+        .map(|(position, cell)| (cell.address(), position))
+        .collect();
+    let position = |value: &Value| handle_address(value).and_then(|address| positions.get(&address).cloned());
+
+    let mut traced = true;
+    let mut pending = Vec::new();
+    {
+        let mut root = |value: &Value| if let Some(position) = position(value) {
+            pending.push(position);
+        };
+        roots.visit(&mut root);
+
+        let mut visited = HashSet::new();
+        for class in classes {
+            if visited.insert(Arc::as_ptr(class)) {
+                class.visit_statics(&mut root);
+            }
+        }
+        for cell in remembered {
+            traced &= cell.references(&mut root);
+        }
+    }
+    if !traced {
+        return None;
+    }
+
+    let mut marked = vec![false; cells.len()];
+    while let Some(current) = pending.pop() {
+        if marked[current] {
+            continue;
+        }
+        marked[current] = true;
+
+        let traced = cells[current].references(|value| if let Some(referenced) = position(value) {
+            if !marked[referenced] {
+                pending.push(referenced);
+            }
+        });
+        if !traced {
+            return None;
+        }
+    }
+
+    Some(marked)
+}
+
+impl Default for Heap {
+    fn default() -> Heap {
+        Heap::new()
+    }
+}
+
+// The cells Rust code still holds once the heap is dropped, which are those whose strong counts are
+// not accounted for by the handle kept by the heap and the references from the other cells.
+fn held_outside(cells: &[&Cell]) -> Option<Vec<Value>> {
+    let positions: HashMap<usize, usize> = cells.iter()
+        .enumerate()
+        .map(|(position, cell)| (cell.address(), position))
+        .collect();
+
+    let mut found = vec![0; cells.len()];
+    for cell in cells.iter() {
+        let traced = cell.references(|value| if let Some(&position) = handle_address(value).and_then(|address| positions.get(&address)) {
+            found[position] += 1;
+        });
+        if !traced {
+            return None;
+        }
+    }
+
+    Some((0..cells.len())
+        .filter(|&position| cells[position].strong_count() - 1 > found[position])
+        .map(|position| cells[position].value())
+        .collect())
+}
+
+// Frees the objects only the heap keeps alive, breaking their cycles. Objects that Rust code
+// still holds are moved out of the heap, so that they outlive it. Nothing runs Java code any
+// more, so they are told apart by their strong counts.
+impl Drop for Heap {
+    fn drop(&mut self) {
+        let generations = mem::take(&mut *self.generations.lock().unwrap());
+        let cells: Vec<&Cell> = generations.young.iter()
+            .map(|young| &young.cell)
+            .chain(generations.old.iter().map(|old| &old.cell))
+            .collect();
+        let marked = held_outside(&cells)
+            .and_then(|held| mark(&cells, &held, &[], &[]))
+            .unwrap_or_else(|| vec![true; cells.len()]);
+        for (cell, live) in cells.iter().zip(marked) {
+            if live {
+                cell.detach();
+            } else {
+                cell.free();
+            }
+        }
+    }
+}
+
+// Detaches the thread from the heap when it stops running Java code.
+pub struct Attachment<'a> {
+    heap: &'a Heap
+}
+
+impl<'a> Drop for Attachment<'a> {
+    fn drop(&mut self) {
+        self.heap.detach();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use runtime::bootstrap::tests::{class_table, run};
+    use runtime::string;
+    use class::reader;
+    use runtime::class::{ClassTable, RuntimeClass};
+    use runtime::stack::StackFrame;
+    use runtime::class::field::RuntimeField;
+    use class::ConstantPool;
+    use runtime::bootstrap::io::SharedBuffer;
+
+    fn node_class() -> Arc<RuntimeClass> {
+        let fields = vec![RuntimeField::new("next", "Ljava/lang/Object;", 0), RuntimeField::new("key", "I", 0)];
+        RuntimeClass::new("Node", 0, None, Vec::new(), ConstantPool::new(), fields, Vec::new())
+    }
+
+    fn new_node(heap: &Heap, class: &Arc<RuntimeClass>) -> Arc<HeapCell<Object>> {
+        heap.new_object(&Vec::new(), class)
+    }
+
+    #[test]
+    fn collects_unreachable_cycles() {
+        let heap = Heap::new();
+        let class = node_class();
+
+        let a = new_node(&heap, &class);
+        let b = new_node(&heap, &class);
+        a.borrow_mut().put_field(String::from("next"), Value::ObjectRef(b.clone()));
+        b.borrow_mut().put_field(String::from("next"), Value::ObjectRef(a.clone()));
+        let weak_a = Arc::downgrade(&a);
+        drop(a);
+        drop(b);
+        assert!(weak_a.upgrade().is_some());
+
+        heap.collect(&Vec::new());
+        assert!(weak_a.upgrade().is_none());
+        assert_eq!(heap.usage().used, 0);
+        assert_eq!(heap.usage().collections, 1);
+    }
+
+    // A root keeps everything it refers to alive, including cycles and arrays.
+    #[test]
+    fn keeps_objects_reachable_from_roots() {
+        let heap = Heap::new();
+        let class = node_class();
+
+        let roots = vec![Value::ObjectRef(new_node(&heap, &class))];
+        let root = match roots[0] {
+            Value::ObjectRef(ref root) => Arc::downgrade(root),
+            _ => unreachable!()
+        };
+        let cycle = new_node(&heap, &class);
+        let array = heap.new_array(&roots, "Ljava/lang/Object;", 2);
+        array.borrow_mut().set(0, Value::ObjectRef(cycle.clone())).unwrap();
+        array.borrow_mut().set(1, Value::ArrayRef(array.clone())).unwrap();
+        cycle.borrow_mut().put_field(String::from("next"), Value::ArrayRef(array.clone()));
+        let root = root.upgrade().unwrap();
+        root.borrow_mut().put_field(String::from("next"), Value::ObjectRef(cycle.clone()));
+        root.borrow_mut().put_field(String::from("key"), Value::Integer(7));
+        drop(cycle);
+        drop(array);
+        let weak_root = Arc::downgrade(&root);
+        drop(root);
+
+        heap.collect(&roots);
+        let root = weak_root.upgrade().unwrap();
+        let cycle = match root.borrow().get_field(String::from("next")) {
+            Value::ObjectRef(cycle) => cycle,
+            x => panic!("Expected the cycle to survive, got {:?}", x)
+        };
+        assert!(matches!(cycle.borrow().get_field(String::from("next")), Value::ArrayRef(_)));
+        assert!(matches!(root.borrow().get_field(String::from("key")), Value::Integer(7)));
+        assert_eq!(heap.usage().used, object_size(2) * 2 + array_size("Ljava/lang/Object;", 2));
+    }
+
+    // Dropping the heap frees what only it holds, and moves what Rust code still holds out of it.
+    #[test]
+    fn detaches_objects_held_outside_the_heap() {
+        let heap = Heap::new();
+        let class = node_class();
+
+        let held = new_node(&heap, &class);
+        let referenced = new_node(&heap, &class);
+        held.borrow_mut().put_field(String::from("next"), Value::ObjectRef(referenced.clone()));
+        let weak_referenced = Arc::downgrade(&referenced);
+        drop(referenced);
+
+        let weak_held = Arc::downgrade(&held);
+        let cycle = new_node(&heap, &class);
+        cycle.borrow_mut().put_field(String::from("next"), Value::ObjectRef(cycle.clone()));
+        let weak_cycle = Arc::downgrade(&cycle);
+        drop(cycle);
+        drop(heap);
+        assert!(weak_cycle.upgrade().is_none());
+        assert!(weak_referenced.upgrade().is_some());
+        assert!(held.borrow().fields()[0].same_reference(&Value::ObjectRef(weak_referenced.upgrade().unwrap())));
+        drop(held);
+        assert!(weak_held.upgrade().is_none());
+        assert!(weak_referenced.upgrade().is_none());
+    }
+
+    // The roots of a class table are the frames of its threads, the statics of its classes and the
+    // strings it interned.
+    #[test]
+    fn scans_frames_statics_and_interned_strings() {
+        let class_table = ClassTable::new();
+        let class = class_table.define_class(&reader::read_class_file(include_bytes!("../../../fixtures/Jit.class")).unwrap()).unwrap();
+        let string = |text: &str| class_table.new_string(text.encode_utf16().collect());
+
+        let garbage = Arc::downgrade(&string("garbage"));
+        let interned = Arc::downgrade(&class_table.intern_string("interned".encode_utf16().collect()));
+        class.put_static("cached", Value::ObjectRef(string("static")));
+        let mut stack_frame = StackFrame::new_frame(1, 1);
+        stack_frame.locals[0] = Value::ObjectRef(string("local"));
+        stack_frame.push(Value::ObjectRef(string("operand")));
+        let guard = class_table.stacks.enter(&class, class.get_declared_method("fib", "(I)I").unwrap());
+        guard.set_stack_frame(&stack_frame);
+
+        let referenced = |value: &Value| match value {
+            Value::ObjectRef(object) => Arc::downgrade(object),
+            _ => panic!("Expected an object, got {:?}", value)
+        };
+        let cached = referenced(&class.get_static("cached").unwrap());
+        let local = referenced(&stack_frame.locals[0]);
+        let operand = referenced(&stack_frame.stack[0]);
+
+        assert!(class_table.collect_garbage());
+        assert!(garbage.upgrade().is_none());
+        for kept in [&interned, &cached, &local, &operand].iter() {
+            assert!(kept.upgrade().is_some());
+        }
+
+        drop(guard);
+        drop(stack_frame);
+        class.put_static("cached", Value::Null);
+        assert!(class_table.collect_garbage());
+        assert!(cached.upgrade().is_none());
+    }
+
+    // Rust code keeps what it holds in a handle scope alive, and what it allocates while the scope
+    // is open.
+    #[test]
+    fn keeps_objects_in_handle_scopes() {
+        let class_table = ClassTable::new();
+        let string = |text: &str| class_table.new_string(text.encode_utf16().collect());
+
+        let held = string("held");
+        let garbage = Arc::downgrade(&string("garbage"));
+        let scope = class_table.stacks.scope();
+        scope.hold(&Value::ObjectRef(held.clone()));
+        let kept = string("kept");
+
+        assert!(class_table.collect_garbage());
+        assert!(garbage.upgrade().is_none());
+        assert_eq!(string::to_rust_string(&held.borrow()), "held");
+        assert_eq!(string::to_rust_string(&kept.borrow()), "kept");
+
+        let weak_held = Arc::downgrade(&held);
+        let weak_kept = Arc::downgrade(&kept);
+        drop(held);
+        drop(kept);
+        drop(scope);
+        assert!(class_table.collect_garbage());
+        assert!(weak_held.upgrade().is_none());
+        assert!(weak_kept.upgrade().is_none());
+    }
+
+    #[test]
+    fn reserves_up_to_the_limit() {
+        let heap = Heap::new();
+        let size = array_size("I", 100);
+        heap.set_max_size(size * 2 + size / 2);
+
+        let mut arrays = Vec::new();
+        for _ in 0..2 {
+            assert!(heap.reserve(&arrays, size));
+            let array = heap.new_array(&arrays, "I", 100);
+            arrays.push(Value::ArrayRef(array));
+        }
+        assert!(!heap.reserve(&arrays, size));
+        assert_eq!(heap.usage().collections, 1);
+
+        // The arrays take more than half of eden, so they were allocated in the old generation,
+        // and only a full collection frees them.
+        arrays.pop();
+        assert!(heap.reserve(&arrays, size));
+        assert_eq!(heap.usage().collections, 2);
+        assert_eq!(heap.usage().used, size);
+    }
+
+    #[test]
+    fn promotes_survivors_of_minor_collections() {
+        let heap = Heap::new();
+        let class = node_class();
+
+        let survivor = new_node(&heap, &class);
+        let roots = vec![Value::ObjectRef(survivor.clone())];
+        let a = new_node(&heap, &class);
+        let b = new_node(&heap, &class);
+        a.borrow_mut().put_field(String::from("next"), Value::ObjectRef(b.clone()));
+        b.borrow_mut().put_field(String::from("next"), Value::ObjectRef(a.clone()));
+        let weak_a = Arc::downgrade(&a);
+        drop(a);
+        drop(b);
+
+        heap.collect_minor(&roots);
+        assert!(weak_a.upgrade().is_none());
+        assert_eq!(heap.usage().promoted, 0);
+
+        heap.collect_minor(&roots);
+        let usage = heap.usage();
+        assert_eq!((usage.minor_collections, usage.collections), (2, 0));
+        assert_eq!(usage.promoted, object_size(2));
+        assert_eq!(usage.used, object_size(2));
+
+        // Old objects are only collected by a full collection.
+        survivor.borrow_mut().put_field(String::from("next"), Value::ObjectRef(survivor.clone()));
+        let weak_survivor = Arc::downgrade(&survivor);
+        drop(roots);
+        drop(survivor);
+        heap.collect_minor(&Vec::new());
+        assert!(weak_survivor.upgrade().is_some());
+        heap.collect(&Vec::new());
+        assert!(weak_survivor.upgrade().is_none());
+        assert_eq!(heap.usage().used, 0);
+    }
+
+    // A young object referenced from an old one survives a minor collection, even if the store
+    // did not go through the write barrier.
+    #[test]
+    fn old_objects_keep_young_objects_alive() {
+        let heap = Heap::new();
+        let class = node_class();
+
+        let old = new_node(&heap, &class);
+        let roots = vec![Value::ObjectRef(old.clone())];
+        for _ in 0..TENURING_THRESHOLD {
+            heap.collect_minor(&roots);
+        }
+        assert_eq!(heap.usage().promoted, object_size(2));
+
+        let young = new_node(&heap, &class);
+        young.borrow_mut().put_field(String::from("next"), Value::ObjectRef(young.clone()));
+        old.borrow_mut().put_field(String::from("next"), Value::ObjectRef(young.clone()));
+        drop(young);
+
+        heap.collect_minor(&roots);
+        let young = match old.borrow().get_field(String::from("next")) {
+            Value::ObjectRef(young) => young,
+            x => panic!("Expected the young object to survive, got {:?}", x)
+        };
+        assert!(matches!(young.borrow().get_field(String::from("next")), Value::ObjectRef(_)));
+    }
+
+    fn fields_address(object: &Arc<HeapCell<Object>>) -> usize {
+        object.borrow().fields().as_ptr() as usize
+    }
+
+    // Live young objects are copied to a survivor space, and their fields and components with them.
+    #[test]
+    fn copies_survivors() {
+        let heap = Heap::new();
+        let class = node_class();
+
+        let node = new_node(&heap, &class);
+        let numbers = heap.new_array_from(&Vec::new(), "I", ArrayElements::Integer(vec![1, 2, 3]));
+        let references = heap.new_array(&Vec::new(), "Ljava/lang/Object;", 2);
+        node.borrow_mut().put_field(String::from("key"), Value::Integer(7));
+        node.borrow_mut().put_field(String::from("next"), Value::ArrayRef(numbers.clone()));
+        references.borrow_mut().set(1, Value::ObjectRef(node.clone())).unwrap();
+        let address = fields_address(&node);
+
+        heap.collect_minor(&vec![Value::ArrayRef(references.clone())]);
+        assert_ne!(fields_address(&node), address);
+        assert!(matches!(node.borrow().get_field(String::from("key")), Value::Integer(7)));
+        assert!(matches!(node.borrow().get_field(String::from("next")), Value::ArrayRef(ref array) if Arc::ptr_eq(array, &numbers)));
+        assert!(matches!(numbers.borrow().elements(), Elements::Integer(&[1, 2, 3])));
+        assert!(matches!(references.borrow().get(0), Value::Null));
+        assert!(matches!(references.borrow().get(1), Value::ObjectRef(ref object) if Arc::ptr_eq(object, &node)));
+    }
+
+    // A full collection slides the live old objects over the dead ones.
+    #[test]
+    fn compacts_the_old_generation() {
+        let heap = Heap::new();
+        let class = node_class();
+
+        let first = new_node(&heap, &class);
+        let second = new_node(&heap, &class);
+        second.borrow_mut().put_field(String::from("key"), Value::Integer(2));
+        let mut roots = vec![Value::ObjectRef(first.clone()), Value::ObjectRef(second.clone())];
+        for _ in 0..TENURING_THRESHOLD {
+            heap.collect_minor(&roots);
+        }
+        let address = fields_address(&first);
+        assert!(fields_address(&second) > address);
+
+        roots.remove(0);
+        drop(first);
+        heap.collect(&roots);
+        assert_eq!(fields_address(&second), address);
+        assert!(matches!(second.borrow().get_field(String::from("key")), Value::Integer(2)));
+        assert_eq!(heap.usage().used, object_size(2));
+    }
+
+    // A young object that only an old object refers to survives minor collections once the store
+    // went through the write barrier.
+    #[test]
+    fn remembers_stores_into_old_objects() {
+        let heap = Heap::new();
+        let class = node_class();
+
+        let old = new_node(&heap, &class);
+        let roots = vec![Value::ObjectRef(old.clone())];
+        for _ in 0..TENURING_THRESHOLD {
+            heap.collect_minor(&roots);
+        }
+        let young = new_node(&heap, &class);
+        young.borrow_mut().put_field(String::from("key"), Value::Integer(3));
+        {
+            let mut old = old.borrow_mut();
+            old.put_field(String::from("next"), Value::ObjectRef(young.clone()));
+            heap.write_barrier(&old.fields()[0]);
+        }
+        let weak_young = Arc::downgrade(&young);
+        drop(young);
+
+        // The card stays dirty while the object is young.
+        for _ in 0..TENURING_THRESHOLD {
+            heap.collect_minor(&roots);
+            assert!(weak_young.upgrade().is_some());
+        }
+        assert_eq!(heap.usage().promoted, object_size(2) * 2);
+        let young = weak_young.upgrade().unwrap();
+        assert!(matches!(young.borrow().get_field(String::from("key")), Value::Integer(3)));
+    }
+
+    // Objects borrowed during a collection are not moved, and the space they are in is kept.
+    #[test]
+    fn pins_borrowed_objects() {
+        let heap = Heap::new();
+        let class = node_class();
+
+        let node = new_node(&heap, &class);
+        let roots = vec![Value::ObjectRef(node.clone())];
+        let address = fields_address(&node);
+        {
+            let _borrowed = node.borrow();
+            heap.collect_minor(&roots);
+        }
+        assert_eq!(fields_address(&node), address);
+        assert_eq!(heap.generations.lock().unwrap().retained.len(), 1);
+
+        heap.collect_minor(&roots);
+        assert_ne!(fields_address(&node), address);
+        assert!(heap.generations.lock().unwrap().retained.is_empty());
+    }
+
+    #[test]
+    fn logs_collections() {
+        let heap = Heap::new();
+        let log = SharedBuffer::new();
+        heap.set_log(Box::new(log.clone()));
+        heap.set_max_size(64 * 1024 * 1024);
+
+        heap.collect_minor(&Vec::new());
+        heap.collect(&Vec::new());
+        let contents = log.contents();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("[info][gc] GC(0) Pause Young 0K->0K(65536K) promoted 0K "));
+        assert!(lines[1].contains("[info][gc] GC(1) Pause Full 0K->0K(65536K) promoted 0K "));
+        assert!(lines[1].ends_with("ms"));
+    }
+
+    // A collection waits for attached threads to reach a safepoint or to block.
+    #[test]
+    fn stops_attached_threads() {
+        let heap = Arc::new(Heap::new());
+        let other = heap.clone();
+        let started = Arc::new((Mutex::new(false), Condvar::new()));
+        let other_started = started.clone();
+
+        let mutator = thread::spawn(move || {
+            let _attachment = other.attach();
+            {
+                let (ref lock, ref condvar) = *other_started;
+                *lock.lock().unwrap() = true;
+                condvar.notify_all();
+            }
+            while other.usage().collections == 0 {
+                other.poll();
+            }
+        });
+
+        let (ref lock, ref condvar) = *started;
+        let mut is_started = lock.lock().unwrap();
+        while !*is_started {
+            is_started = condvar.wait(is_started).unwrap();
+        }
+        drop(is_started);
+
+        heap.collect(&Vec::new());
+        mutator.join().unwrap();
+        assert_eq!(heap.usage().collections, 1);
+    }
+
+    // The cycles created in a loop are collected, so they fit into a heap of 2 MiB.
+    #[test]
+    fn garbage_collection() {
+        let (class_table, out, err) = class_table();
+        class_table.heap.set_max_size(2 * 1024 * 1024);
+
+        let node = reader::read_class_file(include_bytes!("../../../fixtures/GarbageCollection$Node.class")).unwrap();
+        class_table.define_class(&node).unwrap();
+        let main = reader::read_class_file(include_bytes!("../../../fixtures/GarbageCollection.class")).unwrap();
+        let main = class_table.define_class(&main).unwrap();
+        run(&class_table, &main);
+
+        assert_eq!(out.contents(), include_str!("../../../fixtures/GarbageCollection.out"));
+        assert_eq!(err.contents(), "");
+        let usage = class_table.heap.usage();
+        assert!(usage.minor_collections > 2);
+        assert!(usage.collections > 0);
+    }
+
+}
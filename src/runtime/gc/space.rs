@@ -0,0 +1,175 @@
+use std::alloc::{self, Layout};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+// The fields of objects and the components of arrays are allocated in spaces: contiguous blocks
+// of memory that are filled by bumping a pointer. A space is emptied all at once, once whatever
+// is still live in it has been moved elsewhere.
+
+// The alignment of everything allocated in a space, which is enough for any value.
+pub const ALIGNMENT: usize = 16;
+
+// The number of bytes covered by one entry of a card table.
+pub const CARD_SIZE: usize = 512;
+
+// The number of bytes taken in a space by an allocation of the given size. Nothing is empty, so
+// that every allocation has an address of its own.
+pub fn aligned(size: usize) -> usize {
+    size.max(1).saturating_add(ALIGNMENT - 1) & !(ALIGNMENT - 1)
+}
+
+pub struct Space {
+    memory: *mut u8,
+    capacity: usize,
+    top: usize
+}
+
+// The memory is owned by the space, and the heap locks its spaces.
+unsafe impl Send for Space {}
+
+impl Space {
+
+    // Reserves a space of the given capacity, or returns None if the memory is not available.
+    pub fn new(capacity: usize) -> Option<Space> {
+        let layout = Layout::from_size_align(aligned(capacity), ALIGNMENT).ok()?;
+        let memory = unsafe { alloc::alloc(layout) };
+        if memory.is_null() {
+            return None;
+        }
+        Some(Space { memory, capacity: layout.size(), top: 0 })
+    }
+
+    pub fn allocate(&mut self, size: usize) -> Option<*mut u8> {
+        let size = aligned(size);
+        if size > self.capacity - self.top {
+            return None;
+        }
+
+        let memory = unsafe { self.memory.add(self.top) };
+        self.top += size;
+        Some(memory)
+    }
+
+    pub fn contains(&self, address: *const u8) -> bool {
+        let address = address as usize;
+        address >= self.memory as usize && address < self.memory as usize + self.top
+    }
+
+    pub fn base(&self) -> *mut u8 {
+        self.memory
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // Frees everything above the given offset.
+    pub fn truncate(&mut self, top: usize) {
+        self.top = top.min(self.top);
+    }
+
+}
+
+impl Drop for Space {
+    fn drop(&mut self) {
+        let layout = Layout::from_size_align(self.capacity, ALIGNMENT).unwrap();
+        unsafe { alloc::dealloc(self.memory, layout) };
+    }
+}
+
+// Which parts of a space may hold references that a minor collection must look at. The write
+// barrier dirties the card of every slot a reference is stored into, without taking any lock.
+pub struct CardTable {
+    base: usize,
+    capacity: usize,
+    cards: Box<[AtomicU8]>
+}
+
+const CLEAN: u8 = 0;
+const DIRTY: u8 = 1;
+
+impl CardTable {
+
+    pub fn new(space: &Space) -> CardTable {
+        let count = space.capacity().div_ceil(CARD_SIZE);
+        let cards: Vec<AtomicU8> = (0..count).map(|_| AtomicU8::new(CLEAN)).collect();
+        CardTable { base: space.base() as usize, capacity: space.capacity(), cards: cards.into_boxed_slice() }
+    }
+
+    // Dirties the card covering the address, if the space covers it.
+    pub fn dirty(&self, address: *const u8) {
+        let offset = (address as usize).wrapping_sub(self.base);
+        if offset < self.capacity {
+            self.cards[offset / CARD_SIZE].store(DIRTY, Ordering::Relaxed);
+        }
+    }
+
+    // Dirties the cards covering the given number of bytes from start.
+    pub fn dirty_range(&self, start: *const u8, length: usize) {
+        let mut address = start;
+        let end = start.wrapping_add(length);
+        while address < end {
+            self.dirty(address);
+            let offset = (address as usize).wrapping_sub(self.base);
+            address = address.wrapping_add(CARD_SIZE - offset % CARD_SIZE);
+        }
+    }
+
+    // The address ranges of the dirty cards, which are cleaned.
+    pub fn take_dirty(&self) -> Vec<(usize, usize)> {
+        self.cards.iter()
+            .enumerate()
+            .filter(|&(_, card)| card.load(Ordering::Relaxed) == DIRTY)
+            .map(|(index, card)| {
+                card.store(CLEAN, Ordering::Relaxed);
+                let start = self.base + index * CARD_SIZE;
+                (start, start + CARD_SIZE)
+            })
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        self.cards.iter().for_each(|card| card.store(CLEAN, Ordering::Relaxed));
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn allocates_by_bumping() {
+        let mut space = Space::new(64).unwrap();
+        let first = space.allocate(1).unwrap();
+        let second = space.allocate(20).unwrap();
+        assert_eq!(second as usize - first as usize, ALIGNMENT);
+        assert!(space.contains(second));
+        assert!(space.allocate(32).is_none());
+        assert_eq!(space.allocate(16), Some(first.wrapping_add(ALIGNMENT * 3)));
+
+        space.truncate(ALIGNMENT);
+        assert!(!space.contains(second));
+        assert_eq!(space.allocate(48), Some(second));
+    }
+
+    #[test]
+    fn dirties_cards() {
+        let space = Space::new(CARD_SIZE * 4).unwrap();
+        let cards = CardTable::new(&space);
+        let base = space.base();
+
+        cards.dirty(base.wrapping_add(CARD_SIZE + 8));
+        cards.dirty(base.wrapping_add(CARD_SIZE * 4));
+        cards.dirty(base.wrapping_sub(1));
+        cards.dirty_range(base.wrapping_add(CARD_SIZE * 3 - 16), 32);
+        let start = base as usize;
+        assert_eq!(cards.take_dirty(), vec![
+            (start + CARD_SIZE, start + CARD_SIZE * 2),
+            (start + CARD_SIZE * 2, start + CARD_SIZE * 3),
+            (start + CARD_SIZE * 3, start + CARD_SIZE * 4)
+        ]);
+        assert_eq!(cards.take_dirty(), Vec::new());
+    }
+
+}
@@ -1,19 +1,28 @@
-use code::instruction::Instruction;
-use runtime::class::{RuntimeClass, ClassTable};
-use std::rc::Rc;
-use std::cell::RefCell;
-use runtime::{Value, IntArray, Object};
+use class::ConstantPoolEntry;
+use code::disassembler::{ILOAD, LLOAD, FLOAD, DLOAD, ALOAD, ISTORE, LSTORE, FSTORE, DSTORE, ASTORE};
+use code::instruction::{Instruction, TaggedInstruction};
+use runtime::class::{RuntimeClass, ClassTable, ClassState, OBJECT_CLASS_NAME};
+use std::sync::Arc;
+use runtime::{Value, HeapCell, Object, Elements};
+use runtime::gc;
+use runtime::invokedynamic;
+use runtime::jit::{self, Execution};
+use runtime::monitor::{self, Monitor};
+use runtime::profile::{self, FrameCounts};
+use runtime::frames::FrameGuard;
 use runtime::stack::StackFrame;
-use runtime::class::method::{RuntimeMethod, MethodDescriptor};
+use runtime::trace::Exit;
+use runtime::class::method::{RuntimeMethod, MethodDescriptor, Code};
 
 enum Step {
     Next,
-    Jump(i16),
+    Jump(i32),
     Return(Value),
     ReturnVoid, // TODO: Could unify with Return variant
     Exception(Value)
 }
 
+#[derive(Debug)]
 pub enum InvokeResult {
     Void,
     Value(Value),
@@ -24,98 +33,721 @@ pub enum InvokeResult {
 pub enum InterpreterError {
     UnhandledInstruction(Instruction),
     UnexpectedOperand,
-    InvalidArrayType
+    InvalidArrayType,
+    InvalidConstant(u16),
+    InvalidBranch(u16),
+    EndOfCode,
+    ClassNotFound(String),
+    ClassFormat(String),
+    MethodNotFound(String),
+    UnsatisfiedLink(String),
+    // System.exit was called. This unwinds the interpreter without running finally blocks.
+    Exit(i32)
 }
 
 pub fn invoke_static_method(arguments: Vec<Value>,
                             method: &RuntimeMethod,
-                            class: &Rc<RuntimeClass>,
-                            class_table: &ClassTable) -> Option<InvokeResult> {
-    let mut locals = arguments.clone();
-    let mut stack_frame = StackFrame::new_frame_with_locals(method.code.max_stack, method.code.max_locals, arguments);
-    interpret(&mut stack_frame, method, class, class_table)
+                            class: &Arc<RuntimeClass>,
+                            class_table: &ClassTable) -> Result<InvokeResult, InterpreterError> {
+    invoke_method(class, method, arguments, class_table)
 }
 
-pub fn invoke_virtual_method(this: Rc<RefCell<Object>>,
+pub fn invoke_virtual_method(this: Arc<HeapCell<Object>>,
                              method: &RuntimeMethod,
                              arguments: Vec<Value>,
-                             class: &Rc<RuntimeClass>,
-                             class_table: &ClassTable) -> Option<InvokeResult> {
-    let mut locals = arguments.clone();
+                             class: &Arc<RuntimeClass>,
+                             class_table: &ClassTable) -> Result<InvokeResult, InterpreterError> {
+    let mut locals = arguments;
     locals.insert(0, Value::ObjectRef(this));
-    let mut stack_frame = StackFrame::new_frame_with_locals(method.code.max_stack, method.code.max_locals, locals);
+    invoke_method(class, method, locals, class_table)
+}
+
+// Invokes a method that has already been resolved and selected. For instance methods, the first
+// argument is `this`.
+//
+// Synchronized methods hold the monitor of `this`, or of the class for static methods, while they
+// run. It is released however the method completes, see JVMS $2.11.10.
+pub fn invoke_method(class: &Arc<RuntimeClass>,
+                     method: &RuntimeMethod,
+                     arguments: Vec<Value>,
+                     class_table: &ClassTable) -> Result<InvokeResult, InterpreterError> {
+    let result = invoke_synchronized(class, method, arguments, class_table)?;
+    // A native method invoking Java code holds the result in Rust, so it is kept in its scope.
+    if let InvokeResult::Value(ref value) | InvokeResult::Exception(ref value) = result {
+        class_table.stacks.keep(value);
+    }
+    Ok(result)
+}
+
+fn invoke_synchronized(class: &Arc<RuntimeClass>,
+                       method: &RuntimeMethod,
+                       arguments: Vec<Value>,
+                       class_table: &ClassTable) -> Result<InvokeResult, InterpreterError> {
+    if !method.is_synchronized() {
+        return invoke_unsynchronized(class, method, arguments, class_table);
+    }
+
+    let lock = if method.is_static() {
+        Value::ObjectRef(class_table.get_mirror(&class.class_name))
+    } else {
+        arguments.first().cloned().ok_or(InterpreterError::UnexpectedOperand)?
+    };
+    monitor::with_monitor(&lock, |monitor| class_table.heap.enter_monitor(monitor))
+        .ok_or(InterpreterError::UnexpectedOperand)?;
+
+    let result = invoke_unsynchronized(class, method, arguments, class_table)?;
+
+    if monitor::with_monitor(&lock, Monitor::exit) != Some(true) {
+        let exception = new_throwable(class_table, "java/lang/IllegalMonitorStateException", None)?;
+        return Ok(InvokeResult::Exception(exception));
+    }
+
+    Ok(result)
+}
+
+fn invoke_unsynchronized(class: &Arc<RuntimeClass>,
+                         method: &RuntimeMethod,
+                         arguments: Vec<Value>,
+                         class_table: &ClassTable) -> Result<InvokeResult, InterpreterError> {
+    if class_table.profile.is_enabled() {
+        class_table.profile.invoked(class, method);
+    }
+    if !class_table.trace.is_enabled() {
+        return execute(class, method, arguments, class_table);
+    }
+
+    // The frame of the method is not on the stack yet, nor any more when it has completed.
+    let depth = class_table.stacks.depth() + 1;
+    class_table.trace.method_entry(class, method, depth);
+    let result = execute(class, method, arguments, class_table);
+    if let (true, Ok(InvokeResult::Exception(ref exception))) = (method.native.is_some(), &result) {
+        class_table.trace.exception_thrown(class, method, depth, None, exception);
+    }
+    class_table.trace.method_exit(class, method, depth, &Exit::of(&result));
+    result
+}
+
+// Runs the method, native, compiled or interpreted.
+fn execute(class: &Arc<RuntimeClass>,
+           method: &RuntimeMethod,
+           arguments: Vec<Value>,
+           class_table: &ClassTable) -> Result<InvokeResult, InterpreterError> {
+    if let Some(native) = method.native {
+        let _frame = class_table.stacks.enter(class, method);
+        let scope = class_table.stacks.scope();
+        arguments.iter().for_each(|argument| scope.hold(argument));
+        let result = native(class_table, arguments);
+        if let Err(ref error) = result {
+            record_failure(class_table, error);
+        }
+        return result;
+    }
+
+    // An abstract method was selected because the class of the receiver has no implementation,
+    // and a native method without code has no implementation in the bootstrap class library.
+    let code = match method.code {
+        Some(ref code) => code,
+        None => {
+            let error = if method.is_abstract() { "java/lang/AbstractMethodError" } else { "java/lang/UnsatisfiedLinkError" };
+            let message = format!("{}.{}{}", class.class_name.replace('/', "."), method.name, method.descriptor);
+            return new_throwable(class_table, error, Some(&message)).map(InvokeResult::Exception);
+        }
+    };
+
+    let execution = {
+        let _frame = class_table.stacks.enter(class, method);
+        jit::invoke(class, method, &arguments, class_table)
+    };
+    if class_table.profile.is_enabled() && !matches!(execution, Execution::Interpret) {
+        class_table.profile.ran_compiled(class, method);
+    }
+    match execution {
+        Execution::Completed(result) => return Ok(result),
+        Execution::Deoptimized(mut stack_frame, pc) => return resume(&mut stack_frame, method, class, class_table, pc),
+        Execution::Interpret => {}
+    }
+
+    let mut stack_frame = StackFrame::new_frame_with_locals(code.max_stack, code.max_locals, arguments);
     interpret(&mut stack_frame, method, class, class_table)
 }
 
+// Invokes an instance method with the given name and descriptor, selected by the runtime class of
+// `this`. This is how native methods call back into Java code.
+pub fn invoke_virtual(class_table: &ClassTable,
+                      this: Value,
+                      name: &str,
+                      descriptor: &str,
+                      arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let runtime_class = match this {
+        Value::ObjectRef(ref object) => object.borrow().class().clone(),
+        Value::ArrayRef(_) => resolve_class(OBJECT_CLASS_NAME, class_table)?,
+        Value::Null => {
+            let exception = new_throwable(class_table, "java/lang/NullPointerException", None)?;
+            return Ok(InvokeResult::Exception(exception));
+        },
+        _ => return Err(InterpreterError::UnexpectedOperand)
+    };
+
+    let declaring_class = match RuntimeClass::resolve_method(&runtime_class, name, descriptor) {
+        Some(declaring_class) => declaring_class,
+        None => {
+            let exception = no_such_method(class_table, &runtime_class.class_name, name, descriptor)?;
+            return Ok(InvokeResult::Exception(exception));
+        }
+    };
+    let method = declaring_class.get_declared_method(name, descriptor).unwrap();
+
+    let mut locals = arguments;
+    locals.insert(0, this);
+    invoke_method(&declaring_class, method, locals, class_table)
+}
+
 pub fn interpret(stack_frame: &mut StackFrame,
                  method: &RuntimeMethod,
-                 class: &Rc<RuntimeClass>,
-                 class_table: &ClassTable) -> Option<InvokeResult> {
-    let end_index: u16 = method.code.instructions.len() as u16 - 1;
-    let mut current_index: u16 = 0;
-    let mut done = false;
+                 class: &Arc<RuntimeClass>,
+                 class_table: &ClassTable) -> Result<InvokeResult, InterpreterError> {
+    resume(stack_frame, method, class, class_table, 0)
+}
 
-    while done == false {
-        let tagged_instruction = method.code.instructions.get(current_index as usize).unwrap();
-        println!("{}: {:?}", tagged_instruction.index, tagged_instruction.instruction);
+// Runs the method from the instruction at pc, which is where compiled code that deoptimized
+// left off.
+pub fn resume(stack_frame: &mut StackFrame,
+              method: &RuntimeMethod,
+              class: &Arc<RuntimeClass>,
+              class_table: &ClassTable,
+              pc: u16) -> Result<InvokeResult, InterpreterError> {
+    let frame = class_table.stacks.enter(class, method);
+    frame.set_stack_frame(stack_frame);
 
-        let result = interpret_instruction(
+    let result = if !class_table.profile.is_enabled() {
+        run(stack_frame, method, class, class_table, &frame, pc, None)
+    } else {
+        // The frame is added to the profile however it completes.
+        let mut counts = FrameCounts::default();
+        let result = run(stack_frame, method, class, class_table, &frame, pc, Some(&mut counts));
+        class_table.profile.record(class, method, counts);
+        result
+    };
+    if let Err(ref error) = result {
+        record_failure(class_table, error);
+    }
+    result
+}
+
+// Records where an error was raised while the frame it passes is still on the stack, to show
+// the stack trace of the error once it has unwound the interpreter.
+fn record_failure(class_table: &ClassTable, error: &InterpreterError) {
+    if !matches!(*error, InterpreterError::Exit(_)) {
+        class_table.stacks.record_failure();
+    }
+}
+
+fn run(stack_frame: &mut StackFrame,
+       method: &RuntimeMethod,
+       class: &Arc<RuntimeClass>,
+       class_table: &ClassTable,
+       frame: &FrameGuard,
+       pc: u16,
+       mut counts: Option<&mut FrameCounts>) -> Result<InvokeResult, InterpreterError> {
+    let code = method.code.as_ref().ok_or(InterpreterError::EndOfCode)?;
+    let mut current_index = code.instruction_position(pc).ok_or(InterpreterError::InvalidBranch(pc))?;
+
+    loop {
+        let tagged_instruction = code.instructions.get(current_index).ok_or(InterpreterError::EndOfCode)?;
+        frame.set_pc(tagged_instruction.index);
+        if class_table.debugger.is_enabled() {
+            class_table.debugger.before_instruction(class_table, class, method, tagged_instruction.index, stack_frame)?;
+        }
+        if class_table.jdwp.is_enabled() {
+            class_table.jdwp.before_instruction(class_table, class, method, tagged_instruction.index);
+        }
+        if class_table.trace.is_enabled() {
+            class_table.trace.instruction(class, method, class_table.stacks.depth(), tagged_instruction.index, &tagged_instruction.instruction);
+        }
+
+        let step = interpret_instruction(
             &tagged_instruction.instruction,
             stack_frame,
             class,
             class_table
-        );
-
-        match result {
-            Ok(step) => {
-                match step {
-                    Step::Next => {
-                        if current_index == end_index {
-                            done = true;
-                        } else {
-                            current_index += 1;
-                        }
-                    },
-                    Step::Jump(offset) => {
-                        let current_code_index = tagged_instruction.index;
-                        let branch_code_index = ((current_code_index as i16) + offset) as u16;
-                        let next_index = method.code.instructions
-                            .iter()
-                            .position(|&t| t.index == branch_code_index)
-                            .unwrap() as u16;
-                        current_index = next_index;
-                    },
-                    Step::Return(value) => {
-                        println!("{:?}", stack_frame);
-                        println!("{:?}", std::mem::size_of::<Rc<RefCell<Vec<i32>>>>());
-                        return Some(InvokeResult::Value(value));
-                    },
-                    Step::ReturnVoid => {
-                        println!("{:?}", stack_frame);
-                        println!("{:?}", std::mem::size_of::<Rc<RefCell<Vec<i32>>>>());
-                        return Some(InvokeResult::Void);
+        )?;
+
+        if let Some(ref mut counts) = counts {
+            count(counts, current_index, &tagged_instruction.instruction, &step, stack_frame);
+        }
+        if class_table.trace.is_enabled() {
+            trace(class_table, class, method, tagged_instruction, &step, stack_frame);
+        }
+
+        match step {
+            Step::Next => {
+                current_index += 1;
+            },
+            Step::Jump(offset) => {
+                // Backward branches are safepoints, so that a loop that does not allocate cannot
+                // hold up a garbage collection.
+                if offset <= 0 {
+                    class_table.heap.poll();
+                }
+                let current_code_index = tagged_instruction.index;
+                let branch_code_index = ((current_code_index as i32) + offset) as u16;
+                current_index = code.instruction_position(branch_code_index)
+                    .ok_or(InterpreterError::InvalidBranch(branch_code_index))?;
+            },
+            Step::Return(value) => {
+                return Ok(InvokeResult::Value(value));
+            },
+            Step::ReturnVoid => {
+                return Ok(InvokeResult::Void);
+            },
+            Step::Exception(exception) => {
+                let handler = find_exception_handler(code, tagged_instruction.index, &exception, class, class_table)?;
+
+                match handler {
+                    Some(handler_pc) => {
+                        stack_frame.stack.clear();
+                        stack_frame.push(exception);
+                        current_index = code.instruction_position(handler_pc)
+                            .ok_or(InterpreterError::InvalidBranch(handler_pc))?;
                     },
-                    Step::Exception(value) => {
-                        // TODO: Search in exception handler table.
+                    None => {
+                        return Ok(InvokeResult::Exception(exception));
                     }
                 }
-            },
-            Err(e) => {
-                println!("{:?}", e);
-                return None; // TODO: Fix
             }
         }
     }
+}
+
+fn count(counts: &mut FrameCounts, position: usize, instruction: &Instruction, step: &Step, stack_frame: &StackFrame) {
+    counts.executed(position);
+    if profile::is_conditional_branch(instruction) {
+        counts.branched(position, matches!(step, Step::Jump(_)));
+    } else if profile::is_allocation(instruction) && matches!(step, Step::Next) {
+        match stack_frame.stack.last() {
+            Some(Value::ObjectRef(object)) => counts.allocated(&object.borrow().class().class_name),
+            Some(Value::ArrayRef(array)) => counts.allocated(&array.borrow().descriptor()),
+            _ => {}
+        }
+    }
+}
+
+// Tells the tracers of allocations, and of exceptions thrown by the instruction rather than by a
+// method it invoked, which has been traced already.
+fn trace(class_table: &ClassTable,
+         class: &Arc<RuntimeClass>,
+         method: &RuntimeMethod,
+         tagged_instruction: &TaggedInstruction,
+         step: &Step,
+         stack_frame: &StackFrame) {
+    let instruction = &tagged_instruction.instruction;
+    match *step {
+        Step::Next if profile::is_allocation(instruction) => {
+            if let Some(value) = stack_frame.stack.last() {
+                class_table.trace.allocation(class, method, class_table.stacks.depth(), tagged_instruction.index, value);
+            }
+        },
+        Step::Exception(ref exception) if !is_invocation(instruction) => {
+            class_table.trace.exception_thrown(class, method, class_table.stacks.depth(), Some(tagged_instruction.index), exception);
+        },
+        _ => {}
+    }
+}
 
-    // TODO: This line of code should never be reached, so we should return a proper error here.
-    return None;
+fn is_invocation(instruction: &Instruction) -> bool {
+    matches!(instruction,
+        Instruction::Invokevirtual { .. } | Instruction::Invokespecial { .. } |
+        Instruction::Invokestatic { .. } | Instruction::Invokeinterface { .. } |
+        Instruction::Invokedynamic { .. })
+}
+
+// Searches the exception table for a handler covering the given pc, see JVMS $2.10.
+fn find_exception_handler(code: &Code,
+                          pc: u16,
+                          exception: &Value,
+                          class: &Arc<RuntimeClass>,
+                          class_table: &ClassTable) -> Result<Option<u16>, InterpreterError> {
+    for entry in code.exception_table.iter() {
+        if pc < entry.start_pc || pc >= entry.end_pc {
+            continue;
+        }
+
+        if entry.catch_type == 0 {
+            return Ok(Some(entry.handler_pc));
+        }
+
+        let catch_class_name = class.constant_pool.get_class_name(entry.catch_type)
+            .map_err(|_| InterpreterError::InvalidConstant(entry.catch_type))?;
+
+        if is_instance_of(exception, &catch_class_name, class_table) {
+            return Ok(Some(entry.handler_pc));
+        }
+    }
+
+    Ok(None)
+}
+
+pub fn resolve_class(class_name: &str, class_table: &ClassTable) -> Result<Arc<RuntimeClass>, InterpreterError> {
+    match class_table.find_class(class_name) {
+        Ok(Some(class)) => Ok(class),
+        Ok(None) => Err(InterpreterError::ClassNotFound(String::from(class_name))),
+        Err(e) => Err(InterpreterError::ClassFormat(e))
+    }
+}
+
+// Resolves a class named by the constant pool of the running code (JVMS $5.4.3.1). The inner
+// error is the NoClassDefFoundError thrown if it cannot be found.
+pub fn link_class(class_name: &str, class_table: &ClassTable) -> Result<Result<Arc<RuntimeClass>, Value>, InterpreterError> {
+    match class_table.find_class(class_name) {
+        Ok(Some(class)) => Ok(Ok(class)),
+        Ok(None) => new_throwable(class_table, "java/lang/NoClassDefFoundError", Some(class_name)).map(Err),
+        Err(e) => Err(InterpreterError::ClassFormat(e))
+    }
+}
+
+// Resolves the method of invokespecial and invokestatic from the class named by the method
+// reference (JVMS $5.4.3.3). The inner error is the NoClassDefFoundError or NoSuchMethodError
+// thrown if the class or the method cannot be found.
+fn link_method(class_name: &str,
+               name: &str,
+               descriptor: &str,
+               class_table: &ClassTable) -> Result<Result<Arc<RuntimeClass>, Value>, InterpreterError> {
+    let referenced_class = match link_class(class_name, class_table)? {
+        Ok(referenced_class) => referenced_class,
+        Err(exception) => return Ok(Err(exception))
+    };
+    match RuntimeClass::resolve_method(&referenced_class, name, descriptor) {
+        Some(declaring_class) => Ok(Ok(declaring_class)),
+        None => no_such_method(class_table, class_name, name, descriptor).map(Err)
+    }
+}
+
+// e.g. java.lang.NoSuchMethodError: Evolved.removed()V
+fn no_such_method(class_table: &ClassTable, class_name: &str, name: &str, descriptor: &str) -> Result<Value, InterpreterError> {
+    let message = format!("{}.{}{}", class_name.replace('/', "."), name, descriptor);
+    new_throwable(class_table, "java/lang/NoSuchMethodError", Some(&message))
+}
+
+// Runs the static initializer of a class and its superclasses, see JVMS $5.5. Returns the
+// exception thrown by the initializer, if any.
+pub fn initialize_class(class: &Arc<RuntimeClass>, class_table: &ClassTable) -> Result<Option<Value>, InterpreterError> {
+    // A class being initialized by the current thread is treated as initialized, which is what
+    // lets <clinit> use the class it initializes.
+    if class.state() == ClassState::Initialized {
+        return Ok(None);
+    }
+
+    match class_table.heap.blocking(|| class.claim_initialization()) {
+        ClassState::Initialized | ClassState::BeingInitialized(_) => return Ok(None),
+        ClassState::Erroneous => {
+            let exception = new_throwable(class_table, "java/lang/NoClassDefFoundError", Some(&class.class_name))?;
+            return Ok(Some(exception));
+        },
+        ClassState::Linked => {}
+    }
+
+    if !class.is_interface() {
+        if let Some(ref super_class) = class.super_class {
+            if let Some(exception) = initialize_class(super_class, class_table)? {
+                class.set_state(ClassState::Erroneous);
+                return Ok(Some(exception));
+            }
+        }
+    }
+
+    for field in class.fields.iter().filter(|field| field.is_static()) {
+        if let Some(index) = field.constant_value_index {
+            let value = load_constant(index, class, class_table)?;
+            class.put_static(&field.name, value);
+        }
+    }
+
+    if let Some(method) = class.get_declared_method("<clinit>", "()V") {
+        if let InvokeResult::Exception(exception) = invoke_method(class, method, Vec::new(), class_table)? {
+            class.set_state(ClassState::Erroneous);
+
+            if is_instance_of(&exception, "java/lang/Error", class_table) {
+                return Ok(Some(exception));
+            }
+
+            let scope = class_table.stacks.scope();
+            scope.hold(&exception);
+            let error = new_throwable(class_table, "java/lang/ExceptionInInitializerError", None)?;
+            if let Value::ObjectRef(ref object) = error {
+                object.borrow_mut().put_field(String::from("cause"), exception);
+            }
+            return Ok(Some(error));
+        }
+    }
+
+    class.set_state(ClassState::Initialized);
+    Ok(None)
+}
+
+// Creates an instance of a class on the heap, without running its constructor. The inner error
+// is the OutOfMemoryError thrown if the heap is full.
+pub fn allocate_object(class: &Arc<RuntimeClass>, class_table: &ClassTable) -> Result<Result<Arc<HeapCell<Object>>, Value>, InterpreterError> {
+    if !class_table.reserve(gc::object_size(class.instance_fields.len())) {
+        let error = new_throwable(class_table, "java/lang/OutOfMemoryError", Some(gc::OUT_OF_MEMORY))?;
+        return Ok(Err(error));
+    }
+
+    Ok(Ok(class_table.new_object(class)))
+}
+
+// Creates an instance of a Throwable class for an exception raised by the virtual machine itself.
+// Like HotSpot, it is initialized by the constructor taking the message, which fills in the
+// stack trace. If initializing the class or the constructor throws, that exception is returned
+// instead.
+pub fn new_throwable(class_table: &ClassTable, class_name: &str, message: Option<&str>) -> Result<Value, InterpreterError> {
+    let class = resolve_class(class_name, class_table)?;
+    let declaring_class = RuntimeClass::resolve_method(&class, "<init>", "(Ljava/lang/String;)V")
+        .ok_or_else(|| InterpreterError::MethodNotFound(format!("{}.<init>(Ljava/lang/String;)V", class_name)))?;
+    let constructor = declaring_class.get_declared_method("<init>", "(Ljava/lang/String;)V").unwrap();
+
+    let throwable = {
+        let scope = class_table.stacks.scope();
+        if let Some(exception) = initialize_class(&class, class_table)? {
+            return Ok(exception);
+        }
+        let throwable = Value::ObjectRef(class_table.new_object(&class));
+        scope.hold(&throwable);
+        let message = match message {
+            Some(message) => Value::ObjectRef(class_table.new_string(message.encode_utf16().collect())),
+            None => Value::Null
+        };
+        if let InvokeResult::Exception(exception) = invoke_method(&declaring_class, constructor, vec![throwable.clone(), message], class_table)? {
+            return Ok(exception);
+        }
+        throwable
+    };
+
+    // Kept in the scope of the caller, as its allocations are.
+    class_table.stacks.keep(&throwable);
+    Ok(throwable)
+}
+
+fn throw(class_table: &ClassTable, class_name: &str, message: Option<&str>) -> Result<Step, InterpreterError> {
+    let exception = new_throwable(class_table, class_name, message)?;
+    Ok(Step::Exception(exception))
+}
+
+// Whether a non-null value can be assigned to the named class or array type, as tested by
+// instanceof and checkcast (JVMS $6.5).
+pub fn is_instance_of(value: &Value, class_name: &str, class_table: &ClassTable) -> bool {
+    match value {
+        Value::ObjectRef(object) => object.borrow().class().is_subclass_of(class_name),
+        Value::ArrayRef(array) => is_array_assignable(&array.borrow().descriptor(), class_name, class_table),
+        _ => false
+    }
+}
+
+pub fn is_array_assignable(descriptor: &str, class_name: &str, class_table: &ClassTable) -> bool {
+    if !class_name.starts_with('[') {
+        return class_name == OBJECT_CLASS_NAME ||
+            class_name == "java/lang/Cloneable" ||
+            class_name == "java/io/Serializable";
+    }
+
+    let source = &descriptor[1..];
+    let target = &class_name[1..];
+
+    match (source.chars().next(), target.chars().next()) {
+        (Some('L'), Some('L')) => {
+            let source_name = &source[1..(source.len() - 1)];
+            let target_name = &target[1..(target.len() - 1)];
+            match class_table.get_class(source_name) {
+                Some(source_class) => source_class.is_subclass_of(target_name),
+                None => source_name == target_name
+            }
+        },
+        (Some('['), Some('L')) => is_array_assignable(source, &target[1..(target.len() - 1)], class_table),
+        (Some('['), Some('[')) => is_array_assignable(source, target, class_table),
+        _ => source == target
+    }
+}
+
+fn push_invoke_result(stack_frame: &mut StackFrame, result: InvokeResult) -> Step {
+    match result {
+        InvokeResult::Value(value) => {
+            stack_frame.push(value);
+            Step::Next
+        },
+        InvokeResult::Void => Step::Next,
+        InvokeResult::Exception(exception) => Step::Exception(exception)
+    }
+}
+
+// The arguments of an invocation, preceded by the receiver if it has one. They are left on the
+// operand stack until the invocation completes, so that they stay roots of the heap until the
+// invoked method has them in its own frame.
+fn peek_arguments(stack_frame: &StackFrame, descriptor: &str, receiver: bool) -> Result<Vec<Value>, InterpreterError> {
+    let method_descriptor = MethodDescriptor::parse(descriptor)
+        .ok_or(InterpreterError::UnexpectedOperand)?;
+    stack_frame.peek_many(method_descriptor.parameters_length() + receiver as usize)
+        .ok_or(InterpreterError::UnexpectedOperand)
+}
+
+// Pops the arguments of a completed invocation and pushes what it returned.
+fn complete_invoke(stack_frame: &mut StackFrame, arguments: usize, result: InvokeResult) -> Step {
+    stack_frame.drop_many(arguments);
+    push_invoke_result(stack_frame, result)
+}
+
+// Pops values until the given number of stack slots (JVMS $2.6.2) have been removed. The values
+// are returned in the order they were pushed.
+fn pop_slots(stack_frame: &mut StackFrame, slots: usize) -> Result<Vec<Value>, InterpreterError> {
+    let mut values = Vec::new();
+    let mut popped = 0;
+
+    while popped < slots {
+        let value = stack_frame.pop().ok_or(InterpreterError::UnexpectedOperand)?;
+        popped += if value.is_category2() { 2 } else { 1 };
+        values.insert(0, value);
+    }
+
+    Ok(values)
+}
+
+fn push_all(stack_frame: &mut StackFrame, values: &[Value]) {
+    for value in values.iter() {
+        stack_frame.push(value.clone());
+    }
+}
+
+fn compare<T: PartialOrd>(value1: T, value2: T, nan_result: i32) -> i32 {
+    if value1 > value2 {
+        1
+    } else if value1 == value2 {
+        0
+    } else if value1 < value2 {
+        -1
+    } else {
+        nan_result
+    }
+}
+
+fn array_load(stack_frame: &mut StackFrame, class_table: &ClassTable) -> Result<Step, InterpreterError> {
+    let index = stack_frame.pop_int()?;
+    let array = match stack_frame.pop_array()? {
+        Some(array) => array,
+        None => return throw(class_table, "java/lang/NullPointerException", None)
+    };
+    let array = array.borrow();
+
+    if index < 0 || index as usize >= array.len() {
+        let message = format!("Index {} out of bounds for length {}", index, array.len());
+        return throw(class_table, "java/lang/ArrayIndexOutOfBoundsException", Some(&message));
+    }
+
+    stack_frame.push(array.get(index as usize));
+    Ok(Step::Next)
+}
+
+fn array_store(stack_frame: &mut StackFrame, class_table: &ClassTable) -> Result<Step, InterpreterError> {
+    let value = stack_frame.pop().ok_or(InterpreterError::UnexpectedOperand)?;
+    let index = stack_frame.pop_int()?;
+    let array = match stack_frame.pop_array()? {
+        Some(array) => array,
+        None => return throw(class_table, "java/lang/NullPointerException", None)
+    };
+    // The array is not locked during the type check, since an array may be stored into itself.
+    let (length, component_type) = {
+        let array = array.borrow();
+        (array.len(), String::from(array.component_type()))
+    };
+
+    if index < 0 || index as usize >= length {
+        let message = format!("Index {} out of bounds for length {}", index, length);
+        return throw(class_table, "java/lang/ArrayIndexOutOfBoundsException", Some(&message));
+    }
+
+    if let Value::Null = value {
+    } else if value.is_reference() {
+        let component_class = component_class_name(&component_type);
+        if !is_instance_of(&value, component_class, class_table) {
+            return throw(class_table, "java/lang/ArrayStoreException", None);
+        }
+    }
+
+    let mut array = array.borrow_mut();
+    array.set(index as usize, value)?;
+    if let Elements::Reference(elements) = array.elements() {
+        class_table.heap.write_barrier(&elements[index as usize]);
+    }
+    Ok(Step::Next)
+}
+
+// The class name used by instanceof for an array component descriptor: "Ljava/lang/String;" is
+// java/lang/String, while array components such as "[I" are their own class names.
+fn component_class_name(component_type: &str) -> &str {
+    if component_type.starts_with('L') {
+        &component_type[1..(component_type.len() - 1)]
+    } else {
+        component_type
+    }
+}
+
+// The array is not borrowed while its components are allocated, as allocating may collect it,
+// and is held in a scope until it is on the operand stack.
+fn new_multi_array(descriptor: &str, counts: &[i32], class_table: &ClassTable) -> Value {
+    let component_type = &descriptor[1..];
+    let array = class_table.new_array(component_type, counts[0] as usize);
+    let scope = class_table.stacks.scope();
+    scope.hold(&Value::ArrayRef(array.clone()));
+
+    if counts.len() > 1 {
+        for index in 0..(counts[0] as usize) {
+            let component = new_multi_array(component_type, &counts[1..], class_table);
+            array.borrow_mut().set(index, component).unwrap();
+        }
+    }
+
+    Value::ArrayRef(array)
+}
+
+// The size of all the arrays created by new_multi_array.
+fn multi_array_size(descriptor: &str, counts: &[i32]) -> usize {
+    let mut size = 0usize;
+    let mut arrays = 1usize;
+    let mut component_type = descriptor;
+
+    for count in counts.iter() {
+        component_type = &component_type[1..];
+        size = size.saturating_add(arrays.saturating_mul(gc::array_size(component_type, *count as usize)));
+        arrays = arrays.saturating_mul(*count as usize);
+    }
+
+    size
+}
+
+fn branch_if(condition: bool, branch_offset: i16) -> Result<Step, InterpreterError> {
+    if condition {
+        Ok(Step::Jump(branch_offset as i32))
+    } else {
+        Ok(Step::Next)
+    }
+}
+
+fn load_local(stack_frame: &mut StackFrame, index: usize) -> Result<Step, InterpreterError> {
+    let operand = stack_frame.get_local(index).clone();
+    stack_frame.push(operand);
+    Ok(Step::Next)
+}
+
+fn store_local(stack_frame: &mut StackFrame, index: usize) -> Result<Step, InterpreterError> {
+    let operand = stack_frame.pop().ok_or(InterpreterError::UnexpectedOperand)?;
+    stack_frame.set_local(index, operand);
+    Ok(Step::Next)
 }
 
 fn interpret_instruction(instruction: &Instruction,
                          stack_frame: &mut StackFrame,
-                         class: &Rc<RuntimeClass>,
+                         class: &Arc<RuntimeClass>,
                          class_table: &ClassTable) -> Result<Step, InterpreterError> {
     match instruction {
+        Instruction::Aaload => array_load(stack_frame, class_table),
+        Instruction::Aastore => array_store(stack_frame, class_table),
         Instruction::AconstNull => {
             stack_frame.push(Value::Null);
             Ok(Step::Next)
@@ -123,97 +755,380 @@ fn interpret_instruction(instruction: &Instruction,
         Instruction::Aload { index } => {
             // It's not necessary to type check perhaps?
             // The typed instructions should really be used for knowing how many bytes to read/write.
-            let operand = stack_frame.get_local(*index as usize).clone();
-            stack_frame.push(operand);
+            load_local(stack_frame, *index as usize)
+        },
+        Instruction::Aload0 => load_local(stack_frame, 0),
+        Instruction::Aload1 => load_local(stack_frame, 1),
+        Instruction::Aload2 => load_local(stack_frame, 2),
+        Instruction::Aload3 => load_local(stack_frame, 3),
+        Instruction::Anewarray { index } => {
+            let count = stack_frame.pop_int()?;
+            if count < 0 {
+                return throw(class_table, "java/lang/NegativeArraySizeException", Some(&count.to_string()));
+            }
+
+            let class_name = get_class_name(class, *index)?;
+            let component_type = class_name_to_descriptor(&class_name);
+            if !class_table.reserve(gc::array_size(&component_type, count as usize)) {
+                return throw(class_table, "java/lang/OutOfMemoryError", Some(gc::OUT_OF_MEMORY));
+            }
+
+            let array = Value::ArrayRef(class_table.new_array(&component_type, count as usize));
+            stack_frame.push(array);
             Ok(Step::Next)
         },
-        Instruction::Aload0 => {
-            let operand = stack_frame.get_local(0).clone();
-            stack_frame.push(operand);
+        Instruction::Areturn => {
+            let value = stack_frame.pop_reference()?;
+            Ok(Step::Return(value))
+        },
+        Instruction::Arraylength => {
+            match stack_frame.pop_array()? {
+                Some(array) => {
+                    let length = array.borrow().len() as i32;
+                    stack_frame.push_int(length);
+                    Ok(Step::Next)
+                },
+                None => throw(class_table, "java/lang/NullPointerException", None)
+            }
+        },
+        Instruction::Astore { index } => store_local(stack_frame, *index as usize),
+        Instruction::Astore0 => store_local(stack_frame, 0),
+        Instruction::Astore1 => store_local(stack_frame, 1),
+        Instruction::Astore2 => store_local(stack_frame, 2),
+        Instruction::Astore3 => store_local(stack_frame, 3),
+        Instruction::Athrow => {
+            let exception = stack_frame.pop_reference()?;
+            match exception {
+                Value::Null => throw(class_table, "java/lang/NullPointerException", None),
+                exception => Ok(Step::Exception(exception))
+            }
+        },
+        Instruction::Baload => array_load(stack_frame, class_table),
+        Instruction::Bastore => array_store(stack_frame, class_table),
+        Instruction::Bipush { byte } => {
+            let value = *byte as i32;
+            stack_frame.push_int(value);
             Ok(Step::Next)
         },
-        Instruction::Aload0 => {
-            let operand = stack_frame.get_local(0).clone();
-            stack_frame.push(operand);
+        Instruction::Caload => array_load(stack_frame, class_table),
+        Instruction::Castore => array_store(stack_frame, class_table),
+        Instruction::Checkcast { index } => {
+            let class_name = get_class_name(class, *index)?;
+            let reference = stack_frame.pop_reference()?;
+
+            if let Value::Null = reference {
+            } else if !is_instance_of(&reference, &class_name, class_table) {
+                let message = format!("{} cannot be cast to {}", runtime_class_name(&reference), class_name.replace('/', "."));
+                return throw(class_table, "java/lang/ClassCastException", Some(&message));
+            }
+
+            stack_frame.push(reference);
             Ok(Step::Next)
         },
-        Instruction::Aload1 => {
-            let operand = stack_frame.get_local(1).clone();
-            stack_frame.push(operand);
+        Instruction::D2f => {
+            let value = stack_frame.pop_double()?;
+            stack_frame.push_float(value as f32);
             Ok(Step::Next)
         },
-        Instruction::Aload2 => {
-            let operand = stack_frame.get_local(2).clone();
-            stack_frame.push(operand);
+        Instruction::D2i => {
+            // Rust float to int casts saturate and map NaN to zero, as required by JVMS $2.8.3.
+            let value = stack_frame.pop_double()?;
+            stack_frame.push_int(value as i32);
             Ok(Step::Next)
         },
-        Instruction::Aload3 => {
-            let operand = stack_frame.get_local(3).clone();
-            stack_frame.push(operand);
+        Instruction::D2l => {
+            let value = stack_frame.pop_double()?;
+            stack_frame.push_long(value as i64);
             Ok(Step::Next)
         },
-        Instruction::Astore0 => {
-            let operand = stack_frame.pop().unwrap();
-            stack_frame.set_local(0, operand);
+        Instruction::Dadd => {
+            let value2 = stack_frame.pop_double()?;
+            let value1 = stack_frame.pop_double()?;
+            stack_frame.push_double(value1 + value2);
             Ok(Step::Next)
         },
-        Instruction::Astore1 => {
-            let operand = stack_frame.pop().unwrap();
-            stack_frame.set_local(1, operand);
+        Instruction::Daload => array_load(stack_frame, class_table),
+        Instruction::Dastore => array_store(stack_frame, class_table),
+        Instruction::Dcmpg => {
+            let value2 = stack_frame.pop_double()?;
+            let value1 = stack_frame.pop_double()?;
+            stack_frame.push_int(compare(value1, value2, 1));
             Ok(Step::Next)
         },
-        Instruction::Astore2 => {
-            let operand = stack_frame.pop().unwrap();
-            stack_frame.set_local(2, operand);
+        Instruction::Dcmpl => {
+            let value2 = stack_frame.pop_double()?;
+            let value1 = stack_frame.pop_double()?;
+            stack_frame.push_int(compare(value1, value2, -1));
             Ok(Step::Next)
         },
-        Instruction::Astore3 => {
-            let operand = stack_frame.pop().unwrap();
-            stack_frame.set_local(3, operand);
+        Instruction::Dconst0 => {
+            stack_frame.push_double(0.0);
             Ok(Step::Next)
         },
-        Instruction::Bipush { byte } => {
-            let value = *byte as i32;
-            stack_frame.push_int(value);
+        Instruction::Dconst1 => {
+            stack_frame.push_double(1.0);
+            Ok(Step::Next)
+        },
+        Instruction::Ddiv => {
+            let value2 = stack_frame.pop_double()?;
+            let value1 = stack_frame.pop_double()?;
+            stack_frame.push_double(value1 / value2);
+            Ok(Step::Next)
+        },
+        Instruction::Dload { index } => load_local(stack_frame, *index as usize),
+        Instruction::Dload0 => load_local(stack_frame, 0),
+        Instruction::Dload1 => load_local(stack_frame, 1),
+        Instruction::Dload2 => load_local(stack_frame, 2),
+        Instruction::Dload3 => load_local(stack_frame, 3),
+        Instruction::Dmul => {
+            let value2 = stack_frame.pop_double()?;
+            let value1 = stack_frame.pop_double()?;
+            stack_frame.push_double(value1 * value2);
+            Ok(Step::Next)
+        },
+        Instruction::Dneg => {
+            let value = stack_frame.pop_double()?;
+            stack_frame.push_double(-value);
+            Ok(Step::Next)
+        },
+        Instruction::Drem => {
+            let value2 = stack_frame.pop_double()?;
+            let value1 = stack_frame.pop_double()?;
+            stack_frame.push_double(value1 % value2);
+            Ok(Step::Next)
+        },
+        Instruction::Dreturn => {
+            let value = stack_frame.pop_double()?;
+            Ok(Step::Return(Value::Double(value)))
+        },
+        Instruction::Dstore { index } => store_local(stack_frame, *index as usize),
+        Instruction::Dstore0 => store_local(stack_frame, 0),
+        Instruction::Dstore1 => store_local(stack_frame, 1),
+        Instruction::Dstore2 => store_local(stack_frame, 2),
+        Instruction::Dstore3 => store_local(stack_frame, 3),
+        Instruction::Dsub => {
+            let value2 = stack_frame.pop_double()?;
+            let value1 = stack_frame.pop_double()?;
+            stack_frame.push_double(value1 - value2);
             Ok(Step::Next)
         },
         Instruction::Dup => {
             // TODO: Do we need to clone twice here, or is once sufficient?
             // What ends up happening is that we clone it twice and move those out.
             // The original one is freed when we exit the scope.
-            let operand = stack_frame.pop().unwrap();
-            stack_frame.push(operand.clone());
+            let operand = stack_frame.pop().ok_or(InterpreterError::UnexpectedOperand)?;
             stack_frame.push(operand.clone());
+            stack_frame.push(operand);
+            Ok(Step::Next)
+        },
+        Instruction::DupX1 => {
+            let value1 = pop_slots(stack_frame, 1)?;
+            let value2 = pop_slots(stack_frame, 1)?;
+            push_all(stack_frame, &value1);
+            push_all(stack_frame, &value2);
+            push_all(stack_frame, &value1);
+            Ok(Step::Next)
+        },
+        Instruction::DupX2 => {
+            let value1 = pop_slots(stack_frame, 1)?;
+            let value2 = pop_slots(stack_frame, 2)?;
+            push_all(stack_frame, &value1);
+            push_all(stack_frame, &value2);
+            push_all(stack_frame, &value1);
+            Ok(Step::Next)
+        },
+        Instruction::Dup2 => {
+            let value1 = pop_slots(stack_frame, 2)?;
+            push_all(stack_frame, &value1);
+            push_all(stack_frame, &value1);
+            Ok(Step::Next)
+        },
+        Instruction::Dup2X1 => {
+            let value1 = pop_slots(stack_frame, 2)?;
+            let value2 = pop_slots(stack_frame, 1)?;
+            push_all(stack_frame, &value1);
+            push_all(stack_frame, &value2);
+            push_all(stack_frame, &value1);
+            Ok(Step::Next)
+        },
+        Instruction::Dup2X2 => {
+            let value1 = pop_slots(stack_frame, 2)?;
+            let value2 = pop_slots(stack_frame, 2)?;
+            push_all(stack_frame, &value1);
+            push_all(stack_frame, &value2);
+            push_all(stack_frame, &value1);
+            Ok(Step::Next)
+        },
+        Instruction::F2d => {
+            let value = stack_frame.pop_float()?;
+            stack_frame.push_double(value as f64);
+            Ok(Step::Next)
+        },
+        Instruction::F2i => {
+            let value = stack_frame.pop_float()?;
+            stack_frame.push_int(value as i32);
+            Ok(Step::Next)
+        },
+        Instruction::F2l => {
+            let value = stack_frame.pop_float()?;
+            stack_frame.push_long(value as i64);
+            Ok(Step::Next)
+        },
+        Instruction::Fadd => {
+            let value2 = stack_frame.pop_float()?;
+            let value1 = stack_frame.pop_float()?;
+            stack_frame.push_float(value1 + value2);
+            Ok(Step::Next)
+        },
+        Instruction::Faload => array_load(stack_frame, class_table),
+        Instruction::Fastore => array_store(stack_frame, class_table),
+        Instruction::Fcmpg => {
+            let value2 = stack_frame.pop_float()?;
+            let value1 = stack_frame.pop_float()?;
+            stack_frame.push_int(compare(value1, value2, 1));
+            Ok(Step::Next)
+        },
+        Instruction::Fcmpl => {
+            let value2 = stack_frame.pop_float()?;
+            let value1 = stack_frame.pop_float()?;
+            stack_frame.push_int(compare(value1, value2, -1));
+            Ok(Step::Next)
+        },
+        Instruction::Fconst0 => {
+            stack_frame.push_float(0.0);
+            Ok(Step::Next)
+        },
+        Instruction::Fconst1 => {
+            stack_frame.push_float(1.0);
+            Ok(Step::Next)
+        },
+        Instruction::Fconst2 => {
+            stack_frame.push_float(2.0);
+            Ok(Step::Next)
+        },
+        Instruction::Fdiv => {
+            let value2 = stack_frame.pop_float()?;
+            let value1 = stack_frame.pop_float()?;
+            stack_frame.push_float(value1 / value2);
+            Ok(Step::Next)
+        },
+        Instruction::Fload { index } => load_local(stack_frame, *index as usize),
+        Instruction::Fload0 => load_local(stack_frame, 0),
+        Instruction::Fload1 => load_local(stack_frame, 1),
+        Instruction::Fload2 => load_local(stack_frame, 2),
+        Instruction::Fload3 => load_local(stack_frame, 3),
+        Instruction::Fmul => {
+            let value2 = stack_frame.pop_float()?;
+            let value1 = stack_frame.pop_float()?;
+            stack_frame.push_float(value1 * value2);
+            Ok(Step::Next)
+        },
+        Instruction::Fneg => {
+            let value = stack_frame.pop_float()?;
+            stack_frame.push_float(-value);
+            Ok(Step::Next)
+        },
+        Instruction::Frem => {
+            let value2 = stack_frame.pop_float()?;
+            let value1 = stack_frame.pop_float()?;
+            stack_frame.push_float(value1 % value2);
+            Ok(Step::Next)
+        },
+        Instruction::Freturn => {
+            let value = stack_frame.pop_float()?;
+            Ok(Step::Return(Value::Float(value)))
+        },
+        Instruction::Fstore { index } => store_local(stack_frame, *index as usize),
+        Instruction::Fstore0 => store_local(stack_frame, 0),
+        Instruction::Fstore1 => store_local(stack_frame, 1),
+        Instruction::Fstore2 => store_local(stack_frame, 2),
+        Instruction::Fstore3 => store_local(stack_frame, 3),
+        Instruction::Fsub => {
+            let value2 = stack_frame.pop_float()?;
+            let value1 = stack_frame.pop_float()?;
+            stack_frame.push_float(value1 - value2);
+            Ok(Step::Next)
+        },
+        Instruction::Getfield { index } => {
+            let field_ref = class.constant_pool.get_field_ref(*index)
+                .map_err(|_| InterpreterError::InvalidConstant(*index))?;
+            let object_reference = match stack_frame.pop_reference()? {
+                Value::ObjectRef(object_reference) => object_reference,
+                Value::Null => return throw(class_table, "java/lang/NullPointerException", None),
+                _ => return Err(InterpreterError::UnexpectedOperand)
+            };
+            let position = match resolve_instance_field(&field_ref.class_name, &field_ref.name_and_type.name, class_table)? {
+                Ok(position) => position,
+                Err(exception) => return Ok(Step::Exception(exception))
+            };
+            let value = object_reference.borrow().fields()[position].clone();
+            stack_frame.push(value);
+            Ok(Step::Next)
+        },
+        Instruction::Getstatic { index } => {
+            let field_ref = class.constant_pool.get_field_ref(*index)
+                .map_err(|_| InterpreterError::InvalidConstant(*index))?;
+            let declaring_class = match resolve_static_field(&field_ref.class_name, &field_ref.name_and_type.name, class_table)? {
+                Ok(declaring_class) => declaring_class,
+                Err(exception) => return Ok(Step::Exception(exception))
+            };
+            let value = declaring_class.get_static(&field_ref.name_and_type.name).unwrap();
+            stack_frame.push(value);
+            Ok(Step::Next)
+        },
+        Instruction::Goto { branch_offset } => {
+            Ok(Step::Jump(*branch_offset as i32))
+        },
+        Instruction::GotoW(byte1, byte2, byte3, byte4) => {
+            let branch_offset = i32::from_be_bytes([*byte1, *byte2, *byte3, *byte4]);
+            Ok(Step::Jump(branch_offset))
+        },
+        Instruction::I2b => {
+            let value = stack_frame.pop_int()?;
+            stack_frame.push_int(value as i8 as i32);
+            Ok(Step::Next)
+        },
+        Instruction::I2c => {
+            let value = stack_frame.pop_int()?;
+            stack_frame.push_int(value as u16 as i32);
+            Ok(Step::Next)
+        },
+        Instruction::I2d => {
+            let value = stack_frame.pop_int()?;
+            stack_frame.push_double(value as f64);
+            Ok(Step::Next)
+        },
+        Instruction::I2f => {
+            let value = stack_frame.pop_int()?;
+            stack_frame.push_float(value as f32);
             Ok(Step::Next)
         },
-        Instruction::Getfield { index } => {
-            let object_reference = stack_frame.pop_object_reference()?;
-            let field_ref = class.constant_pool.get_field_ref(*index).unwrap();
-            let value = object_reference.borrow_mut().get_field(field_ref.name_and_type.name);
-            stack_frame.push(value);
+        Instruction::I2l => {
+            let value = stack_frame.pop_int()?;
+            stack_frame.push_long(value as i64);
             Ok(Step::Next)
         },
-        Instruction::Goto { branch_offset } => {
-            Ok(Step::Jump(*branch_offset))
+        Instruction::I2s => {
+            let value = stack_frame.pop_int()?;
+            stack_frame.push_int(value as i16 as i32);
+            Ok(Step::Next)
         },
         Instruction::Iadd => {
             let v2 = stack_frame.pop_int()?;
             let v1 = stack_frame.pop_int()?;
-            stack_frame.push_int(v1 + v2);
+            stack_frame.push_int(v1.wrapping_add(v2));
             Ok(Step::Next)
         },
-        Instruction::Iaload => {
-            let index = stack_frame.pop_int()?;
-            let array = stack_frame.pop_int_array()?;
-            let value = array.borrow().get(index as usize);
-            stack_frame.push_int(value);
+        Instruction::Iaload => array_load(stack_frame, class_table),
+        Instruction::Iand => {
+            let value2 = stack_frame.pop_int()?;
+            let value1 = stack_frame.pop_int()?;
+            stack_frame.push_int(value1 & value2);
             Ok(Step::Next)
         },
-        Instruction::Iastore => {
-            let value = stack_frame.pop_int()?;
-            let index = stack_frame.pop_int()?;
-            let mut array = stack_frame.pop_int_array()?;
-            array.borrow_mut().set(index as usize, value);
+        Instruction::Iastore => array_store(stack_frame, class_table),
+        Instruction::IconstM1 => {
+            stack_frame.push_int(-1);
             Ok(Step::Next)
         },
         Instruction::Iconst0 => {
@@ -240,145 +1155,98 @@ fn interpret_instruction(instruction: &Instruction,
             stack_frame.push_int(5);
             Ok(Step::Next)
         },
-        Instruction::IfAcmpeq { branch_offset } => {
-            let value2 = stack_frame.pop_object_reference()?;
-            let value1 = stack_frame.pop_object_reference()?;
-            if Rc::ptr_eq(&value1, &value2) {
-                Ok(Step::Jump(*branch_offset))
-            } else {
-                Ok(Step::Next)
+        Instruction::Idiv => {
+            let value2 = stack_frame.pop_int()?;
+            let value1 = stack_frame.pop_int()?;
+            if value2 == 0 {
+                return throw(class_table, "java/lang/ArithmeticException", Some("/ by zero"));
             }
+            stack_frame.push_int(value1.wrapping_div(value2));
+            Ok(Step::Next)
+        },
+        Instruction::IfAcmpeq { branch_offset } => {
+            let value2 = stack_frame.pop_reference()?;
+            let value1 = stack_frame.pop_reference()?;
+            branch_if(value1.same_reference(&value2), *branch_offset)
         },
         Instruction::IfAcmpne { branch_offset } => {
-            let value2 = stack_frame.pop_object_reference()?;
-            let value1 = stack_frame.pop_object_reference()?;
-            if Rc::ptr_eq(&value1, &value2) {
-                Ok(Step::Next)
-            } else {
-                Ok(Step::Jump(*branch_offset))
-            }
+            let value2 = stack_frame.pop_reference()?;
+            let value1 = stack_frame.pop_reference()?;
+            branch_if(!value1.same_reference(&value2), *branch_offset)
         },
         Instruction::IfIcmpeq { branch_offset } => {
             let value2 = stack_frame.pop_int()?;
             let value1 = stack_frame.pop_int()?;
-            if value1 == value2 {
-                Ok(Step::Jump(*branch_offset))
-            } else {
-                Ok(Step::Next)
-            }
+            branch_if(value1 == value2, *branch_offset)
         },
         Instruction::IfIcmpne { branch_offset } => {
             let value2 = stack_frame.pop_int()?;
             let value1 = stack_frame.pop_int()?;
-            if value1 != value2 {
-                Ok(Step::Jump(*branch_offset))
-            } else {
-                Ok(Step::Next)
-            }
+            branch_if(value1 != value2, *branch_offset)
         },
         Instruction::IfIcmplt { branch_offset } => {
             let value2 = stack_frame.pop_int()?;
             let value1 = stack_frame.pop_int()?;
-            if value1 < value2 {
-                Ok(Step::Jump(*branch_offset))
-            } else {
-                Ok(Step::Next)
-            }
+            branch_if(value1 < value2, *branch_offset)
         },
         Instruction::IfIcmpge { branch_offset } => {
             let value2 = stack_frame.pop_int()?;
             let value1 = stack_frame.pop_int()?;
-            if value1 >= value2 {
-                Ok(Step::Jump(*branch_offset))
-            } else {
-                Ok(Step::Next)
-            }
+            branch_if(value1 >= value2, *branch_offset)
         },
         Instruction::IfIcmpgt { branch_offset } => {
             let value2 = stack_frame.pop_int()?;
             let value1 = stack_frame.pop_int()?;
-            if value1 > value2 {
-                Ok(Step::Jump(*branch_offset))
-            } else {
-                Ok(Step::Next)
-            }
+            branch_if(value1 > value2, *branch_offset)
         },
         Instruction::IfIcmple { branch_offset } => {
             let value2 = stack_frame.pop_int()?;
             let value1 = stack_frame.pop_int()?;
-            if value1 <= value2 {
-                Ok(Step::Jump(*branch_offset))
-            } else {
-                Ok(Step::Next)
-            }
+            branch_if(value1 <= value2, *branch_offset)
         },
         Instruction::Ifeq { branch_offset } => {
             let value = stack_frame.pop_int()?;
-            if value == 0 {
-                Ok(Step::Jump(*branch_offset))
-            } else {
-                Ok(Step::Next)
-            }
+            branch_if(value == 0, *branch_offset)
         },
         Instruction::Ifne { branch_offset } => {
             let value = stack_frame.pop_int()?;
-            if value != 0 {
-                Ok(Step::Jump(*branch_offset))
-            } else {
-                Ok(Step::Next)
-            }
+            branch_if(value != 0, *branch_offset)
         },
         Instruction::Iflt { branch_offset } => {
             let value = stack_frame.pop_int()?;
-            if value < 0 {
-                Ok(Step::Jump(*branch_offset))
-            } else {
-                Ok(Step::Next)
-            }
+            branch_if(value < 0, *branch_offset)
         },
         Instruction::Ifge { branch_offset } => {
             let value = stack_frame.pop_int()?;
-            if value >= 0 {
-                Ok(Step::Jump(*branch_offset))
-            } else {
-                Ok(Step::Next)
-            }
+            branch_if(value >= 0, *branch_offset)
         },
         Instruction::Ifgt { branch_offset } => {
             let value = stack_frame.pop_int()?;
-            if value > 0 {
-                Ok(Step::Jump(*branch_offset))
-            } else {
-                Ok(Step::Next)
-            }
+            branch_if(value > 0, *branch_offset)
         },
         Instruction::Ifle { branch_offset } => {
             let value = stack_frame.pop_int()?;
-            if value <= 0 {
-                Ok(Step::Jump(*branch_offset))
-            } else {
-                Ok(Step::Next)
-            }
+            branch_if(value <= 0, *branch_offset)
         },
-        Instruction::Ifnonnull { branch_offset} => {
-            let reference = stack_frame.pop().unwrap();
+        Instruction::Ifnonnull { branch_offset } => {
+            let reference = stack_frame.pop_reference()?;
 
             match reference {
                 Value::Null => Ok(Step::Next),
-                _ => Ok(Step::Jump(*branch_offset))
+                _ => Ok(Step::Jump(*branch_offset as i32))
             }
         },
-        Instruction::Ifnull { branch_offset} => {
-            let reference = stack_frame.pop().unwrap();
+        Instruction::Ifnull { branch_offset } => {
+            let reference = stack_frame.pop_reference()?;
 
             match reference {
-                Value::Null => Ok(Step::Jump(*branch_offset)),
+                Value::Null => Ok(Step::Jump(*branch_offset as i32)),
                 _ => Ok(Step::Next)
             }
         },
         Instruction::Iinc { index, constant } => {
             let local = stack_frame.get_int_local(*index as usize)?;
-            stack_frame.set_int_local(*index as usize, local + (*constant as i32));
+            stack_frame.set_int_local(*index as usize, local.wrapping_add(*constant as i32));
             Ok(Step::Next)
         },
         Instruction::Iload { index } => {
@@ -406,50 +1274,125 @@ fn interpret_instruction(instruction: &Instruction,
             stack_frame.push_int(int);
             Ok(Step::Next)
         },
-        Instruction::Invokespecial { index } => {
-            // TODO: Implement, will need to read method descriptor to determine how many operands to pull off?
-            // TODO: Remove
-            stack_frame.pop();
-            stack_frame.pop();
+        Instruction::Imul => {
+            let v2 = stack_frame.pop_int()?;
+            let v1 = stack_frame.pop_int()?;
+            stack_frame.push_int(v1.wrapping_mul(v2));
             Ok(Step::Next)
         },
-        Instruction::Invokevirtual { index } => {
-            let method_ref = class.constant_pool.get_method_ref(*index).unwrap();
-            let invoked_class = class_table.get_class(method_ref.class_name.as_str()).unwrap();
+        Instruction::Ineg => {
+            let value = stack_frame.pop_int()?;
+            stack_frame.push_int(value.wrapping_neg());
+            Ok(Step::Next)
+        },
+        Instruction::Instanceof { index } => {
+            let class_name = get_class_name(class, *index)?;
+            let reference = stack_frame.pop_reference()?;
+            let result = is_instance_of(&reference, &class_name, class_table);
+            stack_frame.push_int(result as i32);
+            Ok(Step::Next)
+        },
+        Instruction::Invokedynamic { index } => {
+            let call_site = match invokedynamic::link(*index, class, class_table)? {
+                Ok(call_site) => call_site,
+                Err(exception) => return Ok(Step::Exception(exception))
+            };
+
+            let arguments = peek_arguments(stack_frame, &call_site.descriptor, false)?;
+            let count = arguments.len();
+            let invoke_result = call_site.invoke(arguments, class_table)?;
+            Ok(complete_invoke(stack_frame, count, invoke_result))
+        },
+        Instruction::Invokeinterface { index, .. } => {
+            invoke_dynamically_dispatched(*index, stack_frame, class, class_table)
+        },
+        Instruction::Invokespecial { index } => {
+            // Constructors, private methods and superclass methods are selected statically, see
+            // JVMS $6.5.invokespecial.
+            let method_ref = class.constant_pool.get_method_ref(*index)
+                .map_err(|_| InterpreterError::InvalidConstant(*index))?;
+            let name = method_ref.name_and_type.name.as_str();
+            let descriptor = method_ref.name_and_type.descriptor.as_str();
 
-            // TODO: Verify access flags
+            let declaring_class = match link_method(&method_ref.class_name, name, descriptor, class_table)? {
+                Ok(declaring_class) => declaring_class,
+                Err(exception) => return Ok(Step::Exception(exception))
+            };
+            let method = declaring_class.get_declared_method(name, descriptor).unwrap();
 
-            let method = invoked_class.get_method(method_ref.name_and_type.name.as_str()).unwrap();
-            let method_descriptor = MethodDescriptor::parse(method_ref.name_and_type.descriptor.as_str()).unwrap();
+            let arguments = peek_arguments(stack_frame, descriptor, true)?;
+            match arguments.first() {
+                Some(Value::Null) => return throw(class_table, "java/lang/NullPointerException", None),
+                Some(this) if this.is_reference() => {},
+                _ => return Err(InterpreterError::UnexpectedOperand)
+            }
 
-            let mut arguments = stack_frame.pop_many(method_descriptor.parameters_length()).unwrap();
-            arguments.reverse();
-            let object = stack_frame.pop_object_reference()?;
+            let count = arguments.len();
+            let invoke_result = invoke_method(&declaring_class, method, arguments, class_table)?;
+            Ok(complete_invoke(stack_frame, count, invoke_result))
+        },
+        Instruction::Invokestatic { index } => {
+            let method_ref = class.constant_pool.get_method_ref(*index)
+                .map_err(|_| InterpreterError::InvalidConstant(*index))?;
+            let name = method_ref.name_and_type.name.as_str();
+            let descriptor = method_ref.name_and_type.descriptor.as_str();
 
-            let invoke_result = invoke_virtual_method(object, method, arguments, invoked_class, class_table).unwrap();
-            match invoke_result {
-                InvokeResult::Value(value) => {
-                    stack_frame.push(value);
-                    Ok(Step::Next)
-                },
-                InvokeResult::Void => {
-                    Ok(Step::Next)
-                },
-                InvokeResult::Exception(value) => {
-                    Ok(Step::Exception(value))
-                }
+            if invokedynamic::is_signature_polymorphic(class_table, &method_ref.class_name, name) {
+                let arguments = peek_arguments(stack_frame, descriptor, false)?;
+                let count = arguments.len();
+                let invoke_result = invokedynamic::invoke_polymorphic(*index, class, arguments, class_table)?;
+                return Ok(complete_invoke(stack_frame, count, invoke_result));
             }
+
+            // The class declaring the method is initialized, see JVMS $5.5.
+            let declaring_class = match link_method(&method_ref.class_name, name, descriptor, class_table)? {
+                Ok(declaring_class) => declaring_class,
+                Err(exception) => return Ok(Step::Exception(exception))
+            };
+            if let Some(exception) = initialize_class(&declaring_class, class_table)? {
+                return Ok(Step::Exception(exception));
+            }
+
+            let method = declaring_class.get_declared_method(name, descriptor).unwrap();
+
+            let arguments = peek_arguments(stack_frame, descriptor, false)?;
+            let count = arguments.len();
+            let invoke_result = invoke_method(&declaring_class, method, arguments, class_table)?;
+            Ok(complete_invoke(stack_frame, count, invoke_result))
         },
-        Instruction::Imul => {
-            let v2 = stack_frame.pop_int()?;
-            let v1 = stack_frame.pop_int()?;
-            stack_frame.push_int(v1 * v2);
+        Instruction::Invokevirtual { index } => {
+            invoke_dynamically_dispatched(*index, stack_frame, class, class_table)
+        },
+        Instruction::Ior => {
+            let value2 = stack_frame.pop_int()?;
+            let value1 = stack_frame.pop_int()?;
+            stack_frame.push_int(value1 | value2);
+            Ok(Step::Next)
+        },
+        Instruction::Irem => {
+            let value2 = stack_frame.pop_int()?;
+            let value1 = stack_frame.pop_int()?;
+            if value2 == 0 {
+                return throw(class_table, "java/lang/ArithmeticException", Some("/ by zero"));
+            }
+            stack_frame.push_int(value1.wrapping_rem(value2));
             Ok(Step::Next)
         },
         Instruction::Ireturn => {
-            // TODO: We should probably validate the operand type.
-            let value = stack_frame.pop().unwrap();
-            Ok(Step::Return(value))
+            let value = stack_frame.pop_int()?;
+            Ok(Step::Return(Value::Integer(value)))
+        },
+        Instruction::Ishl => {
+            let value2 = stack_frame.pop_int()?;
+            let value1 = stack_frame.pop_int()?;
+            stack_frame.push_int(value1.wrapping_shl(value2 as u32));
+            Ok(Step::Next)
+        },
+        Instruction::Ishr => {
+            let value2 = stack_frame.pop_int()?;
+            let value1 = stack_frame.pop_int()?;
+            stack_frame.push_int(value1.wrapping_shr(value2 as u32));
+            Ok(Step::Next)
         },
         Instruction::Istore(index) => {
             let int = stack_frame.pop_int()?;
@@ -479,62 +1422,437 @@ fn interpret_instruction(instruction: &Instruction,
         Instruction::Isub => {
             let value2 = stack_frame.pop_int()?;
             let value1 = stack_frame.pop_int()?;
-            stack_frame.push_int(value1 - value2);
+            stack_frame.push_int(value1.wrapping_sub(value2));
             Ok(Step::Next)
         },
-        Instruction::New { index } => {
-            let class_name = class.constant_pool.get_class_name(*index).unwrap();
-            let runtime_class = class_table.get_class(&*class_name).unwrap();
-            let memory: Vec<Value> = runtime_class.default_fields();
+        Instruction::Iushr => {
+            let value2 = stack_frame.pop_int()?;
+            let value1 = stack_frame.pop_int()?;
+            stack_frame.push_int((value1 as u32).wrapping_shr(value2 as u32) as i32);
+            Ok(Step::Next)
+        },
+        Instruction::Ixor => {
+            let value2 = stack_frame.pop_int()?;
+            let value1 = stack_frame.pop_int()?;
+            stack_frame.push_int(value1 ^ value2);
+            Ok(Step::Next)
+        },
+        Instruction::L2d => {
+            let value = stack_frame.pop_long()?;
+            stack_frame.push_double(value as f64);
+            Ok(Step::Next)
+        },
+        Instruction::L2f => {
+            let value = stack_frame.pop_long()?;
+            stack_frame.push_float(value as f32);
+            Ok(Step::Next)
+        },
+        Instruction::L2i => {
+            let value = stack_frame.pop_long()?;
+            stack_frame.push_int(value as i32);
+            Ok(Step::Next)
+        },
+        Instruction::Ladd => {
+            let value2 = stack_frame.pop_long()?;
+            let value1 = stack_frame.pop_long()?;
+            stack_frame.push_long(value1.wrapping_add(value2));
+            Ok(Step::Next)
+        },
+        Instruction::Laload => array_load(stack_frame, class_table),
+        Instruction::Land => {
+            let value2 = stack_frame.pop_long()?;
+            let value1 = stack_frame.pop_long()?;
+            stack_frame.push_long(value1 & value2);
+            Ok(Step::Next)
+        },
+        Instruction::Lastore => array_store(stack_frame, class_table),
+        Instruction::Lcmp => {
+            let value2 = stack_frame.pop_long()?;
+            let value1 = stack_frame.pop_long()?;
+            stack_frame.push_int(compare(value1, value2, 0));
+            Ok(Step::Next)
+        },
+        Instruction::Lconst0 => {
+            stack_frame.push_long(0);
+            Ok(Step::Next)
+        },
+        Instruction::Lconst1 => {
+            stack_frame.push_long(1);
+            Ok(Step::Next)
+        },
+        Instruction::Ldc { index } => {
+            let value = load_constant(*index as u16, class, class_table)?;
+            stack_frame.push(value);
+            Ok(Step::Next)
+        },
+        Instruction::LdcW { index } => {
+            let value = load_constant(*index, class, class_table)?;
+            stack_frame.push(value);
+            Ok(Step::Next)
+        },
+        Instruction::Ldc2W { index } => {
+            let value = load_constant(*index, class, class_table)?;
+            stack_frame.push(value);
+            Ok(Step::Next)
+        },
+        Instruction::Ldiv => {
+            let value2 = stack_frame.pop_long()?;
+            let value1 = stack_frame.pop_long()?;
+            if value2 == 0 {
+                return throw(class_table, "java/lang/ArithmeticException", Some("/ by zero"));
+            }
+            stack_frame.push_long(value1.wrapping_div(value2));
+            Ok(Step::Next)
+        },
+        Instruction::Lload { index } => load_local(stack_frame, *index as usize),
+        Instruction::Lload0 => load_local(stack_frame, 0),
+        Instruction::Lload1 => load_local(stack_frame, 1),
+        Instruction::Lload2 => load_local(stack_frame, 2),
+        Instruction::Lload3 => load_local(stack_frame, 3),
+        Instruction::Lmul => {
+            let value2 = stack_frame.pop_long()?;
+            let value1 = stack_frame.pop_long()?;
+            stack_frame.push_long(value1.wrapping_mul(value2));
+            Ok(Step::Next)
+        },
+        Instruction::Lneg => {
+            let value = stack_frame.pop_long()?;
+            stack_frame.push_long(value.wrapping_neg());
+            Ok(Step::Next)
+        },
+        Instruction::Lookupswitch { default, pairs } => {
+            let key = stack_frame.pop_int()?;
+            // The pairs are sorted by key (JVMS $4.10.1.9).
+            let offset = match pairs.binary_search_by_key(&key, |&(match_key, _)| match_key) {
+                Ok(position) => pairs[position].1,
+                Err(_) => *default
+            };
+            Ok(Step::Jump(offset))
+        },
+        Instruction::Lor => {
+            let value2 = stack_frame.pop_long()?;
+            let value1 = stack_frame.pop_long()?;
+            stack_frame.push_long(value1 | value2);
+            Ok(Step::Next)
+        },
+        Instruction::Lrem => {
+            let value2 = stack_frame.pop_long()?;
+            let value1 = stack_frame.pop_long()?;
+            if value2 == 0 {
+                return throw(class_table, "java/lang/ArithmeticException", Some("/ by zero"));
+            }
+            stack_frame.push_long(value1.wrapping_rem(value2));
+            Ok(Step::Next)
+        },
+        Instruction::Lreturn => {
+            let value = stack_frame.pop_long()?;
+            Ok(Step::Return(Value::Long(value)))
+        },
+        Instruction::Lshl => {
+            let value2 = stack_frame.pop_int()?;
+            let value1 = stack_frame.pop_long()?;
+            stack_frame.push_long(value1.wrapping_shl(value2 as u32));
+            Ok(Step::Next)
+        },
+        Instruction::Lshr => {
+            let value2 = stack_frame.pop_int()?;
+            let value1 = stack_frame.pop_long()?;
+            stack_frame.push_long(value1.wrapping_shr(value2 as u32));
+            Ok(Step::Next)
+        },
+        Instruction::Lstore { index } => store_local(stack_frame, *index as usize),
+        Instruction::Lstore0 => store_local(stack_frame, 0),
+        Instruction::Lstore1 => store_local(stack_frame, 1),
+        Instruction::Lstore2 => store_local(stack_frame, 2),
+        Instruction::Lstore3 => store_local(stack_frame, 3),
+        Instruction::Lsub => {
+            let value2 = stack_frame.pop_long()?;
+            let value1 = stack_frame.pop_long()?;
+            stack_frame.push_long(value1.wrapping_sub(value2));
+            Ok(Step::Next)
+        },
+        Instruction::Lushr => {
+            let value2 = stack_frame.pop_int()?;
+            let value1 = stack_frame.pop_long()?;
+            stack_frame.push_long((value1 as u64).wrapping_shr(value2 as u32) as i64);
+            Ok(Step::Next)
+        },
+        Instruction::Lxor => {
+            let value2 = stack_frame.pop_long()?;
+            let value1 = stack_frame.pop_long()?;
+            stack_frame.push_long(value1 ^ value2);
+            Ok(Step::Next)
+        },
+        Instruction::Monitorenter => {
+            let reference = stack_frame.pop_reference()?;
+            match monitor::with_monitor(&reference, |monitor| class_table.heap.enter_monitor(monitor)) {
+                Some(()) => Ok(Step::Next),
+                None => throw(class_table, "java/lang/NullPointerException", None)
+            }
+        },
+        Instruction::Monitorexit => {
+            let reference = stack_frame.pop_reference()?;
+            match monitor::with_monitor(&reference, Monitor::exit) {
+                Some(true) => Ok(Step::Next),
+                Some(false) => throw(class_table, "java/lang/IllegalMonitorStateException", None),
+                None => throw(class_table, "java/lang/NullPointerException", None)
+            }
+        },
+        Instruction::Multianewarray { index, dimensions } => {
+            let class_name = get_class_name(class, *index)?;
+            let mut counts = stack_frame.pop_many(*dimensions as usize)
+                .ok_or(InterpreterError::UnexpectedOperand)?
+                .into_iter()
+                .map(|count| match count {
+                    Value::Integer(count) => Ok(count),
+                    _ => Err(InterpreterError::UnexpectedOperand)
+                })
+                .collect::<Result<Vec<i32>, InterpreterError>>()?;
+            counts.reverse();
+
+            if let Some(count) = counts.iter().find(|count| **count < 0) {
+                return throw(class_table, "java/lang/NegativeArraySizeException", Some(&count.to_string()));
+            }
+
+            if !class_table.reserve(multi_array_size(&class_name, &counts)) {
+                return throw(class_table, "java/lang/OutOfMemoryError", Some(gc::OUT_OF_MEMORY));
+            }
 
-            let object = Object {
-                class: runtime_class.clone(),
-                memory
+            stack_frame.push(new_multi_array(&class_name, &counts, class_table));
+            Ok(Step::Next)
+        },
+        Instruction::New { index } => {
+            let class_name = get_class_name(class, *index)?;
+            let runtime_class = match link_class(&class_name, class_table)? {
+                Ok(runtime_class) => runtime_class,
+                Err(exception) => return Ok(Step::Exception(exception))
             };
 
-            let object_reference = Value::ObjectRef(Rc::new(RefCell::new(object)));
+            if let Some(exception) = initialize_class(&runtime_class, class_table)? {
+                return Ok(Step::Exception(exception));
+            }
+
+            let object_reference = match allocate_object(&runtime_class, class_table)? {
+                Ok(object) => Value::ObjectRef(object),
+                Err(exception) => return Ok(Step::Exception(exception))
+            };
             stack_frame.push(object_reference);
 
             Ok(Step::Next)
         },
         Instruction::Newarray { atype } => {
             let count = stack_frame.pop_int()?;
-            // TODO: These are array type codes. We could classify them.
-            match atype {
-                10 => {
-                    let array = Value::IntegerArrayRef(IntArray::new(count as usize));
-                    stack_frame.push(array);
-                    Ok(Step::Next)
-                },
-                _ => Err(InterpreterError::InvalidArrayType)
+            if count < 0 {
+                return throw(class_table, "java/lang/NegativeArraySizeException", Some(&count.to_string()));
+            }
+
+            // Array type codes are listed in JVMS $6.5.newarray.
+            let component_type = match atype {
+                4 => "Z",
+                5 => "C",
+                6 => "F",
+                7 => "D",
+                8 => "B",
+                9 => "S",
+                10 => "I",
+                11 => "J",
+                _ => return Err(InterpreterError::InvalidArrayType)
+            };
+
+            if !class_table.reserve(gc::array_size(component_type, count as usize)) {
+                return throw(class_table, "java/lang/OutOfMemoryError", Some(gc::OUT_OF_MEMORY));
             }
+
+            let array = Value::ArrayRef(class_table.new_array(component_type, count as usize));
+            stack_frame.push(array);
+            Ok(Step::Next)
         },
+        Instruction::Nop => Ok(Step::Next),
         Instruction::Pop => {
             stack_frame.pop();
             Ok(Step::Next)
         },
+        Instruction::Pop2 => {
+            pop_slots(stack_frame, 2)?;
+            Ok(Step::Next)
+        },
         Instruction::Putfield { index } => {
-            let value = stack_frame.pop().unwrap();
-            let object_reference = stack_frame.pop_object_reference()?;
-            let field_ref = class.constant_pool.get_field_ref(*index).unwrap();
-            object_reference.borrow_mut().put_field(field_ref.name_and_type.name, value);
+            let field_ref = class.constant_pool.get_field_ref(*index)
+                .map_err(|_| InterpreterError::InvalidConstant(*index))?;
+            // The field is resolved before the operands are popped, as resolving may allocate.
+            let position = match resolve_instance_field(&field_ref.class_name, &field_ref.name_and_type.name, class_table)? {
+                Ok(position) => position,
+                Err(exception) => return Ok(Step::Exception(exception))
+            };
+            let value = stack_frame.pop().ok_or(InterpreterError::UnexpectedOperand)?;
+            let object_reference = match stack_frame.pop_reference()? {
+                Value::ObjectRef(object_reference) => object_reference,
+                Value::Null => return throw(class_table, "java/lang/NullPointerException", None),
+                _ => return Err(InterpreterError::UnexpectedOperand)
+            };
+            let mut object = object_reference.borrow_mut();
+            object.fields_mut()[position] = value;
+            class_table.heap.write_barrier(&object.fields()[position]);
+            Ok(Step::Next)
+        },
+        Instruction::Putstatic { index } => {
+            let field_ref = class.constant_pool.get_field_ref(*index)
+                .map_err(|_| InterpreterError::InvalidConstant(*index))?;
+            let declaring_class = match resolve_static_field(&field_ref.class_name, &field_ref.name_and_type.name, class_table)? {
+                Ok(declaring_class) => declaring_class,
+                Err(exception) => return Ok(Step::Exception(exception))
+            };
+            let value = stack_frame.pop().ok_or(InterpreterError::UnexpectedOperand)?;
+            declaring_class.put_static(&field_ref.name_and_type.name, value);
             Ok(Step::Next)
         },
+        Instruction::Return => {
+            Ok(Step::ReturnVoid)
+        },
+        Instruction::Saload => array_load(stack_frame, class_table),
+        Instruction::Sastore => array_store(stack_frame, class_table),
         Instruction::Sipush(value) => {
             stack_frame.push_int(*value);
             Ok(Step::Next)
         },
         Instruction::Swap => {
-            let value1 = stack_frame.pop().unwrap();
-            let value2 = stack_frame.pop().unwrap();
-            stack_frame.push(value2);
+            let value1 = stack_frame.pop().ok_or(InterpreterError::UnexpectedOperand)?;
+            let value2 = stack_frame.pop().ok_or(InterpreterError::UnexpectedOperand)?;
             stack_frame.push(value1);
+            stack_frame.push(value2);
             Ok(Step::Next)
         },
-        Instruction::Return => {
-            Ok(Step::ReturnVoid)
+        Instruction::Tableswitch { default, low, offsets } => {
+            let index = stack_frame.pop_int()? as i64 - *low as i64;
+            let offset = match offsets.get(index as usize) {
+                Some(&offset) if index >= 0 => offset,
+                _ => *default
+            };
+            Ok(Step::Jump(offset))
+        },
+        Instruction::Wide { opcode, index } => match *opcode {
+            ILOAD | LLOAD | FLOAD | DLOAD | ALOAD => load_local(stack_frame, *index as usize),
+            ISTORE | LSTORE | FSTORE | DSTORE | ASTORE => store_local(stack_frame, *index as usize),
+            _ => Err(InterpreterError::UnhandledInstruction(instruction.clone()))
+        },
+        Instruction::WideIinc { index, constant } => {
+            let local = stack_frame.get_int_local(*index as usize)?;
+            stack_frame.set_int_local(*index as usize, local.wrapping_add(*constant as i32));
+            Ok(Step::Next)
+        },
+        x => Err(InterpreterError::UnhandledInstruction(x.clone()))
+    }
+}
+
+// invokevirtual and invokeinterface select the method to invoke from the runtime class of the
+// receiver, see JVMS $5.4.6.
+fn invoke_dynamically_dispatched(index: u16,
+                                 stack_frame: &mut StackFrame,
+                                 class: &Arc<RuntimeClass>,
+                                 class_table: &ClassTable) -> Result<Step, InterpreterError> {
+    let method_ref = class.constant_pool.get_method_ref(index)
+        .map_err(|_| InterpreterError::InvalidConstant(index))?;
+    let name = method_ref.name_and_type.name.as_str();
+    let descriptor = method_ref.name_and_type.descriptor.as_str();
+
+    // TODO: Verify access flags
+
+    let mut arguments = peek_arguments(stack_frame, descriptor, true)?;
+    let count = arguments.len();
+    if invokedynamic::is_signature_polymorphic(class_table, &method_ref.class_name, name) {
+        let invoke_result = invokedynamic::invoke_polymorphic(index, class, arguments, class_table)?;
+        return Ok(complete_invoke(stack_frame, count, invoke_result));
+    }
+    let this = arguments.remove(0);
+    if !this.is_reference() {
+        return Err(InterpreterError::UnexpectedOperand);
+    }
+
+    let invoke_result = invoke_virtual(class_table, this, name, descriptor, arguments)?;
+    Ok(complete_invoke(stack_frame, count, invoke_result))
+}
+
+// Resolves and initializes the class declaring a static field. The inner error is an exception
+// thrown during initialization.
+fn resolve_static_field(class_name: &str,
+                        field_name: &str,
+                        class_table: &ClassTable) -> Result<Result<Arc<RuntimeClass>, Value>, InterpreterError> {
+    let referenced_class = match link_class(class_name, class_table)? {
+        Ok(referenced_class) => referenced_class,
+        Err(exception) => return Ok(Err(exception))
+    };
+    let declaring_class = match RuntimeClass::resolve_static(&referenced_class, field_name) {
+        Some(declaring_class) => declaring_class,
+        None => return new_throwable(class_table, "java/lang/NoSuchFieldError", Some(field_name)).map(Err)
+    };
+
+    match initialize_class(&declaring_class, class_table)? {
+        Some(exception) => Ok(Err(exception)),
+        None => Ok(Ok(declaring_class))
+    }
+}
+
+// Resolves the field of getfield and putfield from the class named by the field reference
+// (JVMS $5.4.3.2), not from the class of the object: when a subclass hides the field, ((A) b).x
+// still reads the field declared in A. The position is the same in objects of all subclasses.
+fn resolve_instance_field(class_name: &str,
+                          field_name: &str,
+                          class_table: &ClassTable) -> Result<Result<usize, Value>, InterpreterError> {
+    let referenced_class = match link_class(class_name, class_table)? {
+        Ok(referenced_class) => referenced_class,
+        Err(exception) => return Ok(Err(exception))
+    };
+    match referenced_class.instance_field_position(field_name) {
+        Some(position) => Ok(Ok(position)),
+        None => new_throwable(class_table, "java/lang/NoSuchFieldError", Some(field_name)).map(Err)
+    }
+}
+
+fn get_class_name(class: &Arc<RuntimeClass>, index: u16) -> Result<String, InterpreterError> {
+    class.constant_pool.get_class_name(index)
+        .map_err(|_| InterpreterError::InvalidConstant(index))
+}
+
+// Array classes are named by their descriptor, while other classes need to be wrapped in "L;".
+fn class_name_to_descriptor(class_name: &str) -> String {
+    if class_name.starts_with('[') {
+        String::from(class_name)
+    } else {
+        format!("L{};", class_name)
+    }
+}
+
+// The binary name of the class of a non-null reference, as used in exception messages.
+fn runtime_class_name(reference: &Value) -> String {
+    match reference {
+        Value::ObjectRef(object) => object.borrow().class().class_name.replace('/', "."),
+        Value::ArrayRef(array) => array.borrow().descriptor().replace('/', "."),
+        _ => String::from("null")
+    }
+}
+
+// Resolves a loadable constant (JVMS $4.4) into a value for ldc, ldc_w and ldc2_w.
+fn load_constant(index: u16,
+                 class: &Arc<RuntimeClass>,
+                 class_table: &ClassTable) -> Result<Value, InterpreterError> {
+    let cp = &class.constant_pool;
+    let invalid = |_| InterpreterError::InvalidConstant(index);
+
+    match cp.get(index) {
+        Some(ConstantPoolEntry::Integer { .. }) => cp.get_integer(index).map(Value::Integer).map_err(invalid),
+        Some(ConstantPoolEntry::Float { .. }) => cp.get_float(index).map(Value::Float).map_err(invalid),
+        Some(ConstantPoolEntry::Long { .. }) => cp.get_long(index).map(Value::Long).map_err(invalid),
+        Some(ConstantPoolEntry::Double { .. }) => cp.get_double(index).map(Value::Double).map_err(invalid),
+        Some(ConstantPoolEntry::String { .. }) => {
+            let string = cp.get_string(index).map_err(invalid)?;
+            let reference = class_table.intern_string(string);
+            Ok(Value::ObjectRef(reference))
+        },
+        Some(ConstantPoolEntry::Class { .. }) => {
+            let class_name = cp.get_class_name(index).map_err(invalid)?;
+            Ok(Value::ObjectRef(class_table.get_mirror(&class_name)))
         },
-        x => Err(InterpreterError::UnhandledInstruction(*x))
+        _ => Err(InterpreterError::InvalidConstant(index))
     }
 }
 
@@ -543,6 +1861,8 @@ mod tests {
 
     use super::*;
     use class::ConstantPool;
+    use class::mutf8::JavaString;
+    use runtime::string;
 
     #[test]
     fn iadd() {
@@ -608,13 +1928,109 @@ mod tests {
         assert_eq!(result, 2);
     }
 
-    fn test_class() -> Rc<RuntimeClass> {
-        Rc::new(RuntimeClass {
-            class_name: String::from("Test"),
-            constant_pool: ConstantPool::new(),
-            fields: Vec::new(),
-            methods: Vec::new()
-        })
+    #[test]
+    fn ldc_integer_and_float() {
+        let mut stack_frame = StackFrame::new_frame(2, 0);
+        let class = test_class();
+        let class_table = ClassTable::new();
+
+        interpret_instruction(&Instruction::Ldc { index: 3 }, &mut stack_frame, &class, &class_table).unwrap();
+        interpret_instruction(&Instruction::LdcW { index: 4 }, &mut stack_frame, &class, &class_table).unwrap();
+
+        match stack_frame.pop() {
+            Some(Value::Float(f)) => assert_eq!(f, 1.5),
+            x => panic!("expected float, found {:?}", x)
+        }
+        assert_eq!(stack_frame.pop_int().unwrap(), -7);
+    }
+
+    #[test]
+    fn ldc2_w_long_and_double() {
+        let mut stack_frame = StackFrame::new_frame(4, 0);
+        let class = test_class();
+        let class_table = ClassTable::new();
+
+        interpret_instruction(&Instruction::Ldc2W { index: 5 }, &mut stack_frame, &class, &class_table).unwrap();
+        interpret_instruction(&Instruction::Ldc2W { index: 7 }, &mut stack_frame, &class, &class_table).unwrap();
+
+        match stack_frame.pop() {
+            Some(Value::Double(d)) => assert_eq!(d, -0.25),
+            x => panic!("expected double, found {:?}", x)
+        }
+        match stack_frame.pop() {
+            Some(Value::Long(l)) => assert_eq!(l, 0x1_0000_0002),
+            x => panic!("expected long, found {:?}", x)
+        }
+    }
+
+    #[test]
+    fn ldc_string_is_interned() {
+        let mut stack_frame = StackFrame::new_frame(2, 0);
+        let class = test_class();
+        let class_table = ClassTable::new();
+
+        interpret_instruction(&Instruction::Ldc { index: 2 }, &mut stack_frame, &class, &class_table).unwrap();
+        interpret_instruction(&Instruction::Ldc { index: 2 }, &mut stack_frame, &class, &class_table).unwrap();
+
+        let a = stack_frame.pop_object_reference().unwrap();
+        let b = stack_frame.pop_object_reference().unwrap();
+
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(string::to_rust_string(&a.borrow()), "hello");
+    }
+
+    #[test]
+    fn ldc_class_mirror() {
+        let mut stack_frame = StackFrame::new_frame(2, 0);
+        let class = test_class();
+        let class_table = ClassTable::new();
+
+        interpret_instruction(&Instruction::Ldc { index: 10 }, &mut stack_frame, &class, &class_table).unwrap();
+        let mirror = stack_frame.pop_object_reference().unwrap();
+
+        assert!(Arc::ptr_eq(&mirror, &class_table.get_mirror("java/lang/String")));
+        let name = mirror.borrow().get_field(String::from("name"));
+        match name {
+            Value::ObjectRef(name) => assert_eq!(string::to_rust_string(&name.borrow()), "java.lang.String"),
+            x => panic!("expected name, found {:?}", x)
+        }
+    }
+
+    // javac pairs every monitorenter with a monitorexit, but other compilers need not.
+    #[test]
+    fn monitorexit_without_monitorenter() {
+        let mut stack_frame = StackFrame::new_frame(2, 0);
+        let class = test_class();
+        let class_table = ClassTable::new();
+        let lock = Value::ObjectRef(class_table.new_object(&class));
+
+        stack_frame.push(lock.clone());
+        interpret_instruction(&Instruction::Monitorenter, &mut stack_frame, &class, &class_table).unwrap();
+        stack_frame.push(lock.clone());
+        interpret_instruction(&Instruction::Monitorexit, &mut stack_frame, &class, &class_table).unwrap();
+        stack_frame.push(lock);
+
+        match interpret_instruction(&Instruction::Monitorexit, &mut stack_frame, &class, &class_table).unwrap() {
+            Step::Exception(exception) => assert!(is_instance_of(&exception, "java/lang/IllegalMonitorStateException", &class_table)),
+            _ => panic!("expected IllegalMonitorStateException")
+        }
+    }
+
+    fn test_class() -> Arc<RuntimeClass> {
+        let entries = vec![
+            ConstantPoolEntry::Utf8(JavaString::from("hello")),
+            ConstantPoolEntry::String { string_index: 1 },
+            ConstantPoolEntry::Integer { bytes: -7i32 as u32 },
+            ConstantPoolEntry::Float { bytes: 1.5f32.to_bits() },
+            ConstantPoolEntry::Long { high_bytes: 1, low_bytes: 2 },
+            ConstantPoolEntry::Placeholder,
+            ConstantPoolEntry::Double { high_bytes: ((-0.25f64).to_bits() >> 32) as u32, low_bytes: (-0.25f64).to_bits() as u32 },
+            ConstantPoolEntry::Placeholder,
+            ConstantPoolEntry::Utf8(JavaString::from("java/lang/String")),
+            ConstantPoolEntry::Class { name_index: 9 }
+        ];
+
+        RuntimeClass::new("Test", 0, None, Vec::new(), ConstantPool { entries }, Vec::new(), Vec::new())
     }
 
 }
@@ -0,0 +1,763 @@
+// invokedynamic call sites (JVMS $6.5.invokedynamic) are linked by running their bootstrap method.
+// The two bootstrap methods javac emits for lambdas and string concatenation are built in:
+// LambdaMetafactory spins a class implementing the functional interface, and StringConcatFactory
+// concatenates according to a recipe. Other bootstrap methods, such as the one javac emits for the
+// methods of records, are run by java.lang.invoke when the class path has a JDK, which also links
+// the calls to signature polymorphic methods. Linked call sites are cached in the class that
+// contains them.
+
+use class::{ConstantPool, ConstantPoolEntry, MethodHandle};
+use class::field::{ACC_PRIVATE, ACC_FINAL};
+use class::method::{ACC_PUBLIC, ACC_VARARGS};
+use class::reference_kind::*;
+use runtime::{Value, HeapCell, Array, ArrayElements};
+use runtime::bootstrap::{self, jdk, ClassBuilder, format_float, format_double};
+use runtime::class::{RuntimeClass, ClassTable, mirror_name};
+use runtime::class::method::MethodDescriptor;
+use runtime::interpreter::{self, InvokeResult, InterpreterError};
+use std::sync::Arc;
+
+const LAMBDA_METAFACTORY: &str = "java/lang/invoke/LambdaMetafactory";
+const STRING_CONCAT_FACTORY: &str = "java/lang/invoke/StringConcatFactory";
+const METHOD_HANDLE_NATIVES: &str = "java/lang/invoke/MethodHandleNatives";
+
+// Flags of LambdaMetafactory.altMetafactory.
+const FLAG_MARKERS: i32 = 1 << 1;
+const FLAG_BRIDGES: i32 = 1 << 2;
+
+// The tags of a StringConcatFactory.makeConcatWithConstants recipe.
+const TAG_ARGUMENT: u16 = 1;
+const TAG_CONSTANT: u16 = 2;
+
+#[derive(Debug)]
+pub struct CallSite {
+    // The descriptor of the invokedynamic instruction, which gives the arguments it pops.
+    pub descriptor: String,
+    target: Target
+}
+
+#[derive(Debug)]
+enum Target {
+    // A lambda that captures nothing evaluates to the same instance every time.
+    Constant(Value),
+    // A new instance of the lambda class, with the captured arguments stored in its fields.
+    Lambda(Arc<RuntimeClass>),
+    Concat(Vec<Piece>),
+    // The method java.lang.invoke linked the call site to, by its class and position in it. It
+    // takes the appendix, unless that is null, after the arguments of the call site.
+    Linked(Arc<RuntimeClass>, usize, Value)
+}
+
+#[derive(Debug, PartialEq)]
+enum Piece {
+    Literal(Vec<u16>),
+    // An argument with the given field descriptor, converted as by String.valueOf.
+    Argument(String)
+}
+
+impl CallSite {
+
+    pub fn invoke(&self, arguments: Vec<Value>, class_table: &ClassTable) -> Result<InvokeResult, InterpreterError> {
+        match self.target {
+            Target::Constant(ref value) => Ok(InvokeResult::Value(value.clone())),
+            Target::Lambda(ref class) => {
+                let lambda = match interpreter::allocate_object(class, class_table)? {
+                    Ok(lambda) => lambda,
+                    Err(exception) => return Ok(InvokeResult::Exception(exception))
+                };
+                for (index, argument) in arguments.into_iter().enumerate() {
+                    lambda.borrow_mut().put_field(captured_field(index), argument);
+                }
+                Ok(InvokeResult::Value(Value::ObjectRef(lambda)))
+            },
+            Target::Concat(ref pieces) => concat(pieces, arguments, class_table),
+            Target::Linked(ref class, slot, ref appendix) => {
+                let mut arguments = arguments;
+                if !matches!(*appendix, Value::Null) {
+                    arguments.push(appendix.clone());
+                }
+                interpreter::invoke_method(class, &class.methods[slot], arguments, class_table)
+            }
+        }
+    }
+
+    // Calls visit with the instance the call site evaluates to, if it is a constant, or with its
+    // appendix.
+    pub fn visit(&self, visit: &mut dyn FnMut(&Value)) {
+        if let Target::Constant(ref value) | Target::Linked(_, _, ref value) = self.target {
+            visit(value);
+        }
+    }
+
+}
+
+// What invoking the interface method of a lambda class does: the captured arguments followed by
+// the interface method arguments are passed to the implementation method.
+#[derive(Debug)]
+pub struct Lambda {
+    implementation: MethodHandle,
+    // The field descriptors of the captured arguments.
+    captured: Vec<String>,
+    // The erased descriptor of the interface method.
+    interface_descriptor: String
+}
+
+// Returns the call site of an invokedynamic instruction, linking it the first time it is
+// executed. The inner error is an exception thrown while linking.
+pub fn link(index: u16,
+            class: &Arc<RuntimeClass>,
+            class_table: &ClassTable) -> Result<Result<Arc<CallSite>, Value>, InterpreterError> {
+    if let Some(call_site) = class.get_call_site(index) {
+        return Ok(Ok(call_site));
+    }
+
+    let cp = &class.constant_pool;
+    let invoke_dynamic = cp.get_invoke_dynamic(index)
+        .map_err(|_| InterpreterError::InvalidConstant(index))?;
+    let bootstrap_method = class.bootstrap_methods
+        .get(invoke_dynamic.bootstrap_method_attr_index as usize)
+        .ok_or(InterpreterError::InvalidConstant(index))?;
+    let bootstrap_method_ref = bootstrap_method.bootstrap_method_ref;
+    let handle = cp.get_method_handle(bootstrap_method_ref)
+        .map_err(|_| InterpreterError::InvalidConstant(bootstrap_method_ref))?;
+
+    let name = invoke_dynamic.name_and_type.name.as_str();
+    let descriptor = invoke_dynamic.name_and_type.descriptor.as_str();
+    let arguments = &bootstrap_method.bootstrap_arguments;
+
+    let bootstrap_class = handle.method_ref.class_name.as_str();
+    let bootstrap_name = handle.method_ref.name_and_type.name.as_str();
+    let target = match (bootstrap_class, bootstrap_name) {
+        (LAMBDA_METAFACTORY, "metafactory") | (LAMBDA_METAFACTORY, "altMetafactory") => {
+            match link_lambda(class, name, descriptor, arguments, class_table)? {
+                Ok(target) => target,
+                Err(exception) => return Ok(Err(exception))
+            }
+        },
+        (STRING_CONCAT_FACTORY, "makeConcatWithConstants") => link_concat(cp, descriptor, Some(arguments))?,
+        (STRING_CONCAT_FACTORY, "makeConcat") => link_concat(cp, descriptor, None)?,
+        _ if is_loadable(bootstrap_class, class_table) && is_loadable(METHOD_HANDLE_NATIVES, class_table) => {
+            let _scope = class_table.stacks.scope();
+            match link_call_site(class, index, &handle, name, descriptor, arguments, class_table)? {
+                Ok(target) => target,
+                Err(exception) => return Ok(Err(exception))
+            }
+        },
+        _ => {
+            let message = format!("bootstrap method {}.{} is not supported", bootstrap_class.replace('/', "."), bootstrap_name);
+            let exception = interpreter::new_throwable(class_table, "java/lang/BootstrapMethodError", Some(&message))?;
+            return Ok(Err(exception));
+        }
+    };
+
+    let call_site = Arc::new(CallSite { descriptor: String::from(descriptor), target });
+    class.put_call_site(index, call_site.clone());
+    Ok(Ok(call_site))
+}
+
+fn is_loadable(class_name: &str, class_table: &ClassTable) -> bool {
+    matches!(class_table.find_class(class_name), Ok(Some(_)))
+}
+
+// Unwraps the result of calling into java.lang.invoke while linking. If it threw an exception,
+// linking fails with it.
+macro_rules! link_try {
+    ($e:expr) => {
+        match $e? {
+            Ok(value) => value,
+            Err(exception) => return Ok(Err(exception))
+        }
+    };
+}
+
+// Links a call site as HotSpot does: MethodHandleNatives.linkCallSite runs the bootstrap method
+// and returns the method that invokes the target of the call site it made, with the appendix
+// that method takes, which is the call site or its target. The bootstrap method and its static
+// arguments are passed as the objects their constants resolve to.
+fn link_call_site(class: &Arc<RuntimeClass>,
+                  index: u16,
+                  handle: &MethodHandle,
+                  name: &str,
+                  descriptor: &str,
+                  arguments: &[u16],
+                  class_table: &ClassTable) -> Result<Result<Target, Value>, InterpreterError> {
+    let bootstrap_method = link_try!(method_handle_constant(class, handle, class_table));
+    let method_type = link_try!(new_method_type(descriptor, class_table));
+    let mut static_arguments = Vec::new();
+    for &argument in arguments.iter() {
+        static_arguments.push(link_try!(bootstrap_argument(class, argument, class_table)));
+    }
+    let static_arguments = if static_arguments.is_empty() {
+        Value::Null
+    } else {
+        Value::ArrayRef(class_table.new_array_from("Ljava/lang/Object;", ArrayElements::Reference(static_arguments)))
+    };
+    let appendix = class_table.new_array("Ljava/lang/Object;", 1);
+
+    let arguments = vec![
+        Value::ObjectRef(class_table.get_mirror(&class.class_name)),
+        Value::Integer(index as i32),
+        bootstrap_method,
+        Value::ObjectRef(class_table.intern_string(name.encode_utf16().collect())),
+        method_type,
+        static_arguments,
+        Value::ArrayRef(appendix.clone())
+    ];
+    let member = link_try!(call_natives("linkCallSite",
+                                        "(Ljava/lang/Object;ILjava/lang/Object;Ljava/lang/Object;Ljava/lang/Object;Ljava/lang/Object;[Ljava/lang/Object;)Ljava/lang/invoke/MemberName;",
+                                        arguments, class_table));
+    linked_target(&member, &appendix, class_table)
+}
+
+// Whether the named method of a class is signature polymorphic (JVMS $2.9.3): it is declared by
+// MethodHandle or VarHandle as native, taking a variable number of Objects.
+pub fn is_signature_polymorphic(class_table: &ClassTable, class_name: &str, name: &str) -> bool {
+    if class_name != "java/lang/invoke/MethodHandle" && class_name != "java/lang/invoke/VarHandle" {
+        return false;
+    }
+    class_table.get_class(class_name).is_some_and(|class| class.methods.iter().any(|method| {
+        method.name == name && method.is_native() && method.access_flags & ACC_VARARGS != 0 &&
+            method.descriptor.starts_with("([Ljava/lang/Object;)")
+    }))
+}
+
+// Invokes the signature polymorphic method of a method reference with the arguments of the call
+// site, the receiver first if it has one. The intrinsics of java.lang.invoke, such as
+// MethodHandle.invokeBasic, are natives taking the arguments as they are. Calls to the others are
+// linked like invokedynamic, to the method MethodHandleNatives.linkMethod returns, which adapts
+// the arguments to the method handle or variable handle and takes the type of the call site as
+// its appendix.
+pub fn invoke_polymorphic(index: u16,
+                          class: &Arc<RuntimeClass>,
+                          arguments: Vec<Value>,
+                          class_table: &ClassTable) -> Result<InvokeResult, InterpreterError> {
+    let method_ref = class.constant_pool.get_method_ref(index)
+        .map_err(|_| InterpreterError::InvalidConstant(index))?;
+    let name = method_ref.name_and_type.name.as_str();
+    let descriptor = method_ref.name_and_type.descriptor.as_str();
+
+    let polymorphic_class = interpreter::resolve_class(&method_ref.class_name, class_table)?;
+    let method = polymorphic_class.get_method(name)
+        .ok_or_else(|| InterpreterError::MethodNotFound(format!("{}.{}{}", method_ref.class_name, name, descriptor)))?;
+    if method.native.is_some() {
+        return interpreter::invoke_method(&polymorphic_class, method, arguments, class_table);
+    }
+
+    let call_site = match class.get_call_site(index) {
+        Some(call_site) => call_site,
+        None => {
+            let _scope = class_table.stacks.scope();
+            let appendix = class_table.new_array("Ljava/lang/Object;", 1);
+            let method_type = match new_method_type(descriptor, class_table)? {
+                Ok(method_type) => method_type,
+                Err(exception) => return Ok(InvokeResult::Exception(exception))
+            };
+            let arguments = vec![
+                Value::ObjectRef(class_table.get_mirror(&class.class_name)),
+                Value::Integer(REF_INVOKE_VIRTUAL as i32),
+                Value::ObjectRef(class_table.get_mirror(&method_ref.class_name)),
+                Value::ObjectRef(class_table.intern_string(name.encode_utf16().collect())),
+                method_type,
+                Value::ArrayRef(appendix.clone())
+            ];
+            let linked = call_natives("linkMethod",
+                                      "(Ljava/lang/Class;ILjava/lang/Class;Ljava/lang/String;Ljava/lang/Object;[Ljava/lang/Object;)Ljava/lang/invoke/MemberName;",
+                                      arguments, class_table)?
+                .map(|member| linked_target(&member, &appendix, class_table));
+            let target = match linked {
+                Ok(target) => target?,
+                Err(exception) => Err(exception)
+            };
+            let target = match target {
+                Ok(target) => target,
+                Err(exception) => return Ok(InvokeResult::Exception(exception))
+            };
+            let call_site = Arc::new(CallSite { descriptor: String::from(descriptor), target });
+            class.put_call_site(index, call_site.clone());
+            call_site
+        }
+    };
+    call_site.invoke(arguments, class_table)
+}
+
+// The target of a call site linked to the method a MemberName is resolved to, taking the first
+// element of the appendix array, if any. The class declaring the method is initialized.
+fn linked_target(member: &Value, appendix: &Arc<HeapCell<Array>>, class_table: &ClassTable) -> Result<Result<Target, Value>, InterpreterError> {
+    let member = match *member {
+        Value::ObjectRef(ref member) => member,
+        _ => return Err(InterpreterError::UnexpectedOperand)
+    };
+    let (class, slot) = jdk::resolved_method(class_table, member)?.ok_or(InterpreterError::UnexpectedOperand)?;
+    if let Some(exception) = interpreter::initialize_class(&class, class_table)? {
+        return Ok(Err(exception));
+    }
+    let appendix = appendix.borrow().get(0);
+    Ok(Ok(Target::Linked(class, slot, appendix)))
+}
+
+// Calls a static method of MethodHandleNatives, through which the virtual machine calls into
+// java.lang.invoke. The inner error is the exception it threw.
+fn call_natives(name: &str, descriptor: &str, arguments: Vec<Value>, class_table: &ClassTable) -> Result<Result<Value, Value>, InterpreterError> {
+    let natives = interpreter::resolve_class(METHOD_HANDLE_NATIVES, class_table)?;
+    if let Some(exception) = interpreter::initialize_class(&natives, class_table)? {
+        return Ok(Err(exception));
+    }
+    match invoke_resolved(&natives, name, descriptor, arguments, class_table)? {
+        InvokeResult::Value(value) => Ok(Ok(value)),
+        InvokeResult::Exception(exception) => Ok(Err(exception)),
+        InvokeResult::Void => Err(InterpreterError::UnexpectedOperand)
+    }
+}
+
+// The MethodType of a method descriptor.
+fn new_method_type(descriptor: &str, class_table: &ClassTable) -> Result<Result<Value, Value>, InterpreterError> {
+    let descriptor = parse_descriptor(descriptor)?;
+    let mirror = |descriptor: &str| Value::ObjectRef(class_table.get_mirror(&mirror_name(descriptor)));
+    let parameter_types = descriptor.parameter_types().iter().map(|parameter_type| mirror(parameter_type)).collect();
+    let arguments = vec![
+        mirror(&descriptor.return_type()),
+        Value::ArrayRef(class_table.new_array_from("Ljava/lang/Class;", ArrayElements::Reference(parameter_types)))
+    ];
+    call_natives("findMethodHandleType", "(Ljava/lang/Class;[Ljava/lang/Class;)Ljava/lang/invoke/MethodType;", arguments, class_table)
+}
+
+// The java.lang.invoke.MethodHandle a MethodHandle constant (JVMS $5.4.3.5) resolves to.
+fn method_handle_constant(class: &Arc<RuntimeClass>, handle: &MethodHandle, class_table: &ClassTable) -> Result<Result<Value, Value>, InterpreterError> {
+    let method_ref = &handle.method_ref;
+    let descriptor = method_ref.name_and_type.descriptor.as_str();
+    let member_type = if handle.reference_kind <= REF_PUT_STATIC {
+        Value::ObjectRef(class_table.get_mirror(&mirror_name(descriptor)))
+    } else {
+        link_try!(new_method_type(descriptor, class_table))
+    };
+    let arguments = vec![
+        Value::ObjectRef(class_table.get_mirror(&class.class_name)),
+        Value::Integer(handle.reference_kind as i32),
+        Value::ObjectRef(class_table.get_mirror(&method_ref.class_name)),
+        Value::ObjectRef(class_table.intern_string(method_ref.name_and_type.name.encode_utf16().collect())),
+        member_type
+    ];
+    call_natives("linkMethodHandleConstant",
+                 "(Ljava/lang/Class;ILjava/lang/Class;Ljava/lang/String;Ljava/lang/Object;)Ljava/lang/invoke/MethodHandle;",
+                 arguments, class_table)
+}
+
+// The object a static argument of a bootstrap method resolves to. Primitive constants are boxed.
+fn bootstrap_argument(class: &Arc<RuntimeClass>, index: u16, class_table: &ClassTable) -> Result<Result<Value, Value>, InterpreterError> {
+    let cp = &class.constant_pool;
+    let invalid = |_| InterpreterError::InvalidConstant(index);
+    let (value, descriptor) = match cp.get(index) {
+        Some(ConstantPoolEntry::Integer { .. }) => (Value::Integer(cp.get_integer(index).map_err(invalid)?), "I"),
+        Some(ConstantPoolEntry::Float { .. }) => (Value::Float(cp.get_float(index).map_err(invalid)?), "F"),
+        Some(ConstantPoolEntry::Long { .. }) => (Value::Long(cp.get_long(index).map_err(invalid)?), "J"),
+        Some(ConstantPoolEntry::Double { .. }) => (Value::Double(cp.get_double(index).map_err(invalid)?), "D"),
+        Some(ConstantPoolEntry::String { .. }) => {
+            return Ok(Ok(Value::ObjectRef(class_table.intern_string(cp.get_string(index).map_err(invalid)?))));
+        },
+        Some(ConstantPoolEntry::Class { .. }) => {
+            return Ok(Ok(Value::ObjectRef(class_table.get_mirror(&cp.get_class_name(index).map_err(invalid)?))));
+        },
+        Some(ConstantPoolEntry::MethodType { .. }) => return new_method_type(&method_type(cp, index)?, class_table),
+        Some(ConstantPoolEntry::MethodHandle { .. }) => {
+            let handle = cp.get_method_handle(index).map_err(invalid)?;
+            return method_handle_constant(class, &handle, class_table);
+        },
+        _ => return Err(InterpreterError::InvalidConstant(index))
+    };
+    convert(class_table, value, descriptor, "Ljava/lang/Object;")
+}
+
+// LambdaMetafactory.metafactory takes the erased interface method type, the implementation method
+// and the instantiated method type. altMetafactory adds flags followed by marker interfaces and
+// bridge method types. The invokedynamic instruction is named after the interface method, and its
+// descriptor takes the captured arguments and returns the functional interface.
+fn link_lambda(class: &Arc<RuntimeClass>,
+               name: &str,
+               descriptor: &str,
+               arguments: &[u16],
+               class_table: &ClassTable) -> Result<Result<Target, Value>, InterpreterError> {
+    let cp = &class.constant_pool;
+    let mut arguments = arguments.iter().cloned();
+    let mut next_argument = || arguments.next().ok_or(InterpreterError::UnexpectedOperand);
+
+    let interface_descriptor = method_type(cp, next_argument()?)?;
+    let implementation_index = next_argument()?;
+    let implementation = cp.get_method_handle(implementation_index)
+        .map_err(|_| InterpreterError::InvalidConstant(implementation_index))?;
+    next_argument()?;
+
+    let invoked_type = parse_descriptor(descriptor)?;
+    let mut interfaces = vec![class_name_of(&invoked_type.return_type())?];
+    let mut bridges = Vec::new();
+    if let Ok(flags_index) = next_argument() {
+        let flags = integer(cp, flags_index)?;
+        if flags & FLAG_MARKERS != 0 {
+            for _ in 0..integer(cp, next_argument()?)? {
+                let index = next_argument()?;
+                interfaces.push(cp.get_class_name(index).map_err(|_| InterpreterError::InvalidConstant(index))?);
+            }
+        }
+        if flags & FLAG_BRIDGES != 0 {
+            for _ in 0..integer(cp, next_argument()?)? {
+                bridges.push(method_type(cp, next_argument()?)?);
+            }
+        }
+    }
+
+    for interface in interfaces.iter() {
+        interpreter::resolve_class(interface, class_table)?;
+    }
+
+    let captured = invoked_type.parameter_types();
+    let lambda = Lambda {
+        implementation,
+        captured: captured.clone(),
+        interface_descriptor: interface_descriptor.clone()
+    };
+    let lambda_class_name = class_table.add_lambda(&class.class_name, lambda);
+
+    let mut builder = ClassBuilder::new(&lambda_class_name);
+    for interface in interfaces.iter() {
+        builder = builder.implements(interface);
+    }
+    for (index, field_descriptor) in captured.iter().enumerate() {
+        builder = builder.field(&captured_field(index), field_descriptor, ACC_PRIVATE | ACC_FINAL);
+    }
+    builder = builder.native(name, &interface_descriptor, ACC_PUBLIC, invoke_lambda);
+    for bridge in bridges.iter().filter(|bridge| **bridge != interface_descriptor) {
+        builder = builder.native(name, bridge, ACC_PUBLIC, invoke_lambda);
+    }
+    let lambda_class = builder.define(class_table);
+
+    if captured.is_empty() {
+        Ok(Ok(Target::Constant(Value::ObjectRef(class_table.new_object(&lambda_class)))))
+    } else {
+        Ok(Ok(Target::Lambda(lambda_class)))
+    }
+}
+
+fn captured_field(index: usize) -> String {
+    format!("arg${}", index + 1)
+}
+
+// The native implementation of the interface method, and its bridges, in every lambda class.
+fn invoke_lambda(class_table: &ClassTable, arguments: Vec<Value>) -> Result<InvokeResult, InterpreterError> {
+    let mut arguments = arguments.into_iter();
+    let this = match arguments.next() {
+        Some(Value::ObjectRef(this)) => this,
+        _ => return Err(InterpreterError::UnexpectedOperand)
+    };
+    let class_name = this.borrow().class().class_name.clone();
+    let lambda = class_table.get_lambda(&class_name)
+        .ok_or_else(|| InterpreterError::UnsatisfiedLink(class_name.clone()))?;
+
+    let mut typed_arguments = Vec::new();
+    for (index, field_descriptor) in lambda.captured.iter().enumerate() {
+        typed_arguments.push((this.borrow().get_field(captured_field(index)), field_descriptor.clone()));
+    }
+    let interface_type = parse_descriptor(&lambda.interface_descriptor)?;
+    typed_arguments.extend(arguments.zip(interface_type.parameter_types()));
+
+    lambda.invoke(typed_arguments, &interface_type.return_type(), class_table)
+}
+
+impl Lambda {
+
+    // Invokes the implementation method, adapting the arguments and the result between the types
+    // of the interface method and the implementation method.
+    fn invoke(&self,
+              typed_arguments: Vec<(Value, String)>,
+              return_type: &str,
+              class_table: &ClassTable) -> Result<InvokeResult, InterpreterError> {
+        let method_ref = &self.implementation.method_ref;
+        let name = method_ref.name_and_type.name.as_str();
+        let descriptor = method_ref.name_and_type.descriptor.as_str();
+        let implementation_type = parse_descriptor(descriptor)?;
+        let class_type = format!("L{};", method_ref.class_name);
+        let kind = self.implementation.reference_kind;
+
+        // The receiver of an instance method is its first argument.
+        let mut parameter_types = implementation_type.parameter_types();
+        if kind == REF_INVOKE_VIRTUAL || kind == REF_INVOKE_INTERFACE || kind == REF_INVOKE_SPECIAL {
+            parameter_types.insert(0, class_type.clone());
+        }
+        if parameter_types.len() != typed_arguments.len() {
+            return Err(InterpreterError::UnexpectedOperand);
+        }
+
+        let mut arguments = Vec::new();
+        for ((argument, from), to) in typed_arguments.into_iter().zip(parameter_types.iter()) {
+            arguments.push(java_try!(convert(class_table, argument, &from, to)));
+        }
+
+        let (result, result_type) = match kind {
+            REF_INVOKE_STATIC => {
+                let class = interpreter::resolve_class(&method_ref.class_name, class_table)?;
+                if let Some(exception) = interpreter::initialize_class(&class, class_table)? {
+                    return Ok(InvokeResult::Exception(exception));
+                }
+                (invoke_resolved(&class, name, descriptor, arguments, class_table)?, implementation_type.return_type())
+            },
+            REF_INVOKE_SPECIAL => {
+                let class = interpreter::resolve_class(&method_ref.class_name, class_table)?;
+                (invoke_resolved(&class, name, descriptor, arguments, class_table)?, implementation_type.return_type())
+            },
+            REF_INVOKE_VIRTUAL | REF_INVOKE_INTERFACE => {
+                let receiver = arguments.remove(0);
+                (interpreter::invoke_virtual(class_table, receiver, name, descriptor, arguments)?, implementation_type.return_type())
+            },
+            REF_NEW_INVOKE_SPECIAL => {
+                let class = interpreter::resolve_class(&method_ref.class_name, class_table)?;
+                if let Some(exception) = interpreter::initialize_class(&class, class_table)? {
+                    return Ok(InvokeResult::Exception(exception));
+                }
+                let object = match interpreter::allocate_object(&class, class_table)? {
+                    Ok(object) => object,
+                    Err(exception) => return Ok(InvokeResult::Exception(exception))
+                };
+                arguments.insert(0, Value::ObjectRef(object.clone()));
+                let result = match invoke_resolved(&class, name, descriptor, arguments, class_table)? {
+                    InvokeResult::Exception(exception) => InvokeResult::Exception(exception),
+                    _ => InvokeResult::Value(Value::ObjectRef(object))
+                };
+                (result, class_type)
+            },
+            _ => return Err(InterpreterError::UnexpectedOperand)
+        };
+
+        match result {
+            InvokeResult::Value(_) if return_type == "V" => Ok(InvokeResult::Void),
+            InvokeResult::Value(value) => Ok(InvokeResult::Value(java_try!(convert(class_table, value, &result_type, return_type)))),
+            result => Ok(result)
+        }
+    }
+
+}
+
+// Invokes a method selected statically, as invokestatic and invokespecial do.
+fn invoke_resolved(class: &Arc<RuntimeClass>,
+                   name: &str,
+                   descriptor: &str,
+                   arguments: Vec<Value>,
+                   class_table: &ClassTable) -> Result<InvokeResult, InterpreterError> {
+    let declaring_class = RuntimeClass::resolve_method(class, name, descriptor)
+        .ok_or_else(|| InterpreterError::MethodNotFound(format!("{}.{}{}", class.class_name, name, descriptor)))?;
+    let method = declaring_class.get_declared_method(name, descriptor).unwrap();
+    interpreter::invoke_method(&declaring_class, method, arguments, class_table)
+}
+
+// Adapts a value from one field descriptor to another like LambdaMetafactory does: primitives are
+// widened, boxed or unboxed, and references are passed unchanged. The inner error is an exception,
+// such as the NullPointerException thrown when unboxing null.
+pub fn convert(class_table: &ClassTable, value: Value, from: &str, to: &str) -> Result<Result<Value, Value>, InterpreterError> {
+    match (wrapper_class(from), wrapper_class(to)) {
+        (Some(_), Some(_)) => Ok(Ok(widen(value, to))),
+        (Some(wrapper), None) => {
+            let class = interpreter::resolve_class(wrapper, class_table)?;
+            if let Some(exception) = interpreter::initialize_class(&class, class_table)? {
+                return Ok(Err(exception));
+            }
+            let value_of = format!("({})L{};", from, wrapper);
+            match invoke_resolved(&class, "valueOf", &value_of, vec![value], class_table)? {
+                InvokeResult::Value(boxed) => Ok(Ok(boxed)),
+                InvokeResult::Exception(exception) => Ok(Err(exception)),
+                InvokeResult::Void => Err(InterpreterError::UnexpectedOperand)
+            }
+        },
+        (None, Some(_)) => {
+            let name = format!("{}Value", primitive_name(to));
+            match interpreter::invoke_virtual(class_table, value, &name, &format!("(){}", to), Vec::new())? {
+                InvokeResult::Value(unboxed) => Ok(Ok(unboxed)),
+                InvokeResult::Exception(exception) => Ok(Err(exception)),
+                InvokeResult::Void => Err(InterpreterError::UnexpectedOperand)
+            }
+        },
+        (None, None) => Ok(Ok(value))
+    }
+}
+
+// Widening primitive conversions, see JLS $5.1.2. Narrower types are all ints on the stack.
+pub fn widen(value: Value, to: &str) -> Value {
+    match (value, to) {
+        (Value::Integer(i), "J") => Value::Long(i as i64),
+        (Value::Integer(i), "F") => Value::Float(i as f32),
+        (Value::Integer(i), "D") => Value::Double(i as f64),
+        (Value::Long(l), "F") => Value::Float(l as f32),
+        (Value::Long(l), "D") => Value::Double(l as f64),
+        (Value::Float(f), "D") => Value::Double(f as f64),
+        (value, _) => value
+    }
+}
+
+fn wrapper_class(descriptor: &str) -> Option<&'static str> {
+    match descriptor {
+        "Z" => Some("java/lang/Boolean"),
+        "B" => Some("java/lang/Byte"),
+        "C" => Some("java/lang/Character"),
+        "S" => Some("java/lang/Short"),
+        "I" => Some("java/lang/Integer"),
+        "J" => Some("java/lang/Long"),
+        "F" => Some("java/lang/Float"),
+        "D" => Some("java/lang/Double"),
+        _ => None
+    }
+}
+
+fn primitive_name(descriptor: &str) -> &'static str {
+    match descriptor {
+        "Z" => "boolean",
+        "B" => "byte",
+        "C" => "char",
+        "S" => "short",
+        "J" => "long",
+        "F" => "float",
+        "D" => "double",
+        _ => "int"
+    }
+}
+
+// makeConcatWithConstants takes a recipe, in which \1 stands for the next argument and \2 for
+// the next of the remaining bootstrap arguments. makeConcat concatenates the arguments.
+fn link_concat(cp: &ConstantPool, descriptor: &str, arguments: Option<&Vec<u16>>) -> Result<Target, InterpreterError> {
+    let mut parameter_types = parse_descriptor(descriptor)?.parameter_types().into_iter();
+
+    let arguments = match arguments {
+        Some(arguments) => arguments,
+        None => return Ok(Target::Concat(parameter_types.map(Piece::Argument).collect()))
+    };
+
+    let recipe_index = *arguments.first().ok_or(InterpreterError::UnexpectedOperand)?;
+    let recipe = cp.get_string(recipe_index)
+        .map_err(|_| InterpreterError::InvalidConstant(recipe_index))?;
+    let mut constants = arguments[1..].iter();
+
+    let mut pieces = Vec::new();
+    let mut literal = Vec::new();
+    for unit in recipe {
+        match unit {
+            TAG_ARGUMENT => {
+                if !literal.is_empty() {
+                    pieces.push(Piece::Literal(literal.split_off(0)));
+                }
+                let parameter_type = parameter_types.next().ok_or(InterpreterError::UnexpectedOperand)?;
+                pieces.push(Piece::Argument(parameter_type));
+            },
+            TAG_CONSTANT => {
+                let index = *constants.next().ok_or(InterpreterError::UnexpectedOperand)?;
+                literal.extend(constant_chars(cp, index)?);
+            },
+            _ => literal.push(unit)
+        }
+    }
+    if !literal.is_empty() {
+        pieces.push(Piece::Literal(literal));
+    }
+
+    Ok(Target::Concat(pieces))
+}
+
+fn constant_chars(cp: &ConstantPool, index: u16) -> Result<Vec<u16>, InterpreterError> {
+    let invalid = |_| InterpreterError::InvalidConstant(index);
+
+    let string = match cp.get(index) {
+        Some(ConstantPoolEntry::String { .. }) => return cp.get_string(index).map_err(invalid),
+        Some(ConstantPoolEntry::Integer { .. }) => cp.get_integer(index).map_err(invalid)?.to_string(),
+        Some(ConstantPoolEntry::Long { .. }) => cp.get_long(index).map_err(invalid)?.to_string(),
+        Some(ConstantPoolEntry::Float { .. }) => format_float(cp.get_float(index).map_err(invalid)?),
+        Some(ConstantPoolEntry::Double { .. }) => format_double(cp.get_double(index).map_err(invalid)?),
+        _ => return Err(InterpreterError::InvalidConstant(index))
+    };
+
+    Ok(string.encode_utf16().collect())
+}
+
+fn concat(pieces: &[Piece], arguments: Vec<Value>, class_table: &ClassTable) -> Result<InvokeResult, InterpreterError> {
+    let mut arguments = arguments.into_iter();
+    let mut chars = Vec::new();
+
+    for piece in pieces.iter() {
+        match *piece {
+            Piece::Literal(ref literal) => chars.extend(literal.iter()),
+            Piece::Argument(ref descriptor) => {
+                let argument = arguments.next().ok_or(InterpreterError::UnexpectedOperand)?;
+                chars.extend(java_try!(to_chars(class_table, argument, descriptor)));
+            }
+        }
+    }
+
+    Ok(InvokeResult::Value(Value::ObjectRef(class_table.new_string(chars))))
+}
+
+// The characters of String.valueOf(value) for a value of the given type. Booleans, chars and the
+// other int types are all ints on the stack.
+fn to_chars(class_table: &ClassTable, value: Value, descriptor: &str) -> Result<Result<Vec<u16>, Value>, InterpreterError> {
+    let string = match (value, descriptor) {
+        (Value::Integer(i), "Z") => String::from(if i != 0 { "true" } else { "false" }),
+        (Value::Integer(i), "C") => return Ok(Ok(vec![i as u16])),
+        (Value::Integer(i), _) => i.to_string(),
+        (Value::Long(l), _) => l.to_string(),
+        (Value::Float(f), _) => format_float(f),
+        (Value::Double(d), _) => format_double(d),
+        (value, _) => return bootstrap::to_java_chars(class_table, &value)
+    };
+
+    Ok(Ok(string.encode_utf16().collect()))
+}
+
+fn parse_descriptor(descriptor: &str) -> Result<MethodDescriptor, InterpreterError> {
+    MethodDescriptor::parse(descriptor).ok_or(InterpreterError::UnexpectedOperand)
+}
+
+fn method_type(cp: &ConstantPool, index: u16) -> Result<String, InterpreterError> {
+    cp.get_method_type(index).map_err(|_| InterpreterError::InvalidConstant(index))
+}
+
+fn integer(cp: &ConstantPool, index: u16) -> Result<i32, InterpreterError> {
+    cp.get_integer(index).map_err(|_| InterpreterError::InvalidConstant(index))
+}
+
+// The class name in a "Ljava/lang/Runnable;" descriptor.
+fn class_name_of(descriptor: &str) -> Result<String, InterpreterError> {
+    if descriptor.starts_with('L') && descriptor.ends_with(';') {
+        Ok(String::from(&descriptor[1..(descriptor.len() - 1)]))
+    } else {
+        Err(InterpreterError::UnexpectedOperand)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use class::mutf8::JavaString;
+
+    #[test]
+    fn splits_concat_recipes() {
+        let cp = ConstantPool {
+            entries: vec![
+                ConstantPoolEntry::Utf8(JavaString::from("x=\u{1}, \u{2}\u{1}!")),
+                ConstantPoolEntry::String { string_index: 1 },
+                ConstantPoolEntry::Integer { bytes: 42 }
+            ]
+        };
+        let chars = |s: &str| s.encode_utf16().collect::<Vec<u16>>();
+
+        let pieces = match link_concat(&cp, "(IJ)Ljava/lang/String;", Some(&vec![2, 3])).unwrap() {
+            Target::Concat(pieces) => pieces,
+            x => panic!("expected concat, found {:?}", x)
+        };
+        assert_eq!(pieces, vec![
+            Piece::Literal(chars("x=")),
+            Piece::Argument(String::from("I")),
+            Piece::Literal(chars(", 42")),
+            Piece::Argument(String::from("J")),
+            Piece::Literal(chars("!"))
+        ]);
+
+        match link_concat(&cp, "(Ljava/lang/Object;C)Ljava/lang/String;", None).unwrap() {
+            Target::Concat(pieces) => assert_eq!(pieces, vec![Piece::Argument(String::from("Ljava/lang/Object;")), Piece::Argument(String::from("C"))]),
+            x => panic!("expected concat, found {:?}", x)
+        }
+    }
+
+}
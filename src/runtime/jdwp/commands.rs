@@ -0,0 +1,681 @@
+use runtime::{Value, Elements};
+use runtime::class::{ClassTable, ClassState, RuntimeClass};
+use runtime::class::method::MethodDescriptor;
+use runtime::frames::Frame;
+use runtime::string;
+use super::*;
+use super::packet::*;
+use std::sync::Arc;
+use std::thread::ThreadId;
+
+// The commands of the JDWP subset the agent implements, by command set. Each reads its
+// arguments and returns the data of its reply, or an error code.
+
+pub const VIRTUAL_MACHINE: u8 = 1;
+pub const DISPOSE: u8 = 6;
+pub const EXIT: u8 = 10;
+
+const REFERENCE_TYPE: u8 = 2;
+const CLASS_TYPE: u8 = 3;
+const METHOD: u8 = 6;
+const OBJECT_REFERENCE: u8 = 9;
+const STRING_REFERENCE: u8 = 10;
+const THREAD_REFERENCE: u8 = 11;
+const THREAD_GROUP_REFERENCE: u8 = 12;
+const ARRAY_REFERENCE: u8 = 13;
+const EVENT_REQUEST: u8 = 15;
+const STACK_FRAME: u8 = 16;
+
+// Class status bits.
+const VERIFIED: i32 = 1;
+const PREPARED: i32 = 2;
+const INITIALIZED: i32 = 4;
+const ERROR: i32 = 8;
+
+// Thread status.
+const ZOMBIE: i32 = 0;
+const RUNNING: i32 = 1;
+
+// Whether the debugger can ask for the VM_DEATH event is the 14th capability of CapabilitiesNew.
+const CAPABILITIES: usize = 32;
+const CAN_REQUEST_VM_DEATH_EVENT: usize = 13;
+
+type Reply = Result<Writer, u16>;
+
+pub fn run(class_table: &ClassTable, state: &mut State, command_set: u8, command: u8, input: &mut Reader) -> Reply {
+    let mut output = Writer::new();
+    match (command_set, command) {
+        (VIRTUAL_MACHINE, 1) => version(&mut output),
+        (VIRTUAL_MACHINE, 2) => classes_by_signature(class_table, state, input, &mut output)?,
+        (VIRTUAL_MACHINE, 3) => all_classes(class_table, state, &mut output, false),
+        (VIRTUAL_MACHINE, 4) => all_threads(class_table, state, &mut output),
+        (VIRTUAL_MACHINE, 5) => {
+            output.i32(1);
+            output.u64(THREAD_GROUP_ID);
+        },
+        (VIRTUAL_MACHINE, DISPOSE) => {},
+        (VIRTUAL_MACHINE, 7) => {
+            // Field, method, object, reference type and frame ids are all 8 bytes long.
+            for _ in 0..5 {
+                output.i32(8);
+            }
+        },
+        (VIRTUAL_MACHINE, 8) => state.suspended += 1,
+        (VIRTUAL_MACHINE, 9) => state.resume_all(),
+        (VIRTUAL_MACHINE, EXIT) => {},
+        (VIRTUAL_MACHINE, 12) => capabilities(&mut output, 7),
+        (VIRTUAL_MACHINE, 13) => class_paths(class_table, &mut output),
+        // DisposeObjects, HoldEvents and ReleaseEvents have nothing to do.
+        (VIRTUAL_MACHINE, 14) | (VIRTUAL_MACHINE, 15) | (VIRTUAL_MACHINE, 16) => {},
+        (VIRTUAL_MACHINE, 17) => capabilities(&mut output, CAPABILITIES),
+        (VIRTUAL_MACHINE, 20) => all_classes(class_table, state, &mut output, true),
+
+        (REFERENCE_TYPE, 1) => output.string(&state.ids.type_of(input.u64()?)?.signature()),
+        (REFERENCE_TYPE, 2) => output.u64(0),
+        (REFERENCE_TYPE, 3) => output.i32(state.ids.class(input.u64()?)?.access_flags as i32),
+        (REFERENCE_TYPE, 4) => fields(state, input, &mut output, false)?,
+        (REFERENCE_TYPE, 5) => methods(state, input, &mut output, false)?,
+        (REFERENCE_TYPE, 6) => static_values(state, input, &mut output)?,
+        (REFERENCE_TYPE, 7) => {
+            let class = state.ids.class(input.u64()?)?;
+            output.string(class.source_file.as_ref().ok_or(ABSENT_INFORMATION)?);
+        },
+        (REFERENCE_TYPE, 9) => output.i32(class_status(&*state.ids.class(input.u64()?)?)),
+        (REFERENCE_TYPE, 10) => {
+            let class = state.ids.class(input.u64()?)?;
+            output.i32(class.interfaces.len() as i32);
+            for interface in class.interfaces.iter() {
+                output.u64(state.ids.class_id(interface));
+            }
+        },
+        (REFERENCE_TYPE, 13) => {
+            output.string(&state.ids.type_of(input.u64()?)?.signature());
+            output.string("");
+        },
+        (REFERENCE_TYPE, 14) => fields(state, input, &mut output, true)?,
+        (REFERENCE_TYPE, 15) => methods(state, input, &mut output, true)?,
+
+        (CLASS_TYPE, 1) => {
+            let class = state.ids.class(input.u64()?)?;
+            output.u64(class.super_class.as_ref().map_or(0, |super_class| state.ids.class_id(super_class)));
+        },
+
+        (METHOD, 1) => line_table(state, input, &mut output)?,
+        (METHOD, 2) => variable_table(state, input, &mut output, false)?,
+        (METHOD, 5) => variable_table(state, input, &mut output, true)?,
+
+        (OBJECT_REFERENCE, 1) => {
+            let value = state.ids.value(input.u64()?)?;
+            let type_id = type_of_value(state, &value)?;
+            output.u8(state.ids.type_of(type_id)?.tag());
+            output.u64(type_id);
+        },
+        (OBJECT_REFERENCE, 2) => instance_values(state, input, &mut output)?,
+        // Objects the debugger knows of are never collected.
+        (OBJECT_REFERENCE, 7) | (OBJECT_REFERENCE, 8) => {},
+        (OBJECT_REFERENCE, 9) => {
+            state.ids.value(input.u64()?)?;
+            output.boolean(false);
+        },
+
+        (STRING_REFERENCE, 1) => match state.ids.value(input.u64()?)? {
+            Value::ObjectRef(ref object) if object.borrow().class().class_name == string::STRING_CLASS_NAME => {
+                output.string(&string::to_rust_string(&object.borrow()));
+            },
+            _ => return Err(INVALID_OBJECT)
+        },
+
+        (THREAD_REFERENCE, 1) => {
+            let thread = state.ids.thread(input.u64()?)?;
+            output.string(&thread_name(class_table, thread).ok_or(INVALID_THREAD)?);
+        },
+        (THREAD_REFERENCE, 2) => {
+            let thread = state.ids.thread(input.u64()?)?;
+            *state.thread_suspended.entry(thread).or_insert(0) += 1;
+        },
+        (THREAD_REFERENCE, 3) => {
+            let thread = state.ids.thread(input.u64()?)?;
+            state.resume_thread(thread);
+        },
+        (THREAD_REFERENCE, 4) => {
+            let thread = state.ids.thread(input.u64()?)?;
+            output.i32(if thread_name(class_table, thread).is_some() { RUNNING } else { ZOMBIE });
+            output.i32(state.is_suspended(thread) as i32);
+        },
+        (THREAD_REFERENCE, 5) => {
+            state.ids.thread(input.u64()?)?;
+            output.u64(THREAD_GROUP_ID);
+        },
+        (THREAD_REFERENCE, 6) => frames(class_table, state, input, &mut output)?,
+        (THREAD_REFERENCE, 7) => {
+            let thread = suspended_thread(state, input.u64()?)?;
+            output.i32(class_table.stacks.frames(thread).map_or(0, |frames| frames.len()) as i32);
+        },
+        (THREAD_REFERENCE, 12) => {
+            let thread = state.ids.thread(input.u64()?)?;
+            output.i32(state.suspend_count(thread) as i32);
+        },
+        (THREAD_REFERENCE, 15) => {
+            state.ids.thread(input.u64()?)?;
+            output.boolean(false);
+        },
+
+        (THREAD_GROUP_REFERENCE, 1) => {
+            thread_group(input)?;
+            output.string("main");
+        },
+        (THREAD_GROUP_REFERENCE, 2) => {
+            thread_group(input)?;
+            output.u64(0);
+        },
+        (THREAD_GROUP_REFERENCE, 3) => {
+            thread_group(input)?;
+            all_threads(class_table, state, &mut output);
+            output.i32(0);
+        },
+
+        (ARRAY_REFERENCE, 1) => match state.ids.value(input.u64()?)? {
+            Value::ArrayRef(ref array) => output.i32(array.borrow().len() as i32),
+            _ => return Err(INVALID_OBJECT)
+        },
+        (ARRAY_REFERENCE, 2) => array_values(state, input, &mut output)?,
+
+        (EVENT_REQUEST, 1) => set_request(class_table, state, input, &mut output)?,
+        (EVENT_REQUEST, 2) => {
+            let kind = input.u8()?;
+            let id = input.i32()?;
+            state.requests.retain(|request| request.kind != kind || request.id != id);
+        },
+        (EVENT_REQUEST, 3) => state.requests.retain(|request| request.kind != BREAKPOINT),
+
+        (STACK_FRAME, 1) => frame_values(class_table, state, input, &mut output)?,
+        (STACK_FRAME, 3) => {
+            let (_, frame) = stack_frame(class_table, state, input)?;
+            let locals = unsafe { frame.stack_frame().as_ref() }.map(|stack_frame| &stack_frame.locals);
+            let this = match locals {
+                Some(locals) if !frame.method().is_static() => locals.first().cloned().unwrap_or(Value::Null),
+                _ => Value::Null
+            };
+            write_value(state, &mut output, b'L', &this);
+        },
+
+        _ => return Err(NOT_IMPLEMENTED)
+    }
+    Ok(output)
+}
+
+pub fn class_status(class: &RuntimeClass) -> i32 {
+    match class.state() {
+        ClassState::Initialized => VERIFIED | PREPARED | INITIALIZED,
+        ClassState::Erroneous => ERROR,
+        _ => VERIFIED | PREPARED
+    }
+}
+
+fn version(output: &mut Writer) {
+    output.string("IronJDK 17");
+    output.i32(17);
+    output.i32(0);
+    output.string("17");
+    output.string("IronJDK");
+}
+
+fn capabilities(output: &mut Writer, count: usize) {
+    for capability in 0..count {
+        output.boolean(capability == CAN_REQUEST_VM_DEATH_EVENT);
+    }
+}
+
+fn class_paths(class_table: &ClassTable, output: &mut Writer) {
+    output.string(&class_table.get_property("user.dir").unwrap_or_default());
+    let class_path = class_table.get_property("java.class.path").unwrap_or_default();
+    let separator = class_table.get_property("path.separator").unwrap_or_else(|| String::from(":"));
+    let entries: Vec<&str> = class_path.split(separator.as_str()).filter(|entry| !entry.is_empty()).collect();
+    output.i32(entries.len() as i32);
+    for entry in entries {
+        output.string(entry);
+    }
+    output.i32(0);
+}
+
+fn write_class(state: &mut State, output: &mut Writer, class: &Arc<RuntimeClass>, generic: bool) {
+    output.u8(class_tag(class));
+    output.u64(state.ids.class_id(class));
+    output.string(&format!("L{};", class.class_name));
+    if generic {
+        output.string("");
+    }
+    output.i32(class_status(class));
+}
+
+fn classes_by_signature(class_table: &ClassTable, state: &mut State, input: &mut Reader, output: &mut Writer) -> Result<(), u16> {
+    let signature = input.string()?;
+    let class = signature.strip_prefix('L')
+        .and_then(|name| name.strip_suffix(';'))
+        .and_then(|name| class_table.get_class(name));
+    match class {
+        Some(class) => {
+            output.i32(1);
+            output.u8(class_tag(&class));
+            output.u64(state.ids.class_id(&class));
+            output.i32(class_status(&class));
+        },
+        None => output.i32(0)
+    }
+    Ok(())
+}
+
+fn all_classes(class_table: &ClassTable, state: &mut State, output: &mut Writer, generic: bool) {
+    let classes = class_table.loaded_classes();
+    output.i32(classes.len() as i32);
+    for class in classes.iter() {
+        write_class(state, output, class, generic);
+    }
+}
+
+fn thread_name(class_table: &ClassTable, thread: ThreadId) -> Option<String> {
+    class_table.stacks.threads().into_iter().find(|&(id, _)| id == thread).map(|(_, name)| name)
+}
+
+fn all_threads(class_table: &ClassTable, state: &mut State, output: &mut Writer) {
+    let threads = class_table.stacks.threads();
+    output.i32(threads.len() as i32);
+    for (thread, _) in threads {
+        output.u64(state.ids.thread_id(thread));
+    }
+}
+
+fn thread_group(input: &mut Reader) -> Result<(), u16> {
+    match input.u64()? {
+        THREAD_GROUP_ID => Ok(()),
+        _ => Err(INVALID_OBJECT)
+    }
+}
+
+// Field ids are the id of the declaring type in the upper bits, and the position of the field
+// in it plus one in the lower 16.
+fn field_id(type_id: u64, index: usize) -> u64 {
+    (type_id << 16) | (index as u64 + 1)
+}
+
+fn field(state: &State, id: u64) -> Result<(Arc<RuntimeClass>, usize), u16> {
+    let class = state.ids.class(id >> 16).map_err(|_| INVALID_FIELDID)?;
+    let index = ((id & 0xffff) as usize).checked_sub(1).filter(|&index| index < class.fields.len()).ok_or(INVALID_FIELDID)?;
+    Ok((class, index))
+}
+
+fn fields(state: &mut State, input: &mut Reader, output: &mut Writer, generic: bool) -> Result<(), u16> {
+    let type_id = input.u64()?;
+    let class = match *state.ids.type_of(type_id)? {
+        Type::Class(ref class) => class.clone(),
+        Type::Array(_) => {
+            output.i32(0);
+            return Ok(());
+        }
+    };
+    output.i32(class.fields.len() as i32);
+    for (index, field) in class.fields.iter().enumerate() {
+        output.u64(field_id(type_id, index));
+        output.string(&field.name);
+        output.string(&field.descriptor_string);
+        if generic {
+            output.string("");
+        }
+        output.i32(field.access_flags as i32);
+    }
+    Ok(())
+}
+
+fn methods(state: &mut State, input: &mut Reader, output: &mut Writer, generic: bool) -> Result<(), u16> {
+    let class = match *state.ids.type_of(input.u64()?)? {
+        Type::Class(ref class) => class.clone(),
+        Type::Array(_) => {
+            output.i32(0);
+            return Ok(());
+        }
+    };
+    output.i32(class.methods.len() as i32);
+    for (index, method) in class.methods.iter().enumerate() {
+        output.u64(index as u64 + 1);
+        output.string(&method.name);
+        output.string(&method.descriptor);
+        if generic {
+            output.string("");
+        }
+        output.i32(method.access_flags as i32);
+    }
+    Ok(())
+}
+
+fn static_values(state: &mut State, input: &mut Reader, output: &mut Writer) -> Result<(), u16> {
+    input.u64()?;
+    let count = input.i32()?;
+    output.i32(count);
+    for _ in 0..count {
+        let (class, index) = field(state, input.u64()?)?;
+        let field = &class.fields[index];
+        let value = class.get_static(&field.name).ok_or(INVALID_FIELDID)?;
+        write_value(state, output, field.descriptor_string.as_bytes()[0], &value);
+    }
+    Ok(())
+}
+
+fn instance_values(state: &mut State, input: &mut Reader, output: &mut Writer) -> Result<(), u16> {
+    let object = match state.ids.value(input.u64()?)? {
+        Value::ObjectRef(object) => object,
+        _ => return Err(INVALID_OBJECT)
+    };
+    let count = input.i32()?;
+    output.i32(count);
+    for _ in 0..count {
+        let (class, index) = field(state, input.u64()?)?;
+        let field = &class.fields[index];
+        // The declaring class lays out its fields like its subclasses do, so the field is at
+        // the same position in the object.
+        let value = if field.is_static() {
+            class.get_static(&field.name).ok_or(INVALID_FIELDID)?
+        } else {
+            let position = class.instance_fields.iter()
+                .rposition(|instance_field| instance_field.name == field.name)
+                .ok_or(INVALID_FIELDID)?;
+            let object = object.borrow();
+            if !object.class().is_subclass_of(&class.class_name) {
+                return Err(INVALID_FIELDID);
+            }
+            object.fields()[position].clone()
+        };
+        write_value(state, output, field.descriptor_string.as_bytes()[0], &value);
+    }
+    Ok(())
+}
+
+fn array_values(state: &mut State, input: &mut Reader, output: &mut Writer) -> Result<(), u16> {
+    let array = match state.ids.value(input.u64()?)? {
+        Value::ArrayRef(array) => array,
+        _ => return Err(INVALID_OBJECT)
+    };
+    let first = input.i32()?;
+    let length = input.i32()?;
+    let array = array.borrow();
+    if first < 0 || first as usize > array.len() {
+        return Err(INVALID_INDEX);
+    }
+    if length < 0 || (first + length) as usize > array.len() {
+        return Err(INVALID_LENGTH);
+    }
+    let range = first as usize..(first + length) as usize;
+
+    let tag = array.component_type().as_bytes()[0];
+    output.u8(tag);
+    output.i32(length);
+    match array.elements() {
+        Elements::Byte(elements) if tag == b'Z' => elements[range].iter().for_each(|&element| output.boolean(element != 0)),
+        Elements::Byte(elements) => elements[range].iter().for_each(|&element| output.u8(element as u8)),
+        Elements::Character(elements) => elements[range].iter().for_each(|&element| output.u16(element)),
+        Elements::Short(elements) => elements[range].iter().for_each(|&element| output.u16(element as u16)),
+        Elements::Integer(elements) => elements[range].iter().for_each(|&element| output.i32(element)),
+        Elements::Long(elements) => elements[range].iter().for_each(|&element| output.u64(element as u64)),
+        Elements::Float(elements) => elements[range].iter().for_each(|&element| output.i32(element.to_bits() as i32)),
+        Elements::Double(elements) => elements[range].iter().for_each(|&element| output.u64(element.to_bits())),
+        Elements::Reference(elements) => {
+            for element in elements[range].iter() {
+                write_value(state, output, tag, element);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn method(state: &State, input: &mut Reader) -> Result<(Arc<RuntimeClass>, usize), u16> {
+    let class = state.ids.class(input.u64()?)?;
+    let index = (input.u64()? as usize).checked_sub(1).filter(|&index| index < class.methods.len()).ok_or(INVALID_METHODID)?;
+    Ok((class, index))
+}
+
+fn line_table(state: &mut State, input: &mut Reader, output: &mut Writer) -> Result<(), u16> {
+    let (class, index) = method(state, input)?;
+    match class.methods[index].code {
+        Some(ref code) => {
+            output.u64(0);
+            output.u64(code.instructions.last().map_or(0, |instruction| instruction.index as u64));
+            output.i32(code.line_numbers.len() as i32);
+            for entry in code.line_numbers.iter() {
+                output.u64(entry.start_pc as u64);
+                output.i32(entry.line_number as i32);
+            }
+        },
+        None => {
+            output.u64(u64::MAX);
+            output.u64(u64::MAX);
+            output.i32(0);
+        }
+    }
+    Ok(())
+}
+
+fn variable_table(state: &mut State, input: &mut Reader, output: &mut Writer, generic: bool) -> Result<(), u16> {
+    let (class, index) = method(state, input)?;
+    let method = &class.methods[index];
+    let code = method.code.as_ref().filter(|code| !code.local_variables.is_empty()).ok_or(ABSENT_INFORMATION)?;
+
+    // The number of slots the arguments take, with this.
+    let descriptor = MethodDescriptor::parse(&method.descriptor).ok_or(INVALID_METHODID)?;
+    let arguments: usize = descriptor.parameter_types().iter()
+        .map(|parameter| if parameter == "J" || parameter == "D" { 2 } else { 1 })
+        .sum();
+    output.i32((arguments + !method.is_static() as usize) as i32);
+
+    output.i32(code.local_variables.len() as i32);
+    for variable in code.local_variables.iter() {
+        output.u64(variable.start_pc as u64);
+        output.string(&variable.name);
+        output.string(&variable.descriptor);
+        if generic {
+            output.string("");
+        }
+        output.i32(variable.length as i32);
+        output.i32(variable.index as i32);
+    }
+    Ok(())
+}
+
+fn suspended_thread(state: &State, id: u64) -> Result<ThreadId, u16> {
+    let thread = state.ids.thread(id)?;
+    if !state.is_suspended(thread) {
+        return Err(THREAD_NOT_SUSPENDED);
+    }
+    Ok(thread)
+}
+
+// Frame ids are the id of the thread in the upper bits, and the depth of the frame, with the
+// innermost at 0, in the lower 16. They are only good while the thread stays suspended.
+fn frames(class_table: &ClassTable, state: &mut State, input: &mut Reader, output: &mut Writer) -> Result<(), u16> {
+    let thread_id = input.u64()?;
+    let thread = suspended_thread(state, thread_id)?;
+    let start = input.i32()?;
+    let length = input.i32()?;
+
+    let frames: Vec<Arc<Frame>> = class_table.stacks.frames(thread).unwrap_or_default().into_iter().rev().collect();
+    if start < 0 || start as usize > frames.len() {
+        return Err(INVALID_INDEX);
+    }
+    let end = if length == -1 { frames.len() } else { start as usize + length.max(0) as usize };
+    if end > frames.len() {
+        return Err(INVALID_LENGTH);
+    }
+
+    output.i32((end - start as usize) as i32);
+    for (depth, frame) in frames.iter().enumerate().take(end).skip(start as usize) {
+        output.u64((thread_id << 16) | depth as u64);
+        state.ids.write_location(output, &frame.class, frame.method(), frame.pc());
+    }
+    Ok(())
+}
+
+// The frame a StackFrame command names, once its thread has stopped, so that its locals can
+// be read.
+fn stack_frame(class_table: &ClassTable, state: &mut State, input: &mut Reader) -> Result<(ThreadId, Arc<Frame>), u16> {
+    let thread_id = input.u64()?;
+    let thread = state.ids.thread(thread_id)?;
+    let frame_id = input.u64()?;
+    if frame_id >> 16 != thread_id {
+        return Err(INVALID_FRAMEID);
+    }
+    // A thread suspended while it runs compiled or native code has not stopped yet.
+    if !state.stopped.contains(&thread) {
+        return Err(THREAD_NOT_SUSPENDED);
+    }
+    let frames = class_table.stacks.frames(thread).ok_or(INVALID_THREAD)?;
+    let frame = frames.iter().rev().nth((frame_id & 0xffff) as usize).ok_or(INVALID_FRAMEID)?;
+    Ok((thread, frame.clone()))
+}
+
+fn frame_values(class_table: &ClassTable, state: &mut State, input: &mut Reader, output: &mut Writer) -> Result<(), u16> {
+    let (_, frame) = stack_frame(class_table, state, input)?;
+    // The thread is stopped in the agent, waiting for the lock held while commands run.
+    let stack_frame = unsafe { frame.stack_frame().as_ref() }.ok_or(INVALID_FRAMEID)?;
+    let count = input.i32()?;
+    output.i32(count);
+    for _ in 0..count {
+        let slot = input.i32()?;
+        let tag = input.u8()?;
+        let value = stack_frame.locals.get(slot as usize).filter(|_| slot >= 0).ok_or(INVALID_SLOT)?;
+        write_value(state, output, tag, value);
+    }
+    Ok(())
+}
+
+fn type_of_value(state: &mut State, value: &Value) -> Result<u64, u16> {
+    match *value {
+        Value::ObjectRef(ref object) => Ok(state.ids.class_id(object.borrow().class())),
+        Value::ArrayRef(ref array) => Ok(state.ids.type_id(Type::Array(array.borrow().descriptor()))),
+        _ => Err(INVALID_OBJECT)
+    }
+}
+
+// Writes a value tagged with its type. The tag says how to read a primitive; references are
+// tagged as strings, arrays or other objects by what they refer to.
+fn write_value(state: &mut State, output: &mut Writer, tag: u8, value: &Value) {
+    let tag = match *value {
+        Value::ObjectRef(ref object) if object.borrow().class().class_name == string::STRING_CLASS_NAME => b's',
+        Value::ObjectRef(_) => b'L',
+        Value::ArrayRef(_) => b'[',
+        Value::Null if tag == b'[' => b'[',
+        Value::Null => b'L',
+        _ => tag
+    };
+    output.u8(tag);
+    match tag {
+        b'Z' => output.boolean(integer(value) != 0),
+        b'B' => output.u8(integer(value) as u8),
+        b'C' | b'S' => output.u16(integer(value) as u16),
+        b'I' => output.i32(integer(value) as i32),
+        b'J' => output.u64(integer(value) as u64),
+        b'F' => output.i32((double(value) as f32).to_bits() as i32),
+        b'D' => output.u64(double(value).to_bits()),
+        _ => output.u64(state.ids.object_id(value))
+    }
+}
+
+fn integer(value: &Value) -> i64 {
+    match *value {
+        Value::Long(value) => value,
+        Value::Integer(value) => value as i64,
+        Value::Short(value) => value as i64,
+        Value::Byte(value) => value as i64,
+        Value::Character(value) => value as i64,
+        Value::Float(value) => value as i64,
+        Value::Double(value) => value as i64,
+        _ => 0
+    }
+}
+
+fn double(value: &Value) -> f64 {
+    match *value {
+        Value::Float(value) => value as f64,
+        Value::Double(value) => value,
+        _ => integer(value) as f64
+    }
+}
+
+fn read_location(state: &State, input: &mut Reader) -> Result<Location, u16> {
+    input.u8()?;
+    let (class, index) = method(state, input)?;
+    let pc = input.u64()?;
+    let code = class.methods[index].code.as_ref().ok_or(INVALID_LOCATION)?;
+    if pc > u16::MAX as u64 || code.instruction_position(pc as u16).is_none() {
+        return Err(INVALID_LOCATION);
+    }
+    Ok(Location { class, method: index as u64 + 1, pc: pc as u16 })
+}
+
+fn set_request(class_table: &ClassTable, state: &mut State, input: &mut Reader, output: &mut Writer) -> Result<(), u16> {
+    let kind = input.u8()?;
+    let suspend_policy = input.u8()?;
+    if suspend_policy > SUSPEND_ALL {
+        return Err(ILLEGAL_ARGUMENT);
+    }
+    let count = input.i32()?;
+
+    let mut modifiers = Vec::new();
+    for _ in 0..count {
+        let modifier = match input.u8()? {
+            1 => Modifier::Count(input.i32()?),
+            2 => {
+                input.i32()?;
+                Modifier::Ignored
+            },
+            3 => Modifier::ThreadOnly(state.ids.thread(input.u64()?)?),
+            4 => Modifier::ClassOnly(state.ids.class(input.u64()?)?),
+            5 => Modifier::ClassMatch(input.string()?),
+            6 => Modifier::ClassExclude(input.string()?),
+            7 => Modifier::LocationOnly(read_location(state, input)?),
+            8 => {
+                input.u64()?;
+                input.boolean()?;
+                input.boolean()?;
+                Modifier::Ignored
+            },
+            9 => {
+                input.u64()?;
+                input.u64()?;
+                Modifier::Ignored
+            },
+            10 => {
+                let thread = suspended_thread(state, input.u64()?)?;
+                let size = input.i32()?;
+                let depth = input.i32()?;
+                // Where the thread is now, to tell when it has got far enough.
+                let frames = class_table.stacks.frames(thread).unwrap_or_default();
+                let line = frames.last().and_then(|frame| frame.line_number());
+                Modifier::Step { thread, size, depth, frames: frames.len(), line }
+            },
+            11 => {
+                input.u64()?;
+                Modifier::Ignored
+            },
+            12 => {
+                input.string()?;
+                Modifier::Ignored
+            },
+            13 => Modifier::Ignored,
+            _ => return Err(ILLEGAL_ARGUMENT)
+        };
+        modifiers.push(modifier);
+    }
+
+    let has_location = modifiers.iter().any(|modifier| matches!(*modifier, Modifier::LocationOnly(_)));
+    let has_step = modifiers.iter().any(|modifier| matches!(*modifier, Modifier::Step { .. }));
+    if (kind == BREAKPOINT && !has_location) || (kind == SINGLE_STEP && !has_step) {
+        return Err(ILLEGAL_ARGUMENT);
+    }
+    if kind == 0 {
+        return Err(INVALID_EVENT_TYPE);
+    }
+
+    let id = state.next_request;
+    state.next_request += 1;
+    state.requests.push(Request { id, kind, suspend_policy, modifiers, expired: false });
+    output.i32(id);
+    Ok(())
+}
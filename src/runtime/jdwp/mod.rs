@@ -0,0 +1,952 @@
+use runtime::Value;
+use runtime::class::{ClassTable, RuntimeClass};
+use runtime::class::method::RuntimeMethod;
+use self::packet::{Packet, Reader, Writer};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::thread::{self, ThreadId};
+
+mod commands;
+pub mod packet;
+
+// A debug agent speaking the Java Debug Wire Protocol over a socket, so that IDEs can attach to
+// the virtual machine as they attach to HotSpot started with -agentlib:jdwp. Like the --debug
+// debugger, it is asked before each instruction the interpreter runs whether to stop there.
+//
+// Only a subset of JDWP is implemented: enough of the VirtualMachine, ReferenceType, Method,
+// ThreadReference, StackFrame and ObjectReference command sets to browse classes, threads,
+// frames and values, and breakpoint, single step and class prepare events. Requests for other
+// events are accepted but never reported. Threads stop at the next instruction they interpret
+// once suspended; threads running compiled or native code do not stop until they return to the
+// interpreter, which is why the launcher turns the JIT off.
+
+// Event kinds.
+const SINGLE_STEP: u8 = 1;
+const BREAKPOINT: u8 = 2;
+const CLASS_PREPARE: u8 = 8;
+const VM_START: u8 = 90;
+const VM_DEATH: u8 = 99;
+
+// Suspend policies, from weakest to strongest.
+const SUSPEND_NONE: u8 = 0;
+const SUSPEND_EVENT_THREAD: u8 = 1;
+const SUSPEND_ALL: u8 = 2;
+
+// Type tags.
+const TYPE_CLASS: u8 = 1;
+const TYPE_INTERFACE: u8 = 2;
+const TYPE_ARRAY: u8 = 3;
+
+// Step sizes and depths.
+const STEP_MIN: i32 = 0;
+const STEP_OVER: i32 = 1;
+const STEP_OUT: i32 = 2;
+
+const EVENT_COMMAND_SET: u8 = 64;
+const COMPOSITE: u8 = 100;
+
+// The only thread group, which every thread belongs to.
+const THREAD_GROUP_ID: u64 = 1;
+
+// Where the agent listens, as given by the options of -agentlib:jdwp.
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    pub address: String,
+    // Whether the program waits for a debugger to attach before it starts.
+    pub suspend: bool
+}
+
+impl Options {
+
+    // Parses e.g. transport=dt_socket,server=y,suspend=n,address=5005. Like HotSpot, a bare
+    // port only listens on the loopback interface, and *:port on all of them.
+    pub fn parse(options: &str) -> Result<Options, String> {
+        let mut address = None;
+        let mut suspend = true;
+        for option in options.split(',') {
+            match option.split_once('=') {
+                Some(("transport", "dt_socket")) | Some(("server", "y")) => {},
+                Some(("transport", transport)) => return Err(format!("Unsupported JDWP transport: {}", transport)),
+                Some(("server", _)) => return Err(String::from("Only server=y is supported by JDWP")),
+                Some(("suspend", "y")) => suspend = true,
+                Some(("suspend", "n")) => suspend = false,
+                Some(("address", value)) if !value.is_empty() => address = Some(value),
+                _ => return Err(format!("Invalid JDWP option: {}", option))
+            }
+        }
+
+        let address = match address {
+            Some(address) => match address.rsplit_once(':') {
+                Some(("*", port)) => format!("0.0.0.0:{}", port),
+                Some(_) => String::from(address),
+                None => format!("127.0.0.1:{}", address)
+            },
+            None => return Err(String::from("JDWP needs an address"))
+        };
+        Ok(Options { address, suspend })
+    }
+
+}
+
+// What an object id refers to.
+enum Referent {
+    Object(Value),
+    Thread(ThreadId)
+}
+
+// A reference type, as named by a reference type id.
+enum Type {
+    Class(Arc<RuntimeClass>),
+    // An array type, by its descriptor.
+    Array(String)
+}
+
+impl Type {
+
+    fn tag(&self) -> u8 {
+        match *self {
+            Type::Class(ref class) => class_tag(class),
+            Type::Array(_) => TYPE_ARRAY
+        }
+    }
+
+    fn signature(&self) -> String {
+        match *self {
+            Type::Class(ref class) => format!("L{};", class.class_name),
+            Type::Array(ref descriptor) => descriptor.clone()
+        }
+    }
+
+}
+
+// The ids handed out to the debugger. Objects it was told about are kept alive until it
+// disconnects. Field ids carry the id of their declaring type in their upper bits, and method
+// ids are the position of the method in its class plus one.
+struct Ids {
+    objects: HashMap<u64, Referent>,
+    // The ids of objects and arrays by the address of their cell.
+    by_address: HashMap<usize, u64>,
+    by_thread: HashMap<ThreadId, u64>,
+    next_object: u64,
+    // Type i has id i + 1.
+    types: Vec<Type>,
+    by_signature: HashMap<String, u64>
+}
+
+impl Ids {
+
+    fn new() -> Ids {
+        Ids {
+            objects: HashMap::new(),
+            by_address: HashMap::new(),
+            by_thread: HashMap::new(),
+            next_object: THREAD_GROUP_ID + 1,
+            types: Vec::new(),
+            by_signature: HashMap::new()
+        }
+    }
+
+    fn add(&mut self, referent: Referent) -> u64 {
+        let id = self.next_object;
+        self.next_object += 1;
+        self.objects.insert(id, referent);
+        id
+    }
+
+    // The id of an object or array, or 0 for null.
+    fn object_id(&mut self, value: &Value) -> u64 {
+        let address = match *value {
+            Value::ObjectRef(ref object) => Arc::as_ptr(object) as usize,
+            Value::ArrayRef(ref array) => Arc::as_ptr(array) as usize,
+            _ => return 0
+        };
+        if let Some(&id) = self.by_address.get(&address) {
+            return id;
+        }
+        let id = self.add(Referent::Object(value.clone()));
+        self.by_address.insert(address, id);
+        id
+    }
+
+    fn thread_id(&mut self, thread: ThreadId) -> u64 {
+        if let Some(&id) = self.by_thread.get(&thread) {
+            return id;
+        }
+        let id = self.add(Referent::Thread(thread));
+        self.by_thread.insert(thread, id);
+        id
+    }
+
+    fn type_id(&mut self, type_: Type) -> u64 {
+        let signature = type_.signature();
+        if let Some(&id) = self.by_signature.get(&signature) {
+            return id;
+        }
+        self.types.push(type_);
+        let id = self.types.len() as u64;
+        self.by_signature.insert(signature, id);
+        id
+    }
+
+    fn class_id(&mut self, class: &Arc<RuntimeClass>) -> u64 {
+        self.type_id(Type::Class(class.clone()))
+    }
+
+    // The object with the id, or null for 0.
+    fn value(&self, id: u64) -> Result<Value, u16> {
+        match self.objects.get(&id) {
+            _ if id == 0 => Ok(Value::Null),
+            Some(Referent::Object(value)) => Ok(value.clone()),
+            _ => Err(packet::INVALID_OBJECT)
+        }
+    }
+
+    fn thread(&self, id: u64) -> Result<ThreadId, u16> {
+        match self.objects.get(&id) {
+            Some(&Referent::Thread(thread)) => Ok(thread),
+            _ => Err(packet::INVALID_THREAD)
+        }
+    }
+
+    fn type_of(&self, id: u64) -> Result<&Type, u16> {
+        id.checked_sub(1).and_then(|index| self.types.get(index as usize)).ok_or(packet::INVALID_CLASS)
+    }
+
+    fn class(&self, id: u64) -> Result<Arc<RuntimeClass>, u16> {
+        match *self.type_of(id)? {
+            Type::Class(ref class) => Ok(class.clone()),
+            Type::Array(_) => Err(packet::INVALID_CLASS)
+        }
+    }
+
+    // Writes a location: the type, the method and the pc, or -1 for a frame that is not
+    // interpreted.
+    fn write_location(&mut self, output: &mut Writer, class: &Arc<RuntimeClass>, method: &RuntimeMethod, pc: Option<u16>) {
+        output.u8(class_tag(class));
+        output.u64(self.class_id(class));
+        output.u64(method_id(class, method));
+        output.u64(pc.map_or(u64::MAX, u64::from));
+    }
+
+}
+
+fn class_tag(class: &RuntimeClass) -> u8 {
+    if class.is_interface() { TYPE_INTERFACE } else { TYPE_CLASS }
+}
+
+fn method_id(class: &RuntimeClass, method: &RuntimeMethod) -> u64 {
+    class.methods.iter().position(|candidate| std::ptr::eq(candidate, method)).map_or(0, |index| index as u64 + 1)
+}
+
+// A breakpoint location, as the debugger gives it.
+struct Location {
+    class: Arc<RuntimeClass>,
+    method: u64,
+    pc: u16
+}
+
+enum Modifier {
+    // Reported only the nth time, after which the request expires.
+    Count(i32),
+    ThreadOnly(ThreadId),
+    // The class or one of its subclasses.
+    ClassOnly(Arc<RuntimeClass>),
+    ClassMatch(String),
+    ClassExclude(String),
+    LocationOnly(Location),
+    // Where and how deep the thread was when it was asked to step.
+    Step { thread: ThreadId, size: i32, depth: i32, frames: usize, line: Option<u16> },
+    // A filter for events that are never reported.
+    Ignored
+}
+
+struct Request {
+    id: i32,
+    kind: u8,
+    suspend_policy: u8,
+    modifiers: Vec<Modifier>,
+    expired: bool
+}
+
+// Something that happened in a thread the debugger may want to hear of.
+enum Event<'a> {
+    // The thread is about to run the instruction at pc. `frames` is how deep it is.
+    Instruction { class: &'a Arc<RuntimeClass>, method: &'a RuntimeMethod, pc: u16, frames: usize },
+    ClassPrepare(&'a Arc<RuntimeClass>)
+}
+
+impl<'a> Event<'a> {
+
+    fn class(&self) -> &'a Arc<RuntimeClass> {
+        match *self {
+            Event::Instruction { class, .. } => class,
+            Event::ClassPrepare(class) => class
+        }
+    }
+
+}
+
+impl Request {
+
+    fn reports(&self, event: &Event) -> bool {
+        match *event {
+            Event::Instruction { .. } => self.kind == BREAKPOINT || self.kind == SINGLE_STEP,
+            Event::ClassPrepare(_) => self.kind == CLASS_PREPARE
+        }
+    }
+
+    // Whether the event is reported for this request. Count modifiers are applied after all
+    // the others, so that only the events that pass them are counted.
+    fn matches(&mut self, thread: ThreadId, event: &Event) -> bool {
+        if self.expired || !self.reports(event) {
+            return false;
+        }
+        let class = event.class();
+        let class_name = class.class_name.replace('/', ".");
+        let filtered = self.modifiers.iter().all(|modifier| match *modifier {
+            Modifier::ThreadOnly(only) => only == thread,
+            Modifier::ClassOnly(ref only) => class.is_subclass_of(&only.class_name),
+            Modifier::ClassMatch(ref pattern) => matches_pattern(pattern, &class_name),
+            Modifier::ClassExclude(ref pattern) => !matches_pattern(pattern, &class_name),
+            Modifier::LocationOnly(ref location) => match *event {
+                Event::Instruction { method, pc, .. } => {
+                    Arc::ptr_eq(&location.class, class) && location.method == method_id(class, method) && location.pc == pc
+                },
+                _ => false
+            },
+            Modifier::Step { thread: stepping, size, depth, frames, line } => match *event {
+                Event::Instruction { method, pc, frames: now, .. } => {
+                    stepping == thread && stepped(size, depth, frames, line, method, pc, now)
+                },
+                _ => false
+            },
+            Modifier::Count(_) | Modifier::Ignored => true
+        });
+        if !filtered {
+            return false;
+        }
+
+        for modifier in self.modifiers.iter_mut() {
+            if let Modifier::Count(ref mut count) = *modifier {
+                *count -= 1;
+                if *count > 0 {
+                    return false;
+                }
+                self.expired = true;
+            }
+        }
+        true
+    }
+
+}
+
+// Class patterns may start or end with *, e.g. java.* or *.Test.
+fn matches_pattern(pattern: &str, class_name: &str) -> bool {
+    if let Some(suffix) = pattern.strip_prefix('*') {
+        class_name.ends_with(suffix)
+    } else if let Some(prefix) = pattern.strip_suffix('*') {
+        class_name.starts_with(prefix)
+    } else {
+        pattern == class_name
+    }
+}
+
+// Whether a step that started `frames` deep on `line` is done before the instruction at pc, now
+// `now` frames deep. Steps by line stop at the start of another line, or of any line in a frame
+// called from the one that stepped, and as soon as that frame has returned.
+fn stepped(size: i32, depth: i32, frames: usize, line: Option<u16>, method: &RuntimeMethod, pc: u16, now: usize) -> bool {
+    if (depth == STEP_OVER && now > frames) || (depth == STEP_OUT && now >= frames) {
+        return false;
+    }
+    let code = match method.code {
+        Some(ref code) if size != STEP_MIN && now >= frames && !code.line_numbers.is_empty() => code,
+        _ => return true
+    };
+    code.line_numbers.iter().any(|entry| entry.start_pc == pc) && (now != frames || code.line_number(pc) != line)
+}
+
+struct State {
+    connected: bool,
+    // How often all threads were suspended, by VirtualMachine.Suspend or an event.
+    suspended: u32,
+    // How often each thread was suspended on its own, on top of that.
+    thread_suspended: HashMap<ThreadId, u32>,
+    // The threads waiting in the agent until they are resumed.
+    stopped: HashSet<ThreadId>,
+    requests: Vec<Request>,
+    next_request: i32,
+    ids: Ids
+}
+
+impl State {
+
+    fn new() -> State {
+        State {
+            connected: false,
+            suspended: 0,
+            thread_suspended: HashMap::new(),
+            stopped: HashSet::new(),
+            requests: Vec::new(),
+            next_request: 1,
+            ids: Ids::new()
+        }
+    }
+
+    fn suspend_count(&self, thread: ThreadId) -> u32 {
+        self.suspended + self.thread_suspended.get(&thread).cloned().unwrap_or(0)
+    }
+
+    fn is_suspended(&self, thread: ThreadId) -> bool {
+        self.suspend_count(thread) > 0
+    }
+
+    fn suspend(&mut self, policy: u8, thread: ThreadId) {
+        match policy {
+            SUSPEND_ALL => self.suspended += 1,
+            SUSPEND_EVENT_THREAD => *self.thread_suspended.entry(thread).or_insert(0) += 1,
+            _ => {}
+        }
+    }
+
+    // VirtualMachine.Resume, which takes one off the suspend count of every thread.
+    fn resume_all(&mut self) {
+        self.suspended = self.suspended.saturating_sub(1);
+        for count in self.thread_suspended.values_mut() {
+            *count = count.saturating_sub(1);
+        }
+    }
+
+    // ThreadReference.Resume. A thread suspended with all the others stays suspended.
+    fn resume_thread(&mut self, thread: ThreadId) {
+        if let Some(count) = self.thread_suspended.get_mut(&thread) {
+            *count = count.saturating_sub(1);
+        }
+    }
+
+}
+
+pub struct Agent {
+    enabled: AtomicBool,
+    state: Mutex<State>,
+    // Signalled when a thread is resumed or stops.
+    changed: Condvar,
+    // Where replies and events are written. The agent thread reads commands from its own clone.
+    connection: Mutex<Option<TcpStream>>,
+    next_packet: AtomicU32
+}
+
+impl Agent {
+
+    pub fn new() -> Agent {
+        Agent {
+            enabled: AtomicBool::new(false),
+            state: Mutex::new(State::new()),
+            changed: Condvar::new(),
+            connection: Mutex::new(None),
+            next_packet: AtomicU32::new(1)
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    // Calls visit with every object the debugger has an id for, as they are roots of the heap.
+    pub fn visit_objects(&self, visit: &mut dyn FnMut(&Value)) {
+        for referent in self.state.lock().unwrap().ids.objects.values() {
+            if let Referent::Object(ref value) = *referent {
+                visit(value);
+            }
+        }
+    }
+
+    // Serves debuggers connecting to the listener from a thread of its own, one at a time. With
+    // suspend, first waits for a debugger to attach, and the program only starts once it
+    // resumes the virtual machine.
+    pub fn start(class_table: &Arc<ClassTable>, listener: TcpListener, suspend: bool) -> io::Result<()> {
+        let agent = &class_table.jdwp;
+        // The thread that runs main is the one VM_START is reported for.
+        class_table.stacks.register_current();
+        let main = thread::current().id();
+        agent.enabled.store(true, Ordering::SeqCst);
+
+        let mut first = None;
+        if suspend {
+            let stream = accept(&listener)?;
+            agent.attach(&stream, main, SUSPEND_ALL)?;
+            first = Some(stream);
+        }
+
+        let class_table = class_table.clone();
+        thread::Builder::new()
+            .name(String::from("JDWP Transport Listener"))
+            .spawn(move || {
+                let agent = &class_table.jdwp;
+                loop {
+                    let stream = match first.take() {
+                        Some(stream) => stream,
+                        None => match accept(&listener).and_then(|stream| agent.attach(&stream, main, SUSPEND_NONE).map(|_| stream)) {
+                            Ok(stream) => stream,
+                            Err(_) => continue
+                        }
+                    };
+                    agent.serve(&class_table, stream);
+                }
+            })?;
+        Ok(())
+    }
+
+    fn attach(&self, stream: &TcpStream, main: ThreadId, suspend_policy: u8) -> io::Result<()> {
+        *self.connection.lock().unwrap() = Some(stream.try_clone()?);
+        let mut state = self.state.lock().unwrap();
+        state.connected = true;
+        state.suspend(suspend_policy, main);
+
+        let mut event = Writer::new();
+        event.u8(VM_START);
+        event.i32(0);
+        event.u64(state.ids.thread_id(main));
+        self.send_events(suspend_policy, vec![event]);
+        Ok(())
+    }
+
+    // Runs commands until the debugger disposes of the connection or goes away. Everything it
+    // suspended is resumed then.
+    fn serve(&self, class_table: &ClassTable, mut stream: TcpStream) {
+        while let Ok(packet) = Packet::read(&mut stream) {
+            let (id, command_set, command, data) = match packet {
+                Packet::Command { id, command_set, command, data } => (id, command_set, command, data),
+                Packet::Reply { .. } => continue
+            };
+
+            let result = {
+                let mut state = self.state.lock().unwrap();
+                let result = commands::run(class_table, &mut state, command_set, command, &mut Reader::new(&data));
+                self.changed.notify_all();
+                result
+            };
+            let reply = match result {
+                Ok(output) => Packet::Reply { id, error: 0, data: output.data },
+                Err(error) => Packet::Reply { id, error, data: Vec::new() }
+            };
+            self.send(&reply);
+
+            match (command_set, command) {
+                (commands::VIRTUAL_MACHINE, commands::DISPOSE) => break,
+                (commands::VIRTUAL_MACHINE, commands::EXIT) => {
+                    let status = Reader::new(&data).i32().unwrap_or(0);
+                    class_table.console.flush();
+                    process::exit(status);
+                },
+                _ => {}
+            }
+        }
+        self.detach();
+    }
+
+    fn detach(&self) {
+        *self.connection.lock().unwrap() = None;
+        let mut state = self.state.lock().unwrap();
+        *state = State::new();
+        self.changed.notify_all();
+    }
+
+    fn send(&self, packet: &Packet) {
+        if let Some(ref mut stream) = *self.connection.lock().unwrap() {
+            // A debugger that went away is noticed by the agent thread.
+            let _ = packet.write(stream);
+        }
+    }
+
+    fn send_events(&self, suspend_policy: u8, events: Vec<Writer>) {
+        let mut data = Writer::new();
+        data.u8(suspend_policy);
+        data.i32(events.len() as i32);
+        for event in events {
+            data.data.extend(event.data);
+        }
+        let id = self.next_packet.fetch_add(1, Ordering::Relaxed);
+        self.send(&Packet::Command { id, command_set: EVENT_COMMAND_SET, command: COMPOSITE, data: data.data });
+    }
+
+    // Called by the interpreter before it runs the instruction at pc. Reports breakpoints and
+    // steps, and stops the thread while it is suspended.
+    pub fn before_instruction(&self, class_table: &ClassTable, class: &Arc<RuntimeClass>, method: &RuntimeMethod, pc: u16) {
+        let mut state = self.state.lock().unwrap();
+        if !state.connected {
+            return;
+        }
+        let thread = thread::current().id();
+        if !state.requests.is_empty() {
+            let frames = class_table.stacks.depth();
+            self.report(&mut state, thread, &Event::Instruction { class, method, pc, frames });
+        }
+        self.stop_while_suspended(class_table, state, thread);
+    }
+
+    // Called once a class has been loaded from the class path.
+    pub fn class_prepared(&self, class_table: &ClassTable, class: &Arc<RuntimeClass>) {
+        let mut state = self.state.lock().unwrap();
+        if !state.connected || state.requests.is_empty() {
+            return;
+        }
+        let thread = thread::current().id();
+        self.report(&mut state, thread, &Event::ClassPrepare(class));
+        self.stop_while_suspended(class_table, state, thread);
+    }
+
+    // Tells the debugger the virtual machine is about to exit.
+    pub fn vm_death(&self) {
+        let state = self.state.lock().unwrap();
+        if !state.connected {
+            return;
+        }
+        let mut event = Writer::new();
+        event.u8(VM_DEATH);
+        event.i32(0);
+        self.send_events(SUSPEND_NONE, vec![event]);
+    }
+
+    // Sends one composite event for all requests the event matches, and suspends as the
+    // strongest of their policies asks.
+    fn report(&self, state: &mut State, thread: ThreadId, event: &Event) {
+        let mut matched = Vec::new();
+        for request in state.requests.iter_mut() {
+            if request.matches(thread, event) {
+                matched.push((request.kind, request.id, request.suspend_policy));
+            }
+        }
+        state.requests.retain(|request| !request.expired);
+        if matched.is_empty() {
+            return;
+        }
+
+        let mut events = Vec::new();
+        for &(kind, request_id, _) in matched.iter() {
+            let mut data = Writer::new();
+            data.u8(kind);
+            data.i32(request_id);
+            data.u64(state.ids.thread_id(thread));
+            match *event {
+                Event::Instruction { class, method, pc, .. } => state.ids.write_location(&mut data, class, method, Some(pc)),
+                Event::ClassPrepare(class) => {
+                    data.u8(class_tag(class));
+                    data.u64(state.ids.class_id(class));
+                    data.string(&format!("L{};", class.class_name));
+                    data.i32(commands::class_status(class));
+                }
+            }
+            events.push(data);
+        }
+        let suspend_policy = matched.iter().map(|&(_, _, policy)| policy).max().unwrap_or(SUSPEND_NONE);
+        state.suspend(suspend_policy, thread);
+        self.send_events(suspend_policy, events);
+    }
+
+    // Waits, as if at a safepoint, until the thread is resumed.
+    fn stop_while_suspended(&self, class_table: &ClassTable, mut state: MutexGuard<State>, thread: ThreadId) {
+        if !state.is_suspended(thread) {
+            return;
+        }
+        class_table.heap.blocking(move || {
+            state.stopped.insert(thread);
+            self.changed.notify_all();
+            while state.is_suspended(thread) {
+                state = self.changed.wait(state).unwrap();
+            }
+            state.stopped.remove(&thread);
+        });
+    }
+
+}
+
+impl Default for Agent {
+    fn default() -> Agent {
+        Agent::new()
+    }
+}
+
+fn accept(listener: &TcpListener) -> io::Result<TcpStream> {
+    let (mut stream, _) = listener.accept()?;
+    let mut handshake = [0; 14];
+    stream.read_exact(&mut handshake)?;
+    if handshake != packet::HANDSHAKE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a JDWP handshake"));
+    }
+    stream.write_all(packet::HANDSHAKE)?;
+    stream.set_nodelay(true)?;
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use class::reader;
+    use runtime::interpreter;
+    use runtime::bootstrap::io::{Console, SharedBuffer};
+    use std::collections::VecDeque;
+    use std::time::Duration;
+
+    #[test]
+    fn options() {
+        assert_eq!(Options::parse("transport=dt_socket,server=y,address=localhost:5005"), Ok(Options { address: String::from("localhost:5005"), suspend: true }));
+        assert_eq!(Options::parse("server=n,address=5005"), Err(String::from("Only server=y is supported by JDWP")));
+        assert_eq!(Options::parse("address=5005,quiet"), Err(String::from("Invalid JDWP option: quiet")));
+    }
+
+    #[test]
+    fn class_patterns() {
+        assert!(matches_pattern("java.*", "java.lang.String"));
+        assert!(matches_pattern("*.Main", "com.example.Main"));
+        assert!(matches_pattern("Main", "Main"));
+        assert!(!matches_pattern("Main", "com.example.Main"));
+    }
+
+    // A debugger talking to the agent over a socket, as a script. Events that arrive while it
+    // waits for a reply are kept for later.
+    struct Client {
+        stream: TcpStream,
+        next_id: u32,
+        events: VecDeque<Vec<u8>>
+    }
+
+    impl Client {
+
+        fn connect(address: &str) -> Client {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+            stream.write_all(packet::HANDSHAKE).unwrap();
+            Client { stream, next_id: 1, events: VecDeque::new() }
+        }
+
+        fn handshake(&mut self) {
+            let mut handshake = [0; 14];
+            self.stream.read_exact(&mut handshake).unwrap();
+            assert_eq!(&handshake[..], packet::HANDSHAKE);
+        }
+
+        fn request(&mut self, command_set: u8, command: u8, data: Writer) -> Result<Vec<u8>, u16> {
+            let id = self.next_id;
+            self.next_id += 1;
+            Packet::Command { id, command_set, command, data: data.data }.write(&mut self.stream).unwrap();
+            loop {
+                match Packet::read(&mut self.stream).unwrap() {
+                    Packet::Reply { id: reply, error, data } if reply == id => {
+                        return if error == 0 { Ok(data) } else { Err(error) };
+                    },
+                    Packet::Command { command_set: EVENT_COMMAND_SET, command: COMPOSITE, data, .. } => self.events.push_back(data),
+                    packet => panic!("Unexpected packet {:?}", packet)
+                }
+            }
+        }
+
+        fn command(&mut self, command_set: u8, command: u8, data: Writer) -> Vec<u8> {
+            self.request(command_set, command, data).unwrap()
+        }
+
+        fn event(&mut self) -> Vec<u8> {
+            if let Some(event) = self.events.pop_front() {
+                return event;
+            }
+            match Packet::read(&mut self.stream).unwrap() {
+                Packet::Command { command_set: EVENT_COMMAND_SET, command: COMPOSITE, data, .. } => data,
+                packet => panic!("Unexpected packet {:?}", packet)
+            }
+        }
+
+    }
+
+    fn data<F: FnOnce(&mut Writer)>(write: F) -> Writer {
+        let mut writer = Writer::new();
+        write(&mut writer);
+        writer
+    }
+
+    fn id(id: u64) -> Writer {
+        data(|writer| writer.u64(id))
+    }
+
+    // Reads a composite event with a single event in it, returning its suspend policy, kind,
+    // request id and thread.
+    fn single_event<'a>(event: &'a [u8]) -> (u8, u8, i32, u64, Reader<'a>) {
+        let mut reader = Reader::new(event);
+        let policy = reader.u8().unwrap();
+        assert_eq!(reader.i32(), Ok(1));
+        let kind = reader.u8().unwrap();
+        let request = reader.i32().unwrap();
+        let thread = if kind == VM_DEATH { 0 } else { reader.u64().unwrap() };
+        (policy, kind, request, thread, reader)
+    }
+
+    // Reads a location, returning its class, method and pc.
+    fn location(reader: &mut Reader) -> (u64, u64, u64) {
+        assert_eq!(reader.u8(), Ok(TYPE_CLASS));
+        (reader.u64().unwrap(), reader.u64().unwrap(), reader.u64().unwrap())
+    }
+
+    #[test]
+    fn scripted_debugger() {
+        let out = SharedBuffer::new();
+        let class_table = ClassTable::with_console(Console::new(Box::new(out.clone()), Box::new(SharedBuffer::new())));
+        class_table.set_compile_threshold(0);
+        class_table.define_class(&reader::read_class_file(include_bytes!("../../../fixtures/Debuggee.class")).unwrap()).unwrap();
+
+        // The handshake waits in the socket until the agent accepts it.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = Client::connect(&listener.local_addr().unwrap().to_string());
+        Agent::start(&class_table, listener, true).unwrap();
+        client.handshake();
+
+        let start = client.event();
+        let (policy, kind, _, _, _) = single_event(&start);
+        assert_eq!((policy, kind), (SUSPEND_ALL, VM_START));
+
+        // The program is suspended until the debugger resumes it.
+        let vm = {
+            let class_table = class_table.clone();
+            thread::spawn(move || {
+                let class = class_table.get_class("Debuggee").unwrap();
+                let main = class.get_declared_method("main", "([Ljava/lang/String;)V").unwrap();
+                let arguments = Value::ArrayRef(class_table.new_array("Ljava/lang/String;", 0));
+                interpreter::invoke_method(&class, main, vec![arguments], &class_table).unwrap();
+            })
+        };
+
+        let sizes = client.command(1, 7, Writer::new());
+        assert_eq!(sizes, data(|writer| (0..5).for_each(|_| writer.i32(8))).data);
+        let version = client.command(1, 1, Writer::new());
+        let mut reader = Reader::new(&version);
+        reader.string().unwrap();
+        assert_eq!((reader.i32(), reader.i32()), (Ok(17), Ok(0)));
+        reader.string().unwrap();
+        assert_eq!(reader.string(), Ok(String::from("IronJDK")));
+
+        // Finds Debuggee.add and the first instruction of line 17.
+        let classes = client.command(1, 2, data(|writer| writer.string("LDebuggee;")));
+        let mut reader = Reader::new(&classes);
+        assert_eq!(reader.i32(), Ok(1));
+        assert_eq!(reader.u8(), Ok(TYPE_CLASS));
+        let class_id = reader.u64().unwrap();
+        assert_eq!(Reader::new(&client.command(2, 7, id(class_id))).string(), Ok(String::from("Debuggee.java")));
+
+        let methods = client.command(2, 5, id(class_id));
+        let mut reader = Reader::new(&methods);
+        let methods: Vec<(u64, String)> = (0..reader.i32().unwrap())
+            .map(|_| (reader.u64().unwrap(), reader.string().unwrap(), reader.string().unwrap(), reader.i32().unwrap()))
+            .map(|(method, name, _, _)| (method, name))
+            .collect();
+        let method_id = |name: &str| methods.iter().find(|method| method.1 == name).unwrap().0;
+        let (add, main) = (method_id("add"), method_id("main"));
+        let lines = client.command(6, 1, data(|writer| { writer.u64(class_id); writer.u64(add); }));
+        let mut reader = Reader::new(&lines);
+        reader.u64().unwrap();
+        reader.u64().unwrap();
+        let lines: Vec<(u64, i32)> = (0..reader.i32().unwrap()).map(|_| (reader.u64().unwrap(), reader.i32().unwrap())).collect();
+        assert_eq!(lines, vec![(0, 16), (5, 17), (12, 18)]);
+
+        let breakpoint = client.command(15, 1, data(|writer| {
+            writer.u8(BREAKPOINT);
+            writer.u8(SUSPEND_EVENT_THREAD);
+            writer.i32(1);
+            writer.u8(7);
+            writer.u8(TYPE_CLASS);
+            writer.u64(class_id);
+            writer.u64(add);
+            writer.u64(5);
+        }));
+        let breakpoint = Reader::new(&breakpoint).i32().unwrap();
+        client.command(1, 9, Writer::new());
+
+        let hit = client.event();
+        let (policy, kind, request, thread, mut reader) = single_event(&hit);
+        assert_eq!((policy, kind, request), (SUSPEND_EVENT_THREAD, BREAKPOINT, breakpoint));
+        assert_eq!(location(&mut reader), (class_id, add, 5));
+
+        // The frames of the thread, innermost first.
+        let frames = client.command(11, 6, data(|writer| { writer.u64(thread); writer.i32(0); writer.i32(-1); }));
+        let mut reader = Reader::new(&frames);
+        assert_eq!(reader.i32(), Ok(2));
+        let frame = reader.u64().unwrap();
+        assert_eq!(location(&mut reader), (class_id, add, 5));
+        reader.u64().unwrap();
+        assert_eq!(location(&mut reader).1, main);
+
+        let variables = client.command(6, 2, data(|writer| { writer.u64(class_id); writer.u64(add); }));
+        let mut reader = Reader::new(&variables);
+        assert_eq!(reader.i32(), Ok(2));
+        let names: Vec<(String, i32)> = (0..reader.i32().unwrap()).map(|_| {
+            reader.u64().unwrap();
+            let name = reader.string().unwrap();
+            reader.string().unwrap();
+            reader.i32().unwrap();
+            (name, reader.i32().unwrap())
+        }).collect();
+        assert_eq!(names, vec![(String::from("this"), 0), (String::from("amount"), 1), (String::from("before"), 2)]);
+
+        let values = client.command(16, 1, data(|writer| {
+            writer.u64(thread);
+            writer.u64(frame);
+            writer.i32(2);
+            writer.i32(1);
+            writer.u8(b'I');
+            writer.i32(2);
+            writer.u8(b'I');
+        }));
+        assert_eq!(values, data(|writer| { writer.i32(2); writer.u8(b'I'); writer.i32(1); writer.u8(b'I'); writer.i32(0); }).data);
+
+        // this.label, through the fields of Debuggee.
+        let this = client.command(16, 3, data(|writer| { writer.u64(thread); writer.u64(frame); }));
+        let mut reader = Reader::new(&this);
+        assert_eq!(reader.u8(), Ok(b'L'));
+        let this = reader.u64().unwrap();
+        let fields = client.command(2, 4, id(class_id));
+        let mut reader = Reader::new(&fields);
+        let label = (0..reader.i32().unwrap())
+            .map(|_| (reader.u64().unwrap(), reader.string().unwrap(), reader.string().unwrap(), reader.i32().unwrap()))
+            .find(|field| field.1 == "label")
+            .unwrap().0;
+        let value = client.command(9, 2, data(|writer| { writer.u64(this); writer.i32(1); writer.u64(label); }));
+        let mut reader = Reader::new(&value);
+        assert_eq!(reader.i32(), Ok(1));
+        assert_eq!(reader.u8(), Ok(b's'));
+        let string = reader.u64().unwrap();
+        assert_eq!(Reader::new(&client.command(10, 1, id(string))).string(), Ok(String::from("first")));
+
+        // Steps over line 17, to the start of line 18.
+        client.command(15, 2, data(|writer| { writer.u8(BREAKPOINT); writer.i32(breakpoint); }));
+        let step = client.command(15, 1, data(|writer| {
+            writer.u8(SINGLE_STEP);
+            writer.u8(SUSPEND_EVENT_THREAD);
+            writer.i32(1);
+            writer.u8(10);
+            writer.u64(thread);
+            writer.i32(1);
+            writer.i32(STEP_OVER);
+        }));
+        let step = Reader::new(&step).i32().unwrap();
+        client.command(11, 3, id(thread));
+
+        let stepped = client.event();
+        let (_, kind, request, _, mut reader) = single_event(&stepped);
+        assert_eq!((kind, request), (SINGLE_STEP, step));
+        assert_eq!(location(&mut reader), (class_id, add, 12));
+        assert_eq!(client.request(16, 1, data(|writer| { writer.u64(thread); writer.u64(thread << 16 | 9); writer.i32(0); })), Err(packet::INVALID_FRAMEID));
+
+        client.command(15, 2, data(|writer| { writer.u8(SINGLE_STEP); writer.i32(step); }));
+        client.command(1, 9, Writer::new());
+        vm.join().unwrap();
+        class_table.console.flush();
+        assert_eq!(out.contents(), "10\n");
+
+        class_table.jdwp.vm_death();
+        let death = client.event();
+        assert_eq!(single_event(&death).1, VM_DEATH);
+        client.command(1, commands::DISPOSE, Writer::new());
+    }
+
+}
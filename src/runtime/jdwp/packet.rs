@@ -0,0 +1,195 @@
+use std::io::{self, Read, Write};
+
+// The wire format of the Java Debug Wire Protocol. Numbers are big-endian, strings are UTF-8
+// prefixed with their length, and all ids are 8 bytes long, as IDSizes tells the debugger.
+
+pub const HANDSHAKE: &[u8] = b"JDWP-Handshake";
+
+const HEADER_LENGTH: usize = 11;
+const REPLY_FLAG: u8 = 0x80;
+
+// Error codes, see the Error constants of the JDWP specification.
+pub const INVALID_THREAD: u16 = 10;
+pub const THREAD_NOT_SUSPENDED: u16 = 13;
+pub const INVALID_OBJECT: u16 = 20;
+pub const INVALID_CLASS: u16 = 21;
+pub const INVALID_METHODID: u16 = 23;
+pub const INVALID_LOCATION: u16 = 24;
+pub const INVALID_FIELDID: u16 = 25;
+pub const INVALID_FRAMEID: u16 = 30;
+pub const INVALID_SLOT: u16 = 35;
+pub const INVALID_INDEX: u16 = 503;
+pub const INVALID_LENGTH: u16 = 504;
+pub const NOT_IMPLEMENTED: u16 = 99;
+pub const ABSENT_INFORMATION: u16 = 101;
+pub const INVALID_EVENT_TYPE: u16 = 102;
+pub const ILLEGAL_ARGUMENT: u16 = 103;
+
+#[derive(Debug, PartialEq)]
+pub enum Packet {
+    Command { id: u32, command_set: u8, command: u8, data: Vec<u8> },
+    Reply { id: u32, error: u16, data: Vec<u8> }
+}
+
+impl Packet {
+
+    pub fn read<R: Read>(input: &mut R) -> io::Result<Packet> {
+        let mut header = [0; HEADER_LENGTH];
+        input.read_exact(&mut header)?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        if length < HEADER_LENGTH {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "packet shorter than its header"));
+        }
+        let id = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+        let mut data = vec![0; length - HEADER_LENGTH];
+        input.read_exact(&mut data)?;
+
+        Ok(if header[8] & REPLY_FLAG != 0 {
+            Packet::Reply { id, error: u16::from_be_bytes([header[9], header[10]]), data }
+        } else {
+            Packet::Command { id, command_set: header[9], command: header[10], data }
+        })
+    }
+
+    pub fn write<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        let (id, flags, last, data) = match *self {
+            Packet::Command { id, command_set, command, ref data } => (id, 0, [command_set, command], data),
+            Packet::Reply { id, error, ref data } => (id, REPLY_FLAG, error.to_be_bytes(), data)
+        };
+        let mut bytes = Vec::with_capacity(HEADER_LENGTH + data.len());
+        bytes.extend_from_slice(&((HEADER_LENGTH + data.len()) as u32).to_be_bytes());
+        bytes.extend_from_slice(&id.to_be_bytes());
+        bytes.push(flags);
+        bytes.extend_from_slice(&last);
+        bytes.extend_from_slice(data);
+        output.write_all(&bytes)?;
+        output.flush()
+    }
+
+}
+
+// Reads the data of a packet. Running out of data is an ILLEGAL_ARGUMENT error.
+pub struct Reader<'a> {
+    data: &'a [u8],
+    position: usize
+}
+
+impl<'a> Reader<'a> {
+
+    pub fn new(data: &'a [u8]) -> Reader<'a> {
+        Reader { data, position: 0 }
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], u16> {
+        let bytes = self.data.get(self.position..self.position + count).ok_or(ILLEGAL_ARGUMENT)?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, u16> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn boolean(&mut self) -> Result<bool, u16> {
+        Ok(self.u8()? != 0)
+    }
+
+    pub fn i32(&mut self) -> Result<i32, u16> {
+        let bytes = self.bytes(4)?;
+        Ok(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, u16> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_be_bytes(bytes))
+    }
+
+    pub fn string(&mut self) -> Result<String, u16> {
+        let length = self.i32()?;
+        let bytes = self.bytes(length.max(0) as usize)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| ILLEGAL_ARGUMENT)
+    }
+
+}
+
+// Builds the data of a packet.
+#[derive(Default)]
+pub struct Writer {
+    pub data: Vec<u8>
+}
+
+impl Writer {
+
+    pub fn new() -> Writer {
+        Writer::default()
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    pub fn boolean(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.data.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn i32(&mut self, value: i32) {
+        self.data.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.data.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn string(&mut self, value: &str) {
+        self.i32(value.len() as i32);
+        self.data.extend_from_slice(value.as_bytes());
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn packets() {
+        let command = Packet::Command { id: 7, command_set: 1, command: 1, data: vec![1, 2] };
+        let mut bytes = Vec::new();
+        command.write(&mut bytes).unwrap();
+        assert_eq!(bytes, vec![0, 0, 0, 13, 0, 0, 0, 7, 0, 1, 1, 1, 2]);
+        assert_eq!(Packet::read(&mut &bytes[..]).unwrap(), command);
+
+        let reply = Packet::Reply { id: 7, error: INVALID_THREAD, data: Vec::new() };
+        let mut bytes = Vec::new();
+        reply.write(&mut bytes).unwrap();
+        assert_eq!(bytes, vec![0, 0, 0, 11, 0, 0, 0, 7, 0x80, 0, 10]);
+        assert_eq!(Packet::read(&mut &bytes[..]).unwrap(), reply);
+
+        assert!(Packet::read(&mut &[0, 0, 0, 3, 0, 0, 0, 7, 0, 1, 1][..]).is_err());
+    }
+
+    #[test]
+    fn data() {
+        let mut writer = Writer::new();
+        writer.u8(3);
+        writer.boolean(true);
+        writer.i32(-2);
+        writer.u64(1 << 40);
+        writer.string("main");
+
+        let mut reader = Reader::new(&writer.data);
+        assert_eq!(reader.u8(), Ok(3));
+        assert_eq!(reader.boolean(), Ok(true));
+        assert_eq!(reader.i32(), Ok(-2));
+        assert_eq!(reader.u64(), Ok(1 << 40));
+        assert_eq!(reader.string(), Ok(String::from("main")));
+        assert_eq!(reader.u8(), Err(ILLEGAL_ARGUMENT));
+    }
+
+}
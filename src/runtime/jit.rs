@@ -1,3 +0,0 @@
-fn jit() {
-
-}
\ No newline at end of file
@@ -0,0 +1,287 @@
+use runtime::class::ClassTable;
+use runtime::jit::{Code, Lowered, Operation, CallTarget, BinaryOperation, STATUS_OK, STATUS_BAILOUT, stack_depths};
+use runtime::jit::x86::{self, EAX, ECX, EDX, RSI, RDI};
+use std::collections::HashMap;
+
+// The first tier of the JIT. Each instruction is translated to a fixed sequence of machine code
+// that works on the locals and the operand stack in the native stack frame, as the interpreter
+// does on a StackFrame.
+
+pub fn emit(lowered: &Lowered, class_table: &ClassTable) -> Option<Code> {
+    let depths = stack_depths(lowered)?;
+    let safepoint = class_table.heap.safepoint_flag() as *const _ as u64;
+    let mut assembler = Assembler::new(lowered.max_locals, lowered.max_stack);
+
+    assembler.prologue(lowered.parameters.len());
+    for (position, &(pc, operation)) in lowered.operations.iter().enumerate() {
+        let depth = match depths[position] {
+            Some(depth) => depth,
+            None => continue
+        };
+        assembler.bind(pc);
+
+        // Backward branches poll for a garbage collection, like the interpreter does.
+        if let Some(target) = operation.branch_target() {
+            if target <= pc {
+                assembler.poll(safepoint);
+            }
+        }
+        assembler.operation(operation, depth);
+    }
+    assembler.bailout();
+
+    assembler.finish()
+}
+
+// Where a rel32 operand jumps to.
+enum Label {
+    Instruction(u16),
+    Bailout
+}
+
+// Emits x86-64 machine code. The frame holds the call depth budget at [rbp - 8], and below it
+// the locals followed by the operand stack, 4 bytes per slot.
+//
+// Compiled methods are called with a pointer to their int arguments in rdi and the remaining call
+// depth in rsi. They return a status in rax and the result in edx.
+struct Assembler {
+    code: Vec<u8>,
+    frame_size: i32,
+    max_locals: usize,
+    labels: HashMap<u16, usize>,
+    fixups: Vec<(usize, Label)>,
+    absolute: Vec<usize>,
+    bailout: Option<usize>
+}
+
+impl Assembler {
+
+    fn new(max_locals: usize, max_stack: usize) -> Assembler {
+        let frame_size = (8 + 4 * (max_locals + max_stack)).div_ceil(16) * 16;
+        Assembler {
+            code: Vec::new(),
+            frame_size: frame_size as i32,
+            max_locals,
+            labels: HashMap::new(),
+            fixups: Vec::new(),
+            absolute: Vec::new(),
+            bailout: None
+        }
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.code.extend_from_slice(bytes);
+    }
+
+    fn imm32(&mut self, value: i32) {
+        self.code.extend_from_slice(&value.to_le_bytes());
+    }
+
+    // An address that must be relocated if the code moves.
+    fn address(&mut self, address: u64) {
+        self.absolute.push(self.code.len());
+        self.code.extend_from_slice(&address.to_le_bytes());
+    }
+
+    fn local(&self, index: usize) -> i32 {
+        -self.frame_size + 4 * index as i32
+    }
+
+    fn slot(&self, depth: usize) -> i32 {
+        self.local(self.max_locals + depth)
+    }
+
+    // An instruction whose last operand is [rbp + displacement].
+    fn rbp_operand(&mut self, opcode: &[u8], register: u8, displacement: i32) {
+        self.bytes(opcode);
+        self.bytes(&[0x85 | (register << 3)]);
+        self.imm32(displacement);
+    }
+
+    // mov r32, [rbp + displacement]
+    fn load(&mut self, register: u8, displacement: i32) {
+        self.rbp_operand(&[0x8b], register, displacement);
+    }
+
+    // mov [rbp + displacement], r32
+    fn store(&mut self, register: u8, displacement: i32) {
+        self.rbp_operand(&[0x89], register, displacement);
+    }
+
+    fn jump(&mut self, opcode: &[u8], label: Label) {
+        self.bytes(opcode);
+        self.fixups.push((self.code.len(), label));
+        self.imm32(0);
+    }
+
+    fn bind(&mut self, pc: u16) {
+        self.labels.insert(pc, self.code.len());
+    }
+
+    fn prologue(&mut self, parameters: usize) {
+        // push rbp; mov rbp, rsp; sub rsp, frame_size; mov [rbp - 8], rsi
+        self.bytes(&[0x55, 0x48, 0x89, 0xe5, 0x48, 0x81, 0xec]);
+        let frame_size = self.frame_size;
+        self.imm32(frame_size);
+        self.rbp_operand(&[0x48, 0x89], RSI, -8);
+
+        for parameter in 0..parameters {
+            // mov eax, [rdi + 4 * parameter]
+            self.bytes(&[0x8b, 0x87]);
+            self.imm32(4 * parameter as i32);
+            let local = self.local(parameter);
+            self.store(EAX, local);
+        }
+    }
+
+    fn poll(&mut self, safepoint: u64) {
+        // mov rax, safepoint; cmp byte [rax], 0; jne bailout
+        self.bytes(&[0x48, 0xb8]);
+        self.address(safepoint);
+        self.bytes(&[0x80, 0x38, 0x00]);
+        self.jump(&[0x0f, 0x85], Label::Bailout);
+    }
+
+    fn operation(&mut self, operation: Operation, depth: usize) {
+        let top = if depth > 0 { self.slot(depth - 1) } else { 0 };
+        let second = if depth > 1 { self.slot(depth - 2) } else { 0 };
+
+        match operation {
+            Operation::Constant(value) => {
+                // mov dword [rbp + slot], value
+                let slot = self.slot(depth);
+                self.rbp_operand(&[0xc7], 0, slot);
+                self.imm32(value);
+            },
+            Operation::Load(index) => {
+                let local = self.local(index);
+                let slot = self.slot(depth);
+                self.load(EAX, local);
+                self.store(EAX, slot);
+            },
+            Operation::Store(index) => {
+                let local = self.local(index);
+                self.load(EAX, top);
+                self.store(EAX, local);
+            },
+            Operation::Increment(index, constant) => {
+                // add dword [rbp + local], constant
+                let local = self.local(index);
+                self.rbp_operand(&[0x81], 0, local);
+                self.imm32(constant);
+            },
+            Operation::Binary(operation) => {
+                self.load(EAX, second);
+                self.load(ECX, top);
+                self.binary(operation);
+                self.store(EAX, second);
+            },
+            Operation::Negate => {
+                self.load(EAX, top);
+                x86::negate(&mut self.code);
+                self.store(EAX, top);
+            },
+            Operation::Narrow(narrowing) => {
+                self.load(EAX, top);
+                x86::narrow(&mut self.code, narrowing);
+                self.store(EAX, top);
+            },
+            Operation::If(condition, target) => {
+                // test eax, eax
+                self.load(EAX, top);
+                self.bytes(&[0x85, 0xc0]);
+                self.jump(&[0x0f, x86::condition_code(condition)], Label::Instruction(target));
+            },
+            Operation::IfCompare(condition, target) => {
+                // cmp eax, ecx
+                self.load(EAX, second);
+                self.load(ECX, top);
+                self.bytes(&[0x39, 0xc8]);
+                self.jump(&[0x0f, x86::condition_code(condition)], Label::Instruction(target));
+            },
+            Operation::Goto(target) => self.jump(&[0xe9], Label::Instruction(target)),
+            Operation::Call { target, arguments, returns_void } => {
+                let first_argument = self.slot(depth - arguments);
+
+                // mov rsi, [rbp - 8]; dec rsi; jle bailout; lea rdi, [rbp + first_argument]
+                self.rbp_operand(&[0x48, 0x8b], RSI, -8);
+                self.bytes(&[0x48, 0xff, 0xce]);
+                self.jump(&[0x0f, 0x8e], Label::Bailout);
+                self.rbp_operand(&[0x48, 0x8d], RDI, first_argument);
+
+                match target {
+                    CallTarget::Itself => {
+                        // call rel32 to the start of the method
+                        self.bytes(&[0xe8]);
+                        let offset = -(self.code.len() as i32 + 4);
+                        self.imm32(offset);
+                    },
+                    CallTarget::Address(address) => {
+                        // mov rax, address; call rax
+                        self.bytes(&[0x48, 0xb8]);
+                        self.address(address as u64);
+                        self.bytes(&[0xff, 0xd0]);
+                    }
+                }
+
+                // A bailout anywhere in the callee bails out of the caller as well.
+                // test rax, rax; jne bailout
+                self.bytes(&[0x48, 0x85, 0xc0]);
+                self.jump(&[0x0f, 0x85], Label::Bailout);
+                if !returns_void {
+                    self.store(EDX, first_argument);
+                }
+            },
+            Operation::Pop | Operation::Nop => {},
+            Operation::Dup => {
+                let slot = self.slot(depth);
+                self.load(EAX, top);
+                self.store(EAX, slot);
+            },
+            Operation::Return => self.ret(STATUS_OK),
+            Operation::ReturnInt => {
+                self.load(EDX, top);
+                self.ret(STATUS_OK);
+            },
+            Operation::Null | Operation::GetField { .. } | Operation::ArrayLength | Operation::ArrayLoad(_) | Operation::CallVirtual { .. } => {
+                unreachable!("the baseline tier lowers methods without references")
+            }
+        }
+    }
+
+    // Computes eax = eax op ecx. Division bails out on a zero divisor so that the interpreter
+    // throws ArithmeticException.
+    fn binary(&mut self, operation: BinaryOperation) {
+        if operation == BinaryOperation::Divide || operation == BinaryOperation::Remainder {
+            // test ecx, ecx; je bailout
+            self.bytes(&[0x85, 0xc9]);
+            self.jump(&[0x0f, 0x84], Label::Bailout);
+        }
+        x86::binary(&mut self.code, operation);
+    }
+
+    fn ret(&mut self, status: u64) {
+        // mov eax, status; leave; ret
+        self.bytes(&[0xb8]);
+        self.imm32(status as i32);
+        self.bytes(&[0xc9, 0xc3]);
+    }
+
+    fn bailout(&mut self) {
+        self.bailout = Some(self.code.len());
+        self.ret(STATUS_BAILOUT);
+    }
+
+    fn finish(mut self) -> Option<Code> {
+        for (position, label) in self.fixups.iter() {
+            let target = match *label {
+                Label::Instruction(pc) => *self.labels.get(&pc)?,
+                Label::Bailout => self.bailout?
+            };
+            let offset = target as i32 - (*position as i32 + 4);
+            self.code[*position..*position + 4].copy_from_slice(&offset.to_le_bytes());
+        }
+        Some(Code { bytes: self.code, absolute: self.absolute })
+    }
+
+}
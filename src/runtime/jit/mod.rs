@@ -0,0 +1,1286 @@
+use class::ConstantPoolEntry;
+use code::instruction::Instruction;
+use runtime::Value;
+use runtime::class::{RuntimeClass, ClassTable, ClassState};
+use runtime::class::method::{RuntimeMethod, MethodDescriptor};
+use runtime::interpreter::InvokeResult;
+use runtime::stack::StackFrame;
+use std::collections::HashMap;
+use std::fmt;
+use std::ptr;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+mod baseline;
+mod optimizing;
+mod regalloc;
+mod ssa;
+mod x86;
+
+// A baseline JIT compiler for x86-64. Once a method has been invoked often enough, each of its
+// instructions is translated to a fixed sequence of machine code, with the locals and the operand
+// stack kept in the native stack frame.
+//
+// Only static methods that take and return ints are compiled, and only if they use nothing but
+// int arithmetic, locals, branches and calls to other such methods. Compiled code therefore never
+// touches the heap and has no side effects, so whenever it cannot go on (a division by zero, calls
+// nested too deeply or a pending garbage collection) it bails out and the interpreter runs the
+// whole invocation again from the start. Methods that use anything else are left to the
+// interpreter.
+//
+// Methods that stay hot are compiled again by the optimizing tier, which builds an SSA graph,
+// inlines small static methods, folds constants and branches, and allocates registers. Optimized
+// code deoptimizes rather than bailing out: it hands the interpreter the frame at the pc where it
+// stopped, and the interpreter resumes the method there.
+//
+// The optimizing tier also compiles instance methods and methods that take objects and arrays,
+// which it reads the fields and components of. Virtual calls are inlined when only one method can
+// be selected: behind a check of the class of the receiver if only one loaded class can receive
+// the call, or otherwise behind an assumption that no class is loaded that selects another method.
+// Optimized code whose assumptions no longer hold deoptimizes and is not run again. Bounds checks
+// are removed where a counted loop keeps the index within the array.
+
+// The number of invocations after which a method is compiled, unless -XX:CompileThreshold says
+// otherwise.
+pub const COMPILE_THRESHOLD: u32 = 1000;
+
+// Methods are optimized after this many times as many invocations as it takes to compile them.
+const OPTIMIZE_FACTOR: u32 = 10;
+
+// How deeply compiled methods may call each other before bailing out, which keeps deep recursion
+// from overflowing the native stack.
+const MAX_CALL_DEPTH: i64 = 1024;
+
+// The largest frame compiled code uses, in 4 byte slots for locals and the operand stack.
+const MAX_FRAME_SLOTS: usize = 256;
+
+const STATUS_OK: u64 = 0;
+const STATUS_BAILOUT: u64 = 1;
+const STATUS_DEOPTIMIZED: u64 = 2;
+
+// The kinds of values compiled code works with. Both take 32 bits: a reference is the id of the
+// object or array in the References of the run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    Int,
+    Reference
+}
+
+// Where optimized code writes the interpreter frame when it deoptimizes: the values of the locals
+// followed by those of the operand stack, and whether each of them is a reference. The references
+// of the run are kept here as well, since compiled code passes the buffer along to its callees.
+#[repr(C)]
+struct DeoptBuffer {
+    pc: u32,
+    locals: u32,
+    stack: u32,
+    values: [i32; MAX_FRAME_SLOTS],
+    kinds: [u8; MAX_FRAME_SLOTS],
+    references: References
+}
+
+impl DeoptBuffer {
+
+    fn new() -> DeoptBuffer {
+        DeoptBuffer { pc: 0, locals: 0, stack: 0, values: [0; MAX_FRAME_SLOTS], kinds: [0; MAX_FRAME_SLOTS], references: References::default() }
+    }
+
+}
+
+// The objects and arrays that a run of compiled code refers to. Each gets an id, starting from 1
+// since 0 is null, and keeps the same id for the whole run so that ids compare like references.
+// The collector does not visit these references, and need not: compiled code never stops at a
+// safepoint, but deoptimizes instead, so no collection runs until the interpreter holds them.
+#[derive(Default)]
+struct References {
+    values: Vec<Value>,
+    ids: HashMap<usize, i32>
+}
+
+impl References {
+
+    // The 32 bits compiled code holds for a value: an int as it is, a reference as its id.
+    fn encode(&mut self, value: Value) -> i32 {
+        let address = match value {
+            Value::Integer(value) => return value,
+            Value::Short(value) => return value as i32,
+            Value::Byte(value) => return value as i32,
+            Value::Character(value) => return value as i32,
+            Value::ObjectRef(ref object) => Arc::as_ptr(object) as usize,
+            Value::ArrayRef(ref array) => Arc::as_ptr(array) as usize,
+            _ => return 0
+        };
+        if let Some(&id) = self.ids.get(&address) {
+            return id;
+        }
+        self.values.push(value);
+        let id = self.values.len() as i32;
+        self.ids.insert(address, id);
+        id
+    }
+
+    fn decode(&self, value: i32, kind: Kind) -> Value {
+        match kind {
+            Kind::Int => Value::Integer(value),
+            Kind::Reference => self.values.get((value as usize).wrapping_sub(1)).cloned().unwrap_or(Value::Null)
+        }
+    }
+
+}
+
+// How a compiled method ended.
+pub enum Execution {
+    Completed(InvokeResult),
+    // The interpreter must resume the method in this frame, at this pc.
+    Deoptimized(StackFrame, u16),
+    // The interpreter must run the whole invocation.
+    Interpret
+}
+
+// One method is compiled at a time, so that methods calling each other are compiled in order.
+static COMPILER: Mutex<()> = Mutex::new(());
+
+// Machine code, with the positions of the absolute addresses in it that must be relocated if
+// the code is moved.
+struct Code {
+    bytes: Vec<u8>,
+    absolute: Vec<usize>
+}
+
+// The machine code of a compiled method. Methods compiled ahead of time share their memory.
+pub struct CompiledMethod {
+    memory: Arc<native::ExecutableMemory>,
+    offset: usize,
+    length: usize,
+    absolute: Vec<usize>,
+    parameters: Vec<Kind>,
+    returns_void: bool,
+    // Set once a class is loaded that breaks an assumption the code makes, see Dependencies.
+    invalidated: Vec<Arc<AtomicBool>>
+}
+
+impl CompiledMethod {
+
+    // Runs the compiled code with the given arguments, which must be ints and references as the
+    // method takes them.
+    pub fn run(&self, arguments: &[Value]) -> Execution {
+        if arguments.len() != self.parameters.len() {
+            return Execution::Interpret;
+        }
+
+        let mut buffer = DeoptBuffer::new();
+        let arguments = arguments.iter().zip(self.parameters.iter())
+            .map(|(argument, &kind)| match (argument, kind) {
+                (&Value::Integer(value), Kind::Int) => Some(value),
+                (argument, Kind::Reference) if argument.is_reference() => Some(buffer.references.encode(argument.clone())),
+                _ => None
+            })
+            .collect::<Option<Vec<i32>>>();
+        let arguments = match arguments {
+            Some(arguments) => arguments,
+            None => return Execution::Interpret
+        };
+
+        let (status, value) = self.memory.call(self.entry(), arguments.as_ptr(), MAX_CALL_DEPTH, &mut buffer);
+        match status {
+            STATUS_OK if self.returns_void => Execution::Completed(InvokeResult::Void),
+            STATUS_OK => Execution::Completed(InvokeResult::Value(Value::Integer(value as u32 as i32))),
+            STATUS_DEOPTIMIZED => {
+                let length = (buffer.locals + buffer.stack) as usize;
+                let mut values = buffer.values[..length].iter().zip(buffer.kinds[..length].iter())
+                    .map(|(&value, &kind)| buffer.references.decode(value, if kind == 0 { Kind::Int } else { Kind::Reference }))
+                    .collect::<Vec<Value>>();
+                let stack = values.split_off(buffer.locals as usize);
+                Execution::Deoptimized(StackFrame { locals: values, stack }, buffer.pc as u16)
+            },
+            _ => Execution::Interpret
+        }
+    }
+
+    fn new(code: Code, parameters: Vec<Kind>, returns_void: bool, invalidated: Vec<Arc<AtomicBool>>) -> Option<CompiledMethod> {
+        let memory = native::ExecutableMemory::new(&code.bytes)?;
+        Some(CompiledMethod { memory: Arc::new(memory), offset: 0, length: code.bytes.len(), absolute: code.absolute, parameters, returns_void, invalidated })
+    }
+
+    // Whether the assumptions the code makes about the class hierarchy still hold.
+    pub fn is_valid(&self) -> bool {
+        self.invalidated.iter().all(|invalidated| !invalidated.load(Ordering::Acquire))
+    }
+
+    pub fn entry(&self) -> usize {
+        self.memory.address() + self.offset
+    }
+
+    pub fn code(&self) -> &[u8] {
+        &self.memory.bytes()[self.offset..self.offset + self.length]
+    }
+
+    // The positions in the code of the absolute addresses it uses: the entries of the methods it
+    // calls and the safepoint flag of the heap.
+    pub fn absolute_addresses(&self) -> &[usize] {
+        &self.absolute
+    }
+
+}
+
+impl fmt::Debug for CompiledMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CompiledMethod({} bytes at {:#x})", self.length, self.entry())
+    }
+}
+
+// Called by the interpreter before it runs a method. Counts the invocation, compiles the method
+// once it is hot and optimizes it once it stays hot, and runs the best code there is.
+pub fn invoke(class: &Arc<RuntimeClass>,
+              method: &RuntimeMethod,
+              arguments: &[Value],
+              class_table: &ClassTable) -> Execution {
+    let threshold = class_table.compile_threshold();
+    if threshold == 0 {
+        return Execution::Interpret;
+    }
+
+    // Code compiled ahead of time runs from the first invocation. Optimized code that is no
+    // longer valid leaves the method to the baseline tier.
+    let compiled = match method.optimized.get() {
+        Some(Some(optimized)) if optimized.is_valid() => Some(optimized),
+        Some(_) => compile(class, method, class_table),
+        None => {
+            let invocations = u64::from(method.invocations.fetch_add(1, Ordering::Relaxed) + 1);
+            // While profiling, the branches the interpreter ran count as invocations too, so
+            // methods with hot loops are compiled and optimized sooner.
+            let heat = if class_table.profile.is_enabled() {
+                class_table.profile.heat(method).max(invocations)
+            } else {
+                invocations
+            };
+            if heat >= u64::from(threshold) * u64::from(OPTIMIZE_FACTOR) {
+                optimize(class, method, class_table).or_else(|| compile(class, method, class_table))
+            } else if heat >= u64::from(threshold) || method.compiled.get().is_some() {
+                compile(class, method, class_table)
+            } else {
+                None
+            }
+        }
+    };
+
+    match compiled {
+        Some(compiled) => compiled.run(arguments),
+        None => Execution::Interpret
+    }
+}
+
+// Compiles the method unless that was tried before. Returns None if it cannot be compiled.
+pub fn compile<'a>(class: &Arc<RuntimeClass>, method: &'a RuntimeMethod, class_table: &ClassTable) -> Option<&'a CompiledMethod> {
+    if let Some(compiled) = method.compiled.get() {
+        return compiled.as_ref();
+    }
+
+    let _compiling = COMPILER.lock().unwrap_or_else(|e| e.into_inner());
+    compile_locked(class, method, class_table, &mut Vec::new())
+}
+
+// Compiles the method with the optimizing tier unless that was tried before. Returns None if it
+// cannot be optimized.
+pub fn optimize<'a>(class: &Arc<RuntimeClass>, method: &'a RuntimeMethod, class_table: &ClassTable) -> Option<&'a CompiledMethod> {
+    if let Some(optimized) = method.optimized.get() {
+        return optimized.as_ref();
+    }
+
+    let _compiling = COMPILER.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(optimized) = method.optimized.get() {
+        return optimized.as_ref();
+    }
+
+    let optimized = lower(class, method, class_table, &mut vec![method as *const RuntimeMethod], true).and_then(|lowered| {
+        let mut function = ssa::build(class, method, &lowered, class_table)?;
+        ssa::optimize(&mut function);
+        let code = optimizing::emit(&function, class_table)?;
+        CompiledMethod::new(code, lowered.parameters, lowered.returns_void, function.invalidated)
+    });
+
+    let _ = method.optimized.set(optimized);
+    method.optimized.get().and_then(Option::as_ref)
+}
+
+// Where an absolute address in code compiled ahead of time points.
+pub enum RelocationTarget {
+    // An offset into the code being installed.
+    Text(usize),
+    Address(u64)
+}
+
+// Installs code compiled ahead of time as the compiled code of methods. text holds the code of
+// each method at the given offset, and relocations the absolute addresses to patch into it.
+// Methods that were already compiled keep their code. Returns false if the code could not be
+// installed.
+pub fn install(text: &[u8], relocations: &[(usize, RelocationTarget)], methods: &[(&RuntimeMethod, usize, usize)]) -> bool {
+    let memory = native::ExecutableMemory::link(text, |base, code| {
+        for &(position, ref target) in relocations.iter() {
+            let address = match *target {
+                RelocationTarget::Text(offset) => base as u64 + offset as u64,
+                RelocationTarget::Address(address) => address
+            };
+            code[position..position + 8].copy_from_slice(&address.to_le_bytes());
+        }
+    });
+    let memory = match memory {
+        Some(memory) => Arc::new(memory),
+        None => return false
+    };
+
+    for &(method, offset, length) in methods.iter() {
+        if let (true, Some((parameters, returns_void))) = (method.is_static(), int_signature(&method.descriptor)) {
+            let absolute = relocations.iter()
+                .filter(|&&(position, _)| position >= offset && position < offset + length)
+                .map(|&(position, _)| position - offset)
+                .collect();
+            let parameters = vec![Kind::Int; parameters];
+            let _ = method.compiled.set(Some(CompiledMethod { memory: memory.clone(), offset, length, absolute, parameters, returns_void, invalidated: Vec::new() }));
+        }
+    }
+    true
+}
+
+// in_progress holds the methods whose compilation is waiting for this one. A call back to one of
+// them cannot be compiled, except for a method calling itself.
+fn compile_locked<'a>(class: &Arc<RuntimeClass>,
+                      method: &'a RuntimeMethod,
+                      class_table: &ClassTable,
+                      in_progress: &mut Vec<*const RuntimeMethod>) -> Option<&'a CompiledMethod> {
+    if let Some(compiled) = method.compiled.get() {
+        return compiled.as_ref();
+    }
+
+    in_progress.push(method as *const RuntimeMethod);
+    let compiled = lower(class, method, class_table, in_progress, false).and_then(|lowered| {
+        CompiledMethod::new(baseline::emit(&lowered, class_table)?, lowered.parameters, lowered.returns_void, Vec::new())
+    });
+    in_progress.pop();
+
+    let _ = method.compiled.set(compiled);
+    method.compiled.get().and_then(Option::as_ref)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Condition {
+    Equal,
+    NotEqual,
+    Less,
+    GreaterOrEqual,
+    Greater,
+    LessOrEqual
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BinaryOperation {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    And,
+    Or,
+    Xor,
+    ShiftLeft,
+    ShiftRight,
+    UnsignedShiftRight
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Narrowing {
+    Byte,
+    Character,
+    Short
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CallTarget {
+    // The method being compiled.
+    Itself,
+    Address(usize)
+}
+
+// The instructions the JIT supports, with their operands resolved. Branch targets are pcs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operation {
+    Constant(i32),
+    Load(usize),
+    Store(usize),
+    Increment(usize, i32),
+    Binary(BinaryOperation),
+    Negate,
+    Narrow(Narrowing),
+    If(Condition, u16),
+    IfCompare(Condition, u16),
+    Goto(u16),
+    Call { target: CallTarget, arguments: usize, returns_void: bool },
+    Pop,
+    Dup,
+    Return,
+    ReturnInt,
+    Nop,
+    // Only the optimizing tier supports references. Objects and arrays are only read.
+    Null,
+    GetField { position: usize, kind: Kind },
+    ArrayLength,
+    ArrayLoad(Kind),
+    // A call that is only compiled if it is inlined. The receiver is the first argument.
+    CallVirtual { arguments: usize, returns_void: bool }
+}
+
+impl Operation {
+
+    // The number of operand stack slots the operation pops and pushes.
+    fn stack_effect(&self) -> (usize, usize) {
+        match *self {
+            Operation::Constant(_) | Operation::Load(_) | Operation::Null => (0, 1),
+            Operation::Store(_) | Operation::If(..) | Operation::Pop | Operation::ReturnInt => (1, 0),
+            Operation::Increment(..) | Operation::Goto(_) | Operation::Return | Operation::Nop => (0, 0),
+            Operation::Binary(_) => (2, 1),
+            Operation::Negate | Operation::Narrow(_) | Operation::GetField { .. } | Operation::ArrayLength => (1, 1),
+            Operation::IfCompare(..) => (2, 0),
+            Operation::ArrayLoad(_) => (2, 1),
+            Operation::Call { arguments, returns_void, .. } | Operation::CallVirtual { arguments, returns_void } => (arguments, if returns_void { 0 } else { 1 }),
+            Operation::Dup => (1, 2)
+        }
+    }
+
+    fn branch_target(&self) -> Option<u16> {
+        match *self {
+            Operation::If(_, target) | Operation::IfCompare(_, target) | Operation::Goto(target) => Some(target),
+            _ => None
+        }
+    }
+
+    fn falls_through(&self) -> bool {
+        !matches!(*self, Operation::Goto(_) | Operation::Return | Operation::ReturnInt)
+    }
+
+}
+
+struct Lowered {
+    // The pc and operation of each instruction.
+    operations: Vec<(u16, Operation)>,
+    parameters: Vec<Kind>,
+    returns_void: bool,
+    max_locals: usize,
+    max_stack: usize
+}
+
+// Parses a descriptor that only has int parameters and returns an int or void.
+fn int_signature(descriptor: &str) -> Option<(usize, bool)> {
+    let descriptor = MethodDescriptor::parse(descriptor)?;
+    if descriptor.parameter_types().iter().any(|parameter| parameter != "I") {
+        return None;
+    }
+
+    match descriptor.return_type().as_str() {
+        "I" => Some((descriptor.parameters_length(), false)),
+        "V" => Some((descriptor.parameters_length(), true)),
+        _ => None
+    }
+}
+
+// The kinds of the parameters of a method, `this` first for instance methods, and whether it
+// returns void rather than an int. Parameters must be ints, or references if they are allowed.
+fn signature(method: &RuntimeMethod, references: bool) -> Option<(Vec<Kind>, bool)> {
+    let descriptor = MethodDescriptor::parse(&method.descriptor)?;
+    let mut parameters = Vec::new();
+    if !method.is_static() {
+        parameters.push(value_kind("L", references)?);
+    }
+    for parameter in descriptor.parameter_types() {
+        parameters.push(value_kind(&parameter, references)?);
+    }
+
+    match descriptor.return_type().as_str() {
+        "I" => Some((parameters, false)),
+        "V" => Some((parameters, true)),
+        _ => None
+    }
+}
+
+// The kind of the values of a field or parameter with the given descriptor. The int types smaller
+// than int are held as ints.
+fn value_kind(descriptor: &str, references: bool) -> Option<Kind> {
+    match descriptor.chars().next()? {
+        'I' | 'Z' | 'B' | 'C' | 'S' => Some(Kind::Int),
+        'L' | '[' if references => Some(Kind::Reference),
+        _ => None
+    }
+}
+
+// Lowers the instructions of a method. Instance methods and references are only supported if
+// they are allowed.
+fn lower(class: &Arc<RuntimeClass>,
+         method: &RuntimeMethod,
+         class_table: &ClassTable,
+         in_progress: &mut Vec<*const RuntimeMethod>,
+         references: bool) -> Option<Lowered> {
+    if method.native.is_some() {
+        return None;
+    }
+    let code = method.code.as_ref()?;
+    let (parameters, returns_void) = signature(method, references)?;
+    let max_locals = code.max_locals as usize;
+    let max_stack = code.max_stack as usize;
+    if parameters.len() > max_locals || max_locals + max_stack > MAX_FRAME_SLOTS {
+        return None;
+    }
+
+    let mut operations = Vec::new();
+    for tagged_instruction in code.instructions.iter() {
+        let pc = tagged_instruction.index;
+        let target = |offset: i16| Some((pc as i32 + offset as i32) as u16);
+        let local = |index: usize| if index < max_locals { Some(index) } else { None };
+
+        let operation = match tagged_instruction.instruction {
+            Instruction::IconstM1 => Operation::Constant(-1),
+            Instruction::Iconst0 => Operation::Constant(0),
+            Instruction::Iconst1 => Operation::Constant(1),
+            Instruction::Iconst2 => Operation::Constant(2),
+            Instruction::Iconst3 => Operation::Constant(3),
+            Instruction::Iconst4 => Operation::Constant(4),
+            Instruction::Iconst5 => Operation::Constant(5),
+            Instruction::Bipush { byte } => Operation::Constant(byte as i32),
+            Instruction::Sipush(value) => Operation::Constant(value),
+            Instruction::Ldc { index } => Operation::Constant(int_constant(class, index as u16)?),
+            Instruction::LdcW { index } => Operation::Constant(int_constant(class, index)?),
+            Instruction::Iload { index } => Operation::Load(local(index as usize)?),
+            Instruction::Iload0 => Operation::Load(local(0)?),
+            Instruction::Iload1 => Operation::Load(local(1)?),
+            Instruction::Iload2 => Operation::Load(local(2)?),
+            Instruction::Iload3 => Operation::Load(local(3)?),
+            Instruction::Istore(index) => Operation::Store(local(index as usize)?),
+            Instruction::Istore0 => Operation::Store(local(0)?),
+            Instruction::Istore1 => Operation::Store(local(1)?),
+            Instruction::Istore2 => Operation::Store(local(2)?),
+            Instruction::Istore3 => Operation::Store(local(3)?),
+            Instruction::Iinc { index, constant } => Operation::Increment(local(index as usize)?, constant as i32),
+            Instruction::Iadd => Operation::Binary(BinaryOperation::Add),
+            Instruction::Isub => Operation::Binary(BinaryOperation::Subtract),
+            Instruction::Imul => Operation::Binary(BinaryOperation::Multiply),
+            Instruction::Idiv => Operation::Binary(BinaryOperation::Divide),
+            Instruction::Irem => Operation::Binary(BinaryOperation::Remainder),
+            Instruction::Iand => Operation::Binary(BinaryOperation::And),
+            Instruction::Ior => Operation::Binary(BinaryOperation::Or),
+            Instruction::Ixor => Operation::Binary(BinaryOperation::Xor),
+            Instruction::Ishl => Operation::Binary(BinaryOperation::ShiftLeft),
+            Instruction::Ishr => Operation::Binary(BinaryOperation::ShiftRight),
+            Instruction::Iushr => Operation::Binary(BinaryOperation::UnsignedShiftRight),
+            Instruction::Ineg => Operation::Negate,
+            Instruction::I2b => Operation::Narrow(Narrowing::Byte),
+            Instruction::I2c => Operation::Narrow(Narrowing::Character),
+            Instruction::I2s => Operation::Narrow(Narrowing::Short),
+            Instruction::Ifeq { branch_offset } => Operation::If(Condition::Equal, target(branch_offset)?),
+            Instruction::Ifne { branch_offset } => Operation::If(Condition::NotEqual, target(branch_offset)?),
+            Instruction::Iflt { branch_offset } => Operation::If(Condition::Less, target(branch_offset)?),
+            Instruction::Ifge { branch_offset } => Operation::If(Condition::GreaterOrEqual, target(branch_offset)?),
+            Instruction::Ifgt { branch_offset } => Operation::If(Condition::Greater, target(branch_offset)?),
+            Instruction::Ifle { branch_offset } => Operation::If(Condition::LessOrEqual, target(branch_offset)?),
+            Instruction::IfIcmpeq { branch_offset } => Operation::IfCompare(Condition::Equal, target(branch_offset)?),
+            Instruction::IfIcmpne { branch_offset } => Operation::IfCompare(Condition::NotEqual, target(branch_offset)?),
+            Instruction::IfIcmplt { branch_offset } => Operation::IfCompare(Condition::Less, target(branch_offset)?),
+            Instruction::IfIcmpge { branch_offset } => Operation::IfCompare(Condition::GreaterOrEqual, target(branch_offset)?),
+            Instruction::IfIcmpgt { branch_offset } => Operation::IfCompare(Condition::Greater, target(branch_offset)?),
+            Instruction::IfIcmple { branch_offset } => Operation::IfCompare(Condition::LessOrEqual, target(branch_offset)?),
+            Instruction::Goto { branch_offset } => Operation::Goto(target(branch_offset)?),
+            Instruction::Invokestatic { index } => call(class, method, index, class_table, in_progress)?,
+            Instruction::Pop => Operation::Pop,
+            Instruction::Dup => Operation::Dup,
+            Instruction::Ireturn if !returns_void => Operation::ReturnInt,
+            Instruction::Return if returns_void => Operation::Return,
+            Instruction::Nop => Operation::Nop,
+            _ if !references => return None,
+            Instruction::AconstNull => Operation::Null,
+            Instruction::Aload { index } => Operation::Load(local(index as usize)?),
+            Instruction::Aload0 => Operation::Load(local(0)?),
+            Instruction::Aload1 => Operation::Load(local(1)?),
+            Instruction::Aload2 => Operation::Load(local(2)?),
+            Instruction::Aload3 => Operation::Load(local(3)?),
+            Instruction::Astore { index } => Operation::Store(local(index as usize)?),
+            Instruction::Astore0 => Operation::Store(local(0)?),
+            Instruction::Astore1 => Operation::Store(local(1)?),
+            Instruction::Astore2 => Operation::Store(local(2)?),
+            Instruction::Astore3 => Operation::Store(local(3)?),
+            // Null is id 0, and the same object always has the same id.
+            Instruction::Ifnull { branch_offset } => Operation::If(Condition::Equal, target(branch_offset)?),
+            Instruction::Ifnonnull { branch_offset } => Operation::If(Condition::NotEqual, target(branch_offset)?),
+            Instruction::IfAcmpeq { branch_offset } => Operation::IfCompare(Condition::Equal, target(branch_offset)?),
+            Instruction::IfAcmpne { branch_offset } => Operation::IfCompare(Condition::NotEqual, target(branch_offset)?),
+            Instruction::Getfield { index } => field(class, index, class_table)?,
+            Instruction::Arraylength => Operation::ArrayLength,
+            Instruction::Iaload | Instruction::Baload | Instruction::Caload | Instruction::Saload => Operation::ArrayLoad(Kind::Int),
+            Instruction::Aaload => Operation::ArrayLoad(Kind::Reference),
+            Instruction::Invokevirtual { index } => {
+                let method_ref = class.constant_pool.get_method_ref(index).ok()?;
+                let descriptor = MethodDescriptor::parse(&method_ref.name_and_type.descriptor)?;
+                let returns_void = match descriptor.return_type().as_str() {
+                    "I" => false,
+                    "V" => true,
+                    _ => return None
+                };
+                if descriptor.parameter_types().iter().any(|parameter| value_kind(parameter, true).is_none()) {
+                    return None;
+                }
+                Operation::CallVirtual { arguments: descriptor.parameters_length() + 1, returns_void }
+            },
+            _ => return None
+        };
+        operations.push((pc, operation));
+    }
+
+    Some(Lowered { operations, parameters, returns_void, max_locals, max_stack })
+}
+
+fn int_constant(class: &Arc<RuntimeClass>, index: u16) -> Option<i32> {
+    match class.constant_pool.get(index) {
+        Some(ConstantPoolEntry::Integer { .. }) => class.constant_pool.get_integer(index).ok(),
+        _ => None
+    }
+}
+
+// Resolves the field getfield reads, if its class is loaded. Only ints and references are read.
+fn field(class: &Arc<RuntimeClass>, index: u16, class_table: &ClassTable) -> Option<Operation> {
+    let field_ref = class.constant_pool.get_field_ref(index).ok()?;
+    let position = class_table.get_class(&field_ref.class_name)?.instance_field_position(&field_ref.name_and_type.name)?;
+    let kind = value_kind(&field_ref.name_and_type.descriptor, true)?;
+    Some(Operation::GetField { position, kind })
+}
+
+// Resolves the target of invokestatic, which must be compiled too. Only classes that are already
+// initialized are called, so that compiled code never has to initialize one. While profiling,
+// calls are left to the interpreter, which counts every invocation.
+fn call(class: &Arc<RuntimeClass>,
+        method: &RuntimeMethod,
+        index: u16,
+        class_table: &ClassTable,
+        in_progress: &mut Vec<*const RuntimeMethod>) -> Option<Operation> {
+    if class_table.profile.is_enabled() {
+        return None;
+    }
+    let (declaring_class, name, descriptor) = resolve_static(class, index, class_table)?;
+    let (arguments, returns_void) = int_signature(&descriptor)?;
+    let invoked = declaring_class.get_declared_method(&name, &descriptor)?;
+
+    let target = if ptr::eq(invoked, method) {
+        CallTarget::Itself
+    } else {
+        if !Arc::ptr_eq(&declaring_class, class) && declaring_class.state() != ClassState::Initialized {
+            return None;
+        }
+        if in_progress.contains(&(invoked as *const RuntimeMethod)) {
+            return None;
+        }
+        CallTarget::Address(compile_locked(&declaring_class, invoked, class_table, in_progress)?.entry())
+    };
+
+    Some(Operation::Call { target, arguments, returns_void })
+}
+
+// Resolves the method that invokestatic calls, if its class is loaded. Returns the class that
+// declares it, with its name and descriptor.
+fn resolve_static(class: &Arc<RuntimeClass>, index: u16, class_table: &ClassTable) -> Option<(Arc<RuntimeClass>, String, String)> {
+    let method_ref = class.constant_pool.get_method_ref(index).ok()?;
+    let name = method_ref.name_and_type.name.as_str();
+    let descriptor = method_ref.name_and_type.descriptor.as_str();
+
+    let invoked_class = class_table.get_class(&method_ref.class_name)?;
+    let declaring_class = RuntimeClass::resolve_method(&invoked_class, name, descriptor)?;
+    Some((declaring_class, name.to_owned(), descriptor.to_owned()))
+}
+
+// What must hold for an inlined call to select the inlined method.
+enum Guard {
+    // Invokestatic always calls the same method.
+    None,
+    // The receiver is an instance of this class.
+    Class(Arc<RuntimeClass>),
+    // The flag is not set, see Dependencies.
+    Assumption(Arc<AtomicBool>)
+}
+
+// The assumptions that optimized code makes about the class hierarchy. A virtual call that every
+// loaded class selects the same method for is inlined on the assumption that no class is loaded
+// that selects another one. Loading such a class sets the flag of the assumption, which the
+// optimized code checks to deoptimize.
+pub struct Dependencies {
+    assumptions: Mutex<Vec<Assumption>>
+}
+
+struct Assumption {
+    class_name: String,
+    name: String,
+    descriptor: String,
+    // The address of the class declaring the method that is selected.
+    declaring_class: usize,
+    invalidated: Arc<AtomicBool>
+}
+
+impl Dependencies {
+
+    pub fn new() -> Dependencies {
+        Dependencies { assumptions: Mutex::new(Vec::new()) }
+    }
+
+    // Selects the method a virtual call on the named class runs, if every loaded class that can
+    // receive the call selects the same one. Returns the class declaring it, and the guard under
+    // which it is the one: the class of the receiver if only one class can receive the call, or
+    // otherwise a new assumption.
+    fn select(&self, class_table: &ClassTable, class_name: &str, name: &str, descriptor: &str) -> Option<(Arc<RuntimeClass>, Guard)> {
+        // Classes loaded while the lock is held are checked against the assumption once it is
+        // made.
+        let mut assumptions = self.assumptions.lock().unwrap();
+        let receivers: Vec<Arc<RuntimeClass>> = class_table.loaded_classes().into_iter()
+            .filter(|receiver| !receiver.is_interface() && !receiver.is_abstract() && receiver.is_subclass_of(class_name))
+            .collect();
+
+        let mut selected: Option<Arc<RuntimeClass>> = None;
+        for receiver in receivers.iter() {
+            let declaring_class = RuntimeClass::resolve_method(receiver, name, descriptor)?;
+            match selected {
+                Some(ref selected) if !Arc::ptr_eq(selected, &declaring_class) => return None,
+                _ => selected = Some(declaring_class)
+            }
+        }
+        let declaring_class = selected?;
+
+        if receivers.len() == 1 {
+            return Some((declaring_class, Guard::Class(receivers[0].clone())));
+        }
+        let invalidated = Arc::new(AtomicBool::new(false));
+        assumptions.push(Assumption {
+            class_name: class_name.to_owned(),
+            name: name.to_owned(),
+            descriptor: descriptor.to_owned(),
+            declaring_class: Arc::as_ptr(&declaring_class) as usize,
+            invalidated: invalidated.clone()
+        });
+        Some((declaring_class, Guard::Assumption(invalidated)))
+    }
+
+    // Called once a class is loaded. Invalidates the assumptions it breaks.
+    pub fn class_loaded(&self, class: &Arc<RuntimeClass>) {
+        if class.is_interface() || class.is_abstract() {
+            return;
+        }
+        let mut assumptions = self.assumptions.lock().unwrap();
+        assumptions.retain(|assumption| {
+            if !class.is_subclass_of(&assumption.class_name) {
+                return true;
+            }
+            let declaring_class = RuntimeClass::resolve_method(class, &assumption.name, &assumption.descriptor);
+            if declaring_class.is_some_and(|declaring_class| Arc::as_ptr(&declaring_class) as usize == assumption.declaring_class) {
+                return true;
+            }
+            assumption.invalidated.store(true, Ordering::Release);
+            false
+        });
+    }
+
+}
+
+impl Default for Dependencies {
+    fn default() -> Dependencies {
+        Dependencies::new()
+    }
+}
+
+// Computes the operand stack depth before each reachable instruction. Returns None if the depths
+// do not agree where control flow merges, or exceed max_stack.
+fn stack_depths(lowered: &Lowered) -> Option<Vec<Option<usize>>> {
+    let positions: HashMap<u16, usize> = lowered.operations.iter()
+        .enumerate()
+        .map(|(position, &(pc, _))| (pc, position))
+        .collect();
+
+    let mut depths = vec![None; lowered.operations.len()];
+    let mut pending: Vec<(usize, usize)> = vec![(0, 0)];
+    while let Some((position, depth)) = pending.pop() {
+        match depths[position] {
+            Some(known) if known == depth => continue,
+            Some(_) => return None,
+            None => depths[position] = Some(depth)
+        }
+
+        let operation = lowered.operations[position].1;
+        let (pops, pushes) = operation.stack_effect();
+        let after = depth.checked_sub(pops)? + pushes;
+        if after > lowered.max_stack {
+            return None;
+        }
+
+        if let Some(target) = operation.branch_target() {
+            pending.push((*positions.get(&target)?, after));
+        }
+        if operation.falls_through() {
+            if position + 1 >= lowered.operations.len() {
+                return None;
+            }
+            pending.push((position + 1, after));
+        }
+    }
+
+    Some(depths)
+}
+
+#[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+mod native {
+
+    use runtime::jit::DeoptBuffer;
+    use std::os::raw::{c_int, c_long, c_void};
+    use std::ptr;
+
+    const PROT_READ: c_int = 1;
+    const PROT_WRITE: c_int = 2;
+    const PROT_EXEC: c_int = 4;
+    const MAP_PRIVATE: c_int = 2;
+    const MAP_ANONYMOUS: c_int = 0x20;
+
+    extern "C" {
+        fn mmap(address: *mut c_void, length: usize, protection: c_int, flags: c_int, fd: c_int, offset: c_long) -> *mut c_void;
+        fn mprotect(address: *mut c_void, length: usize, protection: c_int) -> c_int;
+        fn munmap(address: *mut c_void, length: usize) -> c_int;
+    }
+
+    #[repr(C)]
+    struct Result {
+        status: u64,
+        value: u64
+    }
+
+    type Entry = extern "sysv64" fn(*const i32, i64, *mut DeoptBuffer) -> Result;
+
+    // Machine code in memory that is executable but not writable.
+    pub struct ExecutableMemory {
+        pointer: *mut c_void,
+        length: usize
+    }
+
+    // The memory is never written after it is made executable.
+    unsafe impl Send for ExecutableMemory {}
+    unsafe impl Sync for ExecutableMemory {}
+
+    impl ExecutableMemory {
+
+        pub fn new(code: &[u8]) -> Option<ExecutableMemory> {
+            ExecutableMemory::link(code, |_, _| {})
+        }
+
+        // Copies the code to executable memory, letting relocate patch it with the address it
+        // ends up at before it is made executable.
+        pub fn link<F: FnOnce(usize, &mut [u8])>(code: &[u8], relocate: F) -> Option<ExecutableMemory> {
+            let length = code.len().max(1);
+            unsafe {
+                let pointer = mmap(ptr::null_mut(), length, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+                if pointer as isize == -1 {
+                    return None;
+                }
+                let memory = ExecutableMemory { pointer, length };
+                let bytes = ::std::slice::from_raw_parts_mut(pointer as *mut u8, code.len());
+                bytes.copy_from_slice(code);
+                relocate(pointer as usize, bytes);
+                if mprotect(pointer, length, PROT_READ | PROT_EXEC) != 0 {
+                    return None;
+                }
+                Some(memory)
+            }
+        }
+
+        pub fn address(&self) -> usize {
+            self.pointer as usize
+        }
+
+        pub fn bytes(&self) -> &[u8] {
+            unsafe { ::std::slice::from_raw_parts(self.pointer as *const u8, self.length) }
+        }
+
+        // Calls the method whose code starts at the entry, an address in this memory. Methods
+        // compiled ahead of time share the memory, so it is not always the first.
+        pub fn call(&self, entry: usize, arguments: *const i32, depth: i64, buffer: &mut DeoptBuffer) -> (u64, u64) {
+            assert!(entry >= self.address() && entry < self.address() + self.length);
+            let entry: Entry = unsafe { ::std::mem::transmute(entry) };
+            let result = entry(arguments, depth, buffer);
+            (result.status, result.value)
+        }
+
+    }
+
+    impl Drop for ExecutableMemory {
+        fn drop(&mut self) {
+            unsafe {
+                munmap(self.pointer, self.length);
+            }
+        }
+    }
+
+}
+
+// Other platforms have no JIT, and every method is interpreted.
+#[cfg(not(all(target_arch = "x86_64", target_os = "linux")))]
+mod native {
+
+    use runtime::jit::DeoptBuffer;
+
+    pub struct ExecutableMemory;
+
+    impl ExecutableMemory {
+
+        pub fn new(_code: &[u8]) -> Option<ExecutableMemory> {
+            None
+        }
+
+        pub fn link<F: FnOnce(usize, &mut [u8])>(_code: &[u8], _relocate: F) -> Option<ExecutableMemory> {
+            None
+        }
+
+        pub fn address(&self) -> usize {
+            0
+        }
+
+        pub fn bytes(&self) -> &[u8] {
+            &[]
+        }
+
+        pub fn call(&self, _entry: usize, _arguments: *const i32, _depth: i64, _buffer: &mut DeoptBuffer) -> (u64, u64) {
+            (super::STATUS_BAILOUT, 0)
+        }
+
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use class::reader;
+    use runtime::ArrayElements;
+    use runtime::interpreter;
+    use std::slice;
+
+    fn jit_class() -> (Arc<ClassTable>, Arc<RuntimeClass>) {
+        let class_table = ClassTable::new();
+        class_table.set_compile_threshold(0);
+        let class = class_table.define_class(&reader::read_class_file(include_bytes!("../../../fixtures/Jit.class")).unwrap()).unwrap();
+        assert!(interpreter::initialize_class(&class, &class_table).unwrap().is_none());
+        (class_table, class)
+    }
+
+    fn integers(arguments: &[i32]) -> Vec<Value> {
+        arguments.iter().map(|&argument| Value::Integer(argument)).collect()
+    }
+
+    fn interpret(class_table: &ClassTable, class: &Arc<RuntimeClass>, method: &RuntimeMethod, arguments: &[i32]) -> String {
+        format!("{:?}", interpreter::invoke_method(class, method, integers(arguments), class_table).unwrap())
+    }
+
+    // Finishes a run of compiled code in the interpreter if it deoptimized. Returns None if it
+    // bailed out.
+    fn finish(class_table: &ClassTable, class: &Arc<RuntimeClass>, method: &RuntimeMethod, execution: Execution) -> Option<String> {
+        match execution {
+            Execution::Completed(result) => Some(format!("{:?}", result)),
+            Execution::Deoptimized(mut stack_frame, pc) => {
+                Some(format!("{:?}", interpreter::resume(&mut stack_frame, method, class, class_table, pc).unwrap()))
+            },
+            Execution::Interpret => None
+        }
+    }
+
+    fn run_compiled(class_table: &ClassTable, class: &Arc<RuntimeClass>, method: &RuntimeMethod, arguments: &[i32]) -> Option<String> {
+        let compiled = compile(class, method, class_table).unwrap();
+        finish(class_table, class, method, compiled.run(&integers(arguments)))
+    }
+
+    fn define(class_table: &ClassTable, bytes: &[u8]) -> Arc<RuntimeClass> {
+        class_table.define_class(&reader::read_class_file(bytes).unwrap()).unwrap()
+    }
+
+    // Loads Inlining and the classes it uses, except for Point3 and Triangle.
+    fn inlining_class() -> (Arc<ClassTable>, Arc<RuntimeClass>) {
+        let class_table = ClassTable::new();
+        class_table.set_compile_threshold(0);
+        define(&class_table, include_bytes!("../../../fixtures/Inlining$Point.class"));
+        define(&class_table, include_bytes!("../../../fixtures/Inlining$Shape.class"));
+        define(&class_table, include_bytes!("../../../fixtures/Inlining$Square.class"));
+        define(&class_table, include_bytes!("../../../fixtures/Inlining$Rectangle.class"));
+        define(&class_table, include_bytes!("../../../fixtures/Inlining$Node.class"));
+        let class = define(&class_table, include_bytes!("../../../fixtures/Inlining.class"));
+        assert!(interpreter::initialize_class(&class, &class_table).unwrap().is_none());
+        (class_table, class)
+    }
+
+    fn new_object(class_table: &ClassTable, class_name: &str, fields: &[(&str, Value)]) -> Value {
+        let object = class_table.new_object(&class_table.get_class(class_name).unwrap());
+        for &(name, ref value) in fields.iter() {
+            object.borrow_mut().put_field(String::from(name), value.clone());
+        }
+        Value::ObjectRef(object)
+    }
+
+    fn run_optimized(class_table: &ClassTable, class: &Arc<RuntimeClass>, method: &RuntimeMethod, arguments: &[Value]) -> (String, bool) {
+        let execution = optimize(class, method, class_table).unwrap().run(arguments);
+        let deoptimized = matches!(execution, Execution::Deoptimized(..));
+        (finish(class_table, class, method, execution).unwrap(), deoptimized)
+    }
+
+    // Runs every int method of Jit with the given arguments, interpreted and compiled by both
+    // tiers. Baseline code may bail out and optimized code may deoptimize, but neither may give a
+    // different result.
+    #[test]
+    #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+    fn agrees_with_the_interpreter() {
+        let (class_table, class) = jit_class();
+        let values = [0, 1, -1, 2, 7, -7, 31, 32, 33, 100, 12345, -65536, 0x7fff, 0x8000, i32::MAX, i32::MIN];
+        let cases: &[(&str, &str, usize)] = &[
+            ("fib", "(I)I", 1),
+            ("gcd", "(II)I", 2),
+            ("sumTo", "(I)I", 1),
+            ("divide", "(II)I", 2),
+            ("bits", "(II)I", 2),
+            ("narrow", "(I)I", 1),
+            ("constants", "(I)I", 1),
+            ("compare", "(II)I", 2),
+            ("callsVoid", "(I)I", 1),
+            ("sumOfSquares", "(I)I", 1),
+            ("folded", "(I)I", 1),
+            ("inlinedDivision", "(II)I", 2),
+            ("manyValues", "(II)I", 2)
+        ];
+
+        let mut bailouts = 0;
+        let mut deoptimizations = 0;
+        for &(name, descriptor, parameters) in cases.iter() {
+            let method = class.get_declared_method(name, descriptor).unwrap();
+            for &a in values.iter() {
+                for &b in values.iter().take(if parameters == 2 { values.len() } else { 1 }) {
+                    let arguments = if parameters == 2 { vec![a, b] } else { vec![a] };
+                    // Keep fib and sumTo fast.
+                    if (name == "fib" && !(0..=20).contains(&a)) || (name == "sumTo" && !(0..=100000).contains(&a))
+                        || (name == "sumOfSquares" && !(0..=1000).contains(&a)) {
+                        continue;
+                    }
+
+                    let expected = interpret(&class_table, &class, method, &arguments);
+                    match run_compiled(&class_table, &class, method, &arguments) {
+                        Some(actual) => assert_eq!(actual, expected, "{}{:?}", name, arguments),
+                        None => {
+                            assert!(expected.starts_with("Exception"), "{}{:?} bailed out", name, arguments);
+                            bailouts += 1;
+                        }
+                    }
+
+                    let execution = optimize(&class, method, &class_table).unwrap().run(&integers(&arguments));
+                    if let Execution::Deoptimized(..) = execution {
+                        deoptimizations += 1;
+                    }
+                    let actual = finish(&class_table, &class, method, execution).unwrap();
+                    if expected.starts_with("Exception") {
+                        assert!(actual.contains("ArithmeticException"), "{}{:?} optimized", name, arguments);
+                    } else {
+                        assert_eq!(actual, expected, "{}{:?} optimized", name, arguments);
+                    }
+                }
+            }
+        }
+
+        // divide and inlinedDivision bail out on every zero divisor so that the interpreter
+        // throws, and optimized code deoptimizes instead.
+        assert_eq!(bailouts, 2 * values.len());
+        assert_eq!(deoptimizations, 2 * values.len());
+    }
+
+    #[test]
+    #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+    fn bails_out_of_deep_recursion() {
+        let (class_table, class) = jit_class();
+        let depth = class.get_declared_method("depth", "(I)I").unwrap();
+
+        assert_eq!(run_compiled(&class_table, &class, depth, &[100]), Some(String::from("Value(Integer(100))")));
+        assert_eq!(run_compiled(&class_table, &class, depth, &[100000]), None);
+        let collatz = class.get_declared_method("collatz", "(I)I").unwrap();
+        assert_eq!(run_compiled(&class_table, &class, collatz, &[27]), Some(String::from("Value(Integer(111))")));
+
+        // Optimized code deoptimizes to the outermost call instead.
+        let optimized = optimize(&class, depth, &class_table).unwrap();
+        assert_eq!(finish(&class_table, &class, depth, optimized.run(&integers(&[100]))), Some(String::from("Value(Integer(100))")));
+        match optimized.run(&integers(&[100000])) {
+            Execution::Deoptimized(stack_frame, pc) => {
+                assert_eq!(format!("{:?}", stack_frame.locals), "[Integer(100000)]");
+                assert_eq!(format!("{:?}", stack_frame.stack), "[Integer(99999)]");
+                assert!(pc > 0);
+            },
+            _ => panic!("depth(100000) did not deoptimize")
+        }
+    }
+
+    #[test]
+    fn leaves_unsupported_methods_to_the_interpreter() {
+        let (class_table, class) = jit_class();
+        let uses_longs = class.get_declared_method("usesLongs", "(I)I").unwrap();
+        assert!(compile(&class, uses_longs, &class_table).is_none());
+        assert!(matches!(uses_longs.compiled.get(), Some(None)));
+        assert!(optimize(&class, uses_longs, &class_table).is_none());
+    }
+
+    // Methods are compiled once they have been invoked often enough.
+    #[test]
+    #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+    fn compiles_hot_methods() {
+        let (class_table, class) = jit_class();
+        class_table.set_compile_threshold(3);
+        let gcd = class.get_declared_method("gcd", "(II)I").unwrap();
+
+        for _ in 0..2 {
+            assert_eq!(interpret(&class_table, &class, gcd, &[12, 18]), "Value(Integer(6))");
+            assert!(gcd.compiled.get().is_none());
+        }
+        assert_eq!(interpret(&class_table, &class, gcd, &[12, 18]), "Value(Integer(6))");
+        assert!(matches!(gcd.compiled.get(), Some(Some(_))));
+        assert_eq!(interpret(&class_table, &class, gcd, &[1071, 462]), "Value(Integer(21))");
+    }
+
+    // While profiling, the iterations of loops count towards compiling and optimizing a method.
+    #[test]
+    #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+    fn compiles_methods_with_hot_loops_sooner_while_profiling() {
+        let (class_table, class) = jit_class();
+        class_table.set_compile_threshold(3);
+        let sum_to = class.get_declared_method("sumTo", "(I)I").unwrap();
+        assert_eq!(interpret(&class_table, &class, sum_to, &[5]), "Value(Integer(15))");
+        assert!(sum_to.compiled.get().is_none());
+
+        let (class_table, class) = jit_class();
+        class_table.set_compile_threshold(3);
+        class_table.profile.enable();
+        let sum_to = class.get_declared_method("sumTo", "(I)I").unwrap();
+        assert_eq!(interpret(&class_table, &class, sum_to, &[5]), "Value(Integer(15))");
+        assert!(sum_to.optimized.get().is_none());
+        // One invocation and six branches are not hot enough to optimize it, but they are enough
+        // to compile it.
+        assert_eq!(interpret(&class_table, &class, sum_to, &[100]), "Value(Integer(5050))");
+        assert!(matches!(sum_to.compiled.get(), Some(Some(_))));
+        assert!(sum_to.optimized.get().is_none());
+        // The hundred and one branches of that invocation were not run by the interpreter.
+        assert_eq!(interpret(&class_table, &class, sum_to, &[30]), "Value(Integer(465))");
+        assert!(sum_to.optimized.get().is_none());
+    }
+
+    // While profiling, compiled code neither calls nor inlines other methods, so that the
+    // interpreter counts every invocation. What compiled code runs is not counted, which the
+    // report marks.
+    #[test]
+    #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+    fn leaves_calls_to_the_interpreter_while_profiling() {
+        let (class_table, class) = jit_class();
+        class_table.set_compile_threshold(1);
+        class_table.profile.enable();
+        let sum_of_squares = class.get_declared_method("sumOfSquares", "(I)I").unwrap();
+        for _ in 0..3 {
+            assert_eq!(interpret(&class_table, &class, sum_of_squares, &[4]), "Value(Integer(24))");
+        }
+
+        assert!(matches!(sum_of_squares.compiled.get(), Some(None)));
+        assert!(matches!(class.get_declared_method("square", "(I)I").unwrap().compiled.get(), Some(Some(_))));
+        let report = class_table.profile.report();
+        assert!(report.contains(&format!("{:>12} {:>12} {:>12}  Jit.square(I)I\n", 24, "0*", 24)), "{}", report);
+        assert!(report.contains(&format!("{:>12} {:>12} {:>12}  Jit.sumOfSquares(I)I\n", 3, 255, 0)), "{}", report);
+        assert!(report.contains("* incomplete: the bytecodes of compiled invocations are not counted"), "{}", report);
+    }
+
+    // Methods that stay hot are optimized, and keep working when the optimized code deoptimizes.
+    #[test]
+    #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+    fn optimizes_hot_methods() {
+        let (class_table, class) = jit_class();
+        class_table.set_compile_threshold(2);
+        let divide = class.get_declared_method("inlinedDivision", "(II)I").unwrap();
+
+        for _ in 0..19 {
+            assert_eq!(interpret(&class_table, &class, divide, &[7, 2]), "Value(Integer(4))");
+        }
+        assert!(matches!(divide.compiled.get(), Some(Some(_))));
+        assert!(divide.optimized.get().is_none());
+        assert_eq!(interpret(&class_table, &class, divide, &[7, 2]), "Value(Integer(4))");
+        assert!(matches!(divide.optimized.get(), Some(Some(_))));
+        assert_eq!(interpret(&class_table, &class, divide, &[-9, 3]), "Value(Integer(-2))");
+        assert!(interpret(&class_table, &class, divide, &[1, 0]).starts_with("Exception"));
+    }
+
+    // A virtual call that only one loaded class can receive is inlined behind a check of the class
+    // of the receiver. Other receivers make the code deoptimize, and the interpreter makes the
+    // call.
+    #[test]
+    #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+    fn inlines_virtual_calls_behind_class_checks() {
+        let (class_table, class) = inlining_class();
+        let sum = class.get_declared_method("sum", "(LInlining$Point;)I").unwrap();
+        let point = new_object(&class_table, "Inlining$Point", &[("x", Value::Integer(3)), ("y", Value::Integer(4))]);
+        assert_eq!(run_optimized(&class_table, &class, sum, &[point]), (String::from("Value(Integer(7))"), false));
+
+        let (result, deoptimized) = run_optimized(&class_table, &class, sum, &[Value::Null]);
+        assert!(result.contains("NullPointerException") && deoptimized);
+
+        define(&class_table, include_bytes!("../../../fixtures/Inlining$Point3.class"));
+        let point3 = new_object(&class_table, "Inlining$Point3", &[("x", Value::Integer(1)), ("y", Value::Integer(2)), ("z", Value::Integer(3))]);
+        assert_eq!(run_optimized(&class_table, &class, sum, &[point3]), (String::from("Value(Integer(6))"), true));
+        assert!(optimize(&class, sum, &class_table).unwrap().is_valid());
+    }
+
+    // A virtual call that several loaded classes select the same method for is inlined on the
+    // assumption that no class is loaded that selects another one. Once one is, the code
+    // deoptimizes and is not run again.
+    #[test]
+    #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+    fn deoptimizes_when_a_class_breaks_an_assumption() {
+        let (class_table, class) = inlining_class();
+        let sides = class.get_declared_method("sides", "(LInlining$Shape;)I").unwrap();
+        let square = new_object(&class_table, "Inlining$Square", &[]);
+        let rectangle = new_object(&class_table, "Inlining$Rectangle", &[]);
+        assert_eq!(run_optimized(&class_table, &class, sides, slice::from_ref(&square)), (String::from("Value(Integer(40))"), false));
+        assert_eq!(run_optimized(&class_table, &class, sides, &[rectangle]), (String::from("Value(Integer(40))"), false));
+        let (result, deoptimized) = run_optimized(&class_table, &class, sides, &[Value::Null]);
+        assert!(result.contains("NullPointerException") && deoptimized);
+
+        define(&class_table, include_bytes!("../../../fixtures/Inlining$Triangle.class"));
+        let triangle = new_object(&class_table, "Inlining$Triangle", &[]);
+        assert!(!optimize(&class, sides, &class_table).unwrap().is_valid());
+        assert_eq!(run_optimized(&class_table, &class, sides, slice::from_ref(&square)), (String::from("Value(Integer(40))"), true));
+        assert_eq!(run_optimized(&class_table, &class, sides, &[triangle]), (String::from("Value(Integer(30))"), true));
+
+        // The baseline tier does not compile methods that take objects.
+        class_table.set_compile_threshold(1);
+        assert!(matches!(invoke(&class, sides, &[square], &class_table), Execution::Interpret));
+    }
+
+    // Loops over arrays and lists agree with the interpreter. The bounds checks of sum are
+    // removed, while those of sumFirst make it deoptimize when n exceeds the length.
+    #[test]
+    #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+    fn reads_arrays_and_fields() {
+        let (class_table, class) = inlining_class();
+        let sum = class.get_declared_method("sum", "([I)I").unwrap();
+        let sum_first = class.get_declared_method("sumFirst", "([II)I").unwrap();
+        let array = Value::ArrayRef(class_table.new_array_from("I", ArrayElements::Integer(vec![1, 2, 3, 4, 5])));
+        let empty = Value::ArrayRef(class_table.new_array_from("I", ArrayElements::Integer(Vec::new())));
+
+        assert_eq!(run_optimized(&class_table, &class, sum, slice::from_ref(&array)), (String::from("Value(Integer(15))"), false));
+        assert_eq!(run_optimized(&class_table, &class, sum, &[empty]), (String::from("Value(Integer(0))"), false));
+        let (result, deoptimized) = run_optimized(&class_table, &class, sum, &[Value::Null]);
+        assert!(result.contains("NullPointerException") && deoptimized);
+
+        assert_eq!(run_optimized(&class_table, &class, sum_first, &[array.clone(), Value::Integer(3)]), (String::from("Value(Integer(6))"), false));
+        let (result, deoptimized) = run_optimized(&class_table, &class, sum_first, &[array, Value::Integer(6)]);
+        assert!(result.contains("ArrayIndexOutOfBoundsException") && deoptimized);
+
+        let total = class.get_declared_method("total", "(LInlining$Node;)I").unwrap();
+        let mut list = Value::Null;
+        for value in 1..=4 {
+            list = new_object(&class_table, "Inlining$Node", &[("value", Value::Integer(value)), ("next", list)]);
+        }
+        assert_eq!(run_optimized(&class_table, &class, total, &[list]), (String::from("Value(Integer(10))"), false));
+        assert_eq!(run_optimized(&class_table, &class, total, &[Value::Null]), (String::from("Value(Integer(0))"), false));
+    }
+
+}
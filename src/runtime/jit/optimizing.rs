@@ -0,0 +1,516 @@
+use runtime::Value;
+use runtime::class::{ClassTable, RuntimeClass};
+use runtime::jit::{Code, CallTarget, Condition, DeoptBuffer, Kind, MAX_FRAME_SLOTS, STATUS_OK, STATUS_DEOPTIMIZED};
+use runtime::jit::regalloc::{self, Allocation, Location};
+use runtime::jit::ssa::{Function, Node, NodeId, BlockId, FrameState, Terminator};
+use runtime::jit::x86::{self, EAX, ECX, EDX, RSI, RDI};
+use std::collections::HashMap;
+use std::ptr;
+use std::sync::Arc;
+
+// The second tier of the JIT, which emits machine code for an optimized SSA graph. Values live in
+// the registers and spill slots the register allocator gave them, and are only brought into eax
+// and ecx to be operated on.
+//
+// Compiled methods are called like baseline ones, with a pointer to their int arguments in rdi
+// and the remaining call depth in rsi, and also a pointer to a DeoptBuffer in rdx. To deoptimize,
+// they write the interpreter frame into the buffer and return STATUS_DEOPTIMIZED.
+//
+// The frame holds the callee-saved registers below rbp, then the call depth at [rbp - 48], the
+// buffer pointer at [rbp - 56], the spill slots and, at the bottom, the arguments of calls.
+//
+// References are ids in the References of the buffer, which the functions of the runtime that
+// read the heap take along with the buffer.
+
+const DEPTH: i32 = -48;
+const BUFFER: i32 = -56;
+
+// The offsets of the fields of DeoptBuffer.
+const BUFFER_PC: u8 = 0;
+const BUFFER_LOCALS: u8 = 4;
+const BUFFER_STACK: u8 = 8;
+const BUFFER_VALUES: i32 = 12;
+const BUFFER_KINDS: i32 = BUFFER_VALUES + 4 * MAX_FRAME_SLOTS as i32;
+
+pub fn emit(function: &Function, class_table: &ClassTable) -> Option<Code> {
+    let order = function.order();
+    let allocation = regalloc::allocate(function, &order);
+    let max_arguments = function.nodes.iter()
+        .map(|node| match *node {
+            Node::Call { ref arguments, .. } => arguments.len(),
+            _ => 0
+        })
+        .max()
+        .unwrap_or(0);
+    let frame_size = 16 + 4 * (allocation.spill_slots + max_arguments);
+    let frame_size = (40 + frame_size).div_ceil(16) * 16 - 40;
+
+    let mut emitter = Emitter {
+        code: Vec::new(),
+        function,
+        kinds: function.kinds(),
+        allocation,
+        labels: HashMap::new(),
+        fixups: Vec::new(),
+        absolute: Vec::new(),
+        stubs: Vec::new(),
+        stub_positions: Vec::new(),
+        safepoint: class_table.heap.safepoint_flag() as *const _ as u64
+    };
+
+    emitter.prologue(frame_size as i32);
+    for (index, &block) in order.iter().enumerate() {
+        emitter.labels.insert(block, emitter.code.len());
+        for &node in function.blocks[block].nodes.iter() {
+            emitter.node(node);
+        }
+        emitter.terminator(block, order.get(index + 1).cloned());
+    }
+    emitter.stubs();
+
+    emitter.finish()
+}
+
+// Where a rel32 operand jumps to.
+enum Label {
+    Block(BlockId),
+    // The code that deoptimizes to one of the frame states in stubs.
+    Stub(usize)
+}
+
+struct Emitter<'a> {
+    code: Vec<u8>,
+    function: &'a Function,
+    kinds: Vec<Kind>,
+    allocation: Allocation,
+    labels: HashMap<BlockId, usize>,
+    fixups: Vec<(usize, Label)>,
+    absolute: Vec<usize>,
+    stubs: Vec<&'a FrameState>,
+    stub_positions: Vec<usize>,
+    safepoint: u64
+}
+
+impl<'a> Emitter<'a> {
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.code.extend_from_slice(bytes);
+    }
+
+    fn imm32(&mut self, value: i32) {
+        self.code.extend_from_slice(&value.to_le_bytes());
+    }
+
+    // An address that must be relocated if the code moves.
+    fn address(&mut self, address: u64) {
+        self.absolute.push(self.code.len());
+        self.code.extend_from_slice(&address.to_le_bytes());
+    }
+
+    fn rex(&mut self, wide: bool, register: u8, rm: u8) {
+        let rex = 0x40 | if wide { 8 } else { 0 } | (register >> 3) << 2 | rm >> 3;
+        if rex != 0x40 {
+            self.code.push(rex);
+        }
+    }
+
+    // An instruction with two register operands.
+    fn registers(&mut self, opcode: u8, register: u8, rm: u8) {
+        self.rex(false, register, rm);
+        self.bytes(&[opcode, 0xc0 | (register & 7) << 3 | rm & 7]);
+    }
+
+    // An instruction whose last operand is [rbp + displacement].
+    fn rbp_operand(&mut self, wide: bool, opcode: u8, register: u8, displacement: i32) {
+        self.rex(wide, register, 0);
+        self.bytes(&[opcode, 0x85 | (register & 7) << 3]);
+        self.imm32(displacement);
+    }
+
+    fn spill_slot(slot: usize) -> i32 {
+        BUFFER - 4 * (slot as i32 + 1)
+    }
+
+    fn location(&self, node: NodeId) -> Location {
+        self.allocation.locations[node]
+    }
+
+    fn load(&mut self, register: u8, location: Location) {
+        match location {
+            Location::Register(source) if source == register => {},
+            Location::Register(source) => self.registers(0x8b, register, source),
+            Location::Stack(slot) => self.rbp_operand(false, 0x8b, register, Self::spill_slot(slot)),
+            Location::Constant(value) => {
+                // mov r32, value
+                self.rex(false, 0, register);
+                self.bytes(&[0xb8 + (register & 7)]);
+                self.imm32(value);
+            },
+            Location::None => unreachable!("a value without a location is used")
+        }
+    }
+
+    fn store(&mut self, register: u8, location: Location) {
+        match location {
+            Location::Register(destination) if destination == register => {},
+            Location::Register(destination) => self.registers(0x8b, destination, register),
+            Location::Stack(slot) => self.rbp_operand(false, 0x89, register, Self::spill_slot(slot)),
+            Location::Constant(_) | Location::None => {}
+        }
+    }
+
+    fn jump(&mut self, opcode: &[u8], label: Label) {
+        self.bytes(opcode);
+        self.fixups.push((self.code.len(), label));
+        self.imm32(0);
+    }
+
+    fn stub(&mut self, state: &'a FrameState) -> Label {
+        self.stubs.push(state);
+        Label::Stub(self.stubs.len() - 1)
+    }
+
+    fn prologue(&mut self, frame_size: i32) {
+        // push rbp; mov rbp, rsp; push rbx; push r12; push r13; push r14; push r15; sub rsp, frame_size
+        self.bytes(&[0x55, 0x48, 0x89, 0xe5, 0x53, 0x41, 0x54, 0x41, 0x55, 0x41, 0x56, 0x41, 0x57, 0x48, 0x81, 0xec]);
+        self.imm32(frame_size);
+        self.rbp_operand(true, 0x89, RSI, DEPTH);
+        self.rbp_operand(true, 0x89, EDX, BUFFER);
+
+        let function = self.function;
+        for &node in function.blocks[0].nodes.iter() {
+            if let Node::Parameter(parameter, _) = function.nodes[node] {
+                // mov eax, [rdi + 4 * parameter]
+                self.bytes(&[0x8b, 0x87]);
+                self.imm32(4 * parameter as i32);
+                let location = self.location(node);
+                self.store(EAX, location);
+            }
+        }
+    }
+
+    fn epilogue(&mut self, status: u64) {
+        // mov eax, status; lea rsp, [rbp - 40]; pop r15; pop r14; pop r13; pop r12; pop rbx; pop rbp; ret
+        self.bytes(&[0xb8]);
+        self.imm32(status as i32);
+        self.bytes(&[0x48, 0x8d, 0x65, 0xd8, 0x41, 0x5f, 0x41, 0x5e, 0x41, 0x5d, 0x41, 0x5c, 0x5b, 0x5d, 0xc3]);
+    }
+
+    fn node(&mut self, node: NodeId) {
+        let function = self.function;
+        let location = self.location(node);
+        match function.nodes[node] {
+            Node::Binary(operation, left, right) => {
+                let (left, right) = (self.location(left), self.location(right));
+                self.load(EAX, left);
+                self.load(ECX, right);
+                x86::binary(&mut self.code, operation);
+                self.store(EAX, location);
+            },
+            Node::Negate(value) => {
+                let value = self.location(value);
+                self.load(EAX, value);
+                x86::negate(&mut self.code);
+                self.store(EAX, location);
+            },
+            Node::Narrow(narrowing, value) => {
+                let value = self.location(value);
+                self.load(EAX, value);
+                x86::narrow(&mut self.code, narrowing);
+                self.store(EAX, location);
+            },
+            Node::CheckNonZero(value, ref state) => {
+                // test eax, eax; je stub
+                let value = self.location(value);
+                self.load(EAX, value);
+                self.bytes(&[0x85, 0xc0]);
+                let stub = self.stub(state);
+                self.jump(&[0x0f, 0x84], stub);
+            },
+            Node::Safepoint(ref state) => {
+                let safepoint = self.safepoint;
+                self.poll(safepoint, state);
+            },
+            Node::CheckAssumption(invalidated, ref state) => self.poll(invalidated as u64, state),
+            Node::CheckNonNull(value, ref state) => {
+                // test eax, eax; je stub
+                let value = self.location(value);
+                self.load(EAX, value);
+                self.bytes(&[0x85, 0xc0]);
+                let stub = self.stub(state);
+                self.jump(&[0x0f, 0x84], stub);
+            },
+            Node::CheckBounds(index, length, ref state) => {
+                // An unsigned comparison also catches negative indices.
+                // cmp eax, ecx; jae stub
+                let (index, length) = (self.location(index), self.location(length));
+                self.load(EAX, index);
+                self.load(ECX, length);
+                self.bytes(&[0x39, 0xc8]);
+                let stub = self.stub(state);
+                self.jump(&[0x0f, 0x83], stub);
+            },
+            Node::CheckClass(object, class, ref state) => {
+                // mov rdx, class; call has_class; test eax, eax; je stub
+                let object = self.location(object);
+                self.load(RSI, object);
+                self.bytes(&[0x48, 0xba]);
+                self.address(class as u64);
+                self.call_runtime(has_class as *const ());
+                self.bytes(&[0x85, 0xc0]);
+                let stub = self.stub(state);
+                self.jump(&[0x0f, 0x84], stub);
+            },
+            Node::GetField(object, position, _) => {
+                let object = self.location(object);
+                self.load(RSI, object);
+                self.load(EDX, Location::Constant(position as i32));
+                self.call_runtime(get_field as *const ());
+                self.store(EAX, location);
+            },
+            Node::ArrayLength(array) => {
+                let array = self.location(array);
+                self.load(RSI, array);
+                self.call_runtime(array_length as *const ());
+                self.store(EAX, location);
+            },
+            Node::ArrayLoad(array, index, _) => {
+                let (array, index) = (self.location(array), self.location(index));
+                self.load(RSI, array);
+                self.load(EDX, index);
+                self.call_runtime(array_load as *const ());
+                self.store(EAX, location);
+            },
+            Node::Call { target, ref arguments, returns_void, ref state } => {
+                for (index, &argument) in arguments.iter().enumerate() {
+                    // mov [rsp + 4 * index], eax
+                    let argument = self.location(argument);
+                    self.load(EAX, argument);
+                    self.bytes(&[0x89, 0x84, 0x24]);
+                    self.imm32(4 * index as i32);
+                }
+
+                // mov rsi, [rbp + DEPTH]; dec rsi; jle stub; mov rdi, rsp; mov rdx, [rbp + BUFFER]
+                let stub = self.stub(state);
+                self.rbp_operand(true, 0x8b, RSI, DEPTH);
+                self.bytes(&[0x48, 0xff, 0xce]);
+                self.jump(&[0x0f, 0x8e], stub);
+                self.bytes(&[0x48, 0x89, 0xe7]);
+                self.rbp_operand(true, 0x8b, EDX, BUFFER);
+
+                match target {
+                    CallTarget::Itself => {
+                        // call rel32 to the start of the method
+                        self.bytes(&[0xe8]);
+                        let offset = -(self.code.len() as i32 + 4);
+                        self.imm32(offset);
+                    },
+                    CallTarget::Address(address) => {
+                        // mov rax, address; call rax
+                        self.bytes(&[0x48, 0xb8]);
+                        self.address(address as u64);
+                        self.bytes(&[0xff, 0xd0]);
+                    }
+                }
+
+                // The interpreter calls the method again if it bailed out or deoptimized.
+                // test rax, rax; jne stub
+                self.bytes(&[0x48, 0x85, 0xc0]);
+                let stub = self.stub(state);
+                self.jump(&[0x0f, 0x85], stub);
+                if !returns_void {
+                    self.store(EDX, location);
+                }
+            },
+            Node::Parameter(..) | Node::Constant(_) | Node::Null | Node::Phi(_) | Node::Removed => {}
+        }
+    }
+
+    // Deoptimizes if the byte at the address is set.
+    fn poll(&mut self, flag: u64, state: &'a FrameState) {
+        // mov rax, flag; cmp byte [rax], 0; jne stub
+        self.bytes(&[0x48, 0xb8]);
+        self.address(flag);
+        self.bytes(&[0x80, 0x38, 0x00]);
+        let stub = self.stub(state);
+        self.jump(&[0x0f, 0x85], stub);
+    }
+
+    // Calls a function of the runtime, which takes the buffer in rdi and its other arguments in
+    // esi and edx, and returns its result in eax. The registers values live in are callee-saved,
+    // and the prologue aligned the stack.
+    fn call_runtime(&mut self, function: *const ()) {
+        // mov rdi, [rbp + BUFFER]; mov rax, function; call rax
+        self.rbp_operand(true, 0x8b, RDI, BUFFER);
+        self.bytes(&[0x48, 0xb8]);
+        self.address(function as u64);
+        self.bytes(&[0xff, 0xd0]);
+    }
+
+    fn terminator(&mut self, block: BlockId, next: Option<BlockId>) {
+        let function = self.function;
+        match function.blocks[block].terminator {
+            Terminator::Jump(target) => {
+                self.phi_moves(block, target);
+                if next != Some(target) {
+                    self.jump(&[0xe9], Label::Block(target));
+                }
+            },
+            Terminator::Branch { condition, left, right, then, otherwise } => {
+                // cmp eax, ecx
+                let (left, right) = (self.location(left), self.location(right));
+                self.load(EAX, left);
+                self.load(ECX, right);
+                self.bytes(&[0x39, 0xc8]);
+                if next == Some(then) {
+                    self.jump(&[0x0f, x86::condition_code(negate(condition))], Label::Block(otherwise));
+                } else {
+                    self.jump(&[0x0f, x86::condition_code(condition)], Label::Block(then));
+                    if next != Some(otherwise) {
+                        self.jump(&[0xe9], Label::Block(otherwise));
+                    }
+                }
+            },
+            Terminator::Return(value) => {
+                if let Some(value) = value {
+                    let value = self.location(value);
+                    self.load(EDX, value);
+                }
+                self.epilogue(STATUS_OK);
+            },
+            Terminator::None => {}
+        }
+    }
+
+    // Moves the operands of the phis of target into place. The moves happen all at once, so a
+    // move waits until no other move still reads its destination, and cycles are broken by
+    // saving a destination in ecx.
+    fn phi_moves(&mut self, block: BlockId, target: BlockId) {
+        let function = self.function;
+        let mut moves: Vec<(Location, Location)> = function.blocks[target].phis.iter()
+            .map(|&phi| (self.location(function.phi_operand(phi, target, block)), self.location(phi)))
+            .filter(|&(source, destination)| source != destination && destination != Location::None)
+            .collect();
+
+        while !moves.is_empty() {
+            let ready = (0..moves.len()).find(|&index| {
+                let destination = moves[index].1;
+                moves.iter().enumerate().all(|(other, &(source, _))| other == index || source != destination)
+            });
+            match ready {
+                Some(index) => {
+                    let (source, destination) = moves.remove(index);
+                    self.move_value(source, destination);
+                },
+                None => {
+                    let saved = moves[0].1;
+                    self.move_value(saved, Location::Register(ECX));
+                    for &mut (ref mut source, _) in moves.iter_mut() {
+                        if *source == saved {
+                            *source = Location::Register(ECX);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn move_value(&mut self, source: Location, destination: Location) {
+        match destination {
+            Location::Register(register) => self.load(register, source),
+            _ => {
+                self.load(EAX, source);
+                self.store(EAX, destination);
+            }
+        }
+    }
+
+    // Each stub writes the interpreter frame into the DeoptBuffer and returns.
+    fn stubs(&mut self) {
+        let stubs = ::std::mem::take(&mut self.stubs);
+        for state in stubs.iter() {
+            self.stub_positions.push(self.code.len());
+
+            // mov rcx, [rbp + BUFFER]
+            self.rbp_operand(true, 0x8b, ECX, BUFFER);
+            for (index, &value) in state.locals.iter().chain(state.stack.iter()).enumerate() {
+                // mov [rcx + BUFFER_VALUES + 4 * index], eax
+                let (location, kind) = (self.location(value), self.kinds[value]);
+                self.load(EAX, location);
+                self.bytes(&[0x89, 0x81]);
+                self.imm32(BUFFER_VALUES + 4 * index as i32);
+                // mov byte [rcx + BUFFER_KINDS + index], kind
+                self.bytes(&[0xc6, 0x81]);
+                self.imm32(BUFFER_KINDS + index as i32);
+                self.bytes(&[(kind == Kind::Reference) as u8]);
+            }
+            // mov dword [rcx + field], value
+            for &(field, value) in [(BUFFER_PC, state.pc as i32), (BUFFER_LOCALS, state.locals.len() as i32), (BUFFER_STACK, state.stack.len() as i32)].iter() {
+                self.bytes(&[0xc7, 0x41, field]);
+                self.imm32(value);
+            }
+            self.epilogue(STATUS_DEOPTIMIZED);
+        }
+    }
+
+    fn finish(mut self) -> Option<Code> {
+        for (position, label) in self.fixups.iter() {
+            let target = match *label {
+                Label::Block(block) => *self.labels.get(&block)?,
+                Label::Stub(stub) => *self.stub_positions.get(stub)?
+            };
+            let offset = target as i32 - (*position as i32 + 4);
+            self.code[*position..*position + 4].copy_from_slice(&offset.to_le_bytes());
+        }
+        Some(Code { bytes: self.code, absolute: self.absolute })
+    }
+
+}
+
+// The functions of the runtime that optimized code reads the heap with. The values they are
+// called with have been checked, so the fallbacks to 0 are never taken.
+
+extern "sysv64" fn get_field(buffer: &mut DeoptBuffer, object: i32, position: i32) -> i32 {
+    let value = match buffer.references.decode(object, Kind::Reference) {
+        Value::ObjectRef(object) => object.borrow().fields().get(position as usize).cloned(),
+        _ => None
+    };
+    buffer.references.encode(value.unwrap_or(Value::Null))
+}
+
+extern "sysv64" fn array_length(buffer: &mut DeoptBuffer, array: i32) -> i32 {
+    match buffer.references.decode(array, Kind::Reference) {
+        Value::ArrayRef(array) => array.borrow().len() as i32,
+        _ => 0
+    }
+}
+
+extern "sysv64" fn array_load(buffer: &mut DeoptBuffer, array: i32, index: i32) -> i32 {
+    let value = match buffer.references.decode(array, Kind::Reference) {
+        Value::ArrayRef(array) => {
+            let array = array.borrow();
+            if (index as usize) < array.len() { Some(array.get(index as usize)) } else { None }
+        },
+        _ => None
+    };
+    buffer.references.encode(value.unwrap_or(Value::Null))
+}
+
+// Whether the object is an instance of exactly the class, rather than of a subclass.
+extern "sysv64" fn has_class(buffer: &mut DeoptBuffer, object: i32, class: *const RuntimeClass) -> i32 {
+    match buffer.references.decode(object, Kind::Reference) {
+        Value::ObjectRef(object) => ptr::eq(Arc::as_ptr(object.borrow().class()), class) as i32,
+        _ => 0
+    }
+}
+
+fn negate(condition: Condition) -> Condition {
+    match condition {
+        Condition::Equal => Condition::NotEqual,
+        Condition::NotEqual => Condition::Equal,
+        Condition::Less => Condition::GreaterOrEqual,
+        Condition::GreaterOrEqual => Condition::Less,
+        Condition::Greater => Condition::LessOrEqual,
+        Condition::LessOrEqual => Condition::Greater
+    }
+}
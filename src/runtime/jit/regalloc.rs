@@ -0,0 +1,205 @@
+use runtime::jit::ssa::{Function, Node, NodeId, BlockId};
+use runtime::jit::x86::{EBX, R12, R13, R14, R15};
+use std::collections::HashSet;
+
+// Linear scan register allocation for the optimizing tier. Blocks are laid out in reverse
+// postorder and each value gets a single interval, from the first to the last position where it
+// is live. Intervals are handed the callee-saved registers in order of their start, and when
+// these run out the interval that ends last is spilled to the stack frame.
+
+// The registers values live in. They are callee-saved, so calls leave them alone.
+const REGISTERS: [u8; 5] = [EBX, R12, R13, R14, R15];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Location {
+    Register(u8),
+    // A 4 byte slot in the stack frame.
+    Stack(usize),
+    Constant(i32),
+    // The node has no value, or it is never used.
+    None
+}
+
+pub struct Allocation {
+    pub locations: Vec<Location>,
+    pub spill_slots: usize
+}
+
+// Whether the node defines a value that needs a location.
+fn has_value(function: &Function, node: NodeId) -> bool {
+    match function.nodes[node] {
+        Node::Parameter(..) | Node::Phi(_) | Node::Binary(..) | Node::Negate(_) | Node::Narrow(..)
+            | Node::GetField(..) | Node::ArrayLength(_) | Node::ArrayLoad(..) => true,
+        Node::Call { returns_void, .. } => !returns_void,
+        Node::Constant(_) | Node::Null | Node::CheckNonZero(..) | Node::CheckNonNull(..) | Node::CheckBounds(..) | Node::CheckClass(..)
+            | Node::CheckAssumption(..) | Node::Safepoint(_) | Node::Removed => false
+    }
+}
+
+pub fn allocate(function: &Function, order: &[BlockId]) -> Allocation {
+    let blocks = function.blocks.len();
+    let mut block_start = vec![0; blocks];
+    let mut block_end = vec![0; blocks];
+    let mut position = 0;
+    for &block in order {
+        block_start[block] = position;
+        position += 2 * (1 + function.blocks[block].nodes.len());
+        block_end[block] = position;
+        position += 2;
+    }
+
+    // Liveness, iterated to a fixed point. A phi operand is live at the end of the predecessor it
+    // comes from.
+    let needs_location = |node: &NodeId| has_value(function, *node);
+    let mut live_in: Vec<HashSet<NodeId>> = vec![HashSet::new(); blocks];
+    let mut live_out: Vec<HashSet<NodeId>> = vec![HashSet::new(); blocks];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &block in order.iter().rev() {
+            let mut live = HashSet::new();
+            for successor in function.successors(block) {
+                live.extend(live_in[successor].iter().cloned());
+                for &phi in function.blocks[successor].phis.iter() {
+                    let operand = function.phi_operand(phi, successor, block);
+                    if needs_location(&operand) {
+                        live.insert(operand);
+                    }
+                }
+            }
+            live_out[block] = live.clone();
+
+            live.extend(function.terminator_uses(block).into_iter().filter(needs_location));
+            for &node in function.blocks[block].nodes.iter().rev() {
+                live.remove(&node);
+                live.extend(function.uses(node).into_iter().filter(needs_location));
+            }
+            for phi in function.blocks[block].phis.iter() {
+                live.remove(phi);
+            }
+
+            if live != live_in[block] {
+                live_in[block] = live;
+                changed = true;
+            }
+        }
+    }
+
+    // Each interval covers every position where its value is live.
+    let mut intervals: Vec<Option<(usize, usize)>> = vec![None; function.nodes.len()];
+    let mut extend = |node: NodeId, position: usize| {
+        let interval = intervals[node].get_or_insert((position, position));
+        interval.0 = interval.0.min(position);
+        interval.1 = interval.1.max(position);
+    };
+    for &block in order {
+        for &node in live_in[block].iter() {
+            extend(node, block_start[block]);
+        }
+        for &node in live_out[block].iter() {
+            extend(node, block_end[block]);
+        }
+        // Phis are written by moves at the end of each predecessor.
+        for &phi in function.blocks[block].phis.iter() {
+            extend(phi, block_start[block]);
+            for &predecessor in function.blocks[block].predecessors.iter() {
+                extend(phi, block_end[predecessor]);
+            }
+        }
+        for (index, &node) in function.blocks[block].nodes.iter().enumerate() {
+            let position = block_start[block] + 2 * (index + 1);
+            if has_value(function, node) {
+                extend(node, position);
+            }
+            for value in function.uses(node).into_iter().filter(needs_location) {
+                extend(value, position);
+            }
+        }
+        for value in function.terminator_uses(block).into_iter().filter(needs_location) {
+            extend(value, block_end[block]);
+        }
+    }
+
+    let mut locations: Vec<Location> = function.nodes.iter()
+        .map(|node| match *node {
+            Node::Constant(value) => Location::Constant(value),
+            Node::Null => Location::Constant(0),
+            _ => Location::None
+        })
+        .collect();
+    let mut sorted: Vec<(usize, usize, NodeId)> = intervals.iter()
+        .enumerate()
+        .filter_map(|(node, interval)| interval.map(|(start, end)| (start, end, node)))
+        .collect();
+    sorted.sort();
+
+    let mut spill_slots = 0;
+    let mut free: Vec<u8> = REGISTERS.iter().rev().cloned().collect();
+    let mut active: Vec<(usize, NodeId, u8)> = Vec::new();
+    for (start, end, node) in sorted {
+        active.retain(|&(active_end, _, register)| {
+            if active_end < start {
+                free.push(register);
+                false
+            } else {
+                true
+            }
+        });
+
+        if let Some(register) = free.pop() {
+            locations[node] = Location::Register(register);
+            active.push((end, node, register));
+            continue;
+        }
+
+        let (furthest, &(furthest_end, furthest_node, register)) = active.iter()
+            .enumerate()
+            .max_by_key(|&(_, &(active_end, _, _))| active_end)
+            .unwrap();
+        locations[node] = Location::Stack(spill_slots);
+        if furthest_end > end {
+            locations[furthest_node] = Location::Stack(spill_slots);
+            locations[node] = Location::Register(register);
+            active[furthest] = (end, node, register);
+        }
+        spill_slots += 1;
+    }
+
+    Allocation { locations, spill_slots }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use class::reader;
+    use runtime::class::ClassTable;
+    use runtime::class::method::RuntimeMethod;
+    use runtime::interpreter;
+    use runtime::jit::{lower, ssa};
+
+    #[test]
+    fn spills_when_registers_run_out() {
+        let class_table = ClassTable::new();
+        class_table.set_compile_threshold(0);
+        let class = class_table.define_class(&reader::read_class_file(include_bytes!("../../../fixtures/Jit.class")).unwrap()).unwrap();
+        assert!(interpreter::initialize_class(&class, &class_table).unwrap().is_none());
+
+        let method = class.get_declared_method("manyValues", "(II)I").unwrap();
+        let lowered = lower(&class, method, &class_table, &mut vec![method as *const RuntimeMethod], true).unwrap();
+        let mut function = ssa::build(&class, method, &lowered, &class_table).unwrap();
+        ssa::optimize(&mut function);
+        let order = function.order();
+        let allocation = allocate(&function, &order);
+
+        assert!(allocation.spill_slots > 0);
+        let registers: HashSet<u8> = allocation.locations.iter()
+            .filter_map(|location| match *location {
+                Location::Register(register) => Some(register),
+                _ => None
+            })
+            .collect();
+        assert_eq!(registers.len(), REGISTERS.len());
+    }
+
+}
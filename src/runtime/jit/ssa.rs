@@ -0,0 +1,1170 @@
+use code::instruction::Instruction;
+use runtime::class::{RuntimeClass, ClassTable};
+use runtime::class::method::RuntimeMethod;
+use runtime::jit::{Lowered, Operation, CallTarget, BinaryOperation, Condition, Narrowing, Kind, Guard, lower, resolve_static, stack_depths};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+
+// The intermediate representation of the optimizing tier. A method is turned into a control flow
+// graph of basic blocks, each holding nodes in static single assignment form: every node is an
+// int or a reference defined once, and values that merge where control flow does are phis.
+//
+// Nodes that may deoptimize carry the interpreter frame at their pc, made of the values of the
+// locals and the operand stack, so that the interpreter can carry on from there.
+
+pub type NodeId = usize;
+pub type BlockId = usize;
+
+// Small methods are inlined into their callers, up to this many operations each, this many calls
+// deep and this many operations in total. Virtual calls are only inlined when a single method can
+// be selected, see Dependencies::select.
+const MAX_INLINE_SIZE: usize = 32;
+const MAX_INLINE_DEPTH: usize = 3;
+const MAX_INLINED_OPERATIONS: usize = 256;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrameState {
+    pub pc: u16,
+    pub locals: Vec<NodeId>,
+    pub stack: Vec<NodeId>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Node {
+    Parameter(usize, Kind),
+    // Constants and null belong to no block.
+    Constant(i32),
+    Null,
+    // Has one operand for each predecessor of its block, in the same order.
+    Phi(Vec<NodeId>),
+    Binary(BinaryOperation, NodeId, NodeId),
+    Negate(NodeId),
+    Narrow(Narrowing, NodeId),
+    // Reads the field at a position of an object, which is checked not to be null.
+    GetField(NodeId, usize, Kind),
+    ArrayLength(NodeId),
+    // Reads a component of an array at an index, both checked beforehand.
+    ArrayLoad(NodeId, NodeId, Kind),
+    Call { target: CallTarget, arguments: Vec<NodeId>, returns_void: bool, state: FrameState },
+    // Deoptimizes on a zero divisor, so that the interpreter throws ArithmeticException.
+    CheckNonZero(NodeId, FrameState),
+    // Deoptimizes on null, so that the interpreter throws NullPointerException.
+    CheckNonNull(NodeId, FrameState),
+    // Deoptimizes unless 0 <= index < length, so that the interpreter throws
+    // ArrayIndexOutOfBoundsException.
+    CheckBounds(NodeId, NodeId, FrameState),
+    // Deoptimizes unless the object is an instance of the class at this address, for which an
+    // inlined virtual call was selected.
+    CheckClass(NodeId, usize, FrameState),
+    // Deoptimizes if the flag at this address is set: a class was loaded that selects another
+    // method for an inlined virtual call.
+    CheckAssumption(usize, FrameState),
+    // Deoptimizes if a garbage collection is waiting, as backward branches in the interpreter do.
+    Safepoint(FrameState),
+    Removed
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Terminator {
+    Jump(BlockId),
+    Branch { condition: Condition, left: NodeId, right: NodeId, then: BlockId, otherwise: BlockId },
+    Return(Option<NodeId>),
+    // The block is unreachable.
+    None
+}
+
+#[derive(Clone, Debug)]
+pub struct Block {
+    pub phis: Vec<NodeId>,
+    pub nodes: Vec<NodeId>,
+    pub terminator: Terminator,
+    pub predecessors: Vec<BlockId>
+}
+
+#[derive(Debug)]
+pub struct Function {
+    pub nodes: Vec<Node>,
+    pub blocks: Vec<Block>,
+    // The flags of the assumptions the function makes, see CheckAssumption.
+    pub invalidated: Vec<Arc<AtomicBool>>,
+    constants: HashMap<i32, NodeId>,
+    null: Option<NodeId>
+}
+
+impl Function {
+
+    fn new() -> Function {
+        Function { nodes: Vec::new(), blocks: Vec::new(), invalidated: Vec::new(), constants: HashMap::new(), null: None }
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push(Block { phis: Vec::new(), nodes: Vec::new(), terminator: Terminator::None, predecessors: Vec::new() });
+        self.blocks.len() - 1
+    }
+
+    fn add(&mut self, node: Node) -> NodeId {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    fn append(&mut self, block: BlockId, node: Node) -> NodeId {
+        let id = self.add(node);
+        self.blocks[block].nodes.push(id);
+        id
+    }
+
+    pub fn constant(&mut self, value: i32) -> NodeId {
+        if let Some(&id) = self.constants.get(&value) {
+            return id;
+        }
+        let id = self.add(Node::Constant(value));
+        self.constants.insert(value, id);
+        id
+    }
+
+    pub fn null(&mut self) -> NodeId {
+        if let Some(null) = self.null {
+            return null;
+        }
+        let null = self.add(Node::Null);
+        self.null = Some(null);
+        null
+    }
+
+    // The kind of the value of each node. A phi holds a reference if any of its operands does.
+    pub fn kinds(&self) -> Vec<Kind> {
+        let mut kinds: Vec<Kind> = self.nodes.iter()
+            .map(|node| match *node {
+                Node::Parameter(_, kind) | Node::GetField(_, _, kind) | Node::ArrayLoad(_, _, kind) => kind,
+                Node::Null => Kind::Reference,
+                _ => Kind::Int
+            })
+            .collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (phi, node) in self.nodes.iter().enumerate() {
+                if let Node::Phi(ref operands) = *node {
+                    if kinds[phi] == Kind::Int && operands.iter().any(|&operand| kinds[operand] == Kind::Reference) {
+                        kinds[phi] = Kind::Reference;
+                        changed = true;
+                    }
+                }
+            }
+        }
+        kinds
+    }
+
+    pub fn constant_value(&self, node: NodeId) -> Option<i32> {
+        match self.nodes[node] {
+            Node::Constant(value) => Some(value),
+            _ => None
+        }
+    }
+
+    fn set_terminator(&mut self, block: BlockId, terminator: Terminator) {
+        for successor in successors(&terminator) {
+            self.blocks[successor].predecessors.push(block);
+        }
+        self.blocks[block].terminator = terminator;
+    }
+
+    pub fn successors(&self, block: BlockId) -> Vec<BlockId> {
+        successors(&self.blocks[block].terminator)
+    }
+
+    // The values a node uses, including those in its frame state. Phi operands are used at the
+    // end of the predecessors rather than by the phi itself, so they are not included.
+    pub fn uses(&self, node: NodeId) -> Vec<NodeId> {
+        match self.nodes[node] {
+            Node::Binary(_, left, right) | Node::ArrayLoad(left, right, _) => vec![left, right],
+            Node::Negate(value) | Node::Narrow(_, value) | Node::GetField(value, ..) | Node::ArrayLength(value) => vec![value],
+            Node::Call { ref arguments, ref state, .. } => arguments.iter().chain(state_values(state)).cloned().collect(),
+            Node::CheckNonZero(value, ref state) | Node::CheckNonNull(value, ref state) | Node::CheckClass(value, _, ref state) => {
+                Some(&value).into_iter().chain(state_values(state)).cloned().collect()
+            },
+            Node::CheckBounds(index, length, ref state) => [index, length].iter().chain(state_values(state)).cloned().collect(),
+            Node::CheckAssumption(_, ref state) | Node::Safepoint(ref state) => state_values(state).cloned().collect(),
+            Node::Parameter(..) | Node::Constant(_) | Node::Null | Node::Phi(_) | Node::Removed => Vec::new()
+        }
+    }
+
+    pub fn terminator_uses(&self, block: BlockId) -> Vec<NodeId> {
+        match self.blocks[block].terminator {
+            Terminator::Branch { left, right, .. } => vec![left, right],
+            Terminator::Return(Some(value)) => vec![value],
+            _ => Vec::new()
+        }
+    }
+
+    // The operand a phi of the block takes from the given predecessor.
+    pub fn phi_operand(&self, phi: NodeId, block: BlockId, predecessor: BlockId) -> NodeId {
+        let index = self.blocks[block].predecessors.iter().position(|&p| p == predecessor).unwrap();
+        match self.nodes[phi] {
+            Node::Phi(ref operands) => operands[index],
+            _ => phi
+        }
+    }
+
+    // The reachable blocks, in reverse postorder.
+    pub fn order(&self) -> Vec<BlockId> {
+        let mut visited = vec![false; self.blocks.len()];
+        let mut postorder = Vec::new();
+        let mut pending = vec![(0, false)];
+        while let Some((block, done)) = pending.pop() {
+            if done {
+                postorder.push(block);
+                continue;
+            }
+            if visited[block] {
+                continue;
+            }
+            visited[block] = true;
+            pending.push((block, true));
+            for successor in self.successors(block).into_iter().rev() {
+                if !visited[successor] {
+                    pending.push((successor, false));
+                }
+            }
+        }
+        postorder.reverse();
+        postorder
+    }
+
+    // Makes every use of old a use of new, and removes old.
+    fn replace(&mut self, old: NodeId, new: NodeId) {
+        let substitute = |node: &mut NodeId| if *node == old { *node = new };
+        let substitute_state = |state: &mut FrameState| state.locals.iter_mut().chain(state.stack.iter_mut()).for_each(substitute);
+        for node in self.nodes.iter_mut() {
+            match *node {
+                Node::Phi(ref mut operands) => operands.iter_mut().for_each(substitute),
+                Node::Binary(_, ref mut left, ref mut right) | Node::ArrayLoad(ref mut left, ref mut right, _) => {
+                    substitute(left);
+                    substitute(right);
+                },
+                Node::Negate(ref mut value) | Node::Narrow(_, ref mut value) | Node::GetField(ref mut value, ..) | Node::ArrayLength(ref mut value) => {
+                    substitute(value)
+                },
+                Node::Call { ref mut arguments, ref mut state, .. } => {
+                    arguments.iter_mut().for_each(substitute);
+                    substitute_state(state);
+                },
+                Node::CheckNonZero(ref mut value, ref mut state) | Node::CheckNonNull(ref mut value, ref mut state) | Node::CheckClass(ref mut value, _, ref mut state) => {
+                    substitute(value);
+                    substitute_state(state);
+                },
+                Node::CheckBounds(ref mut index, ref mut length, ref mut state) => {
+                    substitute(index);
+                    substitute(length);
+                    substitute_state(state);
+                },
+                Node::CheckAssumption(_, ref mut state) | Node::Safepoint(ref mut state) => substitute_state(state),
+                Node::Parameter(..) | Node::Constant(_) | Node::Null | Node::Removed => {}
+            }
+        }
+        for block in self.blocks.iter_mut() {
+            match block.terminator {
+                Terminator::Branch { ref mut left, ref mut right, .. } => {
+                    substitute(left);
+                    substitute(right);
+                },
+                Terminator::Return(Some(ref mut value)) => substitute(value),
+                _ => {}
+            }
+        }
+        self.remove(old);
+    }
+
+    fn remove(&mut self, node: NodeId) {
+        self.nodes[node] = Node::Removed;
+        for block in self.blocks.iter_mut() {
+            block.phis.retain(|&phi| phi != node);
+            block.nodes.retain(|&other| other != node);
+        }
+    }
+
+    // Removes one edge from block to successor, along with the phi operands that came with it.
+    fn remove_edge(&mut self, block: BlockId, successor: BlockId) {
+        let index = match self.blocks[successor].predecessors.iter().position(|&p| p == block) {
+            Some(index) => index,
+            None => return
+        };
+        self.blocks[successor].predecessors.remove(index);
+        for &phi in self.blocks[successor].phis.iter() {
+            if let Node::Phi(ref mut operands) = self.nodes[phi] {
+                operands.remove(index);
+            }
+        }
+    }
+
+}
+
+fn successors(terminator: &Terminator) -> Vec<BlockId> {
+    match *terminator {
+        Terminator::Jump(target) => vec![target],
+        Terminator::Branch { then, otherwise, .. } => vec![then, otherwise],
+        Terminator::Return(_) | Terminator::None => Vec::new()
+    }
+}
+
+fn state_values(state: &FrameState) -> impl Iterator<Item = &NodeId> {
+    state.locals.iter().chain(state.stack.iter())
+}
+
+pub fn evaluate(operation: BinaryOperation, left: i32, right: i32) -> Option<i32> {
+    Some(match operation {
+        BinaryOperation::Add => left.wrapping_add(right),
+        BinaryOperation::Subtract => left.wrapping_sub(right),
+        BinaryOperation::Multiply => left.wrapping_mul(right),
+        BinaryOperation::Divide if right == 0 => return None,
+        BinaryOperation::Divide => left.wrapping_div(right),
+        BinaryOperation::Remainder if right == 0 => return None,
+        BinaryOperation::Remainder => left.wrapping_rem(right),
+        BinaryOperation::And => left & right,
+        BinaryOperation::Or => left | right,
+        BinaryOperation::Xor => left ^ right,
+        BinaryOperation::ShiftLeft => left.wrapping_shl(right as u32),
+        BinaryOperation::ShiftRight => left.wrapping_shr(right as u32),
+        BinaryOperation::UnsignedShiftRight => (left as u32).wrapping_shr(right as u32) as i32
+    })
+}
+
+fn compare(condition: Condition, left: i32, right: i32) -> bool {
+    match condition {
+        Condition::Equal => left == right,
+        Condition::NotEqual => left != right,
+        Condition::Less => left < right,
+        Condition::GreaterOrEqual => left >= right,
+        Condition::Greater => left > right,
+        Condition::LessOrEqual => left <= right
+    }
+}
+
+fn narrow(narrowing: Narrowing, value: i32) -> i32 {
+    match narrowing {
+        Narrowing::Byte => value as i8 as i32,
+        Narrowing::Character => value as u16 as i32,
+        Narrowing::Short => value as i16 as i32
+    }
+}
+
+// The values of the locals and the operand stack while a body is being built.
+#[derive(Clone)]
+struct State {
+    locals: Vec<NodeId>,
+    stack: Vec<NodeId>
+}
+
+#[derive(Clone, Copy)]
+enum Slot {
+    Local(usize),
+    Stack(usize)
+}
+
+struct Builder<'a> {
+    function: Function,
+    class_table: &'a ClassTable,
+    // The state each block leaves to its successors.
+    exit_states: HashMap<BlockId, State>,
+    // The phis whose operands are filled in once every block is built.
+    pending_phis: Vec<(NodeId, BlockId, Slot)>,
+    inlined_operations: usize
+}
+
+// A method inlined at a call, and what must hold for the call to select it.
+struct Inlinee {
+    class: Arc<RuntimeClass>,
+    name: String,
+    descriptor: String,
+    lowered: Lowered,
+    guard: Guard
+}
+
+// Builds the graph of a lowered method, inlining the small methods it calls.
+pub fn build(class: &Arc<RuntimeClass>, method: &RuntimeMethod, lowered: &Lowered, class_table: &ClassTable) -> Option<Function> {
+    let mut builder = Builder {
+        function: Function::new(),
+        class_table,
+        exit_states: HashMap::new(),
+        pending_phis: Vec::new(),
+        inlined_operations: 0
+    };
+
+    let entry = builder.function.new_block();
+    let parameters = lowered.parameters.iter()
+        .enumerate()
+        .map(|(parameter, &kind)| builder.function.append(entry, Node::Parameter(parameter, kind)))
+        .collect();
+    let returns = builder.body(class, method, lowered, parameters, entry, None, 0)?;
+    for (block, value) in returns {
+        builder.function.set_terminator(block, Terminator::Return(value));
+    }
+
+    for (phi, block, slot) in builder.pending_phis.iter() {
+        let operands = builder.function.blocks[*block].predecessors.iter()
+            .map(|predecessor| {
+                let state = &builder.exit_states[predecessor];
+                match *slot {
+                    Slot::Local(index) => state.locals[index],
+                    Slot::Stack(index) => state.stack[index]
+                }
+            })
+            .collect();
+        builder.function.nodes[*phi] = Node::Phi(operands);
+    }
+
+    Some(builder.function)
+}
+
+impl<'a> Builder<'a> {
+
+    // Builds the blocks of a method body, entered from the predecessor block with the given
+    // arguments. An inlined body deoptimizes to the frame of the outermost call, since the
+    // interpreter can simply run the call again. Returns the blocks that return, with their
+    // results, which the caller connects to wherever the body returns to.
+    #[allow(clippy::too_many_arguments)]
+    fn body(&mut self,
+            class: &Arc<RuntimeClass>,
+            method: &RuntimeMethod,
+            lowered: &Lowered,
+            arguments: Vec<NodeId>,
+            predecessor: BlockId,
+            caller_state: Option<&FrameState>,
+            depth: usize) -> Option<Vec<(BlockId, Option<NodeId>)>> {
+        let depths = stack_depths(lowered)?;
+        let operations = &lowered.operations;
+        let positions: HashMap<u16, usize> = operations.iter()
+            .enumerate()
+            .map(|(position, &(pc, _))| (pc, position))
+            .collect();
+
+        let mut inlined = HashMap::new();
+        for (position, &(pc, operation)) in operations.iter().enumerate() {
+            if depths[position].is_some() && matches!(operation, Operation::Call { target: CallTarget::Address(_), .. } | Operation::CallVirtual { .. }) {
+                if let Some(callee) = self.inlinee(class, method, pc, depth) {
+                    inlined.insert(position, callee);
+                }
+            }
+        }
+
+        // Blocks start at branch targets, after branches and after inlined calls.
+        let mut leaders = HashSet::new();
+        leaders.insert(0);
+        for (position, &(_, operation)) in operations.iter().enumerate() {
+            if depths[position].is_none() {
+                continue;
+            }
+            if let Some(target) = operation.branch_target() {
+                leaders.insert(positions[&target]);
+            }
+            if operation.branch_target().is_some() || !operation.falls_through() || inlined.contains_key(&position) {
+                leaders.insert(position + 1);
+            }
+        }
+        let blocks: HashMap<usize, BlockId> = leaders.iter()
+            .filter(|&&leader| leader < operations.len() && depths[leader].is_some())
+            .map(|&leader| (leader, self.function.new_block()))
+            .collect();
+
+        // The number of edges into each block decides whether it needs phis. The end of an inlined
+        // call always gets them, since its edges are only known after the callee is built.
+        let mut incoming: HashMap<usize, usize> = HashMap::new();
+        *incoming.entry(0).or_insert(0) += 1;
+        for (position, &(_, operation)) in operations.iter().enumerate() {
+            if depths[position].is_none() {
+                continue;
+            }
+            if let Some(target) = operation.branch_target() {
+                *incoming.entry(positions[&target]).or_insert(0) += 1;
+            }
+            if inlined.contains_key(&position) {
+                incoming.insert(position + 1, usize::MAX);
+            } else if operation.falls_through() && blocks.contains_key(&(position + 1)) {
+                *incoming.entry(position + 1).or_insert(0) += 1;
+            }
+        }
+
+        let mut locals = arguments;
+        let zero = self.function.constant(0);
+        locals.resize(lowered.max_locals, zero);
+        self.exit_states.insert(predecessor, State { locals, stack: Vec::new() });
+        self.function.set_terminator(predecessor, Terminator::Jump(blocks[&0]));
+
+        let mut returns = Vec::new();
+        for leader in block_order(operations, &positions, &blocks, &inlined) {
+            let block = blocks[&leader];
+            let mut state = if incoming[&leader] == 1 {
+                let predecessor = self.function.blocks[block].predecessors[0];
+                self.exit_states[&predecessor].clone()
+            } else {
+                let stack_depth = depths[leader].unwrap();
+                let slots = (0..lowered.max_locals).map(Slot::Local).chain((0..stack_depth).map(Slot::Stack));
+                let mut state = State { locals: Vec::new(), stack: Vec::new() };
+                for slot in slots {
+                    let phi = self.function.add(Node::Phi(Vec::new()));
+                    self.function.blocks[block].phis.push(phi);
+                    self.pending_phis.push((phi, block, slot));
+                    match slot {
+                        Slot::Local(_) => state.locals.push(phi),
+                        Slot::Stack(_) => state.stack.push(phi)
+                    }
+                }
+                state
+            };
+
+            let mut position = leader;
+            let mut inlined_call = false;
+            loop {
+                let (pc, operation) = operations[position];
+                let frame_state = match caller_state {
+                    Some(caller_state) => caller_state.clone(),
+                    None => FrameState { pc, locals: state.locals.clone(), stack: state.stack.clone() }
+                };
+                let target_block = |target: u16| blocks[&positions[&target]];
+                let backward = operation.branch_target().is_some_and(|target| target <= pc);
+                if backward {
+                    self.function.append(block, Node::Safepoint(frame_state.clone()));
+                }
+
+                match operation {
+                    Operation::Constant(value) => {
+                        let constant = self.function.constant(value);
+                        state.stack.push(constant);
+                    },
+                    Operation::Load(index) => state.stack.push(state.locals[index]),
+                    Operation::Store(index) => state.locals[index] = state.stack.pop()?,
+                    Operation::Increment(index, constant) => {
+                        let constant = self.function.constant(constant);
+                        state.locals[index] = self.function.append(block, Node::Binary(BinaryOperation::Add, state.locals[index], constant));
+                    },
+                    Operation::Binary(operation) => {
+                        let right = state.stack.pop()?;
+                        let left = state.stack.pop()?;
+                        if operation == BinaryOperation::Divide || operation == BinaryOperation::Remainder {
+                            self.function.append(block, Node::CheckNonZero(right, frame_state));
+                        }
+                        state.stack.push(self.function.append(block, Node::Binary(operation, left, right)));
+                    },
+                    Operation::Negate => {
+                        let value = state.stack.pop()?;
+                        state.stack.push(self.function.append(block, Node::Negate(value)));
+                    },
+                    Operation::Narrow(narrowing) => {
+                        let value = state.stack.pop()?;
+                        state.stack.push(self.function.append(block, Node::Narrow(narrowing, value)));
+                    },
+                    Operation::If(condition, target) => {
+                        let left = state.stack.pop()?;
+                        let terminator = Terminator::Branch { condition, left, right: zero, then: target_block(target), otherwise: blocks[&(position + 1)] };
+                        self.function.set_terminator(block, terminator);
+                        break;
+                    },
+                    Operation::IfCompare(condition, target) => {
+                        let right = state.stack.pop()?;
+                        let left = state.stack.pop()?;
+                        let terminator = Terminator::Branch { condition, left, right, then: target_block(target), otherwise: blocks[&(position + 1)] };
+                        self.function.set_terminator(block, terminator);
+                        break;
+                    },
+                    Operation::Goto(target) => {
+                        self.function.set_terminator(block, Terminator::Jump(target_block(target)));
+                        break;
+                    },
+                    Operation::Call { arguments, .. } | Operation::CallVirtual { arguments, .. } => {
+                        let arguments = state.stack.split_off(state.stack.len().checked_sub(arguments)?);
+                        if let Some(inlinee) = inlined.get(&position) {
+                            // The interpreter makes the call if the guard fails.
+                            match inlinee.guard {
+                                Guard::None => {},
+                                Guard::Class(ref class) => {
+                                    self.function.append(block, Node::CheckClass(arguments[0], Arc::as_ptr(class) as usize, frame_state.clone()));
+                                },
+                                Guard::Assumption(ref invalidated) => {
+                                    self.function.append(block, Node::CheckNonNull(arguments[0], frame_state.clone()));
+                                    self.function.append(block, Node::CheckAssumption(Arc::as_ptr(invalidated) as usize, frame_state.clone()));
+                                    self.function.invalidated.push(invalidated.clone());
+                                }
+                            }
+                            let callee = inlinee.class.get_declared_method(&inlinee.name, &inlinee.descriptor)?;
+                            let callee_returns = self.body(&inlinee.class, callee, &inlinee.lowered, arguments, block, Some(&frame_state), depth + 1)?;
+                            let continuation = blocks[&(position + 1)];
+                            for (return_block, value) in callee_returns {
+                                let mut after = state.clone();
+                                after.stack.extend(value);
+                                self.exit_states.insert(return_block, after);
+                                self.function.set_terminator(return_block, Terminator::Jump(continuation));
+                            }
+                            // The body set the terminator and exit state of this block.
+                            inlined_call = true;
+                            break;
+                        }
+                        let (target, returns_void) = match operation {
+                            Operation::Call { target, returns_void, .. } => (target, returns_void),
+                            // A virtual call that is not inlined leaves the method to the
+                            // interpreter.
+                            _ => return None
+                        };
+                        let call = self.function.append(block, Node::Call { target, arguments, returns_void, state: frame_state });
+                        if !returns_void {
+                            state.stack.push(call);
+                        }
+                    },
+                    Operation::Pop => {
+                        state.stack.pop()?;
+                    },
+                    Operation::Dup => {
+                        let top = *state.stack.last()?;
+                        state.stack.push(top);
+                    },
+                    Operation::Return => {
+                        returns.push((block, None));
+                        break;
+                    },
+                    Operation::ReturnInt => {
+                        returns.push((block, Some(state.stack.pop()?)));
+                        break;
+                    },
+                    Operation::Nop => {},
+                    Operation::Null => {
+                        let null = self.function.null();
+                        state.stack.push(null);
+                    },
+                    Operation::GetField { position, kind } => {
+                        let object = state.stack.pop()?;
+                        self.function.append(block, Node::CheckNonNull(object, frame_state));
+                        state.stack.push(self.function.append(block, Node::GetField(object, position, kind)));
+                    },
+                    Operation::ArrayLength => {
+                        let array = state.stack.pop()?;
+                        self.function.append(block, Node::CheckNonNull(array, frame_state));
+                        state.stack.push(self.function.append(block, Node::ArrayLength(array)));
+                    },
+                    Operation::ArrayLoad(kind) => {
+                        let index = state.stack.pop()?;
+                        let array = state.stack.pop()?;
+                        self.function.append(block, Node::CheckNonNull(array, frame_state.clone()));
+                        let length = self.function.append(block, Node::ArrayLength(array));
+                        self.function.append(block, Node::CheckBounds(index, length, frame_state));
+                        state.stack.push(self.function.append(block, Node::ArrayLoad(array, index, kind)));
+                    }
+                }
+
+                position += 1;
+                if blocks.contains_key(&position) {
+                    self.function.set_terminator(block, Terminator::Jump(blocks[&position]));
+                    break;
+                }
+            }
+            if !inlined_call {
+                self.exit_states.insert(block, state);
+            }
+        }
+
+        Some(returns)
+    }
+
+    // Decides whether the method called at pc is small enough to inline, and for a virtual call
+    // whether it is the only one the call can select. Callees that call themselves are not
+    // inlined, and neither is anything once enough has been, or while profiling, which counts the
+    // invocations of every method.
+    fn inlinee(&mut self, class: &Arc<RuntimeClass>, method: &RuntimeMethod, pc: u16, depth: usize) -> Option<Inlinee> {
+        if depth >= MAX_INLINE_DEPTH || self.class_table.profile.is_enabled() {
+            return None;
+        }
+        let code = method.code.as_ref()?;
+        let (callee_class, name, descriptor, guard) = match code.instructions.get(code.instruction_position(pc)?)?.instruction {
+            Instruction::Invokestatic { index } => {
+                let (callee_class, name, descriptor) = resolve_static(class, index, self.class_table)?;
+                (callee_class, name, descriptor, Guard::None)
+            },
+            Instruction::Invokevirtual { index } => {
+                let method_ref = class.constant_pool.get_method_ref(index).ok()?;
+                let name = method_ref.name_and_type.name.clone();
+                let descriptor = method_ref.name_and_type.descriptor.clone();
+                let (callee_class, guard) = self.class_table.dependencies.select(self.class_table, &method_ref.class_name, &name, &descriptor)?;
+                (callee_class, name, descriptor, guard)
+            },
+            _ => return None
+        };
+        let callee = callee_class.get_declared_method(&name, &descriptor)?;
+        if callee.is_synchronized() || callee.is_static() != matches!(guard, Guard::None) {
+            return None;
+        }
+
+        let mut in_progress = vec![callee as *const RuntimeMethod];
+        let lowered = lower(&callee_class, callee, self.class_table, &mut in_progress, true)?;
+        let recursive = lowered.operations.iter().any(|&(_, operation)| matches!(operation, Operation::Call { target: CallTarget::Itself, .. }));
+        if recursive || lowered.operations.len() > MAX_INLINE_SIZE || self.inlined_operations + lowered.operations.len() > MAX_INLINED_OPERATIONS {
+            return None;
+        }
+
+        self.inlined_operations += lowered.operations.len();
+        Some(Inlinee { class: callee_class, name, descriptor, lowered, guard })
+    }
+
+}
+
+// The leaders of the blocks of a body in reverse postorder, so that a block with a single
+// predecessor is built after it.
+fn block_order<T>(operations: &[(u16, Operation)],
+                  positions: &HashMap<u16, usize>,
+                  blocks: &HashMap<usize, BlockId>,
+                  inlined: &HashMap<usize, T>) -> Vec<usize> {
+    let successors = |leader: usize| {
+        let mut position = leader;
+        while !blocks.contains_key(&(position + 1)) && operations[position].1.falls_through() && operations[position].1.branch_target().is_none() && !inlined.contains_key(&position) {
+            position += 1;
+        }
+        let operation = operations[position].1;
+        let mut successors = Vec::new();
+        if let Some(target) = operation.branch_target() {
+            successors.push(positions[&target]);
+        }
+        if operation.falls_through() {
+            successors.push(position + 1);
+        }
+        successors
+    };
+
+    let mut visited = HashSet::new();
+    let mut postorder = Vec::new();
+    let mut pending = vec![(0, false)];
+    while let Some((leader, done)) = pending.pop() {
+        if done {
+            postorder.push(leader);
+            continue;
+        }
+        if !visited.insert(leader) {
+            continue;
+        }
+        pending.push((leader, true));
+        for successor in successors(leader).into_iter().rev() {
+            if !visited.contains(&successor) {
+                pending.push((successor, false));
+            }
+        }
+    }
+    postorder.reverse();
+    postorder
+}
+
+// Simplifies the graph until nothing changes: folds constants, removes branches that always go
+// one way along with the blocks that become unreachable, removes phis that merge a single value,
+// checks that cannot fail and nodes whose values are never used. Finally, splits the edges from
+// blocks with several successors to blocks with several predecessors, so that phi moves have a
+// block to go in.
+pub fn optimize(function: &mut Function) {
+    loop {
+        let mut changed = fold_constants(function);
+        changed |= fold_branches(function);
+        changed |= remove_unreachable_blocks(function);
+        changed |= remove_trivial_phis(function);
+        changed |= remove_redundant_checks(function);
+        changed |= remove_dead_nodes(function);
+        if !changed {
+            break;
+        }
+    }
+    split_critical_edges(function);
+}
+
+fn fold_constants(function: &mut Function) -> bool {
+    let mut changed = false;
+    for block in 0..function.blocks.len() {
+        for node in function.blocks[block].nodes.clone() {
+            let constant = |value: NodeId| function.constant_value(value);
+            let folded = match function.nodes[node] {
+                Node::Binary(operation, left, right) => match (constant(left), constant(right)) {
+                    (Some(left), Some(right)) => evaluate(operation, left, right).map(Err),
+                    (_, Some(0)) if matches!(operation, BinaryOperation::Add | BinaryOperation::Subtract | BinaryOperation::Or | BinaryOperation::Xor
+                        | BinaryOperation::ShiftLeft | BinaryOperation::ShiftRight | BinaryOperation::UnsignedShiftRight) => Some(Ok(left)),
+                    (_, Some(1)) if matches!(operation, BinaryOperation::Multiply | BinaryOperation::Divide) => Some(Ok(left)),
+                    (Some(0), _) if matches!(operation, BinaryOperation::Add | BinaryOperation::Or | BinaryOperation::Xor) => Some(Ok(right)),
+                    (Some(1), _) if operation == BinaryOperation::Multiply => Some(Ok(right)),
+                    (Some(0), _) | (_, Some(0)) if matches!(operation, BinaryOperation::Multiply | BinaryOperation::And) => Some(Err(0)),
+                    _ => None
+                },
+                Node::Negate(value) => constant(value).map(|value| Err(value.wrapping_neg())),
+                Node::Narrow(narrowing, value) => constant(value).map(|value| Err(narrow(narrowing, value))),
+                // A check that cannot fail folds to nothing, which is what the check itself
+                // stands for.
+                Node::CheckNonZero(value, _) if constant(value).is_some_and(|value| value != 0) => {
+                    function.remove(node);
+                    changed = true;
+                    continue;
+                },
+                _ => None
+            };
+
+            // Ok is another node with the same value, Err a constant.
+            let replacement = match folded {
+                Some(Ok(replacement)) => replacement,
+                Some(Err(value)) => function.constant(value),
+                None => continue
+            };
+            function.replace(node, replacement);
+            changed = true;
+        }
+    }
+    changed
+}
+
+fn fold_branches(function: &mut Function) -> bool {
+    let mut changed = false;
+    for block in 0..function.blocks.len() {
+        let (taken, not_taken) = match function.blocks[block].terminator {
+            Terminator::Branch { then, otherwise, .. } if then == otherwise => (then, otherwise),
+            Terminator::Branch { condition, left, right, then, otherwise } => {
+                match (function.constant_value(left), function.constant_value(right)) {
+                    (Some(left), Some(right)) if compare(condition, left, right) => (then, otherwise),
+                    (Some(_), Some(_)) => (otherwise, then),
+                    _ => continue
+                }
+            },
+            _ => continue
+        };
+        function.remove_edge(block, not_taken);
+        function.blocks[block].terminator = Terminator::Jump(taken);
+        changed = true;
+    }
+    changed
+}
+
+fn remove_unreachable_blocks(function: &mut Function) -> bool {
+    let mut reachable = vec![false; function.blocks.len()];
+    for block in function.order() {
+        reachable[block] = true;
+    }
+
+    let mut changed = false;
+    for (block, &reachable) in reachable.iter().enumerate() {
+        if reachable || (function.blocks[block].terminator == Terminator::None && function.blocks[block].nodes.is_empty()) {
+            continue;
+        }
+        for successor in function.successors(block) {
+            function.remove_edge(block, successor);
+        }
+        let removed = &mut function.blocks[block];
+        for node in removed.phis.drain(..).chain(removed.nodes.drain(..)) {
+            function.nodes[node] = Node::Removed;
+        }
+        removed.terminator = Terminator::None;
+        removed.predecessors.clear();
+        changed = true;
+    }
+    changed
+}
+
+fn remove_trivial_phis(function: &mut Function) -> bool {
+    let mut changed = false;
+    for block in 0..function.blocks.len() {
+        for phi in function.blocks[block].phis.clone() {
+            let value = match function.nodes[phi] {
+                Node::Phi(ref operands) => {
+                    let mut others = operands.iter().filter(|&&operand| operand != phi);
+                    match others.next() {
+                        Some(&first) if others.all(|&operand| operand == first) => first,
+                        _ => continue
+                    }
+                },
+                _ => continue
+            };
+            function.replace(phi, value);
+            changed = true;
+        }
+    }
+    changed
+}
+
+fn remove_dead_nodes(function: &mut Function) -> bool {
+    let mut used = vec![false; function.nodes.len()];
+    for block in 0..function.blocks.len() {
+        for &node in function.blocks[block].nodes.iter() {
+            for value in function.uses(node) {
+                used[value] = true;
+            }
+        }
+        for &phi in function.blocks[block].phis.iter() {
+            if let Node::Phi(ref operands) = function.nodes[phi] {
+                // A phi that only uses itself is still dead.
+                for &operand in operands.iter().filter(|&&operand| operand != phi) {
+                    used[operand] = true;
+                }
+            }
+        }
+        for value in function.terminator_uses(block) {
+            used[value] = true;
+        }
+    }
+
+    let dead: Vec<NodeId> = function.blocks.iter()
+        .flat_map(|block| block.phis.iter().chain(block.nodes.iter()))
+        .filter(|&&node| !used[node] && matches!(function.nodes[node], Node::Phi(_) | Node::Binary(..) | Node::Negate(_) | Node::Narrow(..)
+            | Node::GetField(..) | Node::ArrayLength(_) | Node::ArrayLoad(..)))
+        .cloned()
+        .collect();
+    for &node in dead.iter() {
+        function.remove(node);
+    }
+    !dead.is_empty()
+}
+
+// Removes the checks that an earlier one makes redundant: null and class checks of a value that
+// a check dominating them has made, and bounds checks of an index that a loop keeps in bounds.
+// That is an index compared with the length of the array by the loop condition, which starts at
+// zero or above and only goes up by one while the condition holds, so it cannot overflow.
+fn remove_redundant_checks(function: &mut Function) -> bool {
+    let order = function.order();
+    let dominators = dominators(function, &order);
+    let mut positions = HashMap::new();
+    for &block in order.iter() {
+        for (index, &node) in function.blocks[block].nodes.iter().enumerate() {
+            positions.insert(node, (block, index));
+        }
+    }
+    // Whether the node at the first position runs before the one at the second on every path.
+    let before = |(block, index): (BlockId, usize), (other_block, other_index): (BlockId, usize)| {
+        if block == other_block { index < other_index } else { dominates(&dominators, block, other_block) }
+    };
+
+    // The index, length and first block of each loop body that the condition keeps the index
+    // below the length in.
+    let mut in_bounds = Vec::new();
+    for &block in order.iter() {
+        if let Terminator::Branch { condition, left, right, then, otherwise } = function.blocks[block].terminator {
+            let (index, length, inside) = match condition {
+                Condition::Less => (left, right, then),
+                Condition::GreaterOrEqual => (left, right, otherwise),
+                Condition::Greater => (right, left, then),
+                Condition::LessOrEqual => (right, left, otherwise),
+                _ => continue
+            };
+            if then != otherwise && function.blocks[inside].predecessors == [block] && counts_up(function, index, |node| {
+                positions.get(&node).is_some_and(|&(block, _)| dominates(&dominators, inside, block))
+            }) {
+                in_bounds.push((index, length, inside));
+            }
+        }
+    }
+
+    let same_length = |length: NodeId, other: NodeId| length == other || match (&function.nodes[length], &function.nodes[other]) {
+        (&Node::ArrayLength(array), &Node::ArrayLength(other_array)) => array == other_array,
+        _ => false
+    };
+    let mut redundant = Vec::new();
+    for (&node, &position) in positions.iter() {
+        let checked_before = |check: &dyn Fn(&Node) -> bool| positions.iter()
+            .any(|(&other, &other_position)| check(&function.nodes[other]) && before(other_position, position));
+        let removable = match function.nodes[node] {
+            Node::CheckNonNull(value, _) => checked_before(&|other| matches!(*other, Node::CheckNonNull(other_value, _) if other_value == value)),
+            Node::CheckClass(value, class, _) => checked_before(&|other| matches!(*other, Node::CheckClass(other_value, other_class, _) if other_value == value && other_class == class)),
+            Node::CheckBounds(index, length, _) => in_bounds.iter().any(|&(checked_index, checked_length, inside)| {
+                checked_index == index && same_length(checked_length, length) && dominates(&dominators, inside, position.0)
+            }),
+            _ => false
+        };
+        if removable {
+            redundant.push(node);
+        }
+    }
+
+    for &node in redundant.iter() {
+        function.remove(node);
+    }
+    !redundant.is_empty()
+}
+
+// Whether the value is a phi that starts at zero or above, and otherwise only takes its own value
+// plus one, computed where inside holds.
+fn counts_up<F: Fn(NodeId) -> bool>(function: &Function, value: NodeId, inside: F) -> bool {
+    match function.nodes[value] {
+        Node::Phi(ref operands) => operands.iter().all(|&operand| operand == value || match function.nodes[operand] {
+            Node::Constant(constant) => constant >= 0,
+            Node::Binary(BinaryOperation::Add, left, right) => left == value && function.constant_value(right) == Some(1) && inside(operand),
+            _ => false
+        }),
+        _ => false
+    }
+}
+
+// The immediate dominator of each reachable block, with the entry block as its own.
+fn dominators(function: &Function, order: &[BlockId]) -> Vec<Option<BlockId>> {
+    let mut positions = vec![usize::MAX; function.blocks.len()];
+    for (position, &block) in order.iter().enumerate() {
+        positions[block] = position;
+    }
+
+    let mut dominators = vec![None; function.blocks.len()];
+    dominators[order[0]] = Some(order[0]);
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &block in order.iter().skip(1) {
+            let mut dominator = None;
+            for &predecessor in function.blocks[block].predecessors.iter() {
+                if dominators[predecessor].is_none() {
+                    continue;
+                }
+                dominator = Some(match dominator {
+                    None => predecessor,
+                    Some(mut other) => {
+                        // The closest block that dominates both.
+                        let mut predecessor = predecessor;
+                        while predecessor != other {
+                            while positions[predecessor] > positions[other] {
+                                predecessor = dominators[predecessor].unwrap();
+                            }
+                            while positions[other] > positions[predecessor] {
+                                other = dominators[other].unwrap();
+                            }
+                        }
+                        other
+                    }
+                });
+            }
+            if dominator != dominators[block] {
+                dominators[block] = dominator;
+                changed = true;
+            }
+        }
+    }
+    dominators
+}
+
+fn dominates(dominators: &[Option<BlockId>], dominator: BlockId, mut block: BlockId) -> bool {
+    loop {
+        if block == dominator {
+            return true;
+        }
+        match dominators[block] {
+            Some(parent) if parent != block => block = parent,
+            _ => return false
+        }
+    }
+}
+
+fn split_critical_edges(function: &mut Function) {
+    for block in function.order() {
+        let successors = function.successors(block);
+        if successors.len() < 2 {
+            continue;
+        }
+        for successor in successors {
+            if function.blocks[successor].predecessors.len() < 2 {
+                continue;
+            }
+            let split = function.new_block();
+            function.blocks[split].terminator = Terminator::Jump(successor);
+            function.blocks[split].predecessors.push(block);
+            let index = function.blocks[successor].predecessors.iter().position(|&p| p == block).unwrap();
+            function.blocks[successor].predecessors[index] = split;
+            if let Terminator::Branch { ref mut then, ref mut otherwise, .. } = function.blocks[block].terminator {
+                if *then == successor {
+                    *then = split;
+                } else {
+                    *otherwise = split;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use class::reader;
+    use runtime::interpreter;
+
+    fn graph(name: &str, descriptor: &str) -> Function {
+        graph_of(&[include_bytes!("../../../fixtures/Jit.class")], name, descriptor)
+    }
+
+    // Builds the graph of a method of the last class, once all of them are loaded.
+    fn graph_of(classes: &[&[u8]], name: &str, descriptor: &str) -> Function {
+        let class_table = ClassTable::new();
+        class_table.set_compile_threshold(0);
+        let mut defined = None;
+        for bytes in classes.iter() {
+            defined = Some(class_table.define_class(&reader::read_class_file(bytes).unwrap()).unwrap());
+        }
+        let class = defined.unwrap();
+        assert!(interpreter::initialize_class(&class, &class_table).unwrap().is_none());
+
+        let method = class.get_declared_method(name, descriptor).unwrap();
+        let lowered = lower(&class, method, &class_table, &mut vec![method as *const RuntimeMethod], true).unwrap();
+        let mut function = build(&class, method, &lowered, &class_table).unwrap();
+        optimize(&mut function);
+        function
+    }
+
+    fn live_nodes(function: &Function) -> Vec<&Node> {
+        function.order().into_iter()
+            .flat_map(|block| function.blocks[block].nodes.iter())
+            .map(|&node| &function.nodes[node])
+            .collect()
+    }
+
+    #[test]
+    fn folds_constants_and_branches() {
+        let function = graph("folded", "(I)I");
+        let order = function.order();
+        assert!(order.iter().all(|&block| !matches!(function.blocks[block].terminator, Terminator::Branch { .. })));
+        assert!(!live_nodes(&function).iter().any(|node| matches!(node, Node::CheckNonZero(..))));
+    }
+
+    #[test]
+    fn inlines_small_static_methods() {
+        let function = graph("sumOfSquares", "(I)I");
+        let nodes = live_nodes(&function);
+        assert!(!nodes.iter().any(|node| matches!(node, Node::Call { .. })));
+        assert!(nodes.iter().any(|node| matches!(node, Node::Binary(BinaryOperation::Multiply, ..))));
+        assert!(nodes.iter().any(|node| matches!(node, Node::Safepoint(_))));
+
+        // fib calls itself, which is never inlined.
+        assert!(live_nodes(&graph("fib", "(I)I")).iter().any(|node| matches!(node, Node::Call { target: CallTarget::Itself, .. })));
+    }
+
+    fn inlining_graph(name: &str, descriptor: &str) -> Function {
+        let classes: [&[u8]; 5] = [
+            include_bytes!("../../../fixtures/Inlining$Point.class"),
+            include_bytes!("../../../fixtures/Inlining$Shape.class"),
+            include_bytes!("../../../fixtures/Inlining$Square.class"),
+            include_bytes!("../../../fixtures/Inlining$Rectangle.class"),
+            include_bytes!("../../../fixtures/Inlining.class")
+        ];
+        graph_of(&classes, name, descriptor)
+    }
+
+    #[test]
+    fn guards_inlined_virtual_calls() {
+        let function = inlining_graph("sum", "(LInlining$Point;)I");
+        let nodes = live_nodes(&function);
+        assert!(!nodes.iter().any(|node| matches!(node, Node::Call { .. })));
+        assert!(nodes.iter().any(|node| matches!(node, Node::CheckClass(..))));
+        assert!(function.invalidated.is_empty());
+
+        // Square and Rectangle both inherit sides from Shape.
+        let function = inlining_graph("sides", "(LInlining$Shape;)I");
+        let nodes = live_nodes(&function);
+        assert!(nodes.iter().any(|node| matches!(node, Node::CheckAssumption(..))));
+        assert!(!nodes.iter().any(|node| matches!(node, Node::CheckClass(..))));
+        assert_eq!(function.invalidated.len(), 1);
+    }
+
+    #[test]
+    fn removes_bounds_checks_of_counted_loops() {
+        // The loop condition of sum compares the index with the length of the array, and the
+        // null check in the loop body repeats that of the condition.
+        let function = inlining_graph("sum", "([I)I");
+        let nodes = live_nodes(&function);
+        assert!(!nodes.iter().any(|node| matches!(node, Node::CheckBounds(..))));
+        assert_eq!(nodes.iter().filter(|node| matches!(node, Node::CheckNonNull(..))).count(), 1);
+
+        // That of sumFirst compares it with n.
+        let function = inlining_graph("sumFirst", "([II)I");
+        assert!(live_nodes(&function).iter().any(|node| matches!(node, Node::CheckBounds(..))));
+    }
+
+}
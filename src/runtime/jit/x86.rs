@@ -0,0 +1,65 @@
+use runtime::jit::{BinaryOperation, Condition, Narrowing};
+
+// Machine code sequences shared by both tiers of the JIT.
+
+pub const EAX: u8 = 0;
+pub const ECX: u8 = 1;
+pub const EDX: u8 = 2;
+pub const EBX: u8 = 3;
+pub const RSI: u8 = 6;
+pub const RDI: u8 = 7;
+pub const R12: u8 = 12;
+pub const R13: u8 = 13;
+pub const R14: u8 = 14;
+pub const R15: u8 = 15;
+
+// The second opcode byte of jcc rel32.
+pub fn condition_code(condition: Condition) -> u8 {
+    match condition {
+        Condition::Equal => 0x84,
+        Condition::NotEqual => 0x85,
+        Condition::Less => 0x8c,
+        Condition::GreaterOrEqual => 0x8d,
+        Condition::LessOrEqual => 0x8e,
+        Condition::Greater => 0x8f
+    }
+}
+
+// Computes eax = eax op ecx, clobbering edx. The divisor must not be zero. A divisor of -1 is
+// handled without idiv, which would fault on Integer.MIN_VALUE / -1.
+pub fn binary(code: &mut Vec<u8>, operation: BinaryOperation) {
+    match operation {
+        BinaryOperation::Add => code.extend_from_slice(&[0x01, 0xc8]),
+        BinaryOperation::Subtract => code.extend_from_slice(&[0x29, 0xc8]),
+        BinaryOperation::Multiply => code.extend_from_slice(&[0x0f, 0xaf, 0xc1]),
+        BinaryOperation::And => code.extend_from_slice(&[0x21, 0xc8]),
+        BinaryOperation::Or => code.extend_from_slice(&[0x09, 0xc8]),
+        BinaryOperation::Xor => code.extend_from_slice(&[0x31, 0xc8]),
+        // The shift count in cl is masked to 5 bits, as in Java.
+        BinaryOperation::ShiftLeft => code.extend_from_slice(&[0xd3, 0xe0]),
+        BinaryOperation::ShiftRight => code.extend_from_slice(&[0xd3, 0xf8]),
+        BinaryOperation::UnsignedShiftRight => code.extend_from_slice(&[0xd3, 0xe8]),
+        BinaryOperation::Divide => {
+            // cmp ecx, -1; jne idiv; neg eax; jmp over the 3 bytes of cdq; idiv ecx
+            code.extend_from_slice(&[0x83, 0xf9, 0xff, 0x75, 0x04, 0xf7, 0xd8, 0xeb, 0x03, 0x99, 0xf7, 0xf9]);
+        },
+        BinaryOperation::Remainder => {
+            // cmp ecx, -1; jne idiv; xor eax, eax; jmp over the 5 bytes of cdq; idiv ecx; mov eax, edx
+            code.extend_from_slice(&[0x83, 0xf9, 0xff, 0x75, 0x04, 0x31, 0xc0, 0xeb, 0x05, 0x99, 0xf7, 0xf9, 0x89, 0xd0]);
+        }
+    }
+}
+
+// neg eax
+pub fn negate(code: &mut Vec<u8>) {
+    code.extend_from_slice(&[0xf7, 0xd8]);
+}
+
+// Sign or zero extends the low bits of eax, as i2b, i2c and i2s do.
+pub fn narrow(code: &mut Vec<u8>, narrowing: Narrowing) {
+    match narrowing {
+        Narrowing::Byte => code.extend_from_slice(&[0x0f, 0xbe, 0xc0]),
+        Narrowing::Character => code.extend_from_slice(&[0x0f, 0xb7, 0xc0]),
+        Narrowing::Short => code.extend_from_slice(&[0x0f, 0xbf, 0xc0])
+    }
+}
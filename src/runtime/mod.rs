@@ -1,12 +1,27 @@
 use runtime::class::RuntimeClass;
-use std::cell::RefCell;
-use std::rc::Rc;
+use runtime::interpreter::InterpreterError;
+use runtime::monitor::Monitor;
+use std::fmt;
+use std::slice;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError};
+use std::sync::atomic::{AtomicBool, Ordering};
 
+#[macro_use]
+pub mod bootstrap;
 pub mod class;
+pub mod debugger;
+pub mod frames;
 pub mod gc;
 pub mod jit;
 pub mod interpreter;
+pub mod invokedynamic;
+pub mod jdwp;
+pub mod monitor;
+pub mod profile;
+pub mod sampler;
 pub mod stack;
+pub mod string;
+pub mod trace;
 
 // A StackValue is any data type that can be stored in a variable.
 // In Java, there are two kinds of data types: primitive types and reference types.
@@ -15,58 +30,348 @@ pub mod stack;
 pub enum Value {
     Long(i64),
     Integer(i32),
+    Float(f32),
+    Double(f64),
     Short(i16),
     Byte(i8),
     Character(char),
-    ObjectRef(Rc<RefCell<Object>>),
-    IntegerArrayRef(Rc<RefCell<IntArray>>),
+    ObjectRef(Arc<HeapCell<Object>>),
+    ArrayRef(Arc<HeapCell<Array>>),
     Null
 }
 
+impl Value {
+
+    // Longs and doubles are category 2 computational types (JVMS $2.11.1). They occupy two local
+    // variable slots, and the pop2 and dup2 families treat them as a single value.
+    pub fn is_category2(&self) -> bool {
+        matches!(self, Value::Long(_) | Value::Double(_))
+    }
+
+    pub fn is_reference(&self) -> bool {
+        matches!(self, Value::ObjectRef(_) | Value::ArrayRef(_) | Value::Null)
+    }
+
+    // Reference equality, as tested by if_acmpeq.
+    pub fn same_reference(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::ObjectRef(a), Value::ObjectRef(b)) => Arc::ptr_eq(a, b),
+            (Value::ArrayRef(a), Value::ArrayRef(b)) => Arc::ptr_eq(a, b),
+            (Value::Null, Value::Null) => true,
+            _ => false
+        }
+    }
+
+    // The default value of a field or array component with the given descriptor, see JVMS $2.3
+    // and $2.4.
+    pub fn default_for(descriptor: &str) -> Value {
+        match descriptor.chars().next() {
+            Some('B') | Some('C') | Some('I') | Some('S') | Some('Z') => Value::Integer(0),
+            Some('J') => Value::Long(0),
+            Some('F') => Value::Float(0.0),
+            Some('D') => Value::Double(0.0),
+            _ => Value::Null
+        }
+    }
+
+}
+
+// Objects and arrays live on a heap shared by all threads. Every read of a field or an array
+// component takes the read lock of its cell and every write the write lock, so all accesses to
+// the heap are sequentially consistent. That is stronger than the Java memory model asks for
+// plain fields and as strong as it asks for volatile ones (JLS $17.4), so volatile needs no
+// special treatment. The garbage collector takes the locks too, and only moves the fields of
+// objects it can lock.
+//
+// The monitor is kept outside the lock: a thread blocked in monitorenter or Object.wait must not
+// keep other threads from reading and writing the object.
 #[derive(Debug)]
+pub struct HeapCell<T> {
+    contents: RwLock<T>,
+    monitor: Monitor,
+    // Set when the contents are borrowed mutably, for the collector to find the old objects that
+    // references may have been stored into without a write barrier.
+    written: AtomicBool
+}
+
+impl<T> HeapCell<T> {
+
+    fn new(contents: T) -> Arc<HeapCell<T>> {
+        Arc::new(HeapCell { contents: RwLock::new(contents), monitor: Monitor::default(), written: AtomicBool::new(false) })
+    }
+
+    // A thread that panicked while holding a lock leaves the contents as they were, so poisoning
+    // is ignored.
+    pub fn borrow(&self) -> RwLockReadGuard<'_, T> {
+        self.contents.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn borrow_mut(&self) -> RwLockWriteGuard<'_, T> {
+        self.written.store(true, Ordering::Relaxed);
+        self.contents.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Borrows the contents unless somebody else already does.
+    fn try_borrow_mut(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        match self.contents.try_write() {
+            Ok(contents) => Some(contents),
+            Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+            Err(TryLockError::WouldBlock) => None
+        }
+    }
+
+    pub fn monitor(&self) -> &Monitor {
+        &self.monitor
+    }
+
+}
+
+// The fields of an object and the components of an array are stored on the heap, see gc::Heap,
+// which moves them as it collects garbage.
 pub struct Object {
-    class: Rc<RuntimeClass>, // or perhaps an index into a loaded class table
-    memory: Vec<Value>
+    class: Arc<RuntimeClass>, // or perhaps an index into a loaded class table
+    fields: *mut Value,
+    length: usize,
+    // Whether the fields were moved off the heap into memory of their own, because the heap was
+    // dropped while the object was still referenced.
+    detached: bool
 }
 
+// The fields are only accessed through the cell of the object.
+unsafe impl Send for Object {}
+unsafe impl Sync for Object {}
+
 impl Object {
 
-    pub fn put_field(&mut self, field_name: String, value: Value) {
-        let position = self.class.fields
-            .iter()
-            .position(|field| field.name == field_name)
-            .unwrap();
+    pub fn class(&self) -> &Arc<RuntimeClass> {
+        &self.class
+    }
+
+    // Natives know the fields of the classes they are bound to, so a field looked up by name is
+    // the one declared closest to the class of the object.
+    fn field_position(&self, field_name: &str) -> usize {
+        self.class.instance_field_position(field_name).unwrap()
+    }
+
+    // The values of the instance fields, in the order of RuntimeClass.instance_fields.
+    pub fn fields(&self) -> &[Value] {
+        unsafe { slice::from_raw_parts(self.fields, self.length) }
+    }
+
+    pub fn fields_mut(&mut self) -> &mut [Value] {
+        unsafe { slice::from_raw_parts_mut(self.fields, self.length) }
+    }
 
-        self.memory[position] = value;
+    pub fn put_field(&mut self, field_name: String, value: Value) {
+        let position = self.field_position(&field_name);
+        self.fields_mut()[position] = value;
     }
 
     pub fn get_field(&self, field_name: String) -> Value {
-        let position = self.class.fields
-            .iter()
-            .position(|field| field.name == field_name)
-            .unwrap();
+        let position = self.field_position(&field_name);
+        self.fields()[position].clone()
+    }
 
-        self.memory[position].clone()
+}
+
+// Objects can refer to themselves, directly or through the statics of their class, so only the
+// class name and identity are printed.
+impl fmt::Debug for Object {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}@{:p}", self.class.class_name, self)
     }
+}
 
+pub struct Array {
+    component_type: String,
+    elements: *mut u8,
+    length: usize,
+    detached: bool
+}
+
+// The components are only accessed through the cell of the array.
+unsafe impl Send for Array {}
+unsafe impl Sync for Array {}
+
+impl fmt::Debug for Array {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}[{}]@{:p}", self.component_type, self.len(), self)
+    }
+}
+
+// Arrays of primitives are stored unboxed. Boolean arrays share the byte representation, like
+// they do in HotSpot (baload and bastore operate on both). These are the components of an array
+// before it is allocated on the heap.
+#[derive(Clone, Debug)]
+pub enum ArrayElements {
+    Byte(Vec<i8>),
+    Character(Vec<u16>),
+    Short(Vec<i16>),
+    Integer(Vec<i32>),
+    Long(Vec<i64>),
+    Float(Vec<f32>),
+    Double(Vec<f64>),
+    Reference(Vec<Value>)
+}
+
+impl ArrayElements {
+
+    pub fn len(&self) -> usize {
+        match *self {
+            ArrayElements::Byte(ref a) => a.len(),
+            ArrayElements::Character(ref a) => a.len(),
+            ArrayElements::Short(ref a) => a.len(),
+            ArrayElements::Integer(ref a) => a.len(),
+            ArrayElements::Long(ref a) => a.len(),
+            ArrayElements::Float(ref a) => a.len(),
+            ArrayElements::Double(ref a) => a.len(),
+            ArrayElements::Reference(ref a) => a.len()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+}
+
+// The number of bytes a primitive array component with the given descriptor takes, or None for
+// references.
+pub fn primitive_size(component_type: &str) -> Option<usize> {
+    match component_type.chars().next() {
+        Some('B') | Some('Z') => Some(1),
+        Some('C') | Some('S') => Some(2),
+        Some('I') | Some('F') => Some(4),
+        Some('J') | Some('D') => Some(8),
+        _ => None
+    }
+}
+
+// The components of an array on the heap.
+#[derive(Debug)]
+pub enum Elements<'a> {
+    Byte(&'a [i8]),
+    Character(&'a [u16]),
+    Short(&'a [i16]),
+    Integer(&'a [i32]),
+    Long(&'a [i64]),
+    Float(&'a [f32]),
+    Double(&'a [f64]),
+    Reference(&'a [Value])
 }
 
 #[derive(Debug)]
-pub struct IntArray {
-    array: Vec<i32>
+pub enum ElementsMut<'a> {
+    Byte(&'a mut [i8]),
+    Character(&'a mut [u16]),
+    Short(&'a mut [i16]),
+    Integer(&'a mut [i32]),
+    Long(&'a mut [i64]),
+    Float(&'a mut [f32]),
+    Double(&'a mut [f64]),
+    Reference(&'a mut [Value])
 }
 
-impl IntArray {
-    pub fn get(&self, index: usize) -> i32 {
-        self.array[index]
+impl Array {
+
+    pub fn component_type(&self) -> &str {
+        &self.component_type
+    }
+
+    // The descriptor of the array type itself, which is also the name of its class.
+    pub fn descriptor(&self) -> String {
+        format!("[{}", self.component_type)
+    }
+
+    pub fn elements(&self) -> Elements<'_> {
+        let (elements, length) = (self.elements, self.length);
+        unsafe {
+            match self.component_type.as_bytes()[0] {
+                b'B' | b'Z' => Elements::Byte(slice::from_raw_parts(elements as *const i8, length)),
+                b'C' => Elements::Character(slice::from_raw_parts(elements as *const u16, length)),
+                b'S' => Elements::Short(slice::from_raw_parts(elements as *const i16, length)),
+                b'I' => Elements::Integer(slice::from_raw_parts(elements as *const i32, length)),
+                b'J' => Elements::Long(slice::from_raw_parts(elements as *const i64, length)),
+                b'F' => Elements::Float(slice::from_raw_parts(elements as *const f32, length)),
+                b'D' => Elements::Double(slice::from_raw_parts(elements as *const f64, length)),
+                _ => Elements::Reference(slice::from_raw_parts(elements as *const Value, length))
+            }
+        }
     }
 
-    pub fn set(&mut self, index: usize, value: i32) {
-        self.array[index] = value;
+    pub fn elements_mut(&mut self) -> ElementsMut<'_> {
+        let (elements, length) = (self.elements, self.length);
+        unsafe {
+            match self.component_type.as_bytes()[0] {
+                b'B' | b'Z' => ElementsMut::Byte(slice::from_raw_parts_mut(elements as *mut i8, length)),
+                b'C' => ElementsMut::Character(slice::from_raw_parts_mut(elements as *mut u16, length)),
+                b'S' => ElementsMut::Short(slice::from_raw_parts_mut(elements as *mut i16, length)),
+                b'I' => ElementsMut::Integer(slice::from_raw_parts_mut(elements as *mut i32, length)),
+                b'J' => ElementsMut::Long(slice::from_raw_parts_mut(elements as *mut i64, length)),
+                b'F' => ElementsMut::Float(slice::from_raw_parts_mut(elements as *mut f32, length)),
+                b'D' => ElementsMut::Double(slice::from_raw_parts_mut(elements as *mut f64, length)),
+                _ => ElementsMut::Reference(slice::from_raw_parts_mut(elements as *mut Value, length))
+            }
+        }
     }
 
-    pub fn new(size: usize) -> Rc<RefCell<IntArray>> {
-        let array = vec![0; size];
-        Rc::new(RefCell::new(IntArray { array }))
+    pub fn as_chars(&self) -> Option<&[u16]> {
+        match self.elements() {
+            Elements::Character(chars) => Some(chars),
+            _ => None
+        }
     }
+
+    // The components of an array of primitives as bytes in the byte order of the machine, for
+    // Unsafe to access at any offset. Arrays of references have no such representation.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        let size = primitive_size(&self.component_type)?;
+        Some(unsafe { slice::from_raw_parts(self.elements as *const u8, self.length * size) })
+    }
+
+    pub fn as_bytes_mut(&mut self) -> Option<&mut [u8]> {
+        let size = primitive_size(&self.component_type)?;
+        Some(unsafe { slice::from_raw_parts_mut(self.elements, self.length * size) })
+    }
+
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The caller is responsible for checking the index against the length of the array.
+    pub fn get(&self, index: usize) -> Value {
+        match self.elements() {
+            Elements::Byte(a) => Value::Integer(a[index] as i32),
+            Elements::Character(a) => Value::Integer(a[index] as i32),
+            Elements::Short(a) => Value::Integer(a[index] as i32),
+            Elements::Integer(a) => Value::Integer(a[index]),
+            Elements::Long(a) => Value::Long(a[index]),
+            Elements::Float(a) => Value::Float(a[index]),
+            Elements::Double(a) => Value::Double(a[index]),
+            Elements::Reference(a) => a[index].clone()
+        }
+    }
+
+    // Ints stored into byte, char and short arrays are truncated, as by bastore, castore and
+    // sastore.
+    pub fn set(&mut self, index: usize, value: Value) -> Result<(), InterpreterError> {
+        match (self.elements_mut(), value) {
+            (ElementsMut::Byte(a), Value::Integer(i)) => a[index] = i as i8,
+            (ElementsMut::Character(a), Value::Integer(i)) => a[index] = i as u16,
+            (ElementsMut::Short(a), Value::Integer(i)) => a[index] = i as i16,
+            (ElementsMut::Integer(a), Value::Integer(i)) => a[index] = i,
+            (ElementsMut::Long(a), Value::Long(l)) => a[index] = l,
+            (ElementsMut::Float(a), Value::Float(f)) => a[index] = f,
+            (ElementsMut::Double(a), Value::Double(d)) => a[index] = d,
+            (ElementsMut::Reference(a), value) if value.is_reference() => a[index] = value,
+            _ => return Err(InterpreterError::UnexpectedOperand)
+        }
+
+        Ok(())
+    }
+
 }
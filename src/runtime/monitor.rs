@@ -0,0 +1,196 @@
+use runtime::Value;
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, ThreadId};
+use std::time::Duration;
+
+// Every object and array has a monitor, see JVMS $2.11.10. A thread may enter a monitor it already
+// holds, so the monitor counts the entries of its owner.
+#[derive(Debug, Default)]
+pub struct Monitor {
+    state: Mutex<MonitorState>,
+    // Signalled when the monitor is released, for threads blocked in enter.
+    released: Condvar,
+    // Signalled by notify and notifyAll, for threads in wait.
+    notified: Condvar
+}
+
+#[derive(Debug, Default)]
+struct MonitorState {
+    owner: Option<ThreadId>,
+    entry_count: u32
+}
+
+impl Monitor {
+
+    fn lock(&self) -> MutexGuard<'_, MonitorState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Blocks until no other thread holds the monitor, see JVMS $6.5.monitorenter.
+    pub fn enter(&self) {
+        let current = thread::current().id();
+        let mut state = self.lock();
+
+        while state.owner.is_some() && state.owner != Some(current) {
+            state = self.released.wait(state).unwrap_or_else(PoisonError::into_inner);
+        }
+
+        state.owner = Some(current);
+        state.entry_count += 1;
+    }
+
+    // Enters the monitor only if that does not block.
+    pub fn try_enter(&self) -> bool {
+        let current = thread::current().id();
+        let mut state = self.lock();
+        if state.owner.is_some() && state.owner != Some(current) {
+            return false;
+        }
+
+        state.owner = Some(current);
+        state.entry_count += 1;
+        true
+    }
+
+    // Returns false if the current thread does not hold the monitor, in which case
+    // IllegalMonitorStateException is thrown.
+    pub fn exit(&self) -> bool {
+        let mut state = self.lock();
+        if state.owner != Some(thread::current().id()) {
+            return false;
+        }
+
+        state.entry_count -= 1;
+        if state.entry_count == 0 {
+            state.owner = None;
+            self.released.notify_one();
+        }
+        true
+    }
+
+    pub fn is_held(&self) -> bool {
+        self.lock().owner == Some(thread::current().id())
+    }
+
+    // Object.wait: releases the monitor however many times it was entered, waits to be notified
+    // or for the timeout to pass, and enters the monitor again as often as before. Returns false
+    // if the current thread does not hold the monitor. Like the JVM, we may wake up spuriously.
+    pub fn wait(&self, timeout: Option<Duration>) -> bool {
+        let current = thread::current().id();
+        let mut state = self.lock();
+        if state.owner != Some(current) {
+            return false;
+        }
+
+        let entry_count = state.entry_count;
+        state.owner = None;
+        state.entry_count = 0;
+        self.released.notify_one();
+
+        state = match timeout {
+            Some(timeout) => self.notified.wait_timeout(state, timeout).unwrap_or_else(PoisonError::into_inner).0,
+            None => self.notified.wait(state).unwrap_or_else(PoisonError::into_inner)
+        };
+
+        while state.owner.is_some() {
+            state = self.released.wait(state).unwrap_or_else(PoisonError::into_inner);
+        }
+        state.owner = Some(current);
+        state.entry_count = entry_count;
+        true
+    }
+
+    // Object.notify and notifyAll. Returns false if the current thread does not hold the monitor.
+    pub fn notify(&self, all: bool) -> bool {
+        if !self.is_held() {
+            return false;
+        }
+
+        if all {
+            self.notified.notify_all();
+        } else {
+            self.notified.notify_one();
+        }
+        true
+    }
+
+}
+
+// Applies f to the monitor of an object or array. Returns None for null, which monitorenter and
+// monitorexit answer with a NullPointerException.
+pub fn with_monitor<R, F: FnOnce(&Monitor) -> R>(reference: &Value, f: F) -> Option<R> {
+    match reference {
+        Value::ObjectRef(object) => Some(f(object.monitor())),
+        Value::ArrayRef(array) => Some(f(array.monitor())),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use runtime::bootstrap::tests::run_main;
+    use std::sync::Arc;
+
+    #[test]
+    fn counts_recursive_entries() {
+        let monitor = Monitor::default();
+        assert!(!monitor.exit());
+
+        monitor.enter();
+        assert!(monitor.try_enter());
+        assert!(monitor.exit());
+        assert!(monitor.is_held());
+        assert!(monitor.exit());
+        assert!(!monitor.is_held());
+        assert!(!monitor.exit());
+    }
+
+    // A monitor held by one thread cannot be exited or waited on by another.
+    #[test]
+    fn owned_by_one_thread() {
+        let monitor = Arc::new(Monitor::default());
+        monitor.enter();
+
+        let other = monitor.clone();
+        let held_elsewhere = thread::spawn(move || (other.try_enter(), other.exit(), other.wait(None), other.notify(true)))
+            .join()
+            .unwrap();
+        assert_eq!(held_elsewhere, (false, false, false, false));
+
+        assert!(monitor.wait(Some(Duration::from_millis(1))));
+        assert!(monitor.is_held());
+        assert!(monitor.exit());
+    }
+
+    #[test]
+    fn wait_releases_the_monitor_until_notified() {
+        let monitor = Arc::new(Monitor::default());
+        let flag = Arc::new(Mutex::new(false));
+
+        let (other, other_flag) = (monitor.clone(), flag.clone());
+        let notifier = thread::spawn(move || {
+            other.enter();
+            *other_flag.lock().unwrap() = true;
+            assert!(other.notify(false));
+            assert!(other.exit());
+        });
+
+        monitor.enter();
+        while !*flag.lock().unwrap() {
+            monitor.wait(None);
+        }
+        assert!(monitor.exit());
+        notifier.join().unwrap();
+    }
+
+    #[test]
+    fn monitors() {
+        let (out, err) = run_main(&[include_bytes!("../../fixtures/Monitors.class")]);
+
+        assert_eq!(out, include_str!("../../fixtures/Monitors.out"));
+        assert_eq!(err, "");
+    }
+
+}
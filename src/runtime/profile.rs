@@ -0,0 +1,370 @@
+use code::instruction::Instruction;
+use runtime::class::RuntimeClass;
+use runtime::class::method::RuntimeMethod;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Execution statistics gathered by the interpreter while profiling is on, as with -Xstats: how
+// often each method is invoked and each of its instructions runs, how often its conditional
+// branches are taken, and how many instances of each class are allocated.
+//
+// A frame counts into its own FrameCounts as it runs, which are added to the profile when it
+// completes, so the interpreter only takes the lock once per frame. Code the JIT compiled counts
+// nothing, so the instructions of the invocations that ran it are missing from the counts.
+pub struct Profile {
+    enabled: AtomicBool,
+    data: Mutex<ProfileData>
+}
+
+#[derive(Default)]
+struct ProfileData {
+    // By class name, method name and descriptor.
+    methods: BTreeMap<(String, String, String), MethodProfile>,
+    // By class name, or descriptor for arrays.
+    allocations: BTreeMap<String, u64>
+}
+
+#[derive(Default)]
+struct MethodProfile {
+    invocations: u64,
+    // The invocations that ran compiled code, whose instructions were not counted.
+    compiled: u64,
+    // The pc and instruction of every instruction of the method, with how often it ran.
+    instructions: Vec<(u16, Instruction, u64)>,
+    // Taken and not taken counts by the pc of the branch.
+    branches: BTreeMap<u16, (u64, u64)>
+}
+
+// What one frame of the interpreter ran. Instructions are identified by their position in the
+// code of the method.
+#[derive(Default)]
+pub struct FrameCounts {
+    executions: HashMap<usize, u64>,
+    branches: HashMap<usize, (u64, u64)>,
+    allocations: HashMap<String, u64>
+}
+
+impl FrameCounts {
+
+    pub fn executed(&mut self, position: usize) {
+        *self.executions.entry(position).or_insert(0) += 1;
+    }
+
+    pub fn branched(&mut self, position: usize, taken: bool) {
+        let counts = self.branches.entry(position).or_insert((0, 0));
+        if taken {
+            counts.0 += 1;
+        } else {
+            counts.1 += 1;
+        }
+    }
+
+    pub fn allocated(&mut self, class_name: &str) {
+        match self.allocations.get_mut(class_name) {
+            Some(count) => *count += 1,
+            None => {
+                self.allocations.insert(String::from(class_name), 1);
+            }
+        }
+    }
+
+}
+
+// Whether the instruction is one of the if family, whose branch is either taken or not.
+pub fn is_conditional_branch(instruction: &Instruction) -> bool {
+    matches!(instruction,
+        Instruction::IfAcmpeq { .. } | Instruction::IfAcmpne { .. } |
+        Instruction::IfIcmpeq { .. } | Instruction::IfIcmpne { .. } |
+        Instruction::IfIcmplt { .. } | Instruction::IfIcmpge { .. } |
+        Instruction::IfIcmpgt { .. } | Instruction::IfIcmple { .. } |
+        Instruction::Ifeq { .. } | Instruction::Ifne { .. } |
+        Instruction::Iflt { .. } | Instruction::Ifge { .. } |
+        Instruction::Ifgt { .. } | Instruction::Ifle { .. } |
+        Instruction::Ifnonnull { .. } | Instruction::Ifnull { .. })
+}
+
+pub fn is_allocation(instruction: &Instruction) -> bool {
+    matches!(instruction,
+        Instruction::New { .. } | Instruction::Newarray { .. } |
+        Instruction::Anewarray { .. } | Instruction::Multianewarray { .. })
+}
+
+// The name of an instruction without its operands, e.g. IfIcmpge.
+fn mnemonic(instruction: &Instruction) -> String {
+    let debug = format!("{:?}", instruction);
+    let end = debug.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(debug.len());
+    String::from(&debug[..end])
+}
+
+impl Profile {
+
+    pub fn new() -> Profile {
+        Profile { enabled: AtomicBool::new(false), data: Mutex::new(ProfileData::default()) }
+    }
+
+    pub fn enable(&self) {
+        self.enabled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn invoked(&self, class: &Arc<RuntimeClass>, method: &RuntimeMethod) {
+        method.heat.fetch_add(1, Ordering::Relaxed);
+        let mut data = self.data.lock().unwrap();
+        data.method(class, method).invocations += 1;
+    }
+
+    // Counts an invocation of the method that ran its compiled code.
+    pub fn ran_compiled(&self, class: &Arc<RuntimeClass>, method: &RuntimeMethod) {
+        let mut data = self.data.lock().unwrap();
+        data.method(class, method).compiled += 1;
+    }
+
+    // Adds what a frame of the method ran to the profile.
+    pub fn record(&self, class: &Arc<RuntimeClass>, method: &RuntimeMethod, counts: FrameCounts) {
+        let branches: u64 = counts.branches.values().map(|&(taken, not_taken)| taken + not_taken).sum();
+        method.heat.fetch_add(branches, Ordering::Relaxed);
+        let mut data = self.data.lock().unwrap();
+        let profile = data.method(class, method);
+        for (position, count) in counts.executions.into_iter() {
+            if let Some(instruction) = profile.instructions.get_mut(position) {
+                instruction.2 += count;
+            }
+        }
+        for (position, (taken, not_taken)) in counts.branches.into_iter() {
+            let pc = match profile.instructions.get(position) {
+                Some(&(pc, _, _)) => pc,
+                None => continue
+            };
+            let branch = profile.branches.entry(pc).or_insert((0, 0));
+            branch.0 += taken;
+            branch.1 += not_taken;
+        }
+        for (class_name, count) in counts.allocations.into_iter() {
+            *data.allocations.entry(class_name).or_insert(0) += count;
+        }
+    }
+
+    // How often the branch at pc was taken and not taken.
+    pub fn branch_counts(&self, class: &RuntimeClass, method: &RuntimeMethod, pc: u16) -> Option<(u64, u64)> {
+        let data = self.data.lock().unwrap();
+        let key = (class.class_name.clone(), method.name.clone(), method.descriptor.clone());
+        data.methods.get(&key)?.branches.get(&pc).cloned()
+    }
+
+    // How hot the method has run in the interpreter, for the JIT to decide when to compile it by:
+    // its invocations plus the conditional branches it ran, which count the iterations of its loops.
+    // The method counts them itself, so that the JIT does not take the lock on every invocation.
+    pub fn heat(&self, method: &RuntimeMethod) -> u64 {
+        method.heat.load(Ordering::Relaxed)
+    }
+
+    // A summary for people to read, with the hottest methods, bytecodes and allocations first.
+    // The bytecodes of methods with compiled invocations are marked as incomplete.
+    pub fn report(&self) -> String {
+        let data = self.data.lock().unwrap();
+        let mut report = String::new();
+
+        let mut methods: Vec<(String, u64, u64, u64)> = data.methods.iter()
+            .map(|(key, profile)| (symbol(key), profile.invocations, profile.executed(), profile.compiled))
+            .collect();
+        methods.sort_by(|a, b| b.1.cmp(&a.1).then(b.2.cmp(&a.2)).then(a.0.cmp(&b.0)));
+        let _ = writeln!(report, "Methods");
+        let _ = writeln!(report, "{:>12} {:>12} {:>12}  method", "invocations", "bytecodes", "compiled");
+        for (method, invocations, executed, compiled) in methods.iter() {
+            let executed = if *compiled > 0 { format!("{}*", executed) } else { executed.to_string() };
+            let _ = writeln!(report, "{:>12} {:>12} {:>12}  {}", invocations, executed, compiled, method);
+        }
+        if methods.iter().any(|&(_, _, _, compiled)| compiled > 0) {
+            let _ = writeln!(report, "* incomplete: the bytecodes of compiled invocations are not counted, here or below");
+        }
+
+        let mut bytecodes: Vec<(String, u64)> = data.bytecodes().into_iter().collect();
+        bytecodes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        let _ = writeln!(report, "\nBytecodes");
+        let _ = writeln!(report, "{:>12}  bytecode", "executions");
+        for (bytecode, count) in bytecodes.iter() {
+            let _ = writeln!(report, "{:>12}  {}", count, bytecode);
+        }
+
+        let _ = writeln!(report, "\nBranches");
+        let _ = writeln!(report, "{:>12} {:>12} {:>7}  branch", "taken", "not taken", "taken");
+        for (key, profile) in data.methods.iter() {
+            for (pc, &(taken, not_taken)) in profile.branches.iter() {
+                let ratio = 100.0 * taken as f64 / (taken + not_taken) as f64;
+                let _ = writeln!(report, "{:>12} {:>12} {:>6.1}%  {} @ {}", taken, not_taken, ratio, symbol(key), pc);
+            }
+        }
+
+        let mut allocations: Vec<(&String, &u64)> = data.allocations.iter().collect();
+        allocations.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let _ = writeln!(report, "\nAllocations");
+        let _ = writeln!(report, "{:>12}  class", "instances");
+        for (class_name, count) in allocations.iter() {
+            let _ = writeln!(report, "{:>12}  {}", count, class_name.replace('/', "."));
+        }
+
+        report
+    }
+
+    // The whole profile as a JSON object, for tools to read.
+    pub fn to_json(&self) -> String {
+        let data = self.data.lock().unwrap();
+        let mut json = String::from("{\"methods\":[");
+
+        for (index, ((class_name, name, descriptor), profile)) in data.methods.iter().enumerate() {
+            if index > 0 {
+                json.push(',');
+            }
+            let _ = write!(json, "{{\"class\":{},\"name\":{},\"descriptor\":{},\"invocations\":{},\"compiled\":{},\"instructions\":[",
+                           json_string(class_name), json_string(name), json_string(descriptor), profile.invocations, profile.compiled);
+            let executed = profile.instructions.iter().filter(|&&(_, _, count)| count > 0);
+            for (index, &(pc, ref instruction, count)) in executed.enumerate() {
+                let separator = if index > 0 { "," } else { "" };
+                let _ = write!(json, "{}{{\"pc\":{},\"bytecode\":{},\"count\":{}}}", separator, pc, json_string(&mnemonic(instruction)), count);
+            }
+            json.push_str("],\"branches\":[");
+            for (index, (pc, &(taken, not_taken))) in profile.branches.iter().enumerate() {
+                let separator = if index > 0 { "," } else { "" };
+                let _ = write!(json, "{}{{\"pc\":{},\"taken\":{},\"not_taken\":{}}}", separator, pc, taken, not_taken);
+            }
+            json.push_str("]}");
+        }
+
+        json.push_str("],\"bytecodes\":{");
+        for (index, (bytecode, count)) in data.bytecodes().iter().enumerate() {
+            let separator = if index > 0 { "," } else { "" };
+            let _ = write!(json, "{}{}:{}", separator, json_string(bytecode), count);
+        }
+        json.push_str("},\"allocations\":{");
+        for (index, (class_name, count)) in data.allocations.iter().enumerate() {
+            let separator = if index > 0 { "," } else { "" };
+            let _ = write!(json, "{}{}:{}", separator, json_string(class_name), count);
+        }
+        json.push_str("}}");
+        json
+    }
+
+}
+
+impl Default for Profile {
+    fn default() -> Profile {
+        Profile::new()
+    }
+}
+
+impl ProfileData {
+
+    fn method(&mut self, class: &Arc<RuntimeClass>, method: &RuntimeMethod) -> &mut MethodProfile {
+        let key = (class.class_name.clone(), method.name.clone(), method.descriptor.clone());
+        self.methods.entry(key).or_insert_with(|| MethodProfile {
+            instructions: method.code.iter()
+                .flat_map(|code| code.instructions.iter())
+                .map(|tagged_instruction| (tagged_instruction.index, tagged_instruction.instruction.clone(), 0))
+                .collect(),
+            ..MethodProfile::default()
+        })
+    }
+
+    // How often each kind of instruction ran, across all methods.
+    fn bytecodes(&self) -> BTreeMap<String, u64> {
+        let mut bytecodes = BTreeMap::new();
+        for profile in self.methods.values() {
+            for &(_, ref instruction, count) in profile.instructions.iter().filter(|&&(_, _, count)| count > 0) {
+                *bytecodes.entry(mnemonic(instruction)).or_insert(0) += count;
+            }
+        }
+        bytecodes
+    }
+
+}
+
+impl MethodProfile {
+
+    fn executed(&self) -> u64 {
+        self.instructions.iter().map(|&(_, _, count)| count).sum()
+    }
+
+}
+
+fn symbol(key: &(String, String, String)) -> String {
+    format!("{}.{}{}", key.0.replace('/', "."), key.1, key.2)
+}
+
+// A JSON string literal.
+pub fn json_string(value: &str) -> String {
+    let mut json = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            },
+            c => json.push(c)
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use class::reader;
+    use runtime::Value;
+    use runtime::class::ClassTable;
+    use runtime::interpreter;
+
+    #[test]
+    fn counts_invocations_branches_and_bytecodes() {
+        let class_table = ClassTable::new();
+        class_table.set_compile_threshold(0);
+        class_table.profile.enable();
+        let class = class_table.define_class(&reader::read_class_file(include_bytes!("../../fixtures/Jit.class")).unwrap()).unwrap();
+
+        // sumTo(3) tests i <= n four times, and leaves the loop on the last.
+        let method = class.get_declared_method("sumTo", "(I)I").unwrap();
+        for _ in 0..2 {
+            interpreter::invoke_method(&class, method, vec![Value::Integer(3)], &class_table).unwrap();
+        }
+
+        let data = class_table.profile.data.lock().unwrap();
+        let profile = &data.methods[&(String::from("Jit"), String::from("sumTo"), String::from("(I)I"))];
+        assert_eq!(profile.invocations, 2);
+        assert_eq!(profile.branches.values().cloned().collect::<Vec<(u64, u64)>>(), vec![(2, 6)]);
+        assert_eq!(data.bytecodes()["Ireturn"], 2);
+        drop(data);
+        assert_eq!(class_table.profile.heat(method), 10);
+
+        let report = class_table.profile.report();
+        assert!(report.contains("           2 "));
+        assert!(report.contains("Jit.sumTo(I)I @ "));
+        let json = class_table.profile.to_json();
+        assert!(json.starts_with("{\"methods\":[{\"class\":\"Jit\",\"name\":\"sumTo\",\"descriptor\":\"(I)I\",\"invocations\":2,\"compiled\":0,"));
+        assert!(json.contains("\"taken\":2,\"not_taken\":6"));
+    }
+
+    #[test]
+    fn counts_allocations() {
+        let mut counts = FrameCounts::default();
+        counts.allocated("[I");
+        counts.allocated("java/lang/Object");
+        counts.allocated("[I");
+
+        let profile = Profile::new();
+        let class_table = ClassTable::new();
+        let class = class_table.get_class("java/lang/Object").unwrap();
+        profile.record(&class, class.get_declared_method("<init>", "()V").unwrap(), counts);
+        assert!(profile.report().ends_with("Allocations\n   instances  class\n           2  [I\n           1  java.lang.Object\n"));
+        assert!(profile.to_json().ends_with("\"allocations\":{\"[I\":2,\"java/lang/Object\":1}}"));
+        assert_eq!(json_string("a\"b\\\n"), "\"a\\\"b\\\\\\u000a\"");
+    }
+
+}
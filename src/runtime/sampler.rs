@@ -0,0 +1,131 @@
+use runtime::class::ClassTable;
+use runtime::frames::Frame;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+// A sampling profiler, as with -Xprof. A thread of its own looks at the Java stack of every
+// thread at a fixed interval and counts how often it sees each stack. Threads that are blocked
+// or sleeping are sampled like running ones.
+
+pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(10);
+
+pub struct Sampler {
+    stopped: Arc<(Mutex<bool>, Condvar)>,
+    thread: JoinHandle<Samples>
+}
+
+// How often each stack was seen, by its collapsed form.
+#[derive(Debug, Default, PartialEq)]
+pub struct Samples {
+    pub stacks: BTreeMap<String, u64>
+}
+
+impl Sampler {
+
+    pub fn start(class_table: Arc<ClassTable>, interval: Duration) -> Sampler {
+        let stopped = Arc::new((Mutex::new(false), Condvar::new()));
+        let stop = stopped.clone();
+        let thread = thread::Builder::new()
+            .name(String::from("Sampler"))
+            .spawn(move || {
+                let mut samples = Samples::default();
+                let (ref stopped, ref changed) = *stop;
+                let mut stopped = stopped.lock().unwrap();
+                while !*stopped {
+                    stopped = changed.wait_timeout(stopped, interval).unwrap().0;
+                    samples.sample(&class_table);
+                }
+                samples
+            })
+            .unwrap();
+        Sampler { stopped, thread }
+    }
+
+    // Stops sampling and returns what was seen.
+    pub fn stop(self) -> Samples {
+        let (ref stopped, ref changed) = *self.stopped;
+        *stopped.lock().unwrap() = true;
+        changed.notify_all();
+        self.thread.join().unwrap_or_default()
+    }
+
+}
+
+impl Samples {
+
+    pub fn sample(&mut self, class_table: &ClassTable) {
+        for (thread_name, frames) in class_table.stacks.snapshot() {
+            if frames.is_empty() {
+                continue;
+            }
+            *self.stacks.entry(collapse(&thread_name, &frames)).or_insert(0) += 1;
+        }
+    }
+
+    // The stacks in the collapsed format of flamegraph.pl and the tools that read it: one line
+    // per stack, with its frames from the outermost separated by semicolons, then its count.
+    pub fn collapsed(&self) -> String {
+        let mut collapsed = String::new();
+        for (stack, count) in self.stacks.iter() {
+            let _ = writeln!(collapsed, "{} {}", stack, count);
+        }
+        collapsed
+    }
+
+}
+
+// A stack with the name of its thread at the root, e.g. "main;Main.main:5;Main.fib:12".
+pub fn collapse(thread_name: &str, frames: &[Arc<Frame>]) -> String {
+    let mut stack = thread_name.replace([';', ' '], "_");
+    for frame in frames.iter() {
+        let _ = write!(stack, ";{}.{}", frame.class.class_name.replace('/', "."), frame.method().name);
+        if let Some(line_number) = frame.line_number() {
+            let _ = write!(stack, ":{}", line_number);
+        }
+    }
+    stack
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use class::reader;
+
+    #[test]
+    fn collapses_stacks() {
+        let class_table = ClassTable::new();
+        let class = class_table.define_class(&reader::read_class_file(include_bytes!("../../fixtures/Jit.class")).unwrap()).unwrap();
+        let fib = class.get_declared_method("fib", "(I)I").unwrap();
+        let sum_to = class.get_declared_method("sumTo", "(I)I").unwrap();
+
+        let outer = class_table.stacks.enter(&class, sum_to);
+        outer.set_pc(0);
+        let inner = class_table.stacks.enter(&class, fib);
+        let mut samples = Samples::default();
+        samples.sample(&class_table);
+        samples.sample(&class_table);
+        drop(inner);
+        samples.sample(&class_table);
+        drop(outer);
+        samples.sample(&class_table);
+
+        let thread = thread::current();
+        let name = thread.name().unwrap().replace([';', ' '], "_");
+        let line = class.get_declared_method("sumTo", "(I)I").unwrap().code.as_ref().unwrap().line_number(0).unwrap();
+        let expected = format!("{0};Jit.sumTo:{1} 1\n{0};Jit.sumTo:{1};Jit.fib 2\n", name, line);
+        assert_eq!(samples.collapsed(), expected);
+    }
+
+    #[test]
+    fn samples_until_stopped() {
+        let class_table = ClassTable::new();
+        let sampler = Sampler::start(class_table.clone(), Duration::from_millis(1));
+        thread::sleep(Duration::from_millis(5));
+        assert_eq!(sampler.stop(), Samples::default());
+    }
+
+}
@@ -1,6 +1,5 @@
-use runtime::{Value, IntArray, Object};
-use std::cell::RefCell;
-use std::rc::Rc;
+use runtime::{Value, Array, HeapCell, Object};
+use std::sync::Arc;
 use runtime::interpreter::InterpreterError;
 
 // TODO: Implement locals and stack with an array
@@ -20,7 +19,7 @@ impl StackFrame {
     pub fn pop_many(&mut self, count: usize) -> Option<Vec<Value>> {
         let mut values: Vec<Value> = Vec::new();
 
-        for i in 0..count {
+        for _ in 0..count {
             let value = self.pop()?;
             values.push(value);
         }
@@ -28,6 +27,18 @@ impl StackFrame {
         Some(values)
     }
 
+    // The top count values in the order they were pushed, left on the stack. Values that stay on
+    // the stack stay roots of the heap.
+    pub fn peek_many(&self, count: usize) -> Option<Vec<Value>> {
+        let start = self.stack.len().checked_sub(count)?;
+        Some(self.stack[start..].to_vec())
+    }
+
+    pub fn drop_many(&mut self, count: usize) {
+        let length = self.stack.len().saturating_sub(count);
+        self.stack.truncate(length);
+    }
+
     pub fn push(&mut self, operand: Value) {
         self.stack.push(operand)
     }
@@ -68,24 +79,84 @@ impl StackFrame {
         self.set_local(index, Value::Integer(value))
     }
 
-    // int array reference helpers
+    // long helpers
+
+    pub fn push_long(&mut self, long: i64) {
+        self.push(Value::Long(long))
+    }
+
+    pub fn pop_long(&mut self) -> Result<i64, InterpreterError> {
+        let operand = self.pop().unwrap();
+
+        match operand {
+            Value::Long(l) => Ok(l),
+            _ => Err(InterpreterError::UnexpectedOperand)
+        }
+    }
+
+    // float helpers
+
+    pub fn push_float(&mut self, float: f32) {
+        self.push(Value::Float(float))
+    }
+
+    pub fn pop_float(&mut self) -> Result<f32, InterpreterError> {
+        let operand = self.pop().unwrap();
+
+        match operand {
+            Value::Float(f) => Ok(f),
+            _ => Err(InterpreterError::UnexpectedOperand)
+        }
+    }
+
+    // double helpers
+
+    pub fn push_double(&mut self, double: f64) {
+        self.push(Value::Double(double))
+    }
+
+    pub fn pop_double(&mut self) -> Result<f64, InterpreterError> {
+        let operand = self.pop().unwrap();
+
+        match operand {
+            Value::Double(d) => Ok(d),
+            _ => Err(InterpreterError::UnexpectedOperand)
+        }
+    }
+
+    // reference helpers
 
-    pub fn pop_int_array(&mut self) -> Result<Rc<RefCell<IntArray>>, InterpreterError> {
+    // Pops any reference, including null.
+    pub fn pop_reference(&mut self) -> Result<Value, InterpreterError> {
+        let operand = self.pop().unwrap();
+
+        if operand.is_reference() {
+            Ok(operand)
+        } else {
+            Err(InterpreterError::UnexpectedOperand)
+        }
+    }
+
+    // array reference helpers
+
+    // Returns None if the reference was null.
+    pub fn pop_array(&mut self) -> Result<Option<Arc<HeapCell<Array>>>, InterpreterError> {
         let operand = self.pop().unwrap();
 
         match operand {
-            Value::IntegerArrayRef(reference) => Ok(reference),
+            Value::ArrayRef(reference) => Ok(Some(reference)),
+            Value::Null => Ok(None),
             _ => Err(InterpreterError::UnexpectedOperand)
         }
     }
 
     // object reference helpers
 
-    pub fn push_object_reference(&mut self, reference: Rc<RefCell<Object>>) {
+    pub fn push_object_reference(&mut self, reference: Arc<HeapCell<Object>>) {
         self.push(Value::ObjectRef(reference))
     }
 
-    pub fn pop_object_reference(&mut self) -> Result<Rc<RefCell<Object>>, InterpreterError> {
+    pub fn pop_object_reference(&mut self) -> Result<Arc<HeapCell<Object>>, InterpreterError> {
         let operand = self.pop().unwrap();
 
         match operand {
@@ -96,15 +167,30 @@ impl StackFrame {
 
     pub fn new_frame(max_stack: u16, max_locals: u16) -> StackFrame {
         let locals: Vec<Value> = vec![Value::Null; max_locals as usize];
-        let stack: Vec<Value> = Vec::new();
+        let stack: Vec<Value> = Vec::with_capacity(max_stack as usize);
 
         StackFrame { locals, stack }
     }
 
-    pub fn new_frame_with_locals(max_stack: u16, max_locals: u16, mut locals: Vec<Value>) -> StackFrame {
-        let mut remaining_locals = vec![Value::Null; (max_locals as usize) - locals.len()];
-        locals.append(&mut remaining_locals);
-        StackFrame { locals, stack: Vec::new() }
+    // Arguments are passed in consecutive local variables, with longs and doubles taking up two
+    // slots each (JVMS $2.6.1).
+    pub fn new_frame_with_locals(max_stack: u16, max_locals: u16, arguments: Vec<Value>) -> StackFrame {
+        let mut locals: Vec<Value> = Vec::with_capacity(max_locals as usize);
+
+        for argument in arguments.into_iter() {
+            let wide = argument.is_category2();
+            locals.push(argument);
+            if wide {
+                locals.push(Value::Null);
+            }
+        }
+
+        if locals.len() < max_locals as usize {
+            let mut remaining_locals = vec![Value::Null; (max_locals as usize) - locals.len()];
+            locals.append(&mut remaining_locals);
+        }
+
+        StackFrame { locals, stack: Vec::with_capacity(max_stack as usize) }
     }
 
 }
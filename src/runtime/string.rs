@@ -0,0 +1,150 @@
+use runtime::{Value, Object, ArrayElements, Elements, HeapCell};
+use runtime::class::ClassTable;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub const STRING_CLASS_NAME: &str = "java/lang/String";
+
+// Strings are laid out like the java.lang.String of the class library. The bootstrap class
+// library's is like the JDK 8 one: a char[] holding UTF-16 code units and a cached hash code.
+// Since JDK 9 (JEP 254), the value is a byte[] instead, and the coder tells whether it holds one
+// Latin-1 byte per character or two bytes per UTF-16 code unit, in the byte order
+// StringUTF16.isBigEndian gives, which is little-endian.
+pub const VALUE_FIELD: &str = "value";
+pub const CODER_FIELD: &str = "coder";
+
+const LATIN1: i32 = 0;
+const UTF16: i32 = 1;
+
+// The string is held while its array is allocated, and then kept in the scope of the caller as
+// the allocations of the caller are. Strings of Latin-1 characters are compacted, as the JDK
+// does by default.
+pub fn new_string(class_table: &ClassTable, chars: Vec<u16>) -> Arc<HeapCell<Object>> {
+    let string = {
+        let scope = class_table.stacks.scope();
+        let class = class_table.get_class(STRING_CLASS_NAME).unwrap();
+        let string = class_table.new_object(&class);
+        scope.hold(&Value::ObjectRef(string.clone()));
+        if class.instance_field_position(CODER_FIELD).is_none() {
+            let value = Value::ArrayRef(class_table.new_array_from("C", ArrayElements::Character(chars)));
+            string.borrow_mut().put_field(String::from(VALUE_FIELD), value);
+        } else {
+            let (bytes, coder) = if chars.iter().all(|&c| c < 0x100) {
+                (chars.iter().map(|&c| c as i8).collect(), LATIN1)
+            } else {
+                (chars.iter().flat_map(|&c| [c as i8, (c >> 8) as i8]).collect(), UTF16)
+            };
+            let value = Value::ArrayRef(class_table.new_array_from("B", ArrayElements::Byte(bytes)));
+            let mut string = string.borrow_mut();
+            string.put_field(String::from(VALUE_FIELD), value);
+            string.put_field(String::from(CODER_FIELD), Value::Integer(coder));
+        }
+        string
+    };
+    class_table.stacks.keep(&Value::ObjectRef(string.clone()));
+    string
+}
+
+pub fn get_chars(string: &Object) -> Vec<u16> {
+    let array = match string.get_field(String::from(VALUE_FIELD)) {
+        Value::ArrayRef(array) => array,
+        _ => return Vec::new()
+    };
+    let array = array.borrow();
+    match array.elements() {
+        Elements::Character(chars) => chars.to_vec(),
+        Elements::Byte(bytes) if matches!(string.get_field(String::from(CODER_FIELD)), Value::Integer(UTF16)) => {
+            bytes.chunks_exact(2).map(|pair| pair[0] as u8 as u16 | (pair[1] as u8 as u16) << 8).collect()
+        },
+        Elements::Byte(bytes) => bytes.iter().map(|&byte| byte as u8 as u16).collect(),
+        _ => Vec::new()
+    }
+}
+
+// Lone surrogates can't be represented in a Rust string, so they are replaced with U+FFFD.
+pub fn to_rust_string(string: &Object) -> String {
+    String::from_utf16_lossy(&get_chars(string))
+}
+
+// The intern pool described in JVMS $5.1. String literals are always interned, so two ldc
+// instructions referring to the same character sequence must yield the same reference.
+#[derive(Debug, Default)]
+pub struct StringTable {
+    strings: HashMap<Vec<u16>, Arc<HeapCell<Object>>>
+}
+
+impl StringTable {
+
+    pub fn new() -> StringTable {
+        StringTable {
+            strings: HashMap::new()
+        }
+    }
+
+    // The canonical instance for the given UTF-16 code units, if there is one.
+    pub fn get(&self, chars: &[u16]) -> Option<Arc<HeapCell<Object>>> {
+        self.strings.get(chars).cloned()
+    }
+
+    // The semantics of String.intern: the canonical instance is returned if one exists, otherwise
+    // the given string becomes the canonical instance.
+    pub fn intern(&mut self, string: &Arc<HeapCell<Object>>) -> Arc<HeapCell<Object>> {
+        let chars = get_chars(&string.borrow());
+
+        self.strings
+            .entry(chars)
+            .or_insert_with(|| string.clone())
+            .clone()
+    }
+
+    // Calls visit with every canonical instance, which are roots of the heap.
+    pub fn visit(&self, visit: &mut dyn FnMut(&Value)) {
+        for string in self.strings.values() {
+            visit(&Value::ObjectRef(string.clone()));
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn intern_returns_same_reference() {
+        let class_table = ClassTable::new();
+
+        let a = class_table.intern_string("hello".encode_utf16().collect());
+        let b = class_table.intern_string("hello".encode_utf16().collect());
+        let c = class_table.intern_string("world".encode_utf16().collect());
+
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(!Arc::ptr_eq(&a, &c));
+    }
+
+    #[test]
+    fn intern_existing_object() {
+        let class_table = ClassTable::new();
+        let mut table = StringTable::new();
+
+        let literal = new_string(&class_table, "abc".encode_utf16().collect());
+        assert!(Arc::ptr_eq(&table.intern(&literal), &literal));
+        let computed = new_string(&class_table, "abc".encode_utf16().collect());
+        let fresh = new_string(&class_table, "xyz".encode_utf16().collect());
+
+        assert!(Arc::ptr_eq(&table.intern(&computed), &literal));
+        assert!(Arc::ptr_eq(&table.intern(&fresh), &fresh));
+        assert!(Arc::ptr_eq(&table.get(&get_chars(&fresh.borrow())).unwrap(), &fresh));
+    }
+
+    #[test]
+    fn lone_surrogates_are_preserved() {
+        let class_table = ClassTable::new();
+        let string = new_string(&class_table, vec![0x0041, 0xD800, 0x0042]);
+
+        assert_eq!(get_chars(&string.borrow()), vec![0x0041, 0xD800, 0x0042]);
+        assert_eq!(to_rust_string(&string.borrow()), "A\u{FFFD}B");
+    }
+
+}
@@ -0,0 +1,466 @@
+use code::instruction::Instruction;
+use runtime::Value;
+use runtime::class::RuntimeClass;
+use runtime::class::method::RuntimeMethod;
+use runtime::debugger::format_value;
+use runtime::interpreter::{InterpreterError, InvokeResult};
+use runtime::profile::json_string;
+use std::io::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+// Execution tracing, as with -Xtrace. The interpreter tells the tracers that were added what it
+// does: which methods it enters and leaves, the instructions it runs, the objects it allocates,
+// the exceptions it throws and the classes it loads. Each tracer only hears of the events its
+// filter accepts.
+//
+// All tracers share a lock, so the events of one thread are never interleaved with those of
+// another within a line.
+
+// The kinds of events, which filters select with a mask.
+pub const CALLS: u8 = 1;
+pub const INSTRUCTIONS: u8 = 2;
+pub const ALLOCATIONS: u8 = 4;
+pub const EXCEPTIONS: u8 = 8;
+pub const CLASSES: u8 = 16;
+pub const ALL_EVENTS: u8 = CALLS | INSTRUCTIONS | ALLOCATIONS | EXCEPTIONS | CLASSES;
+
+const EVENT_NAMES: [(&str, u8); 5] = [
+    ("calls", CALLS),
+    ("instructions", INSTRUCTIONS),
+    ("allocations", ALLOCATIONS),
+    ("exceptions", EXCEPTIONS),
+    ("classes", CLASSES)
+];
+
+// Where an event happened: the thread, the method it was running and how many frames deep it
+// was, counting the frame of the method. Events at an instruction have its pc.
+pub struct Site<'a> {
+    pub thread: &'a str,
+    pub class: &'a RuntimeClass,
+    pub method: &'a RuntimeMethod,
+    pub depth: usize,
+    pub pc: Option<u16>
+}
+
+// How a method completed.
+pub enum Exit<'a> {
+    // With its return value, or None for void methods.
+    Returned(Option<&'a Value>),
+    Threw(&'a Value),
+    // The interpreter failed, or the program called System.exit.
+    Failed(&'a InterpreterError)
+}
+
+impl<'a> Exit<'a> {
+
+    pub fn of(result: &'a Result<InvokeResult, InterpreterError>) -> Exit<'a> {
+        match *result {
+            Ok(InvokeResult::Void) => Exit::Returned(None),
+            Ok(InvokeResult::Value(ref value)) => Exit::Returned(Some(value)),
+            Ok(InvokeResult::Exception(ref exception)) => Exit::Threw(exception),
+            Err(ref error) => Exit::Failed(error)
+        }
+    }
+
+}
+
+// Receives the events of the interpreter. Every event has a default that ignores it.
+pub trait Tracer: Send {
+
+    fn method_entry(&mut self, _site: &Site) {}
+
+    fn method_exit(&mut self, _site: &Site, _exit: &Exit) {}
+
+    // Before the instruction runs.
+    fn instruction(&mut self, _site: &Site, _instruction: &Instruction) {}
+
+    // After an instruction allocated an object or array.
+    fn allocation(&mut self, _site: &Site, _value: &Value) {}
+
+    // An instruction threw an exception, or a native method returned one. Exceptions passed on
+    // from a callee are not thrown again.
+    fn exception_thrown(&mut self, _site: &Site, _exception: &Value) {}
+
+    // A class was loaded from the class path, from source, which names its file or jar.
+    fn class_loaded(&mut self, _thread: &str, _class: &RuntimeClass, _source: &str) {}
+
+    fn flush(&mut self) {}
+
+}
+
+// Which events a tracer hears of. Class and method globs may use * for any characters and ? for
+// one; an empty list matches everything.
+#[derive(Debug, PartialEq)]
+pub struct Filter {
+    pub events: u8,
+    // Over class names written with dots, e.g. com.example.*.
+    pub classes: Vec<String>,
+    // Over method names, or over Class.method for globs with a dot, e.g. *.run or Main.*.
+    pub methods: Vec<String>
+}
+
+impl Filter {
+
+    pub fn all() -> Filter {
+        Filter { events: ALL_EVENTS, classes: Vec::new(), methods: Vec::new() }
+    }
+
+    fn accepts(&self, event: u8, class: &RuntimeClass, method: Option<&RuntimeMethod>) -> bool {
+        if self.events & event == 0 {
+            return false;
+        }
+        let class_name = class.class_name.replace('/', ".");
+        if !self.classes.is_empty() && !self.classes.iter().any(|pattern| glob(pattern, &class_name)) {
+            return false;
+        }
+        match method {
+            Some(method) if !self.methods.is_empty() => self.methods.iter().any(|pattern| {
+                if pattern.contains('.') {
+                    glob(pattern, &format!("{}.{}", class_name, method.name))
+                } else {
+                    glob(pattern, &method.name)
+                }
+            }),
+            _ => true
+        }
+    }
+
+}
+
+// Parses a list of event names separated by +, e.g. calls+exceptions.
+pub fn parse_events(names: &str) -> Option<u8> {
+    names.split('+').try_fold(0, |events, name| {
+        EVENT_NAMES.iter().find(|&&(event_name, _)| event_name == name).map(|&(_, event)| events | event)
+    })
+}
+
+// Whether the text matches the glob.
+pub fn glob(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    // Where the last * was, and the text position it was last tried at, to backtrack to.
+    let (mut p, mut t) = (0, 0);
+    let mut star = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            },
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            },
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                },
+                None => return false
+            }
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+pub struct Trace {
+    enabled: AtomicBool,
+    tracers: Mutex<Vec<(Filter, Box<dyn Tracer>)>>
+}
+
+impl Trace {
+
+    pub fn new() -> Trace {
+        Trace { enabled: AtomicBool::new(false), tracers: Mutex::new(Vec::new()) }
+    }
+
+    pub fn add(&self, filter: Filter, tracer: Box<dyn Tracer>) {
+        self.tracers.lock().unwrap().push((filter, tracer));
+        self.enabled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    // Passes the event to every tracer whose filter accepts it.
+    fn dispatch<F: FnMut(&mut dyn Tracer, &str)>(&self, event: u8, class: &RuntimeClass, method: Option<&RuntimeMethod>, mut f: F) {
+        let thread = thread::current();
+        let thread_name = thread.name().unwrap_or("main");
+        for &mut (ref filter, ref mut tracer) in self.tracers.lock().unwrap().iter_mut() {
+            if filter.accepts(event, class, method) {
+                f(tracer.as_mut(), thread_name);
+            }
+        }
+    }
+
+    pub fn method_entry(&self, class: &RuntimeClass, method: &RuntimeMethod, depth: usize) {
+        self.dispatch(CALLS, class, Some(method), |tracer, thread| {
+            tracer.method_entry(&Site { thread, class, method, depth, pc: None })
+        });
+    }
+
+    pub fn method_exit(&self, class: &RuntimeClass, method: &RuntimeMethod, depth: usize, exit: &Exit) {
+        self.dispatch(CALLS, class, Some(method), |tracer, thread| {
+            tracer.method_exit(&Site { thread, class, method, depth, pc: None }, exit)
+        });
+    }
+
+    pub fn instruction(&self, class: &RuntimeClass, method: &RuntimeMethod, depth: usize, pc: u16, instruction: &Instruction) {
+        self.dispatch(INSTRUCTIONS, class, Some(method), |tracer, thread| {
+            tracer.instruction(&Site { thread, class, method, depth, pc: Some(pc) }, instruction)
+        });
+    }
+
+    pub fn allocation(&self, class: &RuntimeClass, method: &RuntimeMethod, depth: usize, pc: u16, value: &Value) {
+        self.dispatch(ALLOCATIONS, class, Some(method), |tracer, thread| {
+            tracer.allocation(&Site { thread, class, method, depth, pc: Some(pc) }, value)
+        });
+    }
+
+    pub fn exception_thrown(&self, class: &RuntimeClass, method: &RuntimeMethod, depth: usize, pc: Option<u16>, exception: &Value) {
+        self.dispatch(EXCEPTIONS, class, Some(method), |tracer, thread| {
+            tracer.exception_thrown(&Site { thread, class, method, depth, pc }, exception)
+        });
+    }
+
+    pub fn class_loaded(&self, class: &RuntimeClass, source: &str) {
+        self.dispatch(CLASSES, class, None, |tracer, thread| tracer.class_loaded(thread, class, source));
+    }
+
+    pub fn flush(&self) {
+        for &mut (_, ref mut tracer) in self.tracers.lock().unwrap().iter_mut() {
+            tracer.flush();
+        }
+    }
+
+}
+
+impl Default for Trace {
+    fn default() -> Trace {
+        Trace::new()
+    }
+}
+
+// e.g. Debuggee.add(I)I
+fn describe_method(class: &RuntimeClass, method: &RuntimeMethod) -> String {
+    format!("{}.{}{}", class.class_name.replace('/', "."), method.name, method.descriptor)
+}
+
+// The class of an object or array, e.g. java.lang.String or int[].
+fn type_name(value: &Value) -> String {
+    match *value {
+        Value::ObjectRef(ref object) => object.borrow().class().class_name.replace('/', "."),
+        Value::ArrayRef(ref array) => array.borrow().descriptor(),
+        _ => format_value(value)
+    }
+}
+
+// Writes one line per event, indented by the depth of its frame, e.g.
+//   [main]   -> Debuggee.add(I)I
+//   [main]        0: Aload0
+//   [main]   <- Debuggee.add(I)I returned 1
+pub struct TextTracer {
+    output: Box<dyn Write + Send>
+}
+
+impl TextTracer {
+
+    pub fn new(output: Box<dyn Write + Send>) -> TextTracer {
+        TextTracer { output }
+    }
+
+    fn line(&mut self, site: &Site, text: &str) {
+        let _ = writeln!(self.output, "[{}] {}{}", site.thread, "  ".repeat(site.depth.saturating_sub(1)), text);
+    }
+
+}
+
+impl Tracer for TextTracer {
+
+    fn method_entry(&mut self, site: &Site) {
+        self.line(site, &format!("-> {}", describe_method(site.class, site.method)));
+    }
+
+    fn method_exit(&mut self, site: &Site, exit: &Exit) {
+        let outcome = match *exit {
+            Exit::Returned(Some(value)) => format!("returned {}", format_value(value)),
+            Exit::Returned(None) => String::from("returned"),
+            Exit::Threw(exception) => format!("threw {}", type_name(exception)),
+            Exit::Failed(error) => format!("failed: {:?}", error)
+        };
+        self.line(site, &format!("<- {} {}", describe_method(site.class, site.method), outcome));
+    }
+
+    fn instruction(&mut self, site: &Site, instruction: &Instruction) {
+        self.line(site, &format!("   {:>4}: {:?}", site.pc.unwrap_or(0), instruction));
+    }
+
+    fn allocation(&mut self, site: &Site, value: &Value) {
+        self.line(site, &format!("   new {} at {} pc {}", type_name(value), describe_method(site.class, site.method), site.pc.unwrap_or(0)));
+    }
+
+    fn exception_thrown(&mut self, site: &Site, exception: &Value) {
+        let at = match site.pc {
+            Some(pc) => format!("{} pc {}", describe_method(site.class, site.method), pc),
+            None => describe_method(site.class, site.method)
+        };
+        self.line(site, &format!("   throw {} at {}", type_name(exception), at));
+    }
+
+    fn class_loaded(&mut self, thread: &str, class: &RuntimeClass, source: &str) {
+        let _ = writeln!(self.output, "[{}] class {} loaded from {}", thread, class.class_name.replace('/', "."), source);
+    }
+
+    fn flush(&mut self) {
+        let _ = self.output.flush();
+    }
+
+}
+
+// Writes one JSON object per event and line, e.g.
+//   {"event":"entry","thread":"main","depth":2,"class":"Debuggee","method":"add","descriptor":"(I)I"}
+pub struct JsonTracer {
+    output: Box<dyn Write + Send>
+}
+
+impl JsonTracer {
+
+    pub fn new(output: Box<dyn Write + Send>) -> JsonTracer {
+        JsonTracer { output }
+    }
+
+    // Writes an event at the site, with the given fields after the common ones.
+    fn event(&mut self, event: &str, site: &Site, fields: &str) {
+        let pc = site.pc.map_or(String::new(), |pc| format!(",\"pc\":{}", pc));
+        let _ = writeln!(self.output, "{{\"event\":{},\"thread\":{},\"depth\":{},\"class\":{},\"method\":{},\"descriptor\":{}{}{}}}",
+                         json_string(event), json_string(site.thread), site.depth, json_string(&site.class.class_name.replace('/', ".")),
+                         json_string(&site.method.name), json_string(&site.method.descriptor), pc, fields);
+    }
+
+}
+
+impl Tracer for JsonTracer {
+
+    fn method_entry(&mut self, site: &Site) {
+        self.event("entry", site, "");
+    }
+
+    fn method_exit(&mut self, site: &Site, exit: &Exit) {
+        let fields = match *exit {
+            Exit::Returned(Some(value)) => format!(",\"returned\":{}", json_string(&format_value(value))),
+            Exit::Returned(None) => String::new(),
+            Exit::Threw(exception) => format!(",\"threw\":{}", json_string(&type_name(exception))),
+            Exit::Failed(error) => format!(",\"failed\":{}", json_string(&format!("{:?}", error)))
+        };
+        self.event("exit", site, &fields);
+    }
+
+    fn instruction(&mut self, site: &Site, instruction: &Instruction) {
+        self.event("instruction", site, &format!(",\"instruction\":{}", json_string(&format!("{:?}", instruction))));
+    }
+
+    fn allocation(&mut self, site: &Site, value: &Value) {
+        self.event("allocation", site, &format!(",\"type\":{}", json_string(&type_name(value))));
+    }
+
+    fn exception_thrown(&mut self, site: &Site, exception: &Value) {
+        self.event("throw", site, &format!(",\"exception\":{}", json_string(&type_name(exception))));
+    }
+
+    fn class_loaded(&mut self, thread: &str, class: &RuntimeClass, source: &str) {
+        let _ = writeln!(self.output, "{{\"event\":\"class\",\"thread\":{},\"class\":{},\"source\":{}}}",
+                         json_string(thread), json_string(&class.class_name.replace('/', ".")), json_string(source));
+    }
+
+    fn flush(&mut self) {
+        let _ = self.output.flush();
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use class::reader;
+    use runtime::interpreter;
+    use runtime::bootstrap::io::{Console, SharedBuffer};
+    use runtime::class::ClassTable;
+
+    #[test]
+    fn globs() {
+        assert!(glob("Debuggee", "Debuggee"));
+        assert!(glob("com.example.*", "com.example.Main"));
+        assert!(glob("*.run", "Main.run"));
+        assert!(glob("a*b*c", "aXbYbZc"));
+        assert!(glob("ad?", "add"));
+        assert!(glob("*", ""));
+        assert!(!glob("com.example.*", "com.other.Main"));
+        assert!(!glob("ad?", "ad"));
+        assert!(!glob("a*c", "abcd"));
+    }
+
+    #[test]
+    fn events() {
+        assert_eq!(parse_events("calls"), Some(CALLS));
+        assert_eq!(parse_events("calls+exceptions+classes"), Some(CALLS | EXCEPTIONS | CLASSES));
+        assert_eq!(parse_events("calls+returns"), None);
+        assert_eq!(parse_events(""), None);
+    }
+
+    // Runs Debuggee.main on a thread called main with a tracer of the given kind and filter,
+    // and returns what it wrote.
+    fn trace(json: bool, filter: Filter) -> String {
+        thread::Builder::new().name(String::from("main")).spawn(move || run_traced(json, filter)).unwrap().join().unwrap()
+    }
+
+    fn run_traced(json: bool, filter: Filter) -> String {
+        let class_table = ClassTable::with_console(Console::new(Box::new(SharedBuffer::new()), Box::new(SharedBuffer::new())));
+        class_table.set_compile_threshold(0);
+        let output = SharedBuffer::new();
+        let tracer: Box<dyn Tracer> = if json {
+            Box::new(JsonTracer::new(Box::new(output.clone())))
+        } else {
+            Box::new(TextTracer::new(Box::new(output.clone())))
+        };
+        class_table.trace.add(filter, tracer);
+
+        let class = class_table.define_class(&reader::read_class_file(include_bytes!("../../fixtures/Debuggee.class")).unwrap()).unwrap();
+        let main = class.get_declared_method("main", "([Ljava/lang/String;)V").unwrap();
+        let arguments = Value::ArrayRef(class_table.new_array("Ljava/lang/String;", 0));
+        interpreter::invoke_method(&class, main, vec![arguments], &class_table).unwrap();
+        class_table.trace.flush();
+        output.contents()
+    }
+
+    #[test]
+    fn text_calls_and_allocations() {
+        let filter = Filter { events: CALLS | ALLOCATIONS, classes: vec![String::from("Debuggee")], methods: Vec::new() };
+        let output = trace(false, filter);
+        let expected_start = "[main] -> Debuggee.main([Ljava/lang/String;)V\n\
+            [main]    new Debuggee at Debuggee.main([Ljava/lang/String;)V pc 0\n\
+            [main]   -> Debuggee.<init>(Ljava/lang/String;)V\n\
+            [main]   <- Debuggee.<init>(Ljava/lang/String;)V returned\n";
+        assert!(output.starts_with(expected_start), "{}", output);
+        assert!(output.contains("[main]   <- Debuggee.add(I)I returned 6\n"), "{}", output);
+        assert!(output.ends_with("[main] <- Debuggee.main([Ljava/lang/String;)V returned\n"), "{}", output);
+        // Only Debuggee is traced, not the classes it calls.
+        assert!(!output.contains("java.lang"), "{}", output);
+    }
+
+    #[test]
+    fn json_instructions_of_a_method() {
+        let filter = Filter { events: INSTRUCTIONS, classes: Vec::new(), methods: vec![String::from("Debuggee.add")] };
+        let output = trace(true, filter);
+        let lines: Vec<&str> = output.lines().collect();
+        // Debuggee.add runs three times.
+        assert_eq!(lines.len() % 3, 0);
+        assert_eq!(lines[0], "{\"event\":\"instruction\",\"thread\":\"main\",\"depth\":2,\"class\":\"Debuggee\",\"method\":\"add\",\"descriptor\":\"(I)I\",\"pc\":0,\"instruction\":\"Aload0\"}");
+        assert!(lines.iter().all(|line| line.contains("\"method\":\"add\"")));
+    }
+
+}